@@ -23,6 +23,7 @@ type ServiceCustomer = {
   email: string | null;
   phone: string | null;
   created_at: string;
+  version: number;
 };
 
 type TenantOption = {
@@ -72,6 +73,7 @@ const normalizeCustomer = (entry: unknown): ServiceCustomer | null => {
     email,
     phone,
     created_at: createdAt,
+    version: typeof candidate.version === "number" ? candidate.version : 1,
   };
 };
 
@@ -327,10 +329,19 @@ const CustomersPageContent: React.FC = () => {
           `${CUSTOMER_SERVICE_URL}/customers/${selectedCustomer.id}`,
           {
             method: "PUT",
-            headers: buildHeaders(true),
+            headers: {
+              ...buildHeaders(true),
+              "If-Match": `"${selectedCustomer.version}"`,
+            },
             body: JSON.stringify(payload),
           },
         );
+        if (response.status === 409) {
+          setError(
+            "This customer was changed by someone else. Reload and try again.",
+          );
+          return;
+        }
         if (!response.ok) {
           throw new Error(`Failed to update customer (${response.status})`);
         }
@@ -355,6 +366,7 @@ const CustomersPageContent: React.FC = () => {
               name: payload.name,
               email: payload.email,
               phone: payload.phone,
+              version: entry.version + 1,
             };
           });
         });
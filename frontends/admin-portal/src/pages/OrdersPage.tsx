@@ -40,6 +40,9 @@ interface FiltersState {
   startDate: string;
   endDate: string;
   storeId: string;
+  minTotal: string;
+  maxTotal: string;
+  terminalId: string;
 }
 
 const defaultFilters: FiltersState = {
@@ -50,6 +53,9 @@ const defaultFilters: FiltersState = {
   startDate: "",
   endDate: "",
   storeId: "",
+  minTotal: "",
+  maxTotal: "",
+  terminalId: "",
 };
 
 const OrdersPageContent: React.FC = () => {
@@ -94,13 +100,17 @@ const OrdersPageContent: React.FC = () => {
       const params = new URLSearchParams();
       params.set("limit", String(PAGE_SIZE));
       params.set("offset", String(page * PAGE_SIZE));
-      if (filters.orderId.trim()) params.set("order_id", filters.orderId.trim());
+      // `receipt` accepts a full order id or the first 8+ characters printed on a receipt.
+      if (filters.orderId.trim()) params.set("receipt", filters.orderId.trim());
       if (filters.status !== "all") params.set("status", filters.status.trim());
       if (filters.paymentMethod !== "all") params.set("payment_method", filters.paymentMethod.trim().toLowerCase());
       if (filters.customerTerm.trim()) params.set("customer", filters.customerTerm.trim());
       if (filters.startDate) params.set("start_date", filters.startDate);
       if (filters.endDate) params.set("end_date", filters.endDate);
       if (filters.storeId.trim()) params.set("store_id", filters.storeId.trim());
+      if (filters.minTotal.trim()) params.set("min_total", filters.minTotal.trim());
+      if (filters.maxTotal.trim()) params.set("max_total", filters.maxTotal.trim());
+      if (filters.terminalId.trim()) params.set("terminal_id", filters.terminalId.trim());
       const response = await fetch(`${ORDER_SERVICE_URL}/orders?${params.toString()}`, { method: "GET", headers: buildHeaders() });
       if (!response.ok) throw new Error(`Order search failed (${response.status})`);
       const payload = (await response.json()) as unknown;
@@ -174,8 +184,8 @@ const OrdersPageContent: React.FC = () => {
 
           <div className="mb-6 grid gap-4 rounded-lg bg-white p-4 shadow">
             <div className="grid grid-cols-1 gap-4 md:grid-cols-3">
-              <label className="flex flex-col text-sm font-medium text-gray-700">Order ID
-                <input className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.orderId} onChange={(e) => handleFilterChange("orderId", e.target.value)} placeholder="Search by order ID" />
+              <label className="flex flex-col text-sm font-medium text-gray-700">Receipt / Order ID
+                <input className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.orderId} onChange={(e) => handleFilterChange("orderId", e.target.value)} placeholder="Full ID or first 8 characters" />
               </label>
               <label className="flex flex-col text-sm font-medium text-gray-700">Customer (name or email)
                 <input className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.customerTerm} onChange={(e) => handleFilterChange("customerTerm", e.target.value)} placeholder="Search customer" />
@@ -203,6 +213,17 @@ const OrdersPageContent: React.FC = () => {
                 <input type="date" className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.endDate} onChange={(e) => handleFilterChange("endDate", e.target.value)} />
               </label>
             </div>
+            <div className="grid grid-cols-1 gap-4 md:grid-cols-3">
+              <label className="flex flex-col text-sm font-medium text-gray-700">Min Total
+                <input type="number" min="0" step="0.01" className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.minTotal} onChange={(e) => handleFilterChange("minTotal", e.target.value)} />
+              </label>
+              <label className="flex flex-col text-sm font-medium text-gray-700">Max Total
+                <input type="number" min="0" step="0.01" className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.maxTotal} onChange={(e) => handleFilterChange("maxTotal", e.target.value)} />
+              </label>
+              <label className="flex flex-col text-sm font-medium text-gray-700">Terminal ID
+                <input className="mt-1 rounded border border-gray-300 px-3 py-2 text-sm text-gray-900" value={filters.terminalId} onChange={(e) => handleFilterChange("terminalId", e.target.value)} placeholder="Filter by POS terminal" />
+              </label>
+            </div>
           </div>
 
           <div className="rounded-lg bg-white dark:bg-gray-800 shadow">
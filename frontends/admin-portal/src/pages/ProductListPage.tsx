@@ -25,6 +25,7 @@ type ServiceProduct = {
   description: string;
   active: boolean;
   image: string;
+  version: number;
 };
 
 type ProductFormState = {
@@ -77,9 +78,18 @@ const normalizeProduct = (input: unknown): ServiceProduct | null => {
       typeof candidate.description === "string" ? candidate.description : "",
     active: typeof candidate.active === "boolean" ? candidate.active : true,
     image,
+    version: typeof candidate.version === "number" ? candidate.version : 1,
   };
 };
 
+// Product updates are optimistic: the service rejects stale versions with 409.
+const ifMatchHeader = (version: number): Record<string, string> => ({
+  "If-Match": `"${version}"`,
+});
+
+const STALE_PRODUCT_MESSAGE =
+  "This product was changed by someone else. Reload the list and try again.";
+
 type AuditEntriesContainer = {
   entries?: unknown;
 };
@@ -463,6 +473,7 @@ const ProductListPageContent: React.FC = () => {
     setError(null);
     setSuccessMessage(null);
     setUpdatingProductId(editingProductId);
+    const editingProduct = products.find((prod) => prod.id === editingProductId);
     try {
       const imageValue = editForm.image.trim();
       const requestBody: Record<string, unknown> = {
@@ -483,10 +494,15 @@ const ProductListPageContent: React.FC = () => {
           headers: {
             "Content-Type": "application/json",
             ...buildHeaders(),
+            ...ifMatchHeader(editingProduct?.version ?? 1),
           },
           body: JSON.stringify(requestBody),
         },
       );
+      if (response.status === 409) {
+        setError(STALE_PRODUCT_MESSAGE);
+        return;
+      }
       if (!response.ok) {
         throw new Error(`Failed to update product (${response.status})`);
       }
@@ -521,6 +537,7 @@ const ProductListPageContent: React.FC = () => {
           headers: {
             "Content-Type": "application/json",
             ...buildHeaders(),
+            ...ifMatchHeader(product.version),
           },
           body: JSON.stringify({
             name: product.name,
@@ -531,6 +548,10 @@ const ProductListPageContent: React.FC = () => {
           }),
         },
       );
+      if (response.status === 409) {
+        setError(STALE_PRODUCT_MESSAGE);
+        return;
+      }
       if (!response.ok) {
         throw new Error(`Failed to update product (${response.status})`);
       }
@@ -562,7 +583,7 @@ const ProductListPageContent: React.FC = () => {
       typeof window === "undefined"
         ? true
         : window.confirm(
-            `Delete "${product.name}"? This action cannot be undone.`,
+            `Delete "${product.name}"? It will be hidden from the catalog and can be restored by an admin.`,
           );
     if (!confirmed) return;
 
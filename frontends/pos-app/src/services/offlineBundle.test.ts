@@ -0,0 +1,67 @@
+import { describe, it, expect, beforeAll } from 'vitest';
+import { OFFLINE_BUNDLE_AUDIENCE, verifyOfflineBundle, type Jwk } from './offlineBundle';
+
+function base64Url(bytes: Uint8Array): string {
+  let binary = '';
+  bytes.forEach(b => { binary += String.fromCharCode(b); });
+  return btoa(binary).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '');
+}
+
+const encodeJson = (value: unknown) => base64Url(new TextEncoder().encode(JSON.stringify(value)));
+
+let keyPair: CryptoKeyPair;
+let jwks: { keys: Jwk[] };
+
+async function sign(claims: Record<string, unknown>, kid = 'bundle-key'): Promise<string> {
+  const signingInput = `${encodeJson({ alg: 'RS256', typ: 'JWT', kid })}.${encodeJson(claims)}`;
+  const signature = await crypto.subtle.sign(
+    'RSASSA-PKCS1-v1_5',
+    keyPair.privateKey,
+    new TextEncoder().encode(signingInput),
+  );
+  return `${signingInput}.${base64Url(new Uint8Array(signature))}`;
+}
+
+const now = 1_800_000_000;
+const bundleClaims = (overrides: Record<string, unknown> = {}) => ({
+  tenant_id: 't1',
+  version: 'v1',
+  bundle: { products: [], tax: { rate_bps: 825, non_taxable_codes: ['EXEMPT'] } },
+  iss: 'https://auth.novapos.local',
+  aud: OFFLINE_BUNDLE_AUDIENCE,
+  iat: now - 60,
+  exp: now + 3600,
+  ...overrides,
+});
+
+describe('offline bundle verification', () => {
+  beforeAll(async () => {
+    keyPair = await crypto.subtle.generateKey(
+      { name: 'RSASSA-PKCS1-v1_5', modulusLength: 2048, publicExponent: new Uint8Array([1, 0, 1]), hash: 'SHA-256' },
+      true,
+      ['sign', 'verify'],
+    );
+    const publicJwk = await crypto.subtle.exportKey('jwk', keyPair.publicKey);
+    jwks = { keys: [{ kid: 'bundle-key', kty: 'RSA', alg: 'RS256', n: publicJwk.n, e: publicJwk.e }] };
+  });
+
+  it('accepts a bundle signed for this tenant', async () => {
+    const token = await sign(bundleClaims());
+    const verified = await verifyOfflineBundle(token, jwks, { tenantId: 't1', issuer: 'https://auth.novapos.local', now });
+    expect(verified.version).toBe('v1');
+    expect(verified.bundle.tax.rate_bps).toBe(825);
+  });
+
+  it('rejects tampered, expired, foreign and mis-addressed bundles', async () => {
+    const token = await sign(bundleClaims());
+    const [header, , signature] = token.split('.');
+    const tampered = `${header}.${encodeJson(bundleClaims({ tax: { rate_bps: 0 } }))}.${signature}`;
+    await expect(verifyOfflineBundle(tampered, jwks, { tenantId: 't1', now })).rejects.toThrow(/signature/);
+    await expect(verifyOfflineBundle(token, jwks, { tenantId: 't1', now: now + 7200 })).rejects.toThrow(/expired/);
+    await expect(verifyOfflineBundle(token, jwks, { tenantId: 't2', now })).rejects.toThrow(/another tenant/);
+    const accessToken = await sign(bundleClaims({ aud: 'novapos-api' }));
+    await expect(verifyOfflineBundle(accessToken, jwks, { tenantId: 't1', now })).rejects.toThrow(/not an offline bundle/);
+    const unknownKey = await sign(bundleClaims(), 'rotated-key');
+    await expect(verifyOfflineBundle(unknownKey, jwks, { tenantId: 't1', now })).rejects.toThrow(/unknown key/);
+  });
+});
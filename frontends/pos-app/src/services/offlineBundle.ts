@@ -0,0 +1,132 @@
+// Signed offline price/tax bundle: products, prices, tax and rounding rules the terminal needs to
+// keep selling without a connection. auth-service signs it (RS256, same keys as access tokens,
+// audience `novapos-offline-bundle`); the terminal verifies it against the cached JWKS before use.
+
+export const OFFLINE_BUNDLE_AUDIENCE = 'novapos-offline-bundle';
+
+export type OfflineBundleProduct = {
+  id: string;
+  sku: string | null;
+  name: string;
+  price_cents: number;
+  tax_code: string | null;
+  taxable: boolean;
+};
+
+export type OfflineBundleContent = {
+  tenant_id: string;
+  location_id: string | null;
+  pos_instance_id: string | null;
+  products: OfflineBundleProduct[];
+  tax: { rate_bps: number; non_taxable_codes: string[] };
+  rounding: { mode: string; cash_increment_cents: number };
+};
+
+export type VerifiedOfflineBundle = {
+  tenantId: string;
+  version: string;
+  issuedAt: number;
+  expiresAt: number;
+  bundle: OfflineBundleContent;
+};
+
+export type SignedOfflineBundle = {
+  tenant_id: string;
+  version: string;
+  expires_at: number;
+  token: string;
+};
+
+export type Jwk = { kid?: string; kty?: string; alg?: string; n?: string; e?: string };
+
+export class OfflineBundleError extends Error {}
+
+function base64UrlDecode(value: string): Uint8Array {
+  const padded = value.replace(/-/g, '+').replace(/_/g, '/').padEnd(Math.ceil(value.length / 4) * 4, '=');
+  const binary = atob(padded);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i += 1) bytes[i] = binary.charCodeAt(i);
+  return bytes;
+}
+
+function decodeJson(segment: string): Record<string, unknown> {
+  try {
+    return JSON.parse(new TextDecoder().decode(base64UrlDecode(segment)));
+  } catch {
+    throw new OfflineBundleError('Malformed offline bundle token');
+  }
+}
+
+/**
+ * Verify a bundle token: RS256 signature against `jwks`, issuer (when given), audience, expiry and
+ * tenant. Throws `OfflineBundleError` when the bundle must not be used.
+ */
+export async function verifyOfflineBundle(
+  token: string,
+  jwks: { keys: Jwk[] },
+  options: { tenantId: string; issuer?: string; now?: number },
+): Promise<VerifiedOfflineBundle> {
+  const parts = token.split('.');
+  if (parts.length !== 3) throw new OfflineBundleError('Malformed offline bundle token');
+  const [headerSegment, payloadSegment, signatureSegment] = parts;
+  const header = decodeJson(headerSegment);
+  if (header.alg !== 'RS256') throw new OfflineBundleError('Unsupported offline bundle algorithm');
+  const jwk = jwks.keys.find(key => key.kid === header.kid && key.kty === 'RSA');
+  if (!jwk?.n || !jwk.e) throw new OfflineBundleError('Offline bundle signed with an unknown key');
+
+  const key = await crypto.subtle.importKey(
+    'jwk',
+    { kty: 'RSA', n: jwk.n, e: jwk.e, alg: 'RS256', ext: true },
+    { name: 'RSASSA-PKCS1-v1_5', hash: 'SHA-256' },
+    false,
+    ['verify'],
+  );
+  const valid = await crypto.subtle.verify(
+    'RSASSA-PKCS1-v1_5',
+    key,
+    base64UrlDecode(signatureSegment),
+    new TextEncoder().encode(`${headerSegment}.${payloadSegment}`),
+  );
+  if (!valid) throw new OfflineBundleError('Offline bundle signature is invalid');
+
+  const claims = decodeJson(payloadSegment);
+  const audiences = Array.isArray(claims.aud) ? claims.aud : [claims.aud];
+  if (!audiences.includes(OFFLINE_BUNDLE_AUDIENCE)) throw new OfflineBundleError('Token is not an offline bundle');
+  if (options.issuer && claims.iss !== options.issuer) throw new OfflineBundleError('Offline bundle issuer mismatch');
+  const now = options.now ?? Math.floor(Date.now() / 1000);
+  if (typeof claims.exp !== 'number' || claims.exp <= now) throw new OfflineBundleError('Offline bundle has expired');
+  if (claims.tenant_id !== options.tenantId) throw new OfflineBundleError('Offline bundle belongs to another tenant');
+
+  return {
+    tenantId: String(claims.tenant_id),
+    version: String(claims.version),
+    issuedAt: Number(claims.iat),
+    expiresAt: claims.exp,
+    bundle: claims.bundle as OfflineBundleContent,
+  };
+}
+
+/**
+ * Fetch the signed bundle for this terminal. Pass the cached version to get `null` back (HTTP 304)
+ * while prices, tax and rounding rules are unchanged.
+ */
+export async function fetchOfflineBundle(
+  tenantId: string,
+  token: string,
+  options: { locationId?: string; posInstanceId?: string; cachedVersion?: string } = {},
+): Promise<SignedOfflineBundle | null> {
+  // eslint-disable-next-line @typescript-eslint/no-explicit-any
+  const base = (import.meta as any).env?.VITE_AUTH_SERVICE_URL ?? 'http://localhost:8085';
+  const params = new URLSearchParams();
+  if (options.locationId) params.set('location_id', options.locationId);
+  if (options.posInstanceId) params.set('pos_instance_id', options.posInstanceId);
+  const query = params.toString();
+  const url = `${String(base).replace(/\/$/, '')}/offline-bundle${query ? `?${query}` : ''}`;
+
+  const headers: Record<string, string> = { Authorization: `Bearer ${token}`, 'X-Tenant-ID': tenantId };
+  if (options.cachedVersion) headers['If-None-Match'] = `"${options.cachedVersion}"`;
+  const resp = await fetch(url, { headers });
+  if (resp.status === 304) return null;
+  if (!resp.ok) throw new OfflineBundleError(`Offline bundle request failed (${resp.status})`);
+  return (await resp.json()) as SignedOfflineBundle;
+}
@@ -0,0 +1,13 @@
+-- Disputed revenue per tenant and day, projected from payment.dispute.updated.
+-- Amounts are in major units, like daily_sales.
+CREATE TABLE IF NOT EXISTS daily_disputes (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    opened_count INT NOT NULL DEFAULT 0,
+    opened_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    won_count INT NOT NULL DEFAULT 0,
+    won_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    lost_count INT NOT NULL DEFAULT 0,
+    lost_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date)
+);
@@ -0,0 +1,14 @@
+-- Tips per tenant, business day, employee and register, projected from order.tip_recorded.
+-- A shift is one employee on one register for one business day; adjustments made later are
+-- booked on the day of the sale. Unknown employee/register is stored as the nil UUID.
+-- Amounts are in major units, like daily_sales.
+CREATE TABLE IF NOT EXISTS daily_tips (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    employee_id UUID NOT NULL,
+    pos_instance_id UUID NOT NULL,
+    tip_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    tipped_orders INT NOT NULL DEFAULT 0,
+    adjustments INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, employee_id, pos_instance_id)
+);
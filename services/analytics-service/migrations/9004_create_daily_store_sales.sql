@@ -0,0 +1,14 @@
+-- daily_sales broken down by store, projected from order.completed. Orders without a recorded
+-- store (including events published before location_id was added) use the nil UUID.
+-- item_count is units sold; refunds don't reduce it. Amounts are in major units, like daily_sales.
+CREATE TABLE IF NOT EXISTS daily_store_sales (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    location_id UUID NOT NULL,
+    total_sales DOUBLE PRECISION NOT NULL DEFAULT 0,
+    order_count INT NOT NULL DEFAULT 0,
+    item_count INT NOT NULL DEFAULT 0,
+    refund_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    refund_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, location_id)
+);
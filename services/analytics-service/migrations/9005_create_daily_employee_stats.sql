@@ -0,0 +1,26 @@
+-- Per-employee sales and voids per day, for /analytics/employees. Sales and refunds come from
+-- order.completed (employee_id), voids from order.voided (requested_by). Unknown employees use
+-- the nil UUID. first_sale_at/last_sale_at bound the employee's selling time that day, which is
+-- what items-per-minute is measured against. Amounts are in major units, like daily_sales.
+CREATE TABLE IF NOT EXISTS daily_employee_sales (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    employee_id UUID NOT NULL,
+    total_sales DOUBLE PRECISION NOT NULL DEFAULT 0,
+    order_count INT NOT NULL DEFAULT 0,
+    item_count INT NOT NULL DEFAULT 0,
+    refund_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    refund_count INT NOT NULL DEFAULT 0,
+    first_sale_at TIMESTAMPTZ NULL,
+    last_sale_at TIMESTAMPTZ NULL,
+    PRIMARY KEY (tenant_id, date, employee_id)
+);
+
+CREATE TABLE IF NOT EXISTS daily_employee_voids (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    employee_id UUID NOT NULL,
+    void_count INT NOT NULL DEFAULT 0,
+    void_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, employee_id)
+);
@@ -0,0 +1,14 @@
+-- Component stock drawn by bundle sales per tenant and day, projected from
+-- inventory.components.consumed. Quantities are inventory stock units: whole units, or
+-- thousandths of a kg/lb for weighed components. Refunds subtract.
+CREATE TABLE IF NOT EXISTS daily_component_consumption (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    bundle_id UUID NOT NULL,
+    component_id UUID NOT NULL,
+    quantity BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, bundle_id, component_id)
+);
+
+CREATE INDEX IF NOT EXISTS daily_component_consumption_component_idx
+    ON daily_component_consumption (tenant_id, component_id, date);
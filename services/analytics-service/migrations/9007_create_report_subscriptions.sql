@@ -0,0 +1,75 @@
+-- Units and revenue sold per product, tenant and day, projected from order.completed. Refund
+-- events carry negative quantities and line totals, so both are net. Amounts in major units.
+CREATE TABLE IF NOT EXISTS daily_product_sales (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    product_id UUID NOT NULL,
+    quantity INT NOT NULL DEFAULT 0,
+    revenue DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, product_id)
+);
+
+-- Report emails a tenant has asked for. send_time is wall-clock time in `timezone` (an IANA
+-- name); weekly subscriptions also name an ISO weekday (1 = Monday). next_run_at is kept in UTC
+-- by report_next_run and is what the scheduler polls on.
+CREATE TABLE IF NOT EXISTS report_subscriptions (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    report TEXT NOT NULL CHECK (report IN ('sales_summary', 'low_stock', 'top_products')),
+    frequency TEXT NOT NULL CHECK (frequency IN ('daily', 'weekly')),
+    weekday SMALLINT NULL CHECK (weekday BETWEEN 1 AND 7),
+    send_time TIME NOT NULL,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    recipients TEXT[] NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    created_by UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((frequency = 'weekly') = (weekday IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS report_subscriptions_tenant_idx ON report_subscriptions (tenant_id);
+CREATE INDEX IF NOT EXISTS report_subscriptions_due_idx ON report_subscriptions (next_run_at) WHERE active;
+
+-- One row per send attempt. Subscriptions are deleted with their history.
+CREATE TABLE IF NOT EXISTS report_deliveries (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    subscription_id UUID NOT NULL REFERENCES report_subscriptions(id) ON DELETE CASCADE,
+    report TEXT NOT NULL,
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    scheduled_for TIMESTAMPTZ NOT NULL,
+    recipients TEXT[] NOT NULL,
+    subject TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('sent', 'failed')),
+    error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS report_deliveries_tenant_idx ON report_deliveries (tenant_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS report_deliveries_subscription_idx ON report_deliveries (subscription_id, created_at DESC);
+
+-- First send time strictly after `after`: the next day (or matching weekday) whose local
+-- send_time in `tz` is still ahead. Local times skipped by a DST change resolve to the
+-- following hour.
+CREATE OR REPLACE FUNCTION report_next_run(frequency TEXT, weekday SMALLINT, send_time TIME, tz TEXT, after TIMESTAMPTZ)
+RETURNS TIMESTAMPTZ
+LANGUAGE plpgsql STABLE AS $$
+DECLARE
+    day DATE := (after AT TIME ZONE tz)::date;
+    candidate TIMESTAMPTZ;
+BEGIN
+    FOR i IN 0..8 LOOP
+        IF frequency = 'daily' OR EXTRACT(ISODOW FROM day) = weekday THEN
+            candidate := (day + send_time) AT TIME ZONE tz;
+            IF candidate > after THEN
+                RETURN candidate;
+            END IF;
+        END IF;
+        day := day + 1;
+    END LOOP;
+    RAISE EXCEPTION 'no send time found for frequency % weekday %', frequency, weekday;
+END
+$$;
@@ -0,0 +1,55 @@
+-- Where a tenant's analytics alerts go. Email targets are comma-separated addresses, webhook
+-- targets an https URL; in_app routes have no target. An empty alert_types matches every type.
+CREATE TABLE IF NOT EXISTS alert_routes (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    channel TEXT NOT NULL CHECK (channel IN ('email', 'webhook', 'in_app')),
+    target TEXT NULL,
+    min_severity TEXT NOT NULL DEFAULT 'warning' CHECK (min_severity IN ('info', 'warning', 'critical')),
+    alert_types TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    position INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((channel = 'in_app') = (target IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS alert_routes_tenant_idx ON alert_routes (tenant_id);
+
+-- Windows during which alerts are recorded but not delivered. A NULL alert_type silences
+-- everything (maintenance mode).
+CREATE TABLE IF NOT EXISTS alert_silences (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    alert_type TEXT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    reason TEXT NULL,
+    created_by UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS alert_silences_tenant_idx ON alert_silences (tenant_id, ends_at);
+
+-- Alert history. Repeats of an open alert within the de-dup window bump occurrences and
+-- last_seen_at instead of adding rows; acknowledging closes it, so the next firing starts a new
+-- row. channels lists the routes the alert was delivered to.
+CREATE TABLE IF NOT EXISTS alerts (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    alert_type TEXT NOT NULL,
+    severity TEXT NOT NULL CHECK (severity IN ('info', 'warning', 'critical')),
+    dedup_key TEXT NOT NULL,
+    details TEXT NOT NULL,
+    occurrences INT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    silenced BOOLEAN NOT NULL DEFAULT FALSE,
+    channels TEXT[] NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'acknowledged')),
+    acknowledged_by UUID NULL,
+    acknowledged_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX IF NOT EXISTS alerts_tenant_idx ON alerts (tenant_id, last_seen_at DESC);
+CREATE INDEX IF NOT EXISTS alerts_open_key_idx ON alerts (tenant_id, dedup_key) WHERE status = 'open';
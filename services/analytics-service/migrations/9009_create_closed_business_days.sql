@@ -0,0 +1,22 @@
+-- Z-report figures of closed store days, projected from day.closed. Closing a day replaces that
+-- store's daily_store_sales figures with the Z-report's and marks the row finalized; daily_sales
+-- takes the difference. Amounts are in major units, like daily_sales.
+CREATE TABLE IF NOT EXISTS closed_business_days (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    location_id UUID NOT NULL,
+    order_count INT NOT NULL,
+    total_sales DOUBLE PRECISION NOT NULL,
+    refund_amount DOUBLE PRECISION NOT NULL,
+    refund_count INT NOT NULL,
+    tax DOUBLE PRECISION NOT NULL,
+    tips DOUBLE PRECISION NOT NULL,
+    void_count INT NOT NULL,
+    void_amount DOUBLE PRECISION NOT NULL,
+    cash_variance DOUBLE PRECISION NOT NULL,
+    closed_by UUID NULL,
+    closed_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant_id, date, location_id)
+);
+
+ALTER TABLE daily_store_sales ADD COLUMN IF NOT EXISTS finalized BOOLEAN NOT NULL DEFAULT FALSE;
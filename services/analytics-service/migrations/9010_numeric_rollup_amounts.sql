@@ -0,0 +1,47 @@
+-- Rollup amounts move from DOUBLE PRECISION to NUMERIC so daily figures add up to the cent, like
+-- the order-service totals they are projected from. The consumer writes common-money normalized
+-- amounts; existing values are rounded to cents once here. daily_sales predates these migrations
+-- and is created for databases that don't have it yet.
+CREATE TABLE IF NOT EXISTS daily_sales (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    total_sales NUMERIC(14,2) NOT NULL DEFAULT 0,
+    order_count BIGINT NOT NULL DEFAULT 0,
+    refund_amount NUMERIC(14,2) NOT NULL DEFAULT 0,
+    refund_count BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date)
+);
+
+ALTER TABLE daily_sales
+    ALTER COLUMN total_sales TYPE NUMERIC(14,2) USING round(total_sales::numeric, 2),
+    ALTER COLUMN refund_amount TYPE NUMERIC(14,2) USING round(refund_amount::numeric, 2);
+
+ALTER TABLE daily_store_sales
+    ALTER COLUMN total_sales TYPE NUMERIC(14,2) USING round(total_sales::numeric, 2),
+    ALTER COLUMN refund_amount TYPE NUMERIC(14,2) USING round(refund_amount::numeric, 2);
+
+ALTER TABLE daily_employee_sales
+    ALTER COLUMN total_sales TYPE NUMERIC(14,2) USING round(total_sales::numeric, 2),
+    ALTER COLUMN refund_amount TYPE NUMERIC(14,2) USING round(refund_amount::numeric, 2);
+
+ALTER TABLE daily_employee_voids
+    ALTER COLUMN void_amount TYPE NUMERIC(14,2) USING round(void_amount::numeric, 2);
+
+ALTER TABLE daily_disputes
+    ALTER COLUMN opened_amount TYPE NUMERIC(14,2) USING round(opened_amount::numeric, 2),
+    ALTER COLUMN won_amount TYPE NUMERIC(14,2) USING round(won_amount::numeric, 2),
+    ALTER COLUMN lost_amount TYPE NUMERIC(14,2) USING round(lost_amount::numeric, 2);
+
+ALTER TABLE daily_tips
+    ALTER COLUMN tip_amount TYPE NUMERIC(14,2) USING round(tip_amount::numeric, 2);
+
+ALTER TABLE daily_product_sales
+    ALTER COLUMN revenue TYPE NUMERIC(14,2) USING round(revenue::numeric, 2);
+
+ALTER TABLE closed_business_days
+    ALTER COLUMN total_sales TYPE NUMERIC(14,2) USING round(total_sales::numeric, 2),
+    ALTER COLUMN refund_amount TYPE NUMERIC(14,2) USING round(refund_amount::numeric, 2),
+    ALTER COLUMN tax TYPE NUMERIC(14,2) USING round(tax::numeric, 2),
+    ALTER COLUMN tips TYPE NUMERIC(14,2) USING round(tips::numeric, 2),
+    ALTER COLUMN void_amount TYPE NUMERIC(14,2) USING round(void_amount::numeric, 2),
+    ALTER COLUMN cash_variance TYPE NUMERIC(14,2) USING round(cash_variance::numeric, 2);
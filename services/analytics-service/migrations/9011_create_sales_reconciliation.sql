@@ -0,0 +1,18 @@
+-- Open discrepancies between daily_sales and order-service's own totals (orders and
+-- order_returns by business date), written by the nightly reconciliation. A day that matches
+-- again on a later run is removed, so every row still needs attention.
+CREATE TABLE IF NOT EXISTS sales_reconciliation_discrepancies (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    rollup_order_count BIGINT NOT NULL,
+    rollup_total_sales NUMERIC(14,2) NOT NULL,
+    rollup_refund_amount NUMERIC(14,2) NOT NULL,
+    rollup_refund_count BIGINT NOT NULL,
+    source_order_count BIGINT NOT NULL,
+    source_total_sales NUMERIC(14,2) NOT NULL,
+    source_refund_amount NUMERIC(14,2) NOT NULL,
+    source_refund_count BIGINT NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (tenant_id, date)
+);
@@ -0,0 +1,24 @@
+-- Per-customer sales and refunds per day, from order.completed events that carry a customer_id,
+-- for the customer lifetime value and RFM endpoints. An order is one visit.
+CREATE TABLE IF NOT EXISTS daily_customer_sales (
+    tenant_id UUID NOT NULL,
+    date DATE NOT NULL,
+    customer_id UUID NOT NULL,
+    order_count INT NOT NULL DEFAULT 0,
+    total_sales NUMERIC(14,2) NOT NULL DEFAULT 0,
+    refund_amount NUMERIC(14,2) NOT NULL DEFAULT 0,
+    refund_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, date, customer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_daily_customer_sales_customer ON daily_customer_sales (tenant_id, customer_id);
+
+-- Customers erased on a GDPR request (customer.erased). Their daily_customer_sales rows are
+-- deleted and later or replayed sales for them are not projected again. Replay resets never
+-- touch this table.
+CREATE TABLE IF NOT EXISTS erased_customers (
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL,
+    erased_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant_id, customer_id)
+);
@@ -0,0 +1,12 @@
+-- Sales per store per UTC hour, for the staffing heatmap (/analytics/heatmap). hour_start is the
+-- start of the UTC hour the sale was consumed in (the message time on replays); reads convert it
+-- to the store's time zone. Refunds are not counted. Unknown stores use the nil UUID.
+CREATE TABLE IF NOT EXISTS hourly_store_sales (
+    tenant_id UUID NOT NULL,
+    location_id UUID NOT NULL,
+    hour_start TIMESTAMPTZ NOT NULL,
+    order_count INT NOT NULL DEFAULT 0,
+    total_sales NUMERIC(14,2) NOT NULL DEFAULT 0,
+    item_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, location_id, hour_start)
+);
@@ -0,0 +1,13 @@
+-- Per-location product sales, and units sold in stock units, so inventory-service can read each
+-- store's sales velocity for reorder suggestions. stock_units counts weighed lines in thousandths
+-- of a kg/lb, as inventory does; quantity keeps counting lines. Rows projected before this
+-- migration have the nil location and their quantity as stock_units; replay order.completed to
+-- split them by location.
+ALTER TABLE daily_product_sales
+    ADD COLUMN IF NOT EXISTS location_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000',
+    ADD COLUMN IF NOT EXISTS stock_units BIGINT NOT NULL DEFAULT 0;
+
+UPDATE daily_product_sales SET stock_units = quantity WHERE stock_units = 0;
+
+ALTER TABLE daily_product_sales DROP CONSTRAINT IF EXISTS daily_product_sales_pkey;
+ALTER TABLE daily_product_sales ADD PRIMARY KEY (tenant_id, date, product_id, location_id);
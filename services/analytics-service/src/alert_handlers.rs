@@ -0,0 +1,366 @@
+use crate::AppState;
+use analytics_service::alerts::{AlertRoute, Channel, Severity, ALERT_HIGH_REFUND_VOLUME, ALERT_LOW_STOCK};
+use analytics_service::reports::valid_email;
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_auth::{ensure_role, tenant_id_from_request, AuthContext, ROLE_ADMIN, ROLE_MANAGER, ROLE_SUPER_ADMIN};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Routes send tenant data to outside addresses, so only admins change them.
+const ALERT_ADMIN_ROLES: &[&str] = &[ROLE_SUPER_ADMIN, ROLE_ADMIN];
+/// Managers work the alert inbox and can silence alerts, e.g. during a stock take.
+const ALERT_VIEW_ROLES: &[&str] = &[ROLE_SUPER_ADMIN, ROLE_ADMIN, ROLE_MANAGER];
+const KNOWN_ALERT_TYPES: &[&str] = &[ALERT_LOW_STOCK, ALERT_HIGH_REFUND_VOLUME];
+const MAX_ROUTES: usize = 20;
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("DB query failed: {}", e))
+}
+
+fn known_alert_type(alert_type: &str) -> Result<(), (StatusCode, String)> {
+    if KNOWN_ALERT_TYPES.contains(&alert_type) {
+        return Ok(());
+    }
+    Err((StatusCode::BAD_REQUEST, format!("Unknown alert type '{alert_type}'; use {}", KNOWN_ALERT_TYPES.join(" or "))))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteInput {
+    /// `email`, `webhook` or `in_app`.
+    pub channel: String,
+    /// Comma-separated addresses for email, an https URL for webhooks, absent for in-app.
+    pub target: Option<String>,
+    /// Defaults to `warning`.
+    pub min_severity: Option<String>,
+    #[serde(default)]
+    pub alert_types: Vec<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoutesRequest {
+    pub routes: Vec<RouteInput>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RouteRow {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub route: AlertRoute,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Checks one route and returns `(channel, target, min_severity)` normalised for storage.
+fn validate_route(input: &RouteInput) -> Result<(Channel, Option<String>, Severity), (StatusCode, String)> {
+    let channel = Channel::parse(&input.channel)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported channel '{}'; use email, webhook or in_app", input.channel)))?;
+    let min_severity = match input.min_severity.as_deref() {
+        None => Severity::Warning,
+        Some(s) => Severity::parse(s).ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported severity '{s}'; use info, warning or critical")))?,
+    };
+    for alert_type in &input.alert_types {
+        known_alert_type(alert_type)?;
+    }
+    let target = input.target.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let target = match (channel, target) {
+        (Channel::InApp, None) => None,
+        (Channel::InApp, Some(_)) => return Err((StatusCode::BAD_REQUEST, "In-app routes take no target".into())),
+        (Channel::Email, Some(list)) => {
+            let addresses: Vec<String> = list.split(',').map(|a| a.trim().to_lowercase()).filter(|a| !a.is_empty()).collect();
+            if let Some(bad) = addresses.iter().find(|a| !valid_email(a)) {
+                return Err((StatusCode::BAD_REQUEST, format!("'{bad}' is not a valid email address")));
+            }
+            if addresses.is_empty() {
+                return Err((StatusCode::BAD_REQUEST, "Email routes need at least one address".into()));
+            }
+            Some(addresses.join(","))
+        }
+        (Channel::Webhook, Some(url)) if url.starts_with("https://") && url.len() > "https://".len() => Some(url.to_string()),
+        (Channel::Webhook, Some(_)) => return Err((StatusCode::BAD_REQUEST, "Webhook targets must be https URLs".into())),
+        (Channel::Email | Channel::Webhook, None) => {
+            return Err((StatusCode::BAD_REQUEST, format!("{} routes need a target", channel.as_str())));
+        }
+    };
+    Ok((channel, target, min_severity))
+}
+
+async fn load_routes(db: &sqlx::PgPool, tenant_id: Uuid) -> Result<Vec<RouteRow>, (StatusCode, String)> {
+    sqlx::query_as::<_, RouteRow>(
+        "SELECT id, channel, target, min_severity, alert_types, active, created_at
+         FROM alert_routes WHERE tenant_id = $1 ORDER BY position, id",
+    )
+    .bind(tenant_id)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)
+}
+
+/// `GET /alerts/routes`
+pub async fn get_alert_routes(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RouteRow>>, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    Ok(Json(load_routes(state.db.primary(), tenant_id).await?))
+}
+
+/// `PUT /alerts/routes`: replaces the tenant's routes. An empty list leaves alerts in the history only.
+pub async fn put_alert_routes(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Json(req): Json<RoutesRequest>,
+) -> Result<Json<Vec<RouteRow>>, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    if req.routes.len() > MAX_ROUTES {
+        return Err((StatusCode::BAD_REQUEST, format!("A tenant can have at most {MAX_ROUTES} alert routes")));
+    }
+    let mut validated = Vec::with_capacity(req.routes.len());
+    for input in &req.routes {
+        validated.push((validate_route(input)?, input));
+    }
+
+    let db = state.db.primary();
+    let mut tx = db.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM alert_routes WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    for (position, ((channel, target, min_severity), input)) in validated.into_iter().enumerate() {
+        let mut alert_types = input.alert_types.clone();
+        alert_types.sort();
+        alert_types.dedup();
+        sqlx::query(
+            "INSERT INTO alert_routes (id, tenant_id, channel, target, min_severity, alert_types, active, position)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(tenant_id)
+        .bind(channel.as_str())
+        .bind(target)
+        .bind(min_severity.as_str())
+        .bind(&alert_types)
+        .bind(input.active.unwrap_or(true))
+        .bind(position as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(load_routes(db, tenant_id).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SilenceRequest {
+    /// Omit to silence every alert type (maintenance mode).
+    pub alert_type: Option<String>,
+    /// Defaults to now.
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Silence {
+    pub id: Uuid,
+    pub alert_type: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct SilenceQuery {
+    /// Also list silences that have ended.
+    #[serde(default)]
+    pub include_expired: bool,
+}
+
+/// `GET /alerts/silences`: current and upcoming silences unless `include_expired` is set.
+pub async fn list_silences(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<SilenceQuery>,
+) -> Result<Json<Vec<Silence>>, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let rows = sqlx::query_as::<_, Silence>(
+        "SELECT id, alert_type, starts_at, ends_at, reason, created_by, created_at FROM alert_silences
+         WHERE tenant_id = $1 AND ($2 OR ends_at > now())
+         ORDER BY starts_at DESC, id LIMIT 200",
+    )
+    .bind(tenant_id)
+    .bind(q.include_expired)
+    .fetch_all(state.db.primary())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(rows))
+}
+
+/// `POST /alerts/silences`: silenced alerts are still recorded, just not delivered.
+pub async fn create_silence(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Json(req): Json<SilenceRequest>,
+) -> Result<(StatusCode, Json<Silence>), (StatusCode, String)> {
+    ensure_role(&auth, ALERT_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    if let Some(alert_type) = req.alert_type.as_deref() {
+        known_alert_type(alert_type)?;
+    }
+    let starts_at = req.starts_at.unwrap_or_else(Utc::now);
+    if req.ends_at <= starts_at || req.ends_at <= Utc::now() {
+        return Err((StatusCode::BAD_REQUEST, "ends_at must be in the future and after starts_at".into()));
+    }
+    let row = sqlx::query_as::<_, Silence>(
+        "INSERT INTO alert_silences (id, tenant_id, alert_type, starts_at, ends_at, reason, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING id, alert_type, starts_at, ends_at, reason, created_by, created_at",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(req.alert_type)
+    .bind(starts_at)
+    .bind(req.ends_at)
+    .bind(req.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty()))
+    .bind(auth.claims.subject)
+    .fetch_one(state.db.primary())
+    .await
+    .map_err(db_error)?;
+    Ok((StatusCode::CREATED, Json(row)))
+}
+
+/// `DELETE /alerts/silences/:id`: lifts a silence early.
+pub async fn delete_silence(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let done = sqlx::query("DELETE FROM alert_silences WHERE id = $1 AND tenant_id = $2")
+        .bind(id)
+        .bind(tenant_id)
+        .execute(state.db.primary())
+        .await
+        .map_err(db_error)?;
+    if done.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Silence not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct AlertQuery {
+    /// `open` or `acknowledged`; both when absent.
+    pub status: Option<String>,
+    pub alert_type: Option<String>,
+    /// Only alerts delivered in-app: the back office's alert inbox.
+    #[serde(default)]
+    pub in_app: bool,
+    /// Defaults to 50, at most 200.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AlertRecord {
+    pub id: Uuid,
+    pub alert_type: String,
+    pub severity: String,
+    pub details: String,
+    /// Firings folded into this alert by de-duplication, including the first.
+    pub occurrences: i32,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub silenced: bool,
+    pub channels: Vec<String>,
+    pub status: String,
+    pub acknowledged_by: Option<Uuid>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+const ALERT_COLUMNS: &str =
+    "id, alert_type, severity, details, occurrences, first_seen_at, last_seen_at, silenced, channels, status, acknowledged_by, acknowledged_at";
+
+/// `GET /alerts`: alert history, most recently seen first.
+pub async fn list_alerts(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<AlertQuery>,
+) -> Result<Json<Vec<AlertRecord>>, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    if let Some(status) = q.status.as_deref() {
+        if !matches!(status, "open" | "acknowledged") {
+            return Err((StatusCode::BAD_REQUEST, format!("Unsupported status '{status}'; use open or acknowledged")));
+        }
+    }
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let rows = sqlx::query_as::<_, AlertRecord>(&format!(
+        "SELECT {ALERT_COLUMNS} FROM alerts
+         WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2) AND ($3::text IS NULL OR alert_type = $3)
+           AND (NOT $4 OR 'in_app' = ANY(channels))
+         ORDER BY last_seen_at DESC, id
+         LIMIT $5"
+    ))
+    .bind(tenant_id)
+    .bind(q.status)
+    .bind(q.alert_type)
+    .bind(q.in_app)
+    .bind(limit)
+    .fetch_all(state.db.get().await)
+    .await
+    .map_err(db_error)?;
+    Ok(Json(rows))
+}
+
+/// `POST /alerts/:id/acknowledge`: closes the alert, so the next firing is delivered again.
+/// Acknowledging twice is a no-op that returns the alert as it stands.
+pub async fn acknowledge_alert(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AlertRecord>, (StatusCode, String)> {
+    ensure_role(&auth, ALERT_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let db = state.db.primary();
+    let updated = sqlx::query_as::<_, AlertRecord>(&format!(
+        "UPDATE alerts SET status = 'acknowledged', acknowledged_by = $3, acknowledged_at = now()
+          WHERE id = $1 AND tenant_id = $2 AND status = 'open'
+         RETURNING {ALERT_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(tenant_id)
+    .bind(auth.claims.subject)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    if let Some(alert) = updated {
+        return Ok(Json(alert));
+    }
+    sqlx::query_as::<_, AlertRecord>(&format!("SELECT {ALERT_COLUMNS} FROM alerts WHERE id = $1 AND tenant_id = $2"))
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Alert not found".to_string()))
+}
@@ -0,0 +1,114 @@
+//! Raises analytics alerts: publishes them on `analytics.alert` as before, records them in the
+//! tenant's alert history and delivers new ones to the tenant's routes.
+
+use analytics_service::alerts::{record_alert, Alert, AlertRoute, Channel, Recorded};
+use common_events::{topics, DomainEvent, EmailRequestedEvent};
+use rdkafka::producer::FutureProducer;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+const ALERT_TOPIC: &str = "analytics.alert";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payload on `analytics.alert` and the body POSTed to webhook routes.
+#[derive(Serialize)]
+struct AnalyticsAlertEvent<'a> {
+    tenant_id: Uuid,
+    alert_type: &'a str,
+    details: &'a str,
+    alert_id: Uuid,
+    severity: &'a str,
+}
+
+#[derive(Clone)]
+pub struct AlertRouter {
+    db: PgPool,
+    producer: FutureProducer,
+    http: reqwest::Client,
+    dedup_window: chrono::Duration,
+}
+
+impl AlertRouter {
+    pub fn new(db: PgPool, producer: FutureProducer, dedup_window_seconds: u64) -> Self {
+        let http = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build().unwrap_or_default();
+        let dedup_window = chrono::Duration::seconds(i64::try_from(dedup_window_seconds).unwrap_or(i64::MAX / 1000));
+        Self { db, producer, http, dedup_window }
+    }
+
+    pub async fn raise(&self, alert: Alert) {
+        let recorded = match record_alert(&self.db, &alert, self.dedup_window).await {
+            Ok(recorded) => recorded,
+            Err(err) => {
+                error!(?err, tenant_id = %alert.tenant_id, alert_type = alert.alert_type, "Failed to record alert");
+                return;
+            }
+        };
+        let event = AnalyticsAlertEvent {
+            tenant_id: alert.tenant_id,
+            alert_type: alert.alert_type,
+            details: &alert.details,
+            alert_id: recorded.alert_id(),
+            severity: alert.severity.as_str(),
+        };
+        let payload = serde_json::to_string(&event).unwrap();
+        if let Err(err) = common_kafka::publish(&self.producer, ALERT_TOPIC, &alert.tenant_id.to_string(), &payload).await {
+            error!("Failed to publish analytics.alert: {:?}", err);
+        }
+        let Recorded::Deliver { alert_id, routes } = recorded else {
+            return;
+        };
+        for route in routes {
+            match Channel::parse(&route.channel) {
+                Some(Channel::Email) => self.email(&alert, alert_id, &route).await,
+                Some(Channel::Webhook) => self.webhook(&route, payload.clone()),
+                // In-app alerts are read from the history, which already lists this channel.
+                Some(Channel::InApp) | None => {}
+            }
+        }
+    }
+
+    async fn email(&self, alert: &Alert, alert_id: Uuid, route: &AlertRoute) {
+        let recipients: Vec<String> = route.target.as_deref().unwrap_or_default().split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+        if recipients.is_empty() {
+            return;
+        }
+        let event = EmailRequestedEvent {
+            schema_version: EmailRequestedEvent::SCHEMA_VERSION,
+            tenant_id: alert.tenant_id,
+            message_id: Uuid::new_v4(),
+            recipients,
+            subject: format!("[{}] {}", alert.severity.as_str().to_uppercase(), alert.alert_type),
+            body: format!("{}\n\nAlert {alert_id}. Acknowledge it in the back office to be notified the next time it fires.\n", alert.details),
+            source: format!("analytics.alert.{}", alert.alert_type.to_lowercase()),
+        };
+        let published = match common_events::encode(&event) {
+            Ok(payload) => common_kafka::publish(&self.producer, topics::NOTIFICATION_EMAIL_REQUESTED, &event.partition_key(), &payload)
+                .await
+                .map_err(|err| format!("{err:?}")),
+            Err(err) => Err(err.to_string()),
+        };
+        if let Err(err) = published {
+            warn!(route_id = %route.id, tenant_id = %alert.tenant_id, error = %err, "Failed to hand alert email to the notification path");
+        }
+    }
+
+    /// Delivered in the background so a slow endpoint can't hold up the consumer.
+    fn webhook(&self, route: &AlertRoute, payload: String) {
+        let Some(url) = route.target.clone() else {
+            return;
+        };
+        let http = self.http.clone();
+        let route_id = route.id;
+        tokio::spawn(async move {
+            let result = http.post(&url).header("content-type", "application/json").body(payload).send().await;
+            match result {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => warn!(%route_id, status = %response.status(), "Alert webhook rejected the alert"),
+                Err(err) => warn!(%route_id, error = %err, "Alert webhook failed"),
+            }
+        });
+    }
+}
@@ -0,0 +1,272 @@
+//! Per-tenant handling of `analytics.alert`: every alert lands in the `alerts` history, repeats
+//! of an open alert are folded into it instead of notifying again, silences suppress delivery,
+//! and whatever is left goes to the tenant's matching routes.
+
+use chrono::Duration;
+use common_money::Money;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const ALERT_LOW_STOCK: &str = "LOW_STOCK";
+pub const ALERT_HIGH_REFUND_VOLUME: &str = "HIGH_REFUND_VOLUME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "info" => Some(Self::Info),
+            "warning" => Some(Self::Warning),
+            "critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// `target` is a comma-separated list of addresses, sent through the notification path.
+    Email,
+    /// `target` is an https URL that receives the alert as JSON.
+    Webhook,
+    /// Shown in the back office from `GET /alerts`; no target.
+    InApp,
+}
+
+impl Channel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "email" => Some(Self::Email),
+            "webhook" => Some(Self::Webhook),
+            "in_app" => Some(Self::InApp),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Webhook => "webhook",
+            Self::InApp => "in_app",
+        }
+    }
+}
+
+/// An alert as raised by the consumer, before routing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub tenant_id: Uuid,
+    pub alert_type: &'static str,
+    pub severity: Severity,
+    /// Alerts sharing a key are the same condition firing again.
+    pub dedup_key: String,
+    pub details: String,
+}
+
+impl Alert {
+    /// Critical once the product is out of stock, a warning before that.
+    pub fn low_stock(tenant_id: Uuid, product_id: Uuid, quantity: i32, threshold: i32) -> Self {
+        Self {
+            tenant_id,
+            alert_type: ALERT_LOW_STOCK,
+            severity: if quantity <= 0 { Severity::Critical } else { Severity::Warning },
+            dedup_key: format!("{ALERT_LOW_STOCK}:{product_id}"),
+            details: format!("Product {product_id} down to {quantity} (threshold {threshold})"),
+        }
+    }
+
+    pub fn high_refund_volume(tenant_id: Uuid, refunded: &Money, average: &Money) -> Self {
+        Self {
+            tenant_id,
+            alert_type: ALERT_HIGH_REFUND_VOLUME,
+            severity: Severity::Warning,
+            dedup_key: ALERT_HIGH_REFUND_VOLUME.to_string(),
+            details: format!("${refunded} refunded today vs ${average} avg"),
+        }
+    }
+}
+
+/// One configured destination, as stored in `alert_routes`.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct AlertRoute {
+    pub id: Uuid,
+    pub channel: String,
+    pub target: Option<String>,
+    pub min_severity: String,
+    /// Empty means every alert type.
+    pub alert_types: Vec<String>,
+}
+
+impl AlertRoute {
+    pub fn matches(&self, alert: &Alert) -> bool {
+        let severe_enough = Severity::parse(&self.min_severity).is_some_and(|min| alert.severity >= min);
+        severe_enough && (self.alert_types.is_empty() || self.alert_types.iter().any(|t| t == alert.alert_type))
+    }
+}
+
+/// What [`record_alert`] decided.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recorded {
+    /// A new (or escalated) alert; deliver it to these routes.
+    Deliver { alert_id: Uuid, routes: Vec<AlertRoute> },
+    /// Folded into an alert that is still open and was seen within the de-dup window.
+    Repeat { alert_id: Uuid },
+    /// Recorded, but a silence covers it.
+    Silenced { alert_id: Uuid },
+}
+
+impl Recorded {
+    pub fn alert_id(&self) -> Uuid {
+        match self {
+            Self::Deliver { alert_id, .. } | Self::Repeat { alert_id } | Self::Silenced { alert_id } => *alert_id,
+        }
+    }
+}
+
+/// Record an alert in `alerts` and decide whether it should be delivered. An open alert with the
+/// same key seen within `dedup_window` absorbs the repeat (bumping its count) unless the severity
+/// went up, in which case it is delivered again.
+pub async fn record_alert(db: &PgPool, alert: &Alert, dedup_window: Duration) -> sqlx::Result<Recorded> {
+    let mut tx = db.begin().await?;
+    let open = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, severity FROM alerts
+         WHERE tenant_id = $1 AND dedup_key = $2 AND status = 'open' AND last_seen_at > now() - make_interval(secs => $3)
+         ORDER BY last_seen_at DESC LIMIT 1
+         FOR UPDATE",
+    )
+    .bind(alert.tenant_id)
+    .bind(&alert.dedup_key)
+    .bind(dedup_window.num_seconds() as f64)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let silenced = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM alert_silences
+                        WHERE tenant_id = $1 AND (alert_type IS NULL OR alert_type = $2)
+                          AND starts_at <= now() AND ends_at > now())",
+    )
+    .bind(alert.tenant_id)
+    .bind(alert.alert_type)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let routes = if silenced {
+        Vec::new()
+    } else {
+        sqlx::query_as::<_, AlertRoute>(
+            "SELECT id, channel, target, min_severity, alert_types FROM alert_routes
+             WHERE tenant_id = $1 AND active ORDER BY position, id",
+        )
+        .bind(alert.tenant_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .filter(|route| route.matches(alert))
+        .collect()
+    };
+    let channels: Vec<&str> = routes.iter().map(|r| r.channel.as_str()).collect();
+
+    let recorded = match open {
+        Some((alert_id, severity)) => {
+            let escalated = Severity::parse(&severity).is_some_and(|previous| alert.severity > previous);
+            sqlx::query(
+                "UPDATE alerts
+                    SET occurrences = occurrences + 1, last_seen_at = now(), details = $2,
+                        severity = CASE WHEN $3 THEN $4 ELSE severity END,
+                        channels = CASE WHEN $3 AND NOT $5 THEN $6 ELSE channels END
+                  WHERE id = $1",
+            )
+            .bind(alert_id)
+            .bind(&alert.details)
+            .bind(escalated)
+            .bind(alert.severity.as_str())
+            .bind(silenced)
+            .bind(&channels)
+            .execute(&mut *tx)
+            .await?;
+            match (escalated, silenced) {
+                (true, false) => Recorded::Deliver { alert_id, routes },
+                (true, true) => Recorded::Silenced { alert_id },
+                (false, _) => Recorded::Repeat { alert_id },
+            }
+        }
+        None => {
+            let alert_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO alerts (id, tenant_id, alert_type, severity, dedup_key, details, silenced, channels)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            )
+            .bind(alert_id)
+            .bind(alert.tenant_id)
+            .bind(alert.alert_type)
+            .bind(alert.severity.as_str())
+            .bind(&alert.dedup_key)
+            .bind(&alert.details)
+            .bind(silenced)
+            .bind(&channels)
+            .execute(&mut *tx)
+            .await?;
+            if silenced {
+                Recorded::Silenced { alert_id }
+            } else {
+                Recorded::Deliver { alert_id, routes }
+            }
+        }
+    };
+    tx.commit().await?;
+    Ok(recorded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(min_severity: &str, alert_types: &[&str]) -> AlertRoute {
+        AlertRoute {
+            id: Uuid::new_v4(),
+            channel: "in_app".into(),
+            target: None,
+            min_severity: min_severity.into(),
+            alert_types: alert_types.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn low_stock_escalates_when_sold_out() {
+        let (tenant, product) = (Uuid::new_v4(), Uuid::new_v4());
+        let low = Alert::low_stock(tenant, product, 3, 5);
+        let out = Alert::low_stock(tenant, product, 0, 5);
+        assert_eq!(low.severity, Severity::Warning);
+        assert_eq!(out.severity, Severity::Critical);
+        assert_eq!(low.dedup_key, out.dedup_key);
+        assert_ne!(low.dedup_key, Alert::low_stock(tenant, Uuid::new_v4(), 3, 5).dedup_key);
+    }
+
+    #[test]
+    fn routes_filter_on_severity_and_type() {
+        let tenant = Uuid::new_v4();
+        let low = Alert::low_stock(tenant, Uuid::new_v4(), 3, 5);
+        let refunds = Alert::high_refund_volume(tenant, &Money::from_cents(50_000), &Money::from_cents(10_000));
+
+        assert!(route("info", &[]).matches(&low));
+        assert!(!route("critical", &[]).matches(&low));
+        assert!(route("warning", &[ALERT_LOW_STOCK]).matches(&low));
+        assert!(!route("warning", &[ALERT_LOW_STOCK]).matches(&refunds));
+        assert!(!route("bogus", &[]).matches(&low));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::AppState;
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::{HeaderMap, StatusCode},
     Json,
 };
@@ -8,10 +8,20 @@ use common_auth::{
     ensure_role, tenant_id_from_request, AuthContext, ROLE_ADMIN, ROLE_MANAGER, ROLE_SUPER_ADMIN,
 };
 
-use serde::Serialize;
+use analytics_service::heatmap::{heatmap_cells, Heatmap};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{Duration, NaiveDate, Utc};
+use common_money::Money;
+use serde::{Deserialize, Serialize, Serializer};
 use sqlx::Row;
 use uuid::Uuid;
 
+/// Amounts are summed as exact decimals and only become JSON numbers on the way out, already
+/// normalized to cents, so dashboards keep receiving numbers.
+pub(crate) fn major<S: Serializer>(amount: &Money, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(amount.inner().to_f64().unwrap_or(0.0))
+}
+
 #[derive(Serialize)]
 pub struct TopItem {
     pub product_id: Uuid,
@@ -21,16 +31,24 @@ pub struct TopItem {
 #[derive(Serialize)]
 pub struct Summary {
     pub today_orders: u64,
-    pub today_revenue: f64,
+    #[serde(serialize_with = "major")]
+    pub today_revenue: Money,
+    /// Amount of disputes opened today.
+    #[serde(serialize_with = "major")]
+    pub today_disputed: Money,
+    /// Amount of disputes lost today (revenue charged back).
+    #[serde(serialize_with = "major")]
+    pub today_chargebacks: Money,
     pub top_items: Vec<TopItem>,
 }
 
 #[derive(Serialize)]
 pub struct ForecastResult {
-    next_day_sales: f64,
+    #[serde(serialize_with = "major")]
+    next_day_sales: Money,
 }
 
-const ANALYTICS_VIEW_ROLES: &[&str] = &[ROLE_SUPER_ADMIN, ROLE_ADMIN, ROLE_MANAGER];
+pub(crate) const ANALYTICS_VIEW_ROLES: &[&str] = &[ROLE_SUPER_ADMIN, ROLE_ADMIN, ROLE_MANAGER];
 
 pub async fn get_summary(
     State(state): State<AppState>,
@@ -45,7 +63,7 @@ pub async fn get_summary(
          WHERE tenant_id = $1 AND date = CURRENT_DATE",
     )
     .bind(tenant_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(state.db.get().await)
     .await
     .map_err(|e| {
         (
@@ -62,17 +80,32 @@ pub async fn get_summary(
                     format!("DB row decode failed: {}", e),
                 )
             })?;
-            let total: Option<f64> = row.try_get("total_sales").map_err(|e| {
+            let total: Option<Money> = row.try_get("total_sales").map_err(|e| {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     format!("DB row decode failed: {}", e),
                 )
             })?;
-            (count as u64, total.unwrap_or(0.0))
+            (count as u64, total.unwrap_or_default())
         }
-        None => (0, 0.0),
+        None => (0, Money::default()),
     };
 
+    let (today_disputed, today_chargebacks) = sqlx::query_as::<_, (Money, Money)>(
+        "SELECT opened_amount, lost_amount FROM daily_disputes \
+         WHERE tenant_id = $1 AND date = CURRENT_DATE",
+    )
+    .bind(tenant_id)
+    .fetch_optional(state.db.get().await)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB query failed: {}", e),
+        )
+    })?
+    .unwrap_or_default();
+
     let mut top_items: Vec<TopItem> = Vec::new();
     if let Some(counts) = state.product_counts.lock().unwrap().get(&tenant_id) {
         for (&pid, &qty) in counts.iter() {
@@ -83,7 +116,7 @@ pub async fn get_summary(
                 });
             }
         }
-        top_items.sort_by(|a, b| b.quantity.cmp(&a.quantity));
+        top_items.sort_by_key(|item| std::cmp::Reverse(item.quantity));
         if top_items.len() > 5 {
             top_items.truncate(5);
         }
@@ -92,6 +125,8 @@ pub async fn get_summary(
     Ok(Json(Summary {
         today_orders: order_count,
         today_revenue: total_sales,
+        today_disputed,
+        today_chargebacks,
         top_items,
     }))
 }
@@ -104,13 +139,13 @@ pub async fn get_forecast(
     ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
     let tenant_id = tenant_id_from_request(&headers, &auth)?;
 
-    let rows = sqlx::query_scalar::<_, Option<f64>>(
+    let rows = sqlx::query_scalar::<_, Option<Money>>(
         "SELECT total_sales FROM daily_sales \
              WHERE tenant_id = $1 AND date < CURRENT_DATE \
              ORDER BY date DESC LIMIT 7",
     )
     .bind(tenant_id)
-    .fetch_all(&state.db)
+    .fetch_all(state.db.get().await)
     .await
     .map_err(|e| {
         (
@@ -119,15 +154,15 @@ pub async fn get_forecast(
         )
     })?;
 
-    let values: Vec<f64> = rows.into_iter().flatten().collect();
+    let values: Vec<Money> = rows.into_iter().flatten().collect();
     if values.is_empty() {
         return Ok(Json(ForecastResult {
-            next_day_sales: 0.0,
+            next_day_sales: Money::default(),
         }));
     }
 
-    let sum: f64 = values.iter().sum();
-    let avg = sum / (values.len() as f64);
+    let sum: Money = values.iter().sum();
+    let avg = Money::new(sum.inner() / BigDecimal::from(values.len() as u64));
 
     Ok(Json(ForecastResult {
         next_day_sales: avg,
@@ -142,12 +177,12 @@ pub async fn get_anomalies(
     ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
     let tenant_id = tenant_id_from_request(&headers, &auth)?;
 
-    let avg_refund = sqlx::query_scalar::<_, Option<f64>>(
+    let avg_refund = sqlx::query_scalar::<_, Option<Money>>(
         "SELECT AVG(refund_amount) FROM daily_sales \
              WHERE tenant_id = $1 AND date < CURRENT_DATE",
     )
     .bind(tenant_id)
-    .fetch_one(&state.db)
+    .fetch_one(state.db.get().await)
     .await
     .map_err(|e| {
         (
@@ -155,14 +190,14 @@ pub async fn get_anomalies(
             format!("DB query failed: {}", e),
         )
     })?
-    .unwrap_or(0.0);
+    .unwrap_or_default();
 
-    let today_refund = sqlx::query_scalar::<_, Option<f64>>(
+    let today_refund = sqlx::query_scalar::<_, Option<Money>>(
         "SELECT refund_amount FROM daily_sales \
              WHERE tenant_id = $1 AND date = CURRENT_DATE",
     )
     .bind(tenant_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(state.db.get().await)
     .await
     .map_err(|e| {
         (
@@ -171,15 +206,538 @@ pub async fn get_anomalies(
         )
     })?
     .flatten()
-    .unwrap_or(0.0);
+    .unwrap_or_default();
 
     let mut anomalies = Vec::new();
-    if avg_refund > 0.0 && today_refund > 2.0 * avg_refund {
+    if avg_refund.as_cents() > 0 && today_refund.as_cents() > 2 * avg_refund.as_cents() {
         anomalies.push(format!(
-            "High refund volume detected: ${:.2} refunded in last 24h vs ${:.2} avg",
+            "High refund volume detected: ${} refunded in last 24h vs ${} avg",
             today_refund, avg_refund
         ));
     }
 
     Ok(Json(anomalies))
 }
+
+/// Longest range the day-range reports (`/tips`, `/stores/*`, `/components`) cover at once.
+const MAX_REPORT_DAYS: i64 = 93;
+
+/// Inclusive day range for a report: `to` defaults to today (UTC) and `from` to six days before it.
+fn report_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(NaiveDate, NaiveDate), (StatusCode, String)> {
+    let to = to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = from.unwrap_or(to - Duration::days(6));
+    if from > to {
+        return Err((StatusCode::BAD_REQUEST, "from must not be after to".into()));
+    }
+    if (to - from).num_days() >= MAX_REPORT_DAYS {
+        return Err((StatusCode::BAD_REQUEST, format!("Range is limited to {MAX_REPORT_DAYS} days")));
+    }
+    Ok((from, to))
+}
+
+#[derive(Deserialize)]
+pub struct TipReportQuery {
+    /// First business day, inclusive; defaults to six days before `to`.
+    pub from: Option<NaiveDate>,
+    /// Last business day, inclusive; defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+    /// `employee` (default) or `shift` (employee, register and day).
+    pub group_by: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct TipReportRow {
+    /// `None` for tips on orders without a recorded employee.
+    pub employee_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_instance_id: Option<Uuid>,
+    #[serde(serialize_with = "major")]
+    pub tip_amount: Money,
+    pub tipped_orders: i64,
+    pub adjustments: i64,
+}
+
+#[derive(Serialize)]
+pub struct TipReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub group_by: &'static str,
+    #[serde(serialize_with = "major")]
+    pub total_tips: Money,
+    pub rows: Vec<TipReportRow>,
+}
+
+/// `GET /tips`: tips per employee, or per shift, over a range of business days. Tips adjusted
+/// after the sale count towards the day of the sale.
+pub async fn get_tips(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<TipReportQuery>,
+) -> Result<Json<TipReport>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+
+    let (from, to) = report_range(q.from, q.to)?;
+    let (group_by, sql) = match q.group_by.as_deref().unwrap_or("employee") {
+        "employee" => (
+            "employee",
+            "SELECT NULLIF(employee_id, '00000000-0000-0000-0000-000000000000'::uuid) AS employee_id, \
+                    NULL::date AS date, NULL::uuid AS pos_instance_id, \
+                    SUM(tip_amount) AS tip_amount, SUM(tipped_orders)::BIGINT AS tipped_orders, SUM(adjustments)::BIGINT AS adjustments \
+             FROM daily_tips WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 \
+             GROUP BY employee_id ORDER BY SUM(tip_amount) DESC, employee_id",
+        ),
+        "shift" => (
+            "shift",
+            "SELECT NULLIF(employee_id, '00000000-0000-0000-0000-000000000000'::uuid) AS employee_id, \
+                    date, NULLIF(pos_instance_id, '00000000-0000-0000-0000-000000000000'::uuid) AS pos_instance_id, \
+                    tip_amount, tipped_orders::BIGINT AS tipped_orders, adjustments::BIGINT AS adjustments \
+             FROM daily_tips WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 \
+             ORDER BY date, employee_id, pos_instance_id",
+        ),
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unsupported group_by '{other}'; use employee or shift"))),
+    };
+
+    let rows = sqlx::query_as::<_, TipReportRow>(sql)
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(state.db.get().await)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB query failed: {}", e),
+            )
+        })?;
+    let total_tips = rows.iter().map(|row| &row.tip_amount).sum();
+
+    Ok(Json(TipReport {
+        from,
+        to,
+        group_by,
+        total_tips,
+        rows,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct StoreReportQuery {
+    /// First day, inclusive; defaults to six days before `to`.
+    pub from: Option<NaiveDate>,
+    /// Last day, inclusive; defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+    /// Comma-separated store ids to compare; every store with activity in the range when omitted.
+    pub location_ids: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoreSalesTotals {
+    location_id: Uuid,
+    total_sales: Money,
+    order_count: i64,
+    item_count: i64,
+    refund_amount: Money,
+    refund_count: i64,
+}
+
+/// Sales figures over a period. `average_ticket` and `items_per_ticket` are per sale, before refunds.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct SalesMetrics {
+    #[serde(serialize_with = "major")]
+    pub total_sales: Money,
+    #[serde(serialize_with = "major")]
+    pub net_sales: Money,
+    pub order_count: i64,
+    #[serde(serialize_with = "major")]
+    pub average_ticket: Money,
+    pub item_count: i64,
+    pub items_per_ticket: f64,
+    #[serde(serialize_with = "major")]
+    pub refund_amount: Money,
+    pub refund_count: i64,
+}
+
+impl SalesMetrics {
+    fn new(total_sales: Money, order_count: i64, item_count: i64, refund_amount: Money, refund_count: i64) -> Self {
+        let (average_ticket, items_per_ticket) = if order_count > 0 {
+            (Money::new(total_sales.inner() / BigDecimal::from(order_count)), round2(item_count as f64 / order_count as f64))
+        } else {
+            (Money::default(), 0.0)
+        };
+        Self {
+            net_sales: &total_sales - &refund_amount,
+            total_sales,
+            order_count,
+            average_ticket,
+            item_count,
+            items_per_ticket,
+            refund_amount,
+            refund_count,
+        }
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Serialize)]
+pub struct StoreSales {
+    /// `None` for sales without a recorded store.
+    pub location_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub metrics: SalesMetrics,
+    /// Fraction of the company's sales over the period; only on `/stores/consolidated`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_of_sales: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct StoreComparison {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Highest sales first.
+    pub stores: Vec<StoreSales>,
+}
+
+#[derive(Serialize)]
+pub struct ConsolidatedSales {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Stores with activity in the range, not counting sales without a recorded store.
+    pub store_count: usize,
+    pub totals: SalesMetrics,
+    /// Highest sales first.
+    pub stores: Vec<StoreSales>,
+}
+
+async fn store_sales(
+    state: &AppState,
+    tenant_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    location_ids: Option<Vec<Uuid>>,
+) -> Result<Vec<StoreSalesTotals>, (StatusCode, String)> {
+    sqlx::query_as::<_, StoreSalesTotals>(
+        "SELECT location_id, SUM(total_sales) AS total_sales, SUM(order_count)::BIGINT AS order_count, \
+                SUM(item_count)::BIGINT AS item_count, SUM(refund_amount) AS refund_amount, \
+                SUM(refund_count)::BIGINT AS refund_count \
+         FROM daily_store_sales \
+         WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 AND ($4::uuid[] IS NULL OR location_id = ANY($4)) \
+         GROUP BY location_id ORDER BY SUM(total_sales) DESC, location_id",
+    )
+    .bind(tenant_id)
+    .bind(from)
+    .bind(to)
+    .bind(location_ids)
+    .fetch_all(state.db.get().await)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB query failed: {}", e),
+        )
+    })
+}
+
+fn parse_location_ids(raw: &str) -> Result<Vec<Uuid>, (StatusCode, String)> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| Uuid::parse_str(id).map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid location id '{id}'"))))
+        .collect()
+}
+
+fn to_store_sales(row: StoreSalesTotals, company_sales: Option<&Money>) -> StoreSales {
+    let share_of_sales = company_sales.map(|total| {
+        let total = total.as_cents();
+        if total > 0 { (row.total_sales.as_cents() as f64 / total as f64 * 10_000.0).round() / 10_000.0 } else { 0.0 }
+    });
+    StoreSales {
+        location_id: (!row.location_id.is_nil()).then_some(row.location_id),
+        metrics: SalesMetrics::new(row.total_sales, row.order_count, row.item_count, row.refund_amount, row.refund_count),
+        share_of_sales,
+    }
+}
+
+/// `GET /stores/compare`: sales, average ticket and items per ticket for each store over a range.
+pub async fn compare_stores(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<StoreReportQuery>,
+) -> Result<Json<StoreComparison>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let (from, to) = report_range(q.from, q.to)?;
+    let location_ids = q.location_ids.as_deref().map(parse_location_ids).transpose()?;
+
+    let rows = store_sales(&state, tenant_id, from, to, location_ids).await?;
+    Ok(Json(StoreComparison {
+        from,
+        to,
+        stores: rows.into_iter().map(|row| to_store_sales(row, None)).collect(),
+    }))
+}
+
+/// `GET /stores/consolidated`: company-wide sales over a range with each store's contribution.
+pub async fn get_consolidated_sales(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<StoreReportQuery>,
+) -> Result<Json<ConsolidatedSales>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let (from, to) = report_range(q.from, q.to)?;
+
+    let rows = store_sales(&state, tenant_id, from, to, None).await?;
+    let company_sales: Money = rows.iter().map(|row| &row.total_sales).sum();
+    let totals = SalesMetrics::new(
+        company_sales.clone(),
+        rows.iter().map(|row| row.order_count).sum(),
+        rows.iter().map(|row| row.item_count).sum(),
+        rows.iter().map(|row| &row.refund_amount).sum(),
+        rows.iter().map(|row| row.refund_count).sum(),
+    );
+    let store_count = rows.iter().filter(|row| !row.location_id.is_nil()).count();
+
+    Ok(Json(ConsolidatedSales {
+        from,
+        to,
+        store_count,
+        totals,
+        stores: rows.into_iter().map(|row| to_store_sales(row, Some(&company_sales))).collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct HeatmapQuery {
+    /// One store; every store, each in its own time zone, when omitted.
+    pub location_id: Option<Uuid>,
+    /// First local day, inclusive; defaults to six days before `to`.
+    pub from: Option<NaiveDate>,
+    /// Last local day, inclusive; defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct HeatmapReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub location_id: Option<Uuid>,
+    /// Occurrences of each weekday in the range, Monday first, for per-day averages.
+    pub days: [i64; 7],
+    /// Sales per weekday (Monday first) and local hour.
+    pub transactions: [[i64; 24]; 7],
+    pub revenue: Vec<Vec<f64>>,
+}
+
+/// `GET /analytics/heatmap`: sales and revenue per weekday and local hour of day, for staffing.
+/// Hours are the store's wall-clock hours, daylight saving included; see `heatmap`.
+pub async fn get_heatmap(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<HeatmapQuery>,
+) -> Result<Json<HeatmapReport>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let (from, to) = report_range(q.from, q.to)?;
+
+    let cells = heatmap_cells(state.db.get().await, tenant_id, q.location_id, from, to).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB query failed: {}", e),
+        )
+    })?;
+    let heatmap = Heatmap::new(from, to, &cells);
+    let revenue = heatmap
+        .revenue
+        .iter()
+        .map(|hours| hours.iter().map(|amount| amount.inner().to_f64().unwrap_or(0.0)).collect())
+        .collect();
+
+    Ok(Json(HeatmapReport { from, to, location_id: q.location_id, days: heatmap.days, transactions: heatmap.transactions, revenue }))
+}
+
+#[derive(Deserialize)]
+pub struct EmployeeReportQuery {
+    /// First day, inclusive; defaults to six days before `to`.
+    pub from: Option<NaiveDate>,
+    /// Last day, inclusive; defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+    /// Only report on this employee.
+    pub employee_id: Option<Uuid>,
+}
+
+#[derive(sqlx::FromRow)]
+struct EmployeeTotals {
+    employee_id: Uuid,
+    total_sales: Money,
+    order_count: i64,
+    item_count: i64,
+    refund_amount: Money,
+    refund_count: i64,
+    void_count: i64,
+    void_amount: Money,
+    selling_minutes: f64,
+}
+
+#[derive(Serialize)]
+pub struct EmployeePerformance {
+    /// `None` for orders without a recorded cashier.
+    pub employee_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub metrics: SalesMetrics,
+    pub void_count: i64,
+    #[serde(serialize_with = "major")]
+    pub void_amount: Money,
+    /// Time between each day's first and last sale, summed over the range.
+    pub selling_minutes: f64,
+    /// `None` until there is at least a minute of selling time to measure against.
+    pub items_per_minute: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct EmployeeReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Highest sales first.
+    pub employees: Vec<EmployeePerformance>,
+}
+
+/// `GET /analytics/employees`: sales, average transaction, refunds, voids and items per minute for
+/// each employee over a range. Refunds count against whoever processed them; voids against whoever
+/// requested them.
+pub async fn get_employee_performance(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<EmployeeReportQuery>,
+) -> Result<Json<EmployeeReport>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let (from, to) = report_range(q.from, q.to)?;
+
+    let rows = sqlx::query_as::<_, EmployeeTotals>(
+        "WITH sales AS ( \
+             SELECT employee_id, SUM(total_sales) AS total_sales, SUM(order_count)::BIGINT AS order_count, \
+                    SUM(item_count)::BIGINT AS item_count, SUM(refund_amount) AS refund_amount, \
+                    SUM(refund_count)::BIGINT AS refund_count, \
+                    COALESCE(SUM(EXTRACT(EPOCH FROM last_sale_at - first_sale_at)), 0)::DOUBLE PRECISION / 60 AS selling_minutes \
+             FROM daily_employee_sales WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 GROUP BY employee_id \
+         ), voids AS ( \
+             SELECT employee_id, SUM(void_count)::BIGINT AS void_count, SUM(void_amount) AS void_amount \
+             FROM daily_employee_voids WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 GROUP BY employee_id \
+         ) \
+         SELECT COALESCE(s.employee_id, v.employee_id) AS employee_id, \
+                COALESCE(s.total_sales, 0) AS total_sales, COALESCE(s.order_count, 0) AS order_count, \
+                COALESCE(s.item_count, 0) AS item_count, COALESCE(s.refund_amount, 0) AS refund_amount, \
+                COALESCE(s.refund_count, 0) AS refund_count, COALESCE(v.void_count, 0) AS void_count, \
+                COALESCE(v.void_amount, 0) AS void_amount, COALESCE(s.selling_minutes, 0) AS selling_minutes \
+         FROM sales s FULL OUTER JOIN voids v ON v.employee_id = s.employee_id \
+         WHERE $4::uuid IS NULL OR COALESCE(s.employee_id, v.employee_id) = $4 \
+         ORDER BY 2 DESC, 1",
+    )
+    .bind(tenant_id)
+    .bind(from)
+    .bind(to)
+    .bind(q.employee_id)
+    .fetch_all(state.db.get().await)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB query failed: {}", e),
+        )
+    })?;
+
+    let employees = rows
+        .into_iter()
+        .map(|row| EmployeePerformance {
+            employee_id: (!row.employee_id.is_nil()).then_some(row.employee_id),
+            metrics: SalesMetrics::new(row.total_sales, row.order_count, row.item_count, row.refund_amount, row.refund_count),
+            void_count: row.void_count,
+            void_amount: row.void_amount,
+            selling_minutes: round2(row.selling_minutes),
+            items_per_minute: (row.selling_minutes >= 1.0).then(|| round2(row.item_count as f64 / row.selling_minutes)),
+        })
+        .collect();
+
+    Ok(Json(EmployeeReport { from, to, employees }))
+}
+
+#[derive(Deserialize)]
+pub struct ComponentReportQuery {
+    /// First day, inclusive; defaults to six days before `to`.
+    pub from: Option<NaiveDate>,
+    /// Last day, inclusive; defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+    /// `component` (default) or `bundle` (component per bundle it went into).
+    pub group_by: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct ComponentReportRow {
+    pub component_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_id: Option<Uuid>,
+    /// Stock units, net of refunds: whole units, or thousandths of a kg/lb for weighed components.
+    pub quantity: i64,
+}
+
+#[derive(Serialize)]
+pub struct ComponentReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub group_by: &'static str,
+    pub rows: Vec<ComponentReportRow>,
+}
+
+/// `GET /components`: component stock drawn by bundle sales over a range of days.
+pub async fn get_component_consumption(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<ComponentReportQuery>,
+) -> Result<Json<ComponentReport>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+
+    let (from, to) = report_range(q.from, q.to)?;
+    let (group_by, sql) = match q.group_by.as_deref().unwrap_or("component") {
+        "component" => (
+            "component",
+            "SELECT component_id, NULL::uuid AS bundle_id, SUM(quantity)::BIGINT AS quantity \
+             FROM daily_component_consumption WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 \
+             GROUP BY component_id ORDER BY SUM(quantity) DESC, component_id",
+        ),
+        "bundle" => (
+            "bundle",
+            "SELECT component_id, bundle_id, SUM(quantity)::BIGINT AS quantity \
+             FROM daily_component_consumption WHERE tenant_id = $1 AND date BETWEEN $2 AND $3 \
+             GROUP BY bundle_id, component_id ORDER BY bundle_id, SUM(quantity) DESC, component_id",
+        ),
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unsupported group_by '{other}'; use component or bundle"))),
+    };
+
+    let rows = sqlx::query_as::<_, ComponentReportRow>(sql)
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(state.db.get().await)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB query failed: {}", e),
+            )
+        })?;
+
+    Ok(Json(ComponentReport { from, to, group_by, rows }))
+}
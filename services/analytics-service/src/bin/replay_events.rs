@@ -0,0 +1,311 @@
+use analytics_service::projection::{
+    apply_audit_event, apply_daily_component_consumption, apply_day_closed, apply_daily_customer_sales, apply_daily_disputes, apply_daily_employee_sales,
+    apply_daily_employee_voids, apply_daily_product_sales, apply_daily_sales, apply_daily_store_sales, apply_daily_tips, apply_hourly_store_sales, reset_daily_customer_sales,
+    reset_daily_disputes, reset_daily_employee_sales, reset_daily_employee_voids, reset_daily_component_consumption, reset_daily_product_sales, reset_daily_sales, reset_daily_store_sales, reset_daily_tips, reset_hourly_store_sales, DisputeDelta, SalesDelta, TipDelta, VoidDelta,
+};
+use analytics_service::replay::{replay, resolve_ranges, Outcome, ReplayConfig, ReplayMessage, StartPosition};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, ValueEnum};
+use common_config::{require_secret, KafkaSecurity};
+use common_events::{topics, ComponentsConsumedEvent, DayClosedEvent, OrderCompletedEvent, OrderTipRecordedEvent, OrderVoidedEvent, PaymentDisputeUpdatedEvent};
+use rdkafka::consumer::StreamConsumer;
+use sqlx::PgPool;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ReadModel {
+    /// `daily_sales`, `daily_store_sales`, `hourly_store_sales`, `daily_employee_sales`,
+    /// `daily_product_sales` and `daily_customer_sales`, rebuilt from `order.completed`
+    Analytics,
+    /// `daily_employee_voids`, rebuilt from `order.voided`
+    Voids,
+    /// `daily_disputes`, rebuilt from `payment.dispute.updated`
+    Disputes,
+    /// `daily_tips`, rebuilt from `order.tip_recorded`
+    Tips,
+    /// `daily_component_consumption`, rebuilt from `inventory.components.consumed`
+    Components,
+    /// `closed_business_days` and the finalized `daily_store_sales` rows, from `day.closed`; run
+    /// after an `analytics` rebuild, which drops the finalized figures
+    DayCloses,
+    /// `audit_events`, back-filled from the audit topic
+    Audit,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Re-consume a topic into a read model in rebuild mode (no alerts or other side effects)",
+    long_about = None
+)]
+struct Options {
+    /// Read model to rebuild
+    #[arg(long = "consumer", value_enum)]
+    consumer: ReadModel,
+
+    /// Topic to read (defaults to the read model's topic)
+    #[arg(long = "topic")]
+    topic: Option<String>,
+
+    /// Start every partition at this offset
+    #[arg(long = "from-offset", conflicts_with = "from_timestamp")]
+    from_offset: Option<i64>,
+
+    /// Start at the first message published at or after this RFC 3339 time
+    #[arg(long = "from-timestamp", value_name = "RFC3339")]
+    from_timestamp: Option<DateTime<Utc>>,
+
+    /// Only replay events for this tenant
+    #[arg(long = "tenant", value_name = "UUID")]
+    tenant: Option<Uuid>,
+
+    /// Delete the affected rows of the read model's tables first. With --from-timestamp the
+    /// replay is widened to the start of that UTC day so whole days are rebuilt.
+    #[arg(long = "reset", conflicts_with = "from_offset")]
+    reset: bool,
+
+    /// Maximum messages handled per second
+    #[arg(long = "max-per-sec")]
+    max_per_sec: Option<u32>,
+
+    /// Log progress every N messages
+    #[arg(long = "progress-every", default_value_t = 1000)]
+    progress_every: u64,
+
+    /// Decode and count messages without writing anything
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    common_observability::init_logging("analytics-replay");
+    let opts = Options::parse();
+    if opts.reset && matches!(opts.consumer, ReadModel::Audit | ReadModel::DayCloses) {
+        return Err(anyhow!("--reset only applies to --consumer analytics|voids|disputes|tips|components; audit and day-closes replays are idempotent"));
+    }
+
+    let topic = opts.topic.clone().unwrap_or_else(|| match opts.consumer {
+        ReadModel::Analytics => topics::ORDER_COMPLETED.to_string(),
+        ReadModel::Voids => topics::ORDER_VOIDED.to_string(),
+        ReadModel::Disputes => topics::PAYMENT_DISPUTE_UPDATED.to_string(),
+        ReadModel::Tips => topics::ORDER_TIP_RECORDED.to_string(),
+        ReadModel::Components => topics::INVENTORY_COMPONENTS_CONSUMED.to_string(),
+        ReadModel::DayCloses => topics::DAY_CLOSED.to_string(),
+        ReadModel::Audit => std::env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events".to_string()),
+    });
+    let start = match (opts.from_offset, opts.from_timestamp) {
+        (Some(offset), _) => StartPosition::Offset(offset),
+        (None, Some(ts)) if opts.reset => StartPosition::Timestamp(ts.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc()),
+        (None, Some(ts)) => StartPosition::Timestamp(ts),
+        (None, None) => StartPosition::Beginning,
+    };
+    let config = ReplayConfig { topic, start, max_per_sec: opts.max_per_sec, progress_every: opts.progress_every };
+
+    let database_url = require_secret("DATABASE_URL").await.context("DATABASE_URL is needed for replay")?;
+    let db = PgPool::connect(&database_url).await?;
+    let consumer: StreamConsumer = KafkaSecurity::from_env()
+        .await?
+        .apply(&mut rdkafka::ClientConfig::new())
+        .set("bootstrap.servers", std::env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()))
+        .set("group.id", format!("analytics-replay-{}", Uuid::new_v4()))
+        .set("enable.auto.commit", "false")
+        .set("enable.partition.eof", "false")
+        .create()
+        .context("failed to create kafka consumer")?;
+
+    let ranges = resolve_ranges(&consumer, &config)?;
+    for range in &ranges {
+        println!("{}[{}]: offsets {}..{} ({} messages)", config.topic, range.partition, range.start, range.end, range.len());
+    }
+
+    let since = match start {
+        StartPosition::Timestamp(ts) if opts.reset => Some(ts.date_naive()),
+        _ => None,
+    };
+    if opts.reset && !opts.dry_run {
+        let (table, deleted) = match opts.consumer {
+            ReadModel::Disputes => ("daily_disputes", reset_daily_disputes(&db, since, opts.tenant).await?),
+            ReadModel::Tips => ("daily_tips", reset_daily_tips(&db, since, opts.tenant).await?),
+            ReadModel::Voids => ("daily_employee_voids", reset_daily_employee_voids(&db, since, opts.tenant).await?),
+            ReadModel::Components => ("daily_component_consumption", reset_daily_component_consumption(&db, since, opts.tenant).await?),
+            _ => {
+                let stores = reset_daily_store_sales(&db, since, opts.tenant).await?;
+                println!("Removed {stores} daily_store_sales rows ahead of rebuild");
+                let hours = reset_hourly_store_sales(&db, since, opts.tenant).await?;
+                println!("Removed {hours} hourly_store_sales rows ahead of rebuild");
+                let employees = reset_daily_employee_sales(&db, since, opts.tenant).await?;
+                println!("Removed {employees} daily_employee_sales rows ahead of rebuild");
+                let products = reset_daily_product_sales(&db, since, opts.tenant).await?;
+                println!("Removed {products} daily_product_sales rows ahead of rebuild");
+                let customers = reset_daily_customer_sales(&db, since, opts.tenant).await?;
+                println!("Removed {customers} daily_customer_sales rows ahead of rebuild");
+                ("daily_sales", reset_daily_sales(&db, since, opts.tenant).await?)
+            }
+        };
+        println!("Removed {deleted} {table} rows ahead of rebuild");
+    }
+
+    let tenant = opts.tenant;
+    let dry_run = opts.dry_run;
+    let model = opts.consumer;
+    let stats = replay(&consumer, &config, &ranges, |msg| {
+        let db = db.clone();
+        async move { handle(model, &db, msg, tenant, since, dry_run).await }
+    })
+    .await?;
+
+    let verb = if dry_run { "would apply" } else { "applied" };
+    println!(
+        "Replay of {} complete: {} of {} messages processed, {verb} {}, skipped {}",
+        config.topic, stats.processed, stats.total, stats.applied, stats.skipped
+    );
+    Ok(())
+}
+
+/// `since` is the first day a `--reset` rebuild deleted. Events carrying a business date before it
+/// are skipped: the rows they belong to were kept and already hold them. That covers tip
+/// adjustments to older sales and sales rung up after midnight for the previous business day.
+async fn handle(
+    model: ReadModel,
+    db: &PgPool,
+    msg: ReplayMessage,
+    tenant: Option<Uuid>,
+    since: Option<NaiveDate>,
+    dry_run: bool,
+) -> Result<Outcome> {
+    match model {
+        ReadModel::Analytics => {
+            let evt = match common_events::decode::<OrderCompletedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            let date = evt.business_date.or(msg.timestamp.map(|ts| ts.date_naive()));
+            if tenant.is_some_and(|t| t != evt.tenant_id) {
+                return Ok(Outcome::Skipped);
+            }
+            // Hourly buckets are reset by publish time, so they take every replayed sale, including
+            // ones booked on a business day before the rebuild.
+            let kept_day = since.zip(date).is_some_and(|(since, day)| day < since);
+            if !dry_run {
+                let delta = SalesDelta::from_event(&evt);
+                apply_hourly_store_sales(db, &delta, msg.timestamp).await?;
+                if !kept_day {
+                    apply_daily_sales(db, &delta, date).await?;
+                    apply_daily_store_sales(db, &delta, date).await?;
+                    apply_daily_employee_sales(db, &delta, msg.timestamp, date).await?;
+                    apply_daily_product_sales(db, &evt, date).await?;
+                    apply_daily_customer_sales(db, &evt, date).await?;
+                }
+            }
+            Ok(if kept_day { Outcome::Skipped } else { Outcome::Applied })
+        }
+        ReadModel::Voids => {
+            let evt = match common_events::decode::<OrderVoidedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            let date = evt.business_date.or(msg.timestamp.map(|ts| ts.date_naive()));
+            let Some(delta) = VoidDelta::from_event(&evt)
+                .filter(|_| tenant.is_none_or(|t| t == evt.tenant_id) && since.zip(date).is_none_or(|(since, day)| day >= since))
+            else {
+                return Ok(Outcome::Skipped);
+            };
+            if !dry_run {
+                apply_daily_employee_voids(db, &delta, date).await?;
+            }
+            Ok(Outcome::Applied)
+        }
+        ReadModel::Disputes => {
+            let evt = match common_events::decode::<PaymentDisputeUpdatedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            let Some(delta) = DisputeDelta::from_event(&evt).filter(|_| tenant.is_none_or(|t| t == evt.tenant_id)) else {
+                return Ok(Outcome::Skipped);
+            };
+            if !dry_run {
+                apply_daily_disputes(db, &delta, msg.timestamp.map(|ts| ts.date_naive())).await?;
+            }
+            Ok(Outcome::Applied)
+        }
+        ReadModel::Tips => {
+            let evt = match common_events::decode::<OrderTipRecordedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            if tenant.is_some_and(|t| t != evt.tenant_id) || since.is_some_and(|day| evt.business_date < day) {
+                return Ok(Outcome::Skipped);
+            }
+            let Some(delta) = TipDelta::from_event(&evt) else {
+                return Ok(Outcome::Skipped);
+            };
+            if !dry_run {
+                apply_daily_tips(db, &delta).await?;
+            }
+            Ok(Outcome::Applied)
+        }
+        ReadModel::Components => {
+            let evt = match common_events::decode::<ComponentsConsumedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            if tenant.is_some_and(|t| t != evt.tenant_id) {
+                return Ok(Outcome::Skipped);
+            }
+            if !dry_run {
+                apply_daily_component_consumption(db, &evt, msg.timestamp.map(|ts| ts.date_naive())).await?;
+            }
+            Ok(Outcome::Applied)
+        }
+        ReadModel::DayCloses => {
+            let evt = match common_events::decode::<DayClosedEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            if tenant.is_some_and(|t| t != evt.tenant_id) {
+                return Ok(Outcome::Skipped);
+            }
+            if !dry_run {
+                apply_day_closed(db, &evt).await?;
+            }
+            Ok(Outcome::Applied)
+        }
+        ReadModel::Audit => {
+            let evt = match serde_json::from_str::<common_audit::AuditEvent>(&msg.payload) {
+                Ok(evt) => evt,
+                Err(err) => {
+                    warn!(partition = msg.partition, offset = msg.offset, %err, "skipping undecodable message");
+                    return Ok(Outcome::Skipped);
+                }
+            };
+            if tenant.is_some_and(|t| t != evt.tenant_id) {
+                return Ok(Outcome::Skipped);
+            }
+            if dry_run || apply_audit_event(db, &evt).await? {
+                Ok(Outcome::Applied)
+            } else {
+                Ok(Outcome::Skipped)
+            }
+        }
+    }
+}
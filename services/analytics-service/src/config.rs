@@ -0,0 +1,61 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    /// `ANALYTICS_INBOX_DEDUP`
+    pub inbox_dedup: bool,
+    /// `ANALYTICS_REPORT_TICK_SECONDS`: how often due report subscriptions are sent; 0 disables.
+    pub report_tick_seconds: u64,
+    /// `ANALYTICS_ALERT_DEDUP_SECONDS`: repeats of an open alert within this window are not re-sent.
+    pub alert_dedup_seconds: u64,
+    /// `ANALYTICS_RECONCILE_HOUR_UTC`: hour of the nightly `daily_sales` reconciliation.
+    pub reconcile_hour_utc: u32,
+    /// `ANALYTICS_RECONCILE_DAYS`: business days before today each reconciliation checks; 0 disables.
+    pub reconcile_days: u32,
+    /// `ANALYTICS_RECONCILE_TOLERANCE_CENTS`: amount difference still counted as a match.
+    pub reconcile_tolerance_cents: i64,
+    pub jwt: JwtSettings,
+}
+
+impl AnalyticsConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8086);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let inbox_dedup = env.flag("ANALYTICS_INBOX_DEDUP", true);
+        let report_tick_seconds: u64 = env.or("ANALYTICS_REPORT_TICK_SECONDS", 60);
+        let alert_dedup_seconds: u64 = env.or("ANALYTICS_ALERT_DEDUP_SECONDS", 3600);
+        let reconcile_hour_utc: u32 = env.or("ANALYTICS_RECONCILE_HOUR_UTC", 3);
+        env.check("ANALYTICS_RECONCILE_HOUR_UTC", reconcile_hour_utc < 24, "must be an hour from 0 to 23");
+        let reconcile_days: u32 = env.or("ANALYTICS_RECONCILE_DAYS", 2);
+        let reconcile_tolerance_cents: i64 = env.or("ANALYTICS_RECONCILE_TOLERANCE_CENTS", 0);
+        env.check("ANALYTICS_RECONCILE_TOLERANCE_CENTS", reconcile_tolerance_cents >= 0, "must not be negative");
+        let jwt = JwtSettings::read(&mut env).await;
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                inbox_dedup,
+                report_tick_seconds,
+                alert_dedup_seconds,
+                reconcile_hour_utc,
+                reconcile_days,
+                reconcile_tolerance_cents,
+                jwt: jwt?,
+            })
+        })
+    }
+}
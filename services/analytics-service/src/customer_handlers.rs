@@ -0,0 +1,151 @@
+use crate::analytics_handlers::{major, ANALYTICS_VIEW_ROLES};
+use crate::AppState;
+use analytics_service::customers::{all_customer_totals, customer_totals, score_customers, CustomerTotals, Segment};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use common_auth::{ensure_role, tenant_id_from_request, AuthContext};
+use common_money::Money;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const DEFAULT_RFM_LIMIT: usize = 100;
+const MAX_RFM_LIMIT: usize = 1000;
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("DB query failed: {}", e))
+}
+
+#[derive(Deserialize)]
+pub struct CustomerQuery {
+    /// Count sales up to and including this day; defaults to today (UTC).
+    pub as_of: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct CustomerMetrics {
+    pub customer_id: Uuid,
+    pub as_of: NaiveDate,
+    pub visit_count: i64,
+    /// Sales net of refunds.
+    #[serde(serialize_with = "major")]
+    pub lifetime_spend: Money,
+    #[serde(serialize_with = "major")]
+    pub total_sales: Money,
+    #[serde(serialize_with = "major")]
+    pub refund_amount: Money,
+    pub refund_count: i64,
+    /// Sales per visit, before refunds.
+    #[serde(serialize_with = "major")]
+    pub average_basket: Money,
+    pub first_visit: Option<NaiveDate>,
+    pub last_visit: Option<NaiveDate>,
+    pub days_since_last_visit: Option<i64>,
+}
+
+impl CustomerMetrics {
+    fn new(totals: &CustomerTotals, as_of: NaiveDate) -> Self {
+        Self {
+            customer_id: totals.customer_id,
+            as_of,
+            visit_count: totals.visit_count,
+            lifetime_spend: totals.lifetime_spend(),
+            total_sales: totals.total_sales.clone(),
+            refund_amount: totals.refund_amount.clone(),
+            refund_count: totals.refund_count,
+            average_basket: totals.average_basket(),
+            first_visit: totals.first_visit,
+            last_visit: totals.last_visit,
+            days_since_last_visit: totals.days_since_last_visit(as_of),
+        }
+    }
+}
+
+/// `GET /analytics/customers/:id`: lifetime spend, visits, average basket and days since the last
+/// visit. 404 when no sale to the customer was recorded, including once they are erased.
+pub async fn get_customer_metrics(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(customer_id): Path<Uuid>,
+    Query(q): Query<CustomerQuery>,
+) -> Result<Json<CustomerMetrics>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let as_of = q.as_of.unwrap_or_else(|| Utc::now().date_naive());
+
+    let totals = customer_totals(state.db.get().await, tenant_id, customer_id, as_of)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "No sales recorded for this customer".to_string()))?;
+    Ok(Json(CustomerMetrics::new(&totals, as_of)))
+}
+
+#[derive(Deserialize)]
+pub struct RfmQuery {
+    /// Score visits up to and including this day; defaults to today (UTC).
+    pub as_of: Option<NaiveDate>,
+    /// Only list customers in this segment.
+    pub segment: Option<Segment>,
+    /// Customers listed, highest lifetime spend first; defaults to 100, at most 1000.
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct CustomerRfm {
+    #[serde(flatten)]
+    pub metrics: CustomerMetrics,
+    pub recency: u8,
+    pub frequency: u8,
+    pub monetary: u8,
+    pub segment: Segment,
+}
+
+#[derive(Serialize)]
+pub struct RfmReport {
+    pub as_of: NaiveDate,
+    /// Customers per segment, over all scored customers.
+    pub segments: BTreeMap<Segment, usize>,
+    pub customers: Vec<CustomerRfm>,
+}
+
+/// `GET /analytics/customers/rfm`: every customer with a visit scored 1-5 on recency, frequency and
+/// spend against the tenant's other customers, and the segment those scores put them in.
+pub async fn get_rfm_segments(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<RfmQuery>,
+) -> Result<Json<RfmReport>, (StatusCode, String)> {
+    ensure_role(&auth, ANALYTICS_VIEW_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let as_of = q.as_of.unwrap_or_else(|| Utc::now().date_naive());
+    let limit = q.limit.unwrap_or(DEFAULT_RFM_LIMIT);
+    if limit == 0 || limit > MAX_RFM_LIMIT {
+        return Err((StatusCode::BAD_REQUEST, format!("limit must be between 1 and {MAX_RFM_LIMIT}")));
+    }
+
+    let scored = score_customers(all_customer_totals(state.db.get().await, tenant_id, as_of).await.map_err(db_error)?);
+    let mut segments = BTreeMap::new();
+    for score in &scored {
+        *segments.entry(score.segment).or_insert(0) += 1;
+    }
+    let customers = scored
+        .into_iter()
+        .filter(|score| q.segment.is_none_or(|segment| score.segment == segment))
+        .take(limit)
+        .map(|score| CustomerRfm {
+            metrics: CustomerMetrics::new(&score.totals, as_of),
+            recency: score.recency,
+            frequency: score.frequency,
+            monetary: score.monetary,
+            segment: score.segment,
+        })
+        .collect();
+
+    Ok(Json(RfmReport { as_of, segments, customers }))
+}
@@ -0,0 +1,225 @@
+//! Customer-level figures for loyalty and marketing, read from the `daily_customer_sales` rollup:
+//! lifetime spend, visits, average basket and recency for a customer, and an RFM (recency,
+//! frequency, monetary) segmentation of a tenant's customers. Only sales with a customer attached
+//! are counted. Erased customers are no longer in the rollup; see
+//! [`apply_customer_erased`](crate::projection::apply_customer_erased).
+
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, NaiveDate};
+use common_money::Money;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A customer's totals over every day up to the requested date.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct CustomerTotals {
+    pub customer_id: Uuid,
+    /// Orders; an order is one visit.
+    pub visit_count: i64,
+    pub total_sales: Money,
+    pub refund_amount: Money,
+    pub refund_count: i64,
+    /// `None` when the customer only has refunds on record.
+    pub first_visit: Option<NaiveDate>,
+    pub last_visit: Option<NaiveDate>,
+}
+
+impl CustomerTotals {
+    /// Sales net of refunds.
+    pub fn lifetime_spend(&self) -> Money {
+        &self.total_sales - &self.refund_amount
+    }
+
+    /// Sales per visit, before refunds; zero without visits.
+    pub fn average_basket(&self) -> Money {
+        if self.visit_count == 0 {
+            return Money::default();
+        }
+        Money::new(self.total_sales.inner() / BigDecimal::from(self.visit_count))
+    }
+
+    pub fn days_since_last_visit(&self, today: NaiveDate) -> Option<i64> {
+        self.last_visit.map(|day| (today - day).num_days())
+    }
+}
+
+const TOTALS_SQL: &str = "SELECT customer_id, SUM(order_count)::BIGINT AS visit_count, SUM(total_sales)::NUMERIC AS total_sales, \
+                                 SUM(refund_amount)::NUMERIC AS refund_amount, SUM(refund_count)::BIGINT AS refund_count, \
+                                 MIN(date) FILTER (WHERE order_count > 0) AS first_visit, \
+                                 MAX(date) FILTER (WHERE order_count > 0) AS last_visit \
+                          FROM daily_customer_sales \
+                          WHERE tenant_id = $1 AND date <= $2 AND ($3::uuid IS NULL OR customer_id = $3) \
+                          GROUP BY customer_id ORDER BY customer_id";
+
+/// Totals for one customer up to `as_of`, or `None` when nothing was ever sold to them.
+pub async fn customer_totals(db: &PgPool, tenant_id: Uuid, customer_id: Uuid, as_of: NaiveDate) -> sqlx::Result<Option<CustomerTotals>> {
+    sqlx::query_as::<_, CustomerTotals>(TOTALS_SQL)
+        .bind(tenant_id)
+        .bind(as_of)
+        .bind(customer_id)
+        .fetch_optional(db)
+        .await
+}
+
+/// Totals for every customer of the tenant up to `as_of`.
+pub async fn all_customer_totals(db: &PgPool, tenant_id: Uuid, as_of: NaiveDate) -> sqlx::Result<Vec<CustomerTotals>> {
+    sqlx::query_as::<_, CustomerTotals>(TOTALS_SQL)
+        .bind(tenant_id)
+        .bind(as_of)
+        .bind(None::<Uuid>)
+        .fetch_all(db)
+        .await
+}
+
+/// Marketing segment from the three RFM scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Segment {
+    /// Recent, frequent and high spending.
+    Champions,
+    /// Frequent and not lapsed.
+    Loyal,
+    /// Recent but few visits so far.
+    New,
+    /// Used to visit often, not lately.
+    AtRisk,
+    /// Few visits, none lately.
+    Hibernating,
+    /// Everyone in between.
+    NeedsAttention,
+}
+
+impl Segment {
+    /// Scores run from 1 (worst) to 5 (best); the first matching rule wins.
+    pub fn from_scores(recency: u8, frequency: u8, monetary: u8) -> Self {
+        match (recency, frequency, monetary) {
+            (4.., 4.., 4..) => Segment::Champions,
+            (3.., 4.., _) => Segment::Loyal,
+            (4.., ..=2, _) => Segment::New,
+            (..=2, 3.., _) => Segment::AtRisk,
+            (..=2, _, _) => Segment::Hibernating,
+            _ => Segment::NeedsAttention,
+        }
+    }
+}
+
+/// A customer's RFM scores, each 1 to 5 against the tenant's other customers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RfmScore {
+    pub totals: CustomerTotals,
+    pub recency: u8,
+    pub frequency: u8,
+    pub monetary: u8,
+    pub segment: Segment,
+}
+
+/// Score `customers` on the date of their last visit, their visit count and their lifetime spend.
+/// Customers without a visit are left out. Highest lifetime spend first.
+pub fn score_customers(customers: Vec<CustomerTotals>) -> Vec<RfmScore> {
+    let customers: Vec<CustomerTotals> = customers.into_iter().filter(|c| c.visit_count > 0 && c.last_visit.is_some()).collect();
+    let recency = quintiles(&customers.iter().map(|c| c.last_visit.map_or(0, |day| i64::from(day.num_days_from_ce()))).collect::<Vec<_>>());
+    let frequency = quintiles(&customers.iter().map(|c| c.visit_count).collect::<Vec<_>>());
+    let monetary = quintiles(&customers.iter().map(|c| c.lifetime_spend().as_cents()).collect::<Vec<_>>());
+    let mut scored: Vec<RfmScore> = customers
+        .into_iter()
+        .enumerate()
+        .map(|(i, totals)| RfmScore {
+            totals,
+            recency: recency[i],
+            frequency: frequency[i],
+            monetary: monetary[i],
+            segment: Segment::from_scores(recency[i], frequency[i], monetary[i]),
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        b.totals
+            .lifetime_spend()
+            .as_cents()
+            .cmp(&a.totals.lifetime_spend().as_cents())
+            .then(a.totals.customer_id.cmp(&b.totals.customer_id))
+    });
+    scored
+}
+
+/// Scores 1 to 5 by percent rank, the share of the other values strictly below each value, so
+/// equal values share a score and a lone value scores 1.
+fn quintiles(values: &[i64]) -> Vec<u8> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let others = values.len().saturating_sub(1).max(1) as f64;
+    values
+        .iter()
+        .map(|value| {
+            let below = sorted.partition_point(|other| other < value) as f64;
+            ((below / others * 5.0).floor() as u8 + 1).min(5)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, d).unwrap()
+    }
+
+    fn customer(n: u128, visits: i64, spend_cents: i64, last_visit: Option<NaiveDate>) -> CustomerTotals {
+        CustomerTotals {
+            customer_id: Uuid::from_u128(n),
+            visit_count: visits,
+            total_sales: Money::from_cents(spend_cents),
+            refund_amount: Money::default(),
+            refund_count: 0,
+            first_visit: last_visit,
+            last_visit,
+        }
+    }
+
+    #[test]
+    fn quintiles_rank_against_the_other_values() {
+        assert_eq!(quintiles(&[10, 20, 30, 40, 50]), vec![1, 2, 3, 4, 5]);
+        assert_eq!(quintiles(&[7, 7, 7]), vec![1, 1, 1]);
+        assert_eq!(quintiles(&[1, 1, 1, 9]), vec![1, 1, 1, 5]);
+        assert_eq!(quintiles(&[42]), vec![1]);
+        assert!(quintiles(&[]).is_empty());
+    }
+
+    #[test]
+    fn segments_follow_the_first_matching_rule() {
+        assert_eq!(Segment::from_scores(5, 5, 5), Segment::Champions);
+        assert_eq!(Segment::from_scores(5, 5, 1), Segment::Loyal);
+        assert_eq!(Segment::from_scores(3, 4, 5), Segment::Loyal);
+        assert_eq!(Segment::from_scores(5, 1, 1), Segment::New);
+        assert_eq!(Segment::from_scores(1, 5, 5), Segment::AtRisk);
+        assert_eq!(Segment::from_scores(2, 2, 5), Segment::Hibernating);
+        assert_eq!(Segment::from_scores(3, 2, 3), Segment::NeedsAttention);
+    }
+
+    #[test]
+    fn totals_net_refunds_and_average_gross_sales_per_visit() {
+        let mut totals = customer(1, 3, 1000, Some(day(10)));
+        totals.refund_amount = Money::from_cents(250);
+        assert_eq!(totals.lifetime_spend(), Money::from_cents(750));
+        assert_eq!(totals.average_basket(), Money::from_cents(333));
+        assert_eq!(totals.days_since_last_visit(day(17)), Some(7));
+        assert_eq!(customer(2, 0, 0, None).average_basket(), Money::default());
+    }
+
+    #[test]
+    fn scoring_skips_customers_without_visits_and_orders_by_spend() {
+        let customers = vec![
+            customer(1, 1, 1500, Some(day(17))),
+            customer(2, 6, 9000, Some(day(16))),
+            customer(3, 5, 4000, Some(day(1))),
+            customer(4, 0, 0, None),
+        ];
+        let scored = score_customers(customers);
+        let summary: Vec<_> = scored.iter().map(|s| (s.totals.customer_id.as_u128(), s.recency, s.frequency, s.monetary, s.segment)).collect();
+        assert_eq!(
+            summary,
+            vec![(2, 3, 5, 5, Segment::Loyal), (3, 1, 3, 3, Segment::AtRisk), (1, 5, 1, 1, Segment::New)],
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Sales by weekday and hour of day, for staffing. `hourly_store_sales` holds each store's sales
+//! per UTC hour. Reads convert every bucket to the store's local time at the instant it covers,
+//! using the `timezone` of the store's `store_business_days` row (UTC when there is none), so
+//! daylight saving needs no special casing:
+//! - The repeated autumn hour adds both of its hours to the same local hour.
+//! - The skipped spring hour never has sales.
+//! - Zones offset by a fraction of an hour (e.g. Asia/Kolkata) book each UTC hour on the local
+//!   hour it starts in.
+//!
+//! Days are local calendar days, not business days, so sales after midnight show at the hour they
+//! were rung up.
+
+use chrono::{Datelike, NaiveDate};
+use common_money::Money;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Sales of one weekday (ISO, 1 = Monday) and local hour (0-23) over a range.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct HeatmapCell {
+    pub weekday: i32,
+    pub hour: i32,
+    pub transactions: i64,
+    pub revenue: Money,
+}
+
+/// Weekday × hour matrices, Monday first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    /// How often each weekday occurs in the range, to turn totals into per-day averages.
+    pub days: [i64; 7],
+    pub transactions: [[i64; 24]; 7],
+    pub revenue: Vec<Vec<Money>>,
+}
+
+impl Heatmap {
+    pub fn new(from: NaiveDate, to: NaiveDate, cells: &[HeatmapCell]) -> Self {
+        let mut heatmap = Heatmap { days: weekday_counts(from, to), transactions: [[0; 24]; 7], revenue: vec![vec![Money::default(); 24]; 7] };
+        for cell in cells {
+            let (Ok(day @ 0..=6), Ok(hour @ 0..=23)) = (usize::try_from(cell.weekday - 1), usize::try_from(cell.hour)) else {
+                continue;
+            };
+            heatmap.transactions[day][hour] += cell.transactions;
+            heatmap.revenue[day][hour] += &cell.revenue;
+        }
+        heatmap
+    }
+}
+
+/// Occurrences of each weekday from `from` to `to`, inclusive, Monday first.
+pub fn weekday_counts(from: NaiveDate, to: NaiveDate) -> [i64; 7] {
+    let mut counts = [0; 7];
+    for day in from.iter_days().take_while(|day| *day <= to) {
+        counts[day.weekday().num_days_from_monday() as usize] += 1;
+    }
+    counts
+}
+
+/// Sales per local weekday and hour between the local days `from` and `to`, inclusive, for one
+/// store or, with `location_id` of `None`, all of the tenant's stores in their own time zones.
+pub async fn heatmap_cells(db: &PgPool, tenant_id: Uuid, location_id: Option<Uuid>, from: NaiveDate, to: NaiveDate) -> sqlx::Result<Vec<HeatmapCell>> {
+    // UTC bounds are widened by a day each way, beyond any zone's offset; the local dates decide.
+    sqlx::query_as::<_, HeatmapCell>(
+        "SELECT EXTRACT(ISODOW FROM b.local_time)::INT AS weekday, EXTRACT(HOUR FROM b.local_time)::INT AS hour, \
+                SUM(b.order_count)::BIGINT AS transactions, SUM(b.total_sales)::NUMERIC AS revenue \
+         FROM ( \
+             SELECT h.order_count, h.total_sales, h.hour_start AT TIME ZONE COALESCE(s.timezone, 'UTC') AS local_time \
+             FROM hourly_store_sales h \
+             LEFT JOIN store_business_days s ON s.tenant_id = h.tenant_id AND s.store_id = h.location_id \
+             WHERE h.tenant_id = $1 AND ($4::uuid IS NULL OR h.location_id = $4) \
+               AND h.hour_start >= ($2::date - 1)::timestamp AT TIME ZONE 'UTC' \
+               AND h.hour_start < ($3::date + 2)::timestamp AT TIME ZONE 'UTC' \
+         ) b \
+         WHERE b.local_time >= $2::date AND b.local_time < ($3::date + 1) \
+         GROUP BY 1, 2 ORDER BY 1, 2",
+    )
+    .bind(tenant_id)
+    .bind(from)
+    .bind(to)
+    .bind(location_id)
+    .fetch_all(db)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    #[test]
+    fn weekdays_are_counted_monday_first() {
+        // 2026-10-12 is a Monday; two full weeks plus Monday and Tuesday.
+        assert_eq!(weekday_counts(day(10, 12), day(10, 27)), [3, 3, 2, 2, 2, 2, 2]);
+        assert_eq!(weekday_counts(day(10, 18), day(10, 18)), [0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn cells_land_in_their_weekday_and_hour() {
+        let cell = |weekday, hour, transactions, cents| HeatmapCell { weekday, hour, transactions, revenue: Money::from_cents(cents) };
+        let heatmap = Heatmap::new(day(10, 12), day(10, 18), &[cell(1, 9, 4, 2000), cell(7, 23, 1, 350), cell(8, 0, 5, 100)]);
+        assert_eq!(heatmap.days, [1; 7]);
+        assert_eq!((heatmap.transactions[0][9], heatmap.transactions[6][23]), (4, 1));
+        assert_eq!(heatmap.revenue[0][9], Money::from_cents(2000));
+        assert_eq!(heatmap.transactions.iter().flatten().sum::<i64>(), 5, "out-of-range cells are ignored");
+    }
+}
@@ -0,0 +1,7 @@
+pub mod alerts;
+pub mod customers;
+pub mod heatmap;
+pub mod projection;
+pub mod reconciliation;
+pub mod replay;
+pub mod reports;
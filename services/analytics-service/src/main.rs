@@ -1,28 +1,50 @@
+mod alert_handlers;
+mod alert_routing;
 mod analytics_handlers;
+mod config;
+mod customer_handlers;
+mod report_handlers;
+mod reconciliation_scheduler;
+mod report_scheduler;
 
-use analytics_handlers::{get_anomalies, get_forecast, get_summary};
-use anyhow::Context;
+use alert_handlers::{
+    acknowledge_alert, create_silence, delete_silence, get_alert_routes, list_alerts, list_silences, put_alert_routes,
+};
+use alert_routing::AlertRouter;
+use analytics_handlers::{
+    compare_stores, get_anomalies, get_component_consumption, get_consolidated_sales, get_employee_performance, get_forecast, get_heatmap, get_summary, get_tips,
+};
+use analytics_service::alerts::Alert;
+use customer_handlers::{get_customer_metrics, get_rfm_segments};
+use analytics_service::projection::{
+    apply_customer_erased, apply_daily_component_consumption, apply_day_closed, apply_daily_customer_sales, apply_daily_disputes, apply_daily_employee_sales, apply_daily_employee_voids,
+    apply_daily_product_sales, apply_daily_sales, apply_daily_store_sales, apply_daily_tips, apply_hourly_store_sales, DisputeDelta, SalesDelta, TipDelta, VoidDelta,
+};
+use config::AnalyticsConfig;
+use report_handlers::{create_subscription, delete_subscription, list_deliveries, list_subscriptions, update_subscription};
 use axum::{
     extract::FromRef,
     http::{
         header::{ACCEPT, CONTENT_TYPE},
         HeaderName, HeaderValue, Method,
     },
-    routing::get,
+    routing::{delete, get, post, put},
     Router,
 };
 use common_auth::{JwtConfig, JwtVerifier};
-use common_money::log_rounding_mode_once;
+use common_config::{config_route, JwtSettings, KafkaSecurity};
+use common_observability::{SloConfig, SloTracker};
+use common_events::{
+    topics, ComponentsConsumedEvent, CustomerErasedEvent, DayClosedEvent, InventoryLowStockEvent, OrderCompletedEvent, OrderTipRecordedEvent, OrderVoidedEvent, PaymentDisputeUpdatedEvent,
+};
+use common_db::ReadPool;
+use common_money::{log_rounding_mode_once, Money};
 use futures_util::StreamExt;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::producer::FutureProducer;
 use rdkafka::Message;
-use serde_json::Value;
-use sqlx::PgPool;
 use std::{
     collections::HashMap,
-    env,
-    net::SocketAddr,
     sync::{Arc, Mutex},
     time::Duration,
 };
@@ -34,17 +56,19 @@ use uuid::Uuid;
 use once_cell::sync::Lazy;
 use prometheus::{Registry, IntCounterVec, Opts, Encoder, TextEncoder};
 
-#[derive(Default, Clone, Copy, serde::Serialize)]
+#[derive(Default, Clone, serde::Serialize)]
 pub struct Stats {
-    total_sales: f64,
+    total_sales: Money,
     order_count: u64,
-    refund_amount: f64,
+    refund_amount: Money,
     refund_count: u64,
 }
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
+    /// Dashboard reads go to the replica when `DATABASE_REPLICA_URL` is set; the Kafka consumer
+    /// keeps writing through its own primary pool.
+    pub db: ReadPool,
     pub data: Arc<Mutex<HashMap<Uuid, Stats>>>,
     pub product_counts: Arc<Mutex<HashMap<Uuid, HashMap<Uuid, i32>>>>,
     pub jwt_verifier: Arc<JwtVerifier>,
@@ -56,21 +80,6 @@ impl FromRef<AppState> for Arc<JwtVerifier> {
     }
 }
 
-#[derive(serde::Serialize)]
-struct AnalyticsAlertEvent {
-    tenant_id: Uuid,
-    alert_type: String,
-    details: String,
-}
-
-#[derive(serde::Deserialize)]
-struct LowStockEvent {
-    tenant_id: Uuid,
-    product_id: Uuid,
-    quantity: i32,
-    threshold: i32,
-}
-
 async fn health() -> &'static str {
     "ok"
 }
@@ -95,53 +104,65 @@ static INBOX_DUPLICATES_SKIPPED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("analytics-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL")?;
-    let db = PgPool::connect(&database_url).await?;
+    let config = AnalyticsConfig::from_env().await?;
+    let db = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::register_metrics(&ANALYTICS_REGISTRY);
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db.clone());
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
 
-    let consumer: StreamConsumer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
+    let kafka_security = KafkaSecurity::from_env().await?;
+    let consumer: StreamConsumer = kafka_security
+        .apply(&mut rdkafka::ClientConfig::new())
+        .set("bootstrap.servers", &config.kafka_bootstrap)
         .set("group.id", "analytics-service")
         .set("enable.auto.commit", "true")
         .create()
         .expect("failed to create kafka consumer");
-    consumer.subscribe(&["order.completed", "inventory.low_stock"])?;
+    consumer.subscribe(&[
+        topics::ORDER_COMPLETED,
+        topics::ORDER_VOIDED,
+        topics::INVENTORY_LOW_STOCK,
+        topics::PAYMENT_DISPUTE_UPDATED,
+        topics::ORDER_TIP_RECORDED,
+        topics::INVENTORY_COMPONENTS_CONSUMED,
+        topics::DAY_CLOSED,
+        topics::CUSTOMER_ERASED,
+    ])?;
 
-    let producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
-        .create()
+    let producer: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&config.kafka_bootstrap, &kafka_security)
         .expect("failed to create kafka producer");
+    common_kafka::register_metrics(&ANALYTICS_REGISTRY);
 
     let data_map = Arc::new(Mutex::new(HashMap::<Uuid, Stats>::new()));
     let product_counts_map = Arc::new(Mutex::new(HashMap::<Uuid, HashMap<Uuid, i32>>::new()));
     let db_pool = db.clone();
     let data_ref = Arc::clone(&data_map);
     let product_counts_ref = Arc::clone(&product_counts_map);
-    let alert_producer = producer.clone();
+    let alert_router = AlertRouter::new(db.clone(), producer.clone(), config.alert_dedup_seconds);
+    report_scheduler::spawn_report_scheduler(db.clone(), producer.clone(), config.report_tick_seconds);
+    reconciliation_scheduler::spawn_reconciliation(
+        db.clone(),
+        config.reconcile_hour_utc,
+        config.reconcile_days,
+        common_money::Cents(config.reconcile_tolerance_cents),
+    );
+    let inbox_enabled = config.inbox_dedup;
     tokio::spawn(async move {
         let mut stream = consumer.stream();
         while let Some(message) = stream.next().await {
             if let Ok(m) = message {
                 if let Some(Ok(text)) = m.payload_view::<str>() {
                     let topic = m.topic();
-                    // Inbox de-dup
-                    let inbox_enabled = std::env::var("ANALYTICS_INBOX_DEDUP").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(true);
+                    // Inbox de-dup (ANALYTICS_INBOX_DEDUP; default enabled)
                     if inbox_enabled {
-                        let key_str = m
-                            .key()
-                            .map(|k| String::from_utf8_lossy(k).to_string())
-                            .unwrap_or_else(|| format!("sha1:{}", hex::encode(sha1_smol::Sha1::from(text).digest().bytes())));
+                        let key_str = common_events::inbox_key(m.key(), text);
                         let tenant_hint = serde_json::from_str::<serde_json::Value>(text)
                             .ok()
                             .and_then(|v| v.get("tenant_id").and_then(|t| t.as_str()).map(|s| s.to_string()))
@@ -168,136 +189,107 @@ async fn main() -> anyhow::Result<()> {
                         .await;
                         INBOX_INSERTS_TOTAL.with_label_values(&["analytics-service", topic]).inc();
                     }
-                    if topic == "order.completed" {
-                        if let Ok(val) = serde_json::from_str::<Value>(text) {
-                            if let (Some(tid_str), Some(total_val)) =
-                                (val.get("tenant_id"), val.get("total"))
+                    if topic == topics::ORDER_COMPLETED {
+                        if let Ok(evt) = common_events::decode::<OrderCompletedEvent>(text) {
+                            let tenant_id = evt.tenant_id;
                             {
-                                if let (Some(tid), Some(total)) =
-                                    (tid_str.as_str(), total_val.as_f64())
-                                {
-                                    if let Ok(tenant_id) = Uuid::parse_str(tid) {
-                                        if let Some(items_val) = val.get("items") {
-                                            if let Some(arr) = items_val.as_array() {
-                                                let mut counts = product_counts_ref.lock().unwrap();
-                                                let tenant_counts = counts
-                                                    .entry(tenant_id)
-                                                    .or_default();
-                                                for item in arr {
-                                                    if let Some(pid_str) = item
-                                                        .get("product_id")
-                                                        .and_then(|v| v.as_str())
-                                                    {
-                                                        if let Some(qty) = item
-                                                            .get("quantity")
-                                                            .and_then(|v| v.as_i64())
-                                                        {
-                                                            if let Ok(pid) =
-                                                                Uuid::parse_str(pid_str)
-                                                            {
-                                                                *tenant_counts
-                                                                    .entry(pid)
-                                                                    .or_insert(0) += qty as i32;
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        let (sales_inc, orders_inc, refunds_inc, refund_count_inc) = {
-                                            let mut map = data_ref.lock().unwrap();
-                                            let entry = map.entry(tenant_id).or_default();
-                                            if total < 0.0 {
-                                                entry.refund_count += 1;
-                                                entry.refund_amount += -total;
-                                                (0.0, 0, -total, 1)
-                                            } else {
-                                                entry.order_count += 1;
-                                                entry.total_sales += total;
-                                                (total, 1, 0.0, 0)
-                                            }
-                                        };
-                                        let query = r#"INSERT INTO daily_sales
-                                                (tenant_id, date, total_sales, order_count, refund_amount, refund_count)
-                                            VALUES (, CURRENT_DATE, , , , )
-                                            ON CONFLICT (tenant_id, date)
-                                            DO UPDATE
-                                               SET total_sales = daily_sales.total_sales + ,
-                                                   order_count = daily_sales.order_count + ,
-                                                   refund_amount = daily_sales.refund_amount + ,
-                                                   refund_count = daily_sales.refund_count + "#;
-                                        let _ = sqlx::query(query)
-                                            .bind(tenant_id)
-                                            .bind(sales_inc)
-                                            .bind(orders_inc)
-                                            .bind(refunds_inc)
-                                            .bind(refund_count_inc)
-                                            .execute(&db_pool)
-                                            .await;
+                                let mut counts = product_counts_ref.lock().unwrap();
+                                let tenant_counts = counts.entry(tenant_id).or_default();
+                                for item in &evt.items {
+                                    *tenant_counts.entry(item.product_id).or_insert(0) += item.quantity;
+                                }
+                            }
+                            let delta = SalesDelta::from_event(&evt);
+                            {
+                                let mut map = data_ref.lock().unwrap();
+                                let entry = map.entry(tenant_id).or_default();
+                                entry.total_sales += &delta.sales;
+                                entry.order_count += delta.orders as u64;
+                                entry.refund_amount += &delta.refunds;
+                                entry.refund_count += delta.refund_count as u64;
+                            }
+                            let date = evt.business_date;
+                            if let Err(err) = apply_daily_sales(&db_pool, &delta, date).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, "Failed to update daily_sales");
+                            }
+                            if let Err(err) = apply_daily_store_sales(&db_pool, &delta, date).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, location_id = %delta.location_id, "Failed to update daily_store_sales");
+                            }
+                            if let Err(err) = apply_hourly_store_sales(&db_pool, &delta, None).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, location_id = %delta.location_id, "Failed to update hourly_store_sales");
+                            }
+                            if let Err(err) = apply_daily_employee_sales(&db_pool, &delta, None, date).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, employee_id = %delta.employee_id, "Failed to update daily_employee_sales");
+                            }
+                            if let Err(err) = apply_daily_product_sales(&db_pool, &evt, date).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, "Failed to update daily_product_sales");
+                            }
+                            if let Err(err) = apply_daily_customer_sales(&db_pool, &evt, date).await {
+                                tracing::error!(?err, tenant_id = %tenant_id, "Failed to update daily_customer_sales");
+                            }
+                            let refunds_inc = delta.refunds;
 
-                                        if refunds_inc > 0.0 {
-                                            if let Ok(avg_refund_opt) =
-                                                sqlx::query_scalar::<_, Option<f64>>(
-                                                    "SELECT AVG(refund_amount) FROM daily_sales                                                  WHERE tenant_id =  AND date < CURRENT_DATE",
-                                                )
-                                                .bind(tenant_id)
-                                                .fetch_one(&db_pool)
-                                                .await
-                                            {
-                                                let avg_refund = avg_refund_opt.unwrap_or(0.0);
-                                                if avg_refund > 0.0
-                                                    && refunds_inc > 2.0 * avg_refund
-                                                {
-                                                    let alert = AnalyticsAlertEvent {
-                                                        tenant_id,
-                                                        alert_type: "HIGH_REFUND_VOLUME".into(),
-                                                        details: format!(
-                                                            "${:.2} refunded today vs ${:.2} avg",
-                                                            refunds_inc, avg_refund
-                                                        ),
-                                                    };
-                                                    let payload =
-                                                        serde_json::to_string(&alert).unwrap();
-                                                    if let Err(err) = alert_producer
-                                                        .send(
-                                                            FutureRecord::to("analytics.alert")
-                                                                .payload(&payload)
-                                                                .key(&tenant_id.to_string()),
-                                                            Duration::from_secs(0),
-                                                        )
-                                                        .await
-                                                    {
-                                                        tracing::error!(
-                                                            "Failed to publish analytics.alert: {:?}",
-                                                            err
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
+                            if refunds_inc.as_cents() > 0 {
+                                if let Ok(avg_refund_opt) = sqlx::query_scalar::<_, Option<Money>>(
+                                    "SELECT AVG(refund_amount) FROM daily_sales WHERE tenant_id = $1 AND date < CURRENT_DATE",
+                                )
+                                .bind(tenant_id)
+                                .fetch_one(&db_pool)
+                                .await
+                                {
+                                    let avg_refund = avg_refund_opt.unwrap_or_default();
+                                    if avg_refund.as_cents() > 0 && refunds_inc.as_cents() > 2 * avg_refund.as_cents() {
+                                        alert_router.raise(Alert::high_refund_volume(tenant_id, &refunds_inc, &avg_refund)).await;
                                     }
                                 }
                             }
                         }
-                    } else if topic == "inventory.low_stock" {
-                        if let Ok(evt) = serde_json::from_str::<LowStockEvent>(text) {
-                            let alert = AnalyticsAlertEvent {
-                                tenant_id: evt.tenant_id,
-                                alert_type: "LOW_STOCK".into(),
-                                details: format!(
-                                    "Product {} down to {} (threshold {})",
-                                    evt.product_id, evt.quantity, evt.threshold
-                                ),
-                            };
-                            let payload = serde_json::to_string(&alert).unwrap();
-                            let _ = alert_producer
-                                .send(
-                                    FutureRecord::to("analytics.alert")
-                                        .payload(&payload)
-                                        .key(&evt.tenant_id.to_string()),
-                                    Duration::from_secs(0),
-                                )
-                                .await;
+                    } else if topic == topics::ORDER_VOIDED {
+                        if let Ok(evt) = common_events::decode::<OrderVoidedEvent>(text) {
+                            if let Some(delta) = VoidDelta::from_event(&evt) {
+                                if let Err(err) = apply_daily_employee_voids(&db_pool, &delta, evt.business_date).await {
+                                    tracing::error!(?err, tenant_id = %evt.tenant_id, "Failed to update daily_employee_voids");
+                                }
+                            }
+                        }
+                    } else if topic == topics::PAYMENT_DISPUTE_UPDATED {
+                        if let Ok(evt) = common_events::decode::<PaymentDisputeUpdatedEvent>(text) {
+                            if let Some(delta) = DisputeDelta::from_event(&evt) {
+                                if let Err(err) = apply_daily_disputes(&db_pool, &delta, None).await {
+                                    tracing::error!(?err, tenant_id = %evt.tenant_id, "Failed to update daily_disputes");
+                                }
+                            }
+                        }
+                    } else if topic == topics::ORDER_TIP_RECORDED {
+                        if let Ok(evt) = common_events::decode::<OrderTipRecordedEvent>(text) {
+                            if let Some(delta) = TipDelta::from_event(&evt) {
+                                if let Err(err) = apply_daily_tips(&db_pool, &delta).await {
+                                    tracing::error!(?err, tenant_id = %evt.tenant_id, "Failed to update daily_tips");
+                                }
+                            }
+                        }
+                    } else if topic == topics::INVENTORY_COMPONENTS_CONSUMED {
+                        if let Ok(evt) = common_events::decode::<ComponentsConsumedEvent>(text) {
+                            if let Err(err) = apply_daily_component_consumption(&db_pool, &evt, None).await {
+                                tracing::error!(?err, tenant_id = %evt.tenant_id, "Failed to update daily_component_consumption");
+                            }
+                        }
+                    } else if topic == topics::DAY_CLOSED {
+                        if let Ok(evt) = common_events::decode::<DayClosedEvent>(text) {
+                            if let Err(err) = apply_day_closed(&db_pool, &evt).await {
+                                tracing::error!(?err, tenant_id = %evt.tenant_id, store_id = %evt.store_id, date = %evt.business_date, "Failed to finalize closed day");
+                            }
+                        }
+                    } else if topic == topics::CUSTOMER_ERASED {
+                        if let Ok(evt) = common_events::decode::<CustomerErasedEvent>(text) {
+                            match apply_customer_erased(&db_pool, &evt).await {
+                                Ok(rows) => tracing::info!(tenant_id = %evt.tenant_id, customer_id = %evt.customer_id, rows, "Dropped aggregates of erased customer"),
+                                Err(err) => tracing::error!(?err, tenant_id = %evt.tenant_id, customer_id = %evt.customer_id, "Failed to drop aggregates of erased customer"),
+                            }
+                        }
+                    } else if topic == topics::INVENTORY_LOW_STOCK {
+                        if let Ok(evt) = common_events::decode::<InventoryLowStockEvent>(text) {
+                            alert_router.raise(Alert::low_stock(evt.tenant_id, evt.product_id, evt.quantity, evt.threshold)).await;
                         }
                     }
                 }
@@ -318,7 +310,7 @@ async fn main() -> anyhow::Result<()> {
                 .filter_map(|origin| origin.parse::<HeaderValue>().ok())
                 .collect::<Vec<_>>(),
         ))
-        .allow_methods([Method::GET])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_headers([
             ACCEPT,
             CONTENT_TYPE,
@@ -327,7 +319,7 @@ async fn main() -> anyhow::Result<()> {
         ]);
 
     let app_state = AppState {
-        db,
+        db: ReadPool::from_env(db)?,
         data: data_map,
         product_counts: product_counts_map,
         jwt_verifier,
@@ -343,49 +335,64 @@ async fn main() -> anyhow::Result<()> {
         (axum::http::StatusCode::OK, String::from_utf8_lossy(&buf).to_string())
     }
 
+    let slo = SloTracker::new("analytics-service", SloConfig::from_env(), &ANALYTICS_REGISTRY);
     let app = Router::new()
         .route("/healthz", get(health))
         .route("/metrics", get(metrics))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .route("/summary", get(get_summary))
         .route("/forecast", get(get_forecast))
         .route("/anomalies", get(get_anomalies))
+        .route("/tips", get(get_tips))
+        .route("/components", get(get_component_consumption))
+        .route("/stores/compare", get(compare_stores))
+        .route("/stores/consolidated", get(get_consolidated_sales))
+        .route("/analytics/employees", get(get_employee_performance))
+        .route("/analytics/heatmap", get(get_heatmap))
+        .route("/analytics/customers/rfm", get(get_rfm_segments))
+        .route("/analytics/customers/:id", get(get_customer_metrics))
+        .route("/reports/subscriptions", get(list_subscriptions).post(create_subscription))
+        .route("/reports/subscriptions/:id", put(update_subscription).delete(delete_subscription))
+        .route("/reports/deliveries", get(list_deliveries))
+        .route("/alerts", get(list_alerts))
+        .route("/alerts/:id/acknowledge", post(acknowledge_alert))
+        .route("/alerts/routes", get(get_alert_routes).put(put_alert_routes))
+        .route("/alerts/silences", get(list_silences).post(create_silence))
+        .route("/alerts/silences/:id", delete(delete_silence))
         .with_state(app_state)
-        .layer(cors);
+        .layer(cors)
+        .layer(axum::middleware::from_fn(slo.layer()))
+        .layer(axum::middleware::from_fn(common_observability::request_span));
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8086);
-    let addr = SocketAddr::from((host.parse::<std::net::IpAddr>()?, port));
+    let addr = config.http.addr();
     println!("starting analytics-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(common_observability::shutdown_signal())
+        .await?;
+    common_kafka::flush(&producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -394,16 +401,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
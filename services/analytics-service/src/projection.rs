@@ -0,0 +1,882 @@
+//! Read-model writes driven by consumed events. Shared by the live consumer and the
+//! `replay_events` binary so a rebuild applies exactly what live consumption would have,
+//! minus the side effects (alerts), which stay with the live consumer.
+
+use bigdecimal::Signed;
+use chrono::{DateTime, NaiveDate, Utc};
+use common_audit::AuditEvent;
+use common_events::{ComponentsConsumedEvent, CustomerErasedEvent, DayClosedEvent, DisputeStatus, OrderCompletedEvent, OrderTipRecordedEvent, OrderVoidedEvent, PaymentDisputeUpdatedEvent};
+use common_money::measure::to_milli_units;
+use common_money::Money;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Contribution of a single `order.completed` event to `daily_sales`, `daily_store_sales` and
+/// `daily_employee_sales`. Amounts are normalized with common-money, the same way order-service
+/// rounds the totals it publishes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SalesDelta {
+    pub tenant_id: Uuid,
+    /// Nil when the order has no recorded store.
+    pub location_id: Uuid,
+    /// Nil when the order has no recorded cashier.
+    pub employee_id: Uuid,
+    pub sales: Money,
+    pub orders: i32,
+    /// Units sold; returned units are not subtracted.
+    pub items: i32,
+    pub refunds: Money,
+    pub refund_count: i32,
+}
+
+impl SalesDelta {
+    pub fn from_event(evt: &OrderCompletedEvent) -> Self {
+        let total = Money::new(evt.total.clone());
+        let location_id = evt.location_id.unwrap_or_default();
+        let employee_id = evt.employee_id.unwrap_or_default();
+        if evt.is_refund() {
+            let refunds = Money::new(total.inner().abs());
+            Self { tenant_id: evt.tenant_id, location_id, employee_id, sales: Money::default(), orders: 0, items: 0, refunds, refund_count: 1 }
+        } else {
+            let items = evt.items.iter().map(|item| item.quantity).sum();
+            Self { tenant_id: evt.tenant_id, location_id, employee_id, sales: total, orders: 1, items, refunds: Money::default(), refund_count: 0 }
+        }
+    }
+}
+
+/// Add a delta to the tenant's `daily_sales` row. Callers pass the event's `business_date` when it
+/// has one. Older events fall back to the database's current date when consumed live, and to the
+/// date the event was published on replays.
+pub async fn apply_daily_sales(db: &PgPool, delta: &SalesDelta, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_sales
+                (tenant_id, date, total_sales, order_count, refund_amount, refund_count)
+            VALUES ($1, COALESCE($6, CURRENT_DATE), $2, $3, $4, $5)
+            ON CONFLICT (tenant_id, date)
+            DO UPDATE
+               SET total_sales = daily_sales.total_sales + $2,
+                   order_count = daily_sales.order_count + $3,
+                   refund_amount = daily_sales.refund_amount + $4,
+                   refund_count = daily_sales.refund_count + $5"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(&delta.sales)
+    .bind(delta.orders)
+    .bind(&delta.refunds)
+    .bind(delta.refund_count)
+    .bind(date)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Drop `daily_sales` rows from `since` onwards (all of them when `None`), optionally for one
+/// tenant, ahead of a rebuild so replayed events aren't counted twice. Returns the rows removed.
+pub async fn reset_daily_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_sales WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add a delta to the store's `daily_store_sales` row; `date` behaves as in [`apply_daily_sales`].
+pub async fn apply_daily_store_sales(db: &PgPool, delta: &SalesDelta, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_store_sales
+                (tenant_id, date, location_id, total_sales, order_count, item_count, refund_amount, refund_count)
+            VALUES ($1, COALESCE($8, CURRENT_DATE), $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (tenant_id, date, location_id)
+            DO UPDATE
+               SET total_sales = daily_store_sales.total_sales + $3,
+                   order_count = daily_store_sales.order_count + $4,
+                   item_count = daily_store_sales.item_count + $5,
+                   refund_amount = daily_store_sales.refund_amount + $6,
+                   refund_count = daily_store_sales.refund_count + $7"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.location_id)
+    .bind(&delta.sales)
+    .bind(delta.orders)
+    .bind(delta.items)
+    .bind(&delta.refunds)
+    .bind(delta.refund_count)
+    .bind(date)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_store_sales`.
+pub async fn reset_daily_store_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_store_sales WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add a sale to its store's `hourly_store_sales` bucket for the UTC hour of `at` (now for live
+/// consumption, the publish time for replays). Refunds are not traffic and are left out.
+pub async fn apply_hourly_store_sales(db: &PgPool, delta: &SalesDelta, at: Option<DateTime<Utc>>) -> sqlx::Result<()> {
+    if delta.orders == 0 {
+        return Ok(());
+    }
+    sqlx::query(
+        r#"INSERT INTO hourly_store_sales
+                (tenant_id, location_id, hour_start, order_count, total_sales, item_count)
+            VALUES ($1, $2, date_trunc('hour', COALESCE($6, now()) AT TIME ZONE 'UTC') AT TIME ZONE 'UTC', $3, $4, $5)
+            ON CONFLICT (tenant_id, location_id, hour_start)
+            DO UPDATE
+               SET order_count = hourly_store_sales.order_count + $3,
+                   total_sales = hourly_store_sales.total_sales + $4,
+                   item_count = hourly_store_sales.item_count + $5"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.location_id)
+    .bind(delta.orders)
+    .bind(&delta.sales)
+    .bind(delta.items)
+    .bind(at)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Drop `hourly_store_sales` buckets from UTC midnight of `since` onwards (all of them when `None`),
+/// optionally for one tenant. Buckets follow the publish time rather than the business date, which
+/// is where a `--reset` replay starts reading. Returns the rows removed.
+pub async fn reset_hourly_store_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM hourly_store_sales
+         WHERE ($1::date IS NULL OR hour_start >= $1::timestamp AT TIME ZONE 'UTC') AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+    .bind(since)
+    .bind(tenant_id)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add a delta to the employee's `daily_employee_sales` row. `at` is when the sale happened (now for
+/// live consumption, the publish time for replays); sales widen the row's first/last sale window.
+/// `date` behaves as in [`apply_daily_sales`] and defaults to the day of `at`.
+pub async fn apply_daily_employee_sales(db: &PgPool, delta: &SalesDelta, at: Option<DateTime<Utc>>, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_employee_sales
+                (tenant_id, date, employee_id, total_sales, order_count, item_count, refund_amount, refund_count, first_sale_at, last_sale_at)
+            VALUES ($1, COALESCE($9, CURRENT_DATE), $2, $3, $4, $5, $6, $7,
+                    CASE WHEN $4 > 0 THEN COALESCE($8, now()) END, CASE WHEN $4 > 0 THEN COALESCE($8, now()) END)
+            ON CONFLICT (tenant_id, date, employee_id)
+            DO UPDATE
+               SET total_sales = daily_employee_sales.total_sales + $3,
+                   order_count = daily_employee_sales.order_count + $4,
+                   item_count = daily_employee_sales.item_count + $5,
+                   refund_amount = daily_employee_sales.refund_amount + $6,
+                   refund_count = daily_employee_sales.refund_count + $7,
+                   first_sale_at = LEAST(daily_employee_sales.first_sale_at, EXCLUDED.first_sale_at),
+                   last_sale_at = GREATEST(daily_employee_sales.last_sale_at, EXCLUDED.last_sale_at)"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.employee_id)
+    .bind(&delta.sales)
+    .bind(delta.orders)
+    .bind(delta.items)
+    .bind(&delta.refunds)
+    .bind(delta.refund_count)
+    .bind(at)
+    .bind(date.or(at.map(|ts| ts.date_naive())))
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_employee_sales`.
+pub async fn reset_daily_employee_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_employee_sales WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Contribution of an `order.voided` event to `daily_employee_voids`, charged to the employee who
+/// requested the void.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoidDelta {
+    pub tenant_id: Uuid,
+    pub employee_id: Uuid,
+    pub amount: Money,
+}
+
+impl VoidDelta {
+    /// `None` for voids nobody requested, i.e. orders voided because payment failed.
+    pub fn from_event(evt: &OrderVoidedEvent) -> Option<Self> {
+        let employee_id = evt.requested_by?;
+        Some(Self { tenant_id: evt.tenant_id, employee_id, amount: Money::new(evt.total.clone()) })
+    }
+}
+
+/// Add a void to the employee's `daily_employee_voids` row; `date` behaves as in [`apply_daily_sales`].
+pub async fn apply_daily_employee_voids(db: &PgPool, delta: &VoidDelta, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_employee_voids (tenant_id, date, employee_id, void_count, void_amount)
+            VALUES ($1, COALESCE($4, CURRENT_DATE), $2, 1, $3)
+            ON CONFLICT (tenant_id, date, employee_id)
+            DO UPDATE
+               SET void_count = daily_employee_voids.void_count + 1,
+                   void_amount = daily_employee_voids.void_amount + $3"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.employee_id)
+    .bind(&delta.amount)
+    .bind(date)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_employee_voids`.
+pub async fn reset_daily_employee_voids(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_employee_voids WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Contribution of a `payment.dispute.updated` event to `daily_disputes`, counted on the day the
+/// dispute opened or was decided.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisputeDelta {
+    pub tenant_id: Uuid,
+    pub opened_count: i32,
+    pub opened_amount: Money,
+    pub won_count: i32,
+    pub won_amount: Money,
+    pub lost_count: i32,
+    pub lost_amount: Money,
+}
+
+impl DisputeDelta {
+    /// `None` for updates that neither open nor decide a dispute (e.g. evidence submitted).
+    pub fn from_event(evt: &PaymentDisputeUpdatedEvent) -> Option<Self> {
+        let amount = Money::from_cents(evt.amount_minor);
+        let mut delta = Self {
+            tenant_id: evt.tenant_id,
+            opened_count: 0,
+            opened_amount: Money::default(),
+            won_count: 0,
+            won_amount: Money::default(),
+            lost_count: 0,
+            lost_amount: Money::default(),
+        };
+        if evt.is_opening() {
+            (delta.opened_count, delta.opened_amount) = (1, amount.clone());
+        }
+        if evt.enters(DisputeStatus::Won) {
+            (delta.won_count, delta.won_amount) = (1, amount.clone());
+        }
+        if evt.enters(DisputeStatus::Lost) {
+            (delta.lost_count, delta.lost_amount) = (1, amount);
+        }
+        (delta.opened_count + delta.won_count + delta.lost_count > 0).then_some(delta)
+    }
+}
+
+/// Add a delta to the tenant's `daily_disputes` row; `date` behaves as in [`apply_daily_sales`].
+pub async fn apply_daily_disputes(db: &PgPool, delta: &DisputeDelta, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_disputes
+                (tenant_id, date, opened_count, opened_amount, won_count, won_amount, lost_count, lost_amount)
+            VALUES ($1, COALESCE($8, CURRENT_DATE), $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (tenant_id, date)
+            DO UPDATE
+               SET opened_count = daily_disputes.opened_count + $2,
+                   opened_amount = daily_disputes.opened_amount + $3,
+                   won_count = daily_disputes.won_count + $4,
+                   won_amount = daily_disputes.won_amount + $5,
+                   lost_count = daily_disputes.lost_count + $6,
+                   lost_amount = daily_disputes.lost_amount + $7"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.opened_count)
+    .bind(&delta.opened_amount)
+    .bind(delta.won_count)
+    .bind(&delta.won_amount)
+    .bind(delta.lost_count)
+    .bind(&delta.lost_amount)
+    .bind(date)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_disputes`.
+pub async fn reset_daily_disputes(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_disputes WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Contribution of an `order.tip_recorded` event to `daily_tips`. Adjustments carry the difference
+/// to the previous tip and are booked on the sale's business day, in the same shift.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TipDelta {
+    pub tenant_id: Uuid,
+    pub date: NaiveDate,
+    /// Nil when the order has no recorded employee.
+    pub employee_id: Uuid,
+    /// Nil when the order has no recorded register.
+    pub pos_instance_id: Uuid,
+    pub tip_amount: Money,
+    pub tipped_orders: i32,
+    pub adjustments: i32,
+}
+
+impl TipDelta {
+    /// `None` when the tip didn't change.
+    pub fn from_event(evt: &OrderTipRecordedEvent) -> Option<Self> {
+        let tip = Money::new(evt.tip.clone());
+        let previous = Money::new(evt.previous_tip.clone());
+        if tip == previous {
+            return None;
+        }
+        let tipped_orders = match (previous.inner().is_positive(), tip.inner().is_positive()) {
+            (false, true) => 1,
+            (true, false) => -1,
+            _ => 0,
+        };
+        Some(Self {
+            tenant_id: evt.tenant_id,
+            date: evt.business_date,
+            employee_id: evt.employee_id.unwrap_or_default(),
+            pos_instance_id: evt.pos_instance_id.unwrap_or_default(),
+            tip_amount: tip - &previous,
+            tipped_orders,
+            adjustments: i32::from(evt.adjustment),
+        })
+    }
+}
+
+/// Add a delta to its `daily_tips` row. Unlike the other projections the date always comes from the
+/// event, so live consumption and replays agree.
+pub async fn apply_daily_tips(db: &PgPool, delta: &TipDelta) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO daily_tips
+                (tenant_id, date, employee_id, pos_instance_id, tip_amount, tipped_orders, adjustments)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (tenant_id, date, employee_id, pos_instance_id)
+            DO UPDATE
+               SET tip_amount = daily_tips.tip_amount + $5,
+                   tipped_orders = daily_tips.tipped_orders + $6,
+                   adjustments = daily_tips.adjustments + $7"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(delta.date)
+    .bind(delta.employee_id)
+    .bind(delta.pos_instance_id)
+    .bind(&delta.tip_amount)
+    .bind(delta.tipped_orders)
+    .bind(delta.adjustments)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_tips`.
+pub async fn reset_daily_tips(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_tips WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add each component an `inventory.components.consumed` event drew to `daily_component_consumption`;
+/// `date` behaves as in [`apply_daily_sales`]. Refund events carry negative quantities.
+pub async fn apply_daily_component_consumption(db: &PgPool, evt: &ComponentsConsumedEvent, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    let mut tx = db.begin().await?;
+    for component in evt.components.iter() {
+        sqlx::query(
+            r#"INSERT INTO daily_component_consumption (tenant_id, date, bundle_id, component_id, quantity)
+                VALUES ($1, COALESCE($5, CURRENT_DATE), $2, $3, $4)
+                ON CONFLICT (tenant_id, date, bundle_id, component_id)
+                DO UPDATE SET quantity = daily_component_consumption.quantity + $4"#,
+        )
+        .bind(evt.tenant_id)
+        .bind(component.bundle_id)
+        .bind(component.component_id)
+        .bind(i64::from(component.quantity))
+        .bind(date)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Same as [`reset_daily_sales`], for `daily_component_consumption`.
+pub async fn reset_daily_component_consumption(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_component_consumption WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add the lines of an `order.completed` event to `daily_product_sales` at the order's location
+/// (nil without one); `date` behaves as in [`apply_daily_sales`]. Refund lines carry negative
+/// quantities and totals. Weighed lines count one `quantity` and their weight in thousandths of a
+/// kg/lb as `stock_units`, the unit inventory keeps stock in.
+pub async fn apply_daily_product_sales(db: &PgPool, evt: &OrderCompletedEvent, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    let mut tx = db.begin().await?;
+    for item in evt.items.iter() {
+        let stock_units = item.measured_quantity.as_ref().and_then(to_milli_units).unwrap_or(item.quantity);
+        sqlx::query(
+            r#"INSERT INTO daily_product_sales (tenant_id, date, product_id, location_id, quantity, revenue, stock_units)
+                VALUES ($1, COALESCE($5, CURRENT_DATE), $2, $6, $3, $4, $7)
+                ON CONFLICT (tenant_id, date, product_id, location_id)
+                DO UPDATE SET quantity = daily_product_sales.quantity + $3,
+                              revenue = daily_product_sales.revenue + $4,
+                              stock_units = daily_product_sales.stock_units + $7"#,
+        )
+        .bind(evt.tenant_id)
+        .bind(item.product_id)
+        .bind(item.quantity)
+        .bind(Money::new(item.line_total.clone()))
+        .bind(date)
+        .bind(evt.location_id.unwrap_or_default())
+        .bind(i64::from(stock_units))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Same as [`reset_daily_sales`], for `daily_product_sales`.
+pub async fn reset_daily_product_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_product_sales WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+        .bind(since)
+        .bind(tenant_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected())
+}
+
+/// Add an `order.completed` event to its customer's `daily_customer_sales` row; `date` behaves as
+/// in [`apply_daily_sales`]. Sales without a customer and customers in `erased_customers` are
+/// left out.
+pub async fn apply_daily_customer_sales(db: &PgPool, evt: &OrderCompletedEvent, date: Option<NaiveDate>) -> sqlx::Result<()> {
+    let Some(customer_id) = evt.customer_id else {
+        return Ok(());
+    };
+    let delta = SalesDelta::from_event(evt);
+    sqlx::query(
+        r#"INSERT INTO daily_customer_sales
+                (tenant_id, date, customer_id, order_count, total_sales, refund_amount, refund_count)
+            SELECT $1, COALESCE($7, CURRENT_DATE), $2, $3, $4, $5, $6
+            WHERE NOT EXISTS (SELECT 1 FROM erased_customers WHERE tenant_id = $1 AND customer_id = $2)
+            ON CONFLICT (tenant_id, date, customer_id)
+            DO UPDATE
+               SET order_count = daily_customer_sales.order_count + $3,
+                   total_sales = daily_customer_sales.total_sales + $4,
+                   refund_amount = daily_customer_sales.refund_amount + $5,
+                   refund_count = daily_customer_sales.refund_count + $6"#,
+    )
+    .bind(delta.tenant_id)
+    .bind(customer_id)
+    .bind(delta.orders)
+    .bind(&delta.sales)
+    .bind(&delta.refunds)
+    .bind(delta.refund_count)
+    .bind(date)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Same as [`reset_daily_sales`], for `daily_customer_sales`. `erased_customers` is kept, so a
+/// rebuild does not bring erased customers back.
+pub async fn reset_daily_customer_sales(db: &PgPool, since: Option<NaiveDate>, tenant_id: Option<Uuid>) -> sqlx::Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM daily_customer_sales WHERE ($1::date IS NULL OR date >= $1) AND ($2::uuid IS NULL OR tenant_id = $2)",
+    )
+    .bind(since)
+    .bind(tenant_id)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+/// Handle `customer.erased`: remember the customer in `erased_customers` and delete their
+/// `daily_customer_sales` rows. Idempotent. Returns the rows removed.
+pub async fn apply_customer_erased(db: &PgPool, evt: &CustomerErasedEvent) -> sqlx::Result<u64> {
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "INSERT INTO erased_customers (tenant_id, customer_id, erased_at) VALUES ($1, $2, $3)
+         ON CONFLICT (tenant_id, customer_id) DO NOTHING",
+    )
+    .bind(evt.tenant_id)
+    .bind(evt.customer_id)
+    .bind(evt.erased_at)
+    .execute(&mut *tx)
+    .await?;
+    let done = sqlx::query("DELETE FROM daily_customer_sales WHERE tenant_id = $1 AND customer_id = $2")
+        .bind(evt.tenant_id)
+        .bind(evt.customer_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(done.rows_affected())
+}
+
+/// A store's business day as reported by its `day.closed` Z-report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedDay {
+    pub tenant_id: Uuid,
+    pub location_id: Uuid,
+    pub date: NaiveDate,
+    pub sales: Money,
+    pub orders: i32,
+    pub refunds: Money,
+    pub refund_count: i32,
+}
+
+impl ClosedDay {
+    pub fn from_event(evt: &DayClosedEvent) -> Self {
+        Self {
+            tenant_id: evt.tenant_id,
+            location_id: evt.store_id,
+            date: evt.business_date,
+            sales: Money::new(evt.gross_sales.clone()),
+            orders: i32::try_from(evt.order_count).unwrap_or(i32::MAX),
+            refunds: Money::new(evt.refund_amount.clone()),
+            refund_count: i32::try_from(evt.refund_count).unwrap_or(i32::MAX),
+        }
+    }
+
+    /// What `daily_sales` moves by when the store's row goes from `previous` (sales, orders,
+    /// refunds, refund count) to the closed figures.
+    pub fn correction(&self, previous: (Money, i32, Money, i32)) -> SalesDelta {
+        let (sales, orders, refunds, refund_count) = previous;
+        SalesDelta {
+            tenant_id: self.tenant_id,
+            location_id: self.location_id,
+            employee_id: Uuid::nil(),
+            sales: &self.sales - sales,
+            orders: self.orders - orders,
+            items: 0,
+            refunds: &self.refunds - refunds,
+            refund_count: self.refund_count - refund_count,
+        }
+    }
+}
+
+/// Finalize a store's day from `day.closed`: record the Z-report figures, replace the store's
+/// `daily_store_sales` figures with them (units sold are kept) and move `daily_sales` by the
+/// difference. Applying the same close again changes nothing.
+pub async fn apply_day_closed(db: &PgPool, evt: &DayClosedEvent) -> sqlx::Result<()> {
+    let day = ClosedDay::from_event(evt);
+    let mut tx = db.begin().await?;
+    let previous = sqlx::query_as::<_, (Money, i32, Money, i32)>(
+        "SELECT total_sales, order_count, refund_amount, refund_count FROM daily_store_sales
+         WHERE tenant_id = $1 AND date = $2 AND location_id = $3 FOR UPDATE",
+    )
+    .bind(day.tenant_id)
+    .bind(day.date)
+    .bind(day.location_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .unwrap_or_default();
+    let delta = day.correction(previous);
+
+    sqlx::query(
+        r#"INSERT INTO daily_store_sales
+                (tenant_id, date, location_id, total_sales, order_count, refund_amount, refund_count, finalized)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, TRUE)
+            ON CONFLICT (tenant_id, date, location_id)
+            DO UPDATE
+               SET total_sales = $4, order_count = $5, refund_amount = $6, refund_count = $7, finalized = TRUE"#,
+    )
+    .bind(day.tenant_id)
+    .bind(day.date)
+    .bind(day.location_id)
+    .bind(&day.sales)
+    .bind(day.orders)
+    .bind(&day.refunds)
+    .bind(day.refund_count)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"INSERT INTO daily_sales
+                (tenant_id, date, total_sales, order_count, refund_amount, refund_count)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (tenant_id, date)
+            DO UPDATE
+               SET total_sales = daily_sales.total_sales + $3,
+                   order_count = daily_sales.order_count + $4,
+                   refund_amount = daily_sales.refund_amount + $5,
+                   refund_count = daily_sales.refund_count + $6"#,
+    )
+    .bind(day.tenant_id)
+    .bind(day.date)
+    .bind(&delta.sales)
+    .bind(delta.orders)
+    .bind(&delta.refunds)
+    .bind(delta.refund_count)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"INSERT INTO closed_business_days
+                (tenant_id, date, location_id, order_count, total_sales, refund_amount, refund_count, tax, tips,
+                 void_count, void_amount, cash_variance, closed_by, closed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            ON CONFLICT (tenant_id, date, location_id)
+            DO UPDATE
+               SET order_count = $4, total_sales = $5, refund_amount = $6, refund_count = $7, tax = $8, tips = $9,
+                   void_count = $10, void_amount = $11, cash_variance = $12, closed_by = $13, closed_at = $14"#,
+    )
+    .bind(day.tenant_id)
+    .bind(day.date)
+    .bind(day.location_id)
+    .bind(day.orders)
+    .bind(&day.sales)
+    .bind(&day.refunds)
+    .bind(day.refund_count)
+    .bind(Money::new(evt.tax.clone()))
+    .bind(Money::new(evt.tips.clone()))
+    .bind(i32::try_from(evt.void_count).unwrap_or(i32::MAX))
+    .bind(Money::new(evt.void_amount.clone()))
+    .bind(Money::new(evt.cash_variance.clone()))
+    .bind(evt.closed_by)
+    .bind(evt.closed_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Insert an `audit.events` message into the `audit_events` read model. Idempotent on `event_id`,
+/// so replaying over existing rows only fills gaps.
+pub async fn apply_audit_event(db: &PgPool, evt: &AuditEvent) -> sqlx::Result<bool> {
+    let done = sqlx::query(
+        "INSERT INTO audit_events (
+            event_id, event_version, tenant_id, actor_id, actor_name, actor_email,
+            entity_type, entity_id, action, severity, source_service, occurred_at,
+            trace_id, payload, meta
+        ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15)
+        ON CONFLICT (event_id) DO NOTHING",
+    )
+    .bind(evt.event_id)
+    .bind(evt.event_version)
+    .bind(evt.tenant_id)
+    .bind(evt.actor.id)
+    .bind(&evt.actor.name)
+    .bind(&evt.actor.email)
+    .bind(&evt.entity_type)
+    .bind(evt.entity_id)
+    .bind(&evt.action)
+    .bind(format!("{:?}", evt.severity))
+    .bind(&evt.source_service)
+    .bind(evt.occurred_at)
+    .bind(evt.trace_id)
+    .bind(&evt.payload)
+    .bind(&evt.meta)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_events::OrderEventItem;
+
+    fn event(total: &str, return_id: Option<Uuid>) -> OrderCompletedEvent {
+        OrderCompletedEvent {
+            schema_version: 1,
+            order_id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            items: Vec::new(),
+            total: total.parse().unwrap(),
+            customer_id: None,
+            offline: false,
+            payment_method: "cash".into(),
+            return_id,
+            location_id: None,
+            employee_id: None,
+            rma_id: None,
+            exchange_return_id: None,
+            business_date: None,
+        }
+    }
+
+    fn money(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap())
+    }
+
+    #[test]
+    fn closing_a_day_corrects_by_the_difference() {
+        let day = ClosedDay {
+            tenant_id: Uuid::nil(),
+            location_id: Uuid::new_v4(),
+            date: NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+            sales: money("120.00"),
+            orders: 6,
+            refunds: money("10.00"),
+            refund_count: 1,
+        };
+        let delta = day.correction((money("100.00"), 5, money("10.00"), 1));
+        assert_eq!((delta.sales, delta.orders, delta.refunds, delta.refund_count), (money("20.00"), 1, money("0"), 0));
+        let again = day.correction((money("120.00"), 6, money("10.00"), 1));
+        assert_eq!((again.sales, again.orders, again.refunds, again.refund_count), (money("0"), 0, money("0"), 0));
+    }
+
+    #[test]
+    fn sale_counts_towards_sales() {
+        let delta = SalesDelta::from_event(&event("12.50", None));
+        assert_eq!((delta.sales, delta.orders, delta.refunds, delta.refund_count), (money("12.50"), 1, money("0"), 0));
+    }
+
+    #[test]
+    fn amounts_are_exact_and_normalized() {
+        // 0.1 + 0.2 drifts in f64; ten sales of 0.10 and one of 0.20 must add up to exactly 1.20.
+        let mut total = Money::default();
+        for amount in ["0.10"; 10].into_iter().chain(["0.20"]) {
+            total += SalesDelta::from_event(&event(amount, None)).sales;
+        }
+        assert_eq!(total, money("1.20"));
+        assert_eq!(SalesDelta::from_event(&event("3.005", None)).sales.to_string(), "3.01");
+    }
+
+    #[test]
+    fn refunds_count_as_positive_refund_amounts() {
+        let negative = SalesDelta::from_event(&event("-4.00", None));
+        assert_eq!((negative.sales, negative.orders, negative.refunds, negative.refund_count), (money("0"), 0, money("4.00"), 1));
+        let with_return = SalesDelta::from_event(&event("4.00", Some(Uuid::new_v4())));
+        assert_eq!(with_return.refunds, money("4.00"));
+        assert_eq!(with_return.orders, 0);
+    }
+
+    #[test]
+    fn store_dimension_and_items_come_from_the_event() {
+        let store = Uuid::new_v4();
+        let item = |quantity| OrderEventItem { product_id: Uuid::new_v4(), quantity, unit_price: "1.00".parse().unwrap(), line_total: "1.00".parse().unwrap(), measured_quantity: None };
+        let sale = OrderCompletedEvent { items: vec![item(2), item(3)], location_id: Some(store), ..event("5.00", None) };
+        let delta = SalesDelta::from_event(&sale);
+        assert_eq!((delta.location_id, delta.items), (store, 5));
+        let refund = OrderCompletedEvent { items: vec![item(-2)], location_id: Some(store), ..event("-2.00", Some(Uuid::new_v4())) };
+        assert_eq!(SalesDelta::from_event(&refund).items, 0);
+        assert_eq!(SalesDelta::from_event(&event("1.00", None)).location_id, Uuid::nil());
+    }
+
+    #[test]
+    fn employee_comes_from_the_event_and_voids_need_a_requester() {
+        let cashier = Uuid::new_v4();
+        let sale = OrderCompletedEvent { employee_id: Some(cashier), ..event("5.00", None) };
+        assert_eq!(SalesDelta::from_event(&sale).employee_id, cashier);
+        assert_eq!(SalesDelta::from_event(&event("5.00", None)).employee_id, Uuid::nil());
+
+        let void = |requested_by| OrderVoidedEvent {
+            schema_version: 1,
+            order_id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            items: Vec::new(),
+            total: "7.25".parse().unwrap(),
+            customer_id: None,
+            offline: false,
+            payment_method: "cash".into(),
+            reason: None,
+            reason_code: None,
+            requested_by,
+            approved_by: None,
+            approval_method: None,
+            business_date: None,
+        };
+        let delta = VoidDelta::from_event(&void(Some(cashier))).unwrap();
+        assert_eq!((delta.employee_id, delta.amount), (cashier, money("7.25")));
+        assert_eq!(VoidDelta::from_event(&void(None)), None);
+    }
+
+    fn dispute(status: DisputeStatus, previous_status: Option<DisputeStatus>) -> PaymentDisputeUpdatedEvent {
+        PaymentDisputeUpdatedEvent {
+            schema_version: 1,
+            dispute_id: Uuid::new_v4(),
+            order_id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            payment_intent_id: None,
+            status,
+            previous_status,
+            amount_minor: 2550,
+            currency: "USD".into(),
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn disputes_count_when_opened_and_when_decided() {
+        use DisputeStatus::*;
+        let opened = DisputeDelta::from_event(&dispute(NeedsResponse, None)).unwrap();
+        assert_eq!((opened.opened_count, opened.opened_amount, opened.lost_count), (1, money("25.50"), 0));
+        assert_eq!(DisputeDelta::from_event(&dispute(UnderReview, Some(NeedsResponse))), None);
+        let lost = DisputeDelta::from_event(&dispute(Lost, Some(UnderReview))).unwrap();
+        assert_eq!((lost.opened_count, lost.lost_count, lost.lost_amount), (0, 1, money("25.50")));
+        // Providers can report a dispute for the first time already decided.
+        let won = DisputeDelta::from_event(&dispute(Won, None)).unwrap();
+        assert_eq!((won.opened_count, won.won_count), (1, 1));
+    }
+    fn tip(tip: &str, previous_tip: &str, employee_id: Option<Uuid>) -> OrderTipRecordedEvent {
+        let previous_tip: bigdecimal::BigDecimal = previous_tip.parse().unwrap();
+        OrderTipRecordedEvent {
+            schema_version: 1,
+            order_id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            employee_id,
+            pos_instance_id: None,
+            payment_method: "card".into(),
+            tip: tip.parse().unwrap(),
+            adjustment: previous_tip != bigdecimal::BigDecimal::from(0),
+            previous_tip,
+            business_date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn tips_count_orders_once_and_adjustments_move_the_amount() {
+        let employee = Uuid::new_v4();
+        let first = TipDelta::from_event(&tip("3.00", "0", Some(employee))).unwrap();
+        assert_eq!((first.employee_id, first.tip_amount, first.tipped_orders, first.adjustments), (employee, money("3.00"), 1, 0));
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        let raised = TipDelta::from_event(&tip("4.50", "3.00", None)).unwrap();
+        assert_eq!((raised.employee_id, raised.tip_amount, raised.tipped_orders, raised.adjustments), (Uuid::nil(), money("1.50"), 0, 1));
+        let removed = TipDelta::from_event(&tip("0", "4.50", None)).unwrap();
+        assert_eq!((removed.tip_amount, removed.tipped_orders, removed.adjustments), (money("-4.50"), -1, 1));
+        assert_eq!(TipDelta::from_event(&tip("2.00", "2.00", None)), None);
+    }
+}
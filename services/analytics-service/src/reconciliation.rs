@@ -0,0 +1,208 @@
+//! Nightly check of the `daily_sales` rollup against order-service's own totals for the same
+//! business day: sold orders and their totals from `orders`, refunds from `order_returns`, the
+//! same figures the Z-report is built from. Both sides are compared in exact decimals after
+//! common-money normalization; tenant-days that differ are kept in
+//! `sales_reconciliation_discrepancies` until a later run finds them matching again.
+//!
+//! Sales consumed before events carried `business_date` were booked on the day they were
+//! consumed, so days from before that change can differ for that reason alone.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use common_money::{nearly_equal, Cents, Money};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Order statuses that count as sales, as in order-service's Z-report.
+const SOLD_STATUSES: &[&str] = &["COMPLETED", "PAID", "REFUNDED", "PARTIAL_REFUNDED"];
+
+/// One tenant's business day as the rollup has it next to what order-service recorded.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct DayComparison {
+    pub tenant_id: Uuid,
+    pub date: NaiveDate,
+    pub rollup_order_count: i64,
+    pub rollup_total_sales: Money,
+    pub rollup_refund_amount: Money,
+    pub rollup_refund_count: i64,
+    pub source_order_count: i64,
+    pub source_total_sales: Money,
+    pub source_refund_amount: Money,
+    pub source_refund_count: i64,
+}
+
+impl DayComparison {
+    /// Figures that differ; amounts only when they are more than `tolerance` apart.
+    pub fn mismatches(&self, tolerance: Cents) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.rollup_order_count != self.source_order_count {
+            fields.push("order_count");
+        }
+        if !nearly_equal(self.rollup_total_sales.inner(), self.source_total_sales.inner(), tolerance) {
+            fields.push("total_sales");
+        }
+        if self.rollup_refund_count != self.source_refund_count {
+            fields.push("refund_count");
+        }
+        if !nearly_equal(self.rollup_refund_amount.inner(), self.source_refund_amount.inner(), tolerance) {
+            fields.push("refund_amount");
+        }
+        fields
+    }
+}
+
+/// Outcome of reconciling one day.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DayReport {
+    /// Tenants with sales or refunds on either side.
+    pub tenants_checked: usize,
+    pub discrepancies: Vec<(DayComparison, Vec<&'static str>)>,
+}
+
+/// The business days a run at `now` checks: the `days` days before today (UTC), oldest first.
+/// Today is left out since it is still trading.
+pub fn days_to_check(now: DateTime<Utc>, days: u32) -> Vec<NaiveDate> {
+    let today = now.date_naive();
+    (1..=i64::from(days)).rev().map(|back| today - Duration::days(back)).collect()
+}
+
+/// The first run time strictly after `now` at `hour`:00 UTC.
+pub fn next_run_after(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let at = NaiveTime::from_hms_opt(hour.min(23), 0, 0).unwrap_or(NaiveTime::MIN);
+    let today = now.date_naive().and_time(at).and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Both sides of `date` for every tenant with activity on either side.
+pub async fn compare_day(db: &PgPool, date: NaiveDate) -> sqlx::Result<Vec<DayComparison>> {
+    let sold: Vec<String> = SOLD_STATUSES.iter().map(|s| s.to_string()).collect();
+    sqlx::query_as::<_, DayComparison>(
+        "WITH rollup AS (
+             SELECT tenant_id, order_count::BIGINT AS order_count, total_sales, refund_amount, refund_count::BIGINT AS refund_count
+             FROM daily_sales WHERE date = $1
+         ), sales AS (
+             SELECT tenant_id, COUNT(*)::BIGINT AS order_count, COALESCE(SUM(total), 0)::NUMERIC AS total_sales
+             FROM orders WHERE business_date = $1 AND status = ANY($2) GROUP BY tenant_id
+         ), refunds AS (
+             SELECT tenant_id, COUNT(*)::BIGINT AS refund_count, COALESCE(SUM(total), 0)::NUMERIC AS refund_amount
+             FROM order_returns WHERE business_date = $1 GROUP BY tenant_id
+         ), tenants AS (
+             SELECT tenant_id FROM rollup UNION SELECT tenant_id FROM sales UNION SELECT tenant_id FROM refunds
+         )
+         SELECT t.tenant_id, $1::date AS date,
+                COALESCE(r.order_count, 0) AS rollup_order_count, COALESCE(r.total_sales, 0)::NUMERIC AS rollup_total_sales,
+                COALESCE(r.refund_amount, 0)::NUMERIC AS rollup_refund_amount, COALESCE(r.refund_count, 0) AS rollup_refund_count,
+                COALESCE(s.order_count, 0) AS source_order_count, COALESCE(s.total_sales, 0) AS source_total_sales,
+                COALESCE(f.refund_amount, 0) AS source_refund_amount, COALESCE(f.refund_count, 0) AS source_refund_count
+         FROM tenants t
+         LEFT JOIN rollup r ON r.tenant_id = t.tenant_id
+         LEFT JOIN sales s ON s.tenant_id = t.tenant_id
+         LEFT JOIN refunds f ON f.tenant_id = t.tenant_id
+         ORDER BY t.tenant_id",
+    )
+    .bind(date)
+    .bind(&sold)
+    .fetch_all(db)
+    .await
+}
+
+/// Reconcile `date`: record every tenant whose figures differ and clear the ones that match again.
+/// Runs are idempotent, so replicas checking the same day only refresh `checked_at`.
+pub async fn reconcile_day(db: &PgPool, date: NaiveDate, tolerance: Cents) -> sqlx::Result<DayReport> {
+    let comparisons = compare_day(db, date).await?;
+    let tenants_checked = comparisons.len();
+    let discrepancies: Vec<_> = comparisons
+        .into_iter()
+        .filter_map(|day| {
+            let fields = day.mismatches(tolerance);
+            (!fields.is_empty()).then_some((day, fields))
+        })
+        .collect();
+
+    let mut tx = db.begin().await?;
+    for (day, _) in &discrepancies {
+        sqlx::query(
+            "INSERT INTO sales_reconciliation_discrepancies
+                    (tenant_id, date, rollup_order_count, rollup_total_sales, rollup_refund_amount, rollup_refund_count,
+                     source_order_count, source_total_sales, source_refund_amount, source_refund_count)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             ON CONFLICT (tenant_id, date)
+             DO UPDATE
+                SET rollup_order_count = $3, rollup_total_sales = $4, rollup_refund_amount = $5, rollup_refund_count = $6,
+                    source_order_count = $7, source_total_sales = $8, source_refund_amount = $9, source_refund_count = $10,
+                    checked_at = now()",
+        )
+        .bind(day.tenant_id)
+        .bind(day.date)
+        .bind(day.rollup_order_count)
+        .bind(&day.rollup_total_sales)
+        .bind(&day.rollup_refund_amount)
+        .bind(day.rollup_refund_count)
+        .bind(day.source_order_count)
+        .bind(&day.source_total_sales)
+        .bind(&day.source_refund_amount)
+        .bind(day.source_refund_count)
+        .execute(&mut *tx)
+        .await?;
+    }
+    let open: Vec<Uuid> = discrepancies.iter().map(|(day, _)| day.tenant_id).collect();
+    sqlx::query("DELETE FROM sales_reconciliation_discrepancies WHERE date = $1 AND NOT (tenant_id = ANY($2))")
+        .bind(date)
+        .bind(&open)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(DayReport { tenants_checked, discrepancies })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn money(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap())
+    }
+
+    fn day(rollup: (i64, &str, &str, i64), source: (i64, &str, &str, i64)) -> DayComparison {
+        DayComparison {
+            tenant_id: Uuid::nil(),
+            date: NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+            rollup_order_count: rollup.0,
+            rollup_total_sales: money(rollup.1),
+            rollup_refund_amount: money(rollup.2),
+            rollup_refund_count: rollup.3,
+            source_order_count: source.0,
+            source_total_sales: money(source.1),
+            source_refund_amount: money(source.2),
+            source_refund_count: source.3,
+        }
+    }
+
+    #[test]
+    fn matching_days_have_no_mismatches() {
+        assert!(day((3, "30.30", "5.00", 1), (3, "30.3", "5", 1)).mismatches(Cents::ZERO).is_empty());
+    }
+
+    #[test]
+    fn every_differing_figure_is_reported() {
+        let drifted = day((3, "30.31", "5.00", 1), (4, "30.30", "7.50", 2));
+        assert_eq!(drifted.mismatches(Cents::ZERO), vec!["order_count", "total_sales", "refund_count", "refund_amount"]);
+        // A cent of tolerance absorbs the sales difference but not the counts.
+        assert_eq!(drifted.mismatches(Cents(1)), vec!["order_count", "refund_count", "refund_amount"]);
+    }
+
+    #[test]
+    fn runs_check_the_days_before_today_at_the_configured_hour() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 2, 30, 0).unwrap();
+        let dates = days_to_check(now, 2);
+        assert_eq!(dates, vec![NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()]);
+        assert!(days_to_check(now, 0).is_empty());
+        assert_eq!(next_run_after(now, 3), Utc.with_ymd_and_hms(2026, 10, 18, 3, 0, 0).unwrap());
+        assert_eq!(next_run_after(now, 2), Utc.with_ymd_and_hms(2026, 10, 19, 2, 0, 0).unwrap());
+    }
+}
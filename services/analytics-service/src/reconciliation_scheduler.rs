@@ -0,0 +1,86 @@
+//! Runs the sales reconciliation once a night at `ANALYTICS_RECONCILE_HOUR_UTC`. Each run
+//! re-checks the last `ANALYTICS_RECONCILE_DAYS` business days, so late events and day closes
+//! clear discrepancies a previous run recorded. Runs are idempotent; every replica may run them.
+
+use analytics_service::reconciliation::{days_to_check, next_run_after, reconcile_day};
+use chrono::Utc;
+use common_money::Cents;
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{error, info, warn};
+
+const FIELDS: [&str; 4] = ["order_count", "total_sales", "refund_count", "refund_amount"];
+
+static RECONCILIATION_RUNS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("sales_reconciliation_runs_total", "Nightly daily_sales reconciliation runs by status"),
+        &["status"],
+    )
+    .unwrap();
+    crate::ANALYTICS_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+static RECONCILIATION_DISCREPANCIES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let v = IntGaugeVec::new(
+        Opts::new(
+            "sales_reconciliation_discrepancies",
+            "Tenant-days whose daily_sales figure differs from order-service at the last reconciliation, by figure",
+        ),
+        &["field"],
+    )
+    .unwrap();
+    crate::ANALYTICS_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+pub fn spawn_reconciliation(db: PgPool, hour_utc: u32, days: u32, tolerance: Cents) {
+    if days == 0 {
+        info!("Sales reconciliation disabled");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let wait = (next_run_after(now, hour_utc) - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            match reconcile(&db, days, tolerance).await {
+                Ok(()) => RECONCILIATION_RUNS_TOTAL.with_label_values(&["ok"]).inc(),
+                Err(err) => {
+                    RECONCILIATION_RUNS_TOTAL.with_label_values(&["failed"]).inc();
+                    error!(?err, "Sales reconciliation failed");
+                }
+            }
+        }
+    });
+}
+
+async fn reconcile(db: &PgPool, days: u32, tolerance: Cents) -> sqlx::Result<()> {
+    let mut open: HashMap<&str, i64> = FIELDS.iter().map(|field| (*field, 0)).collect();
+    for date in days_to_check(Utc::now(), days) {
+        let report = reconcile_day(db, date, tolerance).await?;
+        for (day, fields) in &report.discrepancies {
+            warn!(
+                tenant_id = %day.tenant_id,
+                %date,
+                ?fields,
+                rollup_total_sales = %day.rollup_total_sales,
+                source_total_sales = %day.source_total_sales,
+                rollup_refund_amount = %day.rollup_refund_amount,
+                source_refund_amount = %day.source_refund_amount,
+                rollup_order_count = day.rollup_order_count,
+                source_order_count = day.source_order_count,
+                "daily_sales differs from order-service"
+            );
+            for field in fields {
+                *open.entry(field).or_default() += 1;
+            }
+        }
+        info!(%date, tenants = report.tenants_checked, discrepancies = report.discrepancies.len(), "Reconciled daily_sales");
+    }
+    for (field, count) in open {
+        RECONCILIATION_DISCREPANCIES.with_label_values(&[field]).set(count);
+    }
+    Ok(())
+}
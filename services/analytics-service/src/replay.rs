@@ -0,0 +1,284 @@
+//! Bounded re-consumption of a topic, used to rebuild read models from Kafka history.
+//!
+//! A replay assigns partitions directly under a throwaway group id and never commits, so live
+//! consumer groups keep their offsets. The end of each partition is pinned to its high watermark
+//! when the replay starts; messages produced afterwards are left to the live consumer.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, TimeZone, Utc};
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::{Message, Offset, TopicPartitionList};
+use tracing::{info, warn};
+
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+/// Give up waiting for the tail of a partition after this long without a message. Offsets at the
+/// end of a partition can belong to transaction markers that are never delivered.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartPosition {
+    Beginning,
+    /// The same offset in every partition, clamped to what the partition still retains.
+    Offset(i64),
+    /// The first message at or after this time, per partition.
+    Timestamp(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub topic: String,
+    pub start: StartPosition,
+    /// Upper bound on messages handled per second; `None` replays as fast as the handler allows.
+    pub max_per_sec: Option<u32>,
+    /// Log progress every this many messages.
+    pub progress_every: u64,
+}
+
+/// Offsets `[start, end)` of one partition to replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionRange {
+    pub partition: i32,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl PartitionRange {
+    pub fn len(&self) -> u64 {
+        (self.end - self.start).max(0) as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A replayed message, detached from the consumer so handlers can be async.
+#[derive(Debug, Clone)]
+pub struct ReplayMessage {
+    pub partition: i32,
+    pub offset: i64,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub payload: String,
+}
+
+/// What a handler did with a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Applied,
+    /// Filtered out, undecodable or already present.
+    Skipped,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayStats {
+    pub total: u64,
+    pub processed: u64,
+    pub applied: u64,
+    pub skipped: u64,
+}
+
+/// Keeps a replay under `max_per_sec` so a rebuild doesn't starve the live consumers sharing the
+/// database.
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    max_per_sec: Option<u32>,
+}
+
+impl Throttle {
+    pub fn new(max_per_sec: Option<u32>) -> Self {
+        Self { max_per_sec: max_per_sec.filter(|rate| *rate > 0) }
+    }
+
+    /// How long to pause after `processed` messages took `elapsed`, to stay on the target rate.
+    pub fn delay(&self, processed: u64, elapsed: Duration) -> Duration {
+        let Some(rate) = self.max_per_sec else {
+            return Duration::ZERO;
+        };
+        let due = Duration::from_secs_f64(processed as f64 / rate as f64);
+        due.saturating_sub(elapsed)
+    }
+}
+
+/// Clamp a requested start offset to what the partition holds; `None` means "from the beginning".
+pub fn clamp_start(requested: Option<i64>, low: i64, high: i64) -> i64 {
+    requested.unwrap_or(low).clamp(low, high)
+}
+
+/// Work out the offset range of every partition of `config.topic`.
+pub fn resolve_ranges(consumer: &StreamConsumer, config: &ReplayConfig) -> anyhow::Result<Vec<PartitionRange>> {
+    let metadata = consumer
+        .fetch_metadata(Some(&config.topic), METADATA_TIMEOUT)
+        .with_context(|| format!("failed to fetch metadata for {}", config.topic))?;
+    let partitions: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .find(|t| t.name() == config.topic)
+        .map(|t| t.partitions().iter().map(|p| p.id()).collect())
+        .unwrap_or_default();
+    if partitions.is_empty() {
+        return Err(anyhow!("topic {} has no partitions (does it exist?)", config.topic));
+    }
+
+    let by_time = match config.start {
+        StartPosition::Timestamp(ts) => {
+            let mut query = TopicPartitionList::new();
+            for partition in &partitions {
+                query.add_partition_offset(&config.topic, *partition, Offset::Offset(ts.timestamp_millis()))?;
+            }
+            Some(consumer.offsets_for_times(query, METADATA_TIMEOUT).context("failed to look up offsets by timestamp")?)
+        }
+        _ => None,
+    };
+
+    let mut ranges = Vec::with_capacity(partitions.len());
+    for partition in partitions {
+        let (low, high) = consumer
+            .fetch_watermarks(&config.topic, partition, METADATA_TIMEOUT)
+            .with_context(|| format!("failed to fetch watermarks for {}[{partition}]", config.topic))?;
+        let requested = match (config.start, &by_time) {
+            (StartPosition::Beginning, _) => None,
+            (StartPosition::Offset(offset), _) => Some(offset),
+            (StartPosition::Timestamp(_), Some(found)) => match found
+                .find_partition(&config.topic, partition)
+                .map(|elem| elem.offset())
+            {
+                Some(Offset::Offset(offset)) => Some(offset),
+                // Nothing at or after the timestamp.
+                _ => Some(high),
+            },
+            (StartPosition::Timestamp(_), None) => None,
+        };
+        ranges.push(PartitionRange { partition, start: clamp_start(requested, low, high), end: high });
+    }
+    Ok(ranges)
+}
+
+/// Replay every message in `ranges` through `handle`, in offset order within each partition.
+///
+/// Handler errors abort the replay; the error names the partition and offset so the run can be
+/// picked up from there.
+pub async fn replay<F, Fut>(
+    consumer: &StreamConsumer,
+    config: &ReplayConfig,
+    ranges: &[PartitionRange],
+    mut handle: F,
+) -> anyhow::Result<ReplayStats>
+where
+    F: FnMut(ReplayMessage) -> Fut,
+    Fut: Future<Output = anyhow::Result<Outcome>>,
+{
+    let mut stats = ReplayStats { total: ranges.iter().map(PartitionRange::len).sum(), ..Default::default() };
+    let mut pending: Vec<PartitionRange> = ranges.iter().copied().filter(|r| !r.is_empty()).collect();
+    if pending.is_empty() {
+        info!(topic = %config.topic, "nothing to replay");
+        return Ok(stats);
+    }
+
+    let mut assignment = TopicPartitionList::new();
+    for range in &pending {
+        assignment.add_partition_offset(&config.topic, range.partition, Offset::Offset(range.start))?;
+    }
+    consumer.assign(&assignment).context("failed to assign partitions")?;
+    info!(topic = %config.topic, partitions = pending.len(), total = stats.total, "replay started");
+
+    let throttle = Throttle::new(config.max_per_sec);
+    let started = Instant::now();
+    while !pending.is_empty() {
+        let message = match tokio::time::timeout(IDLE_TIMEOUT, consumer.recv()).await {
+            Ok(Ok(message)) => message,
+            Ok(Err(err)) => return Err(err).context("kafka error during replay"),
+            Err(_) => {
+                warn!(
+                    topic = %config.topic,
+                    partitions = ?pending.iter().map(|r| r.partition).collect::<Vec<_>>(),
+                    "no messages for {IDLE_TIMEOUT:?}; stopping before the recorded end offsets"
+                );
+                break;
+            }
+        };
+        let (partition, offset) = (message.partition(), message.offset());
+        let Some(idx) = pending.iter().position(|r| r.partition == partition) else {
+            continue;
+        };
+        let end = pending[idx].end;
+        if offset >= end {
+            pending.swap_remove(idx);
+            continue;
+        }
+        let replayed = ReplayMessage {
+            partition,
+            offset,
+            timestamp: message.timestamp().to_millis().and_then(|ms| Utc.timestamp_millis_opt(ms).single()),
+            payload: message.payload().map(|bytes| String::from_utf8_lossy(bytes).into_owned()).unwrap_or_default(),
+        };
+        drop(message);
+
+        match handle(replayed).await.with_context(|| format!("{}[{partition}] offset {offset}", config.topic))? {
+            Outcome::Applied => stats.applied += 1,
+            Outcome::Skipped => stats.skipped += 1,
+        }
+        stats.processed += 1;
+        if offset + 1 >= end {
+            pending.swap_remove(idx);
+        }
+
+        if config.progress_every > 0 && stats.processed.is_multiple_of(config.progress_every) {
+            log_progress(config, &stats, started.elapsed());
+        }
+        let pause = throttle.delay(stats.processed, started.elapsed());
+        if !pause.is_zero() {
+            tokio::time::sleep(pause).await;
+        }
+    }
+    log_progress(config, &stats, started.elapsed());
+    Ok(stats)
+}
+
+fn log_progress(config: &ReplayConfig, stats: &ReplayStats, elapsed: Duration) {
+    let percent = if stats.total == 0 { 100.0 } else { stats.processed as f64 * 100.0 / stats.total as f64 };
+    let rate = stats.processed as f64 / elapsed.as_secs_f64().max(0.001);
+    info!(
+        topic = %config.topic,
+        processed = stats.processed,
+        total = stats.total,
+        applied = stats.applied,
+        skipped = stats.skipped,
+        "replay progress {percent:.1}% ({rate:.0} msg/s)"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unthrottled_never_waits() {
+        let throttle = Throttle::new(None);
+        assert_eq!(throttle.delay(10_000, Duration::ZERO), Duration::ZERO);
+        assert_eq!(Throttle::new(Some(0)).delay(10_000, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn throttle_waits_until_the_rate_is_met() {
+        let throttle = Throttle::new(Some(100));
+        assert_eq!(throttle.delay(50, Duration::from_millis(200)), Duration::from_millis(300));
+        assert_eq!(throttle.delay(50, Duration::from_millis(600)), Duration::ZERO);
+    }
+
+    #[test]
+    fn start_offsets_are_clamped_to_retained_range() {
+        assert_eq!(clamp_start(None, 40, 90), 40);
+        assert_eq!(clamp_start(Some(10), 40, 90), 40);
+        assert_eq!(clamp_start(Some(55), 40, 90), 55);
+        assert_eq!(clamp_start(Some(500), 40, 90), 90);
+    }
+
+    #[test]
+    fn range_length_is_never_negative() {
+        assert_eq!(PartitionRange { partition: 0, start: 5, end: 9 }.len(), 4);
+        assert!(PartitionRange { partition: 0, start: 9, end: 9 }.is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::AppState;
+use analytics_service::reports::{valid_email, Frequency, ReportKind};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use common_auth::{ensure_role, tenant_id_from_request, AuthContext, ROLE_ADMIN, ROLE_SUPER_ADMIN};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Report subscriptions send tenant data by email, so only admins manage them.
+const REPORT_ADMIN_ROLES: &[&str] = &[ROLE_SUPER_ADMIN, ROLE_ADMIN];
+const MAX_RECIPIENTS: usize = 20;
+const MAX_SUBSCRIPTIONS: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionRequest {
+    /// `sales_summary`, `low_stock` or `top_products`.
+    pub report: String,
+    /// `daily` or `weekly`.
+    pub frequency: String,
+    /// ISO weekday (1 = Monday) for weekly subscriptions.
+    pub weekday: Option<i16>,
+    /// Local wall-clock time in `timezone`, e.g. `07:30`.
+    pub send_time: NaiveTime,
+    /// IANA zone name; defaults to UTC.
+    pub timezone: Option<String>,
+    pub recipients: Vec<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub report: String,
+    pub frequency: String,
+    pub weekday: Option<i16>,
+    pub send_time: NaiveTime,
+    pub timezone: String,
+    pub recipients: Vec<String>,
+    pub active: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "id, report, frequency, weekday, send_time, timezone, recipients, active, next_run_at, created_by, created_at, updated_at";
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("DB query failed: {}", e))
+}
+
+/// A request that passed validation, with recipients trimmed, lower-cased and de-duplicated.
+struct ValidSubscription {
+    report: ReportKind,
+    frequency: Frequency,
+    weekday: Option<i16>,
+    send_time: NaiveTime,
+    timezone: String,
+    recipients: Vec<String>,
+    active: bool,
+}
+
+fn validate(req: SubscriptionRequest) -> Result<ValidSubscription, (StatusCode, String)> {
+    let report = ReportKind::parse(&req.report)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported report '{}'; use sales_summary, low_stock or top_products", req.report)))?;
+    let frequency = Frequency::parse(&req.frequency)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported frequency '{}'; use daily or weekly", req.frequency)))?;
+    let weekday = match (frequency, req.weekday) {
+        (Frequency::Daily, None) => None,
+        (Frequency::Daily, Some(_)) => return Err((StatusCode::BAD_REQUEST, "weekday only applies to weekly reports".into())),
+        (Frequency::Weekly, Some(day)) if (1..=7).contains(&day) => Some(day),
+        (Frequency::Weekly, _) => return Err((StatusCode::BAD_REQUEST, "Weekly reports need a weekday from 1 (Monday) to 7 (Sunday)".into())),
+    };
+    let mut recipients: Vec<String> = Vec::with_capacity(req.recipients.len());
+    for address in &req.recipients {
+        let address = address.trim().to_lowercase();
+        if !valid_email(&address) {
+            return Err((StatusCode::BAD_REQUEST, format!("'{address}' is not a valid email address")));
+        }
+        if !recipients.contains(&address) {
+            recipients.push(address);
+        }
+    }
+    if recipients.is_empty() || recipients.len() > MAX_RECIPIENTS {
+        return Err((StatusCode::BAD_REQUEST, format!("A subscription needs between 1 and {MAX_RECIPIENTS} recipients")));
+    }
+    let timezone = req.timezone.map(|tz| tz.trim().to_string()).filter(|tz| !tz.is_empty()).unwrap_or_else(|| "UTC".into());
+    Ok(ValidSubscription { report, frequency, weekday, send_time: req.send_time, timezone, recipients, active: req.active.unwrap_or(true) })
+}
+
+async fn ensure_timezone(db: &sqlx::PgPool, timezone: &str) -> Result<(), (StatusCode, String)> {
+    let known = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1)")
+        .bind(timezone)
+        .fetch_one(db)
+        .await
+        .map_err(db_error)?;
+    if !known {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown timezone '{timezone}'; use an IANA name such as Europe/Berlin")));
+    }
+    Ok(())
+}
+
+/// `GET /reports/subscriptions`
+pub async fn list_subscriptions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Subscription>>, (StatusCode, String)> {
+    ensure_role(&auth, REPORT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let rows = sqlx::query_as::<_, Subscription>(&format!(
+        "SELECT {SUBSCRIPTION_COLUMNS} FROM report_subscriptions WHERE tenant_id = $1 ORDER BY created_at, id"
+    ))
+    .bind(tenant_id)
+    .fetch_all(state.db.primary())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(rows))
+}
+
+/// `POST /reports/subscriptions`: the first send is the next `send_time` still ahead.
+pub async fn create_subscription(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Json(req): Json<SubscriptionRequest>,
+) -> Result<(StatusCode, Json<Subscription>), (StatusCode, String)> {
+    ensure_role(&auth, REPORT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let sub = validate(req)?;
+    let db = state.db.primary();
+    ensure_timezone(db, &sub.timezone).await?;
+
+    let existing = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM report_subscriptions WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .fetch_one(db)
+        .await
+        .map_err(db_error)?;
+    if existing >= MAX_SUBSCRIPTIONS {
+        return Err((StatusCode::CONFLICT, format!("A tenant can have at most {MAX_SUBSCRIPTIONS} report subscriptions")));
+    }
+
+    let row = sqlx::query_as::<_, Subscription>(&format!(
+        "INSERT INTO report_subscriptions
+                (id, tenant_id, report, frequency, weekday, send_time, timezone, recipients, active, next_run_at, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, report_next_run($4, $5, $6, $7, now()), $10)
+         RETURNING {SUBSCRIPTION_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(sub.report.as_str())
+    .bind(sub.frequency.as_str())
+    .bind(sub.weekday)
+    .bind(sub.send_time)
+    .bind(&sub.timezone)
+    .bind(&sub.recipients)
+    .bind(sub.active)
+    .bind(auth.claims.subject)
+    .fetch_one(db)
+    .await
+    .map_err(db_error)?;
+    Ok((StatusCode::CREATED, Json(row)))
+}
+
+/// `PUT /reports/subscriptions/:id`: replaces the settings and reschedules from now.
+pub async fn update_subscription(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SubscriptionRequest>,
+) -> Result<Json<Subscription>, (StatusCode, String)> {
+    ensure_role(&auth, REPORT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let sub = validate(req)?;
+    let db = state.db.primary();
+    ensure_timezone(db, &sub.timezone).await?;
+
+    let row = sqlx::query_as::<_, Subscription>(&format!(
+        "UPDATE report_subscriptions
+            SET report = $3, frequency = $4, weekday = $5, send_time = $6, timezone = $7, recipients = $8, active = $9,
+                next_run_at = report_next_run($4, $5, $6, $7, now()), updated_at = now()
+          WHERE id = $1 AND tenant_id = $2
+         RETURNING {SUBSCRIPTION_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(tenant_id)
+    .bind(sub.report.as_str())
+    .bind(sub.frequency.as_str())
+    .bind(sub.weekday)
+    .bind(sub.send_time)
+    .bind(&sub.timezone)
+    .bind(&sub.recipients)
+    .bind(sub.active)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Report subscription not found".to_string()))?;
+    Ok(Json(row))
+}
+
+/// `DELETE /reports/subscriptions/:id`: also drops its delivery history.
+pub async fn delete_subscription(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_role(&auth, REPORT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let done = sqlx::query("DELETE FROM report_subscriptions WHERE id = $1 AND tenant_id = $2")
+        .bind(id)
+        .bind(tenant_id)
+        .execute(state.db.primary())
+        .await
+        .map_err(db_error)?;
+    if done.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Report subscription not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct DeliveryQuery {
+    pub subscription_id: Option<Uuid>,
+    /// Defaults to 50, at most 200.
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Delivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub report: String,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub scheduled_for: DateTime<Utc>,
+    pub recipients: Vec<String>,
+    pub subject: String,
+    /// `sent` once handed to the notification path, `failed` otherwise.
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `GET /reports/deliveries`: most recent send attempts first.
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(q): Query<DeliveryQuery>,
+) -> Result<Json<Vec<Delivery>>, (StatusCode, String)> {
+    ensure_role(&auth, REPORT_ADMIN_ROLES)?;
+    let tenant_id = tenant_id_from_request(&headers, &auth)?;
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let rows = sqlx::query_as::<_, Delivery>(
+        "SELECT id, subscription_id, report, period_start, period_end, scheduled_for, recipients, subject, status, error, created_at
+         FROM report_deliveries
+         WHERE tenant_id = $1 AND ($2::uuid IS NULL OR subscription_id = $2)
+         ORDER BY created_at DESC, id
+         LIMIT $3",
+    )
+    .bind(tenant_id)
+    .bind(q.subscription_id)
+    .bind(limit)
+    .fetch_all(state.db.get().await)
+    .await
+    .map_err(db_error)?;
+    Ok(Json(rows))
+}
@@ -0,0 +1,156 @@
+//! Sends due report subscriptions. Each tick claims due subscriptions one at a time with
+//! `FOR UPDATE SKIP LOCKED`, so several replicas can run the scheduler without double sends,
+//! renders the report, hands it to the notification path and records the attempt. The next run
+//! is computed from the current time, so runs missed while the service was down collapse into
+//! one send.
+
+use analytics_service::reports::{render_report, report_period, Frequency, ReportKind};
+use chrono::{DateTime, NaiveDate, Utc};
+use common_events::{topics, DomainEvent, EmailRequestedEvent};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, Opts};
+use rdkafka::producer::FutureProducer;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Upper bound on sends per tick so one tick can't run unbounded.
+const MAX_SENDS_PER_TICK: usize = 100;
+
+static REPORT_DELIVERIES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("report_deliveries_total", "Scheduled report send attempts by report and status"),
+        &["report", "status"],
+    )
+    .unwrap();
+    crate::ANALYTICS_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+#[derive(sqlx::FromRow)]
+struct DueSubscription {
+    id: Uuid,
+    tenant_id: Uuid,
+    report: String,
+    frequency: String,
+    recipients: Vec<String>,
+    scheduled_for: DateTime<Utc>,
+    /// `scheduled_for` as a date in the subscription's timezone.
+    local_date: NaiveDate,
+}
+
+pub fn spawn_report_scheduler(db: PgPool, producer: FutureProducer, tick_seconds: u64) {
+    if tick_seconds == 0 {
+        info!("Report scheduler disabled");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(tick_seconds));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match send_due_reports(&db, &producer).await {
+                Ok(0) => {}
+                Ok(sent) => info!(sent, "Sent scheduled reports"),
+                Err(err) => error!(?err, "Report scheduler tick failed"),
+            }
+        }
+    });
+}
+
+/// Work through due subscriptions until none are left or the per-tick cap is hit. Returns the
+/// number of attempts recorded.
+async fn send_due_reports(db: &PgPool, producer: &FutureProducer) -> sqlx::Result<usize> {
+    let mut attempts = 0;
+    while attempts < MAX_SENDS_PER_TICK {
+        let mut tx = db.begin().await?;
+        let Some(due) = sqlx::query_as::<_, DueSubscription>(
+            "SELECT id, tenant_id, report, frequency, recipients, next_run_at AS scheduled_for,
+                    (next_run_at AT TIME ZONE timezone)::date AS local_date
+             FROM report_subscriptions
+             WHERE active AND next_run_at <= now()
+             ORDER BY next_run_at
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED",
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        deliver(db, &mut tx, producer, &due).await?;
+        sqlx::query(
+            "UPDATE report_subscriptions
+                SET next_run_at = report_next_run(frequency, weekday, send_time, timezone, now())
+              WHERE id = $1",
+        )
+        .bind(due.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        attempts += 1;
+    }
+    Ok(attempts)
+}
+
+async fn deliver(db: &PgPool, tx: &mut sqlx::PgConnection, producer: &FutureProducer, due: &DueSubscription) -> sqlx::Result<()> {
+    let frequency = Frequency::parse(&due.frequency).unwrap_or(Frequency::Daily);
+    let (from, to) = report_period(frequency, due.local_date);
+    let delivery_id = Uuid::new_v4();
+
+    let outcome = match ReportKind::parse(&due.report) {
+        None => Err((format!("{} report", due.report), format!("unknown report '{}'", due.report))),
+        Some(kind) => match render_report(db, due.tenant_id, kind, from, to).await {
+            Err(err) => Err((format!("{} report", due.report), format!("render failed: {err}"))),
+            Ok(rendered) => {
+                let event = EmailRequestedEvent {
+                    schema_version: EmailRequestedEvent::SCHEMA_VERSION,
+                    tenant_id: due.tenant_id,
+                    message_id: delivery_id,
+                    recipients: due.recipients.clone(),
+                    subject: rendered.subject.clone(),
+                    body: rendered.body,
+                    source: format!("analytics.report.{}", kind.as_str()),
+                };
+                match common_events::encode(&event) {
+                    Err(err) => Err((rendered.subject, err.to_string())),
+                    Ok(payload) => match common_kafka::publish(producer, topics::NOTIFICATION_EMAIL_REQUESTED, &event.partition_key(), &payload).await {
+                        Ok(_) => Ok(rendered.subject),
+                        Err(err) => Err((rendered.subject, format!("publish failed: {err:?}"))),
+                    },
+                }
+            }
+        },
+    };
+
+    let (subject, status, error) = match outcome {
+        Ok(subject) => (subject, "sent", None),
+        Err((subject, err)) => {
+            warn!(subscription_id = %due.id, tenant_id = %due.tenant_id, error = %err, "Scheduled report not sent");
+            (subject, "failed", Some(err))
+        }
+    };
+    REPORT_DELIVERIES_TOTAL.with_label_values(&[due.report.as_str(), status]).inc();
+    sqlx::query(
+        "INSERT INTO report_deliveries
+                (id, tenant_id, subscription_id, report, period_start, period_end, scheduled_for, recipients, subject, status, error)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+    .bind(delivery_id)
+    .bind(due.tenant_id)
+    .bind(due.id)
+    .bind(&due.report)
+    .bind(from)
+    .bind(to)
+    .bind(due.scheduled_for)
+    .bind(&due.recipients)
+    .bind(subject)
+    .bind(status)
+    .bind(error)
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! Scheduled report emails: which reports exist, the period a run covers and the plain-text
+//! rendering handed to the notification path.
+//!
+//! Sales figures come from the daily rollups (`daily_sales`, `daily_disputes`,
+//! `daily_product_sales`), which are bucketed by UTC day; stock comes from `inventory_items`
+//! as it stands when the report is rendered.
+
+use bigdecimal::BigDecimal;
+use chrono::{Duration, NaiveDate};
+use common_money::measure::{from_milli_units, UnitOfMeasure};
+use common_money::Money;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Rows listed by the top products and low stock reports.
+pub const REPORT_ROW_LIMIT: i64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    SalesSummary,
+    LowStock,
+    TopProducts,
+}
+
+impl ReportKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sales_summary" => Some(Self::SalesSummary),
+            "low_stock" => Some(Self::LowStock),
+            "top_products" => Some(Self::TopProducts),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SalesSummary => "sales_summary",
+            Self::LowStock => "low_stock",
+            Self::TopProducts => "top_products",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::SalesSummary => "Sales summary",
+            Self::LowStock => "Low stock",
+            Self::TopProducts => "Top products",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    /// Sent on one ISO weekday (1 = Monday).
+    Weekly,
+}
+
+impl Frequency {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+}
+
+/// Business days a run covers, inclusive: the day before the tenant-local send date for daily
+/// reports, the seven days before it for weekly ones.
+pub fn report_period(frequency: Frequency, local_send_date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let to = local_send_date - Duration::days(1);
+    match frequency {
+        Frequency::Daily => (to, to),
+        Frequency::Weekly => (to - Duration::days(6), to),
+    }
+}
+
+/// Loose shape check for recipient addresses; the mailer does the real validation.
+pub fn valid_email(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+    address.len() <= 254
+        && !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !address.chars().any(|c| c.is_whitespace() || c.is_control())
+        && !domain.contains('@')
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
+pub struct SalesTotals {
+    pub order_count: i64,
+    pub total_sales: Money,
+    pub refund_amount: Money,
+    pub refund_count: i64,
+    pub disputed: Money,
+    pub chargebacks: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ProductLine {
+    pub name: String,
+    pub quantity: i64,
+    pub revenue: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct StockLine {
+    pub name: String,
+    /// Inventory stock units (thousandths for weighed products).
+    pub quantity: i64,
+    pub threshold: i32,
+    pub uom: Option<String>,
+}
+
+/// A rendered report, ready to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedReport {
+    pub subject: String,
+    pub body: String,
+}
+
+fn period_label(from: NaiveDate, to: NaiveDate) -> String {
+    if from == to {
+        from.to_string()
+    } else {
+        format!("{from} to {to}")
+    }
+}
+
+fn stock_units(quantity: i64, uom: Option<&str>) -> String {
+    match uom.and_then(UnitOfMeasure::parse).filter(|u| u.is_measured()) {
+        Some(unit) => match i32::try_from(quantity) {
+            Ok(units) => format!("{} {}", from_milli_units(units), unit.as_str()),
+            Err(_) => format!("{quantity} thousandths {}", unit.as_str()),
+        },
+        None => quantity.to_string(),
+    }
+}
+
+pub fn render_sales_summary(from: NaiveDate, to: NaiveDate, totals: &SalesTotals) -> RenderedReport {
+    let mut body = format!("{} for {}\n\n", ReportKind::SalesSummary.title(), period_label(from, to));
+    let net = &totals.total_sales - &totals.refund_amount;
+    let average = if totals.order_count > 0 {
+        Money::new(totals.total_sales.inner() / BigDecimal::from(totals.order_count))
+    } else {
+        Money::default()
+    };
+    let _ = writeln!(body, "Orders:          {}", totals.order_count);
+    let _ = writeln!(body, "Gross sales:     {}", totals.total_sales);
+    let _ = writeln!(body, "Refunds:         {} ({} refunds)", totals.refund_amount, totals.refund_count);
+    let _ = writeln!(body, "Net sales:       {net}");
+    let _ = writeln!(body, "Average order:   {average}");
+    let _ = writeln!(body, "Disputes opened: {}", totals.disputed);
+    let _ = writeln!(body, "Chargebacks:     {}", totals.chargebacks);
+    RenderedReport { subject: format!("{} {}", ReportKind::SalesSummary.title(), period_label(from, to)), body }
+}
+
+pub fn render_top_products(from: NaiveDate, to: NaiveDate, lines: &[ProductLine]) -> RenderedReport {
+    let mut body = format!("{} for {}\n\n", ReportKind::TopProducts.title(), period_label(from, to));
+    if lines.is_empty() {
+        body.push_str("No products were sold in this period.\n");
+    }
+    for (rank, line) in lines.iter().enumerate() {
+        let _ = writeln!(body, "{:>2}. {} - {} sold, {}", rank + 1, line.name, line.quantity, line.revenue);
+    }
+    RenderedReport { subject: format!("{} {}", ReportKind::TopProducts.title(), period_label(from, to)), body }
+}
+
+pub fn render_low_stock(as_of: NaiveDate, lines: &[StockLine]) -> RenderedReport {
+    let mut body = format!("{} as of {as_of}\n\n", ReportKind::LowStock.title());
+    if lines.is_empty() {
+        body.push_str("Every product is above its reorder threshold.\n");
+    }
+    for line in lines {
+        let _ = writeln!(
+            body,
+            "- {}: {} on hand (threshold {})",
+            line.name,
+            stock_units(line.quantity, line.uom.as_deref()),
+            stock_units(line.threshold.into(), line.uom.as_deref())
+        );
+    }
+    RenderedReport { subject: format!("{} {as_of}", ReportKind::LowStock.title()), body }
+}
+
+/// Load and render one report for a tenant. `from..=to` is the period; low stock ignores it and
+/// reports current stock.
+pub async fn render_report(db: &PgPool, tenant_id: Uuid, kind: ReportKind, from: NaiveDate, to: NaiveDate) -> sqlx::Result<RenderedReport> {
+    match kind {
+        ReportKind::SalesSummary => {
+            let totals = sqlx::query_as::<_, SalesTotals>(
+                "SELECT COALESCE(SUM(s.order_count), 0)::BIGINT AS order_count,
+                        COALESCE(SUM(s.total_sales), 0)::NUMERIC AS total_sales,
+                        COALESCE(SUM(s.refund_amount), 0)::NUMERIC AS refund_amount,
+                        COALESCE(SUM(s.refund_count), 0)::BIGINT AS refund_count,
+                        (SELECT COALESCE(SUM(opened_amount), 0) FROM daily_disputes
+                          WHERE tenant_id = $1 AND date BETWEEN $2 AND $3)::NUMERIC AS disputed,
+                        (SELECT COALESCE(SUM(lost_amount), 0) FROM daily_disputes
+                          WHERE tenant_id = $1 AND date BETWEEN $2 AND $3)::NUMERIC AS chargebacks
+                 FROM daily_sales s WHERE s.tenant_id = $1 AND s.date BETWEEN $2 AND $3",
+            )
+            .bind(tenant_id)
+            .bind(from)
+            .bind(to)
+            .fetch_one(db)
+            .await?;
+            Ok(render_sales_summary(from, to, &totals))
+        }
+        ReportKind::TopProducts => {
+            let lines = sqlx::query_as::<_, ProductLine>(
+                "SELECT COALESCE(p.name, d.product_id::text) AS name, SUM(d.quantity)::BIGINT AS quantity,
+                        SUM(d.revenue)::NUMERIC AS revenue
+                 FROM daily_product_sales d
+                 LEFT JOIN products p ON p.tenant_id = d.tenant_id AND p.id = d.product_id
+                 WHERE d.tenant_id = $1 AND d.date BETWEEN $2 AND $3
+                 GROUP BY d.product_id, p.name
+                 HAVING SUM(d.quantity) > 0
+                 ORDER BY SUM(d.quantity) DESC, SUM(d.revenue) DESC, name
+                 LIMIT $4",
+            )
+            .bind(tenant_id)
+            .bind(from)
+            .bind(to)
+            .bind(REPORT_ROW_LIMIT)
+            .fetch_all(db)
+            .await?;
+            Ok(render_top_products(from, to, &lines))
+        }
+        ReportKind::LowStock => {
+            let lines = sqlx::query_as::<_, StockLine>(
+                "SELECT COALESCE(p.name, i.product_id::text) AS name, SUM(i.quantity)::BIGINT AS quantity,
+                        MIN(i.threshold) AS threshold, p.uom
+                 FROM inventory_items i
+                 LEFT JOIN products p ON p.tenant_id = i.tenant_id AND p.id = i.product_id
+                 WHERE i.tenant_id = $1
+                 GROUP BY i.product_id, p.name, p.uom
+                 HAVING SUM(i.quantity) <= MIN(i.threshold)
+                 ORDER BY SUM(i.quantity) - MIN(i.threshold), name
+                 LIMIT $2",
+            )
+            .bind(tenant_id)
+            .bind(REPORT_ROW_LIMIT)
+            .fetch_all(db)
+            .await?;
+            Ok(render_low_stock(to + Duration::days(1), &lines))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn periods_end_the_day_before_sending() {
+        assert_eq!(report_period(Frequency::Daily, day("2026-03-02")), (day("2026-03-01"), day("2026-03-01")));
+        assert_eq!(report_period(Frequency::Weekly, day("2026-03-02")), (day("2026-02-23"), day("2026-03-01")));
+    }
+
+    #[test]
+    fn recipients_need_a_plausible_address() {
+        assert!(valid_email("owner@example.com"));
+        assert!(!valid_email("owner@example"));
+        assert!(!valid_email("@example.com"));
+        assert!(!valid_email("owner @example.com"));
+        assert!(!valid_email("owner@ex@ample.com"));
+        assert!(!valid_email("owner@.com"));
+    }
+
+    #[test]
+    fn reports_render_plain_text() {
+        let totals = SalesTotals {
+            order_count: 4,
+            total_sales: Money::from_cents(10_000),
+            refund_amount: Money::from_cents(1_000),
+            refund_count: 1,
+            ..Default::default()
+        };
+        let summary = render_sales_summary(day("2026-03-01"), day("2026-03-01"), &totals);
+        assert_eq!(summary.subject, "Sales summary 2026-03-01");
+        assert!(summary.body.contains("Net sales:       90.00"));
+        assert!(summary.body.contains("Average order:   25.00"));
+
+        let top = render_top_products(day("2026-02-23"), day("2026-03-01"), &[ProductLine { name: "Latte".into(), quantity: 12, revenue: Money::from_cents(5_400) }]);
+        assert_eq!(top.subject, "Top products 2026-02-23 to 2026-03-01");
+        assert!(top.body.contains(" 1. Latte - 12 sold, 54.00"));
+
+        let stock = render_low_stock(
+            day("2026-03-02"),
+            &[StockLine { name: "Coffee beans".into(), quantity: 1250, threshold: 2000, uom: Some("kg".into()) }],
+        );
+        assert!(stock.body.contains("- Coffee beans: 1.250 kg on hand (threshold 2.000 kg)"));
+        assert!(render_low_stock(day("2026-03-02"), &[]).body.contains("above its reorder threshold"));
+    }
+}
@@ -0,0 +1,14 @@
+//! Consumer side of the `customer.erased` contract: every payload customer-service publishes
+//! names the tenant and customer whose aggregates are dropped.
+
+use common_events::contract::decode_all;
+use common_events::CustomerErasedEvent;
+use uuid::Uuid;
+
+#[test]
+fn every_fixture_names_the_erased_customer() {
+    for (name, evt) in decode_all::<CustomerErasedEvent>() {
+        assert_ne!(evt.tenant_id, Uuid::nil(), "{name}");
+        assert_ne!(evt.customer_id, Uuid::nil(), "{name}");
+    }
+}
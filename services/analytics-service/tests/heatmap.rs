@@ -0,0 +1,101 @@
+//! The staffing heatmap against Postgres: hourly buckets land on the store's local weekday and
+//! hour across daylight saving changes, and local days bound the range. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use analytics_service::heatmap::{heatmap_cells, Heatmap};
+use analytics_service::projection::{apply_hourly_store_sales, SalesDelta};
+use chrono::{NaiveDate, TimeZone, Utc};
+use common_money::Money;
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service", "analytics-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+async fn store_in(db: &PgPool, tenant_id: Uuid, timezone: &str) -> Uuid {
+    let store_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO store_business_days (tenant_id, store_id, timezone) VALUES ($1, $2, $3)")
+        .bind(tenant_id)
+        .bind(store_id)
+        .bind(timezone)
+        .execute(db)
+        .await
+        .unwrap();
+    store_id
+}
+
+/// One sale of `cents` at the UTC time given.
+async fn sale(db: &PgPool, tenant_id: Uuid, location_id: Uuid, cents: i64, (m, d, h, min): (u32, u32, u32, u32)) {
+    let delta = SalesDelta {
+        tenant_id,
+        location_id,
+        employee_id: Uuid::nil(),
+        sales: Money::from_cents(cents),
+        orders: 1,
+        items: 1,
+        refunds: Money::default(),
+        refund_count: 0,
+    };
+    let at = Utc.with_ymd_and_hms(2026, m, d, h, min, 0).unwrap();
+    apply_hourly_store_sales(db, &delta, Some(at)).await.unwrap();
+}
+
+fn day(m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+}
+
+#[tokio::test]
+async fn local_hours_follow_daylight_saving_changes() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let new_york = store_in(db, tenant, "America/New_York").await;
+
+    // Sunday 2026-03-08: clocks jump from 02:00 EST to 03:00 EDT.
+    sale(db, tenant, new_york, 500, (3, 8, 6, 30)).await; // 01:30 EST
+    sale(db, tenant, new_york, 700, (3, 8, 7, 30)).await; // 03:30 EDT
+    sale(db, tenant, new_york, 900, (3, 7, 4, 30)).await; // Friday 23:30 EST, before the range
+    let spring = Heatmap::new(day(3, 7), day(3, 8), &heatmap_cells(db, tenant, Some(new_york), day(3, 7), day(3, 8)).await.unwrap());
+    let sunday = &spring.transactions[6];
+    assert_eq!((sunday[1], sunday[2], sunday[3]), (1, 0, 1));
+    assert_eq!(spring.revenue[6][3], Money::from_cents(700));
+    assert_eq!(spring.transactions.iter().flatten().sum::<i64>(), 2, "Friday night is outside the local range");
+
+    // Sunday 2026-11-01: 01:00-02:00 happens twice, first in EDT then in EST.
+    sale(db, tenant, new_york, 300, (11, 1, 5, 15)).await; // 01:15 EDT
+    sale(db, tenant, new_york, 400, (11, 1, 6, 15)).await; // 01:15 EST
+    sale(db, tenant, new_york, 100, (11, 1, 7, 15)).await; // 02:15 EST
+    let autumn = Heatmap::new(day(11, 1), day(11, 1), &heatmap_cells(db, tenant, Some(new_york), day(11, 1), day(11, 1)).await.unwrap());
+    assert_eq!((autumn.transactions[6][1], autumn.transactions[6][2]), (2, 1));
+    assert_eq!(autumn.revenue[6][1], Money::from_cents(700));
+    assert_eq!(autumn.days, [0, 0, 0, 0, 0, 0, 1]);
+}
+
+#[tokio::test]
+async fn every_store_is_read_in_its_own_time_zone() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let berlin = store_in(db, tenant, "Europe/Berlin").await;
+    let unconfigured = Uuid::new_v4();
+
+    // Monday 2026-10-12 at 12:05 UTC: 14:05 in Berlin (CEST), 12:05 for a store without settings.
+    sale(db, tenant, berlin, 1000, (10, 12, 12, 5)).await;
+    sale(db, tenant, unconfigured, 250, (10, 12, 12, 5)).await;
+    sale(db, tenant, berlin, 1000, (10, 12, 12, 55)).await;
+
+    let all = Heatmap::new(day(10, 12), day(10, 12), &heatmap_cells(db, tenant, None, day(10, 12), day(10, 12)).await.unwrap());
+    assert_eq!((all.transactions[0][14], all.transactions[0][12]), (2, 1));
+    assert_eq!(all.revenue[0][14], Money::from_cents(2000));
+
+    let one = Heatmap::new(day(10, 12), day(10, 12), &heatmap_cells(db, tenant, Some(unconfigured), day(10, 12), day(10, 12)).await.unwrap());
+    assert_eq!(one.transactions.iter().flatten().sum::<i64>(), 1);
+    assert!(heatmap_cells(db, Uuid::new_v4(), None, day(10, 12), day(10, 12)).await.unwrap().is_empty());
+}
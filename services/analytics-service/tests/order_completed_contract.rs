@@ -0,0 +1,41 @@
+//! Consumer side of the `order.completed` contract: every payload order-service publishes (and
+//! the legacy ones still in retention) decodes into the sales deltas the projection applies.
+
+use analytics_service::projection::SalesDelta;
+use common_events::contract::{decode_all, fixture};
+use common_events::{topics, OrderCompletedEvent};
+use common_money::Money;
+use uuid::Uuid;
+
+#[test]
+fn every_fixture_projects_a_sale_or_a_refund() {
+    for (name, evt) in decode_all::<OrderCompletedEvent>() {
+        let delta = SalesDelta::from_event(&evt);
+        assert_eq!(delta.tenant_id, evt.tenant_id, "{name}");
+        if evt.is_refund() {
+            assert_eq!((delta.orders, delta.refund_count, delta.sales), (0, 1, Money::default()), "{name}");
+            assert_eq!(delta.refunds, Money::new(evt.total.abs()), "{name}");
+        } else {
+            assert_eq!((delta.orders, delta.refund_count), (1, 0), "{name}");
+            assert_eq!(delta.sales, Money::new(evt.total.clone()), "{name}");
+            assert_eq!(delta.items, evt.items.iter().map(|item| item.quantity).sum::<i32>(), "{name}");
+        }
+    }
+}
+
+#[test]
+fn current_sales_are_booked_by_store_cashier_and_business_date() {
+    let evt = fixture(topics::ORDER_COMPLETED, "sale").decode::<OrderCompletedEvent>();
+    let delta = SalesDelta::from_event(&evt);
+    assert_eq!((Some(delta.location_id), Some(delta.employee_id)), (evt.location_id, evt.employee_id));
+    assert!(evt.business_date.is_some());
+}
+
+#[test]
+fn legacy_sales_fall_back_to_unattributed_rows() {
+    let evt = fixture(topics::ORDER_COMPLETED, "v1_legacy_sale").decode::<OrderCompletedEvent>();
+    let delta = SalesDelta::from_event(&evt);
+    assert_eq!((delta.location_id, delta.employee_id), (Uuid::nil(), Uuid::nil()));
+    assert_eq!(evt.business_date, None);
+    assert_eq!(delta.sales, Money::from_cents(900));
+}
@@ -3,6 +3,7 @@ use std::time::Duration;
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
 use axum::{Router, routing::get, http::StatusCode};
 use axum::extract::State;
+use common_config::{require_secret, KafkaSecurity};
 use rdkafka::consumer::{Consumer, StreamConsumer};
 use rdkafka::Message;
 use sqlx::PgPool;
@@ -77,8 +78,8 @@ async fn health() -> &'static str { "ok" }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    common_observability::init_logging("audit-consumer");
+    let database_url = require_secret("DATABASE_URL").await?;
     let db = PgPool::connect(&database_url).await?;
 
     // Ensure table exists (migration should have run in at least one service)
@@ -88,7 +89,9 @@ async fn main() -> anyhow::Result<()> {
 
     let enabled = env::var("AUDIT_CONSUMER_ENABLED").unwrap_or_else(|_| "true".into()) == "true";
     let consumer: Option<StreamConsumer> = if enabled {
-        let c: StreamConsumer = rdkafka::ClientConfig::new()
+        let c: StreamConsumer = KafkaSecurity::from_env()
+            .await?
+            .apply(&mut rdkafka::ClientConfig::new())
             .set("bootstrap.servers", &env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()))
             .set("group.id", env::var("AUDIT_CONSUMER_GROUP").unwrap_or("audit-consumer".into()))
             .set("enable.partition.eof", "false")
@@ -0,0 +1,3 @@
+-- Tracks consecutive lockouts so repeated lockouts escalate exponentially.
+ALTER TABLE users
+    ADD COLUMN lockout_count SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,39 @@
+CREATE TABLE tenant_identity_providers (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL UNIQUE REFERENCES tenants(id) ON DELETE CASCADE,
+    issuer TEXT NOT NULL,
+    client_id TEXT NOT NULL,
+    client_secret TEXT NOT NULL,
+    scopes TEXT NOT NULL DEFAULT 'openid email profile',
+    allowed_domains TEXT[] NOT NULL DEFAULT '{}',
+    default_role TEXT NOT NULL DEFAULT 'cashier' CHECK (default_role IN ('admin', 'manager', 'cashier')),
+    role_mappings JSONB NOT NULL DEFAULT '[]'::jsonb,
+    jit_provisioning BOOLEAN NOT NULL DEFAULT TRUE,
+    is_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_tenant_identity_providers_domains ON tenant_identity_providers USING GIN (allowed_domains);
+
+CREATE TABLE auth_user_identities (
+    provider_id UUID NOT NULL REFERENCES tenant_identity_providers(id) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ,
+    PRIMARY KEY (provider_id, subject)
+);
+
+CREATE INDEX idx_auth_user_identities_user ON auth_user_identities (user_id);
+
+CREATE TABLE auth_oidc_states (
+    state TEXT PRIMARY KEY,
+    provider_id UUID NOT NULL REFERENCES tenant_identity_providers(id) ON DELETE CASCADE,
+    nonce TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_auth_oidc_states_expires ON auth_oidc_states (expires_at);
@@ -0,0 +1,21 @@
+CREATE TABLE auth_sessions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id UUID NOT NULL REFERENCES tenants(id),
+    ip_address TEXT,
+    user_agent TEXT,
+    device_fingerprint TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_by UUID,
+    revoke_reason TEXT
+);
+
+CREATE INDEX idx_auth_sessions_user_active ON auth_sessions (user_id) WHERE revoked_at IS NULL;
+
+ALTER TABLE auth_refresh_tokens
+    ADD COLUMN session_id UUID REFERENCES auth_sessions(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_auth_refresh_tokens_session ON auth_refresh_tokens (session_id);
@@ -0,0 +1,20 @@
+ALTER TABLE tenants
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'suspended', 'archived')),
+    ADD COLUMN status_reason TEXT,
+    ADD COLUMN status_changed_at TIMESTAMPTZ,
+    ADD COLUMN status_changed_by UUID;
+
+CREATE INDEX idx_tenants_inactive ON tenants (status) WHERE status <> 'active';
+
+CREATE TABLE tenant_exports (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    requested_by UUID NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    error TEXT,
+    bundle JSONB
+);
+
+CREATE INDEX idx_tenant_exports_tenant ON tenant_exports (tenant_id, requested_at DESC);
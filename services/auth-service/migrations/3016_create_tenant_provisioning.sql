@@ -0,0 +1,19 @@
+CREATE TABLE tenant_provisioning (
+    tenant_id UUID PRIMARY KEY REFERENCES tenants(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    -- Per-service outcome: {"order-service": {"status": "completed", "detail": {...}}, ...}
+    steps JSONB NOT NULL DEFAULT '{}'::jsonb,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_tenant_provisioning_retry ON tenant_provisioning (next_attempt_at) WHERE status = 'failed';
+
+-- Tenants created before provisioning existed are treated as already provisioned.
+INSERT INTO tenant_provisioning (tenant_id, status, completed_at)
+SELECT id, 'completed', NOW() FROM tenants
+ON CONFLICT (tenant_id) DO NOTHING;
@@ -0,0 +1,4 @@
+-- Region a tenant's data must be stored in; NULL means unrestricted. Set at creation only, since
+-- moving a tenant between regions means migrating its data.
+ALTER TABLE tenants
+    ADD COLUMN residency TEXT CHECK (residency ~ '^[a-z0-9-]{2,16}$');
@@ -0,0 +1,25 @@
+-- Invitations let an admin add a user by email; the invitee sets their own password (and MFA
+-- when their role requires it) when redeeming the emailed token. Only the token's SHA-256 is
+-- stored.
+CREATE TABLE user_invitations (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    name TEXT,
+    role TEXT NOT NULL CHECK (role IN ('super_admin', 'admin', 'manager', 'cashier')),
+    token_hash TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'accepted', 'revoked')),
+    -- TOTP secret issued during redemption, moved to the user once the first code checks out.
+    mfa_pending_secret TEXT,
+    invited_by UUID,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    accepted_at TIMESTAMPTZ,
+    accepted_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_by UUID
+);
+
+-- One open invitation per address and tenant.
+CREATE UNIQUE INDEX uq_user_invitations_pending ON user_invitations (tenant_id, lower(email)) WHERE status = 'pending';
+CREATE INDEX idx_user_invitations_tenant ON user_invitations (tenant_id, created_at DESC);
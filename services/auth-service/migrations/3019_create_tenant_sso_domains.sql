@@ -0,0 +1,16 @@
+-- Each email domain routes to at most one tenant's IdP. Where tenants already share a domain,
+-- the earliest configured provider keeps it.
+CREATE TABLE tenant_sso_domains (
+    domain TEXT PRIMARY KEY,
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    provider_id UUID NOT NULL REFERENCES tenant_identity_providers(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_tenant_sso_domains_tenant ON tenant_sso_domains (tenant_id);
+
+INSERT INTO tenant_sso_domains (domain, tenant_id, provider_id)
+SELECT DISTINCT ON (lower(domain)) lower(domain), p.tenant_id, p.id
+FROM tenant_identity_providers p, unnest(p.allowed_domains) AS domain
+ORDER BY lower(domain), p.created_at
+ON CONFLICT (domain) DO NOTHING;
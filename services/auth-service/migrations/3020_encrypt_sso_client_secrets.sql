@@ -0,0 +1,4 @@
+-- Client secrets are sealed with AUTH_SSO_SECRET_KEY. Plaintext secrets left from before are
+-- sealed at startup and the plaintext column is cleared.
+ALTER TABLE tenant_identity_providers ADD COLUMN client_secret_encrypted BYTEA;
+ALTER TABLE tenant_identity_providers ALTER COLUMN client_secret DROP NOT NULL;
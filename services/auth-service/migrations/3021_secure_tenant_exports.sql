@@ -0,0 +1,10 @@
+-- Export bundles hold a tenant's full data set, so they are sealed with AUTH_TENANT_EXPORT_KEY,
+-- kept until expires_at and cleared once downloaded. Bundles stored in plaintext before this
+-- change are discarded; request a new export.
+ALTER TABLE tenant_exports
+    DROP COLUMN bundle,
+    ADD COLUMN bundle_encrypted BYTEA,
+    ADD COLUMN expires_at TIMESTAMPTZ,
+    ADD COLUMN downloaded_at TIMESTAMPTZ;
+
+CREATE INDEX idx_tenant_exports_expiry ON tenant_exports (expires_at) WHERE bundle_encrypted IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Principal that auth-service's own calls to other services (tenant exports, provisioning) are
+-- made as, instead of borrowing the requesting user's token. It is inactive and has no usable
+-- password, so it can never sign in.
+INSERT INTO users (id, tenant_id, name, email, role, password_hash, is_active)
+VALUES (
+  '00000000-0000-0000-0000-000000000102',
+  '00000000-0000-0000-0000-000000000001',
+  'auth-service',
+  'auth-service@system.novapos.local',
+  'super_admin',
+  '!',
+  FALSE
+)
+ON CONFLICT (id) DO NOTHING;
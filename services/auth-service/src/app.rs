@@ -7,10 +7,12 @@ use sqlx::PgPool;
 use tracing::warn;
 
 use crate::config::AuthConfig;
+use crate::login_guard::LoginThrottle;
 use crate::metrics::AuthMetrics;
 use crate::notifications::{
-    post_suspicious_webhook, publish_mfa_activity, KafkaProducer, MfaActivityEvent,
-    SuspiciousLoginPayload,
+    post_suspicious_webhook, publish_integration_key_event, publish_mfa_activity,
+    publish_tenant_event, IntegrationKeyEvent, KafkaProducer, MfaActivityEvent,
+    SuspiciousLoginPayload, TenantLifecycleEvent,
 };
 use crate::tokens::TokenSigner;
 
@@ -22,7 +24,10 @@ pub struct AppState {
     pub config: Arc<AuthConfig>,
     pub kafka_producer: Arc<dyn KafkaProducer>,
     pub http_client: Client,
+    /// Client for tenant identity providers; see [`crate::oidc::idp_client`].
+    pub idp_client: Client,
     pub metrics: Arc<AuthMetrics>,
+    pub login_throttle: Arc<LoginThrottle>,
 }
 
 impl FromRef<AppState> for Arc<JwtVerifier> {
@@ -52,6 +57,35 @@ impl AppState {
         self.metrics.mfa_event(event);
     }
 
+    /// Best-effort: the gateway's periodic refresh still converges if this publish fails.
+    pub async fn emit_integration_key_event(&self, event: IntegrationKeyEvent) {
+        if let Err(err) = publish_integration_key_event(
+            self.kafka_producer.as_ref(),
+            &self.config.integration_key_topic,
+            &event,
+        )
+        .await
+        {
+            warn!(
+                ?err,
+                tenant_id = %event.tenant_id,
+                key_id = %event.key_id,
+                action = event.action,
+                "Failed to publish integration key event",
+            );
+        }
+    }
+
+    /// Returns the publish error so callers can retry; unlike audit events these gate workflows.
+    pub async fn emit_tenant_event(&self, event: TenantLifecycleEvent) -> anyhow::Result<()> {
+        publish_tenant_event(
+            self.kafka_producer.as_ref(),
+            &self.config.tenant_events_topic,
+            &event,
+        )
+        .await
+    }
+
     pub async fn emit_mfa_activity(
         &self,
         event: MfaActivityEvent,
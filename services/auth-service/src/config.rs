@@ -1,8 +1,17 @@
 use anyhow::{anyhow, Context, Result};
-use std::collections::HashSet;
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_crypto::ColumnKey;
+use common_db::PoolSettings;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use uuid::Uuid;
 
+use crate::login_guard::LockoutPolicy;
+use crate::oidc::SsoConfig;
+use crate::password_policy::PasswordPolicy;
+use crate::webauthn::WebAuthnConfig;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CookieSameSite {
     Lax,
@@ -20,6 +29,32 @@ impl CookieSameSite {
     }
 }
 
+/// Second factors accepted for a role once MFA is enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MfaMethod {
+    Any,
+    Totp,
+    WebAuthn,
+}
+
+impl MfaMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MfaMethod::Any => "any",
+            MfaMethod::Totp => "totp",
+            MfaMethod::WebAuthn => "webauthn",
+        }
+    }
+
+    pub fn allows_totp(&self) -> bool {
+        matches!(self, MfaMethod::Any | MfaMethod::Totp)
+    }
+
+    pub fn allows_webauthn(&self) -> bool {
+        matches!(self, MfaMethod::Any | MfaMethod::WebAuthn)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthConfig {
     pub require_mfa: bool,
@@ -34,6 +69,34 @@ pub struct AuthConfig {
     pub refresh_cookie_domain: Option<String>,
     pub refresh_cookie_secure: bool,
     pub refresh_cookie_same_site: CookieSameSite,
+    pub integration_key_topic: String,
+    pub password_policy: PasswordPolicy,
+    pub lockout: LockoutPolicy,
+    pub mfa_role_methods: HashMap<String, MfaMethod>,
+    pub webauthn: WebAuthnConfig,
+    pub sso: SsoConfig,
+    /// `(service, base_url)` pairs queried for `GET /tenants/:id/export` during offboarding.
+    pub tenant_export_sources: Vec<(String, String)>,
+    /// Seals export bundles at rest; exports are refused without it.
+    pub tenant_export_key: Option<ColumnKey>,
+    /// How long an unread export bundle is kept.
+    pub tenant_export_ttl_hours: i64,
+    /// Shared secret services send in `X-Internal-Token` to read `/tenant-status` without a
+    /// super_admin token.
+    pub tenant_status_token: Option<String>,
+    /// Shared secret services send in `X-Internal-Token` to sync every tenant's capability policy.
+    pub capability_policy_token: Option<String>,
+    /// `(service, base_url)` pairs called with `POST /tenants/:id/provision` when a tenant is created.
+    pub tenant_provision_targets: Vec<(String, String)>,
+    pub tenant_events_topic: String,
+    /// Base URL of the service serving `GET /offline/bundle` content; offline bundles are
+    /// disabled when unset.
+    pub offline_bundle_source: Option<String>,
+    pub offline_bundle_ttl_seconds: i64,
+    /// Lifetime of user invitation tokens.
+    pub invite_ttl_hours: i64,
+    /// Page the invitation email links to (with `?token=`); the raw token is emailed when unset.
+    pub invite_accept_url: Option<String>,
 }
 
 impl AuthConfig {
@@ -56,6 +119,14 @@ impl AuthConfig {
         self.required_roles.contains(&role_key)
     }
 
+    /// Roles without an explicit entry in `AUTH_MFA_ROLE_METHODS` accept either factor.
+    pub fn mfa_method_for(&self, role: &str) -> MfaMethod {
+        self.mfa_role_methods
+            .get(&role.to_ascii_lowercase())
+            .copied()
+            .unwrap_or(MfaMethod::Any)
+    }
+
     pub fn required_roles_sorted(&self) -> Vec<String> {
         let mut roles = self.required_roles.iter().cloned().collect::<Vec<_>>();
         roles.sort();
@@ -113,6 +184,115 @@ pub fn load_auth_config() -> Result<AuthConfig> {
         .context("Failed to parse AUTH_REFRESH_COOKIE_SAMESITE")?
         .unwrap_or(CookieSameSite::Lax);
 
+    let integration_key_topic = env::var("INTEGRATION_KEY_EVENTS_TOPIC")
+        .unwrap_or_else(|_| "security.integration_keys.v1".to_string());
+
+    let password_policy =
+        PasswordPolicy::from_env().context("Failed to parse password policy settings")?;
+    let lockout = LockoutPolicy::from_env().context("Failed to parse lockout settings")?;
+
+    let mfa_role_methods = env::var("AUTH_MFA_ROLE_METHODS")
+        .ok()
+        .map(|value| parse_role_methods(&value))
+        .transpose()
+        .context("Failed to parse AUTH_MFA_ROLE_METHODS")?
+        .unwrap_or_default();
+
+    let webauthn = WebAuthnConfig {
+        rp_id: env::var("AUTH_WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string()),
+        rp_name: env::var("AUTH_WEBAUTHN_RP_NAME").unwrap_or_else(|_| mfa_issuer.clone()),
+        origins: env::var("AUTH_WEBAUTHN_ORIGINS")
+            .ok()
+            .map(|value| parse_origins(&value))
+            .unwrap_or_else(|| {
+                vec![
+                    "http://localhost:3000".to_string(),
+                    "http://localhost:3001".to_string(),
+                    "http://localhost:5173".to_string(),
+                ]
+            }),
+        timeout_ms: 60_000,
+        challenge_ttl_seconds: 300,
+    };
+
+    let sso_defaults = SsoConfig::default();
+    let sso = SsoConfig {
+        redirect_uri: env::var("AUTH_SSO_REDIRECT_URI").unwrap_or(sso_defaults.redirect_uri),
+        success_url: env::var("AUTH_SSO_SUCCESS_URL").unwrap_or(sso_defaults.success_url),
+        state_ttl_seconds: sso_defaults.state_ttl_seconds,
+        allow_insecure_loopback: bool_from_env("AUTH_SSO_ALLOW_INSECURE_LOOPBACK")
+            .unwrap_or(sso_defaults.allow_insecure_loopback),
+        secret_key: match env::var("AUTH_SSO_SECRET_KEY") {
+            Ok(value) if !value.trim().is_empty() => Some(
+                ColumnKey::from_base64(value.trim())
+                    .map_err(|err| anyhow!("Invalid AUTH_SSO_SECRET_KEY: {err}"))?,
+            ),
+            _ => None,
+        },
+    };
+
+    let tenant_export_sources = env::var("TENANT_EXPORT_SOURCES")
+        .ok()
+        .map(|value| parse_service_urls(&value))
+        .transpose()
+        .context("Failed to parse TENANT_EXPORT_SOURCES")?
+        .unwrap_or_default();
+    let tenant_export_key = match env::var("AUTH_TENANT_EXPORT_KEY") {
+        Ok(value) if !value.trim().is_empty() => Some(
+            ColumnKey::from_base64(value.trim())
+                .map_err(|err| anyhow!("Invalid AUTH_TENANT_EXPORT_KEY: {err}"))?,
+        ),
+        _ => None,
+    };
+    let tenant_export_ttl_hours = match env::var("TENANT_EXPORT_TTL_HOURS") {
+        Ok(value) => value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|ttl| *ttl > 0)
+            .context("TENANT_EXPORT_TTL_HOURS must be a positive number of hours")?,
+        Err(_) => 24,
+    };
+    let tenant_status_token = env::var("AUTH_TENANT_STATUS_TOKEN")
+        .ok()
+        .and_then(|value| normalize_optional(&value));
+    let capability_policy_token = env::var("AUTH_CAPABILITY_POLICY_TOKEN")
+        .ok()
+        .and_then(|value| normalize_optional(&value));
+    let tenant_provision_targets = env::var("TENANT_PROVISION_TARGETS")
+        .ok()
+        .map(|value| parse_service_urls(&value))
+        .transpose()
+        .context("Failed to parse TENANT_PROVISION_TARGETS")?
+        .unwrap_or_default();
+    let tenant_events_topic =
+        env::var("TENANT_EVENTS_TOPIC").unwrap_or_else(|_| "tenant.lifecycle.v1".to_string());
+    let offline_bundle_source = env::var("OFFLINE_BUNDLE_SOURCE_URL")
+        .ok()
+        .and_then(|value| normalize_optional(&value))
+        .map(|value| value.trim_end_matches('/').to_string());
+    let offline_bundle_ttl_seconds = match env::var("OFFLINE_BUNDLE_TTL_SECONDS") {
+        Ok(value) => value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|ttl| *ttl > 0)
+            .context("OFFLINE_BUNDLE_TTL_SECONDS must be a positive number of seconds")?,
+        Err(_) => 86_400,
+    };
+    let invite_ttl_hours = match env::var("INVITE_TTL_HOURS") {
+        Ok(value) => value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|ttl| *ttl > 0)
+            .context("INVITE_TTL_HOURS must be a positive number of hours")?,
+        Err(_) => 72,
+    };
+    let invite_accept_url = env::var("INVITE_ACCEPT_URL")
+        .ok()
+        .and_then(|value| normalize_optional(&value));
+
     Ok(AuthConfig {
         require_mfa,
         required_roles,
@@ -126,9 +306,75 @@ pub fn load_auth_config() -> Result<AuthConfig> {
         refresh_cookie_domain,
         refresh_cookie_secure,
         refresh_cookie_same_site,
+        integration_key_topic,
+        password_policy,
+        lockout,
+        mfa_role_methods,
+        webauthn,
+        sso,
+        tenant_export_sources,
+        tenant_export_key,
+        tenant_export_ttl_hours,
+        tenant_status_token,
+        capability_policy_token,
+        tenant_provision_targets,
+        tenant_events_topic,
+        offline_bundle_source,
+        offline_bundle_ttl_seconds,
+        invite_ttl_hours,
+        invite_accept_url,
     })
 }
 
+/// Listener, database, Kafka, JWT and token settings `main` reads before building [`AuthConfig`].
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    /// `KAFKA_BOOTSTRAP`, falling back to `KAFKA_BROKERS`.
+    pub kafka_bootstrap: String,
+    pub jwt: JwtSettings,
+    pub token_access_ttl_seconds: i64,
+    pub token_refresh_ttl_seconds: i64,
+    /// `JWT_DEV_PRIVATE_KEY_PEM`, used when no signing key is stored yet.
+    pub dev_private_key_pem: Option<Redacted<String>>,
+}
+
+impl StartupConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8085);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_brokers = env.or("KAFKA_BROKERS", "localhost:9092".to_string());
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", kafka_brokers);
+        let jwt = JwtSettings::read(&mut env).await;
+        let token_access_ttl_seconds: i64 = env.or("TOKEN_ACCESS_TTL_SECONDS", 900);
+        env.check("TOKEN_ACCESS_TTL_SECONDS", token_access_ttl_seconds > 0, "must be greater than 0");
+        let token_refresh_ttl_seconds: i64 = env.or("TOKEN_REFRESH_TTL_SECONDS", 2_592_000);
+        env.check(
+            "TOKEN_REFRESH_TTL_SECONDS",
+            token_refresh_ttl_seconds > token_access_ttl_seconds,
+            "must be longer than TOKEN_ACCESS_TTL_SECONDS",
+        );
+        let dev_private_key_pem = env.secret("JWT_DEV_PRIVATE_KEY_PEM").await;
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                jwt: jwt?,
+                token_access_ttl_seconds,
+                token_refresh_ttl_seconds,
+                dev_private_key_pem,
+            })
+        })
+    }
+}
+
 fn bool_from_env(key: &str) -> Option<bool> {
     env::var(key).ok().map(|value| {
         matches!(
@@ -160,6 +406,59 @@ fn default_roles() -> HashSet<String> {
     ])
 }
 
+/// Parses `role:method` pairs, e.g. `super_admin:webauthn,manager:any`.
+fn parse_role_methods(value: &str) -> Result<HashMap<String, MfaMethod>> {
+    let mut methods = HashMap::new();
+    for item in value.split([',', ';', ' ']) {
+        let trimmed = item.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let (role, method) = trimmed
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Expected role:method but found '{trimmed}'"))?;
+        let method = match method.trim().to_ascii_lowercase().as_str() {
+            "any" => MfaMethod::Any,
+            "totp" => MfaMethod::Totp,
+            "webauthn" | "passkey" => MfaMethod::WebAuthn,
+            other => {
+                return Err(anyhow!(
+                    "Unsupported MFA method '{other}'. Use any, totp, or webauthn."
+                ))
+            }
+        };
+        methods.insert(role.trim().to_ascii_lowercase(), method);
+    }
+    Ok(methods)
+}
+
+fn parse_origins(value: &str) -> Vec<String> {
+    value
+        .split([',', ' '])
+        .map(|item| item.trim().trim_end_matches('/').to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parses `service=url` pairs, e.g. `order-service=http://order-service:8084`.
+fn parse_service_urls(value: &str) -> Result<Vec<(String, String)>> {
+    let mut sources = Vec::new();
+    for item in value.split([',', ';', ' ']) {
+        let trimmed = item.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let (service, url) = trimmed
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected service=url but found '{trimmed}'"))?;
+        sources.push((
+            service.trim().to_string(),
+            url.trim().trim_end_matches('/').to_string(),
+        ));
+    }
+    Ok(sources)
+}
+
 fn parse_tenant_list(value: &str) -> Result<HashSet<Uuid>> {
     let mut tenants = HashSet::new();
     for item in value.split([',', ';', ' ']) {
@@ -216,4 +515,28 @@ mod tests {
         assert!(roles.contains("manager"));
         assert!(!roles.contains("Admin"));
     }
+
+    #[test]
+    fn parse_role_methods_accepts_aliases_and_rejects_unknown() {
+        let methods = parse_role_methods("Super_Admin:webauthn, manager:TOTP cashier:passkey").unwrap();
+        assert_eq!(methods.get("super_admin"), Some(&MfaMethod::WebAuthn));
+        assert_eq!(methods.get("manager"), Some(&MfaMethod::Totp));
+        assert_eq!(methods.get("cashier"), Some(&MfaMethod::WebAuthn));
+        assert!(parse_role_methods("admin:sms").is_err());
+        assert!(parse_role_methods("admin").is_err());
+    }
+
+    #[test]
+    fn parse_service_urls_splits_pairs() {
+        let sources =
+            parse_service_urls("order-service=http://order:8084/, customer-service=http://customer:8089").unwrap();
+        assert_eq!(
+            sources,
+            vec![
+                ("order-service".to_string(), "http://order:8084".to_string()),
+                ("customer-service".to_string(), "http://customer:8089".to_string()),
+            ]
+        );
+        assert!(parse_service_urls("order-service").is_err());
+    }
 }
@@ -0,0 +1,587 @@
+//! User invitations. An admin invites an email address with a role; the invitee gets a signed,
+//! time-limited token by email (`notification.email.requested`) and redeems it by choosing a
+//! password, plus a TOTP code when their role requires MFA. The account only exists once the
+//! invitation is accepted.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use common_auth::{AuthContext, TenantStatus};
+use common_events::{DomainEvent, EmailRequestedEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::mfa::{build_otpauth_uri, generate_totp_secret, normalize_mfa_code, verify_totp_code};
+use crate::notifications::MfaActivityEvent;
+use crate::tenant_lifecycle_handlers::tenant_status;
+use crate::user_handlers::{
+    ensure_role_any, ensure_tenant_access, extract_tenant_id, hash_password, validate_password,
+    validate_role, User,
+};
+use crate::AppState;
+
+/// Audience of invitation tokens, so they can never pass as access tokens.
+pub const INVITE_AUDIENCE: &str = "novapos-invite";
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub email: String,
+    pub name: Option<String>,
+    pub role: String,
+    /// `pending`, `accepted`, `revoked`, or `expired` for pending invitations past `expires_at`.
+    pub status: String,
+    pub invited_by: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub accepted_user_id: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+const INVITATION_COLUMNS: &str = "id, tenant_id, email, name, role,
+    CASE WHEN status = 'pending' AND expires_at <= NOW() THEN 'expired' ELSE status END AS status,
+    invited_by, expires_at, created_at, accepted_at, accepted_user_id, revoked_at";
+
+#[derive(Debug, Serialize)]
+pub struct InvitationResponse {
+    #[serde(flatten)]
+    pub invitation: Invitation,
+    /// False when the email could not be handed to the notification path; resend it later.
+    pub email_sent: bool,
+}
+
+#[derive(Deserialize)]
+pub struct NewInvitation {
+    pub email: String,
+    pub role: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct InvitationListQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationTokenRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+    pub password: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, alias = "mfaCode")]
+    pub mfa_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct InvitationMfaResponse {
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+#[derive(Serialize)]
+struct InviteClaims<'a> {
+    /// Unique per issue, so a resent invitation never reproduces an earlier token.
+    jti: Uuid,
+    invitation_id: Uuid,
+    tid: Uuid,
+    email: &'a str,
+}
+
+#[derive(FromRow)]
+struct OpenInvitation {
+    id: Uuid,
+    tenant_id: Uuid,
+    email: String,
+    name: Option<String>,
+    role: String,
+    mfa_pending_secret: Option<String>,
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+pub(crate) fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+fn invalid_token() -> (StatusCode, String) {
+    (
+        StatusCode::NOT_FOUND,
+        "Invitation is invalid, expired or already used".to_string(),
+    )
+}
+
+/// Signs a fresh token for the invitation and stores its hash, replacing any earlier one.
+async fn issue_token(
+    state: &AppState,
+    invitation_id: Uuid,
+    tenant_id: Uuid,
+    email: &str,
+) -> Result<(String, DateTime<Utc>), (StatusCode, String)> {
+    let ttl_seconds = state.config.invite_ttl_hours * 3600;
+    let token = state
+        .token_signer
+        .sign_document(
+            INVITE_AUDIENCE,
+            ttl_seconds,
+            &InviteClaims { jti: Uuid::new_v4(), invitation_id, tid: tenant_id, email },
+        )
+        .map_err(|err| {
+            error!(invitation_id = %invitation_id, error = %err, "Failed to sign invitation");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unable to sign invitation".to_string(),
+            )
+        })?;
+    let expires_at = Utc::now() + Duration::seconds(ttl_seconds);
+    sqlx::query("UPDATE user_invitations SET token_hash = $2, expires_at = $3 WHERE id = $1")
+        .bind(invitation_id)
+        .bind(token_hash(&token))
+        .bind(expires_at)
+        .execute(&state.db)
+        .await
+        .map_err(db_error)?;
+    Ok((token, expires_at))
+}
+
+fn invitation_email(state: &AppState, invitation: &Invitation, token: &str) -> (String, String) {
+    let link = match &state.config.invite_accept_url {
+        Some(base) => format!("{base}?token={}", urlencoding::encode(token)),
+        None => format!("Invitation code: {token}"),
+    };
+    let greeting = invitation
+        .name
+        .as_deref()
+        .map(|name| format!("Hi {name},"))
+        .unwrap_or_else(|| "Hi,".to_string());
+    let subject = "You're invited to NovaPOS".to_string();
+    let body = format!(
+        "{greeting}\n\nYou've been invited to join NovaPOS as {}. Accept the invitation to choose your password:\n\n{link}\n\nThe invitation expires on {}. If you weren't expecting it, you can ignore this email.\n",
+        invitation.role,
+        invitation.expires_at.format("%Y-%m-%d %H:%M UTC"),
+    );
+    (subject, body)
+}
+
+/// Hands the invitation email to the notification path. Failures are logged and reported back
+/// to the admin rather than failing the request, since the invitation can be resent.
+async fn send_invitation_email(state: &AppState, invitation: &Invitation, token: &str) -> bool {
+    let (subject, body) = invitation_email(state, invitation, token);
+    let event = EmailRequestedEvent {
+        schema_version: EmailRequestedEvent::SCHEMA_VERSION,
+        tenant_id: invitation.tenant_id,
+        message_id: Uuid::new_v4(),
+        recipients: vec![invitation.email.clone()],
+        subject,
+        body,
+        source: "auth.user_invitation".to_string(),
+    };
+    let sent = match common_events::encode(&event) {
+        Ok(payload) => state
+            .kafka_producer
+            .send(EmailRequestedEvent::TOPIC, &event.partition_key(), payload)
+            .await
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    if let Err(err) = &sent {
+        warn!(invitation_id = %invitation.id, tenant_id = %invitation.tenant_id, error = %err, "Failed to send invitation email");
+    }
+    sent.is_ok()
+}
+
+async fn emit_invitation_event(
+    state: &AppState,
+    action: &'static str,
+    invitation: &Invitation,
+    actor: Option<Uuid>,
+) {
+    state.metrics.invitation_event(action);
+    let event = MfaActivityEvent {
+        action,
+        severity: "info",
+        tenant_id: invitation.tenant_id,
+        user_id: actor,
+        trace_id: Uuid::new_v4(),
+        occurred_at: Utc::now(),
+        ip: None,
+        user_agent: None,
+        device: None,
+        role: Some(invitation.role.clone()),
+        detail: Some(
+            json!({
+                "invitation_id": invitation.id,
+                "email": invitation.email,
+                "accepted_user_id": invitation.accepted_user_id,
+            })
+            .to_string(),
+        ),
+    };
+    state.emit_mfa_activity(event, None).await;
+}
+
+async fn load_invitation(
+    state: &AppState,
+    tenant_id: Uuid,
+    invitation_id: Uuid,
+) -> Result<Invitation, (StatusCode, String)> {
+    sqlx::query_as::<_, Invitation>(&format!(
+        "SELECT {INVITATION_COLUMNS} FROM user_invitations WHERE id = $1 AND tenant_id = $2"
+    ))
+    .bind(invitation_id)
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Invitation not found".to_string()))
+}
+
+/// The pending, unexpired invitation a token belongs to.
+async fn open_invitation(state: &AppState, token: &str) -> Result<OpenInvitation, (StatusCode, String)> {
+    if token.trim().is_empty() {
+        return Err(invalid_token());
+    }
+    sqlx::query_as::<_, OpenInvitation>(
+        "SELECT id, tenant_id, email, name, role, mfa_pending_secret
+         FROM user_invitations
+         WHERE token_hash = $1 AND status = 'pending' AND expires_at > NOW()",
+    )
+    .bind(token_hash(token))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(invalid_token)
+}
+
+fn mfa_required(state: &AppState, invitation: &OpenInvitation) -> bool {
+    state.config.should_enforce_for(&invitation.role, invitation.tenant_id, false)
+        && state.config.mfa_method_for(&invitation.role).allows_totp()
+}
+
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Json(payload): Json<NewInvitation>,
+) -> Result<(StatusCode, Json<InvitationResponse>), (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let email = payload.email.trim();
+    if email.is_empty() || !email.contains('@') {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A valid email address is required".to_string(),
+        ));
+    }
+    let role = payload.role.trim();
+    validate_role(role)?;
+    if role == "super_admin" && !auth.has_role("super_admin") {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only super admins can invite super admins".to_string(),
+        ));
+    }
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+
+    let existing_user: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE tenant_id = $1 AND lower(email) = lower($2))",
+    )
+    .bind(tenant_id)
+    .bind(email)
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+    if existing_user {
+        return Err((
+            StatusCode::CONFLICT,
+            "A user with this email already exists".to_string(),
+        ));
+    }
+
+    // Pending invitations past their expiry no longer block a new one.
+    sqlx::query(
+        "UPDATE user_invitations SET status = 'revoked', revoked_at = NOW()
+         WHERE tenant_id = $1 AND lower(email) = lower($2) AND status = 'pending' AND expires_at <= NOW()",
+    )
+    .bind(tenant_id)
+    .bind(email)
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let invitation_id = Uuid::new_v4();
+    let inserted = sqlx::query(
+        "INSERT INTO user_invitations (id, tenant_id, email, name, role, token_hash, invited_by, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())",
+    )
+    .bind(invitation_id)
+    .bind(tenant_id)
+    .bind(email)
+    .bind(name)
+    .bind(role)
+    // Placeholder until the signed token is issued below; it can never match a real token's hash.
+    .bind(format!("pending:{invitation_id}"))
+    .bind(auth.claims.subject)
+    .execute(&state.db)
+    .await;
+    match inserted {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+            return Err((
+                StatusCode::CONFLICT,
+                "An invitation for this email is already pending".to_string(),
+            ));
+        }
+        Err(err) => return Err(db_error(err)),
+    }
+
+    let (token, _) = issue_token(&state, invitation_id, tenant_id, email).await?;
+    let invitation = load_invitation(&state, tenant_id, invitation_id).await?;
+    let email_sent = send_invitation_email(&state, &invitation, &token).await;
+    emit_invitation_event(&state, "user.invitation.created", &invitation, Some(auth.claims.subject)).await;
+    info!(invitation_id = %invitation_id, tenant_id = %tenant_id, role = %invitation.role, email_sent, "User invited");
+
+    Ok((StatusCode::CREATED, Json(InvitationResponse { invitation, email_sent })))
+}
+
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Query(query): Query<InvitationListQuery>,
+) -> Result<Json<Vec<Invitation>>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin", "manager"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let status = query.status.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(status) = status {
+        if !matches!(status, "pending" | "accepted" | "revoked" | "expired") {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "status must be pending, accepted, revoked or expired".to_string(),
+            ));
+        }
+    }
+    let invitations = sqlx::query_as::<_, Invitation>(&format!(
+        "SELECT * FROM (SELECT {INVITATION_COLUMNS} FROM user_invitations WHERE tenant_id = $1) invitations
+         WHERE $2::text IS NULL OR status = $2
+         ORDER BY created_at DESC"
+    ))
+    .bind(tenant_id)
+    .bind(status)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+    Ok(Json(invitations))
+}
+
+/// Issues a new token for a pending (or expired) invitation and emails it again; earlier tokens
+/// stop working.
+pub async fn resend_invitation(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(invitation_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<InvitationResponse>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let invitation = load_invitation(&state, tenant_id, invitation_id).await?;
+    if !matches!(invitation.status.as_str(), "pending" | "expired") {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Invitation is already {}", invitation.status),
+        ));
+    }
+    let (token, _) = issue_token(&state, invitation_id, tenant_id, &invitation.email).await?;
+    let invitation = load_invitation(&state, tenant_id, invitation_id).await?;
+    let email_sent = send_invitation_email(&state, &invitation, &token).await;
+    emit_invitation_event(&state, "user.invitation.resent", &invitation, Some(auth.claims.subject)).await;
+    Ok(Json(InvitationResponse { invitation, email_sent }))
+}
+
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(invitation_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let invitation = load_invitation(&state, tenant_id, invitation_id).await?;
+    if invitation.status == "accepted" {
+        return Err((
+            StatusCode::CONFLICT,
+            "Invitation was already accepted; deactivate the user instead".to_string(),
+        ));
+    }
+    if invitation.status == "revoked" {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    sqlx::query(
+        "UPDATE user_invitations SET status = 'revoked', revoked_at = NOW(), revoked_by = $3
+         WHERE id = $1 AND tenant_id = $2 AND status = 'pending'",
+    )
+    .bind(invitation_id)
+    .bind(tenant_id)
+    .bind(auth.claims.subject)
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?;
+    emit_invitation_event(&state, "user.invitation.revoked", &invitation, Some(auth.claims.subject)).await;
+    info!(invitation_id = %invitation_id, tenant_id = %tenant_id, "Invitation revoked");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Starts TOTP enrollment for the invitee. Required before accepting when the invited role must
+/// use MFA; calling it again replaces the secret.
+pub async fn begin_invitation_mfa(
+    State(state): State<AppState>,
+    Json(payload): Json<InvitationTokenRequest>,
+) -> Result<Json<InvitationMfaResponse>, (StatusCode, String)> {
+    let invitation = open_invitation(&state, &payload.token).await?;
+    let secret = generate_totp_secret();
+    let account_label = format!("{} ({})", invitation.email, invitation.tenant_id);
+    let otpauth_url = build_otpauth_uri(&state.config.mfa_issuer, &account_label, &secret);
+    sqlx::query("UPDATE user_invitations SET mfa_pending_secret = $2 WHERE id = $1")
+        .bind(invitation.id)
+        .bind(&secret)
+        .execute(&state.db)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(InvitationMfaResponse { secret, otpauth_url }))
+}
+
+/// Redeems an invitation: creates the active user with the chosen password and, when enrolled,
+/// the TOTP secret.
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<(StatusCode, Json<User>), (StatusCode, String)> {
+    let invitation = open_invitation(&state, &payload.token).await?;
+    let status = tenant_status(&state, invitation.tenant_id).await.map_err(db_error)?;
+    if status != TenantStatus::Active {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "This organisation is not active".to_string(),
+        ));
+    }
+
+    let name = payload
+        .name
+        .as_deref()
+        .or(invitation.name.as_deref())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or((StatusCode::BAD_REQUEST, "Name must not be empty".to_string()))?
+        .to_string();
+    validate_password(&state.config, &payload.password, Some(&invitation.email))?;
+
+    let mfa_secret = match (invitation.mfa_pending_secret.as_deref(), payload.mfa_code.as_deref()) {
+        (Some(secret), Some(code)) => {
+            let code = normalize_mfa_code(code)
+                .ok_or((StatusCode::BAD_REQUEST, "MFA code must be 6 digits".to_string()))?;
+            if !verify_totp_code(secret, &code) {
+                return Err((StatusCode::UNAUTHORIZED, "Invalid MFA code".to_string()));
+            }
+            Some(secret.to_string())
+        }
+        _ if mfa_required(&state, &invitation) => {
+            return Err((
+                StatusCode::PRECONDITION_REQUIRED,
+                "This role requires MFA: start enrollment and include mfa_code".to_string(),
+            ));
+        }
+        _ => None,
+    };
+    let password_hash = hash_password(&payload.password)?;
+
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    // Locks the invitation so a token can only be redeemed once.
+    let claimed = sqlx::query(
+        "UPDATE user_invitations SET status = 'accepted', accepted_at = NOW(), mfa_pending_secret = NULL
+         WHERE id = $1 AND status = 'pending' AND expires_at > NOW()",
+    )
+    .bind(invitation.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    if claimed.rows_affected() == 0 {
+        return Err(invalid_token());
+    }
+    let user_id = Uuid::new_v4();
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (id, tenant_id, name, email, role, password_hash, is_active, last_password_reset, force_password_reset, mfa_secret, mfa_enrolled_at)
+         VALUES ($1, $2, $3, $4, $5, $6, TRUE, NOW(), FALSE, $7, CASE WHEN $7::text IS NULL THEN NULL ELSE NOW() END)
+         RETURNING id, tenant_id, name, email, role, is_active, created_at, updated_at, last_password_reset, force_password_reset",
+    )
+    .bind(user_id)
+    .bind(invitation.tenant_id)
+    .bind(&name)
+    .bind(&invitation.email)
+    .bind(&invitation.role)
+    .bind(password_hash)
+    .bind(mfa_secret.as_deref())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(db) if db.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "A user with this email already exists".to_string(),
+        ),
+        err => db_error(err),
+    })?;
+    sqlx::query("UPDATE user_invitations SET accepted_user_id = $2 WHERE id = $1")
+        .bind(invitation.id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    let accepted = load_invitation(&state, invitation.tenant_id, invitation.id).await?;
+    emit_invitation_event(&state, "user.invitation.accepted", &accepted, Some(user_id)).await;
+    info!(invitation_id = %invitation.id, tenant_id = %invitation.tenant_id, user_id = %user_id, mfa_enrolled = mfa_secret.is_some(), "Invitation accepted");
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_hash_ignores_surrounding_whitespace() {
+        assert_eq!(token_hash("abc.def.ghi"), token_hash("  abc.def.ghi\n"));
+        assert_ne!(token_hash("abc.def.ghi"), token_hash("abc.def.ghj"));
+        assert_eq!(token_hash("abc").len(), 64);
+    }
+}
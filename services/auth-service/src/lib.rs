@@ -1,11 +1,23 @@
 pub mod app;
 pub mod config;
+pub mod invitation_handlers;
+pub mod login_guard;
 pub mod metrics;
 pub mod mfa;
 pub mod mfa_handlers;
 pub mod notifications;
+pub mod oidc;
+pub mod offline_bundle_handlers;
+pub mod oidc_handlers;
+pub mod password_policy;
+pub mod policy_handlers;
+pub mod session_handlers;
 pub mod tenant_handlers;
+pub mod tenant_lifecycle_handlers;
+pub mod tenant_provisioning_handlers;
 pub mod tokens;
 pub mod user_handlers;
+pub mod webauthn;
+pub mod webauthn_handlers;
 
 pub use app::AppState;
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration as StdDuration, Instant};
+
+/// Per-account lockout settings. Each consecutive lockout doubles the lock duration
+/// (capped at `max_lockout_minutes`) until the user signs in successfully or an admin unlocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    pub max_failed_attempts: i16,
+    pub base_lockout_minutes: i64,
+    pub max_lockout_minutes: i64,
+    pub ip_max_failures: usize,
+    pub ip_window_seconds: u64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            base_lockout_minutes: 15,
+            max_lockout_minutes: 24 * 60,
+            ip_max_failures: 20,
+            ip_window_seconds: 300,
+        }
+    }
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+        let policy = Self {
+            max_failed_attempts: parse_env("AUTH_LOCKOUT_MAX_FAILED_ATTEMPTS")?
+                .unwrap_or(defaults.max_failed_attempts),
+            base_lockout_minutes: parse_env("AUTH_LOCKOUT_BASE_MINUTES")?
+                .unwrap_or(defaults.base_lockout_minutes),
+            max_lockout_minutes: parse_env("AUTH_LOCKOUT_MAX_MINUTES")?
+                .unwrap_or(defaults.max_lockout_minutes),
+            ip_max_failures: parse_env("AUTH_LOGIN_IP_MAX_FAILURES")?
+                .unwrap_or(defaults.ip_max_failures),
+            ip_window_seconds: parse_env("AUTH_LOGIN_IP_WINDOW_SECONDS")?
+                .unwrap_or(defaults.ip_window_seconds),
+        };
+        if policy.max_failed_attempts < 1 {
+            return Err(anyhow!("AUTH_LOCKOUT_MAX_FAILED_ATTEMPTS must be at least 1"));
+        }
+        if policy.base_lockout_minutes < 1 || policy.max_lockout_minutes < policy.base_lockout_minutes {
+            return Err(anyhow!(
+                "AUTH_LOCKOUT_BASE_MINUTES must be >= 1 and <= AUTH_LOCKOUT_MAX_MINUTES"
+            ));
+        }
+        Ok(policy)
+    }
+
+    /// Lock duration for the next lockout given how many lockouts preceded it.
+    pub fn lockout_duration(&self, previous_lockouts: i16) -> Duration {
+        let exponent = previous_lockouts.clamp(0, 16) as u32;
+        let minutes = self
+            .base_lockout_minutes
+            .saturating_mul(1_i64 << exponent)
+            .min(self.max_lockout_minutes);
+        Duration::minutes(minutes)
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(key: &str) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    match env::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse::<T>()
+            .map(Some)
+            .map_err(|err| anyhow!("Invalid {key} '{value}': {err}")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Sliding-window counter of failed logins per client IP, used to slow down
+/// credential stuffing that spreads attempts across many accounts.
+pub struct LoginThrottle {
+    max_failures: usize,
+    window: StdDuration,
+    failures: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl LoginThrottle {
+    pub fn new(max_failures: usize, window_seconds: u64) -> Self {
+        Self {
+            max_failures,
+            window: StdDuration::from_secs(window_seconds.max(1)),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_policy(policy: &LockoutPolicy) -> Self {
+        Self::new(policy.ip_max_failures, policy.ip_window_seconds)
+    }
+
+    /// Returns the seconds until the client may retry when it is currently throttled.
+    pub fn check(&self, ip: &str) -> Option<u64> {
+        self.check_at(ip, Instant::now())
+    }
+
+    pub fn record_failure(&self, ip: &str) {
+        self.record_failure_at(ip, Instant::now());
+    }
+
+    pub fn clear(&self, ip: &str) {
+        self.failures.lock().unwrap().remove(ip);
+    }
+
+    fn check_at(&self, ip: &str, now: Instant) -> Option<u64> {
+        if self.max_failures == 0 {
+            return None;
+        }
+        let mut guard = self.failures.lock().unwrap();
+        let entries = guard.get_mut(ip)?;
+        Self::prune(entries, now, self.window);
+        if entries.len() < self.max_failures {
+            return None;
+        }
+        let oldest = *entries.front()?;
+        let retry_after = self.window.saturating_sub(now.duration_since(oldest));
+        Some(retry_after.as_secs().max(1))
+    }
+
+    fn record_failure_at(&self, ip: &str, now: Instant) {
+        if self.max_failures == 0 {
+            return;
+        }
+        let mut guard = self.failures.lock().unwrap();
+        // Opportunistically drop idle IPs so the map does not grow without bound.
+        if guard.len() > 10_000 {
+            let window = self.window;
+            guard.retain(|_, entries| {
+                Self::prune(entries, now, window);
+                !entries.is_empty()
+            });
+        }
+        let entries = guard.entry(ip.to_string()).or_default();
+        Self::prune(entries, now, self.window);
+        entries.push_back(now);
+    }
+
+    fn prune(entries: &mut VecDeque<Instant>, now: Instant, window: StdDuration) {
+        while let Some(front) = entries.front() {
+            if now.duration_since(*front) >= window {
+                entries.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockout_duration_doubles_and_caps() {
+        let policy = LockoutPolicy::default();
+        assert_eq!(policy.lockout_duration(0), Duration::minutes(15));
+        assert_eq!(policy.lockout_duration(1), Duration::minutes(30));
+        assert_eq!(policy.lockout_duration(3), Duration::minutes(120));
+        assert_eq!(policy.lockout_duration(10), Duration::minutes(24 * 60));
+    }
+
+    #[test]
+    fn throttle_blocks_after_threshold_and_recovers_after_window() {
+        let throttle = LoginThrottle::new(3, 60);
+        let start = Instant::now();
+        for _ in 0..2 {
+            throttle.record_failure_at("10.0.0.1", start);
+        }
+        assert!(throttle.check_at("10.0.0.1", start).is_none());
+        throttle.record_failure_at("10.0.0.1", start);
+        assert_eq!(throttle.check_at("10.0.0.1", start), Some(60));
+        assert!(throttle.check_at("10.0.0.2", start).is_none());
+        assert!(throttle
+            .check_at("10.0.0.1", start + StdDuration::from_secs(61))
+            .is_none());
+    }
+}
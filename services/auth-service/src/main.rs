@@ -7,15 +7,17 @@ use axum::{
         HeaderName, HeaderValue, Method, StatusCode,
     },
     response::Response,
-    routing::{get, post, put},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
 use common_auth::{JwtConfig, JwtVerifier};
+use common_config::{config_route, JwtSettings, KafkaSecurity};
+use common_observability::{SloConfig, SloTracker};
 use rdkafka::producer::FutureProducer;
 use reqwest::Client;
 use serde::Serialize;
 use sqlx::PgPool;
-use std::{env, fs, net::SocketAddr, sync::Arc};
+use std::sync::Arc;
 use tokio::{
     net::TcpListener,
     time::{interval, Duration, MissedTickBehavior},
@@ -24,18 +26,48 @@ use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 use common_money::log_rounding_mode_once;
 
-use auth_service::config::load_auth_config;
+use auth_service::config::{load_auth_config, StartupConfig};
+use auth_service::invitation_handlers::{
+    accept_invitation, begin_invitation_mfa, create_invitation, list_invitations,
+    resend_invitation, revoke_invitation,
+};
+use auth_service::login_guard::LoginThrottle;
 use auth_service::metrics::AuthMetrics;
 use auth_service::mfa_handlers::{begin_mfa_enrollment, verify_mfa_enrollment};
 use auth_service::notifications::KafkaProducer;
+use auth_service::offline_bundle_handlers::get_offline_bundle;
+use auth_service::oidc::idp_client;
+use auth_service::oidc_handlers::{
+    begin_sso_login, complete_sso_login, delete_tenant_sso, get_tenant_sso,
+    seal_plaintext_client_secrets, upsert_tenant_sso,
+};
+use auth_service::policy_handlers::{
+    get_capability_policy, list_policy_documents, reset_capability_policy,
+    update_capability_policy,
+};
+use auth_service::session_handlers::{
+    force_logout_user, list_my_sessions, list_user_sessions, revoke_my_session,
+    revoke_my_sessions, revoke_user_session,
+};
 use auth_service::tenant_handlers::{
     create_integration_key, create_tenant, list_integration_keys, list_tenants,
     revoke_integration_key,
 };
+use auth_service::tenant_lifecycle_handlers::{
+    archive_tenant, get_tenant_export, list_tenant_exports, list_tenant_statuses,
+    reactivate_tenant, request_tenant_export, spawn_tenant_export_purge, suspend_tenant,
+};
+use auth_service::tenant_provisioning_handlers::{
+    get_tenant_provisioning, retry_tenant_provisioning, spawn_provisioning_retry,
+};
 use auth_service::tokens::{JwkKey, TokenConfig, TokenSigner};
+use auth_service::webauthn_handlers::{
+    begin_webauthn_registration, delete_webauthn_credential, finish_webauthn_registration,
+    list_webauthn_credentials, rename_webauthn_credential,
+};
 use auth_service::user_handlers::{
     create_user, list_roles, list_users, login_user, logout_user, refresh_session,
-    reset_user_password, update_user,
+    reset_user_password, unlock_user, update_user,
 };
 
 async fn health() -> &'static str {
@@ -58,18 +90,34 @@ async fn metrics_endpoint(State(state): State<AppState>) -> Response {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("auth-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db_pool = PgPool::connect(&database_url).await?;
+    let config = StartupConfig::from_env().await?;
+    let db_pool = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db_pool.clone());
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
 
-    let token_signer = build_token_signer_from_env(&db_pool).await?;
+    let token_signer = build_token_signer(&db_pool, &config).await?;
 
     let auth_config = Arc::new(load_auth_config()?);
+    match auth_config.sso.secret_key.as_ref() {
+        Some(key) => {
+            let sealed = seal_plaintext_client_secrets(&db_pool, key)
+                .await
+                .context("Failed to seal stored SSO client secrets")?;
+            if sealed > 0 {
+                info!(sealed, "Sealed plaintext SSO client secrets");
+            }
+        }
+        None => warn!("AUTH_SSO_SECRET_KEY not set; SSO providers cannot be configured"),
+    }
+    if auth_config.tenant_export_key.is_none() {
+        warn!("AUTH_TENANT_EXPORT_KEY not set; tenant data exports are disabled");
+    }
     let enforced_roles = auth_config.required_roles_sorted().join(",");
     let bypass_tenants = auth_config
         .bypass_tenants_sorted()
@@ -85,19 +133,16 @@ async fn main() -> anyhow::Result<()> {
         "Loaded auth-service configuration"
     );
 
-    let kafka_bootstrap = env::var("KAFKA_BOOTSTRAP")
-        .or_else(|_| env::var("KAFKA_BROKERS"))
-        .unwrap_or_else(|_| "localhost:9092".to_string());
-
-    let kafka_client: FutureProducer = rdkafka::ClientConfig::new()
-        .set("bootstrap.servers", &kafka_bootstrap)
-        .create()
+    let kafka_security = KafkaSecurity::from_env().await?;
+    let kafka_client: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&config.kafka_bootstrap, &kafka_security)
         .context("Failed to create Kafka producer")?;
-    let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(kafka_client);
+    let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(kafka_client.clone());
 
     let http_client = Client::builder()
         .build()
         .context("Failed to build HTTP client")?;
+    let idp_client = idp_client(&auth_config.sso)?;
 
     let state = AppState {
         db: db_pool,
@@ -106,8 +151,12 @@ async fn main() -> anyhow::Result<()> {
         config: auth_config.clone(),
         kafka_producer,
         http_client,
+        idp_client,
         metrics: Arc::new(AuthMetrics::new()?),
+        login_throttle: Arc::new(LoginThrottle::from_policy(&auth_config.lockout)),
     };
+    spawn_provisioning_retry(state.clone());
+    spawn_tenant_export_purge(state.clone());
 
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::list([
@@ -120,6 +169,7 @@ async fn main() -> anyhow::Result<()> {
             Method::POST,
             Method::PUT,
             Method::PATCH,
+            Method::DELETE,
             Method::OPTIONS,
         ])
         .allow_headers([
@@ -130,127 +180,151 @@ async fn main() -> anyhow::Result<()> {
         ])
         .allow_credentials(true);
 
+    let slo = SloTracker::new("auth-service", SloConfig::from_env(), state.metrics.registry());
     let app = Router::new()
         .route("/healthz", get(health))
         .route("/metrics", get(metrics_endpoint))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .route("/jwks", get(jwks))
         .route("/.well-known/jwks.json", get(jwks))
+        .route("/capability-policies", get(list_policy_documents))
+        .route("/offline-bundle", get(get_offline_bundle))
+        .route("/tenant-status", get(list_tenant_statuses))
         .route("/login", post(login_user))
         .route("/session", get(refresh_session))
         .route("/logout", post(logout_user))
+        .route("/sessions", get(list_my_sessions).delete(revoke_my_sessions))
+        .route("/sessions/:session_id", delete(revoke_my_session))
+        .route("/sso/login", get(begin_sso_login))
+        .route("/sso/callback", get(complete_sso_login))
         .route("/mfa/enroll", post(begin_mfa_enrollment))
         .route("/mfa/verify", post(verify_mfa_enrollment))
+        .route("/mfa/webauthn/register/options", post(begin_webauthn_registration))
+        .route("/mfa/webauthn/register", post(finish_webauthn_registration))
+        .route("/mfa/webauthn/credentials", get(list_webauthn_credentials))
+        .route(
+            "/mfa/webauthn/credentials/:credential_id",
+            patch(rename_webauthn_credential).delete(delete_webauthn_credential),
+        )
         .route("/users", post(create_user).get(list_users))
         .route("/users/:user_id", put(update_user).patch(update_user))
         .route("/users/:user_id/reset-password", post(reset_user_password))
+        .route("/users/:user_id/unlock", post(unlock_user))
+        .route("/users/:user_id/sessions", get(list_user_sessions))
+        .route(
+            "/users/:user_id/sessions/:session_id",
+            delete(revoke_user_session),
+        )
+        .route("/users/:user_id/logout", post(force_logout_user))
         .route("/roles", get(list_roles))
+        .route("/invitations", post(create_invitation).get(list_invitations))
+        .route("/invitations/accept", post(accept_invitation))
+        .route("/invitations/accept/mfa", post(begin_invitation_mfa))
+        .route("/invitations/:invitation_id", delete(revoke_invitation))
+        .route("/invitations/:invitation_id/resend", post(resend_invitation))
         .route("/tenants", post(create_tenant).get(list_tenants))
+        .route("/tenants/:tenant_id/suspend", post(suspend_tenant))
+        .route("/tenants/:tenant_id/reactivate", post(reactivate_tenant))
+        .route("/tenants/:tenant_id/archive", post(archive_tenant))
+        .route(
+            "/tenants/:tenant_id/provisioning",
+            get(get_tenant_provisioning),
+        )
+        .route(
+            "/tenants/:tenant_id/provisioning/retry",
+            post(retry_tenant_provisioning),
+        )
+        .route(
+            "/tenants/:tenant_id/exports",
+            post(request_tenant_export).get(list_tenant_exports),
+        )
+        .route(
+            "/tenants/:tenant_id/exports/:export_id",
+            get(get_tenant_export),
+        )
         .route(
             "/tenants/:tenant_id/integration-keys",
             post(create_integration_key).get(list_integration_keys),
         )
+        .route(
+            "/tenants/:tenant_id/capability-policy",
+            get(get_capability_policy)
+                .put(update_capability_policy)
+                .delete(reset_capability_policy),
+        )
+        .route(
+            "/tenants/:tenant_id/sso",
+            get(get_tenant_sso).put(upsert_tenant_sso).delete(delete_tenant_sso),
+        )
         .route(
             "/integration-keys/:key_id/revoke",
             post(revoke_integration_key),
         )
         .with_state(state)
-        .layer(cors);
+        .layer(cors)
+        .layer(axum::middleware::from_fn(slo.layer()))
+        .layer(axum::middleware::from_fn(common_observability::request_span));
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8085);
-    let ip: std::net::IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    let addr = config.http.addr();
 
     println!("starting auth-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    common_kafka::flush(&kafka_client, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
 
     Ok(())
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Some(pem) = read_secret_env("JWT_DEV_PUBLIC_KEY_PEM")? {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
     let verifier = builder.build().await.map_err(anyhow::Error::from)?;
-
     info!("JWT verifier initialised");
     Ok(Arc::new(verifier))
 }
 
-async fn build_token_signer_from_env(db_pool: &PgPool) -> anyhow::Result<Arc<TokenSigner>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let access_ttl = env::var("TOKEN_ACCESS_TTL_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<i64>().ok())
-        .unwrap_or(900);
-    let refresh_ttl = env::var("TOKEN_REFRESH_TTL_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<i64>().ok())
-        .unwrap_or(2_592_000);
-
+async fn build_token_signer(db_pool: &PgPool, startup: &StartupConfig) -> anyhow::Result<Arc<TokenSigner>> {
+    let access_ttl = startup.token_access_ttl_seconds;
+    let refresh_ttl = startup.token_refresh_ttl_seconds;
     info!(access_ttl, refresh_ttl, "Configuring token TTLs");
 
-    let fallback_private = read_secret_env("JWT_DEV_PRIVATE_KEY_PEM")?;
-
     let config = TokenConfig {
-        issuer,
-        audience,
+        issuer: startup.jwt.issuer.clone(),
+        audience: startup.jwt.audience.clone(),
         access_ttl_seconds: access_ttl,
         refresh_ttl_seconds: refresh_ttl,
     };
 
-    let signer = TokenSigner::new(db_pool.clone(), config, fallback_private.as_deref()).await?;
+    let fallback_private = startup.dev_private_key_pem.as_ref().map(|pem| pem.expose().as_str());
+    let signer = TokenSigner::new(db_pool.clone(), config, fallback_private).await?;
     info!("Token signer initialised");
     Ok(Arc::new(signer))
 }
 
-fn read_secret_env(key: &str) -> anyhow::Result<Option<String>> {
-    let file_var = format!("{}_FILE", key);
-    if let Ok(path) = env::var(&file_var) {
-        let contents = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read {} from {}", file_var, path))?;
-        return Ok(Some(contents));
-    }
-    Ok(env::var(key).ok())
-}
-
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -2,18 +2,25 @@ use anyhow::Result;
 use axum::body::Body;
 use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::Response;
-use prometheus::{Encoder, IntCounterVec, Opts, Registry, TextEncoder};
+use prometheus::{Encoder, IntCounter, IntCounterVec, Opts, Registry, TextEncoder};
 
 #[derive(Clone)]
 pub struct AuthMetrics {
     registry: Registry,
     login_attempts: IntCounterVec,
     mfa_events: IntCounterVec,
+    invitations: IntCounterVec,
+    login_failures: IntCounterVec,
+    account_lockouts: IntCounterVec,
+    account_unlocks: IntCounterVec,
+    login_ip_throttled: IntCounter,
 }
 
 impl AuthMetrics {
     pub fn new() -> Result<Self> {
         let registry = Registry::new();
+        common_db::pool::register_metrics(&registry);
+        common_kafka::register_metrics(&registry);
 
         let login_attempts = IntCounterVec::new(
             Opts::new(
@@ -30,10 +37,54 @@ impl AuthMetrics {
         )?;
         registry.register(Box::new(mfa_events.clone()))?;
 
+        let invitations = IntCounterVec::new(
+            Opts::new("auth_invitations_total", "Count of user invitation events"),
+            &["action"],
+        )?;
+        registry.register(Box::new(invitations.clone()))?;
+
+        let login_failures = IntCounterVec::new(
+            Opts::new(
+                "auth_login_failures_total",
+                "Count of failed login attempts grouped by tenant and reason",
+            ),
+            &["tenant_id", "reason"],
+        )?;
+        registry.register(Box::new(login_failures.clone()))?;
+
+        let account_lockouts = IntCounterVec::new(
+            Opts::new(
+                "auth_account_lockouts_total",
+                "Count of account lockouts grouped by tenant and trigger (password|mfa)",
+            ),
+            &["tenant_id", "reason"],
+        )?;
+        registry.register(Box::new(account_lockouts.clone()))?;
+
+        let account_unlocks = IntCounterVec::new(
+            Opts::new(
+                "auth_account_unlocks_total",
+                "Count of administrator account unlocks grouped by tenant",
+            ),
+            &["tenant_id"],
+        )?;
+        registry.register(Box::new(account_unlocks.clone()))?;
+
+        let login_ip_throttled = IntCounter::new(
+            "auth_login_ip_throttled_total",
+            "Count of login attempts rejected by per-IP throttling",
+        )?;
+        registry.register(Box::new(login_ip_throttled.clone()))?;
+
         Ok(Self {
             registry,
             login_attempts,
             mfa_events,
+            invitations,
+            login_failures,
+            account_lockouts,
+            account_unlocks,
+            login_ip_throttled,
         })
     }
 
@@ -45,6 +96,35 @@ impl AuthMetrics {
         self.mfa_events.with_label_values(&[event]).inc();
     }
 
+    pub fn invitation_event(&self, action: &str) {
+        self.invitations.with_label_values(&[action]).inc();
+    }
+
+    pub fn login_failure(&self, tenant_id: &str, reason: &str) {
+        self.login_failures
+            .with_label_values(&[tenant_id, reason])
+            .inc();
+    }
+
+    pub fn account_lockout(&self, tenant_id: &str, reason: &str) {
+        self.account_lockouts
+            .with_label_values(&[tenant_id, reason])
+            .inc();
+    }
+
+    pub fn account_unlock(&self, tenant_id: &str) {
+        self.account_unlocks.with_label_values(&[tenant_id]).inc();
+    }
+
+    pub fn login_ip_throttled(&self) {
+        self.login_ip_throttled.inc();
+    }
+
+    /// Registry rendered by `/metrics`, for collectors owned elsewhere (e.g. SLO counters).
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
     pub fn render(&self) -> Result<Response> {
         let encoder = TextEncoder::new();
         let metric_families = self.registry.gather();
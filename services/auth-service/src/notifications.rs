@@ -1,10 +1,9 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::producer::FutureProducer;
 use reqwest::Client;
 use serde::Serialize;
-use std::time::Duration;
 use uuid::Uuid;
 
 #[async_trait]
@@ -15,13 +14,9 @@ pub trait KafkaProducer: Send + Sync {
 #[async_trait]
 impl KafkaProducer for FutureProducer {
     async fn send(&self, topic: &str, key: &str, payload: String) -> Result<()> {
-        self.send(
-            FutureRecord::to(topic).payload(&payload).key(key),
-            Duration::from_secs(0),
-        )
-        .await
-        .map_err(|(err, _)| anyhow!("Failed to publish MFA activity: {err}"))?;
-        Ok(())
+        common_kafka::publish(self, topic, key, &payload)
+            .await
+            .map_err(|err| anyhow!("Failed to publish to {topic}: {err}"))
     }
 }
 
@@ -43,6 +38,27 @@ pub struct MfaActivityEvent {
     pub detail: Option<String>,
 }
 
+/// Control event consumed by integration-gateway so key creation/revocation takes effect
+/// without waiting for the gateway's periodic cache refresh.
+#[derive(Debug, Serialize)]
+pub struct IntegrationKeyEvent {
+    pub action: &'static str,
+    pub tenant_id: Uuid,
+    pub key_id: Uuid,
+    pub api_key_hash: String,
+    pub key_suffix: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Tenant lifecycle milestone (e.g. `tenant.provisioned`) for downstream consumers.
+#[derive(Debug, Serialize)]
+pub struct TenantLifecycleEvent {
+    pub action: &'static str,
+    pub tenant_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub detail: serde_json::Value,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SuspiciousLoginPayload {
     pub text: String,
@@ -62,6 +78,34 @@ pub async fn publish_mfa_activity(
     producer.send(topic, &key, payload).await
 }
 
+pub async fn publish_integration_key_event(
+    producer: &dyn KafkaProducer,
+    topic: &str,
+    event: &IntegrationKeyEvent,
+) -> Result<()> {
+    if topic.trim().is_empty() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string(event)?;
+    let key = event.tenant_id.to_string();
+    producer.send(topic, &key, payload).await
+}
+
+pub async fn publish_tenant_event(
+    producer: &dyn KafkaProducer,
+    topic: &str,
+    event: &TenantLifecycleEvent,
+) -> Result<()> {
+    if topic.trim().is_empty() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string(event)?;
+    let key = event.tenant_id.to_string();
+    producer.send(topic, &key, payload).await
+}
+
 pub async fn post_suspicious_webhook(
     client: &Client,
     url: &str,
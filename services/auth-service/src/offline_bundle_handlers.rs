@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use common_auth::{AuthContext, OFFLINE_BUNDLE_AUDIENCE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct OfflineBundleQuery {
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    #[serde(default)]
+    pub pos_instance_id: Option<Uuid>,
+}
+
+/// Claims signed into the bundle token; verified by `common_auth::verify_offline_bundle`.
+#[derive(Serialize)]
+struct OfflineBundleClaims<'a> {
+    tenant_id: Uuid,
+    version: &'a str,
+    bundle: &'a Value,
+}
+
+#[derive(Serialize)]
+pub struct OfflineBundleResponse {
+    pub tenant_id: Uuid,
+    pub version: String,
+    pub expires_at: i64,
+    pub token: String,
+}
+
+/// Signed snapshot of what the caller's terminal needs to sell offline. The content comes from
+/// `OFFLINE_BUNDLE_SOURCE_URL` (order-service `GET /offline/bundle`) and is versioned by its
+/// digest, returned as the ETag so terminals can poll with `If-None-Match` and get a 304 until
+/// prices, tax or rounding rules change.
+pub async fn get_offline_bundle(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<OfflineBundleQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(source) = state.config.offline_bundle_source.as_deref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Offline bundles are not configured".to_string(),
+        ));
+    };
+    let tenant_id = auth.claims.tenant_id;
+
+    let mut params: Vec<(&str, String)> = Vec::new();
+    if let Some(location_id) = query.location_id {
+        params.push(("location_id", location_id.to_string()));
+    }
+    if let Some(pos_instance_id) = query.pos_instance_id {
+        params.push(("pos_instance_id", pos_instance_id.to_string()));
+    }
+    let bundle = async {
+        state
+            .http_client
+            .get(format!("{source}/offline/bundle"))
+            .query(&params)
+            .bearer_auth(&auth.token)
+            .header("X-Tenant-ID", tenant_id.to_string())
+            .header("X-Roles", auth.claims.roles.join(","))
+            .header("X-User-ID", auth.claims.subject.to_string())
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await
+    }
+    .await
+    .map_err(|err| {
+        warn!(tenant_id = %tenant_id, error = %err, "Offline bundle source failed");
+        (
+            StatusCode::BAD_GATEWAY,
+            "Unable to load offline bundle content".to_string(),
+        )
+    })?;
+
+    let version = bundle_version(&bundle);
+    let etag = format!("\"{version}\"");
+    if etag_matches(&headers, &version) {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        insert_etag(response.headers_mut(), &etag);
+        return Ok(response);
+    }
+
+    let ttl_seconds = state.config.offline_bundle_ttl_seconds;
+    let expires_at = (Utc::now() + Duration::seconds(ttl_seconds)).timestamp();
+    let claims = OfflineBundleClaims {
+        tenant_id,
+        version: &version,
+        bundle: &bundle,
+    };
+    let token = state
+        .token_signer
+        .sign_document(OFFLINE_BUNDLE_AUDIENCE, ttl_seconds, &claims)
+        .map_err(|err| {
+            error!(tenant_id = %tenant_id, error = %err, "Failed to sign offline bundle");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unable to sign offline bundle".to_string(),
+            )
+        })?;
+
+    let mut response = Json(OfflineBundleResponse {
+        tenant_id,
+        version,
+        expires_at,
+        token,
+    })
+    .into_response();
+    insert_etag(response.headers_mut(), &etag);
+    Ok(response)
+}
+
+/// Hex SHA-256 of the content. `serde_json` objects keep sorted keys, so equal content always
+/// hashes to the same version.
+fn bundle_version(bundle: &Value) -> String {
+    hex::encode(Sha256::digest(bundle.to_string().as_bytes()))
+}
+
+fn etag_matches(headers: &HeaderMap, version: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .any(|tag| tag == "*" || tag == version)
+}
+
+fn insert_etag(headers: &mut HeaderMap, etag: &str) {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(ETAG, value);
+    }
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn version_tracks_content_not_key_order() {
+        let a = json!({ "products": [{ "id": 1, "price_cents": 250 }], "tax": { "rate_bps": 825 } });
+        let b = json!({ "tax": { "rate_bps": 825 }, "products": [{ "price_cents": 250, "id": 1 }] });
+        let c = json!({ "tax": { "rate_bps": 900 }, "products": [{ "price_cents": 250, "id": 1 }] });
+        assert_eq!(bundle_version(&a), bundle_version(&b));
+        assert_ne!(bundle_version(&a), bundle_version(&c));
+    }
+
+    #[test]
+    fn if_none_match_accepts_quoted_weak_and_listed_tags() {
+        let mut headers = HeaderMap::new();
+        assert!(!etag_matches(&headers, "abc"));
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("\"xyz\", W/\"abc\""));
+        assert!(etag_matches(&headers, "abc"));
+        assert!(!etag_matches(&headers, "def"));
+    }
+}
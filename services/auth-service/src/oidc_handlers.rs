@@ -0,0 +1,885 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::SET_COOKIE, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use common_auth::AuthContext;
+use common_crypto::{ColumnKey, EncryptedColumn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::types::Json as SqlJson;
+use sqlx::{FromRow, PgPool};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::notifications::MfaActivityEvent;
+use crate::oidc::{
+    asserts_mfa, authorization_url, check_idp_url, discover, email_domain_allowed, exchange_code,
+    generate_pkce, map_role, random_token, RoleMappingRule, SSO_ASSIGNABLE_ROLES,
+};
+use crate::tenant_lifecycle_handlers::tenant_residency;
+use crate::tokens::{IssuedTokens, TokenSubject};
+use crate::user_handlers::{
+    build_refresh_cookie, ensure_role_any, ensure_tenant_access, hash_password, AuthError,
+    LoginMetadata,
+};
+use crate::AppState;
+
+#[derive(FromRow)]
+struct ProviderRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    issuer: String,
+    client_id: String,
+    /// Plaintext secret of a provider saved before secrets were sealed; cleared at startup.
+    client_secret: Option<String>,
+    client_secret_encrypted: Option<EncryptedColumn<String>>,
+    scopes: String,
+    allowed_domains: Vec<String>,
+    default_role: String,
+    role_mappings: SqlJson<Vec<RoleMappingRule>>,
+    jit_provisioning: bool,
+    is_enabled: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+const PROVIDER_COLUMNS: &str = "id, tenant_id, issuer, client_id, client_secret, client_secret_encrypted, scopes, allowed_domains, default_role, role_mappings, jit_provisioning, is_enabled, created_at, updated_at";
+
+const CLIENT_SECRET_FIELD: &str = "tenant_identity_providers.client_secret";
+
+impl ProviderRow {
+    fn client_secret(&self, key: Option<&ColumnKey>) -> anyhow::Result<String> {
+        match (&self.client_secret_encrypted, key, &self.client_secret) {
+            (Some(sealed), Some(key), _) => Ok(key.open(
+                self.tenant_id.as_bytes(),
+                CLIENT_SECRET_FIELD,
+                sealed,
+            )?),
+            (Some(_), None, _) => anyhow::bail!("AUTH_SSO_SECRET_KEY is not configured"),
+            (None, _, Some(plaintext)) => Ok(plaintext.clone()),
+            (None, _, None) => anyhow::bail!("provider has no client secret"),
+        }
+    }
+}
+
+/// Seals client secrets stored in plaintext before they were encrypted at rest and clears the
+/// plaintext. Returns how many were sealed.
+pub async fn seal_plaintext_client_secrets(db: &PgPool, key: &ColumnKey) -> anyhow::Result<u64> {
+    let rows: Vec<(Uuid, Uuid, String)> = sqlx::query_as(
+        "SELECT id, tenant_id, client_secret FROM tenant_identity_providers WHERE client_secret IS NOT NULL",
+    )
+    .fetch_all(db)
+    .await?;
+    let mut sealed = 0;
+    for (id, tenant_id, secret) in rows {
+        let encrypted = key.seal(tenant_id.as_bytes(), CLIENT_SECRET_FIELD, &secret)?;
+        sealed += sqlx::query(
+            "UPDATE tenant_identity_providers SET client_secret_encrypted = $2, client_secret = NULL WHERE id = $1 AND client_secret = $3",
+        )
+        .bind(id)
+        .bind(encrypted)
+        .bind(&secret)
+        .execute(db)
+        .await?
+        .rows_affected();
+    }
+    Ok(sealed)
+}
+
+/// Provider configuration as returned to tenant admins; the client secret is never echoed.
+#[derive(Serialize)]
+pub struct SsoProviderView {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub issuer: String,
+    pub client_id: String,
+    pub scopes: String,
+    pub allowed_domains: Vec<String>,
+    pub default_role: String,
+    pub role_mappings: Vec<RoleMappingRule>,
+    pub jit_provisioning: bool,
+    pub is_enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ProviderRow> for SsoProviderView {
+    fn from(row: ProviderRow) -> Self {
+        Self {
+            id: row.id,
+            tenant_id: row.tenant_id,
+            issuer: row.issuer,
+            client_id: row.client_id,
+            scopes: row.scopes,
+            allowed_domains: row.allowed_domains,
+            default_role: row.default_role,
+            role_mappings: row.role_mappings.0,
+            jit_provisioning: row.jit_provisioning,
+            is_enabled: row.is_enabled,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SsoProviderRequest {
+    pub issuer: String,
+    pub client_id: String,
+    /// Optional on update to keep the stored secret.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scopes: Option<String>,
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default)]
+    pub default_role: Option<String>,
+    #[serde(default)]
+    pub role_mappings: Vec<RoleMappingRule>,
+    #[serde(default)]
+    pub jit_provisioning: Option<bool>,
+    #[serde(default)]
+    pub is_enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct SsoLoginQuery {
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SsoCallbackQuery {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(FromRow)]
+struct OidcStateRow {
+    provider_id: Uuid,
+    nonce: String,
+    code_verifier: String,
+}
+
+#[derive(FromRow)]
+struct SsoUserRow {
+    id: Uuid,
+    role: String,
+    is_active: bool,
+}
+
+fn bad_request(message: impl Into<String>) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+fn sso_error(status: StatusCode, code: &'static str, message: impl Into<String>) -> AuthError {
+    AuthError::with_detail(status, code, message, None)
+}
+
+async fn load_provider(
+    state: &AppState,
+    tenant_id: Uuid,
+) -> Result<Option<ProviderRow>, sqlx::Error> {
+    sqlx::query_as::<_, ProviderRow>(&format!(
+        "SELECT {PROVIDER_COLUMNS} FROM tenant_identity_providers WHERE tenant_id = $1"
+    ))
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+}
+
+pub async fn get_tenant_sso(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<SsoProviderView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let provider = load_provider(&state, tenant_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "SSO is not configured for this tenant".to_string(),
+        ))?;
+    Ok(Json(provider.into()))
+}
+
+pub async fn upsert_tenant_sso(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+    Json(payload): Json<SsoProviderRequest>,
+) -> Result<Json<SsoProviderView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let issuer = payload.issuer.trim().trim_end_matches('/').to_string();
+    if let Err(err) = check_idp_url(&issuer, state.config.sso.allow_insecure_loopback) {
+        return Err(bad_request(format!("Invalid issuer: {err}")));
+    }
+    let client_id = payload.client_id.trim();
+    if client_id.is_empty() {
+        return Err(bad_request("client_id must not be empty"));
+    }
+    let default_role = payload
+        .default_role
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or("cashier")
+        .to_ascii_lowercase();
+    if !SSO_ASSIGNABLE_ROLES.contains(&default_role.as_str()) {
+        return Err(bad_request(
+            "default_role must be admin, manager, or cashier",
+        ));
+    }
+    if let Some(rule) = payload.role_mappings.iter().find(|rule| {
+        !SSO_ASSIGNABLE_ROLES.contains(&rule.role.as_str()) || rule.claim.trim().is_empty()
+    }) {
+        return Err(bad_request(format!(
+            "Invalid role mapping for claim '{}': role must be admin, manager, or cashier",
+            rule.claim
+        )));
+    }
+    let mut allowed_domains: Vec<String> = payload
+        .allowed_domains
+        .iter()
+        .map(|domain| domain.trim().trim_start_matches('@').to_ascii_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect();
+    allowed_domains.sort();
+    allowed_domains.dedup();
+    let client_secret = payload
+        .client_secret
+        .as_deref()
+        .map(str::trim)
+        .filter(|secret| !secret.is_empty());
+
+    let Some(secret_key) = state.config.sso.secret_key.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "SSO client secrets cannot be stored: AUTH_SSO_SECRET_KEY is not configured".to_string(),
+        ));
+    };
+
+    let existing = load_provider(&state, tenant_id).await.map_err(db_error)?;
+    let client_secret = match (client_secret, existing.as_ref()) {
+        (Some(secret), _) => secret.to_string(),
+        (None, Some(existing)) => existing.client_secret(Some(secret_key)).map_err(|err| {
+            error!(tenant_id = %tenant_id, error = %err, "Failed to open stored SSO client secret");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The stored client secret could not be read; provide client_secret".to_string(),
+            )
+        })?,
+        (None, None) => return Err(bad_request("client_secret is required")),
+    };
+    let client_secret = secret_key
+        .seal(tenant_id.as_bytes(), CLIENT_SECRET_FIELD, &client_secret)
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to seal client secret: {err}"),
+            )
+        })?;
+
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    let provider = sqlx::query_as::<_, ProviderRow>(&format!(
+        "INSERT INTO tenant_identity_providers (id, tenant_id, issuer, client_id, client_secret_encrypted, scopes, allowed_domains, default_role, role_mappings, jit_provisioning, is_enabled)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+         ON CONFLICT (tenant_id) DO UPDATE SET
+            issuer = EXCLUDED.issuer,
+            client_id = EXCLUDED.client_id,
+            client_secret = NULL,
+            client_secret_encrypted = EXCLUDED.client_secret_encrypted,
+            scopes = EXCLUDED.scopes,
+            allowed_domains = EXCLUDED.allowed_domains,
+            default_role = EXCLUDED.default_role,
+            role_mappings = EXCLUDED.role_mappings,
+            jit_provisioning = EXCLUDED.jit_provisioning,
+            is_enabled = EXCLUDED.is_enabled,
+            updated_at = NOW()
+         RETURNING {PROVIDER_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(&issuer)
+    .bind(client_id)
+    .bind(&client_secret)
+    .bind(
+        payload
+            .scopes
+            .as_deref()
+            .map(str::trim)
+            .filter(|scopes| scopes.contains("openid"))
+            .unwrap_or("openid email profile"),
+    )
+    .bind(&allowed_domains)
+    .bind(&default_role)
+    .bind(SqlJson(&payload.role_mappings))
+    .bind(payload.jit_provisioning.unwrap_or(true))
+    .bind(payload.is_enabled.unwrap_or(true))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    // Email domains route sign-ins to a tenant, so no two tenants may claim the same one.
+    sqlx::query("DELETE FROM tenant_sso_domains WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    for domain in &allowed_domains {
+        let claimed = sqlx::query(
+            "INSERT INTO tenant_sso_domains (domain, tenant_id, provider_id) VALUES ($1, $2, $3)",
+        )
+        .bind(domain)
+        .bind(tenant_id)
+        .bind(provider.id)
+        .execute(&mut *tx)
+        .await;
+        match claimed {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+                return Err((
+                    StatusCode::CONFLICT,
+                    format!("Domain '{domain}' is already claimed by another tenant"),
+                ));
+            }
+            Err(err) => return Err(db_error(err)),
+        }
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    info!(tenant_id = %tenant_id, issuer = %issuer, actor = %auth.claims.subject, "Tenant SSO provider configured");
+    Ok(Json(provider.into()))
+}
+
+pub async fn delete_tenant_sso(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let result = sqlx::query("DELETE FROM tenant_identity_providers WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "SSO is not configured for this tenant".to_string(),
+        ));
+    }
+    info!(tenant_id = %tenant_id, actor = %auth.claims.subject, "Tenant SSO provider removed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Starts the authorization-code flow. The provider is chosen by tenant id or by the
+/// domain of the supplied email address.
+pub async fn begin_sso_login(
+    State(state): State<AppState>,
+    Query(query): Query<SsoLoginQuery>,
+) -> Result<Redirect, AuthError> {
+    let email = query
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.is_empty());
+    let provider = match (query.tenant_id, email) {
+        (Some(tenant_id), _) => load_provider(&state, tenant_id).await,
+        (None, Some(email)) => {
+            let domain = email.rsplit_once('@').map(|(_, domain)| domain.to_ascii_lowercase());
+            sqlx::query_as::<_, ProviderRow>(&format!(
+                "SELECT {PROVIDER_COLUMNS} FROM tenant_identity_providers
+                 WHERE id = (SELECT provider_id FROM tenant_sso_domains WHERE domain = $1) AND is_enabled"
+            ))
+            .bind(domain.unwrap_or_default())
+            .fetch_optional(&state.db)
+            .await
+        }
+        (None, None) => {
+            return Err(sso_error(
+                StatusCode::BAD_REQUEST,
+                "SSO_TENANT_REQUIRED",
+                "Provide a tenant_id or email to start single sign-on.",
+            ))
+        }
+    }
+    .map_err(|err| {
+        error!(error = %err, "Failed to load SSO provider");
+        AuthError::internal_error("Unable to start single sign-on.")
+    })?
+    .filter(|provider| provider.is_enabled)
+    .ok_or_else(|| {
+        sso_error(
+            StatusCode::NOT_FOUND,
+            "SSO_NOT_CONFIGURED",
+            "Single sign-on is not configured for this account.",
+        )
+    })?;
+
+    let metadata = discover(
+        &state.idp_client,
+        &provider.issuer,
+        state.config.sso.allow_insecure_loopback,
+    )
+        .await
+        .map_err(|err| {
+            warn!(tenant_id = %provider.tenant_id, error = %err, "OIDC discovery failed");
+            sso_error(
+                StatusCode::BAD_GATEWAY,
+                "SSO_PROVIDER_UNAVAILABLE",
+                "The identity provider could not be reached.",
+            )
+        })?;
+
+    let oidc_state = random_token();
+    let nonce = random_token();
+    let pkce = generate_pkce();
+    let expires_at = Utc::now() + Duration::seconds(state.config.sso.state_ttl_seconds);
+    sqlx::query(
+        "INSERT INTO auth_oidc_states (state, provider_id, nonce, code_verifier, expires_at) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(&oidc_state)
+    .bind(provider.id)
+    .bind(&nonce)
+    .bind(&pkce.verifier)
+    .bind(expires_at)
+    .execute(&state.db)
+    .await
+    .map_err(|err| {
+        error!(error = %err, "Failed to persist OIDC state");
+        AuthError::internal_error("Unable to start single sign-on.")
+    })?;
+
+    let url = authorization_url(
+        &metadata,
+        &provider.client_id,
+        &state.config.sso.redirect_uri,
+        &provider.scopes,
+        &oidc_state,
+        &nonce,
+        &pkce.challenge,
+        email,
+    );
+    Ok(Redirect::to(&url))
+}
+
+pub async fn complete_sso_login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SsoCallbackQuery>,
+) -> Result<Response, AuthError> {
+    let trace_id = Uuid::new_v4();
+    if let Some(error) = query.error.as_deref() {
+        warn!(error, trace_id = %trace_id, "Identity provider returned an error");
+        state.record_login_metric("sso_rejected");
+        return Err(sso_error(
+            StatusCode::UNAUTHORIZED,
+            "SSO_REJECTED",
+            "The identity provider did not authorize the sign-in.",
+        ));
+    }
+    let (Some(code), Some(oidc_state)) = (query.code.as_deref(), query.state.as_deref()) else {
+        return Err(sso_error(
+            StatusCode::BAD_REQUEST,
+            "SSO_CALLBACK_INVALID",
+            "Missing authorization code or state.",
+        ));
+    };
+
+    let pending = sqlx::query_as::<_, OidcStateRow>(
+        "DELETE FROM auth_oidc_states WHERE state = $1 AND expires_at > NOW() RETURNING provider_id, nonce, code_verifier",
+    )
+    .bind(oidc_state)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| {
+        error!(error = %err, "Failed to consume OIDC state");
+        AuthError::internal_error("Unable to complete single sign-on.")
+    })?
+    .ok_or_else(|| {
+        sso_error(
+            StatusCode::BAD_REQUEST,
+            "SSO_STATE_EXPIRED",
+            "The sign-in request expired. Please start again.",
+        )
+    })?;
+
+    let provider = sqlx::query_as::<_, ProviderRow>(&format!(
+        "SELECT {PROVIDER_COLUMNS} FROM tenant_identity_providers WHERE id = $1 AND is_enabled"
+    ))
+    .bind(pending.provider_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| {
+        error!(error = %err, "Failed to load SSO provider");
+        AuthError::internal_error("Unable to complete single sign-on.")
+    })?
+    .ok_or_else(|| {
+        sso_error(
+            StatusCode::NOT_FOUND,
+            "SSO_NOT_CONFIGURED",
+            "Single sign-on is not configured for this account.",
+        )
+    })?;
+
+    let metadata = discover(
+        &state.idp_client,
+        &provider.issuer,
+        state.config.sso.allow_insecure_loopback,
+    )
+        .await
+        .map_err(|err| {
+            warn!(tenant_id = %provider.tenant_id, error = %err, "OIDC discovery failed");
+            sso_error(
+                StatusCode::BAD_GATEWAY,
+                "SSO_PROVIDER_UNAVAILABLE",
+                "The identity provider could not be reached.",
+            )
+        })?;
+
+    let client_secret = provider
+        .client_secret(state.config.sso.secret_key.as_ref())
+        .map_err(|err| {
+            error!(tenant_id = %provider.tenant_id, error = %err, "Failed to open SSO client secret");
+            AuthError::internal_error("Unable to complete single sign-on.")
+        })?;
+    let claims = exchange_code(
+        &state.idp_client,
+        &metadata,
+        &provider.client_id,
+        &client_secret,
+        &state.config.sso.redirect_uri,
+        code,
+        &pending.code_verifier,
+        &pending.nonce,
+    )
+    .await
+    .map_err(|err| {
+        warn!(tenant_id = %provider.tenant_id, error = %err, trace_id = %trace_id, "OIDC code exchange failed");
+        state.record_login_metric("sso_rejected");
+        sso_error(
+            StatusCode::UNAUTHORIZED,
+            "SSO_TOKEN_INVALID",
+            "The identity provider response could not be verified.",
+        )
+    })?;
+
+    let subject = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            sso_error(
+                StatusCode::UNAUTHORIZED,
+                "SSO_TOKEN_INVALID",
+                "ID token is missing a subject.",
+            )
+        })?
+        .to_string();
+    let email = claims
+        .get("email")
+        .and_then(Value::as_str)
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty())
+        .ok_or_else(|| {
+            sso_error(
+                StatusCode::FORBIDDEN,
+                "SSO_EMAIL_MISSING",
+                "The identity provider did not share an email address.",
+            )
+        })?;
+    let email_verified = claims.get("email_verified").and_then(Value::as_bool) == Some(true);
+    if !email_domain_allowed(&provider.allowed_domains, &email) {
+        state.record_login_metric("sso_domain_rejected");
+        return Err(sso_error(
+            StatusCode::FORBIDDEN,
+            "SSO_DOMAIN_NOT_ALLOWED",
+            "This email domain is not allowed for the tenant.",
+        ));
+    }
+
+    let mapped_role = map_role(&provider.role_mappings.0, &claims);
+    let user = resolve_sso_user(
+        &state,
+        &provider,
+        &subject,
+        &email,
+        email_verified,
+        &claims,
+        mapped_role.as_deref(),
+        trace_id,
+    )
+    .await?;
+    if !user.is_active {
+        state.record_login_metric("inactive");
+        return Err(AuthError::account_inactive());
+    }
+
+    // The tenant's MFA policy applies as for password logins; the second factor is the IdP's.
+    let mfa_enrolled: bool = sqlx::query_scalar(
+        "SELECT mfa_secret IS NOT NULL OR EXISTS (SELECT 1 FROM auth_webauthn_credentials c WHERE c.user_id = u.id) FROM users u WHERE u.id = $1",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|err| {
+        error!(user_id = %user.id, error = %err, "Failed to load MFA enrollment after SSO");
+        AuthError::internal_error("Unable to complete single sign-on.")
+    })?;
+    if state
+        .config
+        .should_enforce_for(&user.role, provider.tenant_id, mfa_enrolled)
+        && !asserts_mfa(&claims)
+    {
+        warn!(user_id = %user.id, tenant_id = %provider.tenant_id, trace_id = %trace_id, "SSO login without IdP multi-factor authentication");
+        state.record_login_metric("sso_mfa_required");
+        return Err(sso_error(
+            StatusCode::FORBIDDEN,
+            "SSO_MFA_REQUIRED",
+            "Your identity provider must verify a second factor for this account.",
+        ));
+    }
+
+    let residency = tenant_residency(&state, provider.tenant_id).await.map_err(|err| {
+        error!(user_id = %user.id, error = ?err, "Failed to load tenant residency after SSO");
+        AuthError::internal_error("Unable to issue authentication tokens.")
+    })?;
+    let issued = state
+        .token_signer
+        .issue_session_tokens(
+            TokenSubject {
+                user_id: user.id,
+                tenant_id: provider.tenant_id,
+                roles: vec![user.role.clone()],
+                residency,
+            },
+            None,
+            &LoginMetadata::from_headers(&headers, None).session_device(),
+        )
+        .await
+        .map_err(|err| {
+            error!(user_id = %user.id, error = ?err, "Failed to issue tokens after SSO");
+            AuthError::internal_error("Unable to issue authentication tokens.")
+        })?;
+    let IssuedTokens {
+        refresh_token,
+        refresh_expires_in,
+        ..
+    } = issued;
+    let cookie = build_refresh_cookie(state.config.as_ref(), &refresh_token, refresh_expires_in);
+
+    emit_sso_event(
+        &state,
+        &provider,
+        user.id,
+        &user.role,
+        "auth.sso.login",
+        trace_id,
+        json!({ "issuer": provider.issuer }),
+    )
+    .await;
+    state.record_login_metric("sso_success");
+
+    // The browser lands on the frontend with only the refresh cookie; it exchanges it via /session.
+    let mut reply = Redirect::to(&state.config.sso.success_url).into_response();
+    let cookie = HeaderValue::from_str(&cookie).map_err(|err| {
+        error!(error = ?err, "Failed to encode refresh cookie");
+        AuthError::internal_error("Unable to encode refresh cookie.")
+    })?;
+    reply.headers_mut().append(SET_COOKIE, cookie);
+    Ok(reply)
+}
+
+/// Finds the user linked to the IdP subject, links an existing account with the same email,
+/// or provisions a new one just in time. A matching role-mapping rule is authoritative.
+/// Linking by email and provisioning trust the address, so both need `email_verified`; an
+/// identity that is already linked is matched on the IdP subject alone.
+#[allow(clippy::too_many_arguments)]
+async fn resolve_sso_user(
+    state: &AppState,
+    provider: &ProviderRow,
+    subject: &str,
+    email: &str,
+    email_verified: bool,
+    claims: &Value,
+    mapped_role: Option<&str>,
+    trace_id: Uuid,
+) -> Result<SsoUserRow, AuthError> {
+    let internal = |err: sqlx::Error| {
+        error!(tenant_id = %provider.tenant_id, error = %err, "Failed to resolve SSO user");
+        AuthError::internal_error("Unable to complete single sign-on.")
+    };
+
+    let linked = sqlx::query_as::<_, SsoUserRow>(
+        "SELECT u.id, u.role, u.is_active FROM auth_user_identities i JOIN users u ON u.id = i.user_id WHERE i.provider_id = $1 AND i.subject = $2",
+    )
+    .bind(provider.id)
+    .bind(subject)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(internal)?;
+
+    let mut user = match linked {
+        Some(user) => user,
+        None if !email_verified => {
+            state.record_login_metric("sso_email_unverified");
+            return Err(sso_error(
+                StatusCode::FORBIDDEN,
+                "SSO_EMAIL_UNVERIFIED",
+                "The email address has not been verified by the identity provider.",
+            ));
+        }
+        None => {
+            let existing = sqlx::query_as::<_, SsoUserRow>(
+                "SELECT id, role, is_active FROM users WHERE tenant_id = $1 AND lower(email) = lower($2)",
+            )
+            .bind(provider.tenant_id)
+            .bind(email)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(internal)?;
+
+            let user = match existing {
+                Some(user) => user,
+                None if provider.jit_provisioning => {
+                    let role = mapped_role.unwrap_or(&provider.default_role).to_string();
+                    let name = claims
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or(email)
+                        .to_string();
+                    // SSO users never sign in with a password; store a hash of random bytes.
+                    let password_hash = hash_password(&crate::oidc::random_token())
+                        .map_err(|(_, message)| AuthError::internal_error(message))?;
+                    let user = sqlx::query_as::<_, SsoUserRow>(
+                        "INSERT INTO users (id, tenant_id, name, email, role, password_hash, is_active, force_password_reset)
+                         VALUES ($1, $2, $3, $4, $5, $6, TRUE, FALSE)
+                         RETURNING id, role, is_active",
+                    )
+                    .bind(Uuid::new_v4())
+                    .bind(provider.tenant_id)
+                    .bind(&name)
+                    .bind(email)
+                    .bind(&role)
+                    .bind(password_hash)
+                    .fetch_one(&state.db)
+                    .await
+                    .map_err(internal)?;
+                    emit_sso_event(
+                        state,
+                        provider,
+                        user.id,
+                        &user.role,
+                        "auth.sso.provisioned",
+                        trace_id,
+                        json!({ "email": email }),
+                    )
+                    .await;
+                    user
+                }
+                None => {
+                    state.record_login_metric("sso_not_provisioned");
+                    return Err(sso_error(
+                        StatusCode::FORBIDDEN,
+                        "SSO_USER_NOT_PROVISIONED",
+                        "No account exists for this user. Ask an administrator to invite you.",
+                    ));
+                }
+            };
+
+            sqlx::query(
+                "INSERT INTO auth_user_identities (provider_id, subject, user_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            )
+            .bind(provider.id)
+            .bind(subject)
+            .bind(user.id)
+            .execute(&state.db)
+            .await
+            .map_err(internal)?;
+            user
+        }
+    };
+
+    if let Some(role) = mapped_role {
+        if role != user.role && user.role != "super_admin" {
+            sqlx::query("UPDATE users SET role = $2, updated_at = NOW() WHERE id = $1")
+                .bind(user.id)
+                .bind(role)
+                .execute(&state.db)
+                .await
+                .map_err(internal)?;
+            emit_sso_event(
+                state,
+                provider,
+                user.id,
+                role,
+                "auth.sso.role_synced",
+                trace_id,
+                json!({ "previous_role": user.role }),
+            )
+            .await;
+            user.role = role.to_string();
+        }
+    }
+
+    if let Err(err) = sqlx::query(
+        "UPDATE auth_user_identities SET last_login_at = NOW() WHERE provider_id = $1 AND subject = $2",
+    )
+    .bind(provider.id)
+    .bind(subject)
+    .execute(&state.db)
+    .await
+    {
+        warn!(user_id = %user.id, error = %err, "Failed to record SSO login time");
+    }
+    Ok(user)
+}
+
+async fn emit_sso_event(
+    state: &AppState,
+    provider: &ProviderRow,
+    user_id: Uuid,
+    role: &str,
+    action: &'static str,
+    trace_id: Uuid,
+    detail: Value,
+) {
+    info!(security_event = action, user_id = %user_id, tenant_id = %provider.tenant_id, trace_id = %trace_id, "Recorded SSO activity");
+    let event = MfaActivityEvent {
+        action,
+        severity: "info",
+        tenant_id: provider.tenant_id,
+        user_id: Some(user_id),
+        trace_id,
+        occurred_at: Utc::now(),
+        ip: None,
+        user_agent: None,
+        device: None,
+        role: Some(role.to_string()),
+        detail: Some(detail.to_string()),
+    };
+    state.emit_mfa_activity(event, None).await;
+}
@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Result};
+use std::env;
+
+/// Password complexity rules applied when passwords are set (user creation, resets).
+/// Existing hashes are never re-validated at login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub disallow_email: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            disallow_email: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+        let min_length = usize_from_env("AUTH_PASSWORD_MIN_LENGTH")?.unwrap_or(defaults.min_length);
+        let max_length = usize_from_env("AUTH_PASSWORD_MAX_LENGTH")?.unwrap_or(defaults.max_length);
+        if min_length == 0 || min_length > max_length {
+            return Err(anyhow!(
+                "AUTH_PASSWORD_MIN_LENGTH ({min_length}) must be between 1 and AUTH_PASSWORD_MAX_LENGTH ({max_length})"
+            ));
+        }
+        Ok(Self {
+            min_length,
+            max_length,
+            require_uppercase: flag("AUTH_PASSWORD_REQUIRE_UPPERCASE", defaults.require_uppercase),
+            require_lowercase: flag("AUTH_PASSWORD_REQUIRE_LOWERCASE", defaults.require_lowercase),
+            require_digit: flag("AUTH_PASSWORD_REQUIRE_DIGIT", defaults.require_digit),
+            require_symbol: flag("AUTH_PASSWORD_REQUIRE_SYMBOL", defaults.require_symbol),
+            disallow_email: flag("AUTH_PASSWORD_DISALLOW_EMAIL", defaults.disallow_email),
+        })
+    }
+
+    /// Returns every violated rule so clients can render all problems at once.
+    pub fn violations(&self, password: &str, email: Option<&str>) -> Vec<&'static str> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push("too_short");
+        }
+        if length > self.max_length {
+            violations.push("too_long");
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            violations.push("missing_uppercase");
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            violations.push("missing_lowercase");
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push("missing_digit");
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            violations.push("missing_symbol");
+        }
+        if self.disallow_email {
+            if let Some(local) = email
+                .and_then(|value| value.split('@').next())
+                .map(|value| value.trim().to_ascii_lowercase())
+                .filter(|value| value.len() >= 3)
+            {
+                if password.to_ascii_lowercase().contains(&local) {
+                    violations.push("contains_email");
+                }
+            }
+        }
+        violations
+    }
+
+    pub fn describe(&self) -> String {
+        let mut rules = vec![format!(
+            "between {} and {} characters",
+            self.min_length, self.max_length
+        )];
+        if self.require_uppercase {
+            rules.push("an uppercase letter".into());
+        }
+        if self.require_lowercase {
+            rules.push("a lowercase letter".into());
+        }
+        if self.require_digit {
+            rules.push("a digit".into());
+        }
+        if self.require_symbol {
+            rules.push("a symbol".into());
+        }
+        format!("Password must contain {}.", rules.join(", "))
+    }
+}
+
+fn flag(key: &str, default: bool) -> bool {
+    env::var(key)
+        .ok()
+        .map(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(default)
+}
+
+fn usize_from_env(key: &str) -> Result<Option<usize>> {
+    match env::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|err| anyhow!("Invalid {key} '{value}': {err}")),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 12,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..PasswordPolicy::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_only_enforces_length() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(policy.violations("short", None), vec!["too_short"]);
+        assert!(policy.violations("longenough", None).is_empty());
+    }
+
+    #[test]
+    fn strict_policy_reports_every_violation() {
+        let violations = strict().violations("abc", None);
+        assert_eq!(
+            violations,
+            vec!["too_short", "missing_uppercase", "missing_digit", "missing_symbol"]
+        );
+        assert!(strict().violations("CorrectHorse9!", None).is_empty());
+    }
+
+    #[test]
+    fn rejects_password_containing_email_local_part() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            policy.violations("Jane.Doe-2024", Some("jane.doe@example.com")),
+            vec!["contains_email"]
+        );
+        assert!(policy.violations("unrelated-secret", Some("jane.doe@example.com")).is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_auth::AuthContext;
+use common_security::tenant_policy::PolicyDocumentClaims;
+use common_security::{default_allowed_roles, Capability, POLICY_AUDIENCE};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::tenant_lifecycle_handlers::internal_token_matches;
+use crate::user_handlers::{ensure_role_any, ensure_tenant_access};
+use crate::AppState;
+
+/// Roles a tenant may grant capabilities to; `super_admin` always holds every capability.
+const GRANTABLE_ROLES: &[&str] = &["admin", "manager", "support", "inventory", "cashier"];
+const POLICY_DOCUMENT_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+type CapabilityGrant = (Capability, Vec<String>);
+
+#[derive(Serialize)]
+pub struct CapabilityGrantView {
+    pub capability: &'static str,
+    pub roles: Vec<String>,
+    pub customized: bool,
+}
+
+#[derive(Serialize)]
+pub struct CapabilityPolicyView {
+    pub tenant_id: Uuid,
+    pub version: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub capabilities: Vec<CapabilityGrantView>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCapabilityPolicy {
+    /// Capability → roles. Capabilities not listed revert to the platform defaults.
+    pub grants: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct PolicyDocumentQuery {
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyDocument {
+    pub tenant_id: Uuid,
+    pub version: i64,
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct PolicyDocumentList {
+    pub documents: Vec<PolicyDocument>,
+}
+
+#[derive(FromRow)]
+struct VersionRow {
+    tenant_id: Uuid,
+    version: i64,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct GrantRow {
+    tenant_id: Uuid,
+    capability: String,
+    roles: Vec<String>,
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+fn default_role_names(cap: Capability) -> Vec<String> {
+    default_allowed_roles(cap)
+        .iter()
+        .map(|role| role.as_str().to_string())
+        .collect()
+}
+
+async fn load_overrides(
+    state: &AppState,
+    tenant_id: Uuid,
+) -> Result<HashMap<String, Vec<String>>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, GrantRow>(
+        "SELECT tenant_id, capability, roles FROM tenant_capability_policies WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.capability, row.roles))
+        .collect())
+}
+
+async fn policy_view(
+    state: &AppState,
+    tenant_id: Uuid,
+) -> Result<CapabilityPolicyView, sqlx::Error> {
+    let version = sqlx::query_as::<_, VersionRow>(
+        "SELECT tenant_id, version, updated_at FROM tenant_capability_policy_versions WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await?;
+    let overrides = load_overrides(state, tenant_id).await?;
+    let capabilities = Capability::ALL
+        .into_iter()
+        .map(|cap| match overrides.get(cap.as_str()) {
+            Some(roles) => CapabilityGrantView {
+                capability: cap.as_str(),
+                roles: roles.clone(),
+                customized: true,
+            },
+            None => CapabilityGrantView {
+                capability: cap.as_str(),
+                roles: default_role_names(cap),
+                customized: false,
+            },
+        })
+        .collect();
+    Ok(CapabilityPolicyView {
+        tenant_id,
+        version: version.as_ref().map(|row| row.version).unwrap_or(0),
+        updated_at: version.map(|row| row.updated_at),
+        capabilities,
+    })
+}
+
+fn validate_grants(
+    grants: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<CapabilityGrant>, (StatusCode, String)> {
+    grants
+        .iter()
+        .map(|(capability, roles)| {
+            let cap = Capability::parse(capability.trim()).ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown capability '{capability}'"),
+                )
+            })?;
+            let mut normalized = Vec::with_capacity(roles.len());
+            for role in roles {
+                let role = role.trim().to_ascii_lowercase();
+                if !GRANTABLE_ROLES.contains(&role.as_str()) {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!("Role '{role}' cannot be granted capabilities"),
+                    ));
+                }
+                if !normalized.contains(&role) {
+                    normalized.push(role);
+                }
+            }
+            normalized.sort();
+            Ok((cap, normalized))
+        })
+        .collect()
+}
+
+pub async fn get_capability_policy(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<CapabilityPolicyView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let view = policy_view(&state, tenant_id).await.map_err(db_error)?;
+    Ok(Json(view))
+}
+
+pub async fn update_capability_policy(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+    Json(payload): Json<UpdateCapabilityPolicy>,
+) -> Result<Json<CapabilityPolicyView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let grants = validate_grants(&payload.grants)?;
+    replace_overrides(&state, tenant_id, auth.claims.subject, &grants)
+        .await
+        .map_err(db_error)?;
+    info!(
+        tenant_id = %tenant_id,
+        actor = %auth.claims.subject,
+        customized = grants.len(),
+        "Tenant capability policy updated"
+    );
+    let view = policy_view(&state, tenant_id).await.map_err(db_error)?;
+    Ok(Json(view))
+}
+
+pub async fn reset_capability_policy(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<CapabilityPolicyView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    replace_overrides(&state, tenant_id, auth.claims.subject, &[])
+        .await
+        .map_err(db_error)?;
+    info!(tenant_id = %tenant_id, actor = %auth.claims.subject, "Tenant capability policy reset to defaults");
+    let view = policy_view(&state, tenant_id).await.map_err(db_error)?;
+    Ok(Json(view))
+}
+
+/// Writes the platform defaults as a new tenant's policy, so its roles start with their grants
+/// spelled out (and editable) instead of implied. Leaves a tenant that already has a policy
+/// alone; returns whether it seeded one.
+pub async fn seed_default_policy(db: &PgPool, tenant_id: Uuid, actor: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let created = sqlx::query(
+        "INSERT INTO tenant_capability_policy_versions (tenant_id, version, updated_at, updated_by)
+         VALUES ($1, 1, NOW(), $2)
+         ON CONFLICT (tenant_id) DO NOTHING",
+    )
+    .bind(tenant_id)
+    .bind(actor)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if !created {
+        return Ok(false);
+    }
+    for cap in Capability::ALL {
+        let roles: Vec<String> = default_role_names(cap)
+            .into_iter()
+            .filter(|role| GRANTABLE_ROLES.contains(&role.as_str()))
+            .collect();
+        sqlx::query(
+            "INSERT INTO tenant_capability_policies (tenant_id, capability, roles) VALUES ($1, $2, $3)",
+        )
+        .bind(tenant_id)
+        .bind(cap.as_str())
+        .bind(&roles)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Replaces all overrides and bumps the version so services drop stale cached documents.
+async fn replace_overrides(
+    state: &AppState,
+    tenant_id: Uuid,
+    actor: Uuid,
+    grants: &[CapabilityGrant],
+) -> Result<(), sqlx::Error> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "INSERT INTO tenant_capability_policy_versions (tenant_id, version, updated_at, updated_by)
+         VALUES ($1, 1, NOW(), $2)
+         ON CONFLICT (tenant_id) DO UPDATE SET
+            version = tenant_capability_policy_versions.version + 1,
+            updated_at = NOW(),
+            updated_by = EXCLUDED.updated_by",
+    )
+    .bind(tenant_id)
+    .bind(actor)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM tenant_capability_policies WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await?;
+    for (cap, roles) in grants {
+        sqlx::query(
+            "INSERT INTO tenant_capability_policies (tenant_id, capability, roles) VALUES ($1, $2, $3)",
+        )
+        .bind(tenant_id)
+        .bind(cap.as_str())
+        .bind(roles)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Signed policy documents for services, analogous to `/jwks`. Tenants that never customised
+/// their policy are omitted; services fall back to the static defaults for them. Services syncing
+/// every tenant present `AUTH_CAPABILITY_POLICY_TOKEN` in `X-Internal-Token`; any other caller
+/// gets only its own tenant's document (super admins may pick one with `tenant_id`).
+pub async fn list_policy_documents(
+    State(state): State<AppState>,
+    auth: Option<AuthContext>,
+    headers: HeaderMap,
+    Query(query): Query<PolicyDocumentQuery>,
+) -> Result<Json<PolicyDocumentList>, (StatusCode, String)> {
+    let internal = state
+        .config
+        .capability_policy_token
+        .as_deref()
+        .is_some_and(|expected| internal_token_matches(&headers, expected));
+    let tenant_filter = if internal {
+        query.tenant_id
+    } else {
+        let auth = auth.ok_or((StatusCode::UNAUTHORIZED, "Authentication required".to_string()))?;
+        let tenant_id = query.tenant_id.unwrap_or(auth.claims.tenant_id);
+        ensure_tenant_access(&auth, tenant_id)?;
+        Some(tenant_id)
+    };
+    let versions = sqlx::query_as::<_, VersionRow>(
+        "SELECT tenant_id, version, updated_at FROM tenant_capability_policy_versions
+         WHERE ($1::uuid IS NULL OR tenant_id = $1)",
+    )
+    .bind(tenant_filter)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+    let grants = sqlx::query_as::<_, GrantRow>(
+        "SELECT tenant_id, capability, roles FROM tenant_capability_policies
+         WHERE ($1::uuid IS NULL OR tenant_id = $1)",
+    )
+    .bind(tenant_filter)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let mut by_tenant: HashMap<Uuid, BTreeMap<String, Vec<String>>> = HashMap::new();
+    for row in grants {
+        by_tenant
+            .entry(row.tenant_id)
+            .or_default()
+            .insert(row.capability, row.roles);
+    }
+
+    let mut documents = Vec::with_capacity(versions.len());
+    for version in versions {
+        let claims = PolicyDocumentClaims {
+            tenant_id: version.tenant_id,
+            version: version.version,
+            grants: by_tenant.remove(&version.tenant_id).unwrap_or_default(),
+        };
+        let token = state
+            .token_signer
+            .sign_document(POLICY_AUDIENCE, POLICY_DOCUMENT_TTL_SECONDS, &claims)
+            .map_err(|err| {
+                error!(tenant_id = %version.tenant_id, error = %err, "Failed to sign capability policy");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Unable to sign capability policy".to_string(),
+                )
+            })?;
+        documents.push(PolicyDocument {
+            tenant_id: version.tenant_id,
+            version: version.version,
+            token,
+        });
+    }
+    Ok(Json(PolicyDocumentList { documents }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_grants_normalises_and_rejects_unknown_values() {
+        let mut grants = BTreeMap::new();
+        grants.insert(
+            "customer_write".to_string(),
+            vec![
+                "Cashier".to_string(),
+                "manager".to_string(),
+                "cashier".to_string(),
+            ],
+        );
+        let validated = validate_grants(&grants).expect("valid grants");
+        assert_eq!(
+            validated,
+            vec![(
+                Capability::CustomerWrite,
+                vec!["cashier".to_string(), "manager".to_string()]
+            )]
+        );
+
+        grants.insert("teleport".to_string(), vec![]);
+        assert!(validate_grants(&grants).is_err());
+
+        let mut grants = BTreeMap::new();
+        grants.insert("gdpr_manage".to_string(), vec!["super_admin".to_string()]);
+        assert!(validate_grants(&grants).is_err());
+    }
+}
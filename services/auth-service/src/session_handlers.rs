@@ -0,0 +1,314 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_auth::AuthContext;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::FromRow;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notifications::MfaActivityEvent;
+use crate::user_handlers::{
+    ensure_role_any, ensure_tenant_access, extract_refresh_cookie, extract_tenant_id,
+    LoginMetadata,
+};
+use crate::AppState;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SessionView {
+    pub id: Uuid,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub device_fingerprint: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// True for the session whose refresh cookie accompanied this request.
+    #[sqlx(skip)]
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokedSessions {
+    pub revoked: Vec<Uuid>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevokeAllQuery {
+    /// Keep the session making the request signed in ("sign out everywhere else").
+    #[serde(default)]
+    pub except_current: bool,
+}
+
+#[derive(FromRow)]
+struct SessionOwner {
+    role: String,
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+async fn active_sessions(
+    state: &AppState,
+    user_id: Uuid,
+    current: Option<Uuid>,
+) -> Result<Vec<SessionView>, (StatusCode, String)> {
+    let mut sessions = sqlx::query_as::<_, SessionView>(
+        "SELECT id, ip_address, user_agent, device_fingerprint, created_at, last_used_at, expires_at
+         FROM auth_sessions
+         WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+         ORDER BY last_used_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+    for session in &mut sessions {
+        session.current = Some(session.id) == current;
+    }
+    Ok(sessions)
+}
+
+async fn current_session(state: &AppState, headers: &HeaderMap) -> Option<Uuid> {
+    let cookie = extract_refresh_cookie(headers, state.config.as_ref())?;
+    match state.token_signer.session_for_refresh_token(&cookie).await {
+        Ok(session_id) => session_id,
+        Err(err) => {
+            warn!(error = %err, "Failed to resolve current session from refresh cookie");
+            None
+        }
+    }
+}
+
+async fn revoke(
+    state: &AppState,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    except: Option<Uuid>,
+    actor: Uuid,
+    reason: &str,
+) -> Result<Vec<Uuid>, (StatusCode, String)> {
+    state
+        .token_signer
+        .revoke_sessions(user_id, session_id, except, Some(actor), reason)
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Unable to revoke sessions: {err}"),
+            )
+        })
+}
+
+/// Publishes one audit event per revocation request on the security activity stream.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn emit_session_event(
+    state: &AppState,
+    action: &'static str,
+    tenant_id: Uuid,
+    user_id: Uuid,
+    role: Option<String>,
+    actor: Uuid,
+    revoked: &[Uuid],
+    reason: &str,
+    metadata: &LoginMetadata,
+) {
+    let trace_id = Uuid::new_v4();
+    info!(
+        security_event = action,
+        user_id = %user_id,
+        tenant_id = %tenant_id,
+        actor_id = %actor,
+        revoked = revoked.len(),
+        reason,
+        trace_id = %trace_id,
+        "Sessions revoked"
+    );
+    state
+        .emit_mfa_activity(
+            MfaActivityEvent {
+                action,
+                severity: "info",
+                tenant_id,
+                user_id: Some(user_id),
+                trace_id,
+                occurred_at: Utc::now(),
+                ip: metadata.ip.clone(),
+                user_agent: metadata.user_agent.clone(),
+                device: metadata.device_fingerprint.clone(),
+                role,
+                detail: Some(
+                    json!({
+                        "revoked_by": actor,
+                        "reason": reason,
+                        "session_ids": revoked,
+                    })
+                    .to_string(),
+                ),
+            },
+            None,
+        )
+        .await;
+}
+
+async fn load_owner(
+    state: &AppState,
+    user_id: Uuid,
+    tenant_id: Uuid,
+) -> Result<SessionOwner, (StatusCode, String)> {
+    sqlx::query_as::<_, SessionOwner>("SELECT role FROM users WHERE id = $1 AND tenant_id = $2")
+        .bind(user_id)
+        .bind(tenant_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
+
+fn caller_role(auth: &AuthContext) -> Option<String> {
+    auth.claims.roles.first().cloned()
+}
+
+pub async fn list_my_sessions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SessionView>>, (StatusCode, String)> {
+    let current = current_session(&state, &headers).await;
+    let sessions = active_sessions(&state, auth.claims.subject, current).await?;
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_my_session(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(session_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user_id = auth.claims.subject;
+    let revoked = revoke(&state, user_id, Some(session_id), None, user_id, "user_revoked").await?;
+    if revoked.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Session not found".to_string()));
+    }
+    emit_session_event(
+        &state,
+        "auth.session.revoked",
+        auth.claims.tenant_id,
+        user_id,
+        caller_role(&auth),
+        user_id,
+        &revoked,
+        "user_revoked",
+        &LoginMetadata::from_headers(&headers, None),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn revoke_my_sessions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<RevokeAllQuery>,
+    headers: HeaderMap,
+) -> Result<Json<RevokedSessions>, (StatusCode, String)> {
+    let user_id = auth.claims.subject;
+    let except = if query.except_current {
+        current_session(&state, &headers).await
+    } else {
+        None
+    };
+    let revoked = revoke(&state, user_id, None, except, user_id, "user_revoked_all").await?;
+    emit_session_event(
+        &state,
+        "auth.session.revoked_all",
+        auth.claims.tenant_id,
+        user_id,
+        caller_role(&auth),
+        user_id,
+        &revoked,
+        "user_revoked_all",
+        &LoginMetadata::from_headers(&headers, None),
+    )
+    .await;
+    Ok(Json(RevokedSessions { revoked }))
+}
+
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SessionView>>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    load_owner(&state, user_id, tenant_id).await?;
+    let sessions = active_sessions(&state, user_id, None).await?;
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_user_session(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path((user_id, session_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let owner = load_owner(&state, user_id, tenant_id).await?;
+    let actor = auth.claims.subject;
+    let revoked = revoke(&state, user_id, Some(session_id), None, actor, "admin_revoked").await?;
+    if revoked.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Session not found".to_string()));
+    }
+    emit_session_event(
+        &state,
+        "auth.session.revoked",
+        tenant_id,
+        user_id,
+        Some(owner.role),
+        actor,
+        &revoked,
+        "admin_revoked",
+        &LoginMetadata::from_headers(&headers, None),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs a user out of every device. Access tokens already issued stay valid until they expire.
+pub async fn force_logout_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<RevokedSessions>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let owner = load_owner(&state, user_id, tenant_id).await?;
+    let actor = auth.claims.subject;
+    let revoked = revoke(&state, user_id, None, None, actor, "admin_forced_logout").await?;
+    emit_session_event(
+        &state,
+        "auth.session.forced_logout",
+        tenant_id,
+        user_id,
+        Some(owner.role),
+        actor,
+        &revoked,
+        "admin_forced_logout",
+        &LoginMetadata::from_headers(&headers, None),
+    )
+    .await;
+    Ok(Json(RevokedSessions { revoked }))
+}
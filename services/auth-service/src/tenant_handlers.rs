@@ -6,25 +6,36 @@ use axum::{
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use chrono::{DateTime, Utc};
+use common_db::residency::{is_valid_region, normalize_region};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use sqlx::FromRow;
+use sqlx::{FromRow, Row};
 use uuid::Uuid;
 
+use crate::notifications::IntegrationKeyEvent;
+use crate::tenant_provisioning_handlers::run_provisioning;
 use crate::{user_handlers::extract_tenant_id, AppState};
 
-const ROOT_TENANT_ID: Uuid = Uuid::from_u128(1);
+const KEY_CREATED_ACTION: &str = "integration_key.created";
+pub(crate) const KEY_REVOKED_ACTION: &str = "integration_key.revoked";
+
+pub(crate) const ROOT_TENANT_ID: Uuid = Uuid::from_u128(1);
 
 #[derive(Deserialize)]
 pub struct NewTenant {
     pub name: String,
+    /// Region the tenant's data must stay in (e.g. `eu`); omitted for unrestricted tenants.
+    #[serde(default)]
+    pub residency: Option<String>,
 }
 
 #[derive(Serialize, FromRow)]
 pub struct TenantRow {
     pub id: Uuid,
     pub name: String,
+    pub status: String,
+    pub residency: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -63,21 +74,48 @@ pub async fn create_tenant(
     if name.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "Tenant name is required".into()));
     }
+    let residency = payload
+        .residency
+        .as_deref()
+        .map(normalize_region)
+        .filter(|region| !region.is_empty());
+    if let Some(region) = residency.as_deref() {
+        if !is_valid_region(region) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid residency region `{region}`"),
+            ));
+        }
+    }
 
+    let create_error = |err: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create tenant: {err}"),
+        )
+    };
     let tenant_id = Uuid::new_v4();
+    let mut tx = state.db.begin().await.map_err(create_error)?;
     let tenant = sqlx::query_as::<_, TenantRow>(
-        "INSERT INTO tenants (id, name) VALUES ($1, $2) RETURNING id, name",
+        "INSERT INTO tenants (id, name, residency) VALUES ($1, $2, $3)
+         RETURNING id, name, status, residency",
     )
     .bind(tenant_id)
     .bind(name)
-    .fetch_one(&state.db)
+    .bind(residency.as_deref())
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|err| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create tenant: {err}"),
-        )
-    })?;
+    .map_err(create_error)?;
+    sqlx::query("INSERT INTO tenant_provisioning (tenant_id) VALUES ($1)")
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(create_error)?;
+    tx.commit().await.map_err(create_error)?;
+
+    // Seeds keys and default data in every service; progress is visible via
+    // GET /tenants/:tenant_id/provisioning.
+    tokio::spawn(run_provisioning(state.clone(), tenant_id));
 
     Ok(Json(tenant))
 }
@@ -87,7 +125,7 @@ pub async fn list_tenants(
     headers: HeaderMap,
 ) -> Result<Json<Vec<TenantRow>>, (StatusCode, String)> {
     ensure_super_admin(&headers)?;
-    let tenants = sqlx::query_as::<_, TenantRow>("SELECT id, name FROM tenants ORDER BY name")
+    let tenants = sqlx::query_as::<_, TenantRow>("SELECT id, name, status, residency FROM tenants ORDER BY name")
         .fetch_all(&state.db)
         .await
         .map_err(|err| {
@@ -143,17 +181,30 @@ pub async fn create_integration_key(
     }
 
     if payload.revoke_existing.unwrap_or(false) {
-        if let Err(err) = sqlx::query(
-            "UPDATE integration_keys SET revoked_at = NOW() WHERE tenant_id = $1 AND revoked_at IS NULL",
+        let revoked = sqlx::query(
+            "UPDATE integration_keys SET revoked_at = NOW() WHERE tenant_id = $1 AND revoked_at IS NULL
+             RETURNING id, api_key_hash, key_suffix",
         )
         .bind(tenant_id)
-        .execute(&state.db)
+        .fetch_all(&state.db)
         .await
-        {
-            return Err((
+        .map_err(|err| {
+            (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Failed to revoke existing keys: {err}"),
-            ));
+            )
+        })?;
+        for row in revoked {
+            state
+                .emit_integration_key_event(IntegrationKeyEvent {
+                    action: KEY_REVOKED_ACTION,
+                    tenant_id,
+                    key_id: row.get("id"),
+                    api_key_hash: row.get("api_key_hash"),
+                    key_suffix: row.get("key_suffix"),
+                    occurred_at: Utc::now(),
+                })
+                .await;
         }
     }
 
@@ -188,6 +239,17 @@ pub async fn create_integration_key(
         )
     })?;
 
+    state
+        .emit_integration_key_event(IntegrationKeyEvent {
+            action: KEY_CREATED_ACTION,
+            tenant_id: record.tenant_id,
+            key_id: record.id,
+            api_key_hash: key_hash,
+            key_suffix: record.key_suffix.clone(),
+            occurred_at: record.created_at,
+        })
+        .await;
+
     Ok(Json(IntegrationKeyCreated {
         id: record.id,
         tenant_id: record.tenant_id,
@@ -238,11 +300,11 @@ pub async fn revoke_integration_key(
         ));
     }
 
-    let updated = sqlx::query_as::<_, IntegrationKeyRow>(
+    let updated = sqlx::query(
         "UPDATE integration_keys
          SET revoked_at = NOW()
          WHERE id = $1 AND revoked_at IS NULL
-         RETURNING id, tenant_id, label, key_suffix, created_at, revoked_at",
+         RETURNING id, tenant_id, label, key_suffix, created_at, revoked_at, api_key_hash",
     )
     .bind(key_id)
     .fetch_optional(&state.db)
@@ -254,16 +316,35 @@ pub async fn revoke_integration_key(
         )
     })?;
 
-    match updated {
-        Some(row) => Ok(Json(row)),
-        None => Err((
+    let Some(row) = updated else {
+        return Err((
             StatusCode::CONFLICT,
             "Integration key already revoked".into(),
-        )),
-    }
+        ));
+    };
+    let api_key_hash: String = row.get("api_key_hash");
+    let revoked = IntegrationKeyRow::from_row(&row).map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to decode integration key: {err}"),
+        )
+    })?;
+
+    state
+        .emit_integration_key_event(IntegrationKeyEvent {
+            action: KEY_REVOKED_ACTION,
+            tenant_id: revoked.tenant_id,
+            key_id: revoked.id,
+            api_key_hash,
+            key_suffix: revoked.key_suffix.clone(),
+            occurred_at: revoked.revoked_at.unwrap_or_else(Utc::now),
+        })
+        .await;
+
+    Ok(Json(revoked))
 }
 
-fn ensure_super_admin(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+pub(crate) fn ensure_super_admin(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
     let tenant = extract_tenant_id(headers)?;
     if tenant == ROOT_TENANT_ID {
         Ok(())
@@ -0,0 +1,674 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_auth::{AuthContext, TenantStatus};
+use common_crypto::EncryptedColumn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{FromRow, Row};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::notifications::{IntegrationKeyEvent, MfaActivityEvent};
+use crate::tenant_handlers::{KEY_REVOKED_ACTION, ROOT_TENANT_ID};
+use crate::tokens::{TokenSubject, SERVICE_PRINCIPAL_ID};
+use crate::user_handlers::{ensure_role_any, ensure_tenant_access};
+use crate::AppState;
+
+const AUTH_SERVICE_SOURCE: &str = "auth-service";
+const EXPORT_BUNDLE_FIELD: &str = "tenant_exports.bundle";
+/// Lifetime of the service token each export source is called with.
+const EXPORT_TOKEN_TTL_SECONDS: i64 = 300;
+const EXPORT_PURGE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Deserialize)]
+pub struct TenantStatusChange {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TenantLifecycleView {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub status_reason: Option<String>,
+    pub status_changed_at: Option<DateTime<Utc>>,
+    pub status_changed_by: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantStatusEntry {
+    pub tenant_id: Uuid,
+    pub status: TenantStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantStatusList {
+    pub tenants: Vec<TenantStatusEntry>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TenantExportView {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub status: String,
+    pub requested_by: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    /// The bundle is deleted at this time if nobody has downloaded it.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Set by the one download the bundle allows.
+    pub downloaded_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<Value>,
+}
+
+const EXPORT_VIEW_COLUMNS: &str =
+    "id, tenant_id, status, requested_by, requested_at, completed_at, error, expires_at, downloaded_at";
+
+struct RevokedKey {
+    id: Uuid,
+    api_key_hash: String,
+    key_suffix: String,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+async fn load_tenant(
+    state: &AppState,
+    tenant_id: Uuid,
+) -> Result<TenantLifecycleView, (StatusCode, String)> {
+    sqlx::query_as::<_, TenantLifecycleView>(
+        "SELECT id, name, status, status_reason, status_changed_at, status_changed_by
+         FROM tenants WHERE id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Tenant not found".to_string()))
+}
+
+/// Current lifecycle status straight from the database, used by login and session refresh.
+pub(crate) async fn tenant_status(state: &AppState, tenant_id: Uuid) -> Result<TenantStatus, sqlx::Error> {
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(status
+        .as_deref()
+        .and_then(TenantStatus::parse)
+        .unwrap_or(TenantStatus::Active))
+}
+
+/// Region the tenant's data must be stored in, if it has one.
+pub(crate) async fn tenant_residency(state: &AppState, tenant_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    let residency: Option<Option<String>> = sqlx::query_scalar("SELECT residency FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(residency.flatten())
+}
+
+/// Applies a status transition; returns 409 when the tenant is not in one of `from`. Archiving
+/// revokes the tenant's integration keys in the same transaction, so an archived tenant never
+/// keeps a working key; the revoked keys are returned for the caller to announce.
+async fn transition(
+    state: &AppState,
+    auth: &AuthContext,
+    tenant_id: Uuid,
+    from: &[TenantStatus],
+    to: TenantStatus,
+    reason: Option<String>,
+) -> Result<(TenantLifecycleView, Vec<RevokedKey>), (StatusCode, String)> {
+    ensure_role_any(auth, &["super_admin"])?;
+    if tenant_id == ROOT_TENANT_ID {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The platform tenant cannot change status".to_string(),
+        ));
+    }
+    let reason = reason
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    if to != TenantStatus::Active && reason.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A reason is required".to_string(),
+        ));
+    }
+
+    let current = load_tenant(state, tenant_id).await?;
+    let allowed: Vec<&str> = from.iter().map(|status| status.as_str()).collect();
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    let updated = sqlx::query_as::<_, TenantLifecycleView>(
+        "UPDATE tenants
+         SET status = $2, status_reason = $3, status_changed_at = NOW(), status_changed_by = $4
+         WHERE id = $1 AND status = ANY($5)
+         RETURNING id, name, status, status_reason, status_changed_at, status_changed_by",
+    )
+    .bind(tenant_id)
+    .bind(to.as_str())
+    .bind(reason.as_deref())
+    .bind(auth.claims.subject)
+    .bind(&allowed)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            format!(
+                "Tenant is {} and cannot become {}",
+                current.status,
+                to.as_str()
+            ),
+        )
+    })?;
+    let revoked = if to == TenantStatus::Archived {
+        sqlx::query(
+            "UPDATE integration_keys SET revoked_at = NOW() WHERE tenant_id = $1 AND revoked_at IS NULL
+             RETURNING id, api_key_hash, key_suffix, revoked_at",
+        )
+        .bind(tenant_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|row| RevokedKey {
+            id: row.get("id"),
+            api_key_hash: row.get("api_key_hash"),
+            key_suffix: row.get("key_suffix"),
+            revoked_at: row.get("revoked_at"),
+        })
+        .collect()
+    } else {
+        Vec::new()
+    };
+    tx.commit().await.map_err(db_error)?;
+
+    // Apply locally right away; other services converge via `/tenant-status`.
+    state.jwt_verifier.tenant_statuses().set(tenant_id, to);
+
+    if to != TenantStatus::Active {
+        match state
+            .token_signer
+            .revoke_tenant_sessions(tenant_id, Some(auth.claims.subject), to.as_str())
+            .await
+        {
+            Ok(count) => info!(tenant_id = %tenant_id, count, "Revoked sessions for inactive tenant"),
+            Err(err) => warn!(tenant_id = %tenant_id, error = %err, "Failed to revoke tenant sessions"),
+        }
+    }
+
+    let action = match to {
+        TenantStatus::Active => "tenant.reactivated",
+        TenantStatus::Suspended => "tenant.suspended",
+        TenantStatus::Archived => "tenant.archived",
+    };
+    let trace_id = Uuid::new_v4();
+    info!(
+        security_event = action,
+        tenant_id = %tenant_id,
+        actor_id = %auth.claims.subject,
+        previous_status = %current.status,
+        trace_id = %trace_id,
+        "Tenant status changed"
+    );
+    state
+        .emit_mfa_activity(
+            MfaActivityEvent {
+                action,
+                severity: if to == TenantStatus::Active { "info" } else { "warn" },
+                tenant_id,
+                user_id: None,
+                trace_id,
+                occurred_at: Utc::now(),
+                ip: None,
+                user_agent: None,
+                device: None,
+                role: None,
+                detail: Some(
+                    json!({
+                        "changed_by": auth.claims.subject,
+                        "previous_status": current.status,
+                        "reason": reason,
+                    })
+                    .to_string(),
+                ),
+            },
+            None,
+        )
+        .await;
+
+    Ok((updated, revoked))
+}
+
+pub async fn suspend_tenant(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+    Json(payload): Json<TenantStatusChange>,
+) -> Result<Json<TenantLifecycleView>, (StatusCode, String)> {
+    let (tenant, _) = transition(
+        &state,
+        &auth,
+        tenant_id,
+        &[TenantStatus::Active],
+        TenantStatus::Suspended,
+        payload.reason,
+    )
+    .await?;
+    Ok(Json(tenant))
+}
+
+pub async fn reactivate_tenant(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<TenantLifecycleView>, (StatusCode, String)> {
+    let (tenant, _) = transition(
+        &state,
+        &auth,
+        tenant_id,
+        &[TenantStatus::Suspended],
+        TenantStatus::Active,
+        None,
+    )
+    .await?;
+    Ok(Json(tenant))
+}
+
+/// Archiving is terminal: sessions are revoked and integration keys retired.
+pub async fn archive_tenant(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+    Json(payload): Json<TenantStatusChange>,
+) -> Result<Json<TenantLifecycleView>, (StatusCode, String)> {
+    let (tenant, revoked) = transition(
+        &state,
+        &auth,
+        tenant_id,
+        &[TenantStatus::Active, TenantStatus::Suspended],
+        TenantStatus::Archived,
+        payload.reason,
+    )
+    .await?;
+
+    for key in revoked {
+        state
+            .emit_integration_key_event(IntegrationKeyEvent {
+                action: KEY_REVOKED_ACTION,
+                tenant_id,
+                key_id: key.id,
+                api_key_hash: key.api_key_hash,
+                key_suffix: key.key_suffix,
+                occurred_at: key.revoked_at.unwrap_or_else(Utc::now),
+            })
+            .await;
+    }
+
+    Ok(Json(tenant))
+}
+
+/// Non-active tenants for services' verifiers. Callers are super admins or services presenting
+/// `AUTH_TENANT_STATUS_TOKEN` in `X-Internal-Token`.
+pub async fn list_tenant_statuses(
+    State(state): State<AppState>,
+    auth: Option<AuthContext>,
+    headers: HeaderMap,
+) -> Result<Json<TenantStatusList>, (StatusCode, String)> {
+    let internal = state
+        .config
+        .tenant_status_token
+        .as_deref()
+        .is_some_and(|expected| internal_token_matches(&headers, expected));
+    if !internal {
+        let auth = auth.ok_or((StatusCode::UNAUTHORIZED, "Authentication required".to_string()))?;
+        ensure_role_any(&auth, &["super_admin"])?;
+    }
+    let rows = sqlx::query("SELECT id, status FROM tenants WHERE status <> 'active'")
+        .fetch_all(&state.db)
+        .await
+        .map_err(db_error)?;
+    let tenants = rows
+        .into_iter()
+        .filter_map(|row| {
+            let status: String = row.get("status");
+            Some(TenantStatusEntry {
+                tenant_id: row.get("id"),
+                status: TenantStatus::parse(&status)?,
+            })
+        })
+        .collect();
+    Ok(Json(TenantStatusList { tenants }))
+}
+
+pub(crate) fn internal_token_matches(headers: &HeaderMap, expected: &str) -> bool {
+    let provided = headers
+        .get("X-Internal-Token")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+pub async fn request_tenant_export(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<TenantExportView>), (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    if state.config.tenant_export_key.is_none() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Tenant exports are disabled: AUTH_TENANT_EXPORT_KEY is not configured".to_string(),
+        ));
+    }
+    load_tenant(&state, tenant_id).await?;
+
+    let export = sqlx::query_as::<_, TenantExportView>(&format!(
+        "INSERT INTO tenant_exports (id, tenant_id, requested_by)
+         VALUES ($1, $2, $3)
+         RETURNING {EXPORT_VIEW_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(auth.claims.subject)
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    info!(
+        tenant_id = %tenant_id,
+        export_id = %export.id,
+        actor_id = %auth.claims.subject,
+        "Tenant data export requested"
+    );
+    tokio::spawn(run_export(state.clone(), export.id, tenant_id));
+
+    Ok((StatusCode::ACCEPTED, Json(export)))
+}
+
+pub async fn list_tenant_exports(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Vec<TenantExportView>>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let exports = sqlx::query_as::<_, TenantExportView>(&format!(
+        "SELECT {EXPORT_VIEW_COLUMNS}
+         FROM tenant_exports WHERE tenant_id = $1 ORDER BY requested_at DESC"
+    ))
+    .bind(tenant_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+    Ok(Json(exports))
+}
+
+/// Export status, with the bundle the first time it is read after completing. Reading it deletes
+/// it, so a second download (or one after `expires_at`) returns the status alone.
+pub async fn get_tenant_export(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path((tenant_id, export_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TenantExportView>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    ensure_tenant_access(&auth, tenant_id)?;
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    // Taking the bundle and clearing it in one statement means concurrent reads get it once.
+    let taken: Option<EncryptedColumn<String>> = sqlx::query_scalar(
+        "WITH taken AS (
+            SELECT id, bundle_encrypted FROM tenant_exports
+            WHERE id = $1 AND tenant_id = $2 AND bundle_encrypted IS NOT NULL AND expires_at > NOW()
+            FOR UPDATE
+         )
+         UPDATE tenant_exports e SET bundle_encrypted = NULL, downloaded_at = NOW()
+         FROM taken WHERE e.id = taken.id
+         RETURNING taken.bundle_encrypted",
+    )
+    .bind(export_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    let mut export = sqlx::query_as::<_, TenantExportView>(&format!(
+        "SELECT {EXPORT_VIEW_COLUMNS} FROM tenant_exports WHERE id = $1 AND tenant_id = $2"
+    ))
+    .bind(export_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Export not found".to_string()))?;
+    if let Some(sealed) = taken {
+        let key = state.config.tenant_export_key.as_ref().ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "AUTH_TENANT_EXPORT_KEY is not configured".to_string(),
+        ))?;
+        let bundle = key
+            .open(tenant_id.as_bytes(), EXPORT_BUNDLE_FIELD, &sealed)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Export bundle could not be decrypted".to_string(),
+            ))?;
+        export.bundle = Some(bundle);
+        info!(export_id = %export_id, tenant_id = %tenant_id, actor_id = %auth.claims.subject, "Tenant export downloaded");
+    }
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(export))
+}
+
+/// Periodically deletes export bundles that were never downloaded before `expires_at`.
+pub fn spawn_tenant_export_purge(state: AppState) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(EXPORT_PURGE_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match sqlx::query(
+                "UPDATE tenant_exports SET bundle_encrypted = NULL
+                 WHERE bundle_encrypted IS NOT NULL AND expires_at <= NOW()",
+            )
+            .execute(&state.db)
+            .await
+            {
+                Ok(result) if result.rows_affected() > 0 => {
+                    info!(purged = result.rows_affected(), "Purged expired tenant export bundles")
+                }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "Failed to purge expired tenant export bundles"),
+            }
+        }
+    });
+}
+
+/// Collects auth-service's own records plus every configured service's
+/// `GET /tenants/:tenant_id/export` payload into one bundle, calling the services with a short-lived
+/// token for the service principal rather than the requester's. Failed sources are listed under
+/// `errors` and mark the export failed while keeping the partial bundle.
+async fn run_export(state: AppState, export_id: Uuid, tenant_id: Uuid) {
+    if let Err(err) = sqlx::query("UPDATE tenant_exports SET status = 'running' WHERE id = $1")
+        .bind(export_id)
+        .execute(&state.db)
+        .await
+    {
+        error!(export_id = %export_id, error = %err, "Failed to start tenant export");
+        return;
+    }
+
+    let mut services = Map::new();
+    let mut errors = Map::new();
+
+    match export_auth_records(&state, tenant_id).await {
+        Ok(records) => {
+            services.insert(AUTH_SERVICE_SOURCE.to_string(), records);
+        }
+        Err(err) => {
+            errors.insert(AUTH_SERVICE_SOURCE.to_string(), json!(err.to_string()));
+        }
+    }
+
+    let token = match service_token(&state, tenant_id).await {
+        Ok(token) => Some(token),
+        Err(err) => {
+            warn!(export_id = %export_id, error = %err, "Failed to issue tenant export service token");
+            None
+        }
+    };
+    for (service, base_url) in &state.config.tenant_export_sources {
+        let Some((token, residency)) = token.as_ref() else {
+            errors.insert(service.clone(), json!("service token unavailable"));
+            continue;
+        };
+        let url = format!("{base_url}/tenants/{tenant_id}/export");
+        let result = async {
+            let mut request = state
+                .http_client
+                .get(&url)
+                .bearer_auth(token)
+                .header("X-Tenant-ID", tenant_id.to_string())
+                .header("X-Roles", "super_admin")
+                .header("X-User-ID", SERVICE_PRINCIPAL_ID.to_string());
+            if let Some(region) = residency {
+                request = request.header("X-Residency", region);
+            }
+            request
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await
+        }
+        .await;
+        match result {
+            Ok(payload) => {
+                services.insert(service.clone(), payload);
+            }
+            Err(err) => {
+                warn!(export_id = %export_id, service = %service, error = %err, "Tenant export source failed");
+                errors.insert(service.clone(), json!(err.to_string()));
+            }
+        }
+    }
+
+    let failed = !errors.is_empty();
+    let summary = failed.then(|| {
+        format!(
+            "Export failed for: {}",
+            errors.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    });
+    let bundle = json!({
+        "tenant_id": tenant_id,
+        "generated_at": Utc::now(),
+        "services": services,
+        "errors": errors,
+    });
+
+    let sealed = match state.config.tenant_export_key.as_ref() {
+        Some(key) => key.seal(tenant_id.as_bytes(), EXPORT_BUNDLE_FIELD, &bundle.to_string()),
+        None => {
+            error!(export_id = %export_id, "AUTH_TENANT_EXPORT_KEY missing; discarding tenant export bundle");
+            return;
+        }
+    };
+    let sealed = match sealed {
+        Ok(sealed) => sealed,
+        Err(err) => {
+            error!(export_id = %export_id, error = %err, "Failed to seal tenant export bundle");
+            return;
+        }
+    };
+    if let Err(err) = sqlx::query(
+        "UPDATE tenant_exports
+         SET status = $2, completed_at = NOW(), error = $3, bundle_encrypted = $4,
+             expires_at = NOW() + make_interval(hours => $5)
+         WHERE id = $1",
+    )
+    .bind(export_id)
+    .bind(if failed { "failed" } else { "completed" })
+    .bind(summary.as_deref())
+    .bind(&sealed)
+    .bind(state.config.tenant_export_ttl_hours as i32)
+    .execute(&state.db)
+    .await
+    {
+        error!(export_id = %export_id, error = %err, "Failed to store tenant export bundle");
+        return;
+    }
+    info!(export_id = %export_id, tenant_id = %tenant_id, failed, "Tenant data export finished");
+}
+
+/// Token for the service principal acting in `tenant_id`, plus the residency it carries.
+async fn service_token(state: &AppState, tenant_id: Uuid) -> anyhow::Result<(String, Option<String>)> {
+    let residency = tenant_residency(state, tenant_id).await?;
+    let token = state.token_signer.issue_service_token(
+        &TokenSubject {
+            user_id: SERVICE_PRINCIPAL_ID,
+            tenant_id,
+            roles: vec!["super_admin".to_string()],
+            residency: residency.clone(),
+        },
+        EXPORT_TOKEN_TTL_SECONDS,
+    )?;
+    Ok((token, residency))
+}
+
+async fn export_auth_records(state: &AppState, tenant_id: Uuid) -> Result<Value, sqlx::Error> {
+    // Secrets (password hashes, MFA secrets, API key hashes, IdP client secrets) are never exported.
+    let queries = [
+        (
+            "tenant",
+            "SELECT row_to_json(t) FROM (SELECT id, name, status, status_reason, status_changed_at FROM tenants WHERE id = $1) t",
+        ),
+        (
+            "users",
+            "SELECT COALESCE(json_agg(t), '[]'::json) FROM (SELECT id, name, email, role, is_active, created_at, updated_at FROM users WHERE tenant_id = $1) t",
+        ),
+        (
+            "integration_keys",
+            "SELECT COALESCE(json_agg(t), '[]'::json) FROM (SELECT id, label, key_suffix, created_at, revoked_at FROM integration_keys WHERE tenant_id = $1) t",
+        ),
+        (
+            "identity_providers",
+            "SELECT COALESCE(json_agg(t), '[]'::json) FROM (SELECT id, issuer, client_id, scopes, allowed_domains, default_role, role_mappings, jit_provisioning, is_enabled FROM tenant_identity_providers WHERE tenant_id = $1) t",
+        ),
+        (
+            "capability_policies",
+            "SELECT COALESCE(json_agg(t), '[]'::json) FROM (SELECT capability, roles FROM tenant_capability_policies WHERE tenant_id = $1) t",
+        ),
+    ];
+    let mut records = Map::new();
+    for (name, sql) in queries {
+        let value: Option<Value> = sqlx::query_scalar(sql)
+            .bind(tenant_id)
+            .fetch_one(&state.db)
+            .await?;
+        records.insert(name.to_string(), value.unwrap_or(Value::Null));
+    }
+    Ok(Value::Object(records))
+}
@@ -0,0 +1,342 @@
+use std::env;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sqlx::FromRow;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::notifications::TenantLifecycleEvent;
+use crate::policy_handlers::seed_default_policy;
+use crate::tenant_handlers::ensure_super_admin;
+use crate::tenant_lifecycle_handlers::tenant_residency;
+use crate::tokens::{TokenSubject, SERVICE_PRINCIPAL_ID};
+use crate::AppState;
+
+const TENANT_PROVISIONED_ACTION: &str = "tenant.provisioned";
+/// Step key for the work auth-service does itself: seeding the tenant's default role grants.
+const AUTH_STEP: &str = "auth-service";
+const SERVICE_TOKEN_TTL_SECONDS: i64 = 120;
+/// A `running` row untouched for this long is assumed to belong to a crashed worker.
+const STALE_RUN_MINUTES: i32 = 10;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TenantProvisioningView {
+    pub tenant_id: Uuid,
+    pub status: String,
+    pub steps: Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+fn db_error(err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {err}"),
+    )
+}
+
+/// Exponential backoff between automatic attempts: 30s, 60s, 120s, ... capped at one hour.
+pub(crate) fn retry_backoff(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(1, 8) as u32 - 1;
+    chrono::Duration::seconds((30_i64 << exponent).min(3_600))
+}
+
+fn step_completed(steps: &Map<String, Value>, service: &str) -> bool {
+    steps
+        .get(service)
+        .and_then(|step| step.get("status"))
+        .and_then(Value::as_str)
+        == Some("completed")
+}
+
+async fn load_provisioning(
+    state: &AppState,
+    tenant_id: Uuid,
+) -> Result<TenantProvisioningView, (StatusCode, String)> {
+    sqlx::query_as::<_, TenantProvisioningView>(
+        "SELECT tenant_id, status, steps, attempts, last_error, next_attempt_at, created_at, updated_at, completed_at
+         FROM tenant_provisioning WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "No provisioning record for tenant".to_string(),
+    ))
+}
+
+pub async fn get_tenant_provisioning(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<TenantProvisioningView>, (StatusCode, String)> {
+    ensure_super_admin(&headers)?;
+    Ok(Json(load_provisioning(&state, tenant_id).await?))
+}
+
+/// Re-runs the failed steps immediately, regardless of the automatic retry budget.
+pub async fn retry_tenant_provisioning(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<TenantProvisioningView>), (StatusCode, String)> {
+    ensure_super_admin(&headers)?;
+    let current = load_provisioning(&state, tenant_id).await?;
+    if current.status != "failed" {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Provisioning is {}; only failed runs can be retried", current.status),
+        ));
+    }
+    sqlx::query(
+        "UPDATE tenant_provisioning SET status = 'pending', next_attempt_at = NULL, updated_at = NOW()
+         WHERE tenant_id = $1 AND status = 'failed'",
+    )
+    .bind(tenant_id)
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    tokio::spawn(run_provisioning(state.clone(), tenant_id));
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(load_provisioning(&state, tenant_id).await?),
+    ))
+}
+
+/// Runs every configured provisioning step that has not yet succeeded and publishes
+/// `tenant.provisioned` once all of them have. Safe to call concurrently: only the caller that
+/// claims the row does any work.
+pub(crate) async fn run_provisioning(state: AppState, tenant_id: Uuid) {
+    let claimed: Option<(Value, i32)> = match sqlx::query_as(
+        "UPDATE tenant_provisioning
+         SET status = 'running', attempts = attempts + 1, updated_at = NOW()
+         WHERE tenant_id = $1
+           AND (status IN ('pending', 'failed')
+                OR (status = 'running' AND updated_at < NOW() - make_interval(mins => $2)))
+         RETURNING steps, attempts",
+    )
+    .bind(tenant_id)
+    .bind(STALE_RUN_MINUTES)
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(claimed) => claimed,
+        Err(err) => {
+            error!(tenant_id = %tenant_id, error = %err, "Failed to claim tenant provisioning");
+            return;
+        }
+    };
+    let Some((steps, attempts)) = claimed else {
+        return;
+    };
+    let mut steps = match steps {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+
+    // Services seed the tenant's data in its residency region, so the token and headers carry it.
+    let token = tenant_residency(&state, tenant_id)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|residency| {
+            let token = state.token_signer.issue_service_token(
+                &TokenSubject {
+                    user_id: SERVICE_PRINCIPAL_ID,
+                    tenant_id,
+                    roles: vec!["super_admin".to_string()],
+                    residency: residency.clone(),
+                },
+                SERVICE_TOKEN_TTL_SECONDS,
+            )?;
+            Ok((token, residency))
+        });
+
+    let mut failures = Vec::new();
+    if !step_completed(&steps, AUTH_STEP) {
+        let result = seed_default_policy(&state.db, tenant_id, SERVICE_PRINCIPAL_ID)
+            .await
+            .map(|seeded| json!({ "default_roles": { "seeded": seeded } }))
+            .map_err(|err| err.to_string());
+        record_step(&mut steps, &mut failures, tenant_id, attempts, AUTH_STEP, result);
+    }
+    for (service, base_url) in &state.config.tenant_provision_targets {
+        if step_completed(&steps, service) {
+            continue;
+        }
+        let result = match &token {
+            Ok((token, residency)) => provision_step(&state, base_url, tenant_id, token, residency.as_deref())
+                .await
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        record_step(&mut steps, &mut failures, tenant_id, attempts, service, result);
+    }
+
+    if failures.is_empty() {
+        let event = TenantLifecycleEvent {
+            action: TENANT_PROVISIONED_ACTION,
+            tenant_id,
+            occurred_at: Utc::now(),
+            detail: json!({ "steps": steps.keys().collect::<Vec<_>>() }),
+        };
+        if let Err(err) = state.emit_tenant_event(event).await {
+            warn!(tenant_id = %tenant_id, error = %err, "Failed to publish tenant.provisioned");
+            failures.push(format!("{TENANT_PROVISIONED_ACTION} event"));
+        }
+    }
+
+    let failed = !failures.is_empty();
+    let last_error = failed.then(|| format!("Provisioning failed for: {}", failures.join(", ")));
+    let next_attempt_at = failed.then(|| Utc::now() + retry_backoff(attempts));
+    if let Err(err) = sqlx::query(
+        "UPDATE tenant_provisioning
+         SET status = $2, steps = $3, last_error = $4, next_attempt_at = $5, updated_at = NOW(),
+             completed_at = CASE WHEN $2 = 'completed' THEN NOW() ELSE NULL END
+         WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .bind(if failed { "failed" } else { "completed" })
+    .bind(Value::Object(steps))
+    .bind(last_error.as_deref())
+    .bind(next_attempt_at)
+    .execute(&state.db)
+    .await
+    {
+        error!(tenant_id = %tenant_id, error = %err, "Failed to record tenant provisioning outcome");
+        return;
+    }
+    info!(tenant_id = %tenant_id, attempts, failed, "Tenant provisioning run finished");
+}
+
+fn record_step(
+    steps: &mut Map<String, Value>,
+    failures: &mut Vec<String>,
+    tenant_id: Uuid,
+    attempts: i32,
+    service: &str,
+    result: Result<Value, String>,
+) {
+    match result {
+        Ok(detail) => {
+            steps.insert(
+                service.to_string(),
+                json!({ "status": "completed", "completed_at": Utc::now(), "detail": detail }),
+            );
+        }
+        Err(err) => {
+            warn!(tenant_id = %tenant_id, service = %service, attempts, error = %err, "Tenant provisioning step failed");
+            steps.insert(
+                service.to_string(),
+                json!({ "status": "failed", "failed_at": Utc::now(), "error": err }),
+            );
+            failures.push(service.to_string());
+        }
+    }
+}
+
+async fn provision_step(
+    state: &AppState,
+    base_url: &str,
+    tenant_id: Uuid,
+    token: &str,
+    residency: Option<&str>,
+) -> Result<Value, reqwest::Error> {
+    let mut request = state
+        .http_client
+        .post(format!("{base_url}/tenants/{tenant_id}/provision"))
+        .bearer_auth(token)
+        .header("X-Tenant-ID", tenant_id.to_string())
+        .header("X-Roles", "super_admin")
+        .header("X-User-ID", SERVICE_PRINCIPAL_ID.to_string());
+    if let Some(region) = residency {
+        request = request.header("X-Residency", region);
+    }
+    request
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Value>()
+        .await
+}
+
+/// Periodically retries failed provisioning runs whose backoff has elapsed, up to
+/// `TENANT_PROVISION_MAX_ATTEMPTS` automatic attempts (default 8).
+pub fn spawn_provisioning_retry(state: AppState) {
+    let interval_secs = env::var("TENANT_PROVISION_RETRY_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(60)
+        .max(5);
+    let max_attempts = env::var("TENANT_PROVISION_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|value| value.parse::<i32>().ok())
+        .unwrap_or(8);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let due: Vec<Uuid> = match sqlx::query_scalar(
+                "SELECT tenant_id FROM tenant_provisioning
+                 WHERE (status = 'failed' AND next_attempt_at <= NOW() AND attempts < $1)
+                    OR (status = 'pending' AND updated_at < NOW() - INTERVAL '1 minute')
+                    OR (status = 'running' AND updated_at < NOW() - make_interval(mins => $2))",
+            )
+            .bind(max_attempts)
+            .bind(STALE_RUN_MINUTES)
+            .fetch_all(&state.db)
+            .await
+            {
+                Ok(due) => due,
+                Err(err) => {
+                    warn!(error = %err, "Failed to load tenant provisioning retries");
+                    continue;
+                }
+            };
+            for tenant_id in due {
+                run_provisioning(state.clone(), tenant_id).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_backoff_doubles_and_caps() {
+        assert_eq!(retry_backoff(1), chrono::Duration::seconds(30));
+        assert_eq!(retry_backoff(2), chrono::Duration::seconds(60));
+        assert_eq!(retry_backoff(4), chrono::Duration::seconds(240));
+        assert_eq!(retry_backoff(20), chrono::Duration::seconds(3_600));
+    }
+
+    #[test]
+    fn only_completed_steps_are_skipped() {
+        let steps = json!({
+            "inventory-service": { "status": "completed" },
+            "customer-service": { "status": "failed", "error": "timeout" },
+        });
+        let steps = steps.as_object().unwrap();
+        assert!(step_completed(steps, "inventory-service"));
+        assert!(!step_completed(steps, "customer-service"));
+        assert!(!step_completed(steps, "order-service"));
+    }
+}
@@ -12,6 +12,9 @@ use sha2::{Digest, Sha256};
 use sqlx::{FromRow, PgPool, Row};
 use uuid::Uuid;
 
+/// Inactive root-tenant user (migration 3022) that auth-service's own service tokens are issued to.
+pub const SERVICE_PRINCIPAL_ID: Uuid = Uuid::from_u128(0x102);
+
 pub struct TokenConfig {
     pub issuer: String,
     pub audience: String,
@@ -46,6 +49,23 @@ pub struct TokenSubject {
     pub user_id: Uuid,
     pub tenant_id: Uuid,
     pub roles: Vec<String>,
+    /// The tenant's data residency region, issued as the `residency` claim.
+    pub residency: Option<String>,
+}
+
+/// The session being rotated was revoked after its refresh token was consumed. Returned (inside
+/// the `anyhow::Error`) by [`TokenSigner::issue_session_tokens`] so callers can answer with an
+/// expired session instead of a server error.
+#[derive(Debug, thiserror::Error)]
+#[error("refresh session invalid or revoked")]
+pub struct SessionRevoked;
+
+/// Client details recorded on a session so users can recognise their devices.
+#[derive(Debug, Clone, Default)]
+pub struct SessionDevice {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub device_fingerprint: Option<String>,
 }
 
 #[derive(FromRow)]
@@ -53,6 +73,8 @@ pub struct TokenSubject {
 #[derive(Debug, Clone)]
 pub struct RefreshTokenAccount {
     pub jti: Uuid,
+    /// `None` for refresh tokens issued before sessions were tracked.
+    pub session_id: Option<Uuid>,
     pub user_id: Uuid,
     pub tenant_id: Uuid,
     pub name: String,
@@ -72,6 +94,7 @@ pub struct IssuedTokens {
     pub access_expires_in: i64,
     pub refresh_expires_in: i64,
     pub token_type: &'static str,
+    pub session_id: Uuid,
 }
 
 impl TokenSigner {
@@ -208,6 +231,18 @@ impl TokenSigner {
     }
 
     pub async fn issue_tokens(&self, subject: TokenSubject) -> Result<IssuedTokens> {
+        self.issue_session_tokens(subject, None, &SessionDevice::default())
+            .await
+    }
+
+    /// Issues an access/refresh pair bound to a session. Passing the session of a consumed
+    /// refresh token keeps the rotation on the same session; `None` starts a new one.
+    pub async fn issue_session_tokens(
+        &self,
+        subject: TokenSubject,
+        session_id: Option<Uuid>,
+        device: &SessionDevice,
+    ) -> Result<IssuedTokens> {
         let now = Utc::now();
         let access_exp = now + Duration::seconds(self.config.access_ttl_seconds);
         let refresh_exp = now + Duration::seconds(self.config.refresh_ttl_seconds);
@@ -216,6 +251,7 @@ impl TokenSigner {
             sub: subject.user_id.to_string(),
             tid: subject.tenant_id.to_string(),
             roles: &subject.roles,
+            residency: subject.residency.as_deref(),
             iss: &self.config.issuer,
             aud: &self.config.audience,
             exp: access_exp.timestamp(),
@@ -233,8 +269,27 @@ impl TokenSigner {
         let refresh_hash = Self::hash_refresh_token(&refresh_token);
         let refresh_jti = Uuid::new_v4();
 
-        self.persist_refresh_token(refresh_jti, &subject, &refresh_hash, now, refresh_exp)
-            .await?;
+        let session_id = match session_id {
+            Some(session_id) => {
+                self.touch_session(session_id, device, now, refresh_exp)
+                    .await?;
+                session_id
+            }
+            None => {
+                self.create_session(&subject, device, now, refresh_exp)
+                    .await?
+            }
+        };
+
+        self.persist_refresh_token(
+            refresh_jti,
+            session_id,
+            &subject,
+            &refresh_hash,
+            now,
+            refresh_exp,
+        )
+        .await?;
 
         Ok(IssuedTokens {
             access_token,
@@ -244,9 +299,52 @@ impl TokenSigner {
             access_expires_in: self.config.access_ttl_seconds,
             refresh_expires_in: self.config.refresh_ttl_seconds,
             token_type: "Bearer",
+            session_id,
         })
     }
 
+    /// Signs a non-access-token document (e.g. a capability policy) with the active key under
+    /// a dedicated audience so it can be verified against the published JWKS.
+    pub fn sign_document<T: Serialize>(&self, audience: &str, ttl_seconds: i64, body: &T) -> Result<String> {
+        let now = Utc::now();
+        let mut claims = serde_json::to_value(body)
+            .map_err(|err| anyhow!("Failed to serialize document: {err}"))?;
+        let fields = claims
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("Signed documents must serialize to a JSON object"))?;
+        fields.insert("iss".into(), self.config.issuer.clone().into());
+        fields.insert("aud".into(), audience.into());
+        fields.insert("iat".into(), now.timestamp().into());
+        fields.insert("exp".into(), (now + Duration::seconds(ttl_seconds)).timestamp().into());
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.active_key.kid.clone());
+        encode(&header, &claims, &self.active_key.encoding_key)
+            .map_err(|err| anyhow!("Failed to sign document: {err}"))
+    }
+
+    /// Signs a short-lived access token with no session or refresh token, for calls auth-service
+    /// makes to other services on a tenant's behalf (e.g. provisioning). The subject should be
+    /// [`SERVICE_PRINCIPAL_ID`] rather than any real user.
+    pub fn issue_service_token(&self, subject: &TokenSubject, ttl_seconds: i64) -> Result<String> {
+        let now = Utc::now();
+        let claims = AccessClaims {
+            sub: subject.user_id.to_string(),
+            tid: subject.tenant_id.to_string(),
+            roles: &subject.roles,
+            residency: subject.residency.as_deref(),
+            iss: &self.config.issuer,
+            aud: &self.config.audience,
+            exp: (now + Duration::seconds(ttl_seconds)).timestamp(),
+            iat: now.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+        };
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.active_key.kid.clone());
+        encode(&header, &claims, &self.active_key.encoding_key)
+            .map_err(|err| anyhow!("Failed to sign service token: {err}"))
+    }
+
     fn generate_refresh_token() -> String {
         let mut bytes = [0u8; 32];
         OsRng.fill_bytes(&mut bytes);
@@ -260,18 +358,160 @@ impl TokenSigner {
         hasher.finalize().to_vec()
     }
 
+    async fn create_session(
+        &self,
+        subject: &TokenSubject,
+        device: &SessionDevice,
+        created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Uuid> {
+        let session_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO auth_sessions (id, user_id, tenant_id, ip_address, user_agent, device_fingerprint, created_at, last_used_at, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8)",
+        )
+        .bind(session_id)
+        .bind(subject.user_id)
+        .bind(subject.tenant_id)
+        .bind(device.ip.as_deref())
+        .bind(device.user_agent.as_deref())
+        .bind(device.device_fingerprint.as_deref())
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| anyhow!("Failed to create session: {err}"))?;
+        Ok(session_id)
+    }
+
+    async fn touch_session(
+        &self,
+        session_id: Uuid,
+        device: &SessionDevice,
+        used_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let updated = sqlx::query(
+            "UPDATE auth_sessions
+             SET last_used_at = $2,
+                 expires_at = $3,
+                 ip_address = COALESCE($4, ip_address),
+                 user_agent = COALESCE($5, user_agent)
+             WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(session_id)
+        .bind(used_at)
+        .bind(expires_at)
+        .bind(device.ip.as_deref())
+        .bind(device.user_agent.as_deref())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| anyhow!("Failed to update session: {err}"))?;
+        if updated.rows_affected() == 0 {
+            // Revoked between consuming the refresh token and rotating it.
+            return Err(SessionRevoked.into());
+        }
+        Ok(())
+    }
+
+    /// Resolves the session a refresh token belongs to without consuming it.
+    pub async fn session_for_refresh_token(&self, token: &str) -> Result<Option<Uuid>> {
+        if token.trim().is_empty() {
+            return Ok(None);
+        }
+        let hash = Self::hash_refresh_token(token);
+        let session_id = sqlx::query_scalar::<_, Option<Uuid>>(
+            "SELECT session_id FROM auth_refresh_tokens WHERE token_hash = $1",
+        )
+        .bind(hash.as_slice())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session_id.flatten())
+    }
+
+    /// Marks matching active sessions revoked and deletes their refresh tokens so the next
+    /// `/session` call is rejected. `session_id = None` targets every session of the user
+    /// (including legacy session-less tokens), optionally sparing `except`.
+    pub async fn revoke_sessions(
+        &self,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+        except: Option<Uuid>,
+        revoked_by: Option<Uuid>,
+        reason: &str,
+    ) -> Result<Vec<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+        let revoked = sqlx::query_scalar::<_, Uuid>(
+            "UPDATE auth_sessions
+             SET revoked_at = NOW(), revoked_by = $4, revoke_reason = $5
+             WHERE user_id = $1
+               AND revoked_at IS NULL
+               AND ($2::uuid IS NULL OR id = $2)
+               AND ($3::uuid IS NULL OR id <> $3)
+             RETURNING id",
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .bind(except)
+        .bind(revoked_by)
+        .bind(reason)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM auth_refresh_tokens
+             WHERE user_id = $1
+               AND (session_id = ANY($2) OR ($3 AND session_id IS NULL))",
+        )
+        .bind(user_id)
+        .bind(&revoked)
+        .bind(session_id.is_none())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(revoked)
+    }
+
+    /// Revokes every active session in a tenant, e.g. when the tenant is suspended.
+    pub async fn revoke_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        revoked_by: Option<Uuid>,
+        reason: &str,
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let revoked = sqlx::query(
+            "UPDATE auth_sessions
+             SET revoked_at = NOW(), revoked_by = $2, revoke_reason = $3
+             WHERE tenant_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(tenant_id)
+        .bind(revoked_by)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM auth_refresh_tokens WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(revoked)
+    }
+
     async fn persist_refresh_token(
         &self,
         jti: Uuid,
+        session_id: Uuid,
         subject: &TokenSubject,
         token_hash: &[u8],
         issued_at: DateTime<Utc>,
         expires_at: DateTime<Utc>,
     ) -> Result<()> {
         sqlx::query(
-            "INSERT INTO auth_refresh_tokens (jti, user_id, tenant_id, token_hash, issued_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO auth_refresh_tokens (jti, session_id, user_id, tenant_id, token_hash, issued_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
         )
         .bind(jti)
+        .bind(session_id)
         .bind(subject.user_id)
         .bind(subject.tenant_id)
         .bind(token_hash)
@@ -298,6 +538,8 @@ impl TokenSigner {
         #[derive(sqlx::FromRow)]
         struct RefreshRow {
             jti: Uuid,
+            session_id: Option<Uuid>,
+            session_revoked_at: Option<DateTime<Utc>>,
             user_id: Uuid,
             tenant_id: Uuid,
             expires_at: DateTime<Utc>,
@@ -312,13 +554,15 @@ impl TokenSigner {
         }
 
         let row = sqlx::query_as::<_, RefreshRow>(
-            "SELECT r.jti, r.user_id, r.tenant_id, r.expires_at,
+            "SELECT r.jti, r.session_id, s.revoked_at AS session_revoked_at,
+                       r.user_id, r.tenant_id, r.expires_at,
                        u.name, u.email, u.role, u.is_active,
                        u.created_at, u.updated_at, u.last_password_reset, u.force_password_reset
                 FROM auth_refresh_tokens r
                 JOIN users u ON u.id = r.user_id
+                LEFT JOIN auth_sessions s ON s.id = r.session_id
                 WHERE r.token_hash = $1
-                FOR UPDATE"
+                FOR UPDATE OF r"
         )
         .bind(hash.as_slice())
         .fetch_optional(&mut *tx)
@@ -331,11 +575,12 @@ impl TokenSigner {
                 .bind(row.jti)
                 .execute(&mut *tx)
                 .await?;
-            if row.expires_at <= now {
+            if row.expires_at <= now || row.session_revoked_at.is_some() {
                 None
             } else {
                 Some(RefreshTokenAccount {
                     jti: row.jti,
+                    session_id: row.session_id,
                     user_id: row.user_id,
                     tenant_id: row.tenant_id,
                     name: row.name,
@@ -370,6 +615,8 @@ struct AccessClaims<'a> {
     sub: String,
     tid: String,
     roles: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    residency: Option<&'a str>,
     iss: &'a str,
     aud: &'a str,
     exp: i64,
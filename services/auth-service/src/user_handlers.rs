@@ -13,25 +13,30 @@ use axum::{
     Json,
 };
 use chrono::{DateTime, Duration, SecondsFormat, Utc};
-use common_auth::AuthContext;
+use common_auth::{AuthContext, TenantStatus};
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::{FromRow, Postgres, QueryBuilder};
-use tracing::{error, info, warn, instrument, Span};
+use tracing::{error, info, instrument, warn, Span};
 use uuid::Uuid;
 
 use crate::config::AuthConfig;
 use crate::mfa::{normalize_mfa_code, verify_totp_code};
 use crate::notifications::MfaActivityEvent;
-use crate::tokens::{IssuedTokens, TokenSubject};
+use crate::session_handlers::emit_session_event;
+use crate::tenant_lifecycle_handlers::{tenant_residency, tenant_status};
+use crate::tokens::{
+    IssuedTokens, RefreshTokenAccount, SessionDevice, SessionRevoked, TokenSubject,
+};
+use crate::webauthn::{AssertionCredential, RequestOptions};
+use crate::webauthn_handlers::{
+    issue_challenge, load_passkeys, verify_login_assertion, PasskeyFailure, PURPOSE_AUTHENTICATION,
+};
 use crate::AppState;
 
 pub(crate) const ALLOWED_ROLES: &[&str] = &["super_admin", "admin", "manager", "cashier"];
 
-const MAX_FAILED_ATTEMPTS: i16 = 5;
-const LOCKOUT_MINUTES: i64 = 15;
-
 const MAX_MFA_FAILED_ATTEMPTS: i16 = 5;
 const MFA_LOCKOUT_MINUTES: i64 = 15;
 
@@ -41,6 +46,10 @@ struct ErrorResponse {
     message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     locked_until: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webauthn: Option<Box<RequestOptions>>,
 }
 
 #[derive(Debug)]
@@ -57,6 +66,8 @@ impl AuthError {
                 code,
                 message: message.into(),
                 locked_until: None,
+                retry_after_seconds: None,
+                webauthn: None,
             },
         }
     }
@@ -80,6 +91,16 @@ impl AuthError {
         error
     }
 
+    pub(crate) fn too_many_attempts(retry_after_seconds: u64) -> Self {
+        let mut error = Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "TOO_MANY_ATTEMPTS",
+            "Too many failed sign-in attempts from this address. Please try again later.",
+        );
+        error.body.retry_after_seconds = Some(retry_after_seconds);
+        error
+    }
+
     pub(crate) fn account_inactive() -> Self {
         Self::new(
             StatusCode::FORBIDDEN,
@@ -96,6 +117,12 @@ impl AuthError {
         )
     }
 
+    /// Attaches the passkey assertion options the client should pass to `navigator.credentials.get`.
+    pub(crate) fn with_webauthn_options(mut self, options: RequestOptions) -> Self {
+        self.body.webauthn = Some(Box::new(options));
+        self
+    }
+
     pub(crate) fn mfa_not_enrolled() -> Self {
         Self::new(
             StatusCode::FORBIDDEN,
@@ -120,6 +147,21 @@ impl AuthError {
         )
     }
 
+    pub(crate) fn tenant_inactive(status: TenantStatus) -> Self {
+        match status {
+            TenantStatus::Archived => Self::new(
+                StatusCode::FORBIDDEN,
+                "TENANT_ARCHIVED",
+                "This organisation has been archived.",
+            ),
+            _ => Self::new(
+                StatusCode::FORBIDDEN,
+                "TENANT_SUSPENDED",
+                "This organisation is suspended. Please contact support.",
+            ),
+        }
+    }
+
     pub(crate) fn internal_error(message: impl Into<String>) -> Self {
         Self::new(StatusCode::INTERNAL_SERVER_ERROR, "SERVER_ERROR", message)
     }
@@ -190,6 +232,7 @@ struct AuthRow {
     password_hash: String,
     failed_attempts: i16,
     locked_until: Option<DateTime<Utc>>,
+    lockout_count: i16,
     last_password_reset: Option<DateTime<Utc>>,
     force_password_reset: bool,
     mfa_secret: Option<String>,
@@ -199,13 +242,17 @@ struct AuthRow {
     mfa_last_challenge_at: Option<DateTime<Utc>>,
 }
 
-struct LoginMetadata {
-    ip: Option<String>,
-    user_agent: Option<String>,
-    device_fingerprint: Option<String>,
+pub(crate) struct LoginMetadata {
+    pub(crate) ip: Option<String>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) device_fingerprint: Option<String>,
 }
 
-fn build_refresh_cookie(config: &AuthConfig, token: &str, max_age_seconds: i64) -> String {
+pub(crate) fn build_refresh_cookie(
+    config: &AuthConfig,
+    token: &str,
+    max_age_seconds: i64,
+) -> String {
     let mut parts = Vec::new();
     parts.push(format!("{}={}", config.refresh_cookie_name, token));
     parts.push("Path=/".to_string());
@@ -258,7 +305,7 @@ fn clear_refresh_cookie(config: &AuthConfig) -> String {
     parts.join("; ")
 }
 
-fn extract_refresh_cookie(headers: &HeaderMap, config: &AuthConfig) -> Option<String> {
+pub(crate) fn extract_refresh_cookie(headers: &HeaderMap, config: &AuthConfig) -> Option<String> {
     let raw = headers.get(COOKIE)?.to_str().ok()?;
     let prefix = format!("{}=", config.refresh_cookie_name);
     raw.split(';')
@@ -268,7 +315,7 @@ fn extract_refresh_cookie(headers: &HeaderMap, config: &AuthConfig) -> Option<St
 }
 
 impl LoginMetadata {
-    fn from_headers(headers: &HeaderMap, device_fingerprint: Option<String>) -> Self {
+    pub(crate) fn from_headers(headers: &HeaderMap, device_fingerprint: Option<String>) -> Self {
         let ip = headers
             .get("x-forwarded-for")
             .or_else(|| headers.get("x-real-ip"))
@@ -288,6 +335,14 @@ impl LoginMetadata {
             device_fingerprint: device_fingerprint.filter(|value| !value.trim().is_empty()),
         }
     }
+
+    pub(crate) fn session_device(&self) -> SessionDevice {
+        SessionDevice {
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            device_fingerprint: self.device_fingerprint.clone(),
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -302,6 +357,32 @@ async fn record_mfa_event(
     notify_webhook: bool,
 ) {
     state.record_mfa_metric(action);
+    record_security_event(
+        state,
+        action,
+        severity,
+        user,
+        metadata,
+        trace_id,
+        detail,
+        notify_webhook,
+    )
+    .await;
+}
+
+/// Logs the event and publishes it on the security activity stream (MFA activity topic),
+/// optionally paging the suspicious-login webhook.
+#[allow(clippy::too_many_arguments)]
+async fn record_security_event(
+    state: &AppState,
+    action: &'static str,
+    severity: &'static str,
+    user: &AuthRow,
+    metadata: &LoginMetadata,
+    trace_id: Uuid,
+    detail: Option<String>,
+    notify_webhook: bool,
+) {
     let ip = metadata.ip.clone();
     let user_agent = metadata.user_agent.clone();
     let device = metadata.device_fingerprint.clone();
@@ -317,7 +398,7 @@ async fn record_mfa_event(
             user_agent = user_agent.as_deref().unwrap_or(""),
             device = device.as_deref().unwrap_or(""),
             trace_id = %trace_id,
-            "Recorded MFA activity"
+            "Recorded security activity"
         ),
         _ => info!(
             security_event = %action,
@@ -329,7 +410,7 @@ async fn record_mfa_event(
             user_agent = user_agent.as_deref().unwrap_or(""),
             device = device.as_deref().unwrap_or(""),
             trace_id = %trace_id,
-            "Recorded MFA activity"
+            "Recorded security activity"
         ),
     }
 
@@ -412,6 +493,7 @@ pub async fn create_user(
 
     let trimmed_role = role.trim();
     validate_role(trimmed_role)?;
+    validate_password(&state.config, &password, Some(trimmed_email))?;
     let password_hash = hash_password(&password)?;
     let now = Utc::now();
 
@@ -596,12 +678,19 @@ pub async fn reset_user_password(
             "Password must not be empty".to_string(),
         ));
     }
-    if trimmed.len() < 8 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Password must be at least 8 characters long.".to_string(),
-        ));
-    }
+    let email: Option<String> =
+        sqlx::query_scalar("SELECT email FROM users WHERE id = $1 AND tenant_id = $2")
+            .bind(user_id)
+            .bind(tenant_id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Database error: {e}"),
+                )
+            })?;
+    validate_password(&state.config, trimmed, email.as_deref())?;
 
     let password_hash = hash_password(trimmed)?;
     let now = Utc::now();
@@ -632,6 +721,8 @@ pub struct LoginRequest {
     pub tenant_id: Option<Uuid>,
     #[serde(default, alias = "mfaCode")]
     pub mfa_code: Option<String>,
+    #[serde(default, alias = "webauthnAssertion")]
+    pub webauthn_assertion: Option<AssertionCredential>,
     #[serde(default, alias = "deviceFingerprint")]
     pub device_fingerprint: Option<String>,
 }
@@ -661,6 +752,7 @@ pub async fn login_user(
         password,
         tenant_id,
         mfa_code,
+        webauthn_assertion,
         device_fingerprint,
     } = login;
 
@@ -668,9 +760,18 @@ pub async fn login_user(
     let trace_id = Uuid::new_v4();
     state.record_login_metric("attempt");
 
+    if let Some(ip) = metadata.ip.as_deref() {
+        if let Some(retry_after) = state.login_throttle.check(ip) {
+            warn!(ip, retry_after, trace_id = %trace_id, "Login throttled for client IP");
+            state.metrics.login_ip_throttled();
+            state.record_login_metric("ip_throttled");
+            return Err(AuthError::too_many_attempts(retry_after));
+        }
+    }
+
     let user_row = match tenant_id {
         Some(tenant) => sqlx::query_as::<_, AuthRow>(
-            "SELECT id, tenant_id, name, email, role, is_active, created_at, updated_at, password_hash, failed_attempts, locked_until, last_password_reset, force_password_reset, mfa_secret, mfa_pending_secret, mfa_enrolled_at, mfa_failed_attempts, mfa_last_challenge_at FROM users WHERE email = $1 AND tenant_id = $2",
+            "SELECT id, tenant_id, name, email, role, is_active, created_at, updated_at, password_hash, failed_attempts, locked_until, lockout_count, last_password_reset, force_password_reset, mfa_secret, mfa_pending_secret, mfa_enrolled_at, mfa_failed_attempts, mfa_last_challenge_at FROM users WHERE email = $1 AND tenant_id = $2",
         )
         .bind(&email)
         .bind(tenant)
@@ -678,7 +779,7 @@ pub async fn login_user(
         .await
         .map_err(|e| AuthError::internal_error(format!("DB query failed: {e}")))?,
         None => sqlx::query_as::<_, AuthRow>(
-            "SELECT id, tenant_id, name, email, role, is_active, created_at, updated_at, password_hash, failed_attempts, locked_until, last_password_reset, force_password_reset, mfa_secret, mfa_pending_secret, mfa_enrolled_at, mfa_failed_attempts, mfa_last_challenge_at FROM users WHERE email = $1",
+            "SELECT id, tenant_id, name, email, role, is_active, created_at, updated_at, password_hash, failed_attempts, locked_until, lockout_count, last_password_reset, force_password_reset, mfa_secret, mfa_pending_secret, mfa_enrolled_at, mfa_failed_attempts, mfa_last_challenge_at FROM users WHERE email = $1",
         )
         .bind(&email)
         .fetch_optional(&state.db)
@@ -689,16 +790,32 @@ pub async fn login_user(
     let mut auth_data = match user_row {
         Some(row) => row,
         None => {
+            let tenant_label = tenant_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            record_login_failure(&state, &metadata, &tenant_label, "unknown_user");
             state.record_login_metric("invalid_credentials");
             return Err(AuthError::invalid_credentials());
         }
     };
+    let tenant_label = auth_data.tenant_id.to_string();
 
     if !auth_data.is_active {
         state.record_login_metric("account_inactive");
         return Err(AuthError::account_inactive());
     }
 
+    match tenant_status(&state, auth_data.tenant_id).await {
+        Ok(TenantStatus::Active) => {}
+        Ok(status) => {
+            state.record_login_metric("tenant_inactive");
+            return Err(AuthError::tenant_inactive(status));
+        }
+        Err(err) => {
+            return Err(AuthError::internal_error(format!("DB query failed: {err}")));
+        }
+    }
+
     let now = Utc::now();
 
     if let Some(locked_until) = auth_data.locked_until {
@@ -733,13 +850,12 @@ pub async fn login_user(
             if auth_data.password_hash == password {
                 match hash_password(&password) {
                     Ok(new_hash) => {
-                        if let Err(err) = sqlx::query(
-                            "UPDATE users SET password_hash = $1 WHERE id = $2",
-                        )
-                        .bind(&new_hash)
-                        .bind(auth_data.id)
-                        .execute(&state.db)
-                        .await
+                        if let Err(err) =
+                            sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+                                .bind(&new_hash)
+                                .bind(auth_data.id)
+                                .execute(&state.db)
+                                .await
                         {
                             warn!(
                                 user_id = %auth_data.id,
@@ -770,18 +886,26 @@ pub async fn login_user(
     };
 
     if !password_valid {
+        record_login_failure(&state, &metadata, &tenant_label, "invalid_password");
+        let lockout = &state.config.lockout;
         let new_attempts = auth_data.failed_attempts.saturating_add(1);
-        let lock_until = if new_attempts >= MAX_FAILED_ATTEMPTS {
-            Some(now + Duration::minutes(LOCKOUT_MINUTES))
+        let lock_until = if new_attempts >= lockout.max_failed_attempts {
+            Some(now + lockout.lockout_duration(auth_data.lockout_count))
         } else {
             None
         };
+        let lockout_count = if lock_until.is_some() {
+            auth_data.lockout_count.saturating_add(1)
+        } else {
+            auth_data.lockout_count
+        };
 
         if let Err(err) = sqlx::query(
-            "UPDATE users SET failed_attempts = $1, locked_until = $2 WHERE id = $3",
+            "UPDATE users SET failed_attempts = $1, locked_until = $2, lockout_count = $3 WHERE id = $4",
         )
         .bind(new_attempts)
         .bind(lock_until)
+        .bind(lockout_count)
         .bind(auth_data.id)
         .execute(&state.db)
         .await
@@ -794,15 +918,42 @@ pub async fn login_user(
         }
 
         if let Some(until) = lock_until {
+            state.metrics.account_lockout(&tenant_label, "password");
+            state.record_login_metric("account_locked");
+            record_security_event(
+                &state,
+                "auth.login.lockout",
+                "error",
+                &auth_data,
+                &metadata,
+                trace_id,
+                Some(
+                    json!({
+                        "reason": "failed_password_attempts",
+                        "failed_attempts": new_attempts,
+                        "lockout_count": lockout_count,
+                        "locked_until": until.to_rfc3339_opts(SecondsFormat::Secs, true),
+                        "ip": metadata.ip.as_deref(),
+                        "user_agent": metadata.user_agent.as_deref(),
+                    })
+                    .to_string(),
+                ),
+                true,
+            )
+            .await;
             return Err(AuthError::account_locked(Some(until)));
         }
 
+        state.record_login_metric("invalid_credentials");
         return Err(AuthError::invalid_credentials());
     }
 
-    if auth_data.failed_attempts != 0 || auth_data.locked_until.is_some() {
+    if auth_data.failed_attempts != 0
+        || auth_data.locked_until.is_some()
+        || auth_data.lockout_count != 0
+    {
         if let Err(err) = sqlx::query(
-            "UPDATE users SET failed_attempts = 0, locked_until = NULL WHERE id = $1",
+            "UPDATE users SET failed_attempts = 0, locked_until = NULL, lockout_count = 0 WHERE id = $1",
         )
         .bind(auth_data.id)
         .execute(&state.db)
@@ -816,178 +967,187 @@ pub async fn login_user(
         } else {
             auth_data.failed_attempts = 0;
             auth_data.locked_until = None;
+            auth_data.lockout_count = 0;
         }
     }
 
+    let mfa_method = state.config.mfa_method_for(&auth_data.role);
+    let passkeys = if mfa_method.allows_webauthn() {
+        load_passkeys(&state.db, auth_data.id)
+            .await
+            .map_err(|err| {
+                error!(user_id = %auth_data.id, error = %err, "Failed to load passkeys");
+                AuthError::internal_error("Unable to complete login.")
+            })?
+    } else {
+        Vec::new()
+    };
+
     let requires_mfa = state.config.should_enforce_for(
         &auth_data.role,
         auth_data.tenant_id,
-        auth_data.mfa_secret.is_some() || auth_data.mfa_pending_secret.is_some(),
+        auth_data.mfa_secret.is_some()
+            || auth_data.mfa_pending_secret.is_some()
+            || !passkeys.is_empty(),
     );
 
     if requires_mfa {
-        if auth_data.mfa_pending_secret.is_some() {
-            record_mfa_event(
-                &state,
-                "mfa.challenge.pending_secret",
-                "info",
-                &auth_data,
-                &metadata,
-                trace_id,
-                Some(
-                    json!({
-                        "reason": "pending_secret",
-                        "ip": metadata.ip.as_deref(),
-                        "user_agent": metadata.user_agent.as_deref(),
-                        "device": metadata.device_fingerprint.as_deref(),
-                    })
-                    .to_string(),
-                ),
-                false,
-            )
-            .await;
-            state.record_login_metric("mfa_required");
-            return Err(AuthError::mfa_required());
-        }
-
-        let secret = match auth_data.mfa_secret.as_deref() {
-            Some(secret) => secret,
-            None => {
-                record_mfa_event(
-                    &state,
-                    "mfa.challenge.unenrolled",
-                    "warn",
-                    &auth_data,
-                    &metadata,
-                    trace_id,
-                    Some(
-                        json!({
-                            "reason": "unenrolled",
-                            "ip": metadata.ip.as_deref(),
-                            "user_agent": metadata.user_agent.as_deref(),
-                            "device": metadata.device_fingerprint.as_deref(),
-                        })
-                        .to_string(),
-                    ),
-                    false,
-                )
-                .await;
-                state.record_login_metric("mfa_not_enrolled");
-                return Err(AuthError::mfa_not_enrolled());
-            }
-        };
-        let code = match mfa_code
-            .as_deref()
-            .and_then(normalize_mfa_code)
-        {
-            Some(code) => code,
-            None => {
+        // Prefer the passkey whenever one is registered unless the client explicitly sent a
+        // TOTP code and the role still accepts TOTP.
+        let use_webauthn = !passkeys.is_empty()
+            && (webauthn_assertion.is_some() || mfa_code.is_none() || !mfa_method.allows_totp());
+
+        if use_webauthn {
+            let Some(assertion) = webauthn_assertion.as_ref() else {
+                let challenge = issue_challenge(&state, auth_data.id, PURPOSE_AUTHENTICATION)
+                    .await
+                    .map_err(|err| {
+                        error!(user_id = %auth_data.id, error = %err, "Failed to issue WebAuthn challenge");
+                        AuthError::internal_error("Unable to start passkey verification.")
+                    })?;
+                let options = state.config.webauthn.request_options(
+                    challenge,
+                    passkeys
+                        .iter()
+                        .map(|key| key.credential_id.clone())
+                        .collect(),
+                );
                 record_mfa_event(
                     &state,
-                    "mfa.challenge.missing_code",
-                    "warn",
+                    "mfa.challenge.webauthn_issued",
+                    "info",
                     &auth_data,
                     &metadata,
                     trace_id,
                     Some(
-                        json!({
-                            "reason": "missing_code",
-                            "ip": metadata.ip.as_deref(),
-                            "user_agent": metadata.user_agent.as_deref(),
-                            "device": metadata.device_fingerprint.as_deref(),
-                        })
-                        .to_string(),
+                        json!({ "reason": "webauthn_challenge", "method": "webauthn" }).to_string(),
                     ),
                     false,
                 )
                 .await;
                 state.record_login_metric("mfa_required");
-                return Err(AuthError::mfa_required());
-            }
-        };
+                return Err(AuthError::mfa_required().with_webauthn_options(options));
+            };
 
-        if !verify_totp_code(secret, &code) {
-            let challenge_at = Utc::now();
-            let next_failed = auth_data.mfa_failed_attempts.saturating_add(1);
+            match verify_login_assertion(&state, auth_data.id, &passkeys, assertion).await {
+                Ok(()) => {}
+                Err(PasskeyFailure::Internal) => {
+                    return Err(AuthError::internal_error("Unable to verify passkey."));
+                }
+                Err(PasskeyFailure::Invalid(reason)) => {
+                    return Err(register_mfa_failure(
+                        &state,
+                        &mut auth_data,
+                        &metadata,
+                        trace_id,
+                        &tenant_label,
+                        json!({ "reason": "invalid_assertion", "method": "webauthn", "detail": reason }),
+                    )
+                    .await);
+                }
+            }
+        } else if !mfa_method.allows_totp() {
             record_mfa_event(
                 &state,
-                "mfa.challenge.failed",
+                "mfa.challenge.unenrolled",
                 "warn",
                 &auth_data,
                 &metadata,
                 trace_id,
-                Some(
-                    json!({
-                        "reason": "invalid_code",
-                        "ip": metadata.ip.as_deref(),
-                        "user_agent": metadata.user_agent.as_deref(),
-                        "device": metadata.device_fingerprint.as_deref(),
-                    })
-                    .to_string(),
-                ),
-                true,
+                Some(json!({ "reason": "webauthn_required", "method": "webauthn" }).to_string()),
+                false,
             )
             .await;
-
-            if next_failed >= MAX_MFA_FAILED_ATTEMPTS {
-                let lock_until = challenge_at + Duration::minutes(MFA_LOCKOUT_MINUTES);
-                if let Err(err) = sqlx::query(
-                    "UPDATE users SET mfa_failed_attempts = 0, locked_until = $2, mfa_last_challenge_at = $3 WHERE id = $1",
-                )
-                .bind(auth_data.id)
-                .bind(lock_until)
-                .bind(challenge_at)
-                .execute(&state.db)
-                .await
-                {
-                    warn!(
-                        user_id = %auth_data.id,
-                        error = ?err,
-                        "Failed to persist MFA lockout"
-                    );
-                }
+            state.record_login_metric("mfa_not_enrolled");
+            return Err(AuthError::mfa_not_enrolled());
+        } else {
+            if auth_data.mfa_pending_secret.is_some() {
                 record_mfa_event(
                     &state,
-                    "mfa.challenge.lockout",
-                    "error",
+                    "mfa.challenge.pending_secret",
+                    "info",
                     &auth_data,
                     &metadata,
                     trace_id,
                     Some(
                         json!({
-                            "reason": "lockout",
+                            "reason": "pending_secret",
                             "ip": metadata.ip.as_deref(),
                             "user_agent": metadata.user_agent.as_deref(),
                             "device": metadata.device_fingerprint.as_deref(),
                         })
                         .to_string(),
                     ),
-                    true,
+                    false,
                 )
                 .await;
-                state.record_login_metric("mfa_lockout");
-                return Err(AuthError::account_locked(Some(lock_until)));
-            } else {
-                if let Err(err) = sqlx::query(
-                    "UPDATE users SET mfa_failed_attempts = $2, mfa_last_challenge_at = $3 WHERE id = $1",
-                )
-                .bind(auth_data.id)
-                .bind(next_failed)
-                .bind(challenge_at)
-                .execute(&state.db)
-                .await
-                {
-                    warn!(
-                        user_id = %auth_data.id,
-                        error = ?err,
-                        "Failed to record MFA failure"
-                    );
-                } else {
-                    auth_data.mfa_failed_attempts = next_failed;
+                state.record_login_metric("mfa_required");
+                return Err(AuthError::mfa_required());
+            }
+
+            let secret = match auth_data.mfa_secret.as_deref() {
+                Some(secret) => secret,
+                None => {
+                    record_mfa_event(
+                        &state,
+                        "mfa.challenge.unenrolled",
+                        "warn",
+                        &auth_data,
+                        &metadata,
+                        trace_id,
+                        Some(
+                            json!({
+                                "reason": "unenrolled",
+                                "ip": metadata.ip.as_deref(),
+                                "user_agent": metadata.user_agent.as_deref(),
+                                "device": metadata.device_fingerprint.as_deref(),
+                            })
+                            .to_string(),
+                        ),
+                        false,
+                    )
+                    .await;
+                    state.record_login_metric("mfa_not_enrolled");
+                    return Err(AuthError::mfa_not_enrolled());
                 }
+            };
+            let code = match mfa_code.as_deref().and_then(normalize_mfa_code) {
+                Some(code) => code,
+                None => {
+                    record_mfa_event(
+                        &state,
+                        "mfa.challenge.missing_code",
+                        "warn",
+                        &auth_data,
+                        &metadata,
+                        trace_id,
+                        Some(
+                            json!({
+                                "reason": "missing_code",
+                                "ip": metadata.ip.as_deref(),
+                                "user_agent": metadata.user_agent.as_deref(),
+                                "device": metadata.device_fingerprint.as_deref(),
+                            })
+                            .to_string(),
+                        ),
+                        false,
+                    )
+                    .await;
+                    state.record_login_metric("mfa_required");
+                    return Err(AuthError::mfa_required());
+                }
+            };
 
-                state.record_login_metric("mfa_invalid");
-                return Err(AuthError::mfa_invalid());
+            if !verify_totp_code(secret, &code) {
+                return Err(register_mfa_failure(
+                    &state,
+                    &mut auth_data,
+                    &metadata,
+                    trace_id,
+                    &tenant_label,
+                    json!({ "reason": "invalid_code", "method": "totp" }),
+                )
+                .await);
             }
         }
 
@@ -1017,6 +1177,7 @@ pub async fn login_user(
             Some(
                 json!({
                     "reason": "success",
+                    "method": if use_webauthn { "webauthn" } else { "totp" },
                     "ip": metadata.ip.as_deref(),
                     "user_agent": metadata.user_agent.as_deref(),
                     "device": metadata.device_fingerprint.as_deref(),
@@ -1041,15 +1202,19 @@ pub async fn login_user(
         force_password_reset: auth_data.force_password_reset,
     };
 
+    let residency = tenant_residency(&state, user.tenant_id)
+        .await
+        .map_err(|err| AuthError::internal_error(format!("DB query failed: {err}")))?;
     let subject = TokenSubject {
         user_id: user.id,
         tenant_id: user.tenant_id,
         roles: vec![user.role.clone()],
+        residency,
     };
 
     let issued = state
         .token_signer
-        .issue_tokens(subject)
+        .issue_session_tokens(subject, None, &metadata.session_device())
         .await
         .map_err(|err| {
             error!(user_id = %user.id, error = ?err, "Failed to issue tokens");
@@ -1064,6 +1229,7 @@ pub async fn login_user(
         access_expires_in,
         refresh_expires_in,
         token_type,
+        ..
     } = issued;
 
     let refresh_cookie =
@@ -1099,14 +1265,18 @@ pub async fn login_user(
     Ok(reply)
 }
 
-#[instrument(name = "refresh_session", skip(state, headers), fields(outcome = "pending"))]
+#[instrument(
+    name = "refresh_session",
+    skip(state, headers),
+    fields(outcome = "pending")
+)]
 pub async fn refresh_session(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> Result<Response, AuthError> {
     let Some(raw_cookie) = extract_refresh_cookie(&headers, state.config.as_ref()) else {
         tracing::debug!("missing refresh cookie");
-    Span::current().record("outcome", tracing::field::display("missing_cookie"));
+        Span::current().record("outcome", tracing::field::display("missing_cookie"));
         return Err(AuthError::session_expired());
     };
 
@@ -1136,6 +1306,19 @@ pub async fn refresh_session(
         }
     };
 
+    match tenant_status(&state, account.tenant_id).await {
+        Ok(TenantStatus::Active) => {}
+        Ok(status) => {
+            Span::current().record("outcome", tracing::field::display("tenant_inactive"));
+            return Err(AuthError::tenant_inactive(status));
+        }
+        Err(err) => {
+            error!(error = %err, "Failed to load tenant status during session refresh");
+            Span::current().record("outcome", tracing::field::display("error"));
+            return Err(AuthError::internal_error("Unable to refresh session."));
+        }
+    }
+
     let user = User {
         id: account.user_id,
         tenant_id: account.tenant_id,
@@ -1149,17 +1332,34 @@ pub async fn refresh_session(
         force_password_reset: account.force_password_reset,
     };
 
+    let residency = tenant_residency(&state, user.tenant_id)
+        .await
+        .map_err(|err| {
+            error!(error = %err, "Failed to load tenant residency during session refresh");
+            Span::current().record("outcome", tracing::field::display("error"));
+            AuthError::internal_error("Unable to refresh session.")
+        })?;
     let subject = TokenSubject {
         user_id: user.id,
         tenant_id: user.tenant_id,
         roles: vec![user.role.clone()],
+        residency,
     };
 
-    let issued = state.token_signer.issue_tokens(subject).await.map_err(|err| {
-        error!(user_id = %user.id, error = %err, "Failed to issue tokens during session refresh");
-    Span::current().record("outcome", tracing::field::display("issue_error"));
-        AuthError::internal_error("Unable to refresh session.")
-    })?;
+    let device = LoginMetadata::from_headers(&headers, None).session_device();
+    let issued = state
+        .token_signer
+        .issue_session_tokens(subject, account.session_id, &device)
+        .await
+        .map_err(|err| {
+            if err.is::<SessionRevoked>() {
+                Span::current().record("outcome", tracing::field::display("session_revoked"));
+                return AuthError::session_expired();
+            }
+            error!(user_id = %user.id, error = %err, "Failed to issue tokens during session refresh");
+            Span::current().record("outcome", tracing::field::display("issue_error"));
+            AuthError::internal_error("Unable to refresh session.")
+        })?;
 
     let IssuedTokens {
         access_token,
@@ -1169,6 +1369,7 @@ pub async fn refresh_session(
         access_expires_in,
         refresh_expires_in,
         token_type,
+        ..
     } = issued;
 
     let refresh_cookie =
@@ -1205,8 +1406,36 @@ pub async fn refresh_session(
 
 pub async fn logout_user(State(state): State<AppState>, headers: HeaderMap) -> Response {
     if let Some(raw_cookie) = extract_refresh_cookie(&headers, state.config.as_ref()) {
-        if let Err(err) = state.token_signer.consume_refresh_token(&raw_cookie).await {
-            warn!(error = %err, "Failed to revoke refresh token during logout");
+        match state.token_signer.consume_refresh_token(&raw_cookie).await {
+            Ok(Some(RefreshTokenAccount {
+                user_id,
+                tenant_id,
+                role,
+                session_id: Some(session_id),
+                ..
+            })) => match state
+                .token_signer
+                .revoke_sessions(user_id, Some(session_id), None, Some(user_id), "logout")
+                .await
+            {
+                Ok(revoked) => {
+                    emit_session_event(
+                        &state,
+                        "auth.session.logout",
+                        tenant_id,
+                        user_id,
+                        Some(role),
+                        user_id,
+                        &revoked,
+                        "logout",
+                        &LoginMetadata::from_headers(&headers, None),
+                    )
+                    .await;
+                }
+                Err(err) => warn!(error = %err, "Failed to revoke session during logout"),
+            },
+            Ok(_) => {}
+            Err(err) => warn!(error = %err, "Failed to revoke refresh token during logout"),
         }
     }
 
@@ -1226,6 +1455,189 @@ pub async fn logout_user(State(state): State<AppState>, headers: HeaderMap) -> R
     response
 }
 
+fn record_login_failure(
+    state: &AppState,
+    metadata: &LoginMetadata,
+    tenant_label: &str,
+    reason: &str,
+) {
+    state.metrics.login_failure(tenant_label, reason);
+    if let Some(ip) = metadata.ip.as_deref() {
+        state.login_throttle.record_failure(ip);
+    }
+}
+
+/// Counts a rejected second factor (TOTP code or passkey assertion) and locks the account
+/// once the MFA failure threshold is reached.
+async fn register_mfa_failure(
+    state: &AppState,
+    auth_data: &mut AuthRow,
+    metadata: &LoginMetadata,
+    trace_id: Uuid,
+    tenant_label: &str,
+    mut detail: serde_json::Value,
+) -> AuthError {
+    record_login_failure(state, metadata, tenant_label, "mfa_invalid");
+    let challenge_at = Utc::now();
+    let next_failed = auth_data.mfa_failed_attempts.saturating_add(1);
+    if let Some(fields) = detail.as_object_mut() {
+        fields.insert("ip".into(), json!(metadata.ip.as_deref()));
+        fields.insert("user_agent".into(), json!(metadata.user_agent.as_deref()));
+        fields.insert(
+            "device".into(),
+            json!(metadata.device_fingerprint.as_deref()),
+        );
+    }
+    record_mfa_event(
+        state,
+        "mfa.challenge.failed",
+        "warn",
+        auth_data,
+        metadata,
+        trace_id,
+        Some(detail.to_string()),
+        true,
+    )
+    .await;
+
+    if next_failed >= MAX_MFA_FAILED_ATTEMPTS {
+        let lock_until = challenge_at + Duration::minutes(MFA_LOCKOUT_MINUTES);
+        if let Err(err) = sqlx::query(
+            "UPDATE users SET mfa_failed_attempts = 0, locked_until = $2, mfa_last_challenge_at = $3 WHERE id = $1",
+        )
+        .bind(auth_data.id)
+        .bind(lock_until)
+        .bind(challenge_at)
+        .execute(&state.db)
+        .await
+        {
+            warn!(
+                user_id = %auth_data.id,
+                error = ?err,
+                "Failed to persist MFA lockout"
+            );
+        }
+        record_mfa_event(
+            state,
+            "mfa.challenge.lockout",
+            "error",
+            auth_data,
+            metadata,
+            trace_id,
+            Some(
+                json!({
+                    "reason": "lockout",
+                    "ip": metadata.ip.as_deref(),
+                    "user_agent": metadata.user_agent.as_deref(),
+                    "device": metadata.device_fingerprint.as_deref(),
+                })
+                .to_string(),
+            ),
+            true,
+        )
+        .await;
+        state.metrics.account_lockout(tenant_label, "mfa");
+        state.record_login_metric("mfa_lockout");
+        AuthError::account_locked(Some(lock_until))
+    } else {
+        if let Err(err) = sqlx::query(
+            "UPDATE users SET mfa_failed_attempts = $2, mfa_last_challenge_at = $3 WHERE id = $1",
+        )
+        .bind(auth_data.id)
+        .bind(next_failed)
+        .bind(challenge_at)
+        .execute(&state.db)
+        .await
+        {
+            warn!(
+                user_id = %auth_data.id,
+                error = ?err,
+                "Failed to record MFA failure"
+            );
+        } else {
+            auth_data.mfa_failed_attempts = next_failed;
+        }
+
+        state.record_login_metric("mfa_invalid");
+        AuthError::mfa_invalid()
+    }
+}
+
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<User>, (StatusCode, String)> {
+    ensure_role_any(&auth, &["super_admin", "admin"])?;
+    let tenant_id = extract_tenant_id(&headers)?;
+    ensure_tenant_access(&auth, tenant_id)?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users
+         SET failed_attempts = 0, mfa_failed_attempts = 0, locked_until = NULL, lockout_count = 0, updated_at = NOW()
+         WHERE id = $1 AND tenant_id = $2
+         RETURNING id, tenant_id, name, email, role, is_active, created_at, updated_at, last_password_reset, force_password_reset",
+    )
+    .bind(user_id)
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {e}")))?
+    .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    state.metrics.account_unlock(&tenant_id.to_string());
+    let trace_id = Uuid::new_v4();
+    info!(
+        security_event = "auth.account.unlocked",
+        user_id = %user.id,
+        tenant_id = %tenant_id,
+        actor_id = %auth.claims.subject,
+        trace_id = %trace_id,
+        "Account unlocked by administrator"
+    );
+    state
+        .emit_mfa_activity(
+            MfaActivityEvent {
+                action: "auth.account.unlocked",
+                severity: "info",
+                tenant_id,
+                user_id: Some(user.id),
+                trace_id,
+                occurred_at: Utc::now(),
+                ip: None,
+                user_agent: None,
+                device: None,
+                role: Some(user.role.clone()),
+                detail: Some(json!({ "unlocked_by": auth.claims.subject }).to_string()),
+            },
+            None,
+        )
+        .await;
+
+    Ok(Json(user))
+}
+
+pub(crate) fn validate_password(
+    config: &AuthConfig,
+    password: &str,
+    email: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let violations = config.password_policy.violations(password, email);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "{} Violations: {}",
+                config.password_policy.describe(),
+                violations.join(", ")
+            ),
+        ))
+    }
+}
+
 pub(crate) fn extract_tenant_id(headers: &HeaderMap) -> Result<Uuid, (StatusCode, String)> {
     match headers
         .get("X-Tenant-ID")
@@ -1240,7 +1652,10 @@ pub(crate) fn extract_tenant_id(headers: &HeaderMap) -> Result<Uuid, (StatusCode
     }
 }
 
-fn ensure_role_any(auth: &AuthContext, allowed: &[&str]) -> Result<(), (StatusCode, String)> {
+pub(crate) fn ensure_role_any(
+    auth: &AuthContext,
+    allowed: &[&str],
+) -> Result<(), (StatusCode, String)> {
     if allowed.iter().any(|role| auth.has_role(role)) {
         Ok(())
     } else {
@@ -1251,7 +1666,10 @@ fn ensure_role_any(auth: &AuthContext, allowed: &[&str]) -> Result<(), (StatusCo
     }
 }
 
-fn ensure_tenant_access(auth: &AuthContext, tenant_id: Uuid) -> Result<(), (StatusCode, String)> {
+pub(crate) fn ensure_tenant_access(
+    auth: &AuthContext,
+    tenant_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
     if auth.has_role("super_admin") || auth.claims.tenant_id == tenant_id {
         Ok(())
     } else {
@@ -1262,7 +1680,7 @@ fn ensure_tenant_access(auth: &AuthContext, tenant_id: Uuid) -> Result<(), (Stat
     }
 }
 
-fn validate_role(role: &str) -> Result<(), (StatusCode, String)> {
+pub(crate) fn validate_role(role: &str) -> Result<(), (StatusCode, String)> {
     if ALLOWED_ROLES.contains(&role) {
         Ok(())
     } else {
@@ -1281,7 +1699,7 @@ pub async fn list_roles() -> Json<Vec<&'static str>> {
     Json(roles)
 }
 
-fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
+pub(crate) fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
     if password.trim().is_empty() {
         return Err((
             StatusCode::BAD_REQUEST,
@@ -1305,9 +1723,13 @@ mod tests {
     use super::*;
     use argon2::{password_hash::PasswordHash, Argon2};
     use axum::http::{header::COOKIE, HeaderMap, HeaderValue};
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
     use crate::config::CookieSameSite;
+    use crate::login_guard::LockoutPolicy;
+    use crate::oidc::SsoConfig;
+    use crate::password_policy::PasswordPolicy;
+    use crate::webauthn::WebAuthnConfig;
 
     fn test_config() -> AuthConfig {
         AuthConfig {
@@ -1323,6 +1745,23 @@ mod tests {
             refresh_cookie_domain: Some("example.com".to_string()),
             refresh_cookie_secure: true,
             refresh_cookie_same_site: CookieSameSite::Strict,
+            integration_key_topic: "security.integration_keys.v1".to_string(),
+            password_policy: PasswordPolicy::default(),
+            lockout: LockoutPolicy::default(),
+            mfa_role_methods: HashMap::new(),
+            webauthn: WebAuthnConfig::default(),
+            sso: SsoConfig::default(),
+            tenant_export_sources: Vec::new(),
+            tenant_export_key: None,
+            tenant_export_ttl_hours: 24,
+            tenant_status_token: None,
+            capability_policy_token: None,
+            tenant_provision_targets: Vec::new(),
+            tenant_events_topic: String::new(),
+            offline_bundle_source: None,
+            offline_bundle_ttl_seconds: 86_400,
+            invite_ttl_hours: 72,
+            invite_accept_url: None,
         }
     }
 
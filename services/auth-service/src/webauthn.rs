@@ -0,0 +1,500 @@
+//! Minimal WebAuthn relying-party verification for passkeys used as a second factor.
+//!
+//! Supports ES256 (P-256) credentials with `none`/self attestation; attestation
+//! statements are not trust-verified because the credential is bound to an already
+//! password-authenticated account.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::value::Value;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const COSE_ALG_ES256: i64 = -7;
+const CHALLENGE_LEN: usize = 32;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_DATA: u8 = 0x40;
+
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origins: Vec<String>,
+    pub timeout_ms: u64,
+    pub challenge_ttl_seconds: i64,
+}
+
+impl Default for WebAuthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_string(),
+            rp_name: "NovaPOS".to_string(),
+            origins: vec!["http://localhost:3000".to_string()],
+            timeout_ms: 60_000,
+            challenge_ttl_seconds: 300,
+        }
+    }
+}
+
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; CHALLENGE_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Browser `PublicKeyCredential` serialised with base64url-encoded binary fields.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(alias = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(alias = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(alias = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(alias = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PubKeyCredParam {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub alg: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebAuthnUser {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: WebAuthnUser,
+    #[serde(rename = "pubKeyCredParams")]
+    pub pub_key_cred_params: Vec<PubKeyCredParam>,
+    pub timeout: u64,
+    pub attestation: &'static str,
+    #[serde(rename = "excludeCredentials")]
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestOptions {
+    pub challenge: String,
+    #[serde(rename = "rpId")]
+    pub rp_id: String,
+    pub timeout: u64,
+    #[serde(rename = "allowCredentials")]
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    #[serde(rename = "userVerification")]
+    pub user_verification: &'static str,
+}
+
+impl WebAuthnConfig {
+    pub fn creation_options(
+        &self,
+        challenge: String,
+        user: WebAuthnUser,
+        existing_credential_ids: Vec<String>,
+    ) -> CreationOptions {
+        CreationOptions {
+            challenge,
+            rp: RelyingParty {
+                id: self.rp_id.clone(),
+                name: self.rp_name.clone(),
+            },
+            user,
+            pub_key_cred_params: vec![PubKeyCredParam {
+                kind: "public-key",
+                alg: COSE_ALG_ES256,
+            }],
+            timeout: self.timeout_ms,
+            attestation: "none",
+            exclude_credentials: existing_credential_ids
+                .into_iter()
+                .map(|id| CredentialDescriptor { kind: "public-key", id })
+                .collect(),
+        }
+    }
+
+    pub fn request_options(&self, challenge: String, credential_ids: Vec<String>) -> RequestOptions {
+        RequestOptions {
+            challenge,
+            rp_id: self.rp_id.clone(),
+            timeout: self.timeout_ms,
+            allow_credentials: credential_ids
+                .into_iter()
+                .map(|id| CredentialDescriptor { kind: "public-key", id })
+                .collect(),
+            user_verification: "preferred",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedRegistration {
+    /// base64url credential id as reported by the authenticator.
+    pub credential_id: String,
+    /// SEC1 uncompressed P-256 point.
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub user_verified: bool,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested: Option<(Vec<u8>, Value)>,
+}
+
+fn decode_b64(label: &str, value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .with_context(|| format!("{label} is not valid base64url"))
+}
+
+fn check_client_data(
+    config: &WebAuthnConfig,
+    raw: &[u8],
+    expected_type: &str,
+    expected_challenge: &str,
+) -> Result<()> {
+    let client: ClientData = serde_json::from_slice(raw).context("clientDataJSON is not valid JSON")?;
+    if client.kind != expected_type {
+        bail!("unexpected client data type '{}'", client.kind);
+    }
+    if client.challenge.trim_end_matches('=') != expected_challenge {
+        bail!("challenge mismatch");
+    }
+    if !config.origins.iter().any(|origin| origin == &client.origin) {
+        bail!("origin '{}' is not allowed", client.origin);
+    }
+    Ok(())
+}
+
+/// Challenge echoed by the browser, used to look up the server-side challenge record
+/// before full verification.
+pub fn client_challenge(client_data_json: &str) -> Result<String> {
+    let raw = decode_b64("clientDataJSON", client_data_json)?;
+    let client: ClientData = serde_json::from_slice(&raw).context("clientDataJSON is not valid JSON")?;
+    Ok(client.challenge.trim_end_matches('=').to_string())
+}
+
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>> {
+    if data.len() < 37 {
+        bail!("authenticator data too short");
+    }
+    let rp_id_hash = &data[..32];
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+    let attested = if flags & FLAG_ATTESTED_DATA != 0 {
+        let rest = &data[37..];
+        if rest.len() < 18 {
+            bail!("attested credential data truncated");
+        }
+        let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        if rest.len() < 18 + id_len {
+            bail!("credential id truncated");
+        }
+        let credential_id = rest[18..18 + id_len].to_vec();
+        let mut key_bytes = &rest[18 + id_len..];
+        let key: Value = ciborium::de::from_reader(&mut key_bytes)
+            .map_err(|err| anyhow!("credential public key is not valid CBOR: {err}"))?;
+        Some((credential_id, key))
+    } else {
+        None
+    };
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        attested,
+    })
+}
+
+fn check_rp_and_presence(config: &WebAuthnConfig, data: &AuthenticatorData<'_>) -> Result<()> {
+    let expected = Sha256::digest(config.rp_id.as_bytes());
+    if data.rp_id_hash != expected.as_slice() {
+        bail!("rpIdHash does not match relying party");
+    }
+    if data.flags & FLAG_USER_PRESENT == 0 {
+        bail!("user presence flag not set");
+    }
+    Ok(())
+}
+
+fn cose_int(map: &[(Value, Value)], key: i64) -> Option<&Value> {
+    map.iter().find_map(|(k, v)| match k {
+        Value::Integer(i) if i128::from(*i) == key as i128 => Some(v),
+        _ => None,
+    })
+}
+
+fn cose_to_sec1(key: &Value) -> Result<Vec<u8>> {
+    let Value::Map(map) = key else {
+        bail!("COSE key must be a map");
+    };
+    let as_int = |value: Option<&Value>| match value {
+        Some(Value::Integer(i)) => Some(i128::from(*i)),
+        _ => None,
+    };
+    let as_bytes = |value: Option<&Value>| match value {
+        Some(Value::Bytes(bytes)) if bytes.len() == 32 => Some(bytes.clone()),
+        _ => None,
+    };
+    if as_int(cose_int(map, 1)) != Some(2) {
+        bail!("only EC2 credential keys are supported");
+    }
+    if as_int(cose_int(map, 3)) != Some(COSE_ALG_ES256 as i128) {
+        bail!("only ES256 credentials are supported");
+    }
+    if as_int(cose_int(map, -1)) != Some(1) {
+        bail!("only P-256 credentials are supported");
+    }
+    let x = as_bytes(cose_int(map, -2)).ok_or_else(|| anyhow!("COSE key missing x coordinate"))?;
+    let y = as_bytes(cose_int(map, -3)).ok_or_else(|| anyhow!("COSE key missing y coordinate"))?;
+    let mut sec1 = Vec::with_capacity(65);
+    sec1.push(0x04);
+    sec1.extend_from_slice(&x);
+    sec1.extend_from_slice(&y);
+    VerifyingKey::from_sec1_bytes(&sec1).map_err(|_| anyhow!("credential key is not a valid P-256 point"))?;
+    Ok(sec1)
+}
+
+pub fn verify_registration(
+    config: &WebAuthnConfig,
+    expected_challenge: &str,
+    credential: &RegistrationCredential,
+) -> Result<VerifiedRegistration> {
+    let client_data = decode_b64("clientDataJSON", &credential.response.client_data_json)?;
+    check_client_data(config, &client_data, "webauthn.create", expected_challenge)?;
+
+    let attestation = decode_b64("attestationObject", &credential.response.attestation_object)?;
+    let object: Value = ciborium::de::from_reader(attestation.as_slice())
+        .map_err(|err| anyhow!("attestationObject is not valid CBOR: {err}"))?;
+    let Value::Map(entries) = object else {
+        bail!("attestationObject must be a map");
+    };
+    let auth_data = entries
+        .iter()
+        .find_map(|(k, v)| match (k, v) {
+            (Value::Text(name), Value::Bytes(bytes)) if name == "authData" => Some(bytes.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("attestationObject missing authData"))?;
+
+    let parsed = parse_authenticator_data(&auth_data)?;
+    check_rp_and_presence(config, &parsed)?;
+    let (credential_id, cose_key) = parsed
+        .attested
+        .ok_or_else(|| anyhow!("registration missing attested credential data"))?;
+    let encoded_id = URL_SAFE_NO_PAD.encode(&credential_id);
+    if encoded_id != credential.id.trim_end_matches('=') {
+        bail!("credential id does not match attested credential");
+    }
+    let public_key = cose_to_sec1(&cose_key)?;
+
+    Ok(VerifiedRegistration {
+        credential_id: encoded_id,
+        public_key,
+        sign_count: parsed.sign_count,
+        user_verified: parsed.flags & FLAG_USER_VERIFIED != 0,
+    })
+}
+
+/// Verifies an assertion against the stored key and returns the authenticator's new
+/// signature counter.
+pub fn verify_assertion(
+    config: &WebAuthnConfig,
+    expected_challenge: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+    credential: &AssertionCredential,
+) -> Result<u32> {
+    let client_data = decode_b64("clientDataJSON", &credential.response.client_data_json)?;
+    check_client_data(config, &client_data, "webauthn.get", expected_challenge)?;
+
+    let auth_data = decode_b64("authenticatorData", &credential.response.authenticator_data)?;
+    let parsed = parse_authenticator_data(&auth_data)?;
+    check_rp_and_presence(config, &parsed)?;
+
+    let signature_der = decode_b64("signature", &credential.response.signature)?;
+    let signature = Signature::from_der(&signature_der).map_err(|_| anyhow!("signature is not valid DER"))?;
+    let key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| anyhow!("stored credential key is invalid"))?;
+
+    let mut signed = auth_data.clone();
+    signed.extend_from_slice(&Sha256::digest(&client_data));
+    key.verify(&signed, &signature)
+        .map_err(|_| anyhow!("assertion signature verification failed"))?;
+
+    // Authenticators that do not implement counters always report zero.
+    if (parsed.sign_count != 0 || stored_sign_count != 0) && parsed.sign_count <= stored_sign_count {
+        bail!("signature counter did not increase; possible cloned authenticator");
+    }
+    Ok(parsed.sign_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+
+    const ORIGIN: &str = "https://pos.example.com";
+
+    fn config() -> WebAuthnConfig {
+        WebAuthnConfig {
+            rp_id: "pos.example.com".into(),
+            rp_name: "NovaPOS".into(),
+            origins: vec![ORIGIN.into()],
+            timeout_ms: 60_000,
+            challenge_ttl_seconds: 300,
+        }
+    }
+
+    fn client_data(kind: &str, challenge: &str) -> String {
+        let json = serde_json::json!({ "type": kind, "challenge": challenge, "origin": ORIGIN });
+        URL_SAFE_NO_PAD.encode(json.to_string())
+    }
+
+    fn auth_data(flags: u8, sign_count: u32, attested: Option<(&[u8], &SigningKey)>) -> Vec<u8> {
+        let mut data = Sha256::digest(b"pos.example.com").to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        if let Some((credential_id, key)) = attested {
+            data.extend_from_slice(&[0u8; 16]);
+            data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(credential_id);
+            let point = key.verifying_key().to_encoded_point(false);
+            let cose = Value::Map(vec![
+                (Value::Integer(1.into()), Value::Integer(2.into())),
+                (Value::Integer(3.into()), Value::Integer((-7).into())),
+                (Value::Integer((-1).into()), Value::Integer(1.into())),
+                (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+                (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+            ]);
+            ciborium::ser::into_writer(&cose, &mut data).unwrap();
+        }
+        data
+    }
+
+    fn register(key: &SigningKey, challenge: &str) -> (RegistrationCredential, Vec<u8>) {
+        let credential_id = b"credential-123".to_vec();
+        let data = auth_data(FLAG_USER_PRESENT | FLAG_ATTESTED_DATA, 0, Some((&credential_id, key)));
+        let object = Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (Value::Text("authData".into()), Value::Bytes(data)),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&object, &mut encoded).unwrap();
+        (
+            RegistrationCredential {
+                id: URL_SAFE_NO_PAD.encode(&credential_id),
+                response: AttestationResponse {
+                    client_data_json: client_data("webauthn.create", challenge),
+                    attestation_object: URL_SAFE_NO_PAD.encode(encoded),
+                },
+            },
+            credential_id,
+        )
+    }
+
+    fn assert_with(key: &SigningKey, challenge: &str, sign_count: u32) -> AssertionCredential {
+        let data = auth_data(FLAG_USER_PRESENT, sign_count, None);
+        let client = client_data("webauthn.get", challenge);
+        let mut signed = data.clone();
+        signed.extend_from_slice(&Sha256::digest(URL_SAFE_NO_PAD.decode(&client).unwrap()));
+        let signature: Signature = key.sign(&signed);
+        AssertionCredential {
+            id: URL_SAFE_NO_PAD.encode(b"credential-123"),
+            response: AssertionResponse {
+                client_data_json: client,
+                authenticator_data: URL_SAFE_NO_PAD.encode(data),
+                signature: URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+            },
+        }
+    }
+
+    #[test]
+    fn registration_then_assertion_round_trip() {
+        let key = SigningKey::random(&mut OsRng);
+        let challenge = generate_challenge();
+        let (credential, raw_id) = register(&key, &challenge);
+        let registered = verify_registration(&config(), &challenge, &credential).expect("registration");
+        assert_eq!(registered.credential_id, URL_SAFE_NO_PAD.encode(raw_id));
+        assert_eq!(registered.public_key.len(), 65);
+
+        let login_challenge = generate_challenge();
+        let assertion = assert_with(&key, &login_challenge, 1);
+        let count = verify_assertion(&config(), &login_challenge, &registered.public_key, 0, &assertion)
+            .expect("assertion");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn rejects_wrong_challenge_foreign_key_and_replayed_counter() {
+        let key = SigningKey::random(&mut OsRng);
+        let challenge = generate_challenge();
+        let (credential, _) = register(&key, &challenge);
+        assert!(verify_registration(&config(), "other", &credential).is_err());
+        let registered = verify_registration(&config(), &challenge, &credential).unwrap();
+
+        let login_challenge = generate_challenge();
+        let other_key = SigningKey::random(&mut OsRng);
+        let forged = assert_with(&other_key, &login_challenge, 5);
+        assert!(verify_assertion(&config(), &login_challenge, &registered.public_key, 0, &forged).is_err());
+
+        let replay = assert_with(&key, &login_challenge, 3);
+        assert!(verify_assertion(&config(), &login_challenge, &registered.public_key, 3, &replay).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use common_auth::AuthContext;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::notifications::MfaActivityEvent;
+use crate::user_handlers::AuthError;
+use crate::webauthn::{
+    client_challenge, generate_challenge, verify_assertion, verify_registration,
+    AssertionCredential, CreationOptions, RegistrationCredential, WebAuthnUser,
+};
+use crate::AppState;
+
+pub(crate) const PURPOSE_REGISTRATION: &str = "registration";
+pub(crate) const PURPOSE_AUTHENTICATION: &str = "authentication";
+const MAX_CREDENTIAL_NAME_LEN: usize = 64;
+const MAX_CREDENTIALS_PER_USER: i64 = 10;
+
+#[derive(FromRow)]
+pub(crate) struct PasskeyRow {
+    pub id: Uuid,
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct WebAuthnCredentialSummary {
+    pub id: Uuid,
+    pub credential_id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct FinishRegistrationRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Deserialize)]
+pub struct RenameCredentialRequest {
+    pub name: String,
+}
+
+/// Why a login assertion was rejected; `Invalid` counts towards MFA lockout.
+pub(crate) enum PasskeyFailure {
+    Invalid(String),
+    Internal,
+}
+
+pub(crate) async fn load_passkeys(db: &PgPool, user_id: Uuid) -> Result<Vec<PasskeyRow>, sqlx::Error> {
+    sqlx::query_as::<_, PasskeyRow>(
+        "SELECT id, credential_id, public_key, sign_count FROM auth_webauthn_credentials WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Stores a fresh single-use challenge, replacing any outstanding challenge for the same purpose.
+pub(crate) async fn issue_challenge(
+    state: &AppState,
+    user_id: Uuid,
+    purpose: &'static str,
+) -> Result<String, sqlx::Error> {
+    let challenge = generate_challenge();
+    let expires_at = Utc::now() + Duration::seconds(state.config.webauthn.challenge_ttl_seconds);
+    let mut tx = state.db.begin().await?;
+    sqlx::query("DELETE FROM auth_webauthn_challenges WHERE user_id = $1 AND purpose = $2")
+        .bind(user_id)
+        .bind(purpose)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO auth_webauthn_challenges (id, user_id, purpose, challenge, expires_at) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(purpose)
+    .bind(&challenge)
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(challenge)
+}
+
+async fn consume_challenge(
+    db: &PgPool,
+    user_id: Uuid,
+    purpose: &'static str,
+    challenge: &str,
+) -> Result<bool, sqlx::Error> {
+    let consumed = sqlx::query(
+        "DELETE FROM auth_webauthn_challenges WHERE user_id = $1 AND purpose = $2 AND challenge = $3 AND expires_at > NOW()",
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(challenge)
+    .execute(db)
+    .await?;
+    Ok(consumed.rows_affected() == 1)
+}
+
+/// Verifies a login assertion against the user's registered passkeys and advances the
+/// stored signature counter.
+pub(crate) async fn verify_login_assertion(
+    state: &AppState,
+    user_id: Uuid,
+    passkeys: &[PasskeyRow],
+    assertion: &AssertionCredential,
+) -> Result<(), PasskeyFailure> {
+    let passkey = passkeys
+        .iter()
+        .find(|key| key.credential_id == assertion.id.trim_end_matches('='))
+        .ok_or_else(|| PasskeyFailure::Invalid("unknown_credential".into()))?;
+    let challenge = client_challenge(&assertion.response.client_data_json)
+        .map_err(|err| PasskeyFailure::Invalid(err.to_string()))?;
+    let consumed = consume_challenge(&state.db, user_id, PURPOSE_AUTHENTICATION, &challenge)
+        .await
+        .map_err(|err| {
+            error!(user_id = %user_id, error = %err, "Failed to consume WebAuthn challenge");
+            PasskeyFailure::Internal
+        })?;
+    if !consumed {
+        return Err(PasskeyFailure::Invalid("challenge_expired".into()));
+    }
+
+    let stored_count = u32::try_from(passkey.sign_count).unwrap_or(u32::MAX);
+    let sign_count = verify_assertion(
+        &state.config.webauthn,
+        &challenge,
+        &passkey.public_key,
+        stored_count,
+        assertion,
+    )
+    .map_err(|err| PasskeyFailure::Invalid(err.to_string()))?;
+
+    if let Err(err) = sqlx::query(
+        "UPDATE auth_webauthn_credentials SET sign_count = $2, last_used_at = NOW() WHERE id = $1",
+    )
+    .bind(passkey.id)
+    .bind(i64::from(sign_count))
+    .execute(&state.db)
+    .await
+    {
+        warn!(credential = %passkey.id, error = %err, "Failed to update passkey signature counter");
+    }
+    Ok(())
+}
+
+async fn emit_passkey_event(
+    state: &AppState,
+    auth: &AuthContext,
+    action: &'static str,
+    severity: &'static str,
+    detail: serde_json::Value,
+) {
+    let trace_id = Uuid::new_v4();
+    info!(
+        event = action,
+        user_id = %auth.claims.subject,
+        tenant_id = %auth.claims.tenant_id,
+        trace_id = %trace_id,
+        "Passkey activity"
+    );
+    let event = MfaActivityEvent {
+        action,
+        severity,
+        tenant_id: auth.claims.tenant_id,
+        user_id: Some(auth.claims.subject),
+        trace_id,
+        occurred_at: Utc::now(),
+        ip: None,
+        user_agent: None,
+        device: None,
+        role: None,
+        detail: Some(detail.to_string()),
+    };
+    state.emit_mfa_activity(event, None).await;
+}
+
+fn normalize_credential_name(name: Option<&str>) -> Result<String, AuthError> {
+    let name = name.map(str::trim).filter(|value| !value.is_empty()).unwrap_or("Passkey");
+    if name.chars().count() > MAX_CREDENTIAL_NAME_LEN {
+        return Err(AuthError::with_detail(
+            StatusCode::BAD_REQUEST,
+            "WEBAUTHN_NAME_INVALID",
+            format!("Passkey names must be at most {MAX_CREDENTIAL_NAME_LEN} characters."),
+            None,
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn database_error(action: &str, err: sqlx::Error) -> AuthError {
+    error!(error = %err, "Failed to {action}");
+    AuthError::internal_error(format!("Unable to {action}"))
+}
+
+pub async fn begin_webauthn_registration(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<CreationOptions>, AuthError> {
+    let user_id = auth.claims.subject;
+    let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|err| database_error("load passkey owner", err))?
+        .ok_or_else(|| AuthError::internal_error("User account missing"))?;
+
+    let existing = load_passkeys(&state.db, user_id)
+        .await
+        .map_err(|err| database_error("load passkeys", err))?;
+    if existing.len() as i64 >= MAX_CREDENTIALS_PER_USER {
+        return Err(AuthError::with_detail(
+            StatusCode::CONFLICT,
+            "WEBAUTHN_LIMIT_REACHED",
+            format!("At most {MAX_CREDENTIALS_PER_USER} passkeys can be registered."),
+            None,
+        ));
+    }
+
+    let challenge = issue_challenge(&state, user_id, PURPOSE_REGISTRATION)
+        .await
+        .map_err(|err| database_error("start passkey registration", err))?;
+
+    let user = WebAuthnUser {
+        id: URL_SAFE_NO_PAD.encode(user_id.as_bytes()),
+        name: email.clone(),
+        display_name: email,
+    };
+    let options = state.config.webauthn.creation_options(
+        challenge,
+        user,
+        existing.into_iter().map(|key| key.credential_id).collect(),
+    );
+    Ok(Json(options))
+}
+
+pub async fn finish_webauthn_registration(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(payload): Json<FinishRegistrationRequest>,
+) -> Result<(StatusCode, Json<WebAuthnCredentialSummary>), AuthError> {
+    let user_id = auth.claims.subject;
+    let name = normalize_credential_name(payload.name.as_deref())?;
+
+    let rejected = |reason: String| {
+        AuthError::with_detail(
+            StatusCode::BAD_REQUEST,
+            "WEBAUTHN_REGISTRATION_INVALID",
+            format!("Passkey registration failed: {reason}"),
+            None,
+        )
+    };
+
+    let challenge = client_challenge(&payload.credential.response.client_data_json)
+        .map_err(|err| rejected(err.to_string()))?;
+    let consumed = consume_challenge(&state.db, user_id, PURPOSE_REGISTRATION, &challenge)
+        .await
+        .map_err(|err| database_error("complete passkey registration", err))?;
+    if !consumed {
+        return Err(rejected("registration challenge expired or unknown".into()));
+    }
+
+    let verified = match verify_registration(&state.config.webauthn, &challenge, &payload.credential) {
+        Ok(verified) => verified,
+        Err(err) => {
+            emit_passkey_event(
+                &state,
+                &auth,
+                "mfa.webauthn.register_failed",
+                "warn",
+                json!({ "reason": err.to_string() }),
+            )
+            .await;
+            return Err(rejected(err.to_string()));
+        }
+    };
+
+    let summary = sqlx::query_as::<_, WebAuthnCredentialSummary>(
+        "INSERT INTO auth_webauthn_credentials (id, user_id, tenant_id, credential_id, public_key, sign_count, name)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (credential_id) DO NOTHING
+         RETURNING id, credential_id, name, created_at, last_used_at",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(auth.claims.tenant_id)
+    .bind(&verified.credential_id)
+    .bind(&verified.public_key)
+    .bind(i64::from(verified.sign_count))
+    .bind(&name)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| database_error("store passkey", err))?
+    .ok_or_else(|| {
+        AuthError::with_detail(
+            StatusCode::CONFLICT,
+            "WEBAUTHN_CREDENTIAL_EXISTS",
+            "This passkey is already registered.",
+            None,
+        )
+    })?;
+
+    emit_passkey_event(
+        &state,
+        &auth,
+        "mfa.webauthn.registered",
+        "info",
+        json!({ "credential": summary.id, "user_verified": verified.user_verified }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+pub async fn list_webauthn_credentials(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Vec<WebAuthnCredentialSummary>>, AuthError> {
+    let credentials = sqlx::query_as::<_, WebAuthnCredentialSummary>(
+        "SELECT id, credential_id, name, created_at, last_used_at FROM auth_webauthn_credentials WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(auth.claims.subject)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| database_error("list passkeys", err))?;
+    Ok(Json(credentials))
+}
+
+pub async fn rename_webauthn_credential(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(credential_id): Path<Uuid>,
+    Json(payload): Json<RenameCredentialRequest>,
+) -> Result<Json<WebAuthnCredentialSummary>, AuthError> {
+    let name = normalize_credential_name(Some(&payload.name))?;
+    let summary = sqlx::query_as::<_, WebAuthnCredentialSummary>(
+        "UPDATE auth_webauthn_credentials SET name = $3 WHERE id = $1 AND user_id = $2
+         RETURNING id, credential_id, name, created_at, last_used_at",
+    )
+    .bind(credential_id)
+    .bind(auth.claims.subject)
+    .bind(&name)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|err| database_error("rename passkey", err))?
+    .ok_or_else(credential_not_found)?;
+
+    emit_passkey_event(
+        &state,
+        &auth,
+        "mfa.webauthn.renamed",
+        "info",
+        json!({ "credential": summary.id }),
+    )
+    .await;
+    Ok(Json(summary))
+}
+
+pub async fn delete_webauthn_credential(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(credential_id): Path<Uuid>,
+) -> Result<StatusCode, AuthError> {
+    let result = sqlx::query("DELETE FROM auth_webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(credential_id)
+        .bind(auth.claims.subject)
+        .execute(&state.db)
+        .await
+        .map_err(|err| database_error("delete passkey", err))?;
+    if result.rows_affected() == 0 {
+        return Err(credential_not_found());
+    }
+
+    emit_passkey_event(
+        &state,
+        &auth,
+        "mfa.webauthn.removed",
+        "warn",
+        json!({ "credential": credential_id }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn credential_not_found() -> AuthError {
+    AuthError::with_detail(
+        StatusCode::NOT_FOUND,
+        "WEBAUTHN_CREDENTIAL_NOT_FOUND",
+        "Passkey not found.",
+        None,
+    )
+}
@@ -1,6 +1,7 @@
 mod support;
 
 use anyhow::{anyhow, Result};
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
 use auth_service::metrics::AuthMetrics;
 use auth_service::mfa::generate_totp_secret;
 use auth_service::mfa_handlers::{begin_mfa_enrollment, verify_mfa_enrollment};
@@ -158,7 +159,9 @@ async fn axum_smoke_tests_core_routes() -> Result<()> {
         config: Arc::new(config),
         kafka_producer,
         http_client,
+        idp_client: Client::new(),
         metrics: Arc::new(AuthMetrics::new()?),
+        login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())),
     };
 
     let app = Router::new()
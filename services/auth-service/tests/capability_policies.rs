@@ -0,0 +1,132 @@
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
+use auth_service::metrics::AuthMetrics;
+use auth_service::notifications::KafkaProducer;
+use auth_service::policy_handlers::{list_policy_documents, seed_default_policy, update_capability_policy};
+use auth_service::tokens::{TokenConfig, TokenSigner, TokenSubject};
+use auth_service::AppState;
+use axum::{Router, routing::{get, put}, http::{Request, StatusCode}, body::{Body, to_bytes}};
+use common_auth::{JwtConfig, JwtVerifier};
+use jsonwebtoken::DecodingKey;
+use reqwest::Client;
+use rsa::{RsaPrivateKey, pkcs1::EncodeRsaPublicKey, pkcs8::EncodePrivateKey};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+mod support;
+use support::{seed_test_user, default_auth_config, RecordingKafkaProducer, TestDatabase};
+
+async fn call(app: &Router, uri: &str, headers: &[(&str, &str)], body: Option<Value>) -> anyhow::Result<(StatusCode, Value)> {
+    let method = if body.is_some() { "PUT" } else { "GET" };
+    let mut req = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    let resp = app.clone().oneshot(req.body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))?).await?;
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await?;
+    Ok((status, serde_json::from_slice(&bytes).unwrap_or(Value::Null)))
+}
+
+fn listed_tenants(body: &Value) -> Vec<Uuid> {
+    let mut tenants: Vec<Uuid> = body["documents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|doc| doc["tenant_id"].as_str().unwrap().parse().unwrap())
+        .collect();
+    tenants.sort();
+    tenants
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn policy_documents_are_scoped_to_the_caller() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let tenant_a = seed_test_user(&pool, "admin").await?.tenant_id;
+    let tenant_b = seed_test_user(&pool, "admin").await?.tenant_id;
+
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048)?;
+    let private_pem = private_key.to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)?.to_string();
+    let public_pem = private_key.to_public_key().to_pkcs1_pem(rsa::pkcs1::LineEnding::LF)?.to_string();
+    let token_config = TokenConfig { issuer: "test-issuer".into(), audience: "test-audience".into(), access_ttl_seconds: 300, refresh_ttl_seconds: 900 };
+    let signer = Arc::new(TokenSigner::new(pool.clone(), token_config, Some(&private_pem)).await?);
+    let jwks = signer.jwks().await?;
+    let mut verifier_builder = JwtVerifier::builder(JwtConfig::new("test-issuer", "test-audience"));
+    if jwks.is_empty() { verifier_builder = verifier_builder.with_rsa_pem("local-dev", public_pem.as_bytes())?; } else { for key in &jwks { verifier_builder = verifier_builder.with_decoding_key(key.kid.clone(), DecodingKey::from_rsa_components(&key.n, &key.e).expect("invalid jwk")); } }
+    let verifier = verifier_builder.build().await?;
+    let mut config = default_auth_config();
+    config.capability_policy_token = Some("policy-secret".into());
+    let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(RecordingKafkaProducer::default());
+    let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: signer.clone(), config: Arc::new(config), kafka_producer, http_client: Client::new(), idp_client: Client::new(), metrics: Arc::new(AuthMetrics::new()?), login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())) };
+    let app = Router::new()
+        .route("/capability-policies", get(list_policy_documents))
+        .route("/tenants/:tenant_id/capability-policy", put(update_capability_policy))
+        .with_state(state);
+    let admin = |tenant_id: Uuid| -> anyhow::Result<String> {
+        let subject = TokenSubject { user_id: Uuid::new_v4(), tenant_id, roles: vec!["admin".into()], residency: None };
+        Ok(format!("Bearer {}", signer.issue_service_token(&subject, 300)?))
+    };
+    let (admin_a, admin_b) = (admin(tenant_a)?, admin(tenant_b)?);
+    for (tenant_id, auth) in [(tenant_a, &admin_a), (tenant_b, &admin_b)] {
+        let grants = json!({"grants": {"customer_write": ["cashier"]}});
+        let (status, body) = call(&app, &format!("/tenants/{tenant_id}/capability-policy"), &[("authorization", auth)], Some(grants)).await?;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    let (status, _) = call(&app, "/capability-policies", &[], None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "policy documents need a credential");
+    let (status, _) = call(&app, "/capability-policies", &[("X-Internal-Token", "wrong")], None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = call(&app, "/capability-policies", &[("authorization", &admin_a)], None).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed_tenants(&body), vec![tenant_a], "a tenant sees only its own document");
+    let (status, _) = call(&app, &format!("/capability-policies?tenant_id={tenant_b}"), &[("authorization", &admin_a)], None).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = call(&app, "/capability-policies", &[("X-Internal-Token", "policy-secret")], None).await?;
+    assert_eq!(status, StatusCode::OK);
+    let listed = listed_tenants(&body);
+    assert!(listed.contains(&tenant_a) && listed.contains(&tenant_b), "services sync every tenant: {body}");
+
+    db.teardown().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn provisioning_seeds_default_roles_once() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let tenant_id = seed_test_user(&pool, "admin").await?.tenant_id;
+    let actor = Uuid::new_v4();
+
+    assert!(seed_default_policy(&pool, tenant_id, actor).await?);
+    let roles: Vec<String> = sqlx::query_scalar("SELECT roles FROM tenant_capability_policies WHERE tenant_id = $1 AND capability = 'order_void'")
+        .bind(tenant_id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(roles, vec!["admin".to_string(), "manager".to_string()], "super_admin is not grantable per tenant");
+
+    sqlx::query("UPDATE tenant_capability_policies SET roles = '{admin}' WHERE tenant_id = $1 AND capability = 'order_void'")
+        .bind(tenant_id)
+        .execute(&pool)
+        .await?;
+    assert!(!seed_default_policy(&pool, tenant_id, actor).await?, "a retried run leaves an existing policy alone");
+    let (rows, roles): (i64, Vec<String>) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM tenant_capability_policies WHERE tenant_id = $1),
+                (SELECT roles FROM tenant_capability_policies WHERE tenant_id = $1 AND capability = 'order_void')",
+    )
+    .bind(tenant_id)
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(rows, common_security::Capability::ALL.len() as i64);
+    assert_eq!(roles, vec!["admin".to_string()]);
+
+    db.teardown().await?;
+    Ok(())
+}
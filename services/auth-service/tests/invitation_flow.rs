@@ -0,0 +1,135 @@
+use auth_service::invitation_handlers::{
+    accept_invitation, begin_invitation_mfa, create_invitation, list_invitations, resend_invitation, revoke_invitation,
+};
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
+use auth_service::metrics::AuthMetrics;
+use auth_service::notifications::KafkaProducer;
+use auth_service::tokens::{TokenConfig, TokenSigner, TokenSubject};
+use auth_service::user_handlers::login_user;
+use auth_service::AppState;
+use axum::{Router, routing::{delete, post}, http::{Request, StatusCode}, body::{Body, to_bytes}};
+use common_auth::{JwtConfig, JwtVerifier};
+use jsonwebtoken::DecodingKey;
+use reqwest::Client;
+use rsa::{RsaPrivateKey, pkcs1::EncodeRsaPublicKey, pkcs8::EncodePrivateKey};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::util::ServiceExt;
+
+mod support;
+use support::{current_totp_code, seed_test_user, default_auth_config, RecordingKafkaProducer, TestDatabase};
+
+async fn call(app: &Router, method: &str, uri: &str, bearer: Option<&str>, tenant: Option<&str>, body: Value) -> anyhow::Result<(StatusCode, Value)> {
+    let mut req = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+    if let Some(token) = bearer { req = req.header("authorization", format!("Bearer {token}")); }
+    if let Some(tenant) = tenant { req = req.header("X-Tenant-ID", tenant); }
+    let resp = app.clone().oneshot(req.body(Body::from(body.to_string()))?).await?;
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await?;
+    Ok((status, serde_json::from_slice(&bytes).unwrap_or(Value::Null)))
+}
+
+/// The token from the last invitation email; without `INVITE_ACCEPT_URL` it is sent verbatim.
+fn emailed_token(kafka: &RecordingKafkaProducer) -> String {
+    let email = kafka.drain().into_iter().rev().find(|event| event.topic == "notification.email.requested").expect("invitation email");
+    let payload: Value = serde_json::from_str(&email.payload).unwrap();
+    let body = payload["body"].as_str().unwrap();
+    body.split("Invitation code: ").nth(1).unwrap().split_whitespace().next().unwrap().to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn invited_users_set_their_password_and_mfa_once() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let admin = seed_test_user(&pool, "admin").await?;
+    let tenant = admin.tenant_id.to_string();
+
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048)?;
+    let private_pem = private_key.to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)?.to_string();
+    let public_pem = private_key.to_public_key().to_pkcs1_pem(rsa::pkcs1::LineEnding::LF)?.to_string();
+    let token_config = TokenConfig { issuer: "test-issuer".into(), audience: "test-audience".into(), access_ttl_seconds: 300, refresh_ttl_seconds: 900 };
+    let token_signer = TokenSigner::new(pool.clone(), token_config, Some(&private_pem)).await?;
+    let jwks = token_signer.jwks().await?;
+    let mut verifier_builder = JwtVerifier::builder(JwtConfig::new("test-issuer", "test-audience"));
+    if jwks.is_empty() { verifier_builder = verifier_builder.with_rsa_pem("local-dev", public_pem.as_bytes())?; } else { for key in &jwks { verifier_builder = verifier_builder.with_decoding_key(key.kid.clone(), DecodingKey::from_rsa_components(&key.n, &key.e).expect("invalid jwk")); } }
+    let verifier = verifier_builder.build().await?;
+    let admin_token = token_signer.issue_tokens(TokenSubject { user_id: admin.user_id, tenant_id: admin.tenant_id, roles: vec!["admin".into()], residency: None }).await?.access_token;
+
+    let kafka = RecordingKafkaProducer::default();
+    let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(kafka.clone());
+    let mut config = default_auth_config();
+    config.required_roles.insert("manager".into());
+    let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: Arc::new(token_signer), config: Arc::new(config), kafka_producer, http_client: Client::builder().build()?, idp_client: Client::new(), metrics: Arc::new(AuthMetrics::new()?), login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())) };
+    let app = Router::new()
+        .route("/login", post(login_user))
+        .route("/invitations", post(create_invitation).get(list_invitations))
+        .route("/invitations/accept", post(accept_invitation))
+        .route("/invitations/accept/mfa", post(begin_invitation_mfa))
+        .route("/invitations/:invitation_id", delete(revoke_invitation))
+        .route("/invitations/:invitation_id/resend", post(resend_invitation))
+        .with_state(state);
+    let admin_call = |method: &'static str, uri: String, body: Value| {
+        let app = app.clone();
+        let (token, tenant) = (admin_token.clone(), tenant.clone());
+        async move { call(&app, method, &uri, Some(&token), Some(&tenant), body).await }
+    };
+
+    // A cashier invite: no MFA needed, the emailed token sets the password once.
+    let (status, invite) = admin_call("POST", "/invitations".into(), json!({"email": "new.cashier@example.com", "role": "cashier", "name": "New Cashier"})).await?;
+    assert_eq!(status, StatusCode::CREATED, "{invite}");
+    assert_eq!((invite["status"].as_str(), invite["email_sent"].as_bool()), (Some("pending"), Some(true)));
+    let (status, _) = admin_call("POST", "/invitations".into(), json!({"email": "NEW.cashier@example.com", "role": "cashier"})).await?;
+    assert_eq!(status, StatusCode::CONFLICT, "one pending invitation per address");
+    let first_token = emailed_token(&kafka);
+    let (status, resent) = admin_call("POST", format!("/invitations/{}/resend", invite["id"].as_str().unwrap()), Value::Null).await?;
+    assert_eq!(status, StatusCode::OK, "{resent}");
+    let token = emailed_token(&kafka);
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": first_token, "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND, "resending replaces the earlier token");
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": token, "password": "short"})).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, user) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": token, "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::CREATED, "{user}");
+    assert_eq!((user["role"].as_str(), user["is_active"].as_bool(), user["force_password_reset"].as_bool()), (Some("cashier"), Some(true), Some(false)));
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": token, "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND, "tokens are single use");
+    let (status, _) = call(&app, "POST", "/login", None, None, json!({"email": "new.cashier@example.com", "password": "AnotherStrongPass!42", "tenant_id": admin.tenant_id})).await?;
+    assert_eq!(status, StatusCode::OK);
+
+    // A manager invite must enroll TOTP before the account exists.
+    let (_, manager) = admin_call("POST", "/invitations".into(), json!({"email": "manager@example.com", "role": "manager"})).await?;
+    let token = emailed_token(&kafka);
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": token, "name": "Manager", "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let (status, enrollment) = call(&app, "POST", "/invitations/accept/mfa", None, None, json!({"token": token})).await?;
+    assert_eq!(status, StatusCode::OK, "{enrollment}");
+    let code = current_totp_code(enrollment["secret"].as_str().unwrap())?;
+    let (status, user) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": token, "name": "Manager", "password": "AnotherStrongPass!42", "mfa_code": code})).await?;
+    assert_eq!(status, StatusCode::CREATED, "{user}");
+    let enrolled: bool = sqlx::query_scalar("SELECT mfa_secret IS NOT NULL FROM users WHERE id = $1::uuid").bind(user["id"].as_str().unwrap()).fetch_one(&pool).await?;
+    assert!(enrolled);
+    let (_, listed) = admin_call("GET", "/invitations?status=accepted".into(), Value::Null).await?;
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    let (status, _) = admin_call("DELETE", format!("/invitations/{}", manager["id"].as_str().unwrap()), Value::Null).await?;
+    assert_eq!(status, StatusCode::CONFLICT, "accepted invitations can't be revoked");
+
+    // Revoked and expired invitations can't be redeemed.
+    let (_, revoked) = admin_call("POST", "/invitations".into(), json!({"email": "revoked@example.com", "role": "cashier", "name": "R"})).await?;
+    let revoked_token = emailed_token(&kafka);
+    let (status, _) = admin_call("DELETE", format!("/invitations/{}", revoked["id"].as_str().unwrap()), Value::Null).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": revoked_token, "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, expired) = admin_call("POST", "/invitations".into(), json!({"email": "late@example.com", "role": "cashier", "name": "L"})).await?;
+    let expired_token = emailed_token(&kafka);
+    sqlx::query("UPDATE user_invitations SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1::uuid").bind(expired["id"].as_str().unwrap()).execute(&pool).await?;
+    let (status, _) = call(&app, "POST", "/invitations/accept", None, None, json!({"token": expired_token, "password": "AnotherStrongPass!42"})).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, listed) = admin_call("GET", "/invitations?status=expired".into(), Value::Null).await?;
+    assert_eq!(listed[0]["email"], "late@example.com");
+
+    db.teardown().await?;
+    Ok(())
+}
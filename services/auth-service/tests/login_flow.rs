@@ -16,6 +16,7 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use auth_service::config::AuthConfig;
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
 use auth_service::metrics::AuthMetrics;
 use auth_service::notifications::KafkaProducer;
 use async_trait::async_trait;
@@ -172,7 +173,9 @@ impl TestContext {
             config: Arc::new(config),
             kafka_producer,
             http_client,
+            idp_client: Client::new(),
             metrics: Arc::new(AuthMetrics::new()?),
+            login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())),
         };
 
         Ok(Some(Self {
@@ -196,6 +199,7 @@ impl TestContext {
             password: password.to_string(),
             tenant_id: Some(self.tenant_id),
             mfa_code: None,
+            webauthn_assertion: None,
             device_fingerprint: Some("device-123".to_string()),
         };
 
@@ -408,6 +412,7 @@ async fn login_locks_account_after_repeated_failures() -> Result<()> {
                 password: "wrong-password".to_string(),
                 tenant_id: Some(ctx.tenant_id),
                 mfa_code: None,
+                webauthn_assertion: None,
                 device_fingerprint: None,
             }),
         )
@@ -457,6 +462,7 @@ async fn login_requires_mfa_when_enforced() -> Result<()> {
                 password: ctx.password.clone(),
                 tenant_id: Some(ctx.tenant_id),
                 mfa_code: None,
+                webauthn_assertion: None,
                 device_fingerprint: None,
             }),
         )
@@ -501,6 +507,7 @@ async fn login_rejects_previously_locked_account() -> Result<()> {
             password: ctx.password.clone(),
             tenant_id: Some(ctx.tenant_id),
             mfa_code: None,
+            webauthn_assertion: None,
             device_fingerprint: None,
         }),
     )
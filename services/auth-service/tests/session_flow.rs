@@ -1,5 +1,6 @@
 use auth_service::user_handlers::{login_user, logout_user, refresh_session};
-use auth_service::{tokens::TokenConfig, AppState};
+use auth_service::{tokens::{SessionDevice, SessionRevoked, TokenConfig, TokenSubject}, AppState};
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
 use auth_service::metrics::AuthMetrics;
 use auth_service::notifications::KafkaProducer;
 use auth_service::mfa_handlers::{begin_mfa_enrollment, verify_mfa_enrollment};
@@ -42,7 +43,7 @@ async fn session_flow_basic_refresh_and_logout() -> anyhow::Result<()> {
     let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(kafka_recorder);
     let http_client = Client::builder().build()?;
     let config = default_auth_config();
-    let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: Arc::new(token_signer), config: Arc::new(config), kafka_producer, http_client, metrics: Arc::new(AuthMetrics::new()?) };
+    let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: Arc::new(token_signer), config: Arc::new(config), kafka_producer, http_client, idp_client: Client::new(), metrics: Arc::new(AuthMetrics::new()?), login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())) };
 
     let app = Router::new()
         .route("/login", post(login_user))
@@ -85,3 +86,28 @@ async fn session_flow_basic_refresh_and_logout() -> anyhow::Result<()> {
     db.teardown().await?;
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn rotating_a_revoked_session_reports_session_revoked() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let seeded = seed_test_user(&pool, "cashier").await?;
+
+    let private_pem = RsaPrivateKey::new(&mut OsRng, 2048)?.to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)?.to_string();
+    let token_config = TokenConfig { issuer: "test-issuer".into(), audience: "test-audience".into(), access_ttl_seconds: 300, refresh_ttl_seconds: 900 };
+    let token_signer = auth_service::tokens::TokenSigner::new(pool.clone(), token_config, Some(&private_pem)).await?;
+    let subject = || TokenSubject { user_id: seeded.user_id, tenant_id: seeded.tenant_id, roles: vec!["cashier".into()], residency: None };
+
+    let issued = token_signer.issue_tokens(subject()).await?;
+    // Revoked between the refresh token being consumed and the session being rotated.
+    sqlx::query("UPDATE auth_sessions SET revoked_at = NOW() WHERE id = $1").bind(issued.session_id).execute(&pool).await?;
+    let err = match token_signer.issue_session_tokens(subject(), Some(issued.session_id), &SessionDevice::default()).await {
+        Ok(_) => anyhow::bail!("a revoked session must not be rotated"),
+        Err(err) => err,
+    };
+    assert!(err.is::<SessionRevoked>(), "{err:#}");
+
+    db.teardown().await?;
+    Ok(())
+}
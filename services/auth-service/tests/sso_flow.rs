@@ -0,0 +1,96 @@
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
+use auth_service::metrics::AuthMetrics;
+use auth_service::notifications::KafkaProducer;
+use auth_service::oidc_handlers::{delete_tenant_sso, seal_plaintext_client_secrets, upsert_tenant_sso};
+use auth_service::tokens::{TokenConfig, TokenSigner, TokenSubject};
+use auth_service::AppState;
+use axum::{Router, routing::put, http::{Request, StatusCode}, body::{Body, to_bytes}};
+use common_auth::{JwtConfig, JwtVerifier};
+use common_crypto::{ColumnKey, MasterKey};
+use jsonwebtoken::DecodingKey;
+use reqwest::Client;
+use rsa::{RsaPrivateKey, pkcs1::EncodeRsaPublicKey, pkcs8::EncodePrivateKey};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+mod support;
+use support::{seed_test_user, default_auth_config, RecordingKafkaProducer, TestDatabase};
+
+async fn put_sso(app: &Router, token: &str, tenant: Uuid, body: Value) -> anyhow::Result<(StatusCode, Value)> {
+    let req = Request::builder().method("PUT").uri(format!("/tenants/{tenant}/sso")).header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}")).header("X-Tenant-ID", tenant.to_string());
+    let resp = app.clone().oneshot(req.body(Body::from(body.to_string()))?).await?;
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await?;
+    Ok((status, serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into()))))
+}
+
+fn provider(domains: &[&str]) -> Value {
+    json!({"issuer": "https://login.contoso.com", "client_id": "pos", "client_secret": "s3cr3t", "allowed_domains": domains})
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn an_email_domain_routes_to_one_tenant_only() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let first = seed_test_user(&pool, "admin").await?;
+    let second = seed_test_user(&pool, "admin").await?;
+
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048)?;
+    let private_pem = private_key.to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)?.to_string();
+    let public_pem = private_key.to_public_key().to_pkcs1_pem(rsa::pkcs1::LineEnding::LF)?.to_string();
+    let token_config = TokenConfig { issuer: "test-issuer".into(), audience: "test-audience".into(), access_ttl_seconds: 300, refresh_ttl_seconds: 900 };
+    let token_signer = TokenSigner::new(pool.clone(), token_config, Some(&private_pem)).await?;
+    let jwks = token_signer.jwks().await?;
+    let mut verifier_builder = JwtVerifier::builder(JwtConfig::new("test-issuer", "test-audience"));
+    if jwks.is_empty() { verifier_builder = verifier_builder.with_rsa_pem("local-dev", public_pem.as_bytes())?; } else { for key in &jwks { verifier_builder = verifier_builder.with_decoding_key(key.kid.clone(), DecodingKey::from_rsa_components(&key.n, &key.e).expect("invalid jwk")); } }
+    let verifier = verifier_builder.build().await?;
+    let mut tokens = Vec::new();
+    for admin in [&first, &second] {
+        tokens.push(token_signer.issue_tokens(TokenSubject { user_id: admin.user_id, tenant_id: admin.tenant_id, roles: vec!["admin".into()], residency: None }).await?.access_token);
+    }
+
+    let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(RecordingKafkaProducer::default());
+    let mut config = default_auth_config();
+    config.sso.secret_key = Some(ColumnKey::new(MasterKey::from_bytes([5u8; 32])?));
+    let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: Arc::new(token_signer), config: Arc::new(config), kafka_producer, http_client: Client::new(), idp_client: Client::new(), metrics: Arc::new(AuthMetrics::new()?), login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())) };
+    let app = Router::new().route("/tenants/:tenant_id/sso", put(upsert_tenant_sso).delete(delete_tenant_sso)).with_state(state);
+    let domain = format!("{}.example.com", Uuid::new_v4().simple());
+    let other = format!("other-{domain}");
+
+    let (status, body) = put_sso(&app, &tokens[0], first.tenant_id, provider(&[&domain, &format!("@{}", domain.to_uppercase())])).await?;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["allowed_domains"], json!([domain]), "domains are normalized and de-duplicated");
+    let (status, body) = put_sso(&app, &tokens[1], second.tenant_id, provider(&[&other, &domain])).await?;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let (status, _) = put_sso(&app, &tokens[1], second.tenant_id, provider(&[&other])).await?;
+    assert_eq!(status, StatusCode::OK, "the rejected save left no partial claim behind");
+
+    // Re-saving keeps a tenant's own domains; dropping one frees it for another tenant.
+    let (status, _) = put_sso(&app, &tokens[0], first.tenant_id, provider(&[&domain])).await?;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = put_sso(&app, &tokens[0], first.tenant_id, provider(&[])).await?;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = put_sso(&app, &tokens[1], second.tenant_id, provider(&[&other, &domain])).await?;
+    assert_eq!(status, StatusCode::OK);
+    let owner: Uuid = sqlx::query_scalar("SELECT tenant_id FROM tenant_sso_domains WHERE domain = $1").bind(&domain).fetch_one(&pool).await?;
+    assert_eq!(owner, second.tenant_id);
+
+    // Secrets are stored sealed; one left in plaintext from before is sealed by the startup pass.
+    let stored: (Option<String>, Option<Vec<u8>>) = sqlx::query_as("SELECT client_secret, client_secret_encrypted FROM tenant_identity_providers WHERE tenant_id = $1").bind(first.tenant_id).fetch_one(&pool).await?;
+    assert!(stored.0.is_none() && !String::from_utf8_lossy(&stored.1.unwrap()).contains("s3cr3t"));
+    sqlx::query("UPDATE tenant_identity_providers SET client_secret = 'legacy', client_secret_encrypted = NULL WHERE tenant_id = $1").bind(first.tenant_id).execute(&pool).await?;
+    assert!(seal_plaintext_client_secrets(&pool, &ColumnKey::new(MasterKey::from_bytes([5u8; 32])?)).await? >= 1);
+    let (status, _) = put_sso(&app, &tokens[0], first.tenant_id, json!({"issuer": "https://login.contoso.com", "client_id": "pos"})).await?;
+    assert_eq!(status, StatusCode::OK, "the sealed legacy secret is kept when none is sent");
+
+    let (status, _) = put_sso(&app, &tokens[0], first.tenant_id, json!({"issuer": "https://169.254.169.254", "client_id": "pos", "client_secret": "x"})).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "internal issuers are refused");
+
+    db.teardown().await?;
+    Ok(())
+}
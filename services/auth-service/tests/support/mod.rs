@@ -1,4 +1,4 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -8,7 +8,11 @@ use anyhow::{anyhow, Context, Result};
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
 use async_trait::async_trait;
 use auth_service::config::{AuthConfig, CookieSameSite};
+use auth_service::login_guard::LockoutPolicy;
 use auth_service::notifications::KafkaProducer;
+use auth_service::oidc::SsoConfig;
+use auth_service::password_policy::PasswordPolicy;
+use auth_service::webauthn::WebAuthnConfig;
 use data_encoding::BASE32_NOPAD;
 use dirs::cache_dir;
 use hmac::{Hmac, Mac};
@@ -343,6 +347,23 @@ pub fn default_auth_config() -> AuthConfig {
         refresh_cookie_domain: None,
         refresh_cookie_secure: false,
         refresh_cookie_same_site: CookieSameSite::Lax,
+        integration_key_topic: "security.integration_keys.v1".to_string(),
+        password_policy: PasswordPolicy::default(),
+        lockout: LockoutPolicy::default(),
+        mfa_role_methods: HashMap::new(),
+        webauthn: WebAuthnConfig::default(),
+        sso: SsoConfig::default(),
+        tenant_export_sources: Vec::new(),
+        tenant_export_key: None,
+        tenant_export_ttl_hours: 24,
+        tenant_status_token: None,
+        capability_policy_token: None,
+        tenant_provision_targets: Vec::new(),
+        tenant_events_topic: String::new(),
+        offline_bundle_source: None,
+        offline_bundle_ttl_seconds: 86_400,
+        invite_ttl_hours: 72,
+        invite_accept_url: None,
     }
 }
 
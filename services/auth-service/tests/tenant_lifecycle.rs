@@ -0,0 +1,165 @@
+use auth_service::config::AuthConfig;
+use auth_service::login_guard::{LockoutPolicy, LoginThrottle};
+use auth_service::metrics::AuthMetrics;
+use auth_service::notifications::KafkaProducer;
+use auth_service::tenant_lifecycle_handlers::{
+    archive_tenant, get_tenant_export, list_tenant_statuses, request_tenant_export,
+};
+use auth_service::tokens::{TokenConfig, TokenSigner, TokenSubject};
+use auth_service::AppState;
+use axum::{Router, routing::{get, post}, http::{Request, StatusCode}, body::{Body, to_bytes}};
+use common_auth::{JwtConfig, JwtVerifier};
+use common_crypto::{ColumnKey, MasterKey};
+use jsonwebtoken::DecodingKey;
+use reqwest::Client;
+use rsa::{RsaPrivateKey, pkcs1::EncodeRsaPublicKey, pkcs8::EncodePrivateKey};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+mod support;
+use support::{seed_test_user, default_auth_config, RecordingKafkaProducer, TestDatabase};
+
+const ROOT_TENANT_ID: Uuid = Uuid::from_u128(1);
+
+struct Harness {
+    app: Router,
+    signer: Arc<TokenSigner>,
+}
+
+impl Harness {
+    async fn new(pool: &PgPool, config: AuthConfig) -> anyhow::Result<Self> {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048)?;
+        let private_pem = private_key.to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)?.to_string();
+        let public_pem = private_key.to_public_key().to_pkcs1_pem(rsa::pkcs1::LineEnding::LF)?.to_string();
+        let token_config = TokenConfig { issuer: "test-issuer".into(), audience: "test-audience".into(), access_ttl_seconds: 300, refresh_ttl_seconds: 900 };
+        let signer = Arc::new(TokenSigner::new(pool.clone(), token_config, Some(&private_pem)).await?);
+        let jwks = signer.jwks().await?;
+        let mut verifier_builder = JwtVerifier::builder(JwtConfig::new("test-issuer", "test-audience"));
+        if jwks.is_empty() { verifier_builder = verifier_builder.with_rsa_pem("local-dev", public_pem.as_bytes())?; } else { for key in &jwks { verifier_builder = verifier_builder.with_decoding_key(key.kid.clone(), DecodingKey::from_rsa_components(&key.n, &key.e).expect("invalid jwk")); } }
+        let verifier = verifier_builder.build().await?;
+        let kafka_producer: Arc<dyn KafkaProducer> = Arc::new(RecordingKafkaProducer::default());
+        let state = AppState { db: pool.clone(), jwt_verifier: Arc::new(verifier), token_signer: signer.clone(), config: Arc::new(config), kafka_producer, http_client: Client::new(), idp_client: Client::new(), metrics: Arc::new(AuthMetrics::new()?), login_throttle: Arc::new(LoginThrottle::from_policy(&LockoutPolicy::default())) };
+        let app = Router::new()
+            .route("/tenant-status", get(list_tenant_statuses))
+            .route("/tenants/:tenant_id/archive", post(archive_tenant))
+            .route("/tenants/:tenant_id/exports", post(request_tenant_export))
+            .route("/tenants/:tenant_id/exports/:export_id", get(get_tenant_export))
+            .with_state(state);
+        Ok(Self { app, signer })
+    }
+
+    fn token(&self, tenant_id: Uuid, role: &str) -> anyhow::Result<String> {
+        self.signer.issue_service_token(&TokenSubject { user_id: Uuid::new_v4(), tenant_id, roles: vec![role.into()], residency: None }, 300)
+    }
+
+    async fn call(&self, method: &str, uri: &str, headers: &[(&str, &str)], body: Option<Value>) -> anyhow::Result<(StatusCode, Value)> {
+        let mut req = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let resp = self.app.clone().oneshot(req.body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))?).await?;
+        let status = resp.status();
+        let bytes = to_bytes(resp.into_body(), usize::MAX).await?;
+        Ok((status, serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into()))))
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn archiving_revokes_keys_and_statuses_need_a_credential() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let tenant = seed_test_user(&pool, "admin").await?;
+    sqlx::query("INSERT INTO integration_keys (id, tenant_id, label, api_key_hash, key_suffix) VALUES ($1, $2, 'till', $3, 'abcd')")
+        .bind(Uuid::new_v4())
+        .bind(tenant.tenant_id)
+        .bind(Uuid::new_v4().simple().to_string())
+        .execute(&pool)
+        .await?;
+    let mut config = default_auth_config();
+    config.tenant_status_token = Some("status-secret".into());
+    let harness = Harness::new(&pool, config).await?;
+    let super_admin = format!("Bearer {}", harness.token(ROOT_TENANT_ID, "super_admin")?);
+    let admin = format!("Bearer {}", harness.token(tenant.tenant_id, "admin")?);
+
+    let (status, _) = harness.call("GET", "/tenant-status", &[("authorization", &admin)], None).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN, "tenant admins cannot list other tenants");
+
+    let (status, body) = harness.call("POST", &format!("/tenants/{}/archive", tenant.tenant_id), &[("authorization", &super_admin)], Some(json!({"reason": "closed"}))).await?;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let live_keys: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM integration_keys WHERE tenant_id = $1 AND revoked_at IS NULL")
+        .bind(tenant.tenant_id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(live_keys, 0, "archiving revokes every key with the status change");
+
+    let (status, _) = harness.call("GET", "/tenant-status", &[], None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = harness.call("GET", "/tenant-status", &[("X-Internal-Token", "wrong")], None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    for headers in [[("X-Internal-Token", "status-secret")], [("authorization", super_admin.as_str())]] {
+        let (status, body) = harness.call("GET", "/tenant-status", &headers, None).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["tenants"].as_array().unwrap().contains(&json!({"tenant_id": tenant.tenant_id, "status": "archived"})), "{body}");
+    }
+
+    db.teardown().await?;
+    Ok(())
+}
+
+/// Requests an export and waits for the background job to store it.
+async fn finished_export(harness: &Harness, pool: &PgPool, tenant_id: Uuid, auth: &str) -> anyhow::Result<Uuid> {
+    let (status, body) = harness.call("POST", &format!("/tenants/{tenant_id}/exports"), &[("authorization", auth)], None).await?;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    let export_id: Uuid = body["id"].as_str().unwrap().parse()?;
+    for _ in 0..100 {
+        let status: String = sqlx::query_scalar("SELECT status FROM tenant_exports WHERE id = $1").bind(export_id).fetch_one(pool).await?;
+        if status == "completed" {
+            return Ok(export_id);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    anyhow::bail!("export {export_id} did not complete")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres)")]
+async fn export_bundles_are_sealed_and_handed_out_once() -> anyhow::Result<()> {
+    let Some(db) = TestDatabase::setup().await? else { return Ok(()); };
+    let pool = db.pool_clone();
+    let tenant = seed_test_user(&pool, "admin").await?;
+    let keyless = Harness::new(&pool, default_auth_config()).await?;
+    let admin = format!("Bearer {}", keyless.token(tenant.tenant_id, "admin")?);
+    let (status, _) = keyless.call("POST", &format!("/tenants/{}/exports", tenant.tenant_id), &[("authorization", &admin)], None).await?;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "exports need a sealing key");
+
+    let mut config = default_auth_config();
+    config.tenant_export_key = Some(ColumnKey::new(MasterKey::from_bytes([9u8; 32])?));
+    let harness = Harness::new(&pool, config).await?;
+    let admin = format!("Bearer {}", harness.token(tenant.tenant_id, "admin")?);
+    let export_id = finished_export(&harness, &pool, tenant.tenant_id, &admin).await?;
+    let uri = format!("/tenants/{}/exports/{export_id}", tenant.tenant_id);
+
+    let sealed: Vec<u8> = sqlx::query_scalar("SELECT bundle_encrypted FROM tenant_exports WHERE id = $1").bind(export_id).fetch_one(&pool).await?;
+    assert!(!String::from_utf8_lossy(&sealed).contains(&tenant.email), "the bundle is not stored in plaintext");
+
+    let (status, body) = harness.call("GET", &uri, &[("authorization", &admin)], None).await?;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["bundle"]["services"]["auth-service"]["users"][0]["email"], tenant.email);
+    let (status, body) = harness.call("GET", &uri, &[("authorization", &admin)], None).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("bundle").is_none() && body["downloaded_at"].is_string(), "a bundle is handed out once: {body}");
+
+    let expired = finished_export(&harness, &pool, tenant.tenant_id, &admin).await?;
+    sqlx::query("UPDATE tenant_exports SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1").bind(expired).execute(&pool).await?;
+    let (_, body) = harness.call("GET", &format!("/tenants/{}/exports/{expired}", tenant.tenant_id), &[("authorization", &admin)], None).await?;
+    assert!(body.get("bundle").is_none(), "expired bundles are not served: {body}");
+
+    db.teardown().await?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 mod support;
 
 use anyhow::{anyhow, Result};
-use auth_service::tokens::{TokenConfig, TokenSigner, TokenSubject};
+use auth_service::tokens::{SessionDevice, TokenConfig, TokenSigner, TokenSubject};
 use rand::rngs::OsRng;
 use rsa::pkcs8::EncodePrivateKey;
 use rsa::RsaPrivateKey;
@@ -137,6 +137,7 @@ async fn consume_refresh_token_revokes_on_second_use() -> Result<()> {
             user_id,
             tenant_id,
             roles: vec!["admin".to_string()],
+            residency: None,
         })
         .await?;
 
@@ -162,3 +163,59 @@ async fn consume_refresh_token_revokes_on_second_use() -> Result<()> {
     db.teardown().await?;
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres: embedded or external)")]
+async fn revoked_session_rejects_rotated_refresh_token() -> Result<()> {
+    let Some(db) = TestDatabase::setup().await? else {
+        return Ok(());
+    };
+    let pool = db.pool_clone();
+
+    let private_pem = generate_private_pem()?;
+    let signer = TokenSigner::new(pool.clone(), token_config(), Some(&private_pem)).await?;
+
+    let (tenant_id, user_id) = seed_user(&pool).await?;
+    let subject = || TokenSubject {
+        user_id,
+        tenant_id,
+        roles: vec!["admin".to_string()],
+        residency: None,
+    };
+
+    let issued = signer.issue_tokens(subject()).await?;
+    let account = signer
+        .consume_refresh_token(&issued.refresh_token)
+        .await?
+        .ok_or_else(|| anyhow!("expected refresh token to be valid"))?;
+    assert_eq!(account.session_id, Some(issued.session_id));
+
+    let rotated = signer
+        .issue_session_tokens(subject(), account.session_id, &SessionDevice::default())
+        .await?;
+    assert_eq!(rotated.session_id, issued.session_id);
+
+    let revoked = signer
+        .revoke_sessions(user_id, None, None, Some(user_id), "test")
+        .await?;
+    assert_eq!(revoked, vec![issued.session_id]);
+
+    let after = signer.consume_refresh_token(&rotated.refresh_token).await?;
+    assert!(after.is_none());
+    assert!(signer
+        .issue_session_tokens(subject(), Some(issued.session_id), &SessionDevice::default())
+        .await
+        .is_err());
+
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .execute(&pool)
+        .await?;
+
+    db.teardown().await?;
+    Ok(())
+}
@@ -2,8 +2,7 @@ use crate::{AuditActor, AuditEvent, AuditResult, AUDIT_EVENT_VERSION, AuditSever
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use crate::AuditError;
 use chrono::Utc;
 use uuid::Uuid;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
@@ -32,9 +31,9 @@ impl AuditSink for KafkaAuditSink {
     async fn emit(&self, event: AuditEvent) -> AuditResult<()> {
         let serialized = serde_json::to_vec(&event).map_err(|e| AuditError::Serialization(e.to_string()))?;
         let key = event.tenant_id.to_string();
-        let record = FutureRecord::to(&self.config.topic).key(&key).payload(&serialized);
-        if let Err((e,_)) = self.inner.send(record, Duration::from_secs(5)).await { return Err(AuditError::Kafka(e.to_string())); }
-        Ok(())
+        common_kafka::publish(&self.inner, &self.config.topic, &key, &serialized)
+            .await
+            .map_err(|e| AuditError::Kafka(e.to_string()))
     }
 }
 
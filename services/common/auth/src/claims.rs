@@ -14,6 +14,8 @@ pub struct Claims {
     pub issued_at: Option<DateTime<Utc>>,
     pub issuer: String,
     pub audience: Vec<String>,
+    /// Region the tenant's data must be stored in (`residency` claim); `None` means unrestricted.
+    pub residency: Option<String>,
     pub raw: serde_json::Value,
 }
 
@@ -37,6 +39,8 @@ struct ClaimsRepr {
     iss: String,
     #[serde(default)]
     aud: Option<AudienceRepr>,
+    #[serde(default)]
+    residency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,6 +87,10 @@ impl TryFrom<ClaimsRepr> for Claims {
             issued_at,
             issuer: value.iss,
             audience,
+            residency: value
+                .residency
+                .map(|region| region.trim().to_ascii_lowercase())
+                .filter(|region| !region.is_empty()),
             raw: serde_json::Value::Null,
         })
     }
@@ -155,6 +163,21 @@ mod tests {
         assert!(claims.issued_at.is_none());
     }
 
+    #[test]
+    fn residency_is_optional_and_normalized() {
+        let base = json!({
+            "sub": Uuid::new_v4().to_string(),
+            "tid": Uuid::new_v4().to_string(),
+            "exp": 1_700_000_000i64,
+            "iss": "issuer",
+        });
+        assert!(Claims::try_from(base.clone()).unwrap().residency.is_none());
+
+        let mut with_region = base;
+        with_region["residency"] = json!(" EU ");
+        assert_eq!(Claims::try_from(with_region).unwrap().residency.as_deref(), Some("eu"));
+    }
+
     #[test]
     fn rejects_invalid_subject() {
         let tenant = Uuid::new_v4();
@@ -38,6 +38,12 @@ pub enum AuthError {
     JwksUnsupportedKey { kid: String, kty: String },
     #[error("JWKS key '{kid}' uses unsupported alg '{alg}'")]
     JwksUnsupportedAlg { kid: String, alg: String },
+    #[error("tenant '{0}' is suspended")]
+    TenantSuspended(uuid::Uuid),
+    #[error("tenant '{0}' is archived")]
+    TenantArchived(uuid::Uuid),
+    #[error("failed to fetch tenant statuses: {0}")]
+    TenantStatusFetch(String),
 }
 
 impl From<jsonwebtoken::errors::Error> for AuthError {
@@ -75,6 +81,11 @@ impl IntoResponse for AuthError {
             | AuthError::JwksUnsupportedAlg { .. } => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "AUTH_JWKS")
             }
+            AuthError::TenantSuspended(_) => (StatusCode::FORBIDDEN, "TENANT_SUSPENDED"),
+            AuthError::TenantArchived(_) => (StatusCode::FORBIDDEN, "TENANT_ARCHIVED"),
+            AuthError::TenantStatusFetch(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "AUTH_TENANT_STATUS")
+            }
         };
 
         let body = ErrorBody {
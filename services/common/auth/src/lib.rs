@@ -4,7 +4,9 @@ pub mod error;
 pub mod extractors;
 pub mod guards;
 pub mod jwks;
+pub mod offline_bundle;
 pub mod roles;
+pub mod tenant_status;
 pub mod verifier;
 
 pub use claims::Claims;
@@ -13,5 +15,7 @@ pub use error::{AuthError, AuthResult};
 pub use extractors::AuthContext;
 pub use guards::{ensure_role, tenant_id_from_request, GuardError};
 pub use jwks::JwksFetcher;
+pub use offline_bundle::{verify_offline_bundle, OfflineBundle, OFFLINE_BUNDLE_AUDIENCE};
 pub use roles::{ROLE_ADMIN, ROLE_CASHIER, ROLE_HIERARCHY, ROLE_MANAGER, ROLE_SUPER_ADMIN};
+pub use tenant_status::{spawn_tenant_status_refresh, TenantStatus, TenantStatusStore};
 pub use verifier::{InMemoryKeyStore, JwtVerifier, JwtVerifierBuilder};
@@ -0,0 +1,127 @@
+//! Signed offline price/tax bundles. auth-service signs a tenant's selling snapshot (catalog,
+//! tax and rounding rules) with its token keys under a dedicated audience; terminals and services
+//! verify it with the same JWKS they use for access tokens.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::{AuthError, AuthResult};
+use crate::verifier::JwtVerifier;
+
+/// JWT audience of offline bundles so they can never be replayed as access tokens.
+pub const OFFLINE_BUNDLE_AUDIENCE: &str = "novapos-offline-bundle";
+
+/// Claims of a verified bundle. `version` is the bundle's ETag: a digest of `bundle`, so it only
+/// changes when the content does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineBundle {
+    pub tenant_id: Uuid,
+    pub version: String,
+    pub bundle: Value,
+    #[serde(rename = "iat")]
+    pub issued_at: i64,
+    #[serde(rename = "exp")]
+    pub expires_at: i64,
+}
+
+/// Verifies signature, issuer, audience and expiry of a bundle token and that it belongs to
+/// `tenant_id`.
+pub fn verify_offline_bundle(
+    verifier: &JwtVerifier,
+    token: &str,
+    tenant_id: Uuid,
+) -> AuthResult<OfflineBundle> {
+    let claims = verifier.verify_document(token, OFFLINE_BUNDLE_AUDIENCE)?;
+    let bundle: OfflineBundle =
+        serde_json::from_value(claims).map_err(|err| AuthError::InvalidJson(err.to_string()))?;
+    if bundle.tenant_id != tenant_id {
+        return Err(AuthError::InvalidClaim(
+            "tenant_id",
+            bundle.tenant_id.to_string(),
+        ));
+    }
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::JwtConfig;
+    use crate::verifier::InMemoryKeyStore;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+    use rsa::pkcs1::{EncodeRsaPrivateKey, EncodeRsaPublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
+    use rsa::RsaPrivateKey;
+    use serde_json::json;
+
+    const KID: &str = "bundle-key";
+
+    fn signer_and_verifier() -> (EncodingKey, JwtVerifier) {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).expect("key generation");
+        let private_pem = private_key.to_pkcs1_pem(LineEnding::LF).expect("private pem");
+        let public_pem = private_key
+            .to_public_key()
+            .to_pkcs1_pem(LineEnding::LF)
+            .expect("public pem");
+        let store = InMemoryKeyStore::new();
+        store.insert_key(
+            KID,
+            DecodingKey::from_rsa_pem(public_pem.as_bytes()).expect("decoding key"),
+        );
+        (
+            EncodingKey::from_rsa_pem(private_pem.as_bytes()).expect("encoding key"),
+            JwtVerifier::with_store(JwtConfig::new("issuer", "api"), store),
+        )
+    }
+
+    fn sign(encoding: &EncodingKey, audience: &str, tenant_id: Uuid, ttl_seconds: i64) -> String {
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "tenant_id": tenant_id,
+            "version": "abc123",
+            "bundle": { "products": [], "tax": { "rate_bps": 825 } },
+            "iss": "issuer",
+            "aud": audience,
+            "iat": now,
+            "exp": now + ttl_seconds,
+        });
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KID.to_string());
+        encode(&header, &claims, encoding).expect("sign bundle")
+    }
+
+    #[test]
+    fn verifies_bundle_for_its_tenant() {
+        let (encoding, verifier) = signer_and_verifier();
+        let tenant_id = Uuid::new_v4();
+        let token = sign(&encoding, OFFLINE_BUNDLE_AUDIENCE, tenant_id, 600);
+
+        let bundle = verify_offline_bundle(&verifier, &token, tenant_id).expect("bundle verifies");
+        assert_eq!(bundle.version, "abc123");
+        assert_eq!(bundle.bundle["tax"]["rate_bps"], 825);
+        assert!(bundle.expires_at > bundle.issued_at);
+
+        let other = verify_offline_bundle(&verifier, &token, Uuid::new_v4()).unwrap_err();
+        assert!(matches!(other, AuthError::InvalidClaim("tenant_id", _)));
+    }
+
+    #[test]
+    fn rejects_expired_tampered_or_foreign_tokens() {
+        let (encoding, verifier) = signer_and_verifier();
+        let tenant_id = Uuid::new_v4();
+
+        let expired = sign(&encoding, OFFLINE_BUNDLE_AUDIENCE, tenant_id, -3600);
+        assert!(verify_offline_bundle(&verifier, &expired, tenant_id).is_err());
+
+        let access = sign(&encoding, "api", tenant_id, 600);
+        assert!(verify_offline_bundle(&verifier, &access, tenant_id).is_err());
+
+        let token = sign(&encoding, OFFLINE_BUNDLE_AUDIENCE, tenant_id, 600);
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged = sign(&encoding, OFFLINE_BUNDLE_AUDIENCE, Uuid::new_v4(), 600);
+        parts[1] = forged.split('.').nth(1).unwrap();
+        assert!(verify_offline_bundle(&verifier, &parts.join("."), tenant_id).is_err());
+    }
+}
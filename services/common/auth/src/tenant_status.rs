@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::error::{AuthError, AuthResult};
+use crate::verifier::JwtVerifier;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantStatus {
+    Active,
+    Suspended,
+    Archived,
+}
+
+impl TenantStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TenantStatus::Active => "active",
+            TenantStatus::Suspended => "suspended",
+            TenantStatus::Archived => "archived",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "active" => Some(TenantStatus::Active),
+            "suspended" => Some(TenantStatus::Suspended),
+            "archived" => Some(TenantStatus::Archived),
+            _ => None,
+        }
+    }
+}
+
+/// Statuses of tenants that are not active; tenants missing from the store are active.
+#[derive(Clone, Default)]
+pub struct TenantStatusStore {
+    inner: Arc<RwLock<HashMap<Uuid, TenantStatus>>>,
+}
+
+impl TenantStatusStore {
+    pub fn status(&self, tenant_id: Uuid) -> TenantStatus {
+        let guard = self.inner.read().expect("rwlock poisoned");
+        guard
+            .get(&tenant_id)
+            .copied()
+            .unwrap_or(TenantStatus::Active)
+    }
+
+    pub fn set(&self, tenant_id: Uuid, status: TenantStatus) {
+        let mut guard = self.inner.write().expect("rwlock poisoned");
+        if status == TenantStatus::Active {
+            guard.remove(&tenant_id);
+        } else {
+            guard.insert(tenant_id, status);
+        }
+    }
+
+    pub fn replace_all<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = (Uuid, TenantStatus)>,
+    {
+        let mut guard = self.inner.write().expect("rwlock poisoned");
+        guard.clear();
+        guard.extend(
+            entries
+                .into_iter()
+                .filter(|(_, status)| *status != TenantStatus::Active),
+        );
+    }
+
+    pub fn ensure_active(&self, tenant_id: Uuid) -> AuthResult<()> {
+        match self.status(tenant_id) {
+            TenantStatus::Active => Ok(()),
+            TenantStatus::Suspended => Err(AuthError::TenantSuspended(tenant_id)),
+            TenantStatus::Archived => Err(AuthError::TenantArchived(tenant_id)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TenantStatusResponse {
+    tenants: Vec<TenantStatusEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TenantStatusEntry {
+    tenant_id: Uuid,
+    status: TenantStatus,
+}
+
+/// Replaces the verifier's tenant statuses with auth-service's `/tenant-status` listing, which
+/// requires the shared `token` (sent as `X-Internal-Token`).
+pub async fn refresh_tenant_statuses(
+    verifier: &JwtVerifier,
+    client: &Client,
+    url: &str,
+    token: Option<&str>,
+) -> AuthResult<usize> {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.header("X-Internal-Token", token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|err| AuthError::TenantStatusFetch(err.to_string()))?;
+    if !response.status().is_success() {
+        return Err(AuthError::TenantStatusFetch(format!(
+            "HTTP {} from {}",
+            response.status(),
+            url
+        )));
+    }
+    let body: TenantStatusResponse = response
+        .json()
+        .await
+        .map_err(|err| AuthError::TenantStatusFetch(err.to_string()))?;
+    let count = body.tenants.len();
+    verifier.tenant_statuses().replace_all(
+        body.tenants
+            .into_iter()
+            .map(|entry| (entry.tenant_id, entry.status)),
+    );
+    Ok(count)
+}
+
+/// Periodically syncs tenant statuses from `TENANT_STATUS_URL`, authenticating with
+/// `TENANT_STATUS_TOKEN` (auth-service's `AUTH_TENANT_STATUS_TOKEN`). Without the URL every
+/// tenant is treated as active.
+pub fn spawn_tenant_status_refresh(verifier: Arc<JwtVerifier>) {
+    let Ok(url) = env::var("TENANT_STATUS_URL") else {
+        return;
+    };
+    let token = env::var("TENANT_STATUS_TOKEN").ok().filter(|value| !value.trim().is_empty());
+    if token.is_none() {
+        warn!(status_url = %url, "TENANT_STATUS_TOKEN not set; tenant status refresh will be rejected");
+    }
+    let refresh_secs = env::var("TENANT_STATUS_REFRESH_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(30)
+        .max(5);
+    let client = Client::new();
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(refresh_secs));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match refresh_tenant_statuses(&verifier, &client, &url, token.as_deref()).await {
+                Ok(count) => debug!(count, status_url = %url, "Refreshed tenant statuses"),
+                Err(err) => warn!(error = %err, status_url = %url, "Tenant status refresh failed"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_defaults_to_active_and_reports_blocked_tenants() {
+        let store = TenantStatusStore::default();
+        let suspended = Uuid::new_v4();
+        let archived = Uuid::new_v4();
+        assert!(store.ensure_active(suspended).is_ok());
+
+        store.replace_all(vec![
+            (suspended, TenantStatus::Suspended),
+            (archived, TenantStatus::Archived),
+        ]);
+        assert!(matches!(
+            store.ensure_active(suspended),
+            Err(AuthError::TenantSuspended(id)) if id == suspended
+        ));
+        assert!(matches!(
+            store.ensure_active(archived),
+            Err(AuthError::TenantArchived(_))
+        ));
+
+        store.set(suspended, TenantStatus::Active);
+        assert!(store.ensure_active(suspended).is_ok());
+    }
+}
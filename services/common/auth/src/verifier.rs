@@ -9,6 +9,7 @@ use crate::claims::Claims;
 use crate::config::JwtConfig;
 use crate::error::{AuthError, AuthResult};
 use crate::jwks::JwksFetcher;
+use crate::tenant_status::TenantStatusStore;
 
 /// Thread-safe store for decoding keys loaded from JWKS/PEM sources.
 #[derive(Clone, Default)]
@@ -61,6 +62,7 @@ pub struct JwtVerifier {
     config: JwtConfig,
     store: InMemoryKeyStore,
     jwks: Option<JwksFetcher>,
+    tenant_statuses: TenantStatusStore,
 }
 
 impl JwtVerifier {
@@ -69,6 +71,7 @@ impl JwtVerifier {
             config,
             store: InMemoryKeyStore::new(),
             jwks: None,
+            tenant_statuses: TenantStatusStore::default(),
         }
     }
 
@@ -77,6 +80,7 @@ impl JwtVerifier {
             config,
             store,
             jwks: None,
+            tenant_statuses: TenantStatusStore::default(),
         }
     }
 
@@ -96,6 +100,11 @@ impl JwtVerifier {
         self.jwks.as_ref()
     }
 
+    /// Suspended/archived tenants whose tokens `verify` rejects.
+    pub fn tenant_statuses(&self) -> &TenantStatusStore {
+        &self.tenant_statuses
+    }
+
     pub fn verify(&self, token: &str) -> AuthResult<Claims> {
         let header =
             decode_header(token).map_err(|err| AuthError::InvalidHeader(err.to_string()))?;
@@ -112,10 +121,30 @@ impl JwtVerifier {
 
         let token_data = decode::<Value>(token, &key, &validation)?;
         let claims = Claims::try_from(token_data.claims)?;
+        self.tenant_statuses.ensure_active(claims.tenant_id)?;
         debug!(kid, "verified JWT successfully");
         Ok(claims)
     }
 
+    /// Verifies a non-access-token document signed with the same keys (same issuer) but a
+    /// dedicated audience, returning the raw claims.
+    pub fn verify_document(&self, token: &str, audience: &str) -> AuthResult<Value> {
+        let header =
+            decode_header(token).map_err(|err| AuthError::InvalidHeader(err.to_string()))?;
+        let kid = header.kid.ok_or(AuthError::MissingKeyId)?;
+        let key = self
+            .store
+            .get(&kid)
+            .ok_or_else(|| AuthError::UnknownKeyId(kid.clone()))?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(std::slice::from_ref(&self.config.issuer));
+        validation.set_audience(&[audience]);
+        validation.leeway = self.config.leeway_seconds.into();
+
+        Ok(decode::<Value>(token, &key, &validation)?.claims)
+    }
+
     pub async fn refresh_jwks(&self) -> AuthResult<usize> {
         let fetcher = match &self.jwks {
             Some(fetcher) => fetcher,
@@ -176,6 +205,7 @@ impl JwtVerifierBuilder {
             config: self.config,
             store: self.store,
             jwks: self.jwks,
+            tenant_statuses: TenantStatusStore::default(),
         };
 
         if verifier.jwks.is_some() {
@@ -327,6 +357,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn verifier_rejects_suspended_tenant() {
+        let material = generate_key_material();
+        let kid = "test-key";
+        let store = InMemoryKeyStore::new();
+        store.insert_key(kid, material.decoding.clone());
+        let verifier = JwtVerifier::with_store(JwtConfig::new("issuer", "aud"), store);
+
+        let (token, _, tenant, _) = issue_token(&material.encoding, kid, "issuer", "aud");
+        verifier
+            .tenant_statuses()
+            .set(tenant, crate::tenant_status::TenantStatus::Suspended);
+        let err = verifier.verify(&token).expect_err("suspended tenant rejected");
+        assert!(matches!(err, AuthError::TenantSuspended(id) if id == tenant));
+    }
+
+    #[test]
+    fn verify_document_requires_document_audience() {
+        let material = generate_key_material();
+        let kid = "doc-key";
+        let store = InMemoryKeyStore::new();
+        store.insert_key(kid, material.decoding.clone());
+        let verifier = JwtVerifier::with_store(JwtConfig::new("issuer", "api"), store);
+
+        let (document, _, tenant, _) = issue_token(&material.encoding, kid, "issuer", "policy");
+        let claims = verifier
+            .verify_document(&document, "policy")
+            .expect("document verifies");
+        assert_eq!(claims["tid"], tenant.to_string());
+        assert!(verifier.verify(&document).is_err(), "documents are not access tokens");
+
+        let (access, _, _, _) = issue_token(&material.encoding, kid, "issuer", "api");
+        assert!(verifier.verify_document(&access, "policy").is_err());
+    }
+
     #[tokio::test]
     async fn refresh_jwks_updates_store() {
         let material = generate_key_material();
@@ -357,6 +422,7 @@ mod tests {
             config,
             store,
             jwks: Some(JwksFetcher::new(format!("{}/jwks", server.base_url()))),
+            tenant_statuses: TenantStatusStore::default(),
         };
 
         assert!(!verifier.store().contains(kid));
@@ -0,0 +1,340 @@
+//! Typed service settings read from the environment.
+//!
+//! [`EnvReader`] parses each variable into its target type. Instead of stopping at the first
+//! problem or quietly using the default for a value it cannot parse, it records the problem, and
+//! [`EnvReader::finish`] reports every missing or invalid variable in one [`ConfigError`].
+//! Secrets are held in [`Redacted`] (or marked with [`redact`]), so [`config_route`] can serve the
+//! loaded config from `/internal/config`.
+
+use crate::{load_secret, SecretError};
+use axum::routing::{get, MethodRouter};
+use axum::Json;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub problem: String,
+}
+
+/// Every problem found while loading a service's configuration.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration ({} problem(s)):", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  {}: {}", issue.key, issue.problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A secret setting. Debug and serialized output show `[redacted]`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Redacted<T>(T);
+
+impl<T> Redacted<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl<T> Serialize for Redacted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("[redacted]")
+    }
+}
+
+/// `serialize_with` helper for secret fields kept as plain strings.
+pub fn redact<T, S: Serializer>(_value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str("[redacted]")
+}
+
+/// `GET /internal/config`: the config as loaded at startup, with secrets redacted.
+pub fn config_route<S>(config: &impl Serialize) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let snapshot = serde_json::to_value(config)
+        .unwrap_or_else(|err| serde_json::json!({ "error": format!("config not serializable: {err}") }));
+    get(move || {
+        let snapshot = snapshot.clone();
+        async move { Json(snapshot) }
+    })
+}
+
+type Lookup = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Reads settings and collects every problem; see the module docs.
+///
+/// Blank values count as unset.
+pub struct EnvReader {
+    lookup: Lookup,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Default for EnvReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvReader {
+    /// Read from the process environment.
+    pub fn new() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Read from `lookup` instead of the process environment.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self { lookup: Box::new(lookup), issues: Vec::new() }
+    }
+
+    fn raw(&self, key: &str) -> Option<String> {
+        (self.lookup)(key).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    /// Record a problem with `key`, e.g. a value outside its allowed range.
+    pub fn invalid(&mut self, key: &str, problem: impl Into<String>) {
+        self.issues.push(ConfigIssue { key: key.to_string(), problem: problem.into() });
+    }
+
+    /// Record `problem` for `key` unless `ok`.
+    pub fn check(&mut self, key: &str, ok: bool, problem: &str) {
+        if !ok {
+            self.invalid(key, problem);
+        }
+    }
+
+    fn parse<T>(&mut self, key: &str, value: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match value.parse::<T>() {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                self.invalid(key, format!("invalid value `{value}`: {err}"));
+                None
+            }
+        }
+    }
+
+    /// A setting the service cannot run without.
+    pub fn required<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.raw(key) {
+            Some(value) => self.parse(key, &value),
+            None => {
+                self.invalid(key, "must be set");
+                None
+            }
+        }
+    }
+
+    /// An optional setting; an unparseable value is still reported.
+    pub fn optional<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.raw(key)?;
+        self.parse(key, &value)
+    }
+
+    /// A setting with a default for when it is unset (not when it is invalid).
+    pub fn or<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.optional(key).unwrap_or(default)
+    }
+
+    /// A boolean accepting `1`/`0`, `true`/`false`, `yes`/`no` and `on`/`off`.
+    pub fn flag(&mut self, key: &str, default: bool) -> bool {
+        let Some(value) = self.raw(key) else {
+            return default;
+        };
+        match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => {
+                self.invalid(key, format!("invalid value `{value}`: expected true or false"));
+                default
+            }
+        }
+    }
+
+    /// A secret resolved through [`load_secret`] (`KEY_FILE`, `KEY`, `KEY_VAULT`).
+    pub async fn secret(&mut self, key: &str) -> Option<Redacted<String>> {
+        match load_secret(key).await {
+            Ok(value) => value.filter(|v| !v.trim().is_empty()).map(Redacted::new),
+            Err(err) => {
+                self.secret_issue(key, err);
+                None
+            }
+        }
+    }
+
+    /// A secret the service cannot run without.
+    pub async fn required_secret(&mut self, key: &str) -> Option<Redacted<String>> {
+        let value = self.secret(key).await;
+        if value.is_none() && !self.issues.iter().any(|issue| issue.key == key) {
+            self.secret_issue(key, SecretError::Missing { key: key.to_string() });
+        }
+        value
+    }
+
+    fn secret_issue(&mut self, key: &str, err: SecretError) {
+        let problem = match err {
+            SecretError::Missing { .. } => format!("must be set (or {key}_FILE / {key}_VAULT)"),
+            other => other.to_string(),
+        };
+        self.invalid(key, problem);
+    }
+
+    /// Fail with every recorded problem, or build the config. `build` only runs when nothing was
+    /// reported, so it can unwrap the `Option`s returned for required settings with `?`.
+    pub fn finish<T>(self, build: impl FnOnce() -> Option<T>) -> Result<T, ConfigError> {
+        if !self.issues.is_empty() {
+            return Err(ConfigError { issues: self.issues });
+        }
+        Ok(build().expect("missing required settings are recorded as issues"))
+    }
+}
+
+/// Listener address from `HOST` (default `0.0.0.0`) and `PORT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpSettings {
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+impl HttpSettings {
+    pub fn read(env: &mut EnvReader, default_port: u16) -> Self {
+        Self { host: env.or("HOST", IpAddr::V4(Ipv4Addr::UNSPECIFIED)), port: env.or("PORT", default_port) }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+/// JWT verification settings shared by every service that checks bearer tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct JwtSettings {
+    pub issuer: String,
+    pub audience: String,
+    pub leeway_seconds: Option<u32>,
+    pub jwks_url: Option<String>,
+    /// Seconds between JWKS refreshes, at least 60.
+    pub jwks_refresh_seconds: u64,
+    /// Local development key; never set in production.
+    pub dev_public_key_pem: Option<Redacted<String>>,
+}
+
+impl JwtSettings {
+    pub const MIN_JWKS_REFRESH_SECONDS: u64 = 60;
+
+    /// Read `JWT_ISSUER`, `JWT_AUDIENCE`, `JWT_LEEWAY_SECONDS`, `JWT_JWKS_URL`,
+    /// `JWKS_REFRESH_SECONDS` (default 300) and the `JWT_DEV_PUBLIC_KEY_PEM` secret.
+    pub async fn read(env: &mut EnvReader) -> Option<Self> {
+        let issuer = env.required("JWT_ISSUER");
+        let audience = env.required("JWT_AUDIENCE");
+        let leeway_seconds = env.optional("JWT_LEEWAY_SECONDS");
+        let jwks_url = env.optional("JWT_JWKS_URL");
+        let jwks_refresh_seconds = env.or("JWKS_REFRESH_SECONDS", 300);
+        env.check(
+            "JWKS_REFRESH_SECONDS",
+            jwks_refresh_seconds >= Self::MIN_JWKS_REFRESH_SECONDS,
+            "must be at least 60",
+        );
+        let dev_public_key_pem = env.secret("JWT_DEV_PUBLIC_KEY_PEM").await;
+        Some(Self { issuer: issuer?, audience: audience?, leeway_seconds, jwks_url, jwks_refresh_seconds, dev_public_key_pem })
+    }
+
+    /// Just the JWT settings, for tests and tools that build a verifier without a service config.
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let settings = Self::read(&mut env).await;
+        env.finish(|| settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn reader(vars: &[(&str, &str)]) -> EnvReader {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        EnvReader::from_lookup(move |key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn every_missing_and_invalid_setting_is_reported() {
+        let mut env = reader(&[("PORT", "80a"), ("TTL_SECS", "-5"), ("DUAL_WRITE", "maybe"), ("NAME", "  ")]);
+        let port = env.or("PORT", 8080u16);
+        let ttl: u64 = env.or("TTL_SECS", 900);
+        let dual_write = env.flag("DUAL_WRITE", false);
+        let name: Option<String> = env.required("NAME");
+        let err = env.finish(|| Some((port, ttl, dual_write, name?))).unwrap_err();
+        let keys: Vec<&str> = err.issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["PORT", "TTL_SECS", "DUAL_WRITE", "NAME"]);
+        let message = err.to_string();
+        assert!(message.starts_with("invalid configuration (4 problem(s)):"), "{message}");
+        assert!(message.contains("\n  PORT: invalid value `80a`"), "{message}");
+        assert!(message.contains("\n  NAME: must be set"), "{message}");
+    }
+
+    #[test]
+    fn defaults_apply_only_when_unset() {
+        let mut env = reader(&[("MULTI_LOCATION", "Yes")]);
+        let port = env.or("PORT", 8080u16);
+        let multi_location = env.flag("MULTI_LOCATION", false);
+        assert_eq!(env.finish(|| Some((port, multi_location))).unwrap(), (8080, true));
+    }
+
+    #[tokio::test]
+    async fn jwt_settings_enforce_the_refresh_floor_and_redact_the_dev_key() {
+        let mut env = reader(&[("JWT_ISSUER", "https://auth"), ("JWT_AUDIENCE", "pos"), ("JWKS_REFRESH_SECONDS", "10")]);
+        let jwt = JwtSettings::read(&mut env).await;
+        let err = env.finish(|| jwt).unwrap_err();
+        assert_eq!(err.issues, [ConfigIssue { key: "JWKS_REFRESH_SECONDS".into(), problem: "must be at least 60".into() }]);
+
+        let settings = JwtSettings {
+            issuer: "https://auth".into(),
+            audience: "pos".into(),
+            leeway_seconds: None,
+            jwks_url: None,
+            jwks_refresh_seconds: 300,
+            dev_public_key_pem: Some(Redacted::new("-----BEGIN PUBLIC KEY-----".into())),
+        };
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["dev_public_key_pem"], "[redacted]");
+        assert_eq!(format!("{:?}", settings.dev_public_key_pem), "Some([redacted])");
+    }
+}
@@ -0,0 +1,238 @@
+//! Configuration and secret loading shared by every service.
+//!
+//! A secret named `KEY` is resolved in this order:
+//!
+//! 1. `KEY_FILE`: path to a file holding the value, e.g. a mounted Kubernetes secret. Surrounding
+//!    whitespace (the trailing newline most tools write) is trimmed.
+//! 2. `KEY`: the value itself.
+//! 3. `KEY_VAULT`: a HashiCorp Vault KV v2 reference `mount/path#field`, read with `VAULT_ADDR`
+//!    and `VAULT_TOKEN` (or `VAULT_TOKEN_FILE`) and optional `VAULT_NAMESPACE`. Only the async
+//!    [`load_secret`] and [`require_secret`] consult Vault; [`read_secret`] stops at step 2.
+//!
+//! [`KafkaSecurity`] applies the same rules to broker credentials. The [`env`] module builds typed,
+//! validated service configs on top of them.
+
+pub mod env;
+
+pub use env::{config_route, ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("{key} must be set (or {key}_FILE / {key}_VAULT)")]
+    Missing { key: String },
+    #[error("failed to read {var} from {path}: {source}")]
+    File {
+        var: String,
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Vault: {0}")]
+    Vault(String),
+}
+
+fn read_file(var: &str, path: &str) -> Result<String, SecretError> {
+    std::fs::read_to_string(Path::new(path))
+        .map(|contents| contents.trim().to_string())
+        .map_err(|source| SecretError::File { var: var.to_string(), path: path.to_string(), source })
+}
+
+/// Resolve `key` from `KEY_FILE` or `KEY`, without Vault. For synchronous startup code.
+pub fn read_secret(key: &str) -> Result<Option<String>, SecretError> {
+    let file_var = format!("{key}_FILE");
+    if let Ok(path) = std::env::var(&file_var) {
+        return read_file(&file_var, &path).map(Some);
+    }
+    Ok(std::env::var(key).ok())
+}
+
+/// Resolve `key` from `KEY_FILE`, `KEY`, then `KEY_VAULT`.
+pub async fn load_secret(key: &str) -> Result<Option<String>, SecretError> {
+    if let Some(value) = read_secret(key)? {
+        return Ok(Some(value));
+    }
+    match std::env::var(format!("{key}_VAULT")) {
+        Ok(reference) => VaultKv::from_env()?.read(&reference).await.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// [`load_secret`] for settings the service cannot start without.
+pub async fn require_secret(key: &str) -> Result<String, SecretError> {
+    load_secret(key).await?.ok_or_else(|| SecretError::Missing { key: key.to_string() })
+}
+
+/// Read-only client for Vault's KV v2 secrets engine.
+pub struct VaultKv {
+    client: reqwest::Client,
+    addr: String,
+    token: String,
+    namespace: Option<String>,
+}
+
+impl VaultKv {
+    /// Configure from `VAULT_ADDR`, `VAULT_TOKEN` or `VAULT_TOKEN_FILE` and `VAULT_NAMESPACE`.
+    pub fn from_env() -> Result<Self, SecretError> {
+        let addr = std::env::var("VAULT_ADDR")
+            .map_err(|_| SecretError::Vault("VAULT_ADDR must be set to read *_VAULT secrets".into()))?;
+        let token = match std::env::var("VAULT_TOKEN_FILE") {
+            Ok(path) => read_file("VAULT_TOKEN_FILE", &path)?,
+            Err(_) => std::env::var("VAULT_TOKEN")
+                .map_err(|_| SecretError::Vault("VAULT_TOKEN or VAULT_TOKEN_FILE must be set".into()))?,
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            addr: addr.trim_end_matches('/').to_string(),
+            token,
+            namespace: std::env::var("VAULT_NAMESPACE").ok().filter(|ns| !ns.is_empty()),
+        })
+    }
+
+    /// Read one field of a KV v2 secret, e.g. `secret/pos/inventory#database_url`.
+    pub async fn read(&self, reference: &str) -> Result<String, SecretError> {
+        let (mount, path, field) = parse_vault_ref(reference)?;
+        let mut request = self
+            .client
+            .get(format!("{}/v1/{mount}/data/{path}", self.addr))
+            .header("X-Vault-Token", &self.token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| SecretError::Vault(format!("request for {mount}/{path} failed: {err}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(SecretError::Vault(format!("reading {mount}/{path} returned {status}")));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| SecretError::Vault(format!("invalid response for {mount}/{path}: {err}")))?;
+        match body.pointer("/data/data").and_then(|data| data.get(field)) {
+            Some(serde_json::Value::String(value)) => Ok(value.clone()),
+            Some(other) => Ok(other.to_string()),
+            None => Err(SecretError::Vault(format!("{mount}/{path} has no field `{field}`"))),
+        }
+    }
+}
+
+/// Split `mount/path#field`; the mount is the first path segment.
+fn parse_vault_ref(reference: &str) -> Result<(&str, &str, &str), SecretError> {
+    let invalid = || SecretError::Vault(format!("`{reference}` is not of the form mount/path#field"));
+    let (location, field) = reference.trim().split_once('#').ok_or_else(invalid)?;
+    let (mount, path) = location.trim_matches('/').split_once('/').ok_or_else(invalid)?;
+    if mount.is_empty() || path.is_empty() || field.is_empty() {
+        return Err(invalid());
+    }
+    Ok((mount, path, field))
+}
+
+/// Broker security settings, added to every Kafka client a service builds.
+///
+/// Read from `KAFKA_SECURITY_PROTOCOL` (e.g. `SASL_SSL`), `KAFKA_SASL_MECHANISM` (default
+/// `PLAIN` once a username is set), `KAFKA_SASL_USERNAME` and `KAFKA_SASL_PASSWORD` (both
+/// secrets, so `_FILE` and `_VAULT` work) and `KAFKA_SSL_CA_LOCATION`. With none of them set the
+/// settings are empty and clients connect in plaintext as before.
+#[derive(Debug, Clone, Default)]
+pub struct KafkaSecurity {
+    settings: Vec<(&'static str, String)>,
+}
+
+impl KafkaSecurity {
+    pub async fn from_env() -> Result<Self, SecretError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Ok(Self::new(
+            var("KAFKA_SECURITY_PROTOCOL"),
+            var("KAFKA_SASL_MECHANISM"),
+            load_secret("KAFKA_SASL_USERNAME").await?,
+            load_secret("KAFKA_SASL_PASSWORD").await?,
+            var("KAFKA_SSL_CA_LOCATION"),
+        ))
+    }
+
+    fn new(
+        protocol: Option<String>,
+        mechanism: Option<String>,
+        username: Option<String>,
+        password: Option<String>,
+        ca_location: Option<String>,
+    ) -> Self {
+        let mut settings = Vec::new();
+        if let Some(protocol) = protocol {
+            settings.push(("security.protocol", protocol));
+        }
+        if let Some(username) = username {
+            settings.push(("sasl.mechanisms", mechanism.unwrap_or_else(|| "PLAIN".into())));
+            settings.push(("sasl.username", username));
+            settings.push(("sasl.password", password.unwrap_or_default()));
+        }
+        if let Some(ca_location) = ca_location {
+            settings.push(("ssl.ca.location", ca_location));
+        }
+        Self { settings }
+    }
+
+    /// `(librdkafka property, value)` pairs.
+    pub fn settings(&self) -> &[(&'static str, String)] {
+        &self.settings
+    }
+
+    #[cfg(feature = "kafka")]
+    pub fn apply<'a>(&self, config: &'a mut rdkafka::ClientConfig) -> &'a mut rdkafka::ClientConfig {
+        for (key, value) in &self.settings {
+            config.set(*key, value);
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_wins_over_env_and_is_trimmed() {
+        let path = std::env::temp_dir().join(format!("common-config-{}", std::process::id()));
+        std::fs::write(&path, "postgres://from-file\n").unwrap();
+        std::env::set_var("CC_TEST_DSN", "postgres://from-env");
+        assert_eq!(read_secret("CC_TEST_DSN").unwrap().as_deref(), Some("postgres://from-env"));
+        std::env::set_var("CC_TEST_DSN_FILE", &path);
+        assert_eq!(read_secret("CC_TEST_DSN").unwrap().as_deref(), Some("postgres://from-file"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(read_secret("CC_TEST_DSN"), Err(SecretError::File { .. })));
+        assert_eq!(read_secret("CC_TEST_UNSET").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn missing_secret_names_every_source() {
+        let err = require_secret("CC_TEST_MISSING").await.unwrap_err();
+        assert_eq!(err.to_string(), "CC_TEST_MISSING must be set (or CC_TEST_MISSING_FILE / CC_TEST_MISSING_VAULT)");
+    }
+
+    #[test]
+    fn vault_references_split_into_mount_path_and_field() {
+        assert_eq!(parse_vault_ref("secret/pos/inventory#database_url").unwrap(), ("secret", "pos/inventory", "database_url"));
+        assert!(parse_vault_ref("secret/pos/inventory").is_err());
+        assert!(parse_vault_ref("secret#field").is_err());
+    }
+
+    #[test]
+    fn kafka_sasl_defaults_to_plain_and_is_empty_when_unset() {
+        assert!(KafkaSecurity::new(None, None, None, None, None).settings().is_empty());
+        let sasl = KafkaSecurity::new(Some("SASL_SSL".into()), None, Some("pos".into()), Some("s3cret".into()), None);
+        assert_eq!(
+            sasl.settings(),
+            &[
+                ("security.protocol", "SASL_SSL".to_string()),
+                ("sasl.mechanisms", "PLAIN".to_string()),
+                ("sasl.username", "pos".to_string()),
+                ("sasl.password", "s3cret".to_string()),
+            ]
+        );
+    }
+}
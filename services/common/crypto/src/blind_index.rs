@@ -0,0 +1,117 @@
+//! Blind n-gram indexes for partial matching over encrypted fields.
+//!
+//! Each distinct n-gram of a normalized value is HMAC'd with a per-field key derived from the
+//! tenant DEK and truncated to [`BLIND_INDEX_TOKEN_LENGTH`] bytes. A row can match a query when
+//! its token set contains every query token; truncation makes false positives possible, so
+//! callers must confirm candidates against the decrypted value.
+
+use crate::{CryptoError, HmacSha256, KEY_LENGTH};
+use hmac::Mac;
+
+/// Bytes kept from each HMAC output. Short tokens limit what the index reveals about gram
+/// frequency while keeping collisions rare within a single tenant.
+pub const BLIND_INDEX_TOKEN_LENGTH: usize = 8;
+
+/// Derive the blind-index key for `field` from a tenant DEK, so tokens for different fields (and
+/// the exact-match [`crate::deterministic_hash`]) are unlinkable.
+pub fn derive_blind_index_key(
+    tenant_key: &[u8; KEY_LENGTH],
+    field: &str,
+) -> Result<[u8; KEY_LENGTH], CryptoError> {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(tenant_key).map_err(|_| CryptoError::InvalidMacKey)?;
+    mac.update(b"novapos-blind-index:");
+    mac.update(field.as_bytes());
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/// Tokens to store for a normalized value: one per distinct `gram_size`-character window,
+/// sorted and deduplicated. Values shorter than `gram_size` produce no tokens.
+pub fn blind_index_tokens(
+    field_key: &[u8; KEY_LENGTH],
+    value: &str,
+    gram_size: usize,
+) -> Result<Vec<Vec<u8>>, CryptoError> {
+    if gram_size == 0 {
+        return Err(CryptoError::InvalidGramSize);
+    }
+    let chars: Vec<char> = value.chars().collect();
+    let mut tokens = chars
+        .windows(gram_size)
+        .map(|gram| token(field_key, &gram.iter().collect::<String>()))
+        .collect::<Result<Vec<_>, _>>()?;
+    tokens.sort_unstable();
+    tokens.dedup();
+    Ok(tokens)
+}
+
+/// Tokens a stored value must contain to possibly include `term` as a substring. Returns `None`
+/// when the term is shorter than `gram_size` and cannot be answered from the index.
+pub fn blind_index_query(
+    field_key: &[u8; KEY_LENGTH],
+    term: &str,
+    gram_size: usize,
+) -> Result<Option<Vec<Vec<u8>>>, CryptoError> {
+    let tokens = blind_index_tokens(field_key, term, gram_size)?;
+    Ok((!tokens.is_empty()).then_some(tokens))
+}
+
+fn token(field_key: &[u8; KEY_LENGTH], gram: &str) -> Result<Vec<u8>, CryptoError> {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(field_key).map_err(|_| CryptoError::InvalidMacKey)?;
+    mac.update(gram.as_bytes());
+    Ok(mac.finalize().into_bytes()[..BLIND_INDEX_TOKEN_LENGTH].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains_all(stored: &[Vec<u8>], query: &[Vec<u8>]) -> bool {
+        query
+            .iter()
+            .all(|token| stored.binary_search(token).is_ok())
+    }
+
+    #[test]
+    fn substrings_match_and_other_values_do_not() {
+        let key = derive_blind_index_key(&[4u8; KEY_LENGTH], "customers.phone").expect("key");
+        let stored = blind_index_tokens(&key, "14155550123", 3).expect("tokens");
+        // 9 windows, "555" appears twice.
+        assert_eq!(stored.len(), 8);
+        assert!(stored
+            .iter()
+            .all(|token| token.len() == BLIND_INDEX_TOKEN_LENGTH));
+
+        for term in ["555", "5550123", "14155550123", "4155"] {
+            let query = blind_index_query(&key, term, 3)
+                .expect("query")
+                .expect("long enough");
+            assert!(contains_all(&stored, &query), "{term} should match");
+        }
+        let miss = blind_index_query(&key, "9990", 3)
+            .expect("query")
+            .expect("long enough");
+        assert!(!contains_all(&stored, &miss));
+        assert_eq!(blind_index_query(&key, "55", 3).expect("query"), None);
+    }
+
+    #[test]
+    fn tokens_are_scoped_to_field_and_tenant_key() {
+        let dek = [4u8; KEY_LENGTH];
+        let email = derive_blind_index_key(&dek, "customers.email").expect("key");
+        let phone = derive_blind_index_key(&dek, "customers.phone").expect("key");
+        let other_tenant =
+            derive_blind_index_key(&[5u8; KEY_LENGTH], "customers.email").expect("key");
+        let a = blind_index_tokens(&email, "alice", 3).expect("tokens");
+        assert_ne!(a, blind_index_tokens(&phone, "alice", 3).expect("tokens"));
+        assert_ne!(
+            a,
+            blind_index_tokens(&other_tenant, "alice", 3).expect("tokens")
+        );
+        assert!(matches!(
+            blind_index_tokens(&email, "alice", 0),
+            Err(CryptoError::InvalidGramSize)
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+//! Column-level encryption for services that do not manage tenant DEKs themselves.
+//!
+//! A service holds one [`ColumnKey`] and derives a key per tenant from it; values are sealed into
+//! versioned envelopes bound to the tenant and column via [`FieldAad`]. [`EncryptedColumn`] keeps
+//! the ciphertext typed so plaintext and encrypted columns cannot be mixed up in row structs.
+
+use crate::{
+    decrypt_field_with_aad, encrypt_field_with_aad, CryptoError, FieldAad, HmacSha256, MasterKey,
+    KEY_LENGTH,
+};
+use hmac::Mac;
+use std::marker::PhantomData;
+use zeroize::Zeroizing;
+
+/// Service-wide secret from which per-tenant column keys are derived.
+#[derive(Clone, Debug)]
+pub struct ColumnKey(MasterKey);
+
+impl ColumnKey {
+    pub fn new(key: MasterKey) -> Self {
+        Self(key)
+    }
+
+    pub fn from_base64(value: &str) -> Result<Self, CryptoError> {
+        MasterKey::from_base64(value).map(Self)
+    }
+
+    /// Stable, non-secret identifier to store next to values this key sealed.
+    pub fn key_id(&self) -> String {
+        crate::local_key_id(&self.0)
+    }
+
+    /// Key used to seal columns belonging to `tenant_id`.
+    pub fn tenant_key(&self, tenant_id: &[u8]) -> Zeroizing<[u8; KEY_LENGTH]> {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(self.0 .0.as_ref())
+            .expect("HMAC accepts any key length");
+        mac.update(b"novapos-column-key:");
+        mac.update(tenant_id);
+        Zeroizing::new(mac.finalize().into_bytes().into())
+    }
+
+    /// Encrypt `value` for `column` of `tenant_id`.
+    pub fn seal<T: ColumnCodec>(
+        &self,
+        tenant_id: &[u8],
+        column: &str,
+        value: &T,
+    ) -> Result<EncryptedColumn<T>, CryptoError> {
+        EncryptedColumn::seal(
+            &self.tenant_key(tenant_id),
+            FieldAad::new(tenant_id, column),
+            value,
+        )
+    }
+
+    /// Decrypt a value sealed by [`ColumnKey::seal`] with the same tenant and column.
+    pub fn open<T: ColumnCodec>(
+        &self,
+        tenant_id: &[u8],
+        column: &str,
+        sealed: &EncryptedColumn<T>,
+    ) -> Result<T, CryptoError> {
+        sealed.open(
+            &self.tenant_key(tenant_id),
+            FieldAad::new(tenant_id, column),
+        )
+    }
+}
+
+/// Plaintext types that can be stored in an [`EncryptedColumn`].
+pub trait ColumnCodec: Sized {
+    fn to_plaintext(&self) -> Zeroizing<Vec<u8>>;
+    fn from_plaintext(bytes: Vec<u8>) -> Result<Self, CryptoError>;
+}
+
+impl ColumnCodec for String {
+    fn to_plaintext(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.as_bytes().to_vec())
+    }
+
+    fn from_plaintext(bytes: Vec<u8>) -> Result<Self, CryptoError> {
+        String::from_utf8(bytes).map_err(|_| CryptoError::InvalidPlaintext)
+    }
+}
+
+impl ColumnCodec for Vec<u8> {
+    fn to_plaintext(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.clone())
+    }
+
+    fn from_plaintext(bytes: Vec<u8>) -> Result<Self, CryptoError> {
+        Ok(bytes)
+    }
+}
+
+/// Ciphertext of a `T`, stored as `BYTEA` (with the `sqlx` feature).
+pub struct EncryptedColumn<T> {
+    ciphertext: Vec<u8>,
+    _plaintext: PhantomData<fn() -> T>,
+}
+
+impl<T: ColumnCodec> EncryptedColumn<T> {
+    /// Encrypt `value` into a versioned envelope bound to `aad`.
+    pub fn seal(key: &[u8; KEY_LENGTH], aad: FieldAad<'_>, value: &T) -> Result<Self, CryptoError> {
+        encrypt_field_with_aad(key, aad, &value.to_plaintext()).map(Self::from_ciphertext)
+    }
+
+    pub fn open(&self, key: &[u8; KEY_LENGTH], aad: FieldAad<'_>) -> Result<T, CryptoError> {
+        T::from_plaintext(decrypt_field_with_aad(key, aad, &self.ciphertext)?)
+    }
+}
+
+impl<T> EncryptedColumn<T> {
+    pub fn from_ciphertext(ciphertext: Vec<u8>) -> Self {
+        Self {
+            ciphertext,
+            _plaintext: PhantomData,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.ciphertext
+    }
+}
+
+impl<T> Clone for EncryptedColumn<T> {
+    fn clone(&self) -> Self {
+        Self::from_ciphertext(self.ciphertext.clone())
+    }
+}
+
+impl<T> PartialEq for EncryptedColumn<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ciphertext == other.ciphertext
+    }
+}
+
+impl<T> Eq for EncryptedColumn<T> {}
+
+impl<T> std::fmt::Debug for EncryptedColumn<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptedColumn({} bytes)", self.ciphertext.len())
+    }
+}
+
+#[cfg(feature = "sqlx")]
+mod sqlx_impls {
+    use super::EncryptedColumn;
+    use sqlx::encode::IsNull;
+    use sqlx::error::BoxDynError;
+    use sqlx::postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueRef};
+    use sqlx::{Decode, Encode, Postgres, Type};
+
+    impl<T> Type<Postgres> for EncryptedColumn<T> {
+        fn type_info() -> PgTypeInfo {
+            <Vec<u8> as Type<Postgres>>::type_info()
+        }
+
+        fn compatible(ty: &PgTypeInfo) -> bool {
+            <Vec<u8> as Type<Postgres>>::compatible(ty)
+        }
+    }
+
+    impl<T> PgHasArrayType for EncryptedColumn<T> {
+        fn array_type_info() -> PgTypeInfo {
+            <Vec<u8> as PgHasArrayType>::array_type_info()
+        }
+    }
+
+    impl<T> Encode<'_, Postgres> for EncryptedColumn<T> {
+        fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+            <&[u8] as Encode<Postgres>>::encode(self.as_bytes(), buf)
+        }
+    }
+
+    impl<'r, T> Decode<'r, Postgres> for EncryptedColumn<T> {
+        fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(Self::from_ciphertext(
+                <Vec<u8> as Decode<Postgres>>::decode(value)?,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_columns_open_only_for_their_tenant_and_column() {
+        let key = ColumnKey::new(MasterKey::from_bytes([8u8; KEY_LENGTH]).expect("key"));
+        let tenant = [1u8; 16];
+        let sealed = key
+            .seal(
+                &tenant,
+                "orders.customer_email",
+                &"alice@example.com".to_string(),
+            )
+            .expect("seal");
+        assert!(!sealed
+            .as_bytes()
+            .windows(5)
+            .any(|window| window == b"alice"));
+        assert_eq!(
+            key.open(&tenant, "orders.customer_email", &sealed)
+                .expect("open"),
+            "alice@example.com"
+        );
+        assert!(key
+            .open(&[2u8; 16], "orders.customer_email", &sealed)
+            .is_err());
+        assert!(key.open(&tenant, "orders.customer_name", &sealed).is_err());
+
+        let other_service = ColumnKey::new(MasterKey::from_bytes([9u8; KEY_LENGTH]).expect("key"));
+        assert!(other_service
+            .open(&tenant, "orders.customer_email", &sealed)
+            .is_err());
+        assert_eq!(
+            format!("{sealed:?}"),
+            format!("EncryptedColumn({} bytes)", sealed.as_bytes().len())
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, KeyInit, Nonce,
+};
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine as _;
 use hmac::{Hmac, Mac};
@@ -8,10 +11,26 @@ use sha2::{Digest, Sha256};
 use thiserror::Error;
 use zeroize::Zeroizing;
 
+pub mod blind_index;
+pub mod column;
+pub mod provider;
+
+pub use blind_index::{
+    blind_index_query, blind_index_tokens, derive_blind_index_key, BLIND_INDEX_TOKEN_LENGTH,
+};
+pub use column::{ColumnCodec, ColumnKey, EncryptedColumn};
+pub use provider::{
+    local_key_id, CachingProvider, LocalMasterKeyProvider, MasterKeyProvider, WrappedDek,
+};
+
 type HmacSha256 = Hmac<Sha256>;
 
 const KEY_LENGTH: usize = 32;
 const NONCE_LENGTH: usize = 12;
+/// Prefix of versioned field envelopes: `b"NPE"` followed by a one-byte format version.
+const ENVELOPE_MAGIC: &[u8; 3] = b"NPE";
+const ENVELOPE_V1: u8 = 1;
+const ENVELOPE_HEADER_LENGTH: usize = ENVELOPE_MAGIC.len() + 1;
 
 /// Errors produced by the common-crypto helpers.
 #[derive(Debug, Error)]
@@ -28,6 +47,54 @@ pub enum CryptoError {
     Base64Decode(#[from] base64::DecodeError),
     #[error("invalid HMAC key length")]
     InvalidMacKey,
+    #[error("decrypted value is not valid for the column type")]
+    InvalidPlaintext,
+    #[error("blind index gram size must be at least 1")]
+    InvalidGramSize,
+    #[error("unknown master key id: {0}")]
+    UnknownMasterKey(String),
+    #[error("master key provider error: {0}")]
+    Provider(String),
+    #[error("ciphertext is not a versioned envelope")]
+    LegacyEnvelope,
+}
+
+/// Layout of a stored field ciphertext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeVersion {
+    /// `nonce || ciphertext` with no associated data (pre-envelope format).
+    Legacy,
+    /// `"NPE" || 0x01 || nonce || ciphertext`, authenticated with [`FieldAad`].
+    V1,
+}
+
+/// Associated data bound into versioned envelopes, so a ciphertext only decrypts for the tenant
+/// and field it was written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldAad<'a> {
+    pub tenant_id: &'a [u8],
+    pub field: &'a str,
+}
+
+impl<'a> FieldAad<'a> {
+    pub fn new(tenant_id: &'a [u8], field: &'a str) -> Self {
+        Self { tenant_id, field }
+    }
+
+    /// Length-prefixed encoding of the envelope header, tenant and field; the header is included
+    /// so the version byte cannot be altered without failing authentication.
+    fn encode(&self, version: u8) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            ENVELOPE_HEADER_LENGTH + 8 + self.tenant_id.len() + self.field.len(),
+        );
+        out.extend_from_slice(ENVELOPE_MAGIC);
+        out.push(version);
+        out.extend_from_slice(&(self.tenant_id.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.tenant_id);
+        out.extend_from_slice(&(self.field.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.field.as_bytes());
+        out
+    }
 }
 
 /// Wrapper around the tenant master key used to encrypt data encryption keys (DEKs).
@@ -94,6 +161,9 @@ pub fn generate_dek() -> [u8; KEY_LENGTH] {
 }
 
 /// Encrypt arbitrary plaintext with the supplied tenant DEK using AES-256-GCM.
+///
+/// Produces the legacy unversioned format without associated data; prefer
+/// [`encrypt_field_with_aad`] for new writes.
 pub fn encrypt_field(
     tenant_key: &[u8; KEY_LENGTH],
     plaintext: &[u8],
@@ -109,6 +179,111 @@ pub fn decrypt_field(
     decrypt_with_key(tenant_key, ciphertext)
 }
 
+/// Encrypt plaintext into a versioned envelope with `aad` bound into the AES-GCM tag.
+pub fn encrypt_field_with_aad(
+    tenant_key: &[u8; KEY_LENGTH],
+    aad: FieldAad<'_>,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let cipher = cipher_for(tenant_key)?;
+    let mut nonce_bytes = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let encoded_aad = aad.encode(ENVELOPE_V1);
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce_bytes),
+            Payload {
+                msg: plaintext,
+                aad: &encoded_aad,
+            },
+        )
+        .map_err(|_| CryptoError::EncryptFailure)?;
+    let mut output = Vec::with_capacity(ENVELOPE_HEADER_LENGTH + NONCE_LENGTH + ciphertext.len());
+    output.extend_from_slice(ENVELOPE_MAGIC);
+    output.push(ENVELOPE_V1);
+    output.extend_from_slice(&nonce_bytes);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// Decrypt either a versioned envelope (verifying `aad`) or a legacy blob (which carries no
+/// associated data, so `aad` cannot be checked until it is re-wrapped).
+pub fn decrypt_field_with_aad(
+    tenant_key: &[u8; KEY_LENGTH],
+    aad: FieldAad<'_>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    match envelope_version(ciphertext) {
+        EnvelopeVersion::V1 => decrypt_v1(tenant_key, aad, ciphertext).or_else(|err| {
+            // A legacy nonce can begin with the envelope header by chance (p = 2^-32).
+            decrypt_with_key(tenant_key, ciphertext).map_err(|_| err)
+        }),
+        EnvelopeVersion::Legacy => decrypt_with_key(tenant_key, ciphertext),
+    }
+}
+
+/// Decrypt a versioned envelope, verifying `aad`. Legacy blobs are refused with
+/// [`CryptoError::LegacyEnvelope`]; switch to this once every stored value has been re-wrapped,
+/// so a blob moved between rows can no longer be read through the unauthenticated path.
+pub fn decrypt_envelope(
+    tenant_key: &[u8; KEY_LENGTH],
+    aad: FieldAad<'_>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    match envelope_version(ciphertext) {
+        EnvelopeVersion::V1 => decrypt_v1(tenant_key, aad, ciphertext),
+        EnvelopeVersion::Legacy => Err(CryptoError::LegacyEnvelope),
+    }
+}
+
+/// Detect the layout of a stored field ciphertext. Unknown header versions are indistinguishable
+/// from legacy nonces and are reported as [`EnvelopeVersion::Legacy`].
+pub fn envelope_version(ciphertext: &[u8]) -> EnvelopeVersion {
+    if ciphertext.len() > ENVELOPE_HEADER_LENGTH + NONCE_LENGTH
+        && ciphertext.starts_with(ENVELOPE_MAGIC)
+        && ciphertext[ENVELOPE_MAGIC.len()] == ENVELOPE_V1
+    {
+        EnvelopeVersion::V1
+    } else {
+        EnvelopeVersion::Legacy
+    }
+}
+
+/// Migration helper: decrypt `ciphertext` (legacy or versioned) with `from_key` and re-encrypt it
+/// as a versioned envelope bound to `aad` under `to_key`. Pass the same key twice to only upgrade
+/// the format; pass the active key as `to_key` to combine the upgrade with a key rotation.
+pub fn rewrap_field(
+    from_key: &[u8; KEY_LENGTH],
+    to_key: &[u8; KEY_LENGTH],
+    aad: FieldAad<'_>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let plaintext = Zeroizing::new(decrypt_field_with_aad(from_key, aad, ciphertext)?);
+    encrypt_field_with_aad(to_key, aad, &plaintext)
+}
+
+fn decrypt_v1(
+    key: &[u8; KEY_LENGTH],
+    aad: FieldAad<'_>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let body = &ciphertext[ENVELOPE_HEADER_LENGTH..];
+    if body.len() <= NONCE_LENGTH {
+        return Err(CryptoError::MissingNonce);
+    }
+    let (nonce_bytes, encrypted) = body.split_at(NONCE_LENGTH);
+    let encoded_aad = aad.encode(ENVELOPE_V1);
+    cipher_for(key)?
+        .decrypt(
+            Nonce::from_slice(nonce_bytes),
+            Payload {
+                msg: encrypted,
+                aad: &encoded_aad,
+            },
+        )
+        .map_err(|_| CryptoError::DecryptFailure)
+}
+
 /// Produce a deterministic HMAC-SHA256 hash for equality queries.
 pub fn deterministic_hash(
     tenant_key: &[u8; KEY_LENGTH],
@@ -131,11 +306,15 @@ fn derive_hash_key(tenant_key: &[u8; KEY_LENGTH]) -> [u8; KEY_LENGTH] {
     out
 }
 
-fn encrypt_with_key(key: &[u8; KEY_LENGTH], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
-    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength {
+fn cipher_for(key: &[u8; KEY_LENGTH]) -> Result<Aes256Gcm, CryptoError> {
+    Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength {
         expected: KEY_LENGTH,
         actual: key.len(),
-    })?;
+    })
+}
+
+fn encrypt_with_key(key: &[u8; KEY_LENGTH], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let cipher = cipher_for(key)?;
     let mut nonce_bytes = [0u8; NONCE_LENGTH];
     OsRng.fill_bytes(&mut nonce_bytes);
     let nonce = Nonce::from_slice(&nonce_bytes);
@@ -153,11 +332,7 @@ fn decrypt_with_key(key: &[u8; KEY_LENGTH], ciphertext: &[u8]) -> Result<Vec<u8>
         return Err(CryptoError::MissingNonce);
     }
     let (nonce_bytes, encrypted) = ciphertext.split_at(NONCE_LENGTH);
-    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength {
-        expected: KEY_LENGTH,
-        actual: key.len(),
-    })?;
-    cipher
+    cipher_for(key)?
         .decrypt(Nonce::from_slice(nonce_bytes), encrypted)
         .map_err(|_| CryptoError::DecryptFailure)
 }
@@ -176,6 +351,68 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn versioned_envelope_binds_tenant_and_field() {
+        let dek = generate_dek();
+        let tenant_a = [1u8; 16];
+        let tenant_b = [2u8; 16];
+        let aad = FieldAad::new(&tenant_a, "email");
+        let blob = encrypt_field_with_aad(&dek, aad, b"alice@example.com").expect("encrypt");
+        assert_eq!(&blob[..4], b"NPE\x01");
+        assert_eq!(envelope_version(&blob), EnvelopeVersion::V1);
+        assert_eq!(
+            decrypt_field_with_aad(&dek, aad, &blob).expect("decrypt"),
+            b"alice@example.com"
+        );
+        // Swapped into another column or tenant, the tag no longer verifies.
+        assert!(decrypt_field_with_aad(&dek, FieldAad::new(&tenant_a, "phone"), &blob).is_err());
+        assert!(decrypt_field_with_aad(&dek, FieldAad::new(&tenant_b, "email"), &blob).is_err());
+        // Tampering with the version byte is detected as well.
+        let mut tampered = blob.clone();
+        tampered[3] = 2;
+        assert!(decrypt_field_with_aad(&dek, aad, &tampered).is_err());
+        // Versioned envelopes are not readable through the legacy path.
+        assert!(decrypt_field(&dek, &blob).is_err());
+    }
+
+    #[test]
+    fn legacy_blobs_decrypt_and_rewrap() {
+        let old_key = generate_dek();
+        let new_key = generate_dek();
+        let tenant = [5u8; 16];
+        let aad = FieldAad::new(&tenant, "phone");
+        let legacy = encrypt_field(&old_key, b"+41 44 000 00 00").expect("encrypt");
+        assert_eq!(envelope_version(&legacy), EnvelopeVersion::Legacy);
+        assert_eq!(
+            decrypt_field_with_aad(&old_key, aad, &legacy).expect("legacy decrypt"),
+            b"+41 44 000 00 00"
+        );
+
+        assert!(matches!(
+            decrypt_envelope(&old_key, aad, &legacy),
+            Err(CryptoError::LegacyEnvelope)
+        ));
+
+        let upgraded = rewrap_field(&old_key, &old_key, aad, &legacy).expect("rewrap");
+        assert_eq!(envelope_version(&upgraded), EnvelopeVersion::V1);
+        assert_eq!(
+            decrypt_field_with_aad(&old_key, aad, &upgraded).expect("decrypt"),
+            b"+41 44 000 00 00"
+        );
+        assert_eq!(
+            decrypt_envelope(&old_key, aad, &upgraded).expect("strict decrypt"),
+            b"+41 44 000 00 00"
+        );
+        assert!(decrypt_envelope(&old_key, FieldAad::new(&tenant, "email"), &upgraded).is_err());
+
+        let rotated = rewrap_field(&old_key, &new_key, aad, &upgraded).expect("rotate");
+        assert!(decrypt_field_with_aad(&old_key, aad, &rotated).is_err());
+        assert_eq!(
+            decrypt_field_with_aad(&new_key, aad, &rotated).expect("decrypt"),
+            b"+41 44 000 00 00"
+        );
+    }
+
     #[test]
     fn envelope_encrypt_decrypt_dek() {
         let master = MasterKey::from_bytes([1u8; KEY_LENGTH]).expect("master");
@@ -0,0 +1,927 @@
+//! Master key providers that wrap and unwrap tenant DEKs.
+//!
+//! Every wrapped DEK is stored with the `key_id` of the master key that produced it, so a new
+//! master key can be activated while DEKs wrapped by older keys stay readable until they are
+//! re-wrapped in the background.
+
+use crate::{CryptoError, MasterKey, KEY_LENGTH};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "aws-kms")]
+pub use aws_kms::{AwsCredentials, AwsKmsProvider};
+#[cfg(feature = "vault")]
+pub use vault::VaultTransitProvider;
+
+/// A tenant DEK encrypted by a master key, tagged with the id of that key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedDek {
+    pub key_id: String,
+    pub blob: Vec<u8>,
+}
+
+/// Source of master keys used to protect tenant DEKs.
+#[async_trait]
+pub trait MasterKeyProvider: Send + Sync {
+    /// Id recorded alongside DEKs wrapped by [`MasterKeyProvider::wrap_dek`].
+    fn active_key_id(&self) -> String;
+
+    /// Encrypt a DEK with the active master key.
+    async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError>;
+
+    /// Decrypt a DEK. `key_id` is `None` for DEKs stored before key ids were recorded.
+    async fn unwrap_dek(
+        &self,
+        key_id: Option<&str>,
+        blob: &[u8],
+    ) -> Result<[u8; KEY_LENGTH], CryptoError>;
+
+    /// Reload key material or credentials from the underlying source.
+    async fn refresh(&self) -> Result<(), CryptoError> {
+        Ok(())
+    }
+}
+
+/// Stable, non-secret identifier for a local master key.
+pub fn local_key_id(key: &MasterKey) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"novapos-master-key-id");
+    hasher.update(key.0.as_ref());
+    format!("local:{}", hex(&hasher.finalize()[..8]))
+}
+
+struct Keyring {
+    active: String,
+    keys: Vec<(String, MasterKey)>,
+}
+
+impl Keyring {
+    fn new(active: MasterKey, retired: Vec<MasterKey>) -> Self {
+        let mut keys = vec![(local_key_id(&active), active)];
+        keys.extend(retired.into_iter().map(|key| (local_key_id(&key), key)));
+        Self {
+            active: keys[0].0.clone(),
+            keys,
+        }
+    }
+
+    fn parse(values: impl IntoIterator<Item = String>) -> Result<Self, CryptoError> {
+        let mut keys = values
+            .into_iter()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty() && !value.starts_with('#'))
+            .map(|value| MasterKey::from_base64(&value));
+        let active = keys
+            .next()
+            .ok_or_else(|| CryptoError::Provider("no master key configured".into()))??;
+        Ok(Self::new(active, keys.collect::<Result<_, _>>()?))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum LocalSource {
+    Static,
+    Env(String),
+    File(PathBuf),
+}
+
+/// Master keys held in process memory, loaded from an environment variable or a key file.
+///
+/// The first key is active; any further keys are retired keys kept only for unwrapping.
+pub struct LocalMasterKeyProvider {
+    source: LocalSource,
+    keyring: RwLock<Keyring>,
+}
+
+impl LocalMasterKeyProvider {
+    /// Provider over a fixed set of keys; `refresh` is a no-op.
+    pub fn new(active: MasterKey, retired: Vec<MasterKey>) -> Self {
+        Self {
+            source: LocalSource::Static,
+            keyring: RwLock::new(Keyring::new(active, retired)),
+        }
+    }
+
+    /// Read the active key from `var` and comma-separated retired keys from `{var}_PREVIOUS`.
+    pub fn from_env(var: &str) -> Result<Self, CryptoError> {
+        let source = LocalSource::Env(var.to_string());
+        let keyring = load_keyring(&source)?;
+        Ok(Self {
+            source,
+            keyring: RwLock::new(keyring),
+        })
+    }
+
+    /// Parse base64 keys already loaded from a secret store: one per line or comma-separated,
+    /// active key first. The keyring is fixed; `refresh` is a no-op.
+    pub fn from_keys(keys: &str) -> Result<Self, CryptoError> {
+        let keyring = Keyring::parse(keys.split(['\n', ',']).map(str::to_string))?;
+        Ok(Self {
+            source: LocalSource::Static,
+            keyring: RwLock::new(keyring),
+        })
+    }
+
+    /// Read base64 keys from `path`, one per line, active key first. `#` starts a comment line.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, CryptoError> {
+        let source = LocalSource::File(path.into());
+        let keyring = load_keyring(&source)?;
+        Ok(Self {
+            source,
+            keyring: RwLock::new(keyring),
+        })
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Keyring> {
+        self.keyring
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+}
+
+fn load_keyring(source: &LocalSource) -> Result<Keyring, CryptoError> {
+    match source {
+        LocalSource::Static => Err(CryptoError::Provider("static keyring cannot reload".into())),
+        LocalSource::Env(var) => {
+            let active = std::env::var(var)
+                .map_err(|_| CryptoError::Provider(format!("{var} must be set")))?;
+            let previous = std::env::var(format!("{var}_PREVIOUS")).unwrap_or_default();
+            Keyring::parse(std::iter::once(active).chain(previous.split(',').map(str::to_string)))
+        }
+        LocalSource::File(path) => {
+            let contents = std::fs::read_to_string(path).map_err(|err| {
+                CryptoError::Provider(format!("failed to read {}: {err}", path.display()))
+            })?;
+            Keyring::parse(contents.lines().map(str::to_string))
+        }
+    }
+}
+
+#[async_trait]
+impl MasterKeyProvider for LocalMasterKeyProvider {
+    fn active_key_id(&self) -> String {
+        self.read().active.clone()
+    }
+
+    async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError> {
+        let keyring = self.read();
+        let (key_id, key) = &keyring.keys[0];
+        Ok(WrappedDek {
+            key_id: key_id.clone(),
+            blob: key.encrypt_tenant_dek(dek)?,
+        })
+    }
+
+    async fn unwrap_dek(
+        &self,
+        key_id: Option<&str>,
+        blob: &[u8],
+    ) -> Result<[u8; KEY_LENGTH], CryptoError> {
+        let keyring = self.read();
+        match key_id {
+            Some(id) => keyring
+                .keys
+                .iter()
+                .find(|(candidate, _)| candidate == id)
+                .ok_or_else(|| CryptoError::UnknownMasterKey(id.to_string()))?
+                .1
+                .decrypt_tenant_dek(blob),
+            // Untagged DEKs predate key ids; try every configured key, active first.
+            None => keyring
+                .keys
+                .iter()
+                .find_map(|(_, key)| key.decrypt_tenant_dek(blob).ok())
+                .ok_or(CryptoError::DecryptFailure),
+        }
+    }
+
+    async fn refresh(&self) -> Result<(), CryptoError> {
+        if matches!(self.source, LocalSource::Static) {
+            return Ok(());
+        }
+        let keyring = load_keyring(&self.source)?;
+        *self
+            .keyring
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner()) = keyring;
+        Ok(())
+    }
+}
+
+/// Unwrapped DEKs keyed by a digest of `(key_id, blob)`, with the time they were loaded.
+type DekCache = HashMap<[u8; 32], (Zeroizing<[u8; KEY_LENGTH]>, Instant)>;
+
+/// Wraps a provider with a TTL cache of unwrapped DEKs and periodic source refresh.
+///
+/// Refresh runs lazily on the first call after `refresh_interval` has elapsed. A failed refresh
+/// keeps the previously loaded keys in service; call [`MasterKeyProvider::refresh`] directly to
+/// observe the error.
+pub struct CachingProvider<P> {
+    inner: P,
+    ttl: Duration,
+    refresh_interval: Duration,
+    last_refresh: Mutex<Instant>,
+    cache: Mutex<DekCache>,
+}
+
+impl<P: MasterKeyProvider> CachingProvider<P> {
+    pub fn new(inner: P, ttl: Duration, refresh_interval: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            refresh_interval,
+            last_refresh: Mutex::new(Instant::now()),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    async fn maybe_refresh(&self) {
+        let due = {
+            let mut last = self
+                .last_refresh
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner());
+            let due = last.elapsed() >= self.refresh_interval;
+            if due {
+                *last = Instant::now();
+            }
+            due
+        };
+        if due {
+            let _ = self.inner.refresh().await;
+        }
+    }
+
+    fn cache_key(key_id: Option<&str>, blob: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(key_id.unwrap_or_default().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(blob);
+        hasher.finalize().into()
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, DekCache> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+}
+
+#[async_trait]
+impl<P: MasterKeyProvider> MasterKeyProvider for CachingProvider<P> {
+    fn active_key_id(&self) -> String {
+        self.inner.active_key_id()
+    }
+
+    async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError> {
+        self.maybe_refresh().await;
+        self.inner.wrap_dek(dek).await
+    }
+
+    async fn unwrap_dek(
+        &self,
+        key_id: Option<&str>,
+        blob: &[u8],
+    ) -> Result<[u8; KEY_LENGTH], CryptoError> {
+        self.maybe_refresh().await;
+        let cache_key = Self::cache_key(key_id, blob);
+        if let Some((dek, loaded_at)) = self.lock_cache().get(&cache_key) {
+            if loaded_at.elapsed() < self.ttl {
+                return Ok(**dek);
+            }
+        }
+        let dek = self.inner.unwrap_dek(key_id, blob).await?;
+        let mut cache = self.lock_cache();
+        cache.retain(|_, (_, loaded_at)| loaded_at.elapsed() < self.ttl);
+        cache.insert(cache_key, (Zeroizing::new(dek), Instant::now()));
+        Ok(dek)
+    }
+
+    async fn refresh(&self) -> Result<(), CryptoError> {
+        self.lock_cache().clear();
+        *self
+            .last_refresh
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner()) = Instant::now();
+        self.inner.refresh().await
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(any(feature = "aws-kms", feature = "vault"))]
+fn decode_dek(encoded: &str) -> Result<[u8; KEY_LENGTH], CryptoError> {
+    use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+    let decoded = Zeroizing::new(BASE64_STANDARD.decode(encoded)?);
+    decoded
+        .as_slice()
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKeyLength {
+            expected: KEY_LENGTH,
+            actual: decoded.len(),
+        })
+}
+
+#[cfg(feature = "aws-kms")]
+mod aws_kms {
+    use super::{decode_dek, hex, MasterKeyProvider, WrappedDek};
+    use crate::{CryptoError, HmacSha256, KEY_LENGTH};
+    use async_trait::async_trait;
+    use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+    use base64::Engine as _;
+    use hmac::Mac;
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+    use std::sync::RwLock;
+
+    const KEY_ID_PREFIX: &str = "aws-kms:";
+    const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+    /// Static or session credentials used to sign KMS requests.
+    #[derive(Clone)]
+    pub struct AwsCredentials {
+        pub access_key_id: String,
+        pub secret_access_key: String,
+        pub session_token: Option<String>,
+    }
+
+    impl AwsCredentials {
+        /// Read `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and optional `AWS_SESSION_TOKEN`.
+        pub fn from_env() -> Result<Self, CryptoError> {
+            let var = |name: &str| {
+                std::env::var(name)
+                    .map_err(|_| CryptoError::Provider(format!("{name} must be set")))
+            };
+            Ok(Self {
+                access_key_id: var("AWS_ACCESS_KEY_ID")?,
+                secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            })
+        }
+    }
+
+    impl std::fmt::Debug for AwsCredentials {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("AwsCredentials")
+                .field("access_key_id", &self.access_key_id)
+                .field("secret_access_key", &"***redacted***")
+                .finish()
+        }
+    }
+
+    /// DEKs wrapped by an AWS KMS symmetric key; the master key never leaves KMS.
+    ///
+    /// Requests are signed with SigV4 and carry a fixed encryption context so blobs cannot be
+    /// decrypted through KMS for another purpose. `refresh` re-reads credentials from the
+    /// environment, picking up rotated session tokens.
+    pub struct AwsKmsProvider {
+        client: reqwest::Client,
+        endpoint: reqwest::Url,
+        region: String,
+        kms_key_id: String,
+        credentials: RwLock<AwsCredentials>,
+    }
+
+    impl AwsKmsProvider {
+        pub fn new(
+            region: impl Into<String>,
+            kms_key_id: impl Into<String>,
+            credentials: AwsCredentials,
+            endpoint: Option<&str>,
+        ) -> Result<Self, CryptoError> {
+            let region = region.into();
+            let endpoint = endpoint
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("https://kms.{region}.amazonaws.com/"));
+            Ok(Self {
+                client: reqwest::Client::new(),
+                endpoint: reqwest::Url::parse(&endpoint)
+                    .map_err(|err| CryptoError::Provider(format!("invalid KMS endpoint: {err}")))?,
+                region,
+                kms_key_id: kms_key_id.into(),
+                credentials: RwLock::new(credentials),
+            })
+        }
+
+        /// Configure from `AWS_REGION` (or `AWS_DEFAULT_REGION`), the key id/ARN in `key_var`,
+        /// optional `AWS_KMS_ENDPOINT`, and the standard credential variables.
+        pub fn from_env(key_var: &str) -> Result<Self, CryptoError> {
+            let region = std::env::var("AWS_REGION")
+                .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+                .map_err(|_| CryptoError::Provider("AWS_REGION must be set".into()))?;
+            let kms_key_id = std::env::var(key_var)
+                .map_err(|_| CryptoError::Provider(format!("{key_var} must be set")))?;
+            let endpoint = std::env::var("AWS_KMS_ENDPOINT").ok();
+            Self::new(
+                region,
+                kms_key_id,
+                AwsCredentials::from_env()?,
+                endpoint.as_deref(),
+            )
+        }
+
+        async fn call(&self, target: &str, body: Value) -> Result<Value, CryptoError> {
+            let payload = body.to_string();
+            let credentials = self
+                .credentials
+                .read()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .clone();
+            let host = self
+                .endpoint
+                .host_str()
+                .ok_or_else(|| CryptoError::Provider("KMS endpoint has no host".into()))?;
+            let host = match self.endpoint.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            };
+            let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+            let target = format!("TrentService.{target}");
+            let authorization = sign_request(
+                &credentials,
+                &self.region,
+                &host,
+                &amz_date,
+                &target,
+                &payload,
+            );
+
+            let mut request = self
+                .client
+                .post(self.endpoint.clone())
+                .header("content-type", CONTENT_TYPE)
+                .header("x-amz-date", &amz_date)
+                .header("x-amz-target", &target)
+                .header("authorization", authorization)
+                .body(payload);
+            if let Some(token) = &credentials.session_token {
+                request = request.header("x-amz-security-token", token);
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|err| CryptoError::Provider(format!("KMS request failed: {err}")))?;
+            let status = response.status();
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|err| CryptoError::Provider(format!("invalid KMS response: {err}")))?;
+            if !status.is_success() {
+                let kind = body["__type"].as_str().unwrap_or("unknown");
+                return Err(CryptoError::Provider(format!(
+                    "KMS {target} failed: {status} {kind}"
+                )));
+            }
+            Ok(body)
+        }
+    }
+
+    fn encryption_context() -> Value {
+        json!({ "purpose": "tenant-dek" })
+    }
+
+    #[async_trait]
+    impl MasterKeyProvider for AwsKmsProvider {
+        fn active_key_id(&self) -> String {
+            format!("{KEY_ID_PREFIX}{}", self.kms_key_id)
+        }
+
+        async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError> {
+            let response = self
+                .call(
+                    "Encrypt",
+                    json!({
+                        "KeyId": self.kms_key_id,
+                        "Plaintext": BASE64_STANDARD.encode(dek),
+                        "EncryptionContext": encryption_context(),
+                    }),
+                )
+                .await?;
+            let blob = response["CiphertextBlob"].as_str().ok_or_else(|| {
+                CryptoError::Provider("KMS response missing CiphertextBlob".into())
+            })?;
+            Ok(WrappedDek {
+                key_id: self.active_key_id(),
+                blob: BASE64_STANDARD.decode(blob)?,
+            })
+        }
+
+        async fn unwrap_dek(
+            &self,
+            key_id: Option<&str>,
+            blob: &[u8],
+        ) -> Result<[u8; KEY_LENGTH], CryptoError> {
+            let kms_key_id = match key_id {
+                Some(id) => id
+                    .strip_prefix(KEY_ID_PREFIX)
+                    .ok_or_else(|| CryptoError::UnknownMasterKey(id.to_string()))?,
+                None => return Err(CryptoError::UnknownMasterKey("<untagged>".into())),
+            };
+            let response = self
+                .call(
+                    "Decrypt",
+                    json!({
+                        "KeyId": kms_key_id,
+                        "CiphertextBlob": BASE64_STANDARD.encode(blob),
+                        "EncryptionContext": encryption_context(),
+                    }),
+                )
+                .await?;
+            let plaintext = response["Plaintext"]
+                .as_str()
+                .ok_or_else(|| CryptoError::Provider("KMS response missing Plaintext".into()))?;
+            decode_dek(plaintext)
+        }
+
+        async fn refresh(&self) -> Result<(), CryptoError> {
+            let credentials = AwsCredentials::from_env()?;
+            *self
+                .credentials
+                .write()
+                .unwrap_or_else(|poison| poison.into_inner()) = credentials;
+            Ok(())
+        }
+    }
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+        let date_key = hmac(format!("AWS4{secret}").as_bytes(), date);
+        let region_key = hmac(&date_key, region);
+        let service_key = hmac(&region_key, service);
+        hmac(&service_key, "aws4_request")
+    }
+
+    /// SigV4 `Authorization` header for a KMS JSON POST to `/`.
+    fn sign_request(
+        credentials: &AwsCredentials,
+        region: &str,
+        host: &str,
+        amz_date: &str,
+        target: &str,
+        payload: &str,
+    ) -> String {
+        let date = &amz_date[..8];
+        let mut headers = vec![
+            ("content-type", CONTENT_TYPE.to_string()),
+            ("host", host.to_string()),
+            ("x-amz-date", amz_date.to_string()),
+            ("x-amz-target", target.to_string()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.sort_by(|a, b| a.0.cmp(b.0));
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+            hex(&Sha256::digest(payload.as_bytes()))
+        );
+        let scope = format!("{date}/{region}/kms/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&credentials.secret_access_key, date, region, "kms");
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={}",
+            credentials.access_key_id,
+            hex(&hmac(&key, &string_to_sign))
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn signing_key_matches_aws_reference_vector() {
+            // Example from the AWS SigV4 documentation ("Deriving the signing key").
+            let key = signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam",
+            );
+            assert_eq!(
+                hex(&key),
+                "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "vault")]
+mod vault {
+    use super::{decode_dek, MasterKeyProvider, WrappedDek};
+    use crate::{CryptoError, KEY_LENGTH};
+    use async_trait::async_trait;
+    use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+    use base64::Engine as _;
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+    use std::sync::RwLock;
+
+    /// DEKs wrapped by a HashiCorp Vault transit key.
+    ///
+    /// Vault versions transit keys internally (`vault:vN:` ciphertext prefix), so rotating the
+    /// transit key keeps the same `key_id`. `refresh` re-reads the token from `VAULT_TOKEN_FILE`
+    /// when one is configured, for agents that renew tokens on disk.
+    pub struct VaultTransitProvider {
+        client: reqwest::Client,
+        addr: String,
+        mount: String,
+        key_name: String,
+        token: RwLock<String>,
+        token_file: Option<PathBuf>,
+    }
+
+    impl VaultTransitProvider {
+        pub fn new(
+            addr: impl Into<String>,
+            mount: impl Into<String>,
+            key_name: impl Into<String>,
+            token: impl Into<String>,
+        ) -> Self {
+            Self {
+                client: reqwest::Client::new(),
+                addr: addr.into().trim_end_matches('/').to_string(),
+                mount: mount.into(),
+                key_name: key_name.into(),
+                token: RwLock::new(token.into()),
+                token_file: None,
+            }
+        }
+
+        /// Configure from `VAULT_ADDR`, `VAULT_TOKEN` or `VAULT_TOKEN_FILE`, optional
+        /// `VAULT_TRANSIT_MOUNT` (default `transit`) and the key name in `key_var`.
+        pub fn from_env(key_var: &str) -> Result<Self, CryptoError> {
+            let var = |name: &str| {
+                std::env::var(name)
+                    .map_err(|_| CryptoError::Provider(format!("{name} must be set")))
+            };
+            let token_file = std::env::var("VAULT_TOKEN_FILE").ok().map(PathBuf::from);
+            let token = match &token_file {
+                Some(path) => read_token(path)?,
+                None => var("VAULT_TOKEN")?,
+            };
+            let mount = std::env::var("VAULT_TRANSIT_MOUNT").unwrap_or_else(|_| "transit".into());
+            let mut provider = Self::new(var("VAULT_ADDR")?, mount, var(key_var)?, token);
+            provider.token_file = token_file;
+            Ok(provider)
+        }
+
+        async fn call(&self, operation: &str, body: Value) -> Result<Value, CryptoError> {
+            let token = self
+                .token
+                .read()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .clone();
+            let url = format!(
+                "{}/v1/{}/{operation}/{}",
+                self.addr, self.mount, self.key_name
+            );
+            let response = self
+                .client
+                .post(url)
+                .header("X-Vault-Token", token)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|err| CryptoError::Provider(format!("Vault request failed: {err}")))?;
+            let status = response.status();
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|err| CryptoError::Provider(format!("invalid Vault response: {err}")))?;
+            if !status.is_success() {
+                return Err(CryptoError::Provider(format!(
+                    "Vault transit {operation} failed: {status} {}",
+                    body["errors"]
+                )));
+            }
+            Ok(body)
+        }
+    }
+
+    fn read_token(path: &PathBuf) -> Result<String, CryptoError> {
+        std::fs::read_to_string(path)
+            .map(|token| token.trim().to_string())
+            .map_err(|err| {
+                CryptoError::Provider(format!("failed to read {}: {err}", path.display()))
+            })
+    }
+
+    #[async_trait]
+    impl MasterKeyProvider for VaultTransitProvider {
+        fn active_key_id(&self) -> String {
+            format!("vault:{}/{}", self.mount, self.key_name)
+        }
+
+        async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError> {
+            let response = self
+                .call(
+                    "encrypt",
+                    json!({ "plaintext": BASE64_STANDARD.encode(dek) }),
+                )
+                .await?;
+            let ciphertext = response["data"]["ciphertext"]
+                .as_str()
+                .ok_or_else(|| CryptoError::Provider("Vault response missing ciphertext".into()))?;
+            Ok(WrappedDek {
+                key_id: self.active_key_id(),
+                blob: ciphertext.as_bytes().to_vec(),
+            })
+        }
+
+        async fn unwrap_dek(
+            &self,
+            key_id: Option<&str>,
+            blob: &[u8],
+        ) -> Result<[u8; KEY_LENGTH], CryptoError> {
+            let active = self.active_key_id();
+            if key_id != Some(active.as_str()) {
+                return Err(CryptoError::UnknownMasterKey(
+                    key_id.unwrap_or("<untagged>").to_string(),
+                ));
+            }
+            let ciphertext = std::str::from_utf8(blob).map_err(|_| CryptoError::DecryptFailure)?;
+            let response = self
+                .call("decrypt", json!({ "ciphertext": ciphertext }))
+                .await?;
+            let plaintext = response["data"]["plaintext"]
+                .as_str()
+                .ok_or_else(|| CryptoError::Provider("Vault response missing plaintext".into()))?;
+            decode_dek(plaintext)
+        }
+
+        async fn refresh(&self) -> Result<(), CryptoError> {
+            if let Some(path) = &self.token_file {
+                let token = read_token(path)?;
+                *self
+                    .token
+                    .write()
+                    .unwrap_or_else(|poison| poison.into_inner()) = token;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_dek;
+    use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey::from_bytes([byte; KEY_LENGTH]).expect("key")
+    }
+
+    #[tokio::test]
+    async fn local_provider_tags_and_unwraps_by_key_id() {
+        let old = LocalMasterKeyProvider::new(key(1), Vec::new());
+        let dek = generate_dek();
+        let legacy_blob = key(1).encrypt_tenant_dek(&dek).expect("wrap");
+        let wrapped_old = old.wrap_dek(&dek).await.expect("wrap");
+        assert_eq!(wrapped_old.key_id, local_key_id(&key(1)));
+
+        // Rotate: key 2 becomes active, key 1 stays readable.
+        let rotated = LocalMasterKeyProvider::new(key(2), vec![key(1)]);
+        assert_ne!(rotated.active_key_id(), wrapped_old.key_id);
+        let unwrapped = rotated
+            .unwrap_dek(Some(&wrapped_old.key_id), &wrapped_old.blob)
+            .await
+            .expect("unwrap retired");
+        assert_eq!(unwrapped, dek);
+        assert_eq!(
+            rotated
+                .unwrap_dek(None, &legacy_blob)
+                .await
+                .expect("legacy"),
+            dek
+        );
+
+        let rewrapped = rotated.wrap_dek(&unwrapped).await.expect("rewrap");
+        assert_eq!(rewrapped.key_id, rotated.active_key_id());
+
+        let only_new = LocalMasterKeyProvider::new(key(2), Vec::new());
+        assert!(matches!(
+            only_new
+                .unwrap_dek(Some(&wrapped_old.key_id), &wrapped_old.blob)
+                .await,
+            Err(CryptoError::UnknownMasterKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn file_provider_reloads_rotated_keys() {
+        let path = std::env::temp_dir().join(format!("master-keys-{}", std::process::id()));
+        let encode = |byte: u8| BASE64_STANDARD.encode([byte; KEY_LENGTH]);
+        std::fs::write(&path, format!("# active\n{}\n", encode(1))).expect("write");
+        let provider = LocalMasterKeyProvider::from_file(&path).expect("load");
+        let wrapped = provider.wrap_dek(&generate_dek()).await.expect("wrap");
+
+        std::fs::write(&path, format!("{}\n{}\n", encode(2), encode(1))).expect("write");
+        provider.refresh().await.expect("refresh");
+        assert_eq!(provider.active_key_id(), local_key_id(&key(2)));
+        assert!(provider
+            .unwrap_dek(Some(&wrapped.key_id), &wrapped.blob)
+            .await
+            .is_ok());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn keys_from_a_secret_accept_lines_or_commas() {
+        let encode = |byte: u8| BASE64_STANDARD.encode([byte; KEY_LENGTH]);
+        for secret in [format!("{}\n{}\n", encode(2), encode(1)), format!("{},{}", encode(2), encode(1))] {
+            let provider = LocalMasterKeyProvider::from_keys(&secret).expect("load");
+            assert_eq!(provider.active_key_id(), local_key_id(&key(2)));
+            assert_eq!(provider.read().keys.len(), 2);
+        }
+        assert!(LocalMasterKeyProvider::from_keys(" \n").is_err());
+    }
+
+    struct CountingProvider {
+        inner: LocalMasterKeyProvider,
+        unwraps: AtomicUsize,
+        refreshes: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MasterKeyProvider for CountingProvider {
+        fn active_key_id(&self) -> String {
+            self.inner.active_key_id()
+        }
+
+        async fn wrap_dek(&self, dek: &[u8; KEY_LENGTH]) -> Result<WrappedDek, CryptoError> {
+            self.inner.wrap_dek(dek).await
+        }
+
+        async fn unwrap_dek(
+            &self,
+            key_id: Option<&str>,
+            blob: &[u8],
+        ) -> Result<[u8; KEY_LENGTH], CryptoError> {
+            self.unwraps.fetch_add(1, Ordering::SeqCst);
+            self.inner.unwrap_dek(key_id, blob).await
+        }
+
+        async fn refresh(&self) -> Result<(), CryptoError> {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn caching_provider_memoizes_unwraps_and_refreshes_lazily() {
+        let counting = CountingProvider {
+            inner: LocalMasterKeyProvider::new(key(7), Vec::new()),
+            unwraps: AtomicUsize::new(0),
+            refreshes: AtomicUsize::new(0),
+        };
+        let cached = CachingProvider::new(counting, Duration::from_secs(60), Duration::ZERO);
+        let wrapped = cached.wrap_dek(&generate_dek()).await.expect("wrap");
+        for _ in 0..3 {
+            cached
+                .unwrap_dek(Some(&wrapped.key_id), &wrapped.blob)
+                .await
+                .expect("unwrap");
+        }
+        assert_eq!(cached.inner().unwraps.load(Ordering::SeqCst), 1);
+        assert!(cached.inner().refreshes.load(Ordering::SeqCst) >= 4);
+
+        cached.refresh().await.expect("refresh");
+        cached
+            .unwrap_dek(Some(&wrapped.key_id), &wrapped.blob)
+            .await
+            .expect("unwrap");
+        assert_eq!(cached.inner().unwraps.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,204 @@
+//! Tenant-scoped database access.
+//!
+//! Handlers open a [`TenantExecutor`] from a [`TenantScopedPool`] instead of querying the pool
+//! directly. The executor is a transaction with the Postgres setting [`TENANT_SETTING`] set to the
+//! caller's tenant, which the row-level security policies in each service's migrations compare
+//! against `tenant_id`, and with the role switched to [`TENANT_ROLE`] so the policies apply even to
+//! an owner or superuser login. The policies fail closed: a session that never sets the tenant sees
+//! no rows, so work spanning tenants goes through [`TenantScopedPool::cross_tenant`]. The [`query`], [`query_as`] and [`query_scalar`] wrappers additionally
+//! reject statements that never mention `tenant_id` in debug builds, so a missing predicate is
+//! caught by tests before it reaches the database.
+//!
+//! [`ReadPool`] and [`WritePool`] split read-only traffic onto an optional replica; see [`replica`].
+//! Pool sizing, pool metrics and the 503 for a saturated pool live in [`pool`].
+//! List endpoints share cursor pagination and sort whitelisting from [`pagination`].
+//! Tenants pinned to a region are routed to that region's database by [`RegionalPools`]; see
+//! [`residency`].
+
+pub mod pagination;
+pub mod pool;
+pub mod replica;
+pub mod residency;
+
+pub use pagination::{Listing, Page, PagePlan, PageRequest, SortDirection, SortField, SortSpec};
+pub use pool::{cross_tenant_options, db_error, PoolSettings};
+pub use replica::{ReadPool, WritePool};
+pub use residency::{RegionSettings, RegionalPools};
+
+use common_security::SecurityContext;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::{Query, QueryAs, QueryScalar};
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, Transaction};
+use std::ops::{Deref, DerefMut};
+use uuid::Uuid;
+
+/// Transaction-local setting holding the current tenant id; RLS policies read it with
+/// `current_setting('app.tenant_id', true)`.
+pub const TENANT_SETTING: &str = "app.tenant_id";
+
+/// Non-owner role tenant transactions run as; the migrations that enable RLS create it.
+pub const TENANT_ROLE: &str = "pos_tenant";
+
+/// Role of the pool for sweepers, consumers and maintenance binaries; it has its own policy that
+/// sees every tenant. See [`PoolSettings::connect_cross_tenant`].
+pub const CROSS_TENANT_ROLE: &str = "pos_cross_tenant";
+
+/// Connection pool that hands out tenant-bound transactions.
+#[derive(Clone, Debug)]
+pub struct TenantScopedPool {
+    pool: PgPool,
+    cross_tenant: PgPool,
+}
+
+impl TenantScopedPool {
+    /// Pool whose cross-tenant work shares `pool`; fine for superuser test databases, where RLS
+    /// does not apply outside tenant transactions. Services use [`Self::with_cross_tenant`].
+    pub fn new(pool: PgPool) -> Self {
+        Self { cross_tenant: pool.clone(), pool }
+    }
+
+    /// Use `cross_tenant` (opened with [`PoolSettings::connect_cross_tenant`]) for
+    /// [`Self::cross_tenant`].
+    pub fn with_cross_tenant(mut self, cross_tenant: PgPool) -> Self {
+        self.cross_tenant = cross_tenant;
+        self
+    }
+
+    /// Begin a transaction bound to `tenant_id`.
+    pub async fn begin(&self, tenant_id: Uuid) -> Result<TenantExecutor, sqlx::Error> {
+        let timer = pool::AcquireTimer::start(&self.pool);
+        let mut tx = self.pool.begin().await?;
+        timer.finish(pool::PRIMARY_POOL);
+        sqlx::query("SELECT set_config($1, $2, true), set_config('role', $3, true)")
+            .bind(TENANT_SETTING)
+            .bind(tenant_id.to_string())
+            .bind(TENANT_ROLE)
+            .execute(&mut *tx)
+            .await?;
+        Ok(TenantExecutor { tx, tenant_id })
+    }
+
+    /// Begin a transaction bound to the tenant of an authenticated request.
+    pub async fn begin_for(&self, sec: &SecurityContext) -> Result<TenantExecutor, sqlx::Error> {
+        self.begin(sec.tenant_id).await
+    }
+
+    /// The service's own login, for tables without row-level security (outbox, inbox,
+    /// idempotency keys). Tables with it return no rows here.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Pool that sees every tenant, for background sweeps and consumers. Request handlers should
+    /// not need this.
+    pub fn cross_tenant(&self) -> &PgPool {
+        &self.cross_tenant
+    }
+}
+
+impl From<PgPool> for TenantScopedPool {
+    fn from(pool: PgPool) -> Self {
+        Self::new(pool)
+    }
+}
+
+/// Transaction bound to a single tenant. Dereferences to the connection, so it is used as
+/// `.fetch_all(&mut *tx)`; dropping it without [`TenantExecutor::commit`] rolls back.
+pub struct TenantExecutor {
+    tx: Transaction<'static, Postgres>,
+    tenant_id: Uuid,
+}
+
+impl TenantExecutor {
+    pub fn tenant_id(&self) -> Uuid {
+        self.tenant_id
+    }
+
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        self.tx.commit().await
+    }
+
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        self.tx.rollback().await
+    }
+}
+
+impl Deref for TenantExecutor {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        &self.tx
+    }
+}
+
+impl DerefMut for TenantExecutor {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+}
+
+/// Whether `sql` references a `tenant_id` column. Deliberately coarse: it cannot prove the
+/// predicate is correct, only that the statement was written with tenancy in mind.
+pub fn is_tenant_scoped(sql: &str) -> bool {
+    sql.to_ascii_lowercase().contains("tenant_id")
+}
+
+#[track_caller]
+fn debug_check(sql: &str) {
+    debug_assert!(
+        is_tenant_scoped(sql),
+        "query does not reference tenant_id: {sql}"
+    );
+}
+
+/// [`sqlx::query`] that asserts (in debug builds) the statement is tenant scoped.
+#[track_caller]
+pub fn query(sql: &str) -> Query<'_, Postgres, PgArguments> {
+    debug_check(sql);
+    sqlx::query(sql)
+}
+
+/// [`sqlx::query_as`] that asserts (in debug builds) the statement is tenant scoped.
+#[track_caller]
+pub fn query_as<'q, O>(sql: &'q str) -> QueryAs<'q, Postgres, O, PgArguments>
+where
+    O: for<'r> FromRow<'r, PgRow>,
+{
+    debug_check(sql);
+    sqlx::query_as(sql)
+}
+
+/// [`sqlx::query_scalar`] that asserts (in debug builds) the statement is tenant scoped.
+#[track_caller]
+pub fn query_scalar<'q, O>(sql: &'q str) -> QueryScalar<'q, Postgres, O, PgArguments>
+where
+    (O,): for<'r> FromRow<'r, PgRow>,
+{
+    debug_check(sql);
+    sqlx::query_scalar(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_check_requires_tenant_column() {
+        assert!(is_tenant_scoped(
+            "SELECT id FROM customers WHERE TENANT_ID = $1"
+        ));
+        assert!(is_tenant_scoped(
+            "INSERT INTO locations (tenant_id, code) VALUES ($1, $2)"
+        ));
+        assert!(!is_tenant_scoped(
+            "SELECT 1 FROM inventory_reservations WHERE order_id = $1"
+        ));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "does not reference tenant_id")]
+    fn unscoped_queries_panic_in_debug_builds() {
+        let _ = query("DELETE FROM customers WHERE id = $1");
+    }
+}
@@ -0,0 +1,565 @@
+//! Cursor pagination, sorting and the list response envelope.
+//!
+//! List handlers take a [`PageRequest`] extractor (`cursor`, `page_size`, `sort`, `direction` query
+//! parameters), resolve it against the endpoint's [`SortSpec`] whitelist into a [`PagePlan`], and
+//! let the plan append the keyset predicate, `ORDER BY` and `LIMIT` to their query. Pages are
+//! keyset based: the opaque cursor carries the sort value and unique key of the last row returned,
+//! so deep pages cost the same as the first and concurrent inserts never shift rows between pages.
+//!
+//! Callers that send neither `cursor` nor `page_size` keep the legacy bare-array response; the
+//! `{ items, next_cursor, total_estimate }` envelope is returned once a client opts in.
+
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use common_http_errors::ApiError;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "asc" | "ascending" => Some(Self::Asc),
+            "desc" | "descending" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+
+    fn after(self) -> &'static str {
+        match self {
+            Self::Asc => ">",
+            Self::Desc => "<",
+        }
+    }
+}
+
+/// A sortable column exposed to clients. `expr` is trusted SQL (never client input) and must be
+/// non-null, e.g. `COALESCE(customer_name, '')`; `sql_type` is the type cursor values are cast to.
+#[derive(Debug)]
+pub struct SortField {
+    pub name: &'static str,
+    pub expr: &'static str,
+    pub sql_type: &'static str,
+}
+
+impl SortField {
+    pub const fn new(name: &'static str, expr: &'static str, sql_type: &'static str) -> Self {
+        Self {
+            name,
+            expr,
+            sql_type,
+        }
+    }
+}
+
+/// Sort whitelist for one endpoint. `tiebreak` names a unique UUID column that orders rows with
+/// equal sort values and anchors the cursor.
+#[derive(Debug)]
+pub struct SortSpec {
+    pub fields: &'static [SortField],
+    pub default_field: &'static str,
+    pub default_direction: SortDirection,
+    pub tiebreak: &'static str,
+}
+
+impl SortSpec {
+    fn field(&self, name: &str) -> Option<&'static SortField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    #[serde(rename = "s")]
+    sort: String,
+    #[serde(rename = "d")]
+    direction: SortDirection,
+    #[serde(rename = "v")]
+    value: String,
+    #[serde(rename = "k")]
+    key: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(value.trim()).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RawPageQuery {
+    cursor: Option<String>,
+    page_size: Option<String>,
+    sort: Option<String>,
+    direction: Option<String>,
+}
+
+/// Pagination and sort parameters of a list request.
+#[derive(Clone, Debug, Default)]
+pub struct PageRequest {
+    cursor: Option<Cursor>,
+    page_size: Option<u32>,
+    sort: Option<String>,
+    direction: Option<SortDirection>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for PageRequest {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(raw) = Query::<RawPageQuery>::try_from_uri(&parts.uri)
+            .map_err(|_| ApiError::bad_request("invalid_query", None))?;
+        Self::from_raw(raw)
+    }
+}
+
+impl PageRequest {
+    fn from_raw(raw: RawPageQuery) -> Result<Self, ApiError> {
+        let non_empty = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+        let cursor = match non_empty(raw.cursor) {
+            Some(value) => Some(Cursor::decode(&value).ok_or(ApiError::BadRequest {
+                code: "invalid_cursor",
+                trace_id: None,
+                message: Some("Cursor is malformed".into()),
+            })?),
+            None => None,
+        };
+        let page_size = match non_empty(raw.page_size) {
+            Some(value) => match value.trim().parse::<i64>() {
+                Ok(size) => Some(checked_page_size(size, "page_size", None)?),
+                Err(_) => {
+                    return Err(ApiError::BadRequest {
+                        code: "invalid_page_size",
+                        trace_id: None,
+                        message: Some(format!("page_size must be between 1 and {MAX_PAGE_SIZE}")),
+                    })
+                }
+            },
+            None => None,
+        };
+        let direction = match non_empty(raw.direction) {
+            Some(value) => Some(SortDirection::parse(&value).ok_or(ApiError::BadRequest {
+                code: "invalid_sort_direction",
+                trace_id: None,
+                message: Some("direction must be asc or desc".into()),
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            cursor,
+            page_size,
+            sort: non_empty(raw.sort).map(|value| value.trim().to_string()),
+            direction,
+        })
+    }
+
+    /// Whether the client asked for the paginated envelope.
+    pub fn is_paginated(&self) -> bool {
+        self.cursor.is_some() || self.page_size.is_some()
+    }
+
+    /// Check the requested sort against `spec`. A cursor carries its own sort, so follow-up
+    /// requests may send just the cursor; an explicit sort that disagrees with it is rejected.
+    pub fn resolve(
+        &self,
+        spec: &'static SortSpec,
+        trace_id: Option<Uuid>,
+    ) -> Result<PagePlan, ApiError> {
+        let requested = self
+            .sort
+            .as_deref()
+            .or(self.cursor.as_ref().map(|cursor| cursor.sort.as_str()))
+            .unwrap_or(spec.default_field);
+        let field = spec.field(requested).ok_or_else(|| ApiError::BadRequest {
+            code: "invalid_sort",
+            trace_id,
+            message: Some(format!(
+                "sort must be one of: {}",
+                spec.fields
+                    .iter()
+                    .map(|field| field.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        })?;
+        let direction = self
+            .direction
+            .or(self.cursor.as_ref().map(|cursor| cursor.direction))
+            .unwrap_or(spec.default_direction);
+        if let Some(cursor) = &self.cursor {
+            if cursor.sort != field.name || cursor.direction != direction {
+                return Err(ApiError::BadRequest {
+                    code: "invalid_cursor",
+                    trace_id,
+                    message: Some("Cursor was issued for a different sort".into()),
+                });
+            }
+        }
+        Ok(PagePlan {
+            field,
+            direction,
+            tiebreak: spec.tiebreak,
+            cursor: self.cursor.clone(),
+            page_size: self
+                .is_paginated()
+                .then(|| self.page_size.unwrap_or(DEFAULT_PAGE_SIZE)),
+        })
+    }
+}
+
+/// Validate a requested page size. Sizes above [`MAX_PAGE_SIZE`] are rejected with
+/// `page_size_too_large` rather than silently clamped, so clients learn to page instead of
+/// assuming they received everything.
+fn checked_page_size(size: i64, param: &str, trace_id: Option<Uuid>) -> Result<u32, ApiError> {
+    if size > i64::from(MAX_PAGE_SIZE) {
+        return Err(ApiError::BadRequest {
+            code: "page_size_too_large",
+            trace_id,
+            message: Some(format!("{param} must be at most {MAX_PAGE_SIZE}; use the cursor to fetch further pages")),
+        });
+    }
+    if size < 1 {
+        return Err(ApiError::BadRequest {
+            code: "invalid_page_size",
+            trace_id,
+            message: Some(format!("{param} must be between 1 and {MAX_PAGE_SIZE}")),
+        });
+    }
+    Ok(size as u32)
+}
+
+/// Resolve an endpoint's legacy `limit` parameter with the same bounds as `page_size`.
+pub fn checked_limit(limit: Option<i64>, trace_id: Option<Uuid>) -> Result<i64, ApiError> {
+    match limit {
+        Some(limit) => checked_page_size(limit, "limit", trace_id).map(i64::from),
+        None => Ok(i64::from(DEFAULT_PAGE_SIZE)),
+    }
+}
+
+/// A validated sort and page position, ready to be applied to a query.
+#[derive(Debug)]
+pub struct PagePlan {
+    field: &'static SortField,
+    direction: SortDirection,
+    tiebreak: &'static str,
+    cursor: Option<Cursor>,
+    page_size: Option<u32>,
+}
+
+impl PagePlan {
+    /// Name of the sort field, as listed in the endpoint's [`SortSpec`].
+    pub fn sort_field(&self) -> &'static str {
+        self.field.name
+    }
+
+    pub fn is_paginated(&self) -> bool {
+        self.page_size.is_some()
+    }
+
+    /// Append ` AND (<sort>, <tiebreak>) > (<cursor>)` (or `<` when descending). Call after the
+    /// tenant and filter predicates of a `WHERE` clause.
+    pub fn push_cursor_filter(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let Some(cursor) = &self.cursor else {
+            return;
+        };
+        builder.push(format_args!(
+            " AND ({}, {}) {} (CAST(",
+            self.field.expr,
+            self.tiebreak,
+            self.direction.after()
+        ));
+        builder.push_bind(cursor.value.clone());
+        builder.push(format_args!(" AS {}), ", self.field.sql_type));
+        builder.push_bind(cursor.key);
+        builder.push(")");
+    }
+
+    pub fn push_order_by(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let direction = self.direction.sql();
+        builder.push(format_args!(
+            " ORDER BY {} {direction}, {} {direction}",
+            self.field.expr, self.tiebreak
+        ));
+    }
+
+    /// Append `LIMIT page_size + 1` for paginated requests (the extra row signals another page).
+    /// Legacy callers apply their own limit.
+    pub fn push_limit(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(size) = self.page_size {
+            builder.push(" LIMIT ");
+            builder.push_bind(i64::from(size) + 1);
+        }
+    }
+
+    /// Shape fetched rows into the response. `key` returns the row's value for the given sort
+    /// field (formatted so it casts back to the field's SQL type) and its tiebreak key.
+    pub fn finish<T>(
+        &self,
+        mut rows: Vec<T>,
+        total_estimate: Option<i64>,
+        key: impl Fn(&T, &str) -> (String, Uuid),
+    ) -> Listing<T> {
+        let Some(size) = self.page_size else {
+            return Listing::Items(rows);
+        };
+        let has_more = rows.len() > size as usize;
+        rows.truncate(size as usize);
+        let next_cursor = match rows.last() {
+            Some(last) if has_more => {
+                let (value, key) = key(last, self.field.name);
+                Some(
+                    Cursor {
+                        sort: self.field.name.to_string(),
+                        direction: self.direction,
+                        value,
+                        key,
+                    }
+                    .encode(),
+                )
+            }
+            _ => None,
+        };
+        Listing::Page(Page {
+            items: rows,
+            next_cursor,
+            total_estimate,
+        })
+    }
+}
+
+/// Standard paginated response body.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_estimate: Option<i64>,
+}
+
+/// List response: a bare array for legacy callers, or a [`Page`] envelope.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    Items(Vec<T>),
+    Page(Page<T>),
+}
+
+impl<T> Listing<T> {
+    /// Drop items after the page boundary was fixed (e.g. by post-decryption filters). The
+    /// cursor is unaffected, so a page may hold fewer than `page_size` items yet have a next page.
+    pub fn retain(&mut self, keep: impl FnMut(&T) -> bool) {
+        match self {
+            Self::Items(items) => items.retain(keep),
+            Self::Page(page) => page.items.retain(keep),
+        }
+    }
+
+    /// Convert every item while keeping the envelope (cursor and estimate) as it was.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Listing<U> {
+        match self {
+            Self::Items(items) => Listing::Items(items.into_iter().map(f).collect()),
+            Self::Page(page) => Listing::Page(Page {
+                items: page.items.into_iter().map(f).collect(),
+                next_cursor: page.next_cursor,
+                total_estimate: page.total_estimate,
+            }),
+        }
+    }
+}
+
+/// Start a query for [`estimate_rows`]: `select` should be the list query's `SELECT ... WHERE`
+/// prefix, to which the caller appends the same filters (without cursor, order or limit).
+pub fn estimate_query(select: &str) -> QueryBuilder<'_, Postgres> {
+    QueryBuilder::new(format!("EXPLAIN (FORMAT JSON) {select}"))
+}
+
+/// Planner row estimate for a query built with [`estimate_query`]. Cheap regardless of table
+/// size, but only as accurate as the table statistics.
+pub async fn estimate_rows<'e, E>(
+    executor: E,
+    mut builder: QueryBuilder<'_, Postgres>,
+) -> Result<i64, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let plan: serde_json::Value = builder.build_query_scalar().fetch_one(executor).await?;
+    Ok(plan
+        .pointer("/0/Plan/Plan Rows")
+        .and_then(serde_json::Value::as_f64)
+        .map(|rows| rows.round() as i64)
+        .unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SPEC: SortSpec = SortSpec {
+        fields: &[
+            SortField::new("created_at", "created_at", "timestamptz"),
+            SortField::new("name", "COALESCE(name, '')", "text"),
+        ],
+        default_field: "created_at",
+        default_direction: SortDirection::Desc,
+        tiebreak: "id",
+    };
+
+    fn request(query: &str) -> Result<PageRequest, ApiError> {
+        let uri: axum::http::Uri = format!("/items?{query}").parse().unwrap();
+        PageRequest::from_raw(Query::<RawPageQuery>::try_from_uri(&uri).unwrap().0)
+    }
+
+    #[test]
+    fn legacy_requests_are_not_paginated() {
+        let plan = request("sort=name").unwrap().resolve(&SPEC, None).unwrap();
+        assert!(!plan.is_paginated());
+        let mut builder = QueryBuilder::new("SELECT id FROM items WHERE tenant_id = ");
+        builder.push_bind(Uuid::nil());
+        plan.push_cursor_filter(&mut builder);
+        plan.push_order_by(&mut builder);
+        plan.push_limit(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT id FROM items WHERE tenant_id = $1 ORDER BY COALESCE(name, '') DESC, id DESC"
+        );
+        assert!(
+            matches!(plan.finish(vec![1, 2], None, |_, _| unreachable!()), Listing::Items(items) if items == vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn unknown_sorts_and_bad_parameters_are_rejected() {
+        let code = |result: Result<PagePlan, ApiError>| match result {
+            Err(ApiError::BadRequest { code, .. }) => code,
+            other => panic!("expected bad request, got {other:?}"),
+        };
+        assert_eq!(
+            code(request("sort=password_hash").unwrap().resolve(&SPEC, None)),
+            "invalid_sort"
+        );
+        assert!(matches!(
+            request("page_size=0"),
+            Err(ApiError::BadRequest {
+                code: "invalid_page_size",
+                ..
+            })
+        ));
+        assert!(matches!(
+            request("direction=sideways"),
+            Err(ApiError::BadRequest {
+                code: "invalid_sort_direction",
+                ..
+            })
+        ));
+        assert!(matches!(
+            request("cursor=not-a-cursor"),
+            Err(ApiError::BadRequest {
+                code: "invalid_cursor",
+                ..
+            })
+        ));
+        assert!(matches!(
+            request("page_size=5000"),
+            Err(ApiError::BadRequest {
+                code: "page_size_too_large",
+                ..
+            })
+        ));
+        assert_eq!(
+            request(&format!("page_size={MAX_PAGE_SIZE}")).unwrap().page_size,
+            Some(MAX_PAGE_SIZE)
+        );
+    }
+
+    #[test]
+    fn legacy_limits_share_page_size_bounds() {
+        assert_eq!(checked_limit(None, None).unwrap(), i64::from(DEFAULT_PAGE_SIZE));
+        assert_eq!(checked_limit(Some(5), None).unwrap(), 5);
+        assert!(matches!(
+            checked_limit(Some(i64::from(MAX_PAGE_SIZE) + 1), None),
+            Err(ApiError::BadRequest {
+                code: "page_size_too_large",
+                ..
+            })
+        ));
+        assert!(matches!(
+            checked_limit(Some(0), None),
+            Err(ApiError::BadRequest {
+                code: "invalid_page_size",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn cursor_round_trips_into_keyset_predicate() {
+        let first = request("page_size=2&sort=name&direction=asc")
+            .unwrap()
+            .resolve(&SPEC, None)
+            .unwrap();
+        let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let Listing::Page(page) = first.finish(vec!["a", "b", "c"], Some(3), |row, field| {
+            assert_eq!(field, "name");
+            (row.to_string(), ids[if *row == "a" { 0 } else { 1 }])
+        }) else {
+            panic!("expected envelope");
+        };
+        assert_eq!(page.items, vec!["a", "b"]);
+        assert_eq!(page.total_estimate, Some(3));
+        let cursor = page.next_cursor.expect("next cursor");
+
+        let next = request(&format!("cursor={cursor}"))
+            .unwrap()
+            .resolve(&SPEC, None)
+            .unwrap();
+        assert_eq!(next.sort_field(), "name");
+        let mut builder = QueryBuilder::new("SELECT id FROM items WHERE tenant_id = ");
+        builder.push_bind(Uuid::nil());
+        next.push_cursor_filter(&mut builder);
+        next.push_order_by(&mut builder);
+        next.push_limit(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT id FROM items WHERE tenant_id = $1 AND (COALESCE(name, ''), id) > (CAST($2 AS text), $3) ORDER BY COALESCE(name, '') ASC, id ASC LIMIT $4"
+        );
+
+        assert!(matches!(
+            request(&format!("cursor={cursor}&sort=created_at"))
+                .unwrap()
+                .resolve(&SPEC, None),
+            Err(ApiError::BadRequest {
+                code: "invalid_cursor",
+                ..
+            })
+        ));
+        let Listing::Page(last) = next.finish(vec!["c"], None, |_, _| unreachable!()) else {
+            panic!("expected envelope");
+        };
+        assert_eq!(last.next_cursor, None);
+    }
+}
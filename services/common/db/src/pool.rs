@@ -0,0 +1,250 @@
+//! Connection pool sizing and saturation metrics.
+//!
+//! Services read [`PoolSettings`] with the rest of their startup config and open the primary pool
+//! with [`PoolSettings::connect`]. [`register_metrics`] adds the `db_pool_*` series to a service's
+//! registry and [`spawn_pool_gauges`] keeps the connection gauges current. When every connection
+//! stays busy for the acquire timeout sqlx returns [`sqlx::Error::PoolTimedOut`]; [`db_error`] turns
+//! that into a 503 `db_pool_exhausted` with `Retry-After` instead of an anonymous 500.
+
+use common_config::EnvReader;
+use common_http_errors::ApiError;
+use once_cell::sync::Lazy;
+use prometheus::{HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry};
+use serde::Serialize;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Label of the pool every service opens from `DATABASE_URL`.
+pub const PRIMARY_POOL: &str = "primary";
+/// `Retry-After` sent with `db_pool_exhausted`.
+pub const POOL_EXHAUSTED_RETRY_AFTER_SECS: u64 = 1;
+const GAUGE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pool sizing and timeouts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing with `db_pool_exhausted`.
+    pub acquire_timeout_secs: u64,
+    /// Idle connections above `min_connections` are closed after this long.
+    pub idle_timeout_secs: u64,
+    /// Server-side `statement_timeout`; unset keeps the database default.
+    pub statement_timeout_ms: Option<u64>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 10,
+            idle_timeout_secs: 600,
+            statement_timeout_ms: None,
+        }
+    }
+}
+
+impl PoolSettings {
+    /// Read `DB_MAX_CONNECTIONS` (default 10), `DB_MIN_CONNECTIONS` (0), `DB_ACQUIRE_TIMEOUT_SECS`
+    /// (10), `DB_IDLE_TIMEOUT_SECS` (600) and `DB_STATEMENT_TIMEOUT_MS`.
+    pub fn read(env: &mut EnvReader) -> Self {
+        let defaults = Self::default();
+        let max_connections = env.or("DB_MAX_CONNECTIONS", defaults.max_connections);
+        env.check("DB_MAX_CONNECTIONS", max_connections > 0, "must be greater than 0");
+        let min_connections = env.or("DB_MIN_CONNECTIONS", defaults.min_connections);
+        env.check(
+            "DB_MIN_CONNECTIONS",
+            min_connections <= max_connections,
+            "must not exceed DB_MAX_CONNECTIONS",
+        );
+        let acquire_timeout_secs = env.or("DB_ACQUIRE_TIMEOUT_SECS", defaults.acquire_timeout_secs);
+        env.check("DB_ACQUIRE_TIMEOUT_SECS", acquire_timeout_secs > 0, "must be greater than 0");
+        let idle_timeout_secs = env.or("DB_IDLE_TIMEOUT_SECS", defaults.idle_timeout_secs);
+        let statement_timeout_ms = env.optional("DB_STATEMENT_TIMEOUT_MS");
+        env.check("DB_STATEMENT_TIMEOUT_MS", statement_timeout_ms != Some(0), "must be greater than 0");
+        Self { max_connections, min_connections, acquire_timeout_secs, idle_timeout_secs, statement_timeout_ms }
+    }
+
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    /// `url` with the statement timeout applied as a session setting.
+    pub fn connect_options(&self, url: &str) -> Result<PgConnectOptions, sqlx::Error> {
+        let options = PgConnectOptions::from_str(url)?;
+        Ok(match self.statement_timeout_ms {
+            Some(ms) => options.options([("statement_timeout", ms)]),
+            None => options,
+        })
+    }
+
+    pub async fn connect(&self, url: &str) -> Result<PgPool, sqlx::Error> {
+        let pool = self.pool_options().connect_with(self.connect_options(url)?).await?;
+        METRICS.max_connections.with_label_values(&[PRIMARY_POOL]).set(i64::from(self.max_connections));
+        Ok(pool)
+    }
+
+    /// Pool whose sessions run as [`crate::CROSS_TENANT_ROLE`], for background work across tenants.
+    /// Connects lazily and never holds more than a quarter of `max_connections`.
+    pub fn connect_cross_tenant(&self, url: &str) -> Result<PgPool, sqlx::Error> {
+        let options = cross_tenant_options(self.connect_options(url)?);
+        Ok(self
+            .pool_options()
+            .max_connections((self.max_connections / 4).max(1))
+            .min_connections(0)
+            .connect_lazy_with(options))
+    }
+}
+
+/// `options` with the session role set to [`crate::CROSS_TENANT_ROLE`]; for maintenance binaries
+/// that open their own connection.
+pub fn cross_tenant_options(options: PgConnectOptions) -> PgConnectOptions {
+    options.options([("role", crate::CROSS_TENANT_ROLE)])
+}
+
+struct PoolMetrics {
+    connections: IntGaugeVec,
+    max_connections: IntGaugeVec,
+    acquire_waits: IntCounterVec,
+    acquire_wait_seconds: HistogramVec,
+    acquire_timeouts: IntCounter,
+}
+
+static METRICS: Lazy<PoolMetrics> = Lazy::new(|| PoolMetrics {
+    connections: IntGaugeVec::new(
+        Opts::new("db_pool_connections", "Open pool connections by state (in_use, idle)"),
+        &["pool", "state"],
+    )
+    .expect("db_pool_connections"),
+    max_connections: IntGaugeVec::new(
+        Opts::new("db_pool_max_connections", "Configured pool size"),
+        &["pool"],
+    )
+    .expect("db_pool_max_connections"),
+    acquire_waits: IntCounterVec::new(
+        Opts::new(
+            "db_pool_acquire_waits_total",
+            "Tenant transactions that found no idle connection and had to wait",
+        ),
+        &["pool"],
+    )
+    .expect("db_pool_acquire_waits_total"),
+    acquire_wait_seconds: HistogramVec::new(
+        HistogramOpts::new("db_pool_acquire_wait_seconds", "Time to obtain a connection for a tenant transaction")
+            .buckets(vec![0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]),
+        &["pool"],
+    )
+    .expect("db_pool_acquire_wait_seconds"),
+    acquire_timeouts: IntCounter::new(
+        "db_pool_acquire_timeouts_total",
+        "Requests rejected with db_pool_exhausted after the acquire timeout",
+    )
+    .expect("db_pool_acquire_timeouts_total"),
+});
+
+/// Add the `db_pool_*` series to `registry`. Safe to call for several registries.
+pub fn register_metrics(registry: &Registry) {
+    let _ = registry.register(Box::new(METRICS.connections.clone()));
+    let _ = registry.register(Box::new(METRICS.max_connections.clone()));
+    let _ = registry.register(Box::new(METRICS.acquire_waits.clone()));
+    let _ = registry.register(Box::new(METRICS.acquire_wait_seconds.clone()));
+    let _ = registry.register(Box::new(METRICS.acquire_timeouts.clone()));
+}
+
+/// Refresh `db_pool_connections` for `pool` every few seconds.
+pub fn spawn_pool_gauges(name: &'static str, pool: PgPool) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(GAUGE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            record_connections(name, &pool);
+        }
+    });
+}
+
+fn record_connections(name: &str, pool: &PgPool) {
+    let idle = pool.num_idle() as i64;
+    let in_use = i64::from(pool.size()) - idle;
+    METRICS.connections.with_label_values(&[name, "idle"]).set(idle);
+    METRICS.connections.with_label_values(&[name, "in_use"]).set(in_use.max(0));
+}
+
+/// Times a connection acquisition; see [`crate::TenantScopedPool::begin`].
+pub(crate) struct AcquireTimer {
+    started: Instant,
+    waited: bool,
+}
+
+impl AcquireTimer {
+    pub(crate) fn start(pool: &PgPool) -> Self {
+        Self { started: Instant::now(), waited: pool.num_idle() == 0 }
+    }
+
+    pub(crate) fn finish(self, name: &str) {
+        METRICS.acquire_wait_seconds.with_label_values(&[name]).observe(self.started.elapsed().as_secs_f64());
+        if self.waited {
+            METRICS.acquire_waits.with_label_values(&[name]).inc();
+        }
+    }
+}
+
+/// Map a database error for a handler response: an acquire timeout is a retryable 503, anything
+/// else a 500.
+pub fn db_error(err: sqlx::Error, trace_id: Option<Uuid>) -> ApiError {
+    match err {
+        sqlx::Error::PoolTimedOut => {
+            METRICS.acquire_timeouts.inc();
+            tracing::warn!(?trace_id, "No database connection available within the acquire timeout");
+            ApiError::ServiceUnavailable {
+                code: "db_pool_exhausted",
+                trace_id,
+                retry_after_secs: POOL_EXHAUSTED_RETRY_AFTER_SECS,
+            }
+        }
+        other => ApiError::internal(other, trace_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use std::collections::HashMap;
+
+    fn reader(vars: &[(&str, &str)]) -> EnvReader {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        EnvReader::from_lookup(move |key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn settings_default_and_reject_inconsistent_sizes() {
+        let mut env = reader(&[]);
+        let settings = PoolSettings::read(&mut env);
+        assert_eq!(env.finish(|| Some(settings)).unwrap(), PoolSettings::default());
+
+        let mut env = reader(&[("DB_MAX_CONNECTIONS", "4"), ("DB_MIN_CONNECTIONS", "8"), ("DB_STATEMENT_TIMEOUT_MS", "0")]);
+        PoolSettings::read(&mut env);
+        let err = env.finish(|| Some(())).unwrap_err();
+        let keys: Vec<&str> = err.issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["DB_MIN_CONNECTIONS", "DB_STATEMENT_TIMEOUT_MS"]);
+    }
+
+    #[test]
+    fn pool_timeout_is_a_retryable_503() {
+        let resp = db_error(sqlx::Error::PoolTimedOut, None).into_response();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "db_pool_exhausted");
+        assert_eq!(resp.headers().get("Retry-After").unwrap(), "1");
+        let resp = db_error(sqlx::Error::RowNotFound, None).into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,194 @@
+//! Read-replica routing.
+//!
+//! Services hold a [`WritePool`] for the primary and a [`ReadPool`] that serves read-only handlers
+//! from `DATABASE_REPLICA_URL` when one is configured. The read pool falls back to the primary
+//! whenever the replica's replay lag exceeds the configured bound or the lag cannot be measured,
+//! so a lagging or unreachable replica degrades to primary load rather than stale or failed reads.
+
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Connection string of the read replica; unset means every read goes to the primary.
+pub const REPLICA_URL_ENV: &str = "DATABASE_REPLICA_URL";
+/// Maximum tolerated replay lag in seconds before reads fall back to the primary.
+pub const REPLICA_MAX_LAG_ENV: &str = "DATABASE_REPLICA_MAX_LAG_SECS";
+pub const DEFAULT_REPLICA_MAX_LAG: Duration = Duration::from_secs(5);
+/// How long a lag measurement is reused before the replica is probed again.
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long reads stay on the primary after the replica could not be reached.
+const REPLICA_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Replica connections are given up on quickly: reads fall back to the primary instead of waiting
+/// out sqlx's 30 second default.
+const REPLICA_ACQUIRE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Replay lag in seconds, NULL when run against a primary. A replica with no WAL receiver
+/// streaming from upstream reports infinite lag: once cut off, its receive and replay positions
+/// match and it would otherwise look fully caught up. While streaming, zero means it has applied
+/// everything it received (an idle primary would otherwise make `pg_last_xact_replay_timestamp`
+/// look arbitrarily old). `status` is NULL for roles without `pg_read_all_stats`; the row itself
+/// still shows that a receiver is running.
+const REPLICA_LAG_SQL: &str = "SELECT CASE
+        WHEN NOT pg_is_in_recovery() THEN NULL
+        WHEN NOT EXISTS (
+            SELECT 1 FROM pg_stat_wal_receiver WHERE status IS NULL OR status = 'streaming'
+        ) THEN 'Infinity'
+        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+        ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())
+    END::float8";
+
+/// Lazy pool for the replica with a short acquire timeout.
+fn replica_pool(url: &str) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .acquire_timeout(REPLICA_ACQUIRE_TIMEOUT)
+        .connect_lazy(url)
+}
+
+/// Handle to the primary, for statements that write or must observe their own writes.
+#[derive(Clone, Debug)]
+pub struct WritePool(PgPool);
+
+impl WritePool {
+    pub fn new(pool: PgPool) -> Self {
+        Self(pool)
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.0
+    }
+}
+
+impl Deref for WritePool {
+    type Target = PgPool;
+
+    fn deref(&self) -> &PgPool {
+        &self.0
+    }
+}
+
+impl From<PgPool> for WritePool {
+    fn from(pool: PgPool) -> Self {
+        Self::new(pool)
+    }
+}
+
+/// Handle for read-only queries that tolerate bounded staleness.
+#[derive(Clone, Debug)]
+pub struct ReadPool {
+    primary: PgPool,
+    replica: Option<PgPool>,
+    max_lag: Duration,
+    /// Last verdict and when it expires.
+    last_check: Arc<Mutex<Option<(Instant, bool)>>>,
+}
+
+impl ReadPool {
+    pub fn new(primary: PgPool, replica: Option<PgPool>, max_lag: Duration) -> Self {
+        Self {
+            primary,
+            replica,
+            max_lag,
+            last_check: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Read pool without a replica; every read goes to `primary`.
+    pub fn primary_only(primary: PgPool) -> Self {
+        Self::new(primary, None, DEFAULT_REPLICA_MAX_LAG)
+    }
+
+    /// Build from `DATABASE_REPLICA_URL` (or `DATABASE_REPLICA_URL_FILE`) /
+    /// `DATABASE_REPLICA_MAX_LAG_SECS`. The replica pool connects lazily, so an unreachable
+    /// replica does not prevent startup, and gives up on connections after
+    /// `REPLICA_ACQUIRE_TIMEOUT`.
+    pub fn from_env(primary: PgPool) -> Result<Self, sqlx::Error> {
+        let url = common_config::read_secret(REPLICA_URL_ENV)
+            .map_err(|err| sqlx::Error::Configuration(err.into()))?;
+        let replica = match url {
+            Some(url) if !url.trim().is_empty() => Some(replica_pool(url.trim())?),
+            _ => None,
+        };
+        let max_lag = std::env::var(REPLICA_MAX_LAG_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_REPLICA_MAX_LAG);
+        Ok(Self::new(primary, replica, max_lag))
+    }
+
+    pub fn has_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /// The primary, for reads that must not be stale (e.g. immediately after a write).
+    pub fn primary(&self) -> &PgPool {
+        &self.primary
+    }
+
+    /// Pool to run a read on: the replica while its lag is within bounds, otherwise the primary.
+    pub async fn get(&self) -> &PgPool {
+        match &self.replica {
+            Some(replica) if self.replica_is_fresh(replica).await => replica,
+            _ => &self.primary,
+        }
+    }
+
+    async fn replica_is_fresh(&self, replica: &PgPool) -> bool {
+        if let Some((valid_until, fresh)) = *self.last_check.lock().expect("lag cache poisoned") {
+            if Instant::now() < valid_until {
+                return fresh;
+            }
+        }
+        let (fresh, recheck_in) = match sqlx::query_scalar::<_, Option<f64>>(REPLICA_LAG_SQL)
+            .fetch_one(replica)
+            .await
+        {
+            Ok(Some(lag)) => (lag <= self.max_lag.as_secs_f64(), LAG_CHECK_INTERVAL),
+            // Not in recovery: the "replica" URL points at a primary, which is never stale.
+            Ok(None) => (true, LAG_CHECK_INTERVAL),
+            Err(err) => {
+                tracing::warn!(error = %err, "Replica lag check failed; reading from primary");
+                (false, REPLICA_RETRY_INTERVAL)
+            }
+        };
+        if !fresh {
+            tracing::debug!(
+                max_lag_secs = self.max_lag.as_secs(),
+                "Replica lag above bound; reading from primary"
+            );
+        }
+        *self.last_check.lock().expect("lag cache poisoned") =
+            Some((Instant::now() + recheck_in, fresh));
+        fresh
+    }
+}
+
+impl From<PgPool> for ReadPool {
+    fn from(pool: PgPool) -> Self {
+        Self::primary_only(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreachable_replica_falls_back_to_primary() {
+        // Built like `from_env` builds it. 192.0.2.1 (TEST-NET-1) never answers, so without a
+        // short acquire timeout the first read would wait out the pool default.
+        let primary = replica_pool("postgres://nobody@127.0.0.1:1/none").expect("lazy pool");
+        let replica = replica_pool("postgres://nobody@192.0.2.1:5432/none").expect("lazy pool");
+        let read = ReadPool::new(primary, Some(replica), DEFAULT_REPLICA_MAX_LAG);
+        assert!(read.has_replica());
+        let started = Instant::now();
+        assert!(std::ptr::eq(read.get().await, read.primary()));
+        assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+        // The failure is remembered, so the next read does not probe again.
+        let started = Instant::now();
+        assert!(std::ptr::eq(read.get().await, read.primary()));
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,233 @@
+//! Per-tenant data residency.
+//!
+//! A tenant may be pinned to a region (the `residency` claim, forwarded to services as
+//! `X-Residency`). Services that store such a tenant's rows hold a [`RegionalPools`]: the primary
+//! `DATABASE_URL` plus one pool per additional region listed in `DB_REGIONS`. Each request is routed
+//! to the pool of its tenant's region, and a tenant pinned to a region this deployment does not
+//! serve is refused with `residency_region_unavailable` instead of falling back to the primary.
+
+use crate::{PoolSettings, TenantScopedPool};
+use common_config::{EnvReader, Redacted};
+use common_http_errors::ApiError;
+use common_security::SecurityContext;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Region the primary `DATABASE_URL` stores data in; unset for untagged deployments.
+pub const HOME_REGION_ENV: &str = "DB_REGION";
+/// Comma-separated list of additional regions, each with its own `DATABASE_URL_<REGION>`.
+pub const REGIONS_ENV: &str = "DB_REGIONS";
+
+/// Region codes are short lowercase identifiers such as `eu` or `us-east`.
+pub fn is_valid_region(region: &str) -> bool {
+    (2..=16).contains(&region.len())
+        && region
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Trim and lowercase a region code from a claim, header or setting.
+pub fn normalize_region(region: &str) -> String {
+    region.trim().to_ascii_lowercase()
+}
+
+/// Environment variable holding the connection string of `region`'s database.
+pub fn region_url_env(region: &str) -> String {
+    format!("DATABASE_URL_{}", region.to_ascii_uppercase().replace('-', "_"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionDatabase {
+    pub region: String,
+    pub database_url: Redacted<String>,
+}
+
+/// Which regions this deployment serves.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RegionSettings {
+    pub home_region: Option<String>,
+    pub regions: Vec<RegionDatabase>,
+}
+
+impl RegionSettings {
+    /// Read `DB_REGION`, `DB_REGIONS` and a `DATABASE_URL_<REGION>` secret per listed region.
+    pub async fn read(env: &mut EnvReader) -> Self {
+        let home_region = env
+            .optional::<String>(HOME_REGION_ENV)
+            .map(|region| normalize_region(&region));
+        if let Some(region) = &home_region {
+            env.check(
+                HOME_REGION_ENV,
+                is_valid_region(region),
+                "must be a region code such as `eu` or `us-east`",
+            );
+        }
+        let listed = env.optional::<String>(REGIONS_ENV).unwrap_or_default();
+        let mut regions: Vec<RegionDatabase> = Vec::new();
+        for region in listed.split(',').map(normalize_region).filter(|r| !r.is_empty()) {
+            if !is_valid_region(&region) {
+                env.invalid(REGIONS_ENV, format!("`{region}` is not a valid region code"));
+                continue;
+            }
+            if home_region.as_deref() == Some(region.as_str())
+                || regions.iter().any(|db| db.region == region)
+            {
+                env.invalid(REGIONS_ENV, format!("region `{region}` is listed more than once"));
+                continue;
+            }
+            if let Some(database_url) = env.required_secret(&region_url_env(&region)).await {
+                regions.push(RegionDatabase { region, database_url });
+            }
+        }
+        Self { home_region, regions }
+    }
+}
+
+/// Refusal to store a tenant's data outside its region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResidencyUnavailable {
+    pub region: String,
+}
+
+impl From<ResidencyUnavailable> for ApiError {
+    fn from(err: ResidencyUnavailable) -> Self {
+        ApiError::ForbiddenCode {
+            code: "residency_region_unavailable",
+            trace_id: None,
+            message: Some(format!(
+                "Tenant data must be stored in region `{}`, which this deployment does not serve",
+                err.region
+            )),
+        }
+    }
+}
+
+/// Tenant-scoped pools keyed by region.
+#[derive(Clone, Debug)]
+pub struct RegionalPools {
+    home: TenantScopedPool,
+    home_region: Option<String>,
+    regions: HashMap<String, TenantScopedPool>,
+}
+
+impl RegionalPools {
+    pub fn new(home: TenantScopedPool, home_region: Option<String>) -> Self {
+        Self { home, home_region, regions: HashMap::new() }
+    }
+
+    /// A deployment with only the primary database; pinned tenants are refused.
+    pub fn single(home: TenantScopedPool) -> Self {
+        Self::new(home, None)
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>, pool: TenantScopedPool) -> Self {
+        self.regions.insert(region.into(), pool);
+        self
+    }
+
+    /// Wrap the primary pool and connect every regional database in `settings`.
+    pub async fn connect(
+        home: PgPool,
+        settings: &RegionSettings,
+        pool_settings: &PoolSettings,
+    ) -> Result<Self, sqlx::Error> {
+        let mut pools = Self::new(TenantScopedPool::new(home), settings.home_region.clone());
+        for db in &settings.regions {
+            let pool = pool_settings.connect(db.database_url.expose()).await?;
+            pools = pools.with_region(db.region.clone(), TenantScopedPool::new(pool));
+        }
+        Ok(pools)
+    }
+
+    /// The primary pool, for tenants without a residency requirement.
+    pub fn home(&self) -> &TenantScopedPool {
+        &self.home
+    }
+
+    /// Every database this deployment serves, the primary first.
+    pub fn pools(&self) -> impl Iterator<Item = &TenantScopedPool> {
+        std::iter::once(&self.home).chain(self.regions.values())
+    }
+
+    /// Pool holding data for `residency`; unpinned tenants use the primary.
+    pub fn route(&self, residency: Option<&str>) -> Result<&TenantScopedPool, ResidencyUnavailable> {
+        let Some(region) = residency else {
+            return Ok(&self.home);
+        };
+        if self.home_region.as_deref() == Some(region) {
+            return Ok(&self.home);
+        }
+        self.regions
+            .get(region)
+            .ok_or_else(|| ResidencyUnavailable { region: region.to_string() })
+    }
+
+    /// Pool for the tenant of an authenticated request.
+    pub fn for_tenant(&self, sec: &SecurityContext) -> Result<&TenantScopedPool, ApiError> {
+        self.route(sec.residency.as_deref()).map_err(|err| {
+            warn!(tenant_id = %sec.tenant_id, region = %err.region, "refusing request for tenant pinned to an unserved region");
+            ApiError::from(err)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn lazy_pool() -> TenantScopedPool {
+        TenantScopedPool::new(
+            PgPoolOptions::new()
+                .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+                .expect("lazy pool"),
+        )
+    }
+
+    #[tokio::test]
+    async fn tenants_are_routed_to_their_region() {
+        let pools = RegionalPools::new(lazy_pool(), Some("us".into())).with_region("eu", lazy_pool());
+        assert!(std::ptr::eq(pools.route(None).unwrap(), pools.home()));
+        assert!(std::ptr::eq(pools.route(Some("us")).unwrap(), pools.home()));
+        let eu = pools.route(Some("eu")).unwrap();
+        assert!(std::ptr::eq(eu, &pools.regions["eu"]));
+        assert_eq!(
+            pools.route(Some("apac")).unwrap_err(),
+            ResidencyUnavailable { region: "apac".into() }
+        );
+    }
+
+    #[tokio::test]
+    async fn untagged_deployments_refuse_pinned_tenants() {
+        let pools = RegionalPools::single(lazy_pool());
+        assert!(pools.route(None).is_ok());
+        assert!(pools.route(Some("eu")).is_err());
+    }
+
+    #[test]
+    fn region_codes_are_validated() {
+        assert!(is_valid_region("eu"));
+        assert!(is_valid_region("us-east-2"));
+        assert!(!is_valid_region("e"));
+        assert!(!is_valid_region("EU"));
+        assert!(!is_valid_region("eu west"));
+        assert_eq!(region_url_env("us-east"), "DATABASE_URL_US_EAST");
+    }
+
+    #[tokio::test]
+    async fn invalid_region_settings_are_reported() {
+        let mut env = EnvReader::from_lookup(|key| match key {
+            "DB_REGION" => Some("EU".into()),
+            "DB_REGIONS" => Some("eu, not a region".into()),
+            _ => None,
+        });
+        let settings = RegionSettings::read(&mut env).await;
+        assert_eq!(settings.home_region.as_deref(), Some("eu"));
+        assert!(settings.regions.is_empty());
+        let err = env.finish(|| Some(())).unwrap_err().to_string();
+        assert!(err.contains("listed more than once"), "{err}");
+        assert!(err.contains("`not a region` is not a valid region code"), "{err}");
+    }
+}
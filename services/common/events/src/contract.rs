@@ -0,0 +1,96 @@
+//! Golden payloads shared by the producer and consumer contract tests.
+//!
+//! Each topic has a directory under `contracts/` with one JSON file per payload shape. Producers
+//! pin what they publish with [`assert_golden`]. Consumers run their decoding and handler logic
+//! over [`fixtures`] for every topic they subscribe to. A change to a golden payload fails its
+//! producer test, and `UPDATE_CONTRACTS=1 cargo test -p common-events` rewrites the file. After
+//! that, every consumer runs its tests against the new shape.
+//!
+//! Files named `v1_*` were captured from producers that predate `schema_version`. No producer
+//! writes them any more, and they are never regenerated. Consumers must keep reading them
+//! because they are still in the topic's retention and in replays.
+
+use crate::{to_value, DomainEvent};
+use serde_json::Value;
+
+/// One payload shape published on `topic`.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub topic: &'static str,
+    pub name: &'static str,
+    pub payload: &'static str,
+}
+
+impl Fixture {
+    /// Whether this is a frozen payload from before versioning rather than a producer's golden file.
+    pub fn is_legacy(&self) -> bool {
+        self.name.starts_with("v1_")
+    }
+
+    /// Decode with the topic's payload type, panicking with the fixture's path on failure.
+    pub fn decode<E: DomainEvent>(&self) -> E {
+        assert_eq!(E::TOPIC, self.topic, "fixture {} decoded as a {} payload", self.path(), E::TOPIC);
+        crate::decode(self.payload).unwrap_or_else(|err| panic!("contract {} no longer decodes: {err}", self.path()))
+    }
+
+    pub fn path(&self) -> String {
+        format!("contracts/{}/{}.json", self.topic, self.name)
+    }
+}
+
+macro_rules! fixture {
+    ($topic:literal, $name:literal) => {
+        Fixture { topic: $topic, name: $name, payload: include_str!(concat!("../contracts/", $topic, "/", $name, ".json")) }
+    };
+}
+
+/// Every registered fixture. A file under `contracts/` that is missing here fails this crate's tests.
+pub const FIXTURES: &[Fixture] = &[
+    fixture!("order.completed", "sale"),
+    fixture!("order.completed", "weighed_sale"),
+    fixture!("order.completed", "exchange_sale"),
+    fixture!("order.completed", "refund"),
+    fixture!("order.completed", "rma_refund"),
+    fixture!("order.completed", "v1_legacy_sale"),
+    fixture!("inventory.cogs.recorded", "sale"),
+    fixture!("inventory.cogs.recorded", "refund"),
+    fixture!("product.merged", "merge"),
+    fixture!("customer.erased", "erasure"),
+];
+
+/// Fixtures published on `topic`.
+pub fn fixtures(topic: &str) -> impl Iterator<Item = &'static Fixture> + '_ {
+    FIXTURES.iter().filter(move |fixture| fixture.topic == topic)
+}
+
+pub fn fixture(topic: &str, name: &str) -> &'static Fixture {
+    fixtures(topic).find(|fixture| fixture.name == name).unwrap_or_else(|| panic!("no contract fixture {topic}/{name}"))
+}
+
+/// Every fixture of `E::TOPIC`, decoded, with its name.
+pub fn decode_all<E: DomainEvent>() -> Vec<(&'static str, E)> {
+    let decoded: Vec<_> = fixtures(E::TOPIC).map(|fixture| (fixture.name, fixture.decode())).collect();
+    assert!(!decoded.is_empty(), "no contract fixtures for {}", E::TOPIC);
+    decoded
+}
+
+/// Fails when `event` no longer encodes to the golden payload `name` of its topic. Set
+/// `UPDATE_CONTRACTS=1` to rewrite the file instead, once the change is known to be compatible.
+pub fn assert_golden<E: DomainEvent>(name: &str, event: &E) {
+    let fixture = fixture(E::TOPIC, name);
+    assert!(!fixture.is_legacy(), "{} is frozen; add a new fixture instead", fixture.path());
+    let encoded = to_value(event).expect("encode event");
+    if std::env::var("UPDATE_CONTRACTS").as_deref() == Ok("1") {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(fixture.path());
+        let pretty = serde_json::to_string_pretty(&encoded).expect("format contract");
+        std::fs::write(&path, pretty + "\n").unwrap_or_else(|err| panic!("write {}: {err}", path.display()));
+        return;
+    }
+    let golden: Value = serde_json::from_str(fixture.payload).unwrap_or_else(|err| panic!("{} is not JSON: {err}", fixture.path()));
+    assert_eq!(
+        encoded,
+        golden,
+        "{} payload changed. If every consumer still reads it, regenerate with UPDATE_CONTRACTS=1; otherwise publish the change on a new topic",
+        fixture.path()
+    );
+}
@@ -0,0 +1,22 @@
+//! Events published by customer-service.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+/// `customer.erased`: a customer's personal data was deleted on a GDPR request. Services holding
+/// per-customer data (analytics aggregates, marketing lists) drop it and must not rebuild it from
+/// later or replayed events. Consumers must be idempotent, as the erasure can be redelivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomerErasedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub customer_id: Uuid,
+    /// The `gdpr_tombstones` row recording the request.
+    pub tombstone_id: Uuid,
+    pub erased_at: DateTime<Utc>,
+}
+domain_event!(CustomerErasedEvent, topics::CUSTOMER_ERASED, 1, customer_id);
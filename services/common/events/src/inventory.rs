@@ -0,0 +1,129 @@
+//! Events published by inventory-service.
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+/// `inventory.low_stock`: on-hand stock crossed down to (or below) the product's threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryLowStockEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub threshold: i32,
+}
+domain_event!(InventoryLowStockEvent, topics::INVENTORY_LOW_STOCK, 1, product_id);
+
+/// `inventory.reservation.expired`: the sweeper released a reservation line past its TTL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReservationExpiredEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub order_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub expired_at_epoch: u64,
+}
+domain_event!(ReservationExpiredEvent, topics::RESERVATION_EXPIRED, 1, order_id);
+
+/// `inventory.adjusted`: stock was received or set directly through the correction API rather
+/// than by an order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryAdjustedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    /// Set in multi-location mode; absent for legacy single-row stock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub adjustment_id: Uuid,
+    /// `receive` or `set_quantity`.
+    pub kind: String,
+    pub reason_code: String,
+    pub previous_quantity: i32,
+    pub new_quantity: i32,
+    pub delta: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<Uuid>,
+}
+domain_event!(InventoryAdjustedEvent, topics::INVENTORY_ADJUSTED, 1, product_id);
+
+/// `inventory.oversell`: active reservations for a product exceed its on-hand stock. Published
+/// once when the checker first sees the condition, not on every check while it lasts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryOversellEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    /// The location reserved against; absent for legacy stock and location-less reservations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub on_hand: i32,
+    pub reserved: i32,
+}
+domain_event!(InventoryOversellEvent, topics::INVENTORY_OVERSELL, 1, product_id);
+
+/// One component's stock drawn down (or, for refunds, returned) by a bundle sale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsumedComponent {
+    pub bundle_id: Uuid,
+    pub component_id: Uuid,
+    /// Stock units: whole units, or thousandths of a kg/lb for weighed components. Negative when
+    /// a refund put them back.
+    pub quantity: i32,
+}
+
+/// `inventory.components.consumed`: completing (or refunding) an order moved the stock of the
+/// components behind its bundle lines rather than the bundles themselves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentsConsumedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub order_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub components: Vec<ConsumedComponent>,
+}
+domain_event!(ComponentsConsumedEvent, topics::INVENTORY_COMPONENTS_CONSUMED, 1, order_id);
+
+/// Cost of one product's stock moved by an order, at the tenant's valuation method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CogsLine {
+    /// The stocked product: a bundle line is costed per component.
+    pub product_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    /// Stock units, as in `ConsumedComponent::quantity`; negative for a refund's returned units.
+    pub quantity: i32,
+    /// Cost of the costed units, negative for returns.
+    pub cost: BigDecimal,
+    /// Units sold with no cost layer left to draw from (stock received without a cost, or sold
+    /// below zero); not included in `cost`.
+    #[serde(default)]
+    pub uncosted_quantity: i32,
+}
+
+/// `inventory.cogs.recorded`: the cost of goods a completed (or refunded) order took out of (or
+/// put back into) stock, for margin reporting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CogsRecordedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub order_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    /// `fifo` or `weighted_average`.
+    pub method: String,
+    pub lines: Vec<CogsLine>,
+    pub total_cost: BigDecimal,
+}
+domain_event!(CogsRecordedEvent, topics::INVENTORY_COGS_RECORDED, 1, order_id);
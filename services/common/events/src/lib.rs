@@ -0,0 +1,123 @@
+//! Versioned payload contracts for the domain events exchanged over Kafka.
+//!
+//! Every topic has exactly one struct here, shared by its producer and all of its consumers, so
+//! renaming a field is a compile error (or a failing compatibility test) rather than a consumer
+//! that silently stops matching. Payloads carry a `schema_version`; changes within a topic must
+//! be additive (new optional fields) and bump the version, while anything breaking needs a new
+//! topic. Payloads published before versioning are read as version 1.
+
+pub mod contract;
+pub mod customer;
+pub mod inventory;
+pub mod notification;
+pub mod order;
+pub mod payment;
+pub mod product;
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+pub use customer::CustomerErasedEvent;
+pub use inventory::{CogsLine, CogsRecordedEvent, ComponentsConsumedEvent, ConsumedComponent, InventoryAdjustedEvent, InventoryLowStockEvent, InventoryOversellEvent, ReservationExpiredEvent};
+pub use notification::EmailRequestedEvent;
+pub use order::{DayClosedEvent, OrderCompletedEvent, OrderEventItem, OrderRefundedEvent, OrderTipRecordedEvent, OrderVoidedEvent};
+pub use payment::{DisputeStatus, PaymentCompletedEvent, PaymentDisputeUpdatedEvent, PaymentFailedEvent, PaymentStandInEvent, PaymentVoidedEvent};
+pub use product::ProductMergedEvent;
+
+/// Topic names, in one place so producers and subscriptions can't drift apart.
+pub mod topics {
+    pub const ORDER_COMPLETED: &str = "order.completed";
+    pub const ORDER_REFUNDED: &str = "order.refunded";
+    pub const ORDER_VOIDED: &str = "order.voided";
+    pub const ORDER_TIP_RECORDED: &str = "order.tip_recorded";
+    pub const DAY_CLOSED: &str = "day.closed";
+    pub const INVENTORY_LOW_STOCK: &str = "inventory.low_stock";
+    pub const INVENTORY_ADJUSTED: &str = "inventory.adjusted";
+    pub const INVENTORY_OVERSELL: &str = "inventory.oversell";
+    pub const INVENTORY_COMPONENTS_CONSUMED: &str = "inventory.components.consumed";
+    pub const INVENTORY_COGS_RECORDED: &str = "inventory.cogs.recorded";
+    pub const RESERVATION_EXPIRED: &str = "inventory.reservation.expired";
+    pub const PAYMENT_COMPLETED: &str = "payment.completed";
+    pub const PAYMENT_FAILED: &str = "payment.failed";
+    pub const PAYMENT_VOIDED: &str = "payment.voided";
+    pub const PAYMENT_DISPUTE_UPDATED: &str = "payment.dispute.updated";
+    pub const PAYMENT_STAND_IN: &str = "payment.stand_in";
+    pub const NOTIFICATION_EMAIL_REQUESTED: &str = "notification.email.requested";
+    pub const PRODUCT_MERGED: &str = "product.merged";
+    pub const CUSTOMER_ERASED: &str = "customer.erased";
+}
+
+/// A payload published on a single topic.
+pub trait DomainEvent: Serialize + DeserializeOwned {
+    const TOPIC: &'static str;
+    /// Version written by this build. Readers accept any version, since later versions only add
+    /// fields and unknown fields are ignored.
+    const SCHEMA_VERSION: u32;
+
+    fn schema_version(&self) -> u32;
+
+    /// Kafka message key: the id of the aggregate the event belongs to (the order for order and
+    /// payment events, the product for stock alerts, the canonical product for merges, the
+    /// customer for erasures). Events for one aggregate land on one partition and stay in order;
+    /// different aggregates of the same tenant spread out.
+    fn partition_key(&self) -> String;
+}
+
+#[derive(Debug, Error)]
+pub enum EventError {
+    #[error("failed to encode {topic} payload: {source}")]
+    Encode { topic: &'static str, source: serde_json::Error },
+    #[error("failed to decode {topic} payload: {source}")]
+    Decode { topic: &'static str, source: serde_json::Error },
+}
+
+/// Serialize an event for publishing on `E::TOPIC`.
+pub fn encode<E: DomainEvent>(event: &E) -> Result<String, EventError> {
+    serde_json::to_string(event).map_err(|source| EventError::Encode { topic: E::TOPIC, source })
+}
+
+/// Same as [`encode`], for producers that stage payloads as JSON (e.g. the outbox table).
+pub fn to_value<E: DomainEvent>(event: &E) -> Result<serde_json::Value, EventError> {
+    serde_json::to_value(event).map_err(|source| EventError::Encode { topic: E::TOPIC, source })
+}
+
+/// Parse a payload received on `E::TOPIC`.
+pub fn decode<E: DomainEvent>(payload: &str) -> Result<E, EventError> {
+    serde_json::from_str(payload).map_err(|source| EventError::Decode { topic: E::TOPIC, source })
+}
+
+/// Inbox de-duplication id for a delivered message.
+///
+/// Message keys identify the aggregate, not the message: a sale and its refund share the order's
+/// key, so consumers must not de-duplicate on the key alone. The payload hash tells distinct
+/// events apart while a redelivery of the same bytes still maps to the same id.
+pub fn inbox_key(message_key: Option<&[u8]>, payload: &str) -> String {
+    let digest = hex::encode(sha1_smol::Sha1::from(payload).digest().bytes());
+    match message_key {
+        Some(key) => format!("{}:sha1:{digest}", String::from_utf8_lossy(key)),
+        None => format!("sha1:{digest}"),
+    }
+}
+
+/// Serde default for `schema_version` on payloads published before versioning.
+pub(crate) fn legacy_version() -> u32 {
+    1
+}
+
+macro_rules! domain_event {
+    ($ty:ty, $topic:expr, $version:expr, $key:ident) => {
+        impl $crate::DomainEvent for $ty {
+            const TOPIC: &'static str = $topic;
+            const SCHEMA_VERSION: u32 = $version;
+
+            fn schema_version(&self) -> u32 {
+                self.schema_version
+            }
+
+            fn partition_key(&self) -> String {
+                self.$key.to_string()
+            }
+        }
+    };
+}
+pub(crate) use domain_event;
@@ -0,0 +1,23 @@
+//! Requests handed to the notification path for delivery.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+/// `notification.email.requested`: a service rendered an email and asks the mailer to send it.
+/// `message_id` is unique per email, so the mailer can drop redeliveries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailRequestedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub message_id: Uuid,
+    pub recipients: Vec<String>,
+    pub subject: String,
+    /// Plain text.
+    pub body: String,
+    /// What produced the email, e.g. `analytics.report.sales_summary`.
+    pub source: String,
+}
+domain_event!(EmailRequestedEvent, topics::NOTIFICATION_EMAIL_REQUESTED, 1, message_id);
@@ -0,0 +1,178 @@
+//! Events published by order-service.
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderEventItem {
+    pub product_id: Uuid,
+    /// Negative on refund events.
+    pub quantity: i32,
+    pub unit_price: BigDecimal,
+    pub line_total: BigDecimal,
+    /// Net weight for products sold by `kg`/`lb` (three decimals, negative on refunds);
+    /// `unit_price` is then per unit of weight and `quantity` counts scale readings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured_quantity: Option<BigDecimal>,
+}
+
+/// `order.completed`: an order was paid. Refunds reuse the topic with negative quantities and
+/// total plus a `return_id`, so consumers net sales by summing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderCompletedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub items: Vec<OrderEventItem>,
+    pub total: BigDecimal,
+    #[serde(default)]
+    pub customer_id: Option<Uuid>,
+    #[serde(default)]
+    pub offline: bool,
+    pub payment_method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_id: Option<Uuid>,
+    /// Store the sale was rung up at (`orders.store_id`); refunds carry the original sale's store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    /// Cashier who rang the sale (`orders.created_by`), or who processed the refund.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub employee_id: Option<Uuid>,
+    /// Return authorization a refund was received against. Its stock was already moved per
+    /// inspection disposition, so inventory must not restock these items again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rma_id: Option<Uuid>,
+    /// Set on the sale half of an exchange: the `return_id` of the linked `order.refunded` event
+    /// whose credit paid for part or all of this order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_return_id: Option<Uuid>,
+    /// Store business date the sale (or refund) is booked on, which follows the store's close time
+    /// rather than the calendar. Absent on events published before business dates existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_date: Option<NaiveDate>,
+}
+domain_event!(OrderCompletedEvent, topics::ORDER_COMPLETED, 7, order_id);
+
+impl OrderCompletedEvent {
+    pub fn is_refund(&self) -> bool {
+        self.return_id.is_some() || self.total < BigDecimal::from(0)
+    }
+}
+
+/// `order.refunded`: the return half of an exchange. Amounts are positive and include tax;
+/// `refund_due` is what went back to `refund_tender` after the credit paid for the new sale,
+/// which is the `order.completed` with `order_id == exchange_order_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRefundedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    /// The original sale the items came back from.
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub return_id: Uuid,
+    pub items: Vec<OrderEventItem>,
+    pub subtotal: BigDecimal,
+    pub tax: BigDecimal,
+    pub total: BigDecimal,
+    pub refund_due: BigDecimal,
+    pub refund_tender: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub employee_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_order_id: Option<Uuid>,
+}
+domain_event!(OrderRefundedEvent, topics::ORDER_REFUNDED, 2, order_id);
+
+/// `order.voided`: an order was voided, either by a manager or because payment failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderVoidedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    #[serde(default)]
+    pub items: Vec<OrderEventItem>,
+    pub total: BigDecimal,
+    #[serde(default)]
+    pub customer_id: Option<Uuid>,
+    #[serde(default)]
+    pub offline: bool,
+    pub payment_method: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Set for manager voids; absent when the void came from a payment failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approved_by: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_method: Option<String>,
+    /// Business date of the voided order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_date: Option<NaiveDate>,
+}
+domain_event!(OrderVoidedEvent, topics::ORDER_VOIDED, 3, order_id);
+
+/// `order.tip_recorded`: the tip on an order was set at checkout or adjusted afterwards. Tips are
+/// not part of the `order.completed` total, which stays the taxable sale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderTipRecordedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    /// Employee who rang up the order, who the tip is credited to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub employee_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pos_instance_id: Option<Uuid>,
+    pub payment_method: String,
+    pub tip: BigDecimal,
+    /// Tip before this change; zero when the tip was set at checkout.
+    #[serde(default)]
+    pub previous_tip: BigDecimal,
+    /// False for the tip taken at checkout, true for a later adjustment.
+    #[serde(default)]
+    pub adjustment: bool,
+    /// Business date of the order; adjustments count towards that day.
+    pub business_date: NaiveDate,
+}
+domain_event!(OrderTipRecordedEvent, topics::ORDER_TIP_RECORDED, 1, order_id);
+
+/// `day.closed`: a store's business day was closed and its Z-report generated. Amounts are the
+/// report's figures in major units; consumers treat them as final for that store and day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayClosedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub store_id: Uuid,
+    pub business_date: NaiveDate,
+    pub order_count: i64,
+    /// Completed sales, before refunds; tips are not included.
+    pub gross_sales: BigDecimal,
+    pub refund_count: i64,
+    pub refund_amount: BigDecimal,
+    pub net_sales: BigDecimal,
+    pub tax: BigDecimal,
+    pub tips: BigDecimal,
+    pub void_count: i64,
+    pub void_amount: BigDecimal,
+    /// Counted less expected cash over the day's drawers; negative when short.
+    pub cash_variance: BigDecimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_by: Option<Uuid>,
+    pub closed_at: DateTime<Utc>,
+}
+domain_event!(DayClosedEvent, topics::DAY_CLOSED, 1, store_id);
@@ -0,0 +1,129 @@
+//! Events published by integration-gateway as payments settle, and by payment-service as
+//! disputes progress and payments are taken in stand-in.
+//!
+//! Amounts of the settlement events stay JSON numbers (`f64`), which is what every producer has
+//! always written; dispute events carry integer minor units.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+/// `payment.completed`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentCompletedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub method: String,
+    pub amount: f64,
+}
+domain_event!(PaymentCompletedEvent, topics::PAYMENT_COMPLETED, 1, order_id);
+
+/// `payment.failed`: a payment attempt was rejected or failed upstream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentFailedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub method: String,
+    pub reason: String,
+}
+domain_event!(PaymentFailedEvent, topics::PAYMENT_FAILED, 1, order_id);
+
+/// `payment.voided`: a pending payment or authorization was voided.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentVoidedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub method: String,
+    pub amount: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+domain_event!(PaymentVoidedEvent, topics::PAYMENT_VOIDED, 1, order_id);
+
+/// `payment.stand_in`: the provider was unreachable and the payment was queued under the
+/// tenant's stand-in floor limit. `payment.completed` or `payment.failed` follows once the queue
+/// is replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentStandInEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub stand_in_id: Uuid,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub method: String,
+    pub amount_minor: i64,
+    pub currency: String,
+}
+domain_event!(PaymentStandInEvent, topics::PAYMENT_STAND_IN, 1, order_id);
+
+/// Lifecycle of a chargeback or dispute raised against a captured payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeStatus {
+    /// Opened by the cardholder's bank; evidence is due.
+    NeedsResponse,
+    /// Evidence submitted; the issuer is deciding.
+    UnderReview,
+    Won,
+    Lost,
+}
+
+impl DisputeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeStatus::NeedsResponse => "needs_response",
+            DisputeStatus::UnderReview => "under_review",
+            DisputeStatus::Won => "won",
+            DisputeStatus::Lost => "lost",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [DisputeStatus::NeedsResponse, DisputeStatus::UnderReview, DisputeStatus::Won, DisputeStatus::Lost]
+            .into_iter()
+            .find(|status| status.as_str() == value)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self, DisputeStatus::Won | DisputeStatus::Lost)
+    }
+}
+
+/// `payment.dispute.updated`: a dispute was opened or changed status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentDisputeUpdatedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub dispute_id: Uuid,
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_intent_id: Option<String>,
+    pub status: DisputeStatus,
+    /// `None` when this event opens the dispute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_status: Option<DisputeStatus>,
+    pub amount_minor: i64,
+    pub currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+domain_event!(PaymentDisputeUpdatedEvent, topics::PAYMENT_DISPUTE_UPDATED, 1, order_id);
+
+impl PaymentDisputeUpdatedEvent {
+    pub fn is_opening(&self) -> bool {
+        self.previous_status.is_none()
+    }
+
+    /// True when this event moves the dispute into `status` (rather than repeating it).
+    pub fn enters(&self, status: DisputeStatus) -> bool {
+        self.status == status && self.previous_status != Some(status)
+    }
+}
@@ -0,0 +1,26 @@
+//! Events published by product-service.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{domain_event, legacy_version, topics};
+
+/// `product.merged`: a duplicate product was folded into its canonical product. The duplicate is
+/// soft-deleted; services holding rows for it (stock, order lines, carts) repoint them at the
+/// canonical product. Consumers must be idempotent, as the merge can be redelivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductMergedEvent {
+    #[serde(default = "legacy_version")]
+    pub schema_version: u32,
+    pub tenant_id: Uuid,
+    pub duplicate_id: Uuid,
+    pub canonical_id: Uuid,
+    /// SKUs at merge time, so SKU-keyed records (carts) can follow the product.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_sku: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_sku: Option<String>,
+    pub merged_at: DateTime<Utc>,
+}
+domain_event!(ProductMergedEvent, topics::PRODUCT_MERGED, 1, canonical_id);
@@ -0,0 +1,397 @@
+//! Contract tests: payloads as producers published them before versioning must still decode,
+//! and encoded field names are pinned so a rename fails here before it reaches a consumer.
+
+use bigdecimal::BigDecimal;
+use common_events::{
+    decode, encode, ComponentsConsumedEvent, ConsumedComponent, DayClosedEvent, DisputeStatus, DomainEvent, EmailRequestedEvent, InventoryAdjustedEvent, InventoryLowStockEvent, InventoryOversellEvent, OrderCompletedEvent, OrderEventItem, OrderRefundedEvent, OrderTipRecordedEvent, OrderVoidedEvent,
+    PaymentCompletedEvent, PaymentDisputeUpdatedEvent, PaymentFailedEvent, PaymentStandInEvent, PaymentVoidedEvent, ReservationExpiredEvent,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const ORDER: &str = "6f1c1d2e-0000-4000-8000-000000000001";
+const TENANT: &str = "6f1c1d2e-0000-4000-8000-0000000000aa";
+const PRODUCT: &str = "6f1c1d2e-0000-4000-8000-000000000002";
+
+fn keys(payload: &str) -> BTreeSet<String> {
+    let value: Value = serde_json::from_str(payload).unwrap();
+    value.as_object().unwrap().keys().cloned().collect()
+}
+
+fn expect_keys(payload: &str, expected: &[&str]) {
+    let expected: BTreeSet<String> = expected.iter().map(|k| k.to_string()).collect();
+    assert_eq!(keys(payload), expected, "wire fields changed; bump schema_version and keep old fields readable");
+}
+
+fn sample_item() -> OrderEventItem {
+    OrderEventItem {
+        product_id: Uuid::parse_str(PRODUCT).unwrap(),
+        quantity: 2,
+        unit_price: BigDecimal::from_str("4.50").unwrap(),
+        line_total: BigDecimal::from_str("9.00").unwrap(),
+        measured_quantity: None,
+    }
+}
+
+#[test]
+fn legacy_order_completed_decodes_as_version_1() {
+    // Shape written by create_order (decimals as strings) and by the payment consumer (floats).
+    for total in [json!("9.00"), json!(9.0)] {
+        let payload = json!({
+            "order_id": ORDER, "tenant_id": TENANT,
+            "items": [{"product_id": PRODUCT, "quantity": 2, "unit_price": "4.50", "line_total": 9.0}],
+            "total": total, "customer_id": null, "offline": false, "payment_method": "cash",
+        });
+        let evt: OrderCompletedEvent = decode(&payload.to_string()).unwrap();
+        assert_eq!(evt.schema_version, 1);
+        assert_eq!(evt.total, BigDecimal::from(9));
+        assert_eq!(evt.items[0].line_total, BigDecimal::from(9));
+        assert!(!evt.is_refund());
+        assert_eq!((evt.location_id, evt.employee_id), (None, None));
+    }
+}
+
+#[test]
+fn refund_on_order_completed_is_recognised() {
+    let payload = json!({
+        "order_id": ORDER, "tenant_id": TENANT, "items": [], "total": "-4.50",
+        "customer_id": null, "offline": false, "payment_method": "card",
+        "return_id": "6f1c1d2e-0000-4000-8000-000000000003",
+        "location_id": "6f1c1d2e-0000-4000-8000-000000000006",
+        "employee_id": "6f1c1d2e-0000-4000-8000-000000000007",
+        "rma_id": "6f1c1d2e-0000-4000-8000-000000000008",
+    });
+    let evt: OrderCompletedEvent = decode(&payload.to_string()).unwrap();
+    assert!(evt.is_refund());
+    assert_eq!(evt.rma_id.map(|id| id.to_string()).as_deref(), Some("6f1c1d2e-0000-4000-8000-000000000008"));
+    assert_eq!(evt.location_id.map(|id| id.to_string()).as_deref(), Some("6f1c1d2e-0000-4000-8000-000000000006"));
+    assert_eq!(evt.employee_id.map(|id| id.to_string()).as_deref(), Some("6f1c1d2e-0000-4000-8000-000000000007"));
+}
+
+#[test]
+fn order_completed_wire_fields_are_stable() {
+    let evt = OrderCompletedEvent {
+        schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+        order_id: Uuid::parse_str(ORDER).unwrap(),
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        items: vec![sample_item()],
+        total: BigDecimal::from_str("9.00").unwrap(),
+        customer_id: None,
+        offline: false,
+        payment_method: "cash".into(),
+        return_id: None,
+        location_id: None,
+        employee_id: None,
+        rma_id: None,
+        exchange_return_id: None,
+        business_date: None,
+    };
+    let payload = encode(&evt).unwrap();
+    expect_keys(&payload, &["schema_version", "order_id", "tenant_id", "items", "total", "customer_id", "offline", "payment_method"]);
+    let item: Value = serde_json::from_str::<Value>(&payload).unwrap()["items"][0].clone();
+    let item_keys: BTreeSet<&str> = item.as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(item_keys, BTreeSet::from(["product_id", "quantity", "unit_price", "line_total"]));
+    assert_eq!(decode::<OrderCompletedEvent>(&payload).unwrap(), evt);
+}
+
+#[test]
+fn sales_carry_their_business_date() {
+    let payload = json!({
+        "order_id": ORDER, "tenant_id": TENANT, "items": [], "total": "9.00",
+        "payment_method": "cash", "business_date": "2026-03-28",
+    });
+    let evt: OrderCompletedEvent = decode(&payload.to_string()).unwrap();
+    assert_eq!(evt.business_date, chrono::NaiveDate::from_ymd_opt(2026, 3, 28));
+    // Sales published before business dates existed fall back to the consumer's own dating.
+    let legacy: OrderCompletedEvent =
+        decode(&json!({"order_id": ORDER, "tenant_id": TENANT, "items": [], "total": "9.00", "payment_method": "cash"}).to_string()).unwrap();
+    assert_eq!(legacy.business_date, None);
+    let voided: OrderVoidedEvent = decode(
+        &json!({"order_id": ORDER, "tenant_id": TENANT, "total": "9.00", "payment_method": "cash", "business_date": "2026-03-28"}).to_string(),
+    )
+    .unwrap();
+    assert_eq!(voided.business_date, evt.business_date);
+}
+
+#[test]
+fn weighed_items_carry_measured_quantity() {
+    let item = OrderEventItem { measured_quantity: Some(BigDecimal::from_str("1.235").unwrap()), ..sample_item() };
+    let value = serde_json::to_value(&item).unwrap();
+    assert_eq!(value["measured_quantity"], json!("1.235"));
+    assert_eq!(serde_json::from_value::<OrderEventItem>(value).unwrap(), item);
+    // Items from older producers have no measured quantity.
+    let legacy: OrderEventItem =
+        serde_json::from_value(json!({"product_id": PRODUCT, "quantity": 1, "unit_price": "3.99", "line_total": "3.99"})).unwrap();
+    assert_eq!(legacy.measured_quantity, None);
+}
+
+#[test]
+fn order_voided_accepts_payment_failure_and_manager_shapes() {
+    let payment_failure = json!({
+        "order_id": ORDER, "tenant_id": TENANT, "items": [], "total": 12.5, "customer_id": null,
+        "offline": false, "payment_method": "card", "reason": "payment_failed: declined",
+    });
+    let evt: OrderVoidedEvent = decode(&payment_failure.to_string()).unwrap();
+    assert_eq!(evt.reason_code, None);
+    assert_eq!(evt.reason.as_deref(), Some("payment_failed: declined"));
+
+    let manager = json!({
+        "order_id": ORDER, "tenant_id": TENANT, "items": [], "total": "12.50", "customer_id": null,
+        "offline": false, "payment_method": "card", "reason": "customer left", "reason_code": "customer_changed_mind",
+        "requested_by": "6f1c1d2e-0000-4000-8000-000000000004", "approved_by": "6f1c1d2e-0000-4000-8000-000000000005",
+        "approval_method": "pin",
+    });
+    let evt: OrderVoidedEvent = decode(&manager.to_string()).unwrap();
+    assert_eq!(evt.approval_method.as_deref(), Some("pin"));
+    // inventory-service only ever needed these two fields; they must stay top-level.
+    assert_eq!(evt.order_id.to_string(), ORDER);
+    assert_eq!(evt.tenant_id.to_string(), TENANT);
+}
+
+#[test]
+fn exchange_halves_reference_each_other() {
+    let return_id = Uuid::parse_str("6f1c1d2e-0000-4000-8000-000000000003").unwrap();
+    let exchange_order_id = Uuid::parse_str("6f1c1d2e-0000-4000-8000-000000000009").unwrap();
+    let refunded = OrderRefundedEvent {
+        schema_version: OrderRefundedEvent::SCHEMA_VERSION,
+        order_id: Uuid::parse_str(ORDER).unwrap(),
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        return_id,
+        items: vec![sample_item()],
+        subtotal: BigDecimal::from_str("9.00").unwrap(),
+        tax: BigDecimal::from_str("0.74").unwrap(),
+        total: BigDecimal::from_str("9.74").unwrap(),
+        refund_due: BigDecimal::from_str("2.24").unwrap(),
+        refund_tender: "card".into(),
+        customer_id: None,
+        location_id: None,
+        employee_id: None,
+        exchange_order_id: Some(exchange_order_id),
+    };
+    let payload = encode(&refunded).unwrap();
+    expect_keys(&payload, &["schema_version", "order_id", "tenant_id", "return_id", "items", "subtotal", "tax", "total", "refund_due", "refund_tender", "exchange_order_id"]);
+    assert_eq!(decode::<OrderRefundedEvent>(&payload).unwrap(), refunded);
+
+    let sale = json!({
+        "order_id": exchange_order_id, "tenant_id": TENANT, "items": [], "total": "7.50",
+        "customer_id": null, "offline": false, "payment_method": "card", "exchange_return_id": return_id,
+    });
+    let evt: OrderCompletedEvent = decode(&sale.to_string()).unwrap();
+    assert!(!evt.is_refund());
+    assert_eq!(evt.exchange_return_id, Some(refunded.return_id));
+}
+
+#[test]
+fn order_tip_recorded_wire_fields_are_stable() {
+    let evt = OrderTipRecordedEvent {
+        schema_version: OrderTipRecordedEvent::SCHEMA_VERSION,
+        order_id: Uuid::parse_str(ORDER).unwrap(),
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        employee_id: Some(Uuid::parse_str(PRODUCT).unwrap()),
+        pos_instance_id: None,
+        payment_method: "card".into(),
+        tip: BigDecimal::from_str("3.00").unwrap(),
+        previous_tip: BigDecimal::from_str("2.00").unwrap(),
+        adjustment: true,
+        business_date: chrono::NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+    };
+    let payload = encode(&evt).unwrap();
+    expect_keys(&payload, &["schema_version", "order_id", "tenant_id", "employee_id", "payment_method", "tip", "previous_tip", "adjustment", "business_date"]);
+    assert_eq!(serde_json::from_str::<Value>(&payload).unwrap()["business_date"], "2026-10-17");
+    assert_eq!(decode::<OrderTipRecordedEvent>(&payload).unwrap(), evt);
+
+    let checkout = json!({
+        "order_id": ORDER, "tenant_id": TENANT, "payment_method": "cash", "tip": "1.50", "business_date": "2026-10-17",
+    });
+    let evt: OrderTipRecordedEvent = decode(&checkout.to_string()).unwrap();
+    assert!(!evt.adjustment);
+    assert_eq!(evt.previous_tip, BigDecimal::from(0));
+}
+
+#[test]
+fn day_closed_is_keyed_by_store() {
+    let store = Uuid::parse_str(PRODUCT).unwrap();
+    let evt = DayClosedEvent {
+        schema_version: DayClosedEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        store_id: store,
+        business_date: chrono::NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+        order_count: 42,
+        gross_sales: BigDecimal::from_str("1250.40").unwrap(),
+        refund_count: 1,
+        refund_amount: BigDecimal::from_str("20.00").unwrap(),
+        net_sales: BigDecimal::from_str("1230.40").unwrap(),
+        tax: BigDecimal::from_str("98.43").unwrap(),
+        tips: BigDecimal::from_str("61.00").unwrap(),
+        void_count: 2,
+        void_amount: BigDecimal::from_str("15.50").unwrap(),
+        cash_variance: BigDecimal::from_str("-0.25").unwrap(),
+        closed_by: None,
+        closed_at: chrono::DateTime::from_timestamp(1_792_281_600, 0).unwrap(),
+    };
+    assert_eq!(evt.partition_key(), store.to_string());
+    let payload = encode(&evt).unwrap();
+    expect_keys(
+        &payload,
+        &[
+            "schema_version", "tenant_id", "store_id", "business_date", "order_count", "gross_sales", "refund_count", "refund_amount", "net_sales", "tax", "tips",
+            "void_count", "void_amount", "cash_variance", "closed_at",
+        ],
+    );
+    assert_eq!(decode::<DayClosedEvent>(&payload).unwrap(), evt);
+}
+
+#[test]
+fn inventory_events_round_trip_with_stable_fields() {
+    let low = InventoryLowStockEvent {
+        schema_version: InventoryLowStockEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        product_id: Uuid::parse_str(PRODUCT).unwrap(),
+        quantity: 3,
+        threshold: 5,
+    };
+    let payload = encode(&low).unwrap();
+    expect_keys(&payload, &["schema_version", "tenant_id", "product_id", "quantity", "threshold"]);
+    assert_eq!(decode::<InventoryLowStockEvent>(&payload).unwrap(), low);
+
+    let legacy_expired = json!({
+        "type": "reservation.expired", "tenant_id": TENANT, "product_id": PRODUCT, "order_id": ORDER,
+        "quantity": 1, "expired_at_epoch": 1_700_000_000u64,
+    });
+    let expired: ReservationExpiredEvent = decode(&legacy_expired.to_string()).unwrap();
+    assert_eq!(expired.schema_version, 1);
+    expect_keys(&encode(&expired).unwrap(), &["schema_version", "tenant_id", "order_id", "product_id", "quantity", "expired_at_epoch"]);
+
+    let adjusted = InventoryAdjustedEvent {
+        schema_version: InventoryAdjustedEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        product_id: Uuid::parse_str(PRODUCT).unwrap(),
+        location_id: None,
+        adjustment_id: Uuid::parse_str(ORDER).unwrap(),
+        kind: "set_quantity".into(),
+        reason_code: "cycle_count".into(),
+        previous_quantity: 12,
+        new_quantity: 9,
+        delta: -3,
+        actor_id: None,
+    };
+    let payload = encode(&adjusted).unwrap();
+    expect_keys(
+        &payload,
+        &["schema_version", "tenant_id", "product_id", "adjustment_id", "kind", "reason_code", "previous_quantity", "new_quantity", "delta"],
+    );
+    assert_eq!(decode::<InventoryAdjustedEvent>(&payload).unwrap(), adjusted);
+    assert_eq!(adjusted.partition_key(), PRODUCT);
+
+    let oversell = InventoryOversellEvent {
+        schema_version: InventoryOversellEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        product_id: Uuid::parse_str(PRODUCT).unwrap(),
+        location_id: None,
+        on_hand: 2,
+        reserved: 5,
+    };
+    let payload = encode(&oversell).unwrap();
+    expect_keys(&payload, &["schema_version", "tenant_id", "product_id", "on_hand", "reserved"]);
+    assert_eq!(decode::<InventoryOversellEvent>(&payload).unwrap(), oversell);
+}
+
+#[test]
+fn components_consumed_is_keyed_by_order() {
+    let consumed = ComponentsConsumedEvent {
+        schema_version: ComponentsConsumedEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        order_id: Uuid::parse_str(ORDER).unwrap(),
+        location_id: None,
+        components: vec![ConsumedComponent { bundle_id: Uuid::parse_str(PRODUCT).unwrap(), component_id: Uuid::nil(), quantity: 125 }],
+    };
+    let payload = encode(&consumed).unwrap();
+    expect_keys(&payload, &["schema_version", "tenant_id", "order_id", "components"]);
+    assert_eq!(ComponentsConsumedEvent::TOPIC, "inventory.components.consumed");
+    assert_eq!(consumed.partition_key(), ORDER);
+    assert_eq!(decode::<ComponentsConsumedEvent>(&payload).unwrap(), consumed);
+}
+
+#[test]
+fn email_requests_are_keyed_by_message() {
+    let email = EmailRequestedEvent {
+        schema_version: EmailRequestedEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::parse_str(TENANT).unwrap(),
+        message_id: Uuid::parse_str(ORDER).unwrap(),
+        recipients: vec!["owner@example.com".into()],
+        subject: "Sales summary 2026-03-01".into(),
+        body: "Orders: 4".into(),
+        source: "analytics.report.sales_summary".into(),
+    };
+    let payload = encode(&email).unwrap();
+    expect_keys(&payload, &["schema_version", "tenant_id", "message_id", "recipients", "subject", "body", "source"]);
+    assert_eq!(EmailRequestedEvent::TOPIC, "notification.email.requested");
+    assert_eq!(email.partition_key(), ORDER);
+    assert_eq!(decode::<EmailRequestedEvent>(&payload).unwrap(), email);
+}
+
+#[test]
+fn payment_events_keep_numeric_amounts() {
+    let completed = json!({"order_id": ORDER, "tenant_id": TENANT, "method": "card", "amount": 12.34});
+    let evt: PaymentCompletedEvent = decode(&completed.to_string()).unwrap();
+    assert_eq!(evt.amount, 12.34);
+    let payload = encode(&evt).unwrap();
+    expect_keys(&payload, &["schema_version", "order_id", "tenant_id", "method", "amount"]);
+    assert!(serde_json::from_str::<Value>(&payload).unwrap()["amount"].is_number());
+
+    let failed = json!({"order_id": ORDER, "tenant_id": TENANT, "method": "card", "reason": "declined"});
+    assert_eq!(decode::<PaymentFailedEvent>(&failed.to_string()).unwrap().reason, "declined");
+
+    let voided = json!({"order_id": ORDER, "tenant_id": TENANT, "method": "card", "amount": 10.0});
+    let evt: PaymentVoidedEvent = decode(&voided.to_string()).unwrap();
+    assert_eq!(evt.reason, None);
+    expect_keys(&encode(&evt).unwrap(), &["schema_version", "order_id", "tenant_id", "method", "amount"]);
+}
+
+#[test]
+fn stand_in_events_carry_minor_units_and_the_queue_entry() {
+    let queued = json!({
+        "schema_version": 1, "stand_in_id": "6f1c1d2e-0000-4000-8000-000000000009", "order_id": ORDER, "tenant_id": TENANT,
+        "method": "card", "amount_minor": 1213, "currency": "CAD",
+    });
+    let evt: PaymentStandInEvent = decode(&queued.to_string()).unwrap();
+    assert_eq!((evt.amount_minor, evt.currency.as_str()), (1213, "CAD"));
+    assert_eq!(PaymentStandInEvent::TOPIC, "payment.stand_in");
+    assert_eq!(evt.partition_key(), ORDER);
+    expect_keys(&encode(&evt).unwrap(), &["schema_version", "stand_in_id", "order_id", "tenant_id", "method", "amount_minor", "currency"]);
+}
+
+#[test]
+fn dispute_events_use_snake_case_statuses_and_minor_units() {
+    let opened = json!({
+        "schema_version": 1, "dispute_id": "6f1c1d2e-0000-4000-8000-000000000007", "order_id": ORDER, "tenant_id": TENANT,
+        "status": "needs_response", "amount_minor": 1250, "currency": "USD",
+    });
+    let evt: PaymentDisputeUpdatedEvent = decode(&opened.to_string()).unwrap();
+    assert!(evt.is_opening() && evt.enters(DisputeStatus::NeedsResponse));
+    assert_eq!(evt.partition_key(), ORDER);
+    expect_keys(&encode(&evt).unwrap(), &["schema_version", "dispute_id", "order_id", "tenant_id", "status", "amount_minor", "currency"]);
+
+    let lost = PaymentDisputeUpdatedEvent { status: DisputeStatus::Lost, previous_status: Some(DisputeStatus::UnderReview), ..evt };
+    let payload: Value = serde_json::from_str(&encode(&lost).unwrap()).unwrap();
+    assert_eq!((payload["status"].as_str(), payload["previous_status"].as_str()), (Some("lost"), Some("under_review")));
+    assert!(decode::<PaymentDisputeUpdatedEvent>(&json!({"status": "escalated"}).to_string()).is_err());
+}
+
+#[test]
+fn newer_versions_with_extra_fields_still_decode() {
+    let future = json!({
+        "schema_version": 2, "tenant_id": TENANT, "product_id": PRODUCT, "quantity": 1, "threshold": 5,
+        "location_id": "6f1c1d2e-0000-4000-8000-000000000006",
+    });
+    let evt: InventoryLowStockEvent = decode(&future.to_string()).unwrap();
+    assert_eq!(evt.schema_version, 2);
+}
+
+#[test]
+fn missing_required_fields_are_rejected_with_topic() {
+    let err = decode::<PaymentFailedEvent>(&json!({"order_id": ORDER, "tenant_id": TENANT}).to_string()).unwrap_err();
+    assert!(err.to_string().contains("payment.failed"), "{err}");
+}
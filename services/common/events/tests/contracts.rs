@@ -0,0 +1,174 @@
+//! Producer side of the contract tests: the payloads order-, inventory-, product- and customer-service publish,
+//! pinned to the golden files under `contracts/`. Consumers run their own handlers over the same files.
+
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, TimeZone, Utc};
+use common_events::contract::{assert_golden, fixtures, FIXTURES};
+use common_events::{topics, CogsLine, CogsRecordedEvent, CustomerErasedEvent, DomainEvent, OrderCompletedEvent, OrderEventItem, ProductMergedEvent};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn id(suffix: &str) -> Uuid {
+    Uuid::parse_str(&format!("6f1c1d2e-0000-4000-8000-{suffix:0>12}")).unwrap()
+}
+
+fn dec(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+fn item(product: &str, quantity: i32, unit_price: &str, line_total: &str) -> OrderEventItem {
+    OrderEventItem { product_id: id(product), quantity, unit_price: dec(unit_price), line_total: dec(line_total), measured_quantity: None }
+}
+
+/// A card sale as `create_order` publishes it once the order is COMPLETED.
+fn sale() -> OrderCompletedEvent {
+    OrderCompletedEvent {
+        schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+        order_id: id("1"),
+        tenant_id: id("aa"),
+        items: vec![item("2", 2, "4.50", "9.00"), item("3", 1, "4.48", "4.48")],
+        total: dec("13.48"),
+        customer_id: Some(id("c1")),
+        offline: false,
+        payment_method: "card".into(),
+        return_id: None,
+        location_id: Some(id("5")),
+        employee_id: Some(id("e1")),
+        rma_id: None,
+        exchange_return_id: None,
+        business_date: NaiveDate::from_ymd_opt(2026, 10, 17),
+    }
+}
+
+#[test]
+fn order_completed_sale() {
+    assert_golden("sale", &sale());
+}
+
+#[test]
+fn order_completed_weighed_sale() {
+    // One scale reading of 1.235 kg at 7.99/kg; quantity counts readings.
+    let weighed = OrderEventItem { measured_quantity: Some(dec("1.235")), ..item("4", 1, "7.99", "9.87") };
+    let evt = OrderCompletedEvent { items: vec![weighed], total: dec("9.87"), customer_id: None, payment_method: "cash".into(), ..sale() };
+    assert_golden("weighed_sale", &evt);
+}
+
+#[test]
+fn order_completed_exchange_sale() {
+    let evt = OrderCompletedEvent { order_id: id("9"), items: vec![item("3", 1, "4.48", "4.48")], total: dec("4.48"), exchange_return_id: Some(id("f1")), ..sale() };
+    assert_golden("exchange_sale", &evt);
+}
+
+#[test]
+fn order_completed_refund() {
+    // `refund_order`: negated quantities, line totals and total, on the original sale's store.
+    let evt = OrderCompletedEvent { items: vec![item("2", -1, "4.50", "-4.50")], total: dec("-4.86"), return_id: Some(id("f1")), ..sale() };
+    assert!(evt.is_refund());
+    assert_golden("refund", &evt);
+}
+
+#[test]
+fn order_completed_rma_refund() {
+    // Received against a return authorization; stock was already moved at inspection.
+    let evt = OrderCompletedEvent {
+        items: vec![item("3", -1, "4.48", "-4.48")],
+        total: dec("-4.84"),
+        return_id: Some(id("f2")),
+        rma_id: Some(id("a1")),
+        ..sale()
+    };
+    assert_golden("rma_refund", &evt);
+}
+
+/// COGS of `sale()` as inventory-service costs it at FIFO: product 2 from two layers, product 3
+/// with one unit nobody recorded a cost for.
+fn cogs_sale() -> CogsRecordedEvent {
+    CogsRecordedEvent {
+        schema_version: CogsRecordedEvent::SCHEMA_VERSION,
+        tenant_id: id("aa"),
+        order_id: id("1"),
+        location_id: Some(id("5")),
+        method: "fifo".into(),
+        lines: vec![
+            CogsLine { product_id: id("2"), location_id: Some(id("5")), quantity: 2, cost: dec("4.30"), uncosted_quantity: 0 },
+            CogsLine { product_id: id("3"), location_id: Some(id("5")), quantity: 1, cost: dec("0.00"), uncosted_quantity: 1 },
+        ],
+        total_cost: dec("4.30"),
+    }
+}
+
+#[test]
+fn cogs_recorded_sale() {
+    let evt = cogs_sale();
+    assert_eq!(evt.partition_key(), evt.order_id.to_string());
+    assert_golden("sale", &evt);
+}
+
+#[test]
+fn cogs_recorded_refund() {
+    // Returned units go back at the average cost, as a negative cost.
+    let evt = CogsRecordedEvent {
+        method: "weighted_average".into(),
+        lines: vec![CogsLine { product_id: id("2"), location_id: None, quantity: -1, cost: dec("-2.15"), uncosted_quantity: 0 }],
+        total_cost: dec("-2.15"),
+        location_id: None,
+        ..cogs_sale()
+    };
+    assert_golden("refund", &evt);
+}
+
+/// A duplicate of product 2 (same item entered twice under another SKU) merged into it.
+#[test]
+fn product_merged() {
+    let evt = ProductMergedEvent {
+        schema_version: ProductMergedEvent::SCHEMA_VERSION,
+        tenant_id: id("aa"),
+        duplicate_id: id("9"),
+        canonical_id: id("2"),
+        duplicate_sku: Some("LATTE-12OZ".into()),
+        canonical_sku: Some("LATTE-12".into()),
+        merged_at: Utc.with_ymd_and_hms(2026, 3, 14, 9, 30, 0).unwrap(),
+    };
+    assert_eq!(evt.partition_key(), evt.canonical_id.to_string());
+    assert_golden("merge", &evt);
+}
+
+/// The customer from the card sale above, erased on request.
+#[test]
+fn customer_erased() {
+    let evt = CustomerErasedEvent {
+        schema_version: CustomerErasedEvent::SCHEMA_VERSION,
+        tenant_id: id("aa"),
+        customer_id: id("c1"),
+        tombstone_id: id("d1"),
+        erased_at: Utc.with_ymd_and_hms(2026, 10, 18, 11, 0, 0).unwrap(),
+    };
+    assert_eq!(evt.partition_key(), evt.customer_id.to_string());
+    assert_golden("erasure", &evt);
+}
+
+#[test]
+fn every_contract_file_is_registered() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("contracts");
+    let mut on_disk = BTreeSet::new();
+    for topic in std::fs::read_dir(&dir).unwrap() {
+        let topic = topic.unwrap();
+        for file in std::fs::read_dir(topic.path()).unwrap() {
+            let file = file.unwrap().path();
+            on_disk.insert(format!("contracts/{}/{}", topic.file_name().to_string_lossy(), file.file_name().unwrap().to_string_lossy()));
+        }
+    }
+    let registered: BTreeSet<String> = FIXTURES.iter().map(|fixture| fixture.path()).collect();
+    assert_eq!(on_disk, registered, "contract files and contract::FIXTURES disagree");
+}
+
+#[test]
+fn fixtures_cover_refunds_and_legacy_payloads() {
+    let completed: Vec<_> = fixtures(topics::ORDER_COMPLETED).collect();
+    assert!(completed.iter().any(|fixture| fixture.is_legacy()));
+    assert!(completed.iter().any(|fixture| fixture.decode::<OrderCompletedEvent>().is_refund()));
+    for fixture in completed.iter().filter(|fixture| !fixture.is_legacy()) {
+        assert_eq!(fixture.decode::<OrderCompletedEvent>().schema_version(), OrderCompletedEvent::SCHEMA_VERSION, "{} is out of date", fixture.path());
+    }
+}
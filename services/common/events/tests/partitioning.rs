@@ -0,0 +1,137 @@
+//! Consumer assumptions about message keys. Events are keyed by aggregate id, so:
+//! - every event of one order (sale, refund, void, payment) shares a key and keeps its order;
+//! - a big tenant's orders spread over partitions;
+//! - keys are not unique per message, so inbox de-duplication must not use them alone;
+//! - the tenant comes from the payload, never from the key.
+
+use bigdecimal::BigDecimal;
+use common_events::{
+    encode, inbox_key, DomainEvent, InventoryLowStockEvent, OrderCompletedEvent, OrderVoidedEvent,
+    PaymentCompletedEvent, PaymentFailedEvent, ReservationExpiredEvent,
+};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+/// Stand-in for the producer's partitioner: any deterministic hash of the key modulo the count.
+fn partition_for(key: &str, partitions: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish() % partitions
+}
+
+fn sale(order_id: Uuid, tenant_id: Uuid) -> OrderCompletedEvent {
+    OrderCompletedEvent {
+        schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+        order_id,
+        tenant_id,
+        items: Vec::new(),
+        total: BigDecimal::from(10),
+        customer_id: None,
+        offline: false,
+        payment_method: "card".into(),
+        return_id: None,
+        location_id: None,
+        employee_id: None,
+        rma_id: None,
+        exchange_return_id: None,
+        business_date: None,
+    }
+}
+
+fn refund(order_id: Uuid, tenant_id: Uuid) -> OrderCompletedEvent {
+    OrderCompletedEvent { total: BigDecimal::from(-4), return_id: Some(Uuid::new_v4()), ..sale(order_id, tenant_id) }
+}
+
+fn void(order_id: Uuid, tenant_id: Uuid) -> OrderVoidedEvent {
+    OrderVoidedEvent {
+        schema_version: OrderVoidedEvent::SCHEMA_VERSION,
+        order_id,
+        tenant_id,
+        items: Vec::new(),
+        total: BigDecimal::from(10),
+        customer_id: None,
+        offline: false,
+        payment_method: "card".into(),
+        reason: Some("payment_failed".into()),
+        reason_code: None,
+        requested_by: None,
+        approved_by: None,
+        approval_method: None,
+        business_date: None,
+    }
+}
+
+#[test]
+fn events_of_one_order_share_a_key_and_partition() {
+    let (order_id, tenant_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let keys = [
+        sale(order_id, tenant_id).partition_key(),
+        refund(order_id, tenant_id).partition_key(),
+        void(order_id, tenant_id).partition_key(),
+        PaymentCompletedEvent {
+            schema_version: 1,
+            order_id,
+            tenant_id,
+            method: "card".into(),
+            amount: 10.0,
+        }
+        .partition_key(),
+        PaymentFailedEvent {
+            schema_version: 1,
+            order_id,
+            tenant_id,
+            method: "card".into(),
+            reason: "declined".into(),
+        }
+        .partition_key(),
+    ];
+    assert!(keys.iter().all(|k| *k == order_id.to_string()), "{keys:?}");
+    for partitions in [1, 3, 6, 12] {
+        let chosen: HashSet<u64> = keys.iter().map(|k| partition_for(k, partitions)).collect();
+        assert_eq!(chosen.len(), 1);
+    }
+}
+
+#[test]
+fn one_tenants_orders_spread_across_partitions() {
+    let tenant_id = Uuid::new_v4();
+    let used: HashSet<u64> = (0..200).map(|_| partition_for(&sale(Uuid::new_v4(), tenant_id).partition_key(), 6)).collect();
+    assert!(used.len() > 1, "all orders of a tenant landed on one partition");
+}
+
+#[test]
+fn stock_events_are_keyed_by_their_aggregate() {
+    let (tenant_id, product_id, order_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let low = InventoryLowStockEvent { schema_version: 1, tenant_id, product_id, quantity: 2, threshold: 5 };
+    assert_eq!(low.partition_key(), product_id.to_string());
+    let expired = ReservationExpiredEvent { schema_version: 1, tenant_id, order_id, product_id, quantity: 1, expired_at_epoch: 0 };
+    assert_eq!(expired.partition_key(), order_id.to_string());
+}
+
+#[test]
+fn inbox_keys_tell_events_with_the_same_key_apart() {
+    let (order_id, tenant_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let key = order_id.to_string();
+    let sale_payload = encode(&sale(order_id, tenant_id)).unwrap();
+    let refund_payload = encode(&refund(order_id, tenant_id)).unwrap();
+    assert_ne!(
+        inbox_key(Some(key.as_bytes()), &sale_payload),
+        inbox_key(Some(key.as_bytes()), &refund_payload),
+        "a refund must not be dropped as a duplicate of its sale"
+    );
+    // A redelivery carries the same bytes and must still be recognised.
+    assert_eq!(inbox_key(Some(key.as_bytes()), &sale_payload), inbox_key(Some(key.as_bytes()), &sale_payload));
+    assert!(inbox_key(None, &sale_payload).starts_with("sha1:"));
+}
+
+#[test]
+fn tenant_is_always_in_the_payload() {
+    let (order_id, tenant_id) = (Uuid::new_v4(), Uuid::new_v4());
+    for payload in [encode(&sale(order_id, tenant_id)).unwrap(), encode(&void(order_id, tenant_id)).unwrap()] {
+        let value: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(value["tenant_id"].as_str(), Some(tenant_id.to_string().as_str()));
+    }
+}
@@ -0,0 +1,84 @@
+//! Optimistic-concurrency helpers: rows carry an integer `version` that is exposed as a strong
+//! ETag on reads and must be echoed back in `If-Match` on updates.
+
+use crate::ApiError;
+use axum::http::{header, HeaderMap, HeaderValue};
+use uuid::Uuid;
+
+/// Strong entity tag for a row version, e.g. `"7"`.
+pub fn etag(version: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{version}\"")).expect("numeric etag is a valid header value")
+}
+
+/// `(ETag, value)` pair suitable for an axum response tuple.
+pub fn etag_header(version: i64) -> [(header::HeaderName, HeaderValue); 1] {
+    [(header::ETAG, etag(version))]
+}
+
+/// Version the client expects to overwrite, taken from `If-Match`.
+///
+/// Returns `Ok(None)` for `If-Match: *` (any current version). A missing header is a 428
+/// `if_match_required`; anything other than a single strong tag produced by [`etag`] is a 400
+/// `invalid_if_match` (weak tags never satisfy `If-Match`).
+pub fn if_match_version(headers: &HeaderMap, trace_id: Option<Uuid>) -> Result<Option<i64>, ApiError> {
+    let raw = headers
+        .get(header::IF_MATCH)
+        .ok_or(ApiError::PreconditionRequired { code: "if_match_required", trace_id })?;
+    let invalid = || ApiError::BadRequest {
+        code: "invalid_if_match",
+        trace_id,
+        message: Some("If-Match must be a single strong ETag returned by a previous read".into()),
+    };
+    let value = raw.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .and_then(|v| v.parse::<i64>().ok())
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// 409 returned when the `If-Match` version no longer matches the stored row.
+pub fn version_conflict(trace_id: Option<Uuid>) -> ApiError {
+    ApiError::Conflict {
+        code: "version_conflict",
+        trace_id,
+        message: Some("Resource was modified by another request; re-read it and retry".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn round_trips_strong_etag() {
+        let tag = etag(42);
+        assert_eq!(tag, "\"42\"");
+        assert_eq!(if_match_version(&headers(tag.to_str().unwrap()), None).unwrap(), Some(42));
+        assert_eq!(if_match_version(&headers("*"), None).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_missing_and_malformed_if_match() {
+        assert!(matches!(
+            if_match_version(&HeaderMap::new(), None),
+            Err(ApiError::PreconditionRequired { code: "if_match_required", .. })
+        ));
+        for bad in ["42", "W/\"42\"", "\"abc\"", "\"1\", \"2\""] {
+            assert!(matches!(
+                if_match_version(&headers(bad), None),
+                Err(ApiError::BadRequest { code: "invalid_if_match", .. })
+            ), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! Latency histograms that can carry trace exemplars and native (sparse exponential) buckets,
+//! and the `/metrics` encoding that exposes them.
+//!
+//! Both extras are off unless `METRICS_EXEMPLARS` / `METRICS_NATIVE_HISTOGRAMS` are set, and even
+//! then only scrapes that ask for them get them: [`encode`] answers `Accept:
+//! application/vnd.google.protobuf` (Prometheus with `--enable-feature=native-histograms`) with
+//! protobuf carrying native buckets and exemplars, `application/openmetrics-text` (exemplar
+//! storage) with exemplars, and everything else with the classic text format, so older
+//! Prometheus versions see exactly what they saw before.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::http::HeaderMap;
+use once_cell::sync::Lazy;
+use prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, ProtobufEncoder, TextEncoder};
+use protobuf::{CodedOutputStream, Message};
+
+pub const TEXT_FORMAT: &str = prometheus::TEXT_FORMAT;
+pub const PROTOBUF_FORMAT: &str = prometheus::PROTOBUF_FORMAT;
+pub const OPENMETRICS_FORMAT: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Native bucket resolution: each bucket is 2^(2^-3), about 9%, wider than the one before.
+const NATIVE_SCHEMA: i32 = 3;
+/// Observations at or below this land in the zero bucket (2^-128, as client_golang uses).
+const NATIVE_ZERO_THRESHOLD: f64 = 2.938_735_877_055_719e-39;
+
+/// Which extras a [`LatencyHistogram`] records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistogramExtras {
+    pub exemplars: bool,
+    pub native: bool,
+}
+
+impl HistogramExtras {
+    /// `METRICS_EXEMPLARS` and `METRICS_NATIVE_HISTOGRAMS` (`1`/`true`/`on`).
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            std::env::var(name)
+                .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+                .unwrap_or(false)
+        };
+        Self { exemplars: flag("METRICS_EXEMPLARS"), native: flag("METRICS_NATIVE_HISTOGRAMS") }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Exemplar {
+    trace_id: String,
+    value: f64,
+    at: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct SeriesExtras {
+    /// Latest exemplar per classic bucket; the last slot is `+Inf`.
+    exemplars: Vec<Option<Exemplar>>,
+    zero_count: u64,
+    /// Native bucket index -> observations.
+    native: BTreeMap<i32, u64>,
+}
+
+#[derive(Debug)]
+struct FamilyExtras {
+    config: HistogramExtras,
+    buckets: Vec<f64>,
+    label_names: Vec<String>,
+    series: Mutex<HashMap<Vec<String>, SeriesExtras>>,
+}
+
+/// Extras of every [`LatencyHistogram`] in the process, by family name, for [`encode`].
+static FAMILIES: Lazy<Mutex<HashMap<String, Arc<FamilyExtras>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A histogram vector in the default registry that also keeps exemplars and native buckets
+/// when `config` asks for them.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    vec: HistogramVec,
+    extras: Arc<FamilyExtras>,
+}
+
+impl LatencyHistogram {
+    pub fn new(opts: HistogramOpts, label_names: &[&str], config: HistogramExtras) -> prometheus::Result<Self> {
+        let name = opts.fq_name();
+        let buckets = if opts.buckets.is_empty() { prometheus::DEFAULT_BUCKETS.to_vec() } else { opts.buckets.clone() };
+        let vec = HistogramVec::new(opts, label_names)?;
+        let _ = prometheus::default_registry().register(Box::new(vec.clone()));
+        let extras = Arc::new(FamilyExtras {
+            config,
+            buckets,
+            label_names: label_names.iter().map(|l| l.to_string()).collect(),
+            series: Mutex::new(HashMap::new()),
+        });
+        FAMILIES.lock().expect("lock histogram extras").insert(name, extras.clone());
+        Ok(Self { vec, extras })
+    }
+
+    pub fn vec(&self) -> &HistogramVec {
+        &self.vec
+    }
+
+    /// Observe `value` (seconds); `trace_id` becomes the bucket's exemplar when exemplars are on.
+    pub fn observe(&self, label_values: &[&str], value: f64, trace_id: Option<&str>) {
+        self.vec.with_label_values(label_values).observe(value);
+        let config = self.extras.config;
+        let exemplar = trace_id.filter(|_| config.exemplars);
+        if exemplar.is_none() && !config.native {
+            return;
+        }
+        let mut series = self.extras.series.lock().expect("lock histogram extras");
+        let entry = series
+            .entry(label_values.iter().map(|v| v.to_string()).collect())
+            .or_insert_with(|| SeriesExtras { exemplars: vec![None; self.extras.buckets.len() + 1], ..Default::default() });
+        if let Some(trace_id) = exemplar {
+            let bucket = self.extras.buckets.iter().position(|upper| value <= *upper).unwrap_or(self.extras.buckets.len());
+            entry.exemplars[bucket] = Some(Exemplar { trace_id: trace_id.to_string(), value, at: SystemTime::now() });
+        }
+        if config.native {
+            // Latencies are never negative; anything at or below the threshold is "zero".
+            if value <= NATIVE_ZERO_THRESHOLD {
+                entry.zero_count += 1;
+            } else {
+                *entry.native.entry(native_index(value)).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Native bucket `i` holds `(2^((i-1)/8), 2^(i/8)]`.
+fn native_index(value: f64) -> i32 {
+    (value.log2() * f64::from(1 << NATIVE_SCHEMA)).ceil() as i32
+}
+
+/// Trace id for exemplars: the W3C `traceparent` trace id when a tracer propagated one, else
+/// our own `X-Trace-ID`.
+pub fn trace_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    let traceparent = headers.get("traceparent").and_then(|v| v.to_str().ok()).and_then(|v| {
+        let trace_id = v.split('-').nth(1)?;
+        let valid = trace_id.len() == 32
+            && trace_id.bytes().all(|b| b.is_ascii_hexdigit())
+            && trace_id.bytes().any(|b| b != b'0');
+        valid.then(|| trace_id.to_ascii_lowercase())
+    });
+    traceparent.or_else(|| {
+        let raw = headers.get("X-Trace-ID")?.to_str().ok()?;
+        uuid::Uuid::parse_str(raw.trim()).ok().map(|id| id.simple().to_string())
+    })
+}
+
+/// A rendered scrape and its `Content-Type`.
+#[derive(Debug)]
+pub struct Exposition {
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+/// Encode `families` in the richest format both the scraper (`accept`) and the enabled extras
+/// allow.
+pub fn encode(families: &[MetricFamily], accept: Option<&str>) -> prometheus::Result<Exposition> {
+    let extras: HashMap<String, Arc<FamilyExtras>> = FAMILIES.lock().expect("lock histogram extras").clone();
+    let accept = accept.unwrap_or_default();
+    let native = extras.values().any(|e| e.config.native);
+    let exemplars = extras.values().any(|e| e.config.exemplars);
+    let mut body = Vec::new();
+    if (native || exemplars) && accept.contains("application/vnd.google.protobuf") {
+        let mut families = families.to_vec();
+        for family in families.iter_mut().filter(|f| f.get_field_type() == MetricType::HISTOGRAM) {
+            if let Some(extras) = extras.get(family.get_name()) {
+                for metric in family.mut_metric().iter_mut() {
+                    attach_protobuf_extras(metric, extras);
+                }
+            }
+        }
+        ProtobufEncoder::new().encode(&families, &mut body)?;
+        return Ok(Exposition { content_type: PROTOBUF_FORMAT, body });
+    }
+    if exemplars && accept.contains("application/openmetrics-text") {
+        body = encode_openmetrics(families, &extras).into_bytes();
+        return Ok(Exposition { content_type: OPENMETRICS_FORMAT, body });
+    }
+    TextEncoder::new().encode(families, &mut body)?;
+    Ok(Exposition { content_type: TEXT_FORMAT, body })
+}
+
+/// This metric's label values in the order its histogram declared them.
+fn series_key(labels: &[LabelPair], names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| labels.iter().find(|l| l.get_name() == name).map(|l| l.get_value().to_string()).unwrap_or_default())
+        .collect()
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+/// Adds the fields prometheus 0.13's proto model predates (native buckets, bucket exemplars) as
+/// unknown fields, which encode exactly like the upstream `io.prometheus.client` ones.
+fn attach_protobuf_extras(metric: &mut Metric, extras: &FamilyExtras) {
+    let key = series_key(metric.get_label(), &extras.label_names);
+    let series = extras.series.lock().expect("lock histogram extras");
+    let series = series.get(&key);
+    let histogram = metric.mut_histogram();
+    if extras.config.native {
+        let (zero_count, native) = series.map(|s| (s.zero_count, Some(&s.native))).unwrap_or_default();
+        let mut spans: Vec<(i32, u32)> = Vec::new();
+        let mut deltas = Vec::new();
+        let (mut previous_index, mut previous_count) = (None, 0i64);
+        for (&index, &count) in native.into_iter().flatten() {
+            match previous_index {
+                Some(previous) if index == previous + 1 => spans.last_mut().expect("open span").1 += 1,
+                Some(previous) => spans.push((index - previous - 1, 1)),
+                None => spans.push((index, 1)),
+            }
+            deltas.push(count as i64 - previous_count);
+            (previous_index, previous_count) = (Some(index), count as i64);
+        }
+        if spans.is_empty() {
+            // An empty span marks the histogram as native even before its first observation.
+            spans.push((0, 0));
+        }
+        let fields = histogram.mut_unknown_fields();
+        fields.add_varint(5, zigzag(i64::from(NATIVE_SCHEMA)));
+        fields.add_fixed64(6, NATIVE_ZERO_THRESHOLD.to_bits());
+        fields.add_varint(7, zero_count);
+        for (offset, length) in spans {
+            fields.add_length_delimited(12, message(|out| {
+                out.write_sint32(1, offset)?;
+                out.write_uint32(2, length)
+            }));
+        }
+        for delta in deltas {
+            fields.add_varint(13, zigzag(delta));
+        }
+    }
+    let Some(series) = series.filter(|_| extras.config.exemplars) else { return };
+    for (bucket, exemplar) in histogram.mut_bucket().iter_mut().zip(&series.exemplars) {
+        let Some(exemplar) = exemplar else { continue };
+        let since_epoch = exemplar.at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let label = message(|out| {
+            out.write_string(1, "trace_id")?;
+            out.write_string(2, &exemplar.trace_id)
+        });
+        let timestamp = message(|out| {
+            out.write_int64(1, since_epoch.as_secs() as i64)?;
+            out.write_int32(2, since_epoch.subsec_nanos() as i32)
+        });
+        let encoded = message(|out| {
+            out.write_bytes(1, &label)?;
+            out.write_double(2, exemplar.value)?;
+            out.write_bytes(3, &timestamp)
+        });
+        bucket.mut_unknown_fields().add_length_delimited(3, encoded);
+    }
+}
+
+fn message(write: impl FnOnce(&mut CodedOutputStream) -> protobuf::ProtobufResult<()>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut out = CodedOutputStream::vec(&mut bytes);
+    write(&mut out).and_then(|_| out.flush()).expect("writing to a Vec cannot fail");
+    drop(out);
+    bytes
+}
+
+fn encode_openmetrics(families: &[MetricFamily], extras: &HashMap<String, Arc<FamilyExtras>>) -> String {
+    let mut out = String::new();
+    for family in families {
+        let name = family.get_name();
+        let kind = family.get_field_type();
+        // OpenMetrics counters are named without `_total`; ours without one stay untyped.
+        let (metadata_name, type_name) = match kind {
+            MetricType::COUNTER => match name.strip_suffix("_total") {
+                Some(stem) => (stem, "counter"),
+                None => (name, "unknown"),
+            },
+            MetricType::GAUGE => (name, "gauge"),
+            MetricType::HISTOGRAM => (name, "histogram"),
+            MetricType::SUMMARY => (name, "summary"),
+            MetricType::UNTYPED => (name, "unknown"),
+        };
+        if !family.get_help().is_empty() {
+            let _ = writeln!(out, "# HELP {metadata_name} {}", escape(family.get_help(), false));
+        }
+        let _ = writeln!(out, "# TYPE {metadata_name} {type_name}");
+        let family_extras = extras.get(name).filter(|e| e.config.exemplars);
+        for metric in family.get_metric() {
+            let labels = metric.get_label();
+            match kind {
+                MetricType::COUNTER => sample(&mut out, name, labels, None, metric.get_counter().get_value()),
+                MetricType::GAUGE => sample(&mut out, name, labels, None, metric.get_gauge().get_value()),
+                MetricType::UNTYPED => sample(&mut out, name, labels, None, metric.get_untyped().get_value()),
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let q = format_value(quantile.get_quantile());
+                        sample(&mut out, name, labels, Some(("quantile", &q)), quantile.get_value());
+                    }
+                    sample(&mut out, &format!("{name}_sum"), labels, None, summary.get_sample_sum());
+                    sample(&mut out, &format!("{name}_count"), labels, None, summary.get_sample_count() as f64);
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let series = family_extras.map(|e| (e, e.series.lock().expect("lock histogram extras")));
+                    let exemplars = series.as_ref().and_then(|(e, s)| s.get(&series_key(labels, &e.label_names))).map(|s| &s.exemplars);
+                    let exemplar_at = |i: usize| exemplars.and_then(|e| e.get(i)).and_then(Option::as_ref);
+                    let bucket_name = format!("{name}_bucket");
+                    let buckets = histogram.get_bucket();
+                    for (i, bucket) in buckets.iter().enumerate() {
+                        let le = format_value(bucket.get_upper_bound());
+                        sample(&mut out, &bucket_name, labels, Some(("le", &le)), bucket.get_cumulative_count() as f64);
+                        exemplar_suffix(&mut out, exemplar_at(i));
+                    }
+                    if buckets.last().is_none_or(|b| b.get_upper_bound() != f64::INFINITY) {
+                        sample(&mut out, &bucket_name, labels, Some(("le", "+Inf")), histogram.get_sample_count() as f64);
+                        exemplar_suffix(&mut out, exemplar_at(buckets.len()));
+                    }
+                    sample(&mut out, &format!("{name}_sum"), labels, None, histogram.get_sample_sum());
+                    sample(&mut out, &format!("{name}_count"), labels, None, histogram.get_sample_count() as f64);
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+/// Writes one sample line, leaving the newline to [`exemplar_suffix`] for histogram buckets.
+fn sample(out: &mut String, name: &str, labels: &[LabelPair], extra: Option<(&str, &str)>, value: f64) {
+    out.push_str(name);
+    let pairs: Vec<(&str, &str)> =
+        labels.iter().map(|l| (l.get_name(), l.get_value())).chain(extra).collect();
+    if !pairs.is_empty() {
+        out.push('{');
+        for (i, (name, value)) in pairs.iter().enumerate() {
+            let _ = write!(out, "{}{name}=\"{}\"", if i == 0 { "" } else { "," }, escape(value, true));
+        }
+        out.push('}');
+    }
+    let _ = write!(out, " {}", format_value(value));
+    if extra.is_none_or(|(name, _)| name != "le") {
+        out.push('\n');
+    }
+}
+
+fn exemplar_suffix(out: &mut String, exemplar: Option<&Exemplar>) {
+    if let Some(exemplar) = exemplar {
+        let at = exemplar.at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let _ = write!(out, " # {{trace_id=\"{}\"}} {} {at:.3}", escape(&exemplar.trace_id, true), format_value(exemplar.value));
+    }
+    out.push('\n');
+}
+
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".into()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".into()
+    } else if value.is_nan() {
+        "NaN".into()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(value: &str, quotes: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '"' if quotes => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_buckets_follow_schema_3_boundaries() {
+        assert_eq!(native_index(1.0), 0);
+        assert_eq!(native_index(1.05), 1);
+        assert_eq!(native_index(2.0), 8);
+        assert_eq!(native_index(0.5), -8);
+        assert_eq!(native_index(0.45), -9);
+    }
+
+    #[test]
+    fn trace_ids_prefer_traceparent_over_x_trace_id() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Trace-ID", "2c7a3f3e-5a55-4b5b-9e0c-1f4f2a6d9b10".parse().unwrap());
+        assert_eq!(trace_id_from_headers(&headers).as_deref(), Some("2c7a3f3e5a554b5b9e0c1f4f2a6d9b10"));
+        headers.insert("traceparent", "00-00000000000000000000000000000000-00f067aa0ba902b7-01".parse().unwrap());
+        assert_eq!(trace_id_from_headers(&headers).as_deref(), Some("2c7a3f3e5a554b5b9e0c1f4f2a6d9b10"), "an all-zero trace id is invalid");
+        headers.insert("traceparent", "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01".parse().unwrap());
+        assert_eq!(trace_id_from_headers(&headers).as_deref(), Some("4bf92f3577b34da6a3ce929d0e0e4736"));
+    }
+}
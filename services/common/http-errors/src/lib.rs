@@ -1,5 +1,6 @@
 use axum::{http::{StatusCode, HeaderValue}, response::{IntoResponse, Response}, Json};
 use serde::Serialize;
+use serde_json::Value;
 use uuid::Uuid;
 
 #[derive(Serialize, Debug)]
@@ -8,76 +9,159 @@ pub struct ErrorBody {
     #[serde(skip_serializing_if = "Option::is_none")] pub missing_role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] pub trace_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")] pub message: Option<String>,
+    // Machine-readable specifics, e.g. `{"fields": [{"field": "email", "code": "required"}]}` on a 422
+    #[serde(skip_serializing_if = "Option::is_none")] pub details: Option<Value>,
+}
+
+/// One rejected input field in a `validation_failed` error's details.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")] pub message: Option<String>,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &'static str) -> Self { Self { field: field.into(), code, message: None } }
+    pub fn with_message(mut self, message: impl Into<String>) -> Self { self.message = Some(message.into()); self }
 }
 
 #[derive(Debug)]
 pub enum ApiError {
     ForbiddenMissingRole { role: &'static str, trace_id: Option<Uuid> },
     Forbidden { trace_id: Option<Uuid> },
+    // 403 with a specific machine-readable code (e.g., tenant suspended)
+    ForbiddenCode { code: &'static str, trace_id: Option<Uuid>, message: Option<String> },
     BadRequest { code: &'static str, trace_id: Option<Uuid>, message: Option<String> },
     // 409 Conflict errors (e.g., invalid state transitions)
     Conflict { code: &'static str, trace_id: Option<Uuid>, message: Option<String> },
     NotFound { code: &'static str, trace_id: Option<Uuid> },
+    // 428 Precondition Required (e.g., missing If-Match on an optimistic-concurrency update)
+    PreconditionRequired { code: &'static str, trace_id: Option<Uuid> },
+    // 422 for a well-formed request that fails validation; `details` carries field-level errors
+    UnprocessableEntity { code: &'static str, trace_id: Option<Uuid>, message: Option<String>, details: Option<Value> },
+    // 429 when a caller is over a rate or concurrency limit; sets Retry-After when known
+    TooManyRequests { code: &'static str, trace_id: Option<Uuid>, retry_after_secs: Option<u64> },
     Internal { trace_id: Option<Uuid>, message: Option<String> },
+    // 503 for a saturated dependency (e.g. no database connection within the acquire timeout); sets Retry-After
+    ServiceUnavailable { code: &'static str, trace_id: Option<Uuid>, retry_after_secs: u64 },
 }
 
 impl ApiError {
     pub fn internal<E: std::fmt::Display>(e: E, trace_id: Option<Uuid>) -> Self { Self::Internal { trace_id, message: Some(e.to_string()) } }
     pub fn bad_request(code: &'static str, trace_id: Option<Uuid>) -> Self { Self::BadRequest { code, trace_id, message: None } }
+    pub fn conflict(code: &'static str, trace_id: Option<Uuid>) -> Self { Self::Conflict { code, trace_id, message: None } }
+    pub fn too_many_requests(code: &'static str, trace_id: Option<Uuid>, retry_after_secs: Option<u64>) -> Self { Self::TooManyRequests { code, trace_id, retry_after_secs } }
+    /// 422 `validation_failed` listing every rejected field, so clients can flag them all at once.
+    pub fn validation_failed(fields: Vec<FieldError>, trace_id: Option<Uuid>) -> Self {
+        Self::UnprocessableEntity { code: "validation_failed", trace_id, message: None, details: Some(serde_json::json!({ "fields": fields })) }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::ForbiddenMissingRole { .. } | ApiError::Forbidden { .. } | ApiError::ForbiddenCode { .. } => StatusCode::FORBIDDEN,
+            ApiError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+            ApiError::PreconditionRequired { .. } => StatusCode::PRECONDITION_REQUIRED,
+            ApiError::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            ApiError::ServiceUnavailable { retry_after_secs, .. } => Some(*retry_after_secs),
+            ApiError::TooManyRequests { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        };
         let (status, body, error_code) = match self {
             ApiError::ForbiddenMissingRole { role, trace_id } => (
                 StatusCode::FORBIDDEN,
-                ErrorBody { code: "missing_role".into(), missing_role: Some(role.into()), trace_id, message: None },
+                ErrorBody { code: "missing_role".into(), missing_role: Some(role.into()), trace_id, message: None, details: None },
                 "missing_role"
             ),
             ApiError::Forbidden { trace_id } => (
                 StatusCode::FORBIDDEN,
-                ErrorBody { code: "forbidden".into(), missing_role: None, trace_id, message: None },
+                ErrorBody { code: "forbidden".into(), missing_role: None, trace_id, message: None, details: None },
                 "forbidden"
             ),
+            ApiError::ForbiddenCode { code, trace_id, message } => (
+                StatusCode::FORBIDDEN,
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message, details: None },
+                code
+            ),
             ApiError::BadRequest { code, trace_id, message } => (
                 StatusCode::BAD_REQUEST,
-                ErrorBody { code: code.into(), missing_role: None, trace_id, message },
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message, details: None },
                 code
             ),
             ApiError::Conflict { code, trace_id, message } => (
                 StatusCode::CONFLICT,
-                ErrorBody { code: code.into(), missing_role: None, trace_id, message },
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message, details: None },
                 code
             ),
             ApiError::NotFound { code, trace_id } => (
                 StatusCode::NOT_FOUND,
-                ErrorBody { code: code.into(), missing_role: None, trace_id, message: None },
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message: None, details: None },
+                code
+            ),
+            ApiError::PreconditionRequired { code, trace_id } => (
+                StatusCode::PRECONDITION_REQUIRED,
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message: None, details: None },
+                code
+            ),
+            ApiError::UnprocessableEntity { code, trace_id, message, details } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message, details },
+                code
+            ),
+            ApiError::TooManyRequests { code, trace_id, .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message: None, details: None },
                 code
             ),
             ApiError::Internal { trace_id, message } => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorBody { code: "internal_error".into(), missing_role: None, trace_id, message },
+                ErrorBody { code: "internal_error".into(), missing_role: None, trace_id, message, details: None },
                 "internal_error"
             ),
+            ApiError::ServiceUnavailable { code, trace_id, .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorBody { code: code.into(), missing_role: None, trace_id, message: None, details: None },
+                code
+            ),
         };
         let mut resp = (status, Json(body)).into_response();
         if let Ok(val) = HeaderValue::from_str(error_code) {
             resp.headers_mut().insert("X-Error-Code", val);
         }
+        if let Some(secs) = retry_after {
+            resp.headers_mut().insert("Retry-After", HeaderValue::from(secs));
+        }
         resp
     }
 }
 
 pub type ApiResult<T> = Result<T, ApiError>;
 
+pub mod etag;
+pub mod exposition;
+pub mod validation;
+
 // Shared HTTP error metrics middleware helper
 use once_cell::sync::Lazy;
-use prometheus::{IntCounterVec, Opts, IntCounter, IntGauge};
+use prometheus::{HistogramOpts, IntCounterVec, Opts, IntCounter, IntGauge};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::collections::HashSet;
 use std::sync::Mutex;
-use axum::{body::Body, http::Request};
+use axum::{body::Body, extract::MatchedPath, http::Request};
 use axum::middleware::Next;
+use std::time::Instant;
 
 static HTTP_ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
     let c = IntCounterVec::new(
@@ -118,23 +202,107 @@ static HTTP_ERROR_CODE_SATURATION: Lazy<IntGauge> = Lazy::new(|| {
     g
 });
 
+// Labelled by the matched route pattern (`/orders/:order_id`), never the raw path. Carries
+// trace exemplars and native buckets when METRICS_EXEMPLARS / METRICS_NATIVE_HISTOGRAMS are set.
+static HTTP_REQUEST_DURATION_SECONDS: Lazy<exposition::LatencyHistogram> = Lazy::new(|| {
+    exposition::LatencyHistogram::new(
+        HistogramOpts::new(
+            "http_request_duration_seconds",
+            "Request latency by matched route and status class",
+        ).buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+        &["service", "route", "status_class"],
+        exposition::HistogramExtras::from_env(),
+    ).expect("http_request_duration_seconds")
+});
+
+static HTTP_ROUTE_OVERFLOW_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    let c = IntCounter::new(
+        "http_route_overflow_total",
+        "Count of requests whose route label was replaced by overflow due to cardinality guard",
+    ).expect("http_route_overflow_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static HTTP_ROUTES_DISTINCT: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new(
+        "http_routes_distinct",
+        "Current number of distinct route labels being tracked (capped by guard)",
+    ).expect("http_routes_distinct");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
 // Cardinality guard: limit the number of distinct error codes to avoid metrics explosion.
 const MAX_ERROR_CODES: usize = 40; // tunable threshold
 static ERROR_CODE_COUNT: AtomicUsize = AtomicUsize::new(0);
 static OBSERVED_CODES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 const OVERFLOW_CODE: &str = "_overflow"; // label used when limit exceeded
 
-/// Returns an Axum middleware function that records HTTP error counts.
+// Same guard for routes. Patterns are bounded by the router, but nested routers and
+// per-tenant path prefixes can still multiply them.
+const MAX_ROUTES: usize = 150;
+static OBSERVED_ROUTES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// Requests no route matched (404 fallbacks, probes for random paths) share one label.
+const UNMATCHED_ROUTE: &str = "_unmatched";
+
+fn route_label(matched: Option<String>) -> String {
+    let Some(route) = matched else { return UNMATCHED_ROUTE.to_string() };
+    let mut set = OBSERVED_ROUTES.lock().expect("lock observed routes");
+    if set.contains(&route) {
+        route
+    } else if set.len() < MAX_ROUTES {
+        set.insert(route.clone());
+        HTTP_ROUTES_DISTINCT.set(set.len() as i64);
+        route
+    } else {
+        HTTP_ROUTE_OVERFLOW_TOTAL.inc();
+        OVERFLOW_CODE.to_string()
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Label for error responses that carry no X-Error-Code (e.g. axum's own extractor rejections).
+fn fallback_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+        _ => "unknown",
+    }
+}
+
+/// Returns an Axum middleware function that records HTTP error counts and per-route latency.
 /// Usage: .layer(axum::middleware::from_fn(http_error_metrics_layer("service-name")))
+/// Add it with `Router::layer` after the routes so the matched route is known; requests it
+/// sees before routing are labelled `_unmatched`.
 type HttpErrFuture = std::pin::Pin<Box<dyn std::future::Future<Output = Result<axum::response::Response, ApiError>> + Send>>;
 pub fn http_error_metrics_layer(service_name: &'static str) -> impl Fn(Request<Body>, Next) -> HttpErrFuture + Clone + Send + Sync + 'static {
     move |req: Request<Body>, next: Next| {
         let svc = service_name;
         Box::pin(async move {
+            let matched = req.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string());
+            let trace_id = exposition::trace_id_from_headers(req.headers());
+            let started = Instant::now();
             let resp = next.run(req).await;
             let status = resp.status();
+            let route = route_label(matched);
+            HTTP_REQUEST_DURATION_SECONDS.observe(
+                &[svc, &route, status_class(status)],
+                started.elapsed().as_secs_f64(),
+                trace_id.as_deref(),
+            );
             if status.as_u16() >= 400 {
-                let raw_code = resp.headers().get("X-Error-Code").and_then(|v| v.to_str().ok()).unwrap_or("unknown");
+                let raw_code = resp.headers().get("X-Error-Code").and_then(|v| v.to_str().ok()).unwrap_or_else(|| fallback_code(status));
                 let code = if raw_code == OVERFLOW_CODE { OVERFLOW_CODE } else {
                     // Track distinct codes under guard
                     let mut set = OBSERVED_CODES.lock().expect("lock observed codes");
@@ -176,6 +344,12 @@ pub mod test_helpers {
         if expected_code == "internal_error" {
             assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
         }
+        if matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) {
+            // Retry-After is optional on 429 but must be a whole number of seconds when present.
+            if let Some(v) = headers.get("Retry-After") {
+                assert!(v.to_str().ok().and_then(|v| v.parse::<u64>().ok()).is_some(), "Retry-After must be seconds: {v:?}");
+            }
+        }
     }
 
     /// Test-only: simulate recording an error code just like the middleware would (without building an HTTP response)
@@ -194,18 +368,47 @@ pub mod test_helpers {
         }
     }
 
+    /// Renders `err` and asserts its status and code; returns the parsed body so callers can check `details`.
+    pub async fn assert_error_status(err: ApiError, expected_status: StatusCode, expected_code: &str) -> serde_json::Value {
+        let resp = err.into_response();
+        assert_eq!(resp.status(), expected_status);
+        assert_eq!(resp.headers().get("X-Error-Code").and_then(|v| v.to_str().ok()), Some(expected_code));
+        let body_bytes = to_bytes(resp.into_body(), 1024 * 64).await.expect("read body");
+        let body: serde_json::Value = serde_json::from_slice(&body_bytes).expect("json body");
+        assert_eq!(body["code"], expected_code);
+        body
+    }
+
+    /// Asserts a `validation_failed` error names exactly `fields` (in order).
+    pub async fn assert_field_errors(err: ApiError, fields: &[&str]) {
+        let body = assert_error_status(err, StatusCode::UNPROCESSABLE_ENTITY, "validation_failed").await;
+        let named: Vec<&str> = body["details"]["fields"].as_array().expect("details.fields").iter().filter_map(|f| f["field"].as_str()).collect();
+        assert_eq!(named, fields);
+    }
+
     pub fn distinct_gauge() -> i64 { HTTP_ERROR_CODES_DISTINCT.get() }
     pub fn overflow_count() -> u64 { HTTP_ERROR_CODE_OVERFLOW_TOTAL.get() }
     pub fn saturation_percent() -> i64 { HTTP_ERROR_CODE_SATURATION.get() }
+    pub fn distinct_routes() -> i64 { HTTP_ROUTES_DISTINCT.get() }
+    pub fn route_overflow_count() -> u64 { HTTP_ROUTE_OVERFLOW_TOTAL.get() }
+    /// Requests observed for `service`/`route`/`status_class`, e.g. `("svc", "/orders/:id", "5xx")`.
+    pub fn route_request_count(service: &str, route: &str, status_class: &str) -> u64 {
+        HTTP_REQUEST_DURATION_SECONDS.vec().with_label_values(&[service, route, status_class]).get_sample_count()
+    }
 }
 
 /// Test-only assertion macro for validating an ApiError's rendered response structure.
 /// Usage:
 /// assert_api_error!(err, "missing_role");
+/// let body = assert_api_error!(err, "validation_failed", StatusCode::UNPROCESSABLE_ENTITY);
 #[macro_export]
 macro_rules! assert_api_error {
     ($err:expr, $code:expr) => {{
         let err: $crate::ApiError = $err; // type ascription if inference ambiguous
         $crate::test_helpers::assert_error_shape(err, $code).await;
     }};
+    ($err:expr, $code:expr, $status:expr) => {{
+        let err: $crate::ApiError = $err;
+        $crate::test_helpers::assert_error_status(err, $status, $code).await
+    }};
 }
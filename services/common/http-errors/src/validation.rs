@@ -0,0 +1,190 @@
+//! Field-level validation for request bodies. A request struct implements [`Validate`] by
+//! running its checks against a [`Validator`]; every failing field is collected, so the client
+//! gets one 422 `validation_failed` naming all of them instead of fixing them one round trip at a
+//! time.
+//!
+//! ```ignore
+//! impl Validate for NewCustomer {
+//!     fn validate(&self, v: &mut Validator) {
+//!         v.required("name", &self.name);
+//!         v.email("email", self.email.as_deref());
+//!     }
+//! }
+//!
+//! new_customer.ensure_valid(sec.trace_id)?;
+//! ```
+//!
+//! Checks only look at the request itself. Rules that need the database or tenant settings
+//! (stock, tip policy, open business day) stay in the handlers.
+
+use crate::{ApiError, FieldError};
+use uuid::Uuid;
+
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+
+    /// `Err` with a 422 listing every invalid field.
+    fn ensure_valid(&self, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        self.validate(&mut v);
+        v.finish(trace_id)
+    }
+}
+
+/// Collects [`FieldError`]s. Each check returns whether it passed, so dependent checks can be
+/// skipped once a field has already failed.
+#[derive(Debug, Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// Records `code` against `field` unless `ok`.
+    pub fn check(&mut self, ok: bool, field: &str, code: &'static str) -> bool {
+        if !ok {
+            self.errors.push(FieldError::new(self.path(field), code));
+        }
+        ok
+    }
+
+    /// Like [`check`](Self::check), with a human-readable message for the field.
+    pub fn check_with(&mut self, ok: bool, field: &str, code: &'static str, message: impl FnOnce() -> String) -> bool {
+        if !ok {
+            self.errors.push(FieldError::new(self.path(field), code).with_message(message()));
+        }
+        ok
+    }
+
+    /// Not empty once trimmed (`required`).
+    pub fn required(&mut self, field: &str, value: &str) -> bool {
+        self.check(!value.trim().is_empty(), field, "required")
+    }
+
+    /// At most `max` characters (`too_long`).
+    pub fn max_len(&mut self, field: &str, value: &str, max: usize) -> bool {
+        self.check_with(value.chars().count() <= max, field, "too_long", || format!("must be at most {max} characters"))
+    }
+
+    /// An optional address that, when present and not blank, looks like `local@domain.tld` (`invalid_email`).
+    pub fn email(&mut self, field: &str, value: Option<&str>) -> bool {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else { return true };
+        let valid = match value.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.') && !value.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+        self.check(valid && value.len() <= 254, field, "invalid_email")
+    }
+
+    /// `value` is one of `allowed` (`not_allowed`); the message lists the choices.
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) -> bool {
+        self.check_with(allowed.contains(&value), field, "not_allowed", || format!("must be one of: {}", allowed.join(", ")))
+    }
+
+    /// Validates each element of a list under `field[i].`, e.g. `items[2].quantity`.
+    pub fn each<T: Validate>(&mut self, field: &str, items: &[T]) {
+        for (i, item) in items.iter().enumerate() {
+            self.nested(&format!("{field}[{i}]"), item);
+        }
+    }
+
+    /// Validates a nested object under `field.`.
+    pub fn nested<T: Validate>(&mut self, field: &str, item: &T) {
+        let path = self.path(field);
+        let outer = std::mem::replace(&mut self.prefix, path);
+        item.validate(self);
+        self.prefix = outer;
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn finish(self, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::validation_failed(self.errors, trace_id))
+        }
+    }
+
+    fn path(&self, field: &str) -> String {
+        if self.prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", self.prefix, field)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Line {
+        quantity: i32,
+    }
+
+    impl Validate for Line {
+        fn validate(&self, v: &mut Validator) {
+            v.check(self.quantity > 0, "quantity", "must_be_positive");
+        }
+    }
+
+    struct Order {
+        name: String,
+        email: Option<String>,
+        lines: Vec<Line>,
+    }
+
+    impl Validate for Order {
+        fn validate(&self, v: &mut Validator) {
+            if v.required("name", &self.name) {
+                v.max_len("name", &self.name, 5);
+            }
+            v.email("email", self.email.as_deref());
+            v.check(!self.lines.is_empty(), "lines", "required");
+            v.each("lines", &self.lines);
+        }
+    }
+
+    fn fields(order: &Order) -> Vec<(String, &'static str)> {
+        let mut v = Validator::default();
+        order.validate(&mut v);
+        v.errors.into_iter().map(|e| (e.field, e.code)).collect()
+    }
+
+    #[test]
+    fn collects_every_failure_with_nested_paths() {
+        let order = Order { name: " ".into(), email: Some("nobody".into()), lines: vec![Line { quantity: 1 }, Line { quantity: 0 }] };
+        assert_eq!(
+            fields(&order),
+            vec![("name".into(), "required"), ("email".into(), "invalid_email"), ("lines[1].quantity".into(), "must_be_positive")]
+        );
+        let order = Order { name: "Too long".into(), email: None, lines: vec![] };
+        assert_eq!(fields(&order), vec![("name".into(), "too_long"), ("lines".into(), "required")]);
+    }
+
+    #[test]
+    fn email_accepts_plain_addresses_and_blanks() {
+        let mut v = Validator::default();
+        assert!(v.email("email", Some("a.b@example.co")));
+        assert!(v.email("email", Some("  ")));
+        assert!(v.email("email", None));
+        assert!(!v.email("email", Some("a@example")));
+        assert!(!v.email("email", Some("a b@example.com")));
+        assert!(!v.email("email", Some("@example.com")));
+    }
+
+    #[test]
+    fn valid_requests_pass() {
+        let order = Order { name: "Ok".into(), email: Some("x@y.io".into()), lines: vec![Line { quantity: 2 }] };
+        assert!(order.ensure_valid(None).is_ok());
+        assert!(matches!(
+            Order { name: String::new(), ..order }.ensure_valid(None),
+            Err(ApiError::UnprocessableEntity { code: "validation_failed", .. })
+        ));
+    }
+}
@@ -19,6 +19,14 @@ fn forbidden_variant() {
     assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "forbidden");
 }
 
+#[test]
+fn forbidden_code_variant() {
+    let err = ApiError::ForbiddenCode { code: "tenant_suspended", trace_id: None, message: None };
+    let resp = err.into_response();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "tenant_suspended");
+}
+
 #[test]
 fn bad_request_variant() {
     let err = ApiError::BadRequest { code: "invalid_something", trace_id: None, message: None };
@@ -35,6 +43,14 @@ fn not_found_variant() {
     assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "missing_resource");
 }
 
+#[test]
+fn precondition_required_variant() {
+    let err = ApiError::PreconditionRequired { code: "if_match_required", trace_id: None };
+    let resp = err.into_response();
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "if_match_required");
+}
+
 #[test]
 fn internal_variant() {
     let trace = Some(Uuid::new_v4());
@@ -43,3 +59,41 @@ fn internal_variant() {
     assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
     assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "internal_error");
 }
+
+#[test]
+fn service_unavailable_variant() {
+    let err = ApiError::ServiceUnavailable { code: "db_pool_exhausted", trace_id: None, retry_after_secs: 2 };
+    let resp = err.into_response();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "db_pool_exhausted");
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "2");
+}
+
+#[tokio::test]
+async fn unprocessable_entity_carries_field_details() {
+    use common_http_errors::{test_helpers::assert_field_errors, FieldError};
+    let err = ApiError::validation_failed(vec![FieldError::new("email", "required"), FieldError::new("quantity", "must_be_positive").with_message("Quantity must be positive")], None);
+    assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_field_errors(err, &["email", "quantity"]).await;
+
+    let err = ApiError::UnprocessableEntity { code: "invalid_sku", trace_id: None, message: None, details: None };
+    let body = common_http_errors::assert_api_error!(err, "invalid_sku", StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.get("details").is_none(), "absent details are omitted: {body}");
+}
+
+#[test]
+fn too_many_requests_variant() {
+    let resp = ApiError::too_many_requests("rate_limited", None, Some(30)).into_response();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "rate_limited");
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "30");
+    let resp = ApiError::too_many_requests("rate_limited", None, None).into_response();
+    assert!(resp.headers().get("Retry-After").is_none());
+}
+
+#[test]
+fn conflict_variant() {
+    let resp = ApiError::conflict("order_already_voided", None).into_response();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "order_already_voided");
+}
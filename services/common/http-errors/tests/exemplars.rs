@@ -0,0 +1,52 @@
+use common_http_errors::exposition::{encode, HistogramExtras, LatencyHistogram, OPENMETRICS_FORMAT, PROTOBUF_FORMAT, TEXT_FORMAT};
+use prometheus::proto::MetricFamily;
+use prometheus::HistogramOpts;
+use protobuf::{CodedInputStream, Message};
+
+const TRACE: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+#[test]
+fn exemplars_and_native_buckets_only_reach_scrapers_that_ask() {
+    let histogram = LatencyHistogram::new(
+        HistogramOpts::new("exemplar_test_seconds", "test latency").buckets(vec![0.1, 1.0]),
+        &["route"],
+        HistogramExtras { exemplars: true, native: true },
+    )
+    .unwrap();
+    histogram.observe(&["/orders/:id"], 0.05, Some(TRACE));
+    histogram.observe(&["/orders/:id"], 0.051, None);
+    histogram.observe(&["/orders/:id"], 3.0, Some("ffffffffffffffffffffffffffffffff"));
+    let families = prometheus::default_registry().gather();
+
+    let classic = encode(&families, Some("text/plain")).unwrap();
+    assert_eq!(classic.content_type, TEXT_FORMAT);
+    let text = String::from_utf8(classic.body).unwrap();
+    assert!(text.contains("exemplar_test_seconds_bucket{route=\"/orders/:id\",le=\"0.1\"} 2\n"), "{text}");
+    assert!(!text.contains(TRACE), "classic text has no exemplars");
+
+    let open = encode(&families, Some("application/openmetrics-text;version=1.0.0,text/plain;q=0.5")).unwrap();
+    assert_eq!(open.content_type, OPENMETRICS_FORMAT);
+    let text = String::from_utf8(open.body).unwrap();
+    assert!(text.contains(&format!("exemplar_test_seconds_bucket{{route=\"/orders/:id\",le=\"0.1\"}} 2 # {{trace_id=\"{TRACE}\"}} 0.05 ")), "{text}");
+    assert!(text.contains("le=\"+Inf\"} 3 # {trace_id=\"ffffffffffffffffffffffffffffffff\"} 3 "), "{text}");
+    assert!(text.ends_with("# EOF\n"));
+
+    let proto = encode(&families, Some("application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited")).unwrap();
+    assert_eq!(proto.content_type, PROTOBUF_FORMAT);
+    let mut input = CodedInputStream::from_bytes(&proto.body);
+    let mut ours = None;
+    while !input.eof().unwrap() {
+        let family: MetricFamily = input.read_message().unwrap();
+        if family.get_name() == "exemplar_test_seconds" {
+            ours = Some(family);
+        }
+    }
+    let family = ours.expect("histogram in protobuf scrape");
+    let histogram = family.get_metric()[0].get_histogram();
+    let native = histogram.get_unknown_fields();
+    assert!(native.get(5).is_some(), "schema");
+    assert_eq!(native.get(12).map(|v| v.length_delimited.len()), Some(2), "0.05/0.051 and 3.0 fall in separate spans");
+    assert_eq!(native.get(13).map(|v| v.varint.len()), Some(2), "one delta per populated native bucket");
+    let exemplar = histogram.get_bucket()[0].get_unknown_fields().get(3).expect("exemplar on the 0.1 bucket");
+    assert!(exemplar.length_delimited[0].windows(TRACE.len()).any(|w| w == TRACE.as_bytes()));
+}
@@ -0,0 +1,46 @@
+use common_http_errors::test_helpers::{distinct_routes, route_overflow_count, route_request_count};
+use common_http_errors::{http_error_metrics_layer, ApiError};
+use axum::{Router, routing::get, http::{Request, StatusCode}, body::Body, middleware};
+use tower::ServiceExt; // for oneshot
+
+async fn ok() -> &'static str { "ok" }
+
+async fn fail() -> Result<&'static str, ApiError> {
+    Err(ApiError::Internal { trace_id: None, message: None })
+}
+
+async fn send(app: &Router, uri: &str) -> StatusCode {
+    app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn latency_is_labelled_by_route_pattern_and_status_class_with_capped_routes() {
+    let app = Router::new()
+        .route("/orders/:order_id", get(ok))
+        .route("/fail", get(fail))
+        .layer(middleware::from_fn(http_error_metrics_layer("route-svc")));
+
+    assert_eq!(send(&app, "/orders/1").await, StatusCode::OK);
+    assert_eq!(send(&app, "/orders/2").await, StatusCode::OK);
+    assert_eq!(send(&app, "/fail").await, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(send(&app, "/no/such/path").await, StatusCode::NOT_FOUND);
+
+    assert_eq!(route_request_count("route-svc", "/orders/:order_id", "2xx"), 2, "raw ids never become labels");
+    assert_eq!(route_request_count("route-svc", "/orders/1", "2xx"), 0);
+    assert_eq!(route_request_count("route-svc", "/fail", "5xx"), 1);
+    assert_eq!(route_request_count("route-svc", "_unmatched", "4xx"), 1);
+
+    // The guard is process-wide, so it is exercised after the checks above.
+    let mut app = Router::new();
+    for i in 0..160 {
+        app = app.route(&format!("/r{i}"), get(ok));
+    }
+    let app = app.layer(middleware::from_fn(http_error_metrics_layer("route-guard-svc")));
+    let before = route_overflow_count();
+    for i in 0..160 {
+        assert_eq!(send(&app, &format!("/r{i}")).await, StatusCode::OK);
+    }
+    assert!(distinct_routes() <= 150, "route labels should be capped at 150, got {}", distinct_routes());
+    assert!(route_overflow_count() > before, "expected route overflow counter to increment");
+    assert!(route_request_count("route-guard-svc", "_overflow", "2xx") > 0);
+}
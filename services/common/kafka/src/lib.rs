@@ -0,0 +1,374 @@
+//! Kafka producer shared by the services.
+//!
+//! [`ProducerSettings::create`] builds an idempotent `acks=all` producer with bounded retries and
+//! delivery timeout, so a send either lands exactly once in the partition log or fails with an
+//! error after `KAFKA_DELIVERY_TIMEOUT_MS`. Sends go through [`publish`], which records
+//! `kafka_producer_*` metrics per topic and trips a process-wide circuit breaker after
+//! `KAFKA_CIRCUIT_FAILURE_THRESHOLD` consecutive delivery failures. While the circuit is open
+//! sends are rejected immediately instead of each waiting out the delivery timeout.
+//!
+//! Domain events go through [`publish_event`]: when the broker is unavailable (send failed or
+//! circuit open) the event is staged in the shared `outbox` table and order-service's relay
+//! publishes it once Kafka is back. Delivery is at-least-once; consumers dedupe through their
+//! inbox tables. Call [`flush`] on shutdown so buffered messages are not dropped.
+
+use common_config::{ConfigError, EnvReader, KafkaSecurity};
+use common_events::DomainEvent;
+use once_cell::sync::Lazy;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry};
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::message::ToBytes;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::ClientConfig;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How long [`flush`] waits for buffered messages during shutdown.
+pub const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Producer delivery and circuit breaker settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProducerSettings {
+    /// Upper bound on a send, retries included (`message.timeout.ms`).
+    pub delivery_timeout_ms: u64,
+    pub retries: u32,
+    pub retry_backoff_ms: u64,
+    /// How long the producer waits to batch messages before sending.
+    pub linger_ms: u64,
+    /// Consecutive delivery failures that open the circuit.
+    pub circuit_failure_threshold: u32,
+    /// How long the circuit stays open before a send is tried again.
+    pub circuit_open_secs: u64,
+}
+
+impl Default for ProducerSettings {
+    fn default() -> Self {
+        Self {
+            delivery_timeout_ms: 30_000,
+            retries: 10,
+            retry_backoff_ms: 200,
+            linger_ms: 5,
+            circuit_failure_threshold: 5,
+            circuit_open_secs: 30,
+        }
+    }
+}
+
+impl ProducerSettings {
+    /// Read `KAFKA_DELIVERY_TIMEOUT_MS` (default 30000), `KAFKA_PRODUCER_RETRIES` (10),
+    /// `KAFKA_RETRY_BACKOFF_MS` (200), `KAFKA_LINGER_MS` (5), `KAFKA_CIRCUIT_FAILURE_THRESHOLD` (5)
+    /// and `KAFKA_CIRCUIT_OPEN_SECS` (30).
+    pub fn read(env: &mut EnvReader) -> Self {
+        let defaults = Self::default();
+        let delivery_timeout_ms = env.or("KAFKA_DELIVERY_TIMEOUT_MS", defaults.delivery_timeout_ms);
+        env.check("KAFKA_DELIVERY_TIMEOUT_MS", delivery_timeout_ms > 0, "must be greater than 0");
+        let retries = env.or("KAFKA_PRODUCER_RETRIES", defaults.retries);
+        let retry_backoff_ms = env.or("KAFKA_RETRY_BACKOFF_MS", defaults.retry_backoff_ms);
+        env.check(
+            "KAFKA_RETRY_BACKOFF_MS",
+            retry_backoff_ms < delivery_timeout_ms,
+            "must be less than KAFKA_DELIVERY_TIMEOUT_MS",
+        );
+        let linger_ms = env.or("KAFKA_LINGER_MS", defaults.linger_ms);
+        let circuit_failure_threshold = env.or("KAFKA_CIRCUIT_FAILURE_THRESHOLD", defaults.circuit_failure_threshold);
+        env.check("KAFKA_CIRCUIT_FAILURE_THRESHOLD", circuit_failure_threshold > 0, "must be greater than 0");
+        let circuit_open_secs = env.or("KAFKA_CIRCUIT_OPEN_SECS", defaults.circuit_open_secs);
+        env.check("KAFKA_CIRCUIT_OPEN_SECS", circuit_open_secs > 0, "must be greater than 0");
+        Self {
+            delivery_timeout_ms,
+            retries,
+            retry_backoff_ms,
+            linger_ms,
+            circuit_failure_threshold,
+            circuit_open_secs,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let settings = Self::read(&mut env);
+        env.finish(|| Some(settings))
+    }
+
+    /// Producer client config for `bootstrap`, with `security` applied.
+    pub fn client_config(&self, bootstrap: &str, security: &KafkaSecurity) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        security
+            .apply(&mut config)
+            .set("bootstrap.servers", bootstrap)
+            .set("enable.idempotence", "true")
+            .set("acks", "all")
+            .set("message.send.max.retries", self.retries.to_string())
+            .set("retry.backoff.ms", self.retry_backoff_ms.to_string())
+            .set("message.timeout.ms", self.delivery_timeout_ms.to_string())
+            .set("linger.ms", self.linger_ms.to_string());
+        config
+    }
+
+    /// Create the producer and apply the circuit breaker settings.
+    pub fn create(&self, bootstrap: &str, security: &KafkaSecurity) -> KafkaResult<FutureProducer> {
+        BREAKER.configure(self.circuit_failure_threshold, Duration::from_secs(self.circuit_open_secs));
+        self.client_config(bootstrap, security).create()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PublishError {
+    #[error("kafka circuit open")]
+    CircuitOpen,
+    #[error("kafka delivery failed: {0}")]
+    Kafka(#[from] KafkaError),
+    #[error(transparent)]
+    Encode(#[from] common_events::EventError),
+    #[error("outbox staging failed: {0}")]
+    Outbox(#[from] sqlx::Error),
+}
+
+/// Where [`publish_event`] left the event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Published,
+    /// Staged in the outbox for the relay to publish.
+    Outboxed,
+}
+
+struct ProducerMetrics {
+    messages: IntCounterVec,
+    delivery_seconds: HistogramVec,
+    circuit_open: IntGauge,
+}
+
+static METRICS: Lazy<ProducerMetrics> = Lazy::new(|| ProducerMetrics {
+    messages: IntCounterVec::new(
+        Opts::new(
+            "kafka_producer_messages_total",
+            "Producer sends by topic and outcome (delivered, failed, rejected, outboxed)",
+        ),
+        &["topic", "outcome"],
+    )
+    .expect("kafka_producer_messages_total"),
+    delivery_seconds: HistogramVec::new(
+        HistogramOpts::new("kafka_producer_delivery_seconds", "Time from send to broker acknowledgement")
+            .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0]),
+        &["topic"],
+    )
+    .expect("kafka_producer_delivery_seconds"),
+    circuit_open: IntGauge::new("kafka_producer_circuit_open", "1 while sends are rejected by the circuit breaker")
+        .expect("kafka_producer_circuit_open"),
+});
+
+/// Add the `kafka_producer_*` series to `registry`. Safe to call for several registries.
+pub fn register_metrics(registry: &Registry) {
+    let _ = registry.register(Box::new(METRICS.messages.clone()));
+    let _ = registry.register(Box::new(METRICS.delivery_seconds.clone()));
+    let _ = registry.register(Box::new(METRICS.circuit_open.clone()));
+}
+
+fn count(topic: &str, outcome: &str) {
+    METRICS.messages.with_label_values(&[topic, outcome]).inc();
+}
+
+struct BreakerState {
+    threshold: u32,
+    open_for: Duration,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Opens after `threshold` consecutive failures. Once `open_for` has passed sends are let through
+/// again; the failure count is only reset by a success, so one more failure reopens it.
+struct Breaker {
+    state: Mutex<BreakerState>,
+}
+
+impl Breaker {
+    fn new(threshold: u32, open_for: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState { threshold, open_for, consecutive_failures: 0, open_until: None }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn configure(&self, threshold: u32, open_for: Duration) {
+        let mut state = self.lock();
+        state.threshold = threshold;
+        state.open_for = open_for;
+    }
+
+    fn allows(&self, now: Instant) -> bool {
+        self.lock().open_until.is_none_or(|until| now >= until)
+    }
+
+    fn record_success(&self) -> bool {
+        let mut state = self.lock();
+        let was_open = state.open_until.is_some();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+        was_open
+    }
+
+    /// Returns true when this failure opened the circuit.
+    fn record_failure(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures < state.threshold {
+            return false;
+        }
+        let was_open = state.open_until.is_some_and(|until| now < until);
+        state.open_until = Some(now + state.open_for);
+        !was_open
+    }
+}
+
+static BREAKER: Lazy<Breaker> = Lazy::new(|| {
+    let defaults = ProducerSettings::default();
+    Breaker::new(defaults.circuit_failure_threshold, Duration::from_secs(defaults.circuit_open_secs))
+});
+
+/// True while sends are being rejected.
+pub fn circuit_open() -> bool {
+    !BREAKER.allows(Instant::now())
+}
+
+/// Send one message and wait for the broker acknowledgement.
+pub async fn publish<P>(producer: &FutureProducer, topic: &str, key: &str, payload: &P) -> Result<(), PublishError>
+where
+    P: ToBytes + ?Sized,
+{
+    if !BREAKER.allows(Instant::now()) {
+        count(topic, "rejected");
+        return Err(PublishError::CircuitOpen);
+    }
+    let started = Instant::now();
+    let result = producer
+        .send(FutureRecord::to(topic).key(key).payload(payload), Duration::from_secs(0))
+        .await;
+    match result {
+        Ok(_) => {
+            METRICS.delivery_seconds.with_label_values(&[topic]).observe(started.elapsed().as_secs_f64());
+            count(topic, "delivered");
+            if BREAKER.record_success() {
+                METRICS.circuit_open.set(0);
+                tracing::info!(topic, "Kafka delivery recovered, closing producer circuit");
+            }
+            Ok(())
+        }
+        Err((err, _)) => {
+            count(topic, "failed");
+            if BREAKER.record_failure(Instant::now()) {
+                METRICS.circuit_open.set(1);
+                tracing::error!(topic, error = %err, "Kafka deliveries failing, opening producer circuit");
+            }
+            Err(PublishError::Kafka(err))
+        }
+    }
+}
+
+/// Publish `event` to `E::TOPIC`, or stage it in the outbox if Kafka is unavailable.
+pub async fn publish_event<E: DomainEvent>(
+    producer: &FutureProducer,
+    db: &PgPool,
+    tenant_id: Uuid,
+    event: &E,
+) -> Result<Delivery, PublishError> {
+    let payload = common_events::encode(event)?;
+    match publish(producer, E::TOPIC, &event.partition_key(), &payload).await {
+        Ok(()) => Ok(Delivery::Published),
+        Err(err @ (PublishError::CircuitOpen | PublishError::Kafka(_))) => {
+            tracing::warn!(topic = E::TOPIC, %tenant_id, error = %err, "Staging event in outbox");
+            stage(db, tenant_id, event).await?;
+            count(E::TOPIC, "outboxed");
+            Ok(Delivery::Outboxed)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Insert `event` into the shared `outbox` table.
+pub async fn stage<'e, E, X>(executor: X, tenant_id: Uuid, event: &E) -> Result<(), PublishError>
+where
+    E: DomainEvent,
+    X: sqlx::PgExecutor<'e>,
+{
+    sqlx::query("INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, $2, $3, $4)")
+        .bind(tenant_id.to_string())
+        .bind(E::TOPIC)
+        .bind(common_events::to_value(event)?)
+        .bind(event.partition_key())
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Wait up to `timeout` for buffered messages to be delivered. Call once the server has stopped
+/// accepting requests.
+pub fn flush(producer: &FutureProducer, timeout: Duration) {
+    let pending = producer.in_flight_count();
+    match producer.flush(timeout) {
+        Ok(()) => tracing::info!(pending, "Flushed Kafka producer"),
+        Err(err) => tracing::warn!(
+            error = %err,
+            undelivered = producer.in_flight_count(),
+            "Kafka producer flush did not complete"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn reader(vars: &[(&str, &str)]) -> EnvReader {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        EnvReader::from_lookup(move |key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn settings_default_and_validate() {
+        let mut env = reader(&[]);
+        let settings = ProducerSettings::read(&mut env);
+        assert_eq!(env.finish(|| Some(settings)).unwrap(), ProducerSettings::default());
+
+        let mut env = reader(&[("KAFKA_DELIVERY_TIMEOUT_MS", "100"), ("KAFKA_RETRY_BACKOFF_MS", "500"), ("KAFKA_CIRCUIT_OPEN_SECS", "0")]);
+        ProducerSettings::read(&mut env);
+        let err = env.finish(|| Some(())).unwrap_err();
+        let keys: Vec<&str> = err.issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["KAFKA_RETRY_BACKOFF_MS", "KAFKA_CIRCUIT_OPEN_SECS"]);
+    }
+
+    #[test]
+    fn client_config_requests_idempotent_acks_all() {
+        let config = ProducerSettings::default().client_config("localhost:9092", &KafkaSecurity::default());
+        assert_eq!(config.get("enable.idempotence"), Some("true"));
+        assert_eq!(config.get("acks"), Some("all"));
+        assert_eq!(config.get("message.timeout.ms"), Some("30000"));
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_half_opens() {
+        let breaker = Breaker::new(3, Duration::from_secs(10));
+        let now = Instant::now();
+        assert!(!breaker.record_failure(now));
+        assert!(!breaker.record_failure(now));
+        assert!(breaker.allows(now));
+        assert!(breaker.record_failure(now));
+        assert!(!breaker.allows(now + Duration::from_secs(5)));
+
+        // After the open period one send is let through; another failure reopens immediately.
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.allows(later));
+        assert!(breaker.record_failure(later));
+        assert!(!breaker.allows(later + Duration::from_secs(1)));
+
+        assert!(breaker.record_success());
+        assert!(breaker.allows(later + Duration::from_secs(1)));
+        assert!(!breaker.record_failure(later));
+    }
+}
@@ -3,7 +3,7 @@ use bigdecimal::BigDecimal;
 use std::str::FromStr;
 
 // Re-export functions from the crate
-use common_money::{normalize_scale, init_rounding_mode_from_env};
+use common_money::{normalize_scale, normalize_scale_with, init_rounding_mode_from_env, Money, RoundingMode};
 
 fn bench_half_up(c: &mut Criterion) {
     std::env::remove_var("MONEY_ROUNDING");
@@ -39,5 +39,44 @@ fn bench_modes_compare(c: &mut Criterion) {
     }
 }
 
-criterion_group!(rounding, bench_half_up, bench_modes_compare);
+// Explicit-mode variant: the global mode is a OnceLock, so `bench_modes_compare` only measures
+// whichever mode was initialized first. These pin the mode per call instead.
+fn bench_modes_explicit(c: &mut Criterion) {
+    let samples: Vec<BigDecimal> = (0..500).map(|i| {
+        let s = format!("{}.{:03}", i, i % 1000);
+        BigDecimal::from_str(&s).unwrap()
+    }).collect();
+
+    for (label, mode) in [("truncate", RoundingMode::Truncate), ("bankers", RoundingMode::Bankers), ("halfup", RoundingMode::HalfUp)] {
+        c.bench_function(format!("round_explicit_{}", label).as_str(), |b| {
+            b.iter(|| {
+                for v in &samples { black_box(normalize_scale_with(v, mode)); }
+            });
+        });
+    }
+}
+
+// Wide-scale and exponent-notation inputs (e.g. tax rate products, values parsed from "1.2345E+3").
+fn bench_wide_scale(c: &mut Criterion) {
+    let samples: Vec<BigDecimal> = [
+        "1.2345E+3", "9.99999999E-1", "123456789.123456789", "-0.0000005", "1E+12", "4.5E-3",
+        "-98765.43215", "0.333333333333333333333333",
+    ].into_iter().map(|s| BigDecimal::from_str(s).unwrap()).collect();
+    c.bench_function("round_wide_scale_halfup", |b| {
+        b.iter(|| {
+            for v in &samples { black_box(normalize_scale_with(v, RoundingMode::HalfUp)); }
+        });
+    });
+}
+
+fn bench_as_cents(c: &mut Criterion) {
+    let samples: Vec<Money> = (0..500).map(|i| Money::from_cents(i * 7_919 - 1_000_000)).collect();
+    c.bench_function("money_as_cents", |b| {
+        b.iter(|| {
+            for m in &samples { black_box(m.as_cents()); }
+        });
+    });
+}
+
+criterion_group!(rounding, bench_half_up, bench_modes_compare, bench_modes_explicit, bench_wide_scale, bench_as_cents);
 criterion_main!(rounding);
@@ -0,0 +1,21 @@
+#![no_main]
+//! Arbitrary decimal strings -> Money -> as_cents must never panic for in-range values and
+//! must agree with the decimal value it was built from.
+
+use bigdecimal::BigDecimal;
+use common_money::Money;
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+    if text.len() > 64 { return; }
+    let Ok(value) = BigDecimal::from_str(text) else { return };
+    // as_cents is documented to panic outside i64 cents; stay comfortably inside that range.
+    if value.abs() >= BigDecimal::from(90_000_000_000_000_000i64) { return; }
+
+    let money = Money::new(value);
+    let cents = money.as_cents();
+    assert_eq!(BigDecimal::new(cents.into(), 2), *money.inner(), "as_cents disagrees for {text}");
+    assert_eq!(Money::from_cents(cents), money, "cents round trip failed for {text}");
+});
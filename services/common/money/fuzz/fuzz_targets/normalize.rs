@@ -0,0 +1,18 @@
+#![no_main]
+//! Every rounding mode must be idempotent, stay within one cent, and emit scale 2.
+
+use bigdecimal::BigDecimal;
+use common_money::{normalize_scale_with, RoundingMode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (i64, u8)| {
+    let (mantissa, scale) = input;
+    let value = BigDecimal::new(mantissa.into(), (scale % 19) as i64);
+    let cent = BigDecimal::new(1.into(), 2);
+    for mode in [RoundingMode::HalfUp, RoundingMode::Truncate, RoundingMode::Bankers] {
+        let rounded = normalize_scale_with(&value, mode);
+        assert_eq!(rounded.as_bigint_and_exponent().1, 2, "{mode:?} scale for {value}");
+        assert_eq!(normalize_scale_with(&rounded, mode), rounded, "{mode:?} not idempotent for {value}");
+        assert!((&rounded - &value).abs() < cent, "{mode:?} moved {value} to {rounded}");
+    }
+});
@@ -1,5 +1,5 @@
-use bigdecimal::{BigDecimal, ToPrimitive};
-use std::str::FromStr;
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+use num_bigint::BigInt;
 use std::sync::OnceLock;
 use sqlx::{postgres::{PgTypeInfo, PgHasArrayType}, Type, Postgres, Encode, Decode};
 use sqlx::encode::IsNull;
@@ -8,6 +8,11 @@ use serde::{Serializer, Deserializer};
 use serde::{Deserialize, Serialize};
 use std::sync::Once;
 
+pub mod measure;
+pub mod parse;
+
+pub use parse::{MoneyLocale, ParseMoneyError};
+
 /// Rounding modes supported (configurable via env in later initialization step)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RoundingMode { HalfUp, Truncate, Bankers }
@@ -21,6 +26,14 @@ impl RoundingMode {
             _ => None,
         }
     }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half-up",
+            RoundingMode::Truncate => "truncate",
+            RoundingMode::Bankers => "bankers",
+        }
+    }
 }
 
 // Global rounding mode (initialized once from env) defaulting to HalfUp.
@@ -74,23 +87,108 @@ pub fn log_rounding_mode_once() {
 
 /// Apply configured rounding to scale=2 using Half-Up (away from zero on .5).
 fn round_scale_2(value: &BigDecimal) -> BigDecimal {
-    let out = match current_rounding_mode() {
-        RoundingMode::HalfUp => half_up(value, 2),
-        RoundingMode::Truncate => truncate(value, 2),
-        RoundingMode::Bankers => bankers(value, 2),
-    };
-    trace_rounding_event(value, &out);
+    round_scale_2_with(value, current_rounding_mode())
+}
+
+fn round_scale_2_with(value: &BigDecimal, mode: RoundingMode) -> BigDecimal {
+    let out = round_with(value, 2, mode);
+    trace_rounding_event(value, &out, mode);
     out
 }
 
+fn round_with(value: &BigDecimal, scale: i32, mode: RoundingMode) -> BigDecimal {
+    match mode {
+        RoundingMode::HalfUp => half_up(value, scale),
+        RoundingMode::Truncate => truncate(value, scale),
+        RoundingMode::Bankers => bankers(value, scale),
+    }
+}
+
 /// Public normalization entrypoint (round + enforce scale 2)
 pub fn normalize_scale(value: &BigDecimal) -> BigDecimal { round_scale_2(value) }
 
+/// Normalization with an explicit mode, bypassing the global `MONEY_ROUNDING` setting.
+pub fn normalize_scale_with(value: &BigDecimal, mode: RoundingMode) -> BigDecimal { round_scale_2_with(value, mode) }
+
 #[inline]
-fn trace_rounding_event(original: &BigDecimal, rounded: &BigDecimal) {
+fn trace_rounding_event(original: &BigDecimal, rounded: &BigDecimal, mode: RoundingMode) {
     // Lightweight debug-only instrumentation; can be swapped for metrics crate later.
     if cfg!(debug_assertions) {
-        tracing::debug!(orig = %original, out = %rounded, mode = ?current_rounding_mode(), "money.normalize");
+        tracing::debug!(orig = %original, out = %rounded, mode = ?mode, "money.normalize");
+    }
+}
+
+/// Exact decimal rate for a basis-point value (1 bps = 0.0001).
+fn bps_rate(rate_bps: i32) -> BigDecimal { BigDecimal::new(rate_bps.into(), 4) }
+
+/// Rounding settings passed down to monetary calculations (e.g. resolved from tenant config)
+/// instead of relying on the process-wide mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundingContext { mode: RoundingMode }
+
+impl RoundingContext {
+    pub const fn new(mode: RoundingMode) -> Self { Self { mode } }
+    /// Context following the process-wide `MONEY_ROUNDING` mode.
+    pub fn global() -> Self { Self::new(current_rounding_mode()) }
+    pub fn mode(&self) -> RoundingMode { self.mode }
+    pub fn normalize(&self, value: &BigDecimal) -> BigDecimal { normalize_scale_with(value, self.mode) }
+    pub fn money(&self, raw: &BigDecimal) -> Money { Money(self.normalize(raw)) }
+    pub fn percent(&self, amount: &Money, rate_bps: i32) -> Money { amount.apply_rate(&bps_rate(rate_bps), self.mode) }
+    pub fn apply_rate(&self, amount: &Money, rate: &BigDecimal) -> Money { amount.apply_rate(rate, self.mode) }
+}
+
+impl Default for RoundingContext {
+    fn default() -> Self { Self::global() }
+}
+
+/// Tenant-level rounding rules: the mode used for scale-2 normalization plus an optional cash
+/// rounding increment applied to payable totals (e.g. `5` for CHF 0.05 rounding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundingPolicy {
+    pub mode: RoundingMode,
+    pub cash_increment_cents: Option<u32>,
+}
+
+impl RoundingPolicy {
+    pub const fn new(mode: RoundingMode) -> Self { Self { mode, cash_increment_cents: None } }
+    /// Policy following the process-wide `MONEY_ROUNDING` mode without cash rounding.
+    pub fn global() -> Self { Self::new(current_rounding_mode()) }
+    /// Sets the cash rounding increment; `0` and `1` cent are treated as no cash rounding.
+    pub fn with_cash_increment(mut self, cents: u32) -> Self {
+        self.cash_increment_cents = (cents > 1).then_some(cents);
+        self
+    }
+    /// Parses `mode` or `mode:increment_cents` (e.g. `half-up:5`).
+    pub fn parse(s: &str) -> Option<Self> {
+        let (mode, increment) = match s.split_once(':') {
+            Some((mode, increment)) => (mode, Some(increment.trim().parse::<u32>().ok()?)),
+            None => (s, None),
+        };
+        let policy = Self::new(RoundingMode::parse(mode)?);
+        Some(match increment { Some(cents) => policy.with_cash_increment(cents), None => policy })
+    }
+    pub fn context(&self) -> RoundingContext { RoundingContext::new(self.mode) }
+    pub fn normalize(&self, value: &BigDecimal) -> BigDecimal { normalize_scale_with(value, self.mode) }
+    /// Rounds a payable amount to the cash increment using the policy mode; unchanged without one.
+    pub fn cash_round(&self, amount: &Money) -> Money {
+        let Some(increment) = self.cash_increment_cents else { return amount.clone(); };
+        let increment = BigDecimal::from(increment);
+        let units = round_with(&(BigDecimal::from(amount.as_cents()) / &increment), 0, self.mode);
+        Money::from_cents((units * increment).to_i64().unwrap_or_else(|| amount.as_cents()))
+    }
+    /// [`Self::cash_round`] on integer cents.
+    pub fn cash_round_cents(&self, cents: i64) -> i64 { self.cash_round(&Money::from_cents(cents)).as_cents() }
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self { Self::global() }
+}
+
+/// Normalization with an optional tenant policy; `None` keeps the env-configured global mode.
+pub fn normalize_scale_for(value: &BigDecimal, policy: Option<&RoundingPolicy>) -> BigDecimal {
+    match policy {
+        Some(policy) => policy.normalize(value),
+        None => normalize_scale(value),
     }
 }
 
@@ -110,104 +208,80 @@ fn init_metrics_once(mode: RoundingMode) {
 pub fn registry() -> Option<&'static prometheus::Registry> { PROM_REGISTRY.get() }
 
 /// Half-up rounding helper: scale target digits; positive & negative symmetrical.
-fn half_up(value: &BigDecimal, scale: i32) -> BigDecimal {
-    // Build factor = 10^scale manually (BigDecimal lacks stable pow for u64 in this crate version)
-    let mut factor = BigDecimal::from(1);
-    for _ in 0..scale { factor *= BigDecimal::from(10u32); }
-    let shifted = value * &factor; // value * 10^scale
-
-    // Determine sign (>=0 treat as positive) using comparison to zero
-    let is_negative = shifted < BigDecimal::from(0);
-    // Add 0.5 or -0.5 depending on sign (away from zero)
-    let adjust_str = if is_negative { "-0.5" } else { "0.5" };
-    let adjusted = &shifted + BigDecimal::from_str(adjust_str).unwrap();
-
-    // Truncate toward zero by converting to string then cutting decimals, avoiding overly large to_i64 casts.
-    let adjusted_str = adjusted.to_string();
-    let int_portion = if let Some(dot) = adjusted_str.find('.') { &adjusted_str[..dot] } else { &adjusted_str };
-    // Handle "-0" artifact
-    let cleaned = if int_portion == "-0" { "0" } else { int_portion };
-    let int_bd = BigDecimal::from_str(cleaned).unwrap();
-    let rounded = int_bd / factor;
-    // Ensure fixed scale with trailing zeros
-    rounded.with_scale(scale as i64)
-}
+fn half_up(value: &BigDecimal, scale: i32) -> BigDecimal { round_digits(value, scale, RoundingMode::HalfUp) }
 
 /// Truncate toward zero at given scale.
-fn truncate(value: &BigDecimal, scale: i32) -> BigDecimal {
-    // factor = 10^scale
-    let mut factor = BigDecimal::from(1);
-    for _ in 0..scale { factor *= BigDecimal::from(10u32); }
-    let shifted = value * &factor;
-    let s = shifted.to_string();
-    let int_portion = if let Some(dot) = s.find('.') { &s[..dot] } else { &s };
-    let cleaned = if int_portion == "-0" { "0" } else { int_portion };
-    let int_bd = BigDecimal::from_str(cleaned).unwrap();
-    (int_bd / factor).with_scale(scale as i64)
-}
+fn truncate(value: &BigDecimal, scale: i32) -> BigDecimal { round_digits(value, scale, RoundingMode::Truncate) }
 
 /// Bankers (round half to even) at given scale.
-fn bankers(value: &BigDecimal, scale: i32) -> BigDecimal {
-    // factor = 10^scale
-    let mut factor = BigDecimal::from(1);
-    for _ in 0..scale { factor *= BigDecimal::from(10u32); }
-    let shifted = value * &factor; // exact scaled number
-    let s = shifted.to_string();
-    let negative = s.starts_with('-');
-    let abs = if negative { &s[1..] } else { &s }; // strip sign
-    let (int_str, frac_str) = if let Some(dot) = abs.find('.') { (&abs[..dot], &abs[dot+1..]) } else { (abs, "") };
-    // Normalize fractional substring (we only care if it's >, <, or == 0.5)
-    // Compare using BigDecimal might reintroduce rounding; use string logic.
-    let cmp_half = {
-        if frac_str.is_empty() { -1 } // no fraction => < 0.5
-        else {
-            // Build a comparison on first digit and beyond
-            let first = frac_str.chars().next().unwrap();
-            match first {
-                '0' => if frac_str.len() == 1 { -1 } else { // e.g. 0xxxxx < 0.5
-                    // any non-zero after still < 0.5
-                    -1
-                },
-                '1'..='4' => -1,
-                '5' => {
-                    // Check if exactly 0.5 == all remaining digits zero
-                    if frac_str.chars().skip(1).all(|c| c == '0') { 0 } else { 1 }
-                },
-                _ => 1, // 6-9 => >0.5
-            }
-        }
-    };
-    // Parse integer portion (always positive here)
-    let mut int_bd = BigDecimal::from_str(if int_str.is_empty() { "0" } else { int_str }).unwrap();
-    match cmp_half {
-        -1 => { /* keep int */ }
-        1 => { int_bd = &int_bd + BigDecimal::from(1); }
-        0 => { // tie 0.5 -> round to even
-            // Determine if int is even
-            let even = {
-                // Extract last digit (string) - safe because int_str not empty for any non-zero
-                let last_digit = int_str.chars().last().unwrap_or('0');
-                let digit_val = last_digit.to_digit(10).unwrap_or(0);
-                digit_val % 2 == 0
-            };
-            if !even { int_bd = &int_bd + BigDecimal::from(1); }
-        }
-        _ => {}
+fn bankers(value: &BigDecimal, scale: i32) -> BigDecimal { round_digits(value, scale, RoundingMode::Bankers) }
+
+/// Rounds `value` to `scale` fractional digits by dividing its unscaled digits by `10^dropped`
+/// and deciding from the remainder, so exponent notation and huge scales need no string handling.
+/// Mantissas that fit in `i128` (all realistic amounts) avoid big-integer allocation entirely.
+fn round_digits(value: &BigDecimal, scale: i32, mode: RoundingMode) -> BigDecimal {
+    let target = scale as i64;
+    let (digits, exponent) = value.as_bigint_and_exponent();
+    if exponent <= target {
+        // Already representable at the target scale; only pad with zeros.
+        return value.with_scale(target);
+    }
+    let dropped = exponent - target;
+    if let Some(mantissa) = digits.to_i128() {
+        // |i128| < 1.8e38 < 10^39 / 2, so dropping 39+ digits always rounds to zero.
+        if dropped >= 39 { return BigDecimal::new(BigInt::zero(), target); }
+        let divisor = 10i128.pow(dropped as u32);
+        let (quotient, remainder) = (mantissa / divisor, mantissa % divisor);
+        let rest = remainder.unsigned_abs();
+        let half = rest.cmp(&(divisor as u128 - rest));
+        let rounded = if steps_away(mode, half, quotient % 2 != 0) { quotient + mantissa.signum() } else { quotient };
+        return BigDecimal::new(BigInt::from(rounded), target);
+    }
+    let divisor = BigInt::from(10u8).pow(dropped as u32);
+    let (quotient, remainder) = (&digits / &divisor, &digits % &divisor);
+    let rest = remainder.abs();
+    let half = rest.cmp(&(&divisor - &rest));
+    let odd = !(&quotient % 2u8).is_zero();
+    let rounded = if steps_away(mode, half, odd) { quotient + digits.signum() } else { quotient };
+    BigDecimal::new(rounded, target)
+}
+
+/// Whether a truncated quotient moves one unit away from zero, given how the discarded remainder
+/// compares to half a unit and whether the quotient is odd.
+#[inline]
+fn steps_away(mode: RoundingMode, remainder_vs_half: std::cmp::Ordering, quotient_odd: bool) -> bool {
+    use std::cmp::Ordering::{Equal, Greater};
+    match mode {
+        RoundingMode::Truncate => false,
+        RoundingMode::HalfUp => matches!(remainder_vs_half, Equal | Greater),
+        RoundingMode::Bankers => remainder_vs_half == Greater || (remainder_vs_half == Equal && quotient_odd),
     }
-    // Reapply sign
-    if negative { int_bd *= BigDecimal::from(-1); }
-    (int_bd / factor).with_scale(scale as i64)
+}
+
+/// A whole number of minor units (cents), used where an amount must not be mistaken for a
+/// decimal major-unit value (e.g. comparison tolerances).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Cents(pub i64);
+
+impl Cents {
+    pub const ZERO: Cents = Cents(0);
+    pub fn abs(self) -> Cents { Cents(self.0.saturating_abs()) }
+}
+
+impl std::fmt::Display for Cents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{}c", self.0) }
+}
+
+/// Exact `a - b` in cents after normalizing both sides, computed on the decimal digits (no f64).
+/// Saturates at the `i64` bounds for differences beyond ~92 quadrillion.
+pub fn cents_diff(a: &BigDecimal, b: &BigDecimal) -> Cents {
+    let (digits, _) = (normalize_scale(a) - normalize_scale(b)).with_scale(2).into_bigint_and_exponent();
+    Cents(digits.to_i64().unwrap_or(if digits.is_negative() { i64::MIN } else { i64::MAX }))
 }
 
 /// Compare two monetary values allowing a tolerance (in cents) after normalization.
-pub fn nearly_equal(a: &BigDecimal, b: &BigDecimal, cents_tolerance: i64) -> bool {
-    let na = normalize_scale(a);
-    let nb = normalize_scale(b);
-    // Convert difference to cents integer to avoid floating comparison.
-    let diff = (na - nb).with_scale(2);
-    // Convert to i64 cents via *100
-    let cents = diff.to_f64().unwrap_or(0.0) * 100.0;
-    cents.abs() <= cents_tolerance as f64
+pub fn nearly_equal(a: &BigDecimal, b: &BigDecimal, tolerance: Cents) -> bool {
+    cents_diff(a, b).abs() <= tolerance.abs()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -234,6 +308,39 @@ impl Money {
         Self::new(value)
     }
     pub fn inner(&self) -> &BigDecimal { &self.0 }
+    /// `rate_bps` basis points of this amount (1000 bps = 10%), rounded once with the global mode.
+    /// Negative amounts or rates give negative results; see [`Money::apply_rate`].
+    pub fn percent(&self, rate_bps: i32) -> Money { self.apply_rate(&bps_rate(rate_bps), current_rounding_mode()) }
+    /// Multiplies by `rate` and rounds the exact product once using `mode`. The magnitude is
+    /// rounded and the sign put back, so a negative amount (a refund, a net-negative subtotal)
+    /// always mirrors its positive counterpart: `(-x).apply_rate(r, m) == -(x.apply_rate(r, m))`.
+    /// Callers that must not go below zero clamp the amount first.
+    pub fn apply_rate(&self, rate: &BigDecimal, mode: RoundingMode) -> Money {
+        let product = &self.0 * rate;
+        let magnitude = normalize_scale_with(&product.abs(), mode);
+        Money(if product.is_negative() { -magnitude } else { magnitude })
+    }
+    /// Splits this amount across `weights` in whole cents, handing leftover cents to the shares
+    /// with the largest remainders (ties to the earliest), so the parts always sum to `self`.
+    /// Returns `None` when the weights sum to zero.
+    pub fn allocate(&self, weights: &[u32]) -> Option<Vec<Money>> {
+        let total_weight: i128 = weights.iter().map(|w| *w as i128).sum();
+        if total_weight == 0 { return None; }
+        let cents = self.as_cents() as i128;
+        let sign = if cents < 0 { -1 } else { 1 };
+        let magnitude = cents.abs();
+        let mut shares: Vec<i128> = weights.iter().map(|w| magnitude * *w as i128 / total_weight).collect();
+        let mut leftover = magnitude - shares.iter().sum::<i128>();
+        let mut order: Vec<usize> = (0..weights.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(magnitude * weights[i] as i128 % total_weight));
+        for i in order {
+            if leftover == 0 { break; }
+            if weights[i] == 0 { continue; }
+            shares[i] += 1;
+            leftover -= 1;
+        }
+        Some(shares.into_iter().map(|share| Money::from_cents((sign * share) as i64)).collect())
+    }
     /// Construct from integer cents (minor units) without intermediate floating rounding.
     pub fn from_cents(cents: i64) -> Self {
         // value = cents / 100
@@ -242,25 +349,9 @@ impl Money {
     }
     /// Return total minor units (cents) as i64. Panics if out of i64 range (extremely unlikely with enforced scale=2).
     pub fn as_cents(&self) -> i64 {
-        // Multiply by 100 exactly using string to avoid floating imprecision
-        // self.0 is guaranteed scale 2, so representation like X.YY
-        let s = self.0.to_string();
-        if let Some(dot) = s.find('.') {
-            let (int_part, frac_part) = s.split_at(dot);
-            let frac = &frac_part[1..]; // skip '.'
-            let mut frac_norm = String::from(frac);
-            if frac_norm.len() < 2 { frac_norm.push('0'); }
-            if frac_norm.len() > 2 { frac_norm.truncate(2); }
-            let sign = if int_part.starts_with('-') { -1i64 } else { 1i64 };
-            let int_clean = if int_part == "-0" || int_part == "+0" { "0" } else { int_part.trim_start_matches('+') };
-            let major = int_clean.parse::<i64>().unwrap();
-            let minor = frac_norm.parse::<i64>().unwrap();
-            sign * (major.abs() * 100 + minor)
-        } else {
-            // Whole number -> multiply by 100
-            let major = s.parse::<i64>().unwrap();
-            major * 100
-        }
+        // self.0 is guaranteed scale 2, so the unscaled digits are the cents.
+        let (digits, _) = self.0.with_scale(2).into_bigint_and_exponent();
+        digits.to_i64().expect("money amount exceeds i64 cents")
     }
 }
 
@@ -367,6 +458,11 @@ impl<'r> Decode<'r, Postgres> for Money {
     }
 }
 
+impl Default for Money {
+    /// Zero, at scale 2.
+    fn default() -> Self { Money::from_cents(0) }
+}
+
 impl std::fmt::Display for Money {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -376,7 +472,7 @@ impl std::fmt::Display for Money {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bigdecimal::BigDecimal;    
+    use std::str::FromStr;
     #[test]
     fn test_half_up_rounding() {
         let v = BigDecimal::parse_bytes(b"12.345", 10).unwrap();
@@ -416,7 +512,22 @@ mod tests {
     fn test_nearly_equal() {
         let a = BigDecimal::parse_bytes(b"10.001", 10).unwrap();
         let b = BigDecimal::parse_bytes(b"10.009", 10).unwrap();
-        assert!(nearly_equal(&a, &b, 1)); // 1 cent tolerance
+        assert!(nearly_equal(&a, &b, Cents(1))); // 1 cent tolerance
+        assert!(!nearly_equal(&a, &b, Cents::ZERO));
+    }
+
+    #[test]
+    fn test_cents_diff_is_exact_for_large_amounts() {
+        // 2^53 + 1 cents is not representable in f64; the old float path reported equality here.
+        let a = BigDecimal::new(9_007_199_254_740_993i64.into(), 2);
+        let b = BigDecimal::new(9_007_199_254_740_992i64.into(), 2);
+        assert_eq!(cents_diff(&a, &b), Cents(1));
+        assert_eq!(cents_diff(&b, &a), Cents(-1));
+        assert!(!nearly_equal(&a, &b, Cents::ZERO));
+        assert!(nearly_equal(&a, &b, Cents(1)));
+        let huge = BigDecimal::from_str("1E+30").unwrap();
+        assert_eq!(cents_diff(&huge, &BigDecimal::from(0)), Cents(i64::MAX));
+        assert_eq!(cents_diff(&BigDecimal::from(0), &huge), Cents(i64::MIN));
     }
 
     #[test]
@@ -517,6 +628,117 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_exponent_notation_and_wide_mantissa() {
+        let cases = [
+            // (input, half_up, truncate, bankers)
+            ("1.2345E+3", "1234.50", "1234.50", "1234.50"),
+            ("1E+12", "1000000000000.00", "1000000000000.00", "1000000000000.00"),
+            ("5E-3", "0.01", "0.00", "0.00"),
+            ("-5E-3", "-0.01", "0.00", "0.00"),
+            ("1E-50", "0.00", "0.00", "0.00"),
+            // Mantissa beyond i128 exercises the big-integer path.
+            ("123456789012345678901234567890123456789012.905", "123456789012345678901234567890123456789012.91", "123456789012345678901234567890123456789012.90", "123456789012345678901234567890123456789012.90"),
+            ("-123456789012345678901234567890123456789012.915", "-123456789012345678901234567890123456789012.92", "-123456789012345678901234567890123456789012.91", "-123456789012345678901234567890123456789012.92"),
+        ];
+        for (input, expect_half, expect_trunc, expect_bank) in cases {
+            let v = BigDecimal::from_str(input).unwrap();
+            assert_eq!(half_up(&v, 2).to_string(), expect_half, "HalfUp mismatch for {input}");
+            assert_eq!(truncate(&v, 2).to_string(), expect_trunc, "Truncate mismatch for {input}");
+            assert_eq!(bankers(&v, 2).to_string(), expect_bank, "Bankers mismatch for {input}");
+        }
+    }
+
+    #[test]
+    fn test_percent_uses_basis_points() {
+        let m = Money::from_cents(1999);
+        // 19.99 * 8.25% = 1.649175
+        let half_up = RoundingContext::new(RoundingMode::HalfUp);
+        assert_eq!(half_up.percent(&m, 825).inner().to_string(), "1.65");
+        assert_eq!(RoundingContext::new(RoundingMode::Truncate).percent(&m, 825).inner().to_string(), "1.64");
+        // The global-mode helper agrees with a context pinned to whatever mode is active.
+        assert_eq!(m.percent(825), RoundingContext::new(current_rounding_mode()).percent(&m, 825));
+        assert_eq!(m.percent(0).as_cents(), 0);
+        assert_eq!(m.percent(10_000), m);
+    }
+
+    #[test]
+    fn test_percent_of_negative_amounts_mirrors_positive() {
+        // 10.05 * 2.5% = 0.25125 and 0.30 * 25% = 0.075: rounding acts on the magnitude.
+        for (cents, bps) in [(1005, 250), (30, 2_500), (1999, 825), (-1, 5_000)] {
+            for mode in [RoundingMode::HalfUp, RoundingMode::Truncate, RoundingMode::Bankers] {
+                let ctx = RoundingContext::new(mode);
+                let positive = ctx.percent(&Money::from_cents(cents), bps);
+                assert_eq!(ctx.percent(&Money::from_cents(-cents), bps).as_cents(), -positive.as_cents(), "{cents} {bps} {mode:?}");
+                assert_eq!(ctx.percent(&Money::from_cents(cents), -bps).as_cents(), -positive.as_cents(), "{cents} {bps} {mode:?}");
+            }
+        }
+        let neg = Money::from_cents(-30);
+        assert_eq!(RoundingContext::new(RoundingMode::HalfUp).percent(&neg, 2_500).inner().to_string(), "-0.08");
+        assert_eq!(RoundingContext::new(RoundingMode::Truncate).percent(&neg, 2_500).inner().to_string(), "-0.07");
+        assert_eq!(RoundingContext::new(RoundingMode::Bankers).percent(&neg, 2_500).inner().to_string(), "-0.08");
+    }
+
+    #[test]
+    fn test_apply_rate_per_call_mode() {
+        let m = Money::from_cents(1005); // 10.05
+        let rate = BigDecimal::from_str("0.5").unwrap(); // 5.025
+        assert_eq!(m.apply_rate(&rate, RoundingMode::HalfUp).inner().to_string(), "5.03");
+        assert_eq!(m.apply_rate(&rate, RoundingMode::Truncate).inner().to_string(), "5.02");
+        assert_eq!(m.apply_rate(&rate, RoundingMode::Bankers).inner().to_string(), "5.02");
+        let neg = Money::from_cents(-1005);
+        assert_eq!(neg.apply_rate(&rate, RoundingMode::HalfUp).inner().to_string(), "-5.03");
+    }
+
+    #[test]
+    fn test_rounding_context_overrides_global_mode() {
+        let ctx = RoundingContext::new(RoundingMode::Truncate);
+        assert_eq!(ctx.mode(), RoundingMode::Truncate);
+        let amount = Money::from_cents(1999);
+        assert_eq!(ctx.percent(&amount, 825).inner().to_string(), "1.64");
+        assert_eq!(ctx.money(&BigDecimal::from_str("2.679").unwrap()).inner().to_string(), "2.67");
+        let bankers_ctx = RoundingContext::new(RoundingMode::Bankers);
+        assert_eq!(bankers_ctx.apply_rate(&Money::from_cents(249), &BigDecimal::from_str("0.5").unwrap()).inner().to_string(), "1.24");
+        assert_eq!(RoundingContext::default().mode(), current_rounding_mode());
+    }
+
+    #[test]
+    fn test_allocate_preserves_total() {
+        let parts = Money::from_cents(1000).allocate(&[1, 1, 1]).unwrap();
+        assert_eq!(parts.iter().map(Money::as_cents).collect::<Vec<_>>(), vec![334, 333, 333]);
+        let parts = Money::from_cents(-101).allocate(&[1, 0, 3]).unwrap();
+        assert_eq!(parts.iter().map(Money::as_cents).collect::<Vec<_>>(), vec![-25, 0, -76]);
+        assert!(Money::from_cents(100).allocate(&[0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_rounding_policy_parse() {
+        assert_eq!(RoundingPolicy::parse("bankers"), Some(RoundingPolicy::new(RoundingMode::Bankers)));
+        let chf = RoundingPolicy::parse("half-up:5").unwrap();
+        assert_eq!(chf.mode, RoundingMode::HalfUp);
+        assert_eq!(chf.cash_increment_cents, Some(5));
+        assert_eq!(RoundingPolicy::parse("truncate:1").unwrap().cash_increment_cents, None);
+        assert!(RoundingPolicy::parse("half-up:abc").is_none());
+        assert!(RoundingPolicy::parse("sideways").is_none());
+    }
+
+    #[test]
+    fn test_rounding_policy_cash_increment() {
+        let chf = RoundingPolicy::new(RoundingMode::HalfUp).with_cash_increment(5);
+        assert_eq!(chf.cash_round_cents(1232), 1230);
+        assert_eq!(chf.cash_round_cents(1233), 1235);
+        assert_eq!(chf.cash_round_cents(1237), 1235);
+        assert_eq!(chf.cash_round_cents(1238), 1240);
+        assert_eq!(chf.cash_round_cents(-1233), -1235);
+        let trunc = RoundingPolicy::new(RoundingMode::Truncate).with_cash_increment(5);
+        assert_eq!(trunc.cash_round_cents(1239), 1235);
+        let none = RoundingPolicy::new(RoundingMode::HalfUp);
+        assert_eq!(none.cash_round_cents(1233), 1233);
+        let v = BigDecimal::from_str("2.675").unwrap();
+        assert_eq!(normalize_scale_for(&v, Some(&RoundingPolicy::new(RoundingMode::Truncate))).to_string(), "2.67");
+        assert_eq!(normalize_scale_for(&v, None), normalize_scale(&v));
+    }
+
     #[test]
     fn test_env_initialization_default() {
         // Ensure clean state for this process: prefer default when not pre-initialized.
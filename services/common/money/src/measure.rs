@@ -0,0 +1,110 @@
+//! Units of measure for products sold by weight.
+//!
+//! Measured quantities carry three decimals (grams for `kg`, thousandths of a pound for `lb`).
+//! Services that keep integer stock (inventory) hold measured products in those thousandths,
+//! so a 1.250 kg sale is 1250 stock units.
+use crate::{half_up, Money, RoundingContext};
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+
+/// Decimal places kept on measured quantities.
+pub const MEASURE_SCALE: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitOfMeasure {
+    /// Counted units; quantities are whole numbers.
+    Each,
+    Kg,
+    Lb,
+}
+
+impl UnitOfMeasure {
+    pub const ALL: [UnitOfMeasure; 3] = [UnitOfMeasure::Each, UnitOfMeasure::Kg, UnitOfMeasure::Lb];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "each" | "ea" => Some(Self::Each),
+            "kg" => Some(Self::Kg),
+            "lb" => Some(Self::Lb),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Each => "each",
+            Self::Kg => "kg",
+            Self::Lb => "lb",
+        }
+    }
+
+    /// True when the product is sold by a scale reading rather than a count.
+    pub fn is_measured(self) -> bool { !matches!(self, Self::Each) }
+}
+
+/// Round a measured quantity half-up to [`MEASURE_SCALE`] decimals.
+pub fn normalize_measure(quantity: &BigDecimal) -> BigDecimal { half_up(quantity, MEASURE_SCALE) }
+
+/// Net quantity of a scale reading after subtracting the tare (container weight), rounded to
+/// three decimals. `None` unless the net is positive.
+pub fn net_measure(gross: &BigDecimal, tare: Option<&BigDecimal>) -> Option<BigDecimal> {
+    let tare = tare.cloned().unwrap_or_else(BigDecimal::zero);
+    if tare.is_negative() {
+        return None;
+    }
+    let net = normalize_measure(&(gross - tare));
+    net.is_positive().then_some(net)
+}
+
+/// A measured quantity in thousandths of its unit (the integer stock unit), keeping the sign.
+pub fn to_milli_units(quantity: &BigDecimal) -> Option<i32> {
+    let (digits, _) = normalize_measure(quantity).with_scale(MEASURE_SCALE as i64).into_bigint_and_exponent();
+    digits.to_i32()
+}
+
+/// Inverse of [`to_milli_units`].
+pub fn from_milli_units(units: i32) -> BigDecimal { BigDecimal::new(units.into(), MEASURE_SCALE as i64) }
+
+/// Price of `quantity` units at `unit_price` per unit, rounded once to cents under `rounding`.
+pub fn extend_price(unit_price: &Money, quantity: &BigDecimal, rounding: &RoundingContext) -> Money {
+    rounding.apply_rate(unit_price, quantity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RoundingMode;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> BigDecimal { BigDecimal::from_str(s).unwrap() }
+
+    #[test]
+    fn tare_is_subtracted_and_net_must_be_positive() {
+        assert_eq!(net_measure(&dec("1.2504"), Some(&dec("0.015"))), Some(dec("1.235")));
+        assert_eq!(net_measure(&dec("0.500"), None), Some(dec("0.500")));
+        assert_eq!(net_measure(&dec("0.010"), Some(&dec("0.010"))), None);
+        assert_eq!(net_measure(&dec("1"), Some(&dec("-0.1"))), None);
+    }
+
+    #[test]
+    fn milli_units_round_trip_with_sign() {
+        assert_eq!(to_milli_units(&dec("1.2345")), Some(1235));
+        assert_eq!(to_milli_units(&dec("-0.75")), Some(-750));
+        assert_eq!(from_milli_units(1250), dec("1.250"));
+    }
+
+    #[test]
+    fn extended_price_rounds_once_under_the_context() {
+        // 1.235 kg at 3.99/kg = 4.92765
+        let price = Money::from_cents(399);
+        assert_eq!(extend_price(&price, &dec("1.235"), &RoundingContext::new(RoundingMode::HalfUp)).as_cents(), 493);
+        assert_eq!(extend_price(&price, &dec("1.235"), &RoundingContext::new(RoundingMode::Truncate)).as_cents(), 492);
+    }
+
+    #[test]
+    fn units_parse_case_insensitively() {
+        assert_eq!(UnitOfMeasure::parse(" KG "), Some(UnitOfMeasure::Kg));
+        assert_eq!(UnitOfMeasure::parse("ea"), Some(UnitOfMeasure::Each));
+        assert_eq!(UnitOfMeasure::parse("oz"), None);
+        assert!(!UnitOfMeasure::Each.is_measured());
+    }
+}
@@ -0,0 +1,292 @@
+//! Reading amounts the way cashiers type them: `"1,234.56"`, `"12,50"`, `"$10"`, `"EUR 5"`.
+//!
+//! The locale decides which of `.` and `,` is the decimal separator; the other one, spaces and
+//! apostrophes (`1'234.50`) may group thousands. Input that only makes sense under the other
+//! convention (`"12,50"` at a decimal-point till, `"1.234"` where it could be a thousand or one
+//! and a bit) is rejected as ambiguous rather than guessed, and more than two decimals are
+//! rejected rather than rounded.
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+
+use crate::Money;
+
+/// How a till writes numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoneyLocale {
+    /// `1,234.56` (en, ja, zh, ko, he, th, hi, de-CH, ...).
+    #[default]
+    DecimalPoint,
+    /// `1.234,56` (de, fr, es, it, nl, pt, pl, ru, the Nordics, ...).
+    DecimalComma,
+}
+
+/// Languages writing `1.234,56`; everything else defaults to a decimal point.
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "bg", "ca", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "is", "it", "lt", "lv", "nb", "nl",
+    "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk", "vi",
+];
+
+/// Regions that write a decimal point even though the language usually doesn't.
+const DECIMAL_POINT_REGIONS: &[&str] = &["ch", "li", "mx"];
+
+impl MoneyLocale {
+    /// Locale for a BCP 47 tag such as `en-US`, `de_DE` or `fr`, or the convention names
+    /// `point` / `comma`. `None` when the tag isn't recognisable.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase().replace('_', "-");
+        match tag.as_str() {
+            "point" | "decimal_point" => return Some(Self::DecimalPoint),
+            "comma" | "decimal_comma" => return Some(Self::DecimalComma),
+            _ => {}
+        }
+        let mut parts = tag.split('-');
+        let language = parts.next().filter(|l| (2..=3).contains(&l.len()) && l.bytes().all(|b| b.is_ascii_alphabetic()))?;
+        let region = parts.find(|p| p.len() == 2);
+        if region.is_some_and(|r| DECIMAL_POINT_REGIONS.contains(&r)) {
+            return Some(Self::DecimalPoint);
+        }
+        Some(if DECIMAL_COMMA_LANGUAGES.contains(&language) { Self::DecimalComma } else { Self::DecimalPoint })
+    }
+
+    fn separators(self) -> (char, char) {
+        match self {
+            Self::DecimalPoint => ('.', ','),
+            Self::DecimalComma => (',', '.'),
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Self::DecimalPoint => Self::DecimalComma,
+            Self::DecimalComma => Self::DecimalPoint,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseMoneyError {
+    #[error("amount is empty")]
+    Empty,
+    #[error("amount contains no digits")]
+    NoDigits,
+    #[error("unexpected {0:?} in amount")]
+    InvalidCharacter(char),
+    #[error("amount names more than one currency")]
+    MultipleCurrencies,
+    #[error("thousands separators are in the wrong place")]
+    InvalidGrouping,
+    #[error("amount has more than one decimal separator")]
+    MultipleDecimalSeparators,
+    #[error("amount has more than two decimal places")]
+    TooManyDecimals,
+    #[error("amount {0:?} reads differently with the other decimal separator; re-enter it without thousands separators")]
+    Ambiguous(String),
+    #[error("amount is too large")]
+    TooLarge,
+}
+
+impl ParseMoneyError {
+    /// Machine-readable code for API errors (`ambiguous_amount`, `too_many_decimals`, ...).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty_amount",
+            Self::NoDigits | Self::InvalidCharacter(_) => "invalid_amount",
+            Self::MultipleCurrencies => "multiple_currencies",
+            Self::InvalidGrouping => "invalid_grouping",
+            Self::MultipleDecimalSeparators => "multiple_decimal_separators",
+            Self::TooManyDecimals => "too_many_decimals",
+            Self::Ambiguous(_) => "ambiguous_amount",
+            Self::TooLarge => "amount_too_large",
+        }
+    }
+}
+
+/// A parsed amount and the currency it was written with, if any (`$`, `€`, `USD`, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedAmount {
+    pub amount: Money,
+    /// ISO code for a recognised symbol, or the code as typed (upper-cased).
+    pub currency: Option<String>,
+}
+
+const SYMBOLS: &[(char, &str)] = &[('$', "USD"), ('€', "EUR"), ('£', "GBP"), ('¥', "JPY"), ('₹', "INR"), ('₩', "KRW"), ('₺', "TRY"), ('₽', "RUB")];
+
+/// Grouping characters accepted in either locale: spaces (including the no-break spaces French
+/// formatting produces) and the Swiss apostrophe.
+const SPACE_GROUPS: &[char] = &[' ', '\u{a0}', '\u{202f}', '\'', '’'];
+
+/// Integer digits accepted; keeps the result well inside `i64` cents.
+const MAX_INTEGER_DIGITS: usize = 15;
+
+impl Money {
+    /// Reads an amount typed by a person; see [`parse_amount`] for the accepted forms.
+    pub fn parse_lenient(input: &str, locale: MoneyLocale) -> Result<Money, ParseMoneyError> {
+        parse_amount(input, locale).map(|parsed| parsed.amount)
+    }
+}
+
+/// Reads an amount typed by a person: an optional sign, an optional currency symbol or ISO code
+/// before or after the number, thousands grouping and a decimal part of at most two digits.
+pub fn parse_amount(input: &str, locale: MoneyLocale) -> Result<ParsedAmount, ParseMoneyError> {
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return Err(ParseMoneyError::Empty);
+    }
+    let mut negative = take_sign(&mut rest);
+    let prefix = take_currency_prefix(&mut rest);
+    if !negative && prefix.is_some() {
+        negative = take_sign(&mut rest);
+    }
+    let suffix = take_currency_suffix(&mut rest);
+    let currency = match (prefix, suffix) {
+        (Some(a), Some(b)) if a != b => return Err(ParseMoneyError::MultipleCurrencies),
+        (a, b) => a.or(b),
+    };
+    let number = match read_number(rest, locale) {
+        Ok(number) => number,
+        Err(err @ (ParseMoneyError::InvalidGrouping | ParseMoneyError::MultipleDecimalSeparators | ParseMoneyError::TooManyDecimals)) => {
+            return Err(if read_number(rest, locale.other()).is_ok() { ParseMoneyError::Ambiguous(input.trim().to_string()) } else { err });
+        }
+        Err(err) => return Err(err),
+    };
+    let value = BigDecimal::from_str(&number).map_err(|_| ParseMoneyError::NoDigits)?;
+    let amount = Money::new(if negative { -value } else { value });
+    Ok(ParsedAmount { amount, currency })
+}
+
+fn take_sign(rest: &mut &str) -> bool {
+    if let Some(stripped) = rest.strip_prefix('-').or_else(|| rest.strip_prefix('\u{2212}')) {
+        *rest = stripped.trim_start();
+        true
+    } else {
+        if let Some(stripped) = rest.strip_prefix('+') {
+            *rest = stripped.trim_start();
+        }
+        false
+    }
+}
+
+fn symbol_code(c: char) -> Option<&'static str> {
+    SYMBOLS.iter().find(|(symbol, _)| *symbol == c).map(|(_, code)| *code)
+}
+
+fn take_currency_prefix(rest: &mut &str) -> Option<String> {
+    let first = rest.chars().next()?;
+    if let Some(code) = symbol_code(first) {
+        *rest = rest[first.len_utf8()..].trim_start();
+        return Some(code.to_string());
+    }
+    let letters = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+    (letters == 3).then(|| {
+        let code = rest[..3].to_ascii_uppercase();
+        *rest = rest[3..].trim_start();
+        code
+    })
+}
+
+fn take_currency_suffix(rest: &mut &str) -> Option<String> {
+    let last = rest.chars().next_back()?;
+    if let Some(code) = symbol_code(last) {
+        *rest = rest[..rest.len() - last.len_utf8()].trim_end();
+        return Some(code.to_string());
+    }
+    let letters = rest.bytes().rev().take_while(u8::is_ascii_alphabetic).count();
+    (letters == 3).then(|| {
+        let code = rest[rest.len() - 3..].to_ascii_uppercase();
+        *rest = rest[..rest.len() - 3].trim_end();
+        code
+    })
+}
+
+/// The plain `123.45` form of `text` under `locale`, checking grouping and decimals.
+fn read_number(text: &str, locale: MoneyLocale) -> Result<String, ParseMoneyError> {
+    let (decimal, group) = locale.separators();
+    if let Some(c) = text.chars().find(|c| !c.is_ascii_digit() && *c != decimal && *c != group && !SPACE_GROUPS.contains(c)) {
+        return Err(ParseMoneyError::InvalidCharacter(c));
+    }
+    if !text.chars().any(|c| c.is_ascii_digit()) {
+        return Err(ParseMoneyError::NoDigits);
+    }
+    let mut halves = text.split(decimal);
+    let integer = halves.next().unwrap_or_default();
+    let fraction = halves.next();
+    if halves.next().is_some() {
+        return Err(ParseMoneyError::MultipleDecimalSeparators);
+    }
+    if let Some(fraction) = fraction {
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ParseMoneyError::InvalidGrouping);
+        }
+        if fraction.len() > 2 {
+            return Err(ParseMoneyError::TooManyDecimals);
+        }
+    }
+    let groups: Vec<&str> = integer.split(|c: char| c == group || SPACE_GROUPS.contains(&c)).collect();
+    if groups.len() > 1 {
+        // `1,234,567`: a leading group of one to three digits, then groups of exactly three.
+        let valid = (1..=3).contains(&groups[0].len()) && groups[1..].iter().all(|g| g.len() == 3);
+        if !valid {
+            return Err(ParseMoneyError::InvalidGrouping);
+        }
+    }
+    let digits: String = groups.concat();
+    if digits.trim_start_matches('0').len() > MAX_INTEGER_DIGITS {
+        return Err(ParseMoneyError::TooLarge);
+    }
+    let integer = if digits.is_empty() { "0" } else { &digits };
+    Ok(match fraction {
+        Some(fraction) if !fraction.is_empty() => format!("{integer}.{fraction}"),
+        _ => integer.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str, locale: MoneyLocale) -> Result<String, ParseMoneyError> {
+        Money::parse_lenient(input, locale).map(|m| m.to_string())
+    }
+
+    #[test]
+    fn reads_grouping_decimals_and_symbols_for_each_locale() {
+        use MoneyLocale::*;
+        assert_eq!(parse("1,234.56", DecimalPoint).as_deref(), Ok("1234.56"));
+        assert_eq!(parse("12,50", DecimalComma).as_deref(), Ok("12.50"));
+        assert_eq!(parse("1.234.567,8", DecimalComma).as_deref(), Ok("1234567.80"));
+        assert_eq!(parse("$10", DecimalPoint).as_deref(), Ok("10.00"));
+        assert_eq!(parse("-$3.5", DecimalPoint).as_deref(), Ok("-3.50"));
+        assert_eq!(parse("1 234,5 €", DecimalComma).as_deref(), Ok("1234.50"));
+        assert_eq!(parse("1'234.50 CHF", DecimalPoint).as_deref(), Ok("1234.50"));
+        assert_eq!(parse(".99", DecimalPoint).as_deref(), Ok("0.99"));
+        assert_eq!(parse("7.", DecimalPoint).as_deref(), Ok("7.00"));
+        assert_eq!(parse_amount("eur 5", DecimalComma).unwrap().currency.as_deref(), Some("EUR"));
+        assert_eq!(parse_amount("5", DecimalComma).unwrap().currency, None);
+    }
+
+    #[test]
+    fn rejects_what_it_would_have_to_guess() {
+        use MoneyLocale::*;
+        assert_eq!(parse("12,50", DecimalPoint), Err(ParseMoneyError::Ambiguous("12,50".into())));
+        assert_eq!(parse("1.234", DecimalPoint), Err(ParseMoneyError::Ambiguous("1.234".into())));
+        assert_eq!(parse("1.5", DecimalComma), Err(ParseMoneyError::Ambiguous("1.5".into())));
+        assert_eq!(parse("1.2345", DecimalPoint), Err(ParseMoneyError::TooManyDecimals));
+        assert_eq!(parse("12,34,567", DecimalPoint), Err(ParseMoneyError::InvalidGrouping));
+        assert_eq!(parse("1.2.3", DecimalPoint), Err(ParseMoneyError::MultipleDecimalSeparators));
+        assert_eq!(parse("$5 EUR", DecimalPoint), Err(ParseMoneyError::MultipleCurrencies));
+        assert_eq!(parse("  ", DecimalPoint), Err(ParseMoneyError::Empty));
+        assert_eq!(parse("$", DecimalPoint), Err(ParseMoneyError::NoDigits));
+        assert_eq!(parse("1e5", DecimalPoint), Err(ParseMoneyError::InvalidCharacter('e')));
+        assert_eq!(parse("9999999999999999", DecimalPoint), Err(ParseMoneyError::TooLarge));
+    }
+
+    #[test]
+    fn locale_tags_pick_the_decimal_separator() {
+        assert_eq!(MoneyLocale::from_tag("en-US"), Some(MoneyLocale::DecimalPoint));
+        assert_eq!(MoneyLocale::from_tag("de_DE"), Some(MoneyLocale::DecimalComma));
+        assert_eq!(MoneyLocale::from_tag("pt-BR"), Some(MoneyLocale::DecimalComma));
+        assert_eq!(MoneyLocale::from_tag("de-CH"), Some(MoneyLocale::DecimalPoint));
+        assert_eq!(MoneyLocale::from_tag("comma"), Some(MoneyLocale::DecimalComma));
+        assert_eq!(MoneyLocale::from_tag("12"), None);
+    }
+}
@@ -0,0 +1,134 @@
+// Invariants for every rounding mode plus differential checks against an exact integer reference.
+// Modes are exercised through `normalize_scale_with`, so results do not depend on MONEY_ROUNDING.
+
+use bigdecimal::BigDecimal;
+use common_money::{normalize_scale_with, Money, RoundingMode};
+use proptest::prelude::*;
+use std::str::FromStr;
+
+const MODES: [RoundingMode; 3] = [RoundingMode::HalfUp, RoundingMode::Truncate, RoundingMode::Bankers];
+
+fn decimal(mantissa: i64, scale: u32) -> BigDecimal { BigDecimal::new(mantissa.into(), scale as i64) }
+
+/// Reference rounding of `mantissa / 10^scale` to whole cents using only i128 arithmetic.
+fn reference_cents(mantissa: i64, scale: u32, mode: RoundingMode) -> i128 {
+    let num = mantissa as i128 * 100;
+    if scale <= 2 { return num / 10i128.pow(scale); }
+    let den = 10i128.pow(scale);
+    let (q, r) = (num / den, num % den); // truncates toward zero; r carries the sign of num
+    let away = q + num.signum();
+    match mode {
+        RoundingMode::Truncate => q,
+        RoundingMode::HalfUp => if r.abs() * 2 >= den { away } else { q },
+        RoundingMode::Bankers => match (r.abs() * 2).cmp(&den) {
+            std::cmp::Ordering::Greater => away,
+            std::cmp::Ordering::Equal if q % 2 != 0 => away,
+            _ => q,
+        },
+    }
+}
+
+fn scale_of(value: &BigDecimal) -> i64 { value.as_bigint_and_exponent().1 }
+
+proptest! {
+    #[test]
+    fn matches_integer_reference(mantissa in -1_000_000_000_000i64..1_000_000_000_000, scale in 0u32..=8) {
+        let value = decimal(mantissa, scale);
+        for mode in MODES {
+            let got = normalize_scale_with(&value, mode);
+            let expected = BigDecimal::new(reference_cents(mantissa, scale, mode).into(), 2);
+            prop_assert_eq!(&got, &expected, "mode={:?} input={}", mode, value);
+            prop_assert_eq!(scale_of(&got), 2, "mode={:?} must emit scale 2 for {}", mode, value);
+        }
+    }
+
+    #[test]
+    fn wide_mantissa_rounds_like_compact_form(mantissa in any::<i64>(), scale in 0u32..=8, padding in 25u32..60) {
+        // Same numeric value with trailing zeros pushed past i128 range must round identically.
+        let compact = decimal(mantissa, scale);
+        let padded = BigDecimal::new(compact.as_bigint_and_exponent().0 * num_bigint::BigInt::from(10u8).pow(padding), (scale + padding) as i64);
+        for mode in MODES {
+            prop_assert_eq!(normalize_scale_with(&padded, mode), normalize_scale_with(&compact, mode), "mode={:?}", mode);
+        }
+    }
+
+    #[test]
+    fn truncate_matches_bigdecimal_with_scale(mantissa in any::<i64>(), scale in 0u32..=10) {
+        let value = decimal(mantissa, scale);
+        prop_assert_eq!(normalize_scale_with(&value, RoundingMode::Truncate), value.with_scale(2));
+    }
+
+    #[test]
+    fn rounding_is_idempotent(mantissa in any::<i64>(), scale in 0u32..=10) {
+        let value = decimal(mantissa, scale);
+        for mode in MODES {
+            let once = normalize_scale_with(&value, mode);
+            let twice = normalize_scale_with(&once, mode);
+            prop_assert_eq!(&once, &twice, "mode={:?}", mode);
+        }
+    }
+
+    #[test]
+    fn rounding_is_monotonic(a in any::<i64>(), b in any::<i64>(), scale in 0u32..=8) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        for mode in MODES {
+            let lo_rounded = normalize_scale_with(&decimal(lo, scale), mode);
+            let hi_rounded = normalize_scale_with(&decimal(hi, scale), mode);
+            prop_assert!(lo_rounded <= hi_rounded, "mode={:?} {} -> {} but {} -> {}", mode, lo, lo_rounded, hi, hi_rounded);
+        }
+    }
+
+    #[test]
+    fn rounding_error_is_below_one_cent(mantissa in any::<i64>(), scale in 0u32..=10) {
+        let value = decimal(mantissa, scale);
+        let cent = BigDecimal::from_str("0.01").unwrap();
+        for mode in MODES {
+            let diff = (normalize_scale_with(&value, mode) - &value).abs();
+            prop_assert!(diff < cent, "mode={:?} moved {} by {}", mode, value, diff);
+        }
+    }
+
+    #[test]
+    fn cents_round_trip(cents in -(i64::MAX / 100)..(i64::MAX / 100)) {
+        let money = Money::from_cents(cents);
+        prop_assert_eq!(money.as_cents(), cents);
+        prop_assert_eq!(Money::new(money.inner().clone()), money);
+    }
+
+    #[test]
+    fn as_cents_agrees_with_decimal_string(cents in any::<i64>()) {
+        let parsed = BigDecimal::from_str(&Money::from_cents(cents).to_string()).unwrap();
+        prop_assert_eq!(parsed, BigDecimal::new(cents.into(), 2));
+    }
+
+    #[test]
+    fn allocate_preserves_sum(cents in -1_000_000_000i64..1_000_000_000, weights in prop::collection::vec(0u32..1_000, 1..12)) {
+        let total = Money::from_cents(cents);
+        match total.allocate(&weights) {
+            None => prop_assert!(weights.iter().all(|w| *w == 0)),
+            Some(parts) => {
+                prop_assert_eq!(parts.len(), weights.len());
+                prop_assert_eq!(parts.iter().map(Money::as_cents).sum::<i64>(), cents);
+                let weight_sum: i128 = weights.iter().map(|w| *w as i128).sum();
+                for (part, weight) in parts.iter().zip(&weights) {
+                    // Each share is within one cent of its exact proportional value.
+                    let exact_times_weight_sum = cents as i128 * *weight as i128;
+                    let deviation = (part.as_cents() as i128 * weight_sum - exact_times_weight_sum).abs();
+                    prop_assert!(deviation < weight_sum, "share {} for weight {} drifted", part, weight);
+                    if *weight == 0 { prop_assert_eq!(part.as_cents(), 0); }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn reference_covers_known_ties() {
+    // Sanity-check the reference itself against the documented rounding matrix.
+    assert_eq!(reference_cents(1005, 3, RoundingMode::HalfUp), 101);
+    assert_eq!(reference_cents(1005, 3, RoundingMode::Bankers), 100);
+    assert_eq!(reference_cents(2675, 3, RoundingMode::Bankers), 268);
+    assert_eq!(reference_cents(-2505, 3, RoundingMode::HalfUp), -251);
+    assert_eq!(reference_cents(-2505, 3, RoundingMode::Bankers), -250);
+    assert_eq!(reference_cents(-1239, 3, RoundingMode::Truncate), -123);
+}
@@ -1,30 +1,64 @@
-use prometheus::{IntCounter, Histogram, Registry, IntCounterVec};
+use prometheus::{IntCounter, IntGauge, IntGaugeVec, Histogram, Registry, IntCounterVec};
+
+pub mod logging;
+pub use logging::{init_logging, request_span};
+pub mod shutdown;
+pub use shutdown::shutdown_signal;
+pub mod slo;
+pub use slo::{SloConfig, SloTracker};
 
 #[derive(Clone)]
 pub struct InventoryMetrics {
     pub registry: Registry,
-    pub dual_write_divergence: IntCounter,
-    pub reservation_expired: IntCounter,
-    pub audit_emit_failures: IntCounter,
+    /// Legacy rows found disagreeing with their locations, by tenant and where the check ran.
+    pub dual_write_divergence: IntCounterVec,
+    /// Reservations the sweeper expired, by tenant.
+    pub reservation_expired: IntCounterVec,
+    /// Audit records that could not be published, by audit action.
+    pub audit_emit_failures: IntCounterVec,
     pub sweeper_duration_seconds: Histogram,
+    /// Reservations claimed per sweeper batch.
+    pub sweeper_batch_claimed: Histogram,
+    /// Claimed reservations whose quantity was returned to stock, per batch.
+    pub sweeper_batch_restocked: Histogram,
+    pub sweeper_batch_duration_seconds: Histogram,
     pub heal_latency_seconds: Histogram,
+    /// Dual-write heal attempts by tenant and outcome (`healed`, `converged`, `failed`).
+    pub dual_write_heals: IntCounterVec,
     pub http_errors_total: IntCounterVec,
+    /// Reservation requests refused for insufficient stock, by endpoint (`create`, `batch`, `adjust`).
+    pub reservation_insufficient_stock: IntCounterVec,
+    /// Actively reserved units per tenant, refreshed by the oversell checker.
+    pub reserved_quantity: IntGaugeVec,
+    /// Products newly found with more reserved than on hand.
+    pub oversell_detected: IntCounter,
+    /// Products currently oversold, as of the last check.
+    pub oversold_products: IntGauge,
 }
 
 impl InventoryMetrics {
     pub fn new() -> Self {
         let registry = Registry::new();
-        let dual_write_divergence = IntCounter::new(
-            "dual_write_divergence_total",
-            "Dual write divergence occurrences",
+        let dual_write_divergence = IntCounterVec::new(
+            prometheus::Opts::new(
+                "dual_write_divergence_total",
+                "Dual write divergence occurrences"
+            ),
+            &["tenant_id", "source"]
         ).unwrap();
-        let reservation_expired = IntCounter::new(
-            "inventory_reservation_expired_total",
-            "Expired reservations count",
+        let reservation_expired = IntCounterVec::new(
+            prometheus::Opts::new(
+                "inventory_reservation_expired_total",
+                "Expired reservations count"
+            ),
+            &["tenant_id"]
         ).unwrap();
-        let audit_emit_failures = IntCounter::new(
-            "audit_event_emit_failures_total",
-            "Audit event emission failures",
+        let audit_emit_failures = IntCounterVec::new(
+            prometheus::Opts::new(
+                "audit_event_emit_failures_total",
+                "Audit event emission failures"
+            ),
+            &["action"]
         ).unwrap();
         let sweeper_duration_seconds = Histogram::with_opts(
             prometheus::HistogramOpts::new(
@@ -32,12 +66,37 @@ impl InventoryMetrics {
                 "Duration of a reservation expiration sweep"
             ).buckets(vec![0.01,0.05,0.1,0.25,0.5,1.0,2.0,5.0])
         ).unwrap();
+        let sweeper_batch_claimed = Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "inventory_reservation_sweeper_batch_claimed",
+                "Expired reservations claimed by one sweeper batch"
+            ).buckets(vec![0.0,1.0,10.0,50.0,100.0,250.0,500.0,1000.0,5000.0])
+        ).unwrap();
+        let sweeper_batch_restocked = Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "inventory_reservation_sweeper_batch_restocked",
+                "Expired reservations returned to stock by one sweeper batch"
+            ).buckets(vec![0.0,1.0,10.0,50.0,100.0,250.0,500.0,1000.0,5000.0])
+        ).unwrap();
+        let sweeper_batch_duration_seconds = Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "inventory_reservation_sweeper_batch_duration_seconds",
+                "Duration of one reservation sweeper batch transaction"
+            ).buckets(vec![0.005,0.01,0.05,0.1,0.25,0.5,1.0,2.0])
+        ).unwrap();
         let heal_latency_seconds = Histogram::with_opts(
             prometheus::HistogramOpts::new(
                 "dual_write_heal_latency_seconds",
                 "Time spent attempting to heal a dual-write divergence"
             ).buckets(vec![0.001,0.005,0.01,0.05,0.1,0.25,0.5])
         ).unwrap();
+        let dual_write_heals = IntCounterVec::new(
+            prometheus::Opts::new(
+                "dual_write_heals_total",
+                "Dual write heal attempts by outcome"
+            ),
+            &["tenant_id", "outcome"]
+        ).unwrap();
         let http_errors_total = IntCounterVec::new(
             prometheus::Opts::new(
                 "http_errors_total",
@@ -45,13 +104,59 @@ impl InventoryMetrics {
             ),
             &["service", "code", "status"]
         ).unwrap();
+        let reservation_insufficient_stock = IntCounterVec::new(
+            prometheus::Opts::new(
+                "inventory_reservation_insufficient_stock_total",
+                "Reservation requests rejected due to insufficient stock"
+            ),
+            &["endpoint"]
+        ).unwrap();
+        let reserved_quantity = IntGaugeVec::new(
+            prometheus::Opts::new(
+                "inventory_reserved_quantity",
+                "Units held by active reservations per tenant"
+            ),
+            &["tenant_id"]
+        ).unwrap();
+        let oversell_detected = IntCounter::new(
+            "inventory_oversell_detected_total",
+            "Products detected with reserved quantity above on-hand stock",
+        ).unwrap();
+        let oversold_products = IntGauge::new(
+            "inventory_oversold_products",
+            "Products currently reserved beyond on-hand stock",
+        ).unwrap();
         let _ = registry.register(Box::new(dual_write_divergence.clone()));
         let _ = registry.register(Box::new(reservation_expired.clone()));
         let _ = registry.register(Box::new(audit_emit_failures.clone()));
         let _ = registry.register(Box::new(sweeper_duration_seconds.clone()));
+        let _ = registry.register(Box::new(sweeper_batch_claimed.clone()));
+        let _ = registry.register(Box::new(sweeper_batch_restocked.clone()));
+        let _ = registry.register(Box::new(sweeper_batch_duration_seconds.clone()));
         let _ = registry.register(Box::new(heal_latency_seconds.clone()));
+        let _ = registry.register(Box::new(dual_write_heals.clone()));
         let _ = registry.register(Box::new(http_errors_total.clone()));
-        InventoryMetrics { registry, dual_write_divergence, reservation_expired, audit_emit_failures, sweeper_duration_seconds, heal_latency_seconds, http_errors_total }
+        let _ = registry.register(Box::new(reservation_insufficient_stock.clone()));
+        let _ = registry.register(Box::new(reserved_quantity.clone()));
+        let _ = registry.register(Box::new(oversell_detected.clone()));
+        let _ = registry.register(Box::new(oversold_products.clone()));
+        InventoryMetrics {
+            registry,
+            dual_write_divergence,
+            reservation_expired,
+            audit_emit_failures,
+            sweeper_duration_seconds,
+            sweeper_batch_claimed,
+            sweeper_batch_restocked,
+            sweeper_batch_duration_seconds,
+            heal_latency_seconds,
+            dual_write_heals,
+            http_errors_total,
+            reservation_insufficient_stock,
+            reserved_quantity,
+            oversell_detected,
+            oversold_products,
+        }
     }
 }
 
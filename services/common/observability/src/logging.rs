@@ -0,0 +1,190 @@
+//! Shared log setup for every service.
+//!
+//! [`init_logging`] replaces the per-service `tracing_subscriber::fmt().with_env_filter("info")`:
+//!
+//! - `LOG_FORMAT=json` switches to one JSON object per line (default `text`).
+//! - `LOG_LEVEL` sets the base level (default `info`); `RUST_LOG` directives are applied on top,
+//!   so `RUST_LOG=common_money=debug,sqlx=warn` overrides single modules.
+//! - `LOG_SAMPLE` keeps one in N DEBUG/TRACE events with a given message, e.g.
+//!   `money.normalize=100` (the default). `0` or `1` keeps every event.
+//!
+//! [`request_span`] wraps each HTTP request in a `request` span carrying `request_id`; the
+//! security context extractor fills in `tenant_id`, `actor_id` and `trace_id` once it has parsed
+//! them, and every event logged while handling the request carries those fields.
+
+use axum::body::Body;
+use axum::http::{HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Instrument, Level, Metadata};
+use tracing_subscriber::layer::{Context, Filter, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as log_fmt, EnvFilter, Layer};
+use uuid::Uuid;
+
+/// Sampling applied when `LOG_SAMPLE` is unset.
+pub const DEFAULT_LOG_SAMPLE: &str = "money.normalize=100";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Install the global subscriber from `LOG_FORMAT`, `LOG_LEVEL`, `RUST_LOG` and `LOG_SAMPLE`.
+/// Calling it again (e.g. from tests) leaves the first subscriber in place.
+pub fn init_logging(service: &str) {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let filter = EnvFilter::new(filter_directives(env("LOG_LEVEL").as_deref(), env("RUST_LOG").as_deref()));
+    let sampler = LogSampler::parse(env("LOG_SAMPLE").as_deref().unwrap_or(DEFAULT_LOG_SAMPLE));
+    let json = env("LOG_FORMAT").is_some_and(|f| f.eq_ignore_ascii_case("json"));
+
+    let output = if json {
+        log_fmt::layer().json().with_current_span(true).with_span_list(false).boxed()
+    } else {
+        log_fmt::layer().boxed()
+    };
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(output.with_filter(sampler))
+        .try_init()
+        .is_ok();
+    if installed {
+        tracing::info!(service, format = if json { "json" } else { "text" }, "Logging initialised");
+    }
+}
+
+/// Base level first, then `RUST_LOG`; later directives win for the modules they name.
+fn filter_directives(level: Option<&str>, overrides: Option<&str>) -> String {
+    let base = level.map(str::trim).unwrap_or("info");
+    match overrides.map(str::trim).filter(|o| !o.is_empty()) {
+        Some(overrides) => format!("{base},{overrides}"),
+        None => base.to_string(),
+    }
+}
+
+/// Per-message 1-in-N sampling of DEBUG and TRACE events. INFO and above always pass.
+#[derive(Debug, Default)]
+pub struct LogSampler {
+    rates: HashMap<String, (u64, AtomicU64)>,
+}
+
+impl LogSampler {
+    /// Parse `message=N` pairs separated by commas; malformed entries are ignored.
+    pub fn parse(spec: &str) -> Self {
+        let rates = spec
+            .split(',')
+            .filter_map(|entry| {
+                let (message, rate) = entry.split_once('=')?;
+                let rate = rate.trim().parse::<u64>().ok()?;
+                (rate > 1).then(|| (message.trim().to_string(), (rate, AtomicU64::new(0))))
+            })
+            .collect();
+        Self { rates }
+    }
+
+    /// Whether the next event with this message should be written.
+    pub fn keep(&self, message: &str) -> bool {
+        match self.rates.get(message) {
+            Some((rate, seen)) => seen.fetch_add(1, Ordering::Relaxed) % rate == 0,
+            None => true,
+        }
+    }
+}
+
+impl<S> Filter<S> for LogSampler {
+    fn enabled(&self, _meta: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        true
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, _cx: &Context<'_, S>) -> bool {
+        if self.rates.is_empty() || *event.metadata().level() < Level::DEBUG {
+            return true;
+        }
+        let mut message = MessageVisitor(None);
+        event.record(&mut message);
+        message.0.is_none_or(|m| self.keep(&m))
+    }
+}
+
+struct MessageVisitor(Option<String>);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+/// Middleware opening the `request` span. Reuses an incoming `X-Request-ID` or generates one,
+/// and echoes it on the response so callers can quote it.
+pub async fn request_span(req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+        tenant_id = tracing::field::Empty,
+        actor_id = tracing::field::Empty,
+        trace_id = tracing::field::Empty,
+    );
+    let mut response = next.run(req).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_keeps_one_in_n_for_listed_messages_only() {
+        let sampler = LogSampler::parse("money.normalize=3, bad, other=x, all=1");
+        let kept = (0..9).filter(|_| sampler.keep("money.normalize")).count();
+        assert_eq!(kept, 3);
+        assert!((0..5).all(|_| sampler.keep("all")));
+        assert!((0..5).all(|_| sampler.keep("order.created")));
+    }
+
+    #[tokio::test]
+    async fn request_id_is_reused_or_generated_and_echoed() {
+        use tower::ServiceExt;
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(request_span));
+        let call = |request_id: Option<&str>| {
+            let mut req = Request::builder().uri("/");
+            if let Some(id) = request_id {
+                req = req.header(REQUEST_ID_HEADER, id);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+        let echoed = call(Some("abc-123")).await.unwrap();
+        assert_eq!(echoed.headers()[REQUEST_ID_HEADER], "abc-123");
+        let generated = call(None).await.unwrap();
+        assert!(Uuid::parse_str(generated.headers()[REQUEST_ID_HEADER].to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn rust_log_overrides_follow_the_base_level() {
+        assert_eq!(filter_directives(None, None), "info");
+        assert_eq!(filter_directives(Some("warn"), Some("common_money=debug")), "warn,common_money=debug");
+        assert_eq!(filter_directives(None, Some("  ")), "info");
+    }
+}
@@ -0,0 +1,534 @@
+//! Availability and latency SLIs per service and tenant, for contractual SLO reporting.
+//!
+//! [`SloTracker::layer`] classifies every request once: a 5xx is *unavailable*, and a request
+//! slower than the latency threshold is *slow*. The outcome is recorded two ways:
+//!
+//! - Prometheus counters `slo_requests_total`, `slo_errors_total` and `slo_slow_requests_total`,
+//!   labelled `{service, tenant}`. [`recording_rules`] renders the matching burn-rate recording
+//!   rules.
+//! - An in-process rolling window served by [`SloTracker::route`] (`GET /internal/slo`). It gives
+//!   the current compliance, remaining error budget and 1h/6h burn rates, overall and per tenant.
+//!
+//! Tenant labels are bounded. Tenants in `SLO_TENANTS` (enterprise contracts) always get their
+//! own label. Otherwise the first `SLO_TOP_TENANTS` tenants to reach `SLO_TENANT_MIN_REQUESTS`
+//! requests get one. Everyone else is reported as `other`, and requests without `X-Tenant-ID`
+//! as `none`. Health, metrics and `/internal` routes are not counted.
+//!
+//! Settings (all optional): `SLO_AVAILABILITY_TARGET` (default `0.999`),
+//! `SLO_LATENCY_THRESHOLD_MS` (`500`), `SLO_LATENCY_TARGET` (`0.99`), `SLO_WINDOW_DAYS` (`30`),
+//! `SLO_TOP_TENANTS` (`20`), `SLO_TENANT_MIN_REQUESTS` (`100`), `SLO_TENANTS` (comma-separated
+//! tenant ids).
+
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::routing::{get, MethodRouter};
+use axum::Json;
+use prometheus::{IntCounterVec, Opts, Registry};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+pub const OTHER_TENANTS: &str = "other";
+pub const NO_TENANT: &str = "none";
+/// Width of one rolling-window bucket.
+const BUCKET_SECS: u64 = 600;
+/// Tenants counted toward promotion at once; beyond this, unseen tenants go straight to `other`.
+const MAX_CANDIDATES: usize = 10_000;
+/// Burn-rate windows reported by `/internal/slo` and rendered by [`recording_rules`].
+const BURN_WINDOWS: [(&str, u64); 2] = [("1h", 3600), ("6h", 6 * 3600)];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SloConfig {
+    /// Share of requests that must not fail with a 5xx, e.g. `0.999`.
+    pub availability_target: f64,
+    pub latency_threshold_ms: u64,
+    /// Share of requests that must finish within the threshold.
+    pub latency_target: f64,
+    pub window_days: u64,
+    pub top_tenants: usize,
+    pub tenant_min_requests: u64,
+    pub pinned_tenants: Vec<Uuid>,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        SloConfig {
+            availability_target: 0.999,
+            latency_threshold_ms: 500,
+            latency_target: 0.99,
+            window_days: 30,
+            top_tenants: 20,
+            tenant_min_requests: 100,
+            pinned_tenants: Vec::new(),
+        }
+    }
+}
+
+impl SloConfig {
+    /// Reads the `SLO_*` settings; unset or unparsable values keep their defaults.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        fn parse<T: std::str::FromStr>(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<T> {
+            lookup(name).and_then(|v| v.trim().parse().ok())
+        }
+        let defaults = SloConfig::default();
+        let ratio = |name: &str, default: f64| parse::<f64>(&lookup, name).filter(|v| *v > 0.0 && *v < 1.0).unwrap_or(default);
+        SloConfig {
+            availability_target: ratio("SLO_AVAILABILITY_TARGET", defaults.availability_target),
+            latency_threshold_ms: parse(&lookup, "SLO_LATENCY_THRESHOLD_MS").filter(|v| *v > 0).unwrap_or(defaults.latency_threshold_ms),
+            latency_target: ratio("SLO_LATENCY_TARGET", defaults.latency_target),
+            window_days: parse(&lookup, "SLO_WINDOW_DAYS").filter(|v| (1..=90).contains(v)).unwrap_or(defaults.window_days),
+            top_tenants: parse(&lookup, "SLO_TOP_TENANTS").unwrap_or(defaults.top_tenants),
+            tenant_min_requests: parse(&lookup, "SLO_TENANT_MIN_REQUESTS").unwrap_or(defaults.tenant_min_requests),
+            pinned_tenants: lookup("SLO_TENANTS")
+                .map(|v| v.split(',').filter_map(|id| Uuid::parse_str(id.trim()).ok()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.window_days * 86_400)
+    }
+}
+
+/// Shared per service; clones record into the same counters and window.
+#[derive(Clone)]
+pub struct SloTracker {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    service: &'static str,
+    config: SloConfig,
+    requests: IntCounterVec,
+    errors: IntCounterVec,
+    slow: IntCounterVec,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    labelled: HashSet<Uuid>,
+    candidates: HashMap<Uuid, u64>,
+    windows: HashMap<String, VecDeque<Bucket>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    start: u64,
+    requests: u64,
+    errors: u64,
+    slow: u64,
+}
+
+impl SloTracker {
+    /// Registers the SLO counters in `registry` (pass `prometheus::default_registry()` for
+    /// services that export the default registry).
+    pub fn new(service: &'static str, config: SloConfig, registry: &Registry) -> Self {
+        let counter = |name: &str, help: &str| {
+            let c = IntCounterVec::new(Opts::new(name, help), &["service", "tenant"]).expect("slo counter");
+            let _ = registry.register(Box::new(c.clone()));
+            c
+        };
+        let requests = counter("slo_requests_total", "Requests counted toward SLOs");
+        let errors = counter("slo_errors_total", "Requests that failed with a 5xx (availability SLI)");
+        let slow = counter("slo_slow_requests_total", "Requests slower than the SLO latency threshold");
+        let state = State { labelled: config.pinned_tenants.iter().copied().collect(), ..State::default() };
+        SloTracker { inner: Arc::new(Inner { service, config, requests, errors, slow, state: Mutex::new(state) }) }
+    }
+
+    pub fn config(&self) -> &SloConfig {
+        &self.inner.config
+    }
+
+    /// Middleware recording every request; add it with `Router::layer` after the routes.
+    pub fn layer(&self) -> impl Fn(Request<Body>, Next) -> Pin<Box<dyn Future<Output = Response> + Send>> + Clone + Send + Sync + 'static {
+        let tracker = self.clone();
+        move |req: Request<Body>, next: Next| {
+            let tracker = tracker.clone();
+            Box::pin(async move {
+                let excluded = req.extensions().get::<MatchedPath>().is_some_and(|p| is_excluded(p.as_str()));
+                let tenant = req
+                    .headers()
+                    .get("X-Tenant-ID")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| Uuid::parse_str(v.trim()).ok());
+                let started = Instant::now();
+                let resp = next.run(req).await;
+                if !excluded {
+                    tracker.record(tenant, resp.status().is_server_error(), started.elapsed());
+                }
+                resp
+            })
+        }
+    }
+
+    /// Counts one request; `failed` means a 5xx.
+    pub fn record(&self, tenant: Option<Uuid>, failed: bool, elapsed: Duration) {
+        self.record_at(tenant, failed, elapsed, unix_now());
+    }
+
+    fn record_at(&self, tenant: Option<Uuid>, failed: bool, elapsed: Duration, now: u64) {
+        let inner = &self.inner;
+        // A failed request is already bad; it is not also counted as slow.
+        let slow = !failed && elapsed > Duration::from_millis(inner.config.latency_threshold_ms);
+        let Ok(mut state) = inner.state.lock() else { return };
+        let label = state.tenant_label(tenant, &inner.config);
+        let labels = [inner.service, label.as_str()];
+        inner.requests.with_label_values(&labels).inc();
+        if failed {
+            inner.errors.with_label_values(&labels).inc();
+        }
+        if slow {
+            inner.slow.with_label_values(&labels).inc();
+        }
+        let retention = inner.config.window().as_secs();
+        let window = state.windows.entry(label).or_default();
+        let start = now - now % BUCKET_SECS;
+        match window.back_mut() {
+            Some(bucket) if bucket.start == start => bucket.add(failed, slow),
+            _ => {
+                let mut bucket = Bucket { start, ..Bucket::default() };
+                bucket.add(failed, slow);
+                window.push_back(bucket);
+            }
+        }
+        while window.front().is_some_and(|b| b.start + retention <= start) {
+            window.pop_front();
+        }
+    }
+
+    /// Current compliance over the configured window, overall and per tenant label.
+    pub fn report(&self) -> SloReport {
+        self.report_at(unix_now())
+    }
+
+    fn report_at(&self, now: u64) -> SloReport {
+        let config = &self.inner.config;
+        let state = self.inner.state.lock().map(|s| s.windows.clone()).unwrap_or_default();
+        let mut overall: Vec<Bucket> = Vec::new();
+        let mut tenants: Vec<TenantSlo> = state
+            .iter()
+            .map(|(tenant, window)| {
+                overall.extend(window.iter().copied());
+                TenantSlo { tenant: tenant.clone(), sli: SliSummary::from_buckets(window.iter().copied(), config, now) }
+            })
+            .collect();
+        // Worst first, so the tenants closest to breaching lead the report.
+        tenants.sort_by(|a, b| a.sli.error_budget_remaining.total_cmp(&b.sli.error_budget_remaining).then_with(|| a.tenant.cmp(&b.tenant)));
+        SloReport {
+            service: self.inner.service,
+            objectives: Objectives {
+                availability_target: config.availability_target,
+                latency_threshold_ms: config.latency_threshold_ms,
+                latency_target: config.latency_target,
+                window_days: config.window_days,
+            },
+            overall: SliSummary::from_buckets(overall.into_iter(), config, now),
+            tenants,
+        }
+    }
+
+    /// `GET /internal/slo`.
+    pub fn route<S>(&self) -> MethodRouter<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let tracker = self.clone();
+        get(move || {
+            let tracker = tracker.clone();
+            async move { Json(tracker.report()) }
+        })
+    }
+
+    /// `GET /internal/slo/rules`: [`recording_rules`] for this service as YAML.
+    pub fn rules_route<S>(&self) -> MethodRouter<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let rules = recording_rules(self.inner.service, &self.inner.config);
+        get(move || {
+            let rules = rules.clone();
+            async move { ([(axum::http::header::CONTENT_TYPE, "application/yaml")], rules) }
+        })
+    }
+}
+
+impl State {
+    fn tenant_label(&mut self, tenant: Option<Uuid>, config: &SloConfig) -> String {
+        let Some(tenant) = tenant else { return NO_TENANT.to_string() };
+        if self.labelled.contains(&tenant) {
+            return tenant.to_string();
+        }
+        let pinned = config.pinned_tenants.len();
+        if self.labelled.len().saturating_sub(pinned) >= config.top_tenants {
+            return OTHER_TENANTS.to_string();
+        }
+        if !self.candidates.contains_key(&tenant) && self.candidates.len() >= MAX_CANDIDATES {
+            return OTHER_TENANTS.to_string();
+        }
+        let seen = self.candidates.entry(tenant).or_insert(0);
+        *seen += 1;
+        if *seen >= config.tenant_min_requests {
+            self.candidates.remove(&tenant);
+            self.labelled.insert(tenant);
+            if self.labelled.len().saturating_sub(pinned) >= config.top_tenants {
+                // Every slot is taken; nobody else can be promoted.
+                self.candidates.clear();
+            }
+            return tenant.to_string();
+        }
+        OTHER_TENANTS.to_string()
+    }
+}
+
+impl Bucket {
+    fn add(&mut self, failed: bool, slow: bool) {
+        self.requests += 1;
+        self.errors += failed as u64;
+        self.slow += slow as u64;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SloReport {
+    pub service: &'static str,
+    pub objectives: Objectives,
+    pub overall: SliSummary,
+    pub tenants: Vec<TenantSlo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Objectives {
+    pub availability_target: f64,
+    pub latency_threshold_ms: u64,
+    pub latency_target: f64,
+    pub window_days: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantSlo {
+    pub tenant: String,
+    #[serde(flatten)]
+    pub sli: SliSummary,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SliSummary {
+    pub requests: u64,
+    pub errors: u64,
+    pub slow: u64,
+    /// Share of requests without a 5xx; `1.0` with no traffic.
+    pub availability: f64,
+    /// Share of requests within the latency threshold.
+    pub latency_compliance: f64,
+    /// Share of the availability error budget left in the window; negative once overspent.
+    pub error_budget_remaining: f64,
+    /// Error ratio over the window divided by the allowed ratio; `1.0` spends the budget exactly
+    /// over the window.
+    pub burn_rate_1h: f64,
+    pub burn_rate_6h: f64,
+    pub compliant: bool,
+}
+
+impl SliSummary {
+    fn from_buckets(buckets: impl Iterator<Item = Bucket>, config: &SloConfig, now: u64) -> Self {
+        let buckets: Vec<Bucket> = buckets.collect();
+        let sum = |since: u64| {
+            buckets.iter().filter(|b| b.start + BUCKET_SECS > since).fold(Bucket::default(), |mut acc, b| {
+                acc.requests += b.requests;
+                acc.errors += b.errors;
+                acc.slow += b.slow;
+                acc
+            })
+        };
+        let total = sum(now.saturating_sub(config.window().as_secs()));
+        let ratio = |bad: u64, all: u64| if all == 0 { 0.0 } else { bad as f64 / all as f64 };
+        let budget = 1.0 - config.availability_target;
+        let burn = |secs: u64| {
+            let window = sum(now.saturating_sub(secs));
+            ratio(window.errors, window.requests) / budget
+        };
+        let availability = 1.0 - ratio(total.errors, total.requests);
+        let latency_compliance = 1.0 - ratio(total.slow, total.requests);
+        SliSummary {
+            requests: total.requests,
+            errors: total.errors,
+            slow: total.slow,
+            availability,
+            latency_compliance,
+            error_budget_remaining: 1.0 - ratio(total.errors, total.requests) / budget,
+            burn_rate_1h: burn(BURN_WINDOWS[0].1),
+            burn_rate_6h: burn(BURN_WINDOWS[1].1),
+            compliant: availability >= config.availability_target && latency_compliance >= config.latency_target,
+        }
+    }
+}
+
+/// Prometheus recording rules for `service`: error and slow ratios per tenant over each burn
+/// window (and the SLO window), plus the availability burn rate. Meant for
+/// `monitoring/prometheus/rules`; alert on e.g. `slo:availability_burn_rate:1h > 14.4`.
+pub fn recording_rules(service: &str, config: &SloConfig) -> String {
+    // Rounded so 0.999 renders as 0.001 rather than 0.0010000000000000009.
+    let budget = ((1.0 - config.availability_target) * 1e9).round() / 1e9;
+    let slo_window = format!("{}d", config.window_days);
+    let windows = BURN_WINDOWS.iter().map(|(name, _)| name.to_string()).chain(std::iter::once(slo_window));
+    let mut out = format!("groups:\n  - name: slo.{service}\n    rules:\n");
+    for window in windows {
+        let rate = |metric: &str| format!("sum by (service, tenant) (rate({metric}{{service=\"{service}\"}}[{window}]))");
+        out.push_str(&format!(
+            "      - record: slo:availability_error_ratio:{window}\n        expr: {} / {}\n",
+            rate("slo_errors_total"),
+            rate("slo_requests_total")
+        ));
+        out.push_str(&format!(
+            "      - record: slo:latency_slow_ratio:{window}\n        expr: {} / {}\n",
+            rate("slo_slow_requests_total"),
+            rate("slo_requests_total")
+        ));
+        out.push_str(&format!(
+            "      - record: slo:availability_burn_rate:{window}\n        expr: slo:availability_error_ratio:{window}{{service=\"{service}\"}} / {budget}\n"
+        ));
+    }
+    out
+}
+
+fn is_excluded(route: &str) -> bool {
+    route == "/healthz" || route == "/metrics" || route.starts_with("/internal/")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(config: SloConfig) -> SloTracker {
+        SloTracker::new("test-svc", config, &Registry::new())
+    }
+
+    const DAY: u64 = 86_400;
+    const NOW: u64 = 1_800_000_000;
+
+    #[test]
+    fn tenant_labels_are_capped_with_pinned_tenants_always_labelled() {
+        let pinned = Uuid::new_v4();
+        let slo = tracker(SloConfig { top_tenants: 2, tenant_min_requests: 3, pinned_tenants: vec![pinned], ..SloConfig::default() });
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for _ in 0..3 {
+            for tenant in [a, b, c] {
+                slo.record_at(Some(tenant), false, Duration::ZERO, NOW);
+            }
+        }
+        slo.record_at(Some(pinned), false, Duration::ZERO, NOW);
+        slo.record_at(None, false, Duration::ZERO, NOW);
+        let report = slo.report_at(NOW);
+        let mut labels: Vec<(String, u64)> = report.tenants.iter().map(|t| (t.tenant.clone(), t.sli.requests)).collect();
+        labels.sort();
+        let mut expected = vec![
+            (a.to_string(), 1),
+            (b.to_string(), 1),
+            (pinned.to_string(), 1),
+            (OTHER_TENANTS.to_string(), 7),
+            (NO_TENANT.to_string(), 1),
+        ];
+        expected.sort();
+        assert_eq!(labels, expected, "c missed the two promotion slots; requests before promotion count as other");
+        assert_eq!(report.overall.requests, 11);
+    }
+
+    #[test]
+    fn budgets_and_burn_rates_follow_the_window() {
+        let slo = tracker(SloConfig { availability_target: 0.99, latency_threshold_ms: 100, ..SloConfig::default() });
+        let tenant = None;
+        // Ten days ago: 1000 requests, 5 errors. Within the last hour: 100 requests, 2 errors, 3 slow.
+        for i in 0..1000 {
+            slo.record_at(tenant, i < 5, Duration::ZERO, NOW - 10 * DAY);
+        }
+        for i in 0..100 {
+            slo.record_at(tenant, i < 2, Duration::from_millis(if i >= 97 { 250 } else { 10 }), NOW - 60);
+        }
+        let overall = slo.report_at(NOW).overall;
+        assert_eq!((overall.requests, overall.errors, overall.slow), (1100, 7, 3));
+        assert!((overall.error_budget_remaining - (1.0 - (7.0 / 1100.0) / 0.01)).abs() < 1e-9);
+        assert!((overall.burn_rate_1h - 2.0).abs() < 1e-9, "2% errors against a 1% budget");
+        assert!(overall.compliant);
+
+        // Forty days on, only traffic inside the 30-day window counts.
+        let later = slo.report_at(NOW + 25 * DAY).overall;
+        assert_eq!(later.requests, 100);
+        assert!(!later.compliant, "2% errors breaches 99%");
+        assert_eq!(later.burn_rate_1h, 0.0);
+        slo.record_at(tenant, false, Duration::ZERO, NOW + 25 * DAY);
+        assert_eq!(slo.inner.state.lock().unwrap().windows.values().next().unwrap().len(), 2, "expired buckets are dropped");
+    }
+
+    #[tokio::test]
+    async fn middleware_feeds_the_report_endpoint() {
+        use axum::http::StatusCode;
+        use axum::Router;
+        use tower::ServiceExt;
+
+        let registry = Registry::new();
+        let tenant = Uuid::new_v4();
+        let slo = SloTracker::new("test-svc", SloConfig { pinned_tenants: vec![tenant], ..SloConfig::default() }, &registry);
+        let app = Router::new()
+            .route("/orders/:id", get(|| async { StatusCode::OK }))
+            .route("/fail", get(|| async { StatusCode::BAD_GATEWAY }))
+            .route("/healthz", get(|| async { "ok" }))
+            .route("/internal/slo", slo.route())
+            .layer(axum::middleware::from_fn(slo.layer()));
+        let send = |uri: &str| {
+            let req = Request::builder().uri(uri).header("X-Tenant-ID", tenant.to_string()).body(Body::empty()).unwrap();
+            app.clone().oneshot(req)
+        };
+        for uri in ["/orders/1", "/orders/2", "/fail", "/healthz"] {
+            send(uri).await.unwrap();
+        }
+        let resp = send("/internal/slo").await.unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["overall"]["requests"], 3, "health and /internal requests are not counted: {report}");
+        assert_eq!(report["tenants"][0]["tenant"], tenant.to_string());
+        assert_eq!(report["tenants"][0]["errors"], 1);
+        assert_eq!(report["tenants"][0]["compliant"], false);
+        let errors = registry.gather().into_iter().find(|f| f.get_name() == "slo_errors_total").expect("registered");
+        assert_eq!(errors.get_metric()[0].get_counter().get_value(), 1.0);
+    }
+
+    #[test]
+    fn config_reads_env_style_settings() {
+        let tenant = Uuid::new_v4();
+        let env: HashMap<&str, String> = HashMap::from([
+            ("SLO_AVAILABILITY_TARGET", "0.9995".to_string()),
+            ("SLO_LATENCY_TARGET", "1.5".to_string()),
+            ("SLO_TENANTS", format!("{tenant}, not-a-uuid")),
+        ]);
+        let config = SloConfig::from_lookup(|name| env.get(name).cloned());
+        assert_eq!(config.availability_target, 0.9995);
+        assert_eq!(config.latency_target, 0.99, "out-of-range ratios keep the default");
+        assert_eq!(config.pinned_tenants, vec![tenant]);
+    }
+
+    #[test]
+    fn recording_rules_cover_each_window() {
+        let rules = recording_rules("order-service", &SloConfig::default());
+        for record in ["slo:availability_burn_rate:1h", "slo:availability_burn_rate:6h", "slo:availability_burn_rate:30d", "slo:latency_slow_ratio:6h"] {
+            assert!(rules.contains(&format!("record: {record}\n")), "missing {record} in\n{rules}");
+        }
+        assert!(rules.contains("rate(slo_errors_total{service=\"order-service\"}[1h])"));
+        assert!(rules.contains("} / 0.001\n"), "{rules}");
+    }
+}
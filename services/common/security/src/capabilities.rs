@@ -0,0 +1,286 @@
+//! Checking several capabilities at once. [`CapabilitySet`] is a copyable bit set of
+//! [`Capability`] values, [`ensure_all`] / [`ensure_any`] assert a whole set with one error, and
+//! [`RequireCapabilities`] lets a handler declare its requirements in its signature:
+//!
+//! ```ignore
+//! capability_requirement!(pub CanRefund: all(PaymentProcess, OrderVoid));
+//!
+//! async fn refund(RequireCapabilities(sec, ..): RequireCapabilities<CanRefund>) -> ... {}
+//! ```
+//!
+//! Outcomes are memoized on the [`SecurityContext`] for the rest of the request, so checking the
+//! same capability again does not resolve the role mapping (and tenant overrides) a second time.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use common_http_errors::ApiError;
+
+use crate::context::{SecurityContext, SecurityCtxExtractor};
+use crate::policy::{check_capability, Capability};
+use crate::SecurityError;
+
+/// A set of capabilities, e.g. `CapabilitySet::of(&[Capability::OrderVoid, Capability::PriceOverride])`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CapabilitySet(u16);
+
+impl CapabilitySet {
+    pub const EMPTY: CapabilitySet = CapabilitySet(0);
+
+    pub const fn of(caps: &[Capability]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < caps.len() {
+            bits |= caps[i].bit();
+            i += 1;
+        }
+        CapabilitySet(bits)
+    }
+
+    pub const fn with(self, cap: Capability) -> Self {
+        CapabilitySet(self.0 | cap.bit())
+    }
+
+    pub const fn contains(self, cap: Capability) -> bool {
+        self.0 & cap.bit() != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Members in [`Capability::ALL`] order.
+    pub fn iter(self) -> impl Iterator<Item = Capability> {
+        Capability::ALL.into_iter().filter(move |cap| self.contains(*cap))
+    }
+
+    pub fn first(self) -> Option<Capability> {
+        self.iter().next()
+    }
+}
+
+impl FromIterator<Capability> for CapabilitySet {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        iter.into_iter().fold(CapabilitySet::EMPTY, CapabilitySet::with)
+    }
+}
+
+impl From<Capability> for CapabilitySet {
+    fn from(cap: Capability) -> Self {
+        CapabilitySet::EMPTY.with(cap)
+    }
+}
+
+impl fmt::Debug for CapabilitySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter().map(|cap| cap.as_str())).finish()
+    }
+}
+
+impl fmt::Display for CapabilitySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.iter().map(|cap| cap.as_str()).collect();
+        f.write_str(&names.join(", "))
+    }
+}
+
+/// Per-request memo of capability outcomes, stored on [`SecurityContext`]. Entries are tied to
+/// the tenant and roles they were computed for, so a context that is edited afterwards (e.g. an
+/// approver's roles swapped in) is re-evaluated rather than served stale answers.
+#[derive(Default)]
+pub struct CapabilityCache(Mutex<Option<CachedOutcomes>>);
+
+#[derive(Clone, Copy)]
+struct CachedOutcomes {
+    fingerprint: u64,
+    checked: CapabilitySet,
+    allowed: CapabilitySet,
+}
+
+impl CapabilityCache {
+    pub(crate) fn get(&self, ctx: &SecurityContext, cap: Capability) -> Option<bool> {
+        let cached = (*self.0.lock().ok()?)?;
+        (cached.fingerprint == fingerprint(ctx) && cached.checked.contains(cap)).then(|| cached.allowed.contains(cap))
+    }
+
+    pub(crate) fn record(&self, ctx: &SecurityContext, cap: Capability, allowed: bool) {
+        let Ok(mut slot) = self.0.lock() else { return };
+        let fingerprint = fingerprint(ctx);
+        let mut entry = match *slot {
+            Some(entry) if entry.fingerprint == fingerprint => entry,
+            _ => CachedOutcomes { fingerprint, checked: CapabilitySet::EMPTY, allowed: CapabilitySet::EMPTY },
+        };
+        entry.checked = entry.checked.with(cap);
+        if allowed {
+            entry.allowed = entry.allowed.with(cap);
+        }
+        *slot = Some(entry);
+    }
+}
+
+fn fingerprint(ctx: &SecurityContext) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    ctx.tenant_id.hash(&mut hasher);
+    ctx.roles.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The memo is a per-request detail: copies of a context start with a fresh one.
+impl Clone for CapabilityCache {
+    fn clone(&self) -> Self {
+        CapabilityCache::default()
+    }
+}
+
+impl fmt::Debug for CapabilityCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CapabilityCache")
+    }
+}
+
+/// Requires every capability in `caps`; the error lists all that are missing.
+pub fn ensure_all(ctx: &SecurityContext, caps: CapabilitySet) -> Result<(), SecurityError> {
+    let missing: CapabilitySet = caps.iter().filter(|cap| !check_capability(ctx, *cap)).collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(SecurityError::MissingCapabilities(missing))
+    }
+}
+
+/// Requires at least one capability in `caps` (an empty set is never satisfied).
+pub fn ensure_any(ctx: &SecurityContext, caps: CapabilitySet) -> Result<(), SecurityError> {
+    // Checks every member rather than stopping early so the allow/deny metrics stay per-capability.
+    let allowed = caps.iter().filter(|cap| check_capability(ctx, *cap)).count();
+    if allowed > 0 {
+        Ok(())
+    } else {
+        Err(SecurityError::MissingCapabilities(caps))
+    }
+}
+
+/// Capabilities a handler declares through [`RequireCapabilities`]; usually written with
+/// [`capability_requirement!`](crate::capability_requirement).
+pub trait CapabilityRequirement: Send + Sync + 'static {
+    const CAPABILITIES: CapabilitySet;
+    /// `true` when holding any one of [`Self::CAPABILITIES`] is enough.
+    const ANY: bool = false;
+}
+
+/// Extracts the [`SecurityContext`] and rejects the request with 403 `missing_role` (naming the
+/// first missing capability) unless it satisfies `R`.
+pub struct RequireCapabilities<R: CapabilityRequirement>(pub SecurityContext, pub PhantomData<R>);
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireCapabilities<R>
+where
+    S: Send + Sync,
+    R: CapabilityRequirement,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let SecurityCtxExtractor(ctx) = SecurityCtxExtractor::from_request_parts(parts, state).await?;
+        let outcome = if R::ANY { ensure_any(&ctx, R::CAPABILITIES) } else { ensure_all(&ctx, R::CAPABILITIES) };
+        outcome.map_err(|err| err.into_api_error(ctx.trace_id))?;
+        Ok(RequireCapabilities(ctx, PhantomData))
+    }
+}
+
+/// Declares a [`CapabilityRequirement`] marker type:
+/// `capability_requirement!(pub CanApprove: all(OrderVoid, PriceOverride));` or `any(...)`.
+#[macro_export]
+macro_rules! capability_requirement {
+    ($vis:vis $name:ident: all($($cap:ident),+ $(,)?)) => {
+        $crate::capability_requirement!(@define $vis $name, false, $($cap),+);
+    };
+    ($vis:vis $name:ident: any($($cap:ident),+ $(,)?)) => {
+        $crate::capability_requirement!(@define $vis $name, true, $($cap),+);
+    };
+    (@define $vis:vis $name:ident, $any:expr, $($cap:ident),+) => {
+        $vis struct $name;
+        impl $crate::capabilities::CapabilityRequirement for $name {
+            const CAPABILITIES: $crate::capabilities::CapabilitySet =
+                $crate::capabilities::CapabilitySet::of(&[$($crate::Capability::$cap),+]);
+            const ANY: bool = $any;
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::Role;
+    use axum::http::{Request, StatusCode};
+    use axum::response::IntoResponse;
+    use common_audit::AuditActor;
+    use uuid::Uuid;
+
+    fn mk_ctx(roles: Vec<Role>) -> SecurityContext {
+        SecurityContext { tenant_id: Uuid::new_v4(), actor: AuditActor { id: None, name: None, email: None }, roles, trace_id: None, residency: None, capability_cache: Default::default(), system: None }
+    }
+
+    #[test]
+    fn sets_iterate_in_declaration_order() {
+        let set = CapabilitySet::of(&[Capability::InventoryAdjust, Capability::CustomerView, Capability::CustomerView]);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Capability::CustomerView, Capability::InventoryAdjust]);
+        assert_eq!(set.to_string(), "customer_view, inventory_adjust");
+        assert_eq!(Capability::ALL.into_iter().collect::<CapabilitySet>().len(), Capability::ALL.len());
+    }
+
+    #[test]
+    fn batch_checks_report_every_missing_capability() {
+        let cashier = mk_ctx(vec![Role::Cashier]);
+        let wanted = CapabilitySet::of(&[Capability::PaymentProcess, Capability::OrderVoid, Capability::PriceOverride]);
+        match ensure_all(&cashier, wanted) {
+            Err(SecurityError::MissingCapabilities(missing)) => {
+                assert_eq!(missing, CapabilitySet::of(&[Capability::OrderVoid, Capability::PriceOverride]))
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(ensure_any(&cashier, wanted).is_ok());
+        assert!(ensure_any(&cashier, CapabilitySet::of(&[Capability::OrderVoid, Capability::GdprManage])).is_err());
+        assert!(ensure_any(&cashier, CapabilitySet::EMPTY).is_err());
+        assert!(ensure_all(&mk_ctx(vec![Role::Manager]), wanted).is_ok());
+    }
+
+    #[test]
+    fn memo_follows_role_changes() {
+        let mut ctx = mk_ctx(vec![Role::Cashier]);
+        assert!(ensure_all(&ctx, Capability::OrderVoid.into()).is_err());
+        assert_eq!(ctx.capability_cache.get(&ctx, Capability::OrderVoid), Some(false));
+        ctx.roles = vec![Role::Manager];
+        assert_eq!(ctx.capability_cache.get(&ctx, Capability::OrderVoid), None);
+        assert!(ensure_all(&ctx, Capability::OrderVoid.into()).is_ok());
+        assert_eq!(ctx.clone().capability_cache.get(&ctx, Capability::OrderVoid), None, "clones start empty");
+    }
+
+    capability_requirement!(CanApprove: all(OrderVoid, PriceOverride));
+
+    #[tokio::test]
+    async fn extractor_rejects_with_the_first_missing_capability() {
+        let parts = |roles: &str| {
+            let mut req = Request::builder().uri("/").body(()).unwrap();
+            crate::test_request_headers!(req, roles = roles, tenant = "11111111-1111-1111-1111-111111111111");
+            req.into_parts().0
+        };
+        let mut manager = parts("manager");
+        assert!(RequireCapabilities::<CanApprove>::from_request_parts(&mut manager, &()).await.is_ok());
+        let mut cashier = parts("cashier");
+        let Err(rejection) = RequireCapabilities::<CanApprove>::from_request_parts(&mut cashier, &()).await else {
+            panic!("cashier must be rejected");
+        };
+        assert!(matches!(rejection, ApiError::ForbiddenMissingRole { role: "price_override", .. }), "{rejection:?}");
+        assert_eq!(rejection.into_response().status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -5,7 +5,9 @@ use tracing::Span;
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use common_http_errors::ApiError;
+use crate::capabilities::CapabilityCache;
 use crate::roles::Role;
+use crate::system::SystemActor;
 use common_audit::{AuditActor, extract_actor_from_headers};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +16,15 @@ pub struct SecurityContext {
     pub actor: AuditActor,
     pub roles: Vec<Role>,
     pub trace_id: Option<Uuid>,
+    /// Region the tenant's data must stay in, from `X-Residency`; `None` means unrestricted.
+    #[serde(default)]
+    pub residency: Option<String>,
+    /// Capability outcomes already resolved for this request; see [`crate::capabilities`].
+    #[serde(skip)]
+    pub capability_cache: CapabilityCache,
+    /// Set only for background work; see [`crate::system`].
+    #[serde(skip)]
+    pub system: Option<SystemActor>,
 }
 
 pub struct SecurityCtxExtractor(pub SecurityContext);
@@ -39,6 +50,13 @@ fn roles_from_headers(headers: &HeaderMap) -> Vec<Role> {
         .unwrap_or_default()
 }
 
+fn residency_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers.get("X-Residency")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim().to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+}
+
 fn trace_id_from_headers(headers: &HeaderMap) -> Option<Uuid> {
     headers.get("X-Trace-ID")
         .and_then(|v| v.to_str().ok())
@@ -67,12 +85,16 @@ impl<S> FromRequestParts<S> for SecurityCtxExtractor where S: Send + Sync {
         let actor = extract_actor_from_headers(headers, &claims, subject);
         let roles = roles_from_headers(headers);
         let trace_id = trace_id_from_headers(headers).or_else(|| Some(Uuid::new_v4()));
+        let residency = residency_from_headers(headers);
 
         Span::current().record("tenant_id", tracing::field::display(tenant_id));
+        if let Some(actor_id) = actor.id {
+            Span::current().record("actor_id", tracing::field::display(actor_id));
+        }
         if let Some(tid) = trace_id.as_ref() {
             Span::current().record("trace_id", tracing::field::display(tid));
         }
 
-        Ok(SecurityCtxExtractor(SecurityContext { tenant_id, actor, roles, trace_id }))
+        Ok(SecurityCtxExtractor(SecurityContext { tenant_id, actor, roles, trace_id, residency, capability_cache: CapabilityCache::default(), system: None }))
     }
 }
@@ -1,5 +1,9 @@
 use axum::http::StatusCode;
+use common_http_errors::ApiError;
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::capabilities::CapabilitySet;
 
 #[derive(Debug, Error)]
 pub enum SecurityError {
@@ -8,6 +12,25 @@ pub enum SecurityError {
     #[error("unauthorized - missing required role")]    Forbidden,
     #[error("invalid authorization token")]  InvalidToken,
     #[error("internal security error")]      Internal,
+    #[error("missing capabilities: {0}")]   MissingCapabilities(CapabilitySet),
+}
+
+impl SecurityError {
+    /// The HTTP error services answer with. Missing capabilities become 403 `missing_role`
+    /// naming the first one, the same shape handlers built by hand around `ensure_capability`.
+    pub fn into_api_error(self, trace_id: Option<Uuid>) -> ApiError {
+        match self {
+            SecurityError::MissingCapabilities(missing) => match missing.first() {
+                Some(cap) => ApiError::ForbiddenMissingRole { role: cap.as_str(), trace_id },
+                None => ApiError::Forbidden { trace_id },
+            },
+            SecurityError::Forbidden => ApiError::Forbidden { trace_id },
+            SecurityError::MissingTenant => ApiError::BadRequest { code: "missing_tenant_id", trace_id, message: None },
+            SecurityError::MismatchedTenant => ApiError::ForbiddenCode { code: "tenant_mismatch", trace_id, message: None },
+            SecurityError::InvalidToken => ApiError::ForbiddenCode { code: "invalid_token", trace_id, message: None },
+            SecurityError::Internal => ApiError::Internal { trace_id, message: None },
+        }
+    }
 }
 
 impl From<SecurityError> for (StatusCode, String) {
@@ -15,7 +38,7 @@ impl From<SecurityError> for (StatusCode, String) {
         match e {
             SecurityError::MissingTenant => (StatusCode::BAD_REQUEST, e.to_string()),
             SecurityError::MismatchedTenant => (StatusCode::UNAUTHORIZED, e.to_string()),
-            SecurityError::Forbidden => (StatusCode::FORBIDDEN, e.to_string()),
+            SecurityError::Forbidden | SecurityError::MissingCapabilities(_) => (StatusCode::FORBIDDEN, e.to_string()),
             SecurityError::InvalidToken => (StatusCode::UNAUTHORIZED, e.to_string()),
             SecurityError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
         }
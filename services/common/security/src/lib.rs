@@ -1,12 +1,18 @@
 pub mod test_macros;
+pub mod capabilities;
 pub mod context;
 pub mod error;
 pub mod roles;
 pub mod policy;
+pub mod tenant_policy;
+pub mod system;
 
+pub use capabilities::{ensure_all, ensure_any, CapabilityRequirement, CapabilitySet, RequireCapabilities};
 pub use context::{SecurityContext, SecurityCtxExtractor};
 pub use error::SecurityError;
 pub use roles::{ensure_role, ensure_any_role, Role};
-pub use policy::{Capability, ensure_capability};
+pub use policy::{Capability, default_allowed_roles, ensure_capability, has_capability};
+pub use system::SystemActor;
+pub use tenant_policy::{spawn_policy_refresh, POLICY_AUDIENCE};
 #[cfg(feature = "kafka")]
 pub use policy::emit_capability_denial_audit;
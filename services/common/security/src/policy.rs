@@ -1,3 +1,4 @@
+use crate::tenant_policy::tenant_allowed_roles;
 use crate::{roles::Role, SecurityContext, SecurityError};
 use once_cell::sync::Lazy;
 use prometheus::{IntCounterVec, Opts};
@@ -10,10 +11,16 @@ pub enum Capability {
     PaymentProcess,
     LoyaltyView,
     GdprManage,
+    PriceOverride,
+    OrderVoid,
+    PaymentReconcile,
+    InventoryAdjust,
+    PaymentStandIn,
 }
 
-// Simple mapping: which roles are allowed each capability.
-fn allowed_roles(cap: Capability) -> &'static [Role] {
+// Simple mapping: which roles are allowed each capability. Tenants may override individual
+// capabilities via `tenant_policy`; these remain the defaults.
+pub fn default_allowed_roles(cap: Capability) -> &'static [Role] {
     use Capability::*;
     use Role::*;
     match cap {
@@ -28,19 +35,65 @@ fn allowed_roles(cap: Capability) -> &'static [Role] {
         LoyaltyView => &[SuperAdmin, Admin, Manager, Inventory, Cashier],
         // GdprManage: currently only high-privilege roles
         GdprManage => &[SuperAdmin, Admin],
+        // PriceOverride: manager approval for manual line prices on open orders
+        PriceOverride => &[SuperAdmin, Admin, Manager],
+        // OrderVoid: approving voids; cashiers may only request one (order-service void requests)
+        OrderVoid => &[SuperAdmin, Admin, Manager],
+        // PaymentReconcile: finance back-office work (settlement uploads and reconciliation reports)
+        PaymentReconcile => &[SuperAdmin, Admin],
+        // InventoryAdjust: direct stock corrections (receive / set quantity) outside the order flow
+        InventoryAdjust => &[SuperAdmin, Admin, Manager, Inventory],
+        // PaymentStandIn: stand-in settings (floor limit) and the queue of payments taken offline
+        PaymentStandIn => &[SuperAdmin, Admin, Manager],
     }
 }
 
+fn is_allowed(ctx: &SecurityContext, cap: Capability) -> bool {
+    if let Some(allowed) = ctx.capability_cache.get(ctx, cap) {
+        return allowed;
+    }
+    let allowed = resolve(ctx, cap);
+    ctx.capability_cache.record(ctx, cap, allowed);
+    allowed
+}
+
+fn resolve(ctx: &SecurityContext, cap: Capability) -> bool {
+    if let Some(system) = &ctx.system {
+        return system.capabilities.contains(cap);
+    }
+    // SuperAdmin is never subject to tenant overrides so a bad policy cannot lock operators out.
+    if ctx.roles.contains(&Role::SuperAdmin) {
+        return true;
+    }
+    match tenant_allowed_roles(ctx.tenant_id, cap) {
+        Some(allowed) => ctx.roles.iter().any(|r| allowed.contains(r)),
+        None => {
+            let allowed = default_allowed_roles(cap);
+            ctx.roles.iter().any(|r| allowed.iter().any(|a| a == r))
+        }
+    }
+}
+
+/// Whether the context holds `cap`, without the denial log and metrics of [`ensure_capability`];
+/// for view-time decisions such as field redaction where "no" is not a denied request.
+pub fn has_capability(ctx: &SecurityContext, cap: Capability) -> bool {
+    is_allowed(ctx, cap)
+}
+
 pub fn ensure_capability(ctx: &SecurityContext, cap: Capability) -> Result<(), SecurityError> {
-    let allowed = allowed_roles(cap);
-    if ctx.roles.iter().any(|r| allowed.iter().any(|a| a == r)) {
+    if check_capability(ctx, cap) { Ok(()) } else { Err(SecurityError::Forbidden) }
+}
+
+/// [`is_allowed`] plus the denial log and allow/deny metrics shared by every `ensure_*` check.
+pub(crate) fn check_capability(ctx: &SecurityContext, cap: Capability) -> bool {
+    if is_allowed(ctx, cap) {
         CAPABILITY_CHECKS_TOTAL.with_label_values(&[cap.as_str(), "allow"]).inc();
-        return Ok(());
+        return true;
     }
     tracing::warn!(?cap, roles = ?ctx.roles, tenant_id = %ctx.tenant_id, "capability_denied");
     CAPABILITY_DENIALS_TOTAL.with_label_values(&[cap.as_str()]).inc();
     CAPABILITY_CHECKS_TOTAL.with_label_values(&[cap.as_str(), "deny"]).inc();
-    Err(SecurityError::Forbidden)
+    false
 }
 
 #[cfg(feature = "kafka")]
@@ -90,6 +143,28 @@ static CAPABILITY_CHECKS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
 });
 
 impl Capability {
+    pub const ALL: [Capability; 11] = [
+        Capability::InventoryView,
+        Capability::CustomerView,
+        Capability::CustomerWrite,
+        Capability::PaymentProcess,
+        Capability::LoyaltyView,
+        Capability::GdprManage,
+        Capability::PriceOverride,
+        Capability::OrderVoid,
+        Capability::PaymentReconcile,
+        Capability::InventoryAdjust,
+        Capability::PaymentStandIn,
+    ];
+
+    pub(crate) const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cap| cap.as_str() == value)
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Capability::InventoryView => "inventory_view",
@@ -98,6 +173,11 @@ impl Capability {
             Capability::PaymentProcess => "payment_process",
             Capability::LoyaltyView => "loyalty_view",
             Capability::GdprManage => "gdpr_manage",
+            Capability::PriceOverride => "price_override",
+            Capability::OrderVoid => "order_void",
+            Capability::PaymentReconcile => "payment_reconcile",
+            Capability::InventoryAdjust => "inventory_adjust",
+            Capability::PaymentStandIn => "payment_stand_in",
         }
     }
 }
@@ -109,7 +189,7 @@ mod tests {
     use common_audit::AuditActor;
 
     fn mk_ctx(roles: Vec<Role>) -> SecurityContext {
-        SecurityContext { tenant_id: Uuid::new_v4(), actor: AuditActor { id: Some(Uuid::new_v4()), name: None, email: None }, roles, trace_id: None }
+        SecurityContext { tenant_id: Uuid::new_v4(), actor: AuditActor { id: Some(Uuid::new_v4()), name: None, email: None }, roles, trace_id: None, residency: None, capability_cache: Default::default(), system: None }
     }
 
     #[test]
@@ -125,10 +205,68 @@ mod tests {
         assert!(ensure_capability(&ctx, Capability::CustomerWrite).is_err(), "Cashier should not retain CustomerWrite after refinement");
     }
 
+    #[test]
+    fn cashier_cannot_override_price() {
+        assert!(ensure_capability(&mk_ctx(vec![Role::Cashier]), Capability::PriceOverride).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Manager]), Capability::PriceOverride).is_ok());
+    }
+
+    #[test]
+    fn only_managers_and_up_approve_voids() {
+        assert!(ensure_capability(&mk_ctx(vec![Role::Cashier]), Capability::OrderVoid).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Support]), Capability::OrderVoid).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Manager]), Capability::OrderVoid).is_ok());
+    }
+
+    #[test]
+    fn reconciliation_is_limited_to_admins() {
+        assert!(ensure_capability(&mk_ctx(vec![Role::Cashier]), Capability::PaymentReconcile).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Manager]), Capability::PaymentReconcile).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Admin]), Capability::PaymentReconcile).is_ok());
+    }
+
+    #[test]
+    fn stand_in_is_managed_by_managers_and_up() {
+        assert!(ensure_capability(&mk_ctx(vec![Role::Cashier]), Capability::PaymentStandIn).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Support]), Capability::PaymentStandIn).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Manager]), Capability::PaymentStandIn).is_ok());
+    }
+
+    #[test]
+    fn stock_corrections_exclude_frontline_roles() {
+        assert!(ensure_capability(&mk_ctx(vec![Role::Cashier]), Capability::InventoryAdjust).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Support]), Capability::InventoryAdjust).is_err());
+        assert!(ensure_capability(&mk_ctx(vec![Role::Inventory]), Capability::InventoryAdjust).is_ok());
+    }
+
+    #[test]
+    fn tenant_override_replaces_defaults_but_not_superadmin() {
+        use crate::tenant_policy::{install_tenant_policy, TenantCapabilityPolicy};
+        use std::collections::HashMap;
+
+        let mut cashier = mk_ctx(vec![Role::Cashier]);
+        let tenant_id = cashier.tenant_id;
+        install_tenant_policy(TenantCapabilityPolicy {
+            tenant_id,
+            version: 1,
+            grants: HashMap::from([
+                (Capability::CustomerWrite, vec![Role::Cashier]),
+                (Capability::GdprManage, vec![]),
+            ]),
+        });
+        assert!(ensure_capability(&cashier, Capability::CustomerWrite).is_ok());
+        assert!(ensure_capability(&cashier, Capability::PaymentProcess).is_ok(), "untouched capability keeps defaults");
+        let mut super_admin = mk_ctx(vec![Role::SuperAdmin]);
+        super_admin.tenant_id = tenant_id;
+        assert!(ensure_capability(&super_admin, Capability::GdprManage).is_ok());
+        cashier.tenant_id = Uuid::new_v4();
+        assert!(ensure_capability(&cashier, Capability::CustomerWrite).is_err(), "other tenants use defaults");
+    }
+
     #[test]
     fn superadmin_has_all() {
         let ctx = mk_ctx(vec![Role::SuperAdmin]);
-        for cap in [Capability::InventoryView, Capability::CustomerView, Capability::CustomerWrite, Capability::PaymentProcess, Capability::LoyaltyView, Capability::GdprManage] { 
+        for cap in Capability::ALL {
             assert!(ensure_capability(&ctx, cap).is_ok(), "SuperAdmin missing {:?}", cap);
         }
     }
@@ -30,6 +30,20 @@ impl Role {
     }
 }
 
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Admin => "admin",
+            Role::Manager => "manager",
+            Role::Support => "support",
+            Role::Inventory => "inventory",
+            Role::SuperAdmin => "super_admin",
+            Role::Cashier => "cashier",
+            Role::Unknown(other) => other,
+        }
+    }
+}
+
 impl std::str::FromStr for Role {
     type Err = ();
 
@@ -0,0 +1,103 @@
+//! Identities for work no user asked for: Kafka consumers, sweepers and other background jobs.
+//!
+//! A [`SystemActor`] names the service and job and carries the capabilities the job needs. Its
+//! [`SecurityContext`] is what the job hands to shared code that demands one (tenant-scoped
+//! connections, capability checks) and what its audit events are attributed to, so they carry a
+//! stable actor instead of none. System contexts are only built in code; request headers can never
+//! produce one.
+//!
+//! ```ignore
+//! const SWEEPER: SystemActor = SystemActor::new("inventory-service", "reservation-sweeper", CapabilitySet::of(&[Capability::InventoryAdjust]));
+//! let sec = SWEEPER.context(tenant_id);
+//! ```
+
+use common_audit::AuditActor;
+use uuid::Uuid;
+
+use crate::capabilities::{CapabilityCache, CapabilitySet};
+use crate::context::SecurityContext;
+
+/// Namespace for [`SystemActor::id`], so every replica derives the same id for a job.
+const SYSTEM_ACTOR_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a57_4d0e_4b8a_9c3e_51a7_d2f0_8b64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemActor {
+    pub service: &'static str,
+    pub job: &'static str,
+    /// Everything the job may do; system contexts ignore roles and tenant overrides.
+    pub capabilities: CapabilitySet,
+}
+
+impl SystemActor {
+    pub const fn new(service: &'static str, job: &'static str, capabilities: CapabilitySet) -> Self {
+        SystemActor { service, job, capabilities }
+    }
+
+    /// `system:<service>/<job>`, the actor name on audit events.
+    pub fn name(&self) -> String {
+        format!("system:{}/{}", self.service, self.job)
+    }
+
+    /// Stable across restarts and replicas.
+    pub fn id(&self) -> Uuid {
+        Uuid::new_v5(&SYSTEM_ACTOR_NAMESPACE, self.name().as_bytes())
+    }
+
+    pub fn audit_actor(&self) -> AuditActor {
+        AuditActor { id: Some(self.id()), name: Some(self.name()), email: None }
+    }
+
+    /// Context for one unit of work on `tenant_id`, with a fresh trace id.
+    pub fn context(&self, tenant_id: Uuid) -> SecurityContext {
+        SecurityContext {
+            tenant_id,
+            actor: self.audit_actor(),
+            roles: Vec::new(),
+            trace_id: Some(Uuid::new_v4()),
+            residency: None,
+            capability_cache: CapabilityCache::default(),
+            system: Some(*self),
+        }
+    }
+}
+
+impl SecurityContext {
+    /// The background job acting, when this is a system context.
+    pub fn system_actor(&self) -> Option<&SystemActor> {
+        self.system.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{ensure_capability, Capability};
+    use crate::roles::{ensure_any_role, Role};
+    use crate::tenant_policy::{install_tenant_policy, TenantCapabilityPolicy};
+    use std::collections::HashMap;
+
+    const SWEEPER: SystemActor = SystemActor::new("inventory-service", "reservation-sweeper", CapabilitySet::of(&[Capability::InventoryAdjust]));
+
+    #[test]
+    fn system_contexts_hold_exactly_their_capabilities() {
+        let tenant_id = Uuid::new_v4();
+        // Tenant overrides govern staff roles, not services.
+        install_tenant_policy(TenantCapabilityPolicy { tenant_id, version: 1, grants: HashMap::from([(Capability::InventoryAdjust, vec![])]) });
+        let sec = SWEEPER.context(tenant_id);
+        assert!(ensure_capability(&sec, Capability::InventoryAdjust).is_ok());
+        assert!(ensure_capability(&sec, Capability::InventoryView).is_err());
+        assert!(ensure_any_role(&sec, &[Role::Admin, Role::SuperAdmin]).is_err(), "system contexts have no staff roles");
+        assert_eq!(sec.system_actor(), Some(&SWEEPER));
+    }
+
+    #[test]
+    fn actor_identity_is_stable() {
+        let first = SWEEPER.context(Uuid::new_v4());
+        let second = SWEEPER.context(Uuid::new_v4());
+        assert_eq!(first.actor.id, second.actor.id);
+        assert_ne!(first.trace_id, second.trace_id);
+        assert_eq!(first.actor.name.as_deref(), Some("system:inventory-service/reservation-sweeper"));
+        let other = SystemActor::new("inventory-service", "oversell-checker", CapabilitySet::EMPTY);
+        assert_ne!(other.id(), SWEEPER.id());
+    }
+}
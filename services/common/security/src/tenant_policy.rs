@@ -0,0 +1,282 @@
+//! Tenant-customised role → capability grants distributed by auth-service as signed policy
+//! documents. `ensure_capability` consults the installed policy first and falls back to the
+//! static defaults in `policy.rs` for tenants (or capabilities) without an override.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use common_auth::JwtVerifier;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::policy::Capability;
+use crate::roles::Role;
+use crate::SecurityError;
+
+/// JWT audience used for policy documents so they can never be replayed as access tokens.
+pub const POLICY_AUDIENCE: &str = "novapos-capability-policy";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantCapabilityPolicy {
+    pub tenant_id: Uuid,
+    pub version: i64,
+    pub grants: HashMap<Capability, Vec<Role>>,
+}
+
+/// Claims carried by a signed policy document. Capability and role names use their
+/// snake_case wire form; unknown capability names are ignored for forward compatibility.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDocumentClaims {
+    pub tenant_id: Uuid,
+    pub version: i64,
+    pub grants: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolicyDocumentList {
+    documents: Vec<PolicyDocumentEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolicyDocumentEntry {
+    tenant_id: Uuid,
+    token: String,
+}
+
+static TENANT_POLICIES: Lazy<RwLock<HashMap<Uuid, TenantCapabilityPolicy>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+impl TenantCapabilityPolicy {
+    pub fn from_claims(claims: PolicyDocumentClaims) -> Self {
+        let grants = claims
+            .grants
+            .into_iter()
+            .filter_map(|(capability, roles)| {
+                let capability = Capability::parse(&capability)?;
+                let roles = roles.iter().map(|role| Role::parse_role(role)).collect();
+                Some((capability, roles))
+            })
+            .collect();
+        Self {
+            tenant_id: claims.tenant_id,
+            version: claims.version,
+            grants,
+        }
+    }
+}
+
+/// Installs a tenant policy unless a newer version is already present. Returns whether it was applied.
+pub fn install_tenant_policy(policy: TenantCapabilityPolicy) -> bool {
+    let mut guard = TENANT_POLICIES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match guard.get(&policy.tenant_id) {
+        Some(existing) if existing.version > policy.version => false,
+        _ => {
+            guard.insert(policy.tenant_id, policy);
+            true
+        }
+    }
+}
+
+pub fn remove_tenant_policy(tenant_id: Uuid) {
+    TENANT_POLICIES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(&tenant_id);
+}
+
+/// Drops installed policies for tenants auth-service no longer lists, so they fall back to the
+/// static defaults. Returns how many were removed.
+pub fn retain_tenant_policies(listed: &HashSet<Uuid>) -> usize {
+    let mut guard = TENANT_POLICIES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    prune_unlisted(&mut guard, listed)
+}
+
+fn prune_unlisted(
+    policies: &mut HashMap<Uuid, TenantCapabilityPolicy>,
+    listed: &HashSet<Uuid>,
+) -> usize {
+    let before = policies.len();
+    policies.retain(|tenant_id, _| listed.contains(tenant_id));
+    before - policies.len()
+}
+
+/// Roles granted `cap` by the tenant override, if the tenant customised that capability.
+pub fn tenant_allowed_roles(tenant_id: Uuid, cap: Capability) -> Option<Vec<Role>> {
+    TENANT_POLICIES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&tenant_id)
+        .and_then(|policy| policy.grants.get(&cap).cloned())
+}
+
+pub fn verify_policy_document(
+    verifier: &JwtVerifier,
+    token: &str,
+) -> Result<TenantCapabilityPolicy, SecurityError> {
+    let claims = verifier
+        .verify_document(token, POLICY_AUDIENCE)
+        .map_err(|err| {
+            tracing::warn!(error = %err, "capability policy document rejected");
+            SecurityError::InvalidToken
+        })?;
+    let claims: PolicyDocumentClaims =
+        serde_json::from_value(claims).map_err(|_| SecurityError::InvalidToken)?;
+    Ok(TenantCapabilityPolicy::from_claims(claims))
+}
+
+/// Fetches every tenant's signed policy document with the shared `token` (sent as
+/// `X-Internal-Token`), installs the ones that verify and drops tenants that are no longer listed.
+/// A listed document that fails verification keeps the previously installed version.
+pub async fn refresh_tenant_policies(
+    verifier: &JwtVerifier,
+    client: &reqwest::Client,
+    url: &str,
+    token: Option<&str>,
+) -> Result<usize, SecurityError> {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.header("X-Internal-Token", token);
+    }
+    let list: PolicyDocumentList = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| {
+            tracing::warn!(error = %err, policy_url = %url, "failed to fetch capability policies");
+            SecurityError::Internal
+        })?
+        .json()
+        .await
+        .map_err(|_| SecurityError::Internal)?;
+
+    let listed: HashSet<Uuid> = list.documents.iter().map(|entry| entry.tenant_id).collect();
+    let mut applied = 0;
+    for entry in list.documents {
+        match verify_policy_document(verifier, &entry.token) {
+            Ok(policy) if policy.tenant_id == entry.tenant_id => {
+                if install_tenant_policy(policy) {
+                    applied += 1;
+                }
+            }
+            Ok(_) => {
+                tracing::warn!(tenant_id = %entry.tenant_id, "capability policy document names another tenant")
+            }
+            Err(_) => {}
+        }
+    }
+    let removed = retain_tenant_policies(&listed);
+    if removed > 0 {
+        tracing::info!(removed, "Dropped capability policies no longer published");
+    }
+    Ok(applied)
+}
+
+/// Periodically syncs tenant policies from `CAPABILITY_POLICY_URL` (auth-service
+/// `/capability-policies`), authenticating with `CAPABILITY_POLICY_TOKEN` (auth-service's
+/// `AUTH_CAPABILITY_POLICY_TOKEN`). Without the URL only the static defaults apply.
+pub fn spawn_policy_refresh(verifier: Arc<JwtVerifier>) {
+    let Ok(url) = env::var("CAPABILITY_POLICY_URL") else {
+        return;
+    };
+    let token = env::var("CAPABILITY_POLICY_TOKEN")
+        .ok()
+        .filter(|value| !value.trim().is_empty());
+    if token.is_none() {
+        tracing::warn!(policy_url = %url, "CAPABILITY_POLICY_TOKEN not set; capability policy refresh will be rejected");
+    }
+    let refresh_secs = env::var("CAPABILITY_POLICY_REFRESH_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(60)
+        .max(10);
+    let client = reqwest::Client::new();
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(refresh_secs));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match refresh_tenant_policies(&verifier, &client, &url, token.as_deref()).await {
+                Ok(count) => {
+                    tracing::debug!(count, policy_url = %url, "Refreshed capability policies")
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, policy_url = %url, "Capability policy refresh failed")
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_claims_skips_unknown_capabilities() {
+        let mut grants = BTreeMap::new();
+        grants.insert("customer_write".to_string(), vec!["cashier".to_string()]);
+        grants.insert("future_capability".to_string(), vec!["admin".to_string()]);
+        let policy = TenantCapabilityPolicy::from_claims(PolicyDocumentClaims {
+            tenant_id: Uuid::new_v4(),
+            version: 3,
+            grants,
+        });
+        assert_eq!(policy.grants.len(), 1);
+        assert_eq!(
+            policy.grants[&Capability::CustomerWrite],
+            vec![Role::Cashier]
+        );
+    }
+
+    #[test]
+    fn install_ignores_stale_versions() {
+        let tenant_id = Uuid::new_v4();
+        let policy = |version, roles: Vec<Role>| TenantCapabilityPolicy {
+            tenant_id,
+            version,
+            grants: HashMap::from([(Capability::LoyaltyView, roles)]),
+        };
+        assert!(install_tenant_policy(policy(2, vec![Role::Admin])));
+        assert!(!install_tenant_policy(policy(1, vec![Role::Cashier])));
+        assert_eq!(
+            tenant_allowed_roles(tenant_id, Capability::LoyaltyView),
+            Some(vec![Role::Admin])
+        );
+        assert_eq!(
+            tenant_allowed_roles(tenant_id, Capability::GdprManage),
+            None
+        );
+        remove_tenant_policy(tenant_id);
+        assert_eq!(
+            tenant_allowed_roles(tenant_id, Capability::LoyaltyView),
+            None
+        );
+    }
+
+    #[test]
+    fn prune_drops_tenants_missing_from_the_sync() {
+        // A local map: the installed set is process-global and shared with parallel tests.
+        let (kept, dropped) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut policies: HashMap<Uuid, TenantCapabilityPolicy> = [kept, dropped]
+            .into_iter()
+            .map(|tenant_id| {
+                let policy = TenantCapabilityPolicy {
+                    tenant_id,
+                    version: 1,
+                    grants: HashMap::from([(Capability::CustomerWrite, vec![Role::Admin])]),
+                };
+                (tenant_id, policy)
+            })
+            .collect();
+        assert_eq!(prune_unlisted(&mut policies, &HashSet::from([kept])), 1);
+        assert!(policies.contains_key(&kept) && !policies.contains_key(&dropped));
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::Utc;
+use common_auth::{JwtConfig, JwtVerifier};
+use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use rsa::pkcs1::{EncodeRsaPrivateKey, EncodeRsaPublicKey, LineEnding};
+use rsa::RsaPrivateKey;
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const TEST_ISSUER: &str = "itest-issuer";
+pub const TEST_AUDIENCE: &str = "itest-aud";
+const KID: &str = "local-dev";
+
+/// A fresh RS256 key pair for one test run: signs tokens and builds verifiers that accept them,
+/// so tests no longer need `jwt-dev.pem` on disk.
+#[derive(Clone)]
+pub struct TestSigner {
+    encoding: EncodingKey,
+    public_pem: String,
+}
+
+impl TestSigner {
+    /// Generates a 2048-bit key; takes a moment in debug builds, so share one per test.
+    pub fn generate() -> Self {
+        let private_key = RsaPrivateKey::new(&mut rand::rngs::OsRng, 2048).expect("generate RSA key");
+        let private_pem = private_key.to_pkcs1_pem(LineEnding::LF).expect("encode private key");
+        let public_pem = private_key.to_public_key().to_pkcs1_pem(LineEnding::LF).expect("encode public key");
+        let encoding = EncodingKey::from_rsa_pem(private_pem.as_bytes()).expect("load private key");
+        Self { encoding, public_pem }
+    }
+
+    /// PEM for `JWT_DEV_PUBLIC_KEY_PEM` when the service under test runs as its own process.
+    pub fn public_pem(&self) -> &str {
+        &self.public_pem
+    }
+
+    /// Verifier for [`TEST_ISSUER`] / [`TEST_AUDIENCE`] that trusts this key.
+    pub fn verifier(&self) -> Arc<JwtVerifier> {
+        let key = DecodingKey::from_rsa_pem(self.public_pem.as_bytes()).expect("load public key");
+        let verifier = JwtVerifier::new(JwtConfig::new(TEST_ISSUER, TEST_AUDIENCE));
+        verifier.store().insert_key(KID, key);
+        Arc::new(verifier)
+    }
+
+    /// Token for a random user of `tenant_id`, valid for an hour.
+    pub fn token(&self, tenant_id: Uuid, roles: &[&str]) -> String {
+        self.token_for(Uuid::new_v4(), tenant_id, roles)
+    }
+
+    pub fn token_for(&self, subject: Uuid, tenant_id: Uuid, roles: &[&str]) -> String {
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "sub": subject.to_string(),
+            "tid": tenant_id.to_string(),
+            "roles": roles,
+            "iss": TEST_ISSUER,
+            "aud": TEST_AUDIENCE,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KID.to_string());
+        encode(&header, &claims, &self.encoding).expect("sign token")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifier_accepts_its_own_tokens() {
+        let signer = TestSigner::generate();
+        let tenant_id = Uuid::new_v4();
+        let claims = signer.verifier().verify(&signer.token(tenant_id, &["cashier"])).unwrap();
+        assert_eq!(claims.tenant_id, tenant_id);
+        assert_eq!(claims.roles, vec!["cashier".to_string()]);
+        assert!(TestSigner::generate().verifier().verify(&signer.token(tenant_id, &["cashier"])).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A product-service product, optionally stocked at the tenant's default location
+/// (inventory-service migrations required for stock).
+pub struct ProductFixture {
+    tenant_id: Uuid,
+    id: Uuid,
+    name: String,
+    price_cents: i64,
+    sku: Option<String>,
+    uom: Option<String>,
+    stock: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeededProduct {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub price_cents: i64,
+    pub sku: Option<String>,
+    /// Where the stock was put, when the fixture was stocked.
+    pub location_id: Option<Uuid>,
+}
+
+impl ProductFixture {
+    pub fn new(tenant_id: Uuid) -> Self {
+        let id = Uuid::new_v4();
+        Self { tenant_id, id, name: format!("Product {}", &id.simple().to_string()[..8]), price_cents: 199, sku: None, uom: None, stock: None }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn price_cents(mut self, cents: i64) -> Self {
+        self.price_cents = cents;
+        self
+    }
+
+    pub fn sku(mut self, sku: impl Into<String>) -> Self {
+        self.sku = Some(sku.into());
+        self
+    }
+
+    /// Unit of measure, e.g. `kg` for products sold by weight.
+    pub fn uom(mut self, uom: impl Into<String>) -> Self {
+        self.uom = Some(uom.into());
+        self
+    }
+
+    /// Units on hand at the default location.
+    pub fn stock(mut self, quantity: i32) -> Self {
+        self.stock = Some(quantity);
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Result<SeededProduct> {
+        sqlx::query(
+            "INSERT INTO products (id, tenant_id, name, price, sku, uom)
+             VALUES ($1, $2, $3, $4::numeric / 100, $5, COALESCE($6, 'each'))",
+        )
+        .bind(self.id)
+        .bind(self.tenant_id)
+        .bind(&self.name)
+        .bind(self.price_cents)
+        .bind(&self.sku)
+        .bind(&self.uom)
+        .execute(pool)
+        .await
+        .context("insert product")?;
+        let location_id = match self.stock {
+            Some(quantity) => Some(stock(pool, self.tenant_id, self.id, quantity).await?),
+            None => None,
+        };
+        Ok(SeededProduct { id: self.id, tenant_id: self.tenant_id, price_cents: self.price_cents, sku: self.sku, location_id })
+    }
+}
+
+/// `count` stocked products priced 1.49, 2.49, ... with SKUs `SKU-000`, `SKU-001`, ...
+pub async fn seed_catalog(pool: &PgPool, tenant_id: Uuid, count: usize, stock: i32) -> Result<Vec<SeededProduct>> {
+    let mut products = Vec::with_capacity(count);
+    for i in 0..count {
+        let product = ProductFixture::new(tenant_id).name(format!("Item {i}")).price_cents(149 + 100 * i as i64).sku(format!("SKU-{i:03}")).stock(stock);
+        products.push(product.insert(pool).await?);
+    }
+    Ok(products)
+}
+
+/// The tenant's `DEFAULT` location, created on first use.
+pub async fn default_location(pool: &PgPool, tenant_id: Uuid) -> Result<Uuid> {
+    sqlx::query("INSERT INTO locations (tenant_id, code, name, timezone) VALUES ($1, 'DEFAULT', 'Default', 'UTC') ON CONFLICT DO NOTHING")
+        .bind(tenant_id)
+        .execute(pool)
+        .await
+        .context("insert default location")?;
+    let id = sqlx::query_scalar("SELECT id FROM locations WHERE tenant_id = $1 AND code = 'DEFAULT'").bind(tenant_id).fetch_one(pool).await?;
+    Ok(id)
+}
+
+/// Stock in both the legacy single-location table and `inventory_items`, so reservations
+/// work with `MULTI_LOCATION_ENABLED` on or off.
+async fn stock(pool: &PgPool, tenant_id: Uuid, product_id: Uuid, quantity: i32) -> Result<Uuid> {
+    let location_id = default_location(pool, tenant_id).await?;
+    sqlx::query("INSERT INTO inventory (product_id, tenant_id, quantity, threshold) VALUES ($1, $2, $3, 0)")
+        .bind(product_id)
+        .bind(tenant_id)
+        .bind(quantity)
+        .execute(pool)
+        .await
+        .context("insert inventory")?;
+    sqlx::query("INSERT INTO inventory_items (tenant_id, product_id, location_id, quantity, threshold) VALUES ($1, $2, $3, $4, 0)")
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(quantity)
+        .execute(pool)
+        .await
+        .context("insert inventory_items")?;
+    Ok(location_id)
+}
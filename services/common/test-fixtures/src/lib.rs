@@ -0,0 +1,32 @@
+//! Shared setup for integration tests: containers, service migrations, signed tokens and
+//! builders for the rows most tests need (tenant, user, product with stock, order).
+//!
+//! ```ignore
+//! if !common_test_fixtures::itests_enabled() { return; }
+//! let db = TestPostgres::start().await?;
+//! db.migrate(&["product-service", "inventory-service"]).await?;
+//! let tenant = TenantFixture::new().insert(db.pool()).await?;
+//! let product = ProductFixture::new(tenant.id).price_cents(499).stock(10).insert(db.pool()).await?;
+//! let signer = TestSigner::generate();
+//! let token = signer.token(tenant.id, &["cashier"]);
+//! ```
+//!
+//! Builders insert only the columns they set and rely on migration defaults for the rest, so
+//! they keep working as tables grow.
+
+pub mod auth;
+pub mod catalog;
+pub mod containers;
+pub mod orders;
+pub mod tenants;
+
+pub use auth::TestSigner;
+pub use catalog::{default_location, seed_catalog, ProductFixture, SeededProduct};
+pub use containers::{migrate, TestKafka, TestPostgres, TestRedis};
+pub use orders::{OrderFixture, SeededOrder};
+pub use tenants::{SeededTenant, SeededUser, TenantFixture, UserFixture};
+
+/// Integration tests that need Docker or a database run only with `ENABLE_ITESTS=1`.
+pub fn itests_enabled() -> bool {
+    std::env::var("ENABLE_ITESTS").ok().as_deref() == Some("1")
+}
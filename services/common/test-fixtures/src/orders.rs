@@ -0,0 +1,111 @@
+use crate::catalog::SeededProduct;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// An order-service order written straight to the tables, for tests of what happens after
+/// checkout (returns, voids, reports). Defaults to a completed cash sale.
+pub struct OrderFixture {
+    tenant_id: Uuid,
+    lines: Vec<(Uuid, i32, i64)>,
+    status: String,
+    payment_method: String,
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeededOrder {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub total_cents: i64,
+    /// `order_items.id` per line, in the order the lines were added.
+    pub item_ids: Vec<Uuid>,
+}
+
+impl OrderFixture {
+    pub fn new(tenant_id: Uuid) -> Self {
+        Self { tenant_id, lines: Vec::new(), status: "COMPLETED".into(), payment_method: "cash".into(), created_at: None }
+    }
+
+    pub fn line(mut self, product_id: Uuid, quantity: i32, unit_price_cents: i64) -> Self {
+        self.lines.push((product_id, quantity, unit_price_cents));
+        self
+    }
+
+    /// A line for a seeded product at its list price.
+    pub fn product(self, product: &SeededProduct, quantity: i32) -> Self {
+        self.line(product.id, quantity, product.price_cents)
+    }
+
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.status = status.into();
+        self
+    }
+
+    pub fn payment_method(mut self, method: impl Into<String>) -> Self {
+        self.payment_method = method.into();
+        self
+    }
+
+    /// Backdate the order, e.g. to fall outside a return window.
+    pub fn created_at(mut self, at: DateTime<Utc>) -> Self {
+        self.created_at = Some(at);
+        self
+    }
+
+    pub fn total_cents(&self) -> i64 {
+        self.lines.iter().map(|(_, quantity, cents)| i64::from(*quantity) * cents).sum()
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Result<SeededOrder> {
+        let id = Uuid::new_v4();
+        let total_cents = self.total_cents();
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO orders (id, tenant_id, total, status, payment_method, created_at)
+             VALUES ($1, $2, $3::numeric / 100, $4, $5, COALESCE($6, NOW()))",
+        )
+        .bind(id)
+        .bind(self.tenant_id)
+        .bind(total_cents)
+        .bind(&self.status)
+        .bind(&self.payment_method)
+        .bind(self.created_at)
+        .execute(&mut *tx)
+        .await
+        .context("insert order")?;
+        let mut item_ids = Vec::with_capacity(self.lines.len());
+        for (product_id, quantity, unit_cents) in &self.lines {
+            let item_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO order_items (id, order_id, product_id, quantity, unit_price, line_total)
+                 VALUES ($1, $2, $3, $4, $5::numeric / 100, $6::numeric / 100)",
+            )
+            .bind(item_id)
+            .bind(id)
+            .bind(product_id)
+            .bind(quantity)
+            .bind(unit_cents)
+            .bind(i64::from(*quantity) * unit_cents)
+            .execute(&mut *tx)
+            .await
+            .context("insert order item")?;
+            item_ids.push(item_id);
+        }
+        tx.commit().await?;
+        Ok(SeededOrder { id, tenant_id: self.tenant_id, total_cents, item_ids })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_sums_every_line() {
+        let product = SeededProduct { id: Uuid::new_v4(), tenant_id: Uuid::nil(), price_cents: 250, sku: None, location_id: None };
+        let order = OrderFixture::new(Uuid::nil()).product(&product, 3).line(Uuid::new_v4(), 1, 99);
+        assert_eq!(order.total_cents(), 849);
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::Argon2;
+use common_crypto::{generate_dek, local_key_id, MasterKey};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A tenant, optionally with an active data key for services that encrypt PII.
+pub struct TenantFixture {
+    id: Uuid,
+    name: String,
+    data_key: Option<WrappedKey>,
+}
+
+struct WrappedKey {
+    dek: [u8; 32],
+    blob: Vec<u8>,
+    master_key_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeededTenant {
+    pub id: Uuid,
+    pub name: String,
+    /// Plain DEK, for asserting on encrypted columns.
+    pub dek: Option<[u8; 32]>,
+}
+
+impl Default for TenantFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TenantFixture {
+    pub fn new() -> Self {
+        let id = Uuid::new_v4();
+        Self { id, name: format!("Tenant {}", &id.simple().to_string()[..8]), data_key: None }
+    }
+
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Provision a fresh DEK wrapped with `master_key`, as customer-service does on first use.
+    pub fn with_data_key(mut self, master_key: &MasterKey) -> Self {
+        let dek = generate_dek();
+        let blob = master_key.encrypt_tenant_dek(&dek).expect("wrap tenant DEK");
+        self.data_key = Some(WrappedKey { dek, blob, master_key_id: local_key_id(master_key) });
+        self
+    }
+
+    /// Writes the `tenants` row when auth-service migrations are applied (other services only
+    /// carry the tenant id) and the data key when one was requested.
+    pub async fn insert(self, pool: &PgPool) -> Result<SeededTenant> {
+        if table_exists(pool, "tenants").await? {
+            sqlx::query("INSERT INTO tenants (id, name) VALUES ($1, $2)")
+                .bind(self.id)
+                .bind(&self.name)
+                .execute(pool)
+                .await
+                .context("insert tenant")?;
+        }
+        if let Some(key) = &self.data_key {
+            sqlx::query(
+                "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, master_key_id, active)
+                 VALUES ($1, $2, 1, $3, $4, TRUE)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(self.id)
+            .bind(&key.blob)
+            .bind(&key.master_key_id)
+            .execute(pool)
+            .await
+            .context("insert tenant data key")?;
+        }
+        Ok(SeededTenant { id: self.id, name: self.name, dek: self.data_key.map(|k| k.dek) })
+    }
+}
+
+/// An auth-service user; the password is hashed the way login expects.
+pub struct UserFixture {
+    tenant_id: Uuid,
+    name: String,
+    email: String,
+    role: String,
+    password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeededUser {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub password: String,
+}
+
+impl UserFixture {
+    pub fn new(tenant_id: Uuid) -> Self {
+        let tag = Uuid::new_v4().simple().to_string();
+        Self {
+            tenant_id,
+            name: "Test User".into(),
+            email: format!("user-{}@example.test", &tag[..12]),
+            role: "cashier".into(),
+            password: "Passw0rd!Passw0rd".into(),
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.email = email.into();
+        self
+    }
+
+    /// One of `super_admin`, `admin`, `manager`, `cashier`.
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = role.into();
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = password.into();
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Result<SeededUser> {
+        let id = Uuid::new_v4();
+        let salt = SaltString::generate(&mut rand::rngs::OsRng);
+        let hash = Argon2::default()
+            .hash_password(self.password.as_bytes(), &salt)
+            .map_err(|err| anyhow::anyhow!("hash password: {err}"))?
+            .to_string();
+        sqlx::query("INSERT INTO users (id, tenant_id, name, email, role, password_hash) VALUES ($1, $2, $3, $4, $5, $6)")
+            .bind(id)
+            .bind(self.tenant_id)
+            .bind(&self.name)
+            .bind(&self.email)
+            .bind(&self.role)
+            .bind(hash)
+            .execute(pool)
+            .await
+            .context("insert user")?;
+        Ok(SeededUser { id, tenant_id: self.tenant_id, email: self.email, role: self.role, password: self.password })
+    }
+}
+
+pub(crate) async fn table_exists(pool: &PgPool, table: &str) -> Result<bool> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL").bind(table).fetch_one(pool).await?;
+    Ok(exists)
+}
@@ -0,0 +1,36 @@
+//! Seeds one of everything into a fresh database. Needs Docker (or `TEST_DATABASE_URL`) and
+//! `ENABLE_ITESTS=1`.
+
+use common_crypto::MasterKey;
+use common_test_fixtures::{itests_enabled, seed_catalog, OrderFixture, TenantFixture, TestPostgres, TestSigner, UserFixture};
+
+#[tokio::test]
+async fn seeds_tenant_user_catalog_and_order() -> anyhow::Result<()> {
+    if !itests_enabled() {
+        return Ok(());
+    }
+    let db = TestPostgres::start().await?;
+    db.migrate(&["auth-service", "customer-service", "product-service", "inventory-service", "order-service"]).await?;
+    let pool = db.pool();
+
+    let master_key = MasterKey::from_bytes([7u8; 32])?;
+    let tenant = TenantFixture::new().with_data_key(&master_key).insert(pool).await?;
+    let user = UserFixture::new(tenant.id).role("manager").insert(pool).await?;
+    let products = seed_catalog(pool, tenant.id, 3, 50).await?;
+    let order = OrderFixture::new(tenant.id).product(&products[0], 2).product(&products[2], 1).insert(pool).await?;
+
+    let wrapped: Vec<u8> = sqlx::query_scalar("SELECT encrypted_key FROM tenant_data_keys WHERE tenant_id = $1 AND active").bind(tenant.id).fetch_one(pool).await?;
+    assert_eq!(Some(master_key.decrypt_tenant_dek(&wrapped)?), tenant.dek);
+    let role: String = sqlx::query_scalar("SELECT role FROM users WHERE id = $1").bind(user.id).fetch_one(pool).await?;
+    assert_eq!(role, "manager");
+    let on_hand: i32 = sqlx::query_scalar("SELECT quantity FROM inventory_items WHERE product_id = $1").bind(products[1].id).fetch_one(pool).await?;
+    assert_eq!(on_hand, 50);
+    let total: String = sqlx::query_scalar("SELECT total::text FROM orders WHERE id = $1").bind(order.id).fetch_one(pool).await?;
+    assert_eq!(total, "6.47");
+    assert_eq!(order.item_ids.len(), 2);
+
+    let signer = TestSigner::generate();
+    let claims = signer.verifier().verify(&signer.token(tenant.id, &["manager"]))?;
+    assert_eq!(claims.tenant_id, tenant.id);
+    Ok(())
+}
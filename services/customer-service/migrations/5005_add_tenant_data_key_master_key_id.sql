@@ -0,0 +1,8 @@
+-- Id of the master key that wrapped each tenant DEK (e.g. `local:<fingerprint>`,
+-- `aws-kms:<key arn>`, `vault:<mount>/<key>`). NULL marks DEKs wrapped before ids were
+-- recorded; those are unwrapped by trying each configured local master key.
+ALTER TABLE tenant_data_keys
+    ADD COLUMN IF NOT EXISTS master_key_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_tenant_data_keys_master_key_id
+    ON tenant_data_keys (master_key_id);
@@ -0,0 +1,12 @@
+-- Blind n-gram indexes (HMAC tokens derived from the tenant DEK) for partial email/phone search.
+ALTER TABLE customers
+    ADD COLUMN IF NOT EXISTS email_search_tokens BYTEA[],
+    ADD COLUMN IF NOT EXISTS phone_search_tokens BYTEA[];
+
+CREATE INDEX IF NOT EXISTS idx_customers_email_search_tokens
+    ON customers USING GIN (email_search_tokens)
+    WHERE email_search_tokens IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_customers_phone_search_tokens
+    ON customers USING GIN (phone_search_tokens)
+    WHERE phone_search_tokens IS NOT NULL;
@@ -0,0 +1,26 @@
+-- 5007: row-level tenant isolation.
+-- Handlers run inside transactions that set app.tenant_id (see common-db's TenantScopedPool);
+-- these policies then hide and reject rows belonging to any other tenant. Sessions that never
+-- set app.tenant_id (migrations, maintenance binaries) are not restricted. FORCE applies the
+-- policies to the table owner too; superuser and BYPASSRLS roles always bypass RLS, so the
+-- service should connect as an ordinary role.
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY['customers', 'tenant_data_keys', 'gdpr_tombstones'] LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)
+                WITH CHECK (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)',
+            t
+        );
+    END LOOP;
+END
+$$;
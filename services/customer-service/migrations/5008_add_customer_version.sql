@@ -0,0 +1,3 @@
+-- Row version for optimistic concurrency (exposed as ETag, required via If-Match on update)
+ALTER TABLE customers
+  ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
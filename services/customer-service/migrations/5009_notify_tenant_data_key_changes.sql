@@ -0,0 +1,17 @@
+-- 5009: tell customer-service replicas when a tenant's data keys change (provisioning, rotation,
+-- rewrap, deletion) so they drop the tenant's cached DEKs. Payload is the tenant id.
+CREATE OR REPLACE FUNCTION notify_tenant_data_key_change() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    PERFORM pg_notify('tenant_data_keys', OLD.tenant_id::text);
+  ELSE
+    PERFORM pg_notify('tenant_data_keys', NEW.tenant_id::text);
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS tenant_data_keys_notify ON tenant_data_keys;
+CREATE TRIGGER tenant_data_keys_notify
+  AFTER INSERT OR UPDATE OR DELETE ON tenant_data_keys
+  FOR EACH ROW EXECUTE FUNCTION notify_tenant_data_key_change();
@@ -0,0 +1,47 @@
+-- 5010: marketing and profiling consent per customer. customer_consents holds the current answer
+-- per purpose; customer_consent_events keeps every change (source and actor) as evidence.
+CREATE TABLE IF NOT EXISTS customer_consents (
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL REFERENCES customers(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL CHECK (purpose IN ('marketing_email', 'marketing_sms', 'profiling')),
+    granted BOOLEAN NOT NULL,
+    source TEXT NOT NULL,
+    recorded_by UUID,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, customer_id, purpose)
+);
+
+CREATE TABLE IF NOT EXISTS customer_consent_events (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL REFERENCES customers(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    granted BOOLEAN NOT NULL,
+    source TEXT NOT NULL,
+    recorded_by UUID,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_consent_events_customer
+    ON customer_consent_events (tenant_id, customer_id, recorded_at);
+
+-- Same tenant isolation as 5007.
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY['customer_consents', 'customer_consent_events'] LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)
+                WITH CHECK (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)',
+            t
+        );
+    END LOOP;
+END
+$$;
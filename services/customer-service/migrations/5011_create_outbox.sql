@@ -0,0 +1,16 @@
+-- 5011: outbox for events customer-service publishes (customer.erased). Same table the other
+-- services stage into; order-service's relay publishes it, so this only creates it when
+-- customer-service is migrated on its own.
+
+CREATE TABLE IF NOT EXISTS outbox (
+  id BIGSERIAL PRIMARY KEY,
+  tenant_id TEXT NOT NULL,
+  topic TEXT NOT NULL,
+  payload JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  published_at TIMESTAMPTZ,
+  retry_count INT NOT NULL DEFAULT 0
+);
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_outbox_unpublished ON outbox (published_at) WHERE published_at IS NULL;
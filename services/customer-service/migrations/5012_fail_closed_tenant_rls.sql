@@ -0,0 +1,58 @@
+-- 5012: make tenant isolation fail closed.
+-- The policies from 5007 and 5010 let any session that never set app.tenant_id see every row,
+-- so a query on the raw pool silently spanned tenants. They now only match the tenant in
+-- app.tenant_id and match nothing when it is unset. Because FORCE applies them to the table owner,
+-- the service's own login sees no customer rows outside a scoped transaction.
+--
+-- Two roles carry the access instead; the login role is made a member of both:
+--   pos_tenant        taken with SET LOCAL ROLE by TenantScopedPool::begin, so the policies apply
+--                     even when the service logs in as the owner or a superuser.
+--   pos_cross_tenant  set on the dedicated pool used by sweepers and maintenance binaries
+--                     (common_db::pool::PoolSettings::connect_cross_tenant); its own policy lets
+--                     it see every tenant.
+-- Creating the roles needs CREATEROLE; where the migration user lacks it, create them beforehand.
+
+DO $$
+DECLARE
+    r TEXT;
+BEGIN
+    FOREACH r IN ARRAY ARRAY['pos_tenant', 'pos_cross_tenant'] LOOP
+        BEGIN
+            EXECUTE format('CREATE ROLE %I NOLOGIN', r);
+        EXCEPTION WHEN duplicate_object THEN
+            NULL;
+        END;
+        IF NOT pg_has_role(current_user, r, 'MEMBER') THEN
+            EXECUTE format('GRANT %I TO %I', r, current_user);
+        END IF;
+        EXECUTE format('GRANT USAGE ON SCHEMA public TO %I', r);
+        EXECUTE format('GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO %I', r);
+        EXECUTE format('GRANT USAGE, SELECT, UPDATE ON ALL SEQUENCES IN SCHEMA public TO %I', r);
+        EXECUTE format('ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO %I', r);
+        EXECUTE format('ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT USAGE, SELECT, UPDATE ON SEQUENCES TO %I', r);
+    END LOOP;
+END
+$$;
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOR t IN
+        SELECT tablename FROM pg_policies
+        WHERE schemaname = current_schema() AND policyname = 'tenant_isolation'
+    LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (tenant_id = NULLIF(current_setting(''app.tenant_id'', true), '''')::uuid)
+                WITH CHECK (tenant_id = NULLIF(current_setting(''app.tenant_id'', true), '''')::uuid)',
+            t
+        );
+        EXECUTE format('DROP POLICY IF EXISTS cross_tenant_jobs ON %I', t);
+        EXECUTE format('CREATE POLICY cross_tenant_jobs ON %I TO pos_cross_tenant USING (true) WITH CHECK (true)', t);
+    END LOOP;
+END
+$$;
@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use common_crypto::{deterministic_hash, encrypt_field, MasterKey};
+use common_config::require_secret;
+use common_crypto::{deterministic_hash, encrypt_field_with_aad, FieldAad, MasterKeyProvider};
+use customer_service::{master_key_provider_from_env, search_tokens, EMAIL_FIELD, PHONE_FIELD};
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -42,14 +44,12 @@ async fn main() -> Result<()> {
         return Err(anyhow!("--batch-size must be positive"));
     }
 
-    let database_url =
-        std::env::var("DATABASE_URL").context("DATABASE_URL must be set for backfill script")?;
-    let master_raw = std::env::var("CUSTOMER_MASTER_KEY")
-        .context("CUSTOMER_MASTER_KEY must be set for backfill script")?;
-    let master = MasterKey::from_base64(&master_raw)
-        .map_err(|err| anyhow!("Failed to parse CUSTOMER_MASTER_KEY: {err}"))?;
+    let database_url = require_secret("DATABASE_URL")
+        .await
+        .context("DATABASE_URL is needed for backfill script")?;
+    let master = master_key_provider_from_env().await?;
 
-    let pool = PgPool::connect(&database_url).await?;
+    let pool = PgPool::connect_with(common_db::cross_tenant_options(database_url.parse()?)).await?;
 
     if opts.dry_run {
         let count: i64 = pending_count(&pool, opts.tenant).await?;
@@ -72,15 +72,19 @@ async fn main() -> Result<()> {
 
         let mut tx = pool.begin().await?;
         for row in &rows {
-            let dek = resolve_dek(&mut cache, &pool, &master, row.tenant_id).await?;
+            let dek = resolve_dek(&mut cache, &pool, master.as_ref(), row.tenant_id).await?;
 
             let sanitized_email = sanitize_optional(row.email.clone());
             let sanitized_phone = sanitize_optional(row.phone.clone());
 
             let email_encrypted = match &sanitized_email {
                 Some(value) => Some(
-                    encrypt_field(&dek.key, value.as_bytes())
-                        .map_err(|err| anyhow!("Failed to encrypt email for {}: {err}", row.id))?,
+                    encrypt_field_with_aad(
+                        &dek.key,
+                        FieldAad::new(row.tenant_id.as_bytes(), EMAIL_FIELD),
+                        value.as_bytes(),
+                    )
+                    .map_err(|err| anyhow!("Failed to encrypt email for {}: {err}", row.id))?,
                 ),
                 None => None,
             };
@@ -101,8 +105,12 @@ async fn main() -> Result<()> {
 
             let phone_encrypted = match &sanitized_phone {
                 Some(value) => Some(
-                    encrypt_field(&dek.key, value.as_bytes())
-                        .map_err(|err| anyhow!("Failed to encrypt phone for {}: {err}", row.id))?,
+                    encrypt_field_with_aad(
+                        &dek.key,
+                        FieldAad::new(row.tenant_id.as_bytes(), PHONE_FIELD),
+                        value.as_bytes(),
+                    )
+                    .map_err(|err| anyhow!("Failed to encrypt phone for {}: {err}", row.id))?,
                 ),
                 None => None,
             };
@@ -121,6 +129,17 @@ async fn main() -> Result<()> {
                     None => None,
                 };
 
+            let email_tokens = match &sanitized_email {
+                Some(value) => search_tokens(&dek.key, EMAIL_FIELD, &normalize_email(value))
+                    .map_err(|err| anyhow!("Failed to index email for {}: {err}", row.id))?,
+                None => None,
+            };
+            let phone_tokens = match &sanitized_phone {
+                Some(value) => search_tokens(&dek.key, PHONE_FIELD, &normalize_phone(value))
+                    .map_err(|err| anyhow!("Failed to index phone for {}: {err}", row.id))?,
+                None => None,
+            };
+
             sqlx::query(
                 "UPDATE customers
                  SET email_encrypted = $1,
@@ -128,7 +147,9 @@ async fn main() -> Result<()> {
                      email_hash = $3,
                      phone_hash = $4,
                      pii_key_version = $5,
-                     pii_encrypted_at = NOW()
+                     pii_encrypted_at = NOW(),
+                     email_search_tokens = $7,
+                     phone_search_tokens = $8
                  WHERE id = $6",
             )
             .bind(email_encrypted.as_ref())
@@ -137,6 +158,8 @@ async fn main() -> Result<()> {
             .bind(phone_hash.as_ref())
             .bind(dek.version)
             .bind(row.id)
+            .bind(email_tokens)
+            .bind(phone_tokens)
             .execute(&mut *tx)
             .await?;
         }
@@ -153,14 +176,14 @@ async fn main() -> Result<()> {
 async fn resolve_dek(
     cache: &mut HashMap<Uuid, TenantDek>,
     pool: &PgPool,
-    master: &MasterKey,
+    master: &dyn MasterKeyProvider,
     tenant_id: Uuid,
 ) -> Result<TenantDek> {
     if let Some(entry) = cache.get(&tenant_id) {
         return Ok(entry.clone());
     }
     let row = sqlx::query(
-        "SELECT key_version, encrypted_key
+        "SELECT key_version, encrypted_key, master_key_id
          FROM tenant_data_keys
          WHERE tenant_id = $1 AND active = TRUE
          ORDER BY key_version DESC
@@ -175,8 +198,10 @@ async fn resolve_dek(
 
     let version = row.get::<i32, _>("key_version");
     let encrypted = row.get::<Vec<u8>, _>("encrypted_key");
+    let master_key_id = row.get::<Option<String>, _>("master_key_id");
     let key = master
-        .decrypt_tenant_dek(&encrypted)
+        .unwrap_dek(master_key_id.as_deref(), &encrypted)
+        .await
         .map_err(|err| anyhow!("Failed to decrypt tenant data key for {tenant_id}: {err}"))?;
 
     let dek = TenantDek { version, key };
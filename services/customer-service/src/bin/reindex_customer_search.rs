@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use common_config::require_secret;
+use common_crypto::{decrypt_field_with_aad, FieldAad, MasterKeyProvider};
+use customer_service::{master_key_provider_from_env, search_tokens, EMAIL_FIELD, PHONE_FIELD};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Rebuild the blind n-gram search index for encrypted customer email/phone values",
+    long_about = None
+)]
+struct Options {
+    /// Limit processing to a single tenant
+    #[arg(long = "tenant", value_name = "UUID")]
+    tenant: Option<Uuid>,
+
+    /// Number of rows to scan per batch
+    #[arg(long = "batch-size", default_value_t = 200)]
+    batch_size: i64,
+
+    /// Re-index every row instead of only rows missing tokens (e.g. after changing the gram size)
+    #[arg(long = "all")]
+    all: bool,
+
+    /// Print stats without writing any changes
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct CustomerRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    email_encrypted: Option<Vec<u8>>,
+    phone_encrypted: Option<Vec<u8>>,
+    pii_key_version: Option<i32>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Options::parse();
+    if opts.batch_size <= 0 {
+        return Err(anyhow!("--batch-size must be positive"));
+    }
+
+    let database_url = require_secret("DATABASE_URL")
+        .await
+        .context("DATABASE_URL is needed for reindex script")?;
+    let master = master_key_provider_from_env().await?;
+
+    let pool = PgPool::connect_with(common_db::cross_tenant_options(database_url.parse()?)).await?;
+    let mut cache: HashMap<(Uuid, i32), [u8; 32]> = HashMap::new();
+    let mut cursor = Uuid::nil();
+    let mut total = 0usize;
+
+    loop {
+        let rows = fetch_batch(&pool, &opts, cursor).await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        cursor = last.id;
+        if opts.dry_run {
+            total += rows.len();
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        for row in &rows {
+            let version = row.pii_key_version.ok_or_else(|| {
+                anyhow!("Customer {} has ciphertext without a key version", row.id)
+            })?;
+            let key =
+                resolve_dek(&mut cache, &pool, master.as_ref(), row.tenant_id, version).await?;
+            let email_tokens = match decrypt(&key, row.tenant_id, EMAIL_FIELD, &row.email_encrypted)
+                .map_err(|err| anyhow!("Failed to decrypt email for {}: {err}", row.id))?
+            {
+                Some(email) => search_tokens(&key, EMAIL_FIELD, &normalize_email(&email))
+                    .map_err(|err| anyhow!("Failed to index email for {}: {err}", row.id))?,
+                None => None,
+            };
+            let phone_tokens = match decrypt(&key, row.tenant_id, PHONE_FIELD, &row.phone_encrypted)
+                .map_err(|err| anyhow!("Failed to decrypt phone for {}: {err}", row.id))?
+            {
+                Some(phone) => search_tokens(&key, PHONE_FIELD, &normalize_phone(&phone))
+                    .map_err(|err| anyhow!("Failed to index phone for {}: {err}", row.id))?,
+                None => None,
+            };
+
+            sqlx::query(
+                "UPDATE customers
+                 SET email_search_tokens = $1,
+                     phone_search_tokens = $2
+                 WHERE id = $3 AND pii_key_version = $4",
+            )
+            .bind(email_tokens)
+            .bind(phone_tokens)
+            .bind(row.id)
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        total += rows.len();
+        println!("Re-indexed {} rows (total {total})", rows.len());
+    }
+
+    if opts.dry_run {
+        println!("Dry run: {total} customers need search tokens");
+    } else {
+        println!("Reindex complete. Updated {total} customers.");
+    }
+    Ok(())
+}
+
+fn decrypt(
+    key: &[u8; 32],
+    tenant_id: Uuid,
+    field: &str,
+    blob: &Option<Vec<u8>>,
+) -> Result<Option<String>> {
+    let Some(bytes) = blob else {
+        return Ok(None);
+    };
+    let plaintext = decrypt_field_with_aad(key, FieldAad::new(tenant_id.as_bytes(), field), bytes)
+        .map_err(|err| anyhow!("{err}"))?;
+    Ok(Some(String::from_utf8(plaintext)?))
+}
+
+async fn resolve_dek(
+    cache: &mut HashMap<(Uuid, i32), [u8; 32]>,
+    pool: &PgPool,
+    master: &dyn MasterKeyProvider,
+    tenant_id: Uuid,
+    version: i32,
+) -> Result<[u8; 32]> {
+    if let Some(key) = cache.get(&(tenant_id, version)) {
+        return Ok(*key);
+    }
+    let row = sqlx::query(
+        "SELECT encrypted_key, master_key_id
+         FROM tenant_data_keys
+         WHERE tenant_id = $1 AND key_version = $2
+         LIMIT 1",
+    )
+    .bind(tenant_id)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("No tenant data key v{version} found for tenant {tenant_id}"))?;
+
+    let encrypted = row.get::<Vec<u8>, _>("encrypted_key");
+    let master_key_id = row.get::<Option<String>, _>("master_key_id");
+    let key = master
+        .unwrap_dek(master_key_id.as_deref(), &encrypted)
+        .await
+        .map_err(|err| anyhow!("Failed to decrypt tenant data key for {tenant_id}: {err}"))?;
+    cache.insert((tenant_id, version), key);
+    Ok(key)
+}
+
+async fn fetch_batch(pool: &PgPool, opts: &Options, after: Uuid) -> Result<Vec<CustomerRow>> {
+    Ok(sqlx::query_as::<_, CustomerRow>(
+        "SELECT id, tenant_id, email_encrypted, phone_encrypted, pii_key_version
+         FROM customers
+         WHERE id > $1
+           AND ($2::uuid IS NULL OR tenant_id = $2)
+           AND (
+                (email_encrypted IS NOT NULL AND ($3 OR email_search_tokens IS NULL))
+             OR (phone_encrypted IS NOT NULL AND ($3 OR phone_search_tokens IS NULL))
+           )
+         ORDER BY id
+         LIMIT $4",
+    )
+    .bind(after)
+    .bind(opts.tenant)
+    .bind(opts.all)
+    .bind(opts.batch_size)
+    .fetch_all(pool)
+    .await?)
+}
+
+fn normalize_email(value: &str) -> String {
+    value.trim().to_ascii_lowercase()
+}
+
+fn normalize_phone(value: &str) -> String {
+    value.chars().filter(|c| c.is_ascii_digit()).collect()
+}
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use common_config::require_secret;
+use common_crypto::{envelope_version, rewrap_field, EnvelopeVersion, FieldAad, MasterKeyProvider};
+use customer_service::{master_key_provider_from_env, EMAIL_FIELD, PHONE_FIELD};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Re-wrap legacy customer PII ciphertexts into the versioned, tenant/field-bound envelope",
+    long_about = None
+)]
+struct Options {
+    /// Limit processing to a single tenant
+    #[arg(long = "tenant", value_name = "UUID")]
+    tenant: Option<Uuid>,
+
+    /// Number of rows to scan per batch
+    #[arg(long = "batch-size", default_value_t = 200)]
+    batch_size: i64,
+
+    /// Print stats without writing any changes
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct CustomerRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    email_encrypted: Option<Vec<u8>>,
+    phone_encrypted: Option<Vec<u8>>,
+    pii_key_version: Option<i32>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Options::parse();
+    if opts.batch_size <= 0 {
+        return Err(anyhow!("--batch-size must be positive"));
+    }
+
+    let database_url = require_secret("DATABASE_URL")
+        .await
+        .context("DATABASE_URL is needed for rewrap script")?;
+    let master = master_key_provider_from_env().await?;
+
+    let pool = PgPool::connect_with(common_db::cross_tenant_options(database_url.parse()?)).await?;
+    let mut cache: HashMap<(Uuid, i32), [u8; 32]> = HashMap::new();
+    let mut cursor = Uuid::nil();
+    let (mut scanned, mut rewrapped) = (0usize, 0usize);
+
+    loop {
+        let rows = fetch_batch(&pool, opts.tenant, cursor, opts.batch_size).await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        cursor = last.id;
+        scanned += rows.len();
+
+        let pending: Vec<&CustomerRow> = rows
+            .iter()
+            .filter(|row| is_legacy(&row.email_encrypted) || is_legacy(&row.phone_encrypted))
+            .collect();
+        if opts.dry_run {
+            rewrapped += pending.len();
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        for row in pending {
+            let version = row.pii_key_version.ok_or_else(|| {
+                anyhow!("Customer {} has ciphertext without a key version", row.id)
+            })?;
+            let key =
+                resolve_dek(&mut cache, &pool, master.as_ref(), row.tenant_id, version).await?;
+            let email = rewrap(&key, row.tenant_id, EMAIL_FIELD, &row.email_encrypted)
+                .map_err(|err| anyhow!("Failed to re-wrap email for {}: {err}", row.id))?;
+            let phone = rewrap(&key, row.tenant_id, PHONE_FIELD, &row.phone_encrypted)
+                .map_err(|err| anyhow!("Failed to re-wrap phone for {}: {err}", row.id))?;
+
+            sqlx::query(
+                "UPDATE customers
+                 SET email_encrypted = $1,
+                     phone_encrypted = $2
+                 WHERE id = $3 AND pii_key_version = $4",
+            )
+            .bind(email.as_ref())
+            .bind(phone.as_ref())
+            .bind(row.id)
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+            rewrapped += 1;
+        }
+        tx.commit().await?;
+        println!("Scanned {scanned} rows, re-wrapped {rewrapped}");
+    }
+
+    if opts.dry_run {
+        println!("Dry run: {rewrapped} of {scanned} customers have legacy ciphertexts");
+    } else {
+        println!("Re-wrap complete. Updated {rewrapped} of {scanned} customers.");
+    }
+    Ok(())
+}
+
+fn is_legacy(blob: &Option<Vec<u8>>) -> bool {
+    blob.as_deref()
+        .is_some_and(|bytes| envelope_version(bytes) == EnvelopeVersion::Legacy)
+}
+
+/// Upgrades a legacy blob in place (same key); versioned blobs and NULLs pass through unchanged.
+fn rewrap(
+    key: &[u8; 32],
+    tenant_id: Uuid,
+    field: &str,
+    blob: &Option<Vec<u8>>,
+) -> Result<Option<Vec<u8>>, common_crypto::CryptoError> {
+    match blob {
+        Some(bytes) if envelope_version(bytes) == EnvelopeVersion::Legacy => {
+            rewrap_field(key, key, FieldAad::new(tenant_id.as_bytes(), field), bytes).map(Some)
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+async fn resolve_dek(
+    cache: &mut HashMap<(Uuid, i32), [u8; 32]>,
+    pool: &PgPool,
+    master: &dyn MasterKeyProvider,
+    tenant_id: Uuid,
+    version: i32,
+) -> Result<[u8; 32]> {
+    if let Some(key) = cache.get(&(tenant_id, version)) {
+        return Ok(*key);
+    }
+    let row = sqlx::query(
+        "SELECT encrypted_key, master_key_id
+         FROM tenant_data_keys
+         WHERE tenant_id = $1 AND key_version = $2
+         LIMIT 1",
+    )
+    .bind(tenant_id)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("No tenant data key v{version} found for tenant {tenant_id}"))?;
+
+    let encrypted = row.get::<Vec<u8>, _>("encrypted_key");
+    let master_key_id = row.get::<Option<String>, _>("master_key_id");
+    let key = master
+        .unwrap_dek(master_key_id.as_deref(), &encrypted)
+        .await
+        .map_err(|err| anyhow!("Failed to decrypt tenant data key for {tenant_id}: {err}"))?;
+    cache.insert((tenant_id, version), key);
+    Ok(key)
+}
+
+async fn fetch_batch(
+    pool: &PgPool,
+    tenant: Option<Uuid>,
+    after: Uuid,
+    batch_size: i64,
+) -> Result<Vec<CustomerRow>> {
+    Ok(sqlx::query_as::<_, CustomerRow>(
+        "SELECT id, tenant_id, email_encrypted, phone_encrypted, pii_key_version
+         FROM customers
+         WHERE id > $1
+           AND ($2::uuid IS NULL OR tenant_id = $2)
+           AND (email_encrypted IS NOT NULL OR phone_encrypted IS NOT NULL)
+         ORDER BY id
+         LIMIT $3",
+    )
+    .bind(after)
+    .bind(tenant)
+    .bind(batch_size)
+    .fetch_all(pool)
+    .await?)
+}
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use common_config::require_secret;
+use customer_service::master_key_provider_from_env;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Re-wrap tenant data keys under the active master key after a master key rotation",
+    long_about = None
+)]
+struct Options {
+    /// Limit processing to a single tenant
+    #[arg(long = "tenant", value_name = "UUID")]
+    tenant: Option<Uuid>,
+
+    /// Number of keys to re-wrap per batch
+    #[arg(long = "batch-size", default_value_t = 100)]
+    batch_size: i64,
+
+    /// Print stats without writing any changes
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct TenantKeyRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    key_version: i32,
+    encrypted_key: Vec<u8>,
+    master_key_id: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Options::parse();
+    if opts.batch_size <= 0 {
+        return Err(anyhow!("--batch-size must be positive"));
+    }
+
+    let database_url = require_secret("DATABASE_URL")
+        .await
+        .context("DATABASE_URL is needed for rewrap script")?;
+    let master = master_key_provider_from_env().await?;
+    let active_key_id = master.active_key_id();
+    let pool = PgPool::connect_with(common_db::cross_tenant_options(database_url.parse()?)).await?;
+
+    if opts.dry_run {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM tenant_data_keys
+             WHERE master_key_id IS DISTINCT FROM $1
+               AND ($2::uuid IS NULL OR tenant_id = $2)",
+        )
+        .bind(&active_key_id)
+        .bind(opts.tenant)
+        .fetch_one(&pool)
+        .await?;
+        println!("{count} tenant data keys are not wrapped by {active_key_id}");
+        return Ok(());
+    }
+
+    let mut total = 0usize;
+    loop {
+        let rows = sqlx::query_as::<_, TenantKeyRow>(
+            "SELECT id, tenant_id, key_version, encrypted_key, master_key_id
+             FROM tenant_data_keys
+             WHERE master_key_id IS DISTINCT FROM $1
+               AND ($2::uuid IS NULL OR tenant_id = $2)
+             ORDER BY id
+             LIMIT $3",
+        )
+        .bind(&active_key_id)
+        .bind(opts.tenant)
+        .bind(opts.batch_size)
+        .fetch_all(&pool)
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        let mut tx = pool.begin().await?;
+        for row in &rows {
+            let dek = master
+                .unwrap_dek(row.master_key_id.as_deref(), &row.encrypted_key)
+                .await
+                .map_err(|err| {
+                    anyhow!(
+                        "Failed to unwrap key v{} for tenant {}: {err}",
+                        row.key_version,
+                        row.tenant_id
+                    )
+                })?;
+            let wrapped = master
+                .wrap_dek(&dek)
+                .await
+                .map_err(|err| anyhow!("Failed to wrap key for tenant {}: {err}", row.tenant_id))?;
+            if wrapped.key_id != active_key_id {
+                return Err(anyhow!(
+                    "Active master key changed during rewrap ({active_key_id} -> {}); rerun",
+                    wrapped.key_id
+                ));
+            }
+
+            // Guard on the old blob so a concurrent rotation is never overwritten.
+            sqlx::query(
+                "UPDATE tenant_data_keys
+                 SET encrypted_key = $1, master_key_id = $2
+                 WHERE id = $3 AND encrypted_key = $4",
+            )
+            .bind(&wrapped.blob)
+            .bind(&wrapped.key_id)
+            .bind(row.id)
+            .bind(&row.encrypted_key)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        total += rows.len();
+        println!("Re-wrapped {} keys (total {total})", rows.len());
+    }
+
+    println!("Rewrap complete. {total} tenant data keys now use {active_key_id}.");
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use common_crypto::{generate_dek, MasterKey};
+use common_config::require_secret;
+use common_crypto::{generate_dek, MasterKeyProvider};
+use customer_service::master_key_provider_from_env;
 use sqlx::{PgPool, Row};
 use uuid::Uuid;
 
@@ -23,17 +25,15 @@ async fn main() -> Result<()> {
         return Err(anyhow!("Provide at least one --tenant <UUID>"));
     }
 
-    let database_url =
-        std::env::var("DATABASE_URL").context("DATABASE_URL must be set for seeding keys")?;
-    let master_raw = std::env::var("CUSTOMER_MASTER_KEY")
-        .context("CUSTOMER_MASTER_KEY must be set for seeding keys")?;
-    let master = MasterKey::from_base64(&master_raw)
-        .map_err(|err| anyhow!("Failed to parse CUSTOMER_MASTER_KEY: {err}"))?;
+    let database_url = require_secret("DATABASE_URL")
+        .await
+        .context("DATABASE_URL is needed for seeding keys")?;
+    let master = master_key_provider_from_env().await?;
 
-    let pool = PgPool::connect(&database_url).await?;
+    let pool = PgPool::connect_with(common_db::cross_tenant_options(database_url.parse()?)).await?;
 
     for tenant in &opts.tenants {
-        seed_for_tenant(&pool, &master, *tenant, opts.rotate).await?;
+        seed_for_tenant(&pool, master.as_ref(), *tenant, opts.rotate).await?;
     }
 
     Ok(())
@@ -41,7 +41,7 @@ async fn main() -> Result<()> {
 
 async fn seed_for_tenant(
     pool: &PgPool,
-    master: &MasterKey,
+    master: &dyn MasterKeyProvider,
     tenant_id: Uuid,
     rotate: bool,
 ) -> Result<()> {
@@ -82,18 +82,20 @@ async fn seed_for_tenant(
 
     let new_version = current_version + 1;
     let dek = generate_dek();
-    let encrypted = master
-        .encrypt_tenant_dek(&dek)
+    let wrapped = master
+        .wrap_dek(&dek)
+        .await
         .map_err(|err| anyhow!("Failed to encrypt tenant DEK: {err}"))?;
 
     sqlx::query(
-        "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, created_at, active)
-         VALUES ($1, $2, $3, $4, NOW(), TRUE)"
+        "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, master_key_id, created_at, active)
+         VALUES ($1, $2, $3, $4, $5, NOW(), TRUE)"
     )
     .bind(Uuid::new_v4())
     .bind(tenant_id)
     .bind(new_version)
-    .bind(&encrypted)
+    .bind(&wrapped.blob)
+    .bind(&wrapped.key_id)
     .execute(&mut *tx)
     .await?;
 
@@ -0,0 +1,50 @@
+//! Startup configuration, validated before the service connects to anything.
+//!
+//! The master key provider keeps its own settings; see `master_key_provider_from_env`.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::{PoolSettings, RegionSettings};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomerConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    /// Regional databases for tenants with a residency requirement.
+    pub regions: RegionSettings,
+    pub jwt: JwtSettings,
+    /// Refuse PII ciphertexts in the legacy pre-envelope format instead of decrypting them
+    /// without their tenant/field binding. Turn on once `rewrap_customer_pii` finds nothing left.
+    pub require_pii_envelope: bool,
+    /// How long unwrapped tenant DEKs stay cached; 0 turns the cache off.
+    pub dek_cache_ttl_secs: u64,
+    pub dek_cache_max_entries: usize,
+}
+
+impl CustomerConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8089);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let regions = RegionSettings::read(&mut env).await;
+        let jwt = JwtSettings::read(&mut env).await;
+        let require_pii_envelope = env.flag("CUSTOMER_PII_REQUIRE_ENVELOPE", false);
+        let dek_cache_ttl_secs = env.or("CUSTOMER_DEK_CACHE_TTL_SECS", 300);
+        let dek_cache_max_entries = env.or("CUSTOMER_DEK_CACHE_MAX_ENTRIES", 10_000);
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                regions,
+                jwt: jwt?,
+                require_pii_envelope,
+                dek_cache_ttl_secs,
+                dek_cache_max_entries,
+            })
+        })
+    }
+}
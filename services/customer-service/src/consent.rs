@@ -0,0 +1,221 @@
+//! Per-customer marketing and profiling consent.
+//!
+//! `customer_consents` holds the current answer per purpose and `customer_consent_events` every
+//! change with its source and actor, which is the record a regulator asks for (migration 5010).
+//! Consent is opt-in: a purpose the customer never answered counts as not granted.
+//!
+//! Anything that contacts customers for marketing must ask [`has_consent`] (or
+//! `GET /customers/:id/consents/:purpose`) first.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use uuid::Uuid;
+
+pub const MAX_SOURCE_LEN: usize = 64;
+
+/// [`ConsentPurpose::as_str`] of every purpose, for validation messages.
+pub const PURPOSE_NAMES: &[&str] = &["marketing_email", "marketing_sms", "profiling"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPurpose {
+    MarketingEmail,
+    MarketingSms,
+    Profiling,
+}
+
+impl ConsentPurpose {
+    pub const ALL: [ConsentPurpose; 3] = [Self::MarketingEmail, Self::MarketingSms, Self::Profiling];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MarketingEmail => "marketing_email",
+            Self::MarketingSms => "marketing_sms",
+            Self::Profiling => "profiling",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|purpose| purpose.as_str() == value)
+    }
+}
+
+/// A customer's current answer for one purpose. `recorded_at` is `None` when they never answered.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ConsentRecord {
+    pub purpose: ConsentPurpose,
+    pub granted: bool,
+    pub source: Option<String>,
+    pub recorded_by: Option<Uuid>,
+    pub recorded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ConsentEvent {
+    pub purpose: String,
+    pub granted: bool,
+    pub source: String,
+    pub recorded_by: Option<Uuid>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct ConsentRow {
+    purpose: String,
+    granted: bool,
+    source: String,
+    recorded_by: Option<Uuid>,
+    recorded_at: DateTime<Utc>,
+}
+
+/// Every purpose, answered or not, in [`ConsentPurpose::ALL`] order.
+pub async fn current_consents(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+) -> sqlx::Result<Vec<ConsentRecord>> {
+    let rows = common_db::query_as::<ConsentRow>(
+        "SELECT purpose, granted, source, recorded_by, recorded_at
+         FROM customer_consents WHERE tenant_id = $1 AND customer_id = $2",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(ConsentPurpose::ALL
+        .into_iter()
+        .map(|purpose| match rows.iter().find(|row| row.purpose == purpose.as_str()) {
+            Some(row) => ConsentRecord {
+                purpose,
+                granted: row.granted,
+                source: Some(row.source.clone()),
+                recorded_by: row.recorded_by,
+                recorded_at: Some(row.recorded_at),
+            },
+            None => ConsentRecord { purpose, granted: false, source: None, recorded_by: None, recorded_at: None },
+        })
+        .collect())
+}
+
+/// Every recorded change, oldest first.
+pub async fn consent_history(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+) -> sqlx::Result<Vec<ConsentEvent>> {
+    common_db::query_as::<ConsentEvent>(
+        "SELECT purpose, granted, source, recorded_by, recorded_at
+         FROM customer_consent_events WHERE tenant_id = $1 AND customer_id = $2
+         ORDER BY recorded_at, id",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Store an answer. Returns `false` (and records nothing) when it matches the current one.
+pub async fn record_consent(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    purpose: ConsentPurpose,
+    granted: bool,
+    source: &str,
+    recorded_by: Option<Uuid>,
+) -> sqlx::Result<bool> {
+    let changed = common_db::query_scalar::<DateTime<Utc>>(
+        "INSERT INTO customer_consents (tenant_id, customer_id, purpose, granted, source, recorded_by)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (tenant_id, customer_id, purpose) DO UPDATE
+             SET granted = EXCLUDED.granted, source = EXCLUDED.source,
+                 recorded_by = EXCLUDED.recorded_by, recorded_at = NOW()
+             WHERE customer_consents.granted IS DISTINCT FROM EXCLUDED.granted
+         RETURNING recorded_at",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .bind(purpose.as_str())
+    .bind(granted)
+    .bind(source)
+    .bind(recorded_by)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(recorded_at) = changed else {
+        return Ok(false);
+    };
+    common_db::query(
+        "INSERT INTO customer_consent_events (tenant_id, customer_id, purpose, granted, source, recorded_by, recorded_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .bind(purpose.as_str())
+    .bind(granted)
+    .bind(source)
+    .bind(recorded_by)
+    .bind(recorded_at)
+    .execute(&mut *conn)
+    .await?;
+    Ok(true)
+}
+
+/// Withdraw every granted purpose, e.g. when the customer is erased. Returns how many were.
+pub async fn withdraw_all(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    source: &str,
+    recorded_by: Option<Uuid>,
+) -> sqlx::Result<u64> {
+    let withdrawn = common_db::query(
+        "WITH withdrawn AS (
+             UPDATE customer_consents SET granted = FALSE, source = $3, recorded_by = $4, recorded_at = NOW()
+             WHERE tenant_id = $1 AND customer_id = $2 AND granted
+             RETURNING tenant_id, customer_id, purpose, source, recorded_by, recorded_at
+         )
+         INSERT INTO customer_consent_events (tenant_id, customer_id, purpose, granted, source, recorded_by, recorded_at)
+         SELECT tenant_id, customer_id, purpose, FALSE, source, recorded_by, recorded_at FROM withdrawn",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .bind(source)
+    .bind(recorded_by)
+    .execute(&mut *conn)
+    .await?;
+    Ok(withdrawn.rows_affected())
+}
+
+/// Whether the customer currently allows `purpose`. The hook for anything sending marketing.
+pub async fn has_consent(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    purpose: ConsentPurpose,
+) -> sqlx::Result<bool> {
+    let granted = common_db::query_scalar::<bool>(
+        "SELECT granted FROM customer_consents WHERE tenant_id = $1 AND customer_id = $2 AND purpose = $3",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .bind(purpose.as_str())
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(granted.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purposes_round_trip_through_their_names() {
+        for purpose in ConsentPurpose::ALL {
+            assert_eq!(ConsentPurpose::parse(purpose.as_str()), Some(purpose));
+            assert_eq!(serde_json::to_value(purpose).unwrap(), purpose.as_str());
+        }
+        assert_eq!(PURPOSE_NAMES, ConsentPurpose::ALL.map(ConsentPurpose::as_str));
+        assert_eq!(ConsentPurpose::parse("telemarketing"), None);
+    }
+}
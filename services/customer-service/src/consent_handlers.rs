@@ -0,0 +1,180 @@
+//! `/customers/:id/consents`: reading and recording marketing and profiling consent (see
+//! `customer_service::consent`).
+
+use std::collections::BTreeMap;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use common_http_errors::validation::{Validate, Validator};
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_capability, Capability, SecurityContext, SecurityCtxExtractor};
+use customer_service::consent::{self, ConsentEvent, ConsentPurpose, ConsentRecord, MAX_SOURCE_LEN, PURPOSE_NAMES};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{db_internal, AppState};
+
+#[derive(Deserialize)]
+pub(crate) struct UpdateConsentsRequest {
+    /// Where the answer was given, e.g. `pos`, `web` or `support`.
+    source: String,
+    consents: BTreeMap<String, bool>,
+}
+
+impl Validate for UpdateConsentsRequest {
+    fn validate(&self, v: &mut Validator) {
+        if v.required("source", &self.source) {
+            v.max_len("source", self.source.trim(), MAX_SOURCE_LEN);
+        }
+        v.check(!self.consents.is_empty(), "consents", "required");
+        for purpose in self.consents.keys() {
+            v.one_of(&format!("consents.{purpose}"), purpose, PURPOSE_NAMES);
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct CustomerConsents {
+    customer_id: Uuid,
+    consents: Vec<ConsentRecord>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ConsentCheck {
+    customer_id: Uuid,
+    purpose: ConsentPurpose,
+    granted: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ConsentHistory {
+    customer_id: Uuid,
+    events: Vec<ConsentEvent>,
+}
+
+fn require(sec: &SecurityContext, capability: Capability, role: &'static str) -> ApiResult<()> {
+    ensure_capability(sec, capability)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role, trace_id: sec.trace_id })
+}
+
+/// 404 unless the customer exists; returns whether they were erased.
+async fn customer_erased(conn: &mut PgConnection, tenant_id: Uuid, customer_id: Uuid) -> ApiResult<bool> {
+    let erased: Option<bool> = common_db::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM gdpr_tombstones g
+             WHERE g.tenant_id = c.tenant_id AND g.customer_id = c.id AND g.request_type = 'delete'
+         )
+         FROM customers c WHERE c.tenant_id = $1 AND c.id = $2",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db_internal)?;
+    erased.ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })
+}
+
+pub(crate) async fn get_consents(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(customer_id): Path<Uuid>,
+) -> ApiResult<Json<CustomerConsents>> {
+    require(&sec, Capability::CustomerView, "customer_view")?;
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    customer_erased(&mut tx, sec.tenant_id, customer_id).await?;
+    let consents = consent::current_consents(&mut tx, sec.tenant_id, customer_id).await.map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
+    Ok(Json(CustomerConsents { customer_id, consents }))
+}
+
+/// Only the purposes named in the body change; answers equal to the current one are not
+/// recorded again.
+pub(crate) async fn put_consents(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(customer_id): Path<Uuid>,
+    Json(req): Json<UpdateConsentsRequest>,
+) -> ApiResult<Json<CustomerConsents>> {
+    require(&sec, Capability::CustomerWrite, "customer_write")?;
+    req.ensure_valid(sec.trace_id)?;
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    if customer_erased(&mut tx, sec.tenant_id, customer_id).await? {
+        return Err(ApiError::Conflict {
+            code: "customer_erased",
+            trace_id: sec.trace_id,
+            message: Some("Consent cannot be recorded for an erased customer".into()),
+        });
+    }
+    let source = req.source.trim();
+    let mut changed = Vec::new();
+    for (name, granted) in &req.consents {
+        let purpose = ConsentPurpose::parse(name).expect("validated purpose");
+        if consent::record_consent(&mut tx, sec.tenant_id, customer_id, purpose, *granted, source, sec.actor.id)
+            .await
+            .map_err(db_internal)?
+        {
+            changed.push(purpose.as_str());
+        }
+    }
+    let consents = consent::current_consents(&mut tx, sec.tenant_id, customer_id).await.map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
+    if !changed.is_empty() {
+        info!(tenant_id = %sec.tenant_id, customer_id = %customer_id, source, changed = ?changed, "Customer consent updated");
+    }
+    Ok(Json(CustomerConsents { customer_id, consents }))
+}
+
+pub(crate) async fn get_consent_history(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(customer_id): Path<Uuid>,
+) -> ApiResult<Json<ConsentHistory>> {
+    require(&sec, Capability::CustomerView, "customer_view")?;
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    customer_erased(&mut tx, sec.tenant_id, customer_id).await?;
+    let events = consent::consent_history(&mut tx, sec.tenant_id, customer_id).await.map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
+    Ok(Json(ConsentHistory { customer_id, events }))
+}
+
+/// `GET /customers/:id/consents/:purpose`: the check a sender makes before a marketing message.
+pub(crate) async fn check_consent(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path((customer_id, purpose)): Path<(Uuid, String)>,
+) -> ApiResult<Json<ConsentCheck>> {
+    require(&sec, Capability::CustomerView, "customer_view")?;
+    let purpose = ConsentPurpose::parse(&purpose).ok_or(ApiError::NotFound {
+        code: "unknown_consent_purpose",
+        trace_id: sec.trace_id,
+    })?;
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    customer_erased(&mut tx, sec.tenant_id, customer_id).await?;
+    let granted = consent::has_consent(&mut tx, sec.tenant_id, customer_id, purpose).await.map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
+    Ok(Json(ConsentCheck { customer_id, purpose, granted }))
+}
+
+/// Consent state and history of one customer, for the GDPR export.
+#[derive(Serialize)]
+pub(crate) struct ConsentExport {
+    current: Vec<ConsentRecord>,
+    history: Vec<ConsentEvent>,
+}
+
+pub(crate) async fn export_consents(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+) -> ApiResult<ConsentExport> {
+    Ok(ConsentExport {
+        current: consent::current_consents(conn, tenant_id, customer_id).await.map_err(db_internal)?,
+        history: consent::consent_history(conn, tenant_id, customer_id).await.map_err(db_internal)?,
+    })
+}
@@ -0,0 +1,318 @@
+//! Process-wide cache of unwrapped tenant data keys (DEKs).
+//!
+//! Customer reads and writes need the tenant's active DEK and the versions their rows were
+//! encrypted under. Without this cache each request read `tenant_data_keys` again. Entries live for
+//! `CUSTOMER_DEK_CACHE_TTL_SECS` (default 300; 0 turns the cache off) and at most
+//! `CUSTOMER_DEK_CACHE_MAX_ENTRIES` keys (default 10000) are held, oldest evicted first; both are
+//! read by [`crate::config::CustomerConfig`]. Keys are handed out as [`Dek`]s, so every copy is
+//! zeroized when dropped, in the cache or not.
+//!
+//! Any insert, update or delete on `tenant_data_keys` (provisioning, rotation, rewrap, shredding)
+//! sends `NOTIFY tenant_data_keys` with the tenant id (migration 5009).
+//! [`spawn_invalidation_listener`] drops that tenant's keys, so every replica sees a rotation at
+//! once. The TTL only bounds staleness while a listener is reconnecting.
+//!
+//! A key loaded before an invalidation must not land in the cache after it. Callers take a
+//! [`Generation`] before reading `tenant_data_keys` and pass it to [`DekCache::insert`], which
+//! drops the key if anything was invalidated in between.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGauge, Opts};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tracing::{debug, warn};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Channel the `tenant_data_keys` trigger notifies, with the tenant id as payload.
+pub const INVALIDATION_CHANNEL: &str = "tenant_data_keys";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+static DEK_CACHE_LOOKUPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("customer_dek_cache_lookups_total", "Tenant DEK cache lookups by result (hit, miss)"),
+        &["result"],
+    )
+    .expect("customer_dek_cache_lookups_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static DEK_CACHE_EVICTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "customer_dek_cache_evictions_total",
+            "Tenant DEKs dropped from the cache by reason (expired, capacity, invalidated)",
+        ),
+        &["reason"],
+    )
+    .expect("customer_dek_cache_evictions_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static DEK_CACHE_ENTRIES: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new("customer_dek_cache_entries", "Tenant DEKs currently cached")
+        .expect("customer_dek_cache_entries");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+/// An unwrapped tenant data key, zeroized on drop.
+pub type Dek = Zeroizing<[u8; 32]>;
+
+/// Count of invalidations at some point, taken before loading a key; see [`DekCache::insert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generation(u64);
+
+struct CachedDek {
+    key: Zeroizing<[u8; 32]>,
+    loaded_at: Instant,
+}
+
+#[derive(Default)]
+struct Entries {
+    keys: HashMap<(Uuid, i32), CachedDek>,
+    /// Active key version per tenant, as of `Instant`.
+    active: HashMap<Uuid, (i32, Instant)>,
+    /// Bumped by every invalidation.
+    generation: u64,
+}
+
+pub struct DekCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+impl DekCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self { ttl, max_entries, entries: Mutex::new(Entries::default()) }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    fn fresh(&self, loaded_at: Instant) -> bool {
+        loaded_at.elapsed() < self.ttl
+    }
+
+    fn record(hit: bool) -> bool {
+        DEK_CACHE_LOOKUPS.with_label_values(&[if hit { "hit" } else { "miss" }]).inc();
+        hit
+    }
+
+    /// The tenant's active key version and key.
+    pub fn active(&self, tenant_id: Uuid) -> Option<(i32, Dek)> {
+        if !self.enabled() {
+            return None;
+        }
+        let entries = self.lock();
+        let found = entries
+            .active
+            .get(&tenant_id)
+            .filter(|(_, loaded_at)| self.fresh(*loaded_at))
+            .and_then(|(version, _)| {
+                let cached = entries.keys.get(&(tenant_id, *version))?;
+                self.fresh(cached.loaded_at).then(|| (*version, cached.key.clone()))
+            });
+        Self::record(found.is_some());
+        found
+    }
+
+    pub fn get(&self, tenant_id: Uuid, version: i32) -> Option<Dek> {
+        if !self.enabled() {
+            return None;
+        }
+        let found = self
+            .lock()
+            .keys
+            .get(&(tenant_id, version))
+            .filter(|cached| self.fresh(cached.loaded_at))
+            .map(|cached| cached.key.clone());
+        Self::record(found.is_some());
+        found
+    }
+
+    /// Take before reading a key from `tenant_data_keys`, for [`DekCache::insert`].
+    pub fn generation(&self) -> Generation {
+        Generation(self.lock().generation)
+    }
+
+    /// Cache a key loaded from `tenant_data_keys` after `generation` was taken; `active` marks it
+    /// as the tenant's current key. Dropped if the cache was invalidated since, as the key may
+    /// predate the change.
+    pub fn insert(&self, generation: Generation, tenant_id: Uuid, version: i32, key: &[u8; 32], active: bool) {
+        if !self.enabled() {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if entries.generation != generation.0 {
+            debug!(%tenant_id, version, "DEK cache invalidated while the key was loading; not caching it");
+            return;
+        }
+        if !entries.keys.contains_key(&(tenant_id, version)) && entries.keys.len() >= self.max_entries {
+            let before = entries.keys.len();
+            entries.keys.retain(|_, cached| cached.loaded_at.elapsed() < self.ttl);
+            DEK_CACHE_EVICTIONS.with_label_values(&["expired"]).inc_by((before - entries.keys.len()) as u64);
+            while entries.keys.len() >= self.max_entries {
+                let Some(oldest) = entries.keys.iter().min_by_key(|(_, cached)| cached.loaded_at).map(|(id, _)| *id) else {
+                    break;
+                };
+                entries.keys.remove(&oldest);
+                DEK_CACHE_EVICTIONS.with_label_values(&["capacity"]).inc();
+            }
+            let Entries { keys, active, .. } = &mut *entries;
+            active.retain(|tenant, (version, _)| keys.contains_key(&(*tenant, *version)));
+        }
+        entries.keys.insert((tenant_id, version), CachedDek { key: Zeroizing::new(*key), loaded_at: now });
+        if active {
+            entries.active.insert(tenant_id, (version, now));
+        }
+        DEK_CACHE_ENTRIES.set(entries.keys.len() as i64);
+    }
+
+    /// Drop every key of `tenant_id`, after its keys changed.
+    pub fn invalidate_tenant(&self, tenant_id: Uuid) {
+        let mut entries = self.lock();
+        let before = entries.keys.len();
+        entries.keys.retain(|(tenant, _), _| *tenant != tenant_id);
+        entries.active.remove(&tenant_id);
+        entries.generation += 1;
+        DEK_CACHE_EVICTIONS.with_label_values(&["invalidated"]).inc_by((before - entries.keys.len()) as u64);
+        DEK_CACHE_ENTRIES.set(entries.keys.len() as i64);
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        DEK_CACHE_EVICTIONS.with_label_values(&["invalidated"]).inc_by(entries.keys.len() as u64);
+        entries.keys.clear();
+        entries.active.clear();
+        entries.generation += 1;
+        DEK_CACHE_ENTRIES.set(0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Listen for `tenant_data_keys` changes on `pool` and drop the affected tenant's keys.
+/// Notifications sent while the connection is down are lost, so the whole cache is cleared
+/// whenever the listener (re)connects.
+pub fn spawn_invalidation_listener(pool: PgPool, cache: Arc<DekCache>) {
+    tokio::spawn(async move {
+        loop {
+            let mut listener = match PgListener::connect_with(&pool).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    warn!(?err, "DEK cache listener failed to connect");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+            if let Err(err) = listener.listen(INVALIDATION_CHANNEL).await {
+                warn!(?err, "DEK cache listener failed to subscribe");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+            cache.clear();
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(notification)) => match Uuid::parse_str(notification.payload()) {
+                        Ok(tenant_id) => {
+                            debug!(%tenant_id, "Tenant data keys changed; dropping cached DEKs");
+                            cache.invalidate_tenant(tenant_id);
+                        }
+                        Err(_) => cache.clear(),
+                    },
+                    // The connection dropped; the next call reconnects and re-subscribes.
+                    Ok(None) => cache.clear(),
+                    Err(err) => {
+                        warn!(?err, "DEK cache listener failed");
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_served_until_they_expire_or_are_invalidated() {
+        let cache = DekCache::new(Duration::from_secs(60), 10);
+        let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(cache.active(tenant), None);
+        cache.insert(cache.generation(), tenant, 2, &[2; 32], true);
+        cache.insert(cache.generation(), tenant, 1, &[1; 32], false);
+        cache.insert(cache.generation(), other, 1, &[9; 32], true);
+        assert_eq!(cache.active(tenant), Some((2, Zeroizing::new([2; 32]))));
+        assert_eq!(cache.get(tenant, 1).as_deref(), Some(&[1; 32]));
+        assert_eq!(cache.get(tenant, 3), None);
+
+        cache.invalidate_tenant(tenant);
+        assert_eq!(cache.active(tenant), None);
+        assert_eq!(cache.get(tenant, 1), None);
+        assert_eq!(cache.active(other), Some((1, Zeroizing::new([9; 32]))), "other tenants keep their keys");
+
+        let expired = DekCache::new(Duration::from_nanos(1), 10);
+        expired.insert(expired.generation(), tenant, 1, &[1; 32], true);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(expired.active(tenant), None);
+        let disabled = DekCache::new(Duration::ZERO, 10);
+        disabled.insert(disabled.generation(), tenant, 1, &[1; 32], true);
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn keys_loaded_before_an_invalidation_are_not_cached() {
+        let cache = DekCache::new(Duration::from_secs(60), 10);
+        let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let before = cache.generation();
+        cache.invalidate_tenant(tenant);
+        cache.insert(before, tenant, 1, &[1; 32], true);
+        assert_eq!(cache.active(tenant), None, "the key may predate the rotation");
+
+        let before = cache.generation();
+        cache.clear();
+        cache.insert(before, other, 1, &[2; 32], true);
+        assert!(cache.is_empty());
+
+        cache.insert(cache.generation(), tenant, 2, &[3; 32], true);
+        assert_eq!(cache.active(tenant), Some((2, Zeroizing::new([3; 32]))));
+    }
+
+    #[test]
+    fn the_oldest_key_makes_room_when_full() {
+        let cache = DekCache::new(Duration::from_secs(60), 2);
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.insert(cache.generation(), a, 1, &[1; 32], true);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert(cache.generation(), b, 1, &[2; 32], true);
+        cache.insert(cache.generation(), c, 1, &[3; 32], true);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.active(a), None, "evicted keys take their active marker along");
+        assert_eq!(cache.active(b), Some((1, Zeroizing::new([2; 32]))));
+        assert_eq!(cache.active(c), Some((1, Zeroizing::new([3; 32]))));
+    }
+}
@@ -1,8 +1,8 @@
 use crate::*; // bring in main module symbols when included from lib/main context
-use axum::{extract::{State, Path, Query}, Json};
+use axum::{extract::{State, Path, Query}, http::HeaderMap, response::IntoResponse, Json};
 use uuid::Uuid;
 use common_security::SecurityCtxExtractor;
-use common_http_errors::ApiResult;
+use common_http_errors::{etag, ApiResult};
 
 pub async fn create_customer(
     State(state): State<AppState>,
@@ -14,17 +14,26 @@ pub async fn get_customer(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Path(customer_id): Path<Uuid>,
-) -> ApiResult<Json<Customer>> { crate::get_customer_impl(state, sec, customer_id).await }
+) -> ApiResult<impl IntoResponse> {
+    let Json(customer) = crate::get_customer_impl(state, sec, customer_id).await?;
+    Ok((etag::etag_header(customer.version), Json(customer)))
+}
 
 pub async fn update_customer(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Path(customer_id): Path<Uuid>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateCustomerRequest>,
-) -> ApiResult<Json<Customer>> { crate::update_customer_impl(state, sec, customer_id, payload).await }
+) -> ApiResult<impl IntoResponse> {
+    let expected_version = etag::if_match_version(&headers, sec.trace_id)?;
+    let Json(customer) = crate::update_customer_impl(state, sec, customer_id, expected_version, payload).await?;
+    Ok((etag::etag_header(customer.version), Json(customer)))
+}
 
 pub async fn search_customers(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Query(params): Query<SearchParams>,
-) -> ApiResult<Json<Vec<Customer>>> { crate::search_customers_impl(state, sec, params).await }
+    page: PageRequest,
+) -> ApiResult<Json<Listing<Customer>>> { crate::search_customers_impl(state, sec, params, page).await }
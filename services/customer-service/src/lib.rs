@@ -1,7 +1,102 @@
+use anyhow::{bail, Context};
+use common_config::require_secret;
+use common_crypto::{
+    blind_index_query, derive_blind_index_key, CachingProvider, CryptoError,
+    LocalMasterKeyProvider, MasterKeyProvider,
+};
 use common_security::roles::Role;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod consent;
+pub mod dek_cache;
+pub mod outbox;
 
 // Re-export role arrays for integration tests and other binaries.
 pub const CUSTOMER_WRITE_ROLES: &[Role] = &[Role::SuperAdmin, Role::Admin, Role::Manager, Role::Inventory, Role::Cashier];
 pub const CUSTOMER_VIEW_ROLES: &[Role]  = &[Role::SuperAdmin, Role::Admin, Role::Manager, Role::Inventory, Role::Cashier];
 
-pub use common_security::SecurityCtxExtractor;
\ No newline at end of file
+/// Field names bound into PII ciphertexts as associated data (with the tenant id), so a blob
+/// copied into another column or tenant fails to decrypt.
+pub const EMAIL_FIELD: &str = "customers.email";
+pub const PHONE_FIELD: &str = "customers.phone";
+
+/// Characters per n-gram in the email/phone search blind index. Changing it invalidates stored
+/// tokens; re-run `reindex_customer_search` afterwards.
+pub const SEARCH_GRAM_SIZE: usize = 3;
+
+/// Blind-index tokens for a normalized email or phone under the tenant DEK, used both when
+/// storing a value and when querying. `None` when the value is shorter than one n-gram.
+pub fn search_tokens(
+    tenant_key: &[u8; 32],
+    field: &str,
+    normalized: &str,
+) -> Result<Option<Vec<Vec<u8>>>, CryptoError> {
+    let field_key = derive_blind_index_key(tenant_key, field)?;
+    blind_index_query(&field_key, normalized, SEARCH_GRAM_SIZE)
+}
+
+pub use common_security::SecurityCtxExtractor;
+
+/// Build the master key provider selected by `CUSTOMER_MASTER_KEY_PROVIDER`:
+///
+/// * `env` (default): base64 key in `CUSTOMER_MASTER_KEY`, retired keys in `CUSTOMER_MASTER_KEY_PREVIOUS`.
+///   Follows the common-config secret rules: a mounted `CUSTOMER_MASTER_KEY_FILE` is used as the
+///   `file` provider, and `CUSTOMER_MASTER_KEY_VAULT` reads the keys (newline or comma separated)
+///   from Vault KV once at startup.
+/// * `file`: key file at `CUSTOMER_MASTER_KEY_FILE`, active key on the first line
+/// * `aws-kms`: KMS key id/ARN in `CUSTOMER_KMS_KEY_ID` (requires the `aws-kms` feature)
+/// * `vault`: transit key name in `CUSTOMER_VAULT_TRANSIT_KEY` (requires the `vault` feature)
+///
+/// Unwrapped DEKs are cached for `CUSTOMER_MASTER_KEY_CACHE_TTL_SECS` (default 300) and the
+/// provider reloads its source every `CUSTOMER_MASTER_KEY_REFRESH_SECS` (default 300).
+pub async fn master_key_provider_from_env() -> anyhow::Result<Arc<dyn MasterKeyProvider>> {
+    let kind = std::env::var("CUSTOMER_MASTER_KEY_PROVIDER").unwrap_or_else(|_| "env".into());
+    let secs = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(300))
+    };
+    let ttl = secs("CUSTOMER_MASTER_KEY_CACHE_TTL_SECS");
+    let refresh = secs("CUSTOMER_MASTER_KEY_REFRESH_SECS");
+
+    let provider: Arc<dyn MasterKeyProvider> = match kind.as_str() {
+        "env" => {
+            let local = if let Ok(path) = std::env::var("CUSTOMER_MASTER_KEY_FILE") {
+                LocalMasterKeyProvider::from_file(path)?
+            } else if std::env::var_os("CUSTOMER_MASTER_KEY").is_some() {
+                LocalMasterKeyProvider::from_env("CUSTOMER_MASTER_KEY")?
+            } else {
+                LocalMasterKeyProvider::from_keys(&require_secret("CUSTOMER_MASTER_KEY").await?)?
+            };
+            Arc::new(CachingProvider::new(local, ttl, refresh))
+        }
+        "file" => {
+            let path = std::env::var("CUSTOMER_MASTER_KEY_FILE")
+                .context("CUSTOMER_MASTER_KEY_FILE must be set for the file provider")?;
+            Arc::new(CachingProvider::new(
+                LocalMasterKeyProvider::from_file(path)?,
+                ttl,
+                refresh,
+            ))
+        }
+        #[cfg(feature = "aws-kms")]
+        "aws-kms" => Arc::new(CachingProvider::new(
+            common_crypto::provider::AwsKmsProvider::from_env("CUSTOMER_KMS_KEY_ID")?,
+            ttl,
+            refresh,
+        )),
+        #[cfg(feature = "vault")]
+        "vault" => Arc::new(CachingProvider::new(
+            common_crypto::provider::VaultTransitProvider::from_env("CUSTOMER_VAULT_TRANSIT_KEY")?,
+            ttl,
+            refresh,
+        )),
+        other => {
+            bail!("unsupported CUSTOMER_MASTER_KEY_PROVIDER `{other}` (is its feature enabled?)")
+        }
+    };
+    Ok(provider)
+}
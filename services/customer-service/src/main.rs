@@ -1,38 +1,46 @@
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 use axum::{
     extract::{FromRef, Path, State},
     http::{
-        header::{ACCEPT, CONTENT_TYPE},
-    HeaderName, HeaderValue, Method, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MATCH},
+        HeaderName, HeaderValue, Method, StatusCode,
     },
     routing::{get, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
-use common_auth::{
-    JwtConfig, JwtVerifier,
+use common_auth::{JwtConfig, JwtVerifier};
+use common_crypto::{
+    decrypt_envelope, decrypt_field_with_aad, deterministic_hash, encrypt_field_with_aad,
+    generate_dek, CryptoError, FieldAad, MasterKeyProvider,
 };
-use common_security::{SecurityCtxExtractor, ensure_capability, Capability};
-#[cfg(test)] use common_security::roles::Role;
-use common_http_errors::{ApiError, ApiResult};
-use common_crypto::{decrypt_field, deterministic_hash, encrypt_field, CryptoError, MasterKey};
+use common_http_errors::validation::{Validate, Validator};
+use common_http_errors::{etag, ApiError, ApiResult};
+use common_money::log_rounding_mode_once;
+use common_security::{ensure_capability, Capability, Role, SecurityCtxExtractor};
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
+use common_events::{CustomerErasedEvent, DomainEvent};
+use customer_service::consent;
+use customer_service::dek_cache::{spawn_invalidation_listener, Dek, DekCache};
+use customer_service::outbox;
+use customer_service::{master_key_provider_from_env, search_tokens, EMAIL_FIELD, PHONE_FIELD};
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, IntCounterVec, Opts, TextEncoder};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sqlx::{Executor, FromRow, PgPool};
+use common_db::pagination::{self, Listing, PageRequest, SortDirection, SortField, SortSpec};
+use common_db::{RegionalPools, TenantScopedPool};
+use sqlx::{Executor, FromRow, QueryBuilder};
 use std::{
     collections::HashMap,
-    env,
-    net::{IpAddr, SocketAddr},
     sync::Arc,
     time::Duration,
 };
 use tokio::net::TcpListener;
 use tokio::time::{interval, MissedTickBehavior};
 use tower_http::cors::{AllowOrigin, CorsLayer};
-use tracing::{debug, info, warn, error};
-use once_cell::sync::Lazy;
-use prometheus::{IntCounterVec, Opts, TextEncoder, Encoder};
-use common_money::log_rounding_mode_once;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 static HTTP_ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
@@ -78,16 +86,24 @@ async fn render_metrics() -> Result<String, StatusCode> {
 }
 
 // Legacy CUSTOMER_*_ROLES arrays retained only for tests until fallback fully removed.
+mod config;
+mod consent_handlers;
 mod handlers;
-use handlers::{create_customer, get_customer, update_customer, search_customers};
+use config::CustomerConfig;
+use consent_handlers::{check_consent, export_consents, get_consent_history, get_consents, put_consents, ConsentExport};
+use handlers::{create_customer, get_customer, search_customers, update_customer};
 // GDPR management now gated by Capability::GdprManage (was CustomerWrite pre-refinement TA-POL-5)
 const GDPR_DELETED_NAME: &str = "[deleted]";
 
 #[derive(Clone)]
 struct AppState {
-    db: PgPool,
+    db: RegionalPools,
     jwt_verifier: Arc<JwtVerifier>,
-    master_key: Arc<MasterKey>,
+    master_key: Arc<dyn MasterKeyProvider>,
+    /// Unwrapped tenant DEKs shared by all requests.
+    dek_cache: Arc<DekCache>,
+    /// Refuse legacy PII ciphertexts (`CUSTOMER_PII_REQUIRE_ENVELOPE`).
+    require_pii_envelope: bool,
 }
 
 // ApiResult now comes from common-http-errors (Result<T, ApiError>)
@@ -112,6 +128,37 @@ struct UpdateCustomerRequest {
     phone: Option<Option<String>>,
 }
 
+const MAX_NAME_LEN: usize = 200;
+
+/// Phone numbers may be formatted (`+1 (555) 000-1234`) but must hold 7 to 15 digits (E.164).
+fn check_phone(v: &mut Validator, phone: Option<&str>) {
+    let Some(phone) = phone.map(str::trim).filter(|p| !p.is_empty()) else { return };
+    let formatted = phone.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c));
+    v.check(formatted && (7..=15).contains(&normalize_phone(phone).len()), "phone", "invalid_phone");
+}
+
+impl Validate for NewCustomer {
+    fn validate(&self, v: &mut Validator) {
+        if v.required("name", &self.name) {
+            v.max_len("name", self.name.trim(), MAX_NAME_LEN);
+        }
+        v.email("email", self.email.as_deref());
+        check_phone(v, self.phone.as_deref());
+    }
+}
+
+impl Validate for UpdateCustomerRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(name) = &self.name {
+            if v.required("name", name) {
+                v.max_len("name", name.trim(), MAX_NAME_LEN);
+            }
+        }
+        v.email("email", self.email.as_ref().and_then(|e| e.as_deref()));
+        check_phone(v, self.phone.as_ref().and_then(|p| p.as_deref()));
+    }
+}
+
 #[derive(Serialize)]
 struct Customer {
     id: Uuid,
@@ -120,12 +167,23 @@ struct Customer {
     email: Option<String>,
     phone: Option<String>,
     created_at: DateTime<Utc>,
+    version: i64,
 }
 
 #[derive(Serialize)]
 struct GdprExportResponse {
     export_id: Uuid,
     customer: Customer,
+    consents: ConsentExport,
+}
+
+#[derive(Serialize)]
+struct TenantExportResponse {
+    export_id: Uuid,
+    customers: Vec<Customer>,
+    gdpr_tombstones: serde_json::Value,
+    customer_consents: serde_json::Value,
+    customer_consent_events: serde_json::Value,
 }
 
 #[derive(Serialize)]
@@ -145,53 +203,95 @@ struct CustomerRow {
     phone_encrypted: Option<Vec<u8>>,
     pii_key_version: Option<i32>,
     created_at: DateTime<Utc>,
+    version: i64,
 }
 
 #[derive(FromRow)]
 struct TenantKeyRow {
     key_version: i32,
     encrypted_key: Vec<u8>,
+    master_key_id: Option<String>,
 }
 
 struct TenantDek {
     version: i32,
-    key: [u8; 32],
+    key: Dek,
 }
 
+/// Keys one request has used, in front of the process-wide [`DekCache`].
 struct TenantKeyCache<'a> {
-    db: &'a PgPool,
-    master: Arc<MasterKey>,
+    db: &'a TenantScopedPool,
+    master: Arc<dyn MasterKeyProvider>,
+    shared: Arc<DekCache>,
     tenant_id: Uuid,
-    cache: HashMap<i32, [u8; 32]>,
+    cache: HashMap<i32, Dek>,
+    require_envelope: bool,
 }
 
 impl<'a> TenantKeyCache<'a> {
-    fn new(state: &'a AppState, tenant_id: Uuid) -> Self {
+    fn new(state: &'a AppState, db: &'a TenantScopedPool, tenant_id: Uuid) -> Self {
         Self {
-            db: &state.db,
+            db,
             master: state.master_key.clone(),
+            shared: state.dek_cache.clone(),
             tenant_id,
             cache: HashMap::new(),
+            require_envelope: state.require_pii_envelope,
         }
     }
 
     fn prime(&mut self, dek: &TenantDek) {
-        self.cache.insert(dek.version, dek.key);
+        self.cache.insert(dek.version, dek.key.clone());
     }
 
     async fn active(&mut self) -> ApiResult<TenantDek> {
+        if let Some((version, key)) = self.shared.active(self.tenant_id) {
+            self.cache.entry(version).or_insert_with(|| key.clone());
+            return Ok(TenantDek { version, key });
+        }
+        let generation = self.shared.generation();
         let dek = load_tenant_dek(self.db, self.master.as_ref(), self.tenant_id, None).await?;
-        self.cache.entry(dek.version).or_insert(dek.key);
+        self.shared.insert(generation, self.tenant_id, dek.version, &dek.key, true);
+        self.cache.entry(dek.version).or_insert_with(|| dek.key.clone());
         Ok(dek)
     }
 
-    async fn by_version(&mut self, version: i32) -> ApiResult<[u8; 32]> {
+    /// Every key the tenant still holds, active first. Customers keep the blind index of the key
+    /// that encrypted them until they are rewrapped, so searches have to try all of them.
+    async fn searchable(&mut self) -> ApiResult<Vec<Dek>> {
+        let active = self.active().await?;
+        let mut tx = self.db.begin(self.tenant_id).await.map_err(db_internal)?;
+        let older: Vec<i32> = common_db::query_scalar(
+            "SELECT key_version FROM tenant_data_keys
+             WHERE tenant_id = $1 AND key_version <> $2
+             ORDER BY key_version DESC",
+        )
+        .bind(self.tenant_id)
+        .bind(active.version)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_internal)?;
+        tx.commit().await.map_err(db_internal)?;
+        let mut keys = vec![active.key];
+        for version in older {
+            keys.push(self.by_version(version).await?);
+        }
+        Ok(keys)
+    }
+
+    async fn by_version(&mut self, version: i32) -> ApiResult<Dek> {
         if let Some(existing) = self.cache.get(&version) {
-            return Ok(*existing);
+            return Ok(existing.clone());
+        }
+        if let Some(key) = self.shared.get(self.tenant_id, version) {
+            self.cache.insert(version, key.clone());
+            return Ok(key);
         }
+        let generation = self.shared.generation();
         let dek =
             load_tenant_dek(self.db, self.master.as_ref(), self.tenant_id, Some(version)).await?;
-        self.cache.insert(dek.version, dek.key);
+        self.shared.insert(generation, self.tenant_id, dek.version, &dek.key, false);
+        self.cache.insert(dek.version, dek.key.clone());
         Ok(dek.key)
     }
 }
@@ -203,24 +303,37 @@ struct SearchParams {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("customer-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL")?;
-    let db_pool = PgPool::connect(&database_url).await?;
-
-    let master_key_raw =
-        env::var("CUSTOMER_MASTER_KEY").context("CUSTOMER_MASTER_KEY must be set")?;
-    let master_key = MasterKey::from_base64(&master_key_raw)
-        .map_err(|err| anyhow!("failed to decode CUSTOMER_MASTER_KEY: {err}"))?;
-
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let config = CustomerConfig::from_env().await?;
+    let db_pool = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::register_metrics(prometheus::default_registry());
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db_pool.clone());
 
+    let master_key = master_key_provider_from_env()
+        .await
+        .map_err(|err| anyhow!("failed to configure master key provider: {err:#}"))?;
+
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    common_security::spawn_policy_refresh(jwt_verifier.clone());
+
+    let db = RegionalPools::connect(db_pool, &config.regions, &config.pool).await?;
+    let dek_cache = Arc::new(DekCache::new(
+        Duration::from_secs(config.dek_cache_ttl_secs),
+        config.dek_cache_max_entries,
+    ));
+    for pool in db.pools() {
+        spawn_invalidation_listener(pool.pool().clone(), dek_cache.clone());
+    }
     let state = AppState {
-        db: db_pool,
+        db,
         jwt_verifier,
-        master_key: Arc::new(master_key),
+        master_key,
+        dek_cache,
+        require_pii_envelope: config.require_pii_envelope,
     };
 
     let allowed_origins = [
@@ -240,29 +353,36 @@ async fn main() -> anyhow::Result<()> {
         .allow_headers([
             ACCEPT,
             CONTENT_TYPE,
+            IF_MATCH,
             HeaderName::from_static("authorization"),
             HeaderName::from_static("x-tenant-id"),
-        ]);
+        ])
+        .expose_headers([ETAG]);
 
+    let slo = SloTracker::new("customer-service", SloConfig::from_env(), prometheus::default_registry());
     let app = Router::new()
         .route("/customers", post(create_customer).get(search_customers))
         .route("/customers/:id", get(get_customer).put(update_customer))
+        .route("/customers/:id/consents", get(get_consents).put(put_consents))
+        .route("/customers/:id/consents/history", get(get_consent_history))
+        .route("/customers/:id/consents/:purpose", get(check_consent))
         .route("/customers/:id/gdpr/export", post(gdpr_export_customer))
         .route("/customers/:id/gdpr/delete", post(gdpr_delete_customer))
+        .route("/tenants/:tenant_id/export", get(export_tenant_data))
+        .route("/tenants/:tenant_id/provision", post(provision_tenant))
         .route("/healthz", get(|| async { "ok" }))
         .route("/internal/metrics", get(render_metrics))
         .route("/metrics", get(render_metrics))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .with_state(state)
         .layer(axum::middleware::from_fn(track_http_errors))
-        .layer(cors);
+        .layer(cors)
+        .layer(axum::middleware::from_fn(slo.layer()))
+        .layer(axum::middleware::from_fn(common_observability::request_span));
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(8089);
-    let ip: IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    let addr = config.http.addr();
 
     println!("starting customer-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
@@ -277,12 +397,18 @@ pub(crate) async fn create_customer_impl(
     sec: common_security::context::SecurityContext,
     new_cust: NewCustomer,
 ) -> ApiResult<Json<Customer>> {
-    ensure_capability(&sec, Capability::GdprManage)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_write", trace_id: sec.trace_id })?;
+    ensure_capability(&sec, Capability::GdprManage).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_write",
+            trace_id: sec.trace_id,
+        }
+    })?;
+    new_cust.ensure_valid(sec.trace_id)?;
     let tenant_id = sec.tenant_id;
     let customer_id = Uuid::new_v4();
 
-    let mut key_cache = TenantKeyCache::new(&state, tenant_id);
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
     let active_key = key_cache.active().await?;
     key_cache.prime(&active_key);
 
@@ -292,7 +418,14 @@ pub(crate) async fn create_customer_impl(
     let phone = sanitize_optional(phone);
 
     let email_encrypted = match &email {
-        Some(value) => Some(encrypt_field(&active_key.key, value.as_bytes()).map_err(crypto_err)?),
+        Some(value) => Some(
+            encrypt_field_with_aad(
+                &active_key.key,
+                pii_aad(&tenant_id, EMAIL_FIELD),
+                value.as_bytes(),
+            )
+            .map_err(crypto_err)?,
+        ),
         None => None,
     };
     let email_hash = match &email {
@@ -311,7 +444,14 @@ pub(crate) async fn create_customer_impl(
     };
 
     let phone_encrypted = match &phone {
-        Some(value) => Some(encrypt_field(&active_key.key, value.as_bytes()).map_err(crypto_err)?),
+        Some(value) => Some(
+            encrypt_field_with_aad(
+                &active_key.key,
+                pii_aad(&tenant_id, PHONE_FIELD),
+                value.as_bytes(),
+            )
+            .map_err(crypto_err)?,
+        ),
         None => None,
     };
     let phone_hash = match &phone {
@@ -328,8 +468,11 @@ pub(crate) async fn create_customer_impl(
         }
         None => None,
     };
+    let email_tokens = pii_search_tokens(&active_key.key, EMAIL_FIELD, email.as_deref())?;
+    let phone_tokens = pii_search_tokens(&active_key.key, PHONE_FIELD, phone.as_deref())?;
 
-    let row = sqlx::query_as::<_, CustomerRow>(
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let row = common_db::query_as::<CustomerRow>(
         "INSERT INTO customers (
             id,
             tenant_id,
@@ -342,8 +485,10 @@ pub(crate) async fn create_customer_impl(
             phone_hash,
             pii_key_version,
             pii_encrypted_at,
+            email_search_tokens,
+            phone_search_tokens
         ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
         )
         RETURNING
             id,
@@ -354,7 +499,8 @@ pub(crate) async fn create_customer_impl(
             email_encrypted,
             phone_encrypted,
             pii_key_version,
-            created_at",
+            created_at,
+            version",
     )
     .bind(customer_id)
     .bind(tenant_id)
@@ -367,9 +513,12 @@ pub(crate) async fn create_customer_impl(
     .bind(phone_hash.as_deref())
     .bind(Some(active_key.version))
     .bind(Some(Utc::now()))
-    .fetch_one(&state.db)
+    .bind(email_tokens)
+    .bind(phone_tokens)
+    .fetch_one(&mut *tx)
     .await
     .map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
 
     let customer = hydrate_customer_row(row, &mut key_cache).await?;
     Ok(Json(customer))
@@ -379,14 +528,20 @@ pub(crate) async fn search_customers_impl(
     state: AppState,
     sec: common_security::context::SecurityContext,
     params: SearchParams,
-) -> ApiResult<Json<Vec<Customer>>> {
-    ensure_capability(&sec, Capability::CustomerView)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_view", trace_id: sec.trace_id })?;
+    page: PageRequest,
+) -> ApiResult<Json<Listing<Customer>>> {
+    ensure_capability(&sec, Capability::CustomerView).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_view",
+            trace_id: sec.trace_id,
+        }
+    })?;
     let tenant_id = sec.tenant_id;
+    let plan = page.resolve(&CUSTOMER_SORT, sec.trace_id)?;
 
-    let mut key_cache = TenantKeyCache::new(&state, tenant_id);
-    let active_key = key_cache.active().await?;
-    key_cache.prime(&active_key);
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
+    let keys = key_cache.searchable().await?;
 
     let search_term = params.q.unwrap_or_default();
     let trimmed = search_term.trim();
@@ -396,59 +551,124 @@ pub(crate) async fn search_customers_impl(
         format!("%{}%", trimmed)
     };
 
-    let email_hash = if trimmed.is_empty() {
-        None
-    } else {
-        let normalized = normalize_email(trimmed);
-        if normalized.is_empty() {
-            None
-        } else {
-            Some(deterministic_hash(&active_key.key, normalized.as_bytes()).map_err(crypto_err)?)
-        }
+    let normalized_email = normalize_email(trimmed);
+    let normalized_phone = normalize_phone(trimmed);
+    let mut filter = CustomerSearchFilter {
+        pattern,
+        ..CustomerSearchFilter::default()
     };
+    // One set of blind values per key: rows are indexed under the key that encrypted them.
+    for key in &keys {
+        if !normalized_email.is_empty() {
+            filter
+                .email_hashes
+                .push(deterministic_hash(key, normalized_email.as_bytes()).map_err(crypto_err)?);
+        }
+        if !normalized_phone.is_empty() {
+            filter
+                .phone_hashes
+                .push(deterministic_hash(key, normalized_phone.as_bytes()).map_err(crypto_err)?);
+        }
+        // Partial matches go through the blind n-gram index; candidates are confirmed after decrypt.
+        filter
+            .email_tokens
+            .extend(pii_search_tokens(key, EMAIL_FIELD, Some(trimmed))?);
+        filter
+            .phone_tokens
+            .extend(pii_search_tokens(key, PHONE_FIELD, Some(trimmed))?);
+    }
 
-    let phone_hash = if trimmed.is_empty() {
-        None
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let mut builder = QueryBuilder::new(CUSTOMER_SEARCH_SELECT);
+    filter.push(&mut builder, tenant_id);
+    plan.push_cursor_filter(&mut builder);
+    plan.push_order_by(&mut builder);
+    if plan.is_paginated() {
+        plan.push_limit(&mut builder);
     } else {
-        let normalized = normalize_phone(trimmed);
-        if normalized.is_empty() {
-            None
-        } else {
-            Some(deterministic_hash(&active_key.key, normalized.as_bytes()).map_err(crypto_err)?)
-        }
+        builder.push(" LIMIT 20");
+    }
+    let rows = builder
+        .build_query_as::<CustomerRow>()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_internal)?;
+    let total_estimate = if plan.is_paginated() {
+        let mut estimate = pagination::estimate_query(CUSTOMER_SEARCH_SELECT);
+        filter.push(&mut estimate, tenant_id);
+        Some(
+            pagination::estimate_rows(&mut *tx, estimate)
+                .await
+                .map_err(db_internal)?,
+        )
+    } else {
+        None
     };
-
-    let rows = sqlx::query_as::<_, CustomerRow>(
-        "SELECT
-            id,
-            tenant_id,
-            name,
-            email,
-            phone,
-            email_encrypted,
-            phone_encrypted,
-            pii_key_version,
-            created_at
-        FROM customers
-        WHERE tenant_id = $1
-          AND (
-                name ILIKE $2
-             OR ($3::bytea IS NOT NULL AND email_hash = $3)
-             OR ($4::bytea IS NOT NULL AND phone_hash = $4)
-          )
-        ORDER BY created_at DESC
-        LIMIT 20",
-    )
-    .bind(tenant_id)
-    .bind(&pattern)
-    .bind(email_hash.as_deref())
-    .bind(phone_hash.as_deref())
-    .fetch_all(&state.db)
-    .await
-    .map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
 
     let customers = hydrate_customer_rows(rows, &mut key_cache).await?;
-    Ok(Json(customers))
+    // Fix the page boundary before dropping false-positive token matches, so the cursor still
+    // points past every candidate that was examined.
+    let mut listing = plan.finish(customers, total_estimate, |customer, field| {
+        let value = match field {
+            "name" => customer.name.clone(),
+            _ => customer.created_at.to_rfc3339(),
+        };
+        (value, customer.id)
+    });
+    listing.retain(|customer| customer_matches_search(customer, trimmed));
+    Ok(Json(listing))
+}
+
+const CUSTOMER_SEARCH_SELECT: &str = "SELECT id, tenant_id, name, email, phone, email_encrypted, phone_encrypted, pii_key_version, created_at, version FROM customers WHERE tenant_id = ";
+
+static CUSTOMER_SORT: SortSpec = SortSpec {
+    fields: &[
+        SortField::new("created_at", "created_at", "timestamptz"),
+        SortField::new("name", "name", "text"),
+    ],
+    default_field: "created_at",
+    default_direction: SortDirection::Desc,
+    tiebreak: "id",
+};
+
+/// Candidate predicate for customer search: name substring, exact email/phone hash, or blind
+/// n-gram token containment.
+/// Search predicates; the hash and token lists hold one entry per tenant key.
+#[derive(Default)]
+struct CustomerSearchFilter {
+    pattern: String,
+    email_hashes: Vec<Vec<u8>>,
+    phone_hashes: Vec<Vec<u8>>,
+    email_tokens: Vec<Vec<Vec<u8>>>,
+    phone_tokens: Vec<Vec<Vec<u8>>>,
+}
+
+impl CustomerSearchFilter {
+    fn push(&self, builder: &mut QueryBuilder<'_, sqlx::Postgres>, tenant_id: Uuid) {
+        builder.push_bind(tenant_id);
+        builder.push(" AND (name ILIKE ");
+        builder.push_bind(self.pattern.clone());
+        if !self.email_hashes.is_empty() {
+            builder.push(" OR email_hash = ANY(");
+            builder.push_bind(self.email_hashes.clone());
+            builder.push(")");
+        }
+        if !self.phone_hashes.is_empty() {
+            builder.push(" OR phone_hash = ANY(");
+            builder.push_bind(self.phone_hashes.clone());
+            builder.push(")");
+        }
+        for tokens in &self.email_tokens {
+            builder.push(" OR email_search_tokens @> ");
+            builder.push_bind(tokens.clone());
+        }
+        for tokens in &self.phone_tokens {
+            builder.push(" OR phone_search_tokens @> ");
+            builder.push_bind(tokens.clone());
+        }
+        builder.push(")");
+    }
 }
 
 pub(crate) async fn get_customer_impl(
@@ -456,13 +676,19 @@ pub(crate) async fn get_customer_impl(
     sec: common_security::context::SecurityContext,
     customer_id: Uuid,
 ) -> ApiResult<Json<Customer>> {
-    ensure_capability(&sec, Capability::CustomerView)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_view", trace_id: sec.trace_id })?;
+    ensure_capability(&sec, Capability::CustomerView).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_view",
+            trace_id: sec.trace_id,
+        }
+    })?;
     let tenant_id = sec.tenant_id;
 
-    let mut key_cache = TenantKeyCache::new(&state, tenant_id);
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
 
-    let row = sqlx::query_as::<_, CustomerRow>(
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let row = common_db::query_as::<CustomerRow>(
         "SELECT
             id,
             tenant_id,
@@ -472,16 +698,21 @@ pub(crate) async fn get_customer_impl(
             email_encrypted,
             phone_encrypted,
             pii_key_version,
-            created_at
+            created_at,
+            version
         FROM customers
         WHERE tenant_id = $1 AND id = $2",
     )
     .bind(tenant_id)
     .bind(customer_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_internal)?
-    .ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })?;
+    .ok_or(ApiError::NotFound {
+        code: "customer_not_found",
+        trace_id: None,
+    })?;
+    tx.commit().await.map_err(db_internal)?;
 
     let customer = hydrate_customer_row(row, &mut key_cache).await?;
     Ok(Json(customer))
@@ -491,22 +722,34 @@ pub(crate) async fn update_customer_impl(
     state: AppState,
     sec: common_security::context::SecurityContext,
     customer_id: Uuid,
+    expected_version: Option<i64>,
     payload: UpdateCustomerRequest,
 ) -> ApiResult<Json<Customer>> {
-    ensure_capability(&sec, Capability::CustomerWrite)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_write", trace_id: sec.trace_id })?;
+    ensure_capability(&sec, Capability::CustomerWrite).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_write",
+            trace_id: sec.trace_id,
+        }
+    })?;
+    payload.ensure_valid(sec.trace_id)?;
     let tenant_id = sec.tenant_id;
-    let mut key_cache = TenantKeyCache::new(&state, tenant_id);
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
 
-    let existing = sqlx::query_as::<_, CustomerRow>(
-        "SELECT id, tenant_id, name, email, phone, email_encrypted, phone_encrypted, pii_key_version, created_at FROM customers WHERE tenant_id = $1 AND id = $2",
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let existing = common_db::query_as::<CustomerRow>(
+        "SELECT id, tenant_id, name, email, phone, email_encrypted, phone_encrypted, pii_key_version, created_at, version FROM customers WHERE tenant_id = $1 AND id = $2",
     )
     .bind(tenant_id)
     .bind(customer_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_internal)?
     .ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })?;
+    let expected_version = expected_version.unwrap_or(existing.version);
+    if expected_version != existing.version {
+        return Err(etag::version_conflict(sec.trace_id));
+    }
 
     let UpdateCustomerRequest { name, email, phone } = payload;
 
@@ -515,6 +758,7 @@ pub(crate) async fn update_customer_impl(
         existing_email = decrypt_optional_field(
             existing.email_encrypted.clone(),
             existing.pii_key_version,
+            pii_aad(&tenant_id, EMAIL_FIELD),
             &mut key_cache,
         )
         .await?;
@@ -525,6 +769,7 @@ pub(crate) async fn update_customer_impl(
         existing_phone = decrypt_optional_field(
             existing.phone_encrypted.clone(),
             existing.pii_key_version,
+            pii_aad(&tenant_id, PHONE_FIELD),
             &mut key_cache,
         )
         .await?;
@@ -534,9 +779,6 @@ pub(crate) async fn update_customer_impl(
     let mut name_changed = false;
     if let Some(candidate) = name.as_ref() {
         let trimmed = candidate.trim();
-        if trimmed.is_empty() {
-            return Err(ApiError::BadRequest { code: "invalid_name", trace_id: None, message: Some("Name must not be empty".into()) });
-        }
         if trimmed != existing.name {
             final_name = trimmed.to_string();
             name_changed = true;
@@ -566,6 +808,7 @@ pub(crate) async fn update_customer_impl(
             phone_encrypted: existing.phone_encrypted.clone(),
             pii_key_version: existing.pii_key_version,
             created_at: existing.created_at,
+            version: existing.version,
         };
         let customer = hydrate_customer_row(row, &mut key_cache).await?;
         return Ok(Json(customer));
@@ -578,13 +821,20 @@ pub(crate) async fn update_customer_impl(
         phone_hash_param,
         pii_key_version_param,
         pii_encrypted_at_param,
+        email_tokens_param,
+        phone_tokens_param,
     ) = if final_email.is_some() || final_phone.is_some() {
         let active_key = key_cache.active().await?;
         let key_bytes = active_key.key;
 
         let (enc_email, hash_email) = match final_email.as_ref() {
             Some(value) => {
-                let encrypted = encrypt_field(&key_bytes, value.as_bytes()).map_err(crypto_err)?;
+                let encrypted = encrypt_field_with_aad(
+                    &key_bytes,
+                    pii_aad(&tenant_id, EMAIL_FIELD),
+                    value.as_bytes(),
+                )
+                .map_err(crypto_err)?;
                 let normalized = normalize_email(value);
                 let hash = if normalized.is_empty() {
                     None
@@ -601,7 +851,12 @@ pub(crate) async fn update_customer_impl(
 
         let (enc_phone, hash_phone) = match final_phone.as_ref() {
             Some(value) => {
-                let encrypted = encrypt_field(&key_bytes, value.as_bytes()).map_err(crypto_err)?;
+                let encrypted = encrypt_field_with_aad(
+                    &key_bytes,
+                    pii_aad(&tenant_id, PHONE_FIELD),
+                    value.as_bytes(),
+                )
+                .map_err(crypto_err)?;
                 let normalized = normalize_phone(value);
                 let hash = if normalized.is_empty() {
                     None
@@ -623,12 +878,14 @@ pub(crate) async fn update_customer_impl(
             hash_phone,
             Some(active_key.version),
             Some(Utc::now()),
+            pii_search_tokens(&key_bytes, EMAIL_FIELD, final_email.as_deref())?,
+            pii_search_tokens(&key_bytes, PHONE_FIELD, final_phone.as_deref())?,
         )
     } else {
-        (None, None, None, None, None, None)
+        (None, None, None, None, None, None, None, None)
     };
 
-    let row = sqlx::query_as::<_, CustomerRow>(
+    let row = common_db::query_as::<CustomerRow>(
         "UPDATE customers
         SET name = $1,
             email = NULL,
@@ -638,9 +895,12 @@ pub(crate) async fn update_customer_impl(
             email_hash = $4,
             phone_hash = $5,
             pii_key_version = $6,
-            pii_encrypted_at = $7
-        WHERE tenant_id = $8 AND id = $9
-        RETURNING id, tenant_id, name, email, phone, email_encrypted, phone_encrypted, pii_key_version, created_at",
+            pii_encrypted_at = $7,
+            email_search_tokens = $10,
+            phone_search_tokens = $11,
+            version = version + 1
+        WHERE tenant_id = $8 AND id = $9 AND version = $12
+        RETURNING id, tenant_id, name, email, phone, email_encrypted, phone_encrypted, pii_key_version, created_at, version",
     )
     .bind(&final_name)
     .bind(email_encrypted_param.as_deref())
@@ -651,10 +911,14 @@ pub(crate) async fn update_customer_impl(
     .bind(pii_encrypted_at_param)
     .bind(tenant_id)
     .bind(customer_id)
-    .fetch_optional(&state.db)
+    .bind(email_tokens_param)
+    .bind(phone_tokens_param)
+    .bind(expected_version)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_internal)?
-    .ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })?;
+    .ok_or_else(|| etag::version_conflict(sec.trace_id))?;
+    tx.commit().await.map_err(db_internal)?;
 
     let customer = hydrate_customer_row(row, &mut key_cache).await?;
     Ok(Json(customer))
@@ -665,13 +929,19 @@ async fn gdpr_export_customer(
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Path(customer_id): Path<Uuid>,
 ) -> ApiResult<Json<GdprExportResponse>> {
-    ensure_capability(&sec, Capability::CustomerWrite)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_write", trace_id: sec.trace_id })?;
+    ensure_capability(&sec, Capability::CustomerWrite).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_write",
+            trace_id: sec.trace_id,
+        }
+    })?;
     let tenant_id = sec.tenant_id;
 
-    let mut key_cache = TenantKeyCache::new(&state, tenant_id);
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
 
-    let row = sqlx::query_as::<_, CustomerRow>(
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let row = common_db::query_as::<CustomerRow>(
         "SELECT
             id,
             tenant_id,
@@ -681,18 +951,23 @@ async fn gdpr_export_customer(
             email_encrypted,
             phone_encrypted,
             pii_key_version,
-            created_at
+            created_at,
+            version
         FROM customers
         WHERE tenant_id = $1 AND id = $2",
     )
     .bind(tenant_id)
     .bind(customer_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_internal)?
-    .ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })?;
+    .ok_or(ApiError::NotFound {
+        code: "customer_not_found",
+        trace_id: None,
+    })?;
 
     let customer = hydrate_customer_row(row, &mut key_cache).await?;
+    let consents = export_consents(&mut tx, tenant_id, customer_id).await?;
     let metadata = json!({
         "request_type": "export",
         "had_email": customer.email.is_some(),
@@ -701,34 +976,197 @@ async fn gdpr_export_customer(
     });
 
     let export_id = insert_gdpr_tombstone(
-        &state.db,
+        &mut *tx,
         tenant_id,
         Some(customer_id),
         "export",
         "completed",
-    sec.actor.id,
+        sec.actor.id,
         metadata,
     )
     .await
     .map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
 
     info!(tenant_id = %tenant_id, customer_id = %customer_id, export_id = %export_id, "GDPR export completed");
     Ok(Json(GdprExportResponse {
         export_id,
         customer,
+        consents,
     }))
 }
 
+/// Offboarding export pulled by auth-service's tenant export job: every customer with PII
+/// decrypted, plus the tenant's GDPR request history.
+async fn export_tenant_data(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> ApiResult<Json<TenantExportResponse>> {
+    ensure_capability(&sec, Capability::GdprManage).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "gdpr_manage",
+            trace_id: sec.trace_id,
+        }
+    })?;
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden {
+            trace_id: sec.trace_id,
+        });
+    }
+
+    let db = state.db.for_tenant(&sec)?;
+    let mut key_cache = TenantKeyCache::new(&state, db, tenant_id);
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let rows = common_db::query_as::<CustomerRow>(
+        "SELECT
+            id,
+            tenant_id,
+            name,
+            email,
+            phone,
+            email_encrypted,
+            phone_encrypted,
+            pii_key_version,
+            created_at,
+            version
+        FROM customers
+        WHERE tenant_id = $1
+        ORDER BY created_at",
+    )
+    .bind(tenant_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_internal)?;
+    let customers = hydrate_customer_rows(rows, &mut key_cache).await?;
+
+    let tombstones: String = common_db::query_scalar(
+        "SELECT COALESCE(json_agg(t), '[]'::json)::text
+         FROM (SELECT * FROM gdpr_tombstones WHERE tenant_id = $1 ORDER BY requested_at) t",
+    )
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_internal)?;
+    let gdpr_tombstones =
+        serde_json::from_str(&tombstones).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let consents: String = common_db::query_scalar(
+        "SELECT COALESCE(json_agg(c), '[]'::json)::text
+         FROM (SELECT * FROM customer_consents WHERE tenant_id = $1 ORDER BY customer_id, purpose) c",
+    )
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_internal)?;
+    let customer_consents =
+        serde_json::from_str(&consents).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let consent_events: String = common_db::query_scalar(
+        "SELECT COALESCE(json_agg(e), '[]'::json)::text
+         FROM (SELECT * FROM customer_consent_events WHERE tenant_id = $1 ORDER BY recorded_at, id) e",
+    )
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_internal)?;
+    let customer_consent_events =
+        serde_json::from_str(&consent_events).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let export_id = insert_gdpr_tombstone(
+        &mut *tx,
+        tenant_id,
+        None,
+        "export",
+        "completed",
+        sec.actor.id,
+        json!({
+            "request_type": "tenant_export",
+            "customer_count": customers.len(),
+            "requested_at": Utc::now(),
+        }),
+    )
+    .await
+    .map_err(db_internal)?;
+    tx.commit().await.map_err(db_internal)?;
+
+    info!(tenant_id = %tenant_id, export_id = %export_id, customers = customers.len(), "Tenant customer export completed");
+    Ok(Json(TenantExportResponse {
+        export_id,
+        customers,
+        gdpr_tombstones,
+        customer_consents,
+        customer_consent_events,
+    }))
+}
+
+/// Provisioning step run by auth-service when a tenant is created: seeds the tenant's first
+/// data encryption key. A tenant that already has a key keeps it, so a retried run never
+/// orphans data sealed under the first one.
+async fn provision_tenant(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> ApiResult<Json<Value>> {
+    if !sec.roles.contains(&Role::SuperAdmin) {
+        return Err(ApiError::ForbiddenMissingRole {
+            role: "super_admin",
+            trace_id: sec.trace_id,
+        });
+    }
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden {
+            trace_id: sec.trace_id,
+        });
+    }
+
+    let wrapped = state
+        .master_key
+        .wrap_dek(&generate_dek())
+        .await
+        .map_err(crypto_err)?;
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let inserted = common_db::query(
+        "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, master_key_id, active)
+         SELECT $1, $2, 1, $3, $4, TRUE
+         WHERE NOT EXISTS (SELECT 1 FROM tenant_data_keys WHERE tenant_id = $2)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(&wrapped.blob)
+    .bind(&wrapped.key_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_internal)?
+    .rows_affected()
+        > 0;
+    tx.commit().await.map_err(db_internal)?;
+
+    let active = load_tenant_dek(db, state.master_key.as_ref(), tenant_id, None).await?;
+    info!(tenant_id = %tenant_id, key_version = active.version, seeded = inserted, "Tenant data key provisioned");
+    Ok(Json(
+        json!({ "tenant_data_key": { "key_version": active.version, "seeded": inserted } }),
+    ))
+}
+
 async fn gdpr_delete_customer(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Path(customer_id): Path<Uuid>,
 ) -> ApiResult<Json<GdprDeleteResponse>> {
-    ensure_capability(&sec, Capability::CustomerWrite)
-        .map_err(|_| ApiError::ForbiddenMissingRole { role: "customer_write", trace_id: sec.trace_id })?;
+    ensure_capability(&sec, Capability::CustomerWrite).map_err(|_| {
+        ApiError::ForbiddenMissingRole {
+            role: "customer_write",
+            trace_id: sec.trace_id,
+        }
+    })?;
     let tenant_id = sec.tenant_id;
 
-    let row = sqlx::query_as::<_, CustomerRow>(
+    let db = state.db.for_tenant(&sec)?;
+    let mut tx = db.begin_for(&sec).await.map_err(db_internal)?;
+    let row = common_db::query_as::<CustomerRow>(
         "SELECT
             id,
             tenant_id,
@@ -738,22 +1176,25 @@ async fn gdpr_delete_customer(
             email_encrypted,
             phone_encrypted,
             pii_key_version,
-            created_at
+            created_at,
+            version
         FROM customers
         WHERE tenant_id = $1 AND id = $2",
     )
     .bind(tenant_id)
     .bind(customer_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_internal)?
-    .ok_or(ApiError::NotFound { code: "customer_not_found", trace_id: None })?;
+    .ok_or(ApiError::NotFound {
+        code: "customer_not_found",
+        trace_id: None,
+    })?;
 
     let had_email = row.email.is_some() || row.email_encrypted.is_some();
     let had_phone = row.phone.is_some() || row.phone_encrypted.is_some();
 
-    let mut tx = state.db.begin().await.map_err(db_internal)?;
-    let result = sqlx::query(
+    let result = common_db::query(
         "UPDATE customers
          SET name = $1,
              email = NULL,
@@ -762,8 +1203,11 @@ async fn gdpr_delete_customer(
              phone_encrypted = NULL,
              email_hash = NULL,
              phone_hash = NULL,
+             email_search_tokens = NULL,
+             phone_search_tokens = NULL,
              pii_key_version = NULL,
-             pii_encrypted_at = NULL
+             pii_encrypted_at = NULL,
+             version = version + 1
          WHERE tenant_id = $2 AND id = $3",
     )
     .bind(GDPR_DELETED_NAME)
@@ -774,14 +1218,23 @@ async fn gdpr_delete_customer(
     .map_err(db_internal)?;
 
     if result.rows_affected() == 0 {
-    tx.rollback().await.map_err(db_internal)?;
-        return Err(ApiError::NotFound { code: "customer_not_found", trace_id: None });
+        tx.rollback().await.map_err(db_internal)?;
+        return Err(ApiError::NotFound {
+            code: "customer_not_found",
+            trace_id: None,
+        });
     }
 
+    let consents_withdrawn =
+        consent::withdraw_all(&mut tx, tenant_id, customer_id, "gdpr_delete", sec.actor.id)
+            .await
+            .map_err(db_internal)?;
+
     let metadata = json!({
         "request_type": "delete",
         "had_email": had_email,
         "had_phone": had_phone,
+        "consents_withdrawn": consents_withdrawn,
         "requested_at": Utc::now(),
     });
 
@@ -791,12 +1244,24 @@ async fn gdpr_delete_customer(
         Some(customer_id),
         "delete",
         "completed",
-    sec.actor.id,
+        sec.actor.id,
         metadata,
     )
     .await
     .map_err(db_internal)?;
 
+    // Downstream per-customer data (analytics aggregates) is dropped off the back of this event.
+    let erased = CustomerErasedEvent {
+        schema_version: CustomerErasedEvent::SCHEMA_VERSION,
+        tenant_id,
+        customer_id,
+        tombstone_id,
+        erased_at: Utc::now(),
+    };
+    outbox::stage(&mut tx, tenant_id, &erased)
+        .await
+        .map_err(|err| ApiError::internal(err, None))?;
+
     tx.commit().await.map_err(db_internal)?;
     info!(tenant_id = %tenant_id, customer_id = %customer_id, tombstone_id = %tombstone_id, "GDPR delete completed");
     Ok(Json(GdprDeleteResponse {
@@ -830,14 +1295,26 @@ async fn hydrate_customer_row(
         phone_encrypted,
         pii_key_version,
         created_at,
-        ..
+        version,
     } = row;
 
     let key_version = pii_key_version;
-    let decrypted_email = decrypt_optional_field(email_encrypted, key_version, key_cache).await?;
+    let decrypted_email = decrypt_optional_field(
+        email_encrypted,
+        key_version,
+        pii_aad(&tenant_id, EMAIL_FIELD),
+        key_cache,
+    )
+    .await?;
     let email = decrypted_email.or(email);
 
-    let decrypted_phone = decrypt_optional_field(phone_encrypted, key_version, key_cache).await?;
+    let decrypted_phone = decrypt_optional_field(
+        phone_encrypted,
+        key_version,
+        pii_aad(&tenant_id, PHONE_FIELD),
+        key_cache,
+    )
+    .await?;
     let phone = decrypted_phone.or(phone);
 
     Ok(Customer {
@@ -847,54 +1324,71 @@ async fn hydrate_customer_row(
         email,
         phone,
         created_at,
+        version,
     })
 }
 
 async fn decrypt_optional_field(
     encrypted: Option<Vec<u8>>,
     key_version: Option<i32>,
+    aad: FieldAad<'_>,
     key_cache: &mut TenantKeyCache<'_>,
 ) -> ApiResult<Option<String>> {
     let Some(ciphertext) = encrypted else {
         return Ok(None);
     };
-    let version = key_version.ok_or(ApiError::Internal { trace_id: None, message: Some("Encrypted value missing key version".into()) })?;
+    let version = key_version.ok_or(ApiError::Internal {
+        trace_id: None,
+        message: Some("Encrypted value missing key version".into()),
+    })?;
     let key = key_cache.by_version(version).await?;
-    let plaintext = decrypt_field(&key, &ciphertext).map_err(crypto_err)?;
-    String::from_utf8(plaintext).map(Some).map_err(|_| ApiError::Internal { trace_id: None, message: Some("Decrypted value was not valid UTF-8".into()) })
+    let plaintext = if key_cache.require_envelope {
+        decrypt_envelope(&key, aad, &ciphertext)
+    } else {
+        decrypt_field_with_aad(&key, aad, &ciphertext)
+    }
+    .map_err(crypto_err)?;
+    String::from_utf8(plaintext)
+        .map(Some)
+        .map_err(|_| ApiError::Internal {
+            trace_id: None,
+            message: Some("Decrypted value was not valid UTF-8".into()),
+        })
 }
 
 async fn load_tenant_dek(
-    db: &PgPool,
-    master: &MasterKey,
+    db: &TenantScopedPool,
+    master: &dyn MasterKeyProvider,
     tenant_id: Uuid,
     version: Option<i32>,
 ) -> ApiResult<TenantDek> {
+    let mut tx = db.begin(tenant_id).await.map_err(db_internal)?;
     let row = if let Some(version) = version {
-        sqlx::query_as::<_, TenantKeyRow>(
-            "SELECT key_version, encrypted_key
+        common_db::query_as::<TenantKeyRow>(
+            "SELECT key_version, encrypted_key, master_key_id
              FROM tenant_data_keys
              WHERE tenant_id = $1 AND key_version = $2
              LIMIT 1",
         )
         .bind(tenant_id)
         .bind(version)
-        .fetch_optional(db)
+        .fetch_optional(&mut *tx)
         .await
-    .map_err(db_internal)?
+        .map_err(db_internal)?
     } else {
-        sqlx::query_as::<_, TenantKeyRow>(
-            "SELECT key_version, encrypted_key
+        common_db::query_as::<TenantKeyRow>(
+            "SELECT key_version, encrypted_key, master_key_id
              FROM tenant_data_keys
              WHERE tenant_id = $1 AND active = TRUE
              ORDER BY key_version DESC
              LIMIT 1",
         )
         .bind(tenant_id)
-        .fetch_optional(db)
+        .fetch_optional(&mut *tx)
         .await
-    .map_err(db_internal)?
+        .map_err(db_internal)?
     };
+    tx.commit().await.map_err(db_internal)?;
 
     let row = row.ok_or_else(|| {
         let scope = match version {
@@ -902,23 +1396,30 @@ async fn load_tenant_dek(
             None => "active".to_string(),
         };
         warn!(tenant_id = %tenant_id, scope = %scope, "Missing tenant data key");
-        ApiError::Internal { trace_id: None, message: Some(format!("No {scope} tenant data key found for tenant {tenant_id}")) }
+        ApiError::Internal {
+            trace_id: None,
+            message: Some(format!(
+                "No {scope} tenant data key found for tenant {tenant_id}"
+            )),
+        }
     })?;
 
     let TenantKeyRow {
         key_version,
         encrypted_key,
+        master_key_id,
     } = row;
 
     let key = master
-        .decrypt_tenant_dek(&encrypted_key)
+        .unwrap_dek(master_key_id.as_deref(), &encrypted_key)
+        .await
         .map_err(|err| {
             error!(tenant_id = %tenant_id, version = key_version, error = ?err, "Failed to decrypt tenant data key");
             ApiError::Internal { trace_id: None, message: Some("Failed to decrypt tenant data key".into()) }
         })?;
     Ok(TenantDek {
         version: key_version,
-        key,
+        key: Dek::new(key),
     })
 }
 
@@ -940,7 +1441,7 @@ where
     } else {
         None
     };
-    sqlx::query(
+    common_db::query(
         "INSERT INTO gdpr_tombstones (id, tenant_id, customer_id, request_type, status, requested_by, metadata, processed_at)
          VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
     )
@@ -970,38 +1471,81 @@ fn normalize_phone(value: &str) -> String {
     value.chars().filter(|c| c.is_ascii_digit()).collect()
 }
 
+/// Blind-index tokens for an email or phone, normalized the same way as its exact-match hash.
+fn pii_search_tokens(
+    key: &[u8; 32],
+    field: &str,
+    value: Option<&str>,
+) -> ApiResult<Option<Vec<Vec<u8>>>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let normalized = if field == PHONE_FIELD {
+        normalize_phone(value)
+    } else {
+        normalize_email(value)
+    };
+    search_tokens(key, field, &normalized).map_err(crypto_err)
+}
+
+/// Drops blind-index false positives: a customer matches when the term occurs in the name
+/// (case-insensitively) or in the normalized email or phone.
+fn customer_matches_search(customer: &Customer, term: &str) -> bool {
+    if term.is_empty() || customer.name.to_lowercase().contains(&term.to_lowercase()) {
+        return true;
+    }
+    let email_term = normalize_email(term);
+    let phone_term = normalize_phone(term);
+    customer
+        .email
+        .as_deref()
+        .is_some_and(|email| normalize_email(email).contains(&email_term))
+        || (!phone_term.is_empty()
+            && customer
+                .phone
+                .as_deref()
+                .is_some_and(|phone| normalize_phone(phone).contains(&phone_term)))
+}
+
+fn pii_aad<'a>(tenant_id: &'a Uuid, field: &'a str) -> FieldAad<'a> {
+    FieldAad::new(tenant_id.as_bytes(), field)
+}
+
 fn crypto_err(err: CryptoError) -> ApiError {
     error!(error = ?err, "Crypto operation failed");
-    ApiError::Internal { trace_id: None, message: Some("Crypto operation failed".into()) }
+    ApiError::Internal {
+        trace_id: None,
+        message: Some("Crypto operation failed".into()),
+    }
 }
 
 fn db_internal(err: sqlx::Error) -> ApiError {
-    ApiError::Internal { trace_id: None, message: Some(format!("DB error: {}", err)) }
+    if matches!(err, sqlx::Error::PoolTimedOut) {
+        return common_db::db_error(err, None);
+    }
+    ApiError::Internal {
+        trace_id: None,
+        message: Some(format!("DB error: {}", err)),
+    }
 }
 
-
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -1009,16 +1553,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     info!("JWT verifier initialised");
     Ok(Arc::new(verifier))
 }
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -1044,13 +1583,13 @@ fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
 mod tests {
     use super::*;
     use axum::{
-        extract::{Path, State},
+        extract::State,
         http::{HeaderMap, HeaderValue},
     };
     // chrono::Utc no longer needed in this test module after refactor
     use common_auth::{JwtConfig, JwtVerifier};
+    use common_crypto::{encrypt_field, generate_dek, LocalMasterKeyProvider, MasterKey};
     use common_security::SecurityContext;
-    use common_crypto::{generate_dek, MasterKey};
     // serde_json::json no longer needed in this test module after refactor
     use sqlx::{migrate::MigrateError, PgPool, Row};
     use std::{io, sync::Arc};
@@ -1063,7 +1602,10 @@ mod tests {
     }
 
     #[tokio::test]
-    #[cfg_attr(not(feature = "integration"), ignore = "enable with --features integration (requires Postgres schema migrations)")]
+    #[cfg_attr(
+        not(feature = "integration"),
+        ignore = "enable with --features integration (requires Postgres schema migrations)"
+    )]
     async fn update_customer_allows_editing_contact_fields(
     ) -> Result<(), Box<dyn std::error::Error>> {
         let database_url = match require_database_url() {
@@ -1087,24 +1629,27 @@ mod tests {
             "test-audience",
         )));
         let state = AppState {
-            db: pool.clone(),
+            db: RegionalPools::single(TenantScopedPool::new(pool.clone())),
             jwt_verifier,
-            master_key: Arc::new(master_key.clone()),
+            master_key: Arc::new(LocalMasterKeyProvider::new(master_key, Vec::new())),
+            dek_cache: Arc::new(DekCache::new(Duration::from_secs(300), 100)),
+            require_pii_envelope: false,
         };
 
         let tenant_id = Uuid::new_v4();
         let actor_id = Uuid::new_v4();
         let dek = generate_dek();
-        let encrypted_key = master_key.encrypt_tenant_dek(&dek)?;
+        let wrapped = state.master_key.wrap_dek(&dek).await?;
 
         sqlx::query(
-            "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, active)
-             VALUES ($1, $2, $3, $4, TRUE)",
+            "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, master_key_id, active)
+             VALUES ($1, $2, $3, $4, $5, TRUE)",
         )
         .bind(Uuid::new_v4())
         .bind(tenant_id)
         .bind(1i32)
-        .bind(&encrypted_key)
+        .bind(&wrapped.blob)
+        .bind(&wrapped.key_id)
         .execute(&pool)
         .await?;
 
@@ -1115,17 +1660,27 @@ mod tests {
         );
 
         // Simulate security context (bypassing extractor since we invoke handler directly)
-        headers.insert("X-Tenant-ID", HeaderValue::from_str(&tenant_id.to_string())?);
+        headers.insert(
+            "X-Tenant-ID",
+            HeaderValue::from_str(&tenant_id.to_string())?,
+        );
         headers.insert("X-Roles", HeaderValue::from_static("admin"));
         headers.insert("X-User-ID", HeaderValue::from_str(&actor_id.to_string())?);
 
         // Minimal dummy actor (structure must match expected fields used by downstream code).
-        let actor = common_audit::AuditActor { id: Some(actor_id), name: None, email: None };
+        let actor = common_audit::AuditActor {
+            id: Some(actor_id),
+            name: None,
+            email: None,
+        };
         let sec = SecurityContext {
             tenant_id,
             actor,
             roles: vec![Role::Admin],
             trace_id: None,
+            residency: None,
+            capability_cache: Default::default(),
+            system: None,
         };
 
         let created = create_customer(
@@ -1138,25 +1693,36 @@ mod tests {
             }),
         )
         .await
-    .map_err(|e| io::Error::other(format!("create_customer failed: {:?}", e)))?
+        .map_err(|e| io::Error::other(format!("create_customer failed: {:?}", e)))?
         .0;
 
         let customer_id = created.id;
 
-        let updated = update_customer(
-            State(state.clone()),
-            SecurityCtxExtractor(sec.clone()),
-            Path(customer_id),
-            Json(UpdateCustomerRequest {
+        let updated = update_customer_impl(
+            state.clone(),
+            sec.clone(),
+            customer_id,
+            Some(created.version),
+            UpdateCustomerRequest {
                 name: Some("Alice Cooper".to_string()),
                 email: Some(Some("alice.cooper@example.com".to_string())),
                 phone: Some(None),
-            }),
+            },
         )
         .await
-    .map_err(|e| io::Error::other(format!("update_customer failed: {:?}", e)))?
+        .map_err(|e| io::Error::other(format!("update_customer failed: {:?}", e)))?
         .0;
 
+        assert_eq!(updated.version, created.version + 1);
+        let stale = update_customer_impl(
+            state.clone(),
+            sec.clone(),
+            customer_id,
+            Some(created.version),
+            UpdateCustomerRequest { name: Some("Alice Stale".to_string()), email: None, phone: None },
+        )
+        .await;
+        assert!(matches!(stale, Err(ApiError::Conflict { code: "version_conflict", .. })));
         assert_eq!(updated.name, "Alice Cooper");
         assert_eq!(updated.email.as_deref(), Some("alice.cooper@example.com"));
         assert!(updated.phone.is_none());
@@ -1180,6 +1746,21 @@ mod tests {
         assert!(phone_hash.is_none());
         assert_eq!(pii_key_version, Some(1));
 
+        // A row still in the pre-envelope format reads until strict mode is switched on.
+        sqlx::query("UPDATE customers SET email_encrypted = $1 WHERE id = $2")
+            .bind(encrypt_field(&dek, b"alice.cooper@example.com")?)
+            .bind(customer_id)
+            .execute(&pool)
+            .await?;
+        let legacy = get_customer_impl(state.clone(), sec.clone(), customer_id)
+            .await
+            .map_err(|e| io::Error::other(format!("get_customer failed: {:?}", e)))?
+            .0;
+        assert_eq!(legacy.email.as_deref(), Some("alice.cooper@example.com"));
+        let strict = AppState { require_pii_envelope: true, ..state.clone() };
+        let refused = get_customer_impl(strict, sec.clone(), customer_id).await;
+        assert!(matches!(refused, Err(ApiError::Internal { .. })));
+
         sqlx::query("DELETE FROM customers WHERE id = $1")
             .bind(customer_id)
             .execute(&pool)
@@ -1191,4 +1772,176 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "integration"),
+        ignore = "enable with --features integration (requires Postgres schema migrations)"
+    )]
+    async fn search_finds_customers_indexed_under_a_rotated_key(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(database_url) = require_database_url() else {
+            eprintln!("Skipping customer search test because DATABASE_URL is not set.");
+            return Ok(());
+        };
+
+        let pool = PgPool::connect(&database_url).await?;
+        if let Err(err) = sqlx::migrate!("./migrations").run(&pool).await {
+            if !matches!(err, MigrateError::VersionMissing(_)) {
+                return Err(err.into());
+            }
+        }
+
+        let state_with_fresh_cache = || AppState {
+            db: RegionalPools::single(TenantScopedPool::new(pool.clone())),
+            jwt_verifier: Arc::new(JwtVerifier::new(JwtConfig::new(
+                "test-issuer",
+                "test-audience",
+            ))),
+            master_key: Arc::new(LocalMasterKeyProvider::new(
+                MasterKey::from_bytes([3u8; 32]).expect("master key"),
+                Vec::new(),
+            )),
+            dek_cache: Arc::new(DekCache::new(Duration::from_secs(300), 100)),
+            require_pii_envelope: false,
+        };
+        let state = state_with_fresh_cache();
+        let tenant_id = Uuid::new_v4();
+        let insert_key = |version: i32, blob: Vec<u8>, key_id: String| {
+            sqlx::query(
+                "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, master_key_id, active)
+                 VALUES ($1, $2, $3, $4, $5, TRUE)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(tenant_id)
+            .bind(version)
+            .bind(blob)
+            .bind(key_id)
+            .execute(&pool)
+        };
+        let wrapped = state.master_key.wrap_dek(&generate_dek()).await?;
+        insert_key(1, wrapped.blob, wrapped.key_id).await?;
+
+        let sec = SecurityContext {
+            tenant_id,
+            actor: common_audit::AuditActor {
+                id: Some(Uuid::new_v4()),
+                name: None,
+                email: None,
+            },
+            roles: vec![Role::Admin],
+            trace_id: None,
+            residency: None,
+            capability_cache: Default::default(),
+            system: None,
+        };
+        let create = |state: AppState, name: &str, email: &str| {
+            create_customer(
+                State(state),
+                SecurityCtxExtractor(sec.clone()),
+                Json(NewCustomer {
+                    name: name.to_string(),
+                    email: Some(email.to_string()),
+                    phone: None,
+                }),
+            )
+        };
+        let _ = create(state.clone(), "Old Key", "rotation@example.com")
+            .await
+            .map_err(|e| io::Error::other(format!("create_customer failed: {:?}", e)))?;
+
+        // Rotate to v2 without rewrapping the existing customer.
+        sqlx::query("UPDATE tenant_data_keys SET active = FALSE WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&pool)
+            .await?;
+        let wrapped = state.master_key.wrap_dek(&generate_dek()).await?;
+        insert_key(2, wrapped.blob, wrapped.key_id).await?;
+        let state = state_with_fresh_cache();
+        let _ = create(state.clone(), "New Key", "rotation@example.org")
+            .await
+            .map_err(|e| io::Error::other(format!("create_customer failed: {:?}", e)))?;
+
+        for (term, expected) in [
+            ("rotation@example", vec!["New Key", "Old Key"]),
+            ("rotation@example.com", vec!["Old Key"]),
+            ("rotation@example.org", vec!["New Key"]),
+        ] {
+            let listing = search_customers_impl(
+                state.clone(),
+                sec.clone(),
+                SearchParams {
+                    q: Some(term.to_string()),
+                },
+                PageRequest::default(),
+            )
+            .await
+            .map_err(|e| io::Error::other(format!("search failed: {:?}", e)))?
+            .0;
+            let Listing::Items(customers) = listing else {
+                panic!("an unpaged search returns a bare list");
+            };
+            let mut names: Vec<_> = customers.iter().map(|c| c.name.as_str()).collect();
+            names.sort();
+            assert_eq!(names, expected, "search for {term}");
+        }
+
+        sqlx::query("DELETE FROM customers WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&pool)
+            .await?;
+        sqlx::query("DELETE FROM tenant_data_keys WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_filter_confirms_partial_pii_matches() {
+        let customer = Customer {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+            name: "Alice Example".into(),
+            email: Some("Alice@Example.com".into()),
+            phone: Some("+1 (415) 555-0123".into()),
+            created_at: Utc::now(),
+            version: 1,
+        };
+        assert!(customer_matches_search(&customer, ""));
+        assert!(customer_matches_search(&customer, "alice ex"));
+        assert!(customer_matches_search(&customer, "example.c"));
+        assert!(customer_matches_search(&customer, "555-01"));
+        assert!(!customer_matches_search(&customer, "555-99"));
+        assert!(!customer_matches_search(&customer, "bob@"));
+
+        // Phone terms are indexed on digits only: "415555" has three distinct 3-grams.
+        let tokens = pii_search_tokens(&[1u8; 32], PHONE_FIELD, Some("(415) 555"))
+            .expect("tokens")
+            .expect("long enough");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            pii_search_tokens(&[1u8; 32], EMAIL_FIELD, None).expect("tokens"),
+            None
+        );
+    }
+
+    #[test]
+    fn customer_payloads_report_each_invalid_field() {
+        let err = NewCustomer { name: "  ".into(), email: Some("alice@".into()), phone: Some("call me".into()) }
+            .ensure_valid(None)
+            .expect_err("invalid");
+        let ApiError::UnprocessableEntity { details: Some(details), .. } = err else { panic!("{err:?}") };
+        let fields: Vec<_> = details["fields"].as_array().unwrap().iter().map(|f| (f["field"].as_str().unwrap(), f["code"].as_str().unwrap())).collect();
+        assert_eq!(fields, vec![("name", "required"), ("email", "invalid_email"), ("phone", "invalid_phone")]);
+
+        let ok = NewCustomer { name: "Alice".into(), email: None, phone: Some("+1 (415) 555-0123".into()) };
+        assert!(ok.ensure_valid(None).is_ok());
+        // Clearing a contact field is allowed; blanking the name is not.
+        let clear = UpdateCustomerRequest { name: None, email: Some(None), phone: Some(Some(String::new())) };
+        assert!(clear.ensure_valid(None).is_ok());
+        let blank = UpdateCustomerRequest { name: Some(String::new()), email: None, phone: None };
+        assert!(blank.ensure_valid(None).is_err());
+    }
 }
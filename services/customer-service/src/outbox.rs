@@ -0,0 +1,17 @@
+//! Events leave customer-service through the shared `outbox` table, written in the same
+//! transaction as the state change. order-service's relay publishes them to Kafka.
+
+use common_events::DomainEvent;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+pub async fn stage<E: DomainEvent>(conn: &mut PgConnection, tenant_id: Uuid, event: &E) -> anyhow::Result<()> {
+    common_db::query("INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, $2, $3, $4)")
+        .bind(tenant_id.to_string())
+        .bind(E::TOPIC)
+        .bind(common_events::to_value(event)?)
+        .bind(event.partition_key())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Consent recording against Postgres: answers are upserted with an event per change, repeats
+//! record nothing and withdrawing everything leaves the history intact. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use customer_service::consent::{self, ConsentPurpose};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn consent_changes_are_recorded_once_and_withdrawn_together() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["customer-service"]).await.expect("migrate");
+    let mut conn = postgres.pool().acquire().await.unwrap();
+    let (tenant, customer, actor) = (Uuid::new_v4(), Uuid::new_v4(), Some(Uuid::new_v4()));
+    sqlx::query("INSERT INTO customers (id, tenant_id, name) VALUES ($1, $2, 'Ada')")
+        .bind(customer)
+        .bind(tenant)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+    let current = consent::current_consents(&mut conn, tenant, customer).await.unwrap();
+    assert_eq!(current.len(), ConsentPurpose::ALL.len());
+    assert!(current.iter().all(|record| !record.granted && record.recorded_at.is_none()), "consent is opt-in");
+
+    let email = ConsentPurpose::MarketingEmail;
+    let sms = ConsentPurpose::MarketingSms;
+    assert!(consent::record_consent(&mut conn, tenant, customer, email, true, "pos", actor).await.unwrap());
+    assert!(!consent::record_consent(&mut conn, tenant, customer, email, true, "web", actor).await.unwrap());
+    assert!(consent::record_consent(&mut conn, tenant, customer, sms, true, "web", actor).await.unwrap());
+    assert!(consent::has_consent(&mut conn, tenant, customer, email).await.unwrap());
+    assert!(!consent::has_consent(&mut conn, tenant, customer, ConsentPurpose::Profiling).await.unwrap());
+    assert!(!consent::has_consent(&mut conn, Uuid::new_v4(), customer, email).await.unwrap());
+
+    let current = consent::current_consents(&mut conn, tenant, customer).await.unwrap();
+    assert_eq!(current[0].source.as_deref(), Some("pos"), "an unchanged answer keeps its source");
+
+    assert_eq!(consent::withdraw_all(&mut conn, tenant, customer, "gdpr_delete", None).await.unwrap(), 2);
+    assert!(!consent::has_consent(&mut conn, tenant, customer, sms).await.unwrap());
+    let history = consent::consent_history(&mut conn, tenant, customer).await.unwrap();
+    let summary: Vec<_> =
+        history.iter().map(|event| (event.purpose.as_str(), event.granted, event.source.as_str())).collect();
+    assert_eq!(summary.len(), 4);
+    assert_eq!(&summary[..2], [("marketing_email", true, "pos"), ("marketing_sms", true, "web")]);
+    assert!(summary[2..].iter().all(|(_, granted, source)| !granted && *source == "gdpr_delete"));
+}
@@ -51,6 +51,10 @@ async fn customer_encryption_round_trip() -> Result<(), ApiError> {
         include_str!("../migrations/5002_add_tenant_data_keys.sql"),
         include_str!("../migrations/5003_add_customer_encrypted_columns.sql"),
         include_str!("../migrations/5004_create_gdpr_tombstones.sql"),
+        include_str!("../migrations/5005_add_tenant_data_key_master_key_id.sql"),
+        include_str!("../migrations/5006_add_customer_search_tokens.sql"),
+        include_str!("../migrations/5007_enable_tenant_row_level_security.sql"),
+        include_str!("../migrations/5008_add_customer_version.sql"),
     ];
     for m in migrations { pool.execute(sqlx::query(m)).await.expect("apply migration"); }
 
@@ -88,7 +92,7 @@ async fn customer_encryption_round_trip() -> Result<(), ApiError> {
     let state = TestState { db: pool.clone(), jwt_verifier: Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud"))), master_key: Arc::new(master_key) };
 
     // Construct SecurityContext (bypassing extractor to focus on encryption path)
-    let _sec_ctx = SecurityContext { tenant_id, actor: AuditActor { id: Some(Uuid::new_v4()), name: None, email: None }, roles: vec![Role::Admin], trace_id: None };
+    let _sec_ctx = SecurityContext { tenant_id, actor: AuditActor { id: Some(Uuid::new_v4()), name: None, email: None }, roles: vec![Role::Admin], trace_id: None, residency: None, capability_cache: Default::default(), system: None };
 
     // Create customer manually replicating encryption portions (simplified: insert plaintext email encrypted columns set) - For brevity we directly call similar SQL subset.
     let customer_id = Uuid::new_v4();
@@ -0,0 +1,51 @@
+//! DEK cache invalidation against Postgres: a change to a tenant's `tenant_data_keys` rows drops
+//! that tenant's cached keys in every listening process. Needs Postgres: set ENABLE_ITESTS=1 (and
+//! TEST_DATABASE_URL to skip the container).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use customer_service::dek_cache::{spawn_invalidation_listener, DekCache};
+use uuid::Uuid;
+
+async fn eventually(mut check: impl FnMut() -> bool) -> bool {
+    for _ in 0..50 {
+        if check() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn key_rotation_drops_the_tenants_cached_keys() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["customer-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+    let cache = Arc::new(DekCache::new(Duration::from_secs(300), 100));
+    spawn_invalidation_listener(db.clone(), cache.clone());
+    // The listener clears the cache once subscribed; wait for that before seeding it.
+    cache.insert(cache.generation(), other, 1, &[7; 32], true);
+    assert!(eventually(|| cache.is_empty()).await, "listener never subscribed");
+    cache.insert(cache.generation(), tenant, 1, &[1; 32], true);
+    cache.insert(cache.generation(), other, 1, &[7; 32], true);
+
+    sqlx::query(
+        "INSERT INTO tenant_data_keys (id, tenant_id, key_version, encrypted_key, active) VALUES ($1, $2, 2, $3, TRUE)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant)
+    .bind(vec![0u8; 60])
+    .execute(db)
+    .await
+    .unwrap();
+    assert!(eventually(|| cache.active(tenant).is_none()).await, "rotation did not invalidate the cache");
+    assert_eq!(cache.active(other).map(|(version, key)| (version, *key)), Some((1, [7; 32])));
+}
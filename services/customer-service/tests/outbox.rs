@@ -0,0 +1,47 @@
+//! Staging `customer.erased` against Postgres: the event lands in the outbox keyed by customer and
+//! disappears with the transaction when the erasure rolls back. Needs Postgres: set ENABLE_ITESTS=1
+//! (and TEST_DATABASE_URL to skip the container).
+
+use chrono::Utc;
+use common_events::{topics, CustomerErasedEvent, DomainEvent};
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use customer_service::outbox;
+use serde_json::Value;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn erasures_are_staged_with_their_transaction() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["customer-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let evt = |customer_id| CustomerErasedEvent {
+        schema_version: CustomerErasedEvent::SCHEMA_VERSION,
+        tenant_id: Uuid::new_v4(),
+        customer_id,
+        tombstone_id: Uuid::new_v4(),
+        erased_at: Utc::now(),
+    };
+    let (kept, rolled_back) = (evt(Uuid::new_v4()), evt(Uuid::new_v4()));
+
+    let mut tx = db.begin().await.unwrap();
+    outbox::stage(&mut tx, kept.tenant_id, &kept).await.unwrap();
+    tx.commit().await.unwrap();
+    let mut tx = db.begin().await.unwrap();
+    outbox::stage(&mut tx, rolled_back.tenant_id, &rolled_back).await.unwrap();
+    tx.rollback().await.unwrap();
+
+    let rows: Vec<(String, String, Option<String>, Value)> =
+        sqlx::query_as("SELECT tenant_id, topic, message_key, payload FROM outbox WHERE topic = $1")
+            .bind(topics::CUSTOMER_ERASED)
+            .fetch_all(db)
+            .await
+            .unwrap();
+    assert_eq!(rows.len(), 1);
+    let (tenant_id, topic, key, payload) = &rows[0];
+    assert_eq!((tenant_id.as_str(), topic.as_str()), (kept.tenant_id.to_string().as_str(), CustomerErasedEvent::TOPIC));
+    assert_eq!(key.as_deref(), Some(kept.customer_id.to_string().as_str()));
+    assert_eq!(serde_json::from_value::<CustomerErasedEvent>(payload.clone()).unwrap(), kept);
+}
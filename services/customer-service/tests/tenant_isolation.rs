@@ -0,0 +1,82 @@
+//! Row-level security against Postgres: a tenant transaction sees and changes only its own rows,
+//! even for statements with no tenant predicate, a session without a tenant sees nothing, and only
+//! the cross-tenant role spans tenants. Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL
+//! to skip the container).
+
+use common_db::{cross_tenant_options, TenantScopedPool, TENANT_ROLE};
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use uuid::Uuid;
+
+async fn insert_customer(pool: &sqlx::PgPool, tenant: Uuid, name: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO customers (id, tenant_id, name) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(tenant)
+        .bind(name)
+        .execute(pool)
+        .await
+        .unwrap();
+    id
+}
+
+#[tokio::test]
+async fn tenant_transactions_cannot_reach_other_tenants() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["customer-service"]).await.expect("migrate");
+    let (tenant_a, tenant_b) = (Uuid::new_v4(), Uuid::new_v4());
+    let ada = insert_customer(postgres.pool(), tenant_a, "Ada").await;
+    let bob = insert_customer(postgres.pool(), tenant_b, "Bob").await;
+    let db = TenantScopedPool::new(postgres.pool().clone());
+
+    let mut tx = db.begin(tenant_a).await.unwrap();
+    let visible: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM customers WHERE id = ANY($1)")
+        .bind(vec![ada, bob])
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap();
+    assert_eq!(visible, vec![ada], "tenant A reads only its own customer");
+    let updated = sqlx::query("UPDATE customers SET name = 'Mallory' WHERE id = $1")
+        .bind(bob)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    assert_eq!(updated.rows_affected(), 0, "tenant A cannot update tenant B's customer");
+    tx.rollback().await.unwrap();
+
+    let mut tx = db.begin(tenant_a).await.unwrap();
+    let insert = sqlx::query("INSERT INTO customers (id, tenant_id, name) VALUES ($1, $2, 'Eve')")
+        .bind(Uuid::new_v4())
+        .bind(tenant_b)
+        .execute(&mut *tx)
+        .await;
+    assert!(insert.is_err(), "tenant A cannot write a row for tenant B");
+    tx.rollback().await.unwrap();
+
+    // The tenant role without app.tenant_id: the policies no longer treat that as "everyone".
+    let mut conn = postgres.pool().acquire().await.unwrap();
+    let mut tx = conn.begin().await.unwrap();
+    sqlx::query("SELECT set_config('role', $1, true)").bind(TENANT_ROLE).execute(&mut *tx).await.unwrap();
+    let unscoped: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customers WHERE id = ANY($1)")
+        .bind(vec![ada, bob])
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+    assert_eq!(unscoped, 0, "a session without a tenant sees no rows");
+    tx.rollback().await.unwrap();
+
+    let options: PgConnectOptions = postgres.url().parse().unwrap();
+    let mut cross = cross_tenant_options(options).connect().await.unwrap();
+    let role: String = sqlx::query_scalar("SELECT current_user::text").fetch_one(&mut cross).await.unwrap();
+    assert_eq!(role, common_db::CROSS_TENANT_ROLE);
+    let spanning: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM customers WHERE id = ANY($1)")
+        .bind(vec![ada, bob])
+        .fetch_one(&mut cross)
+        .await
+        .unwrap();
+    assert_eq!(spanning, 2, "the cross-tenant role sees every tenant");
+}
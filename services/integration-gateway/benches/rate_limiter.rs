@@ -0,0 +1,43 @@
+//! Decision latency per rate limit algorithm. The in-memory engine always runs; set
+//! `RATE_LIMIT_BENCH_REDIS_URL` to also time the Redis Lua scripts (one round trip each).
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use integration_gateway::rate_limiter::{InMemoryRateLimiter, RateAlgorithm, RateLimiterEngine, RatePolicy, RedisRateLimiter};
+
+const ALGORITHMS: [RateAlgorithm; 4] =
+    [RateAlgorithm::FixedWindow, RateAlgorithm::SlidingWindow, RateAlgorithm::SlidingLog, RateAlgorithm::TokenBucket];
+
+fn policy(algorithm: RateAlgorithm) -> RatePolicy {
+    RatePolicy { algorithm, limit: 600, burst: 900 }
+}
+
+fn bench_in_memory(c: &mut Criterion) {
+    let limiter = InMemoryRateLimiter::new(60);
+    for algorithm in ALGORITHMS {
+        let policy = policy(algorithm);
+        let mut now = 0u64;
+        c.bench_function(&format!("rate_limit_memory_{}", algorithm.as_str()), |b| {
+            b.iter(|| {
+                now += 7;
+                black_box(limiter.check_at("bench", &policy, now))
+            })
+        });
+    }
+}
+
+fn bench_redis(c: &mut Criterion) {
+    let Ok(url) = std::env::var("RATE_LIMIT_BENCH_REDIS_URL") else {
+        return;
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let limiter = runtime.block_on(RedisRateLimiter::new(&url, 60, "rate-limit-bench".into())).expect("redis");
+    for algorithm in ALGORITHMS {
+        let policy = policy(algorithm);
+        let key = format!("bench:{}", algorithm.as_str());
+        c.bench_function(&format!("rate_limit_redis_{}", algorithm.as_str()), |b| {
+            b.iter(|| black_box(runtime.block_on(limiter.check(&key, &policy)).unwrap()))
+        });
+    }
+}
+
+criterion_group!(benches, bench_in_memory, bench_redis);
+criterion_main!(benches);
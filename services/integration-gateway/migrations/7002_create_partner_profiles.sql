@@ -0,0 +1,13 @@
+-- 7002: partner profiles map an external system's order payload (Shopify, WooCommerce, custom)
+-- onto POST /external/order for one integration key. `mappings` NULL means the partner preset.
+
+CREATE TABLE IF NOT EXISTS partner_profiles (
+    integration_key_id UUID PRIMARY KEY REFERENCES integration_keys(id) ON DELETE CASCADE,
+    tenant_id          UUID NOT NULL,
+    api_key_hash       TEXT NOT NULL UNIQUE,
+    partner            TEXT NOT NULL CHECK (partner IN ('native','shopify','woocommerce','custom')),
+    mappings           JSONB,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_partner_profiles_tenant ON partner_profiles(tenant_id);
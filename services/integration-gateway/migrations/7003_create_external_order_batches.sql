@@ -0,0 +1,32 @@
+-- 7003: bulk external order ingestion. Items double as the work queue for the gateway's batch
+-- worker; `payload` is the mapped order, `errors` the per-field errors of rejected orders.
+
+CREATE TABLE IF NOT EXISTS external_order_batches (
+    id           UUID PRIMARY KEY,
+    tenant_id    UUID NOT NULL,
+    partner      TEXT NOT NULL,
+    item_count   INT NOT NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_external_order_batches_tenant ON external_order_batches(tenant_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS external_order_batch_items (
+    batch_id     UUID NOT NULL REFERENCES external_order_batches(id) ON DELETE CASCADE,
+    seq          INT NOT NULL,
+    tenant_id    UUID NOT NULL,
+    status       TEXT NOT NULL CHECK (status IN ('pending','processing','created','failed','invalid')),
+    payload      JSONB,
+    errors       JSONB,
+    error        TEXT,
+    order_id     UUID,
+    attempts     INT NOT NULL DEFAULT 0,
+    available_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    attempted_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    PRIMARY KEY (batch_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_external_order_batch_items_queue
+    ON external_order_batch_items(available_at) WHERE status IN ('pending','processing');
@@ -0,0 +1,35 @@
+-- 7004: opt-in request/response capture per integration key, for support to debug partner
+-- integrations. Payloads are stored after masking and expire after the key's retention.
+
+CREATE TABLE IF NOT EXISTS payload_capture_settings (
+    integration_key_id UUID PRIMARY KEY REFERENCES integration_keys(id) ON DELETE CASCADE,
+    tenant_id          UUID NOT NULL,
+    api_key_hash       TEXT NOT NULL UNIQUE,
+    enabled            BOOLEAN NOT NULL DEFAULT TRUE,
+    mask_paths         JSONB NOT NULL DEFAULT '[]'::jsonb,
+    retention_hours    INT NOT NULL DEFAULT 24 CHECK (retention_hours BETWEEN 1 AND 72),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_payload_capture_settings_tenant ON payload_capture_settings(tenant_id);
+
+-- `*_body` is the masked JSON payload; it is NULL when the body was empty or not stored, in
+-- which case `*_omitted` says why (`too_large`, `not_json`).
+CREATE TABLE IF NOT EXISTS captured_exchanges (
+    id                 UUID PRIMARY KEY,
+    tenant_id          UUID NOT NULL,
+    integration_key_id UUID NOT NULL REFERENCES integration_keys(id) ON DELETE CASCADE,
+    method             TEXT NOT NULL,
+    path               TEXT NOT NULL,
+    status             INT NOT NULL,
+    duration_ms        INT NOT NULL,
+    request_body       JSONB,
+    request_omitted    TEXT,
+    response_body      JSONB,
+    response_omitted   TEXT,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at         TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_captured_exchanges_key ON captured_exchanges(integration_key_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_captured_exchanges_expires ON captured_exchanges(expires_at);
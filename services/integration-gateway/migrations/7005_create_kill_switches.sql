@@ -0,0 +1,25 @@
+-- 7005: gateway kill switches. `maintenance` takes the whole partner API down; the others
+-- disable one group of routes. Every gateway instance polls this table, so a change applies
+-- everywhere within KILL_SWITCH_REFRESH_SECONDS. Rows are only written through
+-- PUT /admin/kill-switches/:name, which also appends to the change log below.
+
+CREATE TABLE IF NOT EXISTS gateway_kill_switches (
+    name             TEXT PRIMARY KEY CHECK (name IN ('maintenance','payments','external_orders','catalog','webhooks')),
+    enabled          BOOLEAN NOT NULL,
+    retry_after_secs INT NOT NULL DEFAULT 60 CHECK (retry_after_secs BETWEEN 1 AND 3600),
+    reason           TEXT,
+    updated_by       UUID,
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS gateway_kill_switch_changes (
+    id               UUID PRIMARY KEY,
+    name             TEXT NOT NULL,
+    enabled          BOOLEAN NOT NULL,
+    retry_after_secs INT NOT NULL,
+    reason           TEXT,
+    changed_by       UUID,
+    changed_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_gateway_kill_switch_changes_at ON gateway_kill_switch_changes(changed_at DESC);
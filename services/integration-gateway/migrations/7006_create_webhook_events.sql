@@ -0,0 +1,30 @@
+-- 7006: provider webhook deliveries already handled, keyed by the provider's event id, so a
+-- retried or replayed delivery is acknowledged without being processed twice. A row is written in
+-- the same transaction as the events the delivery stages into the outbox. Rows older than the
+-- provider's freshness window (COINBASE_WEBHOOK_TOLERANCE_SECONDS) may be deleted: deliveries that
+-- old are rejected before this table is consulted.
+
+CREATE TABLE IF NOT EXISTS webhook_events (
+    provider    TEXT NOT NULL,
+    event_id    TEXT NOT NULL,
+    event_type  TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (provider, event_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_events_received ON webhook_events (received_at);
+
+-- The shared outbox order-service's relay publishes; created here only when the gateway is
+-- migrated on its own.
+CREATE TABLE IF NOT EXISTS outbox (
+  id BIGSERIAL PRIMARY KEY,
+  tenant_id TEXT NOT NULL,
+  topic TEXT NOT NULL,
+  payload JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  published_at TIMESTAMPTZ,
+  retry_count INT NOT NULL DEFAULT 0
+);
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_outbox_unpublished ON outbox (published_at) WHERE published_at IS NULL;
@@ -1,10 +1,9 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use reqwest::Client;
 use serde::Serialize;
 use serde_json::json;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration;
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -34,7 +33,7 @@ pub async fn publish_rate_limit_alert(producer: &FutureProducer, topic: &str, ev
             return Ok(());
         }
     }
-    producer.send(FutureRecord::to(topic).payload(&payload).key(&key), Duration::from_secs(0)).await.map_err(|(err, _)| anyhow!("Failed to publish rate limit alert: {err}"))?;
+    common_kafka::publish(producer, topic, &key, &payload).await.map_err(|err| anyhow!("Failed to publish rate limit alert: {err}"))?;
     Ok(())
 }
 
@@ -4,12 +4,16 @@ use std::time::Instant;
 use chrono::Utc;
 use uuid::Uuid;
 use crate::metrics::GatewayMetrics;
-use crate::rate_limiter::RateLimiterEngine;
+use crate::redis_fallback::FallbackRateLimiter;
+use crate::response_cache::ResponseCache;
 #[cfg(any(test, feature = "kafka", feature = "kafka-producer"))] use crate::rate_limiter::InMemoryRateLimiter;
 use crate::usage::UsageTracker;
 use crate::config::GatewayConfig;
+use crate::kill_switches::KillSwitches;
+use crate::payment_normalization::PaymentAdapterRegistry;
 use common_auth::JwtVerifier;
 use reqwest::Client;
+use sqlx::PgPool;
 use tracing::{warn};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use crate::alerts::{post_alert_webhook, RateLimitAlertEvent};
@@ -18,7 +22,7 @@ use crate::alerts::{post_alert_webhook, RateLimitAlertEvent};
 #[derive(Clone)]
 pub struct AppState {
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] pub kafka_producer: FutureProducer,
-    pub rate_limiter: Arc<dyn RateLimiterEngine>,
+    pub rate_limiter: Arc<FallbackRateLimiter>,
     pub key_cache: Arc<tokio::sync::RwLock<HashMap<String, CachedKey>>>,
     pub jwt_verifier: Arc<JwtVerifier>,
     pub metrics: Arc<GatewayMetrics>,
@@ -26,6 +30,16 @@ pub struct AppState {
     pub config: Arc<GatewayConfig>,
     pub http_client: Client,
     pub alert_state: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Wakes the key refresh loop for an immediate reload from the database.
+    pub key_reload: Arc<tokio::sync::Notify>,
+    /// Shared database (partner profiles). `None` in handler tests, which then use the native payload shape.
+    pub db: Option<PgPool>,
+    /// Cache for proxied catalog reads; `None` when `RESPONSE_CACHE_ENABLED` is off.
+    pub response_cache: Option<ResponseCache>,
+    /// Enabled kill switches, refreshed from the database.
+    pub kill_switches: KillSwitches,
+    /// Payment webhook adapters by provider, with their secrets.
+    pub payment_adapters: Arc<PaymentAdapterRegistry>,
 }
 
 #[derive(Clone)]
@@ -44,14 +58,24 @@ impl AppState {
     pub fn test_with_in_memory(rate_window_secs: u64, config: Arc<GatewayConfig>, metrics: Arc<GatewayMetrics>, usage: UsageTracker, jwt_verifier: Arc<JwtVerifier>) -> Self {
         AppState {
             #[cfg(any(feature = "kafka", feature = "kafka-producer"))] kafka_producer: rdkafka::ClientConfig::new().set("bootstrap.servers","localhost:9092").create().expect("test producer"),
-            rate_limiter: Arc::new(InMemoryRateLimiter::new(rate_window_secs)),
+            rate_limiter: Arc::new(FallbackRateLimiter::new(
+                Arc::new(InMemoryRateLimiter::new(rate_window_secs)),
+                config.redis_degradation.clone(),
+                rate_window_secs,
+                metrics.clone(),
+            )),
             key_cache: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
             jwt_verifier,
             metrics,
             usage,
-            config,
             http_client: reqwest::Client::new(),
             alert_state: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            key_reload: Arc::new(tokio::sync::Notify::new()),
+            db: None,
+            response_cache: None,
+            kill_switches: KillSwitches::default(),
+            payment_adapters: Arc::new(PaymentAdapterRegistry::from_config(&config)),
+            config,
         }
     }
 
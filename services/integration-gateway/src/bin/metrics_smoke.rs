@@ -21,6 +21,9 @@ fn main() {
         actor: AuditActor { id: Some(Uuid::new_v4()), name: None, email: None },
         roles: vec![Role::Cashier],
         trace_id: None,
+        residency: None,
+        capability_cache: Default::default(),
+        system: None,
     };
     // Cashier: allowed for PaymentProcess, denied for CustomerWrite (by design)
     let _ = ensure_capability(&dummy_ctx, Capability::PaymentProcess);
@@ -0,0 +1,181 @@
+//! Read-only catalog proxy: `GET /catalog/products[...]` is forwarded to product-service and, when
+//! the response cache is enabled, served from it for up to the configured TTL.
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::Response;
+use axum::{Extension, Json};
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{SecurityContext, SecurityCtxExtractor};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::integration_handlers::ForwardedAuthHeader;
+use crate::key_admin_handlers::ensure_internal_token;
+use crate::response_cache::{cache_key, CachedResponse};
+use crate::AppState;
+
+/// Product-service read paths the gateway exposes under `/catalog`.
+fn upstream_path(path: &str) -> Option<String> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["products"] | ["products", "lookup"] | ["products", "sync"] => Some(format!("/{}", segments.join("/"))),
+        ["products", id] if Uuid::parse_str(id).is_ok() => Some(format!("/products/{id}")),
+        _ => None,
+    }
+}
+
+/// Upstream responses that say they must not be shared are never stored.
+fn cacheable(status: u16, cache_control: Option<&str>) -> bool {
+    let cache_control = cache_control.unwrap_or_default().to_ascii_lowercase();
+    status == 200 && !cache_control.contains("no-store") && !cache_control.contains("private")
+}
+
+fn build_response(cached: &CachedResponse, cache_result: &'static str) -> ApiResult<Response> {
+    let mut builder = Response::builder()
+        .status(StatusCode::from_u16(cached.status).unwrap_or(StatusCode::OK))
+        .header("X-Cache", cache_result);
+    if let Some(content_type) = &cached.content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+    if let Some(etag) = &cached.etag {
+        builder = builder.header(header::ETAG, etag);
+    }
+    builder
+        .body(Body::from(cached.body.clone()))
+        .map_err(|err| ApiError::Internal { trace_id: None, message: Some(err.to_string()) })
+}
+
+async fn fetch_upstream(
+    state: &AppState,
+    sec: &SecurityContext,
+    roles: &str,
+    forwarded_auth: Option<&ForwardedAuthHeader>,
+    target: &str,
+) -> ApiResult<(CachedResponse, Option<String>)> {
+    let base = std::env::var("PRODUCT_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
+    let mut request = state
+        .http_client
+        .get(format!("{base}{target}"))
+        .header("X-Tenant-ID", sec.tenant_id.to_string())
+        .header("X-Roles", roles);
+    if let Some(user_id) = sec.actor.id {
+        request = request.header("X-User-ID", user_id.to_string());
+    }
+    if let Some(ForwardedAuthHeader(auth)) = forwarded_auth {
+        request = request.header(header::AUTHORIZATION, auth);
+    }
+    let resp = request.send().await.map_err(|err| {
+        tracing::warn!(?err, "Product service request failed");
+        ApiError::Internal { trace_id: sec.trace_id, message: Some("Product service unavailable".into()) }
+    })?;
+    let header_str = |name: header::HeaderName| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let content_type = header_str(header::CONTENT_TYPE);
+    let etag = header_str(header::ETAG);
+    let cache_control = header_str(header::CACHE_CONTROL);
+    let status = resp.status().as_u16();
+    let body = resp.text().await.map_err(|err| ApiError::Internal {
+        trace_id: sec.trace_id,
+        message: Some(format!("Invalid response from Product Service: {err}")),
+    })?;
+    Ok((CachedResponse { status, content_type, etag, body }, cache_control))
+}
+
+/// `GET /catalog/*path`. Responses carry `X-Cache: HIT|MISS|BYPASS`; clients can skip the cache
+/// with `Cache-Control: no-cache`. Upstream errors pass through and are never cached.
+pub async fn proxy_catalog_read(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    forwarded_auth: Option<Extension<ForwardedAuthHeader>>,
+    Path(path): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let upstream = upstream_path(&path).ok_or(ApiError::NotFound { code: "catalog_route_not_found", trace_id: sec.trace_id })?;
+    let target = match uri.query() {
+        Some(query) => format!("{upstream}?{query}"),
+        None => upstream.clone(),
+    };
+    let roles = headers.get("X-Roles").and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+    let forwarded_auth = forwarded_auth.as_ref().map(|Extension(auth)| auth);
+
+    let no_cache = headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase().contains("no-cache"))
+        .unwrap_or(false);
+    let Some(cache) = state.response_cache.clone().filter(|_| !no_cache) else {
+        state.metrics.record_response_cache("bypass");
+        let (fetched, _) = fetch_upstream(&state, &sec, &roles, forwarded_auth, &target).await?;
+        return build_response(&fetched, "BYPASS");
+    };
+
+    let key = cache_key(&roles, &upstream, uri.query());
+    match cache.engine.get(sec.tenant_id, &key).await {
+        Ok(Some(hit)) => {
+            state.metrics.record_response_cache("hit");
+            return build_response(&hit, "HIT");
+        }
+        Ok(None) => state.metrics.record_response_cache("miss"),
+        Err(err) => {
+            // A cache outage degrades to plain proxying.
+            tracing::warn!(?err, "Response cache read failed");
+            state.metrics.record_response_cache("error");
+        }
+    }
+    let (fetched, cache_control) = fetch_upstream(&state, &sec, &roles, forwarded_auth, &target).await?;
+    if cacheable(fetched.status, cache_control.as_deref()) {
+        if let Err(err) = cache.engine.put(sec.tenant_id, &key, &fetched, cache.ttl).await {
+            tracing::warn!(?err, "Response cache write failed");
+            state.metrics.record_response_cache("error");
+        }
+    }
+    build_response(&fetched, "MISS")
+}
+
+#[derive(Deserialize)]
+pub struct InvalidateCacheRequest {
+    pub tenant_id: Uuid,
+}
+
+/// `POST /internal/response-cache/invalidate` for services without a Kafka path.
+pub async fn invalidate_response_cache(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<InvalidateCacheRequest>,
+) -> ApiResult<StatusCode> {
+    ensure_internal_token(&state, &headers)?;
+    let Some(cache) = state.response_cache.as_ref() else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+    cache.engine.invalidate_tenant(req.tenant_id).await.map_err(|err| {
+        tracing::warn!(?err, tenant_id = %req.tenant_id, "Failed to invalidate cached responses");
+        ApiError::Internal { trace_id: None, message: Some("Response cache unavailable".into()) }
+    })?;
+    state.metrics.record_response_cache_invalidation("internal");
+    tracing::info!(tenant_id = %req.tenant_id, "Invalidated cached responses");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_catalog_reads_are_proxied_and_cached() {
+        let id = Uuid::new_v4();
+        assert_eq!(upstream_path("products").as_deref(), Some("/products"));
+        assert_eq!(upstream_path("/products/lookup").as_deref(), Some("/products/lookup"));
+        assert_eq!(upstream_path("products/sync").as_deref(), Some("/products/sync"));
+        assert_eq!(upstream_path(&format!("products/{id}")), Some(format!("/products/{id}")));
+        assert_eq!(upstream_path(&format!("products/{id}/audit")), None);
+        assert_eq!(upstream_path("audit/events"), None);
+
+        assert!(cacheable(200, Some("max-age=30")));
+        assert!(cacheable(200, None));
+        assert!(!cacheable(404, None));
+        assert!(!cacheable(200, Some("private, max-age=0")));
+        assert!(!cacheable(200, Some("no-store")));
+    }
+}
@@ -1,10 +1,24 @@
-use anyhow::{Context, Result};
-use std::env;
+use common_config::env::redact;
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+use crate::rate_limiter::{parse_pairs, PlanLimit, RateAlgorithm, RateLimitPolicies};
+use crate::redis_fallback::{RedisDegradation, RedisDownMode, ROUTE_CLASSES};
+use crate::payment_normalization::WebhookSettings;
+
+/// Key classes the gateway limits separately: JWT callers, API keys and bare tenant headers.
+pub const RATE_LIMIT_CLASSES: &[&str] = &["jwt", "api", "tenant_header"];
+
+#[derive(Debug, Clone, Serialize)]
 pub struct GatewayConfig {
     pub rate_limit_rpm: u32,
     pub rate_limit_window_secs: u64,
+    #[serde(serialize_with = "redact")]
     pub redis_url: String,
     pub redis_prefix: String,
     pub api_usage_flush_secs: u64,
@@ -13,55 +27,63 @@ pub struct GatewayConfig {
     pub alert_topic: String,
     pub rate_limit_burst_multiplier: f64,
     pub rate_limit_alert_cooldown_secs: u64,
+    /// Algorithm per key class and limits per tenant plan; see [`RateLimitPolicies`].
+    pub rate_limit_policies: RateLimitPolicies,
+    /// What rate limiting does while Redis is unreachable; see [`crate::redis_fallback`].
+    pub redis_degradation: RedisDegradation,
+    /// Coinbase Commerce webhook secret and freshness window; see [`crate::webhook_handlers`].
+    pub coinbase_webhook: WebhookSettings,
     pub security_alert_webhook_url: Option<String>,
+    #[serde(serialize_with = "redact")]
     pub security_alert_webhook_bearer: Option<String>,
     /// Optional fallback Authorization header value to use when forwarding
     /// payment/void requests to the payment-service and the original caller
     /// authenticated via API key (no bearer token/JWT present).
     /// Example expected format: "Bearer <token>".
+    #[serde(serialize_with = "redact")]
     pub payment_service_fallback_auth: Option<String>,
+    /// Shared secret required in `X-Internal-Token` for the internal key cache
+    /// endpoints. When unset those endpoints are disabled.
+    #[serde(serialize_with = "redact")]
+    pub internal_api_token: Option<String>,
+    /// Kafka topic carrying integration key created/revoked events from auth-service.
+    pub integration_key_topic: String,
 }
 
 impl GatewayConfig {
-    pub fn from_env() -> Result<Self> {
-        let redis_url = env::var("REDIS_URL").context("REDIS_URL must be set")?;
-        let rate_limit_rpm = env::var("GATEWAY_RATE_LIMIT_RPM")
-            .ok()
-            .and_then(|value| value.parse::<u32>().ok())
-            .unwrap_or(60);
-        let rate_limit_window_secs = env::var("GATEWAY_RATE_LIMIT_WINDOW_SECONDS")
-            .ok()
-            .and_then(|value| value.parse::<u64>().ok())
-            .unwrap_or(60);
-        let redis_prefix = env::var("GATEWAY_RATE_LIMIT_PREFIX")
-            .unwrap_or_else(|_| "integration-gateway:rate".to_string());
-        let api_usage_flush_secs = env::var("API_KEY_USAGE_FLUSH_SECONDS")
-            .ok()
-            .and_then(|value| value.parse::<u64>().ok())
-            .unwrap_or(300);
-        let api_usage_summary_secs = env::var("API_KEY_USAGE_SUMMARY_SECONDS")
-            .ok()
-            .and_then(|value| value.parse::<u64>().ok())
-            .unwrap_or(3600);
-        let audit_topic = env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events.v1".to_string());
-        let alert_topic =
-            env::var("SECURITY_ALERT_TOPIC").unwrap_or_else(|_| "security.alerts.v1".to_string());
-        let rate_limit_burst_multiplier = env::var("GATEWAY_RATE_LIMIT_ALERT_MULTIPLIER")
-            .ok()
-            .and_then(|v| v.parse::<f64>().ok())
-            .unwrap_or(3.0);
-        let rate_limit_alert_cooldown_secs = env::var("GATEWAY_RATE_LIMIT_ALERT_COOLDOWN_SECONDS")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(300);
-        let security_alert_webhook_url = env::var("SECURITY_ALERT_WEBHOOK_URL").ok();
-        let security_alert_webhook_bearer = env::var("SECURITY_ALERT_WEBHOOK_BEARER").ok();
-    let payment_service_fallback_auth = env::var("PAYMENT_SERVICE_FALLBACK_AUTH").ok();
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let config = Self::read(&mut env).await;
+        env.finish(|| config)
+    }
 
-        Ok(Self {
+    async fn read(env: &mut EnvReader) -> Option<Self> {
+        let redis_url = env.required_secret("REDIS_URL").await;
+        let rate_limit_rpm = env.or("GATEWAY_RATE_LIMIT_RPM", 60);
+        let rate_limit_window_secs: u64 = env.or("GATEWAY_RATE_LIMIT_WINDOW_SECONDS", 60);
+        let redis_prefix = env.or("GATEWAY_RATE_LIMIT_PREFIX", "integration-gateway:rate".to_string());
+        let api_usage_flush_secs: u64 = env.or("API_KEY_USAGE_FLUSH_SECONDS", 300);
+        let api_usage_summary_secs: u64 = env.or("API_KEY_USAGE_SUMMARY_SECONDS", 3600);
+        let audit_topic = env.or("AUDIT_TOPIC", "audit.events.v1".to_string());
+        let alert_topic = env.or("SECURITY_ALERT_TOPIC", "security.alerts.v1".to_string());
+        let rate_limit_burst_multiplier = env.or("GATEWAY_RATE_LIMIT_ALERT_MULTIPLIER", 3.0);
+        let rate_limit_alert_cooldown_secs: u64 = env.or("GATEWAY_RATE_LIMIT_ALERT_COOLDOWN_SECONDS", 300);
+        let rate_limit_policies = read_rate_limit_policies(env);
+        let redis_degradation = read_redis_degradation(env);
+        let coinbase_secret = env.secret("COINBASE_WEBHOOK_SECRET").await;
+        let coinbase_tolerance_secs: u64 =
+            env.or("COINBASE_WEBHOOK_TOLERANCE_SECONDS", WebhookSettings::default().tolerance_secs);
+        let security_alert_webhook_url = env.optional("SECURITY_ALERT_WEBHOOK_URL");
+        let security_alert_webhook_bearer = env.secret("SECURITY_ALERT_WEBHOOK_BEARER").await;
+        let payment_service_fallback_auth = env.secret("PAYMENT_SERVICE_FALLBACK_AUTH").await;
+        let internal_api_token = env.secret("GATEWAY_INTERNAL_TOKEN").await;
+        let integration_key_topic =
+            env.or("INTEGRATION_KEY_EVENTS_TOPIC", "security.integration_keys.v1".to_string());
+
+        Some(Self {
             rate_limit_rpm,
             rate_limit_window_secs: rate_limit_window_secs.max(1),
-            redis_url,
+            redis_url: redis_url?.expose().clone(),
             redis_prefix,
             api_usage_flush_secs: api_usage_flush_secs.max(60),
             api_usage_summary_secs: api_usage_summary_secs.max(300),
@@ -69,9 +91,125 @@ impl GatewayConfig {
             alert_topic,
             rate_limit_burst_multiplier,
             rate_limit_alert_cooldown_secs: rate_limit_alert_cooldown_secs.max(60),
+            rate_limit_policies,
+            redis_degradation,
+            coinbase_webhook: WebhookSettings {
+                secret: coinbase_secret.map(|s| s.expose().clone()),
+                tolerance_secs: coinbase_tolerance_secs.max(60),
+            },
             security_alert_webhook_url,
-            security_alert_webhook_bearer,
-            payment_service_fallback_auth,
+            security_alert_webhook_bearer: security_alert_webhook_bearer.map(|s| s.expose().clone()),
+            payment_service_fallback_auth: payment_service_fallback_auth.map(|s| s.expose().clone()),
+            internal_api_token: internal_api_token.map(|s| s.expose().clone()),
+            integration_key_topic,
+        })
+    }
+}
+
+/// `name=value,...` settings; a malformed list is reported and ignored.
+fn pairs<K: FromStr, V: FromStr>(env: &mut EnvReader, key: &str) -> Vec<(K, V)>
+where
+    V::Err: Display,
+{
+    let Some(raw) = env.optional::<String>(key) else {
+        return Vec::new();
+    };
+    parse_pairs(&raw).unwrap_or_else(|problem| {
+        env.invalid(key, problem);
+        Vec::new()
+    })
+}
+
+fn read_rate_limit_policies(env: &mut EnvReader) -> RateLimitPolicies {
+    let algorithm: RateAlgorithm = env.or("GATEWAY_RATE_LIMIT_ALGORITHM", RateAlgorithm::FixedWindow);
+    let burst: Option<u32> = env.optional("GATEWAY_RATE_LIMIT_BURST");
+    env.check("GATEWAY_RATE_LIMIT_BURST", burst != Some(0), "must be at least 1");
+    let classes: Vec<(String, RateAlgorithm)> = pairs(env, "GATEWAY_RATE_LIMIT_CLASS_ALGORITHMS");
+    for (class, _) in &classes {
+        if !RATE_LIMIT_CLASSES.contains(&class.as_str()) {
+            env.invalid("GATEWAY_RATE_LIMIT_CLASS_ALGORITHMS", format!("unknown key class `{class}`; expected jwt, api or tenant_header"));
+        }
+    }
+    let plans: BTreeMap<String, PlanLimit> = pairs(env, "GATEWAY_RATE_LIMIT_PLANS").into_iter().collect();
+    let tenant_plans: Vec<(Uuid, String)> = pairs(env, "GATEWAY_RATE_LIMIT_TENANT_PLANS");
+    for (tenant, plan) in &tenant_plans {
+        if !plans.contains_key(plan) {
+            env.invalid("GATEWAY_RATE_LIMIT_TENANT_PLANS", format!("tenant {tenant} is on plan `{plan}`, which GATEWAY_RATE_LIMIT_PLANS does not define"));
+        }
+    }
+    RateLimitPolicies {
+        algorithm,
+        burst,
+        classes: classes.into_iter().collect(),
+        plans,
+        tenant_plans: tenant_plans.into_iter().collect(),
+    }
+}
+
+fn read_redis_degradation(env: &mut EnvReader) -> RedisDegradation {
+    let defaults = RedisDegradation::default();
+    let routes: Vec<(String, RedisDownMode)> = pairs(env, "GATEWAY_REDIS_DOWN_ROUTES");
+    for (class, _) in &routes {
+        if !ROUTE_CLASSES.contains(&class.as_str()) {
+            env.invalid("GATEWAY_REDIS_DOWN_ROUTES", format!("unknown route class `{class}`; expected one of {}", ROUTE_CLASSES.join(", ")));
+        }
+    }
+    let fallback_rpm: Option<u32> = env.optional("GATEWAY_RATE_LIMIT_FALLBACK_RPM");
+    env.check("GATEWAY_RATE_LIMIT_FALLBACK_RPM", fallback_rpm != Some(0), "must be at least 1");
+    RedisDegradation {
+        mode: env.or("GATEWAY_REDIS_DOWN_MODE", defaults.mode),
+        routes: routes.into_iter().collect(),
+        timeout_ms: env.or::<u64>("GATEWAY_REDIS_TIMEOUT_MS", defaults.timeout_ms).max(10),
+        circuit_failure_threshold: env.or::<u32>("GATEWAY_REDIS_CIRCUIT_FAILURE_THRESHOLD", defaults.circuit_failure_threshold).max(1),
+        circuit_open_secs: env.or::<u64>("GATEWAY_REDIS_CIRCUIT_OPEN_SECONDS", defaults.circuit_open_secs).max(1),
+        fallback_rpm,
+        fallback_max_keys: env.or::<usize>("GATEWAY_RATE_LIMIT_FALLBACK_MAX_KEYS", defaults.fallback_max_keys).max(100),
+    }
+}
+
+/// Everything `main` reads at startup: the listener, database, Kafka and JWT settings around the
+/// [`GatewayConfig`] shared with handlers.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    pub jwt: JwtSettings,
+    /// `KEY_REFRESH_SECONDS`, at least 10.
+    pub key_refresh_seconds: u64,
+    /// `GATEWAY_DEV_METRICS_DEMO`; always on in debug builds.
+    pub dev_metrics_demo: bool,
+    /// `KILL_SWITCH_REFRESH_SECONDS`, at least 1: how quickly other instances pick up a switch change.
+    pub kill_switch_refresh_seconds: u64,
+    pub gateway: GatewayConfig,
+}
+
+impl StartupConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8083);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let jwt = JwtSettings::read(&mut env).await;
+        let key_refresh_seconds: u64 = env.or("KEY_REFRESH_SECONDS", 60);
+        let dev_metrics_demo = env.flag("GATEWAY_DEV_METRICS_DEMO", false);
+        let kill_switch_refresh_seconds: u64 = env.or("KILL_SWITCH_REFRESH_SECONDS", 5);
+        let gateway = GatewayConfig::read(&mut env).await;
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                jwt: jwt?,
+                key_refresh_seconds: key_refresh_seconds.max(10),
+                dev_metrics_demo,
+                kill_switch_refresh_seconds: kill_switch_refresh_seconds.max(1),
+                gateway: gateway?,
+            })
         })
     }
 }
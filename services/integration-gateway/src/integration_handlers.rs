@@ -1,7 +1,9 @@
-use axum::extract::{Extension, State};
+use axum::extract::{Extension, Query, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use common_http_errors::{ApiError, ApiResult};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -14,13 +16,18 @@ use prometheus::IntCounter;
 #[allow(unused_imports)]
 use crate::{
     events::{PaymentCompletedEvent, PaymentVoidedEvent},
+    partner_profiles::{load_profile_for_key, map_order, FieldError, FieldMappings, PartnerKind},
     AppState,
 };
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use crate::events::PaymentFailedEvent;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use crate::events::{topics, DomainEvent, PaymentFailedEvent};
 
 #[derive(Clone)]
 pub struct ForwardedAuthHeader(pub String);
 
+/// Hash of the integration key that authenticated the request (API key callers only).
+#[derive(Clone)]
+pub struct IntegrationKeyHash(pub String);
+
 // Payment request/response types
 #[derive(Deserialize, Serialize)]
 pub struct PaymentRequest {
@@ -28,6 +35,9 @@ pub struct PaymentRequest {
     pub order_id: String,
     pub method: String,
     pub amount: f64,
+    /// Forwarded to payment-service for card payments; it defaults to USD when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -47,11 +57,13 @@ pub struct PaymentResult {
     pub payment_url: Option<String>,
 }
 
-#[allow(dead_code)]
 #[derive(Deserialize)]
 struct PaymentServiceResponse {
     status: String,
     approval_code: String,
+    /// Set when the payment went through payment-service's stand-in queue.
+    #[serde(rename = "standInId", default)]
+    stand_in_id: Option<Uuid>,
 }
 
 use common_security::{SecurityCtxExtractor, ensure_capability, Capability};
@@ -90,19 +102,23 @@ pub async fn process_payment(
             #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
             {
                 let producer = state.kafka_producer.clone();
-                let tenant_key = tenant_id.to_string();
                 tokio::spawn(async move {
                     sleep(Duration::from_secs(5)).await;
-                    let completion = PaymentCompletedEvent { order_id, tenant_id, method: "crypto".into(), amount };
+                    let completion = PaymentCompletedEvent {
+                        schema_version: PaymentCompletedEvent::SCHEMA_VERSION,
+                        order_id,
+                        tenant_id,
+                        method: "crypto".into(),
+                        amount,
+                    };
                     if let Ok(payload) = serde_json::to_string(&completion) {
-                        if let Err(err) = producer
-                            .send(
-                                FutureRecord::to("payment.completed")
-                                    .payload(&payload)
-                                    .key(&tenant_key),
-                                Duration::from_secs(0),
-                            )
-                            .await
+                        if let Err(err) = common_kafka::publish(
+                            &producer,
+                            topics::PAYMENT_COMPLETED,
+                            &completion.partition_key(),
+                            &payload,
+                        )
+                        .await
                         {
                             tracing::error!(?err, order_id = %order_id, "Failed to emit stub payment.completed");
                         } else {
@@ -223,28 +239,40 @@ pub async fn process_payment(
             return Err(ApiError::Internal { trace_id: None, message: Some("Payment was declined".into()) });
         }
         // Optionally parse approval; ignore errors (non-fatal)
-        let _ = pay_resp.json::<PaymentServiceResponse>().await;
+        if let Ok(approval) = pay_resp.json::<PaymentServiceResponse>().await {
+            // Taken in stand-in during a provider outage: the order stays pending until
+            // payment-service settles it and emits payment.completed or payment.failed itself.
+            if approval.status == "conditionally_approved" {
+                tracing::warn!(order_id = %order_id, approval_code = %approval.approval_code, "Card payment conditionally approved in stand-in");
+                return Ok(Json(PaymentResult { status: approval.status, payment_url: None }));
+            }
+            // A retry of an order whose stand-in entry was already approved: payment-service
+            // published payment.completed when it replayed the queue.
+            if let Some(stand_in_id) = approval.stand_in_id {
+                tracing::info!(order_id = %order_id, %stand_in_id, "Card payment already settled through stand-in");
+                return Ok(Json(PaymentResult { status: "paid".into(), payment_url: None }));
+            }
+        }
     }
 
     // Emit completion event for non-crypto (immediate) or card payments
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     {
         let pay_event = PaymentCompletedEvent {
+            schema_version: PaymentCompletedEvent::SCHEMA_VERSION,
             order_id,
             tenant_id,
             method: req.method.clone(),
             amount: req.amount,
         };
         if let Ok(payload) = serde_json::to_string(&pay_event) {
-            if let Err(err) = state
-                .kafka_producer
-                .send(
-                    FutureRecord::to("payment.completed")
-                        .payload(&payload)
-                        .key(&tenant_id.to_string()),
-                    Duration::from_secs(0),
-                )
-                .await
+            if let Err(err) = common_kafka::publish(
+                &state.kafka_producer,
+                topics::PAYMENT_COMPLETED,
+                &pay_event.partition_key(),
+                &payload,
+            )
+            .await
             {
                 tracing::error!(?err, order_id = %order_id, "Failed to send payment.completed");
                 return Err(ApiError::Internal { trace_id: None, message: Some("Failed to notify payment completion".into()) });
@@ -264,6 +292,7 @@ async fn emit_payment_failed(
     reason: &str,
 ) {
     let event = PaymentFailedEvent {
+        schema_version: PaymentFailedEvent::SCHEMA_VERSION,
         order_id,
         tenant_id,
         method: method.to_string(),
@@ -276,14 +305,13 @@ async fn emit_payment_failed(
             return;
         }
     };
-    if let Err(err) = producer
-        .send(
-            FutureRecord::to("payment.failed")
-                .payload(&payload)
-                .key(&tenant_id.to_string()),
-            Duration::from_secs(0),
-        )
-        .await
+    if let Err(err) = common_kafka::publish(
+        producer,
+        topics::PAYMENT_FAILED,
+        &event.partition_key(),
+        &payload,
+    )
+    .await
     {
         tracing::error!(?err, order_id = %order_id, "Failed to send payment.failed");
     } else {
@@ -294,14 +322,17 @@ async fn emit_payment_failed(
 // When kafka features are disabled, emit_payment_failed is never invoked.
 
 /// Order payload from external systems (mirrors Order Service's NewOrder format)
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ExternalOrder {
     pub items: Vec<OrderItem>,
     pub payment_method: String,
     pub total: f64,
+    /// Set by the gateway for batch items so retried deliveries create one order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct OrderItem {
     pub product_id: Uuid,
     pub quantity: i32,
@@ -310,38 +341,150 @@ pub struct OrderItem {
 /// Response type matching Order Service's Order struct
 #[derive(Deserialize, Serialize)]
 pub struct Order {
-    id: Uuid,
+    pub id: Uuid,
     tenant_id: Uuid,
     total: f64,
     status: String,
 }
 
-pub async fn handle_external_order(
-    SecurityCtxExtractor(sec): SecurityCtxExtractor,
-    Json(order): Json<ExternalOrder>,
-) -> ApiResult<Json<Order>> {
-    let tenant_id = sec.tenant_id;
+#[derive(Deserialize, Default)]
+pub struct ExternalOrderParams {
+    /// Validate and map the payload without creating an order.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct ExternalOrderRejection<'a> {
+    code: &'static str,
+    message: String,
+    partner: &'a str,
+    errors: Vec<FieldError>,
+}
+
+#[derive(Serialize)]
+struct ExternalOrderDryRun<'a> {
+    dry_run: bool,
+    partner: &'a str,
+    order: ExternalOrder,
+}
+
+/// Accept an order from an external system. Payloads are mapped through the partner profile of
+/// the calling integration key (native shape otherwise); `?dry_run=true` returns the mapped order
+/// instead of creating it. Invalid payloads get 400 `invalid_external_order` with per-field errors.
+/// Partner profile and mappings for the calling integration key; JWT and tenant-header callers,
+/// and keys without a profile, use the native shape.
+pub(crate) async fn resolve_mappings(
+    state: &AppState,
+    tenant_id: Uuid,
+    integration_key: Option<&IntegrationKeyHash>,
+    trace_id: Option<Uuid>,
+) -> ApiResult<(PartnerKind, FieldMappings)> {
+    let profile = match (state.db.as_ref(), integration_key) {
+        (Some(pool), Some(key)) => load_profile_for_key(pool, tenant_id, &key.0).await.map_err(|err| {
+            tracing::error!(?err, tenant_id = %tenant_id, "Failed to load partner profile");
+            ApiError::Internal { trace_id, message: Some("Failed to load partner profile".into()) }
+        })?,
+        _ => None,
+    };
+    match profile {
+        Some(profile) => {
+            let mappings = profile.effective_mappings().ok_or_else(|| ApiError::Internal {
+                trace_id,
+                message: Some("Partner profile has no field mappings".into()),
+            })?;
+            Ok((profile.kind(), mappings))
+        }
+        None => Ok((PartnerKind::Native, FieldMappings::preset(PartnerKind::Native).expect("native preset"))),
+    }
+}
+
+/// Why order-service did not create an order. `retryable` covers transport errors and 5xx.
+#[derive(Debug)]
+pub(crate) struct ForwardError {
+    pub status: Option<StatusCode>,
+    pub message: String,
+}
+
+impl ForwardError {
+    pub fn retryable(&self) -> bool {
+        self.status.map(|s| s.is_server_error()).unwrap_or(true)
+    }
+}
+
+impl From<ForwardError> for ApiError {
+    fn from(err: ForwardError) -> Self {
+        match err.status {
+            Some(status) if status.is_client_error() => {
+                ApiError::BadRequest { code: "order_service_error", trace_id: None, message: Some(err.message) }
+            }
+            _ => ApiError::Internal { trace_id: None, message: Some(err.message) },
+        }
+    }
+}
+
+pub(crate) async fn forward_order(state: &AppState, tenant_id: Uuid, order: &ExternalOrder) -> Result<Order, ForwardError> {
     let order_svc_url =
         std::env::var("ORDER_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8084".to_string());
-    let client = Client::new();
-    let resp = client
+    let resp = state.http_client
         .post(format!("{}/orders", order_svc_url))
         .header("X-Tenant-ID", tenant_id.to_string())
-        .json(&order)
+        .json(order)
         .send()
         .await
-        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Order service request failed: {}", e)) })?;
+        .map_err(|e| ForwardError { status: None, message: format!("Order service request failed: {}", e) })?;
     if !resp.status().is_success() {
-        let code = if resp.status().is_client_error() {
-            ApiError::BadRequest { code: "order_service_error", trace_id: None, message: Some(format!("Order service error status {}", resp.status())) }
-        } else {
-            ApiError::Internal { trace_id: None, message: Some(format!("Order service error status {}", resp.status())) }
-        };
-        return Err(code);
+        return Err(ForwardError { status: Some(resp.status()), message: format!("Order service error status {}", resp.status()) });
+    }
+    resp.json::<Order>()
+        .await
+        .map_err(|e| ForwardError { status: None, message: format!("Invalid response from Order Service: {}", e) })
+}
+
+/// Accept an order from an external system. Payloads are mapped through the partner profile of
+/// the calling integration key (native shape otherwise); `?dry_run=true` returns the mapped order
+/// instead of creating it. Invalid payloads get 400 `invalid_external_order` with per-field errors.
+pub async fn handle_external_order(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    integration_key: Option<Extension<IntegrationKeyHash>>,
+    Query(params): Query<ExternalOrderParams>,
+    Json(payload): Json<serde_json::Value>,
+) -> ApiResult<Response> {
+    let tenant_id = sec.tenant_id;
+    let (kind, mappings) = resolve_mappings(&state, tenant_id, integration_key.as_ref().map(|Extension(k)| k), sec.trace_id).await?;
+    let partner = kind.as_str();
+
+    let order = match map_order(&mappings, &payload) {
+        Ok(order) => order,
+        Err(errors) => {
+            state.metrics.record_external_order(partner, "invalid");
+            let rejection = ExternalOrderRejection {
+                code: "invalid_external_order",
+                message: format!("{} field(s) failed validation", errors.len()),
+                partner,
+                errors,
+            };
+            let mut resp = (StatusCode::BAD_REQUEST, Json(rejection)).into_response();
+            resp.headers_mut().insert("X-Error-Code", HeaderValue::from_static("invalid_external_order"));
+            return Ok(resp);
+        }
+    };
+    if params.dry_run {
+        state.metrics.record_external_order(partner, "dry_run");
+        return Ok(Json(ExternalOrderDryRun { dry_run: true, partner, order }).into_response());
     }
 
-    let created_order = resp.json::<Order>().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Invalid response from Order Service: {}", e)) })?;
-    Ok(Json(created_order))
+    match forward_order(&state, tenant_id, &order).await {
+        Ok(created_order) => {
+            state.metrics.record_external_order(partner, "created");
+            Ok(Json(created_order).into_response())
+        }
+        Err(err) => {
+            state.metrics.record_external_order(partner, "failed");
+            Err(err.into())
+        }
+    }
 }
 
 /// Void a previously pending payment (best-effort demo implementation).
@@ -363,6 +506,7 @@ pub async fn void_payment(
     }
 
     let event = PaymentVoidedEvent {
+        schema_version: PaymentVoidedEvent::SCHEMA_VERSION,
         order_id,
         tenant_id,
         method: req.method.clone(),
@@ -378,14 +522,13 @@ pub async fn void_payment(
             info!(order_id = %order_id, "Skipped broker send (TEST_KAFKA_NO_BROKER=1)");
             return Ok(Json(PaymentResult { status: "voided".into(), payment_url: None }));
         }
-        if let Err(err) = state.kafka_producer
-            .send(
-                FutureRecord::to("payment.voided")
-                    .payload(&payload)
-                    .key(&tenant_id.to_string()),
-                Duration::from_secs(0),
-            )
-            .await
+        if let Err(err) = common_kafka::publish(
+            &state.kafka_producer,
+            topics::PAYMENT_VOIDED,
+            &event.partition_key(),
+            &payload,
+        )
+        .await
         {
             tracing::error!(?err, order_id = %order_id, "Failed to emit payment.voided");
             return Err(ApiError::Internal { trace_id: None, message: Some("Failed to emit void event".into()) });
@@ -0,0 +1,110 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_role, Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::key_cache::{insert_key, invalidate_key, KeyCacheChange};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct UpsertKeyRequest {
+    pub api_key_hash: String,
+    pub tenant_id: Uuid,
+    #[serde(default)]
+    pub key_suffix: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct KeyCacheChangeResponse {
+    pub change: &'static str,
+    pub cache_size: usize,
+}
+
+#[derive(Serialize)]
+pub struct KeyCacheFlushResponse {
+    pub status: &'static str,
+}
+
+/// Internal callers (auth-service, ops tooling) authenticate with a shared secret rather than a
+/// user token; the routes are disabled entirely when no secret is configured.
+pub(crate) fn ensure_internal_token(state: &AppState, headers: &HeaderMap) -> ApiResult<()> {
+    let Some(expected) = state.config.internal_api_token.as_deref() else {
+        return Err(ApiError::NotFound { code: "internal_api_disabled", trace_id: None });
+    };
+    let provided = headers
+        .get("X-Internal-Token")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden { trace_id: None })
+    }
+}
+
+fn validate_key_hash(hash: &str) -> ApiResult<()> {
+    if hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest {
+            code: "invalid_key_hash",
+            trace_id: None,
+            message: Some("api_key_hash must be a hex-encoded SHA-256 digest".into()),
+        })
+    }
+}
+
+async fn change_response(state: &AppState, source: &str, change: KeyCacheChange) -> Json<KeyCacheChangeResponse> {
+    let cache_size = state.key_cache.read().await.len();
+    state.metrics.record_key_cache_event(source, change.as_str());
+    state.metrics.set_key_cache_size(cache_size);
+    Json(KeyCacheChangeResponse { change: change.as_str(), cache_size })
+}
+
+pub async fn upsert_cached_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<UpsertKeyRequest>,
+) -> ApiResult<Json<KeyCacheChangeResponse>> {
+    ensure_internal_token(&state, &headers)?;
+    let hash = req.api_key_hash.trim().to_ascii_lowercase();
+    validate_key_hash(&hash)?;
+    let change = insert_key(&state.key_cache, &hash, req.tenant_id, req.key_suffix.unwrap_or_default()).await;
+    tracing::info!(tenant_id = %req.tenant_id, "Integration key inserted into gateway cache");
+    Ok(change_response(&state, "internal", change).await)
+}
+
+pub async fn invalidate_cached_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key_hash): Path<String>,
+) -> ApiResult<Json<KeyCacheChangeResponse>> {
+    ensure_internal_token(&state, &headers)?;
+    let hash = key_hash.trim().to_ascii_lowercase();
+    validate_key_hash(&hash)?;
+    let change = invalidate_key(&state.key_cache, &hash).await;
+    tracing::info!(change = change.as_str(), "Integration key invalidated in gateway cache");
+    Ok(change_response(&state, "internal", change).await)
+}
+
+/// SuperAdmin-only: schedule an immediate reload of the key cache from the database.
+pub async fn flush_key_cache(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> ApiResult<(StatusCode, Json<KeyCacheFlushResponse>)> {
+    ensure_role(&sec, Role::SuperAdmin)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id })?;
+    state.key_reload.notify_one();
+    state.metrics.record_key_cache_event("admin", "flush_requested");
+    tracing::info!(actor = ?sec.actor.id, "Integration key cache flush requested");
+    Ok((StatusCode::ACCEPTED, Json(KeyCacheFlushResponse { status: "reload_scheduled" })))
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::app_state::CachedKey;
+use crate::metrics::GatewayMetrics;
+
+pub type KeyCache = Arc<RwLock<HashMap<String, CachedKey>>>;
+
+pub const KEY_CREATED_ACTION: &str = "integration_key.created";
+pub const KEY_REVOKED_ACTION: &str = "integration_key.revoked";
+
+/// Control message published by auth-service whenever an integration key is created or revoked.
+/// Mirrors `auth_service::notifications::IntegrationKeyEvent`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationKeyEvent {
+    pub action: String,
+    pub tenant_id: Uuid,
+    #[serde(default)]
+    pub key_id: Option<Uuid>,
+    pub api_key_hash: String,
+    #[serde(default)]
+    pub key_suffix: Option<String>,
+    #[serde(default)]
+    pub occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCacheChange {
+    Inserted,
+    Removed,
+    Unchanged,
+}
+
+impl KeyCacheChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyCacheChange::Inserted => "inserted",
+            KeyCacheChange::Removed => "removed",
+            KeyCacheChange::Unchanged => "unchanged",
+        }
+    }
+}
+
+pub async fn insert_key(cache: &KeyCache, key_hash: &str, tenant_id: Uuid, key_suffix: String) -> KeyCacheChange {
+    let mut guard = cache.write().await;
+    guard.insert(key_hash.to_string(), CachedKey { tenant_id, key_suffix });
+    KeyCacheChange::Inserted
+}
+
+pub async fn invalidate_key(cache: &KeyCache, key_hash: &str) -> KeyCacheChange {
+    let mut guard = cache.write().await;
+    if guard.remove(key_hash).is_some() {
+        KeyCacheChange::Removed
+    } else {
+        KeyCacheChange::Unchanged
+    }
+}
+
+/// Apply a control event to the cache. Unknown actions are ignored so newer producers
+/// can add event types without breaking older gateway instances.
+pub async fn apply_key_event(cache: &KeyCache, event: &IntegrationKeyEvent) -> KeyCacheChange {
+    match event.action.as_str() {
+        KEY_CREATED_ACTION => {
+            let suffix = event.key_suffix.clone().unwrap_or_default();
+            insert_key(cache, &event.api_key_hash, event.tenant_id, suffix).await
+        }
+        KEY_REVOKED_ACTION => invalidate_key(cache, &event.api_key_hash).await,
+        _ => KeyCacheChange::Unchanged,
+    }
+}
+
+pub async fn load_active_keys(pool: &PgPool) -> anyhow::Result<HashMap<String, CachedKey>> {
+    let records = sqlx::query(
+        "SELECT api_key_hash, tenant_id, key_suffix FROM integration_keys WHERE revoked_at IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|row| {
+            let hash: String = row.get("api_key_hash");
+            let tenant_id: Uuid = row.get("tenant_id");
+            let key_suffix: String = row.get("key_suffix");
+            (
+                hash,
+                CachedKey {
+                    tenant_id,
+                    key_suffix,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Replace the cache contents with the active keys currently in the database.
+pub async fn reload_key_cache(pool: &PgPool, cache: &KeyCache, metrics: &GatewayMetrics) -> anyhow::Result<usize> {
+    let latest = load_active_keys(pool).await?;
+    let count = latest.len();
+    let mut guard = cache.write().await;
+    *guard = latest;
+    metrics.set_key_cache_size(count);
+    Ok(count)
+}
+
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+pub fn spawn_key_event_consumer(
+    bootstrap: &str,
+    security: &common_config::KafkaSecurity,
+    topic: &str,
+    cache: KeyCache,
+    metrics: Arc<GatewayMetrics>,
+) -> anyhow::Result<()> {
+    use futures_util::StreamExt;
+    use rdkafka::consumer::{Consumer, StreamConsumer};
+    use rdkafka::Message;
+    use tracing::{debug, warn};
+
+    if topic.trim().is_empty() {
+        return Ok(());
+    }
+    // Every gateway instance must observe every key change, so each process joins its own group.
+    let consumer: StreamConsumer = security
+        .apply(&mut rdkafka::ClientConfig::new())
+        .set("bootstrap.servers", bootstrap)
+        .set("group.id", format!("integration-gateway-keys-{}", Uuid::new_v4()))
+        .set("auto.offset.reset", "latest")
+        .set("enable.auto.commit", "false")
+        .create()?;
+    consumer.subscribe(&[topic])?;
+
+    tokio::spawn(async move {
+        let mut stream = consumer.stream();
+        while let Some(message) = stream.next().await {
+            let message = match message {
+                Ok(msg) => msg,
+                Err(err) => {
+                    warn!(?err, "Integration key control consumer error");
+                    continue;
+                }
+            };
+            let Some(Ok(payload)) = message.payload_view::<str>() else {
+                continue;
+            };
+            match serde_json::from_str::<IntegrationKeyEvent>(payload) {
+                Ok(event) => {
+                    let change = apply_key_event(&cache, &event).await;
+                    metrics.record_key_cache_event("kafka", change.as_str());
+                    metrics.set_key_cache_size(cache.read().await.len());
+                    debug!(tenant_id = %event.tenant_id, action = %event.action, change = change.as_str(), "Applied integration key event");
+                }
+                Err(err) => warn!(?err, "Invalid integration key event payload"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: &str, hash: &str, tenant_id: Uuid) -> IntegrationKeyEvent {
+        IntegrationKeyEvent {
+            action: action.to_string(),
+            tenant_id,
+            key_id: Some(Uuid::new_v4()),
+            api_key_hash: hash.to_string(),
+            key_suffix: Some("abcd".into()),
+            occurred_at: Some(Utc::now()),
+        }
+    }
+
+    #[tokio::test]
+    async fn created_event_inserts_and_revoked_event_removes() {
+        let cache: KeyCache = Arc::new(RwLock::new(HashMap::new()));
+        let tenant_id = Uuid::new_v4();
+
+        let change = apply_key_event(&cache, &event(KEY_CREATED_ACTION, "hash-1", tenant_id)).await;
+        assert_eq!(change, KeyCacheChange::Inserted);
+        let cached = cache.read().await.get("hash-1").cloned().expect("key cached");
+        assert_eq!(cached.tenant_id, tenant_id);
+        assert_eq!(cached.key_suffix, "abcd");
+
+        let change = apply_key_event(&cache, &event(KEY_REVOKED_ACTION, "hash-1", tenant_id)).await;
+        assert_eq!(change, KeyCacheChange::Removed);
+        assert!(cache.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn revoking_unknown_key_and_unknown_actions_are_noops() {
+        let cache: KeyCache = Arc::new(RwLock::new(HashMap::new()));
+        let tenant_id = Uuid::new_v4();
+        insert_key(&cache, "hash-keep", tenant_id, "keep".into()).await;
+
+        assert_eq!(invalidate_key(&cache, "hash-missing").await, KeyCacheChange::Unchanged);
+        let change = apply_key_event(&cache, &event("integration_key.rotated", "hash-keep", tenant_id)).await;
+        assert_eq!(change, KeyCacheChange::Unchanged);
+        assert_eq!(cache.read().await.len(), 1);
+    }
+}
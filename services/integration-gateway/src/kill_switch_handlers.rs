@@ -0,0 +1,168 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_role, Role, SecurityContext, SecurityCtxExtractor};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::kill_switches::{self, KillSwitch, KillSwitchChange, DEFAULT_RETRY_AFTER_SECS, MAX_RETRY_AFTER_SECS, SWITCHES};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct KillSwitchRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub retry_after_secs: Option<i32>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ChangeListParams {
+    pub limit: Option<i64>,
+}
+
+/// Switches affect every tenant, so only platform operators may change them.
+fn authorize(state: &AppState, sec: &SecurityContext) -> ApiResult<PgPool> {
+    ensure_role(sec, Role::SuperAdmin)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id })?;
+    state.db.clone().ok_or(ApiError::Internal { trace_id: sec.trace_id, message: Some("Database unavailable".into()) })
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |err| {
+        if matches!(err, sqlx::Error::PoolTimedOut) {
+            return common_db::db_error(err, trace_id);
+        }
+        tracing::error!(?err, "Kill switch query failed");
+        ApiError::Internal { trace_id, message: Some("Kill switch query failed".into()) }
+    }
+}
+
+fn validate_request(name: &str, req: &KillSwitchRequest, trace_id: Option<Uuid>) -> ApiResult<(&'static str, i32)> {
+    let name = SWITCHES
+        .iter()
+        .copied()
+        .find(|known| *known == name)
+        .ok_or(ApiError::NotFound { code: "unknown_kill_switch", trace_id })?;
+    let retry_after_secs = req.retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+    if !(1..=MAX_RETRY_AFTER_SECS).contains(&retry_after_secs) {
+        return Err(ApiError::BadRequest {
+            code: "invalid_retry_after",
+            trace_id,
+            message: Some(format!("retry_after_secs must be between 1 and {MAX_RETRY_AFTER_SECS}")),
+        });
+    }
+    Ok((name, retry_after_secs))
+}
+
+/// Every switch with its current state; switches never set are listed as disabled.
+pub async fn list_kill_switches(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> ApiResult<Json<Vec<KillSwitch>>> {
+    let pool = authorize(&state, &sec)?;
+    let stored = kill_switches::load_switches(&pool).await.map_err(db_error(sec.trace_id))?;
+    let switches = SWITCHES
+        .iter()
+        .map(|name| {
+            stored.iter().find(|switch| switch.name == *name).cloned().unwrap_or_else(|| KillSwitch {
+                name: name.to_string(),
+                enabled: false,
+                retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+                reason: None,
+                updated_by: None,
+                updated_at: chrono::DateTime::UNIX_EPOCH,
+            })
+        })
+        .collect();
+    Ok(Json(switches))
+}
+
+pub async fn put_kill_switch(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(name): Path<String>,
+    Json(req): Json<KillSwitchRequest>,
+) -> ApiResult<Json<KillSwitch>> {
+    let pool = authorize(&state, &sec)?;
+    let (name, retry_after_secs) = validate_request(&name, &req, sec.trace_id)?;
+    let reason = req.reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+    let switch = kill_switches::save_switch(&pool, name, req.enabled, retry_after_secs, reason, sec.actor.id)
+        .await
+        .map_err(db_error(sec.trace_id))?;
+    if let Err(err) = kill_switches::reload(&pool, &state.kill_switches, &state.metrics).await {
+        tracing::warn!(?err, "Failed to reload kill switches after a change; the next refresh will apply it");
+    }
+    let action = if switch.enabled { "enabled" } else { "disabled" };
+    state.metrics.record_kill_switch_change(name, action);
+    tracing::warn!(
+        switch = name,
+        action,
+        retry_after_secs,
+        reason = reason.unwrap_or("-"),
+        actor = ?sec.actor.id,
+        "Gateway kill switch changed"
+    );
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    publish_audit(&state, &sec, &switch, action).await;
+    Ok(Json(switch))
+}
+
+/// Recent switch changes, newest first.
+pub async fn list_kill_switch_changes(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ChangeListParams>,
+) -> ApiResult<Json<Vec<KillSwitchChange>>> {
+    let pool = authorize(&state, &sec)?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    let changes = kill_switches::recent_changes(&pool, limit).await.map_err(db_error(sec.trace_id))?;
+    Ok(Json(changes))
+}
+
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+async fn publish_audit(state: &AppState, sec: &SecurityContext, switch: &KillSwitch, action: &str) {
+    let event = common_audit::AuditEvent {
+        event_id: Uuid::new_v4(),
+        event_version: common_audit::AUDIT_EVENT_VERSION,
+        tenant_id: sec.tenant_id,
+        actor: sec.actor.clone(),
+        entity_type: "gateway_kill_switch".into(),
+        entity_id: None,
+        action: action.into(),
+        occurred_at: chrono::Utc::now(),
+        source_service: "integration-gateway".into(),
+        severity: common_audit::AuditSeverity::Security,
+        trace_id: sec.trace_id,
+        payload: serde_json::to_value(switch).unwrap_or_default(),
+        meta: serde_json::json!({ "source": "integration-gateway" }),
+    };
+    let published = match serde_json::to_string(&event) {
+        Ok(payload) => common_kafka::publish(&state.kafka_producer, &state.config.audit_topic, &switch.name, &payload)
+            .await
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    if let Err(err) = published {
+        tracing::warn!(error = %err, switch = %switch.name, "Failed to publish kill switch audit event");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_switches_and_sane_retry_after_are_accepted() {
+        let req = |retry_after_secs| KillSwitchRequest { enabled: true, retry_after_secs, reason: None };
+        assert!(matches!(validate_request("payments", &req(None), None), Ok(("payments", DEFAULT_RETRY_AFTER_SECS))));
+        assert!(matches!(validate_request("orders", &req(None), None), Err(ApiError::NotFound { code: "unknown_kill_switch", .. })));
+        assert!(matches!(validate_request("maintenance", &req(Some(0)), None), Err(ApiError::BadRequest { code: "invalid_retry_after", .. })));
+        assert!(matches!(
+            validate_request("maintenance", &req(Some(MAX_RETRY_AFTER_SECS + 1)), None),
+            Err(ApiError::BadRequest { code: "invalid_retry_after", .. })
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+//! Kill switches for shedding load during incidents. `maintenance` answers every partner API
+//! request with 503; the other switches disable one group of routes (see [`switch_for_path`]).
+//! Blocked requests get 503 with `Retry-After` before authentication or rate limiting runs.
+//!
+//! Switches live in `gateway_kill_switches`. Each instance keeps the enabled ones in memory and
+//! reloads them every `KILL_SWITCH_REFRESH_SECONDS`; the instance that saves a change reloads at
+//! once. `/admin/*` routes are never blocked, so switches can always be turned off again.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use common_http_errors::ApiError;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::metrics::GatewayMetrics;
+use crate::AppState;
+
+pub const MAINTENANCE: &str = "maintenance";
+/// Every switch, in the order they are listed.
+pub const SWITCHES: &[&str] = &[MAINTENANCE, "payments", "external_orders", "catalog", "webhooks"];
+pub const DEFAULT_RETRY_AFTER_SECS: i32 = 60;
+pub const MAX_RETRY_AFTER_SECS: i32 = 3600;
+
+/// The route switch covering `path`, if any.
+pub fn switch_for_path(path: &str) -> Option<&'static str> {
+    let under = |prefix: &str| path == prefix || path.starts_with(&format!("{prefix}/"));
+    if under("/payments") {
+        Some("payments")
+    } else if under("/external/order") || under("/external/orders") {
+        Some("external_orders")
+    } else if under("/catalog") {
+        Some("catalog")
+    } else if under("/webhooks") {
+        Some("webhooks")
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct KillSwitch {
+    pub name: String,
+    pub enabled: bool,
+    pub retry_after_secs: i32,
+    pub reason: Option<String>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One entry of the change log.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct KillSwitchChange {
+    pub id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    pub retry_after_secs: i32,
+    pub reason: Option<String>,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Enabled switches of this instance, by name, with their `Retry-After` seconds.
+#[derive(Clone, Default)]
+pub struct KillSwitches {
+    active: Arc<RwLock<HashMap<String, u64>>>,
+}
+
+impl KillSwitches {
+    pub fn replace(&self, switches: &[KillSwitch], metrics: &GatewayMetrics) {
+        let active: HashMap<String, u64> = switches
+            .iter()
+            .filter(|switch| switch.enabled)
+            .map(|switch| (switch.name.clone(), switch.retry_after_secs.max(1) as u64))
+            .collect();
+        for name in SWITCHES {
+            metrics.set_kill_switch_active(name, active.contains_key(*name));
+        }
+        *self.active.write().expect("kill switch lock") = active;
+    }
+
+    /// Names of the enabled switches, sorted.
+    pub fn active(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.active.read().expect("kill switch lock").keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// The switch blocking a request to `path` and its `Retry-After`, maintenance first.
+    pub fn blocking(&self, path: &str) -> Option<(&'static str, u64)> {
+        if path.starts_with("/admin/") {
+            return None;
+        }
+        let active = self.active.read().expect("kill switch lock");
+        if active.is_empty() {
+            return None;
+        }
+        if let Some(retry_after) = active.get(MAINTENANCE) {
+            return Some((MAINTENANCE, *retry_after));
+        }
+        let name = switch_for_path(path)?;
+        active.get(name).map(|retry_after| (name, *retry_after))
+    }
+}
+
+/// Middleware placed outside auth on the partner API.
+pub async fn enforce_kill_switches(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some((name, retry_after_secs)) = state.kill_switches.blocking(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    state.metrics.record_kill_switch_rejection(name);
+    let code = if name == MAINTENANCE { "maintenance_mode" } else { "route_disabled" };
+    ApiError::ServiceUnavailable { code, trace_id: None, retry_after_secs }.into_response()
+}
+
+pub async fn load_switches(pool: &PgPool) -> Result<Vec<KillSwitch>, sqlx::Error> {
+    sqlx::query_as::<_, KillSwitch>(
+        "SELECT name, enabled, retry_after_secs, reason, updated_by, updated_at FROM gateway_kill_switches ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn reload(pool: &PgPool, switches: &KillSwitches, metrics: &GatewayMetrics) -> Result<(), sqlx::Error> {
+    let loaded = load_switches(pool).await?;
+    switches.replace(&loaded, metrics);
+    Ok(())
+}
+
+/// Save a switch and append the change to the log in one transaction.
+pub async fn save_switch(
+    pool: &PgPool,
+    name: &str,
+    enabled: bool,
+    retry_after_secs: i32,
+    reason: Option<&str>,
+    actor: Option<Uuid>,
+) -> Result<KillSwitch, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let switch = sqlx::query_as::<_, KillSwitch>(
+        "INSERT INTO gateway_kill_switches (name, enabled, retry_after_secs, reason, updated_by)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (name) DO UPDATE
+           SET enabled = EXCLUDED.enabled, retry_after_secs = EXCLUDED.retry_after_secs,
+               reason = EXCLUDED.reason, updated_by = EXCLUDED.updated_by, updated_at = now()
+         RETURNING name, enabled, retry_after_secs, reason, updated_by, updated_at",
+    )
+    .bind(name)
+    .bind(enabled)
+    .bind(retry_after_secs)
+    .bind(reason)
+    .bind(actor)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO gateway_kill_switch_changes (id, name, enabled, retry_after_secs, reason, changed_by)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(name)
+    .bind(enabled)
+    .bind(retry_after_secs)
+    .bind(reason)
+    .bind(actor)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(switch)
+}
+
+pub async fn recent_changes(pool: &PgPool, limit: i64) -> Result<Vec<KillSwitchChange>, sqlx::Error> {
+    sqlx::query_as::<_, KillSwitchChange>(
+        "SELECT id, name, enabled, retry_after_secs, reason, changed_by, changed_at
+         FROM gateway_kill_switch_changes ORDER BY changed_at DESC LIMIT $1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Poll the switch table so changes made through other instances apply here too.
+pub fn spawn_kill_switch_refresh(pool: PgPool, switches: KillSwitches, metrics: Arc<GatewayMetrics>, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = reload(&pool, &switches, &metrics).await {
+                tracing::warn!(?err, "Failed to refresh gateway kill switches");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn switch(name: &str, enabled: bool, retry_after_secs: i32) -> KillSwitch {
+        KillSwitch { name: name.into(), enabled, retry_after_secs, reason: None, updated_by: None, updated_at: Utc::now() }
+    }
+
+    #[test]
+    fn routes_map_to_their_switch() {
+        assert_eq!(switch_for_path("/payments"), Some("payments"));
+        assert_eq!(switch_for_path("/payments/void"), Some("payments"));
+        assert_eq!(switch_for_path("/external/order"), Some("external_orders"));
+        assert_eq!(switch_for_path("/external/orders/batch/1"), Some("external_orders"));
+        assert_eq!(switch_for_path("/catalog/products"), Some("catalog"));
+        assert_eq!(switch_for_path("/paymentsx"), None);
+        assert_eq!(switch_for_path("/admin/partner-profiles"), None);
+    }
+
+    #[test]
+    fn maintenance_blocks_everything_but_admin_routes() {
+        let metrics = GatewayMetrics::new().unwrap();
+        let switches = KillSwitches::default();
+        switches.replace(&[switch("payments", true, 30), switch("catalog", false, 60)], &metrics);
+        assert_eq!(switches.blocking("/payments/void"), Some(("payments", 30)));
+        assert_eq!(switches.blocking("/catalog/products"), None);
+        assert_eq!(switches.blocking("/external/order"), None);
+
+        switches.replace(&[switch(MAINTENANCE, true, 120), switch("payments", true, 30)], &metrics);
+        assert_eq!(switches.blocking("/payments"), Some((MAINTENANCE, 120)));
+        assert_eq!(switches.blocking("/external/order"), Some((MAINTENANCE, 120)));
+        assert_eq!(switches.blocking("/admin/kill-switches"), None);
+    }
+}
@@ -1,11 +1,27 @@
 pub mod alerts;
+pub mod catalog_proxy;
 pub mod config;
 pub mod events;
 pub mod integration_handlers;
+pub mod key_admin_handlers;
+pub mod key_cache;
+pub mod kill_switch_handlers;
+pub mod kill_switches;
 #[cfg(feature = "future-order-validation")]
 pub mod validation;
 pub mod metrics;
+pub mod order_batches;
+pub mod outbox;
+pub mod partner_profile_handlers;
+pub mod partner_profiles;
+pub mod payment_adapters;
+pub mod payment_normalization;
+pub mod payload_capture;
+pub mod payload_capture_handlers;
 pub mod rate_limiter;
+pub mod redis_fallback;
+pub mod response_cache;
+pub mod tenant_overview;
 pub mod usage;
 pub mod webhook_handlers;
 pub mod app_state;
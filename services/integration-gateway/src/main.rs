@@ -4,28 +4,28 @@ use axum::{
     extract::State,
     http::{
         header::{self, ACCEPT, CONTENT_TYPE},
-        HeaderName, HeaderValue, Method, Request, StatusCode,
+        HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode,
     },
     middleware::{self, Next},
     response::Response,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 // chrono::Utc not directly used in main after state extraction
-use common_auth::{JwtConfig, JwtVerifier};
+use common_auth::{AuthError, JwtConfig, JwtVerifier};
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
 use common_http_errors::{ApiError};
 use common_money::log_rounding_mode_once;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use reqwest::Client;
 use sha2::{Digest, Sha256};
-use sqlx::{PgPool, Row};
 use std::collections::HashMap;
-use std::env;
-use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use tokio::sync::mpsc;
 use tokio::time::{interval, MissedTickBehavior};
 use tower_http::cors::{AllowOrigin, CorsLayer};
@@ -37,25 +37,42 @@ use uuid::Uuid;
 
 // use integration_gateway::alerts::{post_alert_webhook, RateLimitAlertEvent}; // not needed in main after state extraction
 // Removed direct alert publish import; alerting handled within handlers when feature-enabled.
-use integration_gateway::app_state::{AppState, CachedKey};
-use integration_gateway::config::GatewayConfig;
+use integration_gateway::app_state::AppState;
+use integration_gateway::catalog_proxy::{invalidate_response_cache, proxy_catalog_read};
+use integration_gateway::config::StartupConfig;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use integration_gateway::key_cache::spawn_key_event_consumer;
+use integration_gateway::key_cache::{load_active_keys, reload_key_cache};
+use integration_gateway::kill_switch_handlers::{list_kill_switch_changes, list_kill_switches, put_kill_switch};
+use integration_gateway::kill_switches::{enforce_kill_switches, spawn_kill_switch_refresh, KillSwitches};
 use integration_gateway::metrics::GatewayMetrics;
 use integration_gateway::rate_limiter::RedisRateLimiter;
+use integration_gateway::redis_fallback::{route_class, FallbackRateLimiter};
+use integration_gateway::response_cache::ResponseCache;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use integration_gateway::response_cache::spawn_cache_invalidation_consumer;
+use integration_gateway::tenant_overview::get_tenant_overview;
 use integration_gateway::usage::UsageTracker;
 
 use integration_gateway::integration_handlers::{
-    handle_external_order, process_payment, void_payment, ForwardedAuthHeader,
+    handle_external_order, process_payment, void_payment, ForwardedAuthHeader, IntegrationKeyHash,
 };
 // Security context extraction occurs inside handler modules; no direct main.rs usage.
-use integration_gateway::webhook_handlers::handle_coinbase_webhook;
+use integration_gateway::key_admin_handlers::{flush_key_cache, invalidate_cached_key, upsert_cached_key};
+use integration_gateway::order_batches::{get_order_batch, spawn_batch_worker, submit_order_batch};
+use integration_gateway::partner_profile_handlers::{delete_partner_profile, list_partner_profiles, put_partner_profile};
+use integration_gateway::payload_capture::{capture_exchanges, spawn_capture_purge};
+use integration_gateway::payload_capture_handlers::{
+    delete_capture_settings, get_capture_settings, list_captured_exchanges, put_capture_settings,
+};
+use integration_gateway::payment_normalization::PaymentAdapterRegistry;
+use integration_gateway::webhook_handlers::handle_payment_webhook;
 
 
 async fn health() -> &'static str {
     "ok"
 }
 
-async fn metrics_endpoint(State(state): State<AppState>) -> Response {
-    match state.metrics.render() {
+async fn metrics_endpoint(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    match state.metrics.render(headers.get(ACCEPT).and_then(|v| v.to_str().ok())) {
         Ok(resp) => resp,
         Err(err) => {
             warn!(?err, "Failed to render metrics");
@@ -71,56 +88,61 @@ use common_http_errors::http_error_metrics_layer;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("integration-gateway");
     log_rounding_mode_once();
 
-    let database_url =
-        env::var("DATABASE_URL").expect("DATABASE_URL must be set for integration-gateway");
-    let db_pool = PgPool::connect(&database_url).await?;
+    let startup = StartupConfig::from_env().await?;
+    let db_pool = startup.pool.connect(startup.database_url.expose()).await?;
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db_pool.clone());
 
-    let config = Arc::new(GatewayConfig::from_env()?);
+    let config = Arc::new(startup.gateway.clone());
 
     let initial_keys = load_active_keys(&db_pool).await?;
     tracing::info!(
         count = initial_keys.len(),
         "Loaded integration keys into cache"
     );
+    let initial_key_count = initial_keys.len();
     let key_cache = Arc::new(RwLock::new(initial_keys));
+    let key_reload = Arc::new(Notify::new());
+
+    let rate_limiter = RedisRateLimiter::new(
+        &config.redis_url,
+        config.rate_limit_window_secs,
+        config.redis_prefix.clone(),
+    )
+    .await
+    .expect("Failed to create rate limiter");
+    let metrics = Arc::new(GatewayMetrics::new()?);
+    metrics.set_key_cache_size(initial_key_count);
 
-    let refresh_secs = env::var("KEY_REFRESH_SECONDS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .unwrap_or(60);
+    // Poll-based refresh remains the safety net; the control topic and internal routes
+    // below apply individual changes immediately, and admins can force a reload.
+    let refresh_secs = startup.key_refresh_seconds;
     {
         let pool = db_pool.clone();
         let cache = key_cache.clone();
+        let reload = key_reload.clone();
+        let metrics = metrics.clone();
         tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_secs(refresh_secs.max(10)));
+            let mut ticker = interval(Duration::from_secs(refresh_secs));
             loop {
-                ticker.tick().await;
-                match load_active_keys(&pool).await {
-                    Ok(latest) => {
-                        let count = latest.len();
-                        let mut guard = cache.write().await;
-                        *guard = latest;
-                        tracing::debug!(count, "Refreshed integration key cache");
+                let source = tokio::select! {
+                    _ = ticker.tick() => "poll",
+                    _ = reload.notified() => "admin",
+                };
+                match reload_key_cache(&pool, &cache, &metrics).await {
+                    Ok(count) => {
+                        metrics.record_key_cache_event("reload", source);
+                        tracing::debug!(count, source, "Refreshed integration key cache");
                     }
                     Err(err) => {
-                        tracing::warn!(?err, "Failed to refresh integration key cache");
+                        tracing::warn!(?err, source, "Failed to refresh integration key cache");
                     }
                 }
             }
         });
     }
-
-    let rate_limiter = RedisRateLimiter::new(
-        &config.redis_url,
-        config.rate_limit_window_secs,
-        config.redis_prefix.clone(),
-    )
-    .await
-    .expect("Failed to create rate limiter");
-    let metrics = Arc::new(GatewayMetrics::new()?);
     // Export configured rate limit target (rpm) for alert comparisons
     metrics.set_rate_limit_rpm_target(config.rate_limit_rpm as i64);
     // Set build info (version / commit) for traceability in metrics
@@ -149,18 +171,41 @@ async fn main() -> anyhow::Result<()> {
         .context("Failed to build HTTP client")?;
     let alert_state = Arc::new(Mutex::new(HashMap::new()));
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&startup.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), startup.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    common_security::spawn_policy_refresh(jwt_verifier.clone());
 
     // Initialize Kafka producer (feature gated)
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
-        .create()
+    let kafka_security = KafkaSecurity::from_env().await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    let producer: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&startup.kafka_bootstrap, &kafka_security)
         .expect("failed to create kafka producer");
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    spawn_key_event_consumer(
+        &startup.kafka_bootstrap,
+        &kafka_security,
+        &config.integration_key_topic,
+        key_cache.clone(),
+        metrics.clone(),
+    )
+    .context("Failed to start integration key control consumer")?;
+    let response_cache = ResponseCache::from_env(&config.redis_url)
+        .await
+        .context("Failed to create response cache")?;
+    if let Some(cache) = &response_cache {
+        info!(ttl_secs = cache.ttl.as_secs(), "Response cache enabled for catalog reads");
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        spawn_cache_invalidation_consumer(
+            &startup.kafka_bootstrap,
+            &kafka_security,
+            cache.clone(),
+            metrics.clone(),
+        )
+        .context("Failed to start response cache invalidation consumer")?;
+    }
     #[cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
     tracing::warn!("kafka features DISABLED (no kafka / kafka-producer): events & alerts will not be published (TA-FND-5)");
 
@@ -170,9 +215,24 @@ async fn main() -> anyhow::Result<()> {
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))] Some(producer.clone())
     );
     usage.spawn_background_tasks();
+    let kill_switches = KillSwitches::default();
+    if let Err(err) = integration_gateway::kill_switches::reload(&db_pool, &kill_switches, &metrics).await {
+        warn!(?err, "Failed to load gateway kill switches; starting with none enabled");
+    }
+    spawn_kill_switch_refresh(
+        db_pool.clone(),
+        kill_switches.clone(),
+        metrics.clone(),
+        Duration::from_secs(startup.kill_switch_refresh_seconds),
+    );
     let state = AppState {
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] kafka_producer: producer,
-        rate_limiter: std::sync::Arc::new(rate_limiter),
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] kafka_producer: producer.clone(),
+        rate_limiter: Arc::new(FallbackRateLimiter::new(
+            Arc::new(rate_limiter),
+            config.redis_degradation.clone(),
+            config.rate_limit_window_secs,
+            metrics.clone(),
+        )),
         key_cache,
         jwt_verifier,
         metrics: metrics.clone(),
@@ -180,6 +240,11 @@ async fn main() -> anyhow::Result<()> {
         config: config.clone(),
         http_client: http_client.clone(),
         alert_state: alert_state.clone(),
+        key_reload,
+        db: Some(db_pool.clone()),
+        response_cache,
+        kill_switches,
+        payment_adapters: Arc::new(PaymentAdapterRegistry::from_config(&config)),
     };
 
     // Build routes with authentication + rate-limiting middleware
@@ -197,7 +262,7 @@ async fn main() -> anyhow::Result<()> {
                 .collect::<Vec<_>>(),
         ))
         .allow_methods(
-            [Method::GET, Method::POST, Method::OPTIONS]
+            [Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS]
                 .into_iter()
                 .collect::<Vec<_>>(),
         )
@@ -212,31 +277,61 @@ async fn main() -> anyhow::Result<()> {
             .into_iter()
             .collect::<Vec<_>>(),
         );
+    spawn_batch_worker(state.clone(), db_pool.clone());
+    spawn_capture_purge(db_pool.clone());
     let protected_state = state.clone();
     let auth_state = state.clone();
     let protected_api = Router::new()
         .route("/payments", post(process_payment))
         .route("/payments/void", post(void_payment))
         .route("/external/order", post(handle_external_order))
-        .route("/webhooks/coinbase", post(handle_coinbase_webhook))
+        .route("/external/orders/batch", post(submit_order_batch))
+        .route("/external/orders/batch/:id", get(get_order_batch))
+        .route("/catalog/*path", get(proxy_catalog_read))
+        .route("/webhooks/:provider", post(handle_payment_webhook))
+        .route("/admin/integration-keys/flush", post(flush_key_cache))
+        .route("/admin/partner-profiles", get(list_partner_profiles))
+        .route("/admin/partner-profiles/:key_id", put(put_partner_profile).delete(delete_partner_profile))
+        .route(
+            "/admin/integration-keys/:key_id/capture",
+            get(get_capture_settings).put(put_capture_settings).delete(delete_capture_settings),
+        )
+        .route("/admin/integration-keys/:key_id/exchanges", get(list_captured_exchanges))
+        .route("/admin/kill-switches", get(list_kill_switches))
+        .route("/admin/kill-switches/changes", get(list_kill_switch_changes))
+        .route("/admin/kill-switches/:name", put(put_kill_switch))
+        .route("/admin/tenants/:tenant_id/overview", get(get_tenant_overview))
+        // Inside auth, which identifies the integration key.
+        .layer(middleware::from_fn_with_state(state.clone(), capture_exchanges))
         .layer(middleware::from_fn(move |request, next| {
             let state = auth_state.clone();
             async move { auth_middleware(state, request, next).await }
         }))
+        // Outermost, so blocked requests skip auth and rate limiting.
+        .layer(middleware::from_fn_with_state(state.clone(), enforce_kill_switches))
         .with_state(protected_state);
+    let slo = SloTracker::new("integration-gateway", SloConfig::from_env(), state.metrics.registry());
     let app = Router::new()
         .route("/healthz", get(health))
         .route("/metrics", get(metrics_endpoint))
+        .route("/internal/config", config_route(&startup))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
+        .route("/internal/integration-keys", post(upsert_cached_key))
+        .route("/internal/integration-keys/:key_hash", delete(invalidate_cached_key))
+        .route("/internal/response-cache/invalidate", post(invalidate_response_cache))
         .merge(protected_api)
         .with_state(state)
     .layer(middleware::from_fn(http_error_metrics_adapter)) // existing adapter for ApiError mapping
     .layer(middleware::from_fn(http_error_metrics_layer("integration-gateway")))
-        .layer(cors);
+        .layer(cors)
+        .layer(axum::middleware::from_fn(slo.layer()))
+        .layer(axum::middleware::from_fn(common_observability::request_span));
 
     // Best-effort: push a few items to the queue periodically to exercise depth metric (dev visibility only)
     // Guarded so production builds do not emit synthetic backpressure noise (see backlog addendum 2025-10-02 Stabilization Half Items Clarified)
     // Enable only in debug OR when explicitly opted-in via env (non-production troubleshooting / demos)
-    if cfg!(debug_assertions) || std::env::var("GATEWAY_DEV_METRICS_DEMO").ok().as_deref() == Some("1") {
+    if cfg!(debug_assertions) || startup.dev_metrics_demo {
         let tx_clone = tx.clone();
         let metrics_clone = metrics.clone();
         tokio::spawn(async move {
@@ -249,17 +344,13 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
-    // Start server (bind host/port from env or defaults)
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8083);
-    let ip: std::net::IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    // Start server (HOST/PORT, see StartupConfig)
+    let addr = startup.http.addr();
     println!("starting integration-gateway on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::flush(&producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
@@ -284,7 +375,7 @@ async fn auth_middleware(
     let (limiter_key, tenant_id, identity_label) = if let Some(token) = bearer {
         let claims = state.jwt_verifier.verify(token).map_err(|err| {
             warn!(error = %err, "JWT verification failed");
-            ApiError::Forbidden { trace_id: None }
+            tenant_access_error(err)
         })?;
         if let Some(header_tid) = headers
             .get("X-Tenant-ID")
@@ -327,14 +418,22 @@ async fn auth_middleware(
         return Err(ApiError::Forbidden { trace_id: None });
     };
 
+    state
+        .jwt_verifier
+        .tenant_statuses()
+        .ensure_active(tenant_id)
+        .map_err(tenant_access_error)?;
+
+    let rate_policy = state.config.rate_limit_policies.resolve(identity_label, tenant_id, state.config.rate_limit_rpm);
     let rl_start = Instant::now();
     let decision = state
         .rate_limiter
-        .check(&limiter_key, state.config.rate_limit_rpm)
+        .check(&limiter_key, &rate_policy, route_class(request.uri().path()))
         .await
-        .map_err(|err| {
-            warn!(?err, "Rate limiter failure");
-            ApiError::Internal { trace_id: None, message: Some("Rate limiter failure".into()) }
+        .map_err(|unavailable| ApiError::ServiceUnavailable {
+            code: "rate_limiter_unavailable",
+            trace_id: None,
+            retry_after_secs: unavailable.retry_after_secs,
         })?;
 
     // Record window usage metric and rough latency (TA-PERF-3)
@@ -363,7 +462,7 @@ async fn auth_middleware(
                 tenant_opt,
                 key_hash_opt,
                 key_suffix_opt,
-                state.config.rate_limit_rpm,
+                rate_policy.limit,
                 decision.current,
             )
             .await;
@@ -380,7 +479,16 @@ async fn auth_middleware(
             let role_csv = claims.roles.join(",");
             headers_mut.insert("X-Roles", HeaderValue::from_str(&role_csv).unwrap_or(HeaderValue::from_static("support")));
             headers_mut.insert("X-User-ID", HeaderValue::from_str(&claims.subject.to_string()).unwrap());
+            match claims.residency.as_deref().and_then(|region| HeaderValue::from_str(region).ok()) {
+                Some(region) => {
+                    headers_mut.insert("X-Residency", region);
+                }
+                None => {
+                    headers_mut.remove("X-Residency");
+                }
+            }
         } else {
+            headers_mut.remove("X-Residency");
             headers_mut.insert("X-Roles", HeaderValue::from_static("support"));
             headers_mut.insert("X-User-ID", HeaderValue::from_str(&tenant_id.to_string()).unwrap());
         }
@@ -391,6 +499,9 @@ async fn auth_middleware(
             .extensions_mut()
             .insert(ForwardedAuthHeader(header_value));
     }
+    if let Some((_, key_hash, _)) = usage_context {
+        request.extensions_mut().insert(IntegrationKeyHash(key_hash));
+    }
     if let Some(claims) = claims_opt {
         request.extensions_mut().insert(claims);
     }
@@ -398,64 +509,52 @@ async fn auth_middleware(
     Ok(next.run(request).await)
 }
 
+/// Suspended/archived tenants get a specific 403 code; any other auth failure stays generic.
+fn tenant_access_error(err: AuthError) -> ApiError {
+    match err {
+        AuthError::TenantSuspended(_) => ApiError::ForbiddenCode {
+            code: "tenant_suspended",
+            trace_id: None,
+            message: Some("Tenant is suspended".into()),
+        },
+        AuthError::TenantArchived(_) => ApiError::ForbiddenCode {
+            code: "tenant_archived",
+            trace_id: None,
+            message: Some("Tenant is archived".into()),
+        },
+        _ => ApiError::Forbidden { trace_id: None },
+    }
+}
+
 async fn http_error_metrics_adapter(req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
     // Adapter now just converts ApiError to Response; metrics captured by shared layer earlier.
     let resp = next.run(req).await;
     Ok(resp)
 }
 
-async fn load_active_keys(pool: &PgPool) -> anyhow::Result<HashMap<String, CachedKey>> {
-    let records = sqlx::query(
-        "SELECT api_key_hash, tenant_id, key_suffix FROM integration_keys WHERE revoked_at IS NULL",
-    )
-    .fetch_all(pool)
-    .await?;
-
-    Ok(records
-        .into_iter()
-        .map(|row| {
-            let hash: String = row.get("api_key_hash");
-            let tenant_id: Uuid = row.get("tenant_id");
-            let key_suffix: String = row.get("key_suffix");
-            (
-                hash,
-                CachedKey {
-                    tenant_id,
-                    key_suffix,
-                },
-            )
-        })
-        .collect())
-}
-
 fn hash_api_key(key: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(key.as_bytes());
     hex::encode(hasher.finalize())
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -464,16 +563,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -2,7 +2,9 @@ use anyhow::Result;
 use axum::body::Body;
 use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::Response;
-use prometheus::{Encoder, IntCounterVec, Opts, Registry, TextEncoder, IntGauge, Histogram, HistogramOpts};
+use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder, IntGauge, Histogram, HistogramOpts};
+use common_http_errors::exposition;
+use std::collections::HashSet;
 use std::env;
 
 #[derive(Clone)]
@@ -20,11 +22,31 @@ pub struct GatewayMetrics {
     rate_window_usage: IntGauge,
     rate_limit_rpm_target: IntGauge,
     build_info: IntGauge,
+    // Integration key cache maintenance
+    key_cache_events: IntCounterVec,
+    key_cache_size: IntGauge,
+    // External order intake by partner profile
+    external_orders: IntCounterVec,
+    // Proxied GET response cache
+    response_cache_lookups: IntCounterVec,
+    response_cache_invalidations: IntCounterVec,
+    // Kill switches
+    kill_switch_active: IntGaugeVec,
+    kill_switch_rejections: IntCounterVec,
+    kill_switch_changes: IntCounterVec,
+    // Rate limiting while Redis is unavailable
+    redis_up: IntGauge,
+    redis_errors: IntCounterVec,
+    rate_limit_fallback: IntCounterVec,
+    rate_limit_fallback_keys: IntGauge,
 }
 
 impl GatewayMetrics {
     pub fn new() -> Result<Self> {
         let registry = Registry::new();
+        common_db::pool::register_metrics(&registry);
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        common_kafka::register_metrics(&registry);
         let rate_checks = IntCounterVec::new(
             Opts::new("gateway_rate_limit_checks_total", "Total rate limit checks"),
             &["identity"],
@@ -81,6 +103,81 @@ impl GatewayMetrics {
         registry.register(Box::new(rate_window_usage.clone()))?;
         registry.register(Box::new(rate_limit_rpm_target.clone()))?;
         registry.register(Box::new(build_info.clone()))?;
+        let key_cache_events = IntCounterVec::new(
+            Opts::new(
+                "gateway_key_cache_events_total",
+                "Integration key cache changes grouped by source (kafka|internal|reload) and outcome",
+            ),
+            &["source", "outcome"],
+        )?;
+        let key_cache_size = IntGauge::with_opts(Opts::new(
+            "gateway_key_cache_size",
+            "Number of active integration keys currently cached"
+        ))?;
+        registry.register(Box::new(key_cache_events.clone()))?;
+        registry.register(Box::new(key_cache_size.clone()))?;
+        let external_orders = IntCounterVec::new(
+            Opts::new(
+                "gateway_external_orders_total",
+                "External orders grouped by partner profile and outcome (created|dry_run|invalid|failed|queued)",
+            ),
+            &["partner", "outcome"],
+        )?;
+        registry.register(Box::new(external_orders.clone()))?;
+        let response_cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "gateway_response_cache_requests_total",
+                "Proxied GET requests grouped by cache result (hit|miss|bypass|error)",
+            ),
+            &["result"],
+        )?;
+        let response_cache_invalidations = IntCounterVec::new(
+            Opts::new(
+                "gateway_response_cache_invalidations_total",
+                "Tenant response cache invalidations grouped by source (kafka|internal)",
+            ),
+            &["source"],
+        )?;
+        registry.register(Box::new(response_cache_lookups.clone()))?;
+        registry.register(Box::new(response_cache_invalidations.clone()))?;
+        let kill_switch_active = IntGaugeVec::new(
+            Opts::new("gateway_kill_switch_active", "1 while a kill switch is enabled on this instance"),
+            &["switch"],
+        )?;
+        let kill_switch_rejections = IntCounterVec::new(
+            Opts::new("gateway_kill_switch_rejections_total", "Requests answered with 503, grouped by the switch that blocked them"),
+            &["switch"],
+        )?;
+        let kill_switch_changes = IntCounterVec::new(
+            Opts::new("gateway_kill_switch_changes_total", "Kill switch changes saved through this instance, grouped by action (enabled|disabled)"),
+            &["switch", "action"],
+        )?;
+        registry.register(Box::new(kill_switch_active.clone()))?;
+        registry.register(Box::new(kill_switch_rejections.clone()))?;
+        registry.register(Box::new(kill_switch_changes.clone()))?;
+        let redis_up = IntGauge::with_opts(Opts::new(
+            "gateway_redis_up",
+            "1 while Redis answers rate limit checks; 0 while the circuit is open and checks use the fallback"
+        ))?;
+        let redis_errors = IntCounterVec::new(
+            Opts::new("gateway_redis_errors_total", "Failed Redis rate limit checks grouped by reason (error|timeout)"),
+            &["reason"],
+        )?;
+        let rate_limit_fallback = IntCounterVec::new(
+            Opts::new(
+                "gateway_rate_limit_fallback_total",
+                "Requests rate limited without Redis, grouped by route class and outcome (allowed|rejected|fail_closed)",
+            ),
+            &["route_class", "outcome"],
+        )?;
+        let rate_limit_fallback_keys = IntGauge::with_opts(Opts::new(
+            "gateway_rate_limit_fallback_keys",
+            "Keys held by this instance's in-memory fallback limiter"
+        ))?;
+        registry.register(Box::new(redis_up.clone()))?;
+        registry.register(Box::new(redis_errors.clone()))?;
+        registry.register(Box::new(rate_limit_fallback.clone()))?;
+        registry.register(Box::new(rate_limit_fallback_keys.clone()))?;
         Ok(Self {
             registry,
             rate_checks,
@@ -93,6 +190,18 @@ impl GatewayMetrics {
             rate_window_usage,
             rate_limit_rpm_target,
             build_info,
+            key_cache_events,
+            key_cache_size,
+            external_orders,
+            response_cache_lookups,
+            response_cache_invalidations,
+            kill_switch_active,
+            kill_switch_rejections,
+            kill_switch_changes,
+            redis_up,
+            redis_errors,
+            rate_limit_fallback,
+            rate_limit_fallback_keys,
         })
     }
 
@@ -107,18 +216,75 @@ impl GatewayMetrics {
         self.api_key_requests.with_label_values(&[result]).inc();
     }
 
-    pub fn render(&self) -> Result<Response> {
-        let encoder = TextEncoder::new();
-        let metric_families = self.registry.gather();
-        let mut buffer = Vec::new();
-        encoder.encode(&metric_families, &mut buffer)?;
+    pub fn record_key_cache_event(&self, source: &str, outcome: &str) {
+        self.key_cache_events.with_label_values(&[source, outcome]).inc();
+    }
+
+    pub fn set_key_cache_size(&self, size: usize) {
+        self.key_cache_size.set(size as i64);
+    }
+
+    pub fn record_external_order(&self, partner: &str, outcome: &str) {
+        self.record_external_orders(partner, outcome, 1);
+    }
+
+    pub fn record_external_orders(&self, partner: &str, outcome: &str, count: u64) {
+        self.external_orders.with_label_values(&[partner, outcome]).inc_by(count);
+    }
+
+    pub fn record_response_cache(&self, result: &str) {
+        self.response_cache_lookups.with_label_values(&[result]).inc();
+    }
+
+    pub fn record_response_cache_invalidation(&self, source: &str) {
+        self.response_cache_invalidations.with_label_values(&[source]).inc();
+    }
+
+    pub fn set_kill_switch_active(&self, switch: &str, active: bool) {
+        self.kill_switch_active.with_label_values(&[switch]).set(i64::from(active));
+    }
+
+    pub fn record_kill_switch_rejection(&self, switch: &str) {
+        self.kill_switch_rejections.with_label_values(&[switch]).inc();
+    }
+
+    pub fn record_kill_switch_change(&self, switch: &str, action: &str) {
+        self.kill_switch_changes.with_label_values(&[switch, action]).inc();
+    }
+
+    pub fn set_redis_up(&self, up: bool) {
+        self.redis_up.set(i64::from(up));
+    }
+
+    pub fn record_redis_error(&self, reason: &str) {
+        self.redis_errors.with_label_values(&[reason]).inc();
+    }
+
+    pub fn record_rate_limit_fallback(&self, route_class: &str, outcome: &str) {
+        self.rate_limit_fallback.with_label_values(&[route_class, outcome]).inc();
+    }
+
+    pub fn set_rate_limit_fallback_keys(&self, keys: usize) {
+        self.rate_limit_fallback_keys.set(keys as i64);
+    }
+
+    /// Registry rendered by `/metrics`, for collectors owned elsewhere (e.g. SLO counters).
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Renders the gateway registry plus the shared HTTP metrics (route latency, error codes) in
+    /// the format `accept` negotiates; see `common_http_errors::exposition`.
+    pub fn render(&self, accept: Option<&str>) -> Result<Response> {
+        let mut metric_families = self.registry.gather();
+        let own: HashSet<String> = metric_families.iter().map(|f| f.get_name().to_string()).collect();
+        metric_families.extend(prometheus::gather().into_iter().filter(|f| !own.contains(f.get_name())));
+        metric_families.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        let exposition = exposition::encode(&metric_families, accept)?;
         let response = Response::builder()
             .status(StatusCode::OK)
-            .header(
-                header::CONTENT_TYPE,
-                HeaderValue::from_static("text/plain; version=0.0.4"),
-            )
-            .body(Body::from(buffer))?;
+            .header(header::CONTENT_TYPE, HeaderValue::from_static(exposition.content_type))
+            .body(Body::from(exposition.body))?;
         Ok(response)
     }
 
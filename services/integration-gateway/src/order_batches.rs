@@ -0,0 +1,406 @@
+//! Bulk external order ingestion. `POST /external/orders/batch` maps every order through the
+//! caller's partner profile, stores the valid ones as queued items and returns 202 right away.
+//! A background worker forwards queued items to order-service; partners follow progress with
+//! `GET /external/orders/batch/:id`.
+//!
+//! The `external_order_batch_items` table is the queue, so queued work survives restarts. Each
+//! item is sent with the idempotency key `batch:{batch_id}:{seq}`, which makes retries after a
+//! 5xx, a timeout or a crash mid-item safe.
+//!
+//! Per-tenant concurrency is bounded by claiming at most `EXTERNAL_ORDER_TENANT_CONCURRENCY`
+//! items per tenant per round; a round finishes before the next one is claimed. The limit applies
+//! per gateway instance.
+
+use std::time::Duration;
+
+use axum::extract::{Extension, Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use common_http_errors::{ApiError, ApiResult};
+use common_security::SecurityCtxExtractor;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::integration_handlers::{forward_order, resolve_mappings, ExternalOrder, IntegrationKeyHash};
+use crate::partner_profiles::map_order;
+use crate::AppState;
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).filter(|v| *v > 0).unwrap_or(default)
+}
+
+/// Largest accepted batch (`EXTERNAL_ORDER_BATCH_MAX`, default 500).
+static BATCH_MAX: Lazy<usize> = Lazy::new(|| env_usize("EXTERNAL_ORDER_BATCH_MAX", 500));
+/// Order-service calls in flight per tenant (`EXTERNAL_ORDER_TENANT_CONCURRENCY`, default 4).
+static TENANT_CONCURRENCY: Lazy<usize> = Lazy::new(|| env_usize("EXTERNAL_ORDER_TENANT_CONCURRENCY", 4));
+
+/// Deliveries per item before a retryable failure becomes final.
+const MAX_ATTEMPTS: i32 = 5;
+/// Items stuck in `processing` this long (worker died mid-call) are claimed again.
+const STALE_PROCESSING_SECS: f64 = 300.0;
+
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempts.clamp(1, 8) as u32))
+}
+
+#[derive(Deserialize)]
+pub struct OrderBatchRequest {
+    pub orders: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBatchItem {
+    pub index: i32,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<Uuid>,
+    /// Per-field validation errors for `invalid` items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Value>,
+    /// Last delivery error for `failed` items and items waiting for a retry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub attempts: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBatch {
+    pub id: Uuid,
+    /// `processing` until every item is `created`, `failed` or `invalid`, then `completed`.
+    pub status: &'static str,
+    pub partner: String,
+    pub total: i32,
+    pub pending: i64,
+    pub created: i64,
+    pub failed: i64,
+    pub invalid: i64,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<OrderBatchItem>>,
+}
+
+fn require_db(state: &AppState, trace_id: Option<Uuid>) -> ApiResult<PgPool> {
+    state.db.clone().ok_or(ApiError::Internal { trace_id, message: Some("Batch ingestion unavailable".into()) })
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |err| {
+        if matches!(err, sqlx::Error::PoolTimedOut) {
+            return common_db::db_error(err, trace_id);
+        }
+        tracing::error!(?err, "External order batch query failed");
+        ApiError::Internal { trace_id, message: Some("External order batch query failed".into()) }
+    }
+}
+
+async fn fetch_batch(pool: &PgPool, tenant_id: Uuid, batch_id: Uuid, with_items: bool) -> Result<Option<OrderBatch>, sqlx::Error> {
+    let Some(row) = sqlx::query(
+        "SELECT b.id, b.partner, b.item_count, b.created_at, b.completed_at,
+                COUNT(*) FILTER (WHERE i.status IN ('pending','processing')) AS pending,
+                COUNT(*) FILTER (WHERE i.status = 'created') AS created,
+                COUNT(*) FILTER (WHERE i.status = 'failed') AS failed,
+                COUNT(*) FILTER (WHERE i.status = 'invalid') AS invalid
+         FROM external_order_batches b
+         LEFT JOIN external_order_batch_items i ON i.batch_id = b.id
+         WHERE b.id = $1 AND b.tenant_id = $2
+         GROUP BY b.id",
+    )
+    .bind(batch_id)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let items = if with_items {
+        let rows = sqlx::query(
+            "SELECT seq, status, order_id, errors, error, attempts FROM external_order_batch_items WHERE batch_id = $1 ORDER BY seq",
+        )
+        .bind(batch_id)
+        .fetch_all(pool)
+        .await?;
+        Some(
+            rows.iter()
+                .map(|r| {
+                    Ok(OrderBatchItem {
+                        index: r.try_get("seq")?,
+                        status: r.try_get("status")?,
+                        order_id: r.try_get("order_id")?,
+                        errors: r.try_get("errors")?,
+                        error: r.try_get("error")?,
+                        attempts: r.try_get("attempts")?,
+                    })
+                })
+                .collect::<Result<Vec<_>, sqlx::Error>>()?,
+        )
+    } else {
+        None
+    };
+    let completed_at: Option<DateTime<Utc>> = row.try_get("completed_at")?;
+    Ok(Some(OrderBatch {
+        id: row.try_get("id")?,
+        status: if completed_at.is_some() { "completed" } else { "processing" },
+        partner: row.try_get("partner")?,
+        total: row.try_get("item_count")?,
+        pending: row.try_get("pending")?,
+        created: row.try_get("created")?,
+        failed: row.try_get("failed")?,
+        invalid: row.try_get("invalid")?,
+        created_at: row.try_get("created_at")?,
+        completed_at,
+        items,
+    }))
+}
+
+/// Queue up to `EXTERNAL_ORDER_BATCH_MAX` orders. Invalid orders are recorded with their field
+/// errors and never sent; the rest are created asynchronously.
+pub async fn submit_order_batch(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    integration_key: Option<Extension<IntegrationKeyHash>>,
+    Json(req): Json<OrderBatchRequest>,
+) -> ApiResult<(StatusCode, Json<OrderBatch>)> {
+    let pool = require_db(&state, sec.trace_id)?;
+    if req.orders.is_empty() {
+        return Err(ApiError::BadRequest { code: "empty_batch", trace_id: sec.trace_id, message: Some("orders must not be empty".into()) });
+    }
+    if req.orders.len() > *BATCH_MAX {
+        return Err(ApiError::BadRequest {
+            code: "batch_too_large",
+            trace_id: sec.trace_id,
+            message: Some(format!("at most {} orders per batch", *BATCH_MAX)),
+        });
+    }
+    let tenant_id = sec.tenant_id;
+    let (kind, mappings) = resolve_mappings(&state, tenant_id, integration_key.as_ref().map(|Extension(k)| k), sec.trace_id).await?;
+    let partner = kind.as_str();
+
+    let batch_id = Uuid::new_v4();
+    let items: Vec<(i32, &'static str, Option<Value>, Option<Value>)> = req
+        .orders
+        .iter()
+        .enumerate()
+        .map(|(index, payload)| match map_order(&mappings, payload) {
+            Ok(order) => (index as i32, "pending", Some(serde_json::to_value(order).expect("order serializes")), None),
+            Err(errors) => (index as i32, "invalid", None, Some(serde_json::to_value(errors).expect("errors serialize"))),
+        })
+        .collect();
+    let queued = items.iter().filter(|(_, status, _, _)| *status == "pending").count();
+
+    let mut tx = pool.begin().await.map_err(db_error(sec.trace_id))?;
+    sqlx::query(
+        "INSERT INTO external_order_batches (id, tenant_id, partner, item_count, completed_at)
+         VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN now() END)",
+    )
+    .bind(batch_id)
+    .bind(tenant_id)
+    .bind(partner)
+    .bind(items.len() as i32)
+    .bind(queued == 0)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    let mut insert: QueryBuilder<Postgres> =
+        QueryBuilder::new("INSERT INTO external_order_batch_items (batch_id, seq, tenant_id, status, payload, errors, completed_at) ");
+    insert.push_values(items, |mut row, (seq, status, payload, errors)| {
+        let done = status == "invalid";
+        row.push_bind(batch_id)
+            .push_bind(seq)
+            .push_bind(tenant_id)
+            .push_bind(status)
+            .push_bind(payload)
+            .push_bind(errors)
+            .push("CASE WHEN ")
+            .push_bind_unseparated(done)
+            .push_unseparated(" THEN now() END");
+    });
+    insert.build().execute(&mut *tx).await.map_err(db_error(sec.trace_id))?;
+    tx.commit().await.map_err(db_error(sec.trace_id))?;
+
+    let batch = fetch_batch(&pool, tenant_id, batch_id, false)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .ok_or(ApiError::Internal { trace_id: sec.trace_id, message: Some("Batch vanished after insert".into()) })?;
+    state.metrics.record_external_orders(partner, "queued", queued as u64);
+    state.metrics.record_external_orders(partner, "invalid", batch.invalid as u64);
+    tracing::info!(tenant_id = %tenant_id, batch_id = %batch_id, total = batch.total, queued, partner, "External order batch accepted");
+    Ok((StatusCode::ACCEPTED, Json(batch)))
+}
+
+pub async fn get_order_batch(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(batch_id): Path<Uuid>,
+) -> ApiResult<Json<OrderBatch>> {
+    let pool = require_db(&state, sec.trace_id)?;
+    fetch_batch(&pool, sec.tenant_id, batch_id, true)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .map(Json)
+        .ok_or(ApiError::NotFound { code: "batch_not_found", trace_id: sec.trace_id })
+}
+
+struct ClaimedItem {
+    batch_id: Uuid,
+    seq: i32,
+    tenant_id: Uuid,
+    partner: String,
+    payload: Value,
+    attempts: i32,
+}
+
+async fn claim_items(pool: &PgPool) -> Result<Vec<ClaimedItem>, sqlx::Error> {
+    // The status check is repeated in the UPDATE so two gateway instances can't claim one item.
+    let rows = sqlx::query(
+        "WITH ready AS (
+             SELECT batch_id, seq, ROW_NUMBER() OVER (PARTITION BY tenant_id ORDER BY available_at, batch_id, seq) AS rn
+             FROM external_order_batch_items
+             WHERE (status = 'pending' AND available_at <= now())
+                OR (status = 'processing' AND attempted_at < now() - make_interval(secs => $2))
+         )
+         UPDATE external_order_batch_items i
+            SET status = 'processing', attempts = i.attempts + 1, attempted_at = now()
+           FROM ready r
+          WHERE i.batch_id = r.batch_id AND i.seq = r.seq AND r.rn <= $1
+            AND ((i.status = 'pending' AND i.available_at <= now())
+              OR (i.status = 'processing' AND i.attempted_at < now() - make_interval(secs => $2)))
+         RETURNING i.batch_id, i.seq, i.tenant_id, i.payload, i.attempts,
+                   (SELECT partner FROM external_order_batches b WHERE b.id = i.batch_id) AS partner",
+    )
+    .bind(*TENANT_CONCURRENCY as i64)
+    .bind(STALE_PROCESSING_SECS)
+    .fetch_all(pool)
+    .await?;
+    rows.iter()
+        .map(|r| {
+            Ok(ClaimedItem {
+                batch_id: r.try_get("batch_id")?,
+                seq: r.try_get("seq")?,
+                tenant_id: r.try_get("tenant_id")?,
+                partner: r.try_get("partner")?,
+                payload: r.try_get("payload")?,
+                attempts: r.try_get("attempts")?,
+            })
+        })
+        .collect()
+}
+
+/// Deliver one claimed item and record the outcome. Returns the outcome label.
+async fn deliver(state: &AppState, pool: &PgPool, item: ClaimedItem) -> Result<&'static str, sqlx::Error> {
+    let result = match serde_json::from_value::<ExternalOrder>(item.payload) {
+        Ok(mut order) => {
+            order.idempotency_key = Some(format!("batch:{}:{}", item.batch_id, item.seq));
+            forward_order(state, item.tenant_id, &order).await.map_err(|err| (err.retryable(), err.message))
+        }
+        Err(err) => Err((false, format!("Stored order is unreadable: {err}"))),
+    };
+    let outcome = match result {
+        Ok(order) => {
+            sqlx::query(
+                "UPDATE external_order_batch_items SET status = 'created', order_id = $3, error = NULL, completed_at = now()
+                 WHERE batch_id = $1 AND seq = $2",
+            )
+            .bind(item.batch_id)
+            .bind(item.seq)
+            .bind(order.id)
+            .execute(pool)
+            .await?;
+            "created"
+        }
+        Err((true, message)) if item.attempts < MAX_ATTEMPTS => {
+            sqlx::query(
+                "UPDATE external_order_batch_items SET status = 'pending', error = $3, available_at = now() + make_interval(secs => $4)
+                 WHERE batch_id = $1 AND seq = $2",
+            )
+            .bind(item.batch_id)
+            .bind(item.seq)
+            .bind(&message)
+            .bind(retry_delay(item.attempts).as_secs_f64())
+            .execute(pool)
+            .await?;
+            "retry"
+        }
+        Err((_, message)) => {
+            sqlx::query(
+                "UPDATE external_order_batch_items SET status = 'failed', error = $3, completed_at = now()
+                 WHERE batch_id = $1 AND seq = $2",
+            )
+            .bind(item.batch_id)
+            .bind(item.seq)
+            .bind(&message)
+            .execute(pool)
+            .await?;
+            "failed"
+        }
+    };
+    if outcome != "retry" {
+        state.metrics.record_external_order(&item.partner, outcome);
+        sqlx::query(
+            "UPDATE external_order_batches SET completed_at = now()
+             WHERE id = $1 AND completed_at IS NULL
+               AND NOT EXISTS (SELECT 1 FROM external_order_batch_items WHERE batch_id = $1 AND status IN ('pending','processing'))",
+        )
+        .bind(item.batch_id)
+        .execute(pool)
+        .await?;
+    }
+    Ok(outcome)
+}
+
+/// Claim one round of ready items, deliver them concurrently and wait for all of them.
+/// Returns how many items were claimed.
+pub async fn process_ready_items(state: &AppState, pool: &PgPool) -> anyhow::Result<usize> {
+    let claimed = claim_items(pool).await?;
+    let count = claimed.len();
+    let mut deliveries = JoinSet::new();
+    for item in claimed {
+        let (state, pool) = (state.clone(), pool.clone());
+        let (batch_id, seq) = (item.batch_id, item.seq);
+        deliveries.spawn(async move {
+            if let Err(err) = deliver(&state, &pool, item).await {
+                // The item stays `processing` and is claimed again once stale.
+                tracing::warn!(?err, %batch_id, seq, "Failed to record external order delivery");
+            }
+        });
+    }
+    while deliveries.join_next().await.is_some() {}
+    Ok(count)
+}
+
+/// Background worker; polls every `EXTERNAL_ORDER_BATCH_POLL_MS` (default 1000) while idle.
+pub fn spawn_batch_worker(state: AppState, pool: PgPool) {
+    let idle = Duration::from_millis(env_usize("EXTERNAL_ORDER_BATCH_POLL_MS", 1000) as u64);
+    tokio::spawn(async move {
+        loop {
+            match process_ready_items(&state, &pool).await {
+                Ok(0) => tokio::time::sleep(idle).await,
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!(?err, "External order batch worker failed to claim items");
+                    tokio::time::sleep(idle).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_exponentially_and_cap() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(8));
+        assert_eq!(retry_delay(40), Duration::from_secs(256));
+    }
+}
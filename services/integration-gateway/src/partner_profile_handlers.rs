@@ -0,0 +1,111 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_any_role, Role, SecurityContext, SecurityCtxExtractor};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::partner_profiles::{self, FieldMappings, PartnerKind, PartnerProfile};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct PartnerProfileRequest {
+    pub partner: String,
+    /// Omit to use the partner's preset; required for `custom`.
+    #[serde(default)]
+    pub mappings: Option<serde_json::Value>,
+}
+
+/// Tenant admins manage the profiles of their own integration keys.
+fn authorize(state: &AppState, sec: &SecurityContext) -> ApiResult<PgPool> {
+    ensure_any_role(sec, &[Role::Admin, Role::SuperAdmin])
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "admin", trace_id: sec.trace_id })?;
+    state.db.clone().ok_or(ApiError::Internal { trace_id: sec.trace_id, message: Some("Database unavailable".into()) })
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |err| {
+        if matches!(err, sqlx::Error::PoolTimedOut) {
+            return common_db::db_error(err, trace_id);
+        }
+        tracing::error!(?err, "Partner profile query failed");
+        ApiError::Internal { trace_id, message: Some("Partner profile query failed".into()) }
+    }
+}
+
+fn parse_request(req: PartnerProfileRequest, trace_id: Option<Uuid>) -> ApiResult<(PartnerKind, Option<FieldMappings>)> {
+    let partner = PartnerKind::parse(&req.partner).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_partner",
+        trace_id,
+        message: Some("partner must be one of native, shopify, woocommerce, custom".into()),
+    })?;
+    let mappings = req
+        .mappings
+        .map(serde_json::from_value::<FieldMappings>)
+        .transpose()
+        .map_err(|err| ApiError::BadRequest { code: "invalid_mappings", trace_id, message: Some(err.to_string()) })?;
+    if partner == PartnerKind::Custom && mappings.is_none() {
+        return Err(ApiError::BadRequest {
+            code: "missing_mappings",
+            trace_id,
+            message: Some("custom partner profiles need field mappings".into()),
+        });
+    }
+    Ok((partner, mappings))
+}
+
+pub async fn list_partner_profiles(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> ApiResult<Json<Vec<PartnerProfile>>> {
+    let pool = authorize(&state, &sec)?;
+    let profiles = partner_profiles::list_profiles(&pool, sec.tenant_id).await.map_err(db_error(sec.trace_id))?;
+    Ok(Json(profiles))
+}
+
+pub async fn put_partner_profile(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(key_id): Path<Uuid>,
+    Json(req): Json<PartnerProfileRequest>,
+) -> ApiResult<Json<PartnerProfile>> {
+    let pool = authorize(&state, &sec)?;
+    let (partner, mappings) = parse_request(req, sec.trace_id)?;
+    let profile = partner_profiles::upsert_profile(&pool, sec.tenant_id, key_id, partner, mappings.as_ref())
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "integration_key_not_found", trace_id: sec.trace_id })?;
+    tracing::info!(tenant_id = %sec.tenant_id, key_id = %key_id, partner = partner.as_str(), "Partner profile saved");
+    Ok(Json(profile))
+}
+
+pub async fn delete_partner_profile(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(key_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    let pool = authorize(&state, &sec)?;
+    if partner_profiles::delete_profile(&pool, sec.tenant_id, key_id).await.map_err(db_error(sec.trace_id))? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound { code: "partner_profile_not_found", trace_id: sec.trace_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn custom_profiles_require_valid_mappings() {
+        let req = |partner: &str, mappings| PartnerProfileRequest { partner: partner.into(), mappings };
+        assert!(matches!(parse_request(req("shopify", None), None), Ok((PartnerKind::Shopify, None))));
+        assert!(matches!(parse_request(req("magento", None), None), Err(ApiError::BadRequest { code: "invalid_partner", .. })));
+        assert!(matches!(parse_request(req("custom", None), None), Err(ApiError::BadRequest { code: "missing_mappings", .. })));
+        let partial = json!({"items": "lines"});
+        assert!(matches!(parse_request(req("custom", Some(partial)), None), Err(ApiError::BadRequest { code: "invalid_mappings", .. })));
+    }
+}
@@ -0,0 +1,470 @@
+//! Partner profiles translate an external system's order payload (Shopify, WooCommerce, or a
+//! partner-specific shape) into the gateway's [`ExternalOrder`] before it is forwarded to
+//! order-service.
+//!
+//! A profile is attached to one integration key. Built-in partners come with preset
+//! [`FieldMappings`]; `custom` profiles must supply their own. Keys without a profile keep using
+//! the native shape (`items[].product_id`, `items[].quantity`, `payment_method`, `total`).
+//!
+//! Mapping never stops at the first problem: every field is checked and each failure is reported
+//! as a [`FieldError`] so partners can fix a payload in one round trip.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::integration_handlers::{ExternalOrder, OrderItem};
+
+/// Tender types order-service accepts on `POST /orders`.
+const SUPPORTED_PAYMENT_METHODS: &[&str] = &["cash", "card"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartnerKind {
+    /// The gateway's own payload shape; used when a key has no profile.
+    Native,
+    Shopify,
+    WooCommerce,
+    Custom,
+}
+
+impl PartnerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartnerKind::Native => "native",
+            PartnerKind::Shopify => "shopify",
+            PartnerKind::WooCommerce => "woocommerce",
+            PartnerKind::Custom => "custom",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "native" => Some(PartnerKind::Native),
+            "shopify" => Some(PartnerKind::Shopify),
+            "woocommerce" | "woo" => Some(PartnerKind::WooCommerce),
+            "custom" => Some(PartnerKind::Custom),
+            _ => None,
+        }
+    }
+}
+
+/// One step applied to a source value, in order, after it is read from the payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Transform {
+    Trim,
+    Lowercase,
+    Uppercase,
+    /// Parse numeric strings such as `"19.99"`.
+    ToNumber,
+    /// Parse integral values such as `"2"` or `2.0`.
+    ToInteger,
+    /// Minor currency units to major (`1999` -> `19.99`).
+    MinorToMajor,
+    /// Replace a value using a lookup table. Unlisted values use `fallback`, or fail without one.
+    Map {
+        values: HashMap<String, String>,
+        #[serde(default)]
+        fallback: Option<String>,
+    },
+}
+
+/// Where one order field comes from. `path` is dot-separated; numeric segments index arrays
+/// (`payment_gateway_names.0`). `default` is used when the path is missing or null.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldRule {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<Transform>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+impl FieldRule {
+    fn path(path: &str) -> Self {
+        Self { path: path.to_string(), transforms: Vec::new(), default: None }
+    }
+
+    fn with(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+}
+
+/// How to build an [`ExternalOrder`] from a partner payload. `product_id` and `quantity` are read
+/// relative to each element of the `items` array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMappings {
+    pub items: String,
+    pub product_id: FieldRule,
+    pub quantity: FieldRule,
+    pub payment_method: FieldRule,
+    pub total: FieldRule,
+}
+
+fn tender_map(pairs: &[(&str, &str)]) -> Transform {
+    Transform::Map {
+        values: pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        fallback: None,
+    }
+}
+
+impl FieldMappings {
+    /// Preset mappings for built-in partners. `Custom` has none.
+    ///
+    /// Shopify and WooCommerce line items carry the POS product id in `sku`.
+    pub fn preset(kind: PartnerKind) -> Option<Self> {
+        match kind {
+            PartnerKind::Native => Some(Self {
+                items: "items".into(),
+                product_id: FieldRule::path("product_id"),
+                quantity: FieldRule::path("quantity"),
+                payment_method: FieldRule::path("payment_method").with(Transform::Trim).with(Transform::Lowercase),
+                total: FieldRule::path("total"),
+            }),
+            PartnerKind::Shopify => Some(Self {
+                items: "line_items".into(),
+                product_id: FieldRule::path("sku").with(Transform::Trim),
+                quantity: FieldRule::path("quantity").with(Transform::ToInteger),
+                payment_method: FieldRule::path("payment_gateway_names.0")
+                    .with(Transform::Lowercase)
+                    .with(tender_map(&[("shopify_payments", "card"), ("stripe", "card"), ("cash", "cash"), ("cash on delivery (cod)", "cash")])),
+                total: FieldRule::path("total_price").with(Transform::ToNumber),
+            }),
+            PartnerKind::WooCommerce => Some(Self {
+                items: "line_items".into(),
+                product_id: FieldRule::path("sku").with(Transform::Trim),
+                quantity: FieldRule::path("quantity").with(Transform::ToInteger),
+                payment_method: FieldRule::path("payment_method")
+                    .with(Transform::Lowercase)
+                    .with(tender_map(&[("cod", "cash"), ("stripe", "card"), ("woocommerce_payments", "card"), ("square_credit_card", "card")])),
+                total: FieldRule::path("total").with(Transform::ToNumber),
+            }),
+            PartnerKind::Custom => None,
+        }
+    }
+}
+
+/// A profile as stored for an integration key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartnerProfile {
+    pub integration_key_id: Uuid,
+    pub partner: String,
+    /// Explicit mappings; `None` means the partner's preset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mappings: Option<FieldMappings>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PartnerProfile {
+    pub fn kind(&self) -> PartnerKind {
+        PartnerKind::parse(&self.partner).unwrap_or(PartnerKind::Custom)
+    }
+
+    /// Mappings used for requests: the stored override, else the partner preset.
+    pub fn effective_mappings(&self) -> Option<FieldMappings> {
+        self.mappings.clone().or_else(|| FieldMappings::preset(self.kind()))
+    }
+}
+
+/// A problem with one field of a partner payload. `field` names the order field
+/// (`items[1].quantity`); `source` is the payload path it was read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub code: &'static str,
+    pub message: String,
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').filter(|segment| !segment.is_empty()).try_fold(value, |current, segment| match current {
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+        Value::Object(map) => map.get(segment),
+        _ => None,
+    })
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+impl Transform {
+    fn apply(&self, value: Value) -> Result<Value, String> {
+        let text = || as_text(&value).ok_or_else(|| format!("expected a string, got {value}"));
+        match self {
+            Transform::Trim => Ok(Value::String(text()?.trim().to_string())),
+            Transform::Lowercase => Ok(Value::String(text()?.to_lowercase())),
+            Transform::Uppercase => Ok(Value::String(text()?.to_uppercase())),
+            Transform::ToNumber => as_number(&value)
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("expected a number, got {value}")),
+            Transform::ToInteger => as_number(&value)
+                .filter(|n| n.fract() == 0.0 && n.abs() <= i64::MAX as f64)
+                .map(|n| Value::from(n as i64))
+                .ok_or_else(|| format!("expected a whole number, got {value}")),
+            Transform::MinorToMajor => as_number(&value)
+                .and_then(|n| serde_json::Number::from_f64(n / 100.0))
+                .map(Value::Number)
+                .ok_or_else(|| format!("expected an amount in minor units, got {value}")),
+            Transform::Map { values, fallback } => {
+                let key = text()?;
+                values
+                    .get(&key)
+                    .or(fallback.as_ref())
+                    .map(|mapped| Value::String(mapped.clone()))
+                    .ok_or_else(|| format!("no mapping for value '{key}'"))
+            }
+        }
+    }
+}
+
+struct Mapper<'a> {
+    errors: Vec<FieldError>,
+    source_prefix: &'a str,
+}
+
+impl Mapper<'_> {
+    fn source(&self, path: &str) -> String {
+        if self.source_prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}.{path}", self.source_prefix)
+        }
+    }
+
+    fn fail(&mut self, field: &str, source: Option<String>, code: &'static str, message: String) {
+        self.errors.push(FieldError { field: field.to_string(), source, code, message });
+    }
+
+    fn read(&mut self, payload: &Value, field: &str, rule: &FieldRule) -> Option<Value> {
+        let source = self.source(&rule.path);
+        let raw = match lookup(payload, &rule.path).filter(|v| !v.is_null()).or(rule.default.as_ref()) {
+            Some(value) => value.clone(),
+            None => {
+                self.fail(field, Some(source), "missing_field", format!("{field} is required"));
+                return None;
+            }
+        };
+        match rule.transforms.iter().try_fold(raw, |value, transform| transform.apply(value)) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.fail(field, Some(source), "invalid_value", message);
+                None
+            }
+        }
+    }
+}
+
+/// Build an order from a partner payload, reporting every invalid field.
+pub fn map_order(mappings: &FieldMappings, payload: &Value) -> Result<ExternalOrder, Vec<FieldError>> {
+    let mut mapper = Mapper { errors: Vec::new(), source_prefix: "" };
+
+    let mut items = Vec::new();
+    match lookup(payload, &mappings.items) {
+        Some(Value::Array(lines)) if !lines.is_empty() => {
+            for (index, line) in lines.iter().enumerate() {
+                let prefix = format!("{}.{index}", mappings.items);
+                let mut line_mapper = Mapper { errors: std::mem::take(&mut mapper.errors), source_prefix: &prefix };
+                let product_field = format!("items[{index}].product_id");
+                let quantity_field = format!("items[{index}].quantity");
+                let product_id = line_mapper.read(line, &product_field, &mappings.product_id).and_then(|value| {
+                    let parsed = as_text(&value).and_then(|s| Uuid::parse_str(s.trim()).ok());
+                    if parsed.is_none() {
+                        let source = line_mapper.source(&mappings.product_id.path);
+                        line_mapper.fail(&product_field, Some(source), "invalid_uuid", format!("expected a product UUID, got {value}"));
+                    }
+                    parsed
+                });
+                let quantity = line_mapper.read(line, &quantity_field, &mappings.quantity).and_then(|value| {
+                    let parsed = value.as_i64().and_then(|q| i32::try_from(q).ok()).filter(|q| *q > 0);
+                    if parsed.is_none() {
+                        let source = line_mapper.source(&mappings.quantity.path);
+                        line_mapper.fail(&quantity_field, Some(source), "invalid_quantity", format!("quantity must be a positive whole number, got {value}"));
+                    }
+                    parsed
+                });
+                mapper.errors = line_mapper.errors;
+                if let (Some(product_id), Some(quantity)) = (product_id, quantity) {
+                    items.push(OrderItem { product_id, quantity });
+                }
+            }
+        }
+        Some(Value::Array(_)) => {
+            mapper.fail("items", Some(mappings.items.clone()), "empty_items", "at least one line item is required".into())
+        }
+        Some(other) => mapper.fail("items", Some(mappings.items.clone()), "invalid_value", format!("expected an array, got {other}")),
+        None => mapper.fail("items", Some(mappings.items.clone()), "missing_field", "items is required".into()),
+    }
+
+    let payment_method = mapper.read(payload, "payment_method", &mappings.payment_method).and_then(|value| {
+        let method = as_text(&value).filter(|m| SUPPORTED_PAYMENT_METHODS.contains(&m.as_str()));
+        if method.is_none() {
+            let source = mapper.source(&mappings.payment_method.path);
+            mapper.fail(
+                "payment_method",
+                Some(source),
+                "unsupported_payment_method",
+                format!("payment_method must be one of {}, got {value}", SUPPORTED_PAYMENT_METHODS.join(", ")),
+            );
+        }
+        method
+    });
+
+    let total = mapper.read(payload, "total", &mappings.total).and_then(|value| {
+        let total = value.as_f64().filter(|t| t.is_finite() && *t >= 0.0);
+        if total.is_none() {
+            let source = mapper.source(&mappings.total.path);
+            mapper.fail("total", Some(source), "invalid_total", format!("total must be a non-negative number, got {value}"));
+        }
+        total
+    });
+
+    match (mapper.errors.is_empty(), payment_method, total) {
+        (true, Some(payment_method), Some(total)) => Ok(ExternalOrder { items, payment_method, total, idempotency_key: None }),
+        _ => Err(mapper.errors),
+    }
+}
+
+fn profile_from_row(row: &sqlx::postgres::PgRow) -> Result<PartnerProfile, sqlx::Error> {
+    let mappings: Option<Value> = row.try_get("mappings")?;
+    Ok(PartnerProfile {
+        integration_key_id: row.try_get("integration_key_id")?,
+        partner: row.try_get("partner")?,
+        mappings: mappings.and_then(|m| serde_json::from_value(m).ok()),
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Profile attached to the integration key with this hash, if any.
+pub async fn load_profile_for_key(pool: &PgPool, tenant_id: Uuid, api_key_hash: &str) -> Result<Option<PartnerProfile>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT integration_key_id, partner, mappings, updated_at FROM partner_profiles WHERE api_key_hash = $1 AND tenant_id = $2",
+    )
+    .bind(api_key_hash)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?;
+    row.as_ref().map(profile_from_row).transpose()
+}
+
+pub async fn list_profiles(pool: &PgPool, tenant_id: Uuid) -> Result<Vec<PartnerProfile>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT integration_key_id, partner, mappings, updated_at FROM partner_profiles WHERE tenant_id = $1 ORDER BY updated_at DESC",
+    )
+    .bind(tenant_id)
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(profile_from_row).collect()
+}
+
+/// Attach a profile to an active integration key of the tenant. Returns `None` when no such key
+/// exists.
+pub async fn upsert_profile(
+    pool: &PgPool,
+    tenant_id: Uuid,
+    integration_key_id: Uuid,
+    partner: PartnerKind,
+    mappings: Option<&FieldMappings>,
+) -> Result<Option<PartnerProfile>, sqlx::Error> {
+    let mappings = mappings.map(|m| serde_json::to_value(m).expect("mappings serialize"));
+    let row = sqlx::query(
+        "INSERT INTO partner_profiles (integration_key_id, tenant_id, api_key_hash, partner, mappings)
+         SELECT id, tenant_id, api_key_hash, $3, $4 FROM integration_keys
+         WHERE id = $1 AND tenant_id = $2 AND revoked_at IS NULL
+         ON CONFLICT (integration_key_id) DO UPDATE
+           SET partner = EXCLUDED.partner, mappings = EXCLUDED.mappings, updated_at = now()
+         RETURNING integration_key_id, partner, mappings, updated_at",
+    )
+    .bind(integration_key_id)
+    .bind(tenant_id)
+    .bind(partner.as_str())
+    .bind(mappings)
+    .fetch_optional(pool)
+    .await?;
+    row.as_ref().map(profile_from_row).transpose()
+}
+
+pub async fn delete_profile(pool: &PgPool, tenant_id: Uuid, integration_key_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM partner_profiles WHERE integration_key_id = $1 AND tenant_id = $2")
+        .bind(integration_key_id)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PRODUCT: &str = "6f1c2a52-3c1e-4a8e-9d5b-0a4f3e2b1c7d";
+
+    #[test]
+    fn shopify_preset_maps_line_items_and_gateway() {
+        let payload = json!({
+            "id": 450789469,
+            "total_price": "24.50",
+            "payment_gateway_names": ["Shopify_Payments"],
+            "line_items": [{"sku": format!(" {PRODUCT} "), "quantity": 2}, {"sku": PRODUCT, "quantity": "1"}]
+        });
+        let order = map_order(&FieldMappings::preset(PartnerKind::Shopify).unwrap(), &payload).unwrap();
+        assert_eq!(order.payment_method, "card");
+        assert_eq!(order.total, 24.5);
+        assert_eq!(order.items.iter().map(|i| (i.product_id.to_string(), i.quantity)).collect::<Vec<_>>(), vec![(PRODUCT.to_string(), 2), (PRODUCT.to_string(), 1)]);
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let payload = json!({
+            "payment_method": "bacs",
+            "line_items": [{"sku": "not-a-uuid", "quantity": 0}, {"quantity": 1.5}]
+        });
+        let errors = map_order(&FieldMappings::preset(PartnerKind::WooCommerce).unwrap(), &payload).unwrap_err();
+        let found: Vec<(&str, &str)> = errors.iter().map(|e| (e.field.as_str(), e.code)).collect();
+        assert_eq!(found, vec![
+            ("items[0].product_id", "invalid_uuid"),
+            ("items[0].quantity", "invalid_quantity"),
+            ("items[1].product_id", "missing_field"),
+            ("items[1].quantity", "invalid_value"),
+            ("payment_method", "invalid_value"),
+            ("total", "missing_field"),
+        ]);
+        assert_eq!(errors[2].source.as_deref(), Some("line_items.1.sku"));
+    }
+
+    #[test]
+    fn custom_rules_support_defaults_and_minor_units() {
+        let mappings: FieldMappings = serde_json::from_value(json!({
+            "items": "cart.lines",
+            "product_id": {"path": "ref"},
+            "quantity": {"path": "qty", "default": 1},
+            "payment_method": {"path": "tender", "transforms": [{"op": "map", "values": {"CC": "card"}, "fallback": "cash"}]},
+            "total": {"path": "amount_cents", "transforms": [{"op": "minor_to_major"}]}
+        })).unwrap();
+        let payload = json!({"cart": {"lines": [{"ref": PRODUCT}]}, "tender": "VOUCHER", "amount_cents": 1999});
+        let order = map_order(&mappings, &payload).unwrap();
+        assert_eq!((order.items[0].quantity, order.payment_method.as_str(), order.total), (1, "cash", 19.99));
+    }
+}
@@ -0,0 +1,391 @@
+//! Opt-in capture of partner requests and responses, so support can see what an integration sent
+//! and what it got back. Capture is switched on per integration key
+//! (`PUT /admin/integration-keys/:key_id/capture`); only requests authenticated with that key are
+//! recorded.
+//!
+//! Bodies are stored as JSON after masking: [`ALWAYS_MASKED_KEYS`] are masked wherever they
+//! appear, and each key can list extra JSON paths. A body that isn't JSON, is larger than
+//! [`MAX_CAPTURED_BODY_BYTES`] or has no known length is not stored; the exchange records why.
+//! Captures expire after the key's retention (at most [`MAX_RETENTION_HOURS`]) and are purged by
+//! [`spawn_capture_purge`].
+
+use std::time::{Duration, Instant};
+
+use axum::body::{to_bytes, Body, HttpBody};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use common_http_errors::ApiError;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::integration_handlers::IntegrationKeyHash;
+use crate::AppState;
+
+/// Largest request or response body that is stored.
+pub const MAX_CAPTURED_BODY_BYTES: usize = 16 * 1024;
+/// Extra mask paths one key may configure.
+pub const MAX_MASK_PATHS: usize = 50;
+pub const MAX_RETENTION_HOURS: i32 = 72;
+pub const DEFAULT_RETENTION_HOURS: i32 = 24;
+/// Object keys masked at any depth, compared case-insensitively.
+pub const ALWAYS_MASKED_KEYS: &[&str] =
+    &["email", "phone", "card_number", "cvv", "cvc", "password", "token", "api_key", "authorization"];
+
+const MASK: &str = "***";
+
+/// Capture settings of one integration key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSettings {
+    pub integration_key_id: Uuid,
+    pub enabled: bool,
+    /// Dot-separated paths masked in addition to [`ALWAYS_MASKED_KEYS`]; `*` matches every
+    /// element or field, numeric segments index arrays (`customer.addresses.*.line1`).
+    pub mask_paths: Vec<String>,
+    pub retention_hours: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One captured request and its response, as returned to support.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedExchange {
+    pub id: Uuid,
+    pub method: String,
+    pub path: String,
+    pub status: i32,
+    pub duration_ms: i32,
+    pub request_body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_omitted: Option<String>,
+    pub response_body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_omitted: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+fn mask_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if ALWAYS_MASKED_KEYS.iter().any(|masked| key.eq_ignore_ascii_case(masked)) {
+                    *child = Value::String(MASK.into());
+                } else {
+                    mask_keys(child);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask_keys),
+        _ => {}
+    }
+}
+
+fn mask_path(value: &mut Value, segments: &[&str]) {
+    let Some((first, rest)) = segments.split_first() else {
+        *value = Value::String(MASK.into());
+        return;
+    };
+    match value {
+        Value::Object(map) if *first == "*" => map.values_mut().for_each(|child| mask_path(child, rest)),
+        Value::Object(map) => {
+            if let Some(child) = map.get_mut(*first) {
+                mask_path(child, rest);
+            }
+        }
+        Value::Array(items) if *first == "*" => items.iter_mut().for_each(|child| mask_path(child, rest)),
+        Value::Array(items) => {
+            if let Some(child) = first.parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+                mask_path(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mask [`ALWAYS_MASKED_KEYS`] everywhere, then each of `paths`. Paths that don't exist in the
+/// payload are ignored.
+pub fn scrub(mut value: Value, paths: &[String]) -> Value {
+    mask_keys(&mut value);
+    for path in paths {
+        let segments: Vec<&str> = path.split('.').collect();
+        mask_path(&mut value, &segments);
+    }
+    value
+}
+
+/// Check configured mask paths: at most [`MAX_MASK_PATHS`], no empty segments.
+pub fn validate_mask_paths(paths: &[String]) -> Result<(), String> {
+    if paths.len() > MAX_MASK_PATHS {
+        return Err(format!("at most {MAX_MASK_PATHS} mask paths"));
+    }
+    match paths.iter().find(|path| path.len() > 200 || path.split('.').any(str::is_empty)) {
+        Some(path) => Err(format!("'{path}' is not a valid path")),
+        None => Ok(()),
+    }
+}
+
+/// What is stored for one body: the masked JSON, or why it was left out.
+#[derive(Debug, Default, PartialEq)]
+pub struct CapturedBody {
+    pub body: Option<Value>,
+    pub omitted: Option<&'static str>,
+}
+
+impl CapturedBody {
+    fn omitted(reason: &'static str) -> Self {
+        Self { body: None, omitted: Some(reason) }
+    }
+
+    pub fn from_bytes(bytes: &[u8], mask_paths: &[String]) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        if bytes.len() > MAX_CAPTURED_BODY_BYTES {
+            return Self::omitted("too_large");
+        }
+        match serde_json::from_slice::<Value>(bytes) {
+            Ok(value) => Self { body: Some(scrub(value, mask_paths)), omitted: None },
+            Err(_) => Self::omitted("not_json"),
+        }
+    }
+}
+
+/// Buffer a body for capture when its length is known and within the limit. Other bodies pass
+/// through untouched, so capture never holds a large or streamed body in memory.
+async fn buffer(body: Body, mask_paths: &[String]) -> Result<(Body, CapturedBody), axum::Error> {
+    match body.size_hint().exact() {
+        Some(len) if len as usize > MAX_CAPTURED_BODY_BYTES => Ok((body, CapturedBody::omitted("too_large"))),
+        Some(_) => {
+            let bytes = to_bytes(body, MAX_CAPTURED_BODY_BYTES).await?;
+            let captured = CapturedBody::from_bytes(&bytes, mask_paths);
+            Ok((Body::from(bytes), captured))
+        }
+        None => Ok((body, CapturedBody::omitted("streamed"))),
+    }
+}
+
+/// Middleware recording exchanges of integration keys that have capture switched on. Runs inside
+/// the auth middleware, which identifies the key.
+pub async fn capture_exchanges(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let key_hash = request.extensions().get::<IntegrationKeyHash>().map(|key| key.0.clone());
+    let tenant_id = request.extensions().get::<Uuid>().copied();
+    let (Some(pool), Some(key_hash), Some(tenant_id)) = (state.db.clone(), key_hash, tenant_id) else {
+        return next.run(request).await;
+    };
+    let settings = match load_active_settings(&pool, tenant_id, &key_hash).await {
+        Ok(Some(settings)) => settings,
+        Ok(None) => return next.run(request).await,
+        Err(err) => {
+            tracing::warn!(?err, "Failed to load payload capture settings");
+            return next.run(request).await;
+        }
+    };
+
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let (parts, body) = request.into_parts();
+    let (body, request_body) = match buffer(body, &settings.mask_paths).await {
+        Ok(buffered) => buffered,
+        Err(err) => {
+            return ApiError::BadRequest { code: "invalid_body", trace_id: None, message: Some(format!("Failed to read request body: {err}")) }
+                .into_response();
+        }
+    };
+    let response = next.run(Request::from_parts(parts, body)).await;
+    let status = response.status().as_u16();
+    let (parts, body) = response.into_parts();
+    let (body, response_body) = match buffer(body, &settings.mask_paths).await {
+        Ok(buffered) => buffered,
+        Err(err) => {
+            tracing::error!(?err, "Failed to buffer response body for capture");
+            return ApiError::Internal { trace_id: None, message: None }.into_response();
+        }
+    };
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    tokio::spawn(async move {
+        let stored = sqlx::query(
+            "INSERT INTO captured_exchanges
+               (id, tenant_id, integration_key_id, method, path, status, duration_ms,
+                request_body, request_omitted, response_body, response_omitted, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, now() + make_interval(hours => $12))",
+        )
+        .bind(Uuid::new_v4())
+        .bind(tenant_id)
+        .bind(settings.integration_key_id)
+        .bind(method)
+        .bind(path)
+        .bind(i32::from(status))
+        .bind(duration_ms)
+        .bind(request_body.body)
+        .bind(request_body.omitted)
+        .bind(response_body.body)
+        .bind(response_body.omitted)
+        .bind(settings.retention_hours)
+        .execute(&pool)
+        .await;
+        if let Err(err) = stored {
+            tracing::warn!(?err, key_id = %settings.integration_key_id, "Failed to store captured exchange");
+        }
+    });
+    Response::from_parts(parts, body)
+}
+
+const SETTINGS_COLUMNS: &str = "integration_key_id, enabled, mask_paths, retention_hours, updated_at";
+
+fn settings_from_row(row: &sqlx::postgres::PgRow) -> Result<CaptureSettings, sqlx::Error> {
+    let mask_paths: Value = row.try_get("mask_paths")?;
+    Ok(CaptureSettings {
+        integration_key_id: row.try_get("integration_key_id")?,
+        enabled: row.try_get("enabled")?,
+        mask_paths: serde_json::from_value(mask_paths).unwrap_or_default(),
+        retention_hours: row.try_get("retention_hours")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Enabled settings of the integration key with this hash, if any.
+pub async fn load_active_settings(pool: &PgPool, tenant_id: Uuid, api_key_hash: &str) -> Result<Option<CaptureSettings>, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "SELECT {SETTINGS_COLUMNS} FROM payload_capture_settings WHERE api_key_hash = $1 AND tenant_id = $2 AND enabled"
+    ))
+    .bind(api_key_hash)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?;
+    row.as_ref().map(settings_from_row).transpose()
+}
+
+pub async fn get_settings(pool: &PgPool, tenant_id: Uuid, integration_key_id: Uuid) -> Result<Option<CaptureSettings>, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "SELECT {SETTINGS_COLUMNS} FROM payload_capture_settings WHERE integration_key_id = $1 AND tenant_id = $2"
+    ))
+    .bind(integration_key_id)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?;
+    row.as_ref().map(settings_from_row).transpose()
+}
+
+/// Save capture settings for an active integration key of the tenant. Returns `None` when no such
+/// key exists.
+pub async fn upsert_settings(
+    pool: &PgPool,
+    tenant_id: Uuid,
+    integration_key_id: Uuid,
+    enabled: bool,
+    mask_paths: &[String],
+    retention_hours: i32,
+) -> Result<Option<CaptureSettings>, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "INSERT INTO payload_capture_settings (integration_key_id, tenant_id, api_key_hash, enabled, mask_paths, retention_hours)
+         SELECT id, tenant_id, api_key_hash, $3, $4, $5 FROM integration_keys
+         WHERE id = $1 AND tenant_id = $2 AND revoked_at IS NULL
+         ON CONFLICT (integration_key_id) DO UPDATE
+           SET enabled = EXCLUDED.enabled, mask_paths = EXCLUDED.mask_paths,
+               retention_hours = EXCLUDED.retention_hours, updated_at = now()
+         RETURNING {SETTINGS_COLUMNS}"
+    ))
+    .bind(integration_key_id)
+    .bind(tenant_id)
+    .bind(enabled)
+    .bind(serde_json::json!(mask_paths))
+    .bind(retention_hours)
+    .fetch_optional(pool)
+    .await?;
+    row.as_ref().map(settings_from_row).transpose()
+}
+
+/// Switch capture off for a key and drop what was captured for it.
+pub async fn delete_settings(pool: &PgPool, tenant_id: Uuid, integration_key_id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("DELETE FROM payload_capture_settings WHERE integration_key_id = $1 AND tenant_id = $2")
+        .bind(integration_key_id)
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM captured_exchanges WHERE integration_key_id = $1 AND tenant_id = $2")
+        .bind(integration_key_id)
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Unexpired exchanges of one key, newest first.
+pub async fn recent_exchanges(pool: &PgPool, tenant_id: Uuid, integration_key_id: Uuid, limit: i64) -> Result<Vec<CapturedExchange>, sqlx::Error> {
+    sqlx::query_as::<_, CapturedExchange>(
+        "SELECT id, method, path, status, duration_ms, request_body, request_omitted, response_body, response_omitted, created_at
+         FROM captured_exchanges
+         WHERE integration_key_id = $1 AND tenant_id = $2 AND expires_at > now()
+         ORDER BY created_at DESC
+         LIMIT $3",
+    )
+    .bind(integration_key_id)
+    .bind(tenant_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete expired captures every ten minutes.
+pub fn spawn_capture_purge(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(600));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match sqlx::query("DELETE FROM captured_exchanges WHERE expires_at <= now()").execute(&pool).await {
+                Ok(result) if result.rows_affected() > 0 => {
+                    tracing::debug!(purged = result.rows_affected(), "Purged expired payload captures")
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!(?err, "Failed to purge expired payload captures"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pii_keys_and_configured_paths_are_masked() {
+        let payload = json!({
+            "customer": {"Email": "a@example.com", "name": "Ann Lee", "addresses": [{"line1": "1 Main St", "city": "Oslo"}]},
+            "line_items": [{"sku": "A", "quantity": 1}],
+            "payment": {"card_number": "4242424242424242", "last4": "4242"}
+        });
+        let paths = vec!["customer.name".to_string(), "customer.addresses.*.line1".to_string(), "missing.path".to_string()];
+        let scrubbed = scrub(payload, &paths);
+        assert_eq!(scrubbed, json!({
+            "customer": {"Email": "***", "name": "***", "addresses": [{"line1": "***", "city": "Oslo"}]},
+            "line_items": [{"sku": "A", "quantity": 1}],
+            "payment": {"card_number": "***", "last4": "4242"}
+        }));
+    }
+
+    #[test]
+    fn bodies_outside_the_limits_are_not_stored() {
+        assert_eq!(CapturedBody::from_bytes(b"", &[]), CapturedBody::default());
+        assert_eq!(CapturedBody::from_bytes(b"<xml/>", &[]).omitted, Some("not_json"));
+        let large = format!("{{\"blob\":\"{}\"}}", "x".repeat(MAX_CAPTURED_BODY_BYTES));
+        assert_eq!(CapturedBody::from_bytes(large.as_bytes(), &[]).omitted, Some("too_large"));
+        assert_eq!(CapturedBody::from_bytes(br#"{"phone":"555"}"#, &[]).body, Some(json!({"phone": "***"})));
+    }
+
+    #[test]
+    fn mask_paths_are_validated() {
+        assert!(validate_mask_paths(&["customer.name".into(), "items.*.note".into()]).is_ok());
+        assert!(validate_mask_paths(&["customer..name".into()]).is_err());
+        assert!(validate_mask_paths(&vec!["a".to_string(); MAX_MASK_PATHS + 1]).is_err());
+    }
+}
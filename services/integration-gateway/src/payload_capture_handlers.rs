@@ -0,0 +1,144 @@
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use common_auth::Claims;
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_any_role, Role, SecurityContext, SecurityCtxExtractor};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::payload_capture::{self, CaptureSettings, CapturedExchange, DEFAULT_RETENTION_HOURS, MAX_RETENTION_HOURS};
+use crate::AppState;
+
+/// Most exchanges returned by one listing.
+const MAX_EXCHANGES: i64 = 200;
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct CaptureSettingsRequest {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mask_paths: Vec<String>,
+    #[serde(default)]
+    pub retention_hours: Option<i32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ExchangeListParams {
+    pub limit: Option<i64>,
+}
+
+fn authorize(state: &AppState, sec: &SecurityContext, roles: &[Role]) -> ApiResult<PgPool> {
+    ensure_any_role(sec, roles).map_err(|_| ApiError::ForbiddenMissingRole { role: "admin", trace_id: sec.trace_id })?;
+    state.db.clone().ok_or(ApiError::Internal { trace_id: sec.trace_id, message: Some("Database unavailable".into()) })
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |err| {
+        if matches!(err, sqlx::Error::PoolTimedOut) {
+            return common_db::db_error(err, trace_id);
+        }
+        tracing::error!(?err, "Payload capture query failed");
+        ApiError::Internal { trace_id, message: Some("Payload capture query failed".into()) }
+    }
+}
+
+fn validate_request(req: &CaptureSettingsRequest, trace_id: Option<Uuid>) -> ApiResult<i32> {
+    payload_capture::validate_mask_paths(&req.mask_paths)
+        .map_err(|message| ApiError::BadRequest { code: "invalid_mask_paths", trace_id, message: Some(message) })?;
+    let retention_hours = req.retention_hours.unwrap_or(DEFAULT_RETENTION_HOURS);
+    if !(1..=MAX_RETENTION_HOURS).contains(&retention_hours) {
+        return Err(ApiError::BadRequest {
+            code: "invalid_retention",
+            trace_id,
+            message: Some(format!("retention_hours must be between 1 and {MAX_RETENTION_HOURS}")),
+        });
+    }
+    Ok(retention_hours)
+}
+
+pub async fn get_capture_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(key_id): Path<Uuid>,
+) -> ApiResult<Json<CaptureSettings>> {
+    let pool = authorize(&state, &sec, &[Role::Admin, Role::SuperAdmin])?;
+    payload_capture::get_settings(&pool, sec.tenant_id, key_id)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .map(Json)
+        .ok_or(ApiError::NotFound { code: "capture_not_configured", trace_id: sec.trace_id })
+}
+
+pub async fn put_capture_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(key_id): Path<Uuid>,
+    Json(req): Json<CaptureSettingsRequest>,
+) -> ApiResult<Json<CaptureSettings>> {
+    let pool = authorize(&state, &sec, &[Role::Admin, Role::SuperAdmin])?;
+    let retention_hours = validate_request(&req, sec.trace_id)?;
+    let settings = payload_capture::upsert_settings(&pool, sec.tenant_id, key_id, req.enabled, &req.mask_paths, retention_hours)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "integration_key_not_found", trace_id: sec.trace_id })?;
+    tracing::info!(tenant_id = %sec.tenant_id, key_id = %key_id, enabled = req.enabled, retention_hours, "Payload capture settings saved");
+    Ok(Json(settings))
+}
+
+/// Switches capture off and deletes the key's captured exchanges.
+pub async fn delete_capture_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(key_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    let pool = authorize(&state, &sec, &[Role::Admin, Role::SuperAdmin])?;
+    if payload_capture::delete_settings(&pool, sec.tenant_id, key_id).await.map_err(db_error(sec.trace_id))? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound { code: "capture_not_configured", trace_id: sec.trace_id })
+    }
+}
+
+/// Recent exchanges of one key for support. Needs a signed-in user: API key and bare tenant-header
+/// callers are given the support role by the gateway, and must not read other keys' traffic.
+pub async fn list_captured_exchanges(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    claims: Option<Extension<Claims>>,
+    Path(key_id): Path<Uuid>,
+    Query(params): Query<ExchangeListParams>,
+) -> ApiResult<Json<Vec<CapturedExchange>>> {
+    if claims.is_none() {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+    let pool = authorize(&state, &sec, &[Role::Admin, Role::SuperAdmin, Role::Support])?;
+    let limit = params.limit.unwrap_or(50).clamp(1, MAX_EXCHANGES);
+    let exchanges = payload_capture::recent_exchanges(&pool, sec.tenant_id, key_id, limit)
+        .await
+        .map_err(db_error(sec.trace_id))?;
+    Ok(Json(exchanges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retention_and_paths_are_checked() {
+        let req = |mask_paths: Vec<&str>, retention_hours| CaptureSettingsRequest {
+            enabled: true,
+            mask_paths: mask_paths.into_iter().map(String::from).collect(),
+            retention_hours,
+        };
+        assert!(matches!(validate_request(&req(vec!["customer.name"], None), None), Ok(DEFAULT_RETENTION_HOURS)));
+        assert!(matches!(validate_request(&req(vec![], Some(0)), None), Err(ApiError::BadRequest { code: "invalid_retention", .. })));
+        assert!(matches!(validate_request(&req(vec![], Some(MAX_RETENTION_HOURS + 1)), None), Err(ApiError::BadRequest { code: "invalid_retention", .. })));
+        assert!(matches!(validate_request(&req(vec!["a..b"], None), None), Err(ApiError::BadRequest { code: "invalid_mask_paths", .. })));
+    }
+}
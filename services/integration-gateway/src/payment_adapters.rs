@@ -0,0 +1,115 @@
+//! Provider adapters for [`crate::payment_normalization`]. Each maps one provider's webhook
+//! payload to a [`PaymentEvent`]; register new ones in [`PaymentAdapterRegistry::from_config`].
+//!
+//! [`PaymentAdapterRegistry::from_config`]: crate::payment_normalization::PaymentAdapterRegistry::from_config
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::payment_normalization::{NormalizeError, PaymentEvent, PaymentEventKind, PaymentWebhookAdapter, PAYMENT_EVENT_VERSION};
+
+/// Coinbase Commerce charges. The order, tenant and amount come from the metadata the charge was
+/// created with. `X-CC-Webhook-Signature` is a hex HMAC-SHA256 of the body.
+pub struct CoinbaseAdapter;
+
+#[derive(Deserialize)]
+struct CoinbaseWebhook {
+    event: CoinbaseEvent,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    created_at: DateTime<Utc>,
+    data: CoinbaseCharge,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseCharge {
+    metadata: Option<CoinbaseMeta>,
+    pricing: Option<CoinbasePricing>,
+    #[serde(default)]
+    timeline: Vec<CoinbaseTimelineEntry>,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseMeta {
+    order_id: Option<String>,
+    tenant_id: Option<String>,
+    amount: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CoinbasePricing {
+    local: Option<CoinbasePrice>,
+}
+
+#[derive(Deserialize)]
+struct CoinbasePrice {
+    amount: String,
+    currency: String,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseTimelineEntry {
+    status: String,
+    context: Option<String>,
+}
+
+impl CoinbaseAdapter {
+    fn kind(event_type: &str, timeline: &[CoinbaseTimelineEntry]) -> PaymentEventKind {
+        match event_type {
+            "charge:created" | "charge:pending" | "charge:delayed" => PaymentEventKind::Pending,
+            // Resolved: a merchant accepted an under- or overpayment in the dashboard.
+            "charge:confirmed" | "charge:resolved" => PaymentEventKind::Completed,
+            "charge:failed" => {
+                // The last timeline entry says why: EXPIRED, or UNRESOLVED with the context UNDERPAID.
+                let reason = timeline
+                    .last()
+                    .map(|entry| entry.context.as_deref().unwrap_or(&entry.status).to_ascii_lowercase())
+                    .unwrap_or_else(|| "failed".to_string());
+                PaymentEventKind::Failed { reason }
+            }
+            _ => PaymentEventKind::Other,
+        }
+    }
+}
+
+impl PaymentWebhookAdapter for CoinbaseAdapter {
+    fn provider(&self) -> &'static str {
+        "coinbase"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "X-CC-Webhook-Signature"
+    }
+
+    fn normalize(&self, body: &[u8]) -> Result<PaymentEvent, NormalizeError> {
+        let event = serde_json::from_slice::<CoinbaseWebhook>(body)?.event;
+        let meta = event.data.metadata;
+        let local = event.data.pricing.and_then(|pricing| pricing.local);
+        let uuid = |value: Option<&String>| value.and_then(|value| Uuid::parse_str(value).ok());
+        // The amount the order asked for; the charge's local price when the metadata has none.
+        let amount = meta
+            .as_ref()
+            .and_then(|meta| meta.amount.as_deref())
+            .or(local.as_ref().map(|price| price.amount.as_str()))
+            .and_then(|amount| amount.parse::<f64>().ok());
+        Ok(PaymentEvent {
+            schema_version: PAYMENT_EVENT_VERSION,
+            provider: self.provider().to_string(),
+            kind: Self::kind(&event.event_type, &event.data.timeline),
+            provider_event_id: event.id,
+            provider_event_type: event.event_type,
+            occurred_at: event.created_at,
+            method: "crypto".to_string(),
+            tenant_id: uuid(meta.as_ref().and_then(|meta| meta.tenant_id.as_ref())),
+            order_id: uuid(meta.as_ref().and_then(|meta| meta.order_id.as_ref())),
+            amount,
+            currency: local.map(|price| price.currency),
+        })
+    }
+}
@@ -0,0 +1,261 @@
+//! Payment webhooks from every provider become one canonical [`PaymentEvent`] before anything is
+//! published. A provider plugs in with a [`PaymentWebhookAdapter`], which verifies its signature
+//! and maps its payload; [`PaymentAdapterRegistry`] finds the adapter for `/webhooks/:provider`.
+//! Replay protection, the freshness window and outbox staging are the same for all providers and
+//! live in [`crate::webhook_handlers`]. What a canonical event means internally
+//! ([`PaymentEvent::internal_event`]) is decided once, here, not per provider.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use common_config::env::redact;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::events::{DomainEvent, PaymentCompletedEvent, PaymentFailedEvent};
+use crate::payment_adapters::CoinbaseAdapter;
+use crate::GatewayConfig;
+
+/// Version of the [`PaymentEvent`] shape. Bump it when a field changes meaning or goes away.
+pub const PAYMENT_EVENT_VERSION: u32 = 1;
+
+/// Coinbase retries failed deliveries for up to three days.
+pub const DEFAULT_WEBHOOK_TOLERANCE_SECS: u64 = 3 * 24 * 60 * 60;
+
+/// A provider's webhook secret and freshness window.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookSettings {
+    /// Without a secret every delivery is refused with a 503 until one is configured.
+    #[serde(serialize_with = "redact")]
+    pub secret: Option<String>,
+    /// How far the event's time may be from now, either way.
+    pub tolerance_secs: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self { secret: None, tolerance_secs: DEFAULT_WEBHOOK_TOLERANCE_SECS }
+    }
+}
+
+/// A provider webhook in canonical form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentEvent {
+    pub schema_version: u32,
+    /// Registry name of the provider, e.g. `coinbase`.
+    pub provider: String,
+    /// The provider's id for the event; deliveries are de-duplicated on it.
+    pub provider_event_id: String,
+    /// The provider's own name for the event, e.g. `charge:confirmed`.
+    pub provider_event_type: String,
+    /// When the provider says the event happened. The freshness window is checked against it.
+    pub occurred_at: DateTime<Utc>,
+    pub kind: PaymentEventKind,
+    /// Payment method as order-service knows it, e.g. `crypto`.
+    pub method: String,
+    pub tenant_id: Option<Uuid>,
+    pub order_id: Option<Uuid>,
+    pub amount: Option<f64>,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PaymentEventKind {
+    /// Started or seen, not settled yet.
+    Pending,
+    /// Paid in full.
+    Completed,
+    /// Will not be paid without a new attempt.
+    Failed { reason: String },
+    /// Nothing to do with the payment's state.
+    Other,
+}
+
+/// What a canonical event is published as.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternalPaymentEvent {
+    Completed(PaymentCompletedEvent),
+    Failed(PaymentFailedEvent),
+}
+
+impl PaymentEvent {
+    /// The internal event to publish, if any. Settled events that don't say which tenant and order
+    /// they are for (or, when completed, how much was paid) cannot be published.
+    pub fn internal_event(&self) -> Option<InternalPaymentEvent> {
+        let (tenant_id, order_id) = (self.tenant_id?, self.order_id?);
+        match &self.kind {
+            PaymentEventKind::Completed => Some(InternalPaymentEvent::Completed(PaymentCompletedEvent {
+                schema_version: PaymentCompletedEvent::SCHEMA_VERSION,
+                order_id,
+                tenant_id,
+                method: self.method.clone(),
+                amount: self.amount?,
+            })),
+            PaymentEventKind::Failed { reason } => Some(InternalPaymentEvent::Failed(PaymentFailedEvent {
+                schema_version: PaymentFailedEvent::SCHEMA_VERSION,
+                order_id,
+                tenant_id,
+                method: self.method.clone(),
+                reason: reason.clone(),
+            })),
+            PaymentEventKind::Pending | PaymentEventKind::Other => None,
+        }
+    }
+
+    /// Completed or failed: the kinds that are published.
+    pub fn is_settled(&self) -> bool {
+        matches!(self.kind, PaymentEventKind::Completed | PaymentEventKind::Failed { .. })
+    }
+}
+
+/// Why a verified payload could not be normalized.
+#[derive(Debug, thiserror::Error)]
+pub enum NormalizeError {
+    #[error("malformed payload: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// One payment provider's webhooks.
+pub trait PaymentWebhookAdapter: Send + Sync {
+    /// Name in `/webhooks/:provider` and in `webhook_events`.
+    fn provider(&self) -> &'static str;
+
+    /// Header carrying the signature.
+    fn signature_header(&self) -> &'static str;
+
+    /// Whether `signature` signs `body` under `secret`. By default a hex HMAC-SHA256 of the body.
+    fn verify(&self, secret: &str, body: &[u8], signature: &str) -> bool {
+        verify_hmac_sha256_hex(secret, body, signature)
+    }
+
+    /// The canonical form of a delivery whose signature checked out.
+    fn normalize(&self, body: &[u8]) -> Result<PaymentEvent, NormalizeError>;
+}
+
+/// Whether `signature` (hex) is the HMAC-SHA256 of `body` under `secret`. The comparison takes the
+/// same time wherever the first differing byte is.
+pub fn verify_hmac_sha256_hex(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// A registered adapter and its settings.
+#[derive(Clone)]
+pub struct RegisteredAdapter {
+    pub adapter: Arc<dyn PaymentWebhookAdapter>,
+    pub settings: WebhookSettings,
+}
+
+/// Adapters by provider name.
+#[derive(Clone, Default)]
+pub struct PaymentAdapterRegistry {
+    adapters: BTreeMap<&'static str, RegisteredAdapter>,
+}
+
+impl PaymentAdapterRegistry {
+    /// Every provider the gateway supports, with its settings from `config`.
+    pub fn from_config(config: &GatewayConfig) -> Self {
+        let mut registry = Self::default();
+        registry.register(CoinbaseAdapter, config.coinbase_webhook.clone());
+        registry
+    }
+
+    /// Adds `adapter`, replacing one registered under the same provider name.
+    pub fn register(&mut self, adapter: impl PaymentWebhookAdapter + 'static, settings: WebhookSettings) {
+        self.adapters.insert(adapter.provider(), RegisteredAdapter { adapter: Arc::new(adapter), settings });
+    }
+
+    pub fn get(&self, provider: &str) -> Option<&RegisteredAdapter> {
+        self.adapters.get(provider)
+    }
+
+    pub fn providers(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.adapters.keys().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: PaymentEventKind) -> PaymentEvent {
+        PaymentEvent {
+            schema_version: PAYMENT_EVENT_VERSION,
+            provider: "test".into(),
+            provider_event_id: "evt".into(),
+            provider_event_type: "test".into(),
+            occurred_at: Utc::now(),
+            kind,
+            method: "crypto".into(),
+            tenant_id: Some(Uuid::new_v4()),
+            order_id: Some(Uuid::new_v4()),
+            amount: Some(12.5),
+            currency: Some("USD".into()),
+        }
+    }
+
+    #[test]
+    fn only_settled_events_for_a_known_order_are_published() {
+        let completed = event(PaymentEventKind::Completed);
+        let Some(InternalPaymentEvent::Completed(paid)) = completed.internal_event() else { panic!("completed") };
+        assert_eq!((paid.order_id, paid.amount, paid.method.as_str()), (completed.order_id.unwrap(), 12.5, "crypto"));
+        let failed = event(PaymentEventKind::Failed { reason: "expired".into() });
+        assert!(matches!(failed.internal_event(), Some(InternalPaymentEvent::Failed(f)) if f.reason == "expired"));
+        assert_eq!(event(PaymentEventKind::Pending).internal_event(), None);
+        assert_eq!(event(PaymentEventKind::Other).internal_event(), None);
+        assert_eq!(PaymentEvent { order_id: None, ..completed.clone() }.internal_event(), None);
+        assert_eq!(PaymentEvent { amount: None, ..completed }.internal_event(), None);
+        assert!(PaymentEvent { amount: None, ..failed }.internal_event().is_some(), "a failure needs no amount");
+    }
+
+    #[test]
+    fn signatures_must_match_the_exact_body_and_secret() {
+        let body = br#"{"event":{"id":"1"}}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cr3t").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+        assert!(verify_hmac_sha256_hex("s3cr3t", body, &signature));
+        assert!(verify_hmac_sha256_hex("s3cr3t", body, &signature.to_uppercase()), "hex is case-insensitive");
+        assert!(!verify_hmac_sha256_hex("other", body, &signature));
+        assert!(!verify_hmac_sha256_hex("s3cr3t", br#"{"event":{"id":"2"}}"#, &signature));
+        assert!(!verify_hmac_sha256_hex("s3cr3t", body, &signature[..62]), "a truncated signature is not a prefix match");
+        assert!(!verify_hmac_sha256_hex("s3cr3t", body, "not hex"));
+        assert!(!verify_hmac_sha256_hex("s3cr3t", body, ""));
+    }
+
+    struct Echo;
+
+    impl PaymentWebhookAdapter for Echo {
+        fn provider(&self) -> &'static str {
+            "echo"
+        }
+
+        fn signature_header(&self) -> &'static str {
+            "X-Echo-Signature"
+        }
+
+        fn normalize(&self, body: &[u8]) -> Result<PaymentEvent, NormalizeError> {
+            Ok(serde_json::from_slice(body)?)
+        }
+    }
+
+    #[test]
+    fn adapters_are_found_by_provider_name() {
+        let mut registry = PaymentAdapterRegistry::default();
+        registry.register(Echo, WebhookSettings::default());
+        registry.register(CoinbaseAdapter, WebhookSettings::default());
+        assert_eq!(registry.providers().collect::<Vec<_>>(), ["coinbase", "echo"]);
+        assert_eq!(registry.get("echo").unwrap().adapter.signature_header(), "X-Echo-Signature");
+        assert!(registry.get("stripe").is_none());
+    }
+}
@@ -1,12 +1,33 @@
+//! Request rate limiting for the gateway.
+//!
+//! Four algorithms, chosen per key class and tenant plan (see [`RateLimitPolicies`]):
+//! - `fixed_window`: one counter per window. Cheap, but a client can send `limit` at the end of
+//!   one window and `limit` again at the start of the next, 2x the rate across the boundary.
+//! - `sliding_window`: counters for this and the previous window, the previous one weighted by
+//!   how much of it still overlaps the last `window` seconds. Two keys, close to exact.
+//! - `sliding_log`: a sorted set of admitted request times. Exact; memory grows with `limit`.
+//! - `token_bucket`: `burst` tokens refilled at `limit` per window, for clients that send in
+//!   bursts but stay within the average rate.
+//!
+//! The Redis engine runs each algorithm as one Lua script, so concurrent gateway instances
+//! cannot interleave between read and write, and uses Redis `TIME` so instance clocks don't
+//! matter. Rejected requests do not use up the sliding and bucket allowances, so a client that
+//! keeps retrying still gets `limit` requests per window through. [`RateDecision::current`] is
+//! every request seen in the current fixed window, rejected ones included, which is what burst
+//! alerts compare against. `benches/rate_limiter.rs` times the engines (the Redis ones when
+//! `RATE_LIMIT_BENCH_REDIS_URL` is set).
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
 
 // Redis dependencies (only used by Redis implementation)
 use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
+use redis::Script;
 
 #[derive(Debug, Clone, Copy)]
 pub struct RateDecision {
@@ -14,72 +35,386 @@ pub struct RateDecision {
     pub current: i64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateAlgorithm {
+    #[default]
+    FixedWindow,
+    SlidingWindow,
+    SlidingLog,
+    TokenBucket,
+}
+
+impl RateAlgorithm {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "fixed_window" | "fixed" => Some(Self::FixedWindow),
+            "sliding_window" | "sliding" => Some(Self::SlidingWindow),
+            "sliding_log" | "log" => Some(Self::SlidingLog),
+            "token_bucket" | "bucket" => Some(Self::TokenBucket),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FixedWindow => "fixed_window",
+            Self::SlidingWindow => "sliding_window",
+            Self::SlidingLog => "sliding_log",
+            Self::TokenBucket => "token_bucket",
+        }
+    }
+}
+
+impl std::str::FromStr for RateAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or_else(|| "expected fixed_window, sliding_window, sliding_log or token_bucket".to_string())
+    }
+}
+
+/// How one caller is limited: `limit` requests per window, or for `token_bucket` a refill of
+/// `limit` per window into a bucket holding `burst`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RatePolicy {
+    pub algorithm: RateAlgorithm,
+    pub limit: u32,
+    pub burst: u32,
+}
+
+impl RatePolicy {
+    pub fn fixed(limit: u32) -> Self {
+        Self { algorithm: RateAlgorithm::FixedWindow, limit, burst: limit }
+    }
+}
+
 #[async_trait]
 pub trait RateLimiterEngine: Send + Sync {
-    async fn check(&self, key: &str, limit: u32) -> Result<RateDecision>;
+    async fn check(&self, key: &str, policy: &RatePolicy) -> Result<RateDecision>;
+}
+
+// ---------------- Policy selection ----------------
+
+/// A tenant plan's limit: `algorithm:rpm[:burst]` in `GATEWAY_RATE_LIMIT_PLANS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PlanLimit {
+    pub algorithm: RateAlgorithm,
+    pub rpm: u32,
+    pub burst: Option<u32>,
+}
+
+impl std::str::FromStr for PlanLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let algorithm = parts.next().unwrap_or_default().parse()?;
+        let number = |part: Option<&str>| part.map(|p| p.trim().parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(|| format!("`{p}` is not a positive number")));
+        let rpm = number(parts.next()).ok_or("expected algorithm:rpm[:burst]")??;
+        let burst = number(parts.next()).transpose()?;
+        if parts.next().is_some() {
+            return Err("expected algorithm:rpm[:burst]".into());
+        }
+        Ok(Self { algorithm, rpm, burst })
+    }
+}
+
+/// Which algorithm and limit apply to a request. A tenant on a plan
+/// (`GATEWAY_RATE_LIMIT_TENANT_PLANS`) gets the plan's limit; everyone else gets
+/// `GATEWAY_RATE_LIMIT_RPM` with the algorithm for their key class (`jwt`, `api`,
+/// `tenant_header`) from `GATEWAY_RATE_LIMIT_CLASS_ALGORITHMS`, else
+/// `GATEWAY_RATE_LIMIT_ALGORITHM`. The defaults reproduce the plain fixed window.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RateLimitPolicies {
+    pub algorithm: RateAlgorithm,
+    /// Token bucket capacity for requests not on a plan; the rpm when unset.
+    pub burst: Option<u32>,
+    pub classes: BTreeMap<String, RateAlgorithm>,
+    pub plans: BTreeMap<String, PlanLimit>,
+    pub tenant_plans: HashMap<Uuid, String>,
+}
+
+impl RateLimitPolicies {
+    pub fn resolve(&self, class: &str, tenant_id: Uuid, rpm: u32) -> RatePolicy {
+        if let Some(plan) = self.tenant_plans.get(&tenant_id).and_then(|name| self.plans.get(name)) {
+            return RatePolicy { algorithm: plan.algorithm, limit: plan.rpm, burst: plan.burst.unwrap_or(plan.rpm) };
+        }
+        let algorithm = self.classes.get(class).copied().unwrap_or(self.algorithm);
+        RatePolicy { algorithm, limit: rpm, burst: self.burst.unwrap_or(rpm) }
+    }
+}
+
+/// `name=value` pairs separated by commas, e.g. `api=token_bucket,jwt=sliding_window`.
+pub fn parse_pairs<K, V>(raw: &str) -> Result<Vec<(K, V)>, String>
+where
+    K: std::str::FromStr,
+    V: std::str::FromStr,
+    V::Err: std::fmt::Display,
+{
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, value) = entry.split_once('=').ok_or_else(|| format!("`{entry}` is not name=value"))?;
+            let name = name.trim().parse().map_err(|_| format!("`{}` is not a valid name", name.trim()))?;
+            Ok((name, value.trim().parse().map_err(|err| format!("`{entry}`: {err}"))?))
+        })
+        .collect()
 }
 
 // ---------------- Redis Implementation ----------------
 
+/// Shared by the scripts below: the server clock in milliseconds and the count of every request
+/// seen in the current fixed window (`KEYS[1]:seen:<slot>`).
+const SEEN_PRELUDE: &str = r#"
+redis.replicate_commands()
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local limit = tonumber(ARGV[2])
+local seen_key = KEYS[1] .. ':seen:' .. math.floor(now / window)
+local seen = redis.call('INCR', seen_key)
+if seen == 1 then redis.call('PEXPIRE', seen_key, window) end
+"#;
+
+/// The original counter, now in one script so a crash between `INCR` and `PEXPIRE` cannot
+/// leave a key without a TTL.
+const FIXED_WINDOW: &str = r#"
+local current = redis.call('INCR', KEYS[1])
+if redis.call('PTTL', KEYS[1]) < 0 then redis.call('PEXPIRE', KEYS[1], ARGV[1]) end
+if current <= tonumber(ARGV[2]) then return {1, current} end
+return {0, current}
+"#;
+
+const SLIDING_WINDOW: &str = r#"
+local slot = math.floor(now / window)
+local current_key = KEYS[1] .. ':sw:' .. slot
+local previous = tonumber(redis.call('GET', KEYS[1] .. ':sw:' .. (slot - 1)) or '0')
+local current = tonumber(redis.call('GET', current_key) or '0')
+local estimate = previous * (window - now % window) / window + current
+if estimate + 1 > limit then return {0, seen} end
+redis.call('INCR', current_key)
+redis.call('PEXPIRE', current_key, window * 2)
+return {1, seen}
+"#;
+
+const SLIDING_LOG: &str = r#"
+local log = KEYS[1] .. ':log'
+redis.call('ZREMRANGEBYSCORE', log, '-inf', now - window)
+if redis.call('ZCARD', log) >= limit then return {0, seen} end
+redis.call('ZADD', log, now, now .. ':' .. seen)
+redis.call('PEXPIRE', log, window)
+return {1, seen}
+"#;
+
+const TOKEN_BUCKET: &str = r#"
+local bucket = KEYS[1] .. ':tb'
+local capacity = tonumber(ARGV[3])
+local state = redis.call('HMGET', bucket, 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local last = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - last) * limit / window)
+local allowed = 0
+if tokens >= 1 then
+  allowed = 1
+  tokens = tokens - 1
+end
+redis.call('HSET', bucket, 'tokens', tokens, 'ts', now)
+redis.call('PEXPIRE', bucket, math.ceil(capacity * window / limit))
+return {allowed, seen}
+"#;
+
+struct RateScripts {
+    fixed_window: Script,
+    sliding_window: Script,
+    sliding_log: Script,
+    token_bucket: Script,
+}
+
+impl RateScripts {
+    fn new() -> Self {
+        let with_prelude = |body: &str| Script::new(&format!("{SEEN_PRELUDE}{body}"));
+        Self {
+            fixed_window: Script::new(FIXED_WINDOW),
+            sliding_window: with_prelude(SLIDING_WINDOW),
+            sliding_log: with_prelude(SLIDING_LOG),
+            token_bucket: with_prelude(TOKEN_BUCKET),
+        }
+    }
+
+    fn get(&self, algorithm: RateAlgorithm) -> &Script {
+        match algorithm {
+            RateAlgorithm::FixedWindow => &self.fixed_window,
+            RateAlgorithm::SlidingWindow => &self.sliding_window,
+            RateAlgorithm::SlidingLog => &self.sliding_log,
+            RateAlgorithm::TokenBucket => &self.token_bucket,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RedisRateLimiter {
-    manager: ConnectionManager,
+    client: redis::Client,
+    /// Set on the first successful connect, so the gateway can start while Redis is down.
+    manager: Arc<OnceCell<ConnectionManager>>,
     window_secs: u64,
     prefix: String,
+    scripts: Arc<RateScripts>,
 }
 
 impl RedisRateLimiter {
+    /// Fails only for an unusable URL; if Redis cannot be reached yet, checks retry the connection.
     pub async fn new(redis_url: &str, window_secs: u64, prefix: String) -> Result<Self> {
         let client = redis::Client::open(redis_url).context("Failed to create Redis client")?;
-        let manager = ConnectionManager::new(client)
+        let limiter = Self { client, manager: Arc::new(OnceCell::new()), window_secs, prefix, scripts: Arc::new(RateScripts::new()) };
+        if let Err(err) = limiter.connection().await {
+            tracing::warn!(?err, "Redis unreachable at startup; rate limiting starts in fallback mode");
+        }
+        Ok(limiter)
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let manager = self
+            .manager
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
             .await
             .context("Failed to create Redis connection manager")?;
-        Ok(Self { manager, window_secs, prefix })
+        Ok(manager.clone())
     }
 }
 
 #[async_trait]
 impl RateLimiterEngine for RedisRateLimiter {
-    async fn check(&self, key: &str, limit: u32) -> Result<RateDecision> {
+    async fn check(&self, key: &str, policy: &RatePolicy) -> Result<RateDecision> {
         let redis_key = format!("{}:{}", self.prefix, key);
-        let mut conn = self.manager.clone();
-        let current: i64 = conn.incr(&redis_key, 1).await?;
-        if current == 1 {
-            let _: () = conn.expire(&redis_key, self.window_secs as i64).await?;
-        }
-        let allowed = current <= limit as i64;
-        Ok(RateDecision { allowed, current })
+        let mut conn = self.connection().await?;
+        // EVALSHA, falling back to EVAL (and caching the script) when the server doesn't have it.
+        let (allowed, current): (i64, i64) = self
+            .scripts
+            .get(policy.algorithm)
+            .key(&redis_key)
+            .arg(self.window_secs * 1000)
+            .arg(policy.limit.max(1))
+            .arg(policy.burst.max(1))
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(RateDecision { allowed: allowed == 1, current })
     }
 }
 
 // ---------------- In-Memory Implementation (Tests) ----------------
 
+/// Per-key state for every algorithm, mirroring the Redis keys.
+#[derive(Default)]
+struct KeyState {
+    touched: u64,
+    fixed: (i64, u64),
+    seen: (u64, i64),
+    sliding: (u64, u32, u32),
+    log: VecDeque<u64>,
+    bucket: Option<(f64, u64)>,
+}
+
 #[derive(Clone)]
 pub struct InMemoryRateLimiter {
-    inner: Arc<Mutex<HashMap<String, (i64, std::time::Instant)>>>,
+    inner: Arc<Mutex<HashMap<String, KeyState>>>,
     window_secs: u64,
+    started: Instant,
+    max_keys: Option<usize>,
 }
 
 impl InMemoryRateLimiter {
     pub fn new(window_secs: u64) -> Self {
-        Self { inner: Arc::new(Mutex::new(HashMap::new())), window_secs }
+        Self { inner: Arc::new(Mutex::new(HashMap::new())), window_secs, started: Instant::now(), max_keys: None }
+    }
+
+    /// Holding at most `max_keys` keys; a new key past that evicts the least recently used one.
+    pub fn bounded(window_secs: u64, max_keys: usize) -> Self {
+        Self { max_keys: Some(max_keys.max(1)), ..Self::new(window_secs) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// [`check`](RateLimiterEngine::check) without the `Result`; the in-memory engine cannot fail.
+    pub fn decide(&self, key: &str, policy: &RatePolicy) -> RateDecision {
+        self.check_at(key, policy, self.started.elapsed().as_millis() as u64)
+    }
+
+    /// [`check`](RateLimiterEngine::check) at `now_ms` milliseconds after the limiter was created.
+    pub fn check_at(&self, key: &str, policy: &RatePolicy, now_ms: u64) -> RateDecision {
+        let window = (self.window_secs * 1000).max(1);
+        let limit = policy.limit.max(1);
+        let mut guard = self.inner.lock().unwrap();
+        if !guard.contains_key(key) && self.max_keys.is_some_and(|max| guard.len() >= max) {
+            let idle = guard.iter().min_by_key(|(_, state)| state.touched).map(|(key, _)| key.clone());
+            if let Some(idle) = idle {
+                guard.remove(&idle);
+            }
+        }
+        let state = guard.entry(key.to_string()).or_default();
+        state.touched = now_ms;
+        if policy.algorithm == RateAlgorithm::FixedWindow {
+            let (count, started) = &mut state.fixed;
+            if *count == 0 || now_ms.saturating_sub(*started) >= window {
+                (*count, *started) = (0, now_ms);
+            }
+            *count += 1;
+            return RateDecision { allowed: *count <= limit as i64, current: *count };
+        }
+        let slot = now_ms / window;
+        if state.seen.0 != slot {
+            state.seen = (slot, 0);
+        }
+        state.seen.1 += 1;
+        let allowed = match policy.algorithm {
+            RateAlgorithm::FixedWindow => unreachable!(),
+            RateAlgorithm::SlidingWindow => {
+                let (at, current, previous) = &mut state.sliding;
+                if *at != slot {
+                    *previous = if *at + 1 == slot { *current } else { 0 };
+                    (*at, *current) = (slot, 0);
+                }
+                let estimate = *previous as f64 * (window - now_ms % window) as f64 / window as f64 + *current as f64;
+                let allowed = estimate + 1.0 <= limit as f64;
+                if allowed {
+                    *current += 1;
+                }
+                allowed
+            }
+            RateAlgorithm::SlidingLog => {
+                while state.log.front().is_some_and(|at| at + window <= now_ms) {
+                    state.log.pop_front();
+                }
+                let allowed = state.log.len() < limit as usize;
+                if allowed {
+                    state.log.push_back(now_ms);
+                }
+                allowed
+            }
+            RateAlgorithm::TokenBucket => {
+                let capacity = policy.burst.max(1) as f64;
+                let (tokens, last) = state.bucket.unwrap_or((capacity, now_ms));
+                let tokens = capacity.min(tokens + now_ms.saturating_sub(last) as f64 * limit as f64 / window as f64);
+                let allowed = tokens >= 1.0;
+                state.bucket = Some((if allowed { tokens - 1.0 } else { tokens }, now_ms));
+                allowed
+            }
+        };
+        RateDecision { allowed, current: state.seen.1 }
     }
 }
 
 #[async_trait]
 impl RateLimiterEngine for InMemoryRateLimiter {
-    async fn check(&self, key: &str, limit: u32) -> Result<RateDecision> {
-        let mut guard = self.inner.lock().await;
-        let now = std::time::Instant::now();
-        let window = std::time::Duration::from_secs(self.window_secs);
-        let entry = guard.entry(key.to_string()).or_insert((0, now));
-        if now.duration_since(entry.1) >= window {
-            *entry = (0, now);
-        }
-        entry.0 += 1;
-        let current = entry.0;
-        let allowed = current <= limit as i64;
-        Ok(RateDecision { allowed, current })
+    async fn check(&self, key: &str, policy: &RatePolicy) -> Result<RateDecision> {
+        Ok(self.decide(key, policy))
     }
 }
 
@@ -96,3 +431,99 @@ impl RateLimiter {
     }
     pub fn memory(window_secs: u64) -> Self { RateLimiter::Memory(InMemoryRateLimiter::new(window_secs)) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+
+    fn policy(algorithm: RateAlgorithm, limit: u32, burst: u32) -> RatePolicy {
+        RatePolicy { algorithm, limit, burst }
+    }
+
+    /// Requests admitted out of `count` sent at `at_ms`.
+    fn admitted(limiter: &InMemoryRateLimiter, policy: &RatePolicy, count: u32, at_ms: u64) -> u32 {
+        (0..count).filter(|_| limiter.check_at("k", policy, at_ms).allowed).count() as u32
+    }
+
+    #[test]
+    fn fixed_window_lets_a_double_burst_through_at_the_boundary() {
+        let limiter = InMemoryRateLimiter::new(60);
+        let fixed = RatePolicy::fixed(10);
+        assert_eq!(admitted(&limiter, &fixed, 10, 0), 10);
+        assert_eq!(admitted(&limiter, &fixed, 10, MINUTE - 1), 0);
+        assert_eq!(admitted(&limiter, &fixed, 10, MINUTE), 10, "a new window starts from zero");
+    }
+
+    #[test]
+    fn sliding_algorithms_hold_the_rate_across_the_boundary() {
+        for algorithm in [RateAlgorithm::SlidingWindow, RateAlgorithm::SlidingLog] {
+            let limiter = InMemoryRateLimiter::new(60);
+            let sliding = policy(algorithm, 10, 10);
+            assert_eq!(admitted(&limiter, &sliding, 10, MINUTE - 1_000), 10, "{algorithm:?}");
+            assert_eq!(admitted(&limiter, &sliding, 10, MINUTE + 1_000), 0, "{algorithm:?}: the last minute already had 10");
+            assert_eq!(admitted(&limiter, &sliding, 10, 2 * MINUTE + 500), 10, "{algorithm:?}: the burst has slid out");
+        }
+    }
+
+    #[test]
+    fn sliding_window_weights_the_previous_window_by_its_overlap() {
+        let limiter = InMemoryRateLimiter::new(60);
+        let sliding = policy(RateAlgorithm::SlidingWindow, 10, 10);
+        assert_eq!(admitted(&limiter, &sliding, 10, 0), 10);
+        // Three quarters into the next window a quarter of the previous 10 still counts.
+        assert_eq!(admitted(&limiter, &sliding, 10, MINUTE + 45_000), 7);
+    }
+
+    #[test]
+    fn token_bucket_allows_the_burst_then_refills_at_the_rate() {
+        let limiter = InMemoryRateLimiter::new(60);
+        let bucket = policy(RateAlgorithm::TokenBucket, 60, 20);
+        assert_eq!(admitted(&limiter, &bucket, 30, 0), 20);
+        assert_eq!(admitted(&limiter, &bucket, 30, 5_000), 5, "one token a second");
+        assert_eq!(admitted(&limiter, &bucket, 30, 10 * MINUTE), 20, "never more than the burst");
+    }
+
+    #[test]
+    fn current_counts_rejected_requests_for_alerts() {
+        let limiter = InMemoryRateLimiter::new(60);
+        let bucket = policy(RateAlgorithm::TokenBucket, 5, 5);
+        for _ in 0..11 {
+            limiter.check_at("k", &bucket, 1_000);
+        }
+        let last = limiter.check_at("k", &bucket, 1_000);
+        assert_eq!((last.allowed, last.current), (false, 12));
+    }
+
+    #[test]
+    fn bounded_limiters_evict_the_least_recently_used_key() {
+        let limiter = InMemoryRateLimiter::bounded(60, 2);
+        let one = RatePolicy::fixed(1);
+        assert!(limiter.check_at("a", &one, 0).allowed);
+        assert!(limiter.check_at("b", &one, 1).allowed);
+        assert!(!limiter.check_at("a", &one, 2).allowed);
+        assert!(limiter.check_at("c", &one, 3).allowed, "evicts b");
+        assert_eq!(limiter.len(), 2);
+        assert!(!limiter.check_at("a", &one, 4).allowed, "a was kept");
+        assert!(limiter.check_at("b", &one, 5).allowed, "b starts over");
+    }
+
+    #[test]
+    fn tenant_plans_override_key_class_algorithms() {
+        let enterprise = Uuid::new_v4();
+        let policies = RateLimitPolicies {
+            algorithm: RateAlgorithm::FixedWindow,
+            burst: None,
+            classes: parse_pairs("api=token_bucket, jwt=sliding_window").unwrap().into_iter().collect(),
+            plans: parse_pairs("enterprise=sliding_log:600:900").unwrap().into_iter().collect(),
+            tenant_plans: HashMap::from([(enterprise, "enterprise".to_string())]),
+        };
+        let other = Uuid::new_v4();
+        assert_eq!(policies.resolve("api", other, 60), policy(RateAlgorithm::TokenBucket, 60, 60));
+        assert_eq!(policies.resolve("tenant_header", other, 60), RatePolicy::fixed(60));
+        assert_eq!(policies.resolve("api", enterprise, 60), policy(RateAlgorithm::SlidingLog, 600, 900));
+        assert!(parse_pairs::<String, PlanLimit>("basic=token_bucket").is_err());
+        assert!(parse_pairs::<String, RateAlgorithm>("api=leaky").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Rate limiting while Redis is down.
+//!
+//! Every Redis check runs under `GATEWAY_REDIS_TIMEOUT_MS` behind a circuit breaker that opens
+//! after `GATEWAY_REDIS_CIRCUIT_FAILURE_THRESHOLD` consecutive failures and retries Redis after
+//! `GATEWAY_REDIS_CIRCUIT_OPEN_SECONDS`, so an outage costs one timeout per breaker period rather
+//! than one per request. Requests that cannot be checked in Redis follow the route class's mode
+//! (`GATEWAY_REDIS_DOWN_MODE`, overridden per class by `GATEWAY_REDIS_DOWN_ROUTES`):
+//! - `fail_open`: a token bucket in this instance's memory, at the key's rpm (or
+//!   `GATEWAY_RATE_LIMIT_FALLBACK_RPM` when lower) and holding at most
+//!   `GATEWAY_RATE_LIMIT_FALLBACK_MAX_KEYS` keys. Limits are per instance, not shared.
+//! - `fail_closed`: 503 `rate_limiter_unavailable` with `Retry-After` set to when Redis is tried again.
+//!
+//! `gateway_redis_up` is 0 while the breaker is open, and `gateway_rate_limit_fallback_total`
+//! counts requests decided without Redis, by route class and outcome.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::kill_switches::switch_for_path;
+use crate::metrics::GatewayMetrics;
+use crate::rate_limiter::{InMemoryRateLimiter, RateAlgorithm, RateDecision, RateLimiterEngine, RatePolicy};
+
+/// Route classes a Redis outage can be handled differently for: the kill switch groups plus
+/// `admin` and everything else.
+pub const ROUTE_CLASSES: &[&str] = &["payments", "external_orders", "catalog", "webhooks", "admin", "other"];
+
+pub fn route_class(path: &str) -> &'static str {
+    if path.starts_with("/admin/") {
+        return "admin";
+    }
+    switch_for_path(path).unwrap_or("other")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisDownMode {
+    #[default]
+    FailOpen,
+    FailClosed,
+}
+
+impl std::str::FromStr for RedisDownMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "fail_open" | "open" => Ok(Self::FailOpen),
+            "fail_closed" | "closed" => Ok(Self::FailClosed),
+            _ => Err("expected fail_open or fail_closed".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedisDegradation {
+    pub mode: RedisDownMode,
+    pub routes: BTreeMap<String, RedisDownMode>,
+    pub timeout_ms: u64,
+    pub circuit_failure_threshold: u32,
+    pub circuit_open_secs: u64,
+    pub fallback_rpm: Option<u32>,
+    pub fallback_max_keys: usize,
+}
+
+impl Default for RedisDegradation {
+    fn default() -> Self {
+        Self {
+            mode: RedisDownMode::FailOpen,
+            routes: BTreeMap::new(),
+            timeout_ms: 200,
+            circuit_failure_threshold: 5,
+            circuit_open_secs: 10,
+            fallback_rpm: None,
+            fallback_max_keys: 10_000,
+        }
+    }
+}
+
+impl RedisDegradation {
+    pub fn mode_for(&self, route_class: &str) -> RedisDownMode {
+        self.routes.get(route_class).copied().unwrap_or(self.mode)
+    }
+
+    /// The local bucket standing in for `policy`.
+    fn fallback_policy(&self, policy: &RatePolicy) -> RatePolicy {
+        let limit = self.fallback_rpm.map_or(policy.limit, |rpm| rpm.min(policy.limit)).max(1);
+        RatePolicy { algorithm: RateAlgorithm::TokenBucket, limit, burst: policy.burst.min(limit).max(1) }
+    }
+}
+
+/// A request refused because Redis could not be asked and its route class fails closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedisUnavailable {
+    pub retry_after_secs: u64,
+}
+
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Opens after `threshold` consecutive failures. Once `open_for` has passed calls are let
+/// through again; the failure count is only reset by a success, so one more failure reopens it.
+struct Breaker {
+    threshold: u32,
+    open_for: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl Breaker {
+    fn new(threshold: u32, open_for: Duration) -> Self {
+        Self { threshold: threshold.max(1), open_for, state: Mutex::new(BreakerState { consecutive_failures: 0, open_until: None }) }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn allows(&self, now: Instant) -> bool {
+        self.lock().open_until.is_none_or(|until| now >= until)
+    }
+
+    /// Returns true when this success closed an open circuit.
+    fn record_success(&self) -> bool {
+        let mut state = self.lock();
+        let was_open = state.open_until.is_some();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+        was_open
+    }
+
+    /// Returns true when this failure opened the circuit.
+    fn record_failure(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures < self.threshold {
+            return false;
+        }
+        let was_open = state.open_until.is_some();
+        state.open_until = Some(now + self.open_for);
+        !was_open
+    }
+
+    /// Seconds until Redis is tried again; at least 1.
+    fn retry_after_secs(&self, now: Instant) -> u64 {
+        let remaining = self.lock().open_until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+        remaining.as_secs_f64().ceil().max(1.0) as u64
+    }
+}
+
+/// The gateway's rate limiter: Redis (or any shared engine) first, the degradation policy when
+/// it cannot answer.
+pub struct FallbackRateLimiter {
+    primary: Arc<dyn RateLimiterEngine>,
+    local: InMemoryRateLimiter,
+    settings: RedisDegradation,
+    breaker: Breaker,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl FallbackRateLimiter {
+    pub fn new(primary: Arc<dyn RateLimiterEngine>, settings: RedisDegradation, window_secs: u64, metrics: Arc<GatewayMetrics>) -> Self {
+        let breaker = Breaker::new(settings.circuit_failure_threshold, Duration::from_secs(settings.circuit_open_secs));
+        let local = InMemoryRateLimiter::bounded(window_secs, settings.fallback_max_keys);
+        metrics.set_redis_up(true);
+        Self { primary, local, settings, breaker, metrics }
+    }
+
+    pub async fn check(&self, key: &str, policy: &RatePolicy, route_class: &'static str) -> Result<RateDecision, RedisUnavailable> {
+        if self.breaker.allows(Instant::now()) {
+            let timeout = Duration::from_millis(self.settings.timeout_ms);
+            let failure = match tokio::time::timeout(timeout, self.primary.check(key, policy)).await {
+                Ok(Ok(decision)) => {
+                    if self.breaker.record_success() {
+                        self.metrics.set_redis_up(true);
+                        info!("Redis answering rate limit checks again, leaving fallback mode");
+                    }
+                    return Ok(decision);
+                }
+                Ok(Err(err)) => {
+                    warn!(?err, "Redis rate limit check failed");
+                    "error"
+                }
+                Err(_) => {
+                    warn!(timeout_ms = self.settings.timeout_ms, "Redis rate limit check timed out");
+                    "timeout"
+                }
+            };
+            self.metrics.record_redis_error(failure);
+            if self.breaker.record_failure(Instant::now()) {
+                self.metrics.set_redis_up(false);
+                warn!(
+                    open_secs = self.settings.circuit_open_secs,
+                    mode = ?self.settings.mode,
+                    "Redis unavailable, rate limiting in fallback mode"
+                );
+            }
+        }
+        self.degraded(key, policy, route_class)
+    }
+
+    fn degraded(&self, key: &str, policy: &RatePolicy, route_class: &'static str) -> Result<RateDecision, RedisUnavailable> {
+        if self.settings.mode_for(route_class) == RedisDownMode::FailClosed {
+            self.metrics.record_rate_limit_fallback(route_class, "fail_closed");
+            return Err(RedisUnavailable { retry_after_secs: self.breaker.retry_after_secs(Instant::now()) });
+        }
+        let decision = self.local.decide(key, &self.settings.fallback_policy(policy));
+        let outcome = if decision.allowed { "allowed" } else { "rejected" };
+        self.metrics.record_rate_limit_fallback(route_class, outcome);
+        self.metrics.set_rate_limit_fallback_keys(self.local.len());
+        Ok(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    /// Admits everything unless `down`, when every call fails.
+    #[derive(Default)]
+    struct FlakyRedis {
+        down: AtomicBool,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl RateLimiterEngine for FlakyRedis {
+        async fn check(&self, _key: &str, _policy: &RatePolicy) -> Result<RateDecision> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.down.load(Ordering::SeqCst) {
+                return Err(anyhow!("connection refused"));
+            }
+            Ok(RateDecision { allowed: true, current: 1 })
+        }
+    }
+
+    fn limiter(redis: Arc<FlakyRedis>, settings: RedisDegradation) -> (FallbackRateLimiter, Arc<GatewayMetrics>) {
+        let metrics = Arc::new(GatewayMetrics::new().unwrap());
+        (FallbackRateLimiter::new(redis, settings, 60, metrics.clone()), metrics)
+    }
+
+    #[test]
+    fn route_classes_follow_the_kill_switch_groups() {
+        assert_eq!(route_class("/payments/void"), "payments");
+        assert_eq!(route_class("/catalog/products"), "catalog");
+        assert_eq!(route_class("/admin/kill-switches"), "admin");
+        assert_eq!(route_class("/elsewhere"), "other");
+        assert!(ROUTE_CLASSES.contains(&route_class("/webhooks/coinbase")));
+    }
+
+    #[tokio::test]
+    async fn open_routes_fall_back_to_a_local_bucket_and_closed_ones_refuse() {
+        let redis = Arc::new(FlakyRedis::default());
+        redis.down.store(true, Ordering::SeqCst);
+        let settings = RedisDegradation {
+            routes: BTreeMap::from([("payments".to_string(), RedisDownMode::FailClosed)]),
+            circuit_failure_threshold: 2,
+            circuit_open_secs: 30,
+            fallback_rpm: Some(3),
+            ..RedisDegradation::default()
+        };
+        let (limiter, metrics) = limiter(redis.clone(), settings);
+        let policy = RatePolicy::fixed(100);
+
+        let mut admitted = 0;
+        for _ in 0..5 {
+            admitted += u32::from(limiter.check("k", &policy, "catalog").await.unwrap().allowed);
+        }
+        assert_eq!(admitted, 3, "the fallback rpm caps the local bucket");
+        assert_eq!(redis.calls.load(Ordering::SeqCst), 2, "the breaker stops calling Redis once open");
+        let refused = limiter.check("k", &policy, "payments").await.unwrap_err();
+        assert!((29..=30).contains(&refused.retry_after_secs));
+
+        let text = metrics.gather_text().unwrap();
+        assert!(text.contains("gateway_redis_up 0"), "{text}");
+        assert!(text.contains("gateway_rate_limit_fallback_total{outcome=\"rejected\",route_class=\"catalog\"} 2"), "{text}");
+        assert!(text.contains("gateway_rate_limit_fallback_total{outcome=\"fail_closed\",route_class=\"payments\"} 1"), "{text}");
+        assert!(text.contains("gateway_redis_errors_total{reason=\"error\"} 2"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn a_successful_check_after_the_open_period_leaves_fallback_mode() {
+        let redis = Arc::new(FlakyRedis::default());
+        redis.down.store(true, Ordering::SeqCst);
+        let settings = RedisDegradation { circuit_failure_threshold: 1, circuit_open_secs: 0, ..RedisDegradation::default() };
+        let (limiter, metrics) = limiter(redis.clone(), settings);
+        let policy = RatePolicy::fixed(100);
+        assert!(limiter.check("k", &policy, "other").await.unwrap().allowed);
+        assert!(metrics.gather_text().unwrap().contains("gateway_redis_up 0"));
+
+        redis.down.store(false, Ordering::SeqCst);
+        assert_eq!(limiter.check("k", &policy, "other").await.unwrap().current, 1);
+        assert!(metrics.gather_text().unwrap().contains("gateway_redis_up 1"));
+    }
+
+    #[test]
+    fn the_local_bucket_never_exceeds_the_keys_own_limit() {
+        let settings = RedisDegradation { fallback_rpm: Some(1_000), ..RedisDegradation::default() };
+        let policy = RatePolicy { algorithm: RateAlgorithm::SlidingWindow, limit: 60, burst: 90 };
+        assert_eq!(settings.fallback_policy(&policy), RatePolicy { algorithm: RateAlgorithm::TokenBucket, limit: 60, burst: 60 });
+    }
+}
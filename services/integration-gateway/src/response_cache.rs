@@ -0,0 +1,234 @@
+//! Opt-in cache for GET responses proxied through the gateway (`RESPONSE_CACHE_ENABLED=1`).
+//!
+//! Entries are scoped per tenant and expire after `RESPONSE_CACHE_TTL_SECS` (default 30).
+//! Invalidation drops everything cached for a tenant at once: the Redis engine bumps a per-tenant
+//! generation that is part of every key, so stale entries are never read again and simply expire.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// A stored upstream response. Only UTF-8 bodies are cached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait ResponseCacheEngine: Send + Sync {
+    async fn get(&self, tenant_id: Uuid, key: &str) -> Result<Option<CachedResponse>>;
+    async fn put(&self, tenant_id: Uuid, key: &str, response: &CachedResponse, ttl: Duration) -> Result<()>;
+    /// Forget every cached response of the tenant.
+    async fn invalidate_tenant(&self, tenant_id: Uuid) -> Result<()>;
+}
+
+#[derive(Clone)]
+pub struct ResponseCache {
+    pub engine: Arc<dyn ResponseCacheEngine>,
+    pub ttl: Duration,
+}
+
+impl ResponseCache {
+    /// `None` unless `RESPONSE_CACHE_ENABLED` is set.
+    pub async fn from_env(redis_url: &str) -> Result<Option<Self>> {
+        let enabled = std::env::var("RESPONSE_CACHE_ENABLED")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let ttl_secs = std::env::var("RESPONSE_CACHE_TTL_SECS").ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(30);
+        let prefix = std::env::var("RESPONSE_CACHE_PREFIX").unwrap_or_else(|_| "integration-gateway:respcache".to_string());
+        let engine = RedisResponseCache::new(redis_url, prefix).await?;
+        Ok(Some(Self { engine: Arc::new(engine), ttl: Duration::from_secs(ttl_secs.max(1)) }))
+    }
+}
+
+/// Cache key for a request: the caller's roles (responses may differ by role), the path, and the
+/// query string with its parameters sorted so their order doesn't matter.
+pub fn cache_key(roles: &str, path: &str, query: Option<&str>) -> String {
+    let mut roles: Vec<&str> = roles.split(',').map(str::trim).filter(|r| !r.is_empty()).collect();
+    roles.sort_unstable();
+    let mut params: Vec<&str> = query.unwrap_or_default().split('&').filter(|p| !p.is_empty()).collect();
+    params.sort_unstable();
+    format!("{}:{}?{}", roles.join(","), path, params.join("&"))
+}
+
+// ---------------- Redis Implementation ----------------
+
+#[derive(Clone)]
+pub struct RedisResponseCache {
+    manager: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisResponseCache {
+    pub async fn new(redis_url: &str, prefix: String) -> Result<Self> {
+        let client = redis::Client::open(redis_url).context("Failed to create Redis client")?;
+        let manager = ConnectionManager::new(client)
+            .await
+            .context("Failed to create Redis connection manager")?;
+        Ok(Self { manager, prefix })
+    }
+
+    async fn entry_key(&self, tenant_id: Uuid, key: &str) -> Result<String> {
+        let mut conn = self.manager.clone();
+        let generation: Option<u64> = conn.get(format!("{}:gen:{}", self.prefix, tenant_id)).await?;
+        Ok(format!("{}:{}:{}:{}", self.prefix, tenant_id, generation.unwrap_or(0), key))
+    }
+}
+
+#[async_trait]
+impl ResponseCacheEngine for RedisResponseCache {
+    async fn get(&self, tenant_id: Uuid, key: &str) -> Result<Option<CachedResponse>> {
+        let entry_key = self.entry_key(tenant_id, key).await?;
+        let mut conn = self.manager.clone();
+        let raw: Option<String> = conn.get(&entry_key).await?;
+        Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+    }
+
+    async fn put(&self, tenant_id: Uuid, key: &str, response: &CachedResponse, ttl: Duration) -> Result<()> {
+        let entry_key = self.entry_key(tenant_id, key).await?;
+        let mut conn = self.manager.clone();
+        let _: () = conn.set_ex(&entry_key, serde_json::to_string(response)?, ttl.as_secs()).await?;
+        Ok(())
+    }
+
+    async fn invalidate_tenant(&self, tenant_id: Uuid) -> Result<()> {
+        let mut conn = self.manager.clone();
+        let _: u64 = conn.incr(format!("{}:gen:{}", self.prefix, tenant_id), 1).await?;
+        Ok(())
+    }
+}
+
+// ---------------- In-Memory Implementation (Tests) ----------------
+
+type Entries = HashMap<(Uuid, String), (CachedResponse, Instant)>;
+
+#[derive(Clone, Default)]
+pub struct InMemoryResponseCache {
+    inner: Arc<Mutex<Entries>>,
+}
+
+#[async_trait]
+impl ResponseCacheEngine for InMemoryResponseCache {
+    async fn get(&self, tenant_id: Uuid, key: &str) -> Result<Option<CachedResponse>> {
+        let guard = self.inner.lock().await;
+        Ok(guard
+            .get(&(tenant_id, key.to_string()))
+            .filter(|(_, expires)| Instant::now() < *expires)
+            .map(|(response, _)| response.clone()))
+    }
+
+    async fn put(&self, tenant_id: Uuid, key: &str, response: &CachedResponse, ttl: Duration) -> Result<()> {
+        let mut guard = self.inner.lock().await;
+        guard.insert((tenant_id, key.to_string()), (response.clone(), Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn invalidate_tenant(&self, tenant_id: Uuid) -> Result<()> {
+        self.inner.lock().await.retain(|(tenant, _), _| *tenant != tenant_id);
+        Ok(())
+    }
+}
+
+/// Consume product change events and drop the affected tenant's cached responses. Topics come from
+/// `RESPONSE_CACHE_INVALIDATION_TOPICS` (comma-separated); product-service publishes price changes
+/// as `product.updated`. Instances share one consumer group since the cache itself is shared.
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+pub fn spawn_cache_invalidation_consumer(
+    bootstrap: &str,
+    security: &common_config::KafkaSecurity,
+    cache: ResponseCache,
+    metrics: Arc<crate::metrics::GatewayMetrics>,
+) -> Result<()> {
+    use futures_util::StreamExt;
+    use rdkafka::consumer::{Consumer, StreamConsumer};
+    use rdkafka::Message;
+    use tracing::{debug, warn};
+
+    let topics = std::env::var("RESPONSE_CACHE_INVALIDATION_TOPICS")
+        .unwrap_or_else(|_| "product.created,product.updated,product.deleted,product.restored".to_string());
+    let topics: Vec<&str> = topics.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+    if topics.is_empty() {
+        return Ok(());
+    }
+    let consumer: StreamConsumer = security
+        .apply(&mut rdkafka::ClientConfig::new())
+        .set("bootstrap.servers", bootstrap)
+        .set("group.id", "integration-gateway-response-cache")
+        .set("auto.offset.reset", "latest")
+        .create()?;
+    consumer.subscribe(&topics)?;
+
+    tokio::spawn(async move {
+        let mut stream = consumer.stream();
+        while let Some(message) = stream.next().await {
+            let message = match message {
+                Ok(msg) => msg,
+                Err(err) => {
+                    warn!(?err, "Response cache invalidation consumer error");
+                    continue;
+                }
+            };
+            let Some(Ok(payload)) = message.payload_view::<str>() else {
+                continue;
+            };
+            let tenant_id = serde_json::from_str::<serde_json::Value>(payload)
+                .ok()
+                .and_then(|v| v.get("tenant_id").and_then(|t| t.as_str()).and_then(|t| Uuid::parse_str(t).ok()));
+            let Some(tenant_id) = tenant_id else {
+                warn!(topic = message.topic(), "Cache invalidation event without tenant_id");
+                continue;
+            };
+            match cache.engine.invalidate_tenant(tenant_id).await {
+                Ok(()) => {
+                    metrics.record_response_cache_invalidation("kafka");
+                    debug!(%tenant_id, topic = message.topic(), "Invalidated cached responses");
+                }
+                Err(err) => warn!(?err, %tenant_id, "Failed to invalidate cached responses"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &str) -> CachedResponse {
+        CachedResponse { status: 200, content_type: Some("application/json".into()), etag: None, body: body.into() }
+    }
+
+    #[test]
+    fn keys_ignore_parameter_and_role_order() {
+        assert_eq!(cache_key("support,admin", "/products", Some("page=2&limit=50")), cache_key("admin, support", "/products", Some("limit=50&page=2")));
+        assert_ne!(cache_key("admin", "/products", None), cache_key("support", "/products", None));
+        assert_eq!(cache_key("", "/products/lookup", Some("sku=A")), ":/products/lookup?sku=A");
+    }
+
+    #[tokio::test]
+    async fn invalidation_is_per_tenant_and_entries_expire() {
+        let cache = InMemoryResponseCache::default();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        cache.put(a, "k", &response("a"), Duration::from_secs(60)).await.unwrap();
+        cache.put(b, "k", &response("b"), Duration::from_secs(60)).await.unwrap();
+        cache.put(b, "short", &response("b"), Duration::ZERO).await.unwrap();
+
+        cache.invalidate_tenant(a).await.unwrap();
+        assert_eq!(cache.get(a, "k").await.unwrap(), None);
+        assert_eq!(cache.get(b, "k").await.unwrap(), Some(response("b")));
+        assert_eq!(cache.get(b, "short").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! `GET /admin/tenants/:tenant_id/overview`: one document with a tenant's health for support
+//! engineers.
+//!
+//! Product-service (catalog counts and sync version) and order-service (open orders, carts and
+//! outbox backlog) are asked for their `/tenants/:tenant_id/diagnostics` concurrently, each with
+//! its own timeout. A service that fails or is slow shows up as an error section and marks the
+//! overview `degraded` rather than failing it. The gateway adds what only it knows: the tenant's
+//! integration keys in the key cache and the kill switches in force.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap};
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use common_http_errors::{ApiError, ApiResult};
+use common_security::{ensure_role, Role, SecurityContext, SecurityCtxExtractor};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::integration_handlers::ForwardedAuthHeader;
+use crate::{AppState, CachedKey};
+
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Serialize)]
+pub struct TenantOverview {
+    pub tenant_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    /// `ok`, or `degraded` when a service section could not be fetched.
+    pub status: &'static str,
+    pub services: BTreeMap<&'static str, ServiceSection>,
+    pub key_cache: KeyCacheStatus,
+    /// Kill switches enabled right now; they apply to every tenant.
+    pub kill_switches: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceSection {
+    pub status: &'static str,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct KeyCacheStatus {
+    /// The tenant's integration keys the gateway accepts without a database lookup.
+    pub cached_keys: usize,
+    pub key_suffixes: Vec<String>,
+    pub cache_size: usize,
+}
+
+fn key_cache_status(cache: &HashMap<String, CachedKey>, tenant_id: Uuid) -> KeyCacheStatus {
+    let mut key_suffixes: Vec<String> =
+        cache.values().filter(|key| key.tenant_id == tenant_id).map(|key| key.key_suffix.clone()).collect();
+    key_suffixes.sort();
+    KeyCacheStatus { cached_keys: key_suffixes.len(), key_suffixes, cache_size: cache.len() }
+}
+
+async fn fetch_section(
+    state: &AppState,
+    sec: &SecurityContext,
+    roles: &str,
+    forwarded_auth: Option<&ForwardedAuthHeader>,
+    base: &str,
+    tenant_id: Uuid,
+) -> ServiceSection {
+    let started = Instant::now();
+    let mut request = state
+        .http_client
+        .get(format!("{base}/tenants/{tenant_id}/diagnostics"))
+        .timeout(UPSTREAM_TIMEOUT)
+        .header("X-Tenant-ID", tenant_id.to_string())
+        .header("X-Roles", roles);
+    if let Some(user_id) = sec.actor.id {
+        request = request.header("X-User-ID", user_id.to_string());
+    }
+    if let Some(ForwardedAuthHeader(auth)) = forwarded_auth {
+        request = request.header(header::AUTHORIZATION, auth);
+    }
+    let result = match request.send().await {
+        Ok(resp) if resp.status().is_success() => resp.json::<Value>().await.map_err(|err| format!("invalid response: {err}")),
+        Ok(resp) => Err(format!("HTTP {}", resp.status().as_u16())),
+        Err(err) if err.is_timeout() => Err("timed out".to_string()),
+        Err(err) => Err(format!("unreachable: {err}")),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(data) => ServiceSection { status: "ok", latency_ms, data: Some(data), error: None },
+        Err(error) => {
+            tracing::warn!(%tenant_id, base, error = %error, "Tenant diagnostics request failed");
+            ServiceSection { status: "error", latency_ms, data: None, error: Some(error) }
+        }
+    }
+}
+
+fn service_url(var: &str, default: &str) -> String {
+    std::env::var(var).unwrap_or_else(|_| default.to_string())
+}
+
+/// SuperAdmin only: the overview spans services and may name any tenant.
+pub async fn get_tenant_overview(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    forwarded_auth: Option<Extension<ForwardedAuthHeader>>,
+    Path(tenant_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> ApiResult<Json<TenantOverview>> {
+    ensure_role(&sec, Role::SuperAdmin)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id })?;
+    let roles = headers.get("X-Roles").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let forwarded_auth = forwarded_auth.as_ref().map(|Extension(auth)| auth);
+
+    let product_base = service_url("PRODUCT_SERVICE_URL", "http://localhost:8081");
+    let order_base = service_url("ORDER_SERVICE_URL", "http://localhost:8084");
+    let (product, order) = tokio::join!(
+        fetch_section(&state, &sec, roles, forwarded_auth, &product_base, tenant_id),
+        fetch_section(&state, &sec, roles, forwarded_auth, &order_base, tenant_id),
+    );
+    let services = BTreeMap::from([("product-service", product), ("order-service", order)]);
+    let status = if services.values().all(|section| section.status == "ok") { "ok" } else { "degraded" };
+    let key_cache = key_cache_status(&*state.key_cache.read().await, tenant_id);
+
+    Ok(Json(TenantOverview {
+        tenant_id,
+        generated_at: Utc::now(),
+        status,
+        services,
+        key_cache,
+        kill_switches: state.kill_switches.active(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_cache_status_only_lists_the_tenants_keys() {
+        let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let cache = HashMap::from([
+            ("a".to_string(), CachedKey { tenant_id: tenant, key_suffix: "9f2c".into() }),
+            ("b".to_string(), CachedKey { tenant_id: other, key_suffix: "1111".into() }),
+            ("c".to_string(), CachedKey { tenant_id: tenant, key_suffix: "04ab".into() }),
+        ]);
+        let status = key_cache_status(&cache, tenant);
+        assert_eq!(status, KeyCacheStatus { cached_keys: 2, key_suffixes: vec!["04ab".into(), "9f2c".into()], cache_size: 3 });
+        assert_eq!(key_cache_status(&HashMap::new(), tenant).cached_keys, 0);
+    }
+}
@@ -1,12 +1,11 @@
 use crate::config::GatewayConfig;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use serde::Serialize;
 use sqlx::PgPool;
 use std::collections::HashMap;
 use std::sync::Arc;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration as StdDuration;
 use tokio::sync::Mutex;
 use tokio::time::{interval, Duration, MissedTickBehavior};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use tracing::error;
@@ -236,7 +235,7 @@ impl UsageTracker {
                 let event = ApiKeyUsageSummary { action: "api_key.usage.summary", tenant_id: summary.tenant_id, key_hash: summary.key_hash.clone(), key_suffix: summary.key_suffix.clone(), window_start: summary.window_start, window_end: summary.window_end, request_count: summary.request_count, rejected_count: summary.rejected_count };
                 match serde_json::to_string(&event) {
                     Ok(payload) => {
-                        if let Err(err) = self.inner.producer.send(FutureRecord::to(&self.inner._topic).payload(&payload).key(&summary.tenant_id.to_string()), StdDuration::from_secs(0)).await {
+                        if let Err(err) = common_kafka::publish(&self.inner.producer, &self.inner._topic, &summary.tenant_id.to_string(), &payload).await {
                             error!(?err, tenant_id = %summary.tenant_id, key = %summary.key_hash, "Failed to publish API key usage summary");
                         }
                     }
@@ -1,122 +1,201 @@
-use axum::extract::State;
-use axum::http::{HeaderMap, StatusCode};
+//! Payment provider webhooks (`POST /webhooks/:provider`). The provider's
+//! [`PaymentWebhookAdapter`] verifies and normalizes a delivery; what follows is the same for every
+//! provider. A delivery is processed only when:
+//! - its signature header signs the raw body under the provider's secret (e.g.
+//!   `COINBASE_WEBHOOK_SECRET`), compared in constant time;
+//! - the event's time is within the provider's tolerance of now (e.g.
+//!   `COINBASE_WEBHOOK_TOLERANCE_SECONDS`). Coinbase signs the body only, so this is the one
+//!   timestamp a replayed delivery cannot change. The window has to outlast the provider's retries,
+//!   which go on for up to three days at Coinbase;
+//! - its event id is not yet in `webhook_events`. A delivery seen before is acknowledged without
+//!   effect.
+//!
+//! The internal event of a settled payment ([`PaymentEvent::internal_event`]) is staged into the
+//! outbox in the same transaction that records the event id, and the response is 200 only once
+//! that commits. Any database failure is a 500, so the provider delivers again and nothing was
+//! recorded in between.
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
 use bytes::Bytes;
-use hmac::{Hmac, Mac};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureRecord;
-use serde::Deserialize;
-use sha2::Sha256;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration;
-use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
 
-use crate::{events::PaymentCompletedEvent, AppState};
+use crate::payment_normalization::{InternalPaymentEvent, PaymentAdapterRegistry, PaymentEvent};
+use crate::{outbox, AppState};
 
-#[derive(Deserialize)]
-struct CoinbaseWebhook {
-    event: CoinbaseEvent,
+/// Whether an event created at `created_at` is within `tolerance_secs` of `now`.
+pub fn is_fresh(created_at: DateTime<Utc>, now: DateTime<Utc>, tolerance_secs: u64) -> bool {
+    (now - created_at).num_seconds().unsigned_abs() <= tolerance_secs
 }
 
-#[derive(Deserialize)]
-struct CoinbaseEvent {
-    #[serde(rename = "type")]
-    event_type: String,
-    data: CoinbaseCharge,
+/// A delivery that was acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDelivery {
+    /// Recorded; `staged` tells whether an event went to the outbox.
+    Processed { staged: bool },
+    /// The event id was processed before.
+    Duplicate,
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-struct CoinbaseCharge {
-    id: String,
-    code: String,
-    hosted_url: Option<String>,
-    metadata: Option<CoinbaseMeta>,
-    pricing: Option<CoinbasePricing>, // not used for now
+/// Why a delivery was refused. Sent as `X-Error-Code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookRejection {
+    UnknownProvider,
+    NotConfigured,
+    SignatureMissing,
+    SignatureMismatch,
+    Malformed,
+    Stale,
+    StoreFailed,
 }
 
-#[derive(Deserialize)]
-struct CoinbaseMeta {
-    order_id: Option<String>,
-    tenant_id: Option<String>,
-    amount: Option<String>,
-}
+impl WebhookRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::UnknownProvider => StatusCode::NOT_FOUND,
+            Self::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+            Self::SignatureMissing | Self::SignatureMismatch | Self::Stale => StatusCode::UNAUTHORIZED,
+            Self::Malformed => StatusCode::BAD_REQUEST,
+            Self::StoreFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-struct CoinbasePricing {
-    local: Option<PriceInfo>,
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownProvider => "unknown_provider",
+            Self::NotConfigured => "webhook_not_configured",
+            Self::SignatureMissing => "sig_missing",
+            Self::SignatureMismatch => "sig_mismatch",
+            Self::Malformed => "malformed",
+            Self::Stale => "sig_skew",
+            Self::StoreFailed => "store_failed",
+        }
+    }
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-struct PriceInfo {
-    amount: String,
-    currency: String,
+impl IntoResponse for WebhookRejection {
+    fn into_response(self) -> Response {
+        let mut resp = self.status().into_response();
+        resp.headers_mut().insert("X-Error-Code", HeaderValue::from_static(self.code()));
+        resp
+    }
 }
 
-pub async fn handle_coinbase_webhook(
-    #[allow(unused_variables)] State(state): State<AppState>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> StatusCode {
-    let sig_header = headers
-        .get("X-CC-Webhook-Signature")
-        .and_then(|h| h.to_str().ok());
-    let secret = std::env::var("COINBASE_WEBHOOK_SECRET").unwrap_or_default();
-    if sig_header.is_none() || secret.is_empty() {
-        return StatusCode::BAD_REQUEST;
+/// Verify and normalize one delivery for `provider`, then record its event id and stage what it
+/// means, all or nothing.
+pub async fn process_payment_webhook(
+    db: &PgPool,
+    registry: &PaymentAdapterRegistry,
+    provider: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<WebhookDelivery, WebhookRejection> {
+    let registered = registry.get(provider).ok_or(WebhookRejection::UnknownProvider)?;
+    let adapter = registered.adapter.as_ref();
+    let Some(secret) = registered.settings.secret.as_deref().filter(|secret| !secret.is_empty()) else {
+        return Err(WebhookRejection::NotConfigured);
+    };
+    let signature = headers
+        .get(adapter.signature_header())
+        .and_then(|h| h.to_str().ok())
+        .ok_or(WebhookRejection::SignatureMissing)?;
+    if !adapter.verify(secret, body, signature) {
+        tracing::warn!(provider, "Payment webhook signature mismatch");
+        return Err(WebhookRejection::SignatureMismatch);
     }
-    let signature = sig_header.unwrap();
-    type HmacSha256 = Hmac<Sha256>;
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can be created");
-    mac.update(&body);
-    let calc_sig = hex::encode(mac.finalize().into_bytes());
-    if calc_sig != signature {
-        tracing::warn!("Coinbase webhook signature mismatch");
-        return StatusCode::UNAUTHORIZED;
+    let event = adapter.normalize(body).map_err(|err| {
+        tracing::warn!(provider, %err, "Payment webhook could not be normalized");
+        WebhookRejection::Malformed
+    })?;
+    if !is_fresh(event.occurred_at, now, registered.settings.tolerance_secs) {
+        tracing::warn!(provider, event_id = %event.provider_event_id, occurred_at = %event.occurred_at, "Payment webhook outside the freshness window");
+        return Err(WebhookRejection::Stale);
     }
+    record(db, &event).await
+}
 
-    let Ok(webhook) = serde_json::from_slice::<CoinbaseWebhook>(&body) else {
-        return StatusCode::BAD_REQUEST;
+async fn record(db: &PgPool, event: &PaymentEvent) -> Result<WebhookDelivery, WebhookRejection> {
+    let store_failed = |err: sqlx::Error| {
+        tracing::error!(?err, "Failed to record payment webhook");
+        WebhookRejection::StoreFailed
     };
-    if webhook.event.event_type == "charge:confirmed" {
-        if let Some(meta) = webhook.event.data.metadata {
-            if let (Some(order_id_str), Some(tenant_id_str), Some(amount_str)) =
-                (meta.order_id, meta.tenant_id, meta.amount)
-            {
-                if let (Ok(order_id), Ok(tenant_id), Ok(amount)) = (
-                    Uuid::parse_str(&order_id_str),
-                    Uuid::parse_str(&tenant_id_str),
-                    amount_str.parse::<f64>(),
-                ) {
-                    #[allow(unused_variables)]
-                    let pay_event = PaymentCompletedEvent {
-                        order_id,
-                        tenant_id,
-                        method: "crypto".to_string(),
-                        amount,
-                    };
-                    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] {
-                        let payload = serde_json::to_string(&pay_event).unwrap();
-                        if let Err(err) = state.kafka_producer
-                            .send(
-                                FutureRecord::to("payment.completed")
-                                    .payload(&payload)
-                                    .key(&tenant_id_str),
-                                Duration::from_secs(0),
-                            )
-                            .await
-                        {
-                            tracing::error!("Failed to emit payment.completed: {:?}", err);
-                        } else {
-                            tracing::info!(
-                                "Emitted payment.completed for order {} (crypto confirmed)",
-                                order_id
-                            );
-                        }
-                    }
-                }
+    let (provider, event_id) = (event.provider.as_str(), event.provider_event_id.as_str());
+    let mut tx = db.begin().await.map_err(store_failed)?;
+    // Event ids are the provider's, not a tenant's: the tenant is only known from the event itself.
+    let recorded = sqlx::query(
+        "INSERT INTO webhook_events (provider, event_id, event_type) VALUES ($1, $2, $3) ON CONFLICT (provider, event_id) DO NOTHING",
+    )
+    .bind(provider)
+    .bind(event_id)
+    .bind(&event.provider_event_type)
+    .execute(&mut *tx)
+    .await
+    .map_err(store_failed)?
+    .rows_affected();
+    if recorded == 0 {
+        tracing::info!(provider, event_id, "Duplicate payment webhook acknowledged");
+        return Ok(WebhookDelivery::Duplicate);
+    }
+    let internal = event.internal_event();
+    let staged = match &internal {
+        Some(InternalPaymentEvent::Completed(paid)) => outbox::stage(&mut tx, paid.tenant_id, paid).await,
+        Some(InternalPaymentEvent::Failed(failed)) => outbox::stage(&mut tx, failed.tenant_id, failed).await,
+        None => {
+            if event.is_settled() {
+                tracing::warn!(provider, event_id, "Settled payment webhook without tenant, order or amount");
             }
+            Ok(())
         }
+    };
+    staged.map_err(|err| {
+        tracing::error!(?err, provider, event_id, "Failed to stage payment event");
+        WebhookRejection::StoreFailed
+    })?;
+    tx.commit().await.map_err(store_failed)?;
+    if internal.is_some() {
+        tracing::info!(provider, event_id, order_id = ?event.order_id, kind = ?event.kind, "Staged payment event");
+    }
+    Ok(WebhookDelivery::Processed { staged: internal.is_some() })
+}
+
+pub async fn handle_payment_webhook(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(db) = state.db.as_ref() else {
+        return WebhookRejection::StoreFailed.into_response();
+    };
+    match process_payment_webhook(db, &state.payment_adapters, &provider, &headers, &body, Utc::now()).await {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn freshness_allows_the_window_either_way() {
+        let now = Utc::now();
+        assert!(is_fresh(now - Duration::seconds(300), now, 300));
+        assert!(is_fresh(now + Duration::seconds(300), now, 300), "sender clocks may run ahead");
+        assert!(!is_fresh(now - Duration::seconds(301), now, 300));
+        assert!(!is_fresh(now + Duration::seconds(301), now, 300));
     }
 
-    StatusCode::OK
+    #[test]
+    fn rejections_that_providers_should_retry_are_server_errors() {
+        assert!(WebhookRejection::StoreFailed.status().is_server_error());
+        assert!(WebhookRejection::NotConfigured.status().is_server_error());
+        assert_eq!(WebhookRejection::SignatureMismatch.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(WebhookRejection::Stale.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(WebhookRejection::UnknownProvider.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,140 @@
+//! Coinbase webhooks against Postgres: a signed delivery stages one `payment.completed`, a replay
+//! of it is acknowledged without a second, tampered and stale deliveries are refused, and an
+//! outbox failure is a 5xx that leaves nothing behind for the retry. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use chrono::{DateTime, Duration, Utc};
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use hmac::{Hmac, Mac};
+use integration_gateway::payment_adapters::CoinbaseAdapter;
+use integration_gateway::payment_normalization::{PaymentAdapterRegistry, WebhookSettings};
+use integration_gateway::webhook_handlers::{process_payment_webhook, WebhookDelivery, WebhookRejection};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const SECRET: &str = "whsec_test";
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["auth-service", "integration-gateway"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn settings() -> WebhookSettings {
+    WebhookSettings { secret: Some(SECRET.into()), tolerance_secs: 300 }
+}
+
+async fn process(
+    db: &PgPool,
+    settings: WebhookSettings,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<WebhookDelivery, WebhookRejection> {
+    let mut registry = PaymentAdapterRegistry::default();
+    registry.register(CoinbaseAdapter, settings);
+    process_payment_webhook(db, &registry, "coinbase", headers, body, now).await
+}
+
+fn charge_confirmed(event_id: &str, tenant: Uuid, order: Uuid, created_at: DateTime<Utc>) -> Vec<u8> {
+    serde_json::json!({
+        "event": {
+            "id": event_id,
+            "type": "charge:confirmed",
+            "created_at": created_at,
+            "data": {
+                "id": "charge-1",
+                "code": "ABC123",
+                "metadata": { "order_id": order, "tenant_id": tenant, "amount": "19.99" }
+            }
+        }
+    })
+    .to_string()
+    .into_bytes()
+}
+
+fn signed(body: &[u8]) -> HeaderMap {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(body);
+    let mut headers = HeaderMap::new();
+    headers.insert("X-CC-Webhook-Signature", HeaderValue::from_str(&hex::encode(mac.finalize().into_bytes())).unwrap());
+    headers
+}
+
+async fn staged(db: &PgPool, tenant: Uuid) -> Vec<(String, String)> {
+    sqlx::query_as("SELECT topic, payload->>'order_id' FROM outbox WHERE tenant_id = $1 ORDER BY id")
+        .bind(tenant.to_string())
+        .fetch_all(db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn replayed_and_tampered_deliveries_stage_nothing() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, order) = (Uuid::new_v4(), Uuid::new_v4());
+    let now = Utc::now();
+    let body = charge_confirmed(&format!("{tenant}-1"), tenant, order, now - Duration::seconds(30));
+    let headers = signed(&body);
+
+    let first = process(db, settings(), &headers, &body, now).await;
+    assert_eq!(first, Ok(WebhookDelivery::Processed { staged: true }));
+    let replay = process(db, settings(), &headers, &body, now + Duration::seconds(60)).await;
+    assert_eq!(replay, Ok(WebhookDelivery::Duplicate));
+    assert_eq!(staged(db, tenant).await, [("payment.completed".to_string(), order.to_string())]);
+
+    // The signature of one delivery does not cover another body, even one differing only in the amount.
+    let tampered = String::from_utf8(charge_confirmed(&format!("{tenant}-2"), tenant, order, now)).unwrap().replace("19.99", "0.01");
+    let refused = process(db, settings(), &headers, tampered.as_bytes(), now).await;
+    assert_eq!(refused, Err(WebhookRejection::SignatureMismatch));
+    let unsigned = process(db, settings(), &HeaderMap::new(), &body, now).await;
+    assert_eq!(unsigned, Err(WebhookRejection::SignatureMissing));
+
+    // A correctly signed delivery captured long ago is refused however new its event id looks to us.
+    let old = charge_confirmed(&format!("{tenant}-3"), tenant, Uuid::new_v4(), now - Duration::seconds(301));
+    let stale = process(db, settings(), &signed(&old), &old, now).await;
+    assert_eq!(stale, Err(WebhookRejection::Stale));
+
+    let unconfigured = process(db, WebhookSettings::default(), &headers, &body, now).await;
+    assert_eq!(unconfigured, Err(WebhookRejection::NotConfigured));
+    assert_eq!(staged(db, tenant).await.len(), 1);
+    let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_events WHERE provider = 'coinbase' AND event_id LIKE $1")
+        .bind(format!("{tenant}-%"))
+        .fetch_one(db)
+        .await
+        .unwrap();
+    assert_eq!(recorded, 1);
+}
+
+#[tokio::test]
+async fn an_outbox_failure_is_retried_rather_than_acknowledged() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, order) = (Uuid::new_v4(), Uuid::new_v4());
+    let now = Utc::now();
+    let body = charge_confirmed(&format!("{tenant}-retry"), tenant, order, now);
+    let headers = signed(&body);
+
+    // Refuses this tenant's outbox rows only, so tests sharing the database are unaffected.
+    let constraint = format!("outbox_refuses_{}", tenant.simple());
+    sqlx::query(&format!("ALTER TABLE outbox ADD CONSTRAINT {constraint} CHECK (tenant_id <> '{tenant}') NOT VALID"))
+        .execute(db)
+        .await
+        .unwrap();
+    let failed = process(db, settings(), &headers, &body, now).await;
+    assert_eq!(failed, Err(WebhookRejection::StoreFailed));
+    assert!(WebhookRejection::StoreFailed.status().is_server_error());
+    sqlx::query(&format!("ALTER TABLE outbox DROP CONSTRAINT {constraint}")).execute(db).await.unwrap();
+
+    // The event id was rolled back with the outbox row, so Coinbase's retry goes through.
+    let retried = process(db, settings(), &headers, &body, now).await;
+    assert_eq!(retried, Ok(WebhookDelivery::Processed { staged: true }));
+    assert_eq!(staged(db, tenant).await.len(), 1);
+    assert_eq!(WebhookRejection::SignatureMismatch.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,110 @@
+//! Batch ingestion against a real database, with order-service mocked.
+#![cfg(any(feature = "kafka", feature = "kafka-producer"))]
+
+use axum::{Router, routing::{get, post}, body::{Body, to_bytes}, http::Request};
+use httpmock::prelude::*;
+use tower::ServiceExt;
+use uuid::Uuid;
+use serde_json::{json, Value};
+use integration_gateway::order_batches::{get_order_batch, process_ready_items, submit_order_batch};
+use integration_gateway::{AppState, GatewayConfig, GatewayMetrics, UsageTracker};
+use sqlx::{Executor, PgPool};
+use std::sync::Arc;
+use common_auth::{JwtConfig, JwtVerifier};
+
+async fn state(pool: PgPool) -> AppState {
+    let config = Arc::new(GatewayConfig {
+        redis_url: "ignored".into(),
+        redis_prefix: "itg_test".into(),
+        rate_limit_rpm: 1000,
+        rate_limit_window_secs: 60,
+        rate_limit_burst_multiplier: 2.0,
+        rate_limit_alert_cooldown_secs: 300,
+        rate_limit_policies: Default::default(),
+        redis_degradation: Default::default(),
+        coinbase_webhook: Default::default(),
+        audit_topic: "audit.events.v1".into(),
+        alert_topic: "security.alerts.v1".into(),
+        api_usage_flush_secs: 60,
+        api_usage_summary_secs: 300,
+        security_alert_webhook_url: None,
+        security_alert_webhook_bearer: None,
+        payment_service_fallback_auth: None,
+        internal_api_token: None,
+        integration_key_topic: "security.integration_keys.v1".into(),
+    });
+    let metrics = Arc::new(GatewayMetrics::new().unwrap());
+    let producer: rdkafka::producer::FutureProducer = rdkafka::ClientConfig::new().set("bootstrap.servers","localhost:9092").create().expect("producer");
+    let usage = UsageTracker::new(config.clone(), pool.clone(), Some(producer.clone()));
+    let jwt = Arc::new(JwtVerifier::builder(JwtConfig::new("issuer","aud")).build().await.unwrap());
+    let mut state = AppState::test_with_in_memory(60, config, metrics, usage, jwt);
+    state.kafka_producer = producer;
+    state.db = Some(pool);
+    state
+}
+
+async fn call(app: &Router, method: &str, uri: &str, tenant: Uuid, body: Value) -> (u16, Value) {
+    let req = Request::builder().uri(uri).method(method)
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", "support")
+        .body(Body::from(body.to_string())).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status().as_u16();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_batch_is_processed_asynchronously() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    pool.execute(include_str!("../migrations/7003_create_external_order_batches.sql")).await.unwrap();
+
+    // Card orders are created; order-service rejects the cash one.
+    let order_service = MockServer::start();
+    let created_id = Uuid::new_v4();
+    let created = order_service.mock(|when, then| {
+        when.method(POST).path("/orders").body_contains("\"card\"").body_contains("\"idempotency_key\":\"batch:");
+        then.status(200).json_body(json!({"id": created_id, "tenant_id": Uuid::nil(), "total": 10.0, "status": "COMPLETED"}));
+    });
+    order_service.mock(|when, then| {
+        when.method(POST).path("/orders").body_contains("\"cash\"");
+        then.status(422);
+    });
+    std::env::set_var("ORDER_SERVICE_URL", order_service.base_url());
+
+    let state = state(pool.clone()).await;
+    let app = Router::new()
+        .route("/external/orders/batch", post(submit_order_batch))
+        .route("/external/orders/batch/:id", get(get_order_batch))
+        .with_state(state.clone());
+    let tenant = Uuid::new_v4();
+    let product = Uuid::new_v4();
+    let orders = json!({"orders": [
+        {"items": [{"product_id": product, "quantity": 1}], "payment_method": "card", "total": 10.0},
+        {"items": [{"product_id": "nope", "quantity": 1}], "payment_method": "card", "total": 10.0},
+        {"items": [{"product_id": product, "quantity": 2}], "payment_method": "cash", "total": 20.0}
+    ]});
+    let (status, batch) = call(&app, "POST", "/external/orders/batch", tenant, orders).await;
+    assert_eq!(status, 202, "{batch}");
+    assert_eq!((batch["status"].as_str(), batch["pending"].as_i64(), batch["invalid"].as_i64()), (Some("processing"), Some(2), Some(1)));
+    let id = batch["id"].as_str().unwrap().to_string();
+
+    // Other tenants can't see the batch.
+    let (status, _) = call(&app, "GET", &format!("/external/orders/batch/{id}"), Uuid::new_v4(), Value::Null).await;
+    assert_eq!(status, 404);
+
+    // Drain the queue (other tests' leftovers may be claimed too).
+    while process_ready_items(&state, &pool).await.unwrap() > 0 {}
+    created.assert();
+
+    let (_, done) = call(&app, "GET", &format!("/external/orders/batch/{id}"), tenant, Value::Null).await;
+    assert_eq!(done["status"], "completed");
+    assert_eq!((done["created"].as_i64(), done["failed"].as_i64(), done["invalid"].as_i64()), (Some(1), Some(1), Some(1)));
+    let items = done["items"].as_array().unwrap();
+    assert_eq!(items[0]["orderId"], created_id.to_string());
+    assert_eq!(items[1]["errors"][0]["field"], "items[0].product_id");
+    assert_eq!((items[2]["status"].as_str(), items[2]["attempts"].as_i64()), (Some("failed"), Some(1)));
+}
@@ -0,0 +1,115 @@
+//! Every provider's webhook samples under `tests/webhook_samples/<provider>/`, normalized by the
+//! registered adapter and checked against the canonical event and the internal event it becomes.
+//! A sample file that is missing from the table fails the test, so new samples cannot go unchecked.
+
+use std::collections::BTreeSet;
+
+use integration_gateway::payment_adapters::CoinbaseAdapter;
+use integration_gateway::payment_normalization::{
+    InternalPaymentEvent, PaymentAdapterRegistry, PaymentEventKind, WebhookSettings, PAYMENT_EVENT_VERSION,
+};
+use uuid::{uuid, Uuid};
+
+const TENANT: Uuid = uuid!("3f1b2a9c-5d4e-4f6a-8b7c-9d0e1f2a3b4c");
+const ORDER: Uuid = uuid!("8a7b6c5d-4e3f-4a1b-9c8d-7e6f5a4b3c2d");
+
+/// What a sample must normalize to.
+struct Case {
+    provider: &'static str,
+    sample: &'static str,
+    payload: &'static str,
+    kind: PaymentEventKind,
+    amount: Option<f64>,
+    currency: Option<&'static str>,
+    /// Whether the tenant and order are known.
+    for_order: bool,
+    /// Topic of the internal event, if one is published.
+    publishes: Option<&'static str>,
+}
+
+macro_rules! case {
+    ($provider:literal, $sample:literal, $kind:expr, $amount:expr, $currency:expr, $for_order:expr, $publishes:expr) => {
+        Case {
+            provider: $provider,
+            sample: $sample,
+            payload: include_str!(concat!("webhook_samples/", $provider, "/", $sample, ".json")),
+            kind: $kind,
+            amount: $amount,
+            currency: $currency,
+            for_order: $for_order,
+            publishes: $publishes,
+        }
+    };
+}
+
+fn failed(reason: &str) -> PaymentEventKind {
+    PaymentEventKind::Failed { reason: reason.to_string() }
+}
+
+fn cases() -> Vec<Case> {
+    use PaymentEventKind::{Completed, Pending};
+    vec![
+        case!("coinbase", "charge_created", Pending, Some(24.5), Some("USD"), true, None),
+        case!("coinbase", "charge_pending", Pending, Some(24.5), Some("USD"), true, None),
+        case!("coinbase", "charge_delayed", Pending, Some(24.5), Some("USD"), true, None),
+        case!("coinbase", "charge_confirmed", Completed, Some(24.5), Some("USD"), true, Some("payment.completed")),
+        case!("coinbase", "charge_confirmed_pricing_only", Completed, Some(18.0), Some("EUR"), true, Some("payment.completed")),
+        case!("coinbase", "charge_confirmed_without_metadata", Completed, Some(24.5), Some("USD"), false, None),
+        case!("coinbase", "charge_resolved", Completed, Some(24.5), Some("USD"), true, Some("payment.completed")),
+        case!("coinbase", "charge_failed_expired", failed("expired"), Some(24.5), Some("USD"), true, Some("payment.failed")),
+        case!("coinbase", "charge_failed_underpaid", failed("underpaid"), Some(24.5), Some("USD"), true, Some("payment.failed")),
+    ]
+}
+
+#[test]
+fn every_sample_normalizes_to_its_canonical_event() {
+    let mut registry = PaymentAdapterRegistry::default();
+    registry.register(CoinbaseAdapter, WebhookSettings::default());
+    for case in cases() {
+        let at = format!("{}/{}", case.provider, case.sample);
+        let adapter = &registry.get(case.provider).unwrap_or_else(|| panic!("{at}: provider not registered")).adapter;
+        let event = adapter.normalize(case.payload.as_bytes()).unwrap_or_else(|err| panic!("{at}: {err}"));
+        let raw: serde_json::Value = serde_json::from_str(case.payload).unwrap();
+
+        assert_eq!(event.schema_version, PAYMENT_EVENT_VERSION, "{at}");
+        assert_eq!(event.provider, case.provider, "{at}");
+        assert_eq!(event.provider_event_id, raw["event"]["id"].as_str().unwrap(), "{at}");
+        assert_eq!(event.provider_event_type, raw["event"]["type"].as_str().unwrap(), "{at}");
+        assert_eq!(event.occurred_at.to_rfc3339(), raw["event"]["created_at"].as_str().unwrap().replace('Z', "+00:00"), "{at}");
+        assert_eq!(event.kind, case.kind, "{at}");
+        assert_eq!((event.amount, event.currency.as_deref()), (case.amount, case.currency), "{at}");
+        let expected_refs = case.for_order.then_some((TENANT, ORDER));
+        assert_eq!(event.tenant_id.zip(event.order_id), expected_refs, "{at}");
+
+        let internal = event.internal_event();
+        let topic = internal.as_ref().map(|internal| match internal {
+            InternalPaymentEvent::Completed(_) => "payment.completed",
+            InternalPaymentEvent::Failed(_) => "payment.failed",
+        });
+        assert_eq!(topic, case.publishes, "{at}");
+        match internal {
+            Some(InternalPaymentEvent::Completed(paid)) => {
+                assert_eq!((paid.tenant_id, paid.order_id, Some(paid.amount)), (TENANT, ORDER, case.amount), "{at}");
+                assert_eq!(paid.method, "crypto", "{at}");
+            }
+            Some(InternalPaymentEvent::Failed(failed)) => {
+                assert_eq!(PaymentEventKind::Failed { reason: failed.reason }, case.kind, "{at}");
+            }
+            None => {}
+        }
+    }
+}
+
+#[test]
+fn every_sample_file_is_in_the_table() {
+    let listed: BTreeSet<_> = cases().iter().map(|case| format!("{}/{}.json", case.provider, case.sample)).collect();
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/webhook_samples");
+    let mut on_disk = BTreeSet::new();
+    for provider in std::fs::read_dir(&dir).unwrap() {
+        let provider = provider.unwrap();
+        for sample in std::fs::read_dir(provider.path()).unwrap() {
+            on_disk.insert(format!("{}/{}", provider.file_name().to_string_lossy(), sample.unwrap().file_name().to_string_lossy()));
+        }
+    }
+    assert_eq!(on_disk, listed, "samples on disk and in the table differ");
+}
@@ -0,0 +1,60 @@
+use bigdecimal::BigDecimal;
+use common_money::measure::{to_milli_units, UnitOfMeasure};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use inventory_service::bom::{expand, BomLine, Boms};
+use inventory_service::{candidate_locations, LocationStrategy};
+use std::str::FromStr;
+use uuid::Uuid;
+
+// The in-process part of a reservation: bundle expansion, location ordering and weighed-line
+// conversion. Row locks and the inserts are measured by the load test, not here.
+
+fn cart(lines: usize, bundles: usize) -> (Vec<(Uuid, i32)>, Boms) {
+    let mut boms = Boms::new();
+    let cart: Vec<(Uuid, i32)> = (0..lines).map(|i| (Uuid::from_u128(i as u128 + 1), 1 + (i % 4) as i32)).collect();
+    for (bundle, _) in cart.iter().take(bundles) {
+        let components = (0..3)
+            .map(|c| BomLine {
+                component_id: Uuid::from_u128(10_000 + c),
+                quantity: BigDecimal::from_str(if c == 2 { "0.150" } else { "2" }).unwrap(),
+                uom: if c == 2 { UnitOfMeasure::Kg } else { UnitOfMeasure::Each },
+            })
+            .collect();
+        boms.insert(*bundle, components);
+    }
+    (cart, boms)
+}
+
+fn bench_expand(c: &mut Criterion) {
+    for (lines, bundles) in [(5usize, 0usize), (20, 0), (20, 5)] {
+        let (cart, boms) = cart(lines, bundles);
+        c.bench_function(&format!("reservation_expand_{lines}_lines_{bundles}_bundles"), |b| {
+            b.iter(|| black_box(expand(black_box(&cart), &boms)));
+        });
+    }
+}
+
+fn bench_candidate_locations(c: &mut Criterion) {
+    let stock: Vec<(Uuid, i32)> = (0..25).map(|i| (Uuid::from_u128(i + 1), ((i * 37) % 50) as i32)).collect();
+    let preferred = Some(Uuid::from_u128(7));
+    c.bench_function("reservation_candidates_preferred", |b| {
+        b.iter(|| black_box(candidate_locations(preferred, LocationStrategy::Preferred, black_box(&stock))));
+    });
+    c.bench_function("reservation_candidates_fallback_25", |b| {
+        b.iter(|| black_box(candidate_locations(preferred, LocationStrategy::Fallback, black_box(&stock))));
+    });
+}
+
+fn bench_measured_units(c: &mut Criterion) {
+    let readings: Vec<BigDecimal> = ["0.412", "1.25", "0.0305", "2.4999", "12.5"].into_iter().map(|s| BigDecimal::from_str(s).unwrap()).collect();
+    c.bench_function("reservation_measured_units", |b| {
+        b.iter(|| {
+            for reading in &readings {
+                black_box(to_milli_units(reading));
+            }
+        });
+    });
+}
+
+criterion_group!(reservation, bench_expand, bench_candidate_locations, bench_measured_units);
+criterion_main!(reservation);
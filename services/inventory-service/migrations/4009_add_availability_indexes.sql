@@ -0,0 +1,8 @@
+-- 4009: indexes behind GET /inventory/availability.
+-- Stock rows are already reached through their primary keys; reservations had no index leading
+-- with (tenant_id, product_id), so summing them scanned every reservation for the tenant.
+CREATE INDEX IF NOT EXISTS idx_inventory_reservations_active_product
+    ON inventory_reservations (tenant_id, product_id, location_id) INCLUDE (quantity)
+    WHERE status = 'ACTIVE';
+CREATE INDEX IF NOT EXISTS idx_inventory_reservations_tenant_product
+    ON inventory_reservations (tenant_id, product_id) INCLUDE (quantity);
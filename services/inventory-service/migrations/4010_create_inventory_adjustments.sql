@@ -0,0 +1,28 @@
+-- 4010: log of direct stock corrections made through POST /inventory/receive and
+-- PUT /inventory/quantity. location_id is NULL for legacy single-row stock.
+CREATE TABLE IF NOT EXISTS inventory_adjustments (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NULL REFERENCES locations(id) ON DELETE SET NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('receive', 'set_quantity')),
+    reason_code TEXT NOT NULL,
+    note TEXT NULL,
+    previous_quantity INTEGER NOT NULL,
+    new_quantity INTEGER NOT NULL,
+    delta INTEGER NOT NULL,
+    actor_id UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_adjustments_product
+    ON inventory_adjustments (tenant_id, product_id, created_at DESC);
+
+ALTER TABLE inventory_adjustments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE inventory_adjustments FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS tenant_isolation ON inventory_adjustments;
+CREATE POLICY tenant_isolation ON inventory_adjustments
+    USING (COALESCE(current_setting('app.tenant_id', true), '') = ''
+           OR tenant_id = current_setting('app.tenant_id', true)::uuid)
+    WITH CHECK (COALESCE(current_setting('app.tenant_id', true), '') = ''
+           OR tenant_id = current_setting('app.tenant_id', true)::uuid);
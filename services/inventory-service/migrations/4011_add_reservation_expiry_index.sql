@@ -0,0 +1,6 @@
+-- 4011: index behind the batched reservation sweeper.
+-- The sweeper claims the oldest ACTIVE reservations past expires_at; expired rows pile up over time,
+-- so the older index on expires_at alone kept walking them.
+CREATE INDEX IF NOT EXISTS idx_inventory_reservations_active_expiry
+    ON inventory_reservations (expires_at)
+    WHERE status = 'ACTIVE' AND expires_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- 4012_add_outbox_message_key.sql
+-- Events staged by the shared producer when Kafka is unavailable carry their partition key.
+
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
@@ -0,0 +1,77 @@
+-- 4013: serial and lot tracking for products with products.tracking = 'serial' or 'lot'.
+-- Serials are captured at receive, held by an order's reservation and marked sold when the
+-- order completes. Lots carry an optional expiry for FEFO picking; lot_sales records which
+-- order (and customer) took units of each lot, for recalls. location_id is NULL for legacy
+-- single-row stock. Reservations keep what the till captured until the order completes.
+ALTER TABLE inventory_reservations
+    ADD COLUMN IF NOT EXISTS serial_numbers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS lot_code TEXT NULL;
+
+CREATE TABLE IF NOT EXISTS inventory_lots (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NULL REFERENCES locations(id) ON DELETE SET NULL,
+    lot_code TEXT NOT NULL,
+    expires_on DATE NULL,
+    quantity_received INTEGER NOT NULL DEFAULT 0,
+    quantity_on_hand INTEGER NOT NULL DEFAULT 0 CHECK (quantity_on_hand >= 0),
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ux_inventory_lots_code
+    ON inventory_lots (tenant_id, product_id, lot_code, COALESCE(location_id, '00000000-0000-0000-0000-000000000000'::uuid));
+CREATE INDEX IF NOT EXISTS idx_inventory_lots_fefo
+    ON inventory_lots (tenant_id, product_id, expires_on NULLS LAST, received_at)
+    WHERE quantity_on_hand > 0;
+
+CREATE TABLE IF NOT EXISTS inventory_serials (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NULL REFERENCES locations(id) ON DELETE SET NULL,
+    serial_number TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'in_stock' CHECK (status IN ('in_stock', 'reserved', 'sold')),
+    order_id UUID NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sold_at TIMESTAMPTZ NULL,
+    UNIQUE (tenant_id, product_id, serial_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_serials_order
+    ON inventory_serials (tenant_id, order_id)
+    WHERE order_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS inventory_lot_sales (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    lot_id UUID NOT NULL REFERENCES inventory_lots(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL,
+    order_id UUID NOT NULL,
+    customer_id UUID NULL,
+    quantity INTEGER NOT NULL,
+    sold_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_lot_sales_lot
+    ON inventory_lot_sales (tenant_id, lot_id);
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY['inventory_lots', 'inventory_serials', 'inventory_lot_sales'] LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)
+                WITH CHECK (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)',
+            t
+        );
+    END LOOP;
+END
+$$;
@@ -0,0 +1,68 @@
+-- 4014: inventory valuation. Every inbound movement (receipt, customer return, count increase)
+-- opens a cost layer; every outbound one (sale, count decrease) draws layers down and records
+-- what it cost in inventory_cost_consumptions. The value on hand at any time is the cost of the
+-- layers received by then less the cost consumed by then. unit_cost is per stock unit, so per
+-- gram-equivalent (thousandth of a kg/lb) for weighed products. location_id is NULL for legacy
+-- single-row stock.
+CREATE TABLE IF NOT EXISTS inventory_valuation_settings (
+    tenant_id UUID PRIMARY KEY,
+    method TEXT NOT NULL DEFAULT 'fifo' CHECK (method IN ('fifo', 'weighted_average')),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS inventory_cost_layers (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NULL REFERENCES locations(id) ON DELETE SET NULL,
+    source TEXT NOT NULL CHECK (source IN ('receipt', 'return', 'count')),
+    -- The adjustment (receipts, counts) or order (returns) that opened the layer.
+    source_id UUID NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    remaining INTEGER NOT NULL CHECK (remaining >= 0),
+    unit_cost NUMERIC NOT NULL CHECK (unit_cost >= 0),
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_cost_layers_open
+    ON inventory_cost_layers (tenant_id, product_id, location_id, received_at)
+    WHERE remaining > 0;
+CREATE INDEX IF NOT EXISTS idx_inventory_cost_layers_received
+    ON inventory_cost_layers (tenant_id, received_at);
+
+CREATE TABLE IF NOT EXISTS inventory_cost_consumptions (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NULL REFERENCES locations(id) ON DELETE SET NULL,
+    layer_id UUID NOT NULL REFERENCES inventory_cost_layers(id) ON DELETE CASCADE,
+    source TEXT NOT NULL CHECK (source IN ('sale', 'count')),
+    -- The order (sales) or adjustment (counts) that drew the stock.
+    source_id UUID NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    unit_cost NUMERIC NOT NULL,
+    consumed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_cost_consumptions_consumed
+    ON inventory_cost_consumptions (tenant_id, consumed_at);
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY['inventory_valuation_settings', 'inventory_cost_layers', 'inventory_cost_consumptions'] LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)
+                WITH CHECK (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)',
+            t
+        );
+    END LOOP;
+END
+$$;
@@ -0,0 +1,104 @@
+-- 4015: reorder suggestions. Suppliers carry a lead time in days. A reorder policy per product
+-- and location says how much stock to keep, either between a minimum and a maximum or as days of
+-- cover at the current sales rate. A periodic job turns stock, open purchase orders and sales
+-- velocity (analytics' daily_product_sales) into suggestions. Accepting one adds its quantity to
+-- the draft purchase order for that supplier and location. Quantities are stock units, so
+-- thousandths of a kg/lb for weighed products.
+CREATE TABLE IF NOT EXISTS suppliers (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    lead_time_days INTEGER NOT NULL CHECK (lead_time_days >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS reorder_policies (
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES locations(id) ON DELETE CASCADE,
+    supplier_id UUID NULL REFERENCES suppliers(id) ON DELETE SET NULL,
+    policy TEXT NOT NULL CHECK (policy IN ('min_max', 'days_of_cover')),
+    min_quantity INTEGER NULL,
+    max_quantity INTEGER NULL,
+    cover_days INTEGER NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, product_id, location_id),
+    CHECK (
+        (policy = 'min_max' AND min_quantity >= 0 AND max_quantity > min_quantity)
+        OR (policy = 'days_of_cover' AND cover_days > 0)
+    )
+);
+
+CREATE TABLE IF NOT EXISTS purchase_orders (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    supplier_id UUID NULL REFERENCES suppliers(id) ON DELETE SET NULL,
+    location_id UUID NOT NULL REFERENCES locations(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'submitted', 'received', 'cancelled')),
+    created_by UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One draft per supplier and location, which accepted suggestions fill up.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_purchase_orders_one_draft
+    ON purchase_orders (tenant_id, location_id, COALESCE(supplier_id, '00000000-0000-0000-0000-000000000000'))
+    WHERE status = 'draft';
+
+CREATE TABLE IF NOT EXISTS purchase_order_lines (
+    purchase_order_id UUID NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (purchase_order_id, product_id)
+);
+
+CREATE TABLE IF NOT EXISTS reorder_suggestions (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES locations(id) ON DELETE CASCADE,
+    supplier_id UUID NULL REFERENCES suppliers(id) ON DELETE SET NULL,
+    policy TEXT NOT NULL,
+    -- The figures the quantity was computed from.
+    on_hand BIGINT NOT NULL,
+    on_order BIGINT NOT NULL,
+    daily_velocity DOUBLE PRECISION NOT NULL,
+    lead_time_days INTEGER NOT NULL,
+    suggested_quantity INTEGER NOT NULL CHECK (suggested_quantity > 0),
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'accepted', 'dismissed')),
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMPTZ NULL,
+    decided_by UUID NULL,
+    purchase_order_id UUID NULL REFERENCES purchase_orders(id) ON DELETE SET NULL
+);
+
+-- At most one open suggestion per product and location; the job refreshes it in place.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_reorder_suggestions_open
+    ON reorder_suggestions (tenant_id, product_id, location_id)
+    WHERE status = 'open';
+CREATE INDEX IF NOT EXISTS idx_reorder_suggestions_decided
+    ON reorder_suggestions (tenant_id, product_id, location_id, decided_at)
+    WHERE status = 'dismissed';
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY['suppliers', 'reorder_policies', 'purchase_orders', 'purchase_order_lines', 'reorder_suggestions'] LOOP
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format('DROP POLICY IF EXISTS tenant_isolation ON %I', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I
+                USING (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)
+                WITH CHECK (COALESCE(current_setting(''app.tenant_id'', true), '''') = ''''
+                       OR tenant_id = current_setting(''app.tenant_id'', true)::uuid)',
+            t
+        );
+    END LOOP;
+END
+$$;
@@ -0,0 +1,503 @@
+use crate::{crossed_below_threshold, AppState, DEFAULT_THRESHOLD};
+use crate::location_handlers::DEFAULT_LOCATION_CODE;
+use crate::tracking_handlers::{normalize_lot_code, normalize_serials, record_receipt, ReceiptTracking};
+use crate::valuation;
+use axum::extract::State;
+use axum::Json;
+use common_db::{db_error, query, query_scalar};
+use common_http_errors::ApiError;
+use common_money::measure::to_milli_units;
+use bigdecimal::BigDecimal;
+use common_security::{ensure_capability, Capability, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+/// Reason codes accepted by the stock correction endpoints.
+pub const ADJUSTMENT_REASON_CODES: &[&str] =
+    &["received", "cycle_count", "damaged", "shrinkage", "return_to_stock", "correction", "other"];
+
+const MAX_NOTE_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentKind {
+    /// Add delivered units to the current quantity.
+    Receive,
+    /// Overwrite the quantity with a counted value.
+    SetQuantity,
+}
+
+impl AdjustmentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Receive => "receive",
+            Self::SetQuantity => "set_quantity",
+        }
+    }
+
+    /// The new on-hand quantity, or `None` when receiving would overflow.
+    pub fn apply(&self, previous: i32, quantity: i32) -> Option<i32> {
+        match self {
+            Self::Receive => previous.checked_add(quantity),
+            Self::SetQuantity => Some(quantity),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiveStockRequest {
+    pub product_id: Uuid,
+    /// Units received; must be positive.
+    #[serde(default)]
+    pub quantity: i32,
+    /// Weight received for products sold by kg/lb; replaces `quantity` when set.
+    #[serde(default)]
+    pub measured_quantity: Option<BigDecimal>,
+    /// Multi-location only; defaults to the tenant's MAIN location.
+    pub location_id: Option<Uuid>,
+    /// Defaults to `received`.
+    pub reason_code: Option<String>,
+    pub note: Option<String>,
+    /// Serial-tracked products: one serial per unit received.
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    /// Lot-tracked products: the lot the units belong to, and its expiry if it has one.
+    pub lot_code: Option<String>,
+    pub expires_on: Option<chrono::NaiveDate>,
+    /// Cost per unit received, or per kg/lb with `measured_quantity`; opens the cost layer for
+    /// valuation. Defaults to the current average cost.
+    pub unit_cost: Option<BigDecimal>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetQuantityRequest {
+    pub product_id: Uuid,
+    /// Counted on-hand quantity; zero or more.
+    #[serde(default)]
+    pub quantity: i32,
+    /// Weighed count for products sold by kg/lb; replaces `quantity` when set.
+    #[serde(default)]
+    pub measured_quantity: Option<BigDecimal>,
+    /// Multi-location only; defaults to the tenant's MAIN location.
+    pub location_id: Option<Uuid>,
+    pub reason_code: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdjustmentResponse {
+    pub adjustment_id: Uuid,
+    pub product_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub kind: AdjustmentKind,
+    pub reason_code: String,
+    pub previous_quantity: i32,
+    pub new_quantity: i32,
+    pub delta: i32,
+    pub threshold: i32,
+    /// True when this adjustment took the product to or below its threshold.
+    pub low_stock: bool,
+}
+
+struct Adjustment {
+    kind: AdjustmentKind,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    quantity: i32,
+    reason_code: String,
+    note: Option<String>,
+    /// Serials or lot captured with a receipt; empty for counts.
+    tracking: ReceiptTracking,
+    /// Cost per stock unit of a receipt.
+    unit_cost: Option<BigDecimal>,
+}
+
+fn bad_request(code: &'static str, trace_id: Option<Uuid>, message: String) -> ApiError {
+    ApiError::BadRequest { code, trace_id, message: Some(message) }
+}
+
+/// Stock units for a request: a weight becomes thousandths of its unit, a count is taken as is.
+fn stock_units(quantity: i32, measured: Option<&BigDecimal>, trace_id: Option<Uuid>) -> Result<i32, ApiError> {
+    match measured {
+        Some(measured) => to_milli_units(measured)
+            .ok_or_else(|| bad_request("invalid_quantity", trace_id, "measured_quantity is out of range".into())),
+        None => Ok(quantity),
+    }
+}
+
+/// Cost per stock unit: a weighed receipt's cost per kg/lb spreads over its thousandths.
+fn stock_unit_cost(unit_cost: Option<&BigDecimal>, measured: bool, trace_id: Option<Uuid>) -> Result<Option<BigDecimal>, ApiError> {
+    let Some(cost) = unit_cost else { return Ok(None) };
+    if cost < &BigDecimal::from(0) {
+        return Err(bad_request("invalid_unit_cost", trace_id, "unit_cost cannot be negative".into()));
+    }
+    Ok(Some(if measured { cost / BigDecimal::from(1000) } else { cost.clone() }))
+}
+
+/// Validate the reason code and note shared by both endpoints.
+fn validate_reason(reason_code: &str, note: Option<String>, trace_id: Option<Uuid>) -> Result<(String, Option<String>), ApiError> {
+    let reason_code = reason_code.trim().to_ascii_lowercase();
+    if !ADJUSTMENT_REASON_CODES.contains(&reason_code.as_str()) {
+        return Err(bad_request(
+            "invalid_reason_code",
+            trace_id,
+            format!("reason_code must be one of: {}", ADJUSTMENT_REASON_CODES.join(", ")),
+        ));
+    }
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if note.as_ref().is_some_and(|n| n.chars().count() > MAX_NOTE_LEN) {
+        return Err(bad_request("note_too_long", trace_id, format!("note is limited to {MAX_NOTE_LEN} characters")));
+    }
+    Ok((reason_code, note))
+}
+
+/// `POST /inventory/receive`: add delivered stock.
+pub async fn receive_stock(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(payload): Json<ReceiveStockRequest>,
+) -> Result<Json<AdjustmentResponse>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryAdjust)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_adjust", trace_id: sec.trace_id })?;
+    let quantity = stock_units(payload.quantity, payload.measured_quantity.as_ref(), sec.trace_id)?;
+    if quantity <= 0 {
+        return Err(bad_request("invalid_quantity", sec.trace_id, "quantity must be greater than zero".into()));
+    }
+    let (reason_code, note) = validate_reason(payload.reason_code.as_deref().unwrap_or("received"), payload.note, sec.trace_id)?;
+    let unit_cost = stock_unit_cost(payload.unit_cost.as_ref(), payload.measured_quantity.is_some(), sec.trace_id)?;
+    let tracking = ReceiptTracking {
+        serial_numbers: normalize_serials(&payload.serial_numbers, sec.trace_id)?,
+        lot_code: normalize_lot_code(payload.lot_code.as_deref(), sec.trace_id)?,
+        expires_on: payload.expires_on,
+    };
+    let adjustment = Adjustment {
+        kind: AdjustmentKind::Receive,
+        product_id: payload.product_id,
+        location_id: payload.location_id,
+        quantity,
+        reason_code,
+        note,
+        tracking,
+        unit_cost,
+    };
+    apply_adjustment(&state, &sec, adjustment).await.map(Json)
+}
+
+/// `PUT /inventory/quantity`: overwrite on-hand stock with a counted quantity.
+pub async fn set_quantity(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(payload): Json<SetQuantityRequest>,
+) -> Result<Json<AdjustmentResponse>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryAdjust)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_adjust", trace_id: sec.trace_id })?;
+    let quantity = stock_units(payload.quantity, payload.measured_quantity.as_ref(), sec.trace_id)?;
+    if quantity < 0 {
+        return Err(bad_request("invalid_quantity", sec.trace_id, "quantity cannot be negative".into()));
+    }
+    let (reason_code, note) = validate_reason(&payload.reason_code, payload.note, sec.trace_id)?;
+    let adjustment = Adjustment {
+        kind: AdjustmentKind::SetQuantity,
+        product_id: payload.product_id,
+        location_id: payload.location_id,
+        quantity,
+        reason_code,
+        note,
+        tracking: ReceiptTracking::default(),
+        unit_cost: None,
+    };
+    apply_adjustment(&state, &sec, adjustment).await.map(Json)
+}
+
+/// Product-wide quantity and threshold, matching how order completion detects low stock in
+/// multi-location mode (sum of locations, lowest threshold).
+async fn aggregate_stock(conn: &mut sqlx::PgConnection, tenant_id: Uuid, product_id: Uuid) -> Result<(i32, i32), ApiError> {
+    let row = query(
+        "SELECT COALESCE(SUM(quantity), 0)::int AS quantity, COALESCE(MIN(threshold), $3) AS threshold
+         FROM inventory_items WHERE tenant_id = $1 AND product_id = $2",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .bind(DEFAULT_THRESHOLD)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| ApiError::internal(e, None))?;
+    Ok((row.get("quantity"), row.get("threshold")))
+}
+
+/// The stock location to adjust: the requested one (which must belong to the tenant) or MAIN.
+async fn resolve_location(conn: &mut sqlx::PgConnection, sec: &SecurityContext, location_id: Option<Uuid>) -> Result<Uuid, ApiError> {
+    let found: Option<Uuid> = match location_id {
+        Some(id) => query_scalar::<Uuid>("SELECT id FROM locations WHERE tenant_id = $1 AND id = $2 AND active")
+            .bind(sec.tenant_id)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await,
+        None => query_scalar::<Uuid>("SELECT id FROM locations WHERE tenant_id = $1 AND code = $2")
+            .bind(sec.tenant_id)
+            .bind(DEFAULT_LOCATION_CODE)
+            .fetch_optional(&mut *conn)
+            .await,
+    }
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    match (found, location_id) {
+        (Some(id), _) => Ok(id),
+        (None, Some(_)) => Err(ApiError::NotFound { code: "location_not_found", trace_id: sec.trace_id }),
+        (None, None) => Err(bad_request("location_required", sec.trace_id, "location_id is required: the tenant has no default location".into())),
+    }
+}
+
+async fn apply_adjustment(state: &AppState, sec: &SecurityContext, adj: Adjustment) -> Result<AdjustmentResponse, ApiError> {
+    let tenant_id = sec.tenant_id;
+    let mut tx = state.db.begin_for(sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+
+    // (location, previous, new, threshold, aggregate before, aggregate after)
+    let (location_id, previous, new_quantity, threshold, aggregate_before, aggregate_after) = if state.multi_location_enabled {
+        let location_id = resolve_location(&mut tx, sec, adj.location_id).await?;
+        query(
+            "INSERT INTO inventory_items (tenant_id, product_id, location_id, quantity, threshold) VALUES ($1, $2, $3, 0, $4)
+             ON CONFLICT (tenant_id, product_id, location_id) DO NOTHING",
+        )
+        .bind(tenant_id)
+        .bind(adj.product_id)
+        .bind(location_id)
+        .bind(DEFAULT_THRESHOLD)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let previous: i32 = query_scalar::<i32>(
+            "SELECT quantity FROM inventory_items WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 FOR UPDATE",
+        )
+        .bind(tenant_id)
+        .bind(adj.product_id)
+        .bind(location_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let (aggregate_before, threshold) = aggregate_stock(&mut tx, tenant_id, adj.product_id).await?;
+        let new_quantity = adj
+            .kind
+            .apply(previous, adj.quantity)
+            .ok_or_else(|| bad_request("invalid_quantity", sec.trace_id, "quantity would overflow".into()))?;
+        query("UPDATE inventory_items SET quantity = $4, updated_at = NOW() WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3")
+            .bind(tenant_id)
+            .bind(adj.product_id)
+            .bind(location_id)
+            .bind(new_quantity)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let aggregate_after = aggregate_before - previous + new_quantity;
+        if state.dual_write_enabled {
+            // Keep the legacy row equal to the sum of locations, which the dual-write check expects.
+            query(
+                "INSERT INTO inventory (product_id, tenant_id, quantity, threshold) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (product_id, tenant_id) DO UPDATE SET quantity = EXCLUDED.quantity",
+            )
+            .bind(adj.product_id)
+            .bind(tenant_id)
+            .bind(aggregate_after)
+            .bind(DEFAULT_THRESHOLD)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        }
+        (Some(location_id), previous, new_quantity, threshold, aggregate_before, aggregate_after)
+    } else {
+        query(
+            "INSERT INTO inventory (product_id, tenant_id, quantity, threshold) VALUES ($1, $2, 0, $3)
+             ON CONFLICT (product_id, tenant_id) DO NOTHING",
+        )
+        .bind(adj.product_id)
+        .bind(tenant_id)
+        .bind(DEFAULT_THRESHOLD)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let row = query("SELECT quantity, threshold FROM inventory WHERE tenant_id = $1 AND product_id = $2 FOR UPDATE")
+            .bind(tenant_id)
+            .bind(adj.product_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let (previous, threshold): (i32, i32) = (row.get("quantity"), row.get("threshold"));
+        let new_quantity = adj
+            .kind
+            .apply(previous, adj.quantity)
+            .ok_or_else(|| bad_request("invalid_quantity", sec.trace_id, "quantity would overflow".into()))?;
+        query("UPDATE inventory SET quantity = $3 WHERE tenant_id = $1 AND product_id = $2")
+            .bind(tenant_id)
+            .bind(adj.product_id)
+            .bind(new_quantity)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        (None, previous, new_quantity, threshold, previous, new_quantity)
+    };
+
+    if adj.kind == AdjustmentKind::Receive {
+        record_receipt(&mut tx, sec, adj.product_id, location_id, adj.quantity, &adj.tracking).await?;
+    }
+
+    let adjustment_id = Uuid::new_v4();
+    let delta = new_quantity - previous;
+    let receipt = adj.kind == AdjustmentKind::Receive;
+    valuation::record_adjustment(&mut tx, tenant_id, adj.product_id, location_id, adjustment_id, receipt, delta, adj.unit_cost.as_ref())
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    query(
+        "INSERT INTO inventory_adjustments (id, tenant_id, product_id, location_id, kind, reason_code, note, previous_quantity, new_quantity, delta, actor_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+    .bind(adjustment_id)
+    .bind(tenant_id)
+    .bind(adj.product_id)
+    .bind(location_id)
+    .bind(adj.kind.as_str())
+    .bind(&adj.reason_code)
+    .bind(adj.note.as_deref())
+    .bind(previous)
+    .bind(new_quantity)
+    .bind(delta)
+    .bind(sec.actor.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let low_stock = crossed_below_threshold(aggregate_before, aggregate_after, threshold);
+    tracing::info!(
+        tenant_id = %tenant_id,
+        product_id = %adj.product_id,
+        kind = adj.kind.as_str(),
+        reason_code = %adj.reason_code,
+        previous,
+        new_quantity,
+        "Inventory adjusted"
+    );
+    let response = AdjustmentResponse {
+        adjustment_id,
+        product_id: adj.product_id,
+        location_id,
+        kind: adj.kind,
+        reason_code: adj.reason_code,
+        previous_quantity: previous,
+        new_quantity,
+        delta,
+        threshold,
+        low_stock,
+    };
+    publish_adjustment(state, sec, &response, adj.note.as_deref(), aggregate_after).await;
+    Ok(response)
+}
+
+/// Best-effort publishing after commit: `inventory.adjusted`, `inventory.low_stock` when the
+/// threshold was crossed, and the audit record.
+#[cfg_attr(not(any(feature = "kafka", feature = "kafka-producer")), allow(unused_variables))]
+async fn publish_adjustment(state: &AppState, sec: &SecurityContext, adj: &AdjustmentResponse, note: Option<&str>, aggregate_quantity: i32) {
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        use common_events::{DomainEvent, InventoryAdjustedEvent, InventoryLowStockEvent};
+
+        let event = InventoryAdjustedEvent {
+            schema_version: InventoryAdjustedEvent::SCHEMA_VERSION,
+            tenant_id: sec.tenant_id,
+            product_id: adj.product_id,
+            location_id: adj.location_id,
+            adjustment_id: adj.adjustment_id,
+            kind: adj.kind.as_str().to_string(),
+            reason_code: adj.reason_code.clone(),
+            previous_quantity: adj.previous_quantity,
+            new_quantity: adj.new_quantity,
+            delta: adj.delta,
+            actor_id: sec.actor.id,
+        };
+        if let Err(err) = common_kafka::publish_event(
+            &state.kafka_producer,
+            state.db.pool(),
+            event.tenant_id,
+            &event,
+        )
+        .await
+        {
+            tracing::error!(?err, product_id = %adj.product_id, tenant_id = %sec.tenant_id, "Failed to emit inventory.adjusted");
+        }
+        if adj.low_stock {
+            let alert = InventoryLowStockEvent {
+                schema_version: InventoryLowStockEvent::SCHEMA_VERSION,
+                tenant_id: sec.tenant_id,
+                product_id: adj.product_id,
+                quantity: aggregate_quantity,
+                threshold: adj.threshold,
+            };
+            if let Err(err) = common_kafka::publish_event(
+                &state.kafka_producer,
+                state.db.pool(),
+                alert.tenant_id,
+                &alert,
+            )
+            .await
+            {
+                tracing::error!(?err, product_id = %adj.product_id, tenant_id = %sec.tenant_id, "Failed to emit inventory.low_stock after adjustment");
+            }
+        }
+    }
+
+    let action = format!("inventory.{}", adj.kind.as_str());
+    let _audit = serde_json::json!({
+        "action": action,
+        "schema_version": 1,
+        "tenant_id": sec.tenant_id,
+        "actor_id": sec.actor.id,
+        "adjustment_id": adj.adjustment_id,
+        "product_id": adj.product_id,
+        "location_id": adj.location_id,
+        "reason_code": adj.reason_code,
+        "note": note,
+        "previous_quantity": adj.previous_quantity,
+        "new_quantity": adj.new_quantity,
+        "delta": adj.delta,
+    });
+    #[cfg(feature = "kafka")]
+    if let Err(_err) = common_kafka::publish(
+        &state.kafka_producer,
+        "audit.events",
+        &sec.tenant_id.to_string(),
+        &_audit.to_string(),
+    )
+    .await
+    {
+        state.metrics.audit_emit_failures.with_label_values(&[&action]).inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receive_adds_and_set_overwrites() {
+        assert_eq!(AdjustmentKind::Receive.apply(7, 5), Some(12));
+        assert_eq!(AdjustmentKind::Receive.apply(i32::MAX, 1), None);
+        assert_eq!(AdjustmentKind::SetQuantity.apply(7, 2), Some(2));
+    }
+
+    #[test]
+    fn reason_codes_are_normalised_and_checked() {
+        let (code, note) = validate_reason(" Cycle_Count ", Some("  ".into()), None).unwrap();
+        assert_eq!(code, "cycle_count");
+        assert_eq!(note, None);
+        assert!(validate_reason("lost_in_mail", None, None).is_err());
+        assert!(validate_reason("other", Some("x".repeat(MAX_NOTE_LEN + 1)), None).is_err());
+    }
+
+    #[test]
+    fn weighed_receipts_are_costed_per_thousandth() {
+        let per_kg = BigDecimal::from(8);
+        assert_eq!(stock_unit_cost(Some(&per_kg), true, None).unwrap(), Some("0.008".parse().unwrap()));
+        assert_eq!(stock_unit_cost(Some(&per_kg), false, None).unwrap(), Some(per_kg));
+        assert!(stock_unit_cost(Some(&BigDecimal::from(-1)), false, None).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use common_db::query;
+use common_events::ConsumedComponent;
+use common_money::measure::{to_milli_units, UnitOfMeasure};
+use sqlx::Row;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// One row of a bundle's bill of materials, as maintained by product-service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BomLine {
+    pub component_id: Uuid,
+    /// Component quantity per bundle, in the component's unit of measure.
+    pub quantity: BigDecimal,
+    pub uom: UnitOfMeasure,
+}
+
+/// Bills of materials keyed by bundle id; products without components are absent.
+pub type Boms = HashMap<Uuid, Vec<BomLine>>;
+
+/// Load the components of whichever of `product_ids` are bundles.
+pub async fn load_boms(conn: &mut sqlx::PgConnection, tenant_id: Uuid, product_ids: &[Uuid]) -> Result<Boms, sqlx::Error> {
+    if product_ids.is_empty() {
+        return Ok(Boms::new());
+    }
+    let rows = query(
+        "SELECT pc.bundle_id, pc.component_id, pc.quantity, p.uom
+         FROM product_components pc
+         JOIN products p ON p.tenant_id = pc.tenant_id AND p.id = pc.component_id
+         WHERE pc.tenant_id = $1 AND pc.bundle_id = ANY($2)
+         ORDER BY pc.bundle_id, pc.component_id",
+    )
+    .bind(tenant_id)
+    .bind(product_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut boms = Boms::new();
+    for row in rows {
+        let uom: Option<String> = row.get("uom");
+        boms.entry(row.get("bundle_id")).or_default().push(BomLine {
+            component_id: row.get("component_id"),
+            quantity: row.get("quantity"),
+            uom: uom.as_deref().and_then(UnitOfMeasure::parse).unwrap_or(UnitOfMeasure::Each),
+        });
+    }
+    Ok(boms)
+}
+
+/// Stock units of one component drawn by `bundles` bundles: thousandths for weighed
+/// components, whole units otherwise. `None` when the result does not fit.
+pub fn component_units(line: &BomLine, bundles: i32) -> Option<i32> {
+    let total = &line.quantity * BigDecimal::from(bundles);
+    if line.uom.is_measured() {
+        return to_milli_units(&total);
+    }
+    total.with_scale(0).into_bigint_and_exponent().0.to_i32()
+}
+
+/// Per-product stock lines after expansion, and what each bundle drew from its components.
+pub type Expansion = (Vec<(Uuid, i32)>, Vec<ConsumedComponent>);
+
+/// Replace bundle lines with their components. Stock lines come back sorted by product so row
+/// locks are taken in a stable order. `None` when a quantity overflows.
+pub fn expand(lines: &[(Uuid, i32)], boms: &Boms) -> Option<Expansion> {
+    let mut stock: BTreeMap<Uuid, i32> = BTreeMap::new();
+    let mut consumed: Vec<ConsumedComponent> = Vec::new();
+    for (product_id, quantity) in lines.iter().copied() {
+        let Some(components) = boms.get(&product_id) else {
+            let entry = stock.entry(product_id).or_insert(0);
+            *entry = entry.checked_add(quantity)?;
+            continue;
+        };
+        for line in components {
+            let units = component_units(line, quantity)?;
+            let entry = stock.entry(line.component_id).or_insert(0);
+            *entry = entry.checked_add(units)?;
+            consumed.push(ConsumedComponent { bundle_id: product_id, component_id: line.component_id, quantity: units });
+        }
+    }
+    Some((stock.into_iter().collect(), consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn bom_line(component_id: Uuid, quantity: &str, uom: UnitOfMeasure) -> BomLine {
+        BomLine { component_id, quantity: BigDecimal::from_str(quantity).unwrap(), uom }
+    }
+
+    #[test]
+    fn bundles_expand_into_component_units() {
+        let (bundle, bun, patty, plain) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut boms = Boms::new();
+        boms.insert(bundle, vec![bom_line(bun, "2", UnitOfMeasure::Each), bom_line(patty, "0.150", UnitOfMeasure::Kg)]);
+
+        let (stock, consumed) = expand(&[(bundle, 3), (bun, 1), (plain, 4)], &boms).unwrap();
+        let stock: HashMap<Uuid, i32> = stock.into_iter().collect();
+        assert_eq!(stock.get(&bun), Some(&7));
+        assert_eq!(stock.get(&patty), Some(&450));
+        assert_eq!(stock.get(&plain), Some(&4));
+        assert!(!stock.contains_key(&bundle));
+        assert_eq!(consumed.len(), 2);
+        assert!(consumed.iter().all(|c| c.bundle_id == bundle));
+    }
+
+    #[test]
+    fn refunds_return_components_as_negative_units() {
+        let (bundle, component) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut boms = Boms::new();
+        boms.insert(bundle, vec![bom_line(component, "2", UnitOfMeasure::Each)]);
+        let (stock, consumed) = expand(&[(bundle, -1)], &boms).unwrap();
+        assert_eq!(stock, vec![(component, -2)]);
+        assert_eq!(consumed[0].quantity, -2);
+    }
+}
@@ -0,0 +1,15 @@
+//! Stock movements an `order.completed` event asks for, before bundles are expanded.
+
+use common_events::OrderCompletedEvent;
+use common_money::measure::to_milli_units;
+use uuid::Uuid;
+
+/// `(product_id, units)` per line, negative on refunds. Weighed lines move stock in thousandths of
+/// a kg/lb, matching their reservations. `None` for refunds received against an RMA, whose
+/// resaleable units order-service already restocked at inspection.
+pub fn sold_lines(evt: &OrderCompletedEvent) -> Option<Vec<(Uuid, i32)>> {
+    if evt.rma_id.is_some() {
+        return None;
+    }
+    Some(evt.items.iter().map(|item| (item.product_id, item.measured_quantity.as_ref().and_then(to_milli_units).unwrap_or(item.quantity))).collect())
+}
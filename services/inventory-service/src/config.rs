@@ -0,0 +1,87 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+use crate::{DEFAULT_RESERVATION_TTL_SECS, DEFAULT_SWEEP_BATCH_SIZE, MAX_SWEEP_BATCH_SIZE};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    pub jwt: JwtSettings,
+    /// `MULTI_LOCATION_ENABLED`
+    pub multi_location_enabled: bool,
+    /// `INVENTORY_DUAL_WRITE`
+    pub dual_write_enabled: bool,
+    /// `INVENTORY_DUAL_WRITE_HEAL`: reset diverged legacy rows to the per-location sum.
+    pub dual_write_heal: bool,
+    /// `INVENTORY_INBOX_DEDUP`
+    pub inbox_dedup: bool,
+    pub reservation_default_ttl_secs: u64,
+    pub reservation_expiry_sweep_secs: u64,
+    pub reservation_sweep_batch_size: i64,
+    /// 0 disables the oversell checker.
+    pub oversell_check_interval_secs: u64,
+    /// `REORDER_INTERVAL_SECS`; 0 disables the reorder suggestion job.
+    pub reorder_interval_secs: u64,
+    /// `REORDER_VELOCITY_DAYS`: full days of sales averaged into the velocity.
+    pub reorder_velocity_days: i32,
+    /// `REORDER_DISMISS_DAYS`: how long a dismissed suggestion stays dismissed.
+    pub reorder_dismiss_days: i32,
+}
+
+impl InventoryConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8087);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let jwt = JwtSettings::read(&mut env).await;
+        let multi_location_enabled = env.flag("MULTI_LOCATION_ENABLED", false);
+        let dual_write_enabled = env.flag("INVENTORY_DUAL_WRITE", false);
+        let dual_write_heal = env.flag("INVENTORY_DUAL_WRITE_HEAL", false);
+        let inbox_dedup = env.flag("INVENTORY_INBOX_DEDUP", true);
+        let reservation_default_ttl_secs = env.or("RESERVATION_DEFAULT_TTL_SECS", DEFAULT_RESERVATION_TTL_SECS as u64);
+        env.check("RESERVATION_DEFAULT_TTL_SECS", reservation_default_ttl_secs > 0, "must be greater than 0");
+        let reservation_expiry_sweep_secs = env.or("RESERVATION_EXPIRY_SWEEP_SECS", 60);
+        env.check("RESERVATION_EXPIRY_SWEEP_SECS", reservation_expiry_sweep_secs > 0, "must be greater than 0");
+        let reservation_sweep_batch_size = env.or("RESERVATION_SWEEP_BATCH_SIZE", DEFAULT_SWEEP_BATCH_SIZE);
+        env.check(
+            "RESERVATION_SWEEP_BATCH_SIZE",
+            (1..=MAX_SWEEP_BATCH_SIZE).contains(&reservation_sweep_batch_size),
+            "must be between 1 and 10000",
+        );
+        let oversell_check_interval_secs = env.or("OVERSELL_CHECK_INTERVAL_SECS", 300);
+        let reorder_interval_secs = env.or("REORDER_INTERVAL_SECS", 3600);
+        let reorder_velocity_days = env.or("REORDER_VELOCITY_DAYS", 28);
+        env.check("REORDER_VELOCITY_DAYS", (1..=365).contains(&reorder_velocity_days), "must be between 1 and 365");
+        let reorder_dismiss_days = env.or("REORDER_DISMISS_DAYS", 7);
+        env.check("REORDER_DISMISS_DAYS", (0..=365).contains(&reorder_dismiss_days), "must be between 0 and 365");
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                jwt: jwt?,
+                multi_location_enabled,
+                dual_write_enabled,
+                dual_write_heal,
+                inbox_dedup,
+                reservation_default_ttl_secs,
+                reservation_expiry_sweep_secs,
+                reservation_sweep_batch_size,
+                oversell_check_interval_secs,
+                reorder_interval_secs,
+                reorder_velocity_days,
+                reorder_dismiss_days,
+            })
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! Dual-write consistency: with `MULTI_LOCATION_ENABLED` and `INVENTORY_DUAL_WRITE` both on, the
+//! legacy `inventory.quantity` must equal the sum of the product's `inventory_items`.
+//!
+//! The order-completed consumer checks the tenant it just changed and the reservation sweeper
+//! checks every tenant. Each mismatch counts in `dual_write_divergence_total{tenant_id,source}`.
+//! With `INVENTORY_DUAL_WRITE_HEAL` the legacy row is then reset to the per-location sum (the
+//! side every write path updates first), timed by `dual_write_heal_latency_seconds` and counted in
+//! `dual_write_heals_total{tenant_id,outcome}`.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use common_observability::InventoryMetrics;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Legacy rows that disagree with their locations; `$1` limits the check to one tenant.
+const DIVERGED: &str = "
+    SELECT i.tenant_id, i.product_id, i.quantity AS legacy, s.total
+    FROM inventory i
+    JOIN (
+        SELECT tenant_id, product_id, SUM(quantity)::bigint AS total FROM inventory_items
+        WHERE $1::uuid IS NULL OR tenant_id = $1
+        GROUP BY tenant_id, product_id
+    ) s ON s.tenant_id = i.tenant_id AND s.product_id = i.product_id
+    WHERE i.quantity::bigint <> s.total
+    ORDER BY i.tenant_id, i.product_id";
+
+/// Re-derive one legacy row from its locations, in one statement so a concurrent dual write
+/// cannot slip between reading the sum and writing it.
+const HEAL: &str = "
+    UPDATE inventory i SET quantity = s.total
+    FROM (SELECT SUM(quantity)::int AS total FROM inventory_items WHERE tenant_id = $1 AND product_id = $2) s
+    WHERE i.tenant_id = $1 AND i.product_id = $2 AND s.total IS NOT NULL AND i.quantity <> s.total";
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Divergence {
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub legacy: i32,
+    pub total: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct DualWriteReport {
+    pub divergent: Vec<Divergence>,
+    pub healed: usize,
+}
+
+/// Metrics sink and heal policy shared by the consumer and the sweeper.
+#[derive(Clone)]
+pub struct DualWriteCheck {
+    pub metrics: Arc<InventoryMetrics>,
+    pub heal: bool,
+}
+
+impl DualWriteCheck {
+    /// Find (and, if configured, heal) divergence for `tenant_id`, or for every tenant. `source`
+    /// labels where the check ran (`consumer`, `sweeper`).
+    pub async fn run(&self, db: &PgPool, tenant_id: Option<Uuid>, source: &'static str) -> sqlx::Result<DualWriteReport> {
+        let divergent: Vec<Divergence> = sqlx::query_as(DIVERGED).bind(tenant_id).fetch_all(db).await?;
+        let mut report = DualWriteReport { divergent, healed: 0 };
+        for d in &report.divergent {
+            let tenant = d.tenant_id.to_string();
+            self.metrics.dual_write_divergence.with_label_values(&[&tenant, source]).inc();
+            tracing::warn!(product_id = %d.product_id, tenant_id = %d.tenant_id, legacy = d.legacy, agg = d.total, source, "Dual-write divergence detected");
+            if !self.heal {
+                continue;
+            }
+            let started = Instant::now();
+            let outcome = sqlx::query(HEAL).bind(d.tenant_id).bind(d.product_id).execute(db).await;
+            self.metrics.heal_latency_seconds.observe(started.elapsed().as_secs_f64());
+            match outcome {
+                Ok(done) => {
+                    // Zero rows: a concurrent write already brought the two back in line.
+                    let label = if done.rows_affected() > 0 { "healed" } else { "converged" };
+                    self.metrics.dual_write_heals.with_label_values(&[&tenant, label]).inc();
+                    report.healed += done.rows_affected() as usize;
+                }
+                Err(err) => {
+                    self.metrics.dual_write_heals.with_label_values(&[&tenant, "failed"]).inc();
+                    tracing::error!(?err, product_id = %d.product_id, tenant_id = %d.tenant_id, "Dual-write heal failed");
+                }
+            }
+        }
+        Ok(report)
+    }
+}
@@ -1,110 +1,366 @@
 use crate::AppState;
-use axum::extract::{Query, State};
+use axum::extract::{Path, Query, State};
 use axum::{
     Json,
 };
 use common_security::{SecurityCtxExtractor, Capability, ensure_capability};
 use serde::{Deserialize, Serialize};
-use sqlx::{query, Row};
-use sqlx::query_as;
+use common_db::db_error;
+use common_db::pagination::{self, Listing, PageRequest, SortDirection, SortField, SortSpec};
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 use common_http_errors::ApiError;
 
-pub(crate) const LIST_INVENTORY_SQL: &str =
-    "SELECT product_id, tenant_id, quantity, threshold FROM inventory WHERE tenant_id = $1";
-
-
+/// Stock for one product in the listing's scope. There are no purchase orders or transfers yet,
+/// so nothing is reported as incoming.
 #[derive(Debug, sqlx::FromRow, Serialize)]
 pub struct InventoryRecord {
     pub product_id: Uuid,
     pub tenant_id: Uuid,
+    /// On hand.
     pub quantity: i32,
     pub threshold: i32,
+    /// Held by active reservations (open checkouts).
+    pub reserved: i32,
+    /// On hand minus reserved, floored at zero.
+    pub available: i32,
+    /// On hand at or below the threshold, the same test that raises low-stock alerts.
+    pub below_threshold: bool,
+    /// Nothing left to sell once reservations are taken out.
+    pub out_of_stock: bool,
 }
 
 #[derive(Debug, Deserialize, Default)]
 pub struct InventoryQueryParams {
     pub location_id: Option<Uuid>,
     pub location_ids: Option<String>, // CSV list of location_ids
+    /// Only products at or below their threshold.
+    #[serde(default)]
+    pub below_threshold: bool,
+}
+
+// Wraps one page of stock rows from `InventoryScope::select` (aliased `s`); reservations are
+// summed per product of that page through the 4009 indexes, so a page costs one query.
+const LISTING_SELECT: &str = "SELECT s.product_id, s.tenant_id, s.quantity, s.threshold,
+       COALESCE(r.reserved, 0)::int AS reserved,
+       GREATEST(s.quantity - COALESCE(r.reserved, 0), 0)::int AS available,
+       s.quantity <= s.threshold AS below_threshold,
+       s.quantity - COALESCE(r.reserved, 0) <= 0 AS out_of_stock
+    FROM (";
+
+static INVENTORY_SORT: SortSpec = SortSpec {
+    fields: &[SortField::new("product_id", "product_id", "uuid")],
+    default_field: "product_id",
+    default_direction: SortDirection::Asc,
+    tiebreak: "product_id",
+};
+
+/// Which stock rows a listing covers; multi-location scopes aggregate per product.
+enum InventoryScope {
+    Location(Uuid),
+    Locations(Vec<Uuid>),
+    AllLocations,
+    Legacy,
+}
+
+impl InventoryScope {
+    fn select(&self) -> &'static str {
+        match self {
+            Self::Location(_) => "SELECT product_id, tenant_id, quantity, threshold FROM inventory_items WHERE tenant_id = ",
+            Self::Locations(_) | Self::AllLocations => "SELECT product_id, tenant_id, SUM(quantity)::int AS quantity, MIN(threshold) AS threshold FROM inventory_items WHERE tenant_id = ",
+            Self::Legacy => "SELECT product_id, tenant_id, quantity, threshold FROM inventory WHERE tenant_id = ",
+        }
+    }
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, Postgres>, tenant_id: Uuid) {
+        builder.push_bind(tenant_id);
+        match self {
+            Self::Location(location_id) => {
+                builder.push(" AND location_id = ");
+                builder.push_bind(*location_id);
+            }
+            Self::Locations(ids) => {
+                builder.push(" AND location_id = ANY(");
+                builder.push_bind(ids.clone());
+                builder.push(")");
+            }
+            Self::AllLocations | Self::Legacy => {}
+        }
+    }
+
+    fn push_group_by(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        if matches!(self, Self::Locations(_) | Self::AllLocations) {
+            builder.push(" GROUP BY product_id, tenant_id");
+        }
+    }
+
+    /// Call after [`Self::push_group_by`]: aggregated scopes compare the summed quantity.
+    fn push_below_threshold(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        if matches!(self, Self::Locations(_) | Self::AllLocations) {
+            builder.push(" HAVING SUM(quantity) <= MIN(threshold)");
+        } else {
+            builder.push(" AND quantity <= threshold");
+        }
+    }
+
+    /// Lateral join summing the reservations held against each stock row's product. Legacy
+    /// reservations have no status, so every row counts, as in `POST /inventory/reservations`.
+    fn push_reserved_join(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        builder.push(
+            " LEFT JOIN LATERAL (SELECT SUM(quantity) AS reserved FROM inventory_reservations
+               WHERE tenant_id = s.tenant_id AND product_id = s.product_id",
+        );
+        match self {
+            Self::Location(location_id) => {
+                builder.push(" AND status = 'ACTIVE' AND location_id = ");
+                builder.push_bind(*location_id);
+            }
+            Self::Locations(ids) => {
+                builder.push(" AND status = 'ACTIVE' AND location_id = ANY(");
+                builder.push_bind(ids.clone());
+                builder.push(")");
+            }
+            Self::AllLocations => {
+                builder.push(" AND status = 'ACTIVE'");
+            }
+            Self::Legacy => {}
+        }
+        builder.push(") r ON TRUE");
+    }
 }
 
 pub async fn list_inventory(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Query(params): Query<InventoryQueryParams>,
-) -> Result<Json<Vec<InventoryRecord>>, ApiError> {
+    page: PageRequest,
+) -> Result<Json<Listing<InventoryRecord>>, ApiError> {
     // Capability-based authorization only (legacy role fallback removed)
     ensure_capability(&sec, Capability::InventoryView)
         .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
     let tenant_id = sec.tenant_id;
-    let records = if state.multi_location_enabled {
-        if let Some(location_id) = params.location_id {
-            let rows = query(
-                "SELECT product_id, tenant_id, quantity, threshold FROM inventory_items WHERE tenant_id = $1 AND location_id = $2",
-            )
-            .bind(tenant_id)
-            .bind(location_id)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| ApiError::internal(e, None))?;
-            rows.into_iter()
-                .map(|r| InventoryRecord {
-                    product_id: r.get("product_id"),
-                    tenant_id: r.get("tenant_id"),
-                    quantity: r.get::<i32, _>("quantity"),
-                    threshold: r.get::<i32, _>("threshold"),
-                })
-                .collect()
-        } else if let Some(list) = params.location_ids.as_ref() {
-            let ids: Vec<Uuid> = list
-                .split(',')
-                .filter_map(|s| Uuid::parse_str(s.trim()).ok())
-                .collect();
-            if ids.is_empty() {
-                Vec::new()
-            } else {
-                let rows = query(
-                    "SELECT product_id, tenant_id, SUM(quantity) as quantity, MIN(threshold) as threshold FROM inventory_items WHERE tenant_id = $1 AND location_id = ANY($2) GROUP BY product_id, tenant_id",
-                )
-                .bind(tenant_id)
-                .bind(&ids)
-                .fetch_all(&state.db)
-                .await
-                .map_err(|e| ApiError::internal(e, None))?;
-                rows.into_iter()
-                    .map(|r| InventoryRecord {
-                        product_id: r.get("product_id"),
-                        tenant_id: r.get("tenant_id"),
-                        quantity: r.get::<i64, _>("quantity") as i32,
-                        threshold: r.get::<i32, _>("threshold"),
-                    })
-                    .collect()
-            }
-        } else {
-            let rows = query(
-                "SELECT product_id, tenant_id, SUM(quantity) AS quantity, MIN(threshold) AS threshold FROM inventory_items WHERE tenant_id = $1 GROUP BY product_id, tenant_id",
-            )
-            .bind(tenant_id)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| ApiError::internal(e, None))?;
-            rows.into_iter()
-                .map(|r| InventoryRecord {
-                    product_id: r.get("product_id"),
-                    tenant_id: r.get("tenant_id"),
-                    quantity: r.get::<i64, _>("quantity") as i32,
-                    threshold: r.get::<i32, _>("threshold"),
-                })
-                .collect()
+    let plan = page.resolve(&INVENTORY_SORT, sec.trace_id)?;
+    let scope = if !state.multi_location_enabled {
+        InventoryScope::Legacy
+    } else if let Some(location_id) = params.location_id {
+        InventoryScope::Location(location_id)
+    } else if let Some(list) = params.location_ids.as_ref() {
+        let ids: Vec<Uuid> = list
+            .split(',')
+            .filter_map(|s| Uuid::parse_str(s.trim()).ok())
+            .collect();
+        if ids.is_empty() {
+            return Ok(Json(plan.finish(Vec::new(), Some(0), |record: &InventoryRecord, _| {
+                (record.product_id.to_string(), record.product_id)
+            })));
+        }
+        InventoryScope::Locations(ids)
+    } else {
+        InventoryScope::AllLocations
+    };
+
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, None))?;
+    let mut builder = QueryBuilder::new(LISTING_SELECT);
+    builder.push(scope.select());
+    scope.push_filters(&mut builder, tenant_id);
+    plan.push_cursor_filter(&mut builder);
+    scope.push_group_by(&mut builder);
+    if params.below_threshold {
+        scope.push_below_threshold(&mut builder);
+    }
+    plan.push_order_by(&mut builder);
+    plan.push_limit(&mut builder);
+    builder.push(") s");
+    scope.push_reserved_join(&mut builder);
+    plan.push_order_by(&mut builder);
+    let records = builder
+        .build_query_as::<InventoryRecord>()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, None))?;
+
+    let total_estimate = if plan.is_paginated() {
+        let mut estimate = pagination::estimate_query(scope.select());
+        scope.push_filters(&mut estimate, tenant_id);
+        scope.push_group_by(&mut estimate);
+        if params.below_threshold {
+            scope.push_below_threshold(&mut estimate);
         }
+        Some(pagination::estimate_rows(&mut *tx, estimate).await.map_err(|e| ApiError::internal(e, None))?)
     } else {
-        query_as::<_, InventoryRecord>(LIST_INVENTORY_SQL)
-            .bind(tenant_id)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| ApiError::internal(e, None))?
+        None
     };
-    Ok(Json(records))
+    tx.commit().await.map_err(|e| ApiError::internal(e, None))?;
+
+    Ok(Json(plan.finish(records, total_estimate, |record, _| {
+        (record.product_id.to_string(), record.product_id)
+    })))
+}
+
+/// Upper bound on `product_ids` per availability request (one checkout's worth of lines).
+pub const MAX_AVAILABILITY_PRODUCTS: usize = 200;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AvailabilityParams {
+    /// CSV list of product ids.
+    pub product_ids: Option<String>,
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, sqlx::FromRow, Serialize)]
+pub struct ProductAvailability {
+    pub product_id: Uuid,
+    pub on_hand: i32,
+    pub reserved: i32,
+    /// On hand minus active reservations, floored at zero.
+    pub available_to_promise: i32,
+    /// False when the product has no stock row in scope (never stocked there).
+    pub tracked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvailabilityResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub items: Vec<ProductAvailability>,
+}
+
+// Each query binds tenant `$1` and the product ids `$2`; the location query binds `$3`. All
+// three resolve in one round trip against the stock primary keys and the active-reservation
+// index from migration 4009. The legacy query counts every reservation row, mirroring the
+// check `POST /inventory/reservations` applies.
+const AVAILABILITY_AT_LOCATION: &str = "
+    SELECT p.product_id, COALESCE(ii.quantity, 0) AS on_hand, COALESCE(r.reserved, 0)::int AS reserved,
+           GREATEST(COALESCE(ii.quantity, 0) - COALESCE(r.reserved, 0), 0)::int AS available_to_promise,
+           ii.product_id IS NOT NULL AS tracked
+    FROM UNNEST($2::uuid[]) AS p(product_id)
+    LEFT JOIN inventory_items ii ON ii.tenant_id = $1 AND ii.product_id = p.product_id AND ii.location_id = $3
+    LEFT JOIN LATERAL (
+        SELECT SUM(quantity) AS reserved FROM inventory_reservations
+        WHERE tenant_id = $1 AND product_id = p.product_id AND location_id = $3 AND status = 'ACTIVE'
+    ) r ON TRUE
+    ORDER BY p.product_id";
+const AVAILABILITY_ALL_LOCATIONS: &str = "
+    SELECT p.product_id, COALESCE(ii.on_hand, 0)::int AS on_hand, COALESCE(r.reserved, 0)::int AS reserved,
+           GREATEST(COALESCE(ii.on_hand, 0) - COALESCE(r.reserved, 0), 0)::int AS available_to_promise,
+           ii.on_hand IS NOT NULL AS tracked
+    FROM UNNEST($2::uuid[]) AS p(product_id)
+    LEFT JOIN LATERAL (
+        SELECT SUM(quantity) AS on_hand FROM inventory_items
+        WHERE tenant_id = $1 AND product_id = p.product_id HAVING COUNT(*) > 0
+    ) ii ON TRUE
+    LEFT JOIN LATERAL (
+        SELECT SUM(quantity) AS reserved FROM inventory_reservations
+        WHERE tenant_id = $1 AND product_id = p.product_id AND status = 'ACTIVE'
+    ) r ON TRUE
+    ORDER BY p.product_id";
+const AVAILABILITY_LEGACY: &str = "
+    SELECT p.product_id, COALESCE(i.quantity, 0) AS on_hand, COALESCE(r.reserved, 0)::int AS reserved,
+           GREATEST(COALESCE(i.quantity, 0) - COALESCE(r.reserved, 0), 0)::int AS available_to_promise,
+           i.product_id IS NOT NULL AS tracked
+    FROM UNNEST($2::uuid[]) AS p(product_id)
+    LEFT JOIN inventory i ON i.tenant_id = $1 AND i.product_id = p.product_id
+    LEFT JOIN LATERAL (
+        SELECT SUM(quantity) AS reserved FROM inventory_reservations
+        WHERE tenant_id = $1 AND product_id = p.product_id
+    ) r ON TRUE
+    ORDER BY p.product_id";
+
+/// Parse the `product_ids` CSV, de-duplicating and enforcing the per-request cap.
+fn parse_product_ids(raw: Option<&str>, trace_id: Option<Uuid>) -> Result<Vec<Uuid>, ApiError> {
+    let raw = raw.map(str::trim).filter(|s| !s.is_empty()).ok_or(ApiError::BadRequest {
+        code: "missing_product_ids",
+        trace_id,
+        message: Some("product_ids is required".into()),
+    })?;
+    let mut ids = Vec::new();
+    for part in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let id = Uuid::parse_str(part).map_err(|_| ApiError::BadRequest {
+            code: "invalid_product_id",
+            trace_id,
+            message: Some(format!("'{part}' is not a valid product id")),
+        })?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.len() > MAX_AVAILABILITY_PRODUCTS {
+        return Err(ApiError::BadRequest {
+            code: "too_many_product_ids",
+            trace_id,
+            message: Some(format!("At most {MAX_AVAILABILITY_PRODUCTS} product ids per request")),
+        });
+    }
+    Ok(ids)
+}
+
+/// Available-to-promise per product: on hand minus active reservations, in one query.
+pub async fn get_availability(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<AvailabilityParams>,
+) -> Result<Json<AvailabilityResponse>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryView)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
+    let product_ids = parse_product_ids(params.product_ids.as_deref(), sec.trace_id)?;
+    // Location scoping only exists once stock is tracked per location.
+    let location_id = params.location_id.filter(|_| state.multi_location_enabled);
+    let sql = match (state.multi_location_enabled, location_id) {
+        (true, Some(_)) => AVAILABILITY_AT_LOCATION,
+        (true, None) => AVAILABILITY_ALL_LOCATIONS,
+        (false, _) => AVAILABILITY_LEGACY,
+    };
+
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let mut query = common_db::query_as::<ProductAvailability>(sql).bind(sec.tenant_id).bind(&product_ids);
+    if let Some(location_id) = location_id {
+        query = query.bind(location_id);
+    }
+    let items = query.fetch_all(&mut *tx).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    Ok(Json(AvailabilityResponse { location_id, items }))
+}
+
+/// Tables bundled into a tenant offboarding export; each query binds the tenant id as `$1`.
+const TENANT_EXPORT_QUERIES: &[(&str, &str)] = &[
+    ("inventory", "SELECT * FROM inventory WHERE tenant_id = $1"),
+    ("inventory_items", "SELECT * FROM inventory_items WHERE tenant_id = $1"),
+    ("locations", "SELECT * FROM locations WHERE tenant_id = $1"),
+    ("inventory_reservations", "SELECT * FROM inventory_reservations WHERE tenant_id = $1 ORDER BY created_at"),
+    ("inventory_adjustments", "SELECT * FROM inventory_adjustments WHERE tenant_id = $1 ORDER BY created_at"),
+    ("inventory_lots", "SELECT * FROM inventory_lots WHERE tenant_id = $1 ORDER BY received_at"),
+    ("inventory_serials", "SELECT * FROM inventory_serials WHERE tenant_id = $1 ORDER BY received_at"),
+    ("inventory_lot_sales", "SELECT * FROM inventory_lot_sales WHERE tenant_id = $1 ORDER BY sold_at"),
+];
+
+/// Offboarding export pulled by auth-service's tenant export job.
+pub async fn export_tenant_data(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    ensure_capability(&sec, Capability::GdprManage)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "gdpr_manage", trace_id: sec.trace_id })?;
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let mut tables = serde_json::Map::new();
+    for (name, sql) in TENANT_EXPORT_QUERIES {
+        let rows: String = common_db::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM ({sql}) t"
+        ))
+        .bind(tenant_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let rows = serde_json::from_str(&rows).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        tables.insert((*name).to_string(), rows);
+    }
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(serde_json::Value::Object(tables)))
 }
 
 // Existing tests relying on AuthContext removed; new tests will be added in dedicated test module using SecurityCtxExtractor.
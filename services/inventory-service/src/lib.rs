@@ -1,10 +1,23 @@
 pub mod inventory_handlers;
 pub mod reservation_handlers;
 pub mod location_handlers;
+pub mod adjustment_handlers;
+pub mod tracking_handlers;
+pub mod oversell;
+pub mod dual_write;
+pub mod bom;
+pub mod completion;
+pub mod valuation;
+pub mod product_merge;
+pub mod reorder;
+pub mod reorder_handlers;
+pub mod reservation_expiry;
 mod main_impl_placeholder {} // placeholder to avoid pulling main
 pub use crate::inventory_handlers::*;
 pub use crate::reservation_handlers::*;
 pub use crate::location_handlers::*;
+pub use crate::adjustment_handlers::*;
+pub use crate::tracking_handlers::*;
 pub const DEFAULT_THRESHOLD: i32 = 5;
 /// Returns true if inventory crossed from above threshold to at/below threshold.
 ///
@@ -18,14 +31,14 @@ pub fn crossed_below_threshold(prev: i32, new: i32, threshold: i32) -> bool {
 }
 // Minimal AppState mirror for tests (does not spawn consumer logic)
 use std::sync::Arc;
-use sqlx::PgPool;
+
 use common_auth::JwtVerifier;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use common_observability::InventoryMetrics;
 use std::time::Duration;
 #[derive(Clone)]
 pub struct AppState {
-	pub db: PgPool,
+	pub db: common_db::TenantScopedPool,
 	pub jwt_verifier: Arc<JwtVerifier>,
 	pub multi_location_enabled: bool,
 	pub reservation_default_ttl: Duration,
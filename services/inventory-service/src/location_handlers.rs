@@ -1,13 +1,18 @@
 use crate::AppState;
-use axum::{extract::State, Json};
-use common_security::{SecurityCtxExtractor, Capability, ensure_capability};
+use axum::{extract::{Path, State}, Json};
+use common_security::{SecurityCtxExtractor, Capability, Role, ensure_capability};
 use common_http_errors::ApiError;
+use common_db::db_error;
 use serde::Serialize;
 use sqlx::Row;
 use uuid::Uuid;
 
 // Legacy LOCATION_ROLES removed; rely solely on InventoryView capability.
 
+/// Location every new tenant starts with; stock without an explicit location lands here.
+pub const DEFAULT_LOCATION_CODE: &str = "MAIN";
+const DEFAULT_LOCATION_NAME: &str = "Main Location";
+
 #[derive(Debug, Serialize)]
 pub struct LocationRecord {
     pub id: Uuid,
@@ -26,11 +31,13 @@ pub async fn list_locations(
     if !state.multi_location_enabled {
         return Ok(Json(vec![]));
     }
-    let rows = sqlx::query("SELECT id, code, name, active FROM locations WHERE tenant_id = $1 ORDER BY code")
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, None))?;
+    let rows = common_db::query("SELECT id, code, name, active FROM locations WHERE tenant_id = $1 ORDER BY code")
         .bind(tenant_id)
-        .fetch_all(&state.db)
+        .fetch_all(&mut *tx)
         .await
         .map_err(|e| ApiError::internal(e, None))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, None))?;
 
     Ok(Json(rows.into_iter().map(|r| LocationRecord {
         id: r.get("id"),
@@ -39,3 +46,42 @@ pub async fn list_locations(
         active: r.get::<bool, _>("active"),
     }).collect()))
 }
+
+/// Provisioning step run by auth-service when a tenant is created: seeds the `MAIN` location
+/// that stock lands in before any others are set up. Returns the existing one if already there.
+pub async fn provision_tenant(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if !sec.roles.contains(&Role::SuperAdmin) {
+        return Err(ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id });
+    }
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let seeded = common_db::query(
+        "INSERT INTO locations (tenant_id, code, name) VALUES ($1, $2, $3) ON CONFLICT (tenant_id, code) DO NOTHING",
+    )
+    .bind(tenant_id)
+    .bind(DEFAULT_LOCATION_CODE)
+    .bind(DEFAULT_LOCATION_NAME)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?
+    .rows_affected()
+        > 0;
+    let location_id: Uuid = common_db::query_scalar("SELECT id FROM locations WHERE tenant_id = $1 AND code = $2")
+        .bind(tenant_id)
+        .bind(DEFAULT_LOCATION_CODE)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    Ok(Json(serde_json::json!({
+        "default_location": { "id": location_id, "code": DEFAULT_LOCATION_CODE, "seeded": seeded }
+    })))
+}
@@ -1,65 +1,75 @@
-use anyhow::Context;
 use axum::{
     extract::{FromRef, State},
     http::{
         header::{ACCEPT, CONTENT_TYPE},
         HeaderName, HeaderValue, Method,
     },
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
     middleware,
     body::Body,
 };
 use common_auth::{JwtConfig, JwtVerifier};
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
 use common_money::log_rounding_mode_once;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use futures::StreamExt; // only needed when kafka/kafka-producer feature enabled
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::consumer::{Consumer, StreamConsumer};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::Message;
-use sqlx::{PgPool, Row};
+use common_db::TenantScopedPool;
+use common_security::{Capability, CapabilitySet, SystemActor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use sqlx::Row;
 use prometheus::{Encoder, TextEncoder, IntCounterVec, Opts};
 use common_observability::InventoryMetrics;
-use std::{env, net::SocketAddr, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde::Deserialize; // needed for event struct derives when kafka/kafka-producer feature enabled
 use tokio::net::TcpListener;
 use tokio::time::{interval, MissedTickBehavior};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 use inventory_service::DEFAULT_THRESHOLD; // import shared constant
+use inventory_service::crossed_below_threshold;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use uuid::Uuid;
+use common_events::{DomainEvent, ReservationExpiredEvent};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-use inventory_service::crossed_below_threshold; // helper used only in kafka paths
-use uuid::Uuid;
+use common_events::{topics, CogsLine, CogsRecordedEvent, ComponentsConsumedEvent, InventoryLowStockEvent, OrderCompletedEvent, OrderVoidedEvent, PaymentCompletedEvent, ProductMergedEvent};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+use inventory_service::completion::sold_lines;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+use inventory_service::product_merge::merge_product;
+use inventory_service::reservation_expiry::{self, ExpiredBatch};
 
 mod inventory_handlers;
-use inventory_handlers::list_inventory;
+use inventory_handlers::{export_tenant_data, get_availability, list_inventory};
 mod reservation_handlers;
-use reservation_handlers::{create_reservation, release_reservation};
+use reservation_handlers::{adjust_reservation, create_reservation, create_reservation_batch, release_reservation};
 mod location_handlers;
-use location_handlers::list_locations;
+use location_handlers::{list_locations, provision_tenant};
+mod adjustment_handlers;
+use adjustment_handlers::{receive_stock, set_quantity};
+mod tracking_handlers;
+use tracking_handlers::{list_lots, pick_lots, recall_report};
+mod valuation;
+use valuation::{get_valuation, get_valuation_method, set_valuation_method};
+mod reorder;
+mod reorder_handlers;
+use reorder_handlers::{
+    accept_reorder_suggestion, create_supplier, delete_reorder_policy, dismiss_reorder_suggestion, get_reorder_suggestions, list_purchase_orders,
+    list_reorder_policies, list_suppliers, put_reorder_policy, set_purchase_order_status, update_supplier,
+};
+mod oversell;
+mod dual_write;
+use dual_write::DualWriteCheck;
+mod bom;
+mod config;
+use config::InventoryConfig;
 
 // (Removed placeholder error metrics layer; will reintroduce with proper implementation later)
 
 pub(crate) const DEFAULT_RESERVATION_TTL_SECS: i64 = 900; // 15 minutes
 
-#[derive(Deserialize)]
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] struct OrderCompletedEvent {
-    order_id: Uuid,
-    tenant_id: Uuid,
-    items: Vec<OrderItem>,
-}
-
-#[derive(Deserialize)]
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] struct OrderVoidedEvent {
-    order_id: Uuid,
-    tenant_id: Uuid,
-}
-
-#[derive(Deserialize)]
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] struct OrderItem {
-    product_id: Uuid,
-    quantity: i32,
-}
-
 #[derive(Deserialize, Debug)]
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] struct ProductCreatedEvent {
     product_id: Uuid,
@@ -68,16 +78,9 @@ pub(crate) const DEFAULT_RESERVATION_TTL_SECS: i64 = 900; // 15 minutes
     threshold: Option<i32>,
 }
 
-#[derive(Deserialize, Debug)]
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] struct PaymentCompletedEvent {
-    order_id: Uuid,
-    tenant_id: Uuid,
-    amount: f64,
-}
-
 #[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
+    pub db: TenantScopedPool,
     pub jwt_verifier: Arc<JwtVerifier>,
     #[allow(dead_code)]
     pub multi_location_enabled: bool,
@@ -86,6 +89,7 @@ pub struct AppState {
     #[allow(dead_code)]
     pub reservation_expiry_sweep: Duration,
     pub dual_write_enabled: bool,
+    pub dual_write_heal: bool,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] pub kafka_producer: FutureProducer,
     pub metrics: Arc<InventoryMetrics>,
     // Inbox metrics
@@ -95,6 +99,14 @@ pub struct AppState {
 
 // Metrics implementation now provided by common-observability crate.
 
+impl AppState {
+    /// The dual-write check, when both the legacy and per-location tables are maintained.
+    fn dual_write_check(&self) -> Option<DualWriteCheck> {
+        (self.multi_location_enabled && self.dual_write_enabled)
+            .then(|| DualWriteCheck { metrics: self.metrics.clone(), heal: self.dual_write_heal })
+    }
+}
+
 async fn metrics_endpoint(State(state): State<AppState>) -> (axum::http::StatusCode, String) {
     let encoder = TextEncoder::new();
     let families = state.metrics.registry.gather();
@@ -123,20 +135,27 @@ async fn health() -> &'static str {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("inventory-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db_pool = PgPool::connect(&database_url).await?;
+    let config = InventoryConfig::from_env().await?;
+    let db_pool = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db_pool.clone());
+    let cross_tenant_pool = config.pool.connect_cross_tenant(config.database_url.expose())?;
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    common_security::spawn_policy_refresh(jwt_verifier.clone());
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let consumer: StreamConsumer = rdkafka::ClientConfig::new()
+    let kafka_security = KafkaSecurity::from_env().await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    let consumer: StreamConsumer = kafka_security
+        .apply(&mut rdkafka::ClientConfig::new())
         .set(
             "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
+            &config.kafka_bootstrap,
         )
         .set("group.id", "inventory-service")
         .set("enable.auto.commit", "true")
@@ -144,42 +163,22 @@ async fn main() -> anyhow::Result<()> {
         .expect("failed to create kafka consumer");
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     consumer.subscribe(&[
-        "order.completed",
-        "order.voided",
-        "payment.completed",
+        topics::ORDER_COMPLETED,
+        topics::ORDER_VOIDED,
+        topics::PAYMENT_COMPLETED,
         "product.created",
+        topics::PRODUCT_MERGED,
     ])?;
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
-        .create()
+    let producer: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&config.kafka_bootstrap, &kafka_security)
         .expect("failed to create kafka producer");
 
-    let multi_location_enabled = env::var("MULTI_LOCATION_ENABLED")
-        .ok()
-        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-        .unwrap_or(false);
-    let reservation_default_ttl = env::var("RESERVATION_DEFAULT_TTL_SECS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .map(Duration::from_secs)
-        .unwrap_or(Duration::from_secs(DEFAULT_RESERVATION_TTL_SECS as u64));
-    let reservation_expiry_sweep = env::var("RESERVATION_EXPIRY_SWEEP_SECS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .map(Duration::from_secs)
-        .unwrap_or(Duration::from_secs(60));
-
-    let dual_write_enabled = env::var("INVENTORY_DUAL_WRITE")
-        .ok()
-        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-        .unwrap_or(false);
-
     let metrics = Arc::new(InventoryMetrics::new());
+    common_db::pool::register_metrics(&metrics.registry);
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::register_metrics(&metrics.registry);
     // Register inbox metrics into the same registry used for /metrics
     let inbox_inserts_total = IntCounterVec::new(
         Opts::new("inbox_inserts_total", "Count of inbox insertions for idempotent consumption"),
@@ -192,12 +191,13 @@ async fn main() -> anyhow::Result<()> {
     ).expect("create inbox_duplicates_skipped_total");
     metrics.registry.register(Box::new(inbox_duplicates_skipped_total.clone())).ok();
     let state = AppState {
-        db: db_pool.clone(),
+        db: TenantScopedPool::new(db_pool.clone()).with_cross_tenant(cross_tenant_pool.clone()),
         jwt_verifier,
-        multi_location_enabled,
-    reservation_default_ttl,
-    reservation_expiry_sweep,
-        dual_write_enabled,
+        multi_location_enabled: config.multi_location_enabled,
+        reservation_default_ttl: Duration::from_secs(config.reservation_default_ttl_secs),
+        reservation_expiry_sweep: Duration::from_secs(config.reservation_expiry_sweep_secs),
+        dual_write_enabled: config.dual_write_enabled,
+        dual_write_heal: config.dual_write_heal,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] kafka_producer: producer.clone(),
         metrics: metrics.clone(),
         inbox_inserts_total,
@@ -217,7 +217,7 @@ async fn main() -> anyhow::Result<()> {
                 .filter_map(|origin| origin.parse::<HeaderValue>().ok())
                 .collect::<Vec<_>>(),
         ))
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
         .allow_headers([
             ACCEPT,
             CONTENT_TYPE,
@@ -247,26 +247,52 @@ async fn main() -> anyhow::Result<()> {
         resp
     }
 
+    let slo = SloTracker::new("inventory-service", SloConfig::from_env(), &state.metrics.registry);
     let app = Router::new()
         .route("/healthz", get(health))
         .route("/inventory", get(list_inventory))
+        .route("/inventory/availability", get(get_availability))
+        .route("/inventory/receive", post(receive_stock))
+        .route("/inventory/quantity", put(set_quantity))
+        .route("/inventory/lots", get(list_lots))
+        .route("/inventory/lots/pick", get(pick_lots))
+        .route("/inventory/lots/recall", get(recall_report))
+        .route("/inventory/valuation", get(get_valuation))
+        .route("/inventory/valuation/method", get(get_valuation_method).put(set_valuation_method))
+        .route("/inventory/suppliers", get(list_suppliers).post(create_supplier))
+        .route("/inventory/suppliers/:id", put(update_supplier))
+        .route("/inventory/reorder/policies", get(list_reorder_policies).put(put_reorder_policy).delete(delete_reorder_policy))
+        .route("/inventory/reorder/suggestions", get(get_reorder_suggestions))
+        .route("/inventory/reorder/suggestions/:id/accept", post(accept_reorder_suggestion))
+        .route("/inventory/reorder/suggestions/:id/dismiss", post(dismiss_reorder_suggestion))
+        .route("/inventory/purchase-orders", get(list_purchase_orders))
+        .route("/inventory/purchase-orders/:id/status", put(set_purchase_order_status))
         .route("/inventory/reservations", post(create_reservation))
+        .route("/inventory/reservations/batch", post(create_reservation_batch))
         .route(
             "/inventory/reservations/:order_id",
-            delete(release_reservation),
+            delete(release_reservation).patch(adjust_reservation),
         )
         .route("/locations", get(list_locations))
+        .route("/tenants/:tenant_id/export", get(export_tenant_data))
+        .route("/tenants/:tenant_id/provision", post(provision_tenant))
         .route("/metrics", get(metrics_endpoint))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
     .with_state(state.clone())
     .layer(middleware::from_fn_with_state(metrics.clone(), error_metrics_mw))
-        .layer(cors);
+        .layer(cors)
+        .layer(middleware::from_fn(slo.layer()))
+        .layer(middleware::from_fn(common_observability::request_span));
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     {
-        let db_for_consumer = db_pool.clone();
+        let db_for_consumer = cross_tenant_pool.clone();
         let multi_loc_for_consumer = state.multi_location_enabled;
+        let dual_write_for_consumer = state.dual_write_check();
         let producer = producer.clone();
-        let inbox_enabled = env::var("INVENTORY_INBOX_DEDUP").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(true);
+        let inbox_enabled = config.inbox_dedup;
         let inbox_inserts = state.inbox_inserts_total.clone();
         let inbox_dupes = state.inbox_duplicates_skipped_total.clone();
         tokio::spawn(async move {
@@ -277,11 +303,7 @@ async fn main() -> anyhow::Result<()> {
                         let topic = m.topic();
                         if let Some(Ok(text)) = m.payload_view::<str>() {
                             if inbox_enabled {
-                                // Prefer Kafka key; fallback to payload hash
-                                let key_str = m
-                                    .key()
-                                    .map(|k| String::from_utf8_lossy(k).to_string())
-                                    .unwrap_or_else(|| format!("sha1:{}", hex::encode(sha1_smol::Sha1::from(text).digest().bytes())));
+                                let key_str = common_events::inbox_key(m.key(), text);
                                 let tenant_hint = extract_tenant_from_payload(text).unwrap_or_else(|| "unknown".to_string());
                                 let already = sqlx::query_scalar::<_, Option<i64>>(
                                     "SELECT 1 FROM inbox WHERE tenant_id = $1 AND message_key = $2 AND topic = $3"
@@ -310,16 +332,18 @@ async fn main() -> anyhow::Result<()> {
                                     inbox_inserts.with_label_values(&["inventory-service", topic]).inc();
                                 }
                             }
-                            if topic == "order.completed" {
-                                handle_order_completed(text, &db_for_consumer, &producer, multi_loc_for_consumer).await;
-                            } else if topic == "order.voided" {
+                            if topic == topics::ORDER_COMPLETED {
+                                handle_order_completed(text, &db_for_consumer, &producer, multi_loc_for_consumer, dual_write_for_consumer.as_ref()).await;
+                            } else if topic == topics::ORDER_VOIDED {
                                 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] {
                                     handle_order_voided(text, &db_for_consumer).await;
                                 }
                             } else if topic == "product.created" {
                                 handle_product_created(text, &db_for_consumer).await;
-                            } else if topic == "payment.completed" {
-                                if let Ok(evt) = serde_json::from_str::<PaymentCompletedEvent>(text) {
+                            } else if topic == topics::PRODUCT_MERGED {
+                                handle_product_merged(text, &db_for_consumer).await;
+                            } else if topic == topics::PAYMENT_COMPLETED {
+                                if let Ok(evt) = common_events::decode::<PaymentCompletedEvent>(text) {
                                     tracing::debug!(order_id = %evt.order_id, tenant_id = %evt.tenant_id, amount = evt.amount, "Payment completed event received (no-op for inventory)");
                                 }
                             }
@@ -332,18 +356,16 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Spawn reservation expiration sweeper
-    spawn_reservation_sweeper(state.clone());
+    spawn_reservation_sweeper(state.clone(), config.reservation_sweep_batch_size);
+    spawn_oversell_checker(state.clone(), config.oversell_check_interval_secs);
+    spawn_reorder_job(state.clone(), config.reorder_interval_secs, config.reorder_velocity_days, config.reorder_dismiss_days);
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8087);
-    let ip: std::net::IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    let addr = config.http.addr();
     println!("starting inventory-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::flush(&producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
@@ -355,13 +377,26 @@ fn extract_tenant_from_payload(text: &str) -> Option<String> {
 }
 
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProducer, multi_location_enabled: bool) {
-    match serde_json::from_str::<OrderCompletedEvent>(text) {
+async fn handle_order_completed(
+    text: &str,
+    db: &sqlx::PgPool,
+    producer: &FutureProducer,
+    multi_location_enabled: bool,
+    dual_write: Option<&DualWriteCheck>,
+) {
+    match common_events::decode::<OrderCompletedEvent>(text) {
         Ok(event) => {
+            let is_refund = event.is_refund();
+            let Some(sold) = sold_lines(&event) else {
+                tracing::debug!(order_id = %event.order_id, tenant_id = %event.tenant_id, rma_id = ?event.rma_id, "Skipping stock update for RMA refund");
+                return;
+            };
             let OrderCompletedEvent {
                 order_id,
                 tenant_id,
-                items,
+                customer_id,
+                location_id: order_location_id,
+                ..
             } = event;
 
             let mut tx = match db.begin().await {
@@ -373,10 +408,27 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
             };
 
             let mut alerts: Vec<(Uuid, i32, i32)> = Vec::new();
+            let mut cogs: Vec<CogsLine> = Vec::new();
+            let method = valuation::tenant_method(&mut tx, tenant_id).await.unwrap_or_else(|err| {
+                tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, "Failed to load valuation method; costing at FIFO");
+                valuation::ValuationMethod::Fifo
+            });
+
+            // Bundles move the stock of their components rather than their own.
+            let product_ids: Vec<Uuid> = sold.iter().map(|(product_id, _)| *product_id).collect();
+            let boms = match bom::load_boms(&mut tx, tenant_id, &product_ids).await {
+                Ok(boms) => boms,
+                Err(err) => {
+                    tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, "Failed to load bundle components for completion");
+                    bom::Boms::new()
+                }
+            };
+            let (stock_lines, consumed) = bom::expand(&sold, &boms).unwrap_or_else(|| {
+                tracing::warn!(order_id = %order_id, tenant_id = %tenant_id, "Bundle component quantities overflow; moving bundle stock instead");
+                (sold.clone(), Vec::new())
+            });
 
-            for item in items {
-                let product_id = item.product_id;
-                let quantity_delta = item.quantity;
+            for (product_id, quantity_delta) in stock_lines {
                 let mut attempts = 0;
                 let mut latest: Option<(i32, i32)> = None;
                 if multi_location_enabled {
@@ -418,6 +470,10 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
                                 .await {
                                     tracing::error!(?err, product_id = %product_id, tenant_id = %tenant_id, location_id = %loc, "Failed to decrement inventory_items for completion");
                                 }
+                                match valuation::cost_order_line(&mut tx, tenant_id, method, order_id, product_id, Some(loc), q).await {
+                                    Ok(line) => cogs.push(line),
+                                    Err(err) => tracing::error!(?err, order_id = %order_id, product_id = %product_id, location_id = %loc, "Failed to cost completed order line"),
+                                }
                             }
                         }
                     }
@@ -494,6 +550,42 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
                             }
                         }
                     }
+                    if latest.is_some() {
+                        match valuation::cost_order_line(&mut tx, tenant_id, method, order_id, product_id, None, quantity_delta).await {
+                            Ok(line) => cogs.push(line),
+                            Err(err) => tracing::error!(?err, order_id = %order_id, product_id = %product_id, "Failed to cost completed order line"),
+                        }
+                    }
+                }
+
+                if !is_refund && quantity_delta > 0 {
+                    // Serials and lot captured at the till travel on the reservation row.
+                    let captured = sqlx::query(
+                        "SELECT location_id, lot_code FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2 AND product_id = $3"
+                    )
+                    .bind(order_id)
+                    .bind(tenant_id)
+                    .bind(product_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .ok()
+                    .flatten();
+                    let (location_id, lot_code): (Option<Uuid>, Option<String>) =
+                        captured.map(|r| (r.get("location_id"), r.get("lot_code"))).unwrap_or((None, None));
+                    if let Err(err) = tracking_handlers::record_sale(
+                        &mut tx,
+                        tenant_id,
+                        order_id,
+                        customer_id,
+                        product_id,
+                        quantity_delta,
+                        location_id,
+                        lot_code.as_deref(),
+                    )
+                    .await
+                    {
+                        tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, product_id = %product_id, "Failed to record serial/lot sale");
+                    }
                 }
 
                 if let Err(err) = sqlx::query(
@@ -529,23 +621,47 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
                 return;
             }
 
+            #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+            if !consumed.is_empty() {
+                let consumption = ComponentsConsumedEvent {
+                    schema_version: ComponentsConsumedEvent::SCHEMA_VERSION,
+                    tenant_id,
+                    order_id,
+                    location_id: order_location_id,
+                    components: consumed,
+                };
+                if let Err(err) = common_kafka::publish_event(producer, db, tenant_id, &consumption).await {
+                    tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, "Failed to emit inventory.components.consumed");
+                }
+            }
+
+            #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+            if !cogs.is_empty() {
+                let total_cost = cogs.iter().fold(bigdecimal::BigDecimal::from(0), |sum, line| sum + &line.cost);
+                let recorded = CogsRecordedEvent {
+                    schema_version: CogsRecordedEvent::SCHEMA_VERSION,
+                    tenant_id,
+                    order_id,
+                    location_id: order_location_id,
+                    method: method.as_str().to_string(),
+                    lines: cogs,
+                    total_cost,
+                };
+                if let Err(err) = common_kafka::publish_event(producer, db, tenant_id, &recorded).await {
+                    tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, "Failed to emit inventory.cogs.recorded");
+                }
+            }
+
             #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
             for (product_id, quantity, threshold) in alerts {
-                let alert = serde_json::json!({
-                    "product_id": product_id,
-                    "tenant_id": tenant_id,
-                    "quantity": quantity,
-                    "threshold": threshold
-                });
-                if let Err(err) = producer
-                    .send(
-                        rdkafka::producer::FutureRecord::to("inventory.low_stock")
-                            .payload(&alert.to_string())
-                            .key(&tenant_id.to_string()),
-                        Duration::from_secs(0),
-                    )
-                    .await
-                {
+                let alert = InventoryLowStockEvent {
+                    schema_version: InventoryLowStockEvent::SCHEMA_VERSION,
+                    tenant_id,
+                    product_id,
+                    quantity,
+                    threshold,
+                };
+                if let Err(err) = common_kafka::publish_event(producer, db, tenant_id, &alert).await {
                     tracing::error!(
                         ?err,
                         product_id = %product_id,
@@ -556,33 +672,9 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
             }
 
             // Dual-write validation: verify legacy aggregate matches sum of multi-location if both features active.
-            if multi_location_enabled {
-                if let Ok(rows) = sqlx::query(
-                    "SELECT product_id, SUM(quantity) as sum_qty FROM inventory_items WHERE tenant_id = $1 GROUP BY product_id"
-                )
-                .bind(tenant_id)
-                .fetch_all(db)
-                .await
-                {
-                    for r in rows {
-                        let product_id: Uuid = r.get("product_id");
-                        let sum_qty: Option<i64> = r.get("sum_qty");
-                        if let Some(sum_qty) = sum_qty {
-                            if let Ok(Some(row)) = sqlx::query(
-                                "SELECT quantity FROM inventory WHERE tenant_id = $1 AND product_id = $2"
-                            )
-                            .bind(tenant_id)
-                            .bind(product_id)
-                            .fetch_optional(db)
-                            .await
-                            {
-                                let legacy_qty: i32 = row.get("quantity");
-                                if legacy_qty != sum_qty as i32 {
-                                    tracing::warn!(product_id = %product_id, tenant_id = %tenant_id, legacy = legacy_qty, agg = sum_qty, "Dual-write divergence detected");
-                                }
-                            }
-                        }
-                    }
+            if let Some(check) = dual_write {
+                if let Err(err) = check.run(db, Some(tenant_id), "consumer").await {
+                    tracing::error!(?err, tenant_id = %tenant_id, "Dual-write check failed");
                 }
             }
         }
@@ -591,12 +683,13 @@ async fn handle_order_completed(text: &str, db: &PgPool, producer: &FutureProduc
 }
 
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-async fn handle_order_voided(text: &str, db: &PgPool) {
-    match serde_json::from_str::<OrderVoidedEvent>(text) {
+async fn handle_order_voided(text: &str, db: &sqlx::PgPool) {
+    match common_events::decode::<OrderVoidedEvent>(text) {
         Ok(event) => {
             let OrderVoidedEvent {
                 order_id,
                 tenant_id,
+                ..
             } = event;
 
             let mut tx = match db.begin().await {
@@ -628,6 +721,9 @@ async fn handle_order_voided(text: &str, db: &PgPool) {
                     return;
                 }
             };
+            if let Err(err) = tracking_handlers::release_serials(&mut tx, tenant_id, order_id, None).await {
+                tracing::error!(?err, order_id = %order_id, tenant_id = %tenant_id, "Failed to release serials for voided order");
+            }
 
             for row in reservations.iter() {
                 if row.quantity <= 0 {
@@ -701,7 +797,7 @@ async fn handle_order_voided(text: &str, db: &PgPool) {
 }
 
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-async fn handle_product_created(text: &str, db: &PgPool) {
+async fn handle_product_created(text: &str, db: &sqlx::PgPool) {
     match serde_json::from_str::<ProductCreatedEvent>(text) {
         Ok(event) => {
             let initial_quantity = event.initial_quantity.unwrap_or(0);
@@ -736,28 +832,40 @@ async fn handle_product_created(text: &str, db: &PgPool) {
     }
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+async fn handle_product_merged(text: &str, db: &sqlx::PgPool) {
+    let evt = match common_events::decode::<ProductMergedEvent>(text) {
+        Ok(evt) => evt,
+        Err(err) => return tracing::error!(?err, "Failed to parse ProductMergedEvent"),
+    };
+    match merge_product(db, &evt).await {
+        Ok(summary) => {
+            if summary.serial_clashes > 0 {
+                tracing::warn!(duplicate_id = %evt.duplicate_id, canonical_id = %evt.canonical_id, clashes = summary.serial_clashes, "Serial numbers exist on both merged products; left on the duplicate");
+            }
+            tracing::info!(duplicate_id = %evt.duplicate_id, canonical_id = %evt.canonical_id, tenant_id = %evt.tenant_id, ?summary, "Moved stock of merged product");
         }
+        Err(err) => tracing::error!(?err, duplicate_id = %evt.duplicate_id, canonical_id = %evt.canonical_id, "Failed to move stock of merged product"),
+    }
+}
+
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -766,16 +874,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -797,13 +900,17 @@ fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
     });
 }
 
-fn spawn_reservation_sweeper(state: AppState) {
+/// Reservations claimed per sweeper transaction unless `RESERVATION_SWEEP_BATCH_SIZE` says otherwise.
+pub(crate) const DEFAULT_SWEEP_BATCH_SIZE: i64 = 500;
+pub(crate) const MAX_SWEEP_BATCH_SIZE: i64 = 10_000;
+
+fn spawn_reservation_sweeper(state: AppState, batch_size: i64) {
     tokio::spawn(async move {
         let sweep_interval = state.reservation_expiry_sweep;
         loop {
             tokio::time::sleep(sweep_interval).await;
             let start = std::time::Instant::now();
-            if let Err(err) = expire_reservations(&state).await {
+            if let Err(err) = expire_reservations(&state, batch_size).await {
                 tracing::error!(?err, "Reservation sweeper error");
             }
             let elapsed = start.elapsed().as_secs_f64();
@@ -812,123 +919,134 @@ fn spawn_reservation_sweeper(state: AppState) {
     });
 }
 
-async fn expire_reservations(state: &AppState) -> anyhow::Result<()> {
-    // Update expired reservations and restock inventory for multi-location aware system.
-    let mut tx = state.db.begin().await?;
-    let rows = sqlx::query(
-        "UPDATE inventory_reservations SET status = 'EXPIRED' WHERE status = 'ACTIVE' AND expires_at IS NOT NULL AND expires_at < NOW() RETURNING product_id, tenant_id, location_id, quantity, order_id"
-    )
-    .fetch_all(&mut *tx)
-    .await?;
-    if !rows.is_empty() {
-        for r in rows.iter() {
-            let product_id: Uuid = r.get("product_id");
-            let tenant_id: Uuid = r.get("tenant_id");
-            let quantity: i32 = r.get("quantity");
-            let order_id: Uuid = r.get("order_id");
-            if state.multi_location_enabled {
-                let loc_id: Option<Uuid> = r.get("location_id");
-                if let Some(loc) = loc_id {
-                    let _ = sqlx::query(
-                        "UPDATE inventory_items SET quantity = quantity + $1, updated_at = NOW() WHERE tenant_id = $2 AND product_id = $3 AND location_id = $4"
-                    )
-                    .bind(quantity)
-                    .bind(tenant_id)
-                    .bind(product_id)
-                    .bind(loc)
-                    .execute(&mut *tx)
-                    .await;
-                }
-            } else {
-                let _ = sqlx::query(
-                    "UPDATE inventory SET quantity = quantity + $1 WHERE tenant_id = $2 AND product_id = $3"
-                )
-                .bind(quantity)
-                .bind(tenant_id)
-                .bind(product_id)
-                .execute(&mut *tx)
-                .await;
+fn spawn_oversell_checker(state: AppState, interval_secs: u64) {
+    if interval_secs == 0 {
+        tracing::info!("Oversell checker disabled (OVERSELL_CHECK_INTERVAL_SECS=0)");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tracker = oversell::OversellTracker::default();
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+            if let Err(err) = oversell::check_oversell(&state, &mut tracker).await {
+                tracing::error!(?err, "Oversell check error");
             }
+        }
+    });
+}
 
-            // Emit reservation expired event
-            let expired_at = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let _evt = serde_json::json!({
-                "type": "reservation.expired",
-                "tenant_id": tenant_id,
-                "product_id": product_id,
-                "order_id": order_id,
-                "quantity": quantity,
-                "expired_at_epoch": expired_at,
-            });
-            #[cfg(feature = "kafka")]
-            if let Err(err) = state.kafka_producer.send(
-                rdkafka::producer::FutureRecord::to("inventory.reservation.expired")
-                    .payload(&_evt.to_string())
-                    .key(&tenant_id.to_string()),
-                Duration::from_secs(0)
-            ).await {
-                tracing::error!(?err, tenant_id = %tenant_id, order_id = %order_id, "Failed to emit inventory.reservation.expired");
+fn spawn_reorder_job(state: AppState, interval_secs: u64, velocity_days: i32, dismiss_days: i32) {
+    if interval_secs == 0 {
+        tracing::info!("Reorder suggestions disabled (REORDER_INTERVAL_SECS=0)");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+            match reorder::refresh_suggestions(state.db.cross_tenant(), velocity_days, dismiss_days).await {
+                Ok(summary) => tracing::info!(open = summary.open, withdrawn = summary.withdrawn, "Reorder suggestions refreshed"),
+                Err(err) => tracing::error!(?err, "Reorder suggestion job error"),
             }
-            // Audit event
-            let _audit_evt = serde_json::json!({
-                "action": "inventory.reservation.expired",
-                "schema_version": 1,
-                "tenant_id": tenant_id,
-                "order_id": order_id,
-                "product_id": product_id,
-                "quantity": quantity,
-                "expired_at_epoch": expired_at,
-            });
-            #[cfg(feature = "kafka")] let _ = state.kafka_producer.send(
-                rdkafka::producer::FutureRecord::to("audit.events")
-                    .payload(&_audit_evt.to_string())
-                    .key(&tenant_id.to_string()),
-                Duration::from_secs(0)
-            ).await;
+        }
+    });
+}
+
+/// Identity the sweeper's expiries are audited under; restocking is a stock correction.
+const RESERVATION_SWEEPER: SystemActor =
+    SystemActor::new("inventory-service", "reservation-sweeper", CapabilitySet::of(&[Capability::InventoryAdjust]));
+
+/// Expire overdue reservations batch by batch until none are left.
+///
+/// Each batch is its own transaction and claims rows with `FOR UPDATE SKIP LOCKED`, so replicas
+/// sweeping at the same time take disjoint rows instead of waiting on (or restocking) each other's.
+async fn expire_reservations(state: &AppState, batch_size: i64) -> anyhow::Result<()> {
+    loop {
+        let claimed = expire_reservation_batch(state, batch_size).await?;
+        if (claimed as i64) < batch_size {
+            break;
         }
     }
-    tx.commit().await?;
+    validate_dual_write(state).await;
+    Ok(())
+}
 
-    // Dual-write validation (periodic) if enabled
-    if state.multi_location_enabled && state.dual_write_enabled {
-        if let Ok(tenants) = sqlx::query("SELECT DISTINCT tenant_id FROM inventory_items")
-            .fetch_all(&state.db)
-            .await
+/// Expire one batch (see [`reservation_expiry::expire_batch`]), record its metrics and emit
+/// events after commit so only expiries that stuck are announced.
+async fn expire_reservation_batch(state: &AppState, batch_size: i64) -> anyhow::Result<usize> {
+    let start = std::time::Instant::now();
+    let ExpiredBatch { expired, restocked } =
+        reservation_expiry::expire_batch(state.db.cross_tenant(), state.multi_location_enabled, batch_size).await?;
+
+    state.metrics.sweeper_batch_claimed.observe(expired.len() as f64);
+    state.metrics.sweeper_batch_restocked.observe(restocked as f64);
+    state.metrics.sweeper_batch_duration_seconds.observe(start.elapsed().as_secs_f64());
+    for r in &expired {
+        state.metrics.reservation_expired.with_label_values(&[&r.tenant_id.to_string()]).inc();
+    }
+    if !expired.is_empty() {
+        tracing::debug!(claimed = expired.len(), restocked, "Reservation sweeper batch committed");
+    }
+
+    let expired_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    for r in &expired {
+        let (tenant_id, order_id) = (r.tenant_id, r.order_id);
+        let sec = RESERVATION_SWEEPER.context(tenant_id);
+        // Emit reservation expired event
+        let _evt = ReservationExpiredEvent {
+            schema_version: ReservationExpiredEvent::SCHEMA_VERSION,
+            tenant_id,
+            order_id,
+            product_id: r.product_id,
+            quantity: r.quantity,
+            expired_at_epoch: expired_at,
+        };
+        #[cfg(feature = "kafka")]
+        if let Err(err) = common_kafka::publish_event(
+            &state.kafka_producer,
+            state.db.pool(),
+            _evt.tenant_id,
+            &_evt,
+        )
+        .await
         {
-            for r in tenants {
-                let tenant_id: Uuid = r.get("tenant_id");
-                if let Ok(rows) = sqlx::query(
-                    "SELECT product_id, SUM(quantity) as sum_qty FROM inventory_items WHERE tenant_id = $1 GROUP BY product_id"
-                )
-                .bind(tenant_id)
-                .fetch_all(&state.db)
-                .await
-                {
-                    for row in rows {
-                        let product_id: Uuid = row.get("product_id");
-                        let sum_qty: Option<i64> = row.get("sum_qty");
-                        if let Some(sum_qty) = sum_qty {
-                            if let Ok(Some(lrow)) = sqlx::query(
-                                "SELECT quantity FROM inventory WHERE tenant_id = $1 AND product_id = $2"
-                            )
-                            .bind(tenant_id)
-                            .bind(product_id)
-                            .fetch_optional(&state.db)
-                            .await
-                            {
-                                let legacy_qty: i32 = lrow.get("quantity");
-                                if legacy_qty != sum_qty as i32 {
-                                    tracing::warn!(product_id = %product_id, tenant_id = %tenant_id, legacy = legacy_qty, agg = sum_qty, "Dual-write divergence detected (sweeper)");
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+            tracing::error!(?err, tenant_id = %tenant_id, order_id = %order_id, "Failed to emit inventory.reservation.expired");
+        }
+        // Audit event
+        let _audit_evt = serde_json::json!({
+            "action": "inventory.reservation.expired",
+            "schema_version": 1,
+            "tenant_id": sec.tenant_id,
+            "actor_id": sec.actor.id,
+            "actor_name": sec.actor.name,
+            "trace_id": sec.trace_id,
+            "order_id": order_id,
+            "product_id": r.product_id,
+            "quantity": r.quantity,
+            "expired_at_epoch": expired_at,
+        });
+        #[cfg(feature = "kafka")]
+        if let Err(err) = common_kafka::publish(
+            &state.kafka_producer,
+            "audit.events",
+            &tenant_id.to_string(),
+            &_audit_evt.to_string(),
+        )
+        .await
+        {
+            tracing::error!(?err, tenant_id = %tenant_id, order_id = %order_id, "Failed to audit inventory.reservation.expired");
+            state.metrics.audit_emit_failures.with_label_values(&["inventory.reservation.expired"]).inc();
         }
     }
-    Ok(())
+    Ok(expired.len())
+}
+
+/// Periodic dual-write validation across every tenant.
+async fn validate_dual_write(state: &AppState) {
+    let Some(check) = state.dual_write_check() else { return };
+    if let Err(err) = check.run(state.db.cross_tenant(), None, "sweeper").await {
+        tracing::error!(?err, "Dual-write check failed");
+    }
 }
@@ -0,0 +1,170 @@
+//! Periodic oversell detection: products whose active reservations exceed on-hand stock.
+//!
+//! Runs across all tenants on the unscoped pool, like the reservation sweeper. Each check also
+//! refreshes the per-tenant reserved-quantity gauge.
+
+use crate::AppState;
+use sqlx::Row;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Reservations counted against stock: multi-location only holds ACTIVE ones, while legacy mode
+/// counts every row, matching the availability checks of each mode.
+const OVERSOLD_MULTI_LOCATION: &str = "
+    WITH reserved AS (
+        SELECT tenant_id, product_id, location_id, SUM(quantity)::int AS reserved
+        FROM inventory_reservations WHERE status = 'ACTIVE'
+        GROUP BY tenant_id, product_id, location_id
+    )
+    SELECT r.tenant_id, r.product_id, r.location_id, r.reserved, COALESCE(s.on_hand, 0)::int AS on_hand
+    FROM reserved r
+    LEFT JOIN LATERAL (
+        SELECT SUM(ii.quantity) AS on_hand FROM inventory_items ii
+        WHERE ii.tenant_id = r.tenant_id AND ii.product_id = r.product_id
+          AND (r.location_id IS NULL OR ii.location_id = r.location_id)
+    ) s ON TRUE
+    WHERE r.reserved > COALESCE(s.on_hand, 0)";
+const OVERSOLD_LEGACY: &str = "
+    WITH reserved AS (
+        SELECT tenant_id, product_id, SUM(quantity)::int AS reserved
+        FROM inventory_reservations GROUP BY tenant_id, product_id
+    )
+    SELECT r.tenant_id, r.product_id, NULL::uuid AS location_id, r.reserved, COALESCE(i.quantity, 0) AS on_hand
+    FROM reserved r
+    LEFT JOIN inventory i ON i.tenant_id = r.tenant_id AND i.product_id = r.product_id
+    WHERE r.reserved > COALESCE(i.quantity, 0)";
+const RESERVED_BY_TENANT_MULTI_LOCATION: &str =
+    "SELECT tenant_id, SUM(quantity)::bigint AS reserved FROM inventory_reservations WHERE status = 'ACTIVE' GROUP BY tenant_id";
+const RESERVED_BY_TENANT_LEGACY: &str =
+    "SELECT tenant_id, SUM(quantity)::bigint AS reserved FROM inventory_reservations GROUP BY tenant_id";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversoldStock {
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub on_hand: i32,
+    pub reserved: i32,
+}
+
+impl OversoldStock {
+    fn key(&self) -> (Uuid, Uuid, Option<Uuid>) {
+        (self.tenant_id, self.product_id, self.location_id)
+    }
+}
+
+/// Remembers what was oversold at the last check so each product is reported once per episode.
+#[derive(Debug, Default)]
+pub struct OversellTracker {
+    flagged: HashSet<(Uuid, Uuid, Option<Uuid>)>,
+}
+
+impl OversellTracker {
+    /// Replace the tracked set with `current`, returning the entries that were not oversold before.
+    /// A product that recovers and oversells again is reported again.
+    pub fn observe(&mut self, current: &[OversoldStock]) -> Vec<OversoldStock> {
+        let newly: Vec<OversoldStock> = current.iter().filter(|s| !self.flagged.contains(&s.key())).cloned().collect();
+        self.flagged = current.iter().map(OversoldStock::key).collect();
+        newly
+    }
+
+    /// Number of products currently oversold.
+    pub fn flagged_count(&self) -> usize {
+        self.flagged.len()
+    }
+}
+
+/// One check: refresh the reserved gauge, find oversold stock and publish newly found entries.
+pub async fn check_oversell(state: &AppState, tracker: &mut OversellTracker) -> Result<Vec<OversoldStock>, sqlx::Error> {
+    let pool = state.db.cross_tenant();
+    let (oversold_sql, reserved_sql) = if state.multi_location_enabled {
+        (OVERSOLD_MULTI_LOCATION, RESERVED_BY_TENANT_MULTI_LOCATION)
+    } else {
+        (OVERSOLD_LEGACY, RESERVED_BY_TENANT_LEGACY)
+    };
+
+    let reserved = sqlx::query(reserved_sql).fetch_all(pool).await?;
+    state.metrics.reserved_quantity.reset();
+    for row in reserved {
+        let tenant_id: Uuid = row.get("tenant_id");
+        let quantity: Option<i64> = row.get("reserved");
+        state.metrics.reserved_quantity.with_label_values(&[&tenant_id.to_string()]).set(quantity.unwrap_or(0));
+    }
+
+    let current: Vec<OversoldStock> = sqlx::query(oversold_sql)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| OversoldStock {
+            tenant_id: row.get("tenant_id"),
+            product_id: row.get("product_id"),
+            location_id: row.get("location_id"),
+            on_hand: row.get("on_hand"),
+            reserved: row.get("reserved"),
+        })
+        .collect();
+    let newly = tracker.observe(&current);
+    state.metrics.oversold_products.set(tracker.flagged_count() as i64);
+    for stock in &newly {
+        state.metrics.oversell_detected.inc();
+        tracing::warn!(
+            tenant_id = %stock.tenant_id,
+            product_id = %stock.product_id,
+            location_id = ?stock.location_id,
+            on_hand = stock.on_hand,
+            reserved = stock.reserved,
+            "Oversell detected: reserved quantity exceeds on-hand stock"
+        );
+        publish_oversell(state, stock).await;
+    }
+    Ok(newly)
+}
+
+#[cfg_attr(not(any(feature = "kafka", feature = "kafka-producer")), allow(unused_variables))]
+async fn publish_oversell(state: &AppState, stock: &OversoldStock) {
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        use common_events::{DomainEvent, InventoryOversellEvent};
+
+        let event = InventoryOversellEvent {
+            schema_version: InventoryOversellEvent::SCHEMA_VERSION,
+            tenant_id: stock.tenant_id,
+            product_id: stock.product_id,
+            location_id: stock.location_id,
+            on_hand: stock.on_hand,
+            reserved: stock.reserved,
+        };
+        if let Err(err) = common_kafka::publish_event(
+            &state.kafka_producer,
+            state.db.pool(),
+            event.tenant_id,
+            &event,
+        )
+        .await
+        {
+            tracing::error!(?err, product_id = %stock.product_id, tenant_id = %stock.tenant_id, "Failed to emit inventory.oversell");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oversold(product_id: Uuid, reserved: i32) -> OversoldStock {
+        OversoldStock { tenant_id: Uuid::nil(), product_id, location_id: None, on_hand: 1, reserved }
+    }
+
+    #[test]
+    fn reports_each_oversell_once_until_it_clears() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut tracker = OversellTracker::default();
+        assert_eq!(tracker.observe(&[oversold(a, 3)]).len(), 1);
+        // Still oversold (even with a different count): not reported again.
+        assert!(tracker.observe(&[oversold(a, 4), oversold(b, 2)]).iter().all(|s| s.product_id == b));
+        assert_eq!(tracker.flagged_count(), 2);
+        // `a` recovers, then oversells again.
+        tracker.observe(&[oversold(b, 2)]);
+        assert_eq!(tracker.observe(&[oversold(a, 2), oversold(b, 2)]), vec![oversold(a, 2)]);
+    }
+}
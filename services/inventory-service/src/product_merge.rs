@@ -0,0 +1,123 @@
+//! Repointing stock from a merged duplicate product at its canonical product (`product.merged`).
+//!
+//! Quantities are added to the canonical product's rows where it already has one (stock per
+//! location, a reservation on the same order, a lot with the same code) and the duplicate's rows
+//! are moved over otherwise; history (adjustments, cost layers, lot sales) is relabelled. Every
+//! statement only touches rows still on the duplicate, so a redelivered event is a no-op.
+
+use common_events::ProductMergedEvent;
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{PgPool, Postgres};
+
+/// Rows moved or folded into the canonical product, for the consumer's log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeSummary {
+    pub stock_rows: u64,
+    pub reservations: u64,
+    pub lots: u64,
+    pub serials: u64,
+    /// Serial numbers the canonical product already has, left on the duplicate for review.
+    pub serial_clashes: u64,
+    pub history_rows: u64,
+}
+
+/// Stock tables keyed by product: `$1` tenant, `$2` duplicate, `$3` canonical. Each statement adds
+/// the duplicate's quantity onto the canonical row for the same key; [`DROPS`] then removes the
+/// duplicate's rows.
+const FOLDS: &[&str] = &[
+    "INSERT INTO inventory (product_id, tenant_id, quantity, threshold)
+     SELECT $3, tenant_id, quantity, threshold FROM inventory WHERE tenant_id = $1 AND product_id = $2
+     ON CONFLICT (product_id, tenant_id) DO UPDATE SET quantity = inventory.quantity + EXCLUDED.quantity",
+    "INSERT INTO inventory_items (tenant_id, product_id, location_id, quantity, threshold)
+     SELECT tenant_id, $3, location_id, quantity, threshold FROM inventory_items WHERE tenant_id = $1 AND product_id = $2
+     ON CONFLICT (tenant_id, product_id, location_id)
+     DO UPDATE SET quantity = inventory_items.quantity + EXCLUDED.quantity, updated_at = NOW()",
+];
+
+const DROPS: &[&str] = &[
+    "DELETE FROM inventory WHERE tenant_id = $1 AND product_id = $2",
+    "DELETE FROM inventory_items WHERE tenant_id = $1 AND product_id = $2",
+];
+
+/// Tables whose rows only need relabelling.
+const RELABELS: &[&str] = &[
+    "UPDATE inventory_adjustments SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2",
+    "UPDATE inventory_cost_layers SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2",
+    "UPDATE inventory_cost_consumptions SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2",
+    "UPDATE inventory_lot_sales SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2",
+];
+
+/// Lots with the same code (and location) on both products: `d` is the duplicate's, `c` the canonical's.
+const SAME_LOT: &str = "d.tenant_id = $1 AND d.product_id = $2 AND c.tenant_id = $1 AND c.product_id = $3
+     AND c.lot_code = d.lot_code AND c.location_id IS NOT DISTINCT FROM d.location_id";
+
+/// `sql` with the tenant, duplicate and canonical ids bound as `$1`, `$2` and `$3`.
+fn bound<'q>(sql: &'q str, evt: &ProductMergedEvent) -> Query<'q, Postgres, PgArguments> {
+    sqlx::query(sql).bind(evt.tenant_id).bind(evt.duplicate_id).bind(evt.canonical_id)
+}
+
+pub async fn merge_product(db: &PgPool, evt: &ProductMergedEvent) -> Result<MergeSummary, sqlx::Error> {
+    let mut summary = MergeSummary::default();
+    if evt.duplicate_id == evt.canonical_id {
+        return Ok(summary);
+    }
+    let mut tx = db.begin().await?;
+    for sql in FOLDS {
+        summary.stock_rows += bound(sql, evt).execute(&mut *tx).await?.rows_affected();
+    }
+    for sql in DROPS {
+        bound(sql, evt).execute(&mut *tx).await?;
+    }
+
+    summary.reservations = bound(
+        "INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, created_at, location_id, status, expires_at, serial_numbers, lot_code)
+         SELECT order_id, tenant_id, $3, quantity, created_at, location_id, status, expires_at, serial_numbers, lot_code
+         FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2
+         ON CONFLICT (order_id, product_id) DO UPDATE SET
+             quantity = inventory_reservations.quantity + EXCLUDED.quantity,
+             serial_numbers = inventory_reservations.serial_numbers || EXCLUDED.serial_numbers",
+        evt,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    bound("DELETE FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2", evt).execute(&mut *tx).await?;
+
+    // Lots received under both products are one lot: add up the quantities and point the
+    // duplicate lot's sales at the surviving lot before dropping it.
+    let fold_lots = format!(
+        "UPDATE inventory_lots c SET quantity_received = c.quantity_received + d.quantity_received,
+             quantity_on_hand = c.quantity_on_hand + d.quantity_on_hand
+         FROM inventory_lots d WHERE {SAME_LOT}"
+    );
+    summary.lots = bound(&fold_lots, evt).execute(&mut *tx).await?.rows_affected();
+    if summary.lots > 0 {
+        let move_sales = format!("UPDATE inventory_lot_sales s SET lot_id = c.id FROM inventory_lots d, inventory_lots c WHERE s.lot_id = d.id AND {SAME_LOT}");
+        bound(&move_sales, evt).execute(&mut *tx).await?;
+        let drop_lots = format!("DELETE FROM inventory_lots d USING inventory_lots c WHERE {SAME_LOT}");
+        bound(&drop_lots, evt).execute(&mut *tx).await?;
+    }
+    summary.lots += bound("UPDATE inventory_lots SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2", evt).execute(&mut *tx).await?.rows_affected();
+
+    summary.serials = bound(
+        "UPDATE inventory_serials d SET product_id = $3
+         WHERE d.tenant_id = $1 AND d.product_id = $2
+           AND NOT EXISTS (SELECT 1 FROM inventory_serials c WHERE c.tenant_id = $1 AND c.product_id = $3 AND c.serial_number = d.serial_number)",
+        evt,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    summary.serial_clashes = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM inventory_serials WHERE tenant_id = $1 AND product_id = $2")
+        .bind(evt.tenant_id)
+        .bind(evt.duplicate_id)
+        .fetch_one(&mut *tx)
+        .await? as u64;
+
+    for sql in RELABELS {
+        summary.history_rows += bound(sql, evt).execute(&mut *tx).await?.rows_affected();
+    }
+    tx.commit().await?;
+    Ok(summary)
+}
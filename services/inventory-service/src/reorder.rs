@@ -0,0 +1,469 @@
+//! Reorder suggestions: how much of a product a location should order, from its stock on hand,
+//! what is already on order, how fast it sells and how long its supplier takes to deliver.
+//!
+//! Each product and location with a reorder policy is projected to the day an order placed now
+//! would arrive: on hand plus on order, less the sales expected over the supplier's lead time.
+//! - `min_max`: when the projection is at or below `min_quantity`, order back up to
+//!   `max_quantity`.
+//! - `days_of_cover`: order whatever the projection lacks to cover `cover_days` more days of sales.
+//!
+//! Sales velocity is the average of analytics' `daily_product_sales.stock_units` for the location
+//! over the last full days (`REORDER_VELOCITY_DAYS`), net of refunds. Draft and submitted purchase
+//! orders count as on order, so an accepted suggestion is not suggested again. A periodic job
+//! keeps at most one open suggestion per product and location up to date, and leaves a dismissed
+//! one alone for `REORDER_DISMISS_DAYS`.
+use chrono::{DateTime, Utc};
+use common_db::{query, query_as, query_scalar};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// How much stock a location keeps of a product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum ReorderPolicy {
+    MinMax { min_quantity: i32, max_quantity: i32 },
+    DaysOfCover { cover_days: i32 },
+}
+
+/// Longest cover a `days_of_cover` policy may ask for.
+pub const MAX_COVER_DAYS: i32 = 365;
+
+impl ReorderPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MinMax { .. } => "min_max",
+            Self::DaysOfCover { .. } => "days_of_cover",
+        }
+    }
+
+    /// Rebuild a policy from its `reorder_policies` columns.
+    pub fn from_columns(policy: &str, min_quantity: Option<i32>, max_quantity: Option<i32>, cover_days: Option<i32>) -> Option<Self> {
+        match (policy, min_quantity, max_quantity, cover_days) {
+            ("min_max", Some(min_quantity), Some(max_quantity), _) => Some(Self::MinMax { min_quantity, max_quantity }),
+            ("days_of_cover", _, _, Some(cover_days)) => Some(Self::DaysOfCover { cover_days }),
+            _ => None,
+        }
+    }
+
+    /// A minimum of zero or more below a maximum, or 1 to [`MAX_COVER_DAYS`] days of cover.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Self::MinMax { min_quantity, max_quantity } => min_quantity >= 0 && max_quantity > min_quantity,
+            Self::DaysOfCover { cover_days } => (1..=MAX_COVER_DAYS).contains(&cover_days),
+        }
+    }
+
+    /// Units to order now, or `None` while the stock lasts.
+    pub fn suggest(&self, position: &StockPosition) -> Option<i32> {
+        let projected = position.projected();
+        let quantity = match *self {
+            Self::MinMax { min_quantity, max_quantity } if projected <= i64::from(min_quantity) => i64::from(max_quantity) - projected,
+            Self::MinMax { .. } => 0,
+            Self::DaysOfCover { cover_days } => (position.daily_velocity * f64::from(cover_days)).ceil() as i64 - projected,
+        };
+        (quantity > 0).then(|| i32::try_from(quantity).unwrap_or(i32::MAX))
+    }
+}
+
+/// What a suggestion for one product at one location is computed from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StockPosition {
+    pub on_hand: i64,
+    pub on_order: i64,
+    /// Units sold per day.
+    pub daily_velocity: f64,
+    pub lead_time_days: i32,
+}
+
+impl StockPosition {
+    /// Stock expected to be left when an order placed now arrives; negative when it runs out first.
+    pub fn projected(&self) -> i64 {
+        let lead_time_demand = (self.daily_velocity * f64::from(self.lead_time_days)).ceil() as i64;
+        self.on_hand + self.on_order - lead_time_demand
+    }
+}
+
+/// Units sold per day over `days` days; refunds exceeding sales count as none sold.
+pub fn daily_velocity(units_sold: i64, days: i32) -> f64 {
+    if days <= 0 {
+        return 0.0;
+    }
+    units_sold.max(0) as f64 / f64::from(days)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionStatus {
+    Open,
+    Accepted,
+    Dismissed,
+}
+
+impl SuggestionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Accepted => "accepted",
+            Self::Dismissed => "dismissed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Submitted,
+    Received,
+    Cancelled,
+}
+
+impl PurchaseOrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Submitted => "submitted",
+            Self::Received => "received",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "draft" => Some(Self::Draft),
+            "submitted" => Some(Self::Submitted),
+            "received" => Some(Self::Received),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Drafts are submitted or cancelled; submitted orders are received or cancelled.
+    pub fn can_become(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Draft, Self::Submitted | Self::Cancelled) | (Self::Submitted, Self::Received | Self::Cancelled)
+        )
+    }
+}
+
+/// Every policy with the stock figures its suggestion is computed from. Velocity covers the `$1`
+/// full days before today; `snoozed` is set while a dismissal is younger than `$2` days.
+const POLICY_POSITIONS: &str = "
+    SELECT p.tenant_id, p.product_id, p.location_id, p.supplier_id, p.policy, p.min_quantity, p.max_quantity, p.cover_days,
+           COALESCE(s.lead_time_days, 0) AS lead_time_days,
+           COALESCE((SELECT SUM(i.quantity) FROM inventory_items i
+                     WHERE i.tenant_id = p.tenant_id AND i.product_id = p.product_id AND i.location_id = p.location_id), 0)::bigint AS on_hand,
+           COALESCE((SELECT SUM(l.quantity) FROM purchase_order_lines l
+                     JOIN purchase_orders o ON o.id = l.purchase_order_id
+                     WHERE o.tenant_id = p.tenant_id AND o.location_id = p.location_id AND l.product_id = p.product_id
+                       AND o.status IN ('draft', 'submitted')), 0)::bigint AS on_order,
+           COALESCE((SELECT SUM(d.stock_units) FROM daily_product_sales d
+                     WHERE d.tenant_id = p.tenant_id AND d.product_id = p.product_id AND d.location_id = p.location_id
+                       AND d.date >= CURRENT_DATE - $1::int AND d.date < CURRENT_DATE), 0)::bigint AS units_sold,
+           EXISTS (SELECT 1 FROM reorder_suggestions r
+                   WHERE r.tenant_id = p.tenant_id AND r.product_id = p.product_id AND r.location_id = p.location_id
+                     AND r.status = 'dismissed' AND r.decided_at > NOW() - make_interval(days => $2::int)) AS snoozed
+    FROM reorder_policies p
+    LEFT JOIN suppliers s ON s.tenant_id = p.tenant_id AND s.id = p.supplier_id";
+
+/// What one run of the job left behind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshSummary {
+    /// Open suggestions written or refreshed.
+    pub open: usize,
+    /// Open suggestions withdrawn because stock recovered, a dismissal snoozes them or their policy
+    /// is gone.
+    pub withdrawn: u64,
+}
+
+/// Recompute the open suggestions of every tenant. Runs on the unscoped pool, like the oversell
+/// checker.
+pub async fn refresh_suggestions(db: &PgPool, velocity_days: i32, dismiss_days: i32) -> Result<RefreshSummary, sqlx::Error> {
+    let mut summary = RefreshSummary::default();
+    let rows = query(POLICY_POSITIONS).bind(velocity_days).bind(dismiss_days).fetch_all(db).await?;
+    for row in rows {
+        let tenant_id: Uuid = row.get("tenant_id");
+        let product_id: Uuid = row.get("product_id");
+        let location_id: Uuid = row.get("location_id");
+        let Some(policy) = ReorderPolicy::from_columns(row.get("policy"), row.get("min_quantity"), row.get("max_quantity"), row.get("cover_days")) else {
+            continue;
+        };
+        let position = StockPosition {
+            on_hand: row.get("on_hand"),
+            on_order: row.get("on_order"),
+            daily_velocity: daily_velocity(row.get("units_sold"), velocity_days),
+            lead_time_days: row.get("lead_time_days"),
+        };
+        let suggested = policy.suggest(&position).filter(|_| !row.get::<bool, _>("snoozed"));
+        let Some(quantity) = suggested else {
+            summary.withdrawn += query("DELETE FROM reorder_suggestions WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 AND status = 'open'")
+                .bind(tenant_id)
+                .bind(product_id)
+                .bind(location_id)
+                .execute(db)
+                .await?
+                .rows_affected();
+            continue;
+        };
+        query(
+            "INSERT INTO reorder_suggestions
+                 (id, tenant_id, product_id, location_id, supplier_id, policy, on_hand, on_order, daily_velocity, lead_time_days, suggested_quantity)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             ON CONFLICT (tenant_id, product_id, location_id) WHERE status = 'open'
+             DO UPDATE SET supplier_id = EXCLUDED.supplier_id, policy = EXCLUDED.policy, on_hand = EXCLUDED.on_hand,
+                           on_order = EXCLUDED.on_order, daily_velocity = EXCLUDED.daily_velocity,
+                           lead_time_days = EXCLUDED.lead_time_days, suggested_quantity = EXCLUDED.suggested_quantity,
+                           computed_at = NOW()",
+        )
+        .bind(Uuid::new_v4())
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(row.get::<Option<Uuid>, _>("supplier_id"))
+        .bind(policy.as_str())
+        .bind(position.on_hand)
+        .bind(position.on_order)
+        .bind(position.daily_velocity)
+        .bind(position.lead_time_days)
+        .bind(quantity)
+        .execute(db)
+        .await?;
+        summary.open += 1;
+    }
+    summary.withdrawn += query(
+        "DELETE FROM reorder_suggestions r WHERE r.status = 'open' AND NOT EXISTS (
+             SELECT 1 FROM reorder_policies p WHERE p.tenant_id = r.tenant_id AND p.product_id = r.product_id AND p.location_id = r.location_id
+         )",
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ReorderSuggestion {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub supplier_id: Option<Uuid>,
+    pub policy: String,
+    pub on_hand: i64,
+    pub on_order: i64,
+    pub daily_velocity: f64,
+    pub lead_time_days: i32,
+    pub suggested_quantity: i32,
+    pub status: String,
+    pub computed_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decided_by: Option<Uuid>,
+    pub purchase_order_id: Option<Uuid>,
+}
+
+const SUGGESTION_COLUMNS: &str = "id, product_id, location_id, supplier_id, policy, on_hand, on_order, daily_velocity, lead_time_days, \
+                                  suggested_quantity, status, computed_at, decided_at, decided_by, purchase_order_id";
+
+/// The tenant's suggestions in `status`, optionally at one location, largest orders first.
+pub async fn list_suggestions(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    status: SuggestionStatus,
+    location_id: Option<Uuid>,
+) -> Result<Vec<ReorderSuggestion>, sqlx::Error> {
+    query_as::<ReorderSuggestion>(&format!(
+        "SELECT {SUGGESTION_COLUMNS} FROM reorder_suggestions
+         WHERE tenant_id = $1 AND status = $2 AND ($3::uuid IS NULL OR location_id = $3)
+         ORDER BY suggested_quantity DESC, product_id, location_id"
+    ))
+    .bind(tenant_id)
+    .bind(status.as_str())
+    .bind(location_id)
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Lock one suggestion for a decision.
+pub async fn suggestion_for_update(conn: &mut sqlx::PgConnection, tenant_id: Uuid, suggestion_id: Uuid) -> Result<Option<ReorderSuggestion>, sqlx::Error> {
+    query_as::<ReorderSuggestion>(&format!("SELECT {SUGGESTION_COLUMNS} FROM reorder_suggestions WHERE tenant_id = $1 AND id = $2 FOR UPDATE"))
+        .bind(tenant_id)
+        .bind(suggestion_id)
+        .fetch_optional(&mut *conn)
+        .await
+}
+
+/// Add `quantity` of the suggested product to the draft purchase order for the suggestion's
+/// supplier and location, opening the draft if there is none. Returns the order's id.
+pub async fn add_to_draft_order(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    suggestion: &ReorderSuggestion,
+    quantity: i32,
+    created_by: Option<Uuid>,
+) -> Result<Uuid, sqlx::Error> {
+    let order_id = query_scalar::<Uuid>(
+        "INSERT INTO purchase_orders (id, tenant_id, supplier_id, location_id, created_by) VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (tenant_id, location_id, COALESCE(supplier_id, '00000000-0000-0000-0000-000000000000')) WHERE status = 'draft'
+         DO UPDATE SET updated_at = NOW()
+         RETURNING id",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(suggestion.supplier_id)
+    .bind(suggestion.location_id)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await?;
+    query(
+        "INSERT INTO purchase_order_lines (purchase_order_id, tenant_id, product_id, quantity) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (purchase_order_id, product_id) DO UPDATE SET quantity = purchase_order_lines.quantity + EXCLUDED.quantity",
+    )
+    .bind(order_id)
+    .bind(tenant_id)
+    .bind(suggestion.product_id)
+    .bind(quantity)
+    .execute(&mut *conn)
+    .await?;
+    Ok(order_id)
+}
+
+/// Record the decision on an open suggestion and return it as it now stands.
+pub async fn decide_suggestion(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    suggestion_id: Uuid,
+    status: SuggestionStatus,
+    decided_by: Option<Uuid>,
+    purchase_order_id: Option<Uuid>,
+) -> Result<ReorderSuggestion, sqlx::Error> {
+    query_as::<ReorderSuggestion>(&format!(
+        "UPDATE reorder_suggestions SET status = $3, decided_at = NOW(), decided_by = $4, purchase_order_id = $5
+         WHERE tenant_id = $1 AND id = $2
+         RETURNING {SUGGESTION_COLUMNS}"
+    ))
+    .bind(tenant_id)
+    .bind(suggestion_id)
+    .bind(status.as_str())
+    .bind(decided_by)
+    .bind(purchase_order_id)
+    .fetch_one(&mut *conn)
+    .await
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurchaseOrderLine {
+    pub product_id: Uuid,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PurchaseOrder {
+    pub id: Uuid,
+    pub supplier_id: Option<Uuid>,
+    pub location_id: Uuid,
+    pub status: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub lines: Vec<PurchaseOrderLine>,
+}
+
+/// The tenant's purchase orders with their lines, newest first; one order when `order_id` is
+/// given, else those in `status` (all without one).
+pub async fn load_purchase_orders(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    order_id: Option<Uuid>,
+    status: Option<PurchaseOrderStatus>,
+) -> Result<Vec<PurchaseOrder>, sqlx::Error> {
+    let rows = query(
+        "SELECT o.id, o.supplier_id, o.location_id, o.status, o.created_by, o.created_at, o.updated_at, l.product_id, l.quantity
+         FROM purchase_orders o
+         LEFT JOIN purchase_order_lines l ON l.purchase_order_id = o.id
+         WHERE o.tenant_id = $1 AND ($2::uuid IS NULL OR o.id = $2) AND ($3::text IS NULL OR o.status = $3)
+         ORDER BY o.created_at DESC, o.id, l.product_id",
+    )
+    .bind(tenant_id)
+    .bind(order_id)
+    .bind(status.map(|s| s.as_str()))
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut orders: Vec<PurchaseOrder> = Vec::new();
+    for row in rows {
+        let id: Uuid = row.get("id");
+        if orders.last().is_none_or(|order| order.id != id) {
+            orders.push(PurchaseOrder {
+                id,
+                supplier_id: row.get("supplier_id"),
+                location_id: row.get("location_id"),
+                status: row.get("status"),
+                created_by: row.get("created_by"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                lines: Vec::new(),
+            });
+        }
+        if let (Some(order), Some(product_id)) = (orders.last_mut(), row.get::<Option<Uuid>, _>("product_id")) {
+            order.lines.push(PurchaseOrderLine { product_id, quantity: row.get("quantity") });
+        }
+    }
+    Ok(orders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(on_hand: i64, on_order: i64, daily_velocity: f64, lead_time_days: i32) -> StockPosition {
+        StockPosition { on_hand, on_order, daily_velocity, lead_time_days }
+    }
+
+    #[test]
+    fn min_max_orders_up_to_the_maximum_once_the_projection_reaches_the_minimum() {
+        let policy = ReorderPolicy::MinMax { min_quantity: 10, max_quantity: 50 };
+        // 2.5 a day over a 4 day lead time: 10 sold before the delivery.
+        assert_eq!(policy.suggest(&position(21, 0, 2.5, 4)), None);
+        assert_eq!(policy.suggest(&position(20, 0, 2.5, 4)), Some(40));
+        assert_eq!(policy.suggest(&position(5, 10, 2.5, 4)), Some(45));
+        assert_eq!(policy.suggest(&position(0, 0, 6.0, 2)), Some(62), "a stock-out before delivery is ordered too");
+        assert_eq!(policy.suggest(&position(12, 0, 0.0, 7)), None);
+    }
+
+    #[test]
+    fn days_of_cover_orders_what_the_projection_lacks() {
+        let policy = ReorderPolicy::DaysOfCover { cover_days: 14 };
+        // 3 a day: 6 sold over the lead time, 42 wanted for the cover.
+        assert_eq!(policy.suggest(&position(20, 0, 3.0, 2)), Some(28));
+        assert_eq!(policy.suggest(&position(20, 28, 3.0, 2)), None);
+        assert_eq!(policy.suggest(&position(0, 0, 0.0, 2)), None, "nothing sells, nothing to order");
+        assert_eq!(policy.suggest(&position(-4, 0, 0.5, 0)), Some(11));
+    }
+
+    #[test]
+    fn velocity_is_net_sales_per_day() {
+        assert_eq!(daily_velocity(56, 28), 2.0);
+        assert_eq!(daily_velocity(-3, 28), 0.0);
+        assert_eq!(daily_velocity(10, 0), 0.0);
+    }
+
+    #[test]
+    fn policies_are_validated_and_read_back_from_their_columns() {
+        assert!(ReorderPolicy::MinMax { min_quantity: 0, max_quantity: 1 }.is_valid());
+        assert!(!ReorderPolicy::MinMax { min_quantity: 5, max_quantity: 5 }.is_valid());
+        assert!(!ReorderPolicy::MinMax { min_quantity: -1, max_quantity: 5 }.is_valid());
+        assert!(!ReorderPolicy::DaysOfCover { cover_days: 0 }.is_valid());
+        assert!(!ReorderPolicy::DaysOfCover { cover_days: MAX_COVER_DAYS + 1 }.is_valid());
+        assert_eq!(ReorderPolicy::from_columns("min_max", Some(2), Some(8), None), Some(ReorderPolicy::MinMax { min_quantity: 2, max_quantity: 8 }));
+        assert_eq!(ReorderPolicy::from_columns("days_of_cover", None, None, Some(7)), Some(ReorderPolicy::DaysOfCover { cover_days: 7 }));
+        assert_eq!(ReorderPolicy::from_columns("min_max", None, Some(8), None), None);
+    }
+
+    #[test]
+    fn purchase_orders_move_forward_only() {
+        use PurchaseOrderStatus::*;
+        assert!(Draft.can_become(Submitted) && Draft.can_become(Cancelled) && Submitted.can_become(Received));
+        assert!(!Draft.can_become(Received) && !Received.can_become(Cancelled) && !Cancelled.can_become(Draft));
+        assert_eq!(PurchaseOrderStatus::parse("submitted"), Some(Submitted));
+    }
+}
@@ -0,0 +1,435 @@
+//! HTTP side of reorder suggestions: suppliers, reorder policies, the suggestions the periodic job
+//! computes and the draft purchase orders accepting them creates. See [`crate::reorder`].
+use crate::reorder::{
+    add_to_draft_order, decide_suggestion, list_suggestions, load_purchase_orders, suggestion_for_update, PurchaseOrder, PurchaseOrderStatus,
+    ReorderPolicy, ReorderSuggestion, SuggestionStatus,
+};
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use common_db::{db_error, query, query_as, query_scalar};
+use common_http_errors::ApiError;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+/// Longest supplier lead time accepted, in days.
+const MAX_LEAD_TIME_DAYS: i32 = 365;
+
+fn ensure_role(sec: &SecurityContext, allowed: &[Role], role: &'static str) -> Result<(), ApiError> {
+    if sec.roles.iter().any(|r| allowed.contains(r)) {
+        Ok(())
+    } else {
+        Err(ApiError::ForbiddenMissingRole { role, trace_id: sec.trace_id })
+    }
+}
+
+fn ensure_admin_or_manager(sec: &SecurityContext) -> Result<(), ApiError> {
+    ensure_role(sec, &[Role::SuperAdmin, Role::Admin, Role::Manager], "admin_or_manager")
+}
+
+fn bad_request(code: &'static str, sec: &SecurityContext, message: &str) -> ApiError {
+    ApiError::BadRequest { code, trace_id: sec.trace_id, message: Some(message.into()) }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Supplier {
+    pub id: Uuid,
+    pub name: String,
+    pub lead_time_days: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SupplierBody {
+    pub name: String,
+    pub lead_time_days: i32,
+}
+
+impl SupplierBody {
+    fn validate(&self, sec: &SecurityContext) -> Result<(), ApiError> {
+        if self.name.trim().is_empty() {
+            return Err(bad_request("invalid_supplier", sec, "name must not be blank"));
+        }
+        if !(0..=MAX_LEAD_TIME_DAYS).contains(&self.lead_time_days) {
+            return Err(bad_request("invalid_lead_time", sec, "lead_time_days must be between 0 and 365"));
+        }
+        Ok(())
+    }
+}
+
+fn supplier_write_error(e: sqlx::Error, sec: &SecurityContext) -> ApiError {
+    match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            ApiError::Conflict { code: "supplier_exists", trace_id: sec.trace_id, message: Some("a supplier with this name already exists".into()) }
+        }
+        other => ApiError::internal(other, sec.trace_id),
+    }
+}
+
+/// `GET /inventory/suppliers`
+pub async fn list_suppliers(State(state): State<AppState>, SecurityCtxExtractor(sec): SecurityCtxExtractor) -> Result<Json<Vec<Supplier>>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let suppliers = query_as::<Supplier>("SELECT id, name, lead_time_days, created_at, updated_at FROM suppliers WHERE tenant_id = $1 ORDER BY name")
+        .bind(sec.tenant_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(suppliers))
+}
+
+/// `POST /inventory/suppliers`: 409 `supplier_exists` when the name is taken.
+pub async fn create_supplier(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(body): Json<SupplierBody>,
+) -> Result<(StatusCode, Json<Supplier>), ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    body.validate(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let supplier = query_as::<Supplier>(
+        "INSERT INTO suppliers (id, tenant_id, name, lead_time_days) VALUES ($1, $2, $3, $4)
+         RETURNING id, name, lead_time_days, created_at, updated_at",
+    )
+    .bind(Uuid::new_v4())
+    .bind(sec.tenant_id)
+    .bind(body.name.trim())
+    .bind(body.lead_time_days)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| supplier_write_error(e, &sec))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok((StatusCode::CREATED, Json(supplier)))
+}
+
+/// `PUT /inventory/suppliers/:id`: a new lead time applies from the next suggestion run.
+pub async fn update_supplier(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(supplier_id): Path<Uuid>,
+    Json(body): Json<SupplierBody>,
+) -> Result<Json<Supplier>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    body.validate(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let supplier = query_as::<Supplier>(
+        "UPDATE suppliers SET name = $3, lead_time_days = $4, updated_at = NOW() WHERE tenant_id = $1 AND id = $2
+         RETURNING id, name, lead_time_days, created_at, updated_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(supplier_id)
+    .bind(body.name.trim())
+    .bind(body.lead_time_days)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| supplier_write_error(e, &sec))?
+    .ok_or(ApiError::NotFound { code: "supplier_not_found", trace_id: sec.trace_id })?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(supplier))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyBody {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub supplier_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub policy: ReorderPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PolicyQuery {
+    pub location_id: Option<Uuid>,
+}
+
+/// `GET /inventory/reorder/policies?location_id`
+pub async fn list_reorder_policies(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<PolicyQuery>,
+) -> Result<Json<Vec<PolicyBody>>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let rows = query(
+        "SELECT product_id, location_id, supplier_id, policy, min_quantity, max_quantity, cover_days FROM reorder_policies
+         WHERE tenant_id = $1 AND ($2::uuid IS NULL OR location_id = $2)
+         ORDER BY location_id, product_id",
+    )
+    .bind(sec.tenant_id)
+    .bind(params.location_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(
+        rows.into_iter()
+            .filter_map(|row| {
+                let policy = ReorderPolicy::from_columns(row.get("policy"), row.get("min_quantity"), row.get("max_quantity"), row.get("cover_days"))?;
+                Some(PolicyBody { product_id: row.get("product_id"), location_id: row.get("location_id"), supplier_id: row.get("supplier_id"), policy })
+            })
+            .collect(),
+    ))
+}
+
+/// `PUT /inventory/reorder/policies`: set the policy for one product and location. 400
+/// `invalid_reorder_policy` for bounds the policy cannot use, 404 for a location or supplier the
+/// tenant does not have.
+pub async fn put_reorder_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(body): Json<PolicyBody>,
+) -> Result<Json<PolicyBody>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    if !body.policy.is_valid() {
+        return Err(bad_request(
+            "invalid_reorder_policy",
+            &sec,
+            "min_max needs 0 <= min_quantity < max_quantity; days_of_cover needs cover_days between 1 and 365",
+        ));
+    }
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let location = query_scalar::<Uuid>("SELECT id FROM locations WHERE tenant_id = $1 AND id = $2")
+        .bind(sec.tenant_id)
+        .bind(body.location_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    if location.is_none() {
+        return Err(ApiError::NotFound { code: "location_not_found", trace_id: sec.trace_id });
+    }
+    if let Some(supplier_id) = body.supplier_id {
+        let supplier = query_scalar::<Uuid>("SELECT id FROM suppliers WHERE tenant_id = $1 AND id = $2")
+            .bind(sec.tenant_id)
+            .bind(supplier_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        if supplier.is_none() {
+            return Err(ApiError::NotFound { code: "supplier_not_found", trace_id: sec.trace_id });
+        }
+    }
+    let (min_quantity, max_quantity, cover_days) = match body.policy {
+        ReorderPolicy::MinMax { min_quantity, max_quantity } => (Some(min_quantity), Some(max_quantity), None),
+        ReorderPolicy::DaysOfCover { cover_days } => (None, None, Some(cover_days)),
+    };
+    query(
+        "INSERT INTO reorder_policies (tenant_id, product_id, location_id, supplier_id, policy, min_quantity, max_quantity, cover_days)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (tenant_id, product_id, location_id) DO UPDATE SET
+             supplier_id = EXCLUDED.supplier_id, policy = EXCLUDED.policy, min_quantity = EXCLUDED.min_quantity,
+             max_quantity = EXCLUDED.max_quantity, cover_days = EXCLUDED.cover_days, updated_at = NOW()",
+    )
+    .bind(sec.tenant_id)
+    .bind(body.product_id)
+    .bind(body.location_id)
+    .bind(body.supplier_id)
+    .bind(body.policy.as_str())
+    .bind(min_quantity)
+    .bind(max_quantity)
+    .bind(cover_days)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PolicyKey {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+}
+
+/// `DELETE /inventory/reorder/policies?product_id&location_id`: the next run withdraws its open
+/// suggestion.
+pub async fn delete_reorder_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(key): Query<PolicyKey>,
+) -> Result<StatusCode, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let deleted = query("DELETE FROM reorder_policies WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3")
+        .bind(sec.tenant_id)
+        .bind(key.product_id)
+        .bind(key.location_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .rows_affected();
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    if deleted == 0 {
+        return Err(ApiError::NotFound { code: "reorder_policy_not_found", trace_id: sec.trace_id });
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionQuery {
+    /// Defaults to `open`.
+    pub status: Option<SuggestionStatus>,
+    pub location_id: Option<Uuid>,
+}
+
+/// `GET /inventory/reorder/suggestions?status&location_id`
+pub async fn get_reorder_suggestions(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<SuggestionQuery>,
+) -> Result<Json<Vec<ReorderSuggestion>>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let suggestions = list_suggestions(&mut tx, sec.tenant_id, params.status.unwrap_or(SuggestionStatus::Open), params.location_id)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(suggestions))
+}
+
+/// Lock a suggestion that is still open: 404 `reorder_suggestion_not_found`, 409
+/// `reorder_suggestion_closed` once accepted or dismissed.
+async fn open_suggestion(conn: &mut sqlx::PgConnection, sec: &SecurityContext, suggestion_id: Uuid) -> Result<ReorderSuggestion, ApiError> {
+    let suggestion = suggestion_for_update(conn, sec.tenant_id, suggestion_id)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "reorder_suggestion_not_found", trace_id: sec.trace_id })?;
+    if suggestion.status != SuggestionStatus::Open.as_str() {
+        return Err(ApiError::Conflict {
+            code: "reorder_suggestion_closed",
+            trace_id: sec.trace_id,
+            message: Some(format!("suggestion is already {}", suggestion.status)),
+        });
+    }
+    Ok(suggestion)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AcceptBody {
+    /// Units to order instead of the suggested quantity.
+    pub quantity: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcceptedSuggestion {
+    pub suggestion: ReorderSuggestion,
+    pub purchase_order: PurchaseOrder,
+}
+
+/// `POST /inventory/reorder/suggestions/:id/accept`: add the suggestion, or `quantity` units, to
+/// the draft purchase order for its supplier and location.
+pub async fn accept_reorder_suggestion(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(suggestion_id): Path<Uuid>,
+    body: Option<Json<AcceptBody>>,
+) -> Result<Json<AcceptedSuggestion>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let Json(body) = body.unwrap_or_default();
+    if body.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Err(bad_request("invalid_quantity", &sec, "quantity must be positive"));
+    }
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let suggestion = open_suggestion(&mut tx, &sec, suggestion_id).await?;
+    let quantity = body.quantity.unwrap_or(suggestion.suggested_quantity);
+    let order_id = add_to_draft_order(&mut tx, sec.tenant_id, &suggestion, quantity, sec.actor.id)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let suggestion = decide_suggestion(&mut tx, sec.tenant_id, suggestion_id, SuggestionStatus::Accepted, sec.actor.id, Some(order_id))
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let purchase_order = load_purchase_orders(&mut tx, sec.tenant_id, Some(order_id), None)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .pop()
+        .ok_or_else(|| ApiError::internal("draft purchase order vanished", sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tracing::info!(tenant_id = %sec.tenant_id, suggestion_id = %suggestion_id, purchase_order_id = %order_id, quantity, "Reorder suggestion accepted");
+    Ok(Json(AcceptedSuggestion { suggestion, purchase_order }))
+}
+
+/// `POST /inventory/reorder/suggestions/:id/dismiss`: no new suggestion for the product and
+/// location until `REORDER_DISMISS_DAYS` have passed.
+pub async fn dismiss_reorder_suggestion(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(suggestion_id): Path<Uuid>,
+) -> Result<Json<ReorderSuggestion>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    open_suggestion(&mut tx, &sec, suggestion_id).await?;
+    let suggestion = decide_suggestion(&mut tx, sec.tenant_id, suggestion_id, SuggestionStatus::Dismissed, sec.actor.id, None)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(suggestion))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurchaseOrderQuery {
+    pub status: Option<PurchaseOrderStatus>,
+}
+
+/// `GET /inventory/purchase-orders?status`
+pub async fn list_purchase_orders(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<PurchaseOrderQuery>,
+) -> Result<Json<Vec<PurchaseOrder>>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let orders = load_purchase_orders(&mut tx, sec.tenant_id, None, params.status).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(orders))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurchaseOrderStatusBody {
+    pub status: PurchaseOrderStatus,
+}
+
+/// `PUT /inventory/purchase-orders/:id/status`: 409 `invalid_purchase_order_transition` unless a
+/// draft is submitted or cancelled, or a submitted order received or cancelled. Receiving an order
+/// only takes it off order; the stock itself comes in through `POST /inventory/receive`.
+pub async fn set_purchase_order_status(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(order_id): Path<Uuid>,
+    Json(body): Json<PurchaseOrderStatusBody>,
+) -> Result<Json<PurchaseOrder>, ApiError> {
+    ensure_admin_or_manager(&sec)?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let current = query_scalar::<String>("SELECT status FROM purchase_orders WHERE tenant_id = $1 AND id = $2 FOR UPDATE")
+        .bind(sec.tenant_id)
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "purchase_order_not_found", trace_id: sec.trace_id })?;
+    if !PurchaseOrderStatus::parse(&current).is_some_and(|status| status.can_become(body.status)) {
+        return Err(ApiError::Conflict {
+            code: "invalid_purchase_order_transition",
+            trace_id: sec.trace_id,
+            message: Some(format!("a {current} purchase order cannot become {}", body.status.as_str())),
+        });
+    }
+    query("UPDATE purchase_orders SET status = $3, updated_at = NOW() WHERE tenant_id = $1 AND id = $2")
+        .bind(sec.tenant_id)
+        .bind(order_id)
+        .bind(body.status.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let order = load_purchase_orders(&mut tx, sec.tenant_id, Some(order_id), None)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .pop()
+        .ok_or(ApiError::NotFound { code: "purchase_order_not_found", trace_id: sec.trace_id })?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(order))
+}
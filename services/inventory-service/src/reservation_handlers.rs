@@ -1,11 +1,18 @@
 use crate::{AppState, DEFAULT_THRESHOLD}; // DEFAULT_THRESHOLD now defined in lib
+use crate::bom::{expand, load_boms};
+use crate::tracking_handlers::{claim_for_order, normalize_lot_code, normalize_serials, release_serials};
 use axum::extract::{Path, State};
-use axum::{ Json };
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use common_security::{SecurityCtxExtractor, Capability, ensure_capability};
 use common_http_errors::ApiError;
+use common_money::measure::to_milli_units;
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
-use sqlx::{query, query_as, query_scalar, Row}; // dynamic + typed queries
-use std::collections::HashMap;
+use common_db::{db_error, query, query_as, query_scalar}; // tenant-scoped dynamic + typed queries
+use sqlx::Row;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 // Legacy RESERVATION_ROLES removed; capability Payment/Inventory reservations mapped to InventoryView + (future) InventoryWrite if introduced.
@@ -13,8 +20,79 @@ use uuid::Uuid;
 #[derive(Debug, Deserialize)]
 pub struct ReservationItemPayload {
     pub product_id: Uuid,
+    #[serde(default)]
     pub quantity: i32,
     pub location_id: Option<Uuid>, // optional until multi-location feature enabled
+    /// Serials scanned at the till; required (one per unit) for serial-tracked products.
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    /// Lot the units were taken from, when the till captured it.
+    #[serde(default)]
+    pub lot_code: Option<String>,
+    /// Net scale reading for products sold by weight; reserved as thousandths of the unit.
+    #[serde(default)]
+    pub measured_quantity: Option<BigDecimal>,
+}
+
+impl ReservationItemPayload {
+    /// Stock units this line takes: the reading in thousandths for weighed lines, else the count.
+    fn stock_units(&self, trace_id: Option<Uuid>) -> Result<i32, ApiError> {
+        let units = match &self.measured_quantity {
+            Some(measured) => to_milli_units(measured).unwrap_or(0),
+            None => self.quantity,
+        };
+        if units <= 0 {
+            return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id, message: Some(format!("Quantity for product {} must be positive", self.product_id)) });
+        }
+        Ok(units)
+    }
+}
+
+/// Swap bundle lines for the components they are made of; a component reserved only through a
+/// bundle takes that bundle line's location. Products without a bill of materials pass through.
+async fn expand_bundles(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    trace_id: Option<Uuid>,
+    lines: BTreeMap<Uuid, (i32, Option<Uuid>)>,
+) -> Result<BTreeMap<Uuid, (i32, Option<Uuid>)>, ApiError> {
+    let product_ids: Vec<Uuid> = lines.keys().copied().collect();
+    let boms = load_boms(conn, tenant_id, &product_ids).await.map_err(|err| ApiError::internal(err, trace_id))?;
+    if boms.is_empty() {
+        return Ok(lines);
+    }
+    let quantities: Vec<(Uuid, i32)> = lines.iter().map(|(id, (quantity, _))| (*id, *quantity)).collect();
+    let (stock, consumed) = expand(&quantities, &boms).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_quantity",
+        trace_id,
+        message: Some("Bundle quantities exceed what a single reservation can hold".into()),
+    })?;
+    let location_of = |product_id: Uuid| match lines.get(&product_id) {
+        Some((_, location)) => *location,
+        None => consumed
+            .iter()
+            .find(|c| c.component_id == product_id)
+            .and_then(|c| lines.get(&c.bundle_id))
+            .and_then(|(_, location)| *location),
+    };
+    Ok(stock.into_iter().map(|(product_id, quantity)| (product_id, (quantity, location_of(product_id)))).collect())
+}
+
+/// Serials scanned across every line of one product, and the lot code those lines captured.
+fn captured_tracking(items: &[ReservationItemPayload], product_id: Uuid, trace_id: Option<Uuid>) -> Result<(Vec<String>, Option<String>), ApiError> {
+    let lines = items.iter().filter(|i| i.product_id == product_id);
+    let serials: Vec<String> = lines.clone().flat_map(|i| i.serial_numbers.iter().cloned()).collect();
+    let mut lot_code: Option<String> = None;
+    for line in lines {
+        match (normalize_lot_code(line.lot_code.as_deref(), trace_id)?, lot_code.as_ref()) {
+            (Some(code), Some(existing)) if &code != existing => {
+                return Err(ApiError::BadRequest { code: "conflicting_lot_codes", trace_id, message: Some(format!("Lines for product {} name different lots", product_id)) });
+            }
+            (Some(code), None) => lot_code = Some(code),
+            _ => {}
+        }
+    }
+    Ok((normalize_serials(&serials, trace_id)?, lot_code))
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,24 +133,22 @@ pub async fn create_reservation(
         return Err(ApiError::BadRequest { code: "empty_reservation", trace_id: None, message: Some("Reservation must include at least one item".into()) });
     }
 
-    let mut condensed: HashMap<Uuid, i32> = HashMap::new();
+    let mut condensed: BTreeMap<Uuid, (i32, Option<Uuid>)> = BTreeMap::new();
     for item in payload.items.iter() {
-        if item.quantity <= 0 {
-            return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id: None, message: Some(format!("Quantity for product {} must be positive", item.product_id)) });
-        }
-        *condensed.entry(item.product_id).or_insert(0) += item.quantity;
+        condensed.entry(item.product_id).or_insert((0, item.location_id)).0 += item.stock_units(None)?;
     }
 
     let mut tx = state
         .db
-        .begin()
+        .begin_for(&sec)
     .await
-    .map_err(|err| ApiError::internal(err, None))?;
+    .map_err(|err| db_error(err, None))?;
 
-    let existing = query_scalar::<_, i64>(
-        "SELECT 1 FROM inventory_reservations WHERE order_id = $1",
+    let existing = query_scalar::<i64>(
+        "SELECT 1 FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2",
     )
     .bind(payload.order_id)
+    .bind(tenant_id)
     .fetch_optional(&mut *tx)
     .await
     .map_err(|err| ApiError::internal(err, None))?;
@@ -80,42 +156,20 @@ pub async fn create_reservation(
     if existing.is_some() {
         return Err(ApiError::BadRequest { code: "reservation_exists", trace_id: None, message: Some("Reservation already exists for this order".into()) });
     }
+    let condensed = expand_bundles(&mut tx, tenant_id, sec.trace_id, condensed).await?;
 
     let mut reserved_items = Vec::with_capacity(condensed.len());
 
-    for (product_id, quantity) in condensed.iter() {
-        // Determine a candidate location for this product (first occurrence) if provided.
-        let loc = payload
-            .items
-            .iter()
-            .find(|i| i.product_id == *product_id)
-            .and_then(|i| i.location_id);
+    for (product_id, (quantity, loc)) in condensed.iter() {
+        // Candidate location for this product: its first line's, or its bundle's.
+        let loc = *loc;
 
         if state.multi_location_enabled {
             // Multi-location: compute available = sum(inventory_items at location) - active reservations at that location.
             if let Some(location_id) = loc {
-                let inv_row = query(
-                    "SELECT quantity FROM inventory_items WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 FOR UPDATE",
-                )
-                .bind(tenant_id)
-                .bind(product_id)
-                .bind(location_id)
-                .fetch_optional(&mut *tx)
-                .await
-                .map_err(|e| ApiError::internal(e, None))?;
-                let current_quantity: i32 = inv_row.map(|r| r.get::<i32, _>("quantity")).unwrap_or(0);
-                let reserved_total: i64 = query(
-                    "SELECT COALESCE(SUM(quantity),0) AS total FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 AND status = 'ACTIVE'",
-                )
-                .bind(tenant_id)
-                .bind(product_id)
-                .bind(location_id)
-                .fetch_one(&mut *tx)
-                .await
-                .map_err(|e| ApiError::internal(e, None))?
-                .get::<i64, _>("total");
-                let available = current_quantity - reserved_total as i32;
+                let available = available_stock(&mut tx, tenant_id, *product_id, Some(location_id)).await?;
                 if *quantity > available {
+                    state.metrics.reservation_insufficient_stock.with_label_values(&["create"]).inc();
                     return Err(ApiError::BadRequest { code: "insufficient_stock", trace_id: None, message: Some(format!("Insufficient stock for product {} at location {} (requested {}, available {})", product_id, location_id, quantity, available)) });
                 }
             }
@@ -139,27 +193,9 @@ pub async fn create_reservation(
             });
         } else {
             // Legacy single-inventory path
-            let inventory_row = query(
-                "SELECT quantity FROM inventory WHERE tenant_id = $1 AND product_id = $2 FOR UPDATE",
-            )
-            .bind(tenant_id)
-            .bind(product_id)
-            .fetch_optional(&mut *tx)
-            .await
-            .map_err(|err| ApiError::internal(err, None))?;
-            let current_quantity = inventory_row
-                .map(|row| row.get::<i32, _>("quantity"))
-                .unwrap_or(0);
-            let reserved_total: i64 = query_scalar::<_, i64>(
-                "SELECT COALESCE(SUM(quantity), 0) FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2",
-            )
-            .bind(tenant_id)
-            .bind(product_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|err| ApiError::internal(err, None))?;
-            let available = current_quantity - reserved_total as i32;
+            let available = available_stock(&mut tx, tenant_id, *product_id, None).await?;
             if *quantity > available {
+                state.metrics.reservation_insufficient_stock.with_label_values(&["create"]).inc();
                 return Err(ApiError::BadRequest { code: "insufficient_stock", trace_id: None, message: Some(format!("Insufficient stock for product {} (requested {}, available {})", product_id, quantity, available)) });
             }
             query(
@@ -178,6 +214,9 @@ pub async fn create_reservation(
                 location_id: None,
             });
         }
+
+        let (serials, lot_code) = captured_tracking(&payload.items, *product_id, sec.trace_id)?;
+        claim_for_order(&mut tx, tenant_id, sec.trace_id, payload.order_id, *product_id, *quantity, &serials, lot_code.as_deref()).await?;
     }
 
     tx.commit().await.map_err(|err| ApiError::internal(err, None))?;
@@ -187,6 +226,8 @@ pub async fn create_reservation(
         "action": "inventory.reservation.created",
         "schema_version": 1,
         "tenant_id": tenant_id,
+        "actor_id": sec.actor.id,
+        "trace_id": sec.trace_id,
         "order_id": payload.order_id,
         "items": reserved_items.iter().map(|i| serde_json::json!({
             "product_id": i.product_id,
@@ -195,13 +236,15 @@ pub async fn create_reservation(
         })).collect::<Vec<_>>(),
     });
     #[cfg(feature = "kafka")]
-    if let Err(_err) = state.kafka_producer.send(
-        rdkafka::producer::FutureRecord::to("audit.events")
-            .payload(&_event.to_string())
-            .key(&tenant_id.to_string()),
-        std::time::Duration::from_secs(0),
-    ).await {
-        state.metrics.audit_emit_failures.inc();
+    if let Err(_err) = common_kafka::publish(
+        &state.kafka_producer,
+        "audit.events",
+        &tenant_id.to_string(),
+        &_event.to_string(),
+    )
+    .await
+    {
+        state.metrics.audit_emit_failures.with_label_values(&["inventory.reservation.created"]).inc();
     }
 
     Ok(Json(ReservationResponse {
@@ -210,6 +253,365 @@ pub async fn create_reservation(
     }))
 }
 
+/// How a batch reservation picks a location per line when multi-location is enabled.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationStrategy {
+    /// Reserve only at the line's location (or the request's `preferred_location_id`).
+    #[default]
+    Preferred,
+    /// Try the preferred location first, then any other location with enough stock.
+    Fallback,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchReservationRequest {
+    pub order_id: Uuid,
+    pub items: Vec<ReservationItemPayload>,
+    /// Default location for lines that don't name one.
+    pub preferred_location_id: Option<Uuid>,
+    #[serde(default)]
+    pub location_strategy: LocationStrategy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReservationLineFailure {
+    pub product_id: Uuid,
+    pub requested: i32,
+    pub available: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchReservationFailure {
+    code: &'static str,
+    trace_id: Option<Uuid>,
+    message: String,
+    order_id: Uuid,
+    failures: Vec<ReservationLineFailure>,
+}
+
+/// Order the locations to try for one line: the preferred location first, then (with
+/// `Fallback`) the others by most sellable stock. Each entry is `(location_id, available)`.
+pub fn candidate_locations(
+    preferred: Option<Uuid>,
+    strategy: LocationStrategy,
+    stock: &[(Uuid, i32)],
+) -> Vec<(Uuid, i32)> {
+    let mut candidates: Vec<(Uuid, i32)> = Vec::with_capacity(stock.len());
+    if let Some(preferred) = preferred {
+        let available = stock.iter().find(|(loc, _)| *loc == preferred).map(|(_, a)| *a).unwrap_or(0);
+        candidates.push((preferred, available));
+    }
+    if strategy == LocationStrategy::Fallback {
+        let mut others: Vec<(Uuid, i32)> = stock.iter().copied().filter(|(loc, _)| Some(*loc) != preferred).collect();
+        others.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates.extend(others);
+    }
+    candidates
+}
+
+/// Sellable stock per location for one product, locking its rows for the rest of the transaction.
+async fn location_stock(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    product_id: Uuid,
+) -> Result<Vec<(Uuid, i32)>, ApiError> {
+    let rows = query(
+        "SELECT ii.location_id,
+                (ii.quantity - COALESCE((SELECT SUM(r.quantity) FROM inventory_reservations r
+                     WHERE r.tenant_id = ii.tenant_id AND r.product_id = ii.product_id
+                       AND r.location_id = ii.location_id AND r.status = 'ACTIVE'), 0))::int AS available
+         FROM inventory_items ii
+         WHERE ii.tenant_id = $1 AND ii.product_id = $2
+         ORDER BY ii.location_id
+         FOR UPDATE",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|err| ApiError::internal(err, None))?;
+    Ok(rows.into_iter().map(|r| (r.get("location_id"), r.get("available"))).collect())
+}
+
+/// Reserve every line of a cart in one transaction. Either all lines are reserved or none
+/// are, in which case the 409 body lists why each failing line could not be placed.
+pub async fn create_reservation_batch(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(payload): Json<BatchReservationRequest>,
+) -> Result<Response, ApiError> {
+    ensure_capability(&sec, Capability::InventoryView)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
+    let tenant_id = sec.tenant_id;
+
+    if payload.items.is_empty() {
+        return Err(ApiError::BadRequest { code: "empty_reservation", trace_id: sec.trace_id, message: Some("Reservation must include at least one item".into()) });
+    }
+
+    // Sorted by product so concurrent batches take row locks in the same order.
+    let mut condensed: BTreeMap<Uuid, (i32, Option<Uuid>)> = BTreeMap::new();
+    for item in payload.items.iter() {
+        let units = item.stock_units(sec.trace_id)?;
+        let entry = condensed.entry(item.product_id).or_insert((0, item.location_id));
+        entry.0 = entry.0.saturating_add(units);
+    }
+
+    let mut tx = state
+        .db
+        .begin_for(&sec)
+        .await
+        .map_err(|err| db_error(err, None))?;
+
+    let existing = query_scalar::<i64>(
+        "SELECT 1 FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2",
+    )
+    .bind(payload.order_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|err| ApiError::internal(err, None))?;
+    if existing.is_some() {
+        return Err(ApiError::BadRequest { code: "reservation_exists", trace_id: sec.trace_id, message: Some("Reservation already exists for this order".into()) });
+    }
+    let condensed = expand_bundles(&mut tx, tenant_id, sec.trace_id, condensed).await?;
+
+    let mut placements: Vec<ReservationItem> = Vec::with_capacity(condensed.len());
+    let mut failures: Vec<ReservationLineFailure> = Vec::new();
+    for (product_id, (quantity, line_location)) in condensed.iter() {
+        let (product_id, quantity) = (*product_id, *quantity);
+        let preferred = line_location.or(payload.preferred_location_id);
+        let locate = state.multi_location_enabled && (preferred.is_some() || payload.location_strategy == LocationStrategy::Fallback);
+        if !locate {
+            let available = available_stock(&mut tx, tenant_id, product_id, None).await?;
+            if quantity > available {
+                failures.push(ReservationLineFailure { product_id, requested: quantity, available: available.max(0), location_id: None, reason: "insufficient_stock" });
+            } else {
+                placements.push(ReservationItem { product_id, quantity, location_id: None });
+            }
+            continue;
+        }
+        let stock = location_stock(&mut tx, tenant_id, product_id).await?;
+        let candidates = candidate_locations(preferred, payload.location_strategy, &stock);
+        match candidates.iter().find(|(_, available)| *available >= quantity) {
+            Some((location_id, _)) => placements.push(ReservationItem { product_id, quantity, location_id: Some(*location_id) }),
+            None => {
+                let best = candidates.iter().max_by_key(|(_, available)| *available).copied();
+                failures.push(ReservationLineFailure {
+                    product_id,
+                    requested: quantity,
+                    available: best.map(|(_, a)| a.max(0)).unwrap_or(0),
+                    location_id: best.map(|(loc, _)| loc).or(preferred),
+                    reason: if candidates.is_empty() { "not_stocked" } else { "insufficient_stock" },
+                });
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        // Dropping the transaction rolls back and releases the row locks.
+        drop(tx);
+        state.metrics.reservation_insufficient_stock.with_label_values(&["batch"]).inc();
+        let body = BatchReservationFailure {
+            code: "insufficient_stock",
+            trace_id: sec.trace_id,
+            message: format!("{} of {} lines could not be reserved", failures.len(), condensed.len()),
+            order_id: payload.order_id,
+            failures,
+        };
+        return Ok((StatusCode::CONFLICT, [("X-Error-Code", "insufficient_stock")], Json(body)).into_response());
+    }
+
+    let ttl_secs = state.multi_location_enabled.then_some(state.reservation_default_ttl.as_secs() as i64);
+    for item in placements.iter() {
+        query(
+            "INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, location_id, expires_at)
+             VALUES ($1, $2, $3, $4, $5, NOW() + ($6 * INTERVAL '1 second'))",
+        )
+        .bind(payload.order_id)
+        .bind(tenant_id)
+        .bind(item.product_id)
+        .bind(item.quantity)
+        .bind(item.location_id)
+        .bind(ttl_secs)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| ApiError::internal(err, None))?;
+        let (serials, lot_code) = captured_tracking(&payload.items, item.product_id, sec.trace_id)?;
+        claim_for_order(&mut tx, tenant_id, sec.trace_id, payload.order_id, item.product_id, item.quantity, &serials, lot_code.as_deref()).await?;
+    }
+
+    tx.commit().await.map_err(|err| ApiError::internal(err, None))?;
+
+    Ok(Json(ReservationResponse { order_id: payload.order_id, items: placements }).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReservationDeltaPayload {
+    pub product_id: Uuid,
+    /// Positive to reserve more, negative to give quantity back.
+    pub delta: i32,
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdjustReservationRequest {
+    pub items: Vec<ReservationDeltaPayload>,
+}
+
+/// On-hand stock minus active reservations, locking the stock row so concurrent reservations
+/// for the same product serialize.
+async fn available_stock(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+) -> Result<i32, ApiError> {
+    let (current_quantity, reserved_total) = match location_id {
+        Some(location_id) => {
+            let current: Option<i32> = query_scalar::<i32>(
+                "SELECT quantity FROM inventory_items WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 FOR UPDATE",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(location_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| ApiError::internal(e, None))?;
+            let reserved: i64 = query_scalar::<i64>(
+                "SELECT COALESCE(SUM(quantity),0) FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3 AND status = 'ACTIVE'",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(location_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| ApiError::internal(e, None))?;
+            (current.unwrap_or(0), reserved)
+        }
+        None => {
+            let current: Option<i32> = query_scalar::<i32>(
+                "SELECT quantity FROM inventory WHERE tenant_id = $1 AND product_id = $2 FOR UPDATE",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|err| ApiError::internal(err, None))?;
+            let reserved: i64 = query_scalar::<i64>(
+                "SELECT COALESCE(SUM(quantity), 0) FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|err| ApiError::internal(err, None))?;
+            (current.unwrap_or(0), reserved)
+        }
+    };
+    Ok(current_quantity - reserved_total as i32)
+}
+
+/// Apply per-product quantity deltas to an existing order's reservation (order editing).
+/// Lines whose reserved quantity drops to zero are removed; the response lists what remains.
+pub async fn adjust_reservation(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(order_id): Path<Uuid>,
+    Json(payload): Json<AdjustReservationRequest>,
+) -> Result<Json<ReservationResponse>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryView)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
+    let tenant_id = sec.tenant_id;
+
+    let mut condensed: BTreeMap<Uuid, (i32, Option<Uuid>)> = BTreeMap::new();
+    for item in payload.items.iter() {
+        let entry = condensed.entry(item.product_id).or_insert((0, item.location_id));
+        entry.0 += item.delta;
+    }
+
+    let mut tx = state
+        .db
+        .begin_for(&sec)
+        .await
+        .map_err(|err| db_error(err, None))?;
+    let condensed = expand_bundles(&mut tx, tenant_id, sec.trace_id, condensed).await?;
+
+    for (product_id, (delta, loc)) in condensed.iter() {
+        let (product_id, delta) = (*product_id, *delta);
+        if delta > 0 {
+            let location_id = if state.multi_location_enabled { *loc } else { None };
+            let available = available_stock(&mut tx, tenant_id, product_id, location_id).await?;
+            if delta > available {
+                state.metrics.reservation_insufficient_stock.with_label_values(&["adjust"]).inc();
+                return Err(ApiError::BadRequest { code: "insufficient_stock", trace_id: None, message: Some(format!("Insufficient stock for product {} (requested {}, available {})", product_id, delta, available)) });
+            }
+            // Multi-location reservations expire like freshly created ones; legacy ones never do.
+            let ttl_secs = state.multi_location_enabled.then_some(state.reservation_default_ttl.as_secs() as i64);
+            query(
+                "INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, location_id, expires_at)
+                 VALUES ($1, $2, $3, $4, $5, NOW() + ($6 * INTERVAL '1 second'))
+                 ON CONFLICT (order_id, product_id) DO UPDATE SET quantity = inventory_reservations.quantity + EXCLUDED.quantity",
+            )
+            .bind(order_id)
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(delta)
+            .bind(location_id)
+            .bind(ttl_secs)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| ApiError::internal(err, None))?;
+        } else if delta < 0 {
+            let remaining: Option<i32> = query_scalar::<i32>(
+                "UPDATE inventory_reservations SET quantity = quantity + $4 WHERE order_id = $1 AND tenant_id = $2 AND product_id = $3 AND quantity + $4 > 0 RETURNING quantity",
+            )
+            .bind(order_id)
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(delta)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|err| ApiError::internal(err, None))?;
+            if remaining.is_none() {
+                query("DELETE FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2 AND product_id = $3")
+                    .bind(order_id)
+                    .bind(tenant_id)
+                    .bind(product_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|err| ApiError::internal(err, None))?;
+                release_serials(&mut tx, tenant_id, order_id, Some(product_id))
+                    .await
+                    .map_err(|err| ApiError::internal(err, None))?;
+            }
+        }
+    }
+
+    let items = query(
+        "SELECT product_id, quantity, location_id FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2 ORDER BY product_id",
+    )
+    .bind(order_id)
+    .bind(tenant_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|err| ApiError::internal(err, None))?
+    .into_iter()
+    .map(|r| ReservationItem {
+        product_id: r.get("product_id"),
+        quantity: r.get("quantity"),
+        location_id: r.try_get("location_id").unwrap_or(None),
+    })
+    .collect();
+
+    tx.commit().await.map_err(|err| ApiError::internal(err, None))?;
+
+    Ok(Json(ReservationResponse { order_id, items }))
+}
+
 pub async fn release_reservation(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
@@ -221,9 +623,9 @@ pub async fn release_reservation(
 
     let mut tx = state
         .db
-        .begin()
+        .begin_for(&sec)
     .await
-    .map_err(|err| ApiError::internal(err, None))?;
+    .map_err(|err| db_error(err, None))?;
 
     let rows = if state.multi_location_enabled {
         let raw = query(
@@ -242,7 +644,7 @@ pub async fn release_reservation(
             })
             .collect::<Vec<_>>()
     } else {
-        let legacy = query_as::<_, LegacyReservationRow>(
+        let legacy = query_as::<LegacyReservationRow>(
             "DELETE FROM inventory_reservations WHERE order_id = $1 AND tenant_id = $2 RETURNING product_id, quantity",
         )
         .bind(order_id)
@@ -252,6 +654,9 @@ pub async fn release_reservation(
     .map_err(|err| ApiError::internal(err, None))?;
         legacy.into_iter().map(|r| ReservationItem { product_id: r.product_id, quantity: r.quantity, location_id: None }).collect()
     };
+    release_serials(&mut tx, tenant_id, order_id, None)
+        .await
+        .map_err(|err| ApiError::internal(err, None))?;
 
     for item in rows.iter() {
         if item.quantity <= 0 {
@@ -311,3 +716,39 @@ struct LegacyReservationRow {
     product_id: Uuid,
     quantity: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{candidate_locations, LocationStrategy, ReservationItemPayload};
+    use uuid::Uuid;
+
+    #[test]
+    fn preferred_strategy_only_tries_the_preferred_location() {
+        let (a, b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let stock = [(a, 1), (b, 50)];
+        assert_eq!(candidate_locations(Some(a), LocationStrategy::Preferred, &stock), vec![(a, 1)]);
+        assert!(candidate_locations(None, LocationStrategy::Preferred, &stock).is_empty());
+    }
+
+    #[test]
+    fn fallback_tries_preferred_then_most_stocked() {
+        let (a, b, c) = (Uuid::from_u128(1), Uuid::from_u128(2), Uuid::from_u128(3));
+        let stock = [(a, 5), (b, 20), (c, 8)];
+        assert_eq!(candidate_locations(Some(c), LocationStrategy::Fallback, &stock), vec![(c, 8), (b, 20), (a, 5)]);
+        // A preferred location without stock rows is still tried first (with nothing available).
+        let d = Uuid::from_u128(4);
+        assert_eq!(candidate_locations(Some(d), LocationStrategy::Fallback, &stock)[0], (d, 0));
+    }
+
+    #[test]
+    fn weighed_lines_reserve_thousandths_of_their_unit() {
+        let line: ReservationItemPayload =
+            serde_json::from_value(serde_json::json!({ "product_id": Uuid::nil(), "measured_quantity": "1.2345" })).unwrap();
+        assert_eq!(line.stock_units(None).unwrap(), 1235);
+        let counted: ReservationItemPayload = serde_json::from_value(serde_json::json!({ "product_id": Uuid::nil(), "quantity": 3 })).unwrap();
+        assert_eq!(counted.stock_units(None).unwrap(), 3);
+        let empty: ReservationItemPayload =
+            serde_json::from_value(serde_json::json!({ "product_id": Uuid::nil(), "measured_quantity": "0.0004" })).unwrap();
+        assert!(empty.stock_units(None).is_err());
+    }
+}
@@ -0,0 +1,610 @@
+//! Serial and lot tracking for products whose `products.tracking` is `serial` or `lot`.
+//!
+//! Serials and lots are captured when stock is received (`POST /inventory/receive`) and when an
+//! order reserves stock (`serial_numbers` / `lot_code` on each reservation line). A reserved
+//! serial cannot be claimed by another order, and becomes `sold` when `order.completed` arrives.
+//! Lot-tracked sales draw down lots first-expiry-first-out (the captured lot first), recording
+//! the order and customer per lot so `GET /inventory/lots/recall` can list who bought it.
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{DateTime, NaiveDate, Utc};
+use common_db::{db_error, query, query_as, query_scalar};
+use common_http_errors::ApiError;
+use common_security::{ensure_capability, Capability, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+const MAX_SERIAL_LEN: usize = 100;
+const MAX_LOT_CODE_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackingMode {
+    None,
+    Serial,
+    Lot,
+}
+
+impl TrackingMode {
+    fn parse(raw: Option<&str>) -> Self {
+        match raw {
+            Some("serial") => Self::Serial,
+            Some("lot") => Self::Lot,
+            _ => Self::None,
+        }
+    }
+}
+
+/// The product's tracking mode; products unknown to the catalog are untracked.
+pub async fn product_tracking(conn: &mut sqlx::PgConnection, tenant_id: Uuid, product_id: Uuid) -> Result<TrackingMode, sqlx::Error> {
+    let raw: Option<String> = query_scalar::<String>("SELECT tracking FROM products WHERE tenant_id = $1 AND id = $2")
+        .bind(tenant_id)
+        .bind(product_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(TrackingMode::parse(raw.as_deref()))
+}
+
+fn bad_request(code: &'static str, trace_id: Option<Uuid>, message: String) -> ApiError {
+    ApiError::BadRequest { code, trace_id, message: Some(message) }
+}
+
+/// Trim serials, dropping blanks; rejects duplicates and over-long values.
+pub fn normalize_serials(raw: &[String], trace_id: Option<Uuid>) -> Result<Vec<String>, ApiError> {
+    let mut serials: Vec<String> = Vec::with_capacity(raw.len());
+    for serial in raw.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        if serial.chars().count() > MAX_SERIAL_LEN {
+            return Err(bad_request("invalid_serial_number", trace_id, format!("serial numbers are limited to {MAX_SERIAL_LEN} characters")));
+        }
+        if serials.iter().any(|s| s == serial) {
+            return Err(bad_request("duplicate_serial_number", trace_id, format!("serial {serial} is listed more than once")));
+        }
+        serials.push(serial.to_string());
+    }
+    Ok(serials)
+}
+
+/// Trim a lot code, treating blank as absent.
+pub fn normalize_lot_code(raw: Option<&str>, trace_id: Option<Uuid>) -> Result<Option<String>, ApiError> {
+    let Some(code) = raw.map(str::trim).filter(|c| !c.is_empty()) else { return Ok(None) };
+    if code.chars().count() > MAX_LOT_CODE_LEN {
+        return Err(bad_request("invalid_lot_code", trace_id, format!("lot_code is limited to {MAX_LOT_CODE_LEN} characters")));
+    }
+    Ok(Some(code.to_string()))
+}
+
+/// Serial or lot details captured with a stock receipt.
+#[derive(Debug, Default)]
+pub struct ReceiptTracking {
+    pub serial_numbers: Vec<String>,
+    pub lot_code: Option<String>,
+    pub expires_on: Option<NaiveDate>,
+}
+
+impl ReceiptTracking {
+    fn is_empty(&self) -> bool {
+        self.serial_numbers.is_empty() && self.lot_code.is_none() && self.expires_on.is_none()
+    }
+}
+
+/// Record received serials or lot units inside the receiving transaction. Serial-tracked
+/// receipts need exactly one serial per unit; a serial already in stock or reserved is a 409,
+/// while a previously sold serial is taken back into stock (a customer return).
+pub async fn record_receipt(
+    conn: &mut sqlx::PgConnection,
+    sec: &SecurityContext,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    quantity: i32,
+    tracking: &ReceiptTracking,
+) -> Result<(), ApiError> {
+    let mode = product_tracking(conn, sec.tenant_id, product_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    match mode {
+        TrackingMode::None if tracking.is_empty() => Ok(()),
+        TrackingMode::None => Err(bad_request("product_not_tracked", sec.trace_id, format!("product {product_id} is not serial or lot tracked"))),
+        TrackingMode::Serial => {
+            if tracking.serial_numbers.len() != quantity as usize {
+                return Err(bad_request(
+                    "serial_count_mismatch",
+                    sec.trace_id,
+                    format!("product {product_id} is serial-tracked: {quantity} units need {quantity} serial numbers, got {}", tracking.serial_numbers.len()),
+                ));
+            }
+            for serial in &tracking.serial_numbers {
+                let inserted: Option<String> = query_scalar::<String>(
+                    "INSERT INTO inventory_serials (id, tenant_id, product_id, location_id, serial_number) VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (tenant_id, product_id, serial_number) DO UPDATE
+                        SET status = 'in_stock', location_id = EXCLUDED.location_id, order_id = NULL, sold_at = NULL, received_at = NOW()
+                        WHERE inventory_serials.status = 'sold'
+                     RETURNING serial_number",
+                )
+                .bind(Uuid::new_v4())
+                .bind(sec.tenant_id)
+                .bind(product_id)
+                .bind(location_id)
+                .bind(serial)
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+                if inserted.is_none() {
+                    return Err(ApiError::Conflict {
+                        code: "serial_in_stock",
+                        trace_id: sec.trace_id,
+                        message: Some(format!("serial {serial} is already in stock")),
+                    });
+                }
+            }
+            Ok(())
+        }
+        TrackingMode::Lot => {
+            let lot_code = tracking
+                .lot_code
+                .as_deref()
+                .ok_or_else(|| bad_request("lot_code_required", sec.trace_id, format!("product {product_id} is lot-tracked: lot_code is required")))?;
+            query(
+                "INSERT INTO inventory_lots (id, tenant_id, product_id, location_id, lot_code, expires_on, quantity_received, quantity_on_hand)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                 ON CONFLICT (tenant_id, product_id, lot_code, COALESCE(location_id, '00000000-0000-0000-0000-000000000000'::uuid)) DO UPDATE
+                    SET quantity_received = inventory_lots.quantity_received + EXCLUDED.quantity_received,
+                        quantity_on_hand = inventory_lots.quantity_on_hand + EXCLUDED.quantity_on_hand,
+                        expires_on = COALESCE(EXCLUDED.expires_on, inventory_lots.expires_on)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(sec.tenant_id)
+            .bind(product_id)
+            .bind(location_id)
+            .bind(lot_code)
+            .bind(tracking.expires_on)
+            .bind(quantity)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+            Ok(())
+        }
+    }
+}
+
+/// Hold the serials scanned for one reservation line, and remember them (or the captured lot)
+/// on the reservation row for completion. Runs after the reservation row is inserted, inside
+/// the same transaction, so a failure here rolls the whole reservation back.
+#[allow(clippy::too_many_arguments)]
+pub async fn claim_for_order(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    trace_id: Option<Uuid>,
+    order_id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    serial_numbers: &[String],
+    lot_code: Option<&str>,
+) -> Result<(), ApiError> {
+    let mode = product_tracking(conn, tenant_id, product_id).await.map_err(|e| ApiError::internal(e, trace_id))?;
+    match mode {
+        TrackingMode::None if serial_numbers.is_empty() && lot_code.is_none() => return Ok(()),
+        TrackingMode::None => {
+            return Err(bad_request("product_not_tracked", trace_id, format!("product {product_id} is not serial or lot tracked")));
+        }
+        TrackingMode::Serial => {
+            if serial_numbers.len() != quantity as usize {
+                return Err(bad_request(
+                    "serials_required",
+                    trace_id,
+                    format!("product {product_id} is serial-tracked: scan one serial per unit ({quantity} needed, got {})", serial_numbers.len()),
+                ));
+            }
+            let claimed: Vec<String> = query_scalar::<String>(
+                "UPDATE inventory_serials SET status = 'reserved', order_id = $3
+                 WHERE tenant_id = $1 AND product_id = $2 AND serial_number = ANY($4) AND status = 'in_stock'
+                 RETURNING serial_number",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(order_id)
+            .bind(serial_numbers)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?;
+            if claimed.len() != serial_numbers.len() {
+                let unavailable: Vec<&str> = serial_numbers.iter().filter(|s| !claimed.contains(s)).map(String::as_str).collect();
+                return Err(ApiError::Conflict {
+                    code: "serial_unavailable",
+                    trace_id,
+                    message: Some(format!("serials not in stock: {}", unavailable.join(", "))),
+                });
+            }
+        }
+        TrackingMode::Lot => {
+            if let Some(lot_code) = lot_code {
+                let on_hand: Option<i64> = query_scalar::<Option<i64>>(
+                    "SELECT SUM(quantity_on_hand)::bigint FROM inventory_lots WHERE tenant_id = $1 AND product_id = $2 AND lot_code = $3",
+                )
+                .bind(tenant_id)
+                .bind(product_id)
+                .bind(lot_code)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|e| ApiError::internal(e, trace_id))?;
+                if on_hand.unwrap_or(0) < quantity as i64 {
+                    return Err(ApiError::Conflict {
+                        code: "lot_unavailable",
+                        trace_id,
+                        message: Some(format!("lot {lot_code} has fewer than {quantity} units of product {product_id} on hand")),
+                    });
+                }
+            }
+        }
+    }
+    query("UPDATE inventory_reservations SET serial_numbers = $4, lot_code = $5 WHERE tenant_id = $1 AND order_id = $2 AND product_id = $3")
+        .bind(tenant_id)
+        .bind(order_id)
+        .bind(product_id)
+        .bind(serial_numbers)
+        .bind(lot_code)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+    Ok(())
+}
+
+/// Put serials held by an order back in stock (release, void or expiry); `product_id` limits
+/// the release to one reservation line.
+pub async fn release_serials(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    order_id: Uuid,
+    product_id: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    let released = query(
+        "UPDATE inventory_serials SET status = 'in_stock', order_id = NULL
+         WHERE tenant_id = $1 AND order_id = $2 AND status = 'reserved' AND ($3::uuid IS NULL OR product_id = $3)",
+    )
+    .bind(tenant_id)
+    .bind(order_id)
+    .bind(product_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(released.rows_affected())
+}
+
+/// One lot's sellable stock, as considered by FEFO picking.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct LotStock {
+    pub id: Uuid,
+    pub lot_code: String,
+    pub expires_on: Option<NaiveDate>,
+    pub received_at: DateTime<Utc>,
+    pub quantity_on_hand: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LotPick {
+    pub lot_id: Uuid,
+    pub lot_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_on: Option<NaiveDate>,
+    pub quantity: i32,
+}
+
+/// First-expiry-first-out picks for `quantity` units. Lots expiring earliest go first, lots
+/// without an expiry last, ties broken by receipt order; `preferred` (a lot code captured at
+/// the till) is taken before anything else. Lots that expired before `today` are skipped.
+/// Returns the picks and the shortfall when the lots cannot cover the quantity.
+pub fn fefo_pick(lots: &[LotStock], quantity: i32, preferred: Option<&str>, today: NaiveDate) -> (Vec<LotPick>, i32) {
+    let mut candidates: Vec<&LotStock> = lots
+        .iter()
+        .filter(|lot| lot.quantity_on_hand > 0 && lot.expires_on.is_none_or(|d| d >= today))
+        .collect();
+    candidates.sort_by_key(|lot| (preferred != Some(lot.lot_code.as_str()), lot.expires_on.is_none(), lot.expires_on, lot.received_at));
+    let mut remaining = quantity.max(0);
+    let mut picks = Vec::new();
+    for lot in candidates {
+        if remaining == 0 {
+            break;
+        }
+        let take = remaining.min(lot.quantity_on_hand);
+        picks.push(LotPick { lot_id: lot.id, lot_code: lot.lot_code.clone(), expires_on: lot.expires_on, quantity: take });
+        remaining -= take;
+    }
+    (picks, remaining)
+}
+
+/// Lots of one product with stock on hand at the location (`None` for legacy single-row stock),
+/// locked for the rest of the transaction when `for_update` is set.
+async fn sellable_lots(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    for_update: bool,
+) -> Result<Vec<LotStock>, sqlx::Error> {
+    let sql = format!(
+        "SELECT id, lot_code, expires_on, received_at, quantity_on_hand FROM inventory_lots
+         WHERE tenant_id = $1 AND product_id = $2 AND location_id IS NOT DISTINCT FROM $3 AND quantity_on_hand > 0
+         ORDER BY id{}",
+        if for_update { " FOR UPDATE" } else { "" }
+    );
+    query_as::<LotStock>(&sql)
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .fetch_all(&mut *conn)
+        .await
+}
+
+/// Completion of a sale line: mark the order's reserved serials sold, or draw the quantity down
+/// from lots (FEFO, captured lot first) and record each lot sale for recalls.
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(any(feature = "kafka", feature = "kafka-producer")), allow(dead_code))]
+pub async fn record_sale(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    order_id: Uuid,
+    customer_id: Option<Uuid>,
+    product_id: Uuid,
+    quantity: i32,
+    location_id: Option<Uuid>,
+    lot_code: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    match product_tracking(conn, tenant_id, product_id).await? {
+        TrackingMode::None => {}
+        TrackingMode::Serial => {
+            query(
+                "UPDATE inventory_serials SET status = 'sold', sold_at = NOW()
+                 WHERE tenant_id = $1 AND order_id = $2 AND product_id = $3 AND status = 'reserved'",
+            )
+            .bind(tenant_id)
+            .bind(order_id)
+            .bind(product_id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        TrackingMode::Lot => {
+            let lots = sellable_lots(conn, tenant_id, product_id, location_id, true).await?;
+            let (picks, shortfall) = fefo_pick(&lots, quantity, lot_code, Utc::now().date_naive());
+            if shortfall > 0 {
+                tracing::warn!(order_id = %order_id, tenant_id = %tenant_id, product_id = %product_id, shortfall, "Lot stock does not cover completed sale");
+            }
+            for pick in picks {
+                query("UPDATE inventory_lots SET quantity_on_hand = quantity_on_hand - $3 WHERE tenant_id = $1 AND id = $2")
+                    .bind(tenant_id)
+                    .bind(pick.lot_id)
+                    .bind(pick.quantity)
+                    .execute(&mut *conn)
+                    .await?;
+                query(
+                    "INSERT INTO inventory_lot_sales (id, tenant_id, lot_id, product_id, order_id, customer_id, quantity)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                )
+                .bind(Uuid::new_v4())
+                .bind(tenant_id)
+                .bind(pick.lot_id)
+                .bind(product_id)
+                .bind(order_id)
+                .bind(customer_id)
+                .bind(pick.quantity)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LotsQuery {
+    pub product_id: Uuid,
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LotRecord {
+    pub lot_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub lot_code: String,
+    pub expires_on: Option<NaiveDate>,
+    pub quantity_received: i32,
+    pub quantity_on_hand: i32,
+    pub received_at: DateTime<Utc>,
+}
+
+/// `GET /inventory/lots`: every lot of a product, soonest expiry first.
+pub async fn list_lots(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<LotsQuery>,
+) -> Result<Json<Vec<LotRecord>>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryView)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let lots = query_as::<LotRecord>(
+        "SELECT id AS lot_id, product_id, location_id, lot_code, expires_on, quantity_received, quantity_on_hand, received_at
+         FROM inventory_lots
+         WHERE tenant_id = $1 AND product_id = $2 AND ($3::uuid IS NULL OR location_id = $3)
+         ORDER BY expires_on NULLS LAST, received_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(params.product_id)
+    .bind(params.location_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(lots))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PickQuery {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    /// Multi-location only; defaults to legacy single-row stock.
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PickResponse {
+    pub product_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub quantity: i32,
+    pub picks: Vec<LotPick>,
+    /// Units the unexpired lots cannot cover.
+    pub shortfall: i32,
+}
+
+/// `GET /inventory/lots/pick`: which lots to pull `quantity` units from, first expiry first.
+pub async fn pick_lots(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<PickQuery>,
+) -> Result<Json<PickResponse>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryView)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_view", trace_id: sec.trace_id })?;
+    if params.quantity <= 0 {
+        return Err(bad_request("invalid_quantity", sec.trace_id, "quantity must be greater than zero".into()));
+    }
+    let location_id = params.location_id.filter(|_| state.multi_location_enabled);
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let lots = sellable_lots(&mut tx, sec.tenant_id, params.product_id, location_id, false)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let (picks, shortfall) = fefo_pick(&lots, params.quantity, None, Utc::now().date_naive());
+    Ok(Json(PickResponse { product_id: params.product_id, location_id, quantity: params.quantity, picks, shortfall }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecallQuery {
+    pub product_id: Uuid,
+    pub lot_code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecallCustomer {
+    pub customer_id: Uuid,
+    pub order_ids: Vec<Uuid>,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecallReport {
+    pub product_id: Uuid,
+    pub lot_code: String,
+    /// The lot at each location it was received into.
+    pub lots: Vec<LotRecord>,
+    pub units_sold: i64,
+    pub customers: Vec<RecallCustomer>,
+    /// Sales of the lot with no customer attached (walk-in sales).
+    pub anonymous_orders: Vec<Uuid>,
+}
+
+/// `GET /inventory/lots/recall`: who bought units of a lot, for product recalls.
+pub async fn recall_report(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<RecallQuery>,
+) -> Result<Json<RecallReport>, ApiError> {
+    ensure_capability(&sec, Capability::InventoryAdjust)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "inventory_adjust", trace_id: sec.trace_id })?;
+    let lot_code = normalize_lot_code(Some(&params.lot_code), sec.trace_id)?
+        .ok_or_else(|| bad_request("lot_code_required", sec.trace_id, "lot_code is required".into()))?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let lots = query_as::<LotRecord>(
+        "SELECT id AS lot_id, product_id, location_id, lot_code, expires_on, quantity_received, quantity_on_hand, received_at
+         FROM inventory_lots WHERE tenant_id = $1 AND product_id = $2 AND lot_code = $3 ORDER BY received_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(params.product_id)
+    .bind(&lot_code)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    if lots.is_empty() {
+        return Err(ApiError::NotFound { code: "lot_not_found", trace_id: sec.trace_id });
+    }
+    let lot_ids: Vec<Uuid> = lots.iter().map(|l| l.lot_id).collect();
+    let rows = query(
+        "SELECT customer_id, order_id, SUM(quantity)::bigint AS quantity FROM inventory_lot_sales
+         WHERE tenant_id = $1 AND lot_id = ANY($2)
+         GROUP BY customer_id, order_id
+         ORDER BY customer_id NULLS LAST, order_id",
+    )
+    .bind(sec.tenant_id)
+    .bind(&lot_ids)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let mut units_sold = 0i64;
+    let mut customers: Vec<RecallCustomer> = Vec::new();
+    let mut anonymous_orders = Vec::new();
+    for row in rows {
+        let (customer_id, order_id, quantity): (Option<Uuid>, Uuid, i64) = (row.get("customer_id"), row.get("order_id"), row.get("quantity"));
+        units_sold += quantity;
+        match customer_id {
+            Some(customer_id) => match customers.last_mut() {
+                Some(last) if last.customer_id == customer_id => {
+                    last.order_ids.push(order_id);
+                    last.quantity += quantity;
+                }
+                _ => customers.push(RecallCustomer { customer_id, order_ids: vec![order_id], quantity }),
+            },
+            None => anonymous_orders.push(order_id),
+        }
+    }
+    Ok(Json(RecallReport { product_id: params.product_id, lot_code, lots, units_sold, customers, anonymous_orders }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn lot(n: u128, code: &str, expires: Option<(i32, u32, u32)>, received_day: u32, on_hand: i32) -> LotStock {
+        LotStock {
+            id: Uuid::from_u128(n),
+            lot_code: code.into(),
+            expires_on: expires.and_then(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d)),
+            received_at: Utc.with_ymd_and_hms(2026, 1, received_day, 9, 0, 0).unwrap(),
+            quantity_on_hand: on_hand,
+        }
+    }
+
+    #[test]
+    fn fefo_takes_earliest_expiry_first_and_skips_expired() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let lots = [
+            lot(1, "NOEXP", None, 1, 10),
+            lot(2, "LATE", Some((2026, 6, 1)), 2, 4),
+            lot(3, "SOON", Some((2026, 3, 15)), 3, 3),
+            lot(4, "GONE", Some((2026, 2, 1)), 1, 50),
+        ];
+        let (picks, shortfall) = fefo_pick(&lots, 9, None, today);
+        let taken: Vec<(&str, i32)> = picks.iter().map(|p| (p.lot_code.as_str(), p.quantity)).collect();
+        assert_eq!(taken, vec![("SOON", 3), ("LATE", 4), ("NOEXP", 2)]);
+        assert_eq!(shortfall, 0);
+
+        let (_, shortfall) = fefo_pick(&lots, 20, None, today);
+        assert_eq!(shortfall, 3);
+    }
+
+    #[test]
+    fn captured_lot_is_picked_before_fefo_order() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let lots = [lot(1, "A", Some((2026, 4, 1)), 1, 5), lot(2, "B", Some((2026, 9, 1)), 2, 2)];
+        let (picks, shortfall) = fefo_pick(&lots, 3, Some("B"), today);
+        let taken: Vec<(&str, i32)> = picks.iter().map(|p| (p.lot_code.as_str(), p.quantity)).collect();
+        assert_eq!(taken, vec![("B", 2), ("A", 1)]);
+        assert_eq!(shortfall, 0);
+    }
+
+    #[test]
+    fn serials_are_trimmed_and_must_be_unique() {
+        let serials = normalize_serials(&[" SN1 ".into(), "".into(), "SN2".into()], None).unwrap();
+        assert_eq!(serials, vec!["SN1", "SN2"]);
+        assert!(normalize_serials(&["SN1".into(), "SN1 ".into()], None).is_err());
+        assert_eq!(normalize_lot_code(Some("  "), None).unwrap(), None);
+    }
+}
@@ -0,0 +1,487 @@
+//! Inventory valuation: cost layers, cost of goods sold and the stock valuation report.
+//!
+//! Stock received through `POST /inventory/receive` opens a cost layer at the receipt's
+//! `unit_cost`. Customer returns and count increases open layers at the current average cost.
+//! Sales and count decreases draw layers down oldest first. Each draw is recorded in
+//! `inventory_cost_consumptions` at a unit cost that depends on the tenant's method:
+//! - `fifo`: the cost of the layer drawn from.
+//! - `weighted_average`: the moving average of the stock on hand.
+//!
+//! The value on hand at a date is the cost received by then less the cost consumed by then, so
+//! `GET /inventory/valuation?as_of=` can look back without snapshots. Changing the method only
+//! affects later draws.
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::Json;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDate, Utc};
+use common_db::{db_error, query, query_as, query_scalar};
+use common_events::CogsLine;
+use common_http_errors::ApiError;
+use common_money::normalize_scale;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+/// Decimal places kept on per-unit costs. Weighed stock is costed per thousandth of a kg/lb,
+/// so cents alone would round most of it to zero.
+const UNIT_COST_SCALE: i64 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValuationMethod {
+    Fifo,
+    WeightedAverage,
+}
+
+impl ValuationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fifo => "fifo",
+            Self::WeightedAverage => "weighted_average",
+        }
+    }
+
+    fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "fifo" => Some(Self::Fifo),
+            "weighted_average" => Some(Self::WeightedAverage),
+            _ => None,
+        }
+    }
+}
+
+/// What opened a cost layer or drew one down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostSource {
+    Receipt,
+    Return,
+    Count,
+    Sale,
+}
+
+impl CostSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Receipt => "receipt",
+            Self::Return => "return",
+            Self::Count => "count",
+            Self::Sale => "sale",
+        }
+    }
+}
+
+/// A layer with stock left, as considered when drawing.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct CostLayer {
+    pub id: Uuid,
+    pub remaining: i32,
+    pub unit_cost: BigDecimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerDraw {
+    pub layer_id: Uuid,
+    pub quantity: i32,
+    pub unit_cost: BigDecimal,
+}
+
+/// Stock on hand of one product at one location, and what it cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnHand {
+    pub quantity: i64,
+    pub value: BigDecimal,
+}
+
+impl OnHand {
+    /// Moving average unit cost; `None` with nothing on hand.
+    pub fn average_cost(&self) -> Option<BigDecimal> {
+        (self.quantity > 0).then(|| (&self.value / BigDecimal::from(self.quantity)).round(UNIT_COST_SCALE))
+    }
+}
+
+/// Draw `quantity` units from `layers` (oldest first), at each layer's own cost or, when
+/// `unit_cost` is given, at that cost throughout. Returns the draws and the units the layers
+/// cannot cover.
+pub fn draw(layers: &[CostLayer], quantity: i32, unit_cost: Option<&BigDecimal>) -> (Vec<LayerDraw>, i32) {
+    let mut remaining = quantity.max(0);
+    let mut draws = Vec::new();
+    for layer in layers.iter().filter(|layer| layer.remaining > 0) {
+        if remaining == 0 {
+            break;
+        }
+        let take = remaining.min(layer.remaining);
+        draws.push(LayerDraw { layer_id: layer.id, quantity: take, unit_cost: unit_cost.unwrap_or(&layer.unit_cost).clone() });
+        remaining -= take;
+    }
+    (draws, remaining)
+}
+
+/// Total cost of `draws`, to the cent.
+pub fn draws_cost(draws: &[LayerDraw]) -> BigDecimal {
+    normalize_scale(&draws.iter().map(|d| &d.unit_cost * BigDecimal::from(d.quantity)).fold(BigDecimal::zero(), |sum, cost| sum + cost))
+}
+
+/// The tenant's valuation method; FIFO until one is chosen.
+pub async fn tenant_method(conn: &mut sqlx::PgConnection, tenant_id: Uuid) -> Result<ValuationMethod, sqlx::Error> {
+    let raw: Option<String> = query_scalar::<String>("SELECT method FROM inventory_valuation_settings WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(raw.as_deref().and_then(ValuationMethod::parse).unwrap_or(ValuationMethod::Fifo))
+}
+
+async fn on_hand(conn: &mut sqlx::PgConnection, tenant_id: Uuid, product_id: Uuid, location_id: Option<Uuid>) -> Result<OnHand, sqlx::Error> {
+    let row = query(
+        "SELECT COALESCE(SUM(quantity), 0)::bigint AS quantity, COALESCE(SUM(value), 0) AS value FROM (
+             SELECT quantity, quantity * unit_cost AS value FROM inventory_cost_layers
+             WHERE tenant_id = $1 AND product_id = $2 AND location_id IS NOT DISTINCT FROM $3
+             UNION ALL
+             SELECT -quantity, -(quantity * unit_cost) FROM inventory_cost_consumptions
+             WHERE tenant_id = $1 AND product_id = $2 AND location_id IS NOT DISTINCT FROM $3
+         ) movements",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .bind(location_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(OnHand { quantity: row.get("quantity"), value: row.get("value") })
+}
+
+/// Open a layer of `quantity` units. Without a `unit_cost`, the stock is valued at the current
+/// average cost, else at the latest layer's cost, else at zero. Returns the unit cost used.
+#[allow(clippy::too_many_arguments)]
+pub async fn open_layer(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    source: CostSource,
+    source_id: Option<Uuid>,
+    quantity: i32,
+    unit_cost: Option<&BigDecimal>,
+) -> Result<BigDecimal, sqlx::Error> {
+    let unit_cost = match unit_cost {
+        Some(cost) => cost.round(UNIT_COST_SCALE),
+        None => match on_hand(conn, tenant_id, product_id, location_id).await?.average_cost() {
+            Some(average) => average,
+            None => query_scalar::<BigDecimal>(
+                "SELECT unit_cost FROM inventory_cost_layers
+                 WHERE tenant_id = $1 AND product_id = $2 AND location_id IS NOT DISTINCT FROM $3
+                 ORDER BY received_at DESC, id DESC LIMIT 1",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(location_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or_else(BigDecimal::zero),
+        },
+    };
+    query(
+        "INSERT INTO inventory_cost_layers (id, tenant_id, product_id, location_id, source, source_id, quantity, remaining, unit_cost)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(product_id)
+    .bind(location_id)
+    .bind(source.as_str())
+    .bind(source_id)
+    .bind(quantity)
+    .bind(&unit_cost)
+    .execute(&mut *conn)
+    .await?;
+    Ok(unit_cost)
+}
+
+/// Draw `quantity` units at the tenant's method and record the consumption. Returns the draws
+/// and the units no layer covered.
+#[allow(clippy::too_many_arguments)]
+pub async fn consume(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    method: ValuationMethod,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    source: CostSource,
+    source_id: Option<Uuid>,
+    quantity: i32,
+) -> Result<(Vec<LayerDraw>, i32), sqlx::Error> {
+    let layers = query_as::<CostLayer>(
+        "SELECT id, remaining, unit_cost FROM inventory_cost_layers
+         WHERE tenant_id = $1 AND product_id = $2 AND location_id IS NOT DISTINCT FROM $3 AND remaining > 0
+         ORDER BY received_at, id
+         FOR UPDATE",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .bind(location_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    let average = match method {
+        ValuationMethod::Fifo => None,
+        ValuationMethod::WeightedAverage => on_hand(conn, tenant_id, product_id, location_id).await?.average_cost(),
+    };
+    let (draws, uncosted) = draw(&layers, quantity, average.as_ref());
+    for d in &draws {
+        query("UPDATE inventory_cost_layers SET remaining = remaining - $3 WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(d.layer_id)
+            .bind(d.quantity)
+            .execute(&mut *conn)
+            .await?;
+        query(
+            "INSERT INTO inventory_cost_consumptions (id, tenant_id, product_id, location_id, layer_id, source, source_id, quantity, unit_cost)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(d.layer_id)
+        .bind(source.as_str())
+        .bind(source_id)
+        .bind(d.quantity)
+        .bind(&d.unit_cost)
+        .execute(&mut *conn)
+        .await?;
+    }
+    if uncosted > 0 {
+        tracing::warn!(tenant_id = %tenant_id, product_id = %product_id, uncosted, "Cost layers do not cover stock drawn");
+    }
+    Ok((draws, uncosted))
+}
+
+/// Cost layers for a stock correction of `delta` units: receipts open a layer at their cost,
+/// count increases one at the average cost, and count decreases draw stock down.
+#[allow(clippy::too_many_arguments)]
+pub async fn record_adjustment(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    adjustment_id: Uuid,
+    receipt: bool,
+    delta: i32,
+    unit_cost: Option<&BigDecimal>,
+) -> Result<(), sqlx::Error> {
+    if delta > 0 {
+        let source = if receipt { CostSource::Receipt } else { CostSource::Count };
+        open_layer(conn, tenant_id, product_id, location_id, source, Some(adjustment_id), delta, unit_cost).await?;
+    } else if delta < 0 {
+        let method = tenant_method(conn, tenant_id).await?;
+        consume(conn, tenant_id, method, product_id, location_id, CostSource::Count, Some(adjustment_id), -delta).await?;
+    }
+    Ok(())
+}
+
+/// Cost of one completed order line's stock movement: a sale draws `quantity` units down, a
+/// refund (negative `quantity`) puts them back as a return layer at the average cost.
+#[cfg_attr(not(any(feature = "kafka", feature = "kafka-producer")), allow(dead_code))]
+pub async fn cost_order_line(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    method: ValuationMethod,
+    order_id: Uuid,
+    product_id: Uuid,
+    location_id: Option<Uuid>,
+    quantity: i32,
+) -> Result<CogsLine, sqlx::Error> {
+    let (cost, uncosted_quantity) = if quantity >= 0 {
+        let (draws, uncosted) = consume(conn, tenant_id, method, product_id, location_id, CostSource::Sale, Some(order_id), quantity).await?;
+        (draws_cost(&draws), uncosted)
+    } else {
+        let unit_cost = open_layer(conn, tenant_id, product_id, location_id, CostSource::Return, Some(order_id), -quantity, None).await?;
+        (normalize_scale(&(unit_cost * BigDecimal::from(quantity))), 0)
+    };
+    Ok(CogsLine { product_id, location_id, quantity, cost, uncosted_quantity })
+}
+
+/// One product's stock value at one location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValuationLine {
+    pub product_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub quantity: i64,
+    pub value: BigDecimal,
+    /// Average unit cost of the stock; absent with nothing on hand.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_cost: Option<BigDecimal>,
+}
+
+/// Stock value per product and location just before `until`, limited to `location_id` when given.
+pub async fn valuation_lines(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    location_id: Option<Uuid>,
+    until: DateTime<Utc>,
+) -> Result<Vec<ValuationLine>, sqlx::Error> {
+    let rows = query(
+        "SELECT product_id, location_id, SUM(quantity)::bigint AS quantity, SUM(value) AS value FROM (
+             SELECT product_id, location_id, quantity, quantity * unit_cost AS value FROM inventory_cost_layers
+             WHERE tenant_id = $1 AND ($2::uuid IS NULL OR location_id = $2) AND received_at < $3
+             UNION ALL
+             SELECT product_id, location_id, -quantity, -(quantity * unit_cost) FROM inventory_cost_consumptions
+             WHERE tenant_id = $1 AND ($2::uuid IS NULL OR location_id = $2) AND consumed_at < $3
+         ) movements
+         GROUP BY product_id, location_id
+         HAVING SUM(quantity) <> 0
+         ORDER BY product_id, location_id NULLS FIRST",
+    )
+    .bind(tenant_id)
+    .bind(location_id)
+    .bind(until)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let on_hand = OnHand { quantity: row.get("quantity"), value: row.get("value") };
+            ValuationLine {
+                product_id: row.get("product_id"),
+                location_id: row.get("location_id"),
+                quantity: on_hand.quantity,
+                value: normalize_scale(&on_hand.value),
+                unit_cost: on_hand.average_cost(),
+            }
+        })
+        .collect())
+}
+
+fn ensure_role(sec: &SecurityContext, allowed: &[Role], role: &'static str) -> Result<(), ApiError> {
+    if sec.roles.iter().any(|r| allowed.contains(r)) {
+        Ok(())
+    } else {
+        Err(ApiError::ForbiddenMissingRole { role, trace_id: sec.trace_id })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ValuationQuery {
+    /// Multi-location only; all locations when absent.
+    pub location_id: Option<Uuid>,
+    /// Value at the end of this day (UTC); defaults to now.
+    pub as_of: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValuationReport {
+    pub method: ValuationMethod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<Uuid>,
+    pub lines: Vec<ValuationLine>,
+    pub total_value: BigDecimal,
+}
+
+/// `GET /inventory/valuation`: stock value per product and location.
+pub async fn get_valuation(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ValuationQuery>,
+) -> Result<Json<ValuationReport>, ApiError> {
+    ensure_role(&sec, &[Role::SuperAdmin, Role::Admin, Role::Manager], "admin_or_manager")?;
+    let location_id = params.location_id.filter(|_| state.multi_location_enabled);
+    let until = match params.as_of {
+        Some(day) => day
+            .succ_opt()
+            .and_then(|next| next.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc())
+            .ok_or_else(|| ApiError::BadRequest { code: "invalid_as_of", trace_id: sec.trace_id, message: Some("as_of is out of range".into()) })?,
+        None => Utc::now(),
+    };
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let method = tenant_method(&mut tx, sec.tenant_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let lines = valuation_lines(&mut tx, sec.tenant_id, location_id, until).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let total_value = lines.iter().fold(BigDecimal::zero(), |sum, line| sum + &line.value);
+    Ok(Json(ValuationReport { method, as_of: params.as_of, location_id, lines, total_value }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValuationMethodBody {
+    pub method: ValuationMethod,
+}
+
+/// `GET /inventory/valuation/method`
+pub async fn get_valuation_method(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<ValuationMethodBody>, ApiError> {
+    ensure_role(&sec, &[Role::SuperAdmin, Role::Admin, Role::Manager], "admin_or_manager")?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    let method = tenant_method(&mut tx, sec.tenant_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(ValuationMethodBody { method }))
+}
+
+/// `PUT /inventory/valuation/method`: applies to stock drawn from now on.
+pub async fn set_valuation_method(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(body): Json<ValuationMethodBody>,
+) -> Result<Json<ValuationMethodBody>, ApiError> {
+    ensure_role(&sec, &[Role::SuperAdmin, Role::Admin], "admin")?;
+    let mut tx = state.db.begin_for(&sec).await.map_err(|e| db_error(e, sec.trace_id))?;
+    query(
+        "INSERT INTO inventory_valuation_settings (tenant_id, method) VALUES ($1, $2)
+         ON CONFLICT (tenant_id) DO UPDATE SET method = EXCLUDED.method, updated_at = NOW()",
+    )
+    .bind(sec.tenant_id)
+    .bind(body.method.as_str())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tracing::info!(tenant_id = %sec.tenant_id, method = body.method.as_str(), "Inventory valuation method changed");
+    Ok(Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn layer(n: u128, remaining: i32, unit_cost: &str) -> CostLayer {
+        CostLayer { id: Uuid::from_u128(n), remaining, unit_cost: dec(unit_cost) }
+    }
+
+    #[test]
+    fn fifo_draws_oldest_layers_at_their_cost() {
+        let layers = [layer(1, 3, "2.00"), layer(2, 0, "9.99"), layer(3, 10, "2.50")];
+        let (draws, uncosted) = draw(&layers, 5, None);
+        assert_eq!(uncosted, 0);
+        assert_eq!(
+            draws.iter().map(|d| (d.layer_id.as_u128(), d.quantity)).collect::<Vec<_>>(),
+            [(1, 3), (3, 2)]
+        );
+        assert_eq!(draws_cost(&draws), dec("11.00"));
+    }
+
+    #[test]
+    fn weighted_average_draws_at_the_average() {
+        let on_hand = OnHand { quantity: 13, value: dec("31.00") };
+        let average = on_hand.average_cost().unwrap();
+        assert_eq!(average, dec("2.384615"));
+        let (draws, _) = draw(&[layer(1, 3, "2.00"), layer(3, 10, "2.50")], 5, Some(&average));
+        assert!(draws.iter().all(|d| d.unit_cost == average));
+        assert_eq!(draws_cost(&draws), dec("11.92"));
+    }
+
+    #[test]
+    fn uncovered_units_are_reported() {
+        let (draws, uncosted) = draw(&[layer(1, 2, "1.00")], 5, None);
+        assert_eq!(draws.len(), 1);
+        assert_eq!(uncosted, 3);
+        assert_eq!(OnHand { quantity: 0, value: BigDecimal::zero() }.average_cost(), None);
+    }
+}
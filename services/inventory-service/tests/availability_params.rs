@@ -0,0 +1,48 @@
+//! Request validation for GET /inventory/availability; rejected before any database access.
+
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::{routing::get, Router};
+use inventory_service::get_availability;
+use tower::ServiceExt;
+mod test_utils;
+use test_utils::lazy_app_state;
+
+async fn availability(query: &str) -> (StatusCode, Option<String>) {
+    let app = Router::new().route("/inventory/availability", get(get_availability)).with_state(lazy_app_state());
+    let mut req = Request::builder()
+        .uri(format!("/inventory/availability{query}"))
+        .method("GET")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let h = req.headers_mut();
+    h.insert("X-Tenant-ID", HeaderValue::from_static("11111111-1111-1111-1111-111111111111"));
+    h.insert("X-Roles", HeaderValue::from_static("cashier"));
+    h.insert("X-User-ID", HeaderValue::from_static("22222222-2222-2222-2222-222222222222"));
+    let resp = app.oneshot(req).await.unwrap();
+    let code = resp.headers().get("X-Error-Code").and_then(|v| v.to_str().ok()).map(str::to_string);
+    (resp.status(), code)
+}
+
+#[tokio::test]
+async fn missing_product_ids_rejected() {
+    let (status, code) = availability("").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code.as_deref(), Some("missing_product_ids"));
+}
+
+#[tokio::test]
+async fn malformed_product_id_rejected() {
+    let (status, code) = availability("?product_ids=11111111-1111-1111-1111-111111111111,nope").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code.as_deref(), Some("invalid_product_id"));
+}
+
+#[tokio::test]
+async fn oversized_product_list_rejected() {
+    let ids: Vec<String> = (0..=inventory_service::MAX_AVAILABILITY_PRODUCTS as u128)
+        .map(|n| uuid::Uuid::from_u128(n + 1).to_string())
+        .collect();
+    let (status, code) = availability(&format!("?product_ids={}", ids.join(","))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code.as_deref(), Some("too_many_product_ids"));
+}
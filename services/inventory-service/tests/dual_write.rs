@@ -0,0 +1,74 @@
+//! Dual-write checks against Postgres: divergence is counted per tenant and source, and healing
+//! resets the legacy row to the per-location sum. Needs Postgres: set ENABLE_ITESTS=1 (and
+//! TEST_DATABASE_URL to skip the container).
+
+use std::sync::Arc;
+
+use common_observability::InventoryMetrics;
+use common_test_fixtures::{default_location, itests_enabled, TestPostgres};
+use inventory_service::dual_write::DualWriteCheck;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A product with `legacy` units in `inventory` and `located` at the default location.
+async fn stock(db: &PgPool, tenant: Uuid, legacy: i32, located: i32) -> Uuid {
+    let product = Uuid::new_v4();
+    let location = default_location(db, tenant).await.unwrap();
+    sqlx::query("INSERT INTO inventory (product_id, tenant_id, quantity) VALUES ($1, $2, $3)")
+        .bind(product)
+        .bind(tenant)
+        .bind(legacy)
+        .execute(db)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO inventory_items (tenant_id, product_id, location_id, quantity) VALUES ($1, $2, $3, $4)")
+        .bind(tenant)
+        .bind(product)
+        .bind(location)
+        .bind(located)
+        .execute(db)
+        .await
+        .unwrap();
+    product
+}
+
+async fn legacy(db: &PgPool, product: Uuid) -> i32 {
+    sqlx::query_scalar("SELECT quantity FROM inventory WHERE product_id = $1").bind(product).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn divergence_is_counted_per_tenant_and_healed_when_enabled() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["inventory-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+    let diverged = stock(db, tenant, 7, 10).await;
+    stock(db, tenant, 4, 4).await;
+    let other_diverged = stock(db, other, 1, 2).await;
+    let metrics = Arc::new(InventoryMetrics::new());
+    let divergence = |tenant: Uuid, source: &str| metrics.dual_write_divergence.with_label_values(&[&tenant.to_string(), source]).get();
+
+    let detect = DualWriteCheck { metrics: metrics.clone(), heal: false };
+    let report = detect.run(db, Some(tenant), "consumer").await.unwrap();
+    assert_eq!(report.divergent.len(), 1);
+    assert_eq!((report.divergent[0].product_id, report.divergent[0].legacy, report.divergent[0].total), (diverged, 7, 10));
+    assert_eq!(report.healed, 0);
+    assert_eq!(divergence(tenant, "consumer"), 1);
+    assert_eq!(divergence(other, "consumer"), 0, "a tenant-scoped check leaves other tenants alone");
+    assert_eq!(legacy(db, diverged).await, 7, "detection alone changes nothing");
+    assert_eq!(metrics.heal_latency_seconds.get_sample_count(), 0);
+
+    let heal = DualWriteCheck { metrics: metrics.clone(), heal: true };
+    let report = heal.run(db, None, "sweeper").await.unwrap();
+    assert_eq!((report.divergent.len(), report.healed), (2, 2));
+    assert_eq!((divergence(tenant, "sweeper"), divergence(other, "sweeper")), (1, 1));
+    assert_eq!(metrics.dual_write_heals.with_label_values(&[&tenant.to_string(), "healed"]).get(), 1);
+    assert_eq!(metrics.heal_latency_seconds.get_sample_count(), 2);
+    assert_eq!((legacy(db, diverged).await, legacy(db, other_diverged).await), (10, 2));
+
+    assert!(heal.run(db, None, "sweeper").await.unwrap().divergent.is_empty(), "healed rows stay consistent");
+    assert_eq!(divergence(tenant, "sweeper"), 1);
+}
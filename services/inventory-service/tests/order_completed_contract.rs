@@ -0,0 +1,50 @@
+//! Consumer side of the `order.completed` contract: every payload order-service publishes (and
+//! the legacy ones still in retention) decodes and yields the stock movements the consumer applies.
+
+use bigdecimal::BigDecimal;
+use common_events::contract::{decode_all, fixture};
+use common_events::{topics, OrderCompletedEvent};
+use common_money::measure::UnitOfMeasure;
+use inventory_service::bom::{expand, BomLine, Boms};
+use inventory_service::completion::sold_lines;
+
+#[test]
+fn every_fixture_moves_stock_by_its_lines() {
+    for (name, evt) in decode_all::<OrderCompletedEvent>() {
+        let Some(lines) = sold_lines(&evt) else {
+            assert!(evt.is_refund(), "{name}: only RMA refunds skip stock");
+            continue;
+        };
+        assert_eq!(lines.len(), evt.items.len(), "{name}");
+        for ((product_id, units), item) in lines.iter().zip(&evt.items) {
+            assert_eq!(*product_id, item.product_id, "{name}");
+            assert_eq!(units.signum(), item.quantity.signum(), "{name}: refunds return stock, sales draw it");
+        }
+        let (stock, consumed) = expand(&lines, &Boms::new()).unwrap_or_else(|| panic!("{name}: quantities overflow"));
+        assert!(consumed.is_empty(), "{name}");
+        assert_eq!(stock.len(), lines.len(), "{name}: fixtures have one line per product");
+    }
+}
+
+#[test]
+fn weighed_lines_move_thousandths() {
+    let evt = fixture(topics::ORDER_COMPLETED, "weighed_sale").decode::<OrderCompletedEvent>();
+    assert_eq!(sold_lines(&evt).unwrap(), vec![(evt.items[0].product_id, 1235)]);
+}
+
+#[test]
+fn rma_refunds_leave_stock_alone() {
+    let evt = fixture(topics::ORDER_COMPLETED, "rma_refund").decode::<OrderCompletedEvent>();
+    assert_eq!(sold_lines(&evt), None);
+}
+
+#[test]
+fn refunded_bundles_return_their_components() {
+    let evt = fixture(topics::ORDER_COMPLETED, "refund").decode::<OrderCompletedEvent>();
+    let bundle = evt.items[0].product_id;
+    let component = uuid::Uuid::new_v4();
+    let boms = Boms::from([(bundle, vec![BomLine { component_id: component, quantity: BigDecimal::from(3), uom: UnitOfMeasure::Each }])]);
+    let (stock, consumed) = expand(&sold_lines(&evt).unwrap(), &boms).unwrap();
+    assert_eq!(stock, vec![(component, -3)]);
+    assert_eq!(consumed[0].bundle_id, bundle);
+}
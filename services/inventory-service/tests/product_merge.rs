@@ -0,0 +1,115 @@
+//! `product.merged` against Postgres: stock, reservations, lots and history move from the
+//! duplicate to the canonical product, and a redelivered event changes nothing. Needs Postgres:
+//! set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use common_events::{DomainEvent, ProductMergedEvent};
+use common_test_fixtures::{itests_enabled, ProductFixture, TenantFixture, TestPostgres};
+use inventory_service::product_merge::merge_product;
+use inventory_service::valuation::record_adjustment;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "inventory-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+async fn reserve(db: &PgPool, tenant: Uuid, order: Uuid, product: Uuid, location: Option<Uuid>, quantity: i32) {
+    sqlx::query("INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, location_id) VALUES ($1, $2, $3, $4, $5)")
+        .bind(order)
+        .bind(tenant)
+        .bind(product)
+        .bind(quantity)
+        .bind(location)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+async fn lot(db: &PgPool, tenant: Uuid, product: Uuid, code: &str, on_hand: i32) {
+    sqlx::query(
+        "INSERT INTO inventory_lots (id, tenant_id, product_id, lot_code, quantity_received, quantity_on_hand) VALUES ($1, $2, $3, $4, $5, $5)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant)
+    .bind(product)
+    .bind(code)
+    .bind(on_hand)
+    .execute(db)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn merged_stock_is_added_to_the_canonical_product_once() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = TenantFixture::new().insert(db).await.unwrap().id;
+    let canonical = ProductFixture::new(tenant).stock(5).insert(db).await.unwrap();
+    let duplicate = ProductFixture::new(tenant).stock(3).insert(db).await.unwrap();
+    let location = canonical.location_id;
+
+    let (shared_order, other_order) = (Uuid::new_v4(), Uuid::new_v4());
+    reserve(db, tenant, shared_order, canonical.id, location, 1).await;
+    reserve(db, tenant, shared_order, duplicate.id, location, 2).await;
+    reserve(db, tenant, other_order, duplicate.id, location, 1).await;
+    lot(db, tenant, canonical.id, "L-100", 4).await;
+    lot(db, tenant, duplicate.id, "L-100", 2).await;
+    lot(db, tenant, duplicate.id, "L-200", 1).await;
+    let mut conn = db.acquire().await.unwrap();
+    record_adjustment(&mut conn, tenant, duplicate.id, location, Uuid::new_v4(), true, 3, Some(&BigDecimal::from(2))).await.unwrap();
+    drop(conn);
+
+    let evt = ProductMergedEvent {
+        schema_version: ProductMergedEvent::SCHEMA_VERSION,
+        tenant_id: tenant,
+        duplicate_id: duplicate.id,
+        canonical_id: canonical.id,
+        duplicate_sku: None,
+        canonical_sku: None,
+        merged_at: Utc::now(),
+    };
+    let summary = merge_product(db, &evt).await.unwrap();
+    assert_eq!((summary.reservations, summary.lots, summary.history_rows), (2, 2, 1));
+    let again = merge_product(db, &evt).await.unwrap();
+    assert_eq!(again, Default::default(), "a redelivered merge moves nothing");
+
+    let on_hand: i32 = sqlx::query_scalar("SELECT quantity FROM inventory_items WHERE tenant_id = $1 AND product_id = $2 AND location_id = $3")
+        .bind(tenant)
+        .bind(canonical.id)
+        .bind(location)
+        .fetch_one(db)
+        .await
+        .unwrap();
+    assert_eq!(on_hand, 8);
+    let left: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM inventory_items WHERE product_id = $1) + (SELECT COUNT(*) FROM inventory_reservations WHERE product_id = $1)
+              + (SELECT COUNT(*) FROM inventory_lots WHERE product_id = $1) + (SELECT COUNT(*) FROM inventory_cost_layers WHERE product_id = $1)",
+    )
+    .bind(duplicate.id)
+    .fetch_one(db)
+    .await
+    .unwrap();
+    assert_eq!(left, 0, "nothing stays on the duplicate");
+
+    let reserved: Vec<(Uuid, i32)> = sqlx::query_as("SELECT order_id, quantity FROM inventory_reservations WHERE tenant_id = $1 AND product_id = $2 ORDER BY quantity")
+        .bind(tenant)
+        .bind(canonical.id)
+        .fetch_all(db)
+        .await
+        .unwrap();
+    assert_eq!(reserved, vec![(other_order, 1), (shared_order, 3)]);
+    let lots: Vec<(String, i32)> = sqlx::query_as("SELECT lot_code, quantity_on_hand FROM inventory_lots WHERE tenant_id = $1 AND product_id = $2 ORDER BY lot_code")
+        .bind(tenant)
+        .bind(canonical.id)
+        .fetch_all(db)
+        .await
+        .unwrap();
+    assert_eq!(lots, vec![("L-100".to_string(), 6), ("L-200".to_string(), 1)]);
+}
@@ -0,0 +1,162 @@
+//! Reorder suggestions against Postgres: the job reads stock, purchase orders and analytics'
+//! sales velocity, accepting fills one draft purchase order per supplier and location, and a
+//! dismissal holds off new suggestions. Needs Postgres: set ENABLE_ITESTS=1 (and
+//! TEST_DATABASE_URL to skip the container).
+
+use chrono::{Duration, Utc};
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use inventory_service::reorder::{
+    add_to_draft_order, decide_suggestion, list_suggestions, load_purchase_orders, refresh_suggestions, suggestion_for_update, ReorderSuggestion,
+    SuggestionStatus,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const VELOCITY_DAYS: i32 = 28;
+const DISMISS_DAYS: i32 = 7;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "inventory-service", "analytics-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+/// A location with a supplier delivering in `lead_time_days`.
+async fn store(db: &PgPool, tenant: Uuid, lead_time_days: i32) -> (Uuid, Uuid) {
+    let location: Uuid = sqlx::query_scalar("INSERT INTO locations (tenant_id, code, name) VALUES ($1, 'MAIN', 'Main') RETURNING id")
+        .bind(tenant)
+        .fetch_one(db)
+        .await
+        .unwrap();
+    let supplier = Uuid::new_v4();
+    sqlx::query("INSERT INTO suppliers (id, tenant_id, name, lead_time_days) VALUES ($1, $2, 'Acme', $3)")
+        .bind(supplier)
+        .bind(tenant)
+        .bind(lead_time_days)
+        .execute(db)
+        .await
+        .unwrap();
+    (location, supplier)
+}
+
+/// `on_hand` units in stock, and `sold` units sold over the velocity window plus a sale today,
+/// which the velocity leaves out.
+async fn stock(db: &PgPool, tenant: Uuid, product: Uuid, location: Uuid, on_hand: i32, sold: i64) {
+    sqlx::query("INSERT INTO inventory_items (tenant_id, product_id, location_id, quantity) VALUES ($1, $2, $3, $4)")
+        .bind(tenant)
+        .bind(product)
+        .bind(location)
+        .bind(on_hand)
+        .execute(db)
+        .await
+        .unwrap();
+    let today = Utc::now().date_naive();
+    for (date, units) in [(today - Duration::days(1), sold), (today - Duration::days(VELOCITY_DAYS.into()), 0), (today, 500)] {
+        sqlx::query(
+            "INSERT INTO daily_product_sales (tenant_id, date, product_id, location_id, quantity, revenue, stock_units)
+             VALUES ($1, $2, $3, $4, $5, 0, $5)",
+        )
+        .bind(tenant)
+        .bind(date)
+        .bind(product)
+        .bind(location)
+        .bind(units)
+        .execute(db)
+        .await
+        .unwrap();
+    }
+}
+
+async fn policy(db: &PgPool, tenant: Uuid, product: Uuid, location: Uuid, supplier: Uuid, sql: &str) {
+    sqlx::query(&format!(
+        "INSERT INTO reorder_policies (tenant_id, product_id, location_id, supplier_id, policy, min_quantity, max_quantity, cover_days) VALUES {sql}"
+    ))
+    .bind(tenant)
+    .bind(product)
+    .bind(location)
+    .bind(supplier)
+    .execute(db)
+    .await
+    .unwrap();
+}
+
+async fn open_suggestions(db: &PgPool, tenant: Uuid) -> Vec<ReorderSuggestion> {
+    let mut conn = db.acquire().await.unwrap();
+    list_suggestions(&mut conn, tenant, SuggestionStatus::Open, None).await.unwrap()
+}
+
+#[tokio::test]
+async fn accepted_suggestions_fill_one_draft_and_count_as_on_order() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, bolts, nuts) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let (location, supplier) = store(db, tenant, 4).await;
+    // 2 a day over a 4 day lead time: 15 on hand leaves 7, at most the minimum of 10.
+    stock(db, tenant, bolts, location, 15, 56).await;
+    policy(db, tenant, bolts, location, supplier, "($1, $2, $3, $4, 'min_max', 10, 40, NULL)").await;
+    // 1 a day: 4 sold before delivery, 14 days of cover wanted.
+    stock(db, tenant, nuts, location, 6, 28).await;
+    policy(db, tenant, nuts, location, supplier, "($1, $2, $3, $4, 'days_of_cover', NULL, NULL, 14)").await;
+
+    refresh_suggestions(db, VELOCITY_DAYS, DISMISS_DAYS).await.unwrap();
+    let open = open_suggestions(db, tenant).await;
+    let quantities: Vec<_> = open.iter().map(|s| (s.product_id, s.suggested_quantity)).collect();
+    assert_eq!(quantities, [(bolts, 33), (nuts, 12)]);
+    assert_eq!((open[0].on_hand, open[0].daily_velocity, open[0].lead_time_days), (15, 2.0, 4));
+
+    let mut tx = db.begin().await.unwrap();
+    let mut draft = None;
+    for suggestion in &open {
+        let locked = suggestion_for_update(&mut tx, tenant, suggestion.id).await.unwrap().unwrap();
+        let order_id = add_to_draft_order(&mut tx, tenant, &locked, locked.suggested_quantity, None).await.unwrap();
+        assert!(draft.is_none_or(|draft| draft == order_id), "one draft per supplier and location");
+        draft = Some(order_id);
+        decide_suggestion(&mut tx, tenant, suggestion.id, SuggestionStatus::Accepted, None, Some(order_id)).await.unwrap();
+    }
+    let orders = load_purchase_orders(&mut tx, tenant, draft, None).await.unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!((orders[0].status.as_str(), orders[0].supplier_id, orders[0].lines.len()), ("draft", Some(supplier), 2));
+
+    refresh_suggestions(db, VELOCITY_DAYS, DISMISS_DAYS).await.unwrap();
+    assert!(open_suggestions(db, tenant).await.is_empty(), "the draft is on order");
+
+    // Cancelling the draft takes it off order again.
+    sqlx::query("UPDATE purchase_orders SET status = 'cancelled' WHERE tenant_id = $1").bind(tenant).execute(db).await.unwrap();
+    refresh_suggestions(db, VELOCITY_DAYS, DISMISS_DAYS).await.unwrap();
+    assert_eq!(open_suggestions(db, tenant).await.len(), 2);
+}
+
+#[tokio::test]
+async fn dismissals_hold_off_suggestions_and_recovered_stock_withdraws_them() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, product) = (Uuid::new_v4(), Uuid::new_v4());
+    let (location, supplier) = store(db, tenant, 2).await;
+    stock(db, tenant, product, location, 3, 0).await;
+    policy(db, tenant, product, location, supplier, "($1, $2, $3, $4, 'min_max', 5, 20, NULL)").await;
+
+    refresh_suggestions(db, VELOCITY_DAYS, DISMISS_DAYS).await.unwrap();
+    let open = open_suggestions(db, tenant).await;
+    assert_eq!(open.iter().map(|s| s.suggested_quantity).collect::<Vec<_>>(), [17]);
+    let mut conn = db.acquire().await.unwrap();
+    decide_suggestion(&mut conn, tenant, open[0].id, SuggestionStatus::Dismissed, None, None).await.unwrap();
+
+    refresh_suggestions(db, VELOCITY_DAYS, DISMISS_DAYS).await.unwrap();
+    assert!(open_suggestions(db, tenant).await.is_empty(), "dismissed a moment ago");
+    refresh_suggestions(db, VELOCITY_DAYS, 0).await.unwrap();
+    assert_eq!(open_suggestions(db, tenant).await.len(), 1, "the dismissal has run out");
+
+    sqlx::query("UPDATE inventory_items SET quantity = 12 WHERE tenant_id = $1").bind(tenant).execute(db).await.unwrap();
+    refresh_suggestions(db, VELOCITY_DAYS, 0).await.unwrap();
+    assert!(open_suggestions(db, tenant).await.is_empty(), "stock recovered");
+
+    sqlx::query("UPDATE inventory_items SET quantity = 0 WHERE tenant_id = $1").bind(tenant).execute(db).await.unwrap();
+    refresh_suggestions(db, VELOCITY_DAYS, 0).await.unwrap();
+    sqlx::query("DELETE FROM reorder_policies WHERE tenant_id = $1").bind(tenant).execute(db).await.unwrap();
+    refresh_suggestions(db, VELOCITY_DAYS, 0).await.unwrap();
+    assert!(open_suggestions(db, tenant).await.is_empty(), "no policy, no suggestion");
+}
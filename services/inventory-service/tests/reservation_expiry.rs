@@ -0,0 +1,100 @@
+//! Reservation expiry against Postgres with two sweepers running at once, as two replicas would:
+//! every overdue reservation is expired and restocked exactly once. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use common_db::TenantScopedPool;
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use inventory_service::reservation_expiry::expire_batch;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const BATCH_SIZE: i64 = 4;
+
+/// Run batches until one comes back short, like the sweeper; returns the order ids it expired.
+async fn sweep(db: &PgPool) -> Vec<Uuid> {
+    let mut expired = Vec::new();
+    loop {
+        let batch = expire_batch(db, false, BATCH_SIZE).await.expect("expire batch");
+        let claimed = batch.expired.len();
+        expired.extend(batch.expired.into_iter().map(|r| r.order_id));
+        if (claimed as i64) < BATCH_SIZE {
+            return expired;
+        }
+    }
+}
+
+#[tokio::test]
+async fn concurrent_sweepers_expire_and_restock_each_reservation_once() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "inventory-service"]).await.expect("migrate");
+    let state_db = TenantScopedPool::from(postgres.pool().clone());
+    let db = state_db.cross_tenant();
+    let tenant = Uuid::new_v4();
+    let products = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+    for product in products {
+        sqlx::query("INSERT INTO inventory (product_id, tenant_id, quantity) VALUES ($1, $2, 0)")
+            .bind(product)
+            .bind(tenant)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    // 30 overdue reservations of 1..=3 units spread over the products, and one still live.
+    let mut overdue = Vec::new();
+    for i in 0..30 {
+        let order = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, status, expires_at)
+             VALUES ($1, $2, $3, $4, 'ACTIVE', NOW() - INTERVAL '1 minute')",
+        )
+        .bind(order)
+        .bind(tenant)
+        .bind(products[i % 3])
+        .bind((i % 3) as i32 + 1)
+        .execute(db)
+        .await
+        .unwrap();
+        overdue.push(order);
+    }
+    let live = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity, status, expires_at)
+         VALUES ($1, $2, $3, 5, 'ACTIVE', NOW() + INTERVAL '10 minutes')",
+    )
+    .bind(live)
+    .bind(tenant)
+    .bind(products[0])
+    .execute(db)
+    .await
+    .unwrap();
+
+    let (first, second) = tokio::join!(sweep(db), sweep(db));
+    let mut expired: Vec<Uuid> = first.into_iter().chain(second).filter(|order| overdue.contains(order)).collect();
+    expired.sort();
+    let mut expected = overdue.clone();
+    expected.sort();
+    assert_eq!(expired, expected, "each overdue reservation is claimed by exactly one sweeper");
+
+    let statuses: Vec<(Uuid, String)> = sqlx::query_as("SELECT order_id, status FROM inventory_reservations WHERE tenant_id = $1")
+        .bind(tenant)
+        .fetch_all(db)
+        .await
+        .unwrap();
+    for (order, status) in statuses {
+        assert_eq!(status, if order == live { "ACTIVE" } else { "EXPIRED" }, "order {order}");
+    }
+    // Each product got ten reservations of the same size back, once each.
+    for (i, product) in products.iter().enumerate() {
+        let quantity: i32 = sqlx::query_scalar("SELECT quantity FROM inventory WHERE tenant_id = $1 AND product_id = $2")
+            .bind(tenant)
+            .bind(product)
+            .fetch_one(db)
+            .await
+            .unwrap();
+        assert_eq!(quantity, 10 * (i as i32 + 1), "product {product}");
+    }
+}
@@ -56,7 +56,7 @@ async fn list_inventory_happy_path_empty_ok() {
     let jwt_verifier = Arc::new(common_auth::JwtVerifier::new(common_auth::JwtConfig::new("issuer","aud")));
     #[cfg(feature = "kafka")] let producer: rdkafka::producer::FutureProducer = rdkafka::ClientConfig::new().set("bootstrap.servers","localhost:9092").create().unwrap();
     let state = AppState {
-        db: pool,
+        db: pool.into(),
         jwt_verifier,
         multi_location_enabled: false,
         reservation_default_ttl: std::time::Duration::from_secs(900),
@@ -66,7 +66,7 @@ async fn list_inventory_happy_path_empty_ok() {
         metrics: Arc::new(InventoryMetrics::new()),
     };
 
-    ensure_inventory_schema(&state.db).await.expect("ensure schema");
+    ensure_inventory_schema(state.db.pool()).await.expect("ensure schema");
     let app = Router::new().route("/inventory", get(list_inventory)).with_state(state);
 
     let tenant_id = Uuid::new_v4();
@@ -81,3 +81,46 @@ async fn list_inventory_happy_path_empty_ok() {
 }
 
 // helper removed; tests use real extractor behavior
+
+#[tokio::test]
+async fn list_inventory_reports_reserved_and_below_threshold() {
+    let db_url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("SKIP list_inventory_reports_reserved_and_below_threshold: TEST_DATABASE_URL not set");
+            return;
+        }
+    };
+    let pool = PgPoolOptions::new().connect(&db_url).await.expect("connect test db");
+    ensure_inventory_schema(&pool).await.expect("ensure schema");
+    let tenant_id = Uuid::new_v4();
+    let (low, stocked) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    for (product_id, quantity) in [(low, 3), (stocked, 20)] {
+        sqlx::query("INSERT INTO inventory (product_id, tenant_id, quantity, threshold) VALUES ($1, $2, $3, 5)")
+            .bind(product_id).bind(tenant_id).bind(quantity)
+            .execute(&pool).await.unwrap();
+    }
+    sqlx::query("INSERT INTO inventory_reservations (order_id, tenant_id, product_id, quantity) VALUES ($1, $2, $3, 4)")
+        .bind(Uuid::new_v4()).bind(tenant_id).bind(low)
+        .execute(&pool).await.unwrap();
+
+    let mut state = lazy_app_state();
+    state.db = pool.into();
+    let app = Router::new().route("/inventory", get(list_inventory)).with_state(state);
+    let mut req = Request::builder().uri("/inventory?below_threshold=true").method("GET").body(axum::body::Body::empty()).unwrap();
+    let headers = req.headers_mut();
+    headers.insert("X-Tenant-ID", HeaderValue::from_str(&tenant_id.to_string()).unwrap());
+    headers.insert("X-Roles", HeaderValue::from_static("admin"));
+    headers.insert("X-User-ID", HeaderValue::from_str(&Uuid::new_v4().to_string()).unwrap());
+
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let items: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let items = items.as_array().expect("legacy listing is a bare array");
+    assert_eq!(items.len(), 1, "only the product below its threshold is listed");
+    assert_eq!(items[0]["product_id"], low.to_string());
+    assert_eq!(items[0]["reserved"], 4);
+    assert_eq!(items[0]["available"], 0);
+    assert_eq!(items[0]["out_of_stock"], true);
+}
@@ -0,0 +1,107 @@
+//! Cost layers against Postgres: FIFO and weighted-average COGS, returns, and the valuation
+//! report as of a past date. Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip
+//! the container).
+
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use inventory_service::valuation::{cost_order_line, open_layer, record_adjustment, tenant_method, valuation_lines, CostSource, ValuationMethod};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "inventory-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn dec(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+/// Receive 3 units at 2.00 and then 10 at 2.50.
+async fn receive_two_layers(db: &PgPool, tenant: Uuid, product: Uuid) {
+    let mut conn = db.acquire().await.unwrap();
+    record_adjustment(&mut conn, tenant, product, None, Uuid::new_v4(), true, 3, Some(&dec("2.00"))).await.unwrap();
+    record_adjustment(&mut conn, tenant, product, None, Uuid::new_v4(), true, 10, Some(&dec("2.50"))).await.unwrap();
+}
+
+async fn set_method(db: &PgPool, tenant: Uuid, method: ValuationMethod) {
+    sqlx::query("INSERT INTO inventory_valuation_settings (tenant_id, method) VALUES ($1, $2)")
+        .bind(tenant)
+        .bind(method.as_str())
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fifo_sales_cost_the_oldest_layers_first() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, product) = (Uuid::new_v4(), Uuid::new_v4());
+    receive_two_layers(db, tenant, product).await;
+    let mut conn = db.acquire().await.unwrap();
+    assert_eq!(tenant_method(&mut conn, tenant).await.unwrap(), ValuationMethod::Fifo);
+
+    let line = cost_order_line(&mut conn, tenant, ValuationMethod::Fifo, Uuid::new_v4(), product, None, 5).await.unwrap();
+    assert_eq!((line.quantity, line.cost, line.uncosted_quantity), (5, dec("11.00"), 0));
+    let line = cost_order_line(&mut conn, tenant, ValuationMethod::Fifo, Uuid::new_v4(), product, None, 10).await.unwrap();
+    assert_eq!((line.cost, line.uncosted_quantity), (dec("20.00"), 2), "only 8 units had a cost");
+
+    let lines = valuation_lines(&mut conn, tenant, None, Utc::now() + Duration::seconds(1)).await.unwrap();
+    assert!(lines.is_empty(), "everything costed was sold: {lines:?}");
+}
+
+#[tokio::test]
+async fn weighted_average_costs_and_returns_at_the_moving_average() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, product) = (Uuid::new_v4(), Uuid::new_v4());
+    set_method(db, tenant, ValuationMethod::WeightedAverage).await;
+    receive_two_layers(db, tenant, product).await;
+    let mut conn = db.acquire().await.unwrap();
+    let method = tenant_method(&mut conn, tenant).await.unwrap();
+    assert_eq!(method, ValuationMethod::WeightedAverage);
+
+    // 31.00 for 13 units: 2.384615 each.
+    let sale = cost_order_line(&mut conn, tenant, method, Uuid::new_v4(), product, None, 5).await.unwrap();
+    assert_eq!(sale.cost, dec("11.92"));
+    let refund = cost_order_line(&mut conn, tenant, method, Uuid::new_v4(), product, None, -1).await.unwrap();
+    assert_eq!((refund.quantity, refund.cost), (-1, dec("-2.38")));
+
+    let lines = valuation_lines(&mut conn, tenant, None, Utc::now() + Duration::seconds(1)).await.unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!((lines[0].quantity, lines[0].value.clone()), (9, dec("21.46")));
+    assert_eq!(lines[0].unit_cost, Some(dec("2.384616")));
+}
+
+#[tokio::test]
+async fn counts_and_past_dates_are_valued() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, product) = (Uuid::new_v4(), Uuid::new_v4());
+    receive_two_layers(db, tenant, product).await;
+    // The first receipt arrived two days ago.
+    sqlx::query("UPDATE inventory_cost_layers SET received_at = NOW() - INTERVAL '2 days' WHERE tenant_id = $1 AND unit_cost = 2")
+        .bind(tenant)
+        .execute(db)
+        .await
+        .unwrap();
+    let mut conn = db.acquire().await.unwrap();
+    // A count found 4 units short, and a return came back without a cost.
+    record_adjustment(&mut conn, tenant, product, None, Uuid::new_v4(), false, -4, None).await.unwrap();
+    let cost = open_layer(&mut conn, tenant, product, None, CostSource::Return, None, 1, None).await.unwrap();
+    assert_eq!(cost, dec("2.50"), "returns take the average of what's left");
+
+    let yesterday = valuation_lines(&mut conn, tenant, None, Utc::now() - Duration::days(1)).await.unwrap();
+    assert_eq!(yesterday.len(), 1);
+    assert_eq!((yesterday[0].quantity, yesterday[0].value.clone()), (3, dec("6.00")));
+    let now = valuation_lines(&mut conn, tenant, None, Utc::now() + Duration::seconds(1)).await.unwrap();
+    assert_eq!((now[0].quantity, now[0].value.clone()), (10, dec("25.00")));
+    assert!(valuation_lines(&mut conn, Uuid::new_v4(), None, Utc::now()).await.unwrap().is_empty());
+}
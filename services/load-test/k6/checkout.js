@@ -0,0 +1,103 @@
+// Checkout mix for k6. Budgets come from ../budgets.json so k6 and the Rust driver fail on the
+// same numbers. `cargo run -p load-test -- k6` sets the environment below after seeding a tenant;
+// to point it at an already running stack:
+//
+//   k6 run -e ORDER_URL=http://localhost:8084 -e TOKEN=<jwt> -e TENANT_ID=<uuid> \
+//          -e PRODUCTS=<uuid>:199,<uuid>:349 services/load-test/k6/checkout.js
+//
+// RATE (iterations/s, default 30) and DURATION (default 60s) shape the run.
+
+import http from 'k6/http';
+import { check } from 'k6';
+import { uuidv4 } from 'https://jslib.k6.io/k6-utils/1.4.0/index.js';
+
+const budgets = JSON.parse(open('../budgets.json'));
+const ORDER_URL = (__ENV.ORDER_URL || 'http://localhost:8084').replace(/\/$/, '');
+const RATE = parseInt(__ENV.RATE || '30', 10);
+const DURATION = __ENV.DURATION || '60s';
+const PRODUCTS = (__ENV.PRODUCTS || '')
+  .split(',')
+  .filter(Boolean)
+  .map((entry) => {
+    const [id, cents] = entry.split(':');
+    return { id, cents: parseInt(cents, 10) };
+  });
+
+const thresholds = {
+  http_req_failed: [`rate<${budgets.max_error_rate}`],
+  iterations: [`rate>=${budgets.min_throughput_rps}`],
+};
+for (const [flow, budget] of Object.entries(budgets.scenarios)) {
+  thresholds[`http_req_duration{flow:${flow}}`] = [`p(95)<${budget.p95_ms}`];
+}
+
+export const options = {
+  scenarios: {
+    checkout: {
+      executor: 'constant-arrival-rate',
+      rate: RATE,
+      timeUnit: '1s',
+      duration: DURATION,
+      preAllocatedVUs: Math.max(10, RATE),
+      maxVUs: RATE * 4,
+    },
+  },
+  thresholds,
+};
+
+const headers = {
+  'Content-Type': 'application/json',
+  Authorization: `Bearer ${__ENV.TOKEN}`,
+  'X-Tenant-ID': __ENV.TENANT_ID,
+};
+
+function pickFlow() {
+  const entries = Object.entries(budgets.scenarios);
+  const total = entries.reduce((sum, [, b]) => sum + b.weight, 0);
+  let roll = Math.random() * total;
+  for (const [flow, budget] of entries) {
+    roll -= budget.weight;
+    if (roll < 0) return flow;
+  }
+  return entries[entries.length - 1][0];
+}
+
+// One to three lines, one unit each, so a run does not drain the seeded stock.
+function basket() {
+  const count = 1 + Math.floor(Math.random() * Math.min(3, PRODUCTS.length));
+  const start = Math.floor(Math.random() * PRODUCTS.length);
+  const lines = [];
+  for (let i = 0; i < count; i++) {
+    lines.push(PRODUCTS[(start + i) % PRODUCTS.length]);
+  }
+  return lines;
+}
+
+function checkout(method, lines) {
+  const totalCents = lines.reduce((sum, p) => sum + p.cents, 0);
+  const body = {
+    items: lines.map((p) => ({
+      product_id: p.id,
+      quantity: 1,
+      unit_price: (p.cents / 100).toFixed(2),
+      line_total: (p.cents / 100).toFixed(2),
+    })),
+    payment_method: method,
+    payment: { method, amount_cents: totalCents },
+    total: (totalCents / 100).toFixed(2),
+    idempotency_key: uuidv4(),
+  };
+  return http.post(`${ORDER_URL}/orders`, JSON.stringify(body), { headers, tags: { flow: `${method}_checkout` } });
+}
+
+function compute(lines) {
+  const body = { items: lines.map((p) => ({ product_id: p.id, quantity: 1 })) };
+  return http.post(`${ORDER_URL}/orders/compute`, JSON.stringify(body), { headers, tags: { flow: 'compute' } });
+}
+
+export default function () {
+  const lines = basket();
+  const flow = pickFlow();
+  const res = flow === 'compute' ? compute(lines) : checkout(flow === 'card_checkout' ? 'card' : 'cash', lines);
+  check(res, { 'status is 2xx': (r) => r.status >= 200 && r.status < 300 });
+}
@@ -0,0 +1,119 @@
+//! Criterion results checked against the means committed in `baselines/criterion.json`.
+//! Criterion's own `--save-baseline` data lives under `target/` and is lost with it; the
+//! committed file is what a regression is measured against.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchBaselines {
+    /// Allowed slowdown over the baseline mean, in percent.
+    pub tolerance_pct: f64,
+    /// Mean time per iteration in nanoseconds, keyed by criterion benchmark id.
+    pub benches: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchDelta {
+    pub id: String,
+    pub baseline_ns: f64,
+    /// `None` when the benchmark has not been run since the last `cargo clean`.
+    pub measured_ns: Option<f64>,
+}
+
+impl BenchDelta {
+    pub fn change_pct(&self) -> Option<f64> {
+        self.measured_ns.map(|m| (m - self.baseline_ns) / self.baseline_ns * 100.0)
+    }
+}
+
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+impl BenchBaselines {
+    pub fn default_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("baselines/criterion.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("read baselines {}", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("parse baselines {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut raw = serde_json::to_string_pretty(self)?;
+        raw.push('\n');
+        std::fs::write(path, raw).with_context(|| format!("write baselines {}", path.display()))
+    }
+
+    /// Latest criterion mean for every baselined benchmark under `criterion_dir`.
+    pub fn compare(&self, criterion_dir: &Path) -> Vec<BenchDelta> {
+        self.benches
+            .iter()
+            .map(|(id, baseline_ns)| BenchDelta { id: id.clone(), baseline_ns: *baseline_ns, measured_ns: latest_mean_ns(criterion_dir, id) })
+            .collect()
+    }
+
+    /// Deltas slower than the tolerance, or missing a measurement.
+    pub fn regressions<'a>(&self, deltas: &'a [BenchDelta]) -> Vec<&'a BenchDelta> {
+        deltas.iter().filter(|d| d.change_pct().is_none_or(|pct| pct > self.tolerance_pct)).collect()
+    }
+
+    /// Replace the baselines with whatever was measured.
+    pub fn rebase(&mut self, deltas: &[BenchDelta]) {
+        for delta in deltas {
+            if let Some(measured) = delta.measured_ns {
+                self.benches.insert(delta.id.clone(), measured.round());
+            }
+        }
+    }
+}
+
+fn latest_mean_ns(criterion_dir: &Path, id: &str) -> Option<f64> {
+    let raw = std::fs::read_to_string(criterion_dir.join(id).join("new/estimates.json")).ok()?;
+    serde_json::from_str::<Estimates>(&raw).ok().map(|e| e.mean.point_estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_slowdowns_past_tolerance_and_missing_runs() {
+        let dir = std::env::temp_dir().join(format!("load-test-bench-{}", uuid::Uuid::new_v4()));
+        for (id, mean) in [("fast", 90.0), ("slow", 140.0)] {
+            std::fs::create_dir_all(dir.join(id).join("new")).unwrap();
+            let estimates = serde_json::json!({ "mean": { "point_estimate": mean }, "median": { "point_estimate": mean } });
+            std::fs::write(dir.join(id).join("new/estimates.json"), estimates.to_string()).unwrap();
+        }
+        let mut baselines = BenchBaselines {
+            tolerance_pct: 25.0,
+            benches: [("fast", 100.0), ("slow", 100.0), ("never_run", 100.0)].into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+        };
+
+        let deltas = baselines.compare(&dir);
+        let flagged: Vec<&str> = baselines.regressions(&deltas).iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(flagged, vec!["never_run", "slow"]);
+
+        baselines.rebase(&deltas);
+        assert_eq!(baselines.benches["slow"], 140.0);
+        assert_eq!(baselines.benches["never_run"], 100.0);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn committed_baselines_parse() {
+        let baselines = BenchBaselines::load(&BenchBaselines::default_path()).unwrap();
+        assert!(baselines.benches.keys().any(|id| id.starts_with("reservation_")));
+        assert!(baselines.benches.keys().any(|id| id.starts_with("round_")));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::stats::RunReport;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Latency and throughput budgets for the checkout mix, shared with `k6/checkout.js`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Budgets {
+    pub min_throughput_rps: f64,
+    pub max_error_rate: f64,
+    pub scenarios: BTreeMap<String, ScenarioBudget>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioBudget {
+    /// Relative share of the mix.
+    pub weight: u32,
+    pub p95_ms: f64,
+}
+
+impl Budgets {
+    pub fn default_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("budgets.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("read budgets {}", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("parse budgets {}", path.display()))
+    }
+
+    /// Every budget the run missed; empty when it passed.
+    pub fn violations(&self, report: &RunReport) -> Vec<String> {
+        let mut out = Vec::new();
+        let rps = report.throughput_rps();
+        if rps < self.min_throughput_rps {
+            out.push(format!("throughput {rps:.1} rps below {} rps", self.min_throughput_rps));
+        }
+        let error_rate = report.error_rate();
+        if error_rate > self.max_error_rate {
+            out.push(format!("error rate {:.2}% above {:.2}%", error_rate * 100.0, self.max_error_rate * 100.0));
+        }
+        for (flow, budget) in &self.scenarios {
+            match report.flows.get(flow) {
+                Some(stats) if stats.p95_ms > budget.p95_ms => {
+                    out.push(format!("{flow} p95 {:.1} ms above {} ms", stats.p95_ms, budget.p95_ms));
+                }
+                Some(_) => {}
+                None if budget.weight > 0 => out.push(format!("{flow} was never exercised")),
+                None => {}
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Samples;
+    use std::time::Duration;
+
+    #[test]
+    fn shipped_budgets_parse() {
+        let budgets = Budgets::load(&Budgets::default_path()).unwrap();
+        assert!(budgets.scenarios.contains_key("cash_checkout"));
+        assert!(budgets.scenarios.values().all(|s| s.p95_ms > 0.0));
+    }
+
+    #[test]
+    fn reports_each_missed_budget() {
+        let budgets: Budgets = serde_json::from_value(serde_json::json!({
+            "min_throughput_rps": 10,
+            "max_error_rate": 0.1,
+            "scenarios": { "cash_checkout": { "weight": 1, "p95_ms": 50 }, "compute": { "weight": 1, "p95_ms": 50 } }
+        }))
+        .unwrap();
+        let mut samples = Samples::default();
+        for ms in 1..=100 {
+            samples.record("cash_checkout", Duration::from_millis(ms), ms % 50 != 0);
+        }
+        let report = samples.report(Duration::from_secs(5));
+        assert_eq!(
+            budgets.violations(&report),
+            vec!["cash_checkout p95 95.0 ms above 50 ms".to_string(), "compute was never exercised".to_string()]
+        );
+
+        let report = samples.report(Duration::from_secs(20));
+        assert_eq!(budgets.violations(&report).first().map(String::as_str), Some("throughput 5.0 rps below 10 rps"));
+    }
+}
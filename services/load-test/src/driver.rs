@@ -0,0 +1,113 @@
+use crate::mix::CheckoutMix;
+use crate::stats::{RunReport, Samples};
+use reqwest::Client;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+/// A seeded product the mix can put in a basket.
+#[derive(Debug, Clone)]
+pub struct SeededProduct {
+    pub id: Uuid,
+    pub price_cents: i64,
+}
+
+/// Where the mix is sent, and as whom.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub order_url: String,
+    pub token: String,
+    pub tenant_id: Uuid,
+    pub products: Vec<SeededProduct>,
+}
+
+impl Target {
+    /// `PRODUCTS` as `k6/checkout.js` expects it: `id:cents,id:cents`.
+    pub fn products_arg(&self) -> String {
+        self.products.iter().map(|p| format!("{}:{}", p.id, p.price_cents)).collect::<Vec<_>>().join(",")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DriverConfig {
+    /// Checkouts started per second (open loop: slow responses do not lower the offered load).
+    pub rate: u32,
+    pub duration: Duration,
+    /// Cap on requests in flight; past it the driver falls behind and throughput drops.
+    pub max_in_flight: usize,
+}
+
+/// Offer the checkout mix at a constant rate for the configured duration.
+pub async fn drive(target: Arc<Target>, mix: &CheckoutMix, config: &DriverConfig) -> RunReport {
+    let client = Client::builder().timeout(Duration::from_secs(10)).build().expect("http client");
+    let samples = Arc::new(Mutex::new(Samples::default()));
+    let in_flight = Arc::new(Semaphore::new(config.max_in_flight));
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / f64::from(config.rate.max(1))));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut tasks = JoinSet::new();
+    let started = Instant::now();
+    while started.elapsed() < config.duration {
+        ticker.tick().await;
+        let Ok(permit) = in_flight.clone().acquire_owned().await else { break };
+        let flow = mix.pick(Uuid::new_v4().as_u128() as u64).to_string();
+        let (client, target, samples) = (client.clone(), target.clone(), samples.clone());
+        tasks.spawn(async move {
+            let begun = Instant::now();
+            let ok = send(&client, &target, &flow).await;
+            samples.lock().unwrap().record(&flow, begun.elapsed(), ok);
+            drop(permit);
+        });
+    }
+    while tasks.join_next().await.is_some() {}
+    let elapsed = started.elapsed();
+    let samples = samples.lock().unwrap();
+    samples.report(elapsed)
+}
+
+async fn send(client: &Client, target: &Target, flow: &str) -> bool {
+    let basket = basket(&target.products);
+    let base = target.order_url.trim_end_matches('/');
+    let request = match flow {
+        "compute" => {
+            let items: Vec<_> = basket.iter().map(|p| json!({ "product_id": p.id, "quantity": 1 })).collect();
+            client.post(format!("{base}/orders/compute")).json(&json!({ "items": items }))
+        }
+        _ => {
+            let method = if flow == "card_checkout" { "card" } else { "cash" };
+            client.post(format!("{base}/orders")).json(&checkout_body(method, &basket))
+        }
+    };
+    let response = request.bearer_auth(&target.token).header("X-Tenant-ID", target.tenant_id.to_string()).send().await;
+    matches!(response, Ok(r) if r.status().is_success())
+}
+
+/// One to three consecutive products, one unit each, so a run does not drain the seeded stock.
+fn basket(products: &[SeededProduct]) -> Vec<&SeededProduct> {
+    if products.is_empty() {
+        return Vec::new();
+    }
+    let roll = Uuid::new_v4().as_u128();
+    let count = 1 + (roll % products.len().min(3) as u128) as usize;
+    let start = ((roll >> 64) % products.len() as u128) as usize;
+    (0..count).map(|i| &products[(start + i) % products.len()]).collect()
+}
+
+fn checkout_body(method: &str, basket: &[&SeededProduct]) -> serde_json::Value {
+    let total_cents: i64 = basket.iter().map(|p| p.price_cents).sum();
+    let amount = |cents: i64| format!("{}.{:02}", cents / 100, cents % 100);
+    let items: Vec<_> = basket
+        .iter()
+        .map(|p| json!({ "product_id": p.id, "quantity": 1, "unit_price": amount(p.price_cents), "line_total": amount(p.price_cents) }))
+        .collect();
+    json!({
+        "items": items,
+        "payment_method": method,
+        "payment": { "method": method, "amount_cents": total_cents },
+        "total": amount(total_cents),
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
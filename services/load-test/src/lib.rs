@@ -0,0 +1,15 @@
+//! Checkout load test and benchmark regression checks. See `main.rs` for the commands.
+
+pub mod bench;
+pub mod budget;
+pub mod driver;
+pub mod mix;
+pub mod stack;
+pub mod stats;
+
+pub use bench::{BenchBaselines, BenchDelta};
+pub use budget::{Budgets, ScenarioBudget};
+pub use driver::{drive, DriverConfig, SeededProduct, Target};
+pub use mix::CheckoutMix;
+pub use stack::{Stack, StackOptions};
+pub use stats::{FlowStats, RunReport, Samples};
@@ -0,0 +1,171 @@
+use anyhow::{bail, Context, Result};
+use load_test::{drive, BenchBaselines, Budgets, CheckoutMix, DriverConfig, RunReport, SeededProduct, Stack, StackOptions, Target};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use structopt::StructOpt;
+use uuid::Uuid;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "load-test", about = "Checkout load test and benchmark regression checks")]
+enum Cli {
+    /// Drive the checkout mix with the built-in client and fail on missed budgets
+    Run {
+        #[structopt(flatten)]
+        stack: StackArgs,
+        #[structopt(flatten)]
+        load: LoadArgs,
+        /// Budgets file (default: budgets.json next to this crate)
+        #[structopt(long, parse(from_os_str))]
+        budgets: Option<PathBuf>,
+        /// Write the run report as JSON
+        #[structopt(long, parse(from_os_str))]
+        json: Option<PathBuf>,
+    },
+    /// Run k6/checkout.js against the stack; k6 enforces the same budgets
+    K6 {
+        #[structopt(flatten)]
+        stack: StackArgs,
+        #[structopt(flatten)]
+        load: LoadArgs,
+    },
+    /// Compare the latest criterion results with baselines/criterion.json
+    BenchCheck {
+        /// Criterion output directory
+        #[structopt(long, parse(from_os_str))]
+        criterion_dir: Option<PathBuf>,
+        #[structopt(long, parse(from_os_str))]
+        baselines: Option<PathBuf>,
+        /// Overwrite the baselines with the latest results instead of checking them
+        #[structopt(long)]
+        update: bool,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct StackArgs {
+    /// order-service of an already running stack; without it one is started in containers
+    #[structopt(long)]
+    order_url: Option<String>,
+    /// Bearer token for --order-url (or LOADTEST_TOKEN)
+    #[structopt(long)]
+    token: Option<String>,
+    #[structopt(long)]
+    tenant_id: Option<Uuid>,
+    /// Products with stock for --order-url, as `id:cents,id:cents`
+    #[structopt(long)]
+    products: Option<String>,
+    /// Start the services from debug builds (quicker to build, not representative)
+    #[structopt(long)]
+    debug_build: bool,
+    #[structopt(long, default_value = "8")]
+    product_count: usize,
+}
+
+#[derive(StructOpt, Debug)]
+struct LoadArgs {
+    /// Checkouts started per second
+    #[structopt(long, default_value = "30")]
+    rate: u32,
+    #[structopt(long, default_value = "60")]
+    duration_secs: u64,
+    #[structopt(long, default_value = "64")]
+    max_in_flight: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    match Cli::from_args() {
+        Cli::Run { stack, load, budgets, json } => {
+            let budgets = Budgets::load(&budgets.unwrap_or_else(Budgets::default_path))?;
+            let (target, _stack) = target(&stack).await?;
+            let config = DriverConfig { rate: load.rate, duration: Duration::from_secs(load.duration_secs), max_in_flight: load.max_in_flight };
+            let report = drive(Arc::new(target), &CheckoutMix::from_budgets(&budgets), &config).await;
+            print_report(&report);
+            if let Some(path) = json {
+                std::fs::write(&path, serde_json::to_string_pretty(&report)?).with_context(|| format!("write {}", path.display()))?;
+            }
+            let violations = budgets.violations(&report);
+            if !violations.is_empty() {
+                bail!("budgets missed:\n  {}", violations.join("\n  "));
+            }
+            Ok(())
+        }
+        Cli::K6 { stack, load } => {
+            let (target, _stack) = target(&stack).await?;
+            let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("k6/checkout.js");
+            let status = tokio::process::Command::new("k6")
+                .arg("run")
+                .args(["-e", &format!("ORDER_URL={}", target.order_url)])
+                .args(["-e", &format!("TOKEN={}", target.token)])
+                .args(["-e", &format!("TENANT_ID={}", target.tenant_id)])
+                .args(["-e", &format!("PRODUCTS={}", target.products_arg())])
+                .args(["-e", &format!("RATE={}", load.rate)])
+                .args(["-e", &format!("DURATION={}s", load.duration_secs)])
+                .arg(script)
+                .status()
+                .await
+                .context("run k6 (is it installed?)")?;
+            if !status.success() {
+                bail!("k6 reported missed thresholds ({status})");
+            }
+            Ok(())
+        }
+        Cli::BenchCheck { criterion_dir, baselines, update } => {
+            let path = baselines.unwrap_or_else(BenchBaselines::default_path);
+            let criterion_dir = criterion_dir.unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/criterion"));
+            let mut baselines = BenchBaselines::load(&path)?;
+            let deltas = baselines.compare(&criterion_dir);
+            for delta in &deltas {
+                match (delta.measured_ns, delta.change_pct()) {
+                    (Some(measured), Some(pct)) => println!("{:<40} {:>12.1} ns  baseline {:>12.1} ns  {pct:+6.1}%", delta.id, measured, delta.baseline_ns),
+                    _ => println!("{:<40} {:>12} ns  baseline {:>12.1} ns", delta.id, "-", delta.baseline_ns),
+                }
+            }
+            if update {
+                baselines.rebase(&deltas);
+                return baselines.save(&path);
+            }
+            let regressions = baselines.regressions(&deltas);
+            if !regressions.is_empty() {
+                let ids: Vec<&str> = regressions.iter().map(|d| d.id.as_str()).collect();
+                bail!("{} benchmark(s) slower than baseline by more than {}% or not run: {}", ids.len(), baselines.tolerance_pct, ids.join(", "));
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn target(args: &StackArgs) -> Result<(Target, Option<Stack>)> {
+    if let Some(order_url) = &args.order_url {
+        let token = args.token.clone().or_else(|| std::env::var("LOADTEST_TOKEN").ok()).context("--token (or LOADTEST_TOKEN) is required with --order-url")?;
+        let tenant_id = args.tenant_id.context("--tenant-id is required with --order-url")?;
+        let products = parse_products(args.products.as_deref().context("--products is required with --order-url")?)?;
+        return Ok((Target { order_url: order_url.clone(), token, tenant_id, products }, None));
+    }
+    let options = StackOptions {
+        release: !args.debug_build,
+        products: args.product_count,
+        stock: 1_000_000,
+    };
+    let stack = Stack::start(&options).await?;
+    Ok((stack.target.clone(), Some(stack)))
+}
+
+fn parse_products(raw: &str) -> Result<Vec<SeededProduct>> {
+    raw.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (id, cents) = entry.trim().split_once(':').with_context(|| format!("product `{entry}` is not id:cents"))?;
+            Ok(SeededProduct { id: id.parse()?, price_cents: cents.parse()? })
+        })
+        .collect()
+}
+
+fn print_report(report: &RunReport) {
+    println!("{:<16} {:>8} {:>7} {:>9} {:>9} {:>9}", "flow", "requests", "errors", "p50 ms", "p95 ms", "p99 ms");
+    for (flow, stats) in &report.flows {
+        println!("{flow:<16} {:>8} {:>7} {:>9.1} {:>9.1} {:>9.1}", stats.requests, stats.errors, stats.p50_ms, stats.p95_ms, stats.p99_ms);
+    }
+    println!("{:.1} rps over {:.1}s, {:.2}% errors", report.throughput_rps(), report.elapsed_secs, report.error_rate() * 100.0);
+}
@@ -0,0 +1,55 @@
+use crate::budget::Budgets;
+
+/// Weighted choice between checkout flows, in the proportions of the budgets file.
+#[derive(Debug, Clone)]
+pub struct CheckoutMix {
+    flows: Vec<(String, u32)>,
+    total: u32,
+}
+
+impl CheckoutMix {
+    pub fn from_budgets(budgets: &Budgets) -> Self {
+        let flows: Vec<(String, u32)> = budgets.scenarios.iter().filter(|(_, b)| b.weight > 0).map(|(flow, b)| (flow.clone(), b.weight)).collect();
+        let total = flows.iter().map(|(_, w)| w).sum();
+        Self { flows, total }
+    }
+
+    /// Flow for a uniformly random `roll`.
+    pub fn pick(&self, roll: u64) -> &str {
+        let mut remaining = (roll % u64::from(self.total.max(1))) as u32;
+        for (flow, weight) in &self.flows {
+            if remaining < *weight {
+                return flow;
+            }
+            remaining -= weight;
+        }
+        self.flows.last().map(|(flow, _)| flow.as_str()).unwrap_or("cash_checkout")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_follow_the_weights() {
+        let budgets: Budgets = serde_json::from_value(serde_json::json!({
+            "min_throughput_rps": 1,
+            "max_error_rate": 0.01,
+            "scenarios": {
+                "card_checkout": { "weight": 3, "p95_ms": 1 },
+                "cash_checkout": { "weight": 6, "p95_ms": 1 },
+                "compute": { "weight": 1, "p95_ms": 1 },
+                "disabled": { "weight": 0, "p95_ms": 1 }
+            }
+        }))
+        .unwrap();
+        let mix = CheckoutMix::from_budgets(&budgets);
+        let picks: Vec<&str> = (0..10).map(|roll| mix.pick(roll)).collect();
+        assert_eq!(picks.iter().filter(|f| **f == "card_checkout").count(), 3);
+        assert_eq!(picks.iter().filter(|f| **f == "cash_checkout").count(), 6);
+        assert_eq!(picks.iter().filter(|f| **f == "compute").count(), 1);
+        assert_eq!(mix.pick(12), "card_checkout");
+        assert_eq!(mix.pick(13), "cash_checkout");
+    }
+}
@@ -0,0 +1,108 @@
+//! Throwaway checkout stack: Postgres and Redpanda in containers, and order-, inventory- and
+//! payment-service started from this workspace against them. Requires Docker unless the
+//! `TEST_DATABASE_URL` / `TEST_KAFKA_BOOTSTRAP` overrides of `common-test-fixtures` are set.
+
+use crate::driver::{SeededProduct, Target};
+use anyhow::{bail, Context, Result};
+use common_test_fixtures::auth::{TEST_AUDIENCE, TEST_ISSUER};
+use common_test_fixtures::containers::services_dir;
+use common_test_fixtures::{seed_catalog, TenantFixture, TestKafka, TestPostgres, TestSigner};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+
+/// Migrations run in this order against the one shared database, as `migrate-all.ps1` does.
+const MIGRATED_SERVICES: [&str; 4] = ["product-service", "inventory-service", "payment-service", "order-service"];
+
+#[derive(Debug, Clone)]
+pub struct StackOptions {
+    /// Build the services with `--release`; debug builds are far too slow to hold the budgets.
+    pub release: bool,
+    pub products: usize,
+    /// Units seeded per product; enough for the whole run since nothing restocks.
+    pub stock: i32,
+}
+
+/// Running stack; containers and service processes stop when it is dropped.
+pub struct Stack {
+    pub target: Target,
+    _postgres: TestPostgres,
+    _kafka: TestKafka,
+    _services: Vec<Child>,
+}
+
+struct Service {
+    package: &'static str,
+    port: u16,
+}
+
+const ORDER: Service = Service { package: "order-service", port: 48084 };
+const PAYMENT: Service = Service { package: "payment-service", port: 48086 };
+const INVENTORY: Service = Service { package: "inventory-service", port: 48087 };
+
+impl Stack {
+    pub async fn start(opts: &StackOptions) -> Result<Self> {
+        let postgres = TestPostgres::start().await?;
+        let kafka = TestKafka::start().await;
+        postgres.migrate(&MIGRATED_SERVICES).await?;
+        let tenant_id = TenantFixture::new().insert(postgres.pool()).await?.id;
+        let products = seed_catalog(postgres.pool(), tenant_id, opts.products, opts.stock)
+            .await?
+            .into_iter()
+            .map(|p| SeededProduct { id: p.id, price_cents: p.price_cents })
+            .collect();
+        let signer = TestSigner::generate();
+        let token = signer.token(tenant_id, &["admin", "cashier"]);
+
+        let env = [
+            ("DATABASE_URL", postgres.url().to_string()),
+            ("KAFKA_BOOTSTRAP", kafka.bootstrap().to_string()),
+            ("JWT_ISSUER", TEST_ISSUER.to_string()),
+            ("JWT_AUDIENCE", TEST_AUDIENCE.to_string()),
+            ("JWT_DEV_PUBLIC_KEY_PEM", signer.public_pem().to_string()),
+            ("INVENTORY_SERVICE_URL", format!("http://127.0.0.1:{}", INVENTORY.port)),
+            ("PAYMENT_SERVICE_URL", format!("http://127.0.0.1:{}", PAYMENT.port)),
+            ("ENABLE_PAYMENT_INTENTS", "true".to_string()),
+            ("RUST_LOG", "warn".to_string()),
+        ];
+        let mut services = Vec::new();
+        for service in [&INVENTORY, &PAYMENT, &ORDER] {
+            services.push(spawn(service, &env, opts.release)?);
+        }
+        for service in [&INVENTORY, &PAYMENT, &ORDER] {
+            wait_healthy(service).await?;
+        }
+
+        let target = Target { order_url: format!("http://127.0.0.1:{}", ORDER.port), token, tenant_id, products };
+        Ok(Self { target, _postgres: postgres, _kafka: kafka, _services: services })
+    }
+}
+
+fn spawn(service: &Service, env: &[(&str, String)], release: bool) -> Result<Child> {
+    let mut command = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+    command.current_dir(services_dir()).args(["run", "--quiet", "-p", service.package]);
+    if release {
+        command.arg("--release");
+    }
+    command
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .env("HOST", "127.0.0.1")
+        .env("PORT", service.port.to_string())
+        .stdout(Stdio::null())
+        .kill_on_drop(true);
+    command.spawn().with_context(|| format!("start {}", service.package))
+}
+
+/// First start compiles the services, so allow a few minutes.
+async fn wait_healthy(service: &Service) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/healthz", service.port);
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(600) {
+        if matches!(client.get(&url).send().await, Ok(r) if r.status().is_success()) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    bail!("{} did not become healthy on port {}", service.package, service.port)
+}
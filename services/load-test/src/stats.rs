@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Raw latencies per flow, collected while the driver runs.
+#[derive(Debug, Default)]
+pub struct Samples {
+    flows: BTreeMap<String, FlowSamples>,
+}
+
+#[derive(Debug, Default)]
+struct FlowSamples {
+    latencies_ms: Vec<f64>,
+    errors: u64,
+}
+
+impl Samples {
+    pub fn record(&mut self, flow: &str, latency: Duration, ok: bool) {
+        let entry = self.flows.entry(flow.to_string()).or_default();
+        entry.latencies_ms.push(latency.as_secs_f64() * 1000.0);
+        if !ok {
+            entry.errors += 1;
+        }
+    }
+
+    pub fn report(&self, elapsed: Duration) -> RunReport {
+        let flows = self
+            .flows
+            .iter()
+            .map(|(flow, samples)| {
+                let mut sorted = samples.latencies_ms.clone();
+                sorted.sort_by(f64::total_cmp);
+                let stats = FlowStats {
+                    requests: sorted.len() as u64,
+                    errors: samples.errors,
+                    p50_ms: percentile(&sorted, 0.50),
+                    p95_ms: percentile(&sorted, 0.95),
+                    p99_ms: percentile(&sorted, 0.99),
+                    max_ms: sorted.last().copied().unwrap_or(0.0),
+                };
+                (flow.clone(), stats)
+            })
+            .collect();
+        RunReport { elapsed_secs: elapsed.as_secs_f64(), flows }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice; 0 when empty.
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub elapsed_secs: f64,
+    pub flows: BTreeMap<String, FlowStats>,
+}
+
+impl RunReport {
+    pub fn requests(&self) -> u64 {
+        self.flows.values().map(|f| f.requests).sum()
+    }
+
+    /// Completed requests per second across the whole mix.
+    pub fn throughput_rps(&self) -> f64 {
+        if self.elapsed_secs <= 0.0 {
+            return 0.0;
+        }
+        self.requests() as f64 / self.elapsed_secs
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        let requests = self.requests();
+        if requests == 0 {
+            return 0.0;
+        }
+        self.flows.values().map(|f| f.errors).sum::<u64>() as f64 / requests as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let sorted: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 0.50), 10.0);
+        assert_eq!(percentile(&sorted, 0.95), 19.0);
+        assert_eq!(percentile(&sorted, 1.0), 20.0);
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&[], 0.95), 0.0);
+    }
+}
@@ -0,0 +1,7 @@
+-- Per-tenant accrual configuration seeded during tenant provisioning.
+CREATE TABLE IF NOT EXISTS loyalty_settings (
+    tenant_id UUID PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    points_per_currency_unit INTEGER NOT NULL DEFAULT 1 CHECK (points_per_currency_unit >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
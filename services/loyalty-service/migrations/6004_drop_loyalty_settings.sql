@@ -0,0 +1,3 @@
+-- Accrual stays at one point per whole currency unit; per-tenant accrual settings were never
+-- configurable and are no longer seeded or read.
+DROP TABLE IF EXISTS loyalty_settings;
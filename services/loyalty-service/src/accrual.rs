@@ -0,0 +1,12 @@
+use bigdecimal::ToPrimitive;
+use common_events::OrderCompletedEvent;
+
+/// Points a completed sale earns: one per whole currency unit. `None` for refunds (which reuse
+/// the topic), sales without a customer and sales too small to earn anything.
+pub fn earned_points(evt: &OrderCompletedEvent) -> Option<i32> {
+    if evt.is_refund() || evt.customer_id.is_none() {
+        return None;
+    }
+    let points = evt.total.to_f64().unwrap_or(0.0).floor() as i32;
+    (points > 0).then_some(points)
+}
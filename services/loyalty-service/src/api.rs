@@ -1,4 +1,5 @@
-use axum::extract::{State, Query};
+use axum::extract::{Path, State, Query};
+use axum::Json;
 use std::collections::HashMap;
 use common_http_errors::ApiError;
 use common_security::{SecurityCtxExtractor, Capability, ensure_capability};
@@ -40,3 +41,27 @@ pub async fn get_points(
 
     Ok(points.to_string())
 }
+
+/// Offboarding export pulled by auth-service's tenant export job.
+pub async fn export_tenant_data(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    ensure_capability(&sec, Capability::GdprManage)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "gdpr_manage", trace_id: sec.trace_id })?;
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let rows: String = sqlx::query_scalar(
+        "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM (SELECT customer_id, points FROM loyalty_points WHERE tenant_id = $1 ORDER BY customer_id) t",
+    )
+    .bind(tenant_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("DB error: {e}")) })?;
+    let loyalty_points: serde_json::Value = serde_json::from_str(&rows).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    Ok(Json(serde_json::json!({ "loyalty_points": loyalty_points })))
+}
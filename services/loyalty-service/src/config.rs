@@ -0,0 +1,39 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoyaltyConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    /// `LOYALTY_INBOX_DEDUP`
+    pub inbox_dedup: bool,
+    pub jwt: JwtSettings,
+}
+
+impl LoyaltyConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8088);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let inbox_dedup = env.flag("LOYALTY_INBOX_DEDUP", true);
+        let jwt = JwtSettings::read(&mut env).await;
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                inbox_dedup,
+                jwt: jwt?,
+            })
+        })
+    }
+}
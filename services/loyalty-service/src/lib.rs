@@ -1,2 +1,3 @@
+pub mod accrual;
 mod api;
-pub use api::{AppState, get_points};
+pub use api::{AppState, export_tenant_data, get_points};
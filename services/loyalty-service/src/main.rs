@@ -1,4 +1,3 @@
-use anyhow::Context;
 use axum::{
     extract::FromRef,
     http::{
@@ -9,49 +8,38 @@ use axum::{
     Router,
 };
 use common_auth::{ JwtConfig, JwtVerifier };
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
 use common_money::log_rounding_mode_once;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use futures::StreamExt;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::consumer::{Consumer, StreamConsumer};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::Message;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde::Deserialize;
-use sqlx::PgPool;
-use std::{
-    env,
-    net::{SocketAddr},
-    sync::Arc,
-    time::Duration,
-};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use loyalty_service::accrual::earned_points;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_events::{topics, OrderCompletedEvent};
+use std::{sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 use tokio::time::{interval, MissedTickBehavior};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use uuid::Uuid;
-use once_cell::sync::Lazy;
-use prometheus::{Registry, IntCounterVec, Opts, Encoder, TextEncoder};
+use prometheus::{Encoder, TextEncoder};
 
 mod api; // expose library module for tests & reuse
-pub use crate::api::{AppState, get_points};
-
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-#[derive(Debug, Deserialize)]
-struct CompletedEvent {
-    order_id: Uuid,
-    tenant_id: Uuid,
-    total: f64,
-    customer_id: Option<Uuid>,
-}
+mod config;
+use config::LoyaltyConfig;
+pub use crate::api::{AppState, export_tenant_data, get_points};
 
 impl FromRef<AppState> for Arc<JwtVerifier> {
     fn from_ref(state: &AppState) -> Self { state.jwt_verifier.clone() }
 }
 
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-async fn handle_completed_event(evt: &CompletedEvent, customer_id: Uuid, pool: &PgPool, producer: &FutureProducer) {
+async fn handle_completed_event(evt: &OrderCompletedEvent, customer_id: Uuid, pool: &sqlx::PgPool, producer: &FutureProducer) {
     // Prometheus registry and metrics (module scope)
     // Minimal upsert logic: grant points proportional to total (1 point per whole currency unit)
-    let points = evt.total.floor() as i32;
-    if points <= 0 { return; }
+    let Some(points) = earned_points(evt) else { return; };
     if let Err(err) = sqlx::query(
         "INSERT INTO loyalty_points (customer_id, tenant_id, points)
             VALUES ($1,$2,$3)
@@ -75,35 +63,40 @@ async fn handle_completed_event(evt: &CompletedEvent, customer_id: Uuid, pool: &
         "points_added": points,
         "order_id": evt.order_id,
     });
-    if let Err(err) = producer.send(
-        FutureRecord::to("loyalty.events").payload(&event.to_string()).key(&evt.tenant_id.to_string()),
-        Duration::from_secs(0)
-    ).await {
+    if let Err(err) =
+        common_kafka::publish(producer, "loyalty.events", &customer_id.to_string(), &event.to_string()).await
+    {
         tracing::debug!(error=?err, "Failed to emit loyalty event");
     }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("loyalty-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL")?;
-    let db_pool = PgPool::connect(&database_url).await?;
+    let config = LoyaltyConfig::from_env().await?;
+    let db_pool = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::register_metrics(&LOYALTY_REGISTRY);
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db_pool.clone());
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    common_security::spawn_policy_refresh(jwt_verifier.clone());
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let bootstrap = env::var("KAFKA_BOOTSTRAP").unwrap_or_else(|_| "localhost:9092".into());
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let consumer: StreamConsumer = rdkafka::ClientConfig::new()
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let bootstrap = config.kafka_bootstrap.clone();
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let kafka_security = KafkaSecurity::from_env().await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let consumer: StreamConsumer = kafka_security
+        .apply(&mut rdkafka::ClientConfig::new())
         .set("bootstrap.servers", &bootstrap)
         .set("group.id", "loyalty-service")
         .create()?;
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] consumer.subscribe(&["order.completed"])?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] consumer.subscribe(&[topics::ORDER_COMPLETED])?;
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set("bootstrap.servers", &bootstrap)
-        .create()?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let producer: FutureProducer =
+        common_kafka::ProducerSettings::from_env()?.create(&bootstrap, &kafka_security)?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] common_kafka::register_metrics(&LOYALTY_REGISTRY);
 
     let state = AppState {
         db: db_pool.clone(),
@@ -114,18 +107,15 @@ async fn main() -> anyhow::Result<()> {
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] tokio::spawn({
         let db = db_pool.clone();
         let producer = producer.clone();
+        let inbox_enabled = config.inbox_dedup;
         async move {
             let mut stream = consumer.stream();
             while let Some(message) = stream.next().await {
                 if let Ok(m) = message {
                     if let Some(Ok(text)) = m.payload_view::<str>() {
-                        // Inbox de-duplication (env-guarded; default enabled)
-                        let inbox_enabled = std::env::var("LOYALTY_INBOX_DEDUP").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(true);
+                        // Inbox de-duplication (LOYALTY_INBOX_DEDUP; default enabled)
                         if inbox_enabled {
-                            let key_str = m
-                                .key()
-                                .map(|k| String::from_utf8_lossy(k).to_string())
-                                .unwrap_or_else(|| format!("sha1:{}", hex::encode(sha1_smol::Sha1::from(text).digest().bytes())));
+                            let key_str = common_events::inbox_key(m.key(), text);
                             // Extract tenant_id from payload (stringified UUID)
                             let tenant_hint = serde_json::from_str::<serde_json::Value>(text)
                                 .ok()
@@ -153,7 +143,11 @@ async fn main() -> anyhow::Result<()> {
                             .await;
                             INBOX_INSERTS_TOTAL.with_label_values(&["loyalty-service","order.completed"]).inc();
                         }
-                        if let Ok(evt) = serde_json::from_str::<CompletedEvent>(text) {
+                        if let Ok(evt) = common_events::decode::<OrderCompletedEvent>(text) {
+                            // Refunds reuse the topic; points are only earned on sales.
+                            if evt.is_refund() {
+                                continue;
+                            }
                             if let Some(cust_id) = evt.customer_id {
                                 handle_completed_event(&evt, cust_id, &db, &producer).await;
                             }
@@ -197,6 +191,7 @@ async fn main() -> anyhow::Result<()> {
         LOYALTY_REGISTRY.register(Box::new(v.clone())).ok();
         v
     });
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     static INBOX_INSERTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
         let v = IntCounterVec::new(
             Opts::new("inbox_inserts_total", "Count of inbox insertions for idempotent consumption"),
@@ -205,6 +200,7 @@ async fn main() -> anyhow::Result<()> {
         LOYALTY_REGISTRY.register(Box::new(v.clone())).ok();
         v
     });
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     static INBOX_DUPLICATES_SKIPPED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
         let v = IntCounterVec::new(
             Opts::new("inbox_duplicates_skipped_total", "Count of duplicate messages skipped due to inbox de-dup"),
@@ -232,20 +228,26 @@ async fn main() -> anyhow::Result<()> {
         (axum::http::StatusCode::OK, String::from_utf8_lossy(&buf).to_string())
     }
 
+    let slo = SloTracker::new("loyalty-service", SloConfig::from_env(), &LOYALTY_REGISTRY);
     let app = Router::new()
         .route("/healthz", get(|| async { "ok" }))
         .route("/metrics", get(metrics))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .route("/points", get(get_points))
+        .route("/tenants/:tenant_id/export", get(export_tenant_data))
         .with_state(state)
         .layer(middleware::from_fn(http_error_metrics))
-        .layer(cors);
+        .layer(cors)
+        .layer(axum::middleware::from_fn(slo.layer()))
+        .layer(axum::middleware::from_fn(common_observability::request_span));
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(8088);
-    let addr: SocketAddr = format!("{host}:{port}").parse()?;
+    let addr = config.http.addr();
     info!(%addr, "Starting loyalty-service HTTP server");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] common_kafka::flush(&producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
@@ -288,11 +290,21 @@ mod tests {
         let producer = dummy_producer();
         let customer_id = Uuid::new_v4();
         let tenant_id = Uuid::new_v4();
-        let evt = CompletedEvent {
+        let evt = OrderCompletedEvent {
+            schema_version: 1,
             order_id: Uuid::new_v4(),
             tenant_id,
-            total: 42.75,
+            items: Vec::new(),
+            total: "42.75".parse().unwrap(),
             customer_id: Some(customer_id),
+            offline: false,
+            payment_method: "cash".into(),
+            return_id: None,
+            location_id: None,
+            employee_id: None,
+            rma_id: None,
+            exchange_return_id: None,
+            business_date: None,
         };
         handle_completed_event(&evt, customer_id, &pool, &producer).await;
 
@@ -308,28 +320,23 @@ mod tests {
     }
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -338,16 +345,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
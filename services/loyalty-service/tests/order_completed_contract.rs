@@ -0,0 +1,26 @@
+//! Consumer side of the `order.completed` contract: every payload order-service publishes (and
+//! the legacy ones still in retention) decodes, and only sales to a known customer earn points.
+
+use common_events::contract::{decode_all, fixture};
+use common_events::{topics, OrderCompletedEvent};
+use loyalty_service::accrual::earned_points;
+
+#[test]
+fn every_fixture_earns_points_only_on_customer_sales() {
+    for (name, evt) in decode_all::<OrderCompletedEvent>() {
+        let points = earned_points(&evt);
+        if evt.is_refund() || evt.customer_id.is_none() {
+            assert_eq!(points, None, "{name}");
+        } else {
+            assert!(points.is_some_and(|p| p > 0), "{name}: a customer sale earns points");
+        }
+    }
+}
+
+#[test]
+fn points_follow_whole_currency_units() {
+    let sale = fixture(topics::ORDER_COMPLETED, "sale").decode::<OrderCompletedEvent>();
+    assert_eq!(earned_points(&sale), Some(13));
+    let legacy = fixture(topics::ORDER_COMPLETED, "v1_legacy_sale").decode::<OrderCompletedEvent>();
+    assert_eq!(earned_points(&legacy), Some(9));
+}
@@ -0,0 +1,7 @@
+-- Per-tenant rounding rules: normalization mode and optional cash rounding increment (e.g. 5 = CHF 0.05)
+CREATE TABLE IF NOT EXISTS rounding_policies (
+    tenant_id UUID PRIMARY KEY,
+    mode TEXT NOT NULL CHECK (mode IN ('half-up', 'truncate', 'bankers')),
+    cash_increment_cents INTEGER NOT NULL DEFAULT 0 CHECK (cash_increment_cents >= 0 AND cash_increment_cents <= 100),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
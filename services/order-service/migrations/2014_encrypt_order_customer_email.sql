@@ -0,0 +1,13 @@
+-- 2014: encrypt the customer email snapshot on orders.
+-- customer_email_encrypted holds a common-crypto envelope sealed with a per-tenant key derived
+-- from ORDER_PII_KEY; customer_email_hash is a keyed hash of the lowercased email for exact-match
+-- lookups. New orders leave customer_email NULL; run the encrypt_order_customer_emails binary to
+-- migrate existing rows.
+
+ALTER TABLE orders
+    ADD COLUMN IF NOT EXISTS customer_email_encrypted BYTEA,
+    ADD COLUMN IF NOT EXISTS customer_email_hash BYTEA;
+
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_customer_email_hash
+    ON orders (tenant_id, customer_email_hash)
+    WHERE customer_email_hash IS NOT NULL;
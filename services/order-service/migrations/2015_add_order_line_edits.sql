@@ -0,0 +1,22 @@
+-- Editing open (PENDING) orders: manager price overrides on lines plus an edit trail
+ALTER TABLE order_items
+  ADD COLUMN IF NOT EXISTS original_unit_price NUMERIC(10,2) NULL,
+  ADD COLUMN IF NOT EXISTS price_override_reason TEXT NULL,
+  ADD COLUMN IF NOT EXISTS price_override_by UUID NULL;
+
+CREATE TABLE IF NOT EXISTS order_line_edits (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    order_item_id UUID NULL,
+    action TEXT NOT NULL,
+    reason_code TEXT NULL,
+    actor_id UUID NULL,
+    before JSONB NULL,
+    after JSONB NULL,
+    total_before NUMERIC(10,2) NOT NULL,
+    total_after NUMERIC(10,2) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_line_edits_order ON order_line_edits (tenant_id, order_id, created_at);
@@ -0,0 +1,38 @@
+-- Manager-approved voids: cashier-initiated requests, approver PINs and per-cashier attribution
+ALTER TABLE orders
+  ADD COLUMN IF NOT EXISTS created_by UUID NULL,
+  ADD COLUMN IF NOT EXISTS void_reason_code TEXT NULL,
+  ADD COLUMN IF NOT EXISTS voided_by UUID NULL;
+
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_created_by ON orders (tenant_id, created_by, created_at);
+
+CREATE TABLE IF NOT EXISTS order_void_requests (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    requested_by UUID NULL,
+    reason_code TEXT NOT NULL,
+    note TEXT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING',
+    decided_by UUID NULL,
+    approval_method TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMPTZ NULL
+);
+
+-- At most one open request per order
+CREATE UNIQUE INDEX IF NOT EXISTS idx_order_void_requests_pending
+    ON order_void_requests (order_id) WHERE status = 'PENDING';
+CREATE INDEX IF NOT EXISTS idx_order_void_requests_tenant_status
+    ON order_void_requests (tenant_id, status, created_at);
+
+-- Argon2 hashes of manager approval PINs (approve at a cashier's terminal without a second login)
+CREATE TABLE IF NOT EXISTS approval_pins (
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    pin_hash TEXT NOT NULL,
+    failed_attempts INT NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, user_id)
+);
@@ -0,0 +1,26 @@
+-- Server-side carts (draft orders) that can be parked on one terminal and recalled on another
+CREATE TABLE IF NOT EXISTS carts (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'OPEN', -- OPEN | PARKED | CONVERTED | EXPIRED
+    label TEXT NULL,
+    recall_code TEXT NULL,
+    store_id UUID NULL,
+    customer_id UUID NULL,
+    customer_name TEXT NULL,
+    discount_percent_bp INT NULL,
+    items JSONB NOT NULL DEFAULT '[]'::jsonb,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_by UUID NULL,
+    parked_by UUID NULL,
+    parked_at TIMESTAMPTZ NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    converted_order_id UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Recall codes only need to be unique among carts currently parked
+CREATE UNIQUE INDEX IF NOT EXISTS idx_carts_recall_code ON carts (tenant_id, recall_code) WHERE status = 'PARKED';
+CREATE INDEX IF NOT EXISTS idx_carts_tenant_status ON carts (tenant_id, status, updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_carts_expiry ON carts (expires_at) WHERE status IN ('OPEN', 'PARKED');
@@ -0,0 +1,8 @@
+-- Order search: record the terminal that rang up each order and index the common filters
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS pos_instance_id UUID NULL;
+
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_status_created ON orders (tenant_id, status, created_at DESC, id);
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_customer_created ON orders (tenant_id, customer_id, created_at DESC) WHERE customer_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_payment_created ON orders (tenant_id, payment_method, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_pos_created ON orders (tenant_id, pos_instance_id, created_at DESC) WHERE pos_instance_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_total ON orders (tenant_id, total, id);
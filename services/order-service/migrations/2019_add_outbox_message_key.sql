@@ -0,0 +1,2 @@
+-- Outbox: Kafka key per row (the aggregate id). NULL on rows queued earlier; the relay falls back to tenant_id
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
@@ -0,0 +1,7 @@
+-- Chargeback state mirrored from payment-service (`payment.dispute.updated`) for disputed-revenue reporting
+ALTER TABLE orders
+  ADD COLUMN IF NOT EXISTS dispute_status TEXT NULL,
+  ADD COLUMN IF NOT EXISTS disputed_amount NUMERIC(10,2) NULL,
+  ADD COLUMN IF NOT EXISTS dispute_updated_at TIMESTAMPTZ NULL;
+
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_dispute ON orders (tenant_id, dispute_updated_at) WHERE dispute_status IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Cash rounding applied at tender time. `total` is what the customer paid; for cash tenders it
+-- includes this adjustment (e.g. +0.02 when 12.33 is rounded to 12.35), for card it is zero.
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS rounding_adjustment NUMERIC(10,2) NOT NULL DEFAULT 0;
@@ -0,0 +1,17 @@
+-- Per-tenant tip prompts shown at checkout. `suggestions` are basis points of the sale total in
+-- 'percentage' mode (1800 = 18%) and cents in 'fixed' mode. Card tips can be adjusted for
+-- `adjust_window_hours` after the sale.
+CREATE TABLE IF NOT EXISTS tip_settings (
+    tenant_id UUID PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    mode TEXT NOT NULL CHECK (mode IN ('percentage', 'fixed')),
+    suggestions INTEGER[] NOT NULL DEFAULT '{}',
+    allow_custom BOOLEAN NOT NULL DEFAULT TRUE,
+    adjust_window_hours INTEGER NOT NULL DEFAULT 24 CHECK (adjust_window_hours >= 0 AND adjust_window_hours <= 168),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Tips stay out of `orders.total` (the taxable sale). A payment's `amount` includes its tip.
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS tip_amount NUMERIC(10,2) NOT NULL DEFAULT 0;
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS tip_amount NUMERIC(10,2) NOT NULL DEFAULT 0;
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS tip_adjusted_at TIMESTAMPTZ;
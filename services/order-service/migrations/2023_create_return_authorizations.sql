@@ -0,0 +1,42 @@
+-- Return authorizations (RMAs): returns approved against order lines, then received and inspected
+CREATE TABLE IF NOT EXISTS return_authorizations (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'AUTHORIZED' CHECK (status IN ('AUTHORIZED', 'RECEIVED', 'CANCELLED')),
+    restocking_fee_bps INT NOT NULL DEFAULT 0 CHECK (restocking_fee_bps >= 0 AND restocking_fee_bps <= 10000),
+    note TEXT NULL,
+    -- End of the expected-return window; the RMA can't be received after it
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_by UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    received_by UUID NULL,
+    received_at TIMESTAMPTZ NULL,
+    return_id UUID NULL REFERENCES order_returns(id),
+    refund_total NUMERIC(10,2) NULL,
+    restocking_fee NUMERIC(10,2) NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_return_authorizations_tenant_status
+    ON return_authorizations (tenant_id, status, created_at);
+CREATE INDEX IF NOT EXISTS idx_return_authorizations_order ON return_authorizations (order_id);
+
+CREATE TABLE IF NOT EXISTS return_authorization_items (
+    id UUID PRIMARY KEY,
+    rma_id UUID NOT NULL REFERENCES return_authorizations(id) ON DELETE CASCADE,
+    tenant_id UUID NOT NULL,
+    order_item_id UUID NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    reason_code TEXT NOT NULL,
+    unit_price NUMERIC(10,2) NOT NULL,
+    -- Set by receive-and-inspect
+    received_quantity INT NULL CHECK (received_quantity >= 0),
+    disposition TEXT NULL CHECK (disposition IN ('restock', 'damage')),
+    restocked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_return_authorization_items_rma ON return_authorization_items (rma_id);
+CREATE INDEX IF NOT EXISTS idx_return_authorization_items_order_item ON return_authorization_items (order_item_id);
+CREATE INDEX IF NOT EXISTS idx_return_authorization_items_tenant_product
+    ON return_authorization_items (tenant_id, product_id);
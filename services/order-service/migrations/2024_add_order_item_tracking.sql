@@ -0,0 +1,4 @@
+-- Serials and lot captured at the till for serial/lot-tracked products (see inventory 4013).
+ALTER TABLE order_items
+    ADD COLUMN IF NOT EXISTS serial_numbers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS lot_code TEXT NULL;
@@ -0,0 +1,6 @@
+-- Weighed lines: net scale reading (kg/lb, 3 decimals), its unit and the tare subtracted.
+-- `unit_price` on these lines is per `uom`; `quantity` stays 1 per reading.
+ALTER TABLE order_items
+    ADD COLUMN IF NOT EXISTS measured_quantity NUMERIC(12,3) NULL CHECK (measured_quantity IS NULL OR measured_quantity > 0),
+    ADD COLUMN IF NOT EXISTS uom TEXT NULL,
+    ADD COLUMN IF NOT EXISTS tare_weight NUMERIC(12,3) NULL;
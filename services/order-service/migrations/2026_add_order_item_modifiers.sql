@@ -0,0 +1,4 @@
+-- Menu modifiers chosen on each line, snapshotted at checkout as
+-- [{modifier_id, group, name, price_delta}]. Deltas are already included in unit_price.
+ALTER TABLE order_items
+    ADD COLUMN IF NOT EXISTS modifiers JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,44 @@
+-- Cash drawer sessions: one open drawer per register. expected_cash is worked out when the drawer
+-- is closed (opening float plus net cash taken, less cash refunds); variance = counted - expected.
+CREATE TABLE IF NOT EXISTS drawer_sessions (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    store_id UUID NOT NULL,
+    pos_instance_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'CLOSED')),
+    opening_float NUMERIC(10,2) NOT NULL CHECK (opening_float >= 0),
+    opened_by UUID NULL,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    counted_cash NUMERIC(10,2) NULL,
+    expected_cash NUMERIC(10,2) NULL,
+    variance NUMERIC(10,2) NULL,
+    closed_by UUID NULL,
+    closed_at TIMESTAMPTZ NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_drawer_sessions_open
+    ON drawer_sessions (tenant_id, pos_instance_id) WHERE status = 'OPEN';
+CREATE INDEX IF NOT EXISTS idx_drawer_sessions_store ON drawer_sessions (tenant_id, store_id, opened_at DESC);
+
+-- Offline queue depth as last reported by each register; zero means drained.
+CREATE TABLE IF NOT EXISTS pos_offline_queues (
+    tenant_id UUID NOT NULL,
+    pos_instance_id UUID NOT NULL,
+    store_id UUID NOT NULL,
+    pending_orders INT NOT NULL CHECK (pending_orders >= 0),
+    reported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, pos_instance_id)
+);
+
+-- Closed business days. A row locks the store's date against new sales, refunds and tip
+-- adjustments; z_report is the report as generated at close.
+CREATE TABLE IF NOT EXISTS business_day_closes (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    store_id UUID NOT NULL,
+    business_date DATE NOT NULL,
+    z_report JSONB NOT NULL,
+    closed_by UUID NULL,
+    closed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, store_id, business_date)
+);
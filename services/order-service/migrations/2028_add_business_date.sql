@@ -0,0 +1,41 @@
+-- When a store's business day ends, as wall-clock time in `timezone` (an IANA name). A store that
+-- closes at 02:00 books sales made before 2am on the previous business date. close_time is
+-- limited to the morning so a day can only run late, never end early. Stores without a row use
+-- UTC midnight.
+CREATE TABLE IF NOT EXISTS store_business_days (
+    tenant_id UUID NOT NULL,
+    store_id UUID NOT NULL,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    close_time TIME NOT NULL DEFAULT '00:00' CHECK (close_time < '12:00'),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, store_id)
+);
+
+-- Business date of instant `at` at a store (UTC date when the store has no settings or no store).
+CREATE OR REPLACE FUNCTION business_date_at(p_tenant_id UUID, p_store_id UUID, at TIMESTAMPTZ)
+RETURNS DATE
+LANGUAGE sql STABLE AS $$
+    SELECT COALESCE(
+        (SELECT ((at AT TIME ZONE s.timezone) - s.close_time::interval)::date
+         FROM store_business_days s
+         WHERE s.tenant_id = p_tenant_id AND s.store_id = p_store_id),
+        (at AT TIME ZONE 'UTC')::date)
+$$;
+
+-- Assigned once when the row is created, so later changes to a store's close time don't move
+-- existing sales. Rows from before this migration get the UTC date they were created on.
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS business_date DATE;
+UPDATE orders SET business_date = (created_at AT TIME ZONE 'UTC')::date WHERE business_date IS NULL;
+ALTER TABLE orders ALTER COLUMN business_date SET DEFAULT ((NOW() AT TIME ZONE 'UTC')::date);
+ALTER TABLE orders ALTER COLUMN business_date SET NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_orders_store_business_date ON orders (tenant_id, store_id, business_date);
+
+ALTER TABLE order_returns ADD COLUMN IF NOT EXISTS business_date DATE;
+UPDATE order_returns SET business_date = (created_at AT TIME ZONE 'UTC')::date WHERE business_date IS NULL;
+ALTER TABLE order_returns ALTER COLUMN business_date SET DEFAULT ((NOW() AT TIME ZONE 'UTC')::date);
+ALTER TABLE order_returns ALTER COLUMN business_date SET NOT NULL;
+
+ALTER TABLE drawer_sessions ADD COLUMN IF NOT EXISTS business_date DATE;
+UPDATE drawer_sessions SET business_date = (opened_at AT TIME ZONE 'UTC')::date WHERE business_date IS NULL;
+ALTER TABLE drawer_sessions ALTER COLUMN business_date SET DEFAULT ((NOW() AT TIME ZONE 'UTC')::date);
+ALTER TABLE drawer_sessions ALTER COLUMN business_date SET NOT NULL;
@@ -0,0 +1,21 @@
+-- One row per checkout, keyed by the order id it creates. The request moves it through
+-- started -> inventory_reserved -> payment_authorized -> completed, and the order insert marks it
+-- completed in the same transaction. A checkout that fails part-way releases what it reserved and
+-- voids what it authorized (compensated). When that compensation fails, or the request never
+-- finishes, the sweeper retries it from `compensating` until `failed`.
+CREATE TABLE IF NOT EXISTS checkout_sagas (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'started'
+        CHECK (status IN ('started', 'inventory_reserved', 'payment_authorized', 'completed', 'compensating', 'compensated', 'failed')),
+    inventory_reserved BOOLEAN NOT NULL DEFAULT FALSE,
+    payment_intent_id TEXT NULL,
+    last_error TEXT NULL,
+    compensation_attempts INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_checkout_sagas_open
+    ON checkout_sagas (updated_at) WHERE status NOT IN ('completed', 'compensated', 'failed');
+CREATE INDEX IF NOT EXISTS idx_checkout_sagas_tenant ON checkout_sagas (tenant_id, created_at DESC);
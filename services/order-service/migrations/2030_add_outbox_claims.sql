@@ -0,0 +1,8 @@
+-- Outbox row claims, so several relay workers can run at once. A worker stamps the rows it is
+-- sending with its id and the time; rows whose claim is older than the lease are taken over.
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS claimed_by TEXT;
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMPTZ;
+
+-- Earlier unpublished rows of the same key hold back later ones.
+CREATE INDEX IF NOT EXISTS idx_outbox_unpublished_key
+    ON outbox ((COALESCE(message_key, tenant_id)), id) WHERE published_at IS NULL;
@@ -0,0 +1,20 @@
+-- Poison rows: after OUTBOX_MAX_RETRIES failed sends a row moves here, keeping its id, so it
+-- stops holding back later rows of its key. Admins requeue it via /admin/outbox/dead/:id/requeue.
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS last_error TEXT;
+
+CREATE TABLE IF NOT EXISTS outbox_dead (
+    id BIGINT PRIMARY KEY,
+    tenant_id TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    message_key TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    retry_count INT NOT NULL,
+    last_error TEXT,
+    dead_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_outbox_dead_tenant ON outbox_dead (tenant_id, dead_at DESC);
+
+-- Retention purge of published rows.
+CREATE INDEX IF NOT EXISTS idx_outbox_published ON outbox (published_at) WHERE published_at IS NOT NULL;
@@ -0,0 +1,8 @@
+-- Retention: orders past ORDER_RETENTION_MONTHS lose their customer linkage and contact snapshot
+-- (see order_service::retention); anonymized_at records when that happened.
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS anonymized_at TIMESTAMPTZ NULL;
+
+-- The retention job scans only orders still holding customer data.
+CREATE INDEX IF NOT EXISTS idx_orders_retention_candidates ON orders (created_at)
+    WHERE customer_id IS NOT NULL OR customer_name IS NOT NULL OR customer_email IS NOT NULL
+       OR customer_email_encrypted IS NOT NULL OR customer_email_hash IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Largest tip a tenant accepts, in basis points of the sale (10000 = 100%). Applies at checkout
+-- and to later card tip adjustments.
+ALTER TABLE tip_settings
+    ADD COLUMN IF NOT EXISTS max_tip_bps INTEGER NOT NULL DEFAULT 10000
+        CHECK (max_tip_bps > 0 AND max_tip_bps <= 10000);
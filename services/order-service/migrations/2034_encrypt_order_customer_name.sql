@@ -0,0 +1,15 @@
+-- 2034: encrypt the customer name snapshot on orders and record which key sealed each row.
+-- customer_name_encrypted is sealed like customer_email_encrypted; customer_name_hash is a keyed
+-- hash of the lowercased name for exact-match lookups. pii_key_id names the ORDER_PII_KEY that
+-- sealed both columns; NULL marks rows sealed before key ids were recorded. Run the
+-- encrypt_order_customer_emails binary to migrate plaintext names and to re-seal rows after a
+-- key rotation.
+
+ALTER TABLE orders
+    ADD COLUMN IF NOT EXISTS customer_name_encrypted BYTEA,
+    ADD COLUMN IF NOT EXISTS customer_name_hash BYTEA,
+    ADD COLUMN IF NOT EXISTS pii_key_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_customer_name_hash
+    ON orders (tenant_id, customer_name_hash)
+    WHERE customer_name_hash IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Roles the PIN holder had when they set the PIN. PIN approvals are checked against these (and the
+-- tenant's current capability policy) because no token accompanies them. PINs set before this
+-- migration carry no roles and must be set again before they approve anything.
+ALTER TABLE approval_pins
+    ADD COLUMN IF NOT EXISTS roles TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,6 @@
+-- Compensation claims. The sweeper and a failing checkout move a saga to `compensating` and set
+-- `claimed_until` in one committed statement, then call inventory and payment with no
+-- transaction open. Nobody else compensates a saga while its claim runs; an expired claim (the
+-- process died mid-compensation) lets the sweeper take it over.
+ALTER TABLE checkout_sagas
+    ADD COLUMN IF NOT EXISTS claimed_until TIMESTAMPTZ;
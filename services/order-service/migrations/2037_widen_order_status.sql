@@ -0,0 +1,4 @@
+-- `CONDITIONALLY_APPROVED` (an order paid in payment-service stand-in, waiting for the provider)
+-- does not fit the original VARCHAR(20).
+ALTER TABLE orders
+    ALTER COLUMN status TYPE VARCHAR(32);
@@ -1,10 +1,8 @@
-use std::env;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Context;
-use axum::{middleware, routing::{get, post}, Router};
-use axum::http::{header::{ACCEPT, CONTENT_TYPE}, HeaderName, HeaderValue, Method, StatusCode};
+use axum::{middleware, routing::{delete, get, patch, post, put}, Router};
+use axum::http::{header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MATCH}, HeaderName, HeaderValue, Method, StatusCode};
 use once_cell::sync::Lazy;
 use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
 use reqwest::Client;
@@ -14,11 +12,31 @@ use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 
 use common_auth::{JwtConfig, JwtVerifier};
+use common_config::JwtSettings;
 
 use crate::order_handlers::{
     clear_offline_orders, create_order, get_order, get_order_receipt, list_orders, list_returns, compute_order,
     refund_order, void_order, create_order_from_skus,
     list_tax_rate_overrides, upsert_tax_rate_override, get_return_policy, upsert_return_policy, issue_return_override,
+    export_tenant_data, provision_tenant, get_rounding_policy, upsert_rounding_policy,
+};
+use crate::carts::{checkout_cart, create_cart, get_cart, list_carts, park_cart, recall_cart, update_cart};
+use crate::reorders::reorder_for_customer;
+use crate::drawers::{close_drawer, list_drawers, open_drawer, report_offline_queue};
+use crate::day_close::{close_day, get_day_close, get_store_business_day, preview_day_close, upsert_store_business_day};
+use crate::offline_bundle::get_offline_bundle_content;
+use crate::tips::{adjust_order_tip, get_tip_settings, get_tip_suggestions, upsert_tip_settings};
+use crate::order_edits::{add_order_line, remove_order_line, update_order_line};
+use crate::order_disputes::get_dispute_report;
+use crate::outbox::{list_dead_outbox, requeue_dead_outbox};
+use crate::tenant_diagnostics::tenant_diagnostics;
+use crate::order_rmas::{
+    cancel_return_authorization, create_return_authorization, get_return_authorization, get_return_reason_report,
+    list_return_authorizations, receive_return_authorization,
+};
+use crate::order_voids::{
+    approve_void_request, get_void_rate_report, list_void_requests, reject_void_request, request_void,
+    revoke_approval_pin, set_approval_pin,
 };
 
 // --- Error metrics (mirrors product/inventory services) ---
@@ -51,6 +69,22 @@ static POS_PRINT_GAUGES: Lazy<IntGaugeVec> = Lazy::new(|| {
     v
 });
 
+// Loss prevention: void volume by reason code. Per-cashier rates come from the void-rate report;
+// tenant and cashier ids are unbounded, so they go to the log rather than onto the series.
+static ORDER_VOIDS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("order_voids_total", "Orders voided, by reason code"),
+        &["reason_code"],
+    ).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+pub(crate) fn record_order_void(tenant_id: uuid::Uuid, cashier_id: Option<uuid::Uuid>, reason_code: &str) {
+    tracing::info!(tenant_id = %tenant_id, cashier_id = ?cashier_id, reason_code, "Order voided");
+    ORDER_VOIDS_TOTAL.with_label_values(&[reason_code]).inc();
+}
+
 pub async fn http_error_metrics(req: axum::http::Request<axum::body::Body>, next: axum::middleware::Next) -> axum::response::Response {
     let resp = next.run(req).await;
     let status = resp.status();
@@ -71,6 +105,8 @@ pub struct AppState {
     pub inventory_base_url: String,
     pub payment_base_url: String,
     pub enable_payment_intents: bool,
+    /// Keys for encrypted customer contact snapshots (`ORDER_PII_KEY`); see [`crate::pii`].
+    pub pii_key: Option<Arc<crate::pii::PiiKeyring>>,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     pub kafka_producer: rdkafka::producer::FutureProducer,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -81,31 +117,33 @@ impl axum::extract::FromRef<AppState> for Arc<JwtVerifier> {
     fn from_ref(state: &AppState) -> Self { state.jwt_verifier.clone() }
 }
 
-pub async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() { config = config.with_leeway(leeway); }
+pub async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
+
     let mut builder = JwtVerifier::builder(config);
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
-        builder = builder.with_rsa_pem("local-dev", pem.as_bytes()).map_err(anyhow::Error::from)?;
+        builder = builder
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
+            .map_err(anyhow::Error::from)?;
     }
+
     let verifier = builder.build().await.map_err(anyhow::Error::from)?;
     info!("JWT verifier initialised");
     Ok(Arc::new(verifier))
 }
 
-pub fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+pub fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else { return; };
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS").ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(300).max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -133,11 +171,13 @@ pub fn build_router(state: AppState) -> Router {
             allowed_origins.iter().filter_map(|o| o.parse::<HeaderValue>().ok()).collect::<Vec<_>>(),
         ))
         .allow_methods([
-            Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS,
+            Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS,
         ])
         .allow_headers([
-            ACCEPT, CONTENT_TYPE, HeaderName::from_static("authorization"), HeaderName::from_static("x-tenant-id"),
-        ]);
+            ACCEPT, CONTENT_TYPE, IF_MATCH, HeaderName::from_static("authorization"), HeaderName::from_static("x-tenant-id"),
+        ])
+        // Cart updates echo the ETag back in If-Match, so browsers must be able to read it.
+        .expose_headers([ETAG]);
 
     async fn audit_search() -> (StatusCode, &'static str) { (StatusCode::NOT_IMPLEMENTED, "audit search not implemented") }
     async fn audit_metrics(axum::extract::State(state): axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
@@ -165,7 +205,6 @@ pub fn build_router(state: AppState) -> Router {
     struct PosMetricEntry { name: String, value: i64 }
     #[derive(serde::Deserialize)]
     struct PosTelemetryPayload {
-        ts: i64,
         #[serde(default)]
         labels: serde_json::Value,
         #[serde(default)]
@@ -237,24 +276,62 @@ pub fn build_router(state: AppState) -> Router {
         .route("/orders", post(create_order).get(list_orders))
         .route("/orders/sku", post(create_order_from_skus))
         .route("/orders/compute", post(compute_order))
+        .route("/carts", post(create_cart).get(list_carts))
+        .route("/carts/recall", post(recall_cart))
+        .route("/carts/:cart_id", get(get_cart).put(update_cart))
+        .route("/carts/:cart_id/park", post(park_cart))
+        .route("/carts/:cart_id/checkout", post(checkout_cart))
+        .route("/customers/:customer_id/reorder", post(reorder_for_customer))
         .route("/orders/:order_id", get(get_order))
+        .route("/orders/:order_id/items", post(add_order_line))
+        .route("/orders/:order_id/items/:item_id", patch(update_order_line).delete(remove_order_line))
     .route("/orders/:order_id/receipt", get(get_order_receipt))
-    .route("/orders/:order_id/exchange", post(crate::order_handlers::exchange_order))
+    .route("/orders/:order_id/exchange", post(crate::order_exchanges::exchange_order))
         .route("/orders/offline/clear", post(clear_offline_orders))
         .route("/orders/:order_id/void", post(void_order))
+        .route("/orders/:order_id/tip", post(adjust_order_tip))
+        .route("/tips/suggestions", get(get_tip_suggestions))
+        .route("/offline/bundle", get(get_offline_bundle_content))
+        .route("/orders/:order_id/void_requests", post(request_void))
+        .route("/orders/void_requests", get(list_void_requests))
+        .route("/orders/void_requests/:request_id/approve", post(approve_void_request))
+        .route("/orders/void_requests/:request_id/reject", post(reject_void_request))
+        .route("/admin/approval_pin", put(set_approval_pin))
+        .route("/admin/outbox/dead", get(list_dead_outbox))
+        .route("/admin/outbox/dead/:id/requeue", post(requeue_dead_outbox))
+        .route("/admin/approval_pins/:user_id", delete(revoke_approval_pin))
+        .route("/reports/void_rate", get(get_void_rate_report))
+        .route("/reports/disputes", get(get_dispute_report))
         .route("/orders/refund", post(refund_order))
         // Reports
         .route("/reports/settlement", get(crate::order_handlers::get_settlement_report))
         .route("/returns", get(list_returns))
+        .route("/returns/authorizations", post(create_return_authorization).get(list_return_authorizations))
+        .route("/returns/authorizations/:rma_id", get(get_return_authorization))
+        .route("/returns/authorizations/:rma_id/receive", post(receive_return_authorization))
+        .route("/returns/authorizations/:rma_id/cancel", post(cancel_return_authorization))
+        .route("/reports/return_reasons", get(get_return_reason_report))
         .route("/admin/tax_rate_overrides", get(list_tax_rate_overrides).post(upsert_tax_rate_override))
     .route("/admin/return_policies", get(get_return_policy).post(upsert_return_policy))
+        .route("/admin/rounding_policy", get(get_rounding_policy).post(upsert_rounding_policy))
+        .route("/admin/tip_settings", get(get_tip_settings).post(upsert_tip_settings))
     .route("/admin/overrides/returns", post(issue_return_override))
+        .route("/tenants/:tenant_id/export", get(export_tenant_data))
+        .route("/tenants/:tenant_id/provision", post(provision_tenant))
+        .route("/tenants/:tenant_id/diagnostics", get(tenant_diagnostics))
         .route("/audit/events", get(audit_search))
         .route("/internal/audit_metrics", get(audit_metrics))
     .route("/internal/metrics", get(metrics))
     .route("/metrics", get(metrics))
     .route("/pos/telemetry", post(ingest_pos_telemetry))
+        .route("/pos/offline_queue", put(report_offline_queue))
+        .route("/drawers", post(open_drawer).get(list_drawers))
+        .route("/drawers/:drawer_id/close", post(close_drawer))
+        .route("/stores/:store_id/close", get(preview_day_close).post(close_day))
+        .route("/stores/:store_id/closes/:business_date", get(get_day_close))
+        .route("/stores/:store_id/business_day", get(get_store_business_day).put(upsert_store_business_day))
         .with_state(state)
         .layer(cors)
         .layer(middleware::from_fn(http_error_metrics))
+        .layer(middleware::from_fn(common_observability::request_span))
 }
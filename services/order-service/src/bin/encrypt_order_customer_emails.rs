@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use common_crypto::EncryptedColumn;
+use order_service::pii::{
+    normalize_email, seal_customer_email, seal_customer_name, PiiKeyring, CUSTOMER_EMAIL_FIELD,
+    CUSTOMER_NAME_FIELD,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Encrypt plaintext customer names and emails stored on orders, and re-seal rows \
+             still under a retired ORDER_PII_KEY",
+    long_about = None
+)]
+struct Options {
+    /// Limit processing to a single tenant
+    #[arg(long = "tenant", value_name = "UUID")]
+    tenant: Option<Uuid>,
+
+    /// Number of rows to scan per batch
+    #[arg(long = "batch-size", default_value_t = 200)]
+    batch_size: i64,
+
+    /// Print stats without writing any changes
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct OrderRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    customer_name: Option<String>,
+    customer_email: Option<String>,
+    customer_name_encrypted: Option<EncryptedColumn<String>>,
+    customer_email_encrypted: Option<EncryptedColumn<String>>,
+    pii_key_id: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Options::parse();
+    if opts.batch_size <= 0 {
+        return Err(anyhow!("--batch-size must be positive"));
+    }
+
+    let database_url =
+        std::env::var("DATABASE_URL").context("DATABASE_URL must be set for encryption script")?;
+    let keys = PiiKeyring::from_env()?;
+
+    let pool = PgPool::connect(&database_url).await?;
+    let mut cursor = Uuid::nil();
+    let mut total = 0usize;
+
+    loop {
+        let rows = fetch_batch(&pool, &keys, opts.tenant, cursor, opts.batch_size).await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        cursor = last.id;
+        total += rows.len();
+        if opts.dry_run {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        for row in &rows {
+            let name = current_value(&keys, row, CUSTOMER_NAME_FIELD, &row.customer_name, &row.customer_name_encrypted)?;
+            let email = current_value(&keys, row, CUSTOMER_EMAIL_FIELD, &row.customer_email, &row.customer_email_encrypted)?
+                .and_then(|email| normalize_email(&email));
+            let (name_encrypted, name_hash) = name
+                .map(|name| seal_customer_name(keys.active(), row.tenant_id, &name))
+                .transpose()
+                .map_err(|err| anyhow!("Failed to encrypt name for order {}: {err}", row.id))?
+                .unzip();
+            let (email_encrypted, email_hash) = email
+                .map(|email| seal_customer_email(keys.active(), row.tenant_id, &email))
+                .transpose()
+                .map_err(|err| anyhow!("Failed to encrypt email for order {}: {err}", row.id))?
+                .unzip();
+            sqlx::query(
+                "UPDATE orders
+                 SET customer_name_encrypted = $1,
+                     customer_name_hash = $2,
+                     customer_email_encrypted = $3,
+                     customer_email_hash = $4,
+                     pii_key_id = $5,
+                     customer_name = NULL,
+                     customer_email = NULL
+                 WHERE id = $6 AND tenant_id = $7 AND pii_key_id IS NOT DISTINCT FROM $8",
+            )
+            .bind(name_encrypted)
+            .bind(name_hash)
+            .bind(email_encrypted)
+            .bind(email_hash)
+            .bind(keys.active_id())
+            .bind(row.id)
+            .bind(row.tenant_id)
+            .bind(&row.pii_key_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        println!("Sealed {} rows (total {total})", rows.len());
+    }
+
+    if opts.dry_run {
+        println!("Dry run: {total} orders hold plaintext or retired-key customer details");
+    } else {
+        println!("Encryption complete. Updated {total} orders.");
+    }
+    Ok(())
+}
+
+/// The value to seal under the active key: the decrypted value, or legacy plaintext.
+fn current_value(
+    keys: &PiiKeyring,
+    row: &OrderRow,
+    field: &str,
+    plain: &Option<String>,
+    sealed: &Option<EncryptedColumn<String>>,
+) -> Result<Option<String>> {
+    let Some(sealed) = sealed else {
+        return Ok(plain.clone());
+    };
+    keys.open(row.tenant_id, row.pii_key_id.as_deref(), field, sealed)
+        .map(Some)
+        .map_err(|err| anyhow!("Failed to decrypt {field} for order {}: {err}", row.id))
+}
+
+async fn fetch_batch(
+    pool: &PgPool,
+    keys: &PiiKeyring,
+    tenant: Option<Uuid>,
+    after: Uuid,
+    batch_size: i64,
+) -> Result<Vec<OrderRow>> {
+    Ok(sqlx::query_as::<_, OrderRow>(
+        "SELECT id, tenant_id, customer_name, customer_email, customer_name_encrypted,
+                customer_email_encrypted, pii_key_id
+         FROM orders
+         WHERE id > $1
+           AND ($2::uuid IS NULL OR tenant_id = $2)
+           AND (customer_name IS NOT NULL
+                OR customer_email IS NOT NULL
+                OR ((customer_name_encrypted IS NOT NULL OR customer_email_encrypted IS NOT NULL)
+                    AND pii_key_id IS DISTINCT FROM $4))
+         ORDER BY id
+         LIMIT $3",
+    )
+    .bind(after)
+    .bind(tenant)
+    .bind(batch_size)
+    .bind(keys.active_id())
+    .fetch_all(pool)
+    .await?)
+}
@@ -0,0 +1,458 @@
+//! Server-side carts (draft orders) for park-and-recall.
+//!
+//! A cart is tenant-scoped and lives in `carts` until it is checked out or expires. Parking a
+//! cart issues a short recall code so another terminal can pick it up. Carts hold SKUs and
+//! quantities only: pricing and inventory reservation both happen at checkout, which hands the
+//! cart to the SKU order path.
+
+use axum::extract::{Path, Query, State};
+use axum::{http::HeaderMap, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use common_auth::AuthContext; // bearer token forwarded to inventory-service at checkout
+use common_http_errors::etag::{etag_header, if_match_version, version_conflict};
+use common_http_errors::ApiError;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlJson;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::order_handlers::{create_order_from_skus, NewOrderFromSku, NewOrderSkuItem, Order, PaymentRequest};
+use crate::AppState;
+
+const CART_COLUMNS: &str = "id, status, label, recall_code, store_id, customer_id, customer_name, discount_percent_bp, items, version, created_by, parked_by, parked_at, expires_at, converted_order_id, created_at, updated_at";
+/// Unambiguous characters for recall codes (no 0/O, 1/I/L).
+const RECALL_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+const RECALL_CODE_LEN: usize = 6;
+const MAX_CART_LINES: usize = 200;
+
+/// Idle lifetime of an open or parked cart (`CART_TTL_SECS`, default one 8h shift).
+fn cart_ttl_secs() -> i64 {
+    std::env::var("CART_TTL_SECS").ok().and_then(|v| v.parse::<i64>().ok()).filter(|v| *v > 0).unwrap_or(8 * 3600)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CartItem {
+    pub sku: String,
+    pub quantity: i32,
+    /// Menu modifier options chosen for this line; checked against forced groups at checkout.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, sqlx::FromRow, Debug)]
+pub struct Cart {
+    pub id: Uuid,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recall_code: Option<String>,
+    pub store_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discount_percent_bp: Option<i32>,
+    pub items: SqlJson<Vec<CartItem>>,
+    pub version: i64,
+    pub created_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parked_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parked_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converted_order_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct CartPayload {
+    #[serde(default)]
+    pub items: Vec<CartItem>,
+    pub label: Option<String>,
+    pub store_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    pub customer_name: Option<String>,
+    pub discount_percent_bp: Option<i32>,
+}
+
+pub(crate) fn ensure_cart_role(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::Support | Role::Cashier)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+fn db_error(context: &'static str, trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) },
+    }
+}
+
+/// Trim SKUs, merge duplicate lines (same SKU and modifiers) and reject empty or non-positive entries.
+fn normalize_items(items: Vec<CartItem>, trace_id: Option<Uuid>) -> Result<Vec<CartItem>, ApiError> {
+    let mut merged: Vec<CartItem> = Vec::with_capacity(items.len());
+    for mut item in items {
+        item.modifier_ids.sort();
+        let sku = item.sku.trim();
+        if sku.is_empty() || item.quantity <= 0 {
+            return Err(ApiError::BadRequest { code: "invalid_cart_item", trace_id, message: Some("Cart items need a SKU and a positive quantity".into()) });
+        }
+        match merged.iter_mut().find(|existing| existing.sku == sku && existing.modifier_ids == item.modifier_ids) {
+            Some(existing) => existing.quantity = existing.quantity.saturating_add(item.quantity),
+            None => merged.push(CartItem { sku: sku.to_string(), quantity: item.quantity, modifier_ids: item.modifier_ids }),
+        }
+    }
+    if merged.len() > MAX_CART_LINES {
+        return Err(ApiError::BadRequest { code: "cart_too_large", trace_id, message: Some(format!("Carts are limited to {MAX_CART_LINES} lines")) });
+    }
+    Ok(merged)
+}
+
+fn generate_recall_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..RECALL_CODE_LEN)
+        .map(|_| RECALL_CODE_ALPHABET[rng.gen_range(0..RECALL_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Load a live cart, mapping missing and expired carts to their API errors.
+async fn load_cart(db: &PgPool, tenant_id: Uuid, cart_id: Uuid, trace_id: Option<Uuid>) -> Result<Cart, ApiError> {
+    let cart = sqlx::query_as::<_, Cart>(&format!("SELECT {CART_COLUMNS} FROM carts WHERE id = $1 AND tenant_id = $2"))
+        .bind(cart_id)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error("Failed to load cart", trace_id))?
+        .ok_or(ApiError::NotFound { code: "cart_not_found", trace_id })?;
+    if cart.status == "EXPIRED" || (matches!(cart.status.as_str(), "OPEN" | "PARKED") && cart.expires_at <= Utc::now()) {
+        return Err(ApiError::Conflict { code: "cart_expired", trace_id, message: None });
+    }
+    Ok(cart)
+}
+
+fn require_status(cart: &Cart, status: &str, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+    if cart.status != status {
+        return Err(ApiError::Conflict { code: "invalid_cart_status", trace_id, message: Some(format!("Cart is {} (expected {})", cart.status, status)) });
+    }
+    Ok(())
+}
+
+pub async fn create_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(payload): Json<CartPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_cart_role(&sec)?;
+    let cart = insert_cart(&state.db, &sec, payload).await?;
+    Ok((etag_header(cart.version), Json(cart)))
+}
+
+/// Insert a fresh OPEN cart owned by the caller; shared with the reorder flow.
+pub(crate) async fn insert_cart(db: &PgPool, sec: &SecurityContext, payload: CartPayload) -> Result<Cart, ApiError> {
+    let items = normalize_items(payload.items, sec.trace_id)?;
+    sqlx::query_as::<_, Cart>(&format!(
+        "INSERT INTO carts (id, tenant_id, label, store_id, customer_id, customer_name, discount_percent_bp, items, created_by, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + make_interval(secs => $10))
+         RETURNING {CART_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(sec.tenant_id)
+    .bind(payload.label.as_deref())
+    .bind(payload.store_id)
+    .bind(payload.customer_id)
+    .bind(payload.customer_name.as_deref())
+    .bind(payload.discount_percent_bp)
+    .bind(SqlJson(&items))
+    .bind(sec.actor.id)
+    .bind(cart_ttl_secs() as f64)
+    .fetch_one(db)
+    .await
+    .map_err(db_error("Failed to create cart", sec.trace_id))
+}
+
+pub async fn get_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(cart_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_cart_role(&sec)?;
+    let cart = load_cart(&state.db, sec.tenant_id, cart_id, sec.trace_id).await?;
+    Ok((etag_header(cart.version), Json(cart)))
+}
+
+/// Replace an open cart's contents. Requires `If-Match` since several terminals may hold it.
+pub async fn update_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    headers: HeaderMap,
+    Path(cart_id): Path<Uuid>,
+    Json(payload): Json<CartPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_cart_role(&sec)?;
+    let expected_version = if_match_version(&headers, sec.trace_id)?;
+    let items = normalize_items(payload.items, sec.trace_id)?;
+    let current = load_cart(&state.db, sec.tenant_id, cart_id, sec.trace_id).await?;
+    require_status(&current, "OPEN", sec.trace_id)?;
+    let cart = sqlx::query_as::<_, Cart>(&format!(
+        "UPDATE carts SET label = $3, store_id = $4, customer_id = $5, customer_name = $6, discount_percent_bp = $7, items = $8,
+             version = version + 1, updated_at = NOW(), expires_at = NOW() + make_interval(secs => $9)
+         WHERE id = $1 AND tenant_id = $2 AND status = 'OPEN' AND ($10::bigint IS NULL OR version = $10)
+         RETURNING {CART_COLUMNS}"
+    ))
+    .bind(cart_id)
+    .bind(sec.tenant_id)
+    .bind(payload.label.as_deref())
+    .bind(payload.store_id)
+    .bind(payload.customer_id)
+    .bind(payload.customer_name.as_deref())
+    .bind(payload.discount_percent_bp)
+    .bind(SqlJson(&items))
+    .bind(cart_ttl_secs() as f64)
+    .bind(expected_version)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to update cart", sec.trace_id))?
+    .ok_or_else(|| version_conflict(sec.trace_id))?;
+    Ok((etag_header(cart.version), Json(cart)))
+}
+
+/// Park an open cart and issue a recall code.
+pub async fn park_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(cart_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_cart_role(&sec)?;
+    let current = load_cart(&state.db, sec.tenant_id, cart_id, sec.trace_id).await?;
+    require_status(&current, "OPEN", sec.trace_id)?;
+    if current.items.is_empty() {
+        return Err(ApiError::BadRequest { code: "cart_empty", trace_id: sec.trace_id, message: Some("Add items before parking a cart".into()) });
+    }
+    // Codes are short, so retry on the rare collision with another parked cart.
+    for _ in 0..5 {
+        let parked = sqlx::query_as::<_, Cart>(&format!(
+            "UPDATE carts SET status = 'PARKED', recall_code = $3, parked_by = $4, parked_at = NOW(),
+                 version = version + 1, updated_at = NOW(), expires_at = NOW() + make_interval(secs => $5)
+             WHERE id = $1 AND tenant_id = $2 AND status = 'OPEN'
+             RETURNING {CART_COLUMNS}"
+        ))
+        .bind(cart_id)
+        .bind(sec.tenant_id)
+        .bind(generate_recall_code())
+        .bind(sec.actor.id)
+        .bind(cart_ttl_secs() as f64)
+        .fetch_optional(&state.db)
+        .await;
+        match parked {
+            Ok(Some(cart)) => return Ok((etag_header(cart.version), Json(cart))),
+            Ok(None) => return Err(ApiError::Conflict { code: "invalid_cart_status", trace_id: sec.trace_id, message: Some("Cart is no longer open".into()) }),
+            Err(sqlx::Error::Database(db)) if db.is_unique_violation() => continue,
+            Err(e) => return Err(db_error("Failed to park cart", sec.trace_id)(e)),
+        }
+    }
+    Err(ApiError::Internal { trace_id: sec.trace_id, message: Some("Could not allocate a recall code".into()) })
+}
+
+#[derive(Deserialize)]
+pub struct RecallCartRequest {
+    pub code: String,
+}
+
+/// Recall a parked cart by code (any terminal in the tenant); it becomes OPEN again.
+pub async fn recall_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<RecallCartRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_cart_role(&sec)?;
+    let code = req.code.trim().to_ascii_uppercase();
+    let cart = sqlx::query_as::<_, Cart>(&format!(
+        "UPDATE carts SET status = 'OPEN', recall_code = NULL, version = version + 1, updated_at = NOW(),
+             expires_at = NOW() + make_interval(secs => $3)
+         WHERE tenant_id = $1 AND recall_code = $2 AND status = 'PARKED' AND expires_at > NOW()
+         RETURNING {CART_COLUMNS}"
+    ))
+    .bind(sec.tenant_id)
+    .bind(&code)
+    .bind(cart_ttl_secs() as f64)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to recall cart", sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "recall_code_not_found", trace_id: sec.trace_id })?;
+    Ok((etag_header(cart.version), Json(cart)))
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListCartsParams {
+    /// Defaults to `PARKED` (the recall list shown at the terminal).
+    pub status: Option<String>,
+    pub store_id: Option<Uuid>,
+}
+
+pub async fn list_carts(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ListCartsParams>,
+) -> Result<Json<Vec<Cart>>, ApiError> {
+    ensure_cart_role(&sec)?;
+    let status = params.status.as_deref().map(str::to_ascii_uppercase).unwrap_or_else(|| "PARKED".into());
+    let carts = sqlx::query_as::<_, Cart>(&format!(
+        "SELECT {CART_COLUMNS} FROM carts
+         WHERE tenant_id = $1 AND status = $2 AND ($3::uuid IS NULL OR store_id = $3)
+           AND (status NOT IN ('OPEN', 'PARKED') OR expires_at > NOW())
+         ORDER BY updated_at DESC LIMIT 100"
+    ))
+    .bind(sec.tenant_id)
+    .bind(status)
+    .bind(params.store_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to list carts", sec.trace_id))?;
+    Ok(Json(carts))
+}
+
+#[derive(Deserialize)]
+pub struct CartCheckoutRequest {
+    pub payment_method: String,
+    #[serde(default)]
+    pub payment: Option<PaymentRequest>,
+    #[serde(default)]
+    pub tax_rate_bps: Option<i32>,
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    #[serde(default)]
+    pub pos_instance_id: Option<Uuid>,
+    pub customer_email: Option<String>,
+    pub offline: Option<bool>,
+    /// Tip on top of the cart total; see [`crate::tips`].
+    #[serde(default)]
+    pub tip_cents: i64,
+}
+
+/// Convert an open cart into an order. Pricing and the inventory reservation happen here; the
+/// cart id doubles as the idempotency key so a retried checkout returns the same order.
+pub async fn checkout_cart(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(cart_id): Path<Uuid>,
+    Json(req): Json<CartCheckoutRequest>,
+) -> Result<Json<Order>, ApiError> {
+    ensure_cart_role(&sec)?;
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+    let cart = load_cart(&state.db, tenant_id, cart_id, trace_id).await?;
+    require_status(&cart, "OPEN", trace_id)?;
+    if cart.items.is_empty() {
+        return Err(ApiError::BadRequest { code: "cart_empty", trace_id, message: None });
+    }
+
+    let new_order = NewOrderFromSku {
+        items: cart.items.iter().map(|item| NewOrderSkuItem {
+            sku: item.sku.clone(),
+            quantity: item.quantity,
+            serial_numbers: Vec::new(),
+            lot_code: None,
+            weight: None,
+            tare: None,
+            modifier_ids: item.modifier_ids.clone(),
+        }).collect(),
+        discount_percent_bp: cart.discount_percent_bp,
+        tax_rate_bps: req.tax_rate_bps,
+        location_id: req.location_id,
+        pos_instance_id: req.pos_instance_id,
+        payment_method: req.payment_method,
+        payment: req.payment,
+        customer_id: cart.customer_id.map(|id| id.to_string()),
+        customer_name: cart.customer_name.clone(),
+        customer_email: req.customer_email,
+        store_id: cart.store_id,
+        offline: req.offline,
+        idempotency_key: Some(format!("cart:{}", cart.id)),
+        tip_cents: req.tip_cents,
+        exchange: None,
+    };
+    let Json(order) = create_order_from_skus(State(state.clone()), SecurityCtxExtractor(sec.clone()), auth, headers, Json(new_order)).await?;
+
+    sqlx::query(
+        "UPDATE carts SET status = 'CONVERTED', converted_order_id = $3, recall_code = NULL, version = version + 1, updated_at = NOW()
+         WHERE id = $1 AND tenant_id = $2 AND status = 'OPEN'",
+    )
+    .bind(cart_id)
+    .bind(tenant_id)
+    .bind(order.id)
+    .execute(&state.db)
+    .await
+    .map_err(db_error("Failed to mark cart converted", trace_id))?;
+    Ok(Json(order))
+}
+
+/// Mark carts past their TTL as expired so recall codes free up. Reads already treat them as
+/// expired; this keeps the table and the parked list tidy.
+pub async fn expire_stale_carts(db: &PgPool) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        "UPDATE carts SET status = 'EXPIRED', recall_code = NULL, updated_at = NOW()
+         WHERE status IN ('OPEN', 'PARKED') AND expires_at <= NOW()",
+    )
+    .execute(db)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+pub fn spawn_cart_expiry(db: PgPool) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match expire_stale_carts(&db).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "Expired stale carts"),
+                Err(err) => tracing::warn!(error = %err, "Failed to expire stale carts"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_merges_duplicate_skus() {
+        let item = |sku: &str, quantity: i32| CartItem { sku: sku.into(), quantity, modifier_ids: Vec::new() };
+        let items = vec![item(" ABC ", 1), item("XYZ", 2), item("ABC", 3)];
+        let merged = normalize_items(items, None).unwrap();
+        assert_eq!(merged, vec![item("ABC", 4), item("XYZ", 2)]);
+        assert!(normalize_items(vec![item("A", 0)], None).is_err());
+    }
+
+    #[test]
+    fn normalize_keeps_lines_with_different_modifiers_apart() {
+        let (large, bacon) = (Uuid::new_v4(), Uuid::new_v4());
+        let items = vec![
+            CartItem { sku: "BURGER".into(), quantity: 1, modifier_ids: vec![large, bacon] },
+            CartItem { sku: "BURGER".into(), quantity: 1, modifier_ids: vec![large] },
+            CartItem { sku: "BURGER".into(), quantity: 2, modifier_ids: vec![bacon, large] },
+        ];
+        let merged = normalize_items(items, None).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].quantity, 3);
+        assert_eq!(merged[1].quantity, 1);
+    }
+
+    #[test]
+    fn recall_codes_use_unambiguous_alphabet() {
+        let code = generate_recall_code();
+        assert_eq!(code.len(), RECALL_CODE_LEN);
+        assert!(code.bytes().all(|b| RECALL_CODE_ALPHABET.contains(&b)));
+    }
+}
@@ -0,0 +1,390 @@
+//! Checkout saga: inventory reservation, payment authorization and order persistence run as one
+//! checkout whose progress is stored in `checkout_sagas`, so a failure part-way can be undone.
+//!
+//! The checkout request moves the saga forward and the order insert completes it in the same
+//! transaction. When a step fails, the request compensates straight away: it releases the
+//! reservation and voids the payment intent. Sagas that stop moving for the configured timeout
+//! (the request died, or its compensation failed) are compensated by [`spawn_saga_sweeper`].
+//!
+//! Whoever compensates first claims the saga: one committed update moves it to `compensating` and
+//! sets `claimed_until`, so no transaction or row lock is held while inventory and payment are
+//! called, and a late request can no longer complete it. The sweeper calls the other services
+//! with order-service's own token (`ORDER_SERVICE_TOKEN`), acting as [`SAGA_SWEEPER`]. A saga
+//! that still can't be compensated after [`MAX_COMPENSATION_ATTEMPTS`] is left `failed`;
+//! inventory reservations also expire on their own.
+
+use std::time::Duration;
+
+use common_http_errors::ApiError;
+use common_security::{Capability, CapabilitySet, SecurityContext, SystemActor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGauge, Opts};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::app::ORDER_REGISTRY;
+use crate::AppState;
+
+/// Compensation attempts before a saga is left `failed` for manual follow-up.
+pub const MAX_COMPENSATION_ATTEMPTS: i32 = 5;
+
+const OPEN_STATUSES: &[&str] = &["started", "inventory_reserved", "payment_authorized"];
+
+/// How long a compensation claim lasts before the sweeper may take the saga over; well beyond
+/// what the release and void calls take.
+const CLAIM_LEASE: Duration = Duration::from_secs(60);
+
+/// Identity the sweeper compensates and audits under; it voids payment intents.
+pub const SAGA_SWEEPER: SystemActor =
+    SystemActor::new("order-service", "checkout-saga-sweeper", CapabilitySet::of(&[Capability::PaymentProcess]));
+
+static SAGA_OUTCOMES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("checkout_saga_outcomes_total", "Checkout sagas finished, by outcome and by what finished them (checkout/sweeper)"),
+        &["outcome", "source"],
+    ).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static SAGA_STUCK: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new("checkout_saga_stuck", "Unfinished checkout sagas past the timeout after the last sweep").unwrap();
+    ORDER_REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+fn record_outcome(status: SagaStatus, source: &str) {
+    SAGA_OUTCOMES.with_label_values(&[status.as_str(), source]).inc();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SagaStatus {
+    Started,
+    InventoryReserved,
+    PaymentAuthorized,
+    Completed,
+    Compensating,
+    Compensated,
+    Failed,
+}
+
+impl SagaStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SagaStatus::Started => "started",
+            SagaStatus::InventoryReserved => "inventory_reserved",
+            SagaStatus::PaymentAuthorized => "payment_authorized",
+            SagaStatus::Completed => "completed",
+            SagaStatus::Compensating => "compensating",
+            SagaStatus::Compensated => "compensated",
+            SagaStatus::Failed => "failed",
+        }
+    }
+
+    /// Audit action for a compensation that reached a final state; retries are not audited.
+    pub fn audit_action(self) -> Option<&'static str> {
+        match self {
+            SagaStatus::Compensated => Some("compensated"),
+            SagaStatus::Failed => Some("compensation_failed"),
+            _ => None,
+        }
+    }
+}
+
+/// Where a saga goes after a compensation attempt; `attempts` includes this one.
+pub fn after_compensation(attempts: i32, succeeded: bool) -> SagaStatus {
+    if succeeded {
+        SagaStatus::Compensated
+    } else if attempts >= MAX_COMPENSATION_ATTEMPTS {
+        SagaStatus::Failed
+    } else {
+        SagaStatus::Compensating
+    }
+}
+
+/// A checkout in progress. Keyed by the id of the order it creates, which is also the inventory
+/// reservation id.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CheckoutSaga {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub inventory_reserved: bool,
+    pub payment_intent_id: Option<String>,
+    pub compensation_attempts: i32,
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl Fn(sqlx::Error) -> ApiError {
+    move |e| ApiError::Internal { trace_id, message: Some(format!("Failed to record checkout progress: {e}")) }
+}
+
+fn timed_out(trace_id: Option<Uuid>) -> ApiError {
+    ApiError::Conflict {
+        code: "checkout_timed_out",
+        trace_id,
+        message: Some("Checkout took too long and was rolled back; please retry".into()),
+    }
+}
+
+impl CheckoutSaga {
+    pub async fn begin(db: &PgPool, tenant_id: Uuid, order_id: Uuid, trace_id: Option<Uuid>) -> Result<Self, ApiError> {
+        sqlx::query("INSERT INTO checkout_sagas (id, tenant_id) VALUES ($1, $2)")
+            .bind(order_id)
+            .bind(tenant_id)
+            .execute(db)
+            .await
+            .map_err(db_error(trace_id))?;
+        Ok(Self { id: order_id, tenant_id, inventory_reserved: false, payment_intent_id: None, compensation_attempts: 0 })
+    }
+
+    /// Move an open saga to `status`. Fails with `checkout_timed_out` once the sweeper has taken
+    /// the saga over, so the request compensates instead of carrying on.
+    async fn advance(&self, db: &PgPool, status: SagaStatus, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        let open: Vec<String> = OPEN_STATUSES.iter().map(|s| s.to_string()).collect();
+        let updated = sqlx::query(
+            "UPDATE checkout_sagas
+             SET status = $2, inventory_reserved = $3, payment_intent_id = $4, updated_at = NOW()
+             WHERE id = $1 AND status = ANY($5)",
+        )
+        .bind(self.id)
+        .bind(status.as_str())
+        .bind(self.inventory_reserved)
+        .bind(self.payment_intent_id.as_deref())
+        .bind(&open)
+        .execute(db)
+        .await
+        .map_err(db_error(trace_id))?;
+        if updated.rows_affected() == 0 {
+            return Err(timed_out(trace_id));
+        }
+        Ok(())
+    }
+
+    pub async fn inventory_reserved(&mut self, db: &PgPool, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        self.inventory_reserved = true;
+        self.advance(db, SagaStatus::InventoryReserved, trace_id).await
+    }
+
+    pub async fn payment_authorized(&mut self, db: &PgPool, payment_intent_id: String, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        self.payment_intent_id = Some(payment_intent_id);
+        self.advance(db, SagaStatus::PaymentAuthorized, trace_id).await
+    }
+
+    /// Complete the saga inside the transaction that persists the order, so the order exists if
+    /// and only if the saga completed.
+    pub async fn complete(&self, tx: &mut Transaction<'_, Postgres>, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+        let open: Vec<String> = OPEN_STATUSES.iter().map(|s| s.to_string()).collect();
+        let updated = sqlx::query("UPDATE checkout_sagas SET status = 'completed', updated_at = NOW() WHERE id = $1 AND status = ANY($2)")
+            .bind(self.id)
+            .bind(&open)
+            .execute(&mut **tx)
+            .await
+            .map_err(db_error(trace_id))?;
+        if updated.rows_affected() == 0 {
+            return Err(timed_out(trace_id));
+        }
+        Ok(())
+    }
+
+    pub fn record_completed(&self) {
+        record_outcome(SagaStatus::Completed, "checkout");
+    }
+
+    /// Undo what the checkout did so far after `cause` stopped it. Never fails the caller: an
+    /// unsuccessful compensation is left for the sweeper, and so is a saga the sweeper has
+    /// already claimed.
+    pub async fn compensate(&self, state: &AppState, sec: &SecurityContext, auth_token: &str, cause: &str) {
+        let open: Vec<String> = OPEN_STATUSES.iter().map(|s| s.to_string()).collect();
+        let claimed = sqlx::query_scalar::<_, i32>(
+            "UPDATE checkout_sagas
+             SET status = 'compensating', claimed_until = NOW() + make_interval(secs => $3), updated_at = NOW()
+             WHERE id = $1 AND status = ANY($2)
+             RETURNING compensation_attempts",
+        )
+        .bind(self.id)
+        .bind(&open)
+        .bind(CLAIM_LEASE.as_secs_f64())
+        .fetch_optional(&state.db)
+        .await;
+        let compensation_attempts = match claimed {
+            Ok(Some(attempts)) => attempts,
+            Ok(None) => {
+                tracing::info!(order_id = %self.id, %cause, "Checkout already claimed by the saga sweeper");
+                return;
+            }
+            Err(err) => {
+                tracing::error!(?err, order_id = %self.id, %cause, "Failed to claim checkout for compensation; leaving it to the sweeper");
+                return;
+            }
+        };
+        let saga = CheckoutSaga { compensation_attempts, ..self.clone() };
+        let status = run_compensation(state, &saga, sec, auth_token, cause).await;
+        record_outcome(status, "checkout");
+    }
+}
+
+async fn void_payment_intent(state: &AppState, sec: &SecurityContext, auth_token: &str, intent_id: &str) -> Result<(), String> {
+    let url = format!("{}/payment_intents/void", state.payment_base_url.trim_end_matches('/'));
+    let mut request = state
+        .http_client
+        .post(url)
+        .header("X-Tenant-ID", sec.tenant_id.to_string())
+        .header("X-Roles", "Admin,Manager,Cashier")
+        .json(&serde_json::json!({ "id": intent_id }));
+    if let Some(actor_id) = sec.actor.id {
+        request = request.header("X-User-ID", actor_id.to_string());
+    }
+    if let Some(trace_id) = sec.trace_id {
+        request = request.header("X-Trace-ID", trace_id.to_string());
+    }
+    if !auth_token.is_empty() {
+        request = request.bearer_auth(auth_token);
+    }
+    let response = request.send().await.map_err(|err| format!("Failed to contact payment-service: {err}"))?;
+    // Nothing to void when the intent was never stored.
+    if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(());
+    }
+    Err(format!("Payment void failed with status {}", response.status()))
+}
+
+/// Release the reservation and void the payment of a claimed saga, then record the attempt and
+/// drop the claim. Both calls are idempotent, so a retry repeats whichever half failed. `sec` is
+/// who compensates: the checkout request, or [`SAGA_SWEEPER`]. Returns the saga's new status.
+async fn run_compensation(state: &AppState, saga: &CheckoutSaga, sec: &SecurityContext, auth_token: &str, cause: &str) -> SagaStatus {
+    let mut errors = Vec::new();
+    if saga.inventory_reserved {
+        if let Err(err) = crate::order_handlers::release_inventory(&state.http_client, &state.inventory_base_url, saga.tenant_id, auth_token, saga.id).await {
+            errors.push(err);
+        }
+    }
+    if let Some(intent_id) = saga.payment_intent_id.as_deref() {
+        if let Err(err) = void_payment_intent(state, sec, auth_token, intent_id).await {
+            errors.push(err);
+        }
+    }
+    let attempts = saga.compensation_attempts + 1;
+    let status = after_compensation(attempts, errors.is_empty());
+    let last_error = if errors.is_empty() { cause.to_string() } else { format!("{cause}; compensation: {}", errors.join("; ")) };
+    let actor = sec.actor.name.as_deref().unwrap_or("checkout");
+    match status {
+        SagaStatus::Compensated => tracing::info!(order_id = %saga.id, tenant_id = %saga.tenant_id, %cause, actor, "Checkout compensated"),
+        SagaStatus::Failed => tracing::error!(order_id = %saga.id, tenant_id = %saga.tenant_id, error = %last_error, actor, "Checkout compensation gave up"),
+        _ => tracing::warn!(order_id = %saga.id, tenant_id = %saga.tenant_id, error = %last_error, attempts, actor, "Checkout compensation failed; will retry"),
+    }
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    if let (Some(audit), Some(action)) = (&state.audit_producer, status.audit_action()) {
+        let severity = if status == SagaStatus::Failed { common_audit::AuditSeverity::Warning } else { common_audit::AuditSeverity::Info };
+        let _ = audit
+            .emit(
+                saga.tenant_id,
+                sec.actor.clone(),
+                "checkout_saga",
+                Some(saga.id),
+                action,
+                "order-service",
+                severity,
+                sec.trace_id,
+                serde_json::json!({"cause": cause, "last_error": last_error, "attempts": attempts}),
+                serde_json::json!({"source": "order-service"}),
+            )
+            .await;
+    }
+    if let Err(err) = sqlx::query(
+        "UPDATE checkout_sagas
+         SET status = $2, last_error = $3, compensation_attempts = $4, inventory_reserved = $5, payment_intent_id = $6,
+             claimed_until = NULL, updated_at = NOW()
+         WHERE id = $1 AND status = 'compensating'",
+    )
+    .bind(saga.id)
+    .bind(status.as_str())
+    .bind(&last_error)
+    .bind(attempts)
+    .bind(saga.inventory_reserved)
+    .bind(saga.payment_intent_id.as_deref())
+    .execute(&state.db)
+    .await
+    {
+        tracing::error!(?err, order_id = %saga.id, "Failed to record checkout compensation");
+    }
+    status
+}
+
+/// Claim up to `batch` sagas: ones that stopped moving for `timeout`, and `compensating` ones
+/// whose last attempt failed or whose claim expired.
+async fn claim_stuck_sagas(db: &PgPool, timeout: Duration, batch: i64) -> sqlx::Result<Vec<CheckoutSaga>> {
+    sqlx::query_as::<_, CheckoutSaga>(
+        "UPDATE checkout_sagas s
+         SET status = 'compensating', claimed_until = NOW() + make_interval(secs => $3), updated_at = NOW()
+         FROM (
+             SELECT id FROM checkout_sagas
+             WHERE (status = 'compensating'
+                    OR (status IN ('started', 'inventory_reserved', 'payment_authorized') AND updated_at <= NOW() - make_interval(secs => $1)))
+               AND (claimed_until IS NULL OR claimed_until <= NOW())
+             ORDER BY updated_at
+             LIMIT $2
+             FOR UPDATE SKIP LOCKED
+         ) stuck
+         WHERE s.id = stuck.id
+         RETURNING s.id, s.tenant_id, s.inventory_reserved, s.payment_intent_id, s.compensation_attempts",
+    )
+    .bind(timeout.as_secs_f64())
+    .bind(batch)
+    .bind(CLAIM_LEASE.as_secs_f64())
+    .fetch_all(db)
+    .await
+}
+
+/// Compensate sagas that stopped moving for `timeout`, and retry ones whose compensation failed.
+/// The batch is claimed and committed first, so no locks are held during the downstream calls,
+/// which carry `service_token`. Returns how many were handled.
+pub async fn sweep_stuck_sagas(state: &AppState, timeout: Duration, batch: i64, service_token: &str) -> sqlx::Result<usize> {
+    let claimed = claim_stuck_sagas(&state.db, timeout, batch).await?;
+    for saga in &claimed {
+        let sec = SAGA_SWEEPER.context(saga.tenant_id);
+        let status = run_compensation(state, saga, &sec, service_token, "checkout timed out").await;
+        if status != SagaStatus::Compensating {
+            record_outcome(status, "sweeper");
+        }
+    }
+
+    let remaining = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM checkout_sagas
+         WHERE status NOT IN ('completed', 'compensated', 'failed') AND updated_at <= NOW() - make_interval(secs => $1)",
+    )
+    .bind(timeout.as_secs_f64())
+    .fetch_one(&state.db)
+    .await?;
+    SAGA_STUCK.set(remaining);
+    Ok(claimed.len())
+}
+
+/// Run [`sweep_stuck_sagas`] every `interval`; replicas can all run it.
+pub fn spawn_saga_sweeper(state: AppState, interval: Duration, timeout: Duration, service_token: String) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match sweep_stuck_sagas(&state, timeout, 50, &service_token).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "Swept stuck checkout sagas"),
+                Err(err) => tracing::warn!(error = %err, "Failed to sweep checkout sagas"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compensation_retries_until_the_limit() {
+        assert_eq!(after_compensation(1, true), SagaStatus::Compensated);
+        assert_eq!(after_compensation(1, false), SagaStatus::Compensating);
+        assert_eq!(after_compensation(MAX_COMPENSATION_ATTEMPTS - 1, false), SagaStatus::Compensating);
+        assert_eq!(after_compensation(MAX_COMPENSATION_ATTEMPTS, false), SagaStatus::Failed);
+        // A late success still counts.
+        assert_eq!(after_compensation(MAX_COMPENSATION_ATTEMPTS, true), SagaStatus::Compensated);
+    }
+}
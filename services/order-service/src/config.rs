@@ -0,0 +1,106 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    pub audit_topic: String,
+    pub jwt: JwtSettings,
+    pub inventory_service_url: String,
+    pub payment_service_url: String,
+    /// `ENABLE_PAYMENT_INTENTS`
+    pub enable_payment_intents: bool,
+    /// `OUTBOX_WORKER`: publish rows written to the outbox table.
+    pub outbox_worker: bool,
+    /// `ORDER_OUTBOX_MODE`: write order events to the outbox instead of producing directly.
+    pub outbox_mode: bool,
+    /// `OUTBOX_LEASE_SECS`: how long a worker's claim on outbox rows lasts before another worker
+    /// may take them over; longer than a batch takes to send.
+    pub outbox_lease_secs: u64,
+    /// `OUTBOX_BATCH_SIZE`: rows claimed per poll.
+    pub outbox_batch_size: i64,
+    /// `OUTBOX_MAX_RETRIES`: failed sends before a row is moved to `outbox_dead`; 0 retries forever.
+    pub outbox_max_retries: i32,
+    /// `OUTBOX_RETENTION_DAYS`: published rows older than this are purged; 0 keeps them.
+    pub outbox_retention_days: u64,
+    /// `ORDER_SAGA_TIMEOUT_SECS`: an unfinished checkout older than this is compensated.
+    pub saga_timeout_secs: u64,
+    /// `ORDER_SAGA_SWEEP_SECS`: how often stuck checkouts are swept; 0 disables the sweeper.
+    pub saga_sweep_secs: u64,
+    /// `ORDER_SERVICE_TOKEN`: bearer token order-service presents when a background job (the saga
+    /// sweeper) calls inventory and payment; required while the sweeper is enabled.
+    pub service_token: Option<Redacted<String>>,
+    /// `ORDER_RETENTION_MONTHS`: finished orders older than this lose their customer data; 0 keeps it.
+    pub retention_months: u32,
+    /// `ORDER_RETENTION_BATCH_SIZE`: rows anonymized per statement.
+    pub retention_batch_size: i64,
+    /// `ORDER_RETENTION_DRY_RUN`: only count and audit what the retention job would strip.
+    pub retention_dry_run: bool,
+    /// `ORDER_RETENTION_INTERVAL_SECS`: how often the retention job runs.
+    pub retention_interval_secs: u64,
+}
+
+impl OrderConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8084);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let audit_topic = env.or("AUDIT_TOPIC", "audit.events".to_string());
+        let jwt = JwtSettings::read(&mut env).await;
+        let inventory_service_url = env.or("INVENTORY_SERVICE_URL", "http://localhost:8087".to_string());
+        let payment_service_url = env.or("PAYMENT_SERVICE_URL", "http://localhost:8086".to_string());
+        let enable_payment_intents = env.flag("ENABLE_PAYMENT_INTENTS", false);
+        let outbox_worker = env.flag("OUTBOX_WORKER", false);
+        let outbox_mode = env.flag("ORDER_OUTBOX_MODE", false);
+        let outbox_lease_secs = env.or("OUTBOX_LEASE_SECS", 30u64);
+        let outbox_batch_size = env.or("OUTBOX_BATCH_SIZE", 50i64);
+        let outbox_max_retries = env.or("OUTBOX_MAX_RETRIES", 20i32);
+        let outbox_retention_days = env.or("OUTBOX_RETENTION_DAYS", 7u64);
+        let saga_timeout_secs = env.or("ORDER_SAGA_TIMEOUT_SECS", 120u64);
+        let saga_sweep_secs = env.or("ORDER_SAGA_SWEEP_SECS", 30u64);
+        let service_token = if saga_sweep_secs > 0 {
+            env.required_secret("ORDER_SERVICE_TOKEN").await
+        } else {
+            env.secret("ORDER_SERVICE_TOKEN").await
+        };
+        let retention_months = env.or("ORDER_RETENTION_MONTHS", 0u32);
+        let retention_batch_size = env.or("ORDER_RETENTION_BATCH_SIZE", 500i64);
+        let retention_dry_run = env.flag("ORDER_RETENTION_DRY_RUN", false);
+        let retention_interval_secs = env.or("ORDER_RETENTION_INTERVAL_SECS", 86_400u64);
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                audit_topic,
+                jwt: jwt?,
+                inventory_service_url,
+                payment_service_url,
+                enable_payment_intents,
+                outbox_worker,
+                outbox_mode,
+                outbox_lease_secs,
+                outbox_batch_size,
+                outbox_max_retries,
+                outbox_retention_days,
+                saga_timeout_secs,
+                saga_sweep_secs,
+                service_token,
+                retention_months,
+                retention_batch_size: retention_batch_size.max(1),
+                retention_dry_run,
+                retention_interval_secs: retention_interval_secs.max(60),
+            })
+        })
+    }
+}
@@ -0,0 +1,599 @@
+//! End-of-day close: checks that a store's business day is settled, generates its Z-report,
+//! locks the date and publishes `day.closed` so analytics can finalize the day's rollups.
+//!
+//! A day can close once every drawer opened at the store is closed, no order taken that day is
+//! still PENDING and every register has reported an empty offline queue (see
+//! [`crate::drawers`]).
+//!
+//! Orders, refunds and drawers get their `business_date` when they are created, from the store's
+//! close time (`store_business_days`); sales rung up after midnight but before a 2am close belong
+//! to the previous business date. Stores without settings use UTC dates.
+
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::order_handlers::{is_taxable, resolve_rounding_policy, resolve_tax_rate_bps_with_db};
+use crate::AppState;
+
+/// Statuses of orders that count as sales; refunds are reported separately.
+const SOLD_STATUSES: &[&str] = &["COMPLETED", "PAID", "REFUNDED", "PARTIAL_REFUNDED"];
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SalesSummary {
+    pub order_count: i64,
+    /// Sold orders' totals, cash rounding included and tips excluded.
+    pub gross_sales: BigDecimal,
+    pub refund_count: i64,
+    pub refund_amount: BigDecimal,
+    pub net_sales: BigDecimal,
+    pub tips: BigDecimal,
+    pub rounding: BigDecimal,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct TenderTotal {
+    pub method: String,
+    pub count: i64,
+    /// Captured less change given, tips included.
+    pub amount: BigDecimal,
+    pub tips: BigDecimal,
+}
+
+/// Orders don't store their tax, so it is worked out from the day's taxable line totals at the
+/// store's rate.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TaxSummary {
+    pub rate_bps: i32,
+    pub taxable_sales: BigDecimal,
+    pub exempt_sales: BigDecimal,
+    pub tax: BigDecimal,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct VoidReasonTotal {
+    pub reason_code: String,
+    pub count: i64,
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct VoidSummary {
+    pub count: i64,
+    pub amount: BigDecimal,
+    pub by_reason: Vec<VoidReasonTotal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct DrawerSummary {
+    pub drawer_id: Uuid,
+    pub pos_instance_id: Uuid,
+    pub opening_float: BigDecimal,
+    pub expected_cash: Option<BigDecimal>,
+    pub counted_cash: Option<BigDecimal>,
+    pub variance: Option<BigDecimal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ZReport {
+    pub store_id: Uuid,
+    pub business_date: NaiveDate,
+    pub sales: SalesSummary,
+    pub tenders: Vec<TenderTotal>,
+    pub taxes: TaxSummary,
+    pub voids: VoidSummary,
+    pub drawers: Vec<DrawerSummary>,
+    /// Sum of the closed drawers' variances.
+    pub cash_variance: BigDecimal,
+}
+
+/// Something that keeps the day from closing.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CloseBlocker {
+    /// `drawers_open`, `pending_orders` or `offline_queue`.
+    pub code: &'static str,
+    pub count: i64,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DayClosePreview {
+    pub store_id: Uuid,
+    pub business_date: NaiveDate,
+    pub closed: bool,
+    pub blockers: Vec<CloseBlocker>,
+    pub z_report: ZReport,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct BusinessDayClose {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub business_date: NaiveDate,
+    pub z_report: serde_json::Value,
+    pub closed_by: Option<Uuid>,
+    pub closed_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DayCloseQuery {
+    pub business_date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CloseDayRequest {
+    pub business_date: Option<NaiveDate>,
+}
+
+/// When a store's business day ends. `close_time` is local time in `timezone`.
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct StoreBusinessDay {
+    pub store_id: Uuid,
+    pub timezone: String,
+    pub close_time: NaiveTime,
+    /// The store's business date right now.
+    pub current_business_date: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct StoreBusinessDayRequest {
+    pub timezone: Option<String>,
+    /// Local wall-clock time the day ends, e.g. `02:00`; must be before noon.
+    pub close_time: NaiveTime,
+}
+
+fn ensure_close_role(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+fn db_error(trace_id: Option<Uuid>) -> impl Fn(sqlx::Error) -> ApiError {
+    move |e| ApiError::Internal { trace_id, message: Some(format!("Failed to load business day: {e}")) }
+}
+
+/// Business date of `at` at the store, per its close time; the UTC date without a store.
+pub(crate) async fn business_date_at(db: &PgPool, tenant_id: Uuid, store_id: Option<Uuid>, at: DateTime<Utc>) -> sqlx::Result<NaiveDate> {
+    sqlx::query_scalar::<_, NaiveDate>("SELECT business_date_at($1, $2, $3)")
+        .bind(tenant_id)
+        .bind(store_id)
+        .bind(at)
+        .fetch_one(db)
+        .await
+}
+
+async fn is_day_closed(db: &PgPool, tenant_id: Uuid, store_id: Uuid, business_date: NaiveDate) -> sqlx::Result<bool> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM business_day_closes WHERE tenant_id = $1 AND store_id = $2 AND business_date = $3)",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(business_date)
+    .fetch_one(db)
+    .await
+}
+
+/// Reject changes to a store's business day once it is closed. Orders without a store are never
+/// locked.
+pub(crate) async fn ensure_day_open(
+    db: &PgPool,
+    tenant_id: Uuid,
+    store_id: Option<Uuid>,
+    business_date: NaiveDate,
+    trace_id: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let Some(store_id) = store_id else {
+        return Ok(());
+    };
+    if is_day_closed(db, tenant_id, store_id, business_date).await.map_err(db_error(trace_id))? {
+        return Err(ApiError::Conflict {
+            code: "business_day_closed",
+            trace_id,
+            message: Some(format!("Business day {business_date} is closed for this store")),
+        });
+    }
+    Ok(())
+}
+
+/// Blockers from the raw counts, in the order staff resolve them.
+pub fn close_blockers(open_drawers: i64, pending_orders: i64, offline_registers: i64) -> Vec<CloseBlocker> {
+    let mut blockers = Vec::new();
+    if open_drawers > 0 {
+        blockers.push(CloseBlocker { code: "drawers_open", count: open_drawers, message: format!("{open_drawers} drawer(s) still open") });
+    }
+    if pending_orders > 0 {
+        blockers.push(CloseBlocker { code: "pending_orders", count: pending_orders, message: format!("{pending_orders} order(s) still pending") });
+    }
+    if offline_registers > 0 {
+        blockers.push(CloseBlocker {
+            code: "offline_queue",
+            count: offline_registers,
+            message: format!("{offline_registers} register(s) still hold offline sales"),
+        });
+    }
+    blockers
+}
+
+async fn find_blockers(db: &PgPool, tenant_id: Uuid, store_id: Uuid, date: NaiveDate) -> sqlx::Result<Vec<CloseBlocker>> {
+    let (open_drawers, pending_orders, offline_registers) = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT
+            (SELECT COUNT(*) FROM drawer_sessions WHERE tenant_id = $1 AND store_id = $2 AND status = 'OPEN' AND business_date <= $3),
+            (SELECT COUNT(*) FROM orders WHERE tenant_id = $1 AND store_id = $2 AND status IN ('PENDING', 'CONDITIONALLY_APPROVED') AND business_date = $3),
+            (SELECT COUNT(*) FROM pos_offline_queues WHERE tenant_id = $1 AND store_id = $2 AND pending_orders > 0)",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .fetch_one(db)
+    .await?;
+    Ok(close_blockers(open_drawers, pending_orders, offline_registers))
+}
+
+fn zero_if_null(value: Option<BigDecimal>) -> BigDecimal {
+    value.unwrap_or_else(|| BigDecimal::from(0))
+}
+
+/// Build the Z-report for a store's business day from what order-service recorded.
+pub async fn build_z_report(db: &PgPool, tenant_id: Uuid, store_id: Uuid, date: NaiveDate) -> sqlx::Result<ZReport> {
+    let sold: Vec<String> = SOLD_STATUSES.iter().map(|s| s.to_string()).collect();
+
+    let (order_count, gross_sales, tips, rounding) = sqlx::query_as::<_, (i64, Option<BigDecimal>, Option<BigDecimal>, Option<BigDecimal>)>(
+        "SELECT COUNT(*), SUM(total), SUM(tip_amount), SUM(rounding_adjustment)
+         FROM orders
+         WHERE tenant_id = $1 AND store_id = $2 AND status = ANY($4) AND business_date = $3",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .bind(&sold)
+    .fetch_one(db)
+    .await?;
+
+    let (refund_count, refund_amount) = sqlx::query_as::<_, (i64, Option<BigDecimal>)>(
+        "SELECT COUNT(*), SUM(r.total)
+         FROM order_returns r JOIN orders o ON o.id = r.order_id
+         WHERE r.tenant_id = $1 AND o.store_id = $2 AND r.business_date = $3",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .fetch_one(db)
+    .await?;
+
+    let tenders = sqlx::query_as::<_, TenderTotal>(
+        "SELECT p.method, COUNT(*) AS count,
+                COALESCE(SUM(p.amount - COALESCE(p.change_cents, 0)::NUMERIC / 100), 0)::NUMERIC(12,2) AS amount,
+                COALESCE(SUM(p.tip_amount), 0)::NUMERIC(12,2) AS tips
+         FROM payments p JOIN orders o ON o.id = p.order_id
+         WHERE p.tenant_id = $1 AND o.store_id = $2 AND p.status = 'captured' AND o.business_date = $3
+         GROUP BY p.method
+         ORDER BY p.method",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .fetch_all(db)
+    .await?;
+
+    let lines = sqlx::query_as::<_, (BigDecimal, Option<String>)>(
+        "SELECT oi.line_total, p.tax_code
+         FROM order_items oi
+         JOIN orders o ON o.id = oi.order_id
+         LEFT JOIN products p ON p.id = oi.product_id AND p.tenant_id = o.tenant_id
+         WHERE o.tenant_id = $1 AND o.store_id = $2 AND o.status = ANY($4) AND o.business_date = $3",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .bind(&sold)
+    .fetch_all(db)
+    .await?;
+    let (mut taxable_cents, mut exempt_cents) = (0i64, 0i64);
+    for (line_total, tax_code) in lines {
+        let cents = Money::new(line_total).as_cents();
+        if is_taxable(tax_code.as_deref()) {
+            taxable_cents = taxable_cents.saturating_add(cents);
+        } else {
+            exempt_cents = exempt_cents.saturating_add(cents);
+        }
+    }
+    let rate_bps = resolve_tax_rate_bps_with_db(db, tenant_id, &HeaderMap::new(), None, Some(store_id), None).await;
+    let tax = resolve_rounding_policy(db, tenant_id).await.context().percent(&Money::from_cents(taxable_cents), rate_bps);
+
+    let by_reason = sqlx::query_as::<_, VoidReasonTotal>(
+        "SELECT COALESCE(void_reason_code, 'other') AS reason_code, COUNT(*) AS count, COALESCE(SUM(total), 0)::NUMERIC(12,2) AS amount
+         FROM orders
+         WHERE tenant_id = $1 AND store_id = $2 AND status = 'VOIDED' AND business_date = $3
+         GROUP BY 1
+         ORDER BY 1",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .fetch_all(db)
+    .await?;
+
+    let drawers = sqlx::query_as::<_, DrawerSummary>(
+        "SELECT id AS drawer_id, pos_instance_id, opening_float, expected_cash, counted_cash, variance
+         FROM drawer_sessions
+         WHERE tenant_id = $1 AND store_id = $2 AND business_date = $3
+         ORDER BY opened_at, id",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(date)
+    .fetch_all(db)
+    .await?;
+
+    let gross_sales = zero_if_null(gross_sales);
+    let refund_amount = zero_if_null(refund_amount);
+    Ok(ZReport {
+        store_id,
+        business_date: date,
+        sales: SalesSummary {
+            order_count,
+            net_sales: &gross_sales - &refund_amount,
+            gross_sales,
+            refund_count,
+            refund_amount,
+            tips: zero_if_null(tips),
+            rounding: zero_if_null(rounding),
+        },
+        tenders,
+        taxes: TaxSummary {
+            rate_bps,
+            taxable_sales: Money::from_cents(taxable_cents).inner().clone(),
+            exempt_sales: Money::from_cents(exempt_cents).inner().clone(),
+            tax: tax.inner().clone(),
+        },
+        voids: VoidSummary {
+            count: by_reason.iter().map(|r| r.count).sum(),
+            amount: by_reason.iter().map(|r| r.amount.clone()).sum(),
+            by_reason,
+        },
+        cash_variance: drawers.iter().filter_map(|d| d.variance.clone()).sum(),
+        drawers,
+    })
+}
+
+/// The requested date, or the store's current business date.
+async fn resolve_business_date(db: &PgPool, tenant_id: Uuid, store_id: Uuid, requested: Option<NaiveDate>, trace_id: Option<Uuid>) -> Result<NaiveDate, ApiError> {
+    let today = business_date_at(db, tenant_id, Some(store_id), Utc::now()).await.map_err(db_error(trace_id))?;
+    let date = requested.unwrap_or(today);
+    if date > today {
+        return Err(ApiError::BadRequest { code: "invalid_business_date", trace_id, message: Some("Cannot close a future business day".into()) });
+    }
+    Ok(date)
+}
+
+/// What closing the day would do right now: the blockers left and the Z-report so far.
+pub async fn preview_day_close(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(store_id): Path<Uuid>,
+    Query(q): Query<DayCloseQuery>,
+) -> Result<Json<DayClosePreview>, ApiError> {
+    ensure_close_role(&sec)?;
+    let trace_id = sec.trace_id;
+    let business_date = resolve_business_date(&state.db, sec.tenant_id, store_id, q.business_date, trace_id).await?;
+    let closed = is_day_closed(&state.db, sec.tenant_id, store_id, business_date).await.map_err(db_error(trace_id))?;
+    let blockers = find_blockers(&state.db, sec.tenant_id, store_id, business_date).await.map_err(db_error(trace_id))?;
+    let z_report = build_z_report(&state.db, sec.tenant_id, store_id, business_date).await.map_err(db_error(trace_id))?;
+    Ok(Json(DayClosePreview { store_id, business_date, closed, blockers, z_report }))
+}
+
+/// Close the store's business day: refuse while anything is unsettled, then store the Z-report,
+/// which locks the date, and publish `day.closed`.
+pub async fn close_day(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(store_id): Path<Uuid>,
+    Json(req): Json<CloseDayRequest>,
+) -> Result<Json<BusinessDayClose>, ApiError> {
+    ensure_close_role(&sec)?;
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+    let business_date = resolve_business_date(&state.db, tenant_id, store_id, req.business_date, trace_id).await?;
+    let already_closed = || ApiError::Conflict { code: "day_already_closed", trace_id, message: Some(format!("Business day {business_date} is already closed")) };
+    if is_day_closed(&state.db, tenant_id, store_id, business_date).await.map_err(db_error(trace_id))? {
+        return Err(already_closed());
+    }
+
+    let blockers = find_blockers(&state.db, tenant_id, store_id, business_date).await.map_err(db_error(trace_id))?;
+    if !blockers.is_empty() {
+        let summary: Vec<String> = blockers.iter().map(|b| format!("{}: {}", b.code, b.message)).collect();
+        return Err(ApiError::Conflict { code: "day_close_blocked", trace_id, message: Some(summary.join("; ")) });
+    }
+
+    let z_report = build_z_report(&state.db, tenant_id, store_id, business_date).await.map_err(db_error(trace_id))?;
+    let closed = sqlx::query_as::<_, BusinessDayClose>(
+        "INSERT INTO business_day_closes (id, tenant_id, store_id, business_date, z_report, closed_by)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (tenant_id, store_id, business_date) DO NOTHING
+         RETURNING id, store_id, business_date, z_report, closed_by, closed_at",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(store_id)
+    .bind(business_date)
+    .bind(serde_json::to_value(&z_report).unwrap_or_default())
+    .bind(sec.actor.id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error(trace_id))?
+    .ok_or_else(already_closed)?;
+    tracing::info!(%tenant_id, %store_id, %business_date, cash_variance = %z_report.cash_variance, "Business day closed");
+
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        use common_events::{DayClosedEvent, DomainEvent};
+
+        let event = DayClosedEvent {
+            schema_version: DayClosedEvent::SCHEMA_VERSION,
+            tenant_id,
+            store_id,
+            business_date,
+            order_count: z_report.sales.order_count,
+            gross_sales: z_report.sales.gross_sales.clone(),
+            refund_count: z_report.sales.refund_count,
+            refund_amount: z_report.sales.refund_amount.clone(),
+            net_sales: z_report.sales.net_sales.clone(),
+            tax: z_report.taxes.tax.clone(),
+            tips: z_report.sales.tips.clone(),
+            void_count: z_report.voids.count,
+            void_amount: z_report.voids.amount.clone(),
+            cash_variance: z_report.cash_variance.clone(),
+            closed_by: closed.closed_by,
+            closed_at: closed.closed_at,
+        };
+        if let Err(err) = common_kafka::publish_event(&state.kafka_producer, &state.db, tenant_id, &event).await {
+            tracing::error!(?err, %store_id, %business_date, "Failed to send day.closed");
+        }
+        if let Some(audit) = &state.audit_producer {
+            let _ = audit
+                .emit(
+                    tenant_id,
+                    sec.actor.clone(),
+                    "business_day",
+                    Some(closed.id),
+                    "closed",
+                    "order-service",
+                    common_audit::AuditSeverity::Info,
+                    None,
+                    json!({"store_id": store_id, "business_date": business_date, "z_report": closed.z_report}),
+                    json!({"source":"order-service"}),
+                )
+                .await;
+        }
+    }
+    Ok(Json(closed))
+}
+
+/// The Z-report stored when the day was closed.
+pub async fn get_day_close(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path((store_id, business_date)): Path<(Uuid, NaiveDate)>,
+) -> Result<Json<BusinessDayClose>, ApiError> {
+    ensure_close_role(&sec)?;
+    let closed = sqlx::query_as::<_, BusinessDayClose>(
+        "SELECT id, store_id, business_date, z_report, closed_by, closed_at
+         FROM business_day_closes WHERE tenant_id = $1 AND store_id = $2 AND business_date = $3",
+    )
+    .bind(sec.tenant_id)
+    .bind(store_id)
+    .bind(business_date)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error(sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "day_not_closed", trace_id: sec.trace_id })?;
+    Ok(Json(closed))
+}
+
+const BUSINESS_DAY_QUERY: &str = "SELECT $2::uuid AS store_id, COALESCE(s.timezone, 'UTC') AS timezone,
+            COALESCE(s.close_time, '00:00'::time) AS close_time, business_date_at($1, $2, NOW()) AS current_business_date
+     FROM (SELECT 1) one
+     LEFT JOIN store_business_days s ON s.tenant_id = $1 AND s.store_id = $2";
+
+/// A business day can run past midnight but never end before it, so close times must be in the
+/// morning.
+pub fn validate_close_time(close_time: NaiveTime) -> Result<(), &'static str> {
+    if close_time >= NaiveTime::from_hms_opt(12, 0, 0).expect("noon") {
+        return Err("close_time must be before 12:00");
+    }
+    Ok(())
+}
+
+pub async fn get_store_business_day(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(store_id): Path<Uuid>,
+) -> Result<Json<StoreBusinessDay>, ApiError> {
+    ensure_close_role(&sec)?;
+    let settings = sqlx::query_as::<_, StoreBusinessDay>(BUSINESS_DAY_QUERY)
+        .bind(sec.tenant_id)
+        .bind(store_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(db_error(sec.trace_id))?;
+    Ok(Json(settings))
+}
+
+/// Set when the store's business day ends. Only sales made afterwards use the new close time.
+pub async fn upsert_store_business_day(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(store_id): Path<Uuid>,
+    Json(req): Json<StoreBusinessDayRequest>,
+) -> Result<Json<StoreBusinessDay>, ApiError> {
+    let trace_id = sec.trace_id;
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin", trace_id });
+    }
+    validate_close_time(req.close_time)
+        .map_err(|message| ApiError::BadRequest { code: "invalid_close_time", trace_id, message: Some(message.into()) })?;
+    let timezone = req.timezone.map(|tz| tz.trim().to_string()).filter(|tz| !tz.is_empty()).unwrap_or_else(|| "UTC".into());
+    let known = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1)")
+        .bind(&timezone)
+        .fetch_one(&state.db)
+        .await
+        .map_err(db_error(trace_id))?;
+    if !known {
+        return Err(ApiError::BadRequest { code: "invalid_timezone", trace_id, message: Some(format!("Unknown timezone {timezone}")) });
+    }
+
+    sqlx::query(
+        "INSERT INTO store_business_days (tenant_id, store_id, timezone, close_time, updated_at)
+         VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (tenant_id, store_id)
+         DO UPDATE SET timezone = EXCLUDED.timezone, close_time = EXCLUDED.close_time, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(store_id)
+    .bind(&timezone)
+    .bind(req.close_time)
+    .execute(&state.db)
+    .await
+    .map_err(db_error(trace_id))?;
+    let settings = sqlx::query_as::<_, StoreBusinessDay>(BUSINESS_DAY_QUERY)
+        .bind(sec.tenant_id)
+        .bind(store_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(db_error(trace_id))?;
+    tracing::info!(tenant_id = %sec.tenant_id, %store_id, timezone = %settings.timezone, close_time = %settings.close_time, "Store business day updated");
+    Ok(Json(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blockers_list_only_what_is_outstanding() {
+        assert!(close_blockers(0, 0, 0).is_empty());
+        let blockers = close_blockers(2, 0, 1);
+        assert_eq!(blockers.iter().map(|b| b.code).collect::<Vec<_>>(), vec!["drawers_open", "offline_queue"]);
+        assert_eq!(blockers[0].count, 2);
+    }
+
+    #[test]
+    fn close_time_must_be_in_the_morning() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(validate_close_time(at(0, 0)).is_ok());
+        assert!(validate_close_time(at(2, 30)).is_ok());
+        assert!(validate_close_time(at(11, 59)).is_ok());
+        assert!(validate_close_time(at(12, 0)).is_err());
+        assert!(validate_close_time(at(23, 0)).is_err());
+    }
+}
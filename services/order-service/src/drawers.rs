@@ -0,0 +1,239 @@
+//! Cash drawers and register offline queues: what the end-of-day close checks before a business
+//! day can be closed (see [`crate::day_close`]).
+//!
+//! A drawer is opened on a register with a float and closed with a blind count. Expected cash is
+//! the float plus net cash taken on that register while the drawer was open (tendered less
+//! change, cash tips included), less cash refunds of orders rung up on it.
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::AppState;
+
+const DRAWER_COLUMNS: &str = "id, store_id, pos_instance_id, business_date, status, opening_float, opened_by, opened_at, counted_cash, expected_cash, variance, closed_by, closed_at";
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct DrawerSession {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub pos_instance_id: Uuid,
+    /// Business day the drawer was opened on; its Z-report lists it.
+    pub business_date: NaiveDate,
+    pub status: String,
+    pub opening_float: BigDecimal,
+    pub opened_by: Option<Uuid>,
+    pub opened_at: DateTime<Utc>,
+    pub counted_cash: Option<BigDecimal>,
+    pub expected_cash: Option<BigDecimal>,
+    /// Counted less expected; negative when the drawer is short.
+    pub variance: Option<BigDecimal>,
+    pub closed_by: Option<Uuid>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct OpenDrawerRequest {
+    pub store_id: Uuid,
+    pub pos_instance_id: Uuid,
+    pub opening_float_cents: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CloseDrawerRequest {
+    pub counted_cash_cents: i64,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListDrawersQuery {
+    pub store_id: Option<Uuid>,
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OfflineQueueReport {
+    pub store_id: Uuid,
+    pub pos_instance_id: Uuid,
+    pub pending_orders: i32,
+}
+
+fn ensure_register_role(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Cashier)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_cashier", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+fn db_error(trace_id: Option<Uuid>, context: &'static str) -> impl Fn(sqlx::Error) -> ApiError {
+    move |e| ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) }
+}
+
+/// Cash the drawer should hold if it were counted at `until`.
+pub(crate) async fn expected_cash(tx: &mut Transaction<'_, Postgres>, drawer_id: Uuid, until: DateTime<Utc>) -> sqlx::Result<BigDecimal> {
+    sqlx::query_scalar::<_, BigDecimal>(
+        "SELECT (d.opening_float
+                 + COALESCE((SELECT SUM(p.amount - COALESCE(p.change_cents, 0)::NUMERIC / 100)
+                             FROM payments p JOIN orders o ON o.id = p.order_id
+                             WHERE p.tenant_id = d.tenant_id AND p.method = 'cash' AND p.status = 'captured'
+                               AND o.pos_instance_id = d.pos_instance_id
+                               AND p.created_at >= d.opened_at AND p.created_at < $2), 0)
+                 - COALESCE((SELECT SUM(r.total)
+                             FROM order_returns r JOIN orders o ON o.id = r.order_id
+                             WHERE r.tenant_id = d.tenant_id AND o.payment_method = 'cash'
+                               AND o.pos_instance_id = d.pos_instance_id
+                               AND r.created_at >= d.opened_at AND r.created_at < $2), 0))::NUMERIC(10,2)
+         FROM drawer_sessions d WHERE d.id = $1",
+    )
+    .bind(drawer_id)
+    .bind(until)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+pub async fn open_drawer(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<OpenDrawerRequest>,
+) -> Result<Json<DrawerSession>, ApiError> {
+    ensure_register_role(&sec)?;
+    let trace_id = sec.trace_id;
+    if req.opening_float_cents < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_float", trace_id, message: Some("opening_float_cents cannot be negative".into()) });
+    }
+    let business_date = crate::day_close::business_date_at(&state.db, sec.tenant_id, Some(req.store_id), Utc::now())
+        .await
+        .map_err(db_error(trace_id, "Failed to open drawer"))?;
+    crate::day_close::ensure_day_open(&state.db, sec.tenant_id, Some(req.store_id), business_date, trace_id).await?;
+
+    let opened = sqlx::query_as::<_, DrawerSession>(&format!(
+        "INSERT INTO drawer_sessions (id, tenant_id, store_id, pos_instance_id, opening_float, opened_by, business_date)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (tenant_id, pos_instance_id) WHERE status = 'OPEN' DO NOTHING
+         RETURNING {DRAWER_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(sec.tenant_id)
+    .bind(req.store_id)
+    .bind(req.pos_instance_id)
+    .bind(Money::from_cents(req.opening_float_cents).inner())
+    .bind(sec.actor.id)
+    .bind(business_date)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error(trace_id, "Failed to open drawer"))?;
+    opened.map(Json).ok_or(ApiError::Conflict {
+        code: "drawer_already_open",
+        trace_id,
+        message: Some("This register already has an open drawer".into()),
+    })
+}
+
+/// Close a drawer with its counted cash and record the expected amount and variance.
+pub async fn close_drawer(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(drawer_id): Path<Uuid>,
+    Json(req): Json<CloseDrawerRequest>,
+) -> Result<Json<DrawerSession>, ApiError> {
+    ensure_register_role(&sec)?;
+    let trace_id = sec.trace_id;
+    if req.counted_cash_cents < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_count", trace_id, message: Some("counted_cash_cents cannot be negative".into()) });
+    }
+    let db_err = db_error(trace_id, "Failed to close drawer");
+
+    let mut tx = state.db.begin().await.map_err(&db_err)?;
+    let status = sqlx::query_scalar::<_, String>("SELECT status FROM drawer_sessions WHERE id = $1 AND tenant_id = $2 FOR UPDATE")
+        .bind(drawer_id)
+        .bind(sec.tenant_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(&db_err)?
+        .ok_or(ApiError::NotFound { code: "drawer_not_found", trace_id })?;
+    if status != "OPEN" {
+        return Err(ApiError::Conflict { code: "drawer_closed", trace_id, message: Some("Drawer is already closed".into()) });
+    }
+    let closed_at = Utc::now();
+    let expected = expected_cash(&mut tx, drawer_id, closed_at).await.map_err(&db_err)?;
+    let counted = Money::from_cents(req.counted_cash_cents).inner().clone();
+    let variance = &counted - &expected;
+    let closed = sqlx::query_as::<_, DrawerSession>(&format!(
+        "UPDATE drawer_sessions
+         SET status = 'CLOSED', counted_cash = $2, expected_cash = $3, variance = $4, closed_by = $5, closed_at = $6
+         WHERE id = $1
+         RETURNING {DRAWER_COLUMNS}"
+    ))
+    .bind(drawer_id)
+    .bind(&counted)
+    .bind(&expected)
+    .bind(&variance)
+    .bind(sec.actor.id)
+    .bind(closed_at)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(&db_err)?;
+    tx.commit().await.map_err(&db_err)?;
+    if variance != BigDecimal::from(0) {
+        tracing::info!(drawer_id = %drawer_id, tenant_id = %sec.tenant_id, %variance, "Drawer closed with a variance");
+    }
+    Ok(Json(closed))
+}
+
+pub async fn list_drawers(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(q): Query<ListDrawersQuery>,
+) -> Result<Json<Vec<DrawerSession>>, ApiError> {
+    ensure_register_role(&sec)?;
+    let status = q.status.map(|s| s.trim().to_ascii_uppercase());
+    if status.as_deref().is_some_and(|s| s != "OPEN" && s != "CLOSED") {
+        return Err(ApiError::BadRequest { code: "invalid_status", trace_id: sec.trace_id, message: Some("status must be OPEN or CLOSED".into()) });
+    }
+    let drawers = sqlx::query_as::<_, DrawerSession>(&format!(
+        "SELECT {DRAWER_COLUMNS} FROM drawer_sessions
+         WHERE tenant_id = $1 AND ($2::uuid IS NULL OR store_id = $2) AND ($3::text IS NULL OR status = $3)
+         ORDER BY opened_at DESC
+         LIMIT 200"
+    ))
+    .bind(sec.tenant_id)
+    .bind(q.store_id)
+    .bind(status)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error(sec.trace_id, "Failed to list drawers"))?;
+    Ok(Json(drawers))
+}
+
+/// Registers report how many offline sales they still hold; the day can't close until every
+/// register at the store has reported zero.
+pub async fn report_offline_queue(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<OfflineQueueReport>,
+) -> Result<axum::http::StatusCode, ApiError> {
+    ensure_register_role(&sec)?;
+    if req.pending_orders < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_queue_depth", trace_id: sec.trace_id, message: Some("pending_orders cannot be negative".into()) });
+    }
+    sqlx::query(
+        "INSERT INTO pos_offline_queues (tenant_id, pos_instance_id, store_id, pending_orders, reported_at)
+         VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (tenant_id, pos_instance_id)
+         DO UPDATE SET store_id = EXCLUDED.store_id, pending_orders = EXCLUDED.pending_orders, reported_at = EXCLUDED.reported_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(req.pos_instance_id)
+    .bind(req.store_id)
+    .bind(req.pending_orders)
+    .execute(&state.db)
+    .await
+    .map_err(db_error(sec.trace_id, "Failed to record offline queue"))?;
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
@@ -1,4 +1,24 @@
 pub mod order_handlers;
+pub mod order_edits;
+pub mod order_voids;
+pub mod order_disputes;
+pub mod order_stand_in;
+pub mod order_rmas;
+pub mod order_exchanges;
 pub mod app;
+pub mod config;
+pub mod carts;
+pub mod reorders;
+pub mod pii;
+pub mod tips;
+pub mod offline_bundle;
+pub mod modifiers;
+pub mod drawers;
+pub mod day_close;
+pub mod checkout_saga;
+pub mod outbox;
+pub mod product_merges;
+pub mod retention;
+pub mod tenant_diagnostics;
 
-pub use app::{AppState, build_router, build_jwt_verifier_from_env, spawn_jwks_refresh};
+pub use app::{AppState, build_router, build_jwt_verifier, spawn_jwks_refresh};
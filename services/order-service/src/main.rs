@@ -1,126 +1,80 @@
 use axum::Router;
+use common_config::config_route;
+use common_observability::{SloConfig, SloTracker};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
 use common_money::log_rounding_mode_once;
 use reqwest::Client;
-use sqlx::PgPool;
-use std::env;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
 use std::sync::Arc;
-use std::net::SocketAddr; 
 use tokio::net::TcpListener;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
 use uuid::Uuid;
 
 // Reuse shared app builder and types from the library crate
-use order_service::{AppState, build_router, build_jwt_verifier_from_env, spawn_jwks_refresh};
-use order_service::app::ORDER_REGISTRY;
+use order_service::config::OrderConfig;
+use order_service::{AppState, build_router, build_jwt_verifier, spawn_jwks_refresh};
+use order_service::retention::{spawn_retention_job, RetentionPolicy};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use order_service::outbox::{purge_published, Delivery, OutboxRelay};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use futures_util::StreamExt;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::consumer::{Consumer, StreamConsumer};
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::Message;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use bigdecimal::BigDecimal;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use chrono::NaiveDate;
 
-// Kafka-only event and row types used by the background consumer
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-#[derive(serde::Deserialize, Debug)]
-#[allow(dead_code)]
-struct PaymentCompletedEvent { pub order_id: Uuid, pub tenant_id: Uuid, pub amount: BigDecimal }
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-#[derive(serde::Deserialize, Debug)]
-#[allow(dead_code)]
-struct PaymentFailedEvent { pub order_id: Uuid, pub tenant_id: Uuid, pub method: String, pub reason: String }
+use common_events::{
+    topics, DomainEvent, OrderCompletedEvent, OrderEventItem, OrderVoidedEvent, PaymentCompletedEvent, PaymentDisputeUpdatedEvent,
+    PaymentFailedEvent, PaymentStandInEvent, ProductMergedEvent,
+};
+
+// Kafka-only row types used by the background consumer
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
 #[derive(sqlx::FromRow)]
 #[allow(dead_code)]
-struct OrderFinancialSummary { total: Option<BigDecimal>, customer_id: Option<Uuid>, offline: bool, payment_method: String }
+struct OrderFinancialSummary { total: Option<BigDecimal>, customer_id: Option<Uuid>, offline: bool, payment_method: String, store_id: Option<Uuid>, created_by: Option<Uuid>, business_date: NaiveDate }
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
 #[derive(sqlx::FromRow)]
 #[allow(dead_code)]
-struct OrderItemFinancialRow { product_id: Uuid, quantity: i32, unit_price: BigDecimal, line_total: BigDecimal }
-
+struct OrderItemFinancialRow { product_id: Uuid, quantity: i32, unit_price: BigDecimal, line_total: BigDecimal, measured_quantity: Option<BigDecimal> }
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-#[derive(sqlx::FromRow, Debug)]
-struct OutboxRow {
-    id: i64,
-    tenant_id: String,
-    topic: String,
-    payload: serde_json::Value,
+impl OrderItemFinancialRow {
+    fn into_event_item(self) -> OrderEventItem {
+        OrderEventItem { product_id: self.product_id, quantity: self.quantity, unit_price: self.unit_price, line_total: self.line_total, measured_quantity: self.measured_quantity }
+    }
 }
 
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-static OUTBOX_PUBLISHED: once_cell::sync::Lazy<prometheus::IntCounterVec> = once_cell::sync::Lazy::new(|| {
-    let v = prometheus::IntCounterVec::new(
-        prometheus::Opts::new("outbox_published_total", "Total number of outbox events successfully published"),
-        &["topic"],
-    ).unwrap();
-    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
-    v
-});
-
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-static OUTBOX_FAILURES: once_cell::sync::Lazy<prometheus::IntCounterVec> = once_cell::sync::Lazy::new(|| {
-    let v = prometheus::IntCounterVec::new(
-        prometheus::Opts::new("outbox_publish_failures_total", "Total number of outbox publish failures"),
-        &["topic"],
-    ).unwrap();
-    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
-    v
-});
-
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-static OUTBOX_RETRIES: once_cell::sync::Lazy<prometheus::IntCounterVec> = once_cell::sync::Lazy::new(|| {
-    let v = prometheus::IntCounterVec::new(
-        prometheus::Opts::new("outbox_publish_retries_total", "Total number of outbox publish retries"),
-        &["topic"],
-    ).unwrap();
-    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
-    v
-});
-
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-static OUTBOX_BACKLOG: once_cell::sync::Lazy<prometheus::IntGauge> = once_cell::sync::Lazy::new(|| {
-    let v = prometheus::IntGauge::new("outbox_backlog", "Current number of unpublished outbox rows").unwrap();
-    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
-    v
-});
-
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-static OUTBOX_BACKLOG_BY_TOPIC: once_cell::sync::Lazy<prometheus::IntGaugeVec> = once_cell::sync::Lazy::new(|| {
-    let v = prometheus::IntGaugeVec::new(
-        prometheus::Opts::new("outbox_backlog_by_topic", "Current number of unpublished outbox rows, by topic"),
-        &["topic"],
-    ).unwrap();
-    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
-    v
-});
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("order-service");
     log_rounding_mode_once();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db = PgPool::connect(&database_url).await?;
+    let config = OrderConfig::from_env().await?;
+    let db = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::register_metrics(&order_service::app::ORDER_REGISTRY);
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db.clone());
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let kafka_producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
-        .create()
+    let kafka_security = KafkaSecurity::from_env().await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    let kafka_producer: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&config.kafka_bootstrap, &kafka_security)
         .expect("failed to create kafka producer");
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::register_metrics(&order_service::app::ORDER_REGISTRY);
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    order_service::carts::spawn_cart_expiry(db.clone());
 
     let http_client = Client::new();
-    let inventory_base_url =
-        env::var("INVENTORY_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8087".to_string());
-    let payment_base_url =
-        env::var("PAYMENT_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8086".to_string());
-    let enable_payment_intents = env::var("ENABLE_PAYMENT_INTENTS")
-        .ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
+    let inventory_base_url = config.inventory_service_url.clone();
+    let payment_base_url = config.payment_service_url.clone();
+    let enable_payment_intents = config.enable_payment_intents;
+
+    let pii_key = Some(std::sync::Arc::new(order_service::pii::PiiKeyring::from_env()?));
 
     // TODO(P0-04): expose checkout_latency_seconds and tap_count_total via /metrics with labels tenant_id/store_id/terminal_id
 
@@ -131,16 +85,17 @@ async fn main() -> anyhow::Result<()> {
         jwt_verifier,
         http_client: http_client.clone(),
         inventory_base_url: inventory_base_url.clone(),
+        pii_key: pii_key.clone(),
         audit_producer: Some(Arc::new(common_audit::BufferedAuditProducer::new(
             common_audit::AuditProducer::new(common_audit::KafkaAuditSink::new(
                 kafka_producer.clone(),
-                common_audit::AuditProducerConfig { topic: env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events".to_string()) }
+                common_audit::AuditProducerConfig { topic: config.audit_topic.clone() }
             )),
             1024,
         ))),
     };
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    tracing::info!(topic = %env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events".to_string()), "Audit producer initialized");
+    tracing::info!(topic = %config.audit_topic, "Audit producer initialized");
     #[cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
     let state = AppState {
         db: db.clone(),
@@ -149,88 +104,99 @@ async fn main() -> anyhow::Result<()> {
         inventory_base_url: inventory_base_url.clone(),
         payment_base_url: payment_base_url.clone(),
         enable_payment_intents,
+        pii_key,
     };
 
+    if let Some(service_token) = config.service_token.as_ref().filter(|_| config.saga_sweep_secs > 0) {
+        order_service::checkout_saga::spawn_saga_sweeper(
+            state.clone(),
+            std::time::Duration::from_secs(config.saga_sweep_secs),
+            std::time::Duration::from_secs(config.saga_timeout_secs),
+            service_token.expose().clone(),
+        );
+    }
+
+    if config.retention_months > 0 {
+        let policy = RetentionPolicy {
+            months: config.retention_months,
+            batch_size: config.retention_batch_size,
+            dry_run: config.retention_dry_run,
+        };
+        tracing::info!(months = policy.months, dry_run = policy.dry_run, "Order retention job enabled");
+        spawn_retention_job(state.clone(), policy, std::time::Duration::from_secs(config.retention_interval_secs));
+    }
+
     // Build the HTTP app with shared router wiring (CORS, middleware, routes)
-    let app: Router = build_router(state.clone());
+    let slo = SloTracker::new("order-service", SloConfig::from_env(), &order_service::app::ORDER_REGISTRY);
+    let app: Router = build_router(state.clone())
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
+        .layer(axum::middleware::from_fn(slo.layer()));
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     {
         let db_pool = db.clone();
         let producer = kafka_producer.clone();
-        // Outbox worker (feature-flagged via env var OUTBOX_WORKER)
-        let outbox_enabled = env::var("OUTBOX_WORKER").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
-        if outbox_enabled {
+        let kafka_bootstrap = config.kafka_bootstrap.clone();
+        let outbox_mode = config.outbox_mode;
+        // Outbox worker (feature-flagged via OUTBOX_WORKER); replicas claim disjoint rows.
+        if config.outbox_worker {
+            let relay = OutboxRelay::new(Duration::from_secs(config.outbox_lease_secs), config.outbox_batch_size)
+                .with_max_retries(config.outbox_max_retries);
+            tracing::info!(worker = %relay.worker_id, lease_secs = config.outbox_lease_secs, max_retries = relay.max_retries, "Outbox worker enabled");
+            if config.outbox_retention_days > 0 {
+                let db_pool = db_pool.clone();
+                let retention = Duration::from_secs(config.outbox_retention_days * 86_400);
+                tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(Duration::from_secs(3600));
+                    loop {
+                        ticker.tick().await;
+                        match purge_published(&db_pool, retention).await {
+                            Ok(0) => {}
+                            Ok(purged) => tracing::info!(purged, "Purged published outbox rows"),
+                            Err(err) => tracing::warn!(?err, "Failed to purge published outbox rows"),
+                        }
+                    }
+                });
+            }
             tokio::spawn(async move {
                 let mut ticker = tokio::time::interval(Duration::from_millis(750));
                 loop {
                     ticker.tick().await;
-                    // Fetch a small batch of unpublished outbox rows
-                    let rows: Result<Vec<OutboxRow>, _> = sqlx::query_as::<_, OutboxRow>(
-                        "SELECT id, tenant_id, topic, payload FROM outbox WHERE published_at IS NULL ORDER BY created_at ASC LIMIT 50"
-                    ).fetch_all(&db_pool).await;
-                    let Ok(batch) = rows else { continue };
-                    OUTBOX_BACKLOG.set(batch.len() as i64);
-                    // Refresh per-topic backlog from DB (full counts)
-                    if let Ok(topic_counts) = sqlx::query(
-                        "SELECT topic, COUNT(*)::BIGINT as cnt FROM outbox WHERE published_at IS NULL GROUP BY topic"
-                    ).fetch_all(&db_pool).await {
-                        for r in topic_counts {
-                            let topic: String = r.get::<String, _>("topic");
-                            let cnt: i64 = r.get::<i64, _>("cnt");
-                            OUTBOX_BACKLOG_BY_TOPIC.with_label_values(&[&topic]).set(cnt);
-                        }
+                    if let Err(err) = OutboxRelay::refresh_backlog(&db_pool).await {
+                        tracing::warn!(?err, "Failed to refresh outbox backlog");
                     }
-                    for row in batch {
-                        let payload_str = row.payload.to_string();
-                        let send_res = producer
-                            .send(
-                                FutureRecord::to(&row.topic)
-                                    .payload(&payload_str)
-                                    .key(&row.tenant_id),
-                                Duration::from_secs(0),
-                            )
-                            .await;
-                        match send_res {
-                            Ok(_) => {
-                                // Mark as published
-                                if let Err(err) = sqlx::query("UPDATE outbox SET published_at = NOW() WHERE id = $1")
-                                    .bind(row.id)
-                                    .execute(&db_pool)
-                                    .await
-                                {
-                                    tracing::error!(?err, outbox_id = row.id, "Failed to mark outbox row published");
+                    let sent = relay
+                        .relay_batch(&db_pool, |row| {
+                            let producer = producer.clone();
+                            async move { common_kafka::publish(&producer, &row.topic, row.key(), &row.payload.to_string()).await }
+                        })
+                        .await;
+                    match sent {
+                        Ok(outcomes) => {
+                            for (row, delivery) in outcomes {
+                                if delivery == Delivery::LeaseLost {
+                                    tracing::warn!(outbox_id = row.id, topic = %row.topic, "Outbox row was re-claimed before it was marked published; it may be sent twice");
                                 }
-                                OUTBOX_PUBLISHED.with_label_values(&[&row.topic]).inc();
-                            }
-                            Err(err) => {
-                                tracing::warn!(?err, outbox_id = row.id, topic = %row.topic, "Failed to publish outbox event, will retry");
-                                let _ = sqlx::query("UPDATE outbox SET retry_count = retry_count + 1 WHERE id = $1")
-                                    .bind(row.id)
-                                    .execute(&db_pool)
-                                    .await;
-                                OUTBOX_FAILURES.with_label_values(&[&row.topic]).inc();
-                                OUTBOX_RETRIES.with_label_values(&[&row.topic]).inc();
                             }
                         }
+                        Err(err) => tracing::error!(?err, "Outbox relay batch failed"),
                     }
                 }
             });
-            tracing::info!("Outbox worker enabled");
         } else {
             tracing::info!("Outbox worker disabled (set OUTBOX_WORKER=1 to enable)");
         }
         tokio::spawn(async move {
-            let consumer: StreamConsumer = rdkafka::ClientConfig::new()
-                .set(
-                    "bootstrap.servers",
-                    env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-                )
+            let consumer: StreamConsumer = kafka_security
+                .apply(&mut rdkafka::ClientConfig::new())
+                .set("bootstrap.servers", &kafka_bootstrap)
                 .set("group.id", "order-service")
                 .create()
                 .expect("failed to create kafka consumer");
             consumer
-                .subscribe(&["payment.completed", "payment.failed"])
+                .subscribe(&[topics::PAYMENT_COMPLETED, topics::PAYMENT_FAILED, topics::PAYMENT_STAND_IN, topics::PAYMENT_DISPUTE_UPDATED, topics::PRODUCT_MERGED])
                 .expect("failed to subscribe");
             let mut stream = consumer.stream();
             while let Some(msg) = stream.next().await {
@@ -239,11 +205,11 @@ async fn main() -> anyhow::Result<()> {
                         let topic = m.topic();
                         if let Some(Ok(payload)) = m.payload_view::<str>() {
                             match topic {
-                                "payment.completed" => {
-                                match serde_json::from_str::<PaymentCompletedEvent>(payload) {
+                                topics::PAYMENT_COMPLETED => {
+                                match common_events::decode::<PaymentCompletedEvent>(payload) {
                                     Ok(evt) => {
                                         if let Err(err) = sqlx::query(
-                                            "UPDATE orders SET status = 'COMPLETED' WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'",
+                                            "UPDATE orders SET status = 'COMPLETED' WHERE id = $1 AND tenant_id = $2 AND status IN ('PENDING', 'CONDITIONALLY_APPROVED')",
                                         )
                                         .bind(evt.order_id)
                                         .bind(evt.tenant_id)
@@ -259,7 +225,7 @@ async fn main() -> anyhow::Result<()> {
                                         }
 
                                         match sqlx::query_as::<_, OrderFinancialSummary>(
-                                            "SELECT total::FLOAT8 as total, customer_id, offline, payment_method FROM orders WHERE id = $1 AND tenant_id = $2",
+                                            "SELECT total, customer_id, offline, payment_method, store_id, created_by, business_date FROM orders WHERE id = $1 AND tenant_id = $2",
                                         )
                                         .bind(evt.order_id)
                                         .bind(evt.tenant_id)
@@ -268,43 +234,39 @@ async fn main() -> anyhow::Result<()> {
                                         {
                                             Ok(Some(order_row)) => {
                                                 match sqlx::query_as::<_, OrderItemFinancialRow>(
-                                                    "SELECT product_id, quantity, unit_price::FLOAT8 as unit_price, line_total::FLOAT8 as line_total FROM order_items WHERE order_id = $1",
+                                                    "SELECT product_id, quantity, unit_price, line_total, measured_quantity FROM order_items WHERE order_id = $1",
                                                 )
                                                 .bind(evt.order_id)
                                                 .fetch_all(&db_pool)
                                                 .await
                                                 {
                                                     Ok(item_rows) => {
-                                                        let event_items: Vec<serde_json::Value> = item_rows
-                                                            .into_iter()
-                                                            .map(|row| {
-                                                                serde_json::json!({
-                                                                    "product_id": row.product_id,
-                                                                    "quantity": row.quantity,
-                                                                    "unit_price": row.unit_price,
-                                                                    "line_total": row.line_total,
-                                                                })
-                                                            })
-                                                            .collect();
-
-                                                        let event = serde_json::json!({
-                                                            "order_id": evt.order_id,
-                                                            "tenant_id": evt.tenant_id,
-                                                            "items": event_items,
-                                                            "total": order_row.total,
-                                                            "customer_id": order_row.customer_id,
-                                                            "offline": order_row.offline,
-                                                            "payment_method": order_row.payment_method,
-                                                        });
+                                                        let event = OrderCompletedEvent {
+                                                            schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+                                                            order_id: evt.order_id,
+                                                            tenant_id: evt.tenant_id,
+                                                            items: item_rows.into_iter().map(OrderItemFinancialRow::into_event_item).collect(),
+                                                            total: order_row.total.unwrap_or_else(|| BigDecimal::from(0)),
+                                                            customer_id: order_row.customer_id,
+                                                            offline: order_row.offline,
+                                                            payment_method: order_row.payment_method,
+                                                            return_id: None,
+                                                            location_id: order_row.store_id,
+                                                            employee_id: order_row.created_by,
+                                                            rma_id: None,
+                                                            exchange_return_id: None,
+                                                            business_date: Some(order_row.business_date),
+                                                        };
 
-                                                        let use_outbox = env::var("ORDER_OUTBOX_MODE").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
+                                                        let use_outbox = outbox_mode;
                                                         if use_outbox {
                                                             if let Err(err) = sqlx::query(
-                                                                "INSERT INTO outbox (tenant_id, topic, payload) VALUES ($1, $2, $3)"
+                                                                "INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, $2, $3, $4)"
                                                             )
                                                             .bind(evt.tenant_id.to_string())
-                                                            .bind("order.completed")
-                                                            .bind(event)
+                                                            .bind(topics::ORDER_COMPLETED)
+                                                            .bind(common_events::to_value(&event).unwrap_or_default())
+                                                            .bind(event.partition_key())
                                                             .execute(&db_pool)
                                                             .await {
                                                                 tracing::error!(?err, "Failed to enqueue order.completed to outbox");
@@ -312,14 +274,8 @@ async fn main() -> anyhow::Result<()> {
                                                                 tracing::info!(order_id=%evt.order_id, tenant_id=%evt.tenant_id, "Enqueued order.completed to outbox");
                                                             }
                                                         } else {
-                                                            if let Err(err) = producer
-                                                                .send(
-                                                                    FutureRecord::to("order.completed")
-                                                                        .payload(&event.to_string())
-                                                                        .key(&evt.tenant_id.to_string()),
-                                                                    Duration::from_secs(0),
-                                                                )
-                                                                .await
+                                                            if let Err(err) =
+                                                                common_kafka::publish_event(&producer, &db_pool, evt.tenant_id, &event).await
                                                             {
                                                                 tracing::error!(
                                                                     ?err,
@@ -371,11 +327,11 @@ async fn main() -> anyhow::Result<()> {
                                     }
                                 }
                             }
-                                "payment.failed" => {
-                                match serde_json::from_str::<PaymentFailedEvent>(payload) {
+                                topics::PAYMENT_FAILED => {
+                                match common_events::decode::<PaymentFailedEvent>(payload) {
                                     Ok(evt) => {
                                         match sqlx::query(
-                                            "UPDATE orders SET status = 'NOT_ACCEPTED' WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'",
+                                            "UPDATE orders SET status = 'NOT_ACCEPTED' WHERE id = $1 AND tenant_id = $2 AND status IN ('PENDING', 'CONDITIONALLY_APPROVED')",
                                         )
                                         .bind(evt.order_id)
                                         .bind(evt.tenant_id)
@@ -401,7 +357,7 @@ async fn main() -> anyhow::Result<()> {
                                                     );
 
                                                     match sqlx::query_as::<_, OrderFinancialSummary>(
-                                                        "SELECT total::FLOAT8 as total, customer_id, offline, payment_method FROM orders WHERE id = $1 AND tenant_id = $2",
+                                                        "SELECT total, customer_id, offline, payment_method, store_id, created_by, business_date FROM orders WHERE id = $1 AND tenant_id = $2",
                                                     )
                                                     .bind(evt.order_id)
                                                     .bind(evt.tenant_id)
@@ -410,52 +366,44 @@ async fn main() -> anyhow::Result<()> {
                                                     {
                                                         Ok(Some(order_row)) => {
                                                             match sqlx::query_as::<_, OrderItemFinancialRow>(
-                                                                "SELECT product_id, quantity, unit_price::FLOAT8 as unit_price, line_total::FLOAT8 as line_total FROM order_items WHERE order_id = $1",
+                                                                "SELECT product_id, quantity, unit_price, line_total, measured_quantity FROM order_items WHERE order_id = $1",
                                                             )
                                                             .bind(evt.order_id)
                                                             .fetch_all(&db_pool)
                                                             .await
                                                             {
                                                                 Ok(item_rows) => {
-                                                                    let event_items: Vec<serde_json::Value> = item_rows
-                                                                        .into_iter()
-                                                                        .map(|row| {
-                                                                            serde_json::json!({
-                                                                                "product_id": row.product_id,
-                                                                                "quantity": row.quantity,
-                                                                                "unit_price": row.unit_price,
-                                                                                "line_total": row.line_total,
-                                                                            })
-                                                                        })
-                                                                        .collect();
-
                                                                     let void_reason = if evt.reason.is_empty() {
                                                                         Some(String::from("payment_failed"))
                                                                     } else {
                                                                         Some(format!("payment_failed: {}", evt.reason))
                                                                     };
-                                                                    let void_event = serde_json::json!({
-                                                                        "order_id": evt.order_id,
-                                                                        "tenant_id": evt.tenant_id,
-                                                                        "items": event_items,
-                                                                        "total": order_row
-                                                                            .total
-                                                                            .clone()
-                                                                            .unwrap_or_else(|| BigDecimal::from(0)),
-                                                                        "customer_id": order_row.customer_id,
-                                                                        "offline": order_row.offline,
-                                                                        "payment_method": order_row.payment_method,
-                                                                        "reason": void_reason,
-                                                                    });
+                                                                    let void_event = OrderVoidedEvent {
+                                                                        schema_version: OrderVoidedEvent::SCHEMA_VERSION,
+                                                                        order_id: evt.order_id,
+                                                                        tenant_id: evt.tenant_id,
+                                                                        items: item_rows.into_iter().map(OrderItemFinancialRow::into_event_item).collect(),
+                                                                        total: order_row.total.clone().unwrap_or_else(|| BigDecimal::from(0)),
+                                                                        customer_id: order_row.customer_id,
+                                                                        offline: order_row.offline,
+                                                                        payment_method: order_row.payment_method.clone(),
+                                                                        reason: void_reason,
+                                                                        reason_code: None,
+                                                                        requested_by: None,
+                                                                        approved_by: None,
+                                                                        approval_method: None,
+                                                                        business_date: Some(order_row.business_date),
+                                                                    };
 
-                                                                    let use_outbox = env::var("ORDER_OUTBOX_MODE").ok().map(|v| v=="1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
+                                                                    let use_outbox = outbox_mode;
                                                                     if use_outbox {
                                                                         if let Err(err) = sqlx::query(
-                                                                            "INSERT INTO outbox (tenant_id, topic, payload) VALUES ($1, $2, $3)"
+                                                                            "INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, $2, $3, $4)"
                                                                         )
                                                                         .bind(evt.tenant_id.to_string())
-                                                                        .bind("order.voided")
-                                                                        .bind(void_event)
+                                                                        .bind(topics::ORDER_VOIDED)
+                                                                        .bind(common_events::to_value(&void_event).unwrap_or_default())
+                                                                        .bind(void_event.partition_key())
                                                                         .execute(&db_pool)
                                                                         .await {
                                                                             tracing::error!(?err, "Failed to enqueue order.voided to outbox");
@@ -463,14 +411,8 @@ async fn main() -> anyhow::Result<()> {
                                                                             tracing::info!(order_id=%evt.order_id, tenant_id=%evt.tenant_id, "Enqueued order.voided to outbox");
                                                                         }
                                                                     } else {
-                                                                        if let Err(err) = producer
-                                                                            .send(
-                                                                                FutureRecord::to("order.voided")
-                                                                                    .payload(&void_event.to_string())
-                                                                                    .key(&evt.tenant_id.to_string()),
-                                                                                Duration::from_secs(0),
-                                                                            )
-                                                                            .await
+                                                                        if let Err(err) =
+                                                                            common_kafka::publish_event(&producer, &db_pool, evt.tenant_id, &void_event).await
                                                                         {
                                                                             tracing::error!(
                                                                                 ?err,
@@ -523,6 +465,35 @@ async fn main() -> anyhow::Result<()> {
                                         tracing::error!(?err, "Failed to parse PaymentFailedEvent");
                                     }
                                 }
+                            }
+                                topics::PAYMENT_STAND_IN => {
+                                match common_events::decode::<PaymentStandInEvent>(payload) {
+                                    Ok(evt) => match order_service::order_stand_in::apply_stand_in(&db_pool, &evt).await {
+                                        Ok(true) => tracing::warn!(order_id = %evt.order_id, tenant_id = %evt.tenant_id, stand_in_id = %evt.stand_in_id, "Order conditionally approved in stand-in"),
+                                        Ok(false) => tracing::warn!(order_id = %evt.order_id, tenant_id = %evt.tenant_id, "Stand-in payment received for an order that is not pending"),
+                                        Err(err) => tracing::error!(?err, order_id = %evt.order_id, "Failed to mark order conditionally approved"),
+                                    },
+                                    Err(err) => tracing::error!(?err, "Failed to parse PaymentStandInEvent"),
+                                }
+                            }
+                                topics::PAYMENT_DISPUTE_UPDATED => {
+                                match common_events::decode::<PaymentDisputeUpdatedEvent>(payload) {
+                                    Ok(evt) => match order_service::order_disputes::apply_dispute_update(&db_pool, &evt).await {
+                                        Ok(true) => {}
+                                        Ok(false) => tracing::warn!(order_id = %evt.order_id, tenant_id = %evt.tenant_id, "Dispute received for unknown order"),
+                                        Err(err) => tracing::error!(?err, order_id = %evt.order_id, "Failed to record dispute on order"),
+                                    },
+                                    Err(err) => tracing::error!(?err, "Failed to parse PaymentDisputeUpdatedEvent"),
+                                }
+                            }
+                                topics::PRODUCT_MERGED => {
+                                match common_events::decode::<ProductMergedEvent>(payload) {
+                                    Ok(evt) => match order_service::product_merges::apply_product_merge(&db_pool, &evt).await {
+                                        Ok(counts) => tracing::info!(duplicate_id = %evt.duplicate_id, canonical_id = %evt.canonical_id, ?counts, "Relabelled order lines of merged product"),
+                                        Err(err) => tracing::error!(?err, duplicate_id = %evt.duplicate_id, "Failed to relabel order lines of merged product"),
+                                    },
+                                    Err(err) => tracing::error!(?err, "Failed to parse ProductMergedEvent"),
+                                }
                             }
                                 _ => {}
                             }
@@ -534,16 +505,12 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8084);
-    let ip: std::net::IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    let addr = config.http.addr();
     println!("starting order-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::flush(&kafka_producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
@@ -0,0 +1,182 @@
+//! Menu modifiers chosen on order lines.
+//!
+//! Groups and options are maintained by product-service (`product_modifier_groups`,
+//! `product_modifiers`) and read here from the shared database, like `products`. Lines name the
+//! options they chose by id; each chosen option is snapshotted onto the order item with its group
+//! and price delta, so later catalog edits do not change what was sold or what the kitchen saw.
+
+use bigdecimal::BigDecimal;
+use common_http_errors::ApiError;
+use common_money::Money;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// An option chosen on a line, as recorded on `order_items.modifiers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedModifier {
+    pub modifier_id: Uuid,
+    pub group: String,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+/// One modifier group of a product with its selection bounds.
+#[derive(Debug, Clone)]
+pub struct GroupRule {
+    pub name: String,
+    pub min_select: i32,
+    pub max_select: i32,
+    /// `(modifier_id, name, price_delta)` in display order.
+    pub options: Vec<(Uuid, String, BigDecimal)>,
+}
+
+/// Modifier groups keyed by product; products without groups are absent.
+pub type ModifierRules = HashMap<Uuid, Vec<GroupRule>>;
+
+#[derive(sqlx::FromRow)]
+struct RuleRow {
+    product_id: Uuid,
+    group_id: Uuid,
+    group_name: String,
+    min_select: i32,
+    max_select: i32,
+    modifier_id: Uuid,
+    modifier_name: String,
+    price_delta: BigDecimal,
+}
+
+/// Load the modifier groups of `product_ids`.
+pub async fn load_modifier_rules(db: &PgPool, tenant_id: Uuid, product_ids: &[Uuid]) -> Result<ModifierRules, sqlx::Error> {
+    let rows = sqlx::query_as::<_, RuleRow>(
+        "SELECT g.product_id, g.id AS group_id, g.name AS group_name, g.min_select, g.max_select,
+                m.id AS modifier_id, m.name AS modifier_name, m.price_delta
+         FROM product_modifier_groups g
+         JOIN product_modifiers m ON m.group_id = g.id AND m.tenant_id = g.tenant_id
+         WHERE g.tenant_id = $1 AND g.product_id = ANY($2)
+         ORDER BY g.product_id, g.position, g.id, m.position, m.id",
+    )
+    .bind(tenant_id)
+    .bind(product_ids)
+    .fetch_all(db)
+    .await?;
+    let mut rules = ModifierRules::new();
+    let mut last_group: Option<Uuid> = None;
+    for row in rows {
+        let groups = rules.entry(row.product_id).or_default();
+        if last_group != Some(row.group_id) {
+            groups.push(GroupRule { name: row.group_name, min_select: row.min_select, max_select: row.max_select, options: Vec::new() });
+            last_group = Some(row.group_id);
+        }
+        if let Some(group) = groups.last_mut() {
+            group.options.push((row.modifier_id, row.modifier_name, row.price_delta));
+        }
+    }
+    Ok(rules)
+}
+
+fn bad_request(code: &'static str, message: String) -> ApiError {
+    ApiError::BadRequest { code, trace_id: None, message: Some(message) }
+}
+
+/// Check a line's chosen options against its product's groups: every option must belong to the
+/// product, forced groups must be answered and no group may exceed its maximum. Returns the
+/// snapshot to record, in the product's display order.
+pub fn resolve_modifiers(product_id: Uuid, groups: &[GroupRule], selected: &[Uuid]) -> Result<Vec<AppliedModifier>, ApiError> {
+    for (i, id) in selected.iter().enumerate() {
+        if selected[..i].contains(id) {
+            return Err(bad_request("duplicate_modifier", format!("Modifier {id} is chosen more than once")));
+        }
+        if !groups.iter().any(|g| g.options.iter().any(|(option_id, _, _)| option_id == id)) {
+            return Err(bad_request("unknown_modifier", format!("Modifier {id} is not offered on product {product_id}")));
+        }
+    }
+    let mut applied = Vec::with_capacity(selected.len());
+    for group in groups {
+        let chosen: Vec<&(Uuid, String, BigDecimal)> = group.options.iter().filter(|(id, _, _)| selected.contains(id)).collect();
+        let count = chosen.len() as i32;
+        if count < group.min_select {
+            return Err(bad_request(
+                "modifier_required",
+                format!("'{}' needs at least {} choice(s) on product {product_id}", group.name, group.min_select),
+            ));
+        }
+        if count > group.max_select {
+            return Err(bad_request(
+                "too_many_modifiers",
+                format!("'{}' allows at most {} choice(s) on product {product_id}", group.name, group.max_select),
+            ));
+        }
+        applied.extend(chosen.into_iter().map(|(id, name, delta)| AppliedModifier {
+            modifier_id: *id,
+            group: group.name.clone(),
+            name: name.clone(),
+            price_delta: Money::new(delta.clone()),
+        }));
+    }
+    Ok(applied)
+}
+
+/// Resolve a line against `rules`; lines for products without groups may not name options.
+pub fn resolve_line(rules: &ModifierRules, product_id: Uuid, selected: &[Uuid]) -> Result<Vec<AppliedModifier>, ApiError> {
+    resolve_modifiers(product_id, rules.get(&product_id).map(Vec::as_slice).unwrap_or_default(), selected)
+}
+
+/// Unit price in cents once the chosen options' deltas are added; 400 if that goes negative.
+pub fn modified_unit_cents(base_cents: i64, applied: &[AppliedModifier]) -> Result<i64, ApiError> {
+    let unit = applied.iter().fold(base_cents, |acc, m| acc.saturating_add(m.price_delta.as_cents()));
+    if unit < 0 {
+        return Err(bad_request("invalid_modifier_price", "Modifiers cannot take a unit price below zero".into()));
+    }
+    Ok(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn group(name: &str, min_select: i32, max_select: i32, options: &[(Uuid, &str)]) -> GroupRule {
+        GroupRule {
+            name: name.into(),
+            min_select,
+            max_select,
+            options: options.iter().map(|(id, delta)| (*id, format!("opt {delta}"), BigDecimal::from_str(delta).unwrap())).collect(),
+        }
+    }
+
+    fn code(err: ApiError) -> &'static str {
+        match err {
+            ApiError::BadRequest { code, .. } => code,
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn forced_groups_must_be_answered() {
+        let product = Uuid::new_v4();
+        let (small, large, cheese, bacon) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let groups = vec![group("Size", 1, 1, &[(small, "0"), (large, "1.50")]), group("Toppings", 0, 2, &[(cheese, "0.75"), (bacon, "1.25")])];
+
+        assert_eq!(code(resolve_modifiers(product, &groups, &[cheese]).unwrap_err()), "modifier_required");
+        assert_eq!(code(resolve_modifiers(product, &groups, &[small, large]).unwrap_err()), "too_many_modifiers");
+        assert_eq!(code(resolve_modifiers(product, &groups, &[large, Uuid::new_v4()]).unwrap_err()), "unknown_modifier");
+        assert_eq!(code(resolve_modifiers(product, &groups, &[large, large]).unwrap_err()), "duplicate_modifier");
+
+        let applied = resolve_modifiers(product, &groups, &[bacon, large]).unwrap();
+        assert_eq!(applied.iter().map(|m| m.modifier_id).collect::<Vec<_>>(), vec![large, bacon]);
+        assert_eq!(applied[0].group, "Size");
+        assert_eq!(modified_unit_cents(500, &applied).unwrap(), 775);
+    }
+
+    #[test]
+    fn deltas_cannot_make_the_price_negative() {
+        let product = Uuid::new_v4();
+        let no_bun = Uuid::new_v4();
+        let applied = resolve_modifiers(product, &[group("Bun", 0, 1, &[(no_bun, "-2.00")])], &[no_bun]).unwrap();
+        assert_eq!(modified_unit_cents(250, &applied).unwrap(), 50);
+        assert_eq!(code(modified_unit_cents(150, &applied).unwrap_err()), "invalid_modifier_price");
+        assert!(resolve_line(&ModifierRules::new(), product, &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Content of the offline price/tax bundle: everything a terminal needs to ring up sales while it
+//! cannot reach the services — the active catalog with prices and tax codes, the tax rate that
+//! applies at its location, and the tenant's rounding policy.
+//!
+//! This endpoint returns the content unsigned; auth-service's `GET /offline-bundle` fetches it,
+//! versions it and signs it with the platform keys so terminals can verify it later offline.
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use bigdecimal::BigDecimal;
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::SecurityCtxExtractor;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::order_handlers::{is_taxable, resolve_rounding_policy, resolve_tax_rate_bps_with_db, NON_TAXABLE_TAX_CODES};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct OfflineBundleParams {
+    pub location_id: Option<Uuid>,
+    pub pos_instance_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OfflineBundleProduct {
+    pub id: Uuid,
+    pub sku: Option<String>,
+    pub name: String,
+    pub price_cents: i64,
+    pub tax_code: Option<String>,
+    pub taxable: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OfflineBundleTax {
+    /// Rate for the terminal's location (or terminal override), applied to taxable lines.
+    pub rate_bps: i32,
+    pub non_taxable_codes: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OfflineBundleRounding {
+    pub mode: &'static str,
+    pub cash_increment_cents: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OfflineBundleContent {
+    pub tenant_id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub pos_instance_id: Option<Uuid>,
+    /// Active products ordered by id, so identical catalogs serialize identically.
+    pub products: Vec<OfflineBundleProduct>,
+    pub tax: OfflineBundleTax,
+    pub rounding: OfflineBundleRounding,
+}
+
+#[derive(sqlx::FromRow)]
+struct BundleProductRow {
+    id: Uuid,
+    sku: Option<String>,
+    name: String,
+    price: BigDecimal,
+    tax_code: Option<String>,
+}
+
+pub async fn get_offline_bundle_content(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<OfflineBundleParams>,
+) -> Result<Json<OfflineBundleContent>, ApiError> {
+    let tenant_id = sec.tenant_id;
+    let rows = sqlx::query_as::<_, BundleProductRow>(
+        "SELECT id, sku, name, price, tax_code FROM products
+         WHERE tenant_id = $1 AND active AND deleted_at IS NULL
+         ORDER BY id",
+    )
+    .bind(tenant_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load products for offline bundle: {}", e)) })?;
+
+    // Request headers are deliberately ignored: the bundle carries the stored rate, not a
+    // per-request override.
+    let rate_bps = resolve_tax_rate_bps_with_db(
+        &state.db,
+        tenant_id,
+        &HeaderMap::new(),
+        None,
+        params.location_id,
+        params.pos_instance_id,
+    )
+    .await;
+    let rounding = resolve_rounding_policy(&state.db, tenant_id).await;
+
+    Ok(Json(OfflineBundleContent {
+        tenant_id,
+        location_id: params.location_id,
+        pos_instance_id: params.pos_instance_id,
+        products: rows.into_iter().map(bundle_product).collect(),
+        tax: OfflineBundleTax { rate_bps, non_taxable_codes: NON_TAXABLE_TAX_CODES.to_vec() },
+        rounding: OfflineBundleRounding {
+            mode: rounding.mode.as_str(),
+            cash_increment_cents: rounding.cash_increment_cents.unwrap_or(0),
+        },
+    }))
+}
+
+fn bundle_product(row: BundleProductRow) -> OfflineBundleProduct {
+    OfflineBundleProduct {
+        taxable: is_taxable(row.tax_code.as_deref()),
+        price_cents: Money::new(row.price).as_cents(),
+        id: row.id,
+        sku: row.sku,
+        name: row.name,
+        tax_code: row.tax_code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn products_carry_cents_and_taxability() {
+        let product = bundle_product(BundleProductRow {
+            id: Uuid::nil(),
+            sku: Some("SKU-1".into()),
+            name: "Gift card".into(),
+            price: BigDecimal::from_str("12.50").unwrap(),
+            tax_code: Some("exempt".into()),
+        });
+        assert_eq!(product.price_cents, 1250);
+        assert!(!product.taxable);
+    }
+}
@@ -0,0 +1,153 @@
+//! Chargebacks against orders.
+//!
+//! payment-service owns disputes; order-service mirrors each `payment.dispute.updated` onto the
+//! order (`dispute_status`, `disputed_amount`) so order reporting can show disputed and lost
+//! revenue next to sales.
+
+use axum::extract::{Query, State};
+use axum::Json;
+use bigdecimal::BigDecimal;
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use common_events::PaymentDisputeUpdatedEvent;
+use common_http_errors::ApiError;
+use common_security::{Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Record a dispute update on its order. Returns false when the order isn't known for the tenant.
+pub async fn apply_dispute_update(db: &PgPool, evt: &PaymentDisputeUpdatedEvent) -> sqlx::Result<bool> {
+    let done = sqlx::query(
+        "UPDATE orders
+         SET dispute_status = $3, disputed_amount = ($4::NUMERIC / 100)::NUMERIC(10,2), dispute_updated_at = NOW()
+         WHERE id = $1 AND tenant_id = $2",
+    )
+    .bind(evt.order_id)
+    .bind(evt.tenant_id)
+    .bind(evt.status.as_str())
+    .bind(evt.amount_minor)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected() > 0)
+}
+
+#[derive(Deserialize, Default)]
+pub struct DisputeReportQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct DisputedOrder {
+    pub order_id: Uuid,
+    pub dispute_status: String,
+    pub disputed_amount: BigDecimal,
+    pub order_total: BigDecimal,
+    pub payment_method: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DisputeStatusTotal {
+    pub status: String,
+    pub orders: i64,
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DisputeReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Amount under dispute and not yet decided (`needs_response` + `under_review`).
+    pub open_amount: BigDecimal,
+    /// Revenue lost to chargebacks.
+    pub lost_amount: BigDecimal,
+    pub by_status: Vec<DisputeStatusTotal>,
+    pub orders: Vec<DisputedOrder>,
+}
+
+/// Group disputed orders by status, in lifecycle order.
+pub fn summarize(orders: &[DisputedOrder]) -> Vec<DisputeStatusTotal> {
+    ["needs_response", "under_review", "won", "lost"]
+        .into_iter()
+        .filter_map(|status| {
+            let matching: Vec<&DisputedOrder> = orders.iter().filter(|o| o.dispute_status == status).collect();
+            (!matching.is_empty()).then(|| DisputeStatusTotal {
+                status: status.to_string(),
+                orders: matching.len() as i64,
+                amount: matching.iter().map(|o| o.disputed_amount.clone()).sum(),
+            })
+        })
+        .collect()
+}
+
+/// Orders whose dispute changed in `[start_date, end_date]` (default: the last 30 days).
+pub async fn get_dispute_report(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<DisputeReportQuery>,
+) -> Result<Json<DisputeReport>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Support)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: sec.trace_id });
+    }
+    let end_date = params.end_date.unwrap_or_else(|| Utc::now().date_naive());
+    let start_date = params.start_date.unwrap_or(end_date - Duration::days(30));
+    if start_date > end_date {
+        return Err(ApiError::BadRequest { code: "invalid_date_range", trace_id: sec.trace_id, message: None });
+    }
+    let start = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).expect("midnight"));
+    let end = Utc.from_utc_datetime(&(end_date + Duration::days(1)).and_hms_opt(0, 0, 0).expect("midnight"));
+
+    let orders = sqlx::query_as::<_, DisputedOrder>(
+        "SELECT id AS order_id, dispute_status, COALESCE(disputed_amount, 0) AS disputed_amount, total AS order_total, payment_method
+         FROM orders
+         WHERE tenant_id = $1 AND dispute_status IS NOT NULL AND dispute_updated_at >= $2 AND dispute_updated_at < $3
+         ORDER BY dispute_updated_at DESC
+         LIMIT 1000",
+    )
+    .bind(sec.tenant_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to load disputed orders: {e}")) })?;
+
+    let by_status = summarize(&orders);
+    let amount_for = |statuses: &[&str]| -> BigDecimal {
+        by_status.iter().filter(|t| statuses.contains(&t.status.as_str())).map(|t| t.amount.clone()).sum()
+    };
+    Ok(Json(DisputeReport {
+        start_date,
+        end_date,
+        open_amount: amount_for(&["needs_response", "under_review"]),
+        lost_amount: amount_for(&["lost"]),
+        by_status,
+        orders,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn disputed(status: &str, amount: &str) -> DisputedOrder {
+        DisputedOrder {
+            order_id: Uuid::new_v4(),
+            dispute_status: status.into(),
+            disputed_amount: BigDecimal::from_str(amount).unwrap(),
+            order_total: BigDecimal::from_str(amount).unwrap(),
+            payment_method: "card".into(),
+        }
+    }
+
+    #[test]
+    fn totals_follow_the_lifecycle_and_skip_empty_statuses() {
+        let orders = [disputed("lost", "10.00"), disputed("needs_response", "4.50"), disputed("lost", "2.25")];
+        let totals = summarize(&orders);
+        assert_eq!(totals.iter().map(|t| t.status.as_str()).collect::<Vec<_>>(), vec!["needs_response", "lost"]);
+        assert_eq!(totals[1].orders, 2);
+        assert_eq!(totals[1].amount, BigDecimal::from_str("12.25").unwrap());
+    }
+}
@@ -0,0 +1,570 @@
+//! Editing open (PENDING) orders: add, re-quantity and remove lines, plus manager price overrides.
+//!
+//! Every edit runs under a row lock on the order, keeps the inventory reservation in step via
+//! inventory-service's reservation delta endpoint, reprices the order and records an
+//! `order_line_edits` row for the audit trail.
+
+use axum::extract::{Path, State};
+use axum::{http::{HeaderMap, StatusCode}, Json};
+use bigdecimal::BigDecimal;
+use common_auth::AuthContext; // bearer token propagated to inventory-service
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::{ensure_capability, Capability, Role, SecurityContext, SecurityCtxExtractor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Row, Transaction};
+use uuid::Uuid;
+
+use crate::modifiers::{modified_unit_cents, resolve_line, AppliedModifier};
+use crate::order_handlers::{
+    fetch_modifier_rules, fetch_order_detail, inventory_url, is_taxable, map_legacy_error, price_totals, resolve_rounding_policy,
+    resolve_tax_rate_bps_with_db, till_locale, OrderDetail, PricedTotals,
+};
+use crate::AppState;
+
+/// Reason codes accepted for a price override; free text is not allowed so reports can group them.
+pub const PRICE_OVERRIDE_REASONS: &[&str] = &[
+    "price_match",
+    "damaged_item",
+    "customer_goodwill",
+    "pricing_error",
+    "manager_discretion",
+];
+
+#[derive(Deserialize)]
+pub struct AddOrderLineRequest {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    /// Menu modifier options for the new line; forced groups must be answered.
+    #[serde(default)]
+    pub modifier_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOrderLineRequest {
+    pub quantity: Option<i32>,
+    /// New unit price; requires the `price_override` capability and an `override_reason`.
+    pub unit_price: Option<BigDecimal>,
+    /// The new unit price as typed (`"4,99"`, `"$5"`) instead of `unit_price`, read with the
+    /// separators of `locale` (a BCP 47 tag; decimal point when absent).
+    pub unit_price_input: Option<String>,
+    pub locale: Option<String>,
+    pub override_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ReservationDelta {
+    product_id: Uuid,
+    delta: i32,
+}
+
+#[derive(Serialize)]
+struct AdjustReservationPayload {
+    items: Vec<ReservationDelta>,
+}
+
+enum LineEdit {
+    Add { product_id: Uuid, quantity: i32, modifier_ids: Vec<Uuid> },
+    Update { item_id: Uuid, quantity: Option<i32>, unit_price: Option<BigDecimal>, reason: Option<String> },
+    Remove { item_id: Uuid },
+}
+
+#[derive(sqlx::FromRow)]
+struct EditableLine {
+    id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    unit_price: BigDecimal,
+    original_unit_price: Option<BigDecimal>,
+    modifiers: sqlx::types::Json<Vec<AppliedModifier>>,
+}
+
+impl EditableLine {
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "product_id": self.product_id,
+            "quantity": self.quantity,
+            "unit_price": self.unit_price,
+            "original_unit_price": self.original_unit_price,
+        })
+    }
+}
+
+fn ensure_can_edit(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::Support | Role::Cashier)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+fn db_error(context: &str, trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError + '_ {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) },
+    }
+}
+
+fn line_total(unit_price: &BigDecimal, quantity: i32) -> BigDecimal {
+    Money::from_cents(Money::new(unit_price.clone()).as_cents().saturating_mul(quantity as i64)).inner().clone()
+}
+
+/// Reprice the order from its current lines (inside `tx`, so pending edits are included), with
+/// cash rounding only when the order's tender is cash.
+async fn repriced_totals(
+    state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    rate_bps: i32,
+    order_id: Uuid,
+    payment_method: &str,
+) -> Result<PricedTotals, ApiError> {
+    let rows = sqlx::query(
+        "SELECT oi.line_total, p.tax_code FROM order_items oi LEFT JOIN products p ON p.id = oi.product_id AND p.tenant_id = $2 WHERE oi.order_id = $1",
+    )
+    .bind(order_id)
+    .bind(tenant_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(db_error("Failed to load order lines", None))?;
+    let mut subtotal_cents = 0i64;
+    let mut taxable_subtotal_cents = 0i64;
+    for row in rows {
+        let line_total: BigDecimal = row.try_get("line_total").map_err(db_error("Failed to read line total", None))?;
+        let tax_code: Option<String> = row.try_get("tax_code").unwrap_or(None);
+        let cents = Money::new(line_total).as_cents();
+        subtotal_cents = subtotal_cents.saturating_add(cents);
+        if is_taxable(tax_code.as_deref()) {
+            taxable_subtotal_cents = taxable_subtotal_cents.saturating_add(cents);
+        }
+    }
+    let policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    Ok(price_totals(&policy, subtotal_cents, taxable_subtotal_cents, 0, rate_bps, payment_method))
+}
+
+async fn adjust_inventory_reservation(
+    state: &AppState,
+    tenant_id: Uuid,
+    auth_token: &str,
+    order_id: Uuid,
+    deltas: &[(Uuid, i32)],
+) -> Result<(), ApiError> {
+    if deltas.is_empty() || std::env::var("ORDER_BYPASS_INVENTORY").ok().as_deref() == Some("1") {
+        return Ok(());
+    }
+    let payload = AdjustReservationPayload {
+        items: deltas.iter().map(|(product_id, delta)| ReservationDelta { product_id: *product_id, delta: *delta }).collect(),
+    };
+    let mut request = state
+        .http_client
+        .patch(inventory_url(&state.inventory_base_url, &format!("/inventory/reservations/{}", order_id)))
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant_id.to_string())
+        .header("X-Roles", "Admin,Manager,Cashier")
+        .json(&payload);
+    if !auth_token.is_empty() {
+        request = request.header("Authorization", format!("Bearer {}", auth_token));
+    }
+    let response = request.send().await.map_err(|err| map_legacy_error(StatusCode::BAD_GATEWAY, format!("Failed to contact inventory-service: {err}")))?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status == reqwest::StatusCode::CONFLICT || status == reqwest::StatusCode::BAD_REQUEST {
+        let mapped = StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        return Err(map_legacy_error(mapped, if body.is_empty() { mapped.to_string() } else { body }));
+    }
+    Err(map_legacy_error(StatusCode::BAD_GATEWAY, format!("Inventory reservation adjustment failed with status {status}: {body}")))
+}
+
+fn validate_override_reason(reason: Option<&str>, trace_id: Option<Uuid>) -> Result<String, ApiError> {
+    let reason = reason.map(str::trim).filter(|r| !r.is_empty()).ok_or(ApiError::BadRequest {
+        code: "missing_override_reason",
+        trace_id,
+        message: Some("Price overrides require an override_reason".into()),
+    })?;
+    if !PRICE_OVERRIDE_REASONS.contains(&reason) {
+        return Err(ApiError::BadRequest {
+            code: "invalid_override_reason",
+            trace_id,
+            message: Some(format!("override_reason must be one of: {}", PRICE_OVERRIDE_REASONS.join(", "))),
+        });
+    }
+    Ok(reason.to_string())
+}
+
+async fn apply_line_edit(
+    state: &AppState,
+    sec: &SecurityContext,
+    headers: &HeaderMap,
+    auth_token: &str,
+    order_id: Uuid,
+    edit: LineEdit,
+) -> Result<OrderDetail, ApiError> {
+    ensure_can_edit(sec)?;
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+
+    // Validate the request shape before taking any locks.
+    match &edit {
+        LineEdit::Add { quantity, .. } if *quantity <= 0 => {
+            return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id, message: Some("quantity must be positive".into()) });
+        }
+        LineEdit::Update { quantity, unit_price, reason, .. } => {
+            if quantity.is_none() && unit_price.is_none() {
+                return Err(ApiError::BadRequest { code: "empty_update", trace_id, message: Some("Provide quantity and/or unit_price".into()) });
+            }
+            if matches!(quantity, Some(q) if *q <= 0) {
+                return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id, message: Some("quantity must be positive; delete the line to remove it".into()) });
+            }
+            if let Some(price) = unit_price {
+                ensure_capability(sec, Capability::PriceOverride)
+                    .map_err(|_| ApiError::ForbiddenMissingRole { role: "price_override", trace_id })?;
+                if *price < BigDecimal::from(0) {
+                    return Err(ApiError::BadRequest { code: "invalid_price", trace_id, message: Some("unit_price must not be negative".into()) });
+                }
+                validate_override_reason(reason.as_deref(), trace_id)?;
+            }
+        }
+        _ => {}
+    }
+
+    let mut tx = state.db.begin().await.map_err(db_error("Failed to begin transaction", trace_id))?;
+    let order = sqlx::query("SELECT status, total, store_id, payment_method FROM orders WHERE id = $1 AND tenant_id = $2 FOR UPDATE")
+        .bind(order_id)
+        .bind(tenant_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error("Failed to lock order", trace_id))?
+        .ok_or(ApiError::NotFound { code: "order_not_found", trace_id })?;
+    let status: String = order.try_get("status").map_err(db_error("Failed to read order status", trace_id))?;
+    if status != "PENDING" {
+        return Err(ApiError::Conflict { code: "order_not_editable", trace_id, message: Some(format!("Order in status '{}' cannot be edited", status)) });
+    }
+    let total_before: BigDecimal = order.try_get::<Option<BigDecimal>, _>("total").unwrap_or(None).unwrap_or_else(|| BigDecimal::from(0));
+    let store_id: Option<Uuid> = order.try_get("store_id").unwrap_or(None);
+    let payment_method: String = order.try_get("payment_method").map_err(db_error("Failed to read payment method", trace_id))?;
+
+    let lines = sqlx::query_as::<_, EditableLine>(
+        "SELECT id, product_id, quantity, unit_price, original_unit_price, modifiers FROM order_items WHERE order_id = $1",
+    )
+    .bind(order_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error("Failed to load order lines", trace_id))?;
+    let find_line = |item_id: Uuid| {
+        lines.iter().find(|l| l.id == item_id).ok_or(ApiError::NotFound { code: "order_item_not_found", trace_id })
+    };
+
+    let rate_bps = resolve_tax_rate_bps_with_db(&state.db, tenant_id, headers, None, store_id, None).await;
+    let priced_before = repriced_totals(state, &mut tx, tenant_id, rate_bps, order_id, &payment_method).await?;
+
+    let action: &'static str;
+    let item_id: Uuid;
+    let mut reason_code: Option<String> = None;
+    let before: Option<serde_json::Value>;
+    let mut inventory_deltas: Vec<(Uuid, i32)> = Vec::new();
+
+    match edit {
+        LineEdit::Add { product_id, quantity, modifier_ids } => {
+            let product = sqlx::query("SELECT name, price, active FROM products WHERE tenant_id = $1 AND id = $2 AND deleted_at IS NULL")
+                .bind(tenant_id)
+                .bind(product_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_error("Failed to load product", trace_id))?
+                .ok_or(ApiError::NotFound { code: "product_not_found", trace_id })?;
+            let active: bool = product.try_get("active").unwrap_or(true);
+            if !active {
+                return Err(ApiError::BadRequest { code: "product_inactive", trace_id, message: Some(format!("Product {} is inactive", product_id)) });
+            }
+            let rules = fetch_modifier_rules(&state.db, tenant_id, &[product_id]).await?;
+            let modifiers = resolve_line(&rules, product_id, &modifier_ids)?;
+            inventory_deltas.push((product_id, quantity));
+            action = "line_added";
+            if let Some(existing) = lines.iter().find(|l| l.product_id == product_id && l.modifiers.0 == modifiers) {
+                // Same product and modifiers already on the order: grow that line rather than splitting it.
+                let new_quantity = existing.quantity.saturating_add(quantity);
+                sqlx::query("UPDATE order_items SET quantity = $2, line_total = $3 WHERE id = $1")
+                    .bind(existing.id)
+                    .bind(new_quantity)
+                    .bind(line_total(&existing.unit_price, new_quantity))
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error("Failed to update order line", trace_id))?;
+                item_id = existing.id;
+                before = Some(existing.snapshot());
+            } else {
+                let name: Option<String> = product.try_get("name").unwrap_or(None);
+                let price: BigDecimal = product.try_get("price").map_err(db_error("Failed to read product price", trace_id))?;
+                let price = Money::from_cents(modified_unit_cents(Money::new(price).as_cents(), &modifiers)?);
+                item_id = Uuid::new_v4();
+                sqlx::query(
+                    "INSERT INTO order_items (id, order_id, product_id, quantity, unit_price, line_total, product_name, modifiers) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                )
+                .bind(item_id)
+                .bind(order_id)
+                .bind(product_id)
+                .bind(quantity)
+                .bind(price.inner())
+                .bind(line_total(price.inner(), quantity))
+                .bind(name.as_deref())
+                .bind(sqlx::types::Json(&modifiers))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("Failed to insert order line", trace_id))?;
+                before = None;
+            }
+        }
+        LineEdit::Update { item_id: id, quantity, unit_price, reason } => {
+            let line = find_line(id)?;
+            let new_quantity = quantity.unwrap_or(line.quantity);
+            if new_quantity != line.quantity {
+                inventory_deltas.push((line.product_id, new_quantity - line.quantity));
+            }
+            let new_price = unit_price.map(|p| Money::new(p).inner().clone()).unwrap_or_else(|| line.unit_price.clone());
+            if unit_price_changed(&line.unit_price, &new_price) {
+                reason_code = validate_override_reason(reason.as_deref(), trace_id).ok();
+                // Keep the first catalog price even across repeated overrides.
+                let original = line.original_unit_price.clone().unwrap_or_else(|| line.unit_price.clone());
+                sqlx::query(
+                    "UPDATE order_items SET quantity = $2, unit_price = $3, line_total = $4, original_unit_price = $5, price_override_reason = $6, price_override_by = $7 WHERE id = $1",
+                )
+                .bind(line.id)
+                .bind(new_quantity)
+                .bind(&new_price)
+                .bind(line_total(&new_price, new_quantity))
+                .bind(original)
+                .bind(reason_code.as_deref())
+                .bind(sec.actor.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("Failed to update order line", trace_id))?;
+                action = "price_override";
+            } else {
+                sqlx::query("UPDATE order_items SET quantity = $2, line_total = $3 WHERE id = $1")
+                    .bind(line.id)
+                    .bind(new_quantity)
+                    .bind(line_total(&line.unit_price, new_quantity))
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error("Failed to update order line", trace_id))?;
+                action = "quantity_changed";
+            }
+            item_id = line.id;
+            before = Some(line.snapshot());
+        }
+        LineEdit::Remove { item_id: id } => {
+            let line = find_line(id)?;
+            if lines.len() == 1 {
+                return Err(ApiError::BadRequest { code: "order_requires_items", trace_id, message: Some("Cannot remove the last line; void the order instead".into()) });
+            }
+            sqlx::query("DELETE FROM order_items WHERE id = $1")
+                .bind(line.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("Failed to remove order line", trace_id))?;
+            inventory_deltas.push((line.product_id, -line.quantity));
+            action = "line_removed";
+            item_id = line.id;
+            before = Some(line.snapshot());
+        }
+    }
+
+    // Shift the stored total by the repriced difference so any discount or rounding captured
+    // when the order was placed carries over unchanged.
+    let priced_after = repriced_totals(state, &mut tx, tenant_id, rate_bps, order_id, &payment_method).await?;
+    let total_after_cents = Money::new(total_before.clone())
+        .as_cents()
+        .saturating_add(priced_after.total_cents - priced_before.total_cents)
+        .max(0);
+    let total_after = Money::from_cents(total_after_cents);
+    let rounding_delta = Money::from_cents(priced_after.rounding_adjustment_cents - priced_before.rounding_adjustment_cents);
+    sqlx::query("UPDATE orders SET total = $3, rounding_adjustment = rounding_adjustment + $4 WHERE id = $1 AND tenant_id = $2")
+        .bind(order_id)
+        .bind(tenant_id)
+        .bind(total_after.inner())
+        .bind(rounding_delta.inner())
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error("Failed to update order total", trace_id))?;
+
+    let after = sqlx::query_as::<_, EditableLine>(
+        "SELECT id, product_id, quantity, unit_price, original_unit_price, modifiers FROM order_items WHERE id = $1",
+    )
+    .bind(item_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error("Failed to reload order line", trace_id))?
+    .map(|line| line.snapshot());
+    sqlx::query(
+        "INSERT INTO order_line_edits (id, tenant_id, order_id, order_item_id, action, reason_code, actor_id, before, after, total_before, total_after)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(order_id)
+    .bind(item_id)
+    .bind(action)
+    .bind(reason_code.as_deref())
+    .bind(sec.actor.id)
+    .bind(&before)
+    .bind(&after)
+    .bind(&total_before)
+    .bind(total_after.inner())
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error("Failed to record order edit", trace_id))?;
+
+    // Inventory is adjusted last so a rejected reservation simply rolls the edit back.
+    adjust_inventory_reservation(state, tenant_id, auth_token, order_id, &inventory_deltas).await?;
+    if let Err(err) = tx.commit().await {
+        let compensation: Vec<(Uuid, i32)> = inventory_deltas.iter().map(|(p, d)| (*p, -d)).collect();
+        if let Err(undo) = adjust_inventory_reservation(state, tenant_id, auth_token, order_id, &compensation).await {
+            tracing::error!(order_id = %order_id, tenant_id = %tenant_id, ?undo, "Failed to revert reservation after order edit rollback");
+        }
+        return Err(ApiError::Internal { trace_id, message: Some(format!("Failed to commit order edit: {err}")) });
+    }
+
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    if let Some(audit) = &state.audit_producer {
+        let changes = json!({
+            "order_id": order_id,
+            "order_item_id": item_id,
+            "reason_code": reason_code,
+            "before": before,
+            "after": after,
+            "total_before": total_before,
+            "total_after": total_after,
+        });
+        let _ = audit
+            .emit(
+                tenant_id,
+                sec.actor.clone(),
+                "order",
+                Some(order_id),
+                action,
+                "order-service",
+                common_audit::AuditSeverity::Info,
+                None,
+                changes,
+                json!({"source":"order-service"}),
+            )
+            .await;
+    }
+
+    fetch_order_detail(state, tenant_id, order_id).await
+}
+
+fn unit_price_changed(current: &BigDecimal, requested: &BigDecimal) -> bool {
+    Money::new(current.clone()).as_cents() != Money::new(requested.clone()).as_cents()
+}
+
+pub async fn add_order_line(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path(order_id): Path<Uuid>,
+    Json(req): Json<AddOrderLineRequest>,
+) -> Result<Json<OrderDetail>, ApiError> {
+    let edit = LineEdit::Add { product_id: req.product_id, quantity: req.quantity, modifier_ids: req.modifier_ids };
+    apply_line_edit(&state, &sec, &headers, &auth.token, order_id, edit).await.map(Json)
+}
+
+pub async fn update_order_line(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path((order_id, item_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateOrderLineRequest>,
+) -> Result<Json<OrderDetail>, ApiError> {
+    let unit_price = match (req.unit_price, req.unit_price_input.as_deref()) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::BadRequest { code: "conflicting_unit_price", trace_id: sec.trace_id, message: Some("Send unit_price or unit_price_input, not both".into()) });
+        }
+        (None, Some(input)) => Some(typed_unit_price(input, req.locale.as_deref(), sec.trace_id)?),
+        (price, None) => price,
+    };
+    let edit = LineEdit::Update { item_id, quantity: req.quantity, unit_price, reason: req.override_reason };
+    apply_line_edit(&state, &sec, &headers, &auth.token, order_id, edit).await.map(Json)
+}
+
+/// Reads a typed price override; ambiguous or malformed input is refused rather than guessed.
+fn typed_unit_price(input: &str, locale: Option<&str>, trace_id: Option<Uuid>) -> Result<BigDecimal, ApiError> {
+    let Some(money_locale) = till_locale(locale) else {
+        return Err(ApiError::BadRequest { code: "unsupported_locale", trace_id, message: Some(format!("Unknown locale {:?}", locale.unwrap_or_default())) });
+    };
+    Money::parse_lenient(input, money_locale)
+        .map(BigDecimal::from)
+        .map_err(|err| ApiError::BadRequest { code: err.code(), trace_id, message: Some(err.to_string()) })
+}
+
+pub async fn remove_order_line(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    headers: HeaderMap,
+    Path((order_id, item_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<OrderDetail>, ApiError> {
+    apply_line_edit(&state, &sec, &headers, &auth.token, order_id, LineEdit::Remove { item_id }).await.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_handlers::tender_total_cents;
+
+    #[test]
+    fn override_reason_must_be_whitelisted() {
+        assert_eq!(validate_override_reason(Some(" price_match "), None).unwrap(), "price_match");
+        assert!(matches!(validate_override_reason(None, None), Err(ApiError::BadRequest { code: "missing_override_reason", .. })));
+        assert!(matches!(validate_override_reason(Some("because"), None), Err(ApiError::BadRequest { code: "invalid_override_reason", .. })));
+    }
+
+    #[test]
+    fn typed_prices_follow_the_till_locale() {
+        assert_eq!(typed_unit_price("4,99", Some("de-DE"), None).unwrap(), "4.99".parse::<BigDecimal>().unwrap());
+        assert_eq!(typed_unit_price("$1,250", None, None).unwrap(), "1250".parse::<BigDecimal>().unwrap());
+        assert!(matches!(typed_unit_price("4,99", None, None), Err(ApiError::BadRequest { code: "ambiguous_amount", .. })));
+        assert!(matches!(typed_unit_price("4.9999", Some("en-US"), None), Err(ApiError::BadRequest { code: "too_many_decimals", .. })));
+        assert!(matches!(typed_unit_price("5", Some("??"), None), Err(ApiError::BadRequest { code: "unsupported_locale", .. })));
+    }
+
+    #[test]
+    fn line_total_is_cent_exact() {
+        let price: BigDecimal = "3.33".parse().unwrap();
+        assert_eq!(line_total(&price, 3), "9.99".parse::<BigDecimal>().unwrap());
+        assert!(!unit_price_changed(&"5.00".parse().unwrap(), &"5".parse().unwrap()));
+    }
+
+    #[test]
+    fn repriced_totals_include_tax_and_cash_rounding() {
+        let policy = common_money::RoundingPolicy::new(common_money::RoundingMode::HalfUp).with_cash_increment(5);
+        let totals = price_totals(&policy, 1_000, 1_000, 0, 825, "cash");
+        assert_eq!(totals.tax_cents, 83);
+        assert_eq!(totals.total_cents, 1_085);
+        assert_eq!(totals.rounding_adjustment_cents, 2);
+    }
+
+    #[test]
+    fn net_negative_carts_get_no_discount_or_tax() {
+        let policy = common_money::RoundingPolicy::new(common_money::RoundingMode::HalfUp);
+        let totals = price_totals(&policy, -1_000, -1_000, 1_000, 825, "card");
+        assert_eq!((totals.discount_cents, totals.tax_cents, totals.total_cents), (0, 0, -1_000));
+    }
+
+    #[test]
+    fn only_cash_tenders_are_rounded() {
+        let policy = common_money::RoundingPolicy::new(common_money::RoundingMode::HalfUp).with_cash_increment(5);
+        let card = price_totals(&policy, 1_000, 1_000, 0, 825, "card");
+        assert_eq!((card.total_cents, card.rounding_adjustment_cents), (1_083, 0));
+        let cash = price_totals(&policy, 1_000, 1_000, 0, 825, " Cash ");
+        assert_eq!((cash.total_cents, cash.rounding_adjustment_cents), (1_085, 2));
+        assert_eq!(tender_total_cents(&policy, "cash", 1_233), (1_235, 2));
+        assert_eq!(tender_total_cents(&policy, "cash", 1_232), (1_230, -2));
+        assert_eq!(tender_total_cents(&policy, "card", 1_233), (1_233, 0));
+    }
+}
@@ -0,0 +1,412 @@
+//! Exchanges: returned lines and a new sale processed as one operation.
+//!
+//! Returned lines are credited at their original unit price plus tax at the original store's
+//! rate. The credit pays for the new sale first; the customer pays any difference with `payment`,
+//! or gets it back on the original order's tender. The return is published as `order.refunded`
+//! with `exchange_order_id`, and the new sale's `order.completed` carries `exchange_return_id`, so
+//! consumers can pair the two halves.
+
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use common_auth::AuthContext; // bearer token propagated to inventory-service by the new sale
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+use common_events::{DomainEvent, OrderEventItem, OrderRefundedEvent};
+use common_http_errors::ApiError;
+use common_money::{Money, RoundingContext};
+use common_security::{Role, SecurityCtxExtractor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::order_handlers::{
+    create_order_from_skus, fetch_order_detail, is_taxable, resolve_rounding_policy, resolve_tax_rate_bps_with_db,
+    NewOrderFromSku, NewOrderSkuItem, PaymentRequest,
+};
+use crate::AppState;
+
+/// Credit from an exchange's returned lines, applied to the new sale by `create_order`.
+#[derive(Debug, Clone)]
+pub struct ExchangeCredit {
+    pub original_order_id: Uuid,
+    /// `order_returns` row the credit came from; `None` when nothing was returned.
+    pub return_id: Option<Uuid>,
+    pub credit_cents: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeReturnItem { pub product_id: Uuid, pub qty: i32 }
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeNewItem {
+    pub sku: String,
+    pub qty: i32,
+    #[serde(default)] pub serial_numbers: Vec<String>,
+    #[serde(default)] pub lot_code: Option<String>,
+    #[serde(default)] pub weight: Option<bigdecimal::BigDecimal>,
+    #[serde(default)] pub tare: Option<bigdecimal::BigDecimal>,
+    #[serde(default)] pub modifier_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeRequest {
+    pub return_items: Vec<ExchangeReturnItem>,
+    pub new_items: Vec<ExchangeNewItem>,
+    #[serde(default)] pub discount_percent_bp: Option<i32>,
+    /// Covers only what the credit does not; omit it when the exchange is even or a refund.
+    pub payment: Option<PaymentRequest>,
+    pub cashier_id: Option<Uuid>,
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExchangeResponse {
+    pub original_order_id: Uuid,
+    pub exchange_order_id: Uuid,
+    pub refund: Option<Uuid>,
+    pub refund_subtotal_cents: i64,
+    pub refund_tax_cents: i64,
+    /// Credit for the returned lines, tax included.
+    pub refunded_cents: i64,
+    pub new_order_total_cents: i64,
+    /// New sale total minus credit: positive is collected, negative goes back to `refund_tender`.
+    pub net_delta_cents: i64,
+    pub net_direction: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refund_tender: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnLine {
+    pub order_item_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub unit_price_cents: i64,
+    pub taxable: bool,
+    /// Scale reading of a weighed line, carried onto the refund event.
+    pub measured_quantity: Option<bigdecimal::BigDecimal>,
+}
+
+impl ReturnLine {
+    fn line_total_cents(&self) -> i64 {
+        self.unit_price_cents.saturating_mul(self.quantity as i64)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReturnCredit {
+    pub subtotal_cents: i64,
+    pub tax_cents: i64,
+    pub total_cents: i64,
+}
+
+/// Credit for returned lines: their subtotal plus tax on the taxable part, rounded once like a
+/// sale's tax is.
+pub fn price_return(lines: &[ReturnLine], tax_rate_bps: i32, rounding: &RoundingContext) -> ReturnCredit {
+    let subtotal_cents: i64 = lines.iter().map(ReturnLine::line_total_cents).sum();
+    let taxable_cents: i64 = lines.iter().filter(|line| line.taxable).map(ReturnLine::line_total_cents).sum();
+    let tax_cents = rounding.percent(&Money::from_cents(taxable_cents), tax_rate_bps).as_cents();
+    ReturnCredit { subtotal_cents, tax_cents, total_cents: subtotal_cents.saturating_add(tax_cents) }
+}
+
+/// Net of an exchange: `(new_total - credit, "collect" | "refund" | "even")`.
+pub fn settle(credit_cents: i64, new_total_cents: i64) -> (i64, &'static str) {
+    let net = new_total_cents.saturating_sub(credit_cents);
+    let direction = match net {
+        n if n > 0 => "collect",
+        n if n < 0 => "refund",
+        _ => "even",
+    };
+    (net, direction)
+}
+
+fn db_error(context: &'static str, trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) },
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ItemRow {
+    id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    returned_quantity: i32,
+    unit_price: bigdecimal::BigDecimal,
+    measured_quantity: Option<bigdecimal::BigDecimal>,
+    tax_code: Option<String>,
+}
+
+pub async fn exchange_order(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(original_order_id): Path<Uuid>,
+    headers: HeaderMap,
+    auth: AuthContext,
+    Json(req): Json<ExchangeRequest>,
+) -> Result<Json<ExchangeResponse>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: sec.trace_id });
+    }
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+
+    if req.new_items.is_empty() {
+        return Err(ApiError::BadRequest { code: "missing_items", trace_id, message: Some("Exchange must include new_items; use POST /orders/refund for a plain return".into()) });
+    }
+    let mut returns: Vec<(Uuid, i32)> = Vec::new();
+    for item in &req.return_items {
+        if item.qty <= 0 {
+            return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id, message: Some("Return quantities must be positive".into()) });
+        }
+        match returns.iter_mut().find(|(product_id, _)| *product_id == item.product_id) {
+            Some((_, qty)) => *qty += item.qty,
+            None => returns.push((item.product_id, item.qty)),
+        }
+    }
+
+    // A retried exchange would find its sale through the idempotency key but return the items again.
+    if let Some(key) = req.idempotency_key.as_deref().map(str::trim).filter(|key| !key.is_empty()) {
+        let existing: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM orders WHERE tenant_id = $1 AND idempotency_key = $2 AND exchange_of_order_id = $3",
+        )
+        .bind(tenant_id)
+        .bind(key)
+        .bind(original_order_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(db_error("Failed to check exchange idempotency", trace_id))?;
+        if let Some(exchange_order_id) = existing {
+            return Err(ApiError::Conflict { code: "exchange_already_processed", trace_id, message: Some(format!("Exchange already recorded as order {exchange_order_id}")) });
+        }
+    }
+
+    let detail = fetch_order_detail(&state, tenant_id, original_order_id).await?;
+
+    let mut tx = state.db.begin().await.map_err(db_error("Failed to begin exchange", trace_id))?;
+    let (status, original_tender, store_id, pos_instance_id): (String, String, Option<Uuid>, Option<Uuid>) = sqlx::query_as(
+        "SELECT status, payment_method, store_id, pos_instance_id FROM orders WHERE id = $1 AND tenant_id = $2 FOR UPDATE",
+    )
+    .bind(original_order_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error("Failed to lock original order", trace_id))?
+    .ok_or(ApiError::NotFound { code: "order_not_found", trace_id })?;
+    if !matches!(status.as_str(), "COMPLETED" | "PAID" | "REFUNDED" | "PARTIAL_REFUNDED") {
+        return Err(ApiError::BadRequest { code: "order_not_completed", trace_id, message: Some("Original order not in a refundable state".into()) });
+    }
+
+    let mut lines: Vec<ReturnLine> = Vec::with_capacity(returns.len());
+    let mut credit = ReturnCredit::default();
+    let mut return_id: Option<Uuid> = None;
+    if !returns.is_empty() {
+        // Weighed lines are priced per kg/lb, so their unit credit is the reading's line total.
+        let rows = sqlx::query_as::<_, ItemRow>(
+            "SELECT oi.id, oi.product_id, oi.quantity, oi.returned_quantity,
+                    CASE WHEN oi.measured_quantity IS NULL THEN oi.unit_price ELSE oi.line_total / oi.quantity END AS unit_price,
+                    oi.measured_quantity, p.tax_code
+             FROM order_items oi LEFT JOIN products p ON p.id = oi.product_id AND p.tenant_id = $2
+             WHERE oi.order_id = $1 ORDER BY oi.created_at FOR UPDATE OF oi",
+        )
+        .bind(original_order_id)
+        .bind(tenant_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error("Failed to load original order items", trace_id))?;
+        for (product_id, qty) in &returns {
+            let row = rows
+                .iter()
+                .find(|row| row.product_id == *product_id)
+                .ok_or(ApiError::BadRequest { code: "product_not_in_order", trace_id, message: Some(format!("Product {product_id} is not part of the original order")) })?;
+            let available = row.quantity - row.returned_quantity;
+            if *qty > available {
+                return Err(ApiError::BadRequest { code: "refundable_qty_exceeded", trace_id, message: Some(format!("Cannot return {qty} units; only {available} remain")) });
+            }
+            lines.push(ReturnLine {
+                order_item_id: row.id,
+                product_id: *product_id,
+                quantity: *qty,
+                unit_price_cents: Money::new(row.unit_price.clone()).as_cents(),
+                taxable: is_taxable(row.tax_code.as_deref()),
+                measured_quantity: row.measured_quantity.clone(),
+            });
+        }
+
+        // Tax comes back at the rate the original store charges, not the caller's override headers.
+        let tax_rate_bps = resolve_tax_rate_bps_with_db(&state.db, tenant_id, &HeaderMap::new(), None, store_id, pos_instance_id).await;
+        let rounding = resolve_rounding_policy(&state.db, tenant_id).await.context();
+        credit = price_return(&lines, tax_rate_bps, &rounding);
+
+        let rid = Uuid::new_v4();
+        return_id = Some(rid);
+        sqlx::query(
+            "INSERT INTO order_returns (id, order_id, tenant_id, total, reason, business_date)
+             VALUES ($1, $2, $3, $4, 'exchange', business_date_at($3, $5, NOW()))",
+        )
+            .bind(rid)
+            .bind(original_order_id)
+            .bind(tenant_id)
+            .bind(Money::from_cents(credit.total_cents).inner())
+            .bind(store_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("Failed to record exchange return", trace_id))?;
+        for line in &lines {
+            sqlx::query("UPDATE order_items SET returned_quantity = returned_quantity + $1 WHERE id = $2")
+                .bind(line.quantity)
+                .bind(line.order_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("Failed to update returned quantity", trace_id))?;
+            sqlx::query("INSERT INTO order_return_items (id, return_id, order_item_id, quantity, line_total) VALUES ($1, $2, $3, $4, $5)")
+                .bind(Uuid::new_v4())
+                .bind(rid)
+                .bind(line.order_item_id)
+                .bind(line.quantity)
+                .bind(Money::from_cents(line.line_total_cents()).inner())
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("Failed to record exchange return items", trace_id))?;
+        }
+        let all_returned: bool = sqlx::query_scalar("SELECT COALESCE(bool_and(returned_quantity >= quantity), TRUE) FROM order_items WHERE order_id = $1")
+            .bind(original_order_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("Failed to check returned quantities", trace_id))?;
+        sqlx::query("UPDATE orders SET status = $3 WHERE id = $1 AND tenant_id = $2")
+            .bind(original_order_id)
+            .bind(tenant_id)
+            .bind(if all_returned { "REFUNDED" } else { "PARTIAL_REFUNDED" })
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("Failed to update order status", trace_id))?;
+    }
+
+    // The new sale commits on its own; if it fails the return above rolls back with `tx`.
+    let new_req = NewOrderFromSku {
+        items: req
+            .new_items
+            .iter()
+            .map(|i| NewOrderSkuItem { sku: i.sku.clone(), quantity: i.qty, serial_numbers: i.serial_numbers.clone(), lot_code: i.lot_code.clone(), weight: i.weight.clone(), tare: i.tare.clone(), modifier_ids: i.modifier_ids.clone() })
+            .collect(),
+        discount_percent_bp: req.discount_percent_bp,
+        tax_rate_bps: None,
+        location_id: store_id,
+        pos_instance_id: None,
+        payment_method: req.payment.as_ref().map(|p| p.method.clone()).unwrap_or_else(|| original_tender.clone()),
+        payment: req.payment.clone(),
+        customer_id: detail.order.customer_id.map(|id| id.to_string()),
+        customer_name: detail.order.customer_name.clone(),
+        customer_email: detail.order.customer_email.clone(),
+        store_id,
+        offline: Some(detail.order.offline),
+        idempotency_key: req.idempotency_key.clone(),
+        tip_cents: 0,
+        exchange: Some(ExchangeCredit { original_order_id, return_id, credit_cents: credit.total_cents }),
+    };
+    let exchange_order = create_order_from_skus(State(state.clone()), SecurityCtxExtractor(sec.clone()), auth, headers, Json(new_req)).await?.0;
+
+    if let Err(err) = tx.commit().await {
+        tracing::error!(original_order_id = %original_order_id, exchange_order_id = %exchange_order.id, error = %err, "Exchange sale recorded but its return failed to commit");
+        return Err(db_error("Failed to commit exchange return", trace_id)(err));
+    }
+
+    let new_order_total_cents = exchange_order.total.as_cents();
+    let (net_delta_cents, net_direction) = settle(credit.total_cents, new_order_total_cents);
+    let refund_tender = (net_delta_cents < 0).then(|| original_tender.clone());
+
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        if let Some(rid) = return_id {
+            let event = OrderRefundedEvent {
+                schema_version: OrderRefundedEvent::SCHEMA_VERSION,
+                order_id: original_order_id,
+                tenant_id,
+                return_id: rid,
+                items: lines
+                    .iter()
+                    .map(|line| OrderEventItem {
+                        product_id: line.product_id,
+                        quantity: line.quantity,
+                        unit_price: Money::from_cents(line.unit_price_cents).into(),
+                        line_total: Money::from_cents(line.line_total_cents()).into(),
+                        measured_quantity: line.measured_quantity.clone(),
+                    })
+                    .collect(),
+                subtotal: Money::from_cents(credit.subtotal_cents).into(),
+                tax: Money::from_cents(credit.tax_cents).into(),
+                total: Money::from_cents(credit.total_cents).into(),
+                refund_due: Money::from_cents((-net_delta_cents).max(0)).into(),
+                refund_tender: original_tender.clone(),
+                customer_id: detail.order.customer_id,
+                location_id: store_id,
+                employee_id: sec.actor.id,
+                exchange_order_id: Some(exchange_order.id),
+            };
+            if let Err(err) = common_kafka::publish_event(&state.kafka_producer, &state.db, tenant_id, &event).await {
+                tracing::error!("Failed to send order.refunded (exchange): {:?}", err);
+            }
+        }
+        if let Some(audit) = &state.audit_producer {
+            let _ = audit
+                .emit(
+                    tenant_id,
+                    sec.actor.clone(),
+                    "order",
+                    Some(original_order_id),
+                    "exchanged",
+                    "order-service",
+                    common_audit::AuditSeverity::Info,
+                    trace_id,
+                    json!({
+                        "return_id": return_id,
+                        "exchange_order_id": exchange_order.id,
+                        "refunded_cents": credit.total_cents,
+                        "net_delta_cents": net_delta_cents,
+                    }),
+                    json!({"source":"order-service"}),
+                )
+                .await;
+        }
+    }
+
+    Ok(Json(ExchangeResponse {
+        original_order_id,
+        exchange_order_id: exchange_order.id,
+        refund: return_id,
+        refund_subtotal_cents: credit.subtotal_cents,
+        refund_tax_cents: credit.tax_cents,
+        refunded_cents: credit.total_cents,
+        new_order_total_cents,
+        net_delta_cents,
+        net_direction: net_direction.to_string(),
+        refund_tender,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_money::RoundingMode;
+
+    fn line(unit_price_cents: i64, quantity: i32, taxable: bool) -> ReturnLine {
+        ReturnLine { order_item_id: Uuid::new_v4(), product_id: Uuid::new_v4(), quantity, unit_price_cents, taxable, measured_quantity: None }
+    }
+
+    #[test]
+    fn return_credit_includes_tax_on_taxable_lines_only() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        let credit = price_return(&[line(1_000, 2, true), line(1_500, 1, false)], 825, &rounding);
+        assert_eq!(credit, ReturnCredit { subtotal_cents: 3_500, tax_cents: 165, total_cents: 3_665 });
+        assert_eq!(price_return(&[], 825, &rounding), ReturnCredit::default());
+    }
+
+    #[test]
+    fn settlement_direction_follows_the_net() {
+        assert_eq!(settle(1_000, 1_500), (500, "collect"));
+        assert_eq!(settle(1_500, 1_000), (-500, "refund"));
+        assert_eq!(settle(1_083, 1_083), (0, "even"));
+    }
+}
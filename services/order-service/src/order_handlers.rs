@@ -6,23 +6,31 @@ use axum::{
 };
 use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use common_auth::AuthContext; // retained only for access to bearer token for downstream service calls
-use common_security::{SecurityCtxExtractor, Role};
+use common_security::{ensure_capability, Capability, SecurityCtxExtractor, Role};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_audit; // for AuditSeverity
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use common_http_errors::validation::{Validate, Validator};
 use common_http_errors::ApiError;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureRecord;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+use common_events::{DomainEvent, OrderCompletedEvent, OrderEventItem, OrderVoidedEvent};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use bigdecimal::BigDecimal;
-use common_money::{nearly_equal, Money};
+use common_money::{nearly_equal, Cents, Money, MoneyLocale, RoundingMode, RoundingPolicy};
+use common_money::measure::{extend_price, net_measure, normalize_measure, UnitOfMeasure};
 // Removed unused FromStr import after BigDecimal migration
 use sqlx::{Postgres, QueryBuilder, Row, Transaction};
 use sqlx::Acquire; // acquire a connection handle within a transaction for sqlx 0.7 executor compatibility
 use std::collections::HashMap;
-#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use std::time::Duration;
 use uuid::Uuid;
 
+use crate::modifiers::{load_modifier_rules, modified_unit_cents, resolve_line, AppliedModifier, ModifierRules};
+use crate::order_exchanges::ExchangeCredit;
 use crate::AppState;
+use crate::checkout_saga::CheckoutSaga;
+use crate::pii::{normalize_email, reveal_customer_pii, seal_customer_email, seal_customer_name};
+use common_crypto::EncryptedColumn;
+use common_db::pagination::{self, Listing, PageRequest, SortDirection, SortField, SortSpec};
 
 // Legacy role string constants removed; unified role enforcement now via common-security Role enum.
 // Mapping note: prior ROLE_CASHIER is approximated by Role::Support until a dedicated Cashier role is introduced.
@@ -35,6 +43,26 @@ pub struct OrderItem {
     pub quantity: i32,
     pub unit_price: Money,
     pub line_total: Money,
+    /// Serials scanned for a serial-tracked product, one per unit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub serial_numbers: Vec<String>,
+    /// Lot the units came from, for lot-tracked products.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lot_code: Option<String>,
+    /// Net scale reading for products sold by weight; `unit_price` is then per `uom`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured_quantity: Option<BigDecimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uom: Option<String>,
+    /// Tare subtracted from the gross reading, kept for audit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tare_weight: Option<BigDecimal>,
+    /// Menu modifier options chosen on this line; see [`crate::modifiers`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_ids: Vec<Uuid>,
+    /// What `modifier_ids` resolved to at checkout; filled in by the server.
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<AppliedModifier>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -51,12 +79,82 @@ pub struct NewOrder {
     pub store_id: Option<Uuid>,
     pub offline: Option<bool>,
     pub idempotency_key: Option<String>,
+    #[serde(default)]
+    pub pos_instance_id: Option<Uuid>,
+    /// Tip on top of `total`. The payment covers both; see [`crate::tips`].
+    #[serde(default)]
+    pub tip_cents: i64,
+    /// Set only by the exchange handler; never read from the request body.
+    #[serde(skip)]
+    pub exchange: Option<ExchangeCredit>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PaymentRequest {
     pub method: String, // "cash" | "card"
+    #[serde(default)]
     pub amount_cents: i64,
+    /// The tender as typed at the till (`"20"`, `"1.234,50"`, `"$20"`) instead of `amount_cents`,
+    /// read with the separators of `locale`.
+    #[serde(default)]
+    pub amount_input: Option<String>,
+    /// BCP 47 tag of the till (`de-DE`); a decimal point is assumed when absent.
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// Separator convention for a till's locale tag; a decimal point when the till sends none.
+pub(crate) fn till_locale(tag: Option<&str>) -> Option<MoneyLocale> {
+    tag.map_or(Some(MoneyLocale::default()), MoneyLocale::from_tag)
+}
+
+impl PaymentRequest {
+    /// Replaces `amount_cents` with the typed `amount_input`; call once validation has passed.
+    pub fn apply_amount_input(&mut self) {
+        let typed = self.amount_input.as_deref().zip(till_locale(self.locale.as_deref()));
+        if let Some(Ok(amount)) = typed.map(|(input, locale)| Money::parse_lenient(input, locale)) {
+            self.amount_cents = amount.as_cents();
+        }
+    }
+}
+
+impl Validate for OrderItem {
+    fn validate(&self, v: &mut Validator) {
+        v.check(self.quantity > 0, "quantity", "must_be_positive");
+        v.check(
+            self.measured_quantity.as_ref().is_none_or(|m| normalize_measure(m) > BigDecimal::from(0)),
+            "measured_quantity",
+            "must_be_positive",
+        );
+    }
+}
+
+impl Validate for PaymentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(self.amount_cents >= 0, "amount_cents", "must_not_be_negative");
+        let Some(input) = &self.amount_input else { return };
+        v.check(self.amount_cents == 0, "amount_input", "conflicts_with_amount_cents");
+        let Some(locale) = till_locale(self.locale.as_deref()) else {
+            v.check(false, "locale", "unsupported_locale");
+            return;
+        };
+        match Money::parse_lenient(input, locale) {
+            Ok(amount) => v.check(amount.as_cents() >= 0, "amount_input", "must_not_be_negative"),
+            Err(err) => v.check_with(false, "amount_input", err.code(), || err.to_string()),
+        };
+    }
+}
+
+// Tender, tip and stock rules depend on tenant settings and are checked in `create_order`.
+impl Validate for NewOrder {
+    fn validate(&self, v: &mut Validator) {
+        v.check(!self.items.is_empty(), "items", "required");
+        v.each("items", &self.items);
+        if let Some(payment) = &self.payment {
+            v.nested("payment", payment);
+        }
+        v.email("customer_email", self.customer_email.as_deref());
+    }
 }
 
 #[derive(Serialize, Debug, sqlx::FromRow)]
@@ -64,15 +162,30 @@ pub struct Order {
     pub id: Uuid,
     pub tenant_id: Uuid,
     pub total: Money,
+    /// Cash rounding included in `total`; zero for card and other exact tenders.
+    pub rounding_adjustment: Money,
+    /// Tip paid on top of `total`; kept out of the taxable sale.
+    pub tip_amount: Money,
     pub status: String,
     pub customer_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_email: Option<String>,
+    /// Sealed customer email; see [`crate::pii`]. Decrypted into `customer_email` before responding.
+    #[serde(skip)]
+    pub customer_email_encrypted: Option<EncryptedColumn<String>>,
+    /// Sealed customer name, decrypted into `customer_name` the same way.
+    #[serde(skip)]
+    pub customer_name_encrypted: Option<EncryptedColumn<String>>,
+    /// Id of the `ORDER_PII_KEY` that sealed the encrypted columns.
+    #[serde(skip)]
+    pub pii_key_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
+    /// Store business day the sale is booked on; see [`crate::day_close`].
+    pub business_date: NaiveDate,
     pub offline: bool,
     pub payment_method: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -83,6 +196,12 @@ pub struct Order {
 struct InventoryReservationItemPayload {
     product_id: Uuid,
     quantity: i32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    serial_numbers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lot_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    measured_quantity: Option<BigDecimal>,
 }
 
 #[allow(dead_code)]
@@ -102,6 +221,7 @@ struct OrderItemFinancialRow {
     quantity: i32,
     unit_price: BigDecimal,
     line_total: BigDecimal,
+    measured_quantity: Option<BigDecimal>,
 }
 
 #[derive(Serialize)]
@@ -171,10 +291,8 @@ async fn compute_financials_for_items(
             taxable_subtotal_cents = subtotal_cents;
         }
     }
-    let rate_bps = default_tax_rate_bps() as i64;
-    let estimated_tax_cents = if taxable_subtotal_cents > 0 && rate_bps > 0 {
-        (taxable_subtotal_cents.saturating_mul(rate_bps) + 5_000) / 10_000
-    } else { 0 };
+    let rounding = resolve_rounding_policy(&state.db, tenant_id).await.context();
+    let estimated_tax_cents = rounding.percent(&Money::from_cents(taxable_subtotal_cents.max(0)), default_tax_rate_bps()).as_cents();
     let mut discount_cents = subtotal_cents.saturating_add(estimated_tax_cents).saturating_sub(total_cents);
     if discount_cents < 0 { discount_cents = 0; }
     (subtotal_cents, discount_cents, estimated_tax_cents)
@@ -182,7 +300,7 @@ async fn compute_financials_for_items(
 
 fn clamp_bps2(v: i32) -> i32 { v.clamp(0, 10_000) }
 
-async fn resolve_tax_rate_bps_with_db(
+pub(crate) async fn resolve_tax_rate_bps_with_db(
     db: &sqlx::PgPool,
     tenant_id: Uuid,
     headers: &HeaderMap,
@@ -213,6 +331,20 @@ async fn resolve_tax_rate_bps_with_db(
     default_tax_rate_bps()
 }
 
+/// Tenant rounding policy from `rounding_policies`, falling back to the `MONEY_ROUNDING` mode
+/// without cash rounding when the tenant has none (or the lookup fails).
+pub(crate) async fn resolve_rounding_policy(db: &sqlx::PgPool, tenant_id: Uuid) -> RoundingPolicy {
+    match sqlx::query_as::<_, (String, i32)>(
+        "SELECT mode, cash_increment_cents FROM rounding_policies WHERE tenant_id = $1"
+    ).bind(tenant_id).fetch_optional(db).await {
+        Ok(Some((mode, increment))) => match RoundingMode::parse(&mode) {
+            Some(mode) => RoundingPolicy::new(mode).with_cash_increment(increment.max(0) as u32),
+            None => RoundingPolicy::global(),
+        },
+        _ => RoundingPolicy::global(),
+    }
+}
+
 #[derive(Deserialize, Default)]
 pub struct ListOrdersParams {
     pub limit: Option<i64>,
@@ -226,8 +358,13 @@ pub struct ListOrdersParams {
     pub customer: Option<String>,
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
-    pub sort: Option<String>,
-    pub direction: Option<String>,
+    pub min_total: Option<BigDecimal>,
+    pub max_total: Option<BigDecimal>,
+    /// POS terminal that rang up the order.
+    #[serde(alias = "pos_instance_id")]
+    pub terminal_id: Option<Uuid>,
+    /// Receipt number as printed (the order id), or a prefix of at least 8 hex digits.
+    pub receipt: Option<String>,
 }
 
 #[derive(Deserialize, Default)]
@@ -253,6 +390,7 @@ pub struct ReturnSummary {
 }
 #[derive(Serialize, Debug)]
 pub struct OrderLineItem {
+    pub id: Uuid,
     pub product_id: Uuid,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub product_name: Option<String>,
@@ -260,6 +398,55 @@ pub struct OrderLineItem {
     pub unit_price: Money,
     pub line_total: Money,
     pub returned_quantity: i32,
+    /// Catalog price before a manager price override; absent when the line is at list price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_unit_price: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_override_reason: Option<String>,
+    /// Net scale reading for weighed lines; `unit_price` is then per `uom`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub measured_quantity: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uom: Option<String>,
+    /// Menu modifiers chosen on the line; their deltas are already in `unit_price`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<AppliedModifier>,
+}
+
+impl OrderLineItem {
+    /// Receipt quantity column: the reading with its unit for weighed lines, else the count.
+    fn display_quantity(&self) -> String {
+        match (&self.measured_quantity, &self.uom) {
+            (Some(measured), Some(uom)) => format!("{} {}", normalize_measure(measured), uom),
+            _ => self.quantity.to_string(),
+        }
+    }
+
+    /// Item name followed by its chosen modifiers, for the receipt item column.
+    fn display_name(&self, fallback: &str) -> String {
+        let name = self.product_name.as_deref().unwrap_or(fallback);
+        if self.modifiers.is_empty() {
+            return name.to_string();
+        }
+        let chosen: Vec<&str> = self.modifiers.iter().map(|m| m.name.as_str()).collect();
+        format!("{} ({})", name, chosen.join(", "))
+    }
+}
+
+/// Kitchen ticket (`?format=kitchen`): quantities, items and their modifiers, one per line and
+/// without prices, for kitchen display screens and printers.
+fn kitchen_ticket(detail: &OrderDetail) -> String {
+    use std::fmt::Write as _;
+    let mut body = String::new();
+    writeln!(&mut body, "Order {}  {}", detail.order.id, detail.order.created_at.format("%H:%M")).ok();
+    body.push_str("-------------------------------------\n");
+    for item in &detail.items {
+        writeln!(&mut body, "{} x {}", item.display_quantity(), item.product_name.as_deref().unwrap_or("Item")).ok();
+        for modifier in &item.modifiers {
+            writeln!(&mut body, "    {}: {}", modifier.group, modifier.name).ok();
+        }
+    }
+    body
 }
 
 #[derive(Serialize, Debug)]
@@ -296,7 +483,9 @@ pub struct SettlementQuery {
 pub struct SettlementByMethod {
     pub method: String,
     pub count: i64,
+    /// Captured amount, tips included.
     pub amount: BigDecimal,
+    pub tips: BigDecimal,
 }
 
 #[derive(Debug, Serialize)]
@@ -330,10 +519,12 @@ pub async fn get_settlement_report(
         method: String,
         count: Option<i64>,
         amount: Option<BigDecimal>,
+        tips: Option<BigDecimal>,
     }
 
     let rows = sqlx::query_as::<_, SettlementRow>(
-        r#"SELECT method, COUNT(*) as count, COALESCE(SUM(amount)::NUMERIC, 0)::NUMERIC as amount
+        r#"SELECT method, COUNT(*) as count, COALESCE(SUM(amount)::NUMERIC, 0)::NUMERIC as amount,
+                  COALESCE(SUM(tip_amount)::NUMERIC, 0)::NUMERIC as tips
             FROM payments
             WHERE tenant_id = $1 AND status = 'captured' AND created_at::date = $2
             GROUP BY method
@@ -351,207 +542,26 @@ pub async fn get_settlement_report(
             method: r.method,
             count: r.count.unwrap_or(0),
             amount: r.amount.unwrap_or(BigDecimal::from(0)),
+            tips: r.tips.unwrap_or(BigDecimal::from(0)),
         });
     }
 
     Ok(Json(SettlementReport { date: date_str, totals }))
 }
 
-// --- Exchanges (MVP) ---
-#[derive(Deserialize, Debug)]
-pub struct ExchangeReturnItem { pub product_id: Uuid, pub qty: i32 }
-
-#[derive(Deserialize, Debug)]
-pub struct ExchangeNewItem { pub sku: String, pub qty: i32 }
-
-#[derive(Deserialize, Debug)]
-pub struct ExchangeRequest {
-    pub return_items: Vec<ExchangeReturnItem>,
-    pub new_items: Vec<ExchangeNewItem>,
-    #[serde(default)] pub discount_percent_bp: Option<i32>,
-    pub payment: Option<PaymentRequest>,
-    pub cashier_id: Option<Uuid>,
-    pub idempotency_key: Option<String>,
-}
-
-#[derive(Serialize, Debug)]
-pub struct ExchangeResponse {
-    pub original_order_id: Uuid,
-    pub exchange_order_id: Uuid,
-    pub refund: Option<Uuid>,
-    pub refunded_cents: i64,
-    pub new_order_total_cents: i64,
-    pub net_delta_cents: i64,
-    pub net_direction: String,
-}
-
-pub async fn exchange_order(
-    State(state): State<AppState>,
-    SecurityCtxExtractor(sec): SecurityCtxExtractor,
-    Path(original_order_id): Path<Uuid>,
-    headers: HeaderMap,
-    auth: AuthContext,
-    Json(req): Json<ExchangeRequest>,
-) -> Result<Json<ExchangeResponse>, ApiError> {
-    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager)) {
-        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: None });
-    }
-    let tenant_id = sec.tenant_id;
-
-    if req.return_items.is_empty() && req.new_items.is_empty() {
-        return Err(ApiError::BadRequest { code: "invalid_request", trace_id: None, message: Some("Must provide return_items or new_items".into()) });
-    }
-
-    // 1) Load original order and items with returned balance
-    let detail = fetch_order_detail(&state, tenant_id, original_order_id).await?;
-    if !matches!(detail.order.status.as_str(), "COMPLETED" | "PAID" | "REFUNDED" | "PARTIAL_REFUNDED") {
-        return Err(ApiError::BadRequest { code: "order_not_completed", trace_id: None, message: Some("Original order not in a refundable state".into()) });
-    }
-
-    // Build product_id -> refundable quantity map and order_item_id lookup
-    #[derive(Clone)]
-    struct ItemRow { order_item_id: Uuid, qty: i32, returned: i32, unit_price_cents: i64 }
-    let mut by_product: HashMap<Uuid, ItemRow> = HashMap::new();
-    {
-        let rows = sqlx::query(
-            "SELECT id, product_id, quantity, returned_quantity, unit_price FROM order_items WHERE order_id = $1"
-        ).bind(original_order_id).fetch_all(&state.db).await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load original order items: {}", e)) })?;
-        for row in rows {
-            let order_item_id: Uuid = row.try_get("id").unwrap();
-            let product_id: Uuid = row.try_get("product_id").unwrap();
-            let quantity: i32 = row.try_get("quantity").unwrap();
-            let returned_quantity: i32 = row.try_get("returned_quantity").unwrap_or(0);
-            let unit_price: BigDecimal = row.try_get("unit_price").unwrap();
-            by_product.insert(product_id, ItemRow { order_item_id, qty: quantity, returned: returned_quantity, unit_price_cents: Money::new(unit_price).as_cents() });
-        }
-    }
-
-    // 2) Compute refund_total_cents and update returned_quantity in a tx
-    let mut tx = state.db.begin().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to begin exchange tx: {}", e)) })?;
-    let mut refund_total_cents: i64 = 0;
-    let mut return_id: Option<Uuid> = None;
-    if !req.return_items.is_empty() {
-        // Validate quantities
-        for it in &req.return_items {
-            if it.qty <= 0 { return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id: None, message: Some("Return quantities must be positive".into()) }); }
-            let row = by_product.get(&it.product_id).ok_or(ApiError::BadRequest { code: "product_not_in_order", trace_id: None, message: Some("Product is not part of original order".into()) })?;
-            let available = row.qty - row.returned;
-            if it.qty > available { return Err(ApiError::BadRequest { code: "refundable_qty_exceeded", trace_id: None, message: Some(format!("Cannot return {} units; only {} remain", it.qty, available)) }); }
-        }
-
-        let rid = Uuid::new_v4();
-        return_id = Some(rid);
-        {
-            let conn = tx.acquire().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire conn: {}", e)) })?;
-            sqlx::query("INSERT INTO order_returns (id, order_id, tenant_id, total) VALUES ($1,$2,$3,$4)")
-                .bind(rid)
-                .bind(original_order_id)
-                .bind(tenant_id)
-                .bind(Money::from_cents(0).inner())
-                .execute(&mut *conn).await
-                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert return: {}", e)) })?;
-        }
-        for it in &req.return_items {
-            let row = by_product.get(&it.product_id).unwrap();
-            let line_cents = row.unit_price_cents.saturating_mul(it.qty as i64);
-            refund_total_cents = refund_total_cents.saturating_add(line_cents);
-            // update returned_quantity and insert return item row
-            let conn = tx.acquire().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire conn: {}", e)) })?;
-            sqlx::query("UPDATE order_items SET returned_quantity = returned_quantity + $1 WHERE id = $2")
-                .bind(it.qty)
-                .bind(row.order_item_id)
-                .execute(&mut *conn).await
-                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to update returned qty: {}", e)) })?;
-            let conn2 = tx.acquire().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire conn: {}", e)) })?;
-            sqlx::query("INSERT INTO order_return_items (id, return_id, order_item_id, quantity, line_total) VALUES ($1,$2,$3,$4,$5)")
-                .bind(Uuid::new_v4())
-                .bind(return_id.unwrap())
-                .bind(row.order_item_id)
-                .bind(it.qty)
-                .bind(Money::from_cents(line_cents).inner())
-                .execute(&mut *conn2).await
-                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert return item: {}", e)) })?;
-        }
-        // finalize return total and possibly order status partial/full
-        let all_returned = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM order_items WHERE order_id = $1 AND returned_quantity < quantity")
-            .bind(original_order_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to compute return completion: {}", e)) })? == 0;
-        let new_status = if all_returned { "REFUNDED" } else { "PARTIAL_REFUNDED" };
-        let conn = tx.acquire().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire conn: {}", e)) })?;
-        sqlx::query("UPDATE order_returns SET total = $2 WHERE id = $1")
-            .bind(return_id.unwrap())
-            .bind(Money::from_cents(refund_total_cents).inner())
-            .execute(&mut *conn).await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to finalize return: {}", e)) })?;
-        let conn2 = tx.acquire().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire conn: {}", e)) })?;
-        sqlx::query("UPDATE orders SET status = $3 WHERE id = $1 AND tenant_id = $2")
-            .bind(original_order_id)
-            .bind(tenant_id)
-            .bind(new_status)
-            .execute(&mut *conn2).await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to update order status: {}", e)) })?;
-    }
-
-    // 3) Build new order from SKUs
-    let new_items: Vec<NewOrderSkuItem> = req.new_items.iter().map(|i| NewOrderSkuItem { sku: i.sku.clone(), quantity: i.qty }).collect();
-    let new_req = NewOrderFromSku {
-        items: new_items,
-        discount_percent_bp: req.discount_percent_bp,
-        tax_rate_bps: None,
-        location_id: None,
-        pos_instance_id: None,
-        payment_method: req.payment.as_ref().map(|p| p.method.clone()).unwrap_or_else(|| "cash".to_string()),
-        payment: req.payment.clone(),
-        customer_id: detail.order.customer_id.map(|id| id.to_string()),
-        customer_name: detail.order.customer_name.clone(),
-        customer_email: detail.order.customer_email.clone(),
-        store_id: detail.order.store_id,
-        offline: Some(detail.order.offline),
-        idempotency_key: req.idempotency_key.clone(),
-    };
-    let created = create_order_from_skus(State(state.clone()), SecurityCtxExtractor(sec.clone()), auth, headers.clone(), Json(new_req)).await?;
-    let exchange_order = created.0;
-
-    // 4) Link the new order to the original via exchange_of_order_id
-    sqlx::query("UPDATE orders SET exchange_of_order_id = $3 WHERE id = $1 AND tenant_id = $2")
-        .bind(exchange_order.id)
-        .bind(tenant_id)
-        .bind(original_order_id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to link exchange order: {}", e)) })?;
-
-    let new_total_cents = exchange_order.total.as_cents();
-    let net_delta_cents = new_total_cents.saturating_sub(refund_total_cents);
-    let net_direction = if net_delta_cents > 0 { "collect" } else if net_delta_cents < 0 { "refund" } else { "even" }.to_string();
-
-    tx.commit().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Exchange transaction commit failed: {}", e)) })?;
-
-    Ok(Json(ExchangeResponse {
-        original_order_id,
-        exchange_order_id: exchange_order.id,
-        refund: return_id,
-        refunded_cents: refund_total_cents,
-        new_order_total_cents: new_total_cents,
-        net_delta_cents,
-        net_direction,
-    }))
-}
-
 #[derive(Deserialize)]
 pub struct VoidOrderRequest {
     pub reason: Option<String>,
+    /// One of `order_voids::VOID_REASON_CODES`; omitted by older POS builds and recorded as `other`.
+    pub reason_code: Option<String>,
 }
 
-fn inventory_url(base_url: &str, suffix: &str) -> String {
+pub(crate) fn inventory_url(base_url: &str, suffix: &str) -> String {
     let trimmed = base_url.trim_end_matches('/');
     format!("{}{}", trimmed, suffix)
 }
 
-fn map_legacy_error(status: StatusCode, message: String) -> ApiError {
+pub(crate) fn map_legacy_error(status: StatusCode, message: String) -> ApiError {
     match status {
         StatusCode::BAD_REQUEST => ApiError::BadRequest { code: "bad_request", trace_id: None, message: Some(message) },
         StatusCode::FORBIDDEN => ApiError::Forbidden { trace_id: None },
@@ -581,6 +591,9 @@ async fn reserve_inventory(
             .map(|item| InventoryReservationItemPayload {
                 product_id: item.product_id,
                 quantity: item.quantity,
+                serial_numbers: item.serial_numbers.clone(),
+                lot_code: item.lot_code.clone(),
+                measured_quantity: item.measured_quantity.clone(),
             })
             .collect(),
     };
@@ -615,7 +628,7 @@ async fn reserve_inventory(
     ))
 }
 
-async fn release_inventory(
+pub(crate) async fn release_inventory(
     client: &Client,
     base_url: &str,
     tenant_id: Uuid,
@@ -727,8 +740,8 @@ async fn insert_order_items(
             .await
             .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
             sqlx::query(
-                r#"INSERT INTO order_items (id, order_id, product_id, quantity, unit_price, line_total, product_name)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)"#
+                r#"INSERT INTO order_items (id, order_id, product_id, quantity, unit_price, line_total, product_name, serial_numbers, lot_code, measured_quantity, uom, tare_weight, modifiers)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"#
             )
             .bind(Uuid::new_v4())
             .bind(order_id)
@@ -737,6 +750,12 @@ async fn insert_order_items(
             .bind(item.unit_price.inner())
             .bind(item.line_total.inner())
             .bind(item.product_name.as_deref())
+            .bind(&item.serial_numbers)
+            .bind(item.lot_code.as_deref())
+            .bind(item.measured_quantity.as_ref().map(normalize_measure))
+            .bind(item.uom.as_deref())
+            .bind(item.tare_weight.as_ref())
+            .bind(sqlx::types::Json(&item.modifiers))
             .execute(&mut *conn)
             .await
             .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert order item: {}", e)) })?;
@@ -748,7 +767,7 @@ pub async fn create_order(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     auth: AuthContext, // kept only for propagating bearer token to inventory-service
-    Json(new_order): Json<NewOrder>,
+    Json(mut new_order): Json<NewOrder>,
 ) -> Result<Json<Order>, ApiError> {
     if !sec
         .roles
@@ -758,9 +777,9 @@ pub async fn create_order(
     return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: None });
     }
     let tenant_id = sec.tenant_id;
-
-    if new_order.items.is_empty() {
-    return Err(ApiError::BadRequest { code: "missing_items", trace_id: None, message: Some("Order must contain at least one item".into()) });
+    new_order.ensure_valid(sec.trace_id)?;
+    if let Some(payment) = new_order.payment.as_mut() {
+        payment.apply_amount_input();
     }
 
     // Determine payment method and amount (if provided)
@@ -780,7 +799,7 @@ pub async fn create_order(
 
     if let Some(ref key) = idempotency_key {
         if let Some(existing) = sqlx::query_as::<_, Order>(
-            "SELECT id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key FROM orders WHERE tenant_id = $1 AND idempotency_key = $2"
+            "SELECT id, tenant_id, total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date FROM orders WHERE tenant_id = $1 AND idempotency_key = $2"
         )
         .bind(tenant_id)
         .bind(key)
@@ -788,15 +807,24 @@ pub async fn create_order(
     .await
     .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("DB error while checking idempotency: {}", e)) })?
         {
+            let mut existing = existing;
+            reveal_customer_pii(state.pii_key.as_deref(), &mut existing);
             return Ok(Json(existing));
         }
     }
 
+    // Forced modifier groups must be answered before the order is taken.
+    let product_ids: Vec<Uuid> = new_order.items.iter().map(|item| item.product_id).collect();
+    let modifier_rules = fetch_modifier_rules(&state.db, tenant_id, &product_ids).await?;
+    for item in new_order.items.iter_mut() {
+        item.modifiers = resolve_line(&modifier_rules, item.product_id, &item.modifier_ids)?;
+    }
+
     let total_from_items: BigDecimal = new_order
         .items
         .iter()
     .fold(BigDecimal::from(0), |acc, item| acc + BigDecimal::from(item.line_total.clone())) ;
-    if !nearly_equal(&total_from_items, &new_order.total, 1) {
+    if !nearly_equal(&total_from_items, &new_order.total, Cents(1)) {
         tracing::warn!(
             tenant_id = %tenant_id,
             provided_total = %new_order.total,
@@ -805,26 +833,36 @@ pub async fn create_order(
         );
     }
 
+    // The sale is booked on the store's current business day, which takes no more sales once closed.
+    let business_date = crate::day_close::business_date_at(&state.db, tenant_id, new_order.store_id, Utc::now())
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to resolve business date: {e}")) })?;
+    crate::day_close::ensure_day_open(&state.db, tenant_id, new_order.store_id, business_date, sec.trace_id).await?;
+
     let order_id = Uuid::new_v4();
     let auth_token = auth.token.clone();
 
-    reserve_inventory(
-        &state.http_client,
-        &state.inventory_base_url,
-        tenant_id,
-        &auth_token,
-        order_id,
-        &new_order.items,
-    )
-    .await?;
-
     let offline_flag = new_order.offline.unwrap_or(false);
-    let total_cents = Money::new(new_order.total.clone()).as_cents();
+    // Clients send the exact total; cash is rounded to the tenant's cash increment here so the
+    // drawer balances, while card and other tenders are charged the exact amount.
+    let rounding_policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    let (total_cents, rounding_adjustment_cents) =
+        tender_total_cents(&rounding_policy, &payment_method, Money::new(new_order.total.clone()).as_cents());
+    let total = Money::from_cents(total_cents);
+    // The tip rides on the payment but stays out of `total`, the taxable sale.
+    let tip_cents = new_order.tip_cents;
+    crate::tips::resolve_tip_settings(&state.db, tenant_id)
+        .await
+        .check_checkout_tip(&rounding_policy.context(), total_cents, tip_cents)?;
+    // On an exchange the returned lines' credit pays first; the tender only covers what is left.
+    let exchange_credit_cents = new_order.exchange.as_ref().map_or(0, |x| x.credit_cents.clamp(0, total_cents));
+    let due_cents = total_cents + tip_cents - exchange_credit_cents;
     // Determine final order status based on payment semantics (mock card, cash)
     let status = match payment_method.as_str() {
+        _ if exchange_credit_cents > 0 && due_cents == 0 && new_order.payment.is_none() => "COMPLETED",
         "cash" => {
             if let Some(p) = &new_order.payment {
-                if p.amount_cents < total_cents { return Err(ApiError::BadRequest { code: "insufficient_cash", trace_id: None, message: Some("Cash provided is less than total".into()) }); }
+                if p.amount_cents < due_cents { return Err(ApiError::BadRequest { code: "insufficient_cash", trace_id: None, message: Some("Cash provided is less than total plus tip".into()) }); }
                 "COMPLETED"
             } else {
                 // No amount provided; treat as pending until payment is added (strict MVP requires change logic)
@@ -833,24 +871,7 @@ pub async fn create_order(
         }
         "card" => {
             if let Some(p) = &new_order.payment {
-                if p.amount_cents != total_cents { return Err(ApiError::BadRequest { code: "amount_mismatch", trace_id: None, message: Some("Card amount must equal order total".into()) }); }
-                // Optionally create a payment intent (feature-gated)
-                if state.enable_payment_intents {
-                    let url = format!("{}/payment_intents", state.payment_base_url.trim_end_matches('/'));
-                    let body = serde_json::json!({
-                        "id": format!("pi_{}", order_id),
-                        "orderId": order_id.to_string(),
-                        "amountMinor": total_cents,
-                        "currency": "USD",
-                        "idempotencyKey": new_order.idempotency_key.clone().unwrap_or_else(|| format!("ord:{}", order_id))
-                    });
-                    let resp = state.http_client.post(url)
-                        .header("Content-Type", "application/json")
-                        .header("X-Tenant-ID", tenant_id.to_string())
-                        .json(&body)
-                        .send().await;
-                    if let Err(err) = resp { tracing::warn!(?err, order_id = %order_id, "payment intent create failed (network)"); }
-                }
+                if p.amount_cents != due_cents { return Err(ApiError::BadRequest { code: "amount_mismatch", trace_id: None, message: Some("Card amount must equal order total plus tip".into()) }); }
                 "COMPLETED"
             } else {
                 return Err(ApiError::BadRequest { code: "missing_amount", trace_id: None, message: Some("Card payment requires amount_cents".into()) });
@@ -889,166 +910,221 @@ pub async fn create_order(
 
     let customer_email = new_order
         .customer_email
-        .as_ref()
-        .map(|value| value.trim())
-        .filter(|value| !value.is_empty())
-        .map(|value| value.to_lowercase());
+        .as_deref()
+        .and_then(normalize_email);
+    // Contact details are only ever stored sealed; refuse them rather than drop or store plaintext.
+    let pii_key = match (&customer_name, &customer_email, state.pii_key.as_deref()) {
+        (None, None, _) => None,
+        (_, _, Some(keys)) => Some(keys),
+        (_, _, None) => {
+            return Err(ApiError::ServiceUnavailable { code: "pii_key_unavailable", trace_id: sec.trace_id, retry_after_secs: 60 });
+        }
+    };
+    let seal_err = |e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to encrypt customer details: {e}")) };
+    let (customer_name_encrypted, customer_name_hash) = match (customer_name.as_deref(), pii_key) {
+        (Some(name), Some(keys)) => Some(seal_customer_name(keys.active(), tenant_id, name).map_err(seal_err)?),
+        _ => None,
+    }
+    .unzip();
+    let (customer_email_encrypted, customer_email_hash) = match (customer_email.as_deref(), pii_key) {
+        (Some(email), Some(keys)) => Some(seal_customer_email(keys.active(), tenant_id, email).map_err(seal_err)?),
+        _ => None,
+    }
+    .unzip();
+    let pii_key_id = pii_key.map(|keys| keys.active_id().to_string());
 
     let store_id = new_order.store_id;
 
-    let mut tx = match state.db.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            if let Err(release_err) = release_inventory(
-                &state.http_client,
-                &state.inventory_base_url,
-                tenant_id,
-                &auth_token,
-                order_id,
-            )
-            .await
-            {
-                tracing::error!(?release_err, order_id = %order_id, tenant_id = %tenant_id, "Failed to release inventory after begin failure");
+    // Reservation, payment authorization and the order insert run as a saga: a failure after the
+    // reservation releases it and voids the payment intent (see `crate::checkout_saga`).
+    let mut saga = CheckoutSaga::begin(&state.db, tenant_id, order_id, sec.trace_id).await?;
+    if let Err(err) = reserve_inventory(
+        &state.http_client,
+        &state.inventory_base_url,
+        tenant_id,
+        &auth_token,
+        order_id,
+        &new_order.items,
+    )
+    .await
+    {
+        saga.compensate(&state, &sec, &auth_token, &format!("inventory reservation failed: {err:?}")).await;
+        return Err(err);
+    }
+    if let Err(err) = saga.inventory_reserved(&state.db, sec.trace_id).await {
+        saga.compensate(&state, &sec, &auth_token, &format!("{err:?}")).await;
+        return Err(err);
+    }
+
+    // Optionally create a payment intent (feature-gated)
+    if payment_method == "card" && new_order.payment.is_some() && state.enable_payment_intents {
+        let url = format!("{}/payment_intents", state.payment_base_url.trim_end_matches('/'));
+        let intent_id = format!("pi_{}", order_id);
+        let body = serde_json::json!({
+            "id": intent_id,
+            "orderId": order_id.to_string(),
+            "amountMinor": due_cents,
+            "tipMinor": tip_cents,
+            "currency": "USD",
+            "idempotencyKey": new_order.idempotency_key.clone().unwrap_or_else(|| format!("ord:{}", order_id))
+        });
+        let resp = state.http_client.post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Tenant-ID", tenant_id.to_string())
+            .json(&body)
+            .send().await;
+        match resp {
+            Ok(resp) if resp.status().is_success() => {
+                if let Err(err) = saga.payment_authorized(&state.db, intent_id, sec.trace_id).await {
+                    saga.compensate(&state, &sec, &auth_token, &format!("{err:?}")).await;
+                    return Err(err);
+                }
             }
-            return Err(ApiError::Internal { trace_id: None, message: Some(format!("Failed to begin transaction: {}", e)) });
+            Ok(resp) => tracing::warn!(status = %resp.status(), order_id = %order_id, "payment intent create failed"),
+            Err(err) => tracing::warn!(?err, order_id = %order_id, "payment intent create failed (network)"),
         }
-    };
+    }
 
-    let insert_result = {
-        let conn = tx
-            .acquire()
-            .await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
-        sqlx::query_as::<_, Order>(
-            "INSERT INTO orders (id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, offline, payment_method, idempotency_key)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-             RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key"
-        )
-        .bind(order_id)
-        .bind(tenant_id)
-        .bind(&new_order.total)
-        .bind(status)
-        .bind(customer_uuid)
-        .bind(customer_name.as_deref())
-        .bind(customer_email.as_deref())
-        .bind(store_id)
-        .bind(offline_flag)
-        .bind(&payment_method)
-        .bind(idempotency_key.as_deref())
-        .fetch_one(&mut *conn)
-        .await
-    };
-    let order = match insert_result {
-        Ok(order) => order,
-        Err(e) => {
-            if let Err(release_err) = release_inventory(
-                &state.http_client,
-                &state.inventory_base_url,
-                tenant_id,
-                &auth_token,
-                order_id,
+    let persisted: Result<Order, ApiError> = async {
+        let mut tx = state.db.begin().await
+            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to begin transaction: {}", e)) })?;
+        let order = {
+            let conn = tx
+                .acquire()
+                .await
+                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
+            sqlx::query_as::<_, Order>(
+                "INSERT INTO orders (id, tenant_id, total, status, customer_id, customer_name_encrypted, customer_name_hash, customer_email_encrypted, customer_email_hash, pii_key_id, store_id, offline, payment_method, idempotency_key, created_by, pos_instance_id, rounding_adjustment, tip_amount, exchange_of_order_id, business_date)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                 RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date"
             )
+            .bind(order_id)
+            .bind(tenant_id)
+            .bind(total.inner())
+            .bind(status)
+            .bind(customer_uuid)
+            .bind(customer_name_encrypted)
+            .bind(customer_name_hash)
+            .bind(customer_email_encrypted)
+            .bind(customer_email_hash)
+            .bind(pii_key_id)
+            .bind(store_id)
+            .bind(offline_flag)
+            .bind(&payment_method)
+            .bind(idempotency_key.as_deref())
+            .bind(sec.actor.id)
+            .bind(new_order.pos_instance_id)
+            .bind(Money::from_cents(rounding_adjustment_cents).inner())
+            .bind(Money::from_cents(tip_cents).inner())
+            .bind(new_order.exchange.as_ref().map(|x| x.original_order_id))
+            .bind(business_date)
+            .fetch_one(&mut *conn)
             .await
-            {
-                tracing::error!(?release_err, order_id = %order_id, tenant_id = %tenant_id, "Failed to release inventory after insert failure");
-            }
-            return Err(ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert order: {}", e)) });
-        }
-    };
+            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert order: {}", e)) })?
+        };
 
-    if let Err(err) = insert_order_items(&mut tx, order.id, &new_order.items).await {
-        if let Err(release_err) = release_inventory(
-            &state.http_client,
-            &state.inventory_base_url,
-            tenant_id,
-            &auth_token,
-            order_id,
-        )
-        .await
-        {
-            tracing::error!(?release_err, order_id = %order_id, tenant_id = %tenant_id, "Failed to release inventory after order item insertion failure");
-        }
-        return Err(err);
-    }
+        insert_order_items(&mut tx, order.id, &new_order.items).await?;
 
-    // If we have a payment, persist it within the same tx and compute change for cash
-    if let Some(p) = &new_order.payment {
-        let change_cents = if payment_method == "cash" { Some(p.amount_cents.saturating_sub(total_cents) as i32) } else { None };
-        let conn = tx
-            .acquire()
+        // If we have a payment, persist it within the same tx and compute change for cash
+        if let Some(p) = &new_order.payment {
+            let change_cents = if payment_method == "cash" { Some(p.amount_cents.saturating_sub(due_cents) as i32) } else { None };
+            let conn = tx
+                .acquire()
+                .await
+                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
+            sqlx::query(
+                r#"INSERT INTO payments (id, tenant_id, order_id, method, amount, status, change_cents, tip_amount)
+                   VALUES ($1,$2,$3,$4,$5,$6,$7,$8)"#
+            )
+            .bind(Uuid::new_v4())
+            .bind(tenant_id)
+            .bind(order.id)
+            .bind(&payment_method)
+            .bind(Money::from_cents(p.amount_cents).inner())
+            .bind("captured")
+            .bind(change_cents)
+            .bind(Money::from_cents(tip_cents).inner())
+            .execute(&mut *conn)
             .await
-            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
-        sqlx::query(
-            r#"INSERT INTO payments (id, tenant_id, order_id, method, amount, status, change_cents)
-               VALUES ($1,$2,$3,$4,$5,$6,$7)"#
-        )
-        .bind(Uuid::new_v4())
-        .bind(tenant_id)
-        .bind(order.id)
-        .bind(&payment_method)
-        .bind(Money::from_cents(p.amount_cents).inner())
-        .bind("captured")
-        .bind(change_cents)
-        .execute(&mut *conn)
-        .await
-        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert payment: {e}")) })?;
-    }
-
-    if let Err(e) = tx.commit().await {
-        if let Err(release_err) = release_inventory(
-            &state.http_client,
-            &state.inventory_base_url,
-            tenant_id,
-            &auth_token,
-            order_id,
-        )
-        .await
-        {
-            tracing::error!(?release_err, order_id = %order_id, tenant_id = %tenant_id, "Failed to release inventory after commit failure");
+            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to insert payment: {e}")) })?;
         }
-        return Err(ApiError::Internal { trace_id: None, message: Some(format!("Failed to commit transaction: {}", e)) });
+
+        saga.complete(&mut tx, sec.trace_id).await?;
+        tx.commit().await
+            .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to commit transaction: {}", e)) })?;
+        Ok(order)
     }
+    .await;
+    let mut order = match persisted {
+        Ok(order) => order,
+        Err(err) => {
+            saga.compensate(&state, &sec, &auth_token, &format!("order persistence failed: {err:?}")).await;
+            return Err(err);
+        }
+    };
+    saga.record_completed();
 
     if order.status == "COMPLETED" {
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         // Build both legacy order.completed and new pos.order payloads
-        let event_items: Vec<serde_json::Value> = new_order
+        let event_items: Vec<OrderEventItem> = new_order
             .items
             .iter()
-            .map(|item| {
-                serde_json::json!({
-                    "product_id": item.product_id,
-                    "quantity": item.quantity,
-                    "unit_price": item.unit_price,
-                    "line_total": item.line_total
-                })
+            .map(|item| OrderEventItem {
+                product_id: item.product_id,
+                quantity: item.quantity,
+                unit_price: item.unit_price.clone().into(),
+                line_total: item.line_total.clone().into(),
+                measured_quantity: item.measured_quantity.as_ref().map(normalize_measure),
             })
             .collect();
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event = serde_json::json!({
-            "order_id": order.id,
-            "tenant_id": tenant_id,
-            "items": event_items,
-            "total": new_order.total,
-            "customer_id": customer_uuid,
-            "offline": order.offline,
-            "payment_method": order.payment_method,
-        });
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event = OrderCompletedEvent {
+            schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+            order_id: order.id,
+            tenant_id,
+            items: event_items,
+            total: total.clone().into(),
+            customer_id: customer_uuid,
+            offline: order.offline,
+            payment_method: order.payment_method.clone(),
+            return_id: None,
+            location_id: order.store_id,
+            employee_id: sec.actor.id,
+            rma_id: None,
+            exchange_return_id: new_order.exchange.as_ref().and_then(|x| x.return_id),
+            business_date: Some(order.business_date),
+        };
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         {
-            if let Err(err) = state
-                .kafka_producer
-                .send(
-                    FutureRecord::to("order.completed")
-                        .payload(&event.to_string())
-                        .key(&tenant_id.to_string()),
-                    Duration::from_secs(0),
-                )
-                .await
+            if let Err(err) = common_kafka::publish_event(
+                &state.kafka_producer,
+                &state.db,
+                event.tenant_id,
+                &event,
+            )
+            .await
             {
                 tracing::error!("Failed to send order.completed: {:?}", err);
             }
+            if tip_cents > 0 {
+                crate::tips::publish_tip_recorded(
+                    &state,
+                    crate::tips::TipChange {
+                        order_id: order.id,
+                        tenant_id,
+                        employee_id: sec.actor.id,
+                        pos_instance_id: new_order.pos_instance_id,
+                        payment_method: &order.payment_method,
+                        previous_tip_cents: 0,
+                        tip_cents,
+                        adjustment: false,
+                        business_date: order.business_date,
+                    },
+                )
+                .await;
+            }
             // Emit pos.order event with computed tax/discount and SKU enrichment
             let product_ids: Vec<Uuid> = new_order.items.iter().map(|i| i.product_id).collect();
             let sku_map: std::collections::HashMap<Uuid, Option<String>> = if !product_ids.is_empty() {
@@ -1085,27 +1161,26 @@ pub async fn create_order(
                     "line_total_cents": line_cents
                 })
             }).collect();
-            let total_cents = Money::new(new_order.total.clone()).as_cents();
-            let (_sub, discount_cents, tax_cents) = compute_financials_for_items(&state, tenant_id, &new_order.items, total_cents).await;
+            let (_sub, discount_cents, tax_cents) =
+                compute_financials_for_items(&state, tenant_id, &new_order.items, total_cents - rounding_adjustment_cents).await;
             let pos_evt = serde_json::json!({
                 "order_id": order.id,
                 "status": "paid",
                 "total_cents": total_cents,
                 "tax_cents": tax_cents,
                 "discount_cents": discount_cents,
+                "rounding_adjustment_cents": rounding_adjustment_cents,
                 "items": pos_items,
                 "payment_method": order.payment_method,
                 "occurred_at": chrono::Utc::now().to_rfc3339(),
             });
-            if let Err(err) = state
-                .kafka_producer
-                .send(
-                    FutureRecord::to("pos.order")
-                        .payload(&pos_evt.to_string())
-                        .key(&tenant_id.to_string()),
-                    Duration::from_secs(0),
-                )
-                .await
+            if let Err(err) = common_kafka::publish(
+                &state.kafka_producer,
+                "pos.order",
+                &order.id.to_string(),
+                &pos_evt.to_string(),
+            )
+            .await
             {
                 tracing::error!("Failed to send pos.order: {:?}", err);
             }
@@ -1130,26 +1205,56 @@ pub async fn create_order(
             )
             .await;
     }
+    reveal_customer_pii(state.pii_key.as_deref(), &mut order);
     Ok(Json(order))
 }
 
 pub async fn void_order(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext, // bearer token for releasing the inventory reservation
     Path(order_id): Path<Uuid>,
     Json(req): Json<VoidOrderRequest>,
 ) -> Result<Json<Order>, ApiError> {
-    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager)) {
-    return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: None });
-    }
-    let tenant_id = sec.tenant_id;
+    ensure_capability(&sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id: sec.trace_id })?;
+    let reason_code = crate::order_voids::parse_void_reason_code(req.reason_code.as_deref(), sec.trace_id)?;
+    let decision = VoidDecision {
+        request_id: None,
+        requested_by: sec.actor.id,
+        approval_method: "direct",
+        reason_code,
+        note: trimmed_note(req.reason.as_deref()),
+    };
+    let order = execute_void(&state, &sec, &auth.token, order_id, decision).await?;
+    Ok(Json(order))
+}
 
-    let void_reason = req
-        .reason
-        .as_ref()
-        .map(|value| value.trim())
-        .filter(|value| !value.is_empty())
-        .map(|value| value.to_string());
+pub(crate) fn trimmed_note(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+/// Who asked for a void and how it was approved; `sec` passed alongside is the approver.
+pub(crate) struct VoidDecision {
+    /// Pending request being approved; `None` records a direct manager void.
+    pub request_id: Option<Uuid>,
+    pub requested_by: Option<Uuid>,
+    pub approval_method: &'static str,
+    pub reason_code: &'static str,
+    pub note: Option<String>,
+}
+
+/// Void a PENDING order: void the payment upstream, flip the status, record the decision in
+/// `order_void_requests`, release the inventory reservation and publish `order.voided`.
+pub(crate) async fn execute_void(
+    state: &AppState,
+    sec: &common_security::SecurityContext,
+    auth_token: &str,
+    order_id: Uuid,
+    decision: VoidDecision,
+) -> Result<Order, ApiError> {
+    let tenant_id = sec.tenant_id;
+    let void_reason = decision.note.clone();
 
     let existing = sqlx::query_as::<_, OrderStatusSnapshot>(
     "SELECT status, payment_method, customer_id, offline, total FROM orders WHERE id = $1 AND tenant_id = $2",
@@ -1182,60 +1287,103 @@ pub async fn void_order(
         return Err(ApiError::BadRequest { code: "upstream_error", trace_id: None, message: Some("Unable to void payment with upstream provider".into()) });
     }
 
-    let updated_order = sqlx::query_as::<_, Order>(
-        "UPDATE orders SET status = 'VOIDED', void_reason = $3 WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'
-         RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key",
+    let mut tx = state.db.begin().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to begin void transaction: {}", e)) })?;
+    let mut updated_order = sqlx::query_as::<_, Order>(
+        "UPDATE orders SET status = 'VOIDED', void_reason = $3, void_reason_code = $4, voided_by = $5 WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'
+         RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date",
     )
     .bind(order_id)
     .bind(tenant_id)
     .bind(void_reason.as_deref())
-    .fetch_optional(&state.db)
+    .bind(decision.reason_code)
+    .bind(sec.actor.id)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to void order: {}", e)) })?
     .ok_or(ApiError::BadRequest { code: "status_changed", trace_id: None, message: Some("Order status changed before void could be applied".into()) })?;
 
+    let recorded = match decision.request_id {
+        Some(request_id) => sqlx::query(
+            "UPDATE order_void_requests SET status = 'APPROVED', decided_by = $3, approval_method = $4, decided_at = NOW() WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'",
+        )
+        .bind(request_id)
+        .bind(tenant_id)
+        .bind(sec.actor.id)
+        .bind(decision.approval_method)
+        .execute(&mut *tx)
+        .await,
+        None => sqlx::query(
+            "INSERT INTO order_void_requests (id, tenant_id, order_id, requested_by, reason_code, note, status, decided_by, approval_method, decided_at)
+             VALUES ($1, $2, $3, $4, $5, $6, 'APPROVED', $7, $8, NOW())",
+        )
+        .bind(Uuid::new_v4())
+        .bind(tenant_id)
+        .bind(order_id)
+        .bind(decision.requested_by)
+        .bind(decision.reason_code)
+        .bind(void_reason.as_deref())
+        .bind(sec.actor.id)
+        .bind(decision.approval_method)
+        .execute(&mut *tx)
+        .await,
+    };
+    match recorded {
+        Ok(result) if result.rows_affected() == 1 => {}
+        Ok(_) => return Err(ApiError::Conflict { code: "void_request_decided", trace_id: sec.trace_id, message: Some("Void request was already decided".into()) }),
+        Err(e) => return Err(ApiError::Internal { trace_id: None, message: Some(format!("Failed to record void decision: {}", e)) }),
+    }
+    tx.commit().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to commit void: {}", e)) })?;
+
+    if let Err(err) = release_inventory(&state.http_client, &state.inventory_base_url, tenant_id, auth_token, order_id).await {
+        tracing::error!(order_id = %order_id, tenant_id = %tenant_id, ?err, "Failed to release inventory after void");
+    }
+    crate::app::record_order_void(tenant_id, decision.requested_by, decision.reason_code);
+
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     let item_rows = sqlx::query_as::<_, OrderItemFinancialRow>(
-        "SELECT product_id, quantity, unit_price, line_total FROM order_items WHERE order_id = $1",
+        "SELECT product_id, quantity, unit_price, line_total, measured_quantity FROM order_items WHERE order_id = $1",
     )
     .bind(order_id)
     .fetch_all(&state.db)
     .await
     .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load order items: {}", e)) })?;
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event_items: Vec<serde_json::Value> = item_rows
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event_items: Vec<OrderEventItem> = item_rows
         .into_iter()
-        .map(|row| {
-            serde_json::json!({
-                "product_id": row.product_id,
-                "quantity": row.quantity,
-                "unit_price": row.unit_price,
-                "line_total": row.line_total,
-            })
+        .map(|row| OrderEventItem {
+            product_id: row.product_id,
+            quantity: row.quantity,
+            unit_price: row.unit_price,
+            line_total: row.line_total,
+            measured_quantity: row.measured_quantity,
         })
         .collect();
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let order_void_event = serde_json::json!({
-        "order_id": updated_order.id,
-        "tenant_id": tenant_id,
-        "items": event_items,
-        "total": updated_order.total,
-        "customer_id": updated_order.customer_id,
-        "offline": updated_order.offline,
-        "payment_method": updated_order.payment_method,
-        "reason": void_reason,
-    });
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let order_void_event = OrderVoidedEvent {
+        schema_version: OrderVoidedEvent::SCHEMA_VERSION,
+        order_id: updated_order.id,
+        tenant_id,
+        items: event_items,
+        total: updated_order.total.clone().into(),
+        customer_id: updated_order.customer_id,
+        offline: updated_order.offline,
+        payment_method: updated_order.payment_method.clone(),
+        reason: void_reason.clone(),
+        reason_code: Some(decision.reason_code.to_string()),
+        requested_by: decision.requested_by,
+        approved_by: sec.actor.id,
+        approval_method: Some(decision.approval_method.to_string()),
+        business_date: Some(updated_order.business_date),
+    };
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    if let Err(err) = state
-        .kafka_producer
-        .send(
-            FutureRecord::to("order.voided")
-                .payload(&order_void_event.to_string())
-                .key(&tenant_id.to_string()),
-            Duration::from_secs(0),
-        )
-        .await
+    if let Err(err) = common_kafka::publish_event(
+        &state.kafka_producer,
+        &state.db,
+        order_void_event.tenant_id,
+        &order_void_event,
+    )
+    .await
     {
         tracing::error!("Failed to send order.voided: {:?}", err);
     }
@@ -1245,7 +1393,9 @@ pub async fn void_order(
         let changes = json!({
             "order_id": updated_order.id,
             "before": {"status": "PENDING"},
-            "after": {"status": "VOIDED", "reason": void_reason},
+            "after": {"status": "VOIDED", "reason": void_reason, "reason_code": decision.reason_code},
+            "requested_by": decision.requested_by,
+            "approval_method": decision.approval_method,
         });
         let _ = audit
             .emit(
@@ -1262,7 +1412,8 @@ pub async fn void_order(
             )
             .await;
     }
-    Ok(Json(updated_order))
+    reveal_customer_pii(state.pii_key.as_deref(), &mut updated_order);
+    Ok(updated_order)
 }
 
 pub async fn refund_order(
@@ -1300,7 +1451,7 @@ pub async fn refund_order(
     };
 
     match order_snapshot.status.as_str() {
-        "PENDING" | "VOIDED" | "NOT_ACCEPTED" => {
+        "PENDING" | "CONDITIONALLY_APPROVED" | "VOIDED" | "NOT_ACCEPTED" => {
             return Err(ApiError::BadRequest { code: "invalid_status", trace_id: None, message: Some(format!("Order in status '{}' cannot be refunded", order_snapshot.status)) });
         }
         _ => {}
@@ -1321,6 +1472,11 @@ pub async fn refund_order(
         let store_id: Option<Uuid> = row.try_get("store_id").ok();
         (created_at, store_id)
     };
+    // Refunds are booked on the current business day at the order's store.
+    let refund_business_date = crate::day_close::business_date_at(&state.db, tenant_id, order_store_id, Utc::now())
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to resolve business date: {e}")) })?;
+    crate::day_close::ensure_day_open(&state.db, tenant_id, order_store_id, refund_business_date, sec.trace_id).await?;
 
     // Fetch return policy (location-specific, else tenant default)
     #[derive(sqlx::FromRow)]
@@ -1384,7 +1540,7 @@ pub async fn refund_order(
             .await
             .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
         sqlx::query(
-            "SELECT id, product_id, quantity, returned_quantity, unit_price FROM order_items WHERE order_id = $1 FOR UPDATE",
+            "SELECT id, product_id, quantity, returned_quantity, unit_price, line_total, measured_quantity FROM order_items WHERE order_id = $1 FOR UPDATE",
         )
         .bind(req.order_id)
         .fetch_all(&mut *conn)
@@ -1403,6 +1559,7 @@ pub async fn refund_order(
         quantity: i32,
     unit_price: BigDecimal,
     line_total: BigDecimal,
+        measured_quantity: Option<BigDecimal>,
     }
 
     struct DbItem {
@@ -1410,6 +1567,8 @@ pub async fn refund_order(
         quantity: i32,
         returned_quantity: i32,
     unit_price: BigDecimal,
+        line_total: BigDecimal,
+        measured_quantity: Option<BigDecimal>,
     }
 
     let mut items_map: HashMap<Uuid, DbItem> = HashMap::new();
@@ -1419,6 +1578,8 @@ pub async fn refund_order(
         let quantity: i32 = row.try_get("quantity").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item quantity: {}", e)) })?;
         let returned_quantity: i32 = row.try_get("returned_quantity").unwrap_or(0);
     let unit_price: BigDecimal = row.try_get("unit_price").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item unit price: {}", e)) })?;
+        let line_total: BigDecimal = row.try_get("line_total").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item line total: {}", e)) })?;
+        let measured_quantity: Option<BigDecimal> = row.try_get("measured_quantity").unwrap_or(None);
 
         items_map.insert(
             product_id,
@@ -1427,6 +1588,8 @@ pub async fn refund_order(
                 quantity,
                 returned_quantity,
                 unit_price,
+                line_total,
+                measured_quantity,
             },
         );
     }
@@ -1451,7 +1614,11 @@ pub async fn refund_order(
         }
 
         entry.returned_quantity += request_item.quantity;
-        let line_total = &entry.unit_price * BigDecimal::from(request_item.quantity);
+        // A weighed line is one scale reading priced per kg/lb, so it refunds at its recorded total.
+        let line_total = match entry.measured_quantity {
+            Some(_) => entry.line_total.clone(),
+            None => &entry.unit_price * BigDecimal::from(request_item.quantity),
+        };
         refund_total += line_total.clone();
         updates.push(PendingUpdate {
             order_item_id: entry.order_item_id,
@@ -1459,6 +1626,7 @@ pub async fn refund_order(
             quantity: request_item.quantity,
             unit_price: entry.unit_price.clone(),
             line_total: line_total.clone(),
+            measured_quantity: entry.measured_quantity.clone(),
         });
     }
 
@@ -1469,13 +1637,12 @@ pub async fn refund_order(
     // Apply restock fee
     if policy.restock_fee_bps > 0 {
         // refund_total := refund_total * (1 - bps/10000)
-        let fee = BigDecimal::from(policy.restock_fee_bps);
-        let discounted = &refund_total * (BigDecimal::from(10000) - fee) / BigDecimal::from(10000);
-        refund_total = discounted;
+        let rounding = resolve_rounding_policy(&state.db, tenant_id).await.context();
+        refund_total = rounding.percent(&Money::from(refund_total), 10_000 - policy.restock_fee_bps).into();
     }
 
     if let Some(client_total) = req.total.clone() {
-        if !nearly_equal(&client_total, &refund_total, 1) {
+        if !nearly_equal(&client_total, &refund_total, Cents(1)) {
             tracing::warn!(
                 order_id = %req.order_id,
                 tenant_id = %tenant_id,
@@ -1518,13 +1685,14 @@ pub async fn refund_order(
             .await
             .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
         sqlx::query(
-            "INSERT INTO order_returns (id, order_id, tenant_id, total, reason) VALUES ($1, $2, $3, $4, $5)",
+            "INSERT INTO order_returns (id, order_id, tenant_id, total, reason, business_date) VALUES ($1, $2, $3, $4, $5, $6)",
         )
         .bind(return_id)
         .bind(req.order_id)
         .bind(tenant_id)
         .bind(&refund_total)
         .bind(reason_text.as_deref())
+        .bind(refund_business_date)
         .execute(&mut *conn)
         .await
         .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to record order return: {}", e)) })?;
@@ -1557,14 +1725,14 @@ pub async fn refund_order(
         "PARTIAL_REFUNDED"
     };
 
-    let updated_order = {
+    let mut updated_order = {
         let conn = tx
             .acquire()
             .await
             .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to acquire transaction connection: {e}")) })?;
         sqlx::query_as::<_, Order>(
             "UPDATE orders SET status = $3 WHERE id = $1 AND tenant_id = $2
-             RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key",
+             RETURNING id, tenant_id, total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date",
         )
         .bind(req.order_id)
         .bind(tenant_id)
@@ -1576,39 +1744,42 @@ pub async fn refund_order(
 
     tx.commit().await.map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to commit refund transaction: {}", e)) })?;
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event_items: Vec<serde_json::Value> = updates
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let event_items: Vec<OrderEventItem> = updates
         .iter()
-        .map(|update| {
-            serde_json::json!({
-                "product_id": update.product_id,
-                "quantity": -update.quantity,
-                "unit_price": update.unit_price,
-                "line_total": update.line_total.clone() * BigDecimal::from(-1),
-            })
+        .map(|update| OrderEventItem {
+            product_id: update.product_id,
+            quantity: -update.quantity,
+            unit_price: update.unit_price.clone(),
+            line_total: update.line_total.clone() * BigDecimal::from(-1),
+            measured_quantity: update.measured_quantity.as_ref().map(|m| -m),
         })
         .collect();
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let refund_event = serde_json::json!({
-        "order_id": updated_order.id,
-        "tenant_id": tenant_id,
-        "items": event_items,
-    "total": (&refund_total * BigDecimal::from(-1)),
-        "customer_id": updated_order.customer_id,
-        "offline": updated_order.offline,
-        "payment_method": updated_order.payment_method,
-        "return_id": return_id,
-    });
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let refund_event = OrderCompletedEvent {
+        schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+        order_id: updated_order.id,
+        tenant_id,
+        items: event_items,
+        total: &refund_total * BigDecimal::from(-1),
+        customer_id: updated_order.customer_id,
+        offline: updated_order.offline,
+        payment_method: updated_order.payment_method.clone(),
+        return_id: Some(return_id),
+        location_id: order_store_id,
+        employee_id: sec.actor.id,
+        rma_id: None,
+        exchange_return_id: None,
+        business_date: Some(refund_business_date),
+    };
 
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    if let Err(err) = state
-        .kafka_producer
-        .send(
-            FutureRecord::to("order.completed")
-                .payload(&refund_event.to_string())
-                .key(&tenant_id.to_string()),
-            Duration::from_secs(0),
-        )
-        .await
+    if let Err(err) = common_kafka::publish_event(
+        &state.kafka_producer,
+        &state.db,
+        refund_event.tenant_id,
+        &refund_event,
+    )
+    .await
     {
         tracing::error!("Failed to send order.completed (refund): {:?}", err);
     }
@@ -1636,47 +1807,34 @@ pub async fn refund_order(
             )
             .await;
     }
+    reveal_customer_pii(state.pii_key.as_deref(), &mut updated_order);
     Ok(Json(updated_order))
 }
 
-pub async fn list_orders(
-    State(state): State<AppState>,
-    SecurityCtxExtractor(sec): SecurityCtxExtractor,
-    Query(params): Query<ListOrdersParams>,
-) -> Result<Json<Vec<Order>>, ApiError> {
-    if !sec
-        .roles
-        .iter()
-        .any(|r| matches!(r, Role::Admin | Role::Manager | Role::Support))
-    {
-    return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: None });
-    }
-    let tenant_id = sec.tenant_id;
-
-    let limit = params.limit.unwrap_or(50).clamp(1, 200);
-    let offset = params.offset.unwrap_or(0).max(0);
-
-    let sort_field = match params.sort.as_deref() {
-        Some("total") => "total",
-        Some("status") => "status",
-        Some("payment_method") => "payment_method",
-        Some("customer") | Some("customer_name") => "customer_name",
-        Some("customer_email") => "customer_email",
-        Some("store_id") => "store_id",
-        Some("created_at") => "created_at",
-        _ => "created_at",
-    };
-
-    let sort_direction = match params.direction.as_ref() {
-        Some(dir) if dir.eq_ignore_ascii_case("asc") || dir.eq_ignore_ascii_case("ascending") => {
-            "ASC"
-        }
-        _ => "DESC",
-    };
+const ORDER_LIST_SELECT: &str =
+    "SELECT id, tenant_id, total AS total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date FROM orders WHERE tenant_id = ";
+
+static ORDER_SORT: SortSpec = SortSpec {
+    fields: &[
+        SortField::new("created_at", "created_at", "timestamptz"),
+        SortField::new("total", "total", "numeric"),
+        SortField::new("status", "status", "text"),
+        SortField::new("payment_method", "payment_method", "text"),
+        SortField::new("customer", "COALESCE(customer_name, '')", "text"),
+        SortField::new("customer_name", "COALESCE(customer_name, '')", "text"),
+        SortField::new("store_id", "COALESCE(store_id, '00000000-0000-0000-0000-000000000000'::uuid)", "uuid"),
+    ],
+    default_field: "created_at",
+    default_direction: SortDirection::Desc,
+    tiebreak: "id",
+};
 
-    let mut builder = QueryBuilder::new(
-    "SELECT id, tenant_id, total AS total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key FROM orders WHERE tenant_id = "
-    );
+fn push_order_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    state: &AppState,
+    tenant_id: Uuid,
+    params: &ListOrdersParams,
+) -> Result<(), ApiError> {
     builder.push_bind(tenant_id);
 
     if let Some(order_id) = params.order_id {
@@ -1721,10 +1879,23 @@ pub async fn list_orders(
         .filter(|value| !value.is_empty())
     {
         let pattern = format!("%{}%", customer_term);
-        builder.push(" AND (COALESCE(customer_name, ') ILIKE ");
+        builder.push(" AND (COALESCE(customer_name, '') ILIKE ");
         builder.push_bind(pattern.clone());
-        builder.push(" OR COALESCE(customer_email, ') ILIKE ");
+        // Rows written before migration 2014 may still hold a plaintext email.
+        builder.push(" OR COALESCE(customer_email, '') ILIKE ");
         builder.push_bind(pattern);
+        // Encrypted names and emails only support exact (case-insensitive) matches, under any
+        // key that may still have sealed a row.
+        if let (Some(keys), Some(term)) = (state.pii_key.as_deref(), normalize_email(customer_term)) {
+            let hashes = keys
+                .search_hashes(tenant_id, &term)
+                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to hash customer details: {e}")) })?;
+            builder.push(" OR customer_name_hash = ANY(");
+            builder.push_bind(hashes.clone());
+            builder.push(") OR customer_email_hash = ANY(");
+            builder.push_bind(hashes);
+            builder.push(")");
+        }
         builder.push(")");
     }
 
@@ -1744,6 +1915,46 @@ pub async fn list_orders(
         }
     }
 
+    if let (Some(min), Some(max)) = (&params.min_total, &params.max_total) {
+        if min > max {
+            return Err(ApiError::BadRequest { code: "invalid_amount_range", trace_id: None, message: Some("min_total must not exceed max_total".into()) });
+        }
+    }
+    if let Some(min_total) = &params.min_total {
+        builder.push(" AND total >= ");
+        builder.push_bind(min_total.clone());
+    }
+    if let Some(max_total) = &params.max_total {
+        builder.push(" AND total <= ");
+        builder.push_bind(max_total.clone());
+    }
+
+    if let Some(terminal_id) = params.terminal_id {
+        builder.push(" AND pos_instance_id = ");
+        builder.push_bind(terminal_id);
+    }
+
+    if let Some(receipt) = params
+        .receipt
+        .as_ref()
+        .map(|value| value.trim().trim_start_matches('#').to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+    {
+        if let Ok(order_id) = Uuid::parse_str(&receipt) {
+            builder.push(" AND id = ");
+            builder.push_bind(order_id);
+        } else if let Some((first, last)) = receipt_prefix_range(&receipt) {
+            // Prefix match on the printed id, e.g. the first block of a faded receipt, as an id
+            // range so the primary key index serves it.
+            builder.push(" AND id BETWEEN ");
+            builder.push_bind(first);
+            builder.push(" AND ");
+            builder.push_bind(last);
+        } else {
+            return Err(ApiError::BadRequest { code: "invalid_receipt", trace_id: None, message: Some("receipt must be an order id or a prefix of at least 8 characters".into()) });
+        }
+    }
+
     if let Some(term) = params
         .q
         .as_ref()
@@ -1758,29 +1969,28 @@ pub async fn list_orders(
         builder.push(")");
     }
 
-    builder.push(" ORDER BY ");
-    builder.push(sort_field);
-    builder.push(" ");
-    builder.push(sort_direction);
-    builder.push(" LIMIT ");
-    builder.push_bind(limit);
-    builder.push(" OFFSET ");
-    builder.push_bind(offset);
-
-    let orders = builder
-        .build_query_as::<Order>()
-        .fetch_all(&state.db)
-        .await
-        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Database error: {}", e)) })?;
+    Ok(())
+}
 
-    Ok(Json(orders))
+/// Lowest and highest order ids starting with a printed receipt prefix of 8 to 32 hex digits
+/// (hyphens ignored). Postgres orders uuids bytewise, so the ids sharing the prefix are exactly
+/// this range.
+fn receipt_prefix_range(prefix: &str) -> Option<(Uuid, Uuid)> {
+    let hex: String = prefix.chars().filter(|c| *c != '-').collect();
+    if !(8..=32).contains(&hex.len()) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let first = Uuid::parse_str(&format!("{hex:0<32}")).ok()?;
+    let last = Uuid::parse_str(&format!("{hex:f<32}")).ok()?;
+    Some((first, last))
 }
 
-pub async fn list_returns(
+pub async fn list_orders(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
-    Query(params): Query<ListReturnsParams>,
-) -> Result<Json<Vec<ReturnSummary>>, ApiError> {
+    Query(params): Query<ListOrdersParams>,
+    page: PageRequest,
+) -> Result<Json<Listing<Order>>, ApiError> {
     if !sec
         .roles
         .iter()
@@ -1789,16 +1999,69 @@ pub async fn list_returns(
     return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: None });
     }
     let tenant_id = sec.tenant_id;
+    let plan = page.resolve(&ORDER_SORT, sec.trace_id)?;
+
+    let mut builder = QueryBuilder::new(ORDER_LIST_SELECT);
+    push_order_filters(&mut builder, &state, tenant_id, &params)?;
+    plan.push_cursor_filter(&mut builder);
+    plan.push_order_by(&mut builder);
+    if plan.is_paginated() {
+        plan.push_limit(&mut builder);
+    } else {
+        // Legacy offset paging for clients that have not moved to cursors.
+        builder.push(" LIMIT ");
+        builder.push_bind(pagination::checked_limit(params.limit, sec.trace_id)?);
+        builder.push(" OFFSET ");
+        builder.push_bind(params.offset.unwrap_or(0).max(0));
+    }
+
+    let mut orders = builder
+        .build_query_as::<Order>()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Database error: {}", e)) })?;
+    for order in &mut orders {
+        reveal_customer_pii(state.pii_key.as_deref(), order);
+    }
 
-    let limit = params.limit.unwrap_or(50).clamp(1, 200);
-    let offset = params.offset.unwrap_or(0).max(0);
+    let total_estimate = if plan.is_paginated() {
+        let mut estimate = pagination::estimate_query(ORDER_LIST_SELECT);
+        push_order_filters(&mut estimate, &state, tenant_id, &params)?;
+        Some(
+            pagination::estimate_rows(&state.db, estimate)
+                .await
+                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Database error: {}", e)) })?,
+        )
+    } else {
+        None
+    };
 
-    let mut builder = QueryBuilder::new(
-    "SELECT r.id, r.order_id, r.total AS total, r.reason, r.created_at, o.store_id \
+    Ok(Json(plan.finish(orders, total_estimate, |order, field| {
+        let value = match field {
+            "total" => order.total.to_string(),
+            "status" => order.status.clone(),
+            "payment_method" => order.payment_method.clone(),
+            "customer" | "customer_name" => order.customer_name.clone().unwrap_or_default(),
+            "store_id" => order.store_id.unwrap_or_default().to_string(),
+            _ => order.created_at.to_rfc3339(),
+        };
+        (value, order.id)
+    })))
+}
+
+const RETURN_LIST_SELECT: &str = "SELECT r.id, r.order_id, r.total AS total, r.reason, r.created_at, o.store_id \
          FROM order_returns r \
          JOIN orders o ON o.id = r.order_id \
-         WHERE r.tenant_id = ",
-    );
+         WHERE r.tenant_id = ";
+
+static RETURN_SORT: SortSpec = SortSpec {
+    fields: &[SortField::new("created_at", "r.created_at", "timestamptz")],
+    default_field: "created_at",
+    default_direction: SortDirection::Desc,
+    tiebreak: "r.id",
+};
+
+fn push_return_filters(builder: &mut QueryBuilder<'_, Postgres>, tenant_id: Uuid, params: &ListReturnsParams) {
     builder.push_bind(tenant_id);
 
     if let Some(order_id) = params.order_id {
@@ -1826,12 +2089,36 @@ pub async fn list_returns(
             builder.push_bind(end_dt);
         }
     }
+}
 
-    builder.push(" ORDER BY r.created_at DESC");
-    builder.push(" LIMIT ");
-    builder.push_bind(limit);
-    builder.push(" OFFSET ");
-    builder.push_bind(offset);
+pub async fn list_returns(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ListReturnsParams>,
+    page: PageRequest,
+) -> Result<Json<Listing<ReturnSummary>>, ApiError> {
+    if !sec
+        .roles
+        .iter()
+        .any(|r| matches!(r, Role::Admin | Role::Manager | Role::Support))
+    {
+    return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: None });
+    }
+    let tenant_id = sec.tenant_id;
+    let plan = page.resolve(&RETURN_SORT, sec.trace_id)?;
+
+    let mut builder = QueryBuilder::new(RETURN_LIST_SELECT);
+    push_return_filters(&mut builder, tenant_id, &params);
+    plan.push_cursor_filter(&mut builder);
+    plan.push_order_by(&mut builder);
+    if plan.is_paginated() {
+        plan.push_limit(&mut builder);
+    } else {
+        builder.push(" LIMIT ");
+        builder.push_bind(pagination::checked_limit(params.limit, sec.trace_id)?);
+        builder.push(" OFFSET ");
+        builder.push_bind(params.offset.unwrap_or(0).max(0));
+    }
 
     let raw_rows = builder
         .build()
@@ -1850,15 +2137,29 @@ pub async fn list_returns(
         returns.push(ReturnSummary { id, order_id, total: Money::new(total), reason, created_at, store_id });
     }
 
-    Ok(Json(returns))
+    let total_estimate = if plan.is_paginated() {
+        let mut estimate = pagination::estimate_query(RETURN_LIST_SELECT);
+        push_return_filters(&mut estimate, tenant_id, &params);
+        Some(
+            pagination::estimate_rows(&state.db, estimate)
+                .await
+                .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Database error: {}", e)) })?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Json(plan.finish(returns, total_estimate, |summary, _| {
+        (summary.created_at.to_rfc3339(), summary.id)
+    })))
 }
-async fn fetch_order_detail(
+pub(crate) async fn fetch_order_detail(
     state: &AppState,
     tenant_id: Uuid,
     order_id: Uuid,
 ) -> Result<OrderDetail, ApiError> {
-    let order = sqlx::query_as::<_, Order>(
-    "SELECT id, tenant_id, total, status, customer_id, customer_name, customer_email, store_id, created_at, offline, payment_method, idempotency_key FROM orders WHERE id = $1 AND tenant_id = $2",
+    let mut order = sqlx::query_as::<_, Order>(
+    "SELECT id, tenant_id, total, status, customer_id, customer_name, customer_email, customer_email_encrypted, customer_name_encrypted, pii_key_id, store_id, created_at, offline, payment_method, idempotency_key, rounding_adjustment, tip_amount, business_date FROM orders WHERE id = $1 AND tenant_id = $2",
     )
     .bind(order_id)
     .bind(tenant_id)
@@ -1866,9 +2167,10 @@ async fn fetch_order_detail(
     .await
     .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load order: {}", e)) })?
     .ok_or(ApiError::NotFound { code: "order_not_found", trace_id: None })?;
+    reveal_customer_pii(state.pii_key.as_deref(), &mut order);
 
     let item_rows = sqlx::query(
-    "SELECT product_id, product_name, quantity, returned_quantity, unit_price, line_total FROM order_items WHERE order_id = $1 ORDER BY created_at"
+    "SELECT id, product_id, product_name, quantity, returned_quantity, unit_price, line_total, original_unit_price, price_override_reason, measured_quantity, uom, modifiers FROM order_items WHERE order_id = $1 ORDER BY created_at"
     )
     .bind(order_id)
     .fetch_all(&state.db)
@@ -1878,19 +2180,31 @@ async fn fetch_order_detail(
     let items = item_rows
         .into_iter()
         .map(|row| {
+            let id: Uuid = row.try_get("id").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item id: {}", e)) })?;
             let product_id: Uuid = row.try_get("product_id").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item product: {}", e)) })?;
             let product_name: Option<String> = row.try_get("product_name").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item product name: {}", e)) })?;
             let quantity: i32 = row.try_get("quantity").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item quantity: {}", e)) })?;
             let returned_quantity: i32 = row.try_get("returned_quantity").unwrap_or(0);
             let unit_price: BigDecimal = row.try_get("unit_price").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item unit price: {}", e)) })?;
             let line_total: BigDecimal = row.try_get("line_total").map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to read order item line total: {}", e)) })?;
+            let original_unit_price: Option<BigDecimal> = row.try_get("original_unit_price").unwrap_or(None);
+            let price_override_reason: Option<String> = row.try_get("price_override_reason").unwrap_or(None);
+            let measured_quantity: Option<BigDecimal> = row.try_get("measured_quantity").unwrap_or(None);
+            let uom: Option<String> = row.try_get("uom").unwrap_or(None);
+            let modifiers = row.try_get::<sqlx::types::Json<Vec<AppliedModifier>>, _>("modifiers").map(|m| m.0).unwrap_or_default();
             Ok(OrderLineItem {
+                id,
                 product_id,
                 product_name,
                 quantity,
                 unit_price: Money::new(unit_price),
                 line_total: Money::new(line_total),
                 returned_quantity,
+                original_unit_price: original_unit_price.map(Money::new),
+                price_override_reason,
+                measured_quantity,
+                uom,
+                modifiers,
             })
         })
     .collect::<Result<Vec<_>, ApiError>>()?;
@@ -1932,6 +2246,9 @@ pub async fn get_order_receipt(
     }
     let tenant_id = sec.tenant_id;
     let detail = fetch_order_detail(&state, tenant_id, order_id).await?;
+    if q.format.as_deref().is_some_and(|f| f.eq_ignore_ascii_case("kitchen")) {
+        return Ok((StatusCode::OK, [(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"))], kitchen_ticket(&detail)).into_response());
+    }
 
     // Derive financials for receipt: subtotal, discount (residual), tax, total
     let subtotal_cents: i64 = detail
@@ -1963,11 +2280,10 @@ pub async fn get_order_receipt(
             }
         }
     }
-    let rate_bps = default_tax_rate_bps() as i64;
-    let estimated_tax_cents = if taxable_subtotal_cents > 0 && rate_bps > 0 {
-        (taxable_subtotal_cents.saturating_mul(rate_bps) + 5_000) / 10_000
-    } else { 0 };
-    let total_cents = detail.order.total.as_cents();
+    let rounding = resolve_rounding_policy(&state.db, tenant_id).await.context();
+    let estimated_tax_cents = rounding.percent(&Money::from_cents(taxable_subtotal_cents.max(0)), default_tax_rate_bps()).as_cents();
+    let rounding_adjustment_cents = detail.order.rounding_adjustment.as_cents();
+    let total_cents = detail.order.total.as_cents() - rounding_adjustment_cents;
     let mut discount_cents = subtotal_cents.saturating_add(estimated_tax_cents).saturating_sub(total_cents);
     if discount_cents < 0 { discount_cents = 0; }
 
@@ -1984,14 +2300,20 @@ pub async fn get_order_receipt(
         body.push_str("-------------------------------------\n");
         writeln!(&mut body, "Qty  {:8} {:>7} {:>6}", "SKU", "Price", "Line").ok();
         for item in &detail.items {
-            let name_or_sku = item.product_name.as_deref().unwrap_or("SKU");
-            writeln!(&mut body, "{:<4} {:8} {:>7} {:>6}", item.quantity, name_or_sku, format!("{:.2}", item.unit_price), format!("{:.2}", item.line_total)).ok();
+            let name_or_sku = item.display_name("SKU");
+            writeln!(&mut body, "{:<4} {:8} {:>7} {:>6}", item.display_quantity(), name_or_sku, format!("{:.2}", item.unit_price), format!("{:.2}", item.line_total)).ok();
         }
         body.push_str("-------------------------------------\n");
         writeln!(&mut body, "Subtotal:         ${:.2}", Money::from_cents(subtotal_cents)).ok();
         writeln!(&mut body, "Discount:         ${:.2}", Money::from_cents(discount_cents)).ok();
         writeln!(&mut body, "Tax:              ${:.2}", Money::from_cents(estimated_tax_cents)).ok();
+        if rounding_adjustment_cents != 0 {
+            writeln!(&mut body, "Cash rounding:    ${:.2}", detail.order.rounding_adjustment).ok();
+        }
         writeln!(&mut body, "Total:            ${:.2}", detail.order.total).ok();
+        if detail.order.tip_amount.as_cents() != 0 {
+            writeln!(&mut body, "Tip:              ${:.2}", detail.order.tip_amount).ok();
+        }
         // Try to fetch payment to show tendered and change (best-effort; ignore errors)
         match sqlx::query(
             r#"SELECT method, amount::FLOAT8 as amount, change_cents FROM payments WHERE order_id = $1 ORDER BY created_at DESC LIMIT 1"#
@@ -2016,7 +2338,7 @@ pub async fn get_order_receipt(
                 }
             }
             Ok(None) | Err(_) => {
-                writeln!(&mut body, "Paid ({}):        ${:.2}", detail.order.payment_method, detail.order.total).ok();
+                writeln!(&mut body, "Paid ({}):        ${:.2}", detail.order.payment_method, detail.order.total.clone() + detail.order.tip_amount.clone()).ok();
             }
         }
         body.push_str("-------------------------------------\nThank you!\n");
@@ -2055,10 +2377,10 @@ pub async fn get_order_receipt(
     body.push('\n');
     body.push_str("| Item | Qty | Price | Total |\n| --- | ---: | ---: | ---: |\n");
     for item in &detail.items {
-        let name = item.product_name.as_deref().unwrap_or("Item");
+        let name = item.display_name("Item");
         body.push_str(&format!(
             "| {} | {} | ${:.2} | ${:.2} |\n",
-            name, item.quantity, item.unit_price, item.line_total
+            name, item.display_quantity(), item.unit_price, item.line_total
         ));
     }
 
@@ -2066,7 +2388,13 @@ pub async fn get_order_receipt(
         body.push_str(&format!("**Subtotal:** ${:.2}\n", Money::from_cents(subtotal_cents)));
         body.push_str(&format!("**Discount:** ${:.2}\n", Money::from_cents(discount_cents)));
         body.push_str(&format!("**Tax:** ${:.2}\n", Money::from_cents(estimated_tax_cents)));
+        if rounding_adjustment_cents != 0 {
+            body.push_str(&format!("**Cash Rounding:** ${:.2}\n", detail.order.rounding_adjustment));
+        }
         body.push_str(&format!("**Grand Total:** ${:.2}\n", detail.order.total));
+        if detail.order.tip_amount.as_cents() != 0 {
+            body.push_str(&format!("**Tip:** ${:.2}\n", detail.order.tip_amount));
+        }
         body.push_str(&format!(
             "**Payment Method:** {}\n",
             detail.order.payment_method
@@ -2094,6 +2422,14 @@ pub struct ComputeOrderItemInput {
     #[serde(default)]
     pub product_id: Option<Uuid>,
     pub quantity: i32,
+    /// Gross scale reading and optional tare for products sold by weight.
+    #[serde(default)]
+    pub weight: Option<BigDecimal>,
+    #[serde(default)]
+    pub tare: Option<BigDecimal>,
+    /// Menu modifier options chosen on the line; their deltas are added to the unit price.
+    #[serde(default)]
+    pub modifier_ids: Vec<Uuid>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -2110,6 +2446,9 @@ pub struct ComputeOrderRequest {
     /// Explicit override for tax rate if caller already resolved
     #[serde(default)]
     pub tax_rate_bps: Option<i32>,
+    /// Tender the totals are for; cash rounding only applies to `cash` (the default).
+    #[serde(default)]
+    pub payment_method: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -2121,6 +2460,8 @@ pub struct ComputedItemSummary {
     pub unit_price_cents: i64,
     pub line_subtotal_cents: i64,
     #[serde(skip_serializing_if = "Option::is_none")] pub tax_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub measured_quantity: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub modifiers: Vec<AppliedModifier>,
 }
 
 #[derive(Serialize, Debug)]
@@ -2129,6 +2470,9 @@ pub struct ComputeOrderResponse {
     pub subtotal_cents: i64,
     pub discount_cents: i64,
     pub tax_cents: i64,
+    /// Cash rounding applied to reach `total_cents` under the tenant rounding policy (0 when none
+    /// or when the tender is not cash).
+    pub rounding_adjustment_cents: i64,
     pub total_cents: i64,
 }
 
@@ -2142,9 +2486,12 @@ fn default_tax_rate_bps() -> i32 {
         .unwrap_or(0)
 }
 
-fn is_taxable(tax_code: Option<&str>) -> bool {
+/// Product tax codes that are never taxed; any other code (or none) is taxed at the resolved rate.
+pub(crate) const NON_TAXABLE_TAX_CODES: &[&str] = &["EXEMPT", "ZERO", "NONE"];
+
+pub(crate) fn is_taxable(tax_code: Option<&str>) -> bool {
     match tax_code.map(|s| s.to_ascii_uppercase()) {
-        Some(code) if code == "EXEMPT" || code == "ZERO" || code == "NONE" => false,
+        Some(code) if NON_TAXABLE_TAX_CODES.contains(&code.as_str()) => false,
         _ => true, // treat STD or missing as taxable
     }
 }
@@ -2250,6 +2597,83 @@ pub async fn upsert_tax_rate_override(
     Ok(Json(row))
 }
 
+// --- Admin: Rounding policy ---
+#[derive(Serialize, Debug)]
+pub struct RoundingPolicyView {
+    pub tenant_id: Uuid,
+    pub mode: &'static str,
+    pub cash_increment_cents: u32,
+    /// False when the tenant has no stored policy and the global default applies.
+    pub configured: bool,
+}
+
+pub async fn get_rounding_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<RoundingPolicyView>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: None });
+    }
+    let tenant_id = sec.tenant_id;
+    let configured = sqlx::query_scalar::<_, i32>("SELECT 1 FROM rounding_policies WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load rounding policy: {}", e)) })?
+        .is_some();
+    let policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    Ok(Json(RoundingPolicyView {
+        tenant_id,
+        mode: policy.mode.as_str(),
+        cash_increment_cents: policy.cash_increment_cents.unwrap_or(0),
+        configured,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct UpsertRoundingPolicyRequest {
+    pub mode: String,
+    #[serde(default)]
+    pub cash_increment_cents: u32,
+}
+
+pub async fn upsert_rounding_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<UpsertRoundingPolicyRequest>,
+) -> Result<Json<RoundingPolicyView>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin", trace_id: None });
+    }
+    let tenant_id = sec.tenant_id;
+    let mode = RoundingMode::parse(&req.mode).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_rounding_mode",
+        trace_id: None,
+        message: Some("mode must be one of half-up, truncate, bankers".into()),
+    })?;
+    if req.cash_increment_cents > 100 {
+        return Err(ApiError::BadRequest { code: "invalid_cash_increment", trace_id: None, message: Some("cash_increment_cents must be between 0 and 100".into()) });
+    }
+    let policy = RoundingPolicy::new(mode).with_cash_increment(req.cash_increment_cents);
+    sqlx::query(
+        "INSERT INTO rounding_policies (tenant_id, mode, cash_increment_cents) VALUES ($1, $2, $3)
+         ON CONFLICT (tenant_id) DO UPDATE SET mode = EXCLUDED.mode, cash_increment_cents = EXCLUDED.cash_increment_cents, updated_at = NOW()"
+    )
+    .bind(tenant_id)
+    .bind(policy.mode.as_str())
+    .bind(policy.cash_increment_cents.unwrap_or(0) as i32)
+    .execute(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to upsert rounding policy: {}", e)) })?;
+
+    Ok(Json(RoundingPolicyView {
+        tenant_id,
+        mode: policy.mode.as_str(),
+        cash_increment_cents: policy.cash_increment_cents.unwrap_or(0),
+        configured: true,
+    }))
+}
+
 // --- Admin: Return policies CRUD (MVP stub) ---
 #[derive(Serialize, sqlx::FromRow, Clone)]
 pub struct ReturnPolicyRow {
@@ -2350,8 +2774,8 @@ pub async fn upsert_return_policy(
     )
     .bind(tenant_id)
     .bind(req.location_id)
-    .bind(req.allow_window_days.max(0).min(3650))
-    .bind(req.restock_fee_bps.max(0).min(10000))
+    .bind(req.allow_window_days.clamp(0, 3650))
+    .bind(req.restock_fee_bps.clamp(0, 10000))
     .bind(req.receipt_required)
     .bind(req.manager_override_allowed)
     .fetch_one(&state.db).await
@@ -2400,6 +2824,59 @@ pub async fn issue_return_override(
     Ok(Json(serde_json::json!({"override_token": id})))
 }
 
+/// Order-level amounts derived from line subtotals.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct PricedTotals {
+    pub discount_cents: i64,
+    pub tax_cents: i64,
+    pub rounding_adjustment_cents: i64,
+    pub total_cents: i64,
+}
+
+/// Apply a cart discount, tax and, for cash tenders, cash rounding to line subtotals. The
+/// discount is allocated to the taxable portion pro rata (rounded half up) so tax is charged on
+/// the net amount.
+pub(crate) fn price_totals(
+    policy: &RoundingPolicy,
+    subtotal_cents: i64,
+    taxable_subtotal_cents: i64,
+    discount_bps: i32,
+    rate_bps: i32,
+    tender: &str,
+) -> PricedTotals {
+    let rounding = policy.context();
+    // A net-negative cart (returns outweighing sales) gets no discount rather than a negative one.
+    let discount_cents = rounding.percent(&Money::from_cents(subtotal_cents.max(0)), discount_bps).as_cents();
+
+    let mut tax_cents = 0i64;
+    if taxable_subtotal_cents > 0 {
+        let discount_on_taxable = if subtotal_cents > 0 && discount_cents > 0 {
+            (discount_cents.saturating_mul(taxable_subtotal_cents) + (subtotal_cents / 2)) / subtotal_cents
+        } else { 0 };
+        let taxable_net_cents = taxable_subtotal_cents.saturating_sub(discount_on_taxable).max(0);
+        tax_cents = rounding.percent(&Money::from_cents(taxable_net_cents), rate_bps).as_cents();
+    }
+
+    let exact_total_cents = subtotal_cents.saturating_sub(discount_cents).saturating_add(tax_cents);
+    let (total_cents, rounding_adjustment_cents) = tender_total_cents(policy, tender, exact_total_cents);
+    PricedTotals { discount_cents, tax_cents, rounding_adjustment_cents, total_cents }
+}
+
+/// Whether a tender is settled in cash and therefore subject to the tenant's cash rounding. Card
+/// and other electronic tenders are charged the exact amount.
+pub(crate) fn is_cash_tender(method: &str) -> bool {
+    method.trim().eq_ignore_ascii_case("cash")
+}
+
+/// Amount due for `method` given the exact total, and the cash rounding adjustment included in it.
+pub(crate) fn tender_total_cents(policy: &RoundingPolicy, method: &str, exact_total_cents: i64) -> (i64, i64) {
+    if !is_cash_tender(method) {
+        return (exact_total_cents, 0);
+    }
+    let total_cents = policy.cash_round_cents(exact_total_cents);
+    (total_cents, total_cents - exact_total_cents)
+}
+
 async fn compute_with_db_inner(
     db: &sqlx::PgPool,
     tenant_id: Uuid,
@@ -2428,7 +2905,7 @@ async fn compute_with_db_inner(
 
     // Fetch products by SKU and by ID
     #[derive(sqlx::FromRow)]
-    struct ProductRow { id: Uuid, name: String, price: BigDecimal, sku: Option<String>, tax_code: Option<String>, active: bool }
+    struct ProductRow { id: Uuid, name: String, price: BigDecimal, sku: Option<String>, tax_code: Option<String>, active: bool, uom: String, tare_weight: Option<BigDecimal> }
 
     use std::collections::HashMap as Map;
     let mut by_sku: Map<String, ProductRow> = Map::new();
@@ -2436,7 +2913,7 @@ async fn compute_with_db_inner(
 
     if !want_skus.is_empty() {
         let rows = sqlx::query_as::<_, ProductRow>(
-            "SELECT id, name, price, sku, tax_code, active FROM products WHERE tenant_id = $1 AND sku = ANY($2)"
+            "SELECT id, name, price, sku, tax_code, active, uom, tare_weight FROM products WHERE tenant_id = $1 AND sku = ANY($2) AND deleted_at IS NULL"
         )
         .bind(tenant_id)
         .bind(&want_skus)
@@ -2447,7 +2924,7 @@ async fn compute_with_db_inner(
     }
     if !want_ids.is_empty() {
         let rows = sqlx::query_as::<_, ProductRow>(
-            "SELECT id, name, price, sku, tax_code, active FROM products WHERE tenant_id = $1 AND id = ANY($2)"
+            "SELECT id, name, price, sku, tax_code, active, uom, tare_weight FROM products WHERE tenant_id = $1 AND id = ANY($2) AND deleted_at IS NULL"
         )
         .bind(tenant_id)
         .bind(&want_ids)
@@ -2457,6 +2934,11 @@ async fn compute_with_db_inner(
         for r in rows { by_id.insert(r.id, r); }
     }
 
+    let product_ids: Vec<Uuid> = by_sku.values().chain(by_id.values()).map(|r| r.id).collect();
+    let modifier_rules = fetch_modifier_rules(db, tenant_id, &product_ids).await?;
+    let policy = resolve_rounding_policy(db, tenant_id).await;
+    let rounding = policy.context();
+
     // Build computed item list preserving input order
     let mut items: Vec<ComputedItemSummary> = Vec::with_capacity(req.items.len());
     let mut subtotal_cents: i64 = 0;
@@ -2472,9 +2954,13 @@ async fn compute_with_db_inner(
         if !row.active {
             return Err(ApiError::BadRequest { code: "inactive_product", trace_id: None, message: Some(format!("Product {} is inactive", row.id)) });
         }
-        let unit_cents = Money::new(row.price.clone()).as_cents();
-        let qty = it.quantity as i64;
-        let line_subtotal_cents = unit_cents.saturating_mul(qty);
+        let modifiers = resolve_line(&modifier_rules, row.id, &it.modifier_ids)?;
+        let unit_cents = modified_unit_cents(Money::new(row.price.clone()).as_cents(), &modifiers)?;
+        let measure = weighed_line(it.quantity, it.weight.as_ref(), it.tare.as_ref(), row.id, &row.uom, row.tare_weight.as_ref())?;
+        let line_subtotal_cents = match &measure {
+            Some((net, _, _)) => extend_price(&Money::from_cents(unit_cents), net, &rounding).as_cents(),
+            None => unit_cents.saturating_mul(it.quantity as i64),
+        };
         subtotal_cents = subtotal_cents.saturating_add(line_subtotal_cents);
         if is_taxable(row.tax_code.as_deref()) { taxable_subtotal_cents = taxable_subtotal_cents.saturating_add(line_subtotal_cents); }
         items.push(ComputedItemSummary {
@@ -2485,37 +2971,27 @@ async fn compute_with_db_inner(
             unit_price_cents: unit_cents,
             line_subtotal_cents,
             tax_code: row.tax_code.clone(),
+            measured_quantity: measure.map(|(net, _, _)| net),
+            modifiers,
         });
     }
 
-    // Compute discount
-    let discount_bps = clamp_bps(req.discount_percent_bp.unwrap_or(0));
-    let discount_cents = if subtotal_cents > 0 && discount_bps > 0 {
-        // round half up: add half denominator before integer division
-        (subtotal_cents.saturating_mul(discount_bps as i64) + 5_000) / 10_000
-    } else { 0 };
-
-    // Allocate discount proportion applicable to taxable portion
-    let mut tax_cents = 0i64;
-    if taxable_subtotal_cents > 0 {
-        let discount_on_taxable = if subtotal_cents > 0 && discount_cents > 0 {
-            (discount_cents.saturating_mul(taxable_subtotal_cents) + (subtotal_cents / 2)) / subtotal_cents
-        } else { 0 };
-        let taxable_net_cents = taxable_subtotal_cents.saturating_sub(discount_on_taxable).max(0);
-        let rate_bps = resolve_tax_rate_bps_with_db(
+    let rate_bps = if taxable_subtotal_cents > 0 {
+        resolve_tax_rate_bps_with_db(
             db,
             tenant_id,
             headers,
             req.tax_rate_bps,
             req.location_id,
             req.pos_instance_id,
-        ).await as i64;
-        tax_cents = (taxable_net_cents.saturating_mul(rate_bps) + 5_000) / 10_000;
-    }
-
-    let total_cents = subtotal_cents.saturating_sub(discount_cents).saturating_add(tax_cents);
+        ).await
+    } else { 0 };
+    let discount_bps = clamp_bps(req.discount_percent_bp.unwrap_or(0));
+    let tender = req.payment_method.as_deref().unwrap_or("cash");
+    let PricedTotals { discount_cents, tax_cents, rounding_adjustment_cents, total_cents } =
+        price_totals(&policy, subtotal_cents, taxable_subtotal_cents, discount_bps, rate_bps, tender);
 
-    Ok(ComputeOrderResponse { items, subtotal_cents, discount_cents, tax_cents, total_cents })
+    Ok(ComputeOrderResponse { items, subtotal_cents, discount_cents, tax_cents, rounding_adjustment_cents, total_cents })
 }
 
 pub async fn compute_order(
@@ -2565,7 +3041,27 @@ mod integration_tests {
               price numeric NOT NULL,
               sku text,
               tax_code text,
-              active boolean NOT NULL DEFAULT true
+              active boolean NOT NULL DEFAULT true,
+              uom text NOT NULL DEFAULT 'each',
+              tare_weight numeric(12,3),
+              deleted_at timestamptz
+            );
+            CREATE TABLE IF NOT EXISTS product_modifier_groups (
+              id uuid PRIMARY KEY,
+              tenant_id uuid NOT NULL,
+              product_id uuid NOT NULL,
+              name text NOT NULL,
+              min_select int NOT NULL DEFAULT 0,
+              max_select int NOT NULL,
+              position int NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS product_modifiers (
+              id uuid PRIMARY KEY,
+              tenant_id uuid NOT NULL,
+              group_id uuid NOT NULL,
+              name text NOT NULL,
+              price_delta numeric(12,2) NOT NULL DEFAULT 0,
+              position int NOT NULL DEFAULT 0
             );
             "#
         ).await;
@@ -2584,8 +3080,8 @@ mod integration_tests {
         // Build request and headers
         let req = ComputeOrderRequest {
             items: vec![
-                ComputeOrderItemInput { sku: Some("SKU-SODA".into()), product_id: None, quantity: 2 },
-                ComputeOrderItemInput { sku: Some("SKU-WATER".into()), product_id: None, quantity: 1 },
+                ComputeOrderItemInput { sku: Some("SKU-SODA".into()), product_id: None, quantity: 2, weight: None, tare: None, modifier_ids: Vec::new() },
+                ComputeOrderItemInput { sku: Some("SKU-WATER".into()), product_id: None, quantity: 1, weight: None, tare: None, modifier_ids: Vec::new() },
             ],
             discount_percent_bp: Some(1000),
             location_id: None,
@@ -2634,7 +3130,27 @@ mod integration_tests {
               price numeric NOT NULL,
               sku text,
               tax_code text,
-              active boolean NOT NULL DEFAULT true
+              active boolean NOT NULL DEFAULT true,
+              uom text NOT NULL DEFAULT 'each',
+              tare_weight numeric(12,3),
+              deleted_at timestamptz
+            );
+            CREATE TABLE IF NOT EXISTS product_modifier_groups (
+              id uuid PRIMARY KEY,
+              tenant_id uuid NOT NULL,
+              product_id uuid NOT NULL,
+              name text NOT NULL,
+              min_select int NOT NULL DEFAULT 0,
+              max_select int NOT NULL,
+              position int NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS product_modifiers (
+              id uuid PRIMARY KEY,
+              tenant_id uuid NOT NULL,
+              group_id uuid NOT NULL,
+              name text NOT NULL,
+              price_delta numeric(12,2) NOT NULL DEFAULT 0,
+              position int NOT NULL DEFAULT 0
             );
             "#
         ).await;
@@ -2677,8 +3193,8 @@ mod integration_tests {
             .execute(&pool).await.expect("insert pos override");
 
         let base_items = vec![
-            ComputeOrderItemInput { sku: Some("SKU-SODA".into()), product_id: None, quantity: 2 },
-            ComputeOrderItemInput { sku: Some("SKU-WATER".into()), product_id: None, quantity: 1 },
+            ComputeOrderItemInput { sku: Some("SKU-SODA".into()), product_id: None, quantity: 2, weight: None, tare: None, modifier_ids: Vec::new() },
+            ComputeOrderItemInput { sku: Some("SKU-WATER".into()), product_id: None, quantity: 1, weight: None, tare: None, modifier_ids: Vec::new() },
         ];
 
         // Case 1: tenant only (expect tax 25)
@@ -2723,9 +3239,54 @@ pub async fn clear_offline_orders(
     }))
 }
 
+/// Net quantity, unit and tare for a line sold by weight; `None` for counted products.
+/// Weighed lines are a single scale reading, so `quantity` must be 1.
+/// Modifier groups of the products on an order, read from product-service's tables.
+pub(crate) async fn fetch_modifier_rules(db: &sqlx::PgPool, tenant_id: Uuid, product_ids: &[Uuid]) -> Result<ModifierRules, ApiError> {
+    load_modifier_rules(db, tenant_id, product_ids)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("Failed to load modifier groups: {}", e)) })
+}
+
+fn weighed_line(
+    quantity: i32,
+    weight: Option<&BigDecimal>,
+    tare: Option<&BigDecimal>,
+    product_id: Uuid,
+    uom: &str,
+    product_tare: Option<&BigDecimal>,
+) -> Result<Option<(BigDecimal, UnitOfMeasure, Option<BigDecimal>)>, ApiError> {
+    let uom = UnitOfMeasure::parse(uom).unwrap_or(UnitOfMeasure::Each);
+    if !uom.is_measured() {
+        if weight.is_some() || tare.is_some() {
+            return Err(ApiError::BadRequest { code: "weight_not_applicable", trace_id: None, message: Some(format!("Product {} is sold by the unit", product_id)) });
+        }
+        return Ok(None);
+    }
+    let gross = weight.ok_or(ApiError::BadRequest { code: "weight_required", trace_id: None, message: Some(format!("Product {} is sold by {} and needs a scale reading", product_id, uom.as_str())) })?;
+    if quantity != 1 {
+        return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id: None, message: Some("Weighed items are sold one scale reading per line".into()) });
+    }
+    let tare = tare.or(product_tare).map(normalize_measure);
+    let net = net_measure(gross, tare.as_ref()).ok_or(ApiError::BadRequest { code: "invalid_weight", trace_id: None, message: Some("Net weight after tare must be positive".into()) })?;
+    Ok(Some((net, uom, tare)))
+}
+
 // --- Create order from SKUs: resolve items and compute totals server-side ---
 #[derive(Deserialize, Debug)]
-pub struct NewOrderSkuItem { pub sku: String, pub quantity: i32 }
+pub struct NewOrderSkuItem {
+    pub sku: String,
+    pub quantity: i32,
+    /// Serial/lot capture for tracked products; forwarded to the inventory reservation.
+    #[serde(default)] pub serial_numbers: Vec<String>,
+    #[serde(default)] pub lot_code: Option<String>,
+    /// Gross scale reading for products sold by weight (`quantity` must then be 1).
+    #[serde(default)] pub weight: Option<BigDecimal>,
+    /// Container weight to subtract; defaults to the product's configured tare.
+    #[serde(default)] pub tare: Option<BigDecimal>,
+    /// Menu modifier options chosen on the line; their deltas are added to the unit price.
+    #[serde(default)] pub modifier_ids: Vec<Uuid>,
+}
 
 #[derive(Deserialize, Debug)]
 pub struct NewOrderFromSku {
@@ -2742,6 +3303,8 @@ pub struct NewOrderFromSku {
     pub store_id: Option<Uuid>,
     pub offline: Option<bool>,
     pub idempotency_key: Option<String>,
+    #[serde(default)] pub tip_cents: i64,
+    #[serde(skip)] pub exchange: Option<ExchangeCredit>,
 }
 
 pub async fn create_order_from_skus(
@@ -2769,9 +3332,9 @@ pub async fn create_order_from_skus(
     }
 
     #[derive(sqlx::FromRow)]
-    struct ProductRow { id: Uuid, name: String, price: BigDecimal, sku: Option<String>, tax_code: Option<String>, active: bool }
+    struct ProductRow { id: Uuid, name: String, price: BigDecimal, sku: Option<String>, tax_code: Option<String>, active: bool, uom: String, tare_weight: Option<BigDecimal> }
     let rows = sqlx::query_as::<_, ProductRow>(
-        "SELECT id, name, price, sku, tax_code, active FROM products WHERE tenant_id = $1 AND sku = ANY($2)"
+        "SELECT id, name, price, sku, tax_code, active, uom, tare_weight FROM products WHERE tenant_id = $1 AND sku = ANY($2) AND deleted_at IS NULL"
     )
     .bind(tenant_id)
     .bind(&want_skus)
@@ -2782,6 +3345,11 @@ pub async fn create_order_from_skus(
     let mut by_sku: Map<String, ProductRow> = Map::new();
     for r in rows { if let Some(s) = r.sku.clone() { by_sku.insert(s, r); } }
 
+    let product_ids: Vec<Uuid> = by_sku.values().map(|r| r.id).collect();
+    let modifier_rules = fetch_modifier_rules(&state.db, tenant_id, &product_ids).await?;
+    let policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    let rounding = policy.context();
+
     // Build order items and compute totals
     let mut order_items: Vec<OrderItem> = Vec::with_capacity(req.items.len());
     let mut subtotal_cents: i64 = 0;
@@ -2790,9 +3358,13 @@ pub async fn create_order_from_skus(
         let s = it.sku.trim();
         let r = by_sku.get(s).ok_or(ApiError::NotFound { code: "product_not_found", trace_id: None })?;
         if !r.active { return Err(ApiError::BadRequest { code: "inactive_product", trace_id: None, message: Some(format!("Product {} is inactive", r.id)) }); }
-        let unit_cents = Money::new(r.price.clone()).as_cents();
-        let qty = it.quantity as i64;
-        let line_subtotal = unit_cents.saturating_mul(qty);
+        let modifiers = resolve_line(&modifier_rules, r.id, &it.modifier_ids)?;
+        let unit_cents = modified_unit_cents(Money::new(r.price.clone()).as_cents(), &modifiers)?;
+        let measure = weighed_line(it.quantity, it.weight.as_ref(), it.tare.as_ref(), r.id, &r.uom, r.tare_weight.as_ref())?;
+        let line_subtotal = match &measure {
+            Some((net, _, _)) => extend_price(&Money::from_cents(unit_cents), net, &rounding).as_cents(),
+            None => unit_cents.saturating_mul(it.quantity as i64),
+        };
         subtotal_cents = subtotal_cents.saturating_add(line_subtotal);
         if is_taxable(r.tax_code.as_deref()) { taxable_subtotal_cents = taxable_subtotal_cents.saturating_add(line_subtotal); }
         order_items.push(OrderItem {
@@ -2801,13 +3373,18 @@ pub async fn create_order_from_skus(
             quantity: it.quantity,
             unit_price: Money::from_cents(unit_cents),
             line_total: Money::from_cents(line_subtotal),
+            serial_numbers: it.serial_numbers.clone(),
+            lot_code: it.lot_code.clone(),
+            measured_quantity: measure.as_ref().map(|(net, _, _)| net.clone()),
+            uom: measure.as_ref().map(|(_, uom, _)| uom.as_str().to_string()),
+            tare_weight: measure.and_then(|(_, _, tare)| tare),
+            modifier_ids: it.modifier_ids.clone(),
+            modifiers,
         });
     }
 
     let discount_bps = req.discount_percent_bp.unwrap_or(0).clamp(0, 10_000);
-    let discount_cents = if subtotal_cents > 0 && discount_bps > 0 {
-        (subtotal_cents.saturating_mul(discount_bps as i64) + 5_000) / 10_000
-    } else { 0 };
+    let discount_cents = rounding.percent(&Money::from_cents(subtotal_cents.max(0)), discount_bps).as_cents();
     let discount_on_taxable = if subtotal_cents > 0 && discount_cents > 0 {
         (discount_cents.saturating_mul(taxable_subtotal_cents) + (subtotal_cents / 2)) / subtotal_cents
     } else { 0 };
@@ -2819,8 +3396,9 @@ pub async fn create_order_from_skus(
         req.tax_rate_bps,
         req.location_id,
         req.pos_instance_id,
-    ).await as i64;
-    let tax_cents = (taxable_net.saturating_mul(tax_rate_bps) + 5_000) / 10_000;
+    ).await;
+    let tax_cents = rounding.percent(&Money::from_cents(taxable_net), tax_rate_bps).as_cents();
+    // Exact total; create_order applies cash rounding when the tender is cash.
     let total_cents = subtotal_cents.saturating_sub(discount_cents).saturating_add(tax_cents);
 
     // Construct a NewOrder and delegate to existing create_order by reusing its persistence path
@@ -2835,8 +3413,148 @@ pub async fn create_order_from_skus(
         store_id: req.store_id,
         offline: req.offline,
         idempotency_key: req.idempotency_key,
+        pos_instance_id: req.pos_instance_id,
+        tip_cents: req.tip_cents,
+        exchange: req.exchange,
     };
 
     // Call inner create_order logic directly instead of HTTP roundtrip
     create_order(State(state), SecurityCtxExtractor(sec), auth, Json(new_order)).await
 }
+
+/// Tables bundled into a tenant offboarding export; each query binds the tenant id as `$1`.
+const TENANT_EXPORT_QUERIES: &[(&str, &str)] = &[
+    ("orders", "SELECT * FROM orders WHERE tenant_id = $1 ORDER BY created_at"),
+    (
+        "order_items",
+        "SELECT oi.* FROM order_items oi JOIN orders o ON o.id = oi.order_id WHERE o.tenant_id = $1",
+    ),
+    ("order_returns", "SELECT * FROM order_returns WHERE tenant_id = $1 ORDER BY created_at"),
+    ("order_line_edits", "SELECT * FROM order_line_edits WHERE tenant_id = $1 ORDER BY created_at"),
+    ("order_void_requests", "SELECT * FROM order_void_requests WHERE tenant_id = $1 ORDER BY created_at"),
+    (
+        "order_return_items",
+        "SELECT ri.* FROM order_return_items ri JOIN order_returns r ON r.id = ri.return_id WHERE r.tenant_id = $1",
+    ),
+    ("payments", "SELECT * FROM payments WHERE tenant_id = $1 ORDER BY created_at"),
+    ("tax_rate_overrides", "SELECT * FROM tax_rate_overrides WHERE tenant_id = $1"),
+    ("rounding_policies", "SELECT * FROM rounding_policies WHERE tenant_id = $1"),
+    ("tip_settings", "SELECT * FROM tip_settings WHERE tenant_id = $1"),
+    ("return_policies", "SELECT * FROM return_policies WHERE tenant_id = $1"),
+];
+
+/// Offboarding export pulled by auth-service's tenant export job.
+pub async fn export_tenant_data(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    ensure_capability(&sec, Capability::GdprManage)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "gdpr_manage", trace_id: sec.trace_id })?;
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let mut tables = serde_json::Map::new();
+    for (name, query) in TENANT_EXPORT_QUERIES {
+        let rows: String = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM ({query}) t"
+        ))
+        .bind(tenant_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let mut rows = serde_json::from_str(&rows).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        if *name == "orders" {
+            decrypt_exported_pii(&state, tenant_id, &mut rows)
+                .await
+                .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        }
+        tables.insert((*name).to_string(), rows);
+    }
+    Ok(Json(serde_json::Value::Object(tables)))
+}
+
+/// Replace encrypted name and email columns in exported order rows with the decrypted values, so
+/// the export is usable without the service key.
+async fn decrypt_exported_pii(
+    state: &AppState,
+    tenant_id: Uuid,
+    rows: &mut serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    type SealedRow = (Uuid, Option<String>, Option<EncryptedColumn<String>>, Option<EncryptedColumn<String>>);
+    let sealed: Vec<SealedRow> = sqlx::query_as(
+        "SELECT id, pii_key_id, customer_name_encrypted, customer_email_encrypted FROM orders
+         WHERE tenant_id = $1 AND (customer_name_encrypted IS NOT NULL OR customer_email_encrypted IS NOT NULL)",
+    )
+    .bind(tenant_id)
+    .fetch_all(&state.db)
+    .await?;
+    let mut revealed: HashMap<String, Vec<(&str, String)>> = HashMap::with_capacity(sealed.len());
+    if let Some(keys) = state.pii_key.as_deref() {
+        for (id, key_id, name, email) in sealed {
+            for (column, field, value) in [
+                ("customer_name", crate::pii::CUSTOMER_NAME_FIELD, name),
+                ("customer_email", crate::pii::CUSTOMER_EMAIL_FIELD, email),
+            ] {
+                let Some(value) = value else { continue };
+                match keys.open(tenant_id, key_id.as_deref(), field, &value) {
+                    Ok(plain) => revealed.entry(id.to_string()).or_default().push((column, plain)),
+                    Err(err) => tracing::warn!(order_id = %id, field, error = %err, "Failed to decrypt customer details for export"),
+                }
+            }
+        }
+    }
+    for row in rows.as_array_mut().into_iter().flatten() {
+        let Some(obj) = row.as_object_mut() else { continue };
+        for column in ["customer_name_encrypted", "customer_name_hash", "customer_email_encrypted", "customer_email_hash", "pii_key_id"] {
+            obj.remove(column);
+        }
+        let id = obj.get("id").and_then(|v| v.as_str()).map(str::to_string);
+        for (column, plain) in id.and_then(|id| revealed.remove(&id)).into_iter().flatten() {
+            obj.insert(column.into(), serde_json::Value::String(plain));
+        }
+    }
+    Ok(())
+}
+
+/// Provisioning step run by auth-service when a tenant is created: seeds the tenant-wide tax
+/// rate from `DEFAULT_TAX_RATE_BPS` so admins can see and adjust it. A rate that is already set,
+/// including one an admin changed, is reported back and left alone.
+pub async fn provision_tenant(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if !sec.roles.contains(&Role::SuperAdmin) {
+        return Err(ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id });
+    }
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let seeded = sqlx::query(
+        "INSERT INTO tax_rate_overrides (tenant_id, location_id, pos_instance_id, rate_bps)
+         SELECT $1, NULL, NULL, $2
+         WHERE NOT EXISTS (
+             SELECT 1 FROM tax_rate_overrides WHERE tenant_id = $1 AND location_id IS NULL AND pos_instance_id IS NULL
+         )
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(tenant_id)
+    .bind(default_tax_rate_bps())
+    .execute(&state.db)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?
+    .rows_affected()
+        > 0;
+    let rate_bps: i32 = sqlx::query_scalar(
+        "SELECT rate_bps FROM tax_rate_overrides WHERE tenant_id = $1 AND location_id IS NULL AND pos_instance_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+    )
+    .bind(tenant_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    Ok(Json(serde_json::json!({ "tax_rate": { "rate_bps": rate_bps, "seeded": seeded } })))
+}
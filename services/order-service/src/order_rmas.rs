@@ -0,0 +1,864 @@
+//! Return authorizations (RMAs).
+//!
+//! A manager authorizes a return against lines of a completed order, optionally with a
+//! restocking fee, and the customer has until `expires_at` to bring or ship the goods back. When
+//! they arrive the goods are inspected and each line gets a disposition: `restock` puts the units
+//! back on hand through inventory-service (`return_to_stock`), `damage` keeps them out of
+//! sellable stock. Receiving records the refund like `POST /orders/refund` does and publishes the
+//! refund `order.completed` with `rma_id`, which tells inventory-service not to restock it again.
+//! RMA reason codes back the per-product return reason report.
+
+use std::collections::HashMap;
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use common_auth::AuthContext; // bearer token propagated to inventory-service
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+use common_events::{DomainEvent, OrderCompletedEvent, OrderEventItem};
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::order_handlers::{inventory_url, resolve_rounding_policy, trimmed_note};
+use crate::AppState;
+
+/// Reason codes accepted on RMA lines; the return reason report groups by these.
+pub const RETURN_REASON_CODES: &[&str] = &[
+    "defective",
+    "damaged_in_transit",
+    "wrong_item",
+    "not_as_described",
+    "no_longer_needed",
+    "other",
+];
+
+/// Days the customer has to return the goods when the request does not say.
+const DEFAULT_RETURN_WINDOW_DAYS: i64 = 14;
+const MAX_RETURN_WINDOW_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Disposition {
+    /// Resaleable: back on hand at the order's store.
+    Restock,
+    /// Not resaleable: refunded but kept out of stock.
+    Damage,
+}
+
+impl Disposition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposition::Restock => "restock",
+            Disposition::Damage => "damage",
+        }
+    }
+}
+
+fn db_error(context: &'static str, trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) },
+    }
+}
+
+fn parse_reason_code(value: &str, trace_id: Option<Uuid>) -> Result<&'static str, ApiError> {
+    let value = value.trim();
+    RETURN_REASON_CODES.iter().copied().find(|code| *code == value).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_reason_code",
+        trace_id,
+        message: Some(format!("reason_code must be one of: {}", RETURN_REASON_CODES.join(", "))),
+    })
+}
+
+fn ensure_manager(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+/// Refund for `subtotal_cents` after a restocking fee of `fee_bps`, as `(refund, fee)` in cents.
+/// The fee is what the rounded refund leaves over, so the two always add up to the subtotal.
+pub fn apply_restocking_fee(policy: &common_money::RoundingPolicy, subtotal_cents: i64, fee_bps: i32) -> (i64, i64) {
+    if fee_bps <= 0 {
+        return (subtotal_cents, 0);
+    }
+    let refund = policy.context().percent(&Money::from_cents(subtotal_cents), 10_000 - fee_bps.min(10_000)).as_cents();
+    (refund, subtotal_cents - refund)
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ReturnAuthorizationItem {
+    pub id: Uuid,
+    pub order_item_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub reason_code: String,
+    pub unit_price: BigDecimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_quantity: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<String>,
+    /// True once inventory-service has put restocked units back on hand.
+    pub restocked: bool,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ReturnAuthorization {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub status: String,
+    pub restocking_fee_bps: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refund_total: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restocking_fee: Option<BigDecimal>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReturnAuthorizationView {
+    #[serde(flatten)]
+    pub rma: ReturnAuthorization,
+    /// Authorized but the return window has passed; it can no longer be received.
+    pub expired: bool,
+    /// Refund if every authorized unit comes back, after the restocking fee.
+    pub expected_refund: BigDecimal,
+    pub items: Vec<ReturnAuthorizationItem>,
+}
+
+const RMA_COLUMNS: &str = "id, order_id, status, restocking_fee_bps, note, expires_at, created_by, created_at, received_by, received_at, return_id, refund_total, restocking_fee";
+const RMA_ITEM_COLUMNS: &str = "id, order_item_id, product_id, quantity, reason_code, unit_price, received_quantity, disposition, restocked";
+
+async fn load_view(state: &AppState, tenant_id: Uuid, rma_id: Uuid, trace_id: Option<Uuid>) -> Result<ReturnAuthorizationView, ApiError> {
+    let rma = sqlx::query_as::<_, ReturnAuthorization>(&format!(
+        "SELECT {RMA_COLUMNS} FROM return_authorizations WHERE id = $1 AND tenant_id = $2"
+    ))
+    .bind(rma_id)
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to load return authorization", trace_id))?
+    .ok_or(ApiError::NotFound { code: "rma_not_found", trace_id })?;
+    let items = sqlx::query_as::<_, ReturnAuthorizationItem>(&format!(
+        "SELECT {RMA_ITEM_COLUMNS} FROM return_authorization_items WHERE rma_id = $1 ORDER BY id"
+    ))
+    .bind(rma_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to load return authorization items", trace_id))?;
+    let subtotal_cents: i64 = items
+        .iter()
+        .map(|item| Money::new(item.unit_price.clone()).as_cents().saturating_mul(item.quantity as i64))
+        .sum();
+    let policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    let (expected_cents, _) = apply_restocking_fee(&policy, subtotal_cents, rma.restocking_fee_bps);
+    Ok(ReturnAuthorizationView {
+        expired: rma.status == "AUTHORIZED" && rma.expires_at <= Utc::now(),
+        expected_refund: Money::from_cents(expected_cents).into(),
+        rma,
+        items,
+    })
+}
+
+/// Allowed return window and default restocking fee from the order store's return policy
+/// (falling back to the tenant default, then 30 days with no fee).
+async fn return_policy_for(db: &PgPool, tenant_id: Uuid, store_id: Option<Uuid>) -> (i32, i32) {
+    let row = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT allow_window_days, restock_fee_bps FROM return_policies
+         WHERE tenant_id = $1 AND (location_id IS NOT DISTINCT FROM $2 OR location_id IS NULL)
+         ORDER BY (location_id IS NULL), updated_at DESC LIMIT 1",
+    )
+    .bind(tenant_id)
+    .bind(store_id)
+    .fetch_optional(db)
+    .await;
+    match row {
+        Ok(Some(policy)) => policy,
+        _ => (30, 0),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RmaLineRequest {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub reason_code: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateRmaRequest {
+    pub order_id: Uuid,
+    pub items: Vec<RmaLineRequest>,
+    /// Defaults to the return policy's `restock_fee_bps`.
+    pub restocking_fee_bps: Option<i32>,
+    /// Days the customer has to return the goods (1-90, default 14).
+    pub return_window_days: Option<i64>,
+    pub note: Option<String>,
+}
+
+/// `POST /returns/authorizations`
+pub async fn create_return_authorization(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<CreateRmaRequest>,
+) -> Result<Json<ReturnAuthorizationView>, ApiError> {
+    ensure_manager(&sec)?;
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+    if req.items.is_empty() {
+        return Err(ApiError::BadRequest { code: "missing_items", trace_id, message: Some("A return authorization needs at least one item".into()) });
+    }
+    let window_days = req.return_window_days.unwrap_or(DEFAULT_RETURN_WINDOW_DAYS);
+    if !(1..=MAX_RETURN_WINDOW_DAYS).contains(&window_days) {
+        return Err(ApiError::BadRequest { code: "invalid_return_window", trace_id, message: Some(format!("return_window_days must be between 1 and {MAX_RETURN_WINDOW_DAYS}")) });
+    }
+    if req.restocking_fee_bps.is_some_and(|bps| !(0..=10_000).contains(&bps)) {
+        return Err(ApiError::BadRequest { code: "invalid_restocking_fee", trace_id, message: Some("restocking_fee_bps must be between 0 and 10000".into()) });
+    }
+    let mut lines: Vec<(Uuid, i32, &'static str)> = Vec::with_capacity(req.items.len());
+    for item in &req.items {
+        if item.quantity <= 0 {
+            return Err(ApiError::BadRequest { code: "invalid_quantity", trace_id, message: Some("Return quantities must be positive".into()) });
+        }
+        if lines.iter().any(|(product_id, _, _)| *product_id == item.product_id) {
+            return Err(ApiError::BadRequest { code: "duplicate_item", trace_id, message: Some(format!("Product {} is listed more than once", item.product_id)) });
+        }
+        lines.push((item.product_id, item.quantity, parse_reason_code(&item.reason_code, trace_id)?));
+    }
+
+    let mut tx = state.db.begin().await.map_err(db_error("Failed to begin return authorization", trace_id))?;
+    // The order lock serializes authorizations and refunds against the same lines.
+    let order = sqlx::query("SELECT status, store_id, created_at FROM orders WHERE id = $1 AND tenant_id = $2 FOR UPDATE")
+        .bind(req.order_id)
+        .bind(tenant_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error("Failed to load order", trace_id))?
+        .ok_or(ApiError::NotFound { code: "order_not_found", trace_id })?;
+    let status: String = order.try_get("status").map_err(db_error("Failed to read order status", trace_id))?;
+    let store_id: Option<Uuid> = order.try_get("store_id").unwrap_or(None);
+    let created_at: DateTime<Utc> = order.try_get("created_at").map_err(db_error("Failed to read order date", trace_id))?;
+    if !matches!(status.as_str(), "COMPLETED" | "PAID" | "PARTIAL_REFUNDED") {
+        return Err(ApiError::BadRequest { code: "invalid_status", trace_id, message: Some(format!("Order in status '{status}' cannot be returned")) });
+    }
+    let (allow_window_days, policy_fee_bps) = return_policy_for(&state.db, tenant_id, store_id).await;
+    if allow_window_days > 0 && Utc::now() - created_at > Duration::days(allow_window_days as i64) {
+        return Err(ApiError::BadRequest { code: "return_window_expired", trace_id, message: Some(format!("Return window of {allow_window_days} days has expired")) });
+    }
+
+    // Units still returnable per product: sold, minus already returned, minus open authorizations.
+    // Weighed lines are priced per kg/lb, so their unit credit is the reading's line total.
+    let rows = sqlx::query(
+        "SELECT oi.id, oi.product_id, oi.quantity, oi.returned_quantity,
+                CASE WHEN oi.measured_quantity IS NULL THEN oi.unit_price ELSE oi.line_total / oi.quantity END AS unit_price,
+                COALESCE((SELECT SUM(ri.quantity) FROM return_authorization_items ri
+                          JOIN return_authorizations r ON r.id = ri.rma_id
+                          WHERE ri.order_item_id = oi.id AND r.status = 'AUTHORIZED' AND r.expires_at > NOW()), 0)::INT AS authorized
+         FROM order_items oi WHERE oi.order_id = $1",
+    )
+    .bind(req.order_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error("Failed to load order items", trace_id))?;
+    let mut by_product: HashMap<Uuid, (Uuid, i32, BigDecimal)> = HashMap::new();
+    for row in rows {
+        let product_id: Uuid = row.try_get("product_id").map_err(db_error("Failed to read order item", trace_id))?;
+        let quantity: i32 = row.try_get("quantity").map_err(db_error("Failed to read order item", trace_id))?;
+        let returned: i32 = row.try_get("returned_quantity").unwrap_or(0);
+        let authorized: i32 = row.try_get("authorized").unwrap_or(0);
+        by_product.insert(product_id, (
+            row.try_get("id").map_err(db_error("Failed to read order item", trace_id))?,
+            quantity - returned - authorized,
+            row.try_get("unit_price").map_err(db_error("Failed to read order item", trace_id))?,
+        ));
+    }
+
+    let rma_id = Uuid::new_v4();
+    let restocking_fee_bps = req.restocking_fee_bps.unwrap_or(policy_fee_bps).clamp(0, 10_000);
+    sqlx::query(
+        "INSERT INTO return_authorizations (id, tenant_id, order_id, restocking_fee_bps, note, expires_at, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(rma_id)
+    .bind(tenant_id)
+    .bind(req.order_id)
+    .bind(restocking_fee_bps)
+    .bind(trimmed_note(req.note.as_deref()))
+    .bind(Utc::now() + Duration::days(window_days))
+    .bind(sec.actor.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error("Failed to create return authorization", trace_id))?;
+    for (product_id, quantity, reason_code) in lines {
+        let (order_item_id, available, unit_price) = by_product.get(&product_id).cloned().ok_or(ApiError::BadRequest {
+            code: "product_not_in_order",
+            trace_id,
+            message: Some(format!("Product {product_id} is not part of the order")),
+        })?;
+        if quantity > available {
+            return Err(ApiError::BadRequest {
+                code: "exceeds_available",
+                trace_id,
+                message: Some(format!("Cannot authorize {quantity} units of product {product_id}; only {} remain returnable", available.max(0))),
+            });
+        }
+        sqlx::query(
+            "INSERT INTO return_authorization_items (id, rma_id, tenant_id, order_item_id, product_id, quantity, reason_code, unit_price)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(rma_id)
+        .bind(tenant_id)
+        .bind(order_item_id)
+        .bind(product_id)
+        .bind(quantity)
+        .bind(reason_code)
+        .bind(&unit_price)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error("Failed to record return authorization item", trace_id))?;
+    }
+    tx.commit().await.map_err(db_error("Failed to commit return authorization", trace_id))?;
+
+    let view = load_view(&state, tenant_id, rma_id, trace_id).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    if let Some(audit) = &state.audit_producer {
+        let _ = audit
+            .emit(
+                tenant_id,
+                sec.actor.clone(),
+                "order",
+                Some(req.order_id),
+                "rma_authorized",
+                "order-service",
+                common_audit::AuditSeverity::Info,
+                trace_id,
+                json!({"rma_id": rma_id, "restocking_fee_bps": restocking_fee_bps, "expires_at": view.rma.expires_at, "expected_refund": view.expected_refund}),
+                json!({"source":"order-service"}),
+            )
+            .await;
+    }
+    Ok(Json(view))
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListRmasParams {
+    /// `AUTHORIZED`, `RECEIVED` or `CANCELLED`; all when omitted.
+    pub status: Option<String>,
+    pub order_id: Option<Uuid>,
+}
+
+/// `GET /returns/authorizations`
+pub async fn list_return_authorizations(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ListRmasParams>,
+) -> Result<Json<Vec<ReturnAuthorization>>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Inventory | Role::Support)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_inventory", trace_id: sec.trace_id });
+    }
+    let rows = sqlx::query_as::<_, ReturnAuthorization>(&format!(
+        "SELECT {RMA_COLUMNS} FROM return_authorizations
+         WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2) AND ($3::uuid IS NULL OR order_id = $3)
+         ORDER BY created_at DESC LIMIT 200"
+    ))
+    .bind(sec.tenant_id)
+    .bind(params.status.as_deref().map(str::to_ascii_uppercase))
+    .bind(params.order_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to list return authorizations", sec.trace_id))?;
+    Ok(Json(rows))
+}
+
+/// `GET /returns/authorizations/:rma_id`
+pub async fn get_return_authorization(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(rma_id): Path<Uuid>,
+) -> Result<Json<ReturnAuthorizationView>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Inventory | Role::Support)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_inventory", trace_id: sec.trace_id });
+    }
+    load_view(&state, sec.tenant_id, rma_id, sec.trace_id).await.map(Json)
+}
+
+/// `POST /returns/authorizations/:rma_id/cancel`: release the authorized units.
+pub async fn cancel_return_authorization(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(rma_id): Path<Uuid>,
+) -> Result<Json<ReturnAuthorizationView>, ApiError> {
+    ensure_manager(&sec)?;
+    let done = sqlx::query("UPDATE return_authorizations SET status = 'CANCELLED' WHERE id = $1 AND tenant_id = $2 AND status = 'AUTHORIZED'")
+        .bind(rma_id)
+        .bind(sec.tenant_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_error("Failed to cancel return authorization", sec.trace_id))?;
+    let view = load_view(&state, sec.tenant_id, rma_id, sec.trace_id).await?;
+    if done.rows_affected() == 0 {
+        return Err(ApiError::Conflict { code: "rma_closed", trace_id: sec.trace_id, message: Some(format!("Return authorization is already {}", view.rma.status)) });
+    }
+    Ok(Json(view))
+}
+
+#[derive(Deserialize)]
+pub struct ReceiveLineRequest {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub disposition: Disposition,
+}
+
+#[derive(Deserialize)]
+pub struct ReceiveRmaRequest {
+    /// Lines that arrived; authorized units not listed are released.
+    pub items: Vec<ReceiveLineRequest>,
+    pub note: Option<String>,
+}
+
+struct ReceivedLine {
+    rma_item_id: Uuid,
+    order_item_id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    unit_price: BigDecimal,
+    /// Scale reading of a weighed line; restocked and reported instead of `quantity`.
+    measured_quantity: Option<BigDecimal>,
+    disposition: Disposition,
+}
+
+impl ReceivedLine {
+    fn line_total_cents(&self) -> i64 {
+        Money::new(self.unit_price.clone()).as_cents().saturating_mul(self.quantity as i64)
+    }
+}
+
+#[derive(Serialize)]
+struct RestockPayload {
+    product_id: Uuid,
+    quantity: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    measured_quantity: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location_id: Option<Uuid>,
+    reason_code: &'static str,
+    note: String,
+}
+
+/// `POST /returns/authorizations/:rma_id/receive`: inspect the returned goods, refund them and
+/// restock the resaleable units.
+pub async fn receive_return_authorization(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    Path(rma_id): Path<Uuid>,
+    Json(req): Json<ReceiveRmaRequest>,
+) -> Result<Json<ReturnAuthorizationView>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Inventory)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_inventory", trace_id: sec.trace_id });
+    }
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+    if req.items.is_empty() {
+        return Err(ApiError::BadRequest { code: "missing_items", trace_id, message: Some("List the items that were received".into()) });
+    }
+
+    let mut tx = state.db.begin().await.map_err(db_error("Failed to begin return receipt", trace_id))?;
+    let rma = sqlx::query_as::<_, ReturnAuthorization>(&format!(
+        "SELECT {RMA_COLUMNS} FROM return_authorizations WHERE id = $1 AND tenant_id = $2 FOR UPDATE"
+    ))
+    .bind(rma_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error("Failed to load return authorization", trace_id))?
+    .ok_or(ApiError::NotFound { code: "rma_not_found", trace_id })?;
+    if rma.status != "AUTHORIZED" {
+        return Err(ApiError::Conflict { code: "rma_closed", trace_id, message: Some(format!("Return authorization is already {}", rma.status)) });
+    }
+    if rma.expires_at <= Utc::now() {
+        return Err(ApiError::Conflict { code: "rma_expired", trace_id, message: Some("The return window for this authorization has passed".into()) });
+    }
+    let order = sqlx::query("SELECT store_id, payment_method, customer_id, offline FROM orders WHERE id = $1 AND tenant_id = $2 FOR UPDATE")
+        .bind(rma.order_id)
+        .bind(tenant_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error("Failed to load order", trace_id))?;
+    let store_id: Option<Uuid> = order.try_get("store_id").unwrap_or(None);
+
+    let authorized = sqlx::query_as::<_, ReturnAuthorizationItem>(&format!(
+        "SELECT {RMA_ITEM_COLUMNS} FROM return_authorization_items WHERE rma_id = $1"
+    ))
+    .bind(rma_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error("Failed to load return authorization items", trace_id))?;
+    let measured: HashMap<Uuid, BigDecimal> = sqlx::query_as::<_, (Uuid, BigDecimal)>(
+        "SELECT id, measured_quantity FROM order_items WHERE order_id = $1 AND measured_quantity IS NOT NULL",
+    )
+    .bind(rma.order_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error("Failed to load order items", trace_id))?
+    .into_iter()
+    .collect();
+    let mut received: Vec<ReceivedLine> = Vec::with_capacity(req.items.len());
+    for line in &req.items {
+        let item = authorized.iter().find(|item| item.product_id == line.product_id).ok_or(ApiError::BadRequest {
+            code: "product_not_authorized",
+            trace_id,
+            message: Some(format!("Product {} is not on this return authorization", line.product_id)),
+        })?;
+        if received.iter().any(|r| r.product_id == line.product_id) {
+            return Err(ApiError::BadRequest { code: "duplicate_item", trace_id, message: Some(format!("Product {} is listed more than once", line.product_id)) });
+        }
+        if line.quantity <= 0 || line.quantity > item.quantity {
+            return Err(ApiError::BadRequest {
+                code: "invalid_quantity",
+                trace_id,
+                message: Some(format!("Received quantity for product {} must be between 1 and {}", line.product_id, item.quantity)),
+            });
+        }
+        received.push(ReceivedLine {
+            rma_item_id: item.id,
+            order_item_id: item.order_item_id,
+            product_id: item.product_id,
+            quantity: line.quantity,
+            unit_price: item.unit_price.clone(),
+            measured_quantity: measured.get(&item.order_item_id).cloned(),
+            disposition: line.disposition,
+        });
+    }
+
+    let policy = resolve_rounding_policy(&state.db, tenant_id).await;
+    let subtotal_cents: i64 = received.iter().map(ReceivedLine::line_total_cents).sum();
+    let (refund_cents, fee_cents) = apply_restocking_fee(&policy, subtotal_cents, rma.restocking_fee_bps);
+    let refund_total: BigDecimal = Money::from_cents(refund_cents).into();
+    let restocking_fee: BigDecimal = Money::from_cents(fee_cents).into();
+
+    let return_id = Uuid::new_v4();
+    let business_date = crate::day_close::business_date_at(&state.db, tenant_id, store_id, Utc::now())
+        .await
+        .map_err(db_error("Failed to resolve business date", trace_id))?;
+    sqlx::query("INSERT INTO order_returns (id, order_id, tenant_id, total, reason, business_date) VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(return_id)
+        .bind(rma.order_id)
+        .bind(tenant_id)
+        .bind(&refund_total)
+        .bind(format!("rma:{rma_id}"))
+        .bind(business_date)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error("Failed to record order return", trace_id))?;
+    for line in &received {
+        // A direct refund may have taken these units since the authorization was issued.
+        let updated = sqlx::query("UPDATE order_items SET returned_quantity = returned_quantity + $1 WHERE id = $2 AND returned_quantity + $1 <= quantity")
+            .bind(line.quantity)
+            .bind(line.order_item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("Failed to write return quantity", trace_id))?;
+        if updated.rows_affected() == 0 {
+            return Err(ApiError::Conflict {
+                code: "already_returned",
+                trace_id,
+                message: Some(format!("Units of product {} were already refunded outside this authorization", line.product_id)),
+            });
+        }
+        sqlx::query("INSERT INTO order_return_items (id, return_id, order_item_id, quantity, line_total) VALUES ($1, $2, $3, $4, $5)")
+            .bind(Uuid::new_v4())
+            .bind(return_id)
+            .bind(line.order_item_id)
+            .bind(line.quantity)
+            .bind(BigDecimal::from(Money::from_cents(line.line_total_cents())))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("Failed to record order return items", trace_id))?;
+        sqlx::query("UPDATE return_authorization_items SET received_quantity = $2, disposition = $3 WHERE id = $1")
+            .bind(line.rma_item_id)
+            .bind(line.quantity)
+            .bind(line.disposition.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("Failed to record inspection", trace_id))?;
+    }
+    let all_returned: bool = sqlx::query_scalar("SELECT COALESCE(bool_and(returned_quantity >= quantity), TRUE) FROM order_items WHERE order_id = $1")
+        .bind(rma.order_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error("Failed to check returned quantities", trace_id))?;
+    sqlx::query("UPDATE orders SET status = $3 WHERE id = $1 AND tenant_id = $2")
+        .bind(rma.order_id)
+        .bind(tenant_id)
+        .bind(if all_returned { "REFUNDED" } else { "PARTIAL_REFUNDED" })
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error("Failed to update order status", trace_id))?;
+    sqlx::query(
+        "UPDATE return_authorizations
+         SET status = 'RECEIVED', received_by = $2, received_at = NOW(), return_id = $3, refund_total = $4, restocking_fee = $5,
+             note = COALESCE($6, note)
+         WHERE id = $1",
+    )
+    .bind(rma_id)
+    .bind(sec.actor.id)
+    .bind(return_id)
+    .bind(&refund_total)
+    .bind(&restocking_fee)
+    .bind(trimmed_note(req.note.as_deref()))
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error("Failed to close return authorization", trace_id))?;
+    tx.commit().await.map_err(db_error("Failed to commit return receipt", trace_id))?;
+
+    // Stock moves after the refund commits; a failed restock is left `restocked = false` for a
+    // manual `/inventory/receive` rather than undoing the refund.
+    for line in received.iter().filter(|line| line.disposition == Disposition::Restock) {
+        match restock(&state, tenant_id, &auth.token, rma_id, store_id, line).await {
+            Ok(()) => {
+                let _ = sqlx::query("UPDATE return_authorization_items SET restocked = TRUE WHERE id = $1")
+                    .bind(line.rma_item_id)
+                    .execute(&state.db)
+                    .await;
+            }
+            Err(err) => tracing::warn!(rma_id = %rma_id, product_id = %line.product_id, error = %err, "Failed to restock returned units"),
+        }
+    }
+
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        let refund_event = OrderCompletedEvent {
+            schema_version: OrderCompletedEvent::SCHEMA_VERSION,
+            order_id: rma.order_id,
+            tenant_id,
+            items: received
+                .iter()
+                .map(|line| OrderEventItem {
+                    product_id: line.product_id,
+                    quantity: -line.quantity,
+                    unit_price: line.unit_price.clone(),
+                    line_total: BigDecimal::from(Money::from_cents(-line.line_total_cents())),
+                    measured_quantity: line.measured_quantity.as_ref().map(|m| -m),
+                })
+                .collect(),
+            total: &refund_total * BigDecimal::from(-1),
+            customer_id: order.try_get("customer_id").unwrap_or(None),
+            offline: order.try_get("offline").unwrap_or(false),
+            payment_method: order.try_get("payment_method").unwrap_or_default(),
+            return_id: Some(return_id),
+            location_id: store_id,
+            employee_id: sec.actor.id,
+            rma_id: Some(rma_id),
+            exchange_return_id: None,
+            business_date: Some(business_date),
+        };
+        if let Err(err) = common_kafka::publish_event(&state.kafka_producer, &state.db, tenant_id, &refund_event).await {
+            tracing::error!("Failed to send order.completed (rma refund): {:?}", err);
+        }
+        if let Some(audit) = &state.audit_producer {
+            let dispositions: Vec<_> = received
+                .iter()
+                .map(|line| json!({"product_id": line.product_id, "quantity": line.quantity, "disposition": line.disposition}))
+                .collect();
+            let _ = audit
+                .emit(
+                    tenant_id,
+                    sec.actor.clone(),
+                    "order",
+                    Some(rma.order_id),
+                    "rma_received",
+                    "order-service",
+                    common_audit::AuditSeverity::Info,
+                    trace_id,
+                    json!({"rma_id": rma_id, "return_id": return_id, "refund_total": refund_total, "restocking_fee": restocking_fee, "items": dispositions}),
+                    json!({"source":"order-service"}),
+                )
+                .await;
+        }
+    }
+
+    load_view(&state, tenant_id, rma_id, trace_id).await.map(Json)
+}
+
+async fn restock(state: &AppState, tenant_id: Uuid, auth_token: &str, rma_id: Uuid, location_id: Option<Uuid>, line: &ReceivedLine) -> Result<(), String> {
+    if std::env::var("ORDER_BYPASS_INVENTORY").ok().as_deref() == Some("1") {
+        return Ok(());
+    }
+    let payload = RestockPayload {
+        product_id: line.product_id,
+        quantity: line.quantity,
+        measured_quantity: line.measured_quantity.clone(),
+        location_id,
+        reason_code: "return_to_stock",
+        note: format!("RMA {rma_id}"),
+    };
+    let response = state
+        .http_client
+        .post(inventory_url(&state.inventory_base_url, "/inventory/receive"))
+        .bearer_auth(auth_token)
+        .header("X-Tenant-ID", tenant_id.to_string())
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to contact inventory-service: {err}"))?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    Err(format!("inventory-service returned {status}: {}", response.text().await.unwrap_or_default()))
+}
+
+#[derive(Deserialize, Default)]
+pub struct ReturnReasonReportQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ProductReturnReason {
+    pub product_id: Uuid,
+    pub product_name: Option<String>,
+    pub reason_code: String,
+    pub authorizations: i64,
+    pub authorized_quantity: i64,
+    pub received_quantity: i64,
+    pub restocked_quantity: i64,
+    pub damaged_quantity: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ReturnReasonTotal {
+    pub reason_code: String,
+    pub authorized_quantity: i64,
+    pub received_quantity: i64,
+    pub damaged_quantity: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReturnReasonReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub by_reason: Vec<ReturnReasonTotal>,
+    /// Per product and reason, most-returned first.
+    pub products: Vec<ProductReturnReason>,
+}
+
+/// Totals per reason code, in `RETURN_REASON_CODES` order, skipping reasons with no returns.
+pub fn summarize_reasons(rows: &[ProductReturnReason]) -> Vec<ReturnReasonTotal> {
+    RETURN_REASON_CODES
+        .iter()
+        .filter_map(|reason| {
+            let matching: Vec<&ProductReturnReason> = rows.iter().filter(|r| r.reason_code == *reason).collect();
+            (!matching.is_empty()).then(|| ReturnReasonTotal {
+                reason_code: reason.to_string(),
+                authorized_quantity: matching.iter().map(|r| r.authorized_quantity).sum(),
+                received_quantity: matching.iter().map(|r| r.received_quantity).sum(),
+                damaged_quantity: matching.iter().map(|r| r.damaged_quantity).sum(),
+            })
+        })
+        .collect()
+}
+
+/// `GET /reports/return_reasons`: RMA lines authorized in `[start_date, end_date]` (default:
+/// the last 30 days) grouped by product and reason. Cancelled authorizations are left out.
+pub async fn get_return_reason_report(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ReturnReasonReportQuery>,
+) -> Result<Json<ReturnReasonReport>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::SuperAdmin | Role::Admin | Role::Manager | Role::Support)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: sec.trace_id });
+    }
+    let end_date = params.end_date.unwrap_or_else(|| Utc::now().date_naive());
+    let start_date = params.start_date.unwrap_or(end_date - Duration::days(30));
+    if start_date > end_date {
+        return Err(ApiError::BadRequest { code: "invalid_date_range", trace_id: sec.trace_id, message: None });
+    }
+    let start = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).expect("midnight"));
+    let end = Utc.from_utc_datetime(&(end_date + Duration::days(1)).and_hms_opt(0, 0, 0).expect("midnight"));
+
+    let products = sqlx::query_as::<_, ProductReturnReason>(
+        "SELECT ri.product_id, MAX(p.name) AS product_name, ri.reason_code,
+                COUNT(DISTINCT ri.rma_id) AS authorizations,
+                SUM(ri.quantity)::BIGINT AS authorized_quantity,
+                COALESCE(SUM(ri.received_quantity), 0)::BIGINT AS received_quantity,
+                COALESCE(SUM(ri.received_quantity) FILTER (WHERE ri.disposition = 'restock'), 0)::BIGINT AS restocked_quantity,
+                COALESCE(SUM(ri.received_quantity) FILTER (WHERE ri.disposition = 'damage'), 0)::BIGINT AS damaged_quantity
+         FROM return_authorization_items ri
+         JOIN return_authorizations r ON r.id = ri.rma_id
+         LEFT JOIN products p ON p.id = ri.product_id AND p.tenant_id = ri.tenant_id
+         WHERE ri.tenant_id = $1 AND r.status <> 'CANCELLED' AND r.created_at >= $2 AND r.created_at < $3
+         GROUP BY ri.product_id, ri.reason_code
+         ORDER BY authorized_quantity DESC, ri.product_id, ri.reason_code
+         LIMIT 1000",
+    )
+    .bind(sec.tenant_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to load return reasons", sec.trace_id))?;
+
+    Ok(Json(ReturnReasonReport { start_date, end_date, by_reason: summarize_reasons(&products), products }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_money::{RoundingMode, RoundingPolicy};
+
+    fn reason(reason_code: &str, authorized: i64, received: i64, damaged: i64) -> ProductReturnReason {
+        ProductReturnReason {
+            product_id: Uuid::new_v4(),
+            product_name: None,
+            reason_code: reason_code.into(),
+            authorizations: 1,
+            authorized_quantity: authorized,
+            received_quantity: received,
+            restocked_quantity: received - damaged,
+            damaged_quantity: damaged,
+        }
+    }
+
+    #[test]
+    fn restocking_fee_and_refund_add_up_to_the_subtotal() {
+        let policy = RoundingPolicy::new(RoundingMode::HalfUp);
+        assert_eq!(apply_restocking_fee(&policy, 2_999, 0), (2_999, 0));
+        assert_eq!(apply_restocking_fee(&policy, 2_999, 1_500), (2_549, 450));
+        assert_eq!(apply_restocking_fee(&policy, 2_999, 10_000), (0, 2_999));
+    }
+
+    #[test]
+    fn reasons_are_totalled_in_code_order() {
+        let rows = [reason("wrong_item", 2, 2, 0), reason("defective", 3, 1, 1), reason("wrong_item", 1, 0, 0)];
+        let totals = summarize_reasons(&rows);
+        assert_eq!(totals.iter().map(|t| t.reason_code.as_str()).collect::<Vec<_>>(), vec!["defective", "wrong_item"]);
+        assert_eq!(totals[1], ReturnReasonTotal { reason_code: "wrong_item".into(), authorized_quantity: 3, received_quantity: 2, damaged_quantity: 0 });
+        assert_eq!(totals[0].damaged_quantity, 1);
+    }
+
+    #[test]
+    fn reason_codes_are_validated() {
+        assert_eq!(parse_reason_code(" defective ", None).unwrap(), "defective");
+        assert!(parse_reason_code("changed_mind", None).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! Orders paid while the payment provider was down.
+//!
+//! payment-service queues such payments under the tenant's stand-in floor limit and publishes
+//! `payment.stand_in`. The order waits in `CONDITIONALLY_APPROVED` until the queue is replayed and
+//! `payment.completed` or `payment.failed` moves it to `COMPLETED` or `NOT_ACCEPTED`.
+
+use common_events::PaymentStandInEvent;
+use sqlx::PgPool;
+
+pub const CONDITIONALLY_APPROVED: &str = "CONDITIONALLY_APPROVED";
+
+/// Mark a pending order conditionally approved. Returns false when the order isn't pending for
+/// the tenant, including when the provider's answer arrived first.
+pub async fn apply_stand_in(db: &PgPool, evt: &PaymentStandInEvent) -> sqlx::Result<bool> {
+    let done = sqlx::query("UPDATE orders SET status = $3 WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING'")
+        .bind(evt.order_id)
+        .bind(evt.tenant_id)
+        .bind(CONDITIONALLY_APPROVED)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected() > 0)
+}
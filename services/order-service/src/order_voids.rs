@@ -0,0 +1,494 @@
+//! Manager-approved voids.
+//!
+//! Cashiers cannot void on their own: they open a void request with a reason code and a holder
+//! of `order_void` approves it, either signed in on the same terminal, by handing over a second
+//! JWT, or by entering an approval PIN (checked against the roles its holder had when setting it).
+//! Managers can still void directly via `POST /orders/:id/void`. Every void lands in
+//! `order_void_requests`, which backs the per-cashier void-rate report.
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::extract::{Path, Query, State};
+use axum::{http::StatusCode, Json};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use common_auth::AuthContext; // bearer token for releasing the inventory reservation
+use common_http_errors::ApiError;
+use common_security::{ensure_capability, Capability, Role, SecurityContext, SecurityCtxExtractor};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use serde_json::json;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::order_handlers::{execute_void, trimmed_note, Order, VoidDecision};
+use crate::AppState;
+
+/// Reason codes accepted on voids; loss-prevention reports group by these.
+pub const VOID_REASON_CODES: &[&str] = &[
+    "customer_cancelled",
+    "cashier_error",
+    "payment_issue",
+    "duplicate_order",
+    "suspected_fraud",
+    "other",
+];
+
+/// Consecutive bad PINs before the approver is locked out.
+const PIN_MAX_ATTEMPTS: i32 = 5;
+const PIN_LOCKOUT_MINUTES: i64 = 15;
+
+/// Validate a void reason code; `None` (older POS builds) is recorded as `other`.
+pub(crate) fn parse_void_reason_code(value: Option<&str>, trace_id: Option<Uuid>) -> Result<&'static str, ApiError> {
+    let value = value.map(str::trim).unwrap_or("other");
+    VOID_REASON_CODES.iter().copied().find(|code| *code == value).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_reason_code",
+        trace_id,
+        message: Some(format!("reason_code must be one of: {}", VOID_REASON_CODES.join(", "))),
+    })
+}
+
+fn validate_pin(pin: &str, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+    if !(4..=8).contains(&pin.len()) || !pin.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ApiError::BadRequest { code: "invalid_pin_format", trace_id, message: Some("PIN must be 4-8 digits".into()) });
+    }
+    Ok(())
+}
+
+fn db_error(context: &'static str, trace_id: Option<Uuid>) -> impl FnOnce(sqlx::Error) -> ApiError {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("{context}: {e}")) },
+    }
+}
+
+#[derive(Serialize, sqlx::FromRow, Debug)]
+pub struct VoidRequest {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub requested_by: Option<Uuid>,
+    pub reason_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_method: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+const VOID_REQUEST_COLUMNS: &str =
+    "id, order_id, requested_by, reason_code, note, status, decided_by, approval_method, created_at, decided_at";
+
+#[derive(Deserialize)]
+pub struct CreateVoidRequest {
+    pub reason_code: Option<String>,
+    pub note: Option<String>,
+}
+
+pub async fn request_void(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(order_id): Path<Uuid>,
+    Json(req): Json<CreateVoidRequest>,
+) -> Result<Json<VoidRequest>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::Support | Role::Cashier)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_support", trace_id: sec.trace_id });
+    }
+    let tenant_id = sec.tenant_id;
+    if req.reason_code.as_deref().map(str::trim).unwrap_or("").is_empty() {
+        return Err(ApiError::BadRequest { code: "missing_reason_code", trace_id: sec.trace_id, message: None });
+    }
+    let reason_code = parse_void_reason_code(req.reason_code.as_deref(), sec.trace_id)?;
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1 AND tenant_id = $2")
+        .bind(order_id)
+        .bind(tenant_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(db_error("Failed to load order", sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "order_not_found", trace_id: sec.trace_id })?;
+    if status != "PENDING" {
+        return Err(ApiError::BadRequest { code: "invalid_status", trace_id: sec.trace_id, message: Some(format!("Order in status '{}' cannot be voided", status)) });
+    }
+
+    let created = sqlx::query_as::<_, VoidRequest>(&format!(
+        "INSERT INTO order_void_requests (id, tenant_id, order_id, requested_by, reason_code, note) VALUES ($1, $2, $3, $4, $5, $6) RETURNING {VOID_REQUEST_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(order_id)
+    .bind(sec.actor.id)
+    .bind(reason_code)
+    .bind(trimmed_note(req.note.as_deref()))
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => ApiError::Conflict {
+            code: "void_request_pending",
+            trace_id: sec.trace_id,
+            message: Some("A void request for this order is already awaiting approval".into()),
+        },
+        _ => ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to create void request: {e}")) },
+    })?;
+
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    if let Some(audit) = &state.audit_producer {
+        let _ = audit
+            .emit(
+                tenant_id,
+                sec.actor.clone(),
+                "order",
+                Some(order_id),
+                "void_requested",
+                "order-service",
+                common_audit::AuditSeverity::Info,
+                sec.trace_id,
+                json!({"request_id": created.id, "reason_code": reason_code, "note": created.note}),
+                json!({"source":"order-service"}),
+            )
+            .await;
+    }
+    Ok(Json(created))
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListVoidRequestsParams {
+    /// Defaults to `PENDING` (the manager approval queue).
+    pub status: Option<String>,
+    pub order_id: Option<Uuid>,
+}
+
+pub async fn list_void_requests(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ListVoidRequestsParams>,
+) -> Result<Json<Vec<VoidRequest>>, ApiError> {
+    ensure_capability(&sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id: sec.trace_id })?;
+    let status = params.status.as_deref().map(str::to_ascii_uppercase).unwrap_or_else(|| "PENDING".into());
+    let rows = sqlx::query_as::<_, VoidRequest>(&format!(
+        "SELECT {VOID_REQUEST_COLUMNS} FROM order_void_requests WHERE tenant_id = $1 AND status = $2 AND ($3::uuid IS NULL OR order_id = $3) ORDER BY created_at DESC LIMIT 200"
+    ))
+    .bind(sec.tenant_id)
+    .bind(status)
+    .bind(params.order_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to list void requests", sec.trace_id))?;
+    Ok(Json(rows))
+}
+
+async fn load_pending_request(state: &AppState, tenant_id: Uuid, request_id: Uuid, trace_id: Option<Uuid>) -> Result<VoidRequest, ApiError> {
+    let request = sqlx::query_as::<_, VoidRequest>(&format!(
+        "SELECT {VOID_REQUEST_COLUMNS} FROM order_void_requests WHERE id = $1 AND tenant_id = $2"
+    ))
+    .bind(request_id)
+    .bind(tenant_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to load void request", trace_id))?
+    .ok_or(ApiError::NotFound { code: "void_request_not_found", trace_id })?;
+    if request.status != "PENDING" {
+        return Err(ApiError::Conflict { code: "void_request_decided", trace_id, message: Some(format!("Void request is already {}", request.status)) });
+    }
+    Ok(request)
+}
+
+#[derive(Deserialize, Default)]
+pub struct ApproveVoidRequest {
+    /// Second JWT from the approving manager (e.g. a badge tap on the cashier's terminal).
+    pub approver_token: Option<String>,
+    /// Approving manager's user id, paired with `pin`.
+    pub manager_id: Option<Uuid>,
+    pub pin: Option<String>,
+}
+
+/// Work out who is approving. Without a token or PIN the signed-in caller must hold `order_void`.
+pub(crate) async fn resolve_approver(
+    state: &AppState,
+    sec: &SecurityContext,
+    req: &ApproveVoidRequest,
+) -> Result<(SecurityContext, &'static str), ApiError> {
+    let trace_id = sec.trace_id;
+    if let Some(token) = req.approver_token.as_deref().filter(|t| !t.is_empty()) {
+        let claims = state
+            .jwt_verifier
+            .verify(token)
+            .map_err(|_| ApiError::ForbiddenCode { code: "invalid_approver_token", trace_id, message: None })?;
+        if claims.tenant_id != sec.tenant_id {
+            return Err(ApiError::Forbidden { trace_id });
+        }
+        let mut approver = sec.clone();
+        approver.actor.id = Some(claims.subject);
+        approver.actor.name = None;
+        approver.actor.email = None;
+        approver.roles = claims.roles.iter().map(|r| Role::parse_role(r)).collect();
+        ensure_capability(&approver, Capability::OrderVoid)
+            .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id })?;
+        return Ok((approver, "jwt"));
+    }
+    if let Some(pin) = req.pin.as_deref() {
+        let manager_id = req.manager_id.ok_or(ApiError::BadRequest { code: "missing_manager_id", trace_id, message: None })?;
+        let roles = verify_pin(state, sec.tenant_id, manager_id, pin, trace_id).await?;
+        // No token comes with a PIN, so check the roles recorded with it against today's policy.
+        let mut approver = sec.clone();
+        approver.actor.id = Some(manager_id);
+        approver.actor.name = None;
+        approver.actor.email = None;
+        approver.roles = roles.iter().map(|r| Role::parse_role(r)).collect();
+        ensure_capability(&approver, Capability::OrderVoid)
+            .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id })?;
+        return Ok((approver, "pin"));
+    }
+    ensure_capability(sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id })?;
+    Ok((sec.clone(), "session"))
+}
+
+/// Check `pin` for `user_id` and return the roles recorded with it. Each attempt is counted up
+/// front in one conditional update, so concurrent guesses cannot get past the lockout.
+async fn verify_pin(state: &AppState, tenant_id: Uuid, user_id: Uuid, pin: &str, trace_id: Option<Uuid>) -> Result<Vec<String>, ApiError> {
+    let invalid = || ApiError::ForbiddenCode { code: "invalid_pin", trace_id, message: None };
+    let attempt = sqlx::query(
+        "UPDATE approval_pins SET failed_attempts = failed_attempts + 1,
+             locked_until = CASE WHEN failed_attempts + 1 >= $3 THEN NOW() + make_interval(mins => $4) ELSE locked_until END
+         WHERE tenant_id = $1 AND user_id = $2 AND (locked_until IS NULL OR locked_until <= NOW())
+         RETURNING pin_hash, roles",
+    )
+    .bind(tenant_id)
+    .bind(user_id)
+    .bind(PIN_MAX_ATTEMPTS)
+    .bind(PIN_LOCKOUT_MINUTES as i32)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to record PIN attempt", trace_id))?;
+    let Some(row) = attempt else {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM approval_pins WHERE tenant_id = $1 AND user_id = $2)")
+            .bind(tenant_id)
+            .bind(user_id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(db_error("Failed to load approval PIN", trace_id))?;
+        return Err(if exists {
+            ApiError::ForbiddenCode { code: "approver_locked", trace_id, message: Some(format!("Too many failed PIN attempts; retry in {PIN_LOCKOUT_MINUTES} minutes")) }
+        } else {
+            invalid()
+        });
+    };
+    let pin_hash: String = row.try_get("pin_hash").map_err(db_error("Failed to read approval PIN", trace_id))?;
+    let matches = PasswordHash::new(&pin_hash)
+        .map(|parsed| Argon2::default().verify_password(pin.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false);
+    if !matches {
+        return Err(invalid());
+    }
+    sqlx::query("UPDATE approval_pins SET failed_attempts = 0, locked_until = NULL WHERE tenant_id = $1 AND user_id = $2")
+        .bind(tenant_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_error("Failed to reset PIN attempts", trace_id))?;
+    row.try_get("roles").map_err(db_error("Failed to read approval PIN roles", trace_id))
+}
+
+pub async fn approve_void_request(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    Path(request_id): Path<Uuid>,
+    body: Option<Json<ApproveVoidRequest>>,
+) -> Result<Json<Order>, ApiError> {
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let request = load_pending_request(&state, sec.tenant_id, request_id, sec.trace_id).await?;
+    let (approver, approval_method) = resolve_approver(&state, &sec, &req).await?;
+    if approver.actor.id.is_some() && approver.actor.id == request.requested_by {
+        return Err(ApiError::BadRequest { code: "self_approval_not_allowed", trace_id: sec.trace_id, message: Some("A different manager must approve this void".into()) });
+    }
+    let decision = VoidDecision {
+        request_id: Some(request.id),
+        requested_by: request.requested_by,
+        approval_method,
+        reason_code: parse_void_reason_code(Some(&request.reason_code), sec.trace_id)?,
+        note: request.note,
+    };
+    let order = execute_void(&state, &approver, &auth.token, request.order_id, decision).await?;
+    Ok(Json(order))
+}
+
+pub async fn reject_void_request(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<VoidRequest>, ApiError> {
+    ensure_capability(&sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id: sec.trace_id })?;
+    let rejected = sqlx::query_as::<_, VoidRequest>(&format!(
+        "UPDATE order_void_requests SET status = 'REJECTED', decided_by = $3, approval_method = 'session', decided_at = NOW()
+         WHERE id = $1 AND tenant_id = $2 AND status = 'PENDING' RETURNING {VOID_REQUEST_COLUMNS}"
+    ))
+    .bind(request_id)
+    .bind(sec.tenant_id)
+    .bind(sec.actor.id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error("Failed to reject void request", sec.trace_id))?;
+    match rejected {
+        Some(row) => Ok(Json(row)),
+        // Distinguish unknown ids from requests someone already decided.
+        None => load_pending_request(&state, sec.tenant_id, request_id, sec.trace_id).await.map(Json),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetApprovalPinRequest {
+    pub pin: String,
+}
+
+/// Set or rotate the caller's own approval PIN. The caller's current roles are stored with it;
+/// set the PIN again (or revoke it) after a role change.
+pub async fn set_approval_pin(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<SetApprovalPinRequest>,
+) -> Result<StatusCode, ApiError> {
+    ensure_capability(&sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id: sec.trace_id })?;
+    let user_id = sec.actor.id.ok_or(ApiError::Forbidden { trace_id: sec.trace_id })?;
+    validate_pin(&req.pin, sec.trace_id)?;
+    let salt = SaltString::generate(&mut OsRng);
+    let pin_hash = Argon2::default()
+        .hash_password(req.pin.as_bytes(), &salt)
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .to_string();
+    let roles: Vec<&str> = sec.roles.iter().map(Role::as_str).collect();
+    sqlx::query(
+        "INSERT INTO approval_pins (tenant_id, user_id, pin_hash, roles) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (tenant_id, user_id) DO UPDATE SET pin_hash = EXCLUDED.pin_hash, roles = EXCLUDED.roles,
+             failed_attempts = 0, locked_until = NULL, updated_at = NOW()",
+    )
+    .bind(sec.tenant_id)
+    .bind(user_id)
+    .bind(pin_hash)
+    .bind(roles)
+    .execute(&state.db)
+    .await
+    .map_err(db_error("Failed to store approval PIN", sec.trace_id))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke a user's approval PIN (e.g. after a role change).
+pub async fn revoke_approval_pin(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin", trace_id: sec.trace_id });
+    }
+    let deleted = sqlx::query("DELETE FROM approval_pins WHERE tenant_id = $1 AND user_id = $2")
+        .bind(sec.tenant_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_error("Failed to revoke approval PIN", sec.trace_id))?;
+    if deleted.rows_affected() == 0 {
+        return Err(ApiError::NotFound { code: "approval_pin_not_found", trace_id: sec.trace_id });
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Default)]
+pub struct VoidRateQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CashierVoidRate {
+    pub cashier_id: Uuid,
+    pub orders: i64,
+    pub voids: i64,
+    /// `voids / orders`; absent when the cashier rang up no orders in the window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_rate: Option<f64>,
+}
+
+/// Per-cashier void rate over `[start_date, end_date]` (default: the last 30 days).
+pub async fn get_void_rate_report(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<VoidRateQuery>,
+) -> Result<Json<Vec<CashierVoidRate>>, ApiError> {
+    ensure_capability(&sec, Capability::OrderVoid)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "order_void", trace_id: sec.trace_id })?;
+    let today = Utc::now().date_naive();
+    let end_date = params.end_date.unwrap_or(today);
+    let start_date = params.start_date.unwrap_or(end_date - Duration::days(30));
+    if start_date > end_date {
+        return Err(ApiError::BadRequest { code: "invalid_date_range", trace_id: sec.trace_id, message: None });
+    }
+    let start = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).expect("midnight"));
+    let end = Utc.from_utc_datetime(&(end_date + Duration::days(1)).and_hms_opt(0, 0, 0).expect("midnight"));
+
+    let rows = sqlx::query(
+        "WITH sales AS (
+             SELECT created_by AS cashier_id, COUNT(*) AS orders FROM orders
+             WHERE tenant_id = $1 AND created_by IS NOT NULL AND created_at >= $2 AND created_at < $3
+             GROUP BY created_by
+         ), voids AS (
+             SELECT requested_by AS cashier_id, COUNT(*) AS voids FROM order_void_requests
+             WHERE tenant_id = $1 AND requested_by IS NOT NULL AND status = 'APPROVED' AND decided_at >= $2 AND decided_at < $3
+             GROUP BY requested_by
+         )
+         SELECT COALESCE(s.cashier_id, v.cashier_id) AS cashier_id, COALESCE(s.orders, 0) AS orders, COALESCE(v.voids, 0) AS voids
+         FROM sales s FULL OUTER JOIN voids v ON v.cashier_id = s.cashier_id
+         ORDER BY voids DESC, orders DESC",
+    )
+    .bind(sec.tenant_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error("Failed to compute void rate", sec.trace_id))?;
+
+    let report = rows
+        .into_iter()
+        .map(|row| {
+            let orders: i64 = row.try_get("orders").unwrap_or(0);
+            let voids: i64 = row.try_get("voids").unwrap_or(0);
+            Ok(CashierVoidRate {
+                cashier_id: row.try_get("cashier_id").map_err(db_error("Failed to read cashier id", sec.trace_id))?,
+                orders,
+                voids,
+                void_rate: (orders > 0).then(|| voids as f64 / orders as f64),
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reason_codes_default_to_other_and_reject_free_text() {
+        assert_eq!(parse_void_reason_code(None, None).unwrap(), "other");
+        assert_eq!(parse_void_reason_code(Some(" cashier_error "), None).unwrap(), "cashier_error");
+        assert!(matches!(parse_void_reason_code(Some("oops"), None), Err(ApiError::BadRequest { code: "invalid_reason_code", .. })));
+    }
+
+    #[test]
+    fn pins_are_four_to_eight_digits() {
+        assert!(validate_pin("1234", None).is_ok());
+        assert!(validate_pin("12345678", None).is_ok());
+        assert!(validate_pin("123", None).is_err());
+        assert!(validate_pin("12a4", None).is_err());
+        assert!(validate_pin("123456789", None).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+//! Relay for the shared `outbox` table, safe to run on every replica.
+//!
+//! A worker claims a batch with `FOR UPDATE SKIP LOCKED` and stamps it with its `claimed_by` and
+//! `claimed_at`, so concurrent workers never pick the same rows. The claim is a lease. Rows of a
+//! worker that died mid-batch become claimable again once [`OutboxRelay::lease`] has passed. A
+//! worker only marks a row published while it still holds the row's lease.
+//!
+//! Rows are published in id order per message key. A row is only claimed together with every
+//! earlier unpublished row of its key, and after a failed send the rest of that key's rows in the
+//! batch are handed back untouched. A worker that outlives its lease (a send slower than the lease)
+//! can still publish a row twice; consumers de-duplicate with `common_events::inbox_key`.
+//!
+//! A row that fails [`OutboxRelay::max_retries`] times is moved to `outbox_dead` and stops holding
+//! its key back. Admins list and requeue dead rows of their tenant under `/admin/outbox/dead`.
+//! Published rows are deleted by [`purge_published`] after the retention period.
+
+use std::future::Future;
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use common_http_errors::ApiError;
+use common_security::{Role, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::app::ORDER_REGISTRY;
+use crate::AppState;
+
+/// Published rows deleted per statement when purging, so a large backlog doesn't hold long locks.
+const PURGE_CHUNK: i64 = 5_000;
+
+static OUTBOX_PUBLISHED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(Opts::new("outbox_published_total", "Total number of outbox events successfully published"), &["topic"]).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(Opts::new("outbox_publish_failures_total", "Total number of outbox publish failures"), &["topic"]).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_RETRIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(Opts::new("outbox_publish_retries_total", "Total number of outbox publish retries"), &["topic"]).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_LEASE_LOST: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("outbox_lease_lost_total", "Outbox rows published after their lease had passed to another worker"),
+        &["topic"],
+    )
+    .unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_BACKLOG: Lazy<IntGauge> = Lazy::new(|| {
+    let v = IntGauge::new("outbox_backlog", "Current number of unpublished outbox rows").unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_DEAD_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(Opts::new("outbox_dead_total", "Outbox rows moved to outbox_dead after too many failed sends"), &["topic"]).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_DEAD_ROWS: Lazy<IntGauge> = Lazy::new(|| {
+    let v = IntGauge::new("outbox_dead_rows", "Rows waiting in outbox_dead for an admin to requeue them").unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_OLDEST_UNPUBLISHED: Lazy<IntGauge> = Lazy::new(|| {
+    let v = IntGauge::new("outbox_oldest_unpublished_seconds", "Age of the oldest unpublished outbox row; 0 when the outbox is drained").unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_PURGED: Lazy<IntCounter> = Lazy::new(|| {
+    let v = IntCounter::new("outbox_purged_total", "Published outbox rows deleted after the retention period").unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static OUTBOX_BACKLOG_BY_TOPIC: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let v = IntGaugeVec::new(Opts::new("outbox_backlog_by_topic", "Current number of unpublished outbox rows, by topic"), &["topic"]).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxRow {
+    pub id: i64,
+    pub tenant_id: String,
+    pub topic: String,
+    pub payload: serde_json::Value,
+    pub message_key: Option<String>,
+}
+
+impl OutboxRow {
+    /// Kafka key; rows queued before `message_key` existed keep their tenant key.
+    pub fn key(&self) -> &str {
+        self.message_key.as_deref().unwrap_or(&self.tenant_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Published,
+    /// Published, but another worker had claimed the row in the meantime and may send it again.
+    LeaseLost,
+    /// The send failed; the row is released for a later attempt.
+    Failed,
+    /// The send failed for the last allowed time; the row was moved to `outbox_dead`.
+    Dead,
+    /// Not sent because an earlier row with the same key failed in this batch.
+    Deferred,
+}
+
+/// One outbox worker. Each replica builds its own; the id ends up in `outbox.claimed_by`.
+#[derive(Debug, Clone)]
+pub struct OutboxRelay {
+    pub worker_id: String,
+    pub lease: Duration,
+    pub batch_size: i64,
+    /// Failed sends before a row is moved to `outbox_dead`; 0 retries forever.
+    pub max_retries: i32,
+}
+
+impl OutboxRelay {
+    pub fn new(lease: Duration, batch_size: i64) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "order-service".to_string());
+        Self { worker_id: format!("{host}-{}", &Uuid::new_v4().simple().to_string()[..8]), lease, batch_size, max_retries: 0 }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: i32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Claim up to `batch_size` unpublished rows that are unclaimed or whose lease has run out,
+    /// in id order.
+    pub async fn claim(&self, db: &PgPool) -> sqlx::Result<Vec<OutboxRow>> {
+        let mut rows = sqlx::query_as::<_, OutboxRow>(
+            "WITH candidates AS (
+                 SELECT id, COALESCE(message_key, tenant_id) AS key
+                 FROM outbox
+                 WHERE published_at IS NULL
+                   AND (claimed_at IS NULL OR claimed_at < NOW() - make_interval(secs => $3))
+                 ORDER BY id
+                 LIMIT $2
+                 FOR UPDATE SKIP LOCKED
+             ), claimable AS (
+                 SELECT c.id FROM candidates c
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM outbox e
+                     WHERE e.published_at IS NULL
+                       AND COALESCE(e.message_key, e.tenant_id) = c.key
+                       AND e.id < c.id
+                       AND e.id NOT IN (SELECT id FROM candidates)
+                 )
+             )
+             UPDATE outbox o SET claimed_by = $1, claimed_at = NOW()
+             FROM claimable
+             WHERE o.id = claimable.id
+             RETURNING o.id, o.tenant_id, o.topic, o.payload, o.message_key",
+        )
+        .bind(&self.worker_id)
+        .bind(self.batch_size)
+        .bind(self.lease.as_secs_f64())
+        .fetch_all(db)
+        .await?;
+        rows.sort_by_key(|row| row.id);
+        Ok(rows)
+    }
+
+    /// Mark a sent row published. Returns false when the row is no longer leased to this worker.
+    pub async fn mark_published(&self, db: &PgPool, id: i64) -> sqlx::Result<bool> {
+        let done = sqlx::query("UPDATE outbox SET published_at = NOW(), claimed_by = NULL, claimed_at = NULL WHERE id = $1 AND claimed_by = $2")
+            .bind(id)
+            .bind(&self.worker_id)
+            .execute(db)
+            .await?;
+        if done.rows_affected() == 1 {
+            return Ok(true);
+        }
+        // Re-claimed after our lease ran out; it was still sent, so record that it went out.
+        sqlx::query("UPDATE outbox SET published_at = NOW() WHERE id = $1 AND published_at IS NULL").bind(id).execute(db).await?;
+        Ok(false)
+    }
+
+    /// Hand a row back unsent.
+    pub async fn release(&self, db: &PgPool, id: i64) -> sqlx::Result<()> {
+        sqlx::query("UPDATE outbox SET claimed_by = NULL, claimed_at = NULL WHERE id = $1 AND claimed_by = $2")
+            .bind(id)
+            .bind(&self.worker_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Hand a row back after a failed send. Returns true when that was its last allowed attempt
+    /// and the row was moved to `outbox_dead`.
+    pub async fn record_failure(&self, db: &PgPool, id: i64, error: &str) -> sqlx::Result<bool> {
+        let retries: Option<i32> = sqlx::query_scalar(
+            "UPDATE outbox SET claimed_by = NULL, claimed_at = NULL, retry_count = retry_count + 1, last_error = $3
+             WHERE id = $1 AND claimed_by = $2
+             RETURNING retry_count",
+        )
+        .bind(id)
+        .bind(&self.worker_id)
+        .bind(error)
+        .fetch_optional(db)
+        .await?;
+        match retries {
+            Some(retries) if self.max_retries > 0 && retries >= self.max_retries => bury(db, id).await,
+            _ => Ok(false),
+        }
+    }
+
+    /// Claim one batch and send each row with `publish`, in id order.
+    pub async fn relay_batch<F, Fut, E>(&self, db: &PgPool, mut publish: F) -> sqlx::Result<Vec<(OutboxRow, Delivery)>>
+    where
+        F: FnMut(OutboxRow) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: std::fmt::Debug,
+    {
+        let batch = self.claim(db).await?;
+        let mut failed_keys: Vec<String> = Vec::new();
+        let mut outcomes = Vec::with_capacity(batch.len());
+        for row in batch {
+            let delivery = if failed_keys.iter().any(|key| key == row.key()) {
+                self.release(db, row.id).await?;
+                Delivery::Deferred
+            } else {
+                match publish(row.clone()).await {
+                    Ok(()) => {
+                        if self.mark_published(db, row.id).await? {
+                            Delivery::Published
+                        } else {
+                            Delivery::LeaseLost
+                        }
+                    }
+                    Err(err) => {
+                        if self.record_failure(db, row.id, &format!("{err:?}")).await? {
+                            tracing::error!(?err, outbox_id = row.id, topic = %row.topic, "Outbox event failed too often; moved to outbox_dead");
+                            Delivery::Dead
+                        } else {
+                            tracing::warn!(?err, outbox_id = row.id, topic = %row.topic, worker = %self.worker_id, "Failed to publish outbox event, will retry");
+                            failed_keys.push(row.key().to_string());
+                            Delivery::Failed
+                        }
+                    }
+                }
+            };
+            record(&row.topic, delivery);
+            outcomes.push((row, delivery));
+        }
+        Ok(outcomes)
+    }
+
+    /// Refresh the backlog, staleness and dead-row gauges from the tables.
+    pub async fn refresh_backlog(db: &PgPool) -> sqlx::Result<()> {
+        let rows = sqlx::query("SELECT topic, COUNT(*)::BIGINT AS cnt FROM outbox WHERE published_at IS NULL GROUP BY topic").fetch_all(db).await?;
+        OUTBOX_BACKLOG_BY_TOPIC.reset();
+        let mut total = 0;
+        for row in rows {
+            let count: i64 = row.get("cnt");
+            OUTBOX_BACKLOG_BY_TOPIC.with_label_values(&[row.get::<String, _>("topic").as_str()]).set(count);
+            total += count;
+        }
+        OUTBOX_BACKLOG.set(total);
+        let oldest: f64 = sqlx::query_scalar("SELECT COALESCE(EXTRACT(EPOCH FROM NOW() - MIN(created_at)), 0)::float8 FROM outbox WHERE published_at IS NULL")
+            .fetch_one(db)
+            .await?;
+        OUTBOX_OLDEST_UNPUBLISHED.set(oldest as i64);
+        let dead: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox_dead").fetch_one(db).await?;
+        OUTBOX_DEAD_ROWS.set(dead);
+        Ok(())
+    }
+}
+
+/// Move an unpublished row to `outbox_dead`, keeping its id.
+async fn bury(db: &PgPool, id: i64) -> sqlx::Result<bool> {
+    let moved = sqlx::query(
+        "WITH moved AS (
+             DELETE FROM outbox WHERE id = $1 AND published_at IS NULL
+             RETURNING id, tenant_id, topic, payload, message_key, created_at, retry_count, last_error
+         )
+         INSERT INTO outbox_dead (id, tenant_id, topic, payload, message_key, created_at, retry_count, last_error)
+         SELECT id, tenant_id, topic, payload, message_key, created_at, retry_count, last_error FROM moved",
+    )
+    .bind(id)
+    .execute(db)
+    .await?;
+    Ok(moved.rows_affected() == 1)
+}
+
+/// Move a dead row of `tenant_id` back into the outbox as a new row (behind anything already
+/// queued for its key) with its retries reset. Returns the new outbox id.
+pub async fn requeue_dead(db: &PgPool, tenant_id: Uuid, id: i64) -> sqlx::Result<Option<i64>> {
+    sqlx::query_scalar(
+        "WITH revived AS (
+             DELETE FROM outbox_dead WHERE id = $1 AND tenant_id = $2
+             RETURNING tenant_id, topic, payload, message_key
+         )
+         INSERT INTO outbox (tenant_id, topic, payload, message_key)
+         SELECT tenant_id, topic, payload, message_key FROM revived
+         RETURNING id",
+    )
+    .bind(id)
+    .bind(tenant_id.to_string())
+    .fetch_optional(db)
+    .await
+}
+
+/// Delete rows published more than `retention` ago. Returns how many went.
+pub async fn purge_published(db: &PgPool, retention: Duration) -> sqlx::Result<u64> {
+    let mut purged = 0;
+    loop {
+        let done = sqlx::query(
+            "DELETE FROM outbox WHERE id IN (
+                 SELECT id FROM outbox WHERE published_at < NOW() - make_interval(secs => $1) LIMIT $2
+             )",
+        )
+        .bind(retention.as_secs_f64())
+        .bind(PURGE_CHUNK)
+        .execute(db)
+        .await?;
+        purged += done.rows_affected();
+        if done.rows_affected() < PURGE_CHUNK as u64 {
+            break;
+        }
+    }
+    OUTBOX_PURGED.inc_by(purged);
+    Ok(purged)
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DeadOutboxRow {
+    pub id: i64,
+    pub topic: String,
+    pub message_key: Option<String>,
+    pub payload: serde_json::Value,
+    pub retry_count: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub dead_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequeuedOutboxRow {
+    pub dead_id: i64,
+    pub outbox_id: i64,
+}
+
+fn require_admin(sec: &common_security::SecurityContext) -> Result<(), ApiError> {
+    if sec.roles.iter().any(|r| matches!(r, Role::Admin)) {
+        Ok(())
+    } else {
+        Err(ApiError::ForbiddenMissingRole { role: "admin", trace_id: sec.trace_id })
+    }
+}
+
+/// `GET /admin/outbox/dead`: the tenant's dead rows, newest first.
+pub async fn list_dead_outbox(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<Vec<DeadOutboxRow>>, ApiError> {
+    require_admin(&sec)?;
+    let rows = sqlx::query_as::<_, DeadOutboxRow>(
+        "SELECT id, topic, message_key, payload, retry_count, last_error, created_at, dead_at
+         FROM outbox_dead WHERE tenant_id = $1 ORDER BY dead_at DESC, id DESC LIMIT 200",
+    )
+    .bind(sec.tenant_id.to_string())
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(rows))
+}
+
+/// `POST /admin/outbox/dead/:id/requeue`: send a dead row again.
+pub async fn requeue_dead_outbox(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(id): Path<i64>,
+) -> Result<Json<RequeuedOutboxRow>, ApiError> {
+    require_admin(&sec)?;
+    let outbox_id = requeue_dead(&state.db, sec.tenant_id, id)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "outbox_row_not_found", trace_id: sec.trace_id })?;
+    tracing::info!(dead_id = id, outbox_id, tenant_id = %sec.tenant_id, "Requeued dead outbox row");
+    Ok(Json(RequeuedOutboxRow { dead_id: id, outbox_id }))
+}
+
+fn record(topic: &str, delivery: Delivery) {
+    match delivery {
+        Delivery::Published => OUTBOX_PUBLISHED.with_label_values(&[topic]).inc(),
+        Delivery::LeaseLost => {
+            OUTBOX_PUBLISHED.with_label_values(&[topic]).inc();
+            OUTBOX_LEASE_LOST.with_label_values(&[topic]).inc();
+        }
+        Delivery::Failed => {
+            OUTBOX_FAILURES.with_label_values(&[topic]).inc();
+            OUTBOX_RETRIES.with_label_values(&[topic]).inc();
+        }
+        Delivery::Dead => {
+            OUTBOX_FAILURES.with_label_values(&[topic]).inc();
+            OUTBOX_DEAD_TOTAL.with_label_values(&[topic]).inc();
+        }
+        Delivery::Deferred => {}
+    }
+}
@@ -0,0 +1,198 @@
+//! Encryption of customer contact snapshots stored on orders.
+//!
+//! The customer name and email captured at checkout are sealed with a per-tenant key derived from
+//! `ORDER_PII_KEY` and stored in `customer_name_encrypted` / `customer_email_encrypted`, alongside
+//! keyed hashes for exact-match search. `pii_key_id` records which key sealed a row, so the key can
+//! be rotated: the new key goes in `ORDER_PII_KEY`, the old one moves to `ORDER_PII_KEY_PREVIOUS`,
+//! and `encrypt_order_customer_emails` re-seals rows still under it. The plaintext columns are
+//! only read for rows written before migrations 2014 and 2034 that have not been migrated yet.
+
+use common_crypto::{deterministic_hash, ColumnKey, CryptoError, EncryptedColumn};
+use uuid::Uuid;
+
+use crate::order_handlers::Order;
+
+pub const CUSTOMER_EMAIL_FIELD: &str = "orders.customer_email";
+pub const CUSTOMER_NAME_FIELD: &str = "orders.customer_name";
+
+/// Order PII keys. The first key is active and seals new values; retired keys only open rows
+/// that have not been re-sealed yet.
+pub struct PiiKeyring {
+    keys: Vec<(String, ColumnKey)>,
+}
+
+impl PiiKeyring {
+    pub fn new(active: ColumnKey, retired: Vec<ColumnKey>) -> Self {
+        let keys = std::iter::once(active)
+            .chain(retired)
+            .map(|key| (key.key_id(), key))
+            .collect();
+        Self { keys }
+    }
+
+    /// Load the active key from `ORDER_PII_KEY` and comma-separated retired keys from
+    /// `ORDER_PII_KEY_PREVIOUS` (base64, 32 bytes each).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let active = std::env::var("ORDER_PII_KEY")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("ORDER_PII_KEY must be set"))?;
+        let previous = std::env::var("ORDER_PII_KEY_PREVIOUS").unwrap_or_default();
+        let parse = |value: &str| {
+            ColumnKey::from_base64(value.trim())
+                .map_err(|err| anyhow::anyhow!("Invalid ORDER_PII_KEY: {err}"))
+        };
+        let retired = previous
+            .split(',')
+            .filter(|value| !value.trim().is_empty())
+            .map(parse)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self::new(parse(&active)?, retired))
+    }
+
+    pub fn active(&self) -> &ColumnKey {
+        &self.keys[0].1
+    }
+
+    /// Id stored in `pii_key_id` for values sealed with [`PiiKeyring::active`].
+    pub fn active_id(&self) -> &str {
+        &self.keys[0].0
+    }
+
+    /// Keys that may have sealed a row: the recorded one, or every key for rows sealed before
+    /// key ids were recorded.
+    fn candidates<'a>(&'a self, key_id: Option<&'a str>) -> impl Iterator<Item = &'a ColumnKey> {
+        self.keys
+            .iter()
+            .filter(move |(id, _)| key_id.is_none_or(|wanted| wanted == id))
+            .map(|(_, key)| key)
+    }
+
+    /// Open a value sealed for `field` under the key recorded as `key_id`.
+    pub fn open(
+        &self,
+        tenant_id: Uuid,
+        key_id: Option<&str>,
+        field: &str,
+        sealed: &EncryptedColumn<String>,
+    ) -> Result<String, CryptoError> {
+        let mut last_err = CryptoError::Provider(format!("unknown order PII key {key_id:?}"));
+        for key in self.candidates(key_id) {
+            match key.open(tenant_id.as_bytes(), field, sealed) {
+                Ok(value) => return Ok(value),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Hashes of a normalized value under every key, for searching rows sealed by any of them.
+    pub fn search_hashes(&self, tenant_id: Uuid, normalized: &str) -> Result<Vec<Vec<u8>>, CryptoError> {
+        self.keys
+            .iter()
+            .map(|(_, key)| customer_pii_hash(key, tenant_id, normalized))
+            .collect()
+    }
+}
+
+/// Normalize an email for storage and hashing.
+pub fn normalize_email(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_lowercase())
+}
+
+/// Keyed hash of a normalized name or email, scoped to the tenant.
+pub fn customer_pii_hash(
+    key: &ColumnKey,
+    tenant_id: Uuid,
+    normalized: &str,
+) -> Result<Vec<u8>, CryptoError> {
+    deterministic_hash(&key.tenant_key(tenant_id.as_bytes()), normalized.as_bytes())
+}
+
+/// Ciphertext and search hash for a normalized email.
+pub fn seal_customer_email(
+    key: &ColumnKey,
+    tenant_id: Uuid,
+    normalized_email: &str,
+) -> Result<(EncryptedColumn<String>, Vec<u8>), CryptoError> {
+    let sealed = key.seal(
+        tenant_id.as_bytes(),
+        CUSTOMER_EMAIL_FIELD,
+        &normalized_email.to_string(),
+    )?;
+    Ok((sealed, customer_pii_hash(key, tenant_id, normalized_email)?))
+}
+
+/// Ciphertext of the name as entered, and a search hash of its lowercased form.
+pub fn seal_customer_name(
+    key: &ColumnKey,
+    tenant_id: Uuid,
+    name: &str,
+) -> Result<(EncryptedColumn<String>, Vec<u8>), CryptoError> {
+    let sealed = key.seal(tenant_id.as_bytes(), CUSTOMER_NAME_FIELD, &name.to_string())?;
+    Ok((sealed, customer_pii_hash(key, tenant_id, &name.to_lowercase())?))
+}
+
+/// Fill `order.customer_name` and `order.customer_email` from the encrypted columns. Legacy
+/// plaintext is left untouched, and values that cannot be decrypted are omitted rather than
+/// failing the request.
+pub fn reveal_customer_pii(keys: Option<&PiiKeyring>, order: &mut Order) {
+    if order.customer_name_encrypted.is_none() && order.customer_email_encrypted.is_none() {
+        return;
+    }
+    let Some(keys) = keys else {
+        tracing::warn!(order_id = %order.id, "ORDER_PII_KEY not configured; cannot decrypt customer details");
+        return;
+    };
+    let key_id = order.pii_key_id.as_deref();
+    for (field, sealed, target) in [
+        (CUSTOMER_NAME_FIELD, &order.customer_name_encrypted, &mut order.customer_name),
+        (CUSTOMER_EMAIL_FIELD, &order.customer_email_encrypted, &mut order.customer_email),
+    ] {
+        let Some(sealed) = sealed else { continue };
+        match keys.open(order.tenant_id, key_id, field, sealed) {
+            Ok(value) => *target = Some(value),
+            Err(err) => {
+                tracing::warn!(order_id = %order.id, field, error = %err, "Failed to decrypt customer details")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_crypto::MasterKey;
+
+    fn column_key(byte: u8) -> ColumnKey {
+        ColumnKey::new(MasterKey::from_bytes([byte; 32]).expect("key"))
+    }
+
+    #[test]
+    fn rows_open_under_the_key_they_record_after_a_rotation() {
+        let tenant = Uuid::new_v4();
+        let before = PiiKeyring::new(column_key(1), Vec::new());
+        let (sealed, hash) = seal_customer_name(before.active(), tenant, "Ada Lovelace").expect("seal");
+        let old_id = before.active_id().to_string();
+
+        let rotated = PiiKeyring::new(column_key(2), vec![column_key(1)]);
+        assert_ne!(rotated.active_id(), old_id);
+        assert_eq!(
+            rotated.open(tenant, Some(&old_id), CUSTOMER_NAME_FIELD, &sealed).expect("open"),
+            "Ada Lovelace"
+        );
+        assert_eq!(
+            rotated.open(tenant, None, CUSTOMER_NAME_FIELD, &sealed).expect("open"),
+            "Ada Lovelace",
+            "rows without a key id try every key"
+        );
+        assert!(rotated
+            .open(tenant, Some(rotated.active_id()), CUSTOMER_NAME_FIELD, &sealed)
+            .is_err());
+        assert!(rotated.search_hashes(tenant, "ada lovelace").expect("hash").contains(&hash));
+
+        let dropped = PiiKeyring::new(column_key(2), Vec::new());
+        assert!(dropped.open(tenant, Some(&old_id), CUSTOMER_NAME_FIELD, &sealed).is_err());
+    }
+}
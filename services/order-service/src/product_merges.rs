@@ -0,0 +1,60 @@
+//! Following a product merge (`product.merged`) in order history.
+//!
+//! Order lines and return authorizations that sold the duplicate are relabelled with the
+//! canonical product, so sales reports, reorders and returns see one product. Open and parked
+//! carts hold SKUs rather than product ids; their lines for the duplicate's SKU switch to the
+//! canonical SKU, since the duplicate's no longer resolves at checkout. Each statement only
+//! matches rows still pointing at the duplicate, so a redelivered event changes nothing.
+
+use common_events::ProductMergedEvent;
+use sqlx::PgPool;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeCounts {
+    pub order_items: u64,
+    pub rma_items: u64,
+    pub carts: u64,
+}
+
+pub async fn apply_product_merge(db: &PgPool, evt: &ProductMergedEvent) -> Result<MergeCounts, sqlx::Error> {
+    let mut counts = MergeCounts::default();
+    if evt.duplicate_id == evt.canonical_id {
+        return Ok(counts);
+    }
+    let mut tx = db.begin().await?;
+    counts.order_items = sqlx::query(
+        "UPDATE order_items oi SET product_id = $3
+         FROM orders o
+         WHERE o.id = oi.order_id AND o.tenant_id = $1 AND oi.product_id = $2",
+    )
+    .bind(evt.tenant_id)
+    .bind(evt.duplicate_id)
+    .bind(evt.canonical_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    counts.rma_items = sqlx::query("UPDATE return_authorization_items SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2")
+        .bind(evt.tenant_id)
+        .bind(evt.duplicate_id)
+        .bind(evt.canonical_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if let (Some(duplicate_sku), Some(canonical_sku)) = (&evt.duplicate_sku, &evt.canonical_sku) {
+        counts.carts = sqlx::query(
+            "UPDATE carts SET items = (
+                 SELECT jsonb_agg(CASE WHEN line->>'sku' = $2 THEN jsonb_set(line, '{sku}', to_jsonb($3::text)) ELSE line END ORDER BY n)
+                 FROM jsonb_array_elements(items) WITH ORDINALITY AS lines(line, n)
+             ), version = version + 1, updated_at = NOW()
+             WHERE tenant_id = $1 AND status IN ('OPEN', 'PARKED') AND items @> jsonb_build_array(jsonb_build_object('sku', $2::text))",
+        )
+        .bind(evt.tenant_id)
+        .bind(duplicate_sku)
+        .bind(canonical_sku)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+    Ok(counts)
+}
@@ -0,0 +1,415 @@
+//! "Reorder last purchase": rebuild a customer's recent completed orders as a draft cart.
+//!
+//! History and current product status are resolved in one query, substitutes for every
+//! discontinued line in another, and stock in one call to inventory-service's availability
+//! endpoint, so a reorder costs a fixed number of round trips regardless of basket size. Lines
+//! whose product was deleted or deactivated are left out of the draft and come back with
+//! substitution hints instead.
+
+use axum::extract::{Path, State};
+use axum::Json;
+use bigdecimal::BigDecimal;
+use common_auth::AuthContext; // bearer token forwarded to inventory-service
+use common_crypto::EncryptedColumn;
+use common_http_errors::ApiError;
+use common_security::SecurityCtxExtractor;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::carts::{ensure_cart_role, insert_cart, Cart, CartItem, CartPayload};
+use crate::order_handlers::inventory_url;
+use crate::AppState;
+
+const MAX_ORDERS_BACK: i64 = 10;
+const MAX_SUBSTITUTES: i64 = 3;
+/// Matches inventory-service's per-request cap on `/inventory/availability`.
+const AVAILABILITY_BATCH: usize = 200;
+
+#[derive(Deserialize, Default)]
+pub struct ReorderRequest {
+    /// Reorder this specific completed order instead of the most recent ones.
+    pub order_id: Option<Uuid>,
+    /// Number of most recent completed orders to merge (default 1, max 10).
+    pub orders_back: Option<i64>,
+    pub store_id: Option<Uuid>,
+    /// Check stock at this location instead of across all locations.
+    pub location_id: Option<Uuid>,
+    pub label: Option<String>,
+    /// Validate and return the lines without creating a cart.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReorderLineStatus {
+    Available,
+    /// Less stock than previously bought; the draft carries what is available.
+    Partial,
+    OutOfStock,
+    /// Deleted, inactive or no longer sellable by SKU; see `substitutes`.
+    Discontinued,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct SubstituteHint {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub price: BigDecimal,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReorderLine {
+    pub product_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    pub name: Option<String>,
+    pub status: ReorderLineStatus,
+    pub previous_quantity: i32,
+    /// Quantity placed on the draft cart.
+    pub quantity: i32,
+    pub previous_unit_price: BigDecimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_unit_price: Option<BigDecimal>,
+    pub price_changed: bool,
+    /// Sellable stock; `None` when the product has no inventory record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub substitutes: Vec<SubstituteHint>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReorderDraft {
+    pub source_order_ids: Vec<Uuid>,
+    /// Draft cart holding every orderable line; absent on dry runs or when nothing is orderable.
+    pub cart: Option<Cart>,
+    pub lines: Vec<ReorderLine>,
+}
+
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+    product_id: Uuid,
+    previous_name: Option<String>,
+    previous_quantity: i32,
+    previous_unit_price: BigDecimal,
+    name: Option<String>,
+    sku: Option<String>,
+    price: Option<BigDecimal>,
+    sellable: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubstituteRow {
+    replaces: Uuid,
+    #[sqlx(flatten)]
+    hint: SubstituteHint,
+}
+
+#[derive(Deserialize)]
+struct AvailabilityItem {
+    product_id: Uuid,
+    available_to_promise: i64,
+    tracked: bool,
+}
+
+#[derive(Deserialize)]
+struct AvailabilityResponse {
+    items: Vec<AvailabilityItem>,
+}
+
+/// Classify a history line against current stock: the status plus the quantity to draft.
+fn classify_line(sellable: bool, wanted: i32, available: Option<i64>) -> (ReorderLineStatus, i32) {
+    if !sellable {
+        return (ReorderLineStatus::Discontinued, 0);
+    }
+    match available {
+        None => (ReorderLineStatus::Available, wanted),
+        Some(avail) if avail <= 0 => (ReorderLineStatus::OutOfStock, 0),
+        Some(avail) if avail < wanted as i64 => (ReorderLineStatus::Partial, avail as i32),
+        Some(_) => (ReorderLineStatus::Available, wanted),
+    }
+}
+
+/// Leading word of a product name, used to look for same-family replacements.
+fn name_stem(name: &str) -> Option<String> {
+    name.split_whitespace()
+        .next()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| w.chars().count() >= 3)
+        .map(|w| w.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+}
+
+async fn source_orders(
+    db: &PgPool,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    req: &ReorderRequest,
+    trace_id: Option<Uuid>,
+) -> Result<Vec<Uuid>, ApiError> {
+    let map_db = |e: sqlx::Error| ApiError::Internal { trace_id, message: Some(format!("Failed to load order history: {e}")) };
+    if let Some(order_id) = req.order_id {
+        let found = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM orders WHERE tenant_id = $1 AND customer_id = $2 AND id = $3 AND status = 'COMPLETED'",
+        )
+        .bind(tenant_id)
+        .bind(customer_id)
+        .bind(order_id)
+        .fetch_optional(db)
+        .await
+        .map_err(map_db)?;
+        return found.map(|id| vec![id]).ok_or(ApiError::NotFound { code: "order_not_found", trace_id });
+    }
+    let orders_back = req.orders_back.unwrap_or(1);
+    if !(1..=MAX_ORDERS_BACK).contains(&orders_back) {
+        return Err(ApiError::BadRequest {
+            code: "invalid_orders_back",
+            trace_id,
+            message: Some(format!("orders_back must be between 1 and {MAX_ORDERS_BACK}")),
+        });
+    }
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM orders WHERE tenant_id = $1 AND customer_id = $2 AND status = 'COMPLETED'
+         ORDER BY created_at DESC LIMIT $3",
+    )
+    .bind(tenant_id)
+    .bind(customer_id)
+    .bind(orders_back)
+    .fetch_all(db)
+    .await
+    .map_err(map_db)?;
+    if ids.is_empty() {
+        return Err(ApiError::NotFound { code: "no_purchase_history", trace_id });
+    }
+    Ok(ids)
+}
+
+/// Available-to-promise for tracked products. Best effort: when inventory-service can't be
+/// reached the draft is still built, with every line treated as untracked.
+async fn fetch_availability(
+    state: &AppState,
+    tenant_id: Uuid,
+    auth_token: &str,
+    product_ids: &[Uuid],
+    location_id: Option<Uuid>,
+) -> HashMap<Uuid, i64> {
+    let mut available = HashMap::new();
+    if std::env::var("ORDER_BYPASS_INVENTORY").ok().as_deref() == Some("1") {
+        return available;
+    }
+    for chunk in product_ids.chunks(AVAILABILITY_BATCH) {
+        let mut query = vec![("product_ids", chunk.iter().map(Uuid::to_string).collect::<Vec<_>>().join(","))];
+        if let Some(location_id) = location_id {
+            query.push(("location_id", location_id.to_string()));
+        }
+        let mut request = state
+            .http_client
+            .get(inventory_url(&state.inventory_base_url, "/inventory/availability"))
+            .query(&query)
+            .header("X-Tenant-ID", tenant_id.to_string())
+            .header("X-Roles", "Admin,Manager,Cashier");
+        if !auth_token.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", auth_token));
+        }
+        let body = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response.json::<AvailabilityResponse>().await,
+            Err(err) => Err(err),
+        };
+        match body {
+            Ok(body) => available.extend(body.items.into_iter().filter(|i| i.tracked).map(|i| (i.product_id, i.available_to_promise))),
+            Err(err) => {
+                tracing::warn!(tenant_id = %tenant_id, error = %err, "Availability lookup failed; drafting reorder without stock checks");
+                return HashMap::new();
+            }
+        }
+    }
+    available
+}
+
+/// Up to [`MAX_SUBSTITUTES`] replacements for each discontinued line, by product id.
+async fn substitutes_for(
+    db: &PgPool,
+    tenant_id: Uuid,
+    discontinued: &[&HistoryRow],
+    trace_id: Option<Uuid>,
+) -> Result<HashMap<Uuid, Vec<SubstituteHint>>, ApiError> {
+    let (mut product_ids, mut skus, mut stems) = (Vec::new(), Vec::new(), Vec::new());
+    for row in discontinued {
+        let stem = row.name.as_deref().or(row.previous_name.as_deref()).and_then(name_stem);
+        if row.sku.is_some() || stem.is_some() {
+            product_ids.push(row.product_id);
+            skus.push(row.sku.clone());
+            stems.push(stem);
+        }
+    }
+    if product_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // A deleted SKU may have been reissued to a replacement product, so that ranks first.
+    let rows = sqlx::query_as::<_, SubstituteRow>(
+        "WITH wanted AS (
+             SELECT * FROM UNNEST($2::uuid[], $3::text[], $4::text[]) AS w(product_id, sku, stem)
+         ),
+         ranked AS (
+             SELECT w.product_id AS replaces, p.id AS product_id, p.sku, p.name, p.price,
+                    ROW_NUMBER() OVER (PARTITION BY w.product_id ORDER BY (p.sku = w.sku) DESC NULLS LAST, p.name, p.id) AS rank
+             FROM wanted w
+             JOIN products p ON p.tenant_id = $1 AND p.id <> w.product_id
+              AND p.active AND p.deleted_at IS NULL AND p.sku IS NOT NULL
+              AND (p.sku = w.sku OR p.name ILIKE w.stem || '%')
+         )
+         SELECT replaces, product_id, sku, name, price FROM ranked WHERE rank <= $5 ORDER BY replaces, rank",
+    )
+    .bind(tenant_id)
+    .bind(&product_ids)
+    .bind(&skus)
+    .bind(&stems)
+    .bind(MAX_SUBSTITUTES)
+    .fetch_all(db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id, message: Some(format!("Failed to look up substitutes: {e}")) })?;
+    let mut by_product: HashMap<Uuid, Vec<SubstituteHint>> = HashMap::new();
+    for row in rows {
+        by_product.entry(row.replaces).or_default().push(row.hint);
+    }
+    Ok(by_product)
+}
+
+/// Build a draft cart from a customer's last completed purchase(s).
+pub async fn reorder_for_customer(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    Path(customer_id): Path<Uuid>,
+    body: Option<Json<ReorderRequest>>,
+) -> Result<Json<ReorderDraft>, ApiError> {
+    ensure_cart_role(&sec)?;
+    let req = body.map(|Json(r)| r).unwrap_or_default();
+    let tenant_id = sec.tenant_id;
+    let order_ids = source_orders(&state.db, tenant_id, customer_id, &req, sec.trace_id).await?;
+
+    // Net of returns, merged across the source orders; the latest price paid wins.
+    let rows = sqlx::query_as::<_, HistoryRow>(
+        "WITH history AS (
+             SELECT oi.product_id,
+                    (ARRAY_AGG(oi.product_name ORDER BY o.created_at DESC))[1] AS previous_name,
+                    SUM(oi.quantity - oi.returned_quantity)::int AS previous_quantity,
+                    (ARRAY_AGG(oi.unit_price ORDER BY o.created_at DESC))[1] AS previous_unit_price,
+                    MIN(o.created_at) AS first_seen
+             FROM order_items oi
+             JOIN orders o ON o.id = oi.order_id
+             WHERE o.tenant_id = $1 AND o.id = ANY($2)
+             GROUP BY oi.product_id
+             HAVING SUM(oi.quantity - oi.returned_quantity) > 0
+         )
+         SELECT h.product_id, h.previous_name, h.previous_quantity, h.previous_unit_price,
+                p.name, p.sku, p.price,
+                COALESCE(p.active AND p.deleted_at IS NULL AND p.sku IS NOT NULL, FALSE) AS sellable
+         FROM history h
+         LEFT JOIN products p ON p.id = h.product_id AND p.tenant_id = $1
+         ORDER BY h.first_seen, h.product_id",
+    )
+    .bind(tenant_id)
+    .bind(&order_ids)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to load order history: {e}")) })?;
+
+    let sellable_ids: Vec<Uuid> = rows.iter().filter(|r| r.sellable).map(|r| r.product_id).collect();
+    let availability = fetch_availability(&state, tenant_id, &auth.token, &sellable_ids, req.location_id).await;
+    let discontinued: Vec<&HistoryRow> = rows.iter().filter(|r| !r.sellable).collect();
+    let mut substitutes = substitutes_for(&state.db, tenant_id, &discontinued, sec.trace_id).await?;
+
+    let mut lines = Vec::with_capacity(rows.len());
+    let mut items = Vec::new();
+    for row in rows {
+        let available = availability.get(&row.product_id).copied();
+        let (status, quantity) = classify_line(row.sellable, row.previous_quantity, available);
+        let substitutes = if status == ReorderLineStatus::Discontinued {
+            substitutes.remove(&row.product_id).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        if quantity > 0 {
+            if let Some(sku) = row.sku.clone() {
+                items.push(CartItem { sku, quantity, modifier_ids: Vec::new() });
+            }
+        }
+        let price_changed = row.price.as_ref().is_some_and(|p| *p != row.previous_unit_price);
+        lines.push(ReorderLine {
+            product_id: row.product_id,
+            sku: row.sku,
+            name: row.name.or(row.previous_name),
+            status,
+            previous_quantity: row.previous_quantity,
+            quantity,
+            previous_unit_price: row.previous_unit_price,
+            current_unit_price: row.price,
+            price_changed,
+            available,
+            substitutes,
+        });
+    }
+
+    let cart = if req.dry_run || items.is_empty() {
+        None
+    } else {
+        let customer_name = last_customer_name(&state, tenant_id, order_ids[0], sec.trace_id).await?;
+        let payload = CartPayload {
+            items,
+            label: req.label.or_else(|| Some("Reorder".to_string())),
+            store_id: req.store_id,
+            customer_id: Some(customer_id),
+            customer_name,
+            discount_percent_bp: None,
+        };
+        Some(insert_cart(&state.db, &sec, payload).await?)
+    };
+
+    Ok(Json(ReorderDraft { source_order_ids: order_ids, cart, lines }))
+}
+
+/// Name captured on `order_id`, decrypted; `None` when absent or unreadable.
+async fn last_customer_name(state: &AppState, tenant_id: Uuid, order_id: Uuid, trace_id: Option<Uuid>) -> Result<Option<String>, ApiError> {
+    let row = sqlx::query_as::<_, (Option<String>, Option<EncryptedColumn<String>>, Option<String>)>(
+        "SELECT customer_name, customer_name_encrypted, pii_key_id FROM orders WHERE tenant_id = $1 AND id = $2",
+    )
+    .bind(tenant_id)
+    .bind(order_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id, message: Some(format!("Failed to load customer name: {e}")) })?;
+    let Some((plain, sealed, key_id)) = row else { return Ok(None) };
+    let Some(sealed) = sealed else { return Ok(plain) };
+    let Some(pii_key) = state.pii_key.as_deref() else { return Ok(None) };
+    Ok(pii_key
+        .open(tenant_id, key_id.as_deref(), crate::pii::CUSTOMER_NAME_FIELD, &sealed)
+        .map_err(|err| tracing::warn!(order_id = %order_id, error = %err, "Failed to decrypt customer name for reorder"))
+        .ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_caps_quantity_to_available_stock() {
+        assert_eq!(classify_line(true, 3, Some(10)), (ReorderLineStatus::Available, 3));
+        assert_eq!(classify_line(true, 3, Some(2)), (ReorderLineStatus::Partial, 2));
+        assert_eq!(classify_line(true, 3, Some(0)), (ReorderLineStatus::OutOfStock, 0));
+        assert_eq!(classify_line(true, 3, Some(-1)), (ReorderLineStatus::OutOfStock, 0));
+        assert_eq!(classify_line(true, 3, None), (ReorderLineStatus::Available, 3));
+        assert_eq!(classify_line(false, 3, Some(10)), (ReorderLineStatus::Discontinued, 0));
+    }
+
+    #[test]
+    fn name_stem_skips_short_words_and_escapes_like_wildcards() {
+        assert_eq!(name_stem("Espresso Beans 1kg").as_deref(), Some("Espresso"));
+        assert_eq!(name_stem("  100%_Juice ").as_deref(), Some("100\\%\\_Juice"));
+        assert_eq!(name_stem("XL shirt"), None);
+        assert_eq!(name_stem(""), None);
+    }
+}
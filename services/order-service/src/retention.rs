@@ -0,0 +1,239 @@
+//! Retention for finished orders: once an order is older than `ORDER_RETENTION_MONTHS`, its
+//! customer linkage (`customer_id`) and contact snapshot (name, email, email hash) are removed
+//! and `anonymized_at` is set. Totals, lines, taxes, tenders and business dates stay, so sales,
+//! Z-reports and settlement figures are unchanged. Parked carts that were converted or expired
+//! lose the same fields.
+//!
+//! Pending orders, orders with an open chargeback and orders with an RMA still awaiting its
+//! return are skipped until they settle. [`spawn_retention_job`] runs [`run_retention`] in
+//! batches of `ORDER_RETENTION_BATCH_SIZE` rows (each its own statement, taken with
+//! `SKIP LOCKED` so replicas can all run it) and emits one audit event per tenant summarizing
+//! the run. With `ORDER_RETENTION_DRY_RUN` the job only counts what it would strip.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use common_security::{CapabilitySet, SystemActor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::app::ORDER_REGISTRY;
+use crate::AppState;
+
+/// Identity the retention job audits under.
+pub const RETENTION_JOB: SystemActor = SystemActor::new("order-service", "retention", CapabilitySet::EMPTY);
+
+/// Orders whose customer data the job strips; `$1` is the cutoff.
+const ORDER_CANDIDATES: &str = "FROM orders o
+     WHERE o.created_at < $1
+       AND o.status NOT IN ('PENDING', 'CONDITIONALLY_APPROVED')
+       AND (o.customer_id IS NOT NULL OR o.customer_name IS NOT NULL OR o.customer_email IS NOT NULL
+            OR o.customer_email_encrypted IS NOT NULL OR o.customer_email_hash IS NOT NULL
+            OR o.customer_name_encrypted IS NOT NULL OR o.customer_name_hash IS NOT NULL)
+       AND (o.dispute_status IS NULL OR o.dispute_status IN ('won', 'lost'))
+       AND NOT EXISTS (
+           SELECT 1 FROM return_authorizations r WHERE r.order_id = o.id AND r.status = 'AUTHORIZED'
+       )";
+
+/// Finished carts whose customer data the job strips; `$1` is the cutoff.
+const CART_CANDIDATES: &str = "FROM carts c
+     WHERE c.updated_at < $1
+       AND c.status IN ('CONVERTED', 'EXPIRED')
+       AND (c.customer_id IS NOT NULL OR c.customer_name IS NOT NULL)";
+
+static RETENTION_ROWS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let v = IntCounterVec::new(
+        Opts::new("order_retention_rows_total", "Rows the retention job anonymized (or would have, in dry-run mode), by table and mode"),
+        &["table", "mode"],
+    ).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static RETENTION_PENDING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let v = IntGaugeVec::new(
+        Opts::new("order_retention_pending", "Rows past the retention period still holding customer data, by table"),
+        &["table"],
+    ).unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+static RETENTION_LAST_RUN: Lazy<IntGauge> = Lazy::new(|| {
+    let v = IntGauge::new("order_retention_last_run_timestamp_seconds", "Unix time the last retention run finished").unwrap();
+    ORDER_REGISTRY.register(Box::new(v.clone())).ok();
+    v
+});
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RetentionPolicy {
+    /// Age in months after which orders are anonymized; 0 disables the job.
+    pub months: u32,
+    pub batch_size: i64,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TenantRetention {
+    pub orders: u64,
+    pub carts: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetentionRun {
+    pub cutoff: DateTime<Utc>,
+    pub dry_run: bool,
+    pub batches: u32,
+    pub tenants: BTreeMap<Uuid, TenantRetention>,
+}
+
+impl RetentionRun {
+    pub fn orders(&self) -> u64 {
+        self.tenants.values().map(|t| t.orders).sum()
+    }
+
+    pub fn carts(&self) -> u64 {
+        self.tenants.values().map(|t| t.carts).sum()
+    }
+}
+
+/// One pass over every tenant. In dry-run mode nothing is written and the counts are what a
+/// real run would strip.
+pub async fn run_retention(db: &PgPool, policy: &RetentionPolicy) -> sqlx::Result<RetentionRun> {
+    let cutoff: DateTime<Utc> = sqlx::query_scalar("SELECT NOW() - make_interval(months => $1)")
+        .bind(policy.months as i32)
+        .fetch_one(db)
+        .await?;
+    let mut run = RetentionRun { cutoff, dry_run: policy.dry_run, batches: 0, tenants: BTreeMap::new() };
+
+    if policy.dry_run {
+        for (table, alias, candidates) in [("orders", "o", ORDER_CANDIDATES), ("carts", "c", CART_CANDIDATES)] {
+            let counts: Vec<(Uuid, i64)> =
+                sqlx::query_as(&format!("SELECT {alias}.tenant_id, COUNT(*) {candidates} GROUP BY {alias}.tenant_id"))
+                    .bind(cutoff)
+                    .fetch_all(db)
+                    .await?;
+            for (tenant_id, count) in counts {
+                let entry = run.tenants.entry(tenant_id).or_default();
+                if table == "orders" {
+                    entry.orders = count as u64;
+                } else {
+                    entry.carts = count as u64;
+                }
+                RETENTION_ROWS.with_label_values(&[table, "dry_run"]).inc_by(count as u64);
+            }
+        }
+    } else {
+        let orders = format!(
+            "WITH batch AS (
+                 SELECT o.id {ORDER_CANDIDATES} ORDER BY o.created_at LIMIT $2 FOR UPDATE SKIP LOCKED
+             )
+             UPDATE orders SET customer_id = NULL, customer_name = NULL, customer_email = NULL,
+                               customer_email_encrypted = NULL, customer_email_hash = NULL,
+                               customer_name_encrypted = NULL, customer_name_hash = NULL, pii_key_id = NULL, anonymized_at = NOW()
+             FROM batch WHERE orders.id = batch.id
+             RETURNING orders.tenant_id"
+        );
+        for tenant_id in in_batches(db, &orders, cutoff, policy.batch_size, &mut run.batches, "orders").await? {
+            run.tenants.entry(tenant_id).or_default().orders += 1;
+        }
+        let carts = format!(
+            "WITH batch AS (
+                 SELECT c.id {CART_CANDIDATES} ORDER BY c.updated_at LIMIT $2 FOR UPDATE SKIP LOCKED
+             )
+             UPDATE carts SET customer_id = NULL, customer_name = NULL
+             FROM batch WHERE carts.id = batch.id
+             RETURNING carts.tenant_id"
+        );
+        for tenant_id in in_batches(db, &carts, cutoff, policy.batch_size, &mut run.batches, "carts").await? {
+            run.tenants.entry(tenant_id).or_default().carts += 1;
+        }
+    }
+
+    for (table, candidates) in [("orders", ORDER_CANDIDATES), ("carts", CART_CANDIDATES)] {
+        let pending: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {candidates}")).bind(cutoff).fetch_one(db).await?;
+        RETENTION_PENDING.with_label_values(&[table]).set(pending);
+    }
+    RETENTION_LAST_RUN.set(Utc::now().timestamp());
+    Ok(run)
+}
+
+/// Run a batched `UPDATE ... RETURNING tenant_id` until a batch comes back short. Returns the
+/// tenant of every row changed.
+async fn in_batches(
+    db: &PgPool,
+    statement: &str,
+    cutoff: DateTime<Utc>,
+    batch_size: i64,
+    batches: &mut u32,
+    table: &str,
+) -> sqlx::Result<Vec<Uuid>> {
+    let mut changed = Vec::new();
+    loop {
+        let tenants: Vec<Uuid> = sqlx::query_scalar(statement).bind(cutoff).bind(batch_size).fetch_all(db).await?;
+        *batches += 1;
+        RETENTION_ROWS.with_label_values(&[table, "anonymized"]).inc_by(tenants.len() as u64);
+        let done = (tenants.len() as i64) < batch_size;
+        changed.extend(tenants);
+        if done {
+            return Ok(changed);
+        }
+    }
+}
+
+/// Run [`run_retention`] every `interval` and audit what each tenant lost.
+pub fn spawn_retention_job(state: AppState, policy: RetentionPolicy, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let run = match run_retention(&state.db, &policy).await {
+                Ok(run) => run,
+                Err(err) => {
+                    tracing::warn!(?err, "Order retention run failed");
+                    continue;
+                }
+            };
+            if run.tenants.is_empty() {
+                continue;
+            }
+            tracing::info!(
+                dry_run = run.dry_run,
+                cutoff = %run.cutoff,
+                orders = run.orders(),
+                carts = run.carts(),
+                tenants = run.tenants.len(),
+                "Order retention run finished"
+            );
+            #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+            if let Some(audit) = &state.audit_producer {
+                for (tenant_id, counts) in &run.tenants {
+                    let _ = audit
+                        .emit(
+                            *tenant_id,
+                            RETENTION_JOB.audit_actor(),
+                            "order_retention",
+                            None,
+                            if run.dry_run { "dry_run" } else { "anonymized" },
+                            "order-service",
+                            common_audit::AuditSeverity::Info,
+                            None,
+                            serde_json::json!({
+                                "cutoff": run.cutoff,
+                                "retention_months": policy.months,
+                                "orders": counts.orders,
+                                "carts": counts.carts,
+                            }),
+                            serde_json::json!({"source": "order-service"}),
+                        )
+                        .await;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,64 @@
+//! Order and outbox counts for the gateway's tenant overview (`GET /tenants/:tenant_id/diagnostics`).
+
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use common_http_errors::ApiError;
+use common_security::{ensure_role, Role, SecurityCtxExtractor};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::AppState;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OrderDiagnostics {
+    /// Orders still `PENDING` (not completed, voided or refused).
+    pub open_orders: i64,
+    /// Carts `OPEN` or `PARKED`.
+    pub open_carts: i64,
+    pub last_order_at: Option<DateTime<Utc>>,
+    #[sqlx(flatten)]
+    pub outbox: OutboxDiagnostics,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OutboxDiagnostics {
+    #[sqlx(rename = "outbox_pending")]
+    pub pending: i64,
+    #[sqlx(rename = "outbox_oldest_pending_at")]
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+    /// Rows parked in `outbox_dead` after exhausting their retries.
+    #[sqlx(rename = "outbox_dead")]
+    pub dead: i64,
+    #[sqlx(rename = "outbox_last_published_at")]
+    pub last_published_at: Option<DateTime<Utc>>,
+}
+
+pub async fn load_diagnostics(db: &PgPool, tenant_id: Uuid) -> sqlx::Result<OrderDiagnostics> {
+    sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM orders WHERE tenant_id = $1 AND status IN ('PENDING', 'CONDITIONALLY_APPROVED')) AS open_orders,
+                (SELECT COUNT(*) FROM carts WHERE tenant_id = $1 AND status IN ('OPEN', 'PARKED')) AS open_carts,
+                (SELECT MAX(created_at) FROM orders WHERE tenant_id = $1) AS last_order_at,
+                (SELECT COUNT(*) FROM outbox WHERE tenant_id = $2 AND published_at IS NULL) AS outbox_pending,
+                (SELECT MIN(created_at) FROM outbox WHERE tenant_id = $2 AND published_at IS NULL) AS outbox_oldest_pending_at,
+                (SELECT COUNT(*) FROM outbox_dead WHERE tenant_id = $2) AS outbox_dead,
+                (SELECT MAX(published_at) FROM outbox WHERE tenant_id = $2) AS outbox_last_published_at",
+    )
+    .bind(tenant_id)
+    .bind(tenant_id.to_string())
+    .fetch_one(db)
+    .await
+}
+
+/// Support engineers look at any tenant, so the path tenant is not matched against the caller's.
+pub async fn tenant_diagnostics(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<OrderDiagnostics>, ApiError> {
+    ensure_role(&sec, Role::SuperAdmin)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id })?;
+    let diagnostics = load_diagnostics(&state.db, tenant_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(diagnostics))
+}
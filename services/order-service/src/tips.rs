@@ -0,0 +1,709 @@
+//! Tips: per-tenant checkout suggestions, validation of the tip taken at checkout, and card tip
+//! adjustment during the settlement window.
+//!
+//! A tip is stored next to the sale, not in it: `orders.total` stays the taxable amount while
+//! `orders.tip_amount` and `payments.tip_amount` carry the tip, and the payment's `amount` covers
+//! both. Every tip that is set or changed publishes `order.tip_recorded` for tip reporting.
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use common_auth::AuthContext; // bearer token forwarded to payment-service
+use common_http_errors::ApiError;
+use common_money::{Money, RoundingContext};
+use common_security::{Role, SecurityContext, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::order_handlers::resolve_rounding_policy;
+use crate::order_voids::{resolve_approver, ApproveVoidRequest};
+use crate::AppState;
+
+const DEFAULT_ADJUST_WINDOW_HOURS: i32 = 24;
+const MAX_ADJUST_WINDOW_HOURS: i32 = 168;
+const MAX_SUGGESTIONS: usize = 4;
+/// Tips are capped at the sale itself unless the tenant sets a lower cap.
+const DEFAULT_MAX_TIP_BPS: i32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TipMode {
+    /// Suggestions are basis points of the sale total.
+    Percentage,
+    /// Suggestions are amounts in cents.
+    Fixed,
+}
+
+impl TipMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "percentage" => Some(TipMode::Percentage),
+            "fixed" => Some(TipMode::Fixed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipMode::Percentage => "percentage",
+            TipMode::Fixed => "fixed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TipSettings {
+    pub enabled: bool,
+    pub mode: TipMode,
+    pub suggestions: Vec<i32>,
+    /// When false, only the suggested amounts are accepted at checkout.
+    pub allow_custom: bool,
+    /// How long after the sale a card tip can still be adjusted.
+    pub adjust_window_hours: i32,
+    /// Largest tip accepted, in basis points of the sale.
+    pub max_tip_bps: i32,
+    /// False when the tenant has no stored settings.
+    pub configured: bool,
+}
+
+/// Tenants without a stored row get no tip prompt and can't take tips.
+impl Default for TipSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: TipMode::Percentage,
+            suggestions: Vec::new(),
+            allow_custom: true,
+            adjust_window_hours: DEFAULT_ADJUST_WINDOW_HOURS,
+            max_tip_bps: DEFAULT_MAX_TIP_BPS,
+            configured: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TipSuggestion {
+    pub label: String,
+    pub amount_cents: i64,
+}
+
+impl TipSettings {
+    /// Suggested tips for a sale, rounded with the tenant's rounding mode.
+    pub fn suggestions_for(&self, rounding: &RoundingContext, sale_cents: i64) -> Vec<TipSuggestion> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.suggestions
+            .iter()
+            .map(|&value| match self.mode {
+                TipMode::Percentage => TipSuggestion {
+                    label: format_percent(value),
+                    amount_cents: rounding.percent(&Money::from_cents(sale_cents.max(0)), value).as_cents(),
+                },
+                TipMode::Fixed => TipSuggestion {
+                    label: Money::from_cents(value as i64).to_string(),
+                    amount_cents: value as i64,
+                },
+            })
+            .collect()
+    }
+
+    /// Validate the tip sent with a checkout, or the new tip on a later adjustment.
+    pub fn check_checkout_tip(&self, rounding: &RoundingContext, sale_cents: i64, tip_cents: i64) -> Result<(), ApiError> {
+        if tip_cents < 0 {
+            return Err(ApiError::BadRequest { code: "invalid_tip", trace_id: None, message: Some("tip_cents cannot be negative".into()) });
+        }
+        if tip_cents == 0 {
+            return Ok(());
+        }
+        if !self.enabled {
+            return Err(ApiError::BadRequest { code: "tips_disabled", trace_id: None, message: Some("Tips are not enabled for this tenant".into()) });
+        }
+        if !self.allow_custom && !self.suggestions_for(rounding, sale_cents).iter().any(|s| s.amount_cents == tip_cents) {
+            return Err(ApiError::BadRequest {
+                code: "tip_not_offered",
+                trace_id: None,
+                message: Some("Only the suggested tip amounts are accepted".into()),
+            });
+        }
+        let max_tip = rounding.percent(&Money::from_cents(sale_cents.max(0)), self.max_tip_bps);
+        if tip_cents > max_tip.as_cents() {
+            return Err(ApiError::BadRequest {
+                code: "tip_exceeds_limit",
+                trace_id: None,
+                message: Some(format!("Tips are limited to {} of the sale ({max_tip})", format_percent(self.max_tip_bps))),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn adjust_deadline(&self, sold_at: DateTime<Utc>) -> DateTime<Utc> {
+        sold_at + Duration::hours(self.adjust_window_hours as i64)
+    }
+}
+
+fn format_percent(bps: i32) -> String {
+    if bps % 100 == 0 {
+        format!("{}%", bps / 100)
+    } else {
+        format!("{}%", format!("{:.2}", bps as f64 / 100.0).trim_end_matches('0'))
+    }
+}
+
+/// Tenant tip settings, falling back to the defaults (tips off) when the tenant has none or the
+/// lookup fails.
+pub(crate) async fn resolve_tip_settings(db: &PgPool, tenant_id: Uuid) -> TipSettings {
+    match sqlx::query_as::<_, (bool, String, Vec<i32>, bool, i32, i32)>(
+        "SELECT enabled, mode, suggestions, allow_custom, adjust_window_hours, max_tip_bps FROM tip_settings WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_optional(db)
+    .await
+    {
+        Ok(Some((enabled, mode, suggestions, allow_custom, adjust_window_hours, max_tip_bps))) => TipSettings {
+            enabled,
+            mode: TipMode::parse(&mode).unwrap_or(TipMode::Percentage),
+            suggestions,
+            allow_custom,
+            adjust_window_hours,
+            max_tip_bps,
+            configured: true,
+        },
+        Ok(None) => TipSettings::default(),
+        Err(err) => {
+            tracing::warn!(?err, %tenant_id, "Failed to load tip settings; tips disabled");
+            TipSettings::default()
+        }
+    }
+}
+
+/// A tip set at checkout or adjusted later, as published on `order.tip_recorded`.
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+pub(crate) struct TipChange<'a> {
+    pub order_id: Uuid,
+    pub tenant_id: Uuid,
+    pub employee_id: Option<Uuid>,
+    pub pos_instance_id: Option<Uuid>,
+    pub payment_method: &'a str,
+    pub previous_tip_cents: i64,
+    pub tip_cents: i64,
+    pub adjustment: bool,
+    pub business_date: NaiveDate,
+}
+
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+pub(crate) async fn publish_tip_recorded(state: &AppState, change: TipChange<'_>) {
+    use common_events::{DomainEvent, OrderTipRecordedEvent};
+
+    let event = OrderTipRecordedEvent {
+        schema_version: OrderTipRecordedEvent::SCHEMA_VERSION,
+        order_id: change.order_id,
+        tenant_id: change.tenant_id,
+        employee_id: change.employee_id,
+        pos_instance_id: change.pos_instance_id,
+        payment_method: change.payment_method.to_string(),
+        tip: Money::from_cents(change.tip_cents).into(),
+        previous_tip: Money::from_cents(change.previous_tip_cents).into(),
+        adjustment: change.adjustment,
+        business_date: change.business_date,
+    };
+    if let Err(err) = common_kafka::publish_event(
+        &state.kafka_producer,
+        &state.db,
+        event.tenant_id,
+        &event,
+    )
+    .await
+    {
+        tracing::error!(?err, order_id = %change.order_id, "Failed to send order.tip_recorded");
+    }
+}
+
+// --- Admin: tip settings ---
+
+#[derive(Deserialize)]
+pub struct UpsertTipSettingsRequest {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub mode: String,
+    #[serde(default)]
+    pub suggestions: Vec<i32>,
+    #[serde(default = "default_enabled")]
+    pub allow_custom: bool,
+    #[serde(default)]
+    pub adjust_window_hours: Option<i32>,
+    #[serde(default)]
+    pub max_tip_bps: Option<i32>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn validate_settings(req: UpsertTipSettingsRequest, trace_id: Option<Uuid>) -> Result<TipSettings, ApiError> {
+    let bad = |code: &'static str, message: String| ApiError::BadRequest { code, trace_id, message: Some(message) };
+    let mode = TipMode::parse(&req.mode).ok_or_else(|| bad("invalid_tip_mode", "mode must be percentage or fixed".into()))?;
+    if req.suggestions.len() > MAX_SUGGESTIONS {
+        return Err(bad("invalid_tip_suggestions", format!("At most {MAX_SUGGESTIONS} suggestions are allowed")));
+    }
+    let max = match mode {
+        TipMode::Percentage => 10_000,
+        TipMode::Fixed => 100_000,
+    };
+    if req.suggestions.iter().any(|&value| value <= 0 || value > max) {
+        return Err(bad("invalid_tip_suggestions", format!("Suggestions must be between 1 and {max}")));
+    }
+    if req.enabled && !req.allow_custom && req.suggestions.is_empty() {
+        return Err(bad("invalid_tip_suggestions", "Suggestions are required when custom tips are not allowed".into()));
+    }
+    let adjust_window_hours = req.adjust_window_hours.unwrap_or(DEFAULT_ADJUST_WINDOW_HOURS);
+    if !(0..=MAX_ADJUST_WINDOW_HOURS).contains(&adjust_window_hours) {
+        return Err(bad("invalid_adjust_window", format!("adjust_window_hours must be between 0 and {MAX_ADJUST_WINDOW_HOURS}")));
+    }
+    let max_tip_bps = req.max_tip_bps.unwrap_or(DEFAULT_MAX_TIP_BPS);
+    if !(1..=DEFAULT_MAX_TIP_BPS).contains(&max_tip_bps) {
+        return Err(bad("invalid_max_tip", format!("max_tip_bps must be between 1 and {DEFAULT_MAX_TIP_BPS}")));
+    }
+    if mode == TipMode::Percentage && req.suggestions.iter().any(|&value| value > max_tip_bps) {
+        return Err(bad("invalid_tip_suggestions", "Suggestions cannot exceed max_tip_bps".into()));
+    }
+    Ok(TipSettings {
+        enabled: req.enabled,
+        mode,
+        suggestions: req.suggestions,
+        allow_custom: req.allow_custom,
+        adjust_window_hours,
+        max_tip_bps,
+        configured: true,
+    })
+}
+
+pub async fn get_tip_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<TipSettings>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager", trace_id: sec.trace_id });
+    }
+    Ok(Json(resolve_tip_settings(&state.db, sec.tenant_id).await))
+}
+
+pub async fn upsert_tip_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<UpsertTipSettingsRequest>,
+) -> Result<Json<TipSettings>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "admin", trace_id: sec.trace_id });
+    }
+    let settings = validate_settings(req, sec.trace_id)?;
+    sqlx::query(
+        "INSERT INTO tip_settings (tenant_id, enabled, mode, suggestions, allow_custom, adjust_window_hours, max_tip_bps) VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (tenant_id) DO UPDATE SET enabled = EXCLUDED.enabled, mode = EXCLUDED.mode, suggestions = EXCLUDED.suggestions,
+             allow_custom = EXCLUDED.allow_custom, adjust_window_hours = EXCLUDED.adjust_window_hours, max_tip_bps = EXCLUDED.max_tip_bps, updated_at = NOW()",
+    )
+    .bind(sec.tenant_id)
+    .bind(settings.enabled)
+    .bind(settings.mode.as_str())
+    .bind(&settings.suggestions)
+    .bind(settings.allow_custom)
+    .bind(settings.adjust_window_hours)
+    .bind(settings.max_tip_bps)
+    .execute(&state.db)
+    .await
+    .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to upsert tip settings: {e}")) })?;
+    Ok(Json(settings))
+}
+
+// --- Checkout prompt ---
+
+#[derive(Deserialize)]
+pub struct TipSuggestionsQuery {
+    /// Sale total the tip is calculated on.
+    pub total_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct TipPrompt {
+    pub enabled: bool,
+    pub allow_custom: bool,
+    pub suggestions: Vec<TipSuggestion>,
+}
+
+pub async fn get_tip_suggestions(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(q): Query<TipSuggestionsQuery>,
+) -> Result<Json<TipPrompt>, ApiError> {
+    ensure_tip_role(&sec)?;
+    let settings = resolve_tip_settings(&state.db, sec.tenant_id).await;
+    let rounding = resolve_rounding_policy(&state.db, sec.tenant_id).await.context();
+    Ok(Json(TipPrompt {
+        enabled: settings.enabled,
+        allow_custom: settings.allow_custom,
+        suggestions: settings.suggestions_for(&rounding, q.total_cents),
+    }))
+}
+
+fn ensure_tip_role(sec: &SecurityContext) -> Result<(), ApiError> {
+    if sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::Cashier)) {
+        Ok(())
+    } else {
+        Err(ApiError::ForbiddenMissingRole { role: "admin_or_manager_or_cashier", trace_id: sec.trace_id })
+    }
+}
+
+// --- Tip adjustment ---
+
+#[derive(Deserialize)]
+pub struct AdjustTipRequest {
+    pub tip_cents: i64,
+    /// Raising a tip needs a holder of `order_void`, approving the same ways as a void request.
+    #[serde(flatten)]
+    pub approval: ApproveVoidRequest,
+}
+
+#[derive(Serialize)]
+pub struct TipAdjustment {
+    pub order_id: Uuid,
+    pub previous_tip: Money,
+    pub tip_amount: Money,
+    /// Captured amount after the adjustment (sale plus tip).
+    pub payment_amount: Money,
+    pub adjustable_until: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TippedOrder {
+    status: String,
+    payment_method: String,
+    total: Money,
+    tip_amount: Money,
+    created_at: DateTime<Utc>,
+    created_by: Option<Uuid>,
+    pos_instance_id: Option<Uuid>,
+    store_id: Option<Uuid>,
+    business_date: NaiveDate,
+}
+
+/// Why a card tip can no longer be changed, if it can't.
+fn adjustment_blocker(order: &TippedOrder, settings: &TipSettings, now: DateTime<Utc>) -> Option<(&'static str, String)> {
+    if order.status != "COMPLETED" {
+        return Some(("order_not_completed", format!("Order is {}", order.status)));
+    }
+    if order.payment_method != "card" {
+        return Some(("tip_adjust_card_only", "Only card tips can be adjusted".into()));
+    }
+    let deadline = settings.adjust_deadline(order.created_at);
+    if now > deadline {
+        return Some(("tip_adjust_window_closed", format!("Tips on this order could be adjusted until {}", deadline.to_rfc3339())));
+    }
+    None
+}
+
+/// `POST /orders/:order_id/tip`: replace the tip on a completed card order, e.g. once the signed
+/// receipt is back. Allowed until the tenant's adjust window closes or the payment settles. The new
+/// tip must pass the checkout rules (including the tenant's cap), and raising it needs manager
+/// approval. payment-service is called after the local change commits; if it refuses, the change
+/// is reverted.
+pub async fn adjust_order_tip(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    auth: AuthContext,
+    Path(order_id): Path<Uuid>,
+    Json(req): Json<AdjustTipRequest>,
+) -> Result<Json<TipAdjustment>, ApiError> {
+    ensure_tip_role(&sec)?;
+    let tenant_id = sec.tenant_id;
+    let trace_id = sec.trace_id;
+    if req.tip_cents < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_tip", trace_id, message: Some("tip_cents cannot be negative".into()) });
+    }
+    let settings = resolve_tip_settings(&state.db, tenant_id).await;
+    if !settings.enabled {
+        return Err(ApiError::BadRequest { code: "tips_disabled", trace_id, message: Some("Tips are not enabled for this tenant".into()) });
+    }
+    let rounding = resolve_rounding_policy(&state.db, tenant_id).await.context();
+    let db_err = |e: sqlx::Error| ApiError::Internal { trace_id, message: Some(format!("Failed to adjust tip: {e}")) };
+
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    let order = sqlx::query_as::<_, TippedOrder>(
+        "SELECT status, payment_method, total, tip_amount, created_at, created_by, pos_instance_id, store_id, business_date FROM orders WHERE id = $1 AND tenant_id = $2 FOR UPDATE",
+    )
+    .bind(order_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_err)?
+    .ok_or(ApiError::NotFound { code: "order_not_found", trace_id })?;
+    if let Some((code, message)) = adjustment_blocker(&order, &settings, Utc::now()) {
+        return Err(ApiError::Conflict { code, trace_id, message: Some(message) });
+    }
+    settings.check_checkout_tip(&rounding, order.total.as_cents(), req.tip_cents)?;
+    let previous_tip_cents = order.tip_amount.as_cents();
+    let approval = if req.tip_cents > previous_tip_cents {
+        let (approver, method) = resolve_approver(&state, &sec, &req.approval).await?;
+        Some((approver.actor.id, method))
+    } else {
+        None
+    };
+    // The tip belongs to the day of the sale, which may already be closed.
+    crate::day_close::ensure_day_open(&state.db, tenant_id, order.store_id, order.business_date, trace_id).await?;
+    let delta = Money::from_cents(req.tip_cents - previous_tip_cents);
+    let (payment_id, payment_amount) = sqlx::query_as::<_, (Uuid, Money)>(
+        "UPDATE payments SET tip_amount = $3, amount = amount + $4, tip_adjusted_at = NOW()
+         WHERE id = (SELECT id FROM payments WHERE order_id = $1 AND tenant_id = $2 AND method = 'card' AND status = 'captured' ORDER BY created_at DESC LIMIT 1)
+         RETURNING id, amount",
+    )
+    .bind(order_id)
+    .bind(tenant_id)
+    .bind(Money::from_cents(req.tip_cents).inner())
+    .bind(delta.inner())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_err)?
+    .ok_or(ApiError::Conflict { code: "payment_not_captured", trace_id, message: Some("Order has no captured card payment".into()) })?;
+    sqlx::query("UPDATE orders SET tip_amount = $3 WHERE id = $1 AND tenant_id = $2")
+        .bind(order_id)
+        .bind(tenant_id)
+        .bind(Money::from_cents(req.tip_cents).inner())
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+
+    if state.enable_payment_intents {
+        if let Err(err) = adjust_intent_tip(&state, &sec, &auth, order_id, req.tip_cents).await {
+            if let Err(revert_err) = revert_tip(&state.db, tenant_id, order_id, payment_id, previous_tip_cents, req.tip_cents).await {
+                tracing::error!(?revert_err, %order_id, %tenant_id, "Failed to revert tip after payment-service refused it");
+            }
+            return Err(err);
+        }
+    }
+
+    tracing::info!(
+        %order_id,
+        %tenant_id,
+        employee_id = ?order.created_by,
+        pos_instance_id = ?order.pos_instance_id,
+        approved_by = ?approval.and_then(|(approver, _)| approver),
+        approval_method = approval.map(|(_, method)| method),
+        previous_tip_cents,
+        tip_cents = req.tip_cents,
+        "Order tip adjusted"
+    );
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    {
+        publish_tip_recorded(
+            &state,
+            TipChange {
+                order_id,
+                tenant_id,
+                employee_id: order.created_by,
+                pos_instance_id: order.pos_instance_id,
+                payment_method: &order.payment_method,
+                previous_tip_cents,
+                tip_cents: req.tip_cents,
+                adjustment: true,
+                business_date: order.business_date,
+            },
+        )
+        .await;
+        if let Some(audit) = &state.audit_producer {
+            let _ = audit
+                .emit(
+                    tenant_id,
+                    sec.actor.clone(),
+                    "order",
+                    Some(order_id),
+                    "tip_adjusted",
+                    "order-service",
+                    common_audit::AuditSeverity::Info,
+                    trace_id,
+                    serde_json::json!({
+                        "previous_tip_cents": previous_tip_cents,
+                        "tip_cents": req.tip_cents,
+                        "approved_by": approval.and_then(|(approver, _)| approver),
+                        "approval_method": approval.map(|(_, method)| method),
+                    }),
+                    serde_json::json!({"source":"order-service"}),
+                )
+                .await;
+        }
+    }
+    Ok(Json(TipAdjustment {
+        order_id,
+        previous_tip: order.tip_amount,
+        tip_amount: Money::from_cents(req.tip_cents),
+        payment_amount,
+        adjustable_until: settings.adjust_deadline(order.created_at),
+    }))
+}
+
+/// Put the previous tip back after payment-service refused the new one. Only undoes this request's
+/// change: a later adjustment that already replaced it is left alone.
+async fn revert_tip(db: &PgPool, tenant_id: Uuid, order_id: Uuid, payment_id: Uuid, previous_tip_cents: i64, tip_cents: i64) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let reverted = sqlx::query(
+        "UPDATE payments SET tip_amount = $3, amount = amount - $4 WHERE id = $1 AND tenant_id = $2 AND tip_amount = $5",
+    )
+    .bind(payment_id)
+    .bind(tenant_id)
+    .bind(Money::from_cents(previous_tip_cents).inner())
+    .bind(Money::from_cents(tip_cents - previous_tip_cents).inner())
+    .bind(Money::from_cents(tip_cents).inner())
+    .execute(&mut *tx)
+    .await?;
+    if reverted.rows_affected() == 0 {
+        return Ok(());
+    }
+    sqlx::query("UPDATE orders SET tip_amount = $3 WHERE id = $1 AND tenant_id = $2 AND tip_amount = $4")
+        .bind(order_id)
+        .bind(tenant_id)
+        .bind(Money::from_cents(previous_tip_cents).inner())
+        .bind(Money::from_cents(tip_cents).inner())
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Move the tip on the order's payment intent. payment-service refuses once the intent appears in
+/// a settlement report, which closes the window regardless of the tenant setting.
+async fn adjust_intent_tip(state: &AppState, sec: &SecurityContext, auth: &AuthContext, order_id: Uuid, tip_cents: i64) -> Result<(), ApiError> {
+    let url = format!("{}/payment_intents/tip", state.payment_base_url.trim_end_matches('/'));
+    let roles: Vec<&str> = sec.roles.iter().map(|r| r.as_str()).collect();
+    let resp = state
+        .http_client
+        .post(url)
+        .bearer_auth(&auth.token)
+        .header("X-Tenant-ID", sec.tenant_id.to_string())
+        .header("X-Roles", roles.join(","))
+        .json(&serde_json::json!({"id": format!("pi_{order_id}"), "tipMinor": tip_cents}))
+        .send()
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("Failed to contact payment-service: {e}")) })?;
+    match resp.status() {
+        status if status.is_success() => Ok(()),
+        status if status == reqwest::StatusCode::CONFLICT => Err(ApiError::Conflict {
+            code: "payment_settled",
+            trace_id: sec.trace_id,
+            message: Some("The card payment has already settled".into()),
+        }),
+        status => Err(ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("payment-service tip adjustment failed (status {status})")) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_money::RoundingMode;
+
+    fn settings(mode: TipMode, suggestions: Vec<i32>, allow_custom: bool) -> TipSettings {
+        TipSettings { enabled: true, mode, suggestions, allow_custom, configured: true, ..TipSettings::default() }
+    }
+
+    #[test]
+    fn suggestions_follow_mode_and_rounding() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        let pct = settings(TipMode::Percentage, vec![1500, 1800, 2250], true);
+        let got = pct.suggestions_for(&rounding, 4_233);
+        assert_eq!(got.iter().map(|s| s.label.as_str()).collect::<Vec<_>>(), ["15%", "18%", "22.5%"]);
+        assert_eq!(got.iter().map(|s| s.amount_cents).collect::<Vec<_>>(), [635, 762, 952]);
+
+        let fixed = settings(TipMode::Fixed, vec![100, 250], true);
+        assert_eq!(fixed.suggestions_for(&rounding, 4_233), [
+            TipSuggestion { label: "1.00".into(), amount_cents: 100 },
+            TipSuggestion { label: "2.50".into(), amount_cents: 250 },
+        ]);
+        assert!(TipSettings::default().suggestions_for(&rounding, 4_233).is_empty());
+    }
+
+    #[test]
+    fn checkout_tips_respect_settings() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        let code = |r: Result<(), ApiError>| match r {
+            Ok(()) => "ok",
+            Err(ApiError::BadRequest { code, .. }) => code,
+            Err(_) => "other",
+        };
+        assert_eq!(code(TipSettings::default().check_checkout_tip(&rounding, 1_000, 0)), "ok");
+        assert_eq!(code(TipSettings::default().check_checkout_tip(&rounding, 1_000, 100)), "tips_disabled");
+        let strict = settings(TipMode::Percentage, vec![1500, 2000], false);
+        assert_eq!(code(strict.check_checkout_tip(&rounding, 1_000, 150)), "ok");
+        assert_eq!(code(strict.check_checkout_tip(&rounding, 1_000, 175)), "tip_not_offered");
+        assert_eq!(code(strict.check_checkout_tip(&rounding, 1_000, -1)), "invalid_tip");
+        let open = settings(TipMode::Percentage, vec![1500], true);
+        assert_eq!(code(open.check_checkout_tip(&rounding, 1_000, 175)), "ok");
+    }
+
+    #[test]
+    fn tips_are_capped_at_a_share_of_the_sale() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        let code = |r: Result<(), ApiError>| match r {
+            Ok(()) => "ok",
+            Err(ApiError::BadRequest { code, .. }) => code,
+            Err(_) => "other",
+        };
+        let uncapped = settings(TipMode::Percentage, vec![1500], true);
+        assert_eq!(code(uncapped.check_checkout_tip(&rounding, 1_000, 1_000)), "ok");
+        assert_eq!(code(uncapped.check_checkout_tip(&rounding, 1_000, 1_001)), "tip_exceeds_limit");
+        let capped = TipSettings { max_tip_bps: 2500, ..uncapped };
+        assert_eq!(code(capped.check_checkout_tip(&rounding, 1_000, 250)), "ok");
+        assert_eq!(code(capped.check_checkout_tip(&rounding, 1_000, 251)), "tip_exceeds_limit");
+        // Nothing to tip on: only a zero tip passes.
+        assert_eq!(code(capped.check_checkout_tip(&rounding, -500, 0)), "ok");
+        assert_eq!(code(capped.check_checkout_tip(&rounding, -500, 1)), "tip_exceeds_limit");
+    }
+
+    #[test]
+    fn only_recent_completed_card_orders_can_be_adjusted() {
+        let sold_at = Utc::now() - Duration::hours(3);
+        let order = |status: &str, method: &str| TippedOrder {
+            status: status.into(),
+            payment_method: method.into(),
+            total: Money::from_cents(2_000),
+            tip_amount: Money::from_cents(0),
+            created_at: sold_at,
+            created_by: None,
+            pos_instance_id: None,
+            store_id: None,
+            business_date: sold_at.date_naive(),
+        };
+        let mut window = settings(TipMode::Percentage, vec![1500], true);
+        let now = Utc::now();
+        assert_eq!(adjustment_blocker(&order("COMPLETED", "card"), &window, now), None);
+        assert_eq!(adjustment_blocker(&order("COMPLETED", "cash"), &window, now).map(|b| b.0), Some("tip_adjust_card_only"));
+        assert_eq!(adjustment_blocker(&order("VOIDED", "card"), &window, now).map(|b| b.0), Some("order_not_completed"));
+        window.adjust_window_hours = 2;
+        assert_eq!(adjustment_blocker(&order("COMPLETED", "card"), &window, now).map(|b| b.0), Some("tip_adjust_window_closed"));
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        let req = |mode: &str, suggestions: Vec<i32>, allow_custom: bool, window: Option<i32>| UpsertTipSettingsRequest {
+            enabled: true,
+            mode: mode.into(),
+            suggestions,
+            allow_custom,
+            adjust_window_hours: window,
+            max_tip_bps: None,
+        };
+        let code = |r: Result<TipSettings, ApiError>| match r {
+            Ok(_) => "ok",
+            Err(ApiError::BadRequest { code, .. }) => code,
+            Err(_) => "other",
+        };
+        assert_eq!(code(validate_settings(req("percentage", vec![1500, 1800], true, None), None)), "ok");
+        assert_eq!(code(validate_settings(req("tiered", vec![], true, None), None)), "invalid_tip_mode");
+        assert_eq!(code(validate_settings(req("percentage", vec![0], true, None), None)), "invalid_tip_suggestions");
+        assert_eq!(code(validate_settings(req("fixed", vec![], false, None), None)), "invalid_tip_suggestions");
+        assert_eq!(code(validate_settings(req("fixed", vec![100, 200, 300, 400, 500], true, None), None)), "invalid_tip_suggestions");
+        assert_eq!(code(validate_settings(req("fixed", vec![100], true, Some(200)), None)), "invalid_adjust_window");
+        let capped = |max_tip_bps| UpsertTipSettingsRequest { max_tip_bps: Some(max_tip_bps), ..req("percentage", vec![1500, 1800], true, None) };
+        assert_eq!(code(validate_settings(capped(2000), None)), "ok");
+        assert_eq!(code(validate_settings(capped(0), None)), "invalid_max_tip");
+        assert_eq!(code(validate_settings(capped(10_001), None)), "invalid_max_tip");
+        assert_eq!(code(validate_settings(capped(1600), None)), "invalid_tip_suggestions");
+    }
+}
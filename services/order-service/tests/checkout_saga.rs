@@ -0,0 +1,226 @@
+//! Checkout saga compensation against Postgres and stand-in inventory and payment services: a
+//! failed release or void is retried by the sweeper, the sweeper calls out with the service token
+//! and no row lock held, and concurrent sweepers compensate each saga once. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::routing::{delete, post};
+use axum::{Json, Router};
+use common_http_errors::ApiError;
+use common_security::SecurityContext;
+use common_test_fixtures::{itests_enabled, TestPostgres, TestSigner};
+use http::{HeaderMap, StatusCode};
+use order_service::checkout_saga::{sweep_stuck_sagas, CheckoutSaga};
+use order_service::AppState;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const SERVICE_TOKEN: &str = "order-service-token";
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A release or void the stand-in services received.
+#[derive(Debug, Clone)]
+struct Call {
+    service: &'static str,
+    target: String,
+    authorization: Option<String>,
+    /// Saga status read with `FOR UPDATE NOWAIT` while the call was in flight; `None` if the row
+    /// was locked.
+    saga_status: Option<String>,
+}
+
+#[derive(Clone)]
+struct Upstream {
+    db: PgPool,
+    calls: Arc<Mutex<Vec<Call>>>,
+    failing: Arc<Mutex<Option<&'static str>>>,
+}
+
+impl Upstream {
+    async fn start(db: &PgPool) -> (Self, String) {
+        let upstream = Self { db: db.clone(), calls: Arc::default(), failing: Arc::default() };
+        let router = Router::new()
+            .route("/inventory/reservations/:id", delete(release))
+            .route("/payment_intents/void", post(void))
+            .with_state(upstream.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        (upstream, url)
+    }
+
+    fn fail(&self, service: Option<&'static str>) {
+        *self.failing.lock().unwrap() = service;
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    async fn record(&self, service: &'static str, saga_id: Uuid, target: String, headers: &HeaderMap) -> StatusCode {
+        let saga_status = sqlx::query_scalar::<_, String>("SELECT status FROM checkout_sagas WHERE id = $1 FOR UPDATE NOWAIT")
+            .bind(saga_id)
+            .fetch_one(&self.db)
+            .await
+            .ok();
+        let authorization = headers.get("Authorization").and_then(|value| value.to_str().ok()).map(str::to_string);
+        self.calls.lock().unwrap().push(Call { service, target, authorization, saga_status });
+        if *self.failing.lock().unwrap() == Some(service) {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+}
+
+async fn release(State(upstream): State<Upstream>, Path(id): Path<Uuid>, headers: HeaderMap) -> StatusCode {
+    upstream.record("inventory", id, id.to_string(), &headers).await
+}
+
+/// Intent ids in these tests are `pi_<order id>`.
+async fn void(State(upstream): State<Upstream>, headers: HeaderMap, Json(body): Json<Value>) -> StatusCode {
+    let intent = body["id"].as_str().unwrap_or_default().to_string();
+    let saga_id = intent.trim_start_matches("pi_").parse().unwrap_or_default();
+    upstream.record("payment", saga_id, intent, &headers).await
+}
+
+fn state(db: &PgPool, upstream_url: &str) -> AppState {
+    AppState {
+        db: db.clone(),
+        jwt_verifier: TestSigner::generate().verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: upstream_url.into(),
+        payment_base_url: upstream_url.into(),
+        enable_payment_intents: true,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    }
+}
+
+fn cashier(tenant: Uuid) -> SecurityContext {
+    serde_json::from_value(json!({
+        "tenant_id": tenant,
+        "actor": {"id": Uuid::new_v4(), "name": "cashier", "email": null},
+        "roles": [],
+        "trace_id": null,
+    }))
+    .unwrap()
+}
+
+/// A saga that reserved inventory and authorized payment intent `pi_<id>`.
+async fn authorized_saga(db: &PgPool, tenant: Uuid) -> CheckoutSaga {
+    let id = Uuid::new_v4();
+    let mut saga = CheckoutSaga::begin(db, tenant, id, None).await.unwrap();
+    saga.inventory_reserved(db, None).await.unwrap();
+    saga.payment_authorized(db, format!("pi_{id}"), None).await.unwrap();
+    saga
+}
+
+async fn saga_row(db: &PgPool, id: Uuid) -> (String, i32, Option<String>, bool) {
+    sqlx::query_as("SELECT status, compensation_attempts, last_error, claimed_until IS NOT NULL FROM checkout_sagas WHERE id = $1")
+        .bind(id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+// The sweeper works across tenants, so both scenarios run in one test against one database.
+#[tokio::test]
+async fn sagas_are_compensated_once_by_whoever_claims_them() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let (upstream, url) = Upstream::start(db).await;
+    let state = state(db, &url);
+    let tenant = Uuid::new_v4();
+    failed_steps_are_retried_by_the_sweeper(db, &upstream, &state, tenant).await;
+    concurrent_sweepers_compensate_each_timed_out_saga_once(db, &upstream, &state, tenant).await;
+}
+
+async fn failed_steps_are_retried_by_the_sweeper(db: &PgPool, upstream: &Upstream, state: &AppState, tenant: Uuid) {
+    for (failing, message) in [("inventory", "503 Service Unavailable"), ("payment", "Payment void failed with status 503")] {
+        let saga = authorized_saga(db, tenant).await;
+        upstream.fail(Some(failing));
+        saga.compensate(state, &cashier(tenant), "cashier-token", "order persistence failed").await;
+
+        let (status, attempts, last_error, claimed) = saga_row(db, saga.id).await;
+        assert_eq!((status.as_str(), attempts, claimed), ("compensating", 1, false), "{failing}");
+        assert!(last_error.unwrap().contains(message), "{failing}");
+        // Both steps run even when one fails, and a late request can no longer complete the saga.
+        let first_try: Vec<_> = upstream.calls().into_iter().filter(|call| call.target.ends_with(&saga.id.to_string())).collect();
+        assert_eq!(first_try.len(), 2, "{failing}");
+        assert!(first_try.iter().all(|call| call.authorization.as_deref() == Some("Bearer cashier-token")));
+        let mut tx = db.begin().await.unwrap();
+        let late = saga.complete(&mut tx, None).await;
+        assert!(matches!(late, Err(ApiError::Conflict { code: "checkout_timed_out", .. })), "{late:?}");
+        tx.rollback().await.unwrap();
+
+        upstream.fail(None);
+        assert_eq!(sweep_stuck_sagas(state, TIMEOUT, 10, SERVICE_TOKEN).await.unwrap(), 1, "{failing}");
+        let (status, attempts, _, claimed) = saga_row(db, saga.id).await;
+        assert_eq!((status.as_str(), attempts, claimed), ("compensated", 2, false), "{failing}");
+        let retry: Vec<_> = upstream.calls().into_iter().filter(|call| call.target.ends_with(&saga.id.to_string())).skip(2).collect();
+        assert_eq!(retry.len(), 2, "{failing}");
+        assert!(retry.iter().all(|call| call.authorization.as_deref() == Some("Bearer order-service-token")));
+    }
+}
+
+async fn concurrent_sweepers_compensate_each_timed_out_saga_once(db: &PgPool, upstream: &Upstream, state: &AppState, tenant: Uuid) {
+    let mut stuck = Vec::new();
+    for _ in 0..6 {
+        stuck.push(authorized_saga(db, tenant).await.id);
+    }
+    sqlx::query("UPDATE checkout_sagas SET updated_at = NOW() - INTERVAL '10 minutes' WHERE id = ANY($1)")
+        .bind(&stuck)
+        .execute(db)
+        .await
+        .unwrap();
+    let in_flight = authorized_saga(db, tenant).await;
+
+    let (first, second) = tokio::join!(
+        sweep_stuck_sagas(state, TIMEOUT, 10, SERVICE_TOKEN),
+        sweep_stuck_sagas(state, TIMEOUT, 10, SERVICE_TOKEN),
+    );
+    assert_eq!(first.unwrap() + second.unwrap(), stuck.len());
+
+    let calls: Vec<_> = upstream.calls().into_iter().filter(|call| stuck.iter().any(|id| call.target.ends_with(&id.to_string()))).collect();
+    for id in &stuck {
+        let releases = calls.iter().filter(|call| call.service == "inventory" && call.target == id.to_string()).count();
+        let voids = calls.iter().filter(|call| call.service == "payment" && call.target == format!("pi_{id}")).count();
+        assert_eq!((releases, voids), (1, 1), "saga {id}");
+        assert_eq!(saga_row(db, *id).await, ("compensated".to_string(), 1, Some("checkout timed out".to_string()), false));
+    }
+    for call in &calls {
+        assert_eq!(call.authorization.as_deref(), Some("Bearer order-service-token"));
+        assert_eq!(call.saga_status.as_deref(), Some("compensating"), "the claim is committed and unlocked during {call:?}");
+    }
+    assert_eq!(saga_row(db, in_flight.id).await.0, "payment_authorized", "sagas within the timeout are left alone");
+
+    // A claim that is still running is skipped; an expired one is taken over.
+    let orphaned = authorized_saga(db, tenant).await;
+    sqlx::query("UPDATE checkout_sagas SET status = 'compensating', claimed_until = NOW() + INTERVAL '1 minute' WHERE id = $1")
+        .bind(orphaned.id)
+        .execute(db)
+        .await
+        .unwrap();
+    assert_eq!(sweep_stuck_sagas(state, TIMEOUT, 10, SERVICE_TOKEN).await.unwrap(), 0);
+    sqlx::query("UPDATE checkout_sagas SET claimed_until = NOW() - INTERVAL '1 second' WHERE id = $1")
+        .bind(orphaned.id)
+        .execute(db)
+        .await
+        .unwrap();
+    assert_eq!(sweep_stuck_sagas(state, TIMEOUT, 10, SERVICE_TOKEN).await.unwrap(), 1);
+    assert_eq!(saga_row(db, orphaned.id).await.0, "compensated");
+}
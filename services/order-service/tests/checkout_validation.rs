@@ -0,0 +1,60 @@
+use common_http_errors::test_helpers::assert_field_errors;
+use common_http_errors::validation::Validate;
+use order_service::order_handlers::NewOrder;
+use serde_json::json;
+use uuid::Uuid;
+
+fn line(quantity: i32) -> serde_json::Value {
+    json!({"product_id": Uuid::new_v4(), "quantity": quantity, "unit_price": "2.50", "line_total": "2.50"})
+}
+
+#[tokio::test]
+async fn checkout_payloads_name_each_invalid_field() {
+    let mut weighed = line(1);
+    weighed["measured_quantity"] = json!("0.000");
+    let order: NewOrder = serde_json::from_value(json!({
+        "items": [line(1), line(0), weighed],
+        "payment_method": "cash",
+        "payment": {"method": "cash", "amount_cents": -100},
+        "total": "5.00",
+        "customer_id": null,
+        "customer_name": null,
+        "customer_email": "not-an-email",
+        "store_id": null,
+        "offline": null,
+        "idempotency_key": null
+    })).unwrap();
+    let err = order.ensure_valid(None).expect_err("invalid order");
+    assert_field_errors(err, &["items[1].quantity", "items[2].measured_quantity", "payment.amount_cents", "customer_email"]).await;
+}
+
+#[tokio::test]
+async fn empty_orders_are_rejected() {
+    let order: NewOrder = serde_json::from_value(json!({
+        "items": [], "payment_method": "cash", "total": "0", "customer_id": null, "customer_name": null,
+        "customer_email": null, "store_id": null, "offline": null, "idempotency_key": null
+    })).unwrap();
+    assert_field_errors(order.ensure_valid(None).expect_err("no items"), &["items"]).await;
+}
+
+#[tokio::test]
+async fn typed_cash_tenders_are_read_per_locale() {
+    let order = |payment: serde_json::Value| -> NewOrder {
+        serde_json::from_value(json!({
+            "items": [line(1)], "payment_method": "cash", "payment": payment, "total": "2.50", "customer_id": null,
+            "customer_name": null, "customer_email": null, "store_id": null, "offline": null, "idempotency_key": null
+        })).unwrap()
+    };
+    let mut typed = order(json!({"method": "cash", "amount_input": "1.234,50 €", "locale": "de-DE"}));
+    typed.ensure_valid(None).expect("valid tender");
+    let payment = typed.payment.as_mut().unwrap();
+    payment.apply_amount_input();
+    assert_eq!(payment.amount_cents, 123_450);
+
+    let ambiguous = order(json!({"method": "cash", "amount_input": "12,50"}));
+    assert_field_errors(ambiguous.ensure_valid(None).expect_err("ambiguous"), &["payment.amount_input"]).await;
+    let unknown = order(json!({"method": "cash", "amount_input": "12", "locale": "42"}));
+    assert_field_errors(unknown.ensure_valid(None).expect_err("bad locale"), &["payment.locale"]).await;
+    let both = order(json!({"method": "cash", "amount_cents": 500, "amount_input": "-5"}));
+    assert_field_errors(both.ensure_valid(None).expect_err("conflict"), &["payment.amount_input", "payment.amount_input"]).await;
+}
@@ -2,7 +2,7 @@
 
 use axum::{Router, body::{Body, to_bytes}};
 use http::{Request, StatusCode};
-use order_service::{build_router, AppState, build_jwt_verifier_from_env};
+use order_service::{build_router, AppState, build_jwt_verifier};
 use serde_json::json;
 use tower::ServiceExt;
 use uuid::Uuid;
@@ -16,7 +16,8 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           price numeric NOT NULL,
           sku text,
           tax_code text,
-          active boolean NOT NULL DEFAULT true
+          active boolean NOT NULL DEFAULT true,
+          deleted_at timestamptz
         );
         CREATE TABLE IF NOT EXISTS orders (
           id uuid PRIMARY KEY,
@@ -26,12 +27,21 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           customer_id uuid NULL,
           customer_name text NULL,
           customer_email text NULL,
+          customer_email_encrypted bytea NULL,
+          customer_email_hash bytea NULL,
+          customer_name_encrypted bytea NULL,
+          customer_name_hash bytea NULL,
+          pii_key_id text NULL,
           store_id uuid NULL,
           created_at timestamptz NOT NULL DEFAULT now(),
           offline boolean NOT NULL DEFAULT false,
           payment_method text NOT NULL,
           idempotency_key text NULL,
-          exchange_of_order_id uuid NULL
+          rounding_adjustment numeric NOT NULL DEFAULT 0,
+          tip_amount numeric NOT NULL DEFAULT 0,
+          exchange_of_order_id uuid NULL,
+          created_by uuid NULL,
+          pos_instance_id uuid NULL
         );
         CREATE TABLE IF NOT EXISTS order_items (
           id uuid PRIMARY KEY,
@@ -67,6 +77,7 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           amount numeric NOT NULL,
           status text NOT NULL,
           change_cents int NULL,
+          tip_amount numeric NOT NULL DEFAULT 0,
           created_at timestamptz NOT NULL DEFAULT now()
         );
     "#).execute(pool).await;
@@ -87,7 +98,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
     std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
     std::env::set_var("JWT_ISSUER", "https://auth.novapos.local");
     std::env::set_var("JWT_AUDIENCE", "novapos-admin");
-    let verifier = build_jwt_verifier_from_env().await.expect("jwt verifier");
+    let verifier = build_jwt_verifier(&common_config::JwtSettings::from_env().await.expect("jwt settings")).await.expect("jwt verifier");
     let state = AppState {
         db: pool,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -97,6 +108,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
         inventory_base_url: "http://localhost:8087".to_string(),
         payment_base_url: "http://localhost:8086".to_string(),
         enable_payment_intents: false,
+        pii_key: None,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         audit_producer: None,
     };
@@ -183,11 +195,11 @@ async fn exchange_net_collect() {
     let original_order: serde_json::Value = serde_json::from_slice(&to_bytes(resp.into_body(), 1024*1024).await.unwrap()).unwrap();
     let original_order_id = original_order["id"].as_str().unwrap().parse::<Uuid>().unwrap();
 
-    // Exchange: return A, buy B with card amount equal to B total (1500)
+    // Exchange: return A, buy B; the card only covers the difference (500)
     let exch_body = json!({
         "return_items": [{"product_id": a, "qty": 1}],
         "new_items": [{"sku": "SKU-B", "qty": 1}],
-        "payment": {"method": "card", "amount_cents": 1500}
+        "payment": {"method": "card", "amount_cents": 500}
     });
     let resp = app.clone().oneshot(
         Request::builder().method("POST").uri(format!("/orders/{}/exchange", original_order_id))
@@ -204,6 +216,7 @@ async fn exchange_net_collect() {
     assert_eq!(body["new_order_total_cents"].as_i64().unwrap(), 1500);
     assert_eq!(body["net_delta_cents"].as_i64().unwrap(), 500);
     assert_eq!(body["net_direction"].as_str().unwrap(), "collect");
+    assert!(body.get("refund_tender").is_none());
 
     // Verify linkage exists
     let exch_id = body["exchange_order_id"].as_str().unwrap().parse::<Uuid>().unwrap();
@@ -247,11 +260,10 @@ async fn exchange_net_refund() {
     let original_order: serde_json::Value = serde_json::from_slice(&to_bytes(resp.into_body(), 1024*1024).await.unwrap()).unwrap();
     let original_order_id = original_order["id"].as_str().unwrap().parse::<Uuid>().unwrap();
 
-    // Exchange: return A, buy B with card amount equal to B total (1000)
+    // Exchange: return A, buy B; the credit covers B, so no payment is taken
     let exch_body = json!({
         "return_items": [{"product_id": a, "qty": 1}],
-        "new_items": [{"sku": "SKU-B", "qty": 1}],
-        "payment": {"method": "card", "amount_cents": 1000}
+        "new_items": [{"sku": "SKU-B", "qty": 1}]
     });
     let resp = app.clone().oneshot(
         Request::builder().method("POST").uri(format!("/orders/{}/exchange", original_order_id))
@@ -267,4 +279,6 @@ async fn exchange_net_refund() {
     assert_eq!(body["new_order_total_cents"].as_i64().unwrap(), 1000);
     assert_eq!(body["net_delta_cents"].as_i64().unwrap(), -500);
     assert_eq!(body["net_direction"].as_str().unwrap(), "refund");
+    // The difference goes back on the original order's tender
+    assert_eq!(body["refund_tender"].as_str().unwrap(), "card");
 }
@@ -0,0 +1,150 @@
+//! Editing open orders through the router against Postgres. Needs Postgres: set ENABLE_ITESTS=1
+//! (and TEST_DATABASE_URL to skip the container).
+
+use axum::body::{to_bytes, Body};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, OrderFixture, ProductFixture, TestPostgres, TestSigner};
+use http::{Request, StatusCode};
+use order_service::{build_router, AppState};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "order-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn app(db: &PgPool, signer: &TestSigner) -> Router {
+    std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
+    build_router(AppState {
+        db: db.clone(),
+        jwt_verifier: signer.verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: "http://localhost:8087".into(),
+        payment_base_url: "http://localhost:8086".into(),
+        enable_payment_intents: false,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    })
+}
+
+async fn send(app: &Router, token: &str, tenant: Uuid, method: &str, uri: String, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", "manager")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// A PENDING card order (no cash rounding) for tax-exempt products, so totals are plain sums.
+async fn open_order(db: &PgPool, tenant: Uuid, lines: &[(i64, i32)]) -> (Vec<Uuid>, common_test_fixtures::SeededOrder) {
+    let mut fixture = OrderFixture::new(tenant).status("PENDING").payment_method("card");
+    let mut products = Vec::new();
+    for (price_cents, quantity) in lines {
+        let product = ProductFixture::new(tenant).price_cents(*price_cents).insert(db).await.unwrap();
+        sqlx::query("UPDATE products SET tax_code = 'EXEMPT' WHERE id = $1").bind(product.id).execute(db).await.unwrap();
+        fixture = fixture.product(&product, *quantity);
+        products.push(product.id);
+    }
+    (products, fixture.insert(db).await.unwrap())
+}
+
+#[tokio::test]
+async fn lines_with_modifiers_can_be_edited() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let tenant = Uuid::new_v4();
+    let (_, order) = open_order(db, tenant, &[(450, 1), (200, 1)]).await;
+    let modifiers = json!([{ "modifier_id": Uuid::new_v4(), "group": "Milk", "name": "Oat", "price_delta": "0.50" }]);
+    sqlx::query("UPDATE order_items SET modifiers = $2 WHERE id = $1").bind(order.item_ids[0]).bind(&modifiers).execute(db).await.unwrap();
+
+    let app = app(db, &signer);
+    let token = signer.token(tenant, &["manager"]);
+    let uri = format!("/orders/{}/items/{}", order.id, order.item_ids[0]);
+    let (status, body) = send(&app, &token, tenant, "PATCH", uri, json!({ "quantity": 2 })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let line = body["items"].as_array().unwrap().iter().find(|item| item["id"] == json!(order.item_ids[0])).unwrap();
+    assert_eq!(line["quantity"], 2);
+    assert_eq!(line["modifiers"][0]["name"], "Oat", "{line}");
+
+    let after: Value = sqlx::query_scalar("SELECT after FROM order_line_edits WHERE order_item_id = $1").bind(order.item_ids[0]).fetch_one(db).await.unwrap();
+    assert_eq!(after["quantity"], 2);
+}
+
+#[tokio::test]
+async fn edits_reprice_the_order_and_leave_an_audit_row() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let tenant = Uuid::new_v4();
+    let (products, order) = open_order(db, tenant, &[(450, 2), (200, 1)]).await;
+    let app = app(db, &signer);
+    let token = signer.token(tenant, &["manager"]);
+    let total = |body: &Value| body["order"]["total"].as_str().unwrap().parse::<f64>().unwrap();
+
+    // 9.00 + 2.00 → 3 × 4.50 + 2.00
+    let line = |item: Uuid| format!("/orders/{}/items/{item}", order.id);
+    let (status, body) = send(&app, &token, tenant, "PATCH", line(order.item_ids[0]), json!({ "quantity": 3 })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(total(&body), 15.50);
+
+    // Adding a product already on the order grows its line instead of adding one.
+    let (status, body) = send(&app, &token, tenant, "POST", format!("/orders/{}/items", order.id), json!({ "product_id": products[1], "quantity": 2 })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    assert_eq!(total(&body), 19.50);
+
+    let (status, body) = send(&app, &token, tenant, "PATCH", line(order.item_ids[1]), json!({ "unit_price": "1.50", "override_reason": "price_match" })).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(total(&body), 18.00);
+
+    let (status, body) = send(&app, &token, tenant, "DELETE", line(order.item_ids[0]), Value::Null).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(total(&body), 4.50);
+    let stored: f64 = sqlx::query_scalar("SELECT total::float8 FROM orders WHERE id = $1").bind(order.id).fetch_one(db).await.unwrap();
+    assert_eq!(stored, 4.50);
+
+    let audit: Vec<(String, Option<String>, f64, f64)> = sqlx::query_as(
+        "SELECT action, reason_code, total_before::float8, total_after::float8 FROM order_line_edits WHERE tenant_id = $1 AND order_id = $2 ORDER BY created_at",
+    )
+    .bind(tenant)
+    .bind(order.id)
+    .fetch_all(db)
+    .await
+    .unwrap();
+    let expected = [
+        ("quantity_changed", None, 11.00, 15.50),
+        ("line_added", None, 15.50, 19.50),
+        ("price_override", Some("price_match"), 19.50, 18.00),
+        ("line_removed", None, 18.00, 4.50),
+    ];
+    assert_eq!(audit.len(), expected.len(), "{audit:?}");
+    for (row, (action, reason, before, after)) in audit.iter().zip(expected) {
+        assert_eq!((row.0.as_str(), row.1.as_deref(), row.2, row.3), (action, reason, before, after));
+    }
+
+    // The last line cannot be removed, and a completed order cannot be edited at all.
+    let (status, _) = send(&app, &token, tenant, "DELETE", line(order.item_ids[1]), Value::Null).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    sqlx::query("UPDATE orders SET status = 'COMPLETED' WHERE id = $1").bind(order.id).execute(db).await.unwrap();
+    let (status, _) = send(&app, &token, tenant, "PATCH", line(order.item_ids[1]), json!({ "quantity": 5 })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
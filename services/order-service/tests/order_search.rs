@@ -0,0 +1,109 @@
+//! Order search filters through the router against Postgres: total bounds, terminal and receipt
+//! prefix. Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use axum::body::{to_bytes, Body};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres, TestSigner};
+use http::{Request, StatusCode};
+use order_service::{build_router, AppState};
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn app(db: &PgPool, signer: &TestSigner) -> Router {
+    build_router(AppState {
+        db: db.clone(),
+        jwt_verifier: signer.verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: "http://localhost:8087".into(),
+        payment_base_url: "http://localhost:8086".into(),
+        enable_payment_intents: false,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    })
+}
+
+/// Ids of the orders `GET /orders?{query}` returns, sorted, or the error code.
+async fn search(app: &Router, signer: &TestSigner, tenant: Uuid, query: &str) -> Result<Vec<Uuid>, (StatusCode, String)> {
+    let user = Uuid::new_v4();
+    let request = Request::builder()
+        .uri(format!("/orders?{query}"))
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-User-ID", user.to_string())
+        .header("X-Roles", "manager")
+        .header("Authorization", format!("Bearer {}", signer.token_for(user, tenant, &["manager"])))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap_or(Value::Null);
+    if status != StatusCode::OK {
+        return Err((status, body["code"].as_str().unwrap_or_default().to_string()));
+    }
+    let mut ids: Vec<Uuid> = body.as_array().unwrap().iter().map(|order| order["id"].as_str().unwrap().parse().unwrap()).collect();
+    ids.sort();
+    Ok(ids)
+}
+
+fn sorted(mut ids: Vec<Uuid>) -> Vec<Uuid> {
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn search_filters_by_total_terminal_and_receipt() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let app = app(db, &signer);
+    let tenant = Uuid::new_v4();
+
+    let mut orders = Vec::new();
+    for cents in [500, 1_250, 4_000] {
+        orders.push(OrderFixture::new(tenant).line(Uuid::new_v4(), 1, cents).insert(db).await.unwrap().id);
+    }
+    let (small, medium, large) = (orders[0], orders[1], orders[2]);
+    let terminal = Uuid::new_v4();
+    sqlx::query("UPDATE orders SET pos_instance_id = $1 WHERE id = ANY($2)")
+        .bind(terminal)
+        .bind(vec![small, large])
+        .execute(db)
+        .await
+        .unwrap();
+
+    // Total bounds are inclusive and combine with each other and with other filters.
+    assert_eq!(search(&app, &signer, tenant, "min_total=12.50").await.unwrap(), sorted(vec![medium, large]));
+    assert_eq!(search(&app, &signer, tenant, "max_total=12.50").await.unwrap(), sorted(vec![small, medium]));
+    assert_eq!(search(&app, &signer, tenant, "min_total=5&max_total=12.49").await.unwrap(), vec![small]);
+    assert_eq!(
+        search(&app, &signer, tenant, "min_total=20&max_total=10").await,
+        Err((StatusCode::BAD_REQUEST, "invalid_amount_range".into()))
+    );
+
+    assert_eq!(search(&app, &signer, tenant, &format!("terminal_id={terminal}")).await.unwrap(), sorted(vec![small, large]));
+    assert_eq!(search(&app, &signer, tenant, &format!("pos_instance_id={terminal}&min_total=10")).await.unwrap(), vec![large]);
+    assert_eq!(search(&app, &signer, tenant, &format!("terminal_id={}", Uuid::new_v4())).await.unwrap(), Vec::<Uuid>::new());
+
+    // Receipts: the full id, or a printed prefix of 8+ hex digits in any case, with or without '#'.
+    let printed = medium.to_string();
+    for receipt in [printed.clone(), printed[..8].to_string(), format!("%23{}", printed[..13].to_uppercase()), printed.replace('-', "")[..20].to_string()] {
+        assert_eq!(search(&app, &signer, tenant, &format!("receipt={receipt}")).await.unwrap(), vec![medium], "{receipt}");
+    }
+    for receipt in [&printed[..7], "zzzzzzzz"] {
+        assert_eq!(
+            search(&app, &signer, tenant, &format!("receipt={receipt}")).await,
+            Err((StatusCode::BAD_REQUEST, "invalid_receipt".into())),
+            "{receipt}"
+        );
+    }
+    // Another tenant's receipt never matches.
+    assert_eq!(search(&app, &signer, Uuid::new_v4(), &format!("receipt={}", &printed[..8])).await.unwrap(), Vec::<Uuid>::new());
+}
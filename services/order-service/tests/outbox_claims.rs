@@ -0,0 +1,186 @@
+//! Outbox relay workers running side by side: every row goes out once, rows of one key keep their
+//! order, a crashed worker's claims are taken over once its lease runs out, poison rows are
+//! dead-lettered, and published rows are purged after the retention period.
+//! Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use common_test_fixtures::{itests_enabled, TestPostgres};
+use order_service::outbox::{purge_published, requeue_dead, Delivery, OutboxRelay};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate order-service");
+    Some(postgres)
+}
+
+/// Queue `per_key` rows for each of `keys` keys, interleaved; returns the ids.
+async fn enqueue(db: &PgPool, tenant: Uuid, keys: usize, per_key: usize) -> Vec<i64> {
+    let mut ids = Vec::new();
+    for seq in 0..per_key {
+        for key in 0..keys {
+            let id: i64 = sqlx::query_scalar("INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, 'order.completed', $2, $3) RETURNING id")
+                .bind(tenant.to_string())
+                .bind(json!({ "seq": seq }))
+                .bind(format!("{tenant}-{key}"))
+                .fetch_one(db)
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// (worker, outbox id, key, seq) in the order the sends happened.
+type Sends = Arc<Mutex<Vec<(usize, i64, String, u64)>>>;
+
+async fn unpublished(db: &PgPool, tenant: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM outbox WHERE tenant_id = $1 AND published_at IS NULL").bind(tenant.to_string()).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn concurrent_workers_publish_each_row_once_in_key_order() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool().clone();
+    let tenant = Uuid::new_v4();
+    let ids = enqueue(&db, tenant, 30, 10).await;
+
+    let sent: Sends = Arc::default();
+    // The first send of one row fails, so its key has to wait for the retry.
+    let fail_once = Arc::new(AtomicBool::new(true));
+    let flaky = ids[40];
+    let mut workers = Vec::new();
+    for worker in 0..4 {
+        let (db, sent, fail_once) = (db.clone(), sent.clone(), fail_once.clone());
+        workers.push(tokio::spawn(async move {
+            let relay = OutboxRelay::new(Duration::from_secs(30), 25);
+            let deadline = Instant::now() + Duration::from_secs(60);
+            while unpublished(&db, tenant).await > 0 && Instant::now() < deadline {
+                relay
+                    .relay_batch(&db, |row| {
+                        let (sent, fail_once) = (sent.clone(), fail_once.clone());
+                        async move {
+                            tokio::task::yield_now().await;
+                            if row.id == flaky && fail_once.swap(false, Ordering::SeqCst) {
+                                return Err("broker unavailable");
+                            }
+                            let seq = row.payload["seq"].as_u64().unwrap_or_default();
+                            sent.lock().unwrap().push((worker, row.id, row.key().to_string(), seq));
+                            Ok(())
+                        }
+                    })
+                    .await
+                    .expect("relay batch");
+            }
+        }));
+    }
+    for worker in workers {
+        worker.await.unwrap();
+    }
+
+    assert_eq!(unpublished(&db, tenant).await, 0);
+    let sent = sent.lock().unwrap();
+    let mine: Vec<_> = sent.iter().filter(|(_, id, _, _)| ids.contains(id)).collect();
+    let unique: HashSet<i64> = mine.iter().map(|(_, id, _, _)| *id).collect();
+    assert_eq!(mine.len(), ids.len(), "every row is sent exactly once");
+    assert_eq!(unique.len(), ids.len());
+    assert!(!fail_once.load(Ordering::SeqCst), "the flaky row was attempted");
+
+    let mut last_seq: HashMap<&str, u64> = HashMap::new();
+    for (_, _, key, seq) in &mine {
+        if let Some(previous) = last_seq.insert(key.as_str(), *seq) {
+            assert!(*seq > previous, "{key}: seq {seq} sent after {previous}");
+        }
+    }
+    let workers_used: HashSet<usize> = mine.iter().map(|(worker, _, _, _)| *worker).collect();
+    assert!(workers_used.len() > 1, "the batch was shared between workers");
+}
+
+#[tokio::test]
+async fn expired_lease_is_taken_over() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let ids = enqueue(db, tenant, 1, 3).await;
+    let mine = |rows: Vec<order_service::outbox::OutboxRow>| rows.into_iter().map(|row| row.id).filter(|id| ids.contains(id)).collect::<Vec<_>>();
+
+    let crashed = OutboxRelay::new(Duration::from_secs(1), 1000);
+    let survivor = OutboxRelay::new(Duration::from_secs(1), 1000);
+    assert_eq!(mine(crashed.claim(db).await.unwrap()), ids);
+    assert!(mine(survivor.claim(db).await.unwrap()).is_empty(), "claimed rows are not handed out twice");
+
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_eq!(mine(survivor.claim(db).await.unwrap()), ids, "expired claims are taken over");
+
+    // The crashed worker comes back and finishes its send late.
+    assert!(!crashed.mark_published(db, ids[0]).await.unwrap());
+    assert!(survivor.mark_published(db, ids[1]).await.unwrap());
+    assert!(!survivor.record_failure(db, ids[2], "broker unavailable").await.unwrap(), "retries are unlimited by default");
+    let (retries, claimed_by, last_error): (i32, Option<String>, Option<String>) =
+        sqlx::query_as("SELECT retry_count, claimed_by, last_error FROM outbox WHERE id = $1").bind(ids[2]).fetch_one(db).await.unwrap();
+    assert_eq!((retries, claimed_by, last_error.as_deref()), (1, None, Some("broker unavailable")));
+
+    let outcomes = survivor.relay_batch(db, |_| async { Ok::<(), ()>(()) }).await.unwrap();
+    assert!(outcomes.iter().any(|(row, delivery)| row.id == ids[2] && *delivery == Delivery::Published));
+    assert_eq!(unpublished(db, tenant).await, 0);
+}
+
+#[tokio::test]
+async fn poison_row_is_dead_lettered_and_requeued() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let ids = enqueue(db, tenant, 1, 3).await;
+    let poison = ids[0];
+
+    let relay = OutboxRelay::new(Duration::from_secs(30), 1000).with_max_retries(2);
+    let mut outcomes = Vec::new();
+    for _ in 0..2 {
+        let batch = relay
+            .relay_batch(db, |row| async move { if row.id == poison { Err("schema registry rejected payload") } else { Ok(()) } })
+            .await
+            .unwrap();
+        outcomes.push(batch.into_iter().filter(|(row, _)| ids.contains(&row.id)).map(|(_, delivery)| delivery).collect::<Vec<_>>());
+    }
+    // Failing, the poison row holds its key back; buried, it no longer does.
+    assert_eq!(outcomes[0], [Delivery::Failed, Delivery::Deferred, Delivery::Deferred]);
+    assert_eq!(outcomes[1], [Delivery::Dead, Delivery::Published, Delivery::Published]);
+    let (retries, last_error): (i32, Option<String>) =
+        sqlx::query_as("SELECT retry_count, last_error FROM outbox_dead WHERE id = $1").bind(poison).fetch_one(db).await.unwrap();
+    assert_eq!((retries, last_error.as_deref()), (2, Some("\"schema registry rejected payload\"")));
+
+    assert_eq!(requeue_dead(db, Uuid::new_v4(), poison).await.unwrap(), None, "other tenants cannot requeue it");
+    let requeued = requeue_dead(db, tenant, poison).await.unwrap().expect("requeued");
+    assert!(requeued > ids[2], "requeued rows go behind the rest of their key");
+    assert_eq!(requeue_dead(db, tenant, poison).await.unwrap(), None);
+    let (retries, key): (i32, Option<String>) =
+        sqlx::query_as("SELECT retry_count, message_key FROM outbox WHERE id = $1").bind(requeued).fetch_one(db).await.unwrap();
+    assert_eq!((retries, key), (0, Some(format!("{tenant}-0"))));
+    let outcomes = relay.relay_batch(db, |_| async { Ok::<(), ()>(()) }).await.unwrap();
+    assert!(outcomes.iter().any(|(row, delivery)| row.id == requeued && *delivery == Delivery::Published));
+    assert_eq!(unpublished(db, tenant).await, 0);
+}
+
+#[tokio::test]
+async fn published_rows_are_purged_after_retention() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let ids = enqueue(db, tenant, 1, 3).await;
+    // One old published row, one recent, one still pending.
+    sqlx::query("UPDATE outbox SET published_at = NOW() - INTERVAL '8 days' WHERE id = $1").bind(ids[0]).execute(db).await.unwrap();
+    sqlx::query("UPDATE outbox SET published_at = NOW() - INTERVAL '1 day' WHERE id = $1").bind(ids[1]).execute(db).await.unwrap();
+
+    assert!(purge_published(db, Duration::from_secs(7 * 86_400)).await.unwrap() >= 1);
+    let left: Vec<i64> = sqlx::query_scalar("SELECT id FROM outbox WHERE tenant_id = $1 ORDER BY id").bind(tenant.to_string()).fetch_all(db).await.unwrap();
+    assert_eq!(left, ids[1..]);
+}
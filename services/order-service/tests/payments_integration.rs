@@ -6,7 +6,7 @@
 
 use axum::{Router, body::{Body, to_bytes}};
 use http::{Request, StatusCode};
-use order_service::{build_router, AppState, build_jwt_verifier_from_env};
+use order_service::{build_router, AppState, build_jwt_verifier};
 use serde_json::json;
 use tower::ServiceExt;
 use uuid::Uuid;
@@ -35,11 +35,18 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           customer_id uuid NULL,
           customer_name text NULL,
           customer_email text NULL,
+          customer_email_encrypted bytea NULL,
+          customer_email_hash bytea NULL,
+          customer_name_encrypted bytea NULL,
+          customer_name_hash bytea NULL,
+          pii_key_id text NULL,
           store_id uuid NULL,
           created_at timestamptz NOT NULL DEFAULT now(),
           offline boolean NOT NULL DEFAULT false,
           payment_method text NOT NULL,
-          idempotency_key text NULL
+          idempotency_key text NULL,
+          rounding_adjustment numeric NOT NULL DEFAULT 0,
+          tip_amount numeric NOT NULL DEFAULT 0
         );
         CREATE TABLE IF NOT EXISTS order_items (
           order_id uuid NOT NULL,
@@ -56,6 +63,7 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           amount numeric NOT NULL,
           status text NOT NULL,
           change_cents int NULL,
+          tip_amount numeric NOT NULL DEFAULT 0,
           created_at timestamptz NOT NULL DEFAULT now()
         );
         CREATE TABLE IF NOT EXISTS tax_rate_overrides (
@@ -65,6 +73,12 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           rate_bps int NOT NULL,
           updated_at timestamptz NOT NULL DEFAULT now()
         );
+        CREATE TABLE IF NOT EXISTS rounding_policies (
+          tenant_id uuid PRIMARY KEY,
+          mode text NOT NULL,
+          cash_increment_cents int NOT NULL DEFAULT 0,
+          updated_at timestamptz NOT NULL DEFAULT now()
+        );
     "#).execute(pool).await;
 }
 
@@ -87,7 +101,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
     std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
     std::env::set_var("JWT_ISSUER", "https://auth.novapos.local");
     std::env::set_var("JWT_AUDIENCE", "novapos-admin");
-    let verifier = build_jwt_verifier_from_env().await.expect("jwt verifier");
+    let verifier = build_jwt_verifier(&common_config::JwtSettings::from_env().await.expect("jwt settings")).await.expect("jwt verifier");
     let state = AppState {
         db: pool,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -97,6 +111,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
         inventory_base_url: "http://localhost:8087".to_string(),
         payment_base_url: "http://localhost:8086".to_string(),
         enable_payment_intents: false,
+        pii_key: None,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         audit_producer: None,
     };
@@ -506,6 +521,97 @@ async fn admin_tax_override_affects_compute() {
     assert_eq!(v1["total_cents"].as_i64().unwrap(), 1100);
 }
 
+#[tokio::test]
+async fn tenant_cash_rounding_applies_to_compute_total() {
+    let Some(pool) = start_test_db().await else { return; };
+    let tenant = Uuid::new_v4();
+    let (pub_pem, token_admin) = generate_key_and_token("https://auth.novapos.local", "novapos-admin", tenant, &["admin"]);
+    std::env::set_var("JWT_DEV_PUBLIC_KEY_PEM", pub_pem);
+    let app = build_test_app(pool.clone()).await;
+
+    sqlx::query("INSERT INTO products (id, tenant_id, name, price, sku, tax_code, active) VALUES ($1,$2,$3,$4,$5,$6,$7)")
+        .bind(Uuid::new_v4()).bind(tenant).bind("Chocolate").bind(dec(1233)).bind("SKU-CHF").bind(Some("EXEMPT")).bind(true)
+        .execute(&pool).await.expect("insert");
+
+    // CHF-style 0.05 cash rounding
+    let upsert = json!({"mode": "half-up", "cash_increment_cents": 5});
+    let resp = app.clone().oneshot(Request::builder().method("POST").uri("/admin/rounding_policy")
+        .header("Content-Type","application/json").header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles","admin").header("Authorization", format!("Bearer {}", token_admin))
+        .body(Body::from(upsert.to_string())).unwrap()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let compute_body = json!({"items": [{"sku":"SKU-CHF","quantity":1}]});
+    let resp = app.clone().oneshot(Request::builder().method("POST").uri("/orders/compute")
+        .header("Content-Type","application/json").header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles","admin").header("Authorization", format!("Bearer {}", token_admin))
+        .body(Body::from(compute_body.to_string())).unwrap()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&to_bytes(resp.into_body(), 1024*1024).await.unwrap()).unwrap();
+    assert_eq!(v["subtotal_cents"].as_i64().unwrap(), 1233);
+    assert_eq!(v["rounding_adjustment_cents"].as_i64().unwrap(), 2);
+    assert_eq!(v["total_cents"].as_i64().unwrap(), 1235);
+}
+
+#[tokio::test]
+async fn cash_rounding_applies_only_to_cash_tenders() {
+    let Some(pool) = start_test_db().await else { return; };
+    let tenant = Uuid::new_v4();
+    let (pub_pem, token) = generate_key_and_token("https://auth.novapos.local", "novapos-admin", tenant, &["admin", "cashier"]);
+    std::env::set_var("JWT_DEV_PUBLIC_KEY_PEM", pub_pem);
+    let app = build_test_app(pool.clone()).await;
+
+    sqlx::query("INSERT INTO products (id, tenant_id, name, price, sku, tax_code, active) VALUES ($1,$2,$3,$4,$5,$6,$7)")
+        .bind(Uuid::new_v4()).bind(tenant).bind("Chocolate").bind(dec(1233)).bind("SKU-CHF").bind(Some("EXEMPT")).bind(true)
+        .execute(&pool).await.expect("insert");
+    let upsert = json!({"mode": "half-up", "cash_increment_cents": 5});
+    let resp = app.clone().oneshot(Request::builder().method("POST").uri("/admin/rounding_policy")
+        .header("Content-Type","application/json").header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles","admin").header("Authorization", format!("Bearer {}", token))
+        .body(Body::from(upsert.to_string())).unwrap()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Card tenders are quoted and charged to the cent.
+    let compute_body = json!({"items": [{"sku":"SKU-CHF","quantity":1}], "payment_method": "card"});
+    let resp = app.clone().oneshot(Request::builder().method("POST").uri("/orders/compute")
+        .header("Content-Type","application/json").header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles","admin").header("Authorization", format!("Bearer {}", token))
+        .body(Body::from(compute_body.to_string())).unwrap()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&to_bytes(resp.into_body(), 1024*1024).await.unwrap()).unwrap();
+    assert_eq!(v["rounding_adjustment_cents"].as_i64().unwrap(), 0);
+    assert_eq!(v["total_cents"].as_i64().unwrap(), 1233);
+
+    let mut order_ids = Vec::new();
+    for (method, amount_cents) in [("card", 1233), ("cash", 1235)] {
+        let order_body = json!({
+            "items": [{"sku": "SKU-CHF", "quantity": 1}],
+            "payment_method": method,
+            "payment": {"method": method, "amount_cents": amount_cents}
+        });
+        let resp = app.clone().oneshot(Request::builder().method("POST").uri("/orders/sku")
+            .header("Content-Type","application/json").header("X-Tenant-ID", tenant.to_string())
+            .header("X-Roles","cashier").header("Authorization", format!("Bearer {}", token))
+            .body(Body::from(order_body.to_string())).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{method}");
+        let order: serde_json::Value = serde_json::from_slice(&to_bytes(resp.into_body(), 1024*1024).await.unwrap()).unwrap();
+        order_ids.push(order["id"].as_str().unwrap().parse::<Uuid>().unwrap());
+    }
+
+    let stored: Vec<(String, String)> = sqlx::query_as("SELECT total::text, rounding_adjustment::text FROM orders WHERE id = ANY($1) ORDER BY payment_method DESC")
+        .bind(&order_ids).fetch_all(&pool).await.unwrap();
+    let stored: Vec<(f64, f64)> = stored.iter().map(|(t, r)| (t.parse().unwrap(), r.parse().unwrap())).collect();
+    assert_eq!(stored, vec![(12.33, 0.0), (12.35, 0.02)]);
+
+    let resp = app.clone().oneshot(Request::builder().method("GET").uri(format!("/orders/{}/receipt", order_ids[1]))
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles","admin").header("Authorization", format!("Bearer {}", token))
+        .body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let txt = String::from_utf8(to_bytes(resp.into_body(), 1024*1024).await.unwrap().to_vec()).unwrap();
+    assert!(txt.contains("Cash rounding:"), "{txt}");
+}
+
 #[tokio::test]
 async fn tax_override_precedence_pos_over_location_over_tenant() {
     let Some(pool) = start_test_db().await else { return; };
@@ -0,0 +1,67 @@
+//! `product.merged` against Postgres: order lines move to the canonical product, parked carts
+//! switch to its SKU, other tenants are untouched, and a redelivered event changes nothing.
+//! Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use chrono::Utc;
+use common_events::{DomainEvent, ProductMergedEvent};
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres};
+use order_service::product_merges::apply_product_merge;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate order-service");
+    Some(postgres)
+}
+
+async fn cart(db: &PgPool, tenant: Uuid, status: &str, items: Value) -> Uuid {
+    sqlx::query_scalar("INSERT INTO carts (id, tenant_id, status, items, expires_at) VALUES ($1, $2, $3, $4, NOW() + INTERVAL '1 hour') RETURNING id")
+        .bind(Uuid::new_v4())
+        .bind(tenant)
+        .bind(status)
+        .bind(items)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+async fn cart_items(db: &PgPool, id: Uuid) -> Value {
+    sqlx::query_scalar("SELECT items FROM carts WHERE id = $1").bind(id).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn order_lines_and_open_carts_follow_the_canonical_product() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, other_tenant) = (Uuid::new_v4(), Uuid::new_v4());
+    let (duplicate, canonical, unrelated) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let order = OrderFixture::new(tenant).line(duplicate, 2, 450).line(unrelated, 1, 100).insert(db).await.unwrap();
+    let foreign = OrderFixture::new(other_tenant).line(duplicate, 1, 450).insert(db).await.unwrap();
+    let parked = cart(db, tenant, "PARKED", json!([{"sku": "COFFEE", "quantity": 1}, {"sku": "LATTE-12OZ", "quantity": 2}])).await;
+    let converted = cart(db, tenant, "CONVERTED", json!([{"sku": "LATTE-12OZ", "quantity": 1}])).await;
+
+    let evt = ProductMergedEvent {
+        schema_version: ProductMergedEvent::SCHEMA_VERSION,
+        tenant_id: tenant,
+        duplicate_id: duplicate,
+        canonical_id: canonical,
+        duplicate_sku: Some("LATTE-12OZ".into()),
+        canonical_sku: Some("LATTE-12".into()),
+        merged_at: Utc::now(),
+    };
+    let counts = apply_product_merge(db, &evt).await.unwrap();
+    assert_eq!((counts.order_items, counts.carts), (1, 1));
+    assert_eq!(apply_product_merge(db, &evt).await.unwrap(), Default::default(), "a redelivered merge changes nothing");
+
+    let product_of = |item: Uuid| sqlx::query_scalar::<_, Uuid>("SELECT product_id FROM order_items WHERE id = $1").bind(item).fetch_one(db);
+    assert_eq!(product_of(order.item_ids[0]).await.unwrap(), canonical);
+    assert_eq!(product_of(order.item_ids[1]).await.unwrap(), unrelated);
+    assert_eq!(product_of(foreign.item_ids[0]).await.unwrap(), duplicate, "other tenants keep their rows");
+    assert_eq!(cart_items(db, parked).await, json!([{"sku": "COFFEE", "quantity": 1}, {"sku": "LATTE-12", "quantity": 2}]));
+    assert_eq!(cart_items(db, converted).await, json!([{"sku": "LATTE-12OZ", "quantity": 1}]), "checked-out carts are history");
+}
@@ -0,0 +1,178 @@
+//! "Reorder last purchase" through the router against Postgres: history net of returns, merged
+//! across orders at the latest price, discontinued lines with substitutes, and the draft cart.
+//! Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use axum::body::{to_bytes, Body};
+use axum::Router;
+use chrono::{Duration, Utc};
+use common_test_fixtures::{itests_enabled, OrderFixture, ProductFixture, SeededOrder, SeededProduct, TestPostgres, TestSigner};
+use http::{Request, StatusCode};
+use order_service::{build_router, AppState};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service", "order-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn app(db: &PgPool, signer: &TestSigner) -> Router {
+    std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
+    build_router(AppState {
+        db: db.clone(),
+        jwt_verifier: signer.verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: "http://localhost:8087".into(),
+        payment_base_url: "http://localhost:8086".into(),
+        enable_payment_intents: false,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    })
+}
+
+async fn reorder(app: &Router, signer: &TestSigner, tenant: Uuid, customer: Uuid, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/customers/{customer}/reorder"))
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", "cashier")
+        .header("Authorization", format!("Bearer {}", signer.token(tenant, &["cashier"])))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// Attach `order` to `customer`, `days_ago`, with its customer name on file.
+async fn bought_by(db: &PgPool, order: &SeededOrder, customer: Uuid, days_ago: i64) {
+    sqlx::query("UPDATE orders SET customer_id = $2, customer_name = 'Ada', created_at = $3 WHERE id = $1")
+        .bind(order.id)
+        .bind(customer)
+        .bind(Utc::now() - Duration::days(days_ago))
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+async fn product(db: &PgPool, tenant: Uuid, sku: &str, name: &str, price_cents: i64) -> SeededProduct {
+    ProductFixture::new(tenant).sku(sku).name(name).price_cents(price_cents).insert(db).await.unwrap()
+}
+
+fn line<'a>(draft: &'a Value, product: &SeededProduct) -> &'a Value {
+    let id = product.id.to_string();
+    draft["lines"].as_array().unwrap().iter().find(|line| line["product_id"] == id.as_str()).unwrap_or_else(|| panic!("no line for {id}: {draft}"))
+}
+
+/// A decimal amount as serialized, whatever its scale.
+fn amount(value: &Value) -> f64 {
+    value.as_str().and_then(|s| s.parse().ok()).unwrap_or_else(|| panic!("not an amount: {value}"))
+}
+
+async fn carts_of(db: &PgPool, tenant: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM carts WHERE tenant_id = $1").bind(tenant).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn history_is_merged_net_of_returns_at_the_latest_price() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let (tenant, customer, other_customer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let beans = product(db, tenant, "BEANS-1", "Espresso Beans", 350).await;
+    let filters = product(db, tenant, "FILTER-1", "Paper Filters", 600).await;
+    let mugs = product(db, tenant, "MUG-1", "Travel Mug", 1500).await;
+
+    let older = OrderFixture::new(tenant).line(beans.id, 3, 300).line(filters.id, 1, 500).insert(db).await.unwrap();
+    bought_by(db, &older, customer, 2).await;
+    sqlx::query("UPDATE order_items SET returned_quantity = 1 WHERE id = $1").bind(older.item_ids[0]).execute(db).await.unwrap();
+    let newer = OrderFixture::new(tenant).line(beans.id, 1, 350).line(mugs.id, 1, 1500).insert(db).await.unwrap();
+    bought_by(db, &newer, customer, 1).await;
+    sqlx::query("UPDATE order_items SET returned_quantity = 1 WHERE id = $1").bind(newer.item_ids[1]).execute(db).await.unwrap();
+    // Neither an open order nor another customer's purchase is part of the history.
+    let open = OrderFixture::new(tenant).line(filters.id, 5, 500).status("PENDING").insert(db).await.unwrap();
+    bought_by(db, &open, customer, 0).await;
+    let theirs = OrderFixture::new(tenant).line(filters.id, 7, 500).insert(db).await.unwrap();
+    bought_by(db, &theirs, other_customer, 0).await;
+
+    let app = app(db, &signer);
+    let (status, draft) = reorder(&app, &signer, tenant, customer, json!({ "orders_back": 2, "dry_run": true })).await;
+    assert_eq!(status, StatusCode::OK, "{draft}");
+    assert_eq!(draft["source_order_ids"], json!([newer.id, older.id]));
+    assert!(draft["cart"].is_null(), "dry runs create no cart: {draft}");
+    assert_eq!(carts_of(db, tenant).await, 0);
+
+    let lines = draft["lines"].as_array().unwrap();
+    assert_eq!(lines.len(), 2, "the fully returned mug is left out: {draft}");
+    let beans_line = line(&draft, &beans);
+    assert_eq!((beans_line["previous_quantity"].as_i64(), beans_line["quantity"].as_i64()), (Some(3), Some(3)), "2 net of the return, plus 1");
+    assert_eq!(amount(&beans_line["previous_unit_price"]), 3.5, "the latest price paid wins");
+    assert_eq!((beans_line["status"].as_str(), beans_line["price_changed"].as_bool()), (Some("available"), Some(false)));
+    let filters_line = line(&draft, &filters);
+    assert_eq!(filters_line["previous_quantity"], 1);
+    assert_eq!((amount(&filters_line["current_unit_price"]), filters_line["price_changed"].as_bool()), (6.0, Some(true)));
+
+    // Only the latest order by default.
+    let (_, draft) = reorder(&app, &signer, tenant, customer, json!({ "dry_run": true })).await;
+    assert_eq!(draft["source_order_ids"], json!([newer.id]));
+    assert_eq!(draft["lines"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn discontinued_lines_get_substitutes_and_the_rest_become_a_cart() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let (tenant, other_tenant, customer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let beans = product(db, tenant, "BEANS-1", "Espresso Beans", 1200).await;
+    let oat = product(db, tenant, "OAT-1", "Oatly Oat Milk", 300).await;
+    let cups = product(db, tenant, "CUP-1", "Paper Cups", 250).await;
+    sqlx::query("UPDATE products SET deleted_at = NOW() WHERE id = $1").bind(beans.id).execute(db).await.unwrap();
+    sqlx::query("UPDATE products SET active = FALSE WHERE id = $1").bind(oat.id).execute(db).await.unwrap();
+    let roast = product(db, tenant, "ROAST-1", "Espresso Roast", 1300).await;
+    product(db, tenant, "BARISTA-1", "Oatly Barista", 350).await;
+    // Another tenant's lookalike is never offered.
+    product(db, other_tenant, "ESP-9", "Espresso Blend", 900).await;
+
+    let order = OrderFixture::new(tenant).line(beans.id, 1, 1200).line(oat.id, 2, 300).line(cups.id, 4, 250).insert(db).await.unwrap();
+    bought_by(db, &order, customer, 1).await;
+
+    let app = app(db, &signer);
+    let (status, draft) = reorder(&app, &signer, tenant, customer, json!({ "label": "Weekly" })).await;
+    assert_eq!(status, StatusCode::OK, "{draft}");
+
+    let beans_line = line(&draft, &beans);
+    assert_eq!((beans_line["status"].as_str(), beans_line["quantity"].as_i64()), (Some("discontinued"), Some(0)));
+    let substitutes = beans_line["substitutes"].as_array().unwrap();
+    assert_eq!(substitutes.len(), 1, "{draft}");
+    assert_eq!((&substitutes[0]["product_id"], substitutes[0]["sku"].as_str()), (&json!(roast.id), Some("ROAST-1")));
+    assert_eq!(amount(&substitutes[0]["price"]), 13.0);
+    let oat_line = line(&draft, &oat);
+    assert_eq!(oat_line["status"], "discontinued", "inactive products are discontinued too");
+    let oat_subs: Vec<&str> = oat_line["substitutes"].as_array().unwrap().iter().map(|s| s["sku"].as_str().unwrap()).collect();
+    assert_eq!(oat_subs, ["BARISTA-1"]);
+    assert!(line(&draft, &cups).get("substitutes").is_none());
+
+    let cart = &draft["cart"];
+    assert_eq!(cart["items"], json!([{ "sku": "CUP-1", "quantity": 4 }]), "{draft}");
+    assert_eq!((cart["label"].as_str(), cart["customer_name"].as_str()), (Some("Weekly"), Some("Ada")));
+    assert_eq!(cart["customer_id"], json!(customer));
+    assert_eq!(carts_of(db, tenant).await, 1);
+
+    // The same customer id under another tenant has no history there.
+    let (status, body) = reorder(&app, &signer, other_tenant, customer, json!({})).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::NOT_FOUND, Some("no_purchase_history")));
+    let (status, body) = reorder(&app, &signer, other_tenant, customer, json!({ "order_id": order.id })).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::NOT_FOUND, Some("order_not_found")));
+}
@@ -0,0 +1,78 @@
+//! Order retention against Postgres: only settled orders past the cutoff lose their customer
+//! data, dry runs change nothing, and totals survive. Needs Postgres: set ENABLE_ITESTS=1 (and
+//! TEST_DATABASE_URL to skip the container).
+
+use chrono::{Duration, Utc};
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres};
+use order_service::retention::{run_retention, RetentionPolicy, TenantRetention};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn order_with_customer(db: &PgPool, tenant: Uuid, status: &str, age_days: i64, dispute: Option<&str>) -> Uuid {
+    let order = OrderFixture::new(tenant)
+        .line(Uuid::new_v4(), 2, 450)
+        .status(status)
+        .created_at(Utc::now() - Duration::days(age_days))
+        .insert(db)
+        .await
+        .unwrap();
+    sqlx::query(
+        "UPDATE orders SET customer_id = $2, customer_name = 'Ada', customer_email_hash = '\\x01', dispute_status = $3
+         WHERE id = $1",
+    )
+    .bind(order.id)
+    .bind(Uuid::new_v4())
+    .bind(dispute)
+    .execute(db)
+    .await
+    .unwrap();
+    order.id
+}
+
+async fn customer_name(db: &PgPool, order: Uuid) -> Option<String> {
+    sqlx::query_scalar("SELECT customer_name FROM orders WHERE id = $1").bind(order).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn only_settled_orders_past_the_cutoff_are_anonymized() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate order-service");
+    let db = postgres.pool();
+    let tenant = Uuid::new_v4();
+    let expired = order_with_customer(db, tenant, "COMPLETED", 800, None).await;
+    let expired_too = order_with_customer(db, tenant, "VOIDED", 700, Some("lost")).await;
+    let pending = order_with_customer(db, tenant, "PENDING", 800, None).await;
+    let disputed = order_with_customer(db, tenant, "COMPLETED", 800, Some("needs_response")).await;
+    let recent = order_with_customer(db, tenant, "COMPLETED", 30, None).await;
+
+    let mut policy = RetentionPolicy { months: 12, batch_size: 1, dry_run: true };
+    let dry = run_retention(db, &policy).await.unwrap();
+    assert_eq!(dry.tenants.get(&tenant), Some(&TenantRetention { orders: 2, carts: 0 }));
+    assert_eq!(customer_name(db, expired).await.as_deref(), Some("Ada"), "a dry run changes nothing");
+
+    policy.dry_run = false;
+    let run = run_retention(db, &policy).await.unwrap();
+    assert_eq!(run.tenants.get(&tenant), Some(&TenantRetention { orders: 2, carts: 0 }));
+    assert!(run.batches >= 3, "one row per batch, plus the short batches that end each table");
+    for order in [expired, expired_too] {
+        let (customer_id, name, hash, anonymized, total): (Option<Uuid>, Option<String>, Option<Vec<u8>>, bool, String) =
+            sqlx::query_as(
+                "SELECT customer_id, customer_name, customer_email_hash, anonymized_at IS NOT NULL, total::text FROM orders WHERE id = $1",
+            )
+            .bind(order)
+            .fetch_one(db)
+            .await
+            .unwrap();
+        assert_eq!((customer_id, name, hash, anonymized), (None, None, None, true));
+        assert_eq!(total, "9.00", "financial figures are kept");
+    }
+    for order in [pending, disputed, recent] {
+        assert_eq!(customer_name(db, order).await.as_deref(), Some("Ada"));
+    }
+
+    let again = run_retention(db, &policy).await.unwrap();
+    assert_eq!(again.tenants.get(&tenant), None, "anonymized orders are not counted twice");
+}
@@ -2,7 +2,7 @@
 
 use axum::{Router, body::{Body, to_bytes}};
 use http::{Request, StatusCode};
-use order_service::{build_router, AppState, build_jwt_verifier_from_env};
+use order_service::{build_router, AppState, build_jwt_verifier};
 use serde_json::json;
 use tower::ServiceExt;
 use uuid::Uuid;
@@ -17,11 +17,18 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           customer_id uuid NULL,
           customer_name text NULL,
           customer_email text NULL,
+          customer_email_encrypted bytea NULL,
+          customer_email_hash bytea NULL,
+          customer_name_encrypted bytea NULL,
+          customer_name_hash bytea NULL,
+          pii_key_id text NULL,
           store_id uuid NULL,
           created_at timestamptz NOT NULL DEFAULT now(),
           offline boolean NOT NULL DEFAULT false,
           payment_method text NOT NULL,
-          idempotency_key text NULL
+          idempotency_key text NULL,
+          rounding_adjustment numeric NOT NULL DEFAULT 0,
+          tip_amount numeric NOT NULL DEFAULT 0
         );
         CREATE TABLE IF NOT EXISTS order_items (
           id uuid PRIMARY KEY,
@@ -85,7 +92,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
     std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
     std::env::set_var("JWT_ISSUER", "https://auth.novapos.local");
     std::env::set_var("JWT_AUDIENCE", "novapos-admin");
-    let verifier = build_jwt_verifier_from_env().await.expect("jwt verifier");
+    let verifier = build_jwt_verifier(&common_config::JwtSettings::from_env().await.expect("jwt settings")).await.expect("jwt verifier");
     let state = AppState {
         db: pool,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -95,6 +102,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
         inventory_base_url: "http://localhost:8087".to_string(),
         payment_base_url: "http://localhost:8086".to_string(),
         enable_payment_intents: false,
+        pii_key: None,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         audit_producer: None,
     };
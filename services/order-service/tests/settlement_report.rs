@@ -6,7 +6,7 @@
 
 use axum::{Router, body::{Body, to_bytes}};
 use http::{Request, StatusCode};
-use order_service::{build_router, AppState, build_jwt_verifier_from_env};
+use order_service::{build_router, AppState, build_jwt_verifier};
 use tower::ServiceExt;
 use uuid::Uuid;
 
@@ -30,11 +30,18 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           customer_id uuid NULL,
           customer_name text NULL,
           customer_email text NULL,
+          customer_email_encrypted bytea NULL,
+          customer_email_hash bytea NULL,
+          customer_name_encrypted bytea NULL,
+          customer_name_hash bytea NULL,
+          pii_key_id text NULL,
           store_id uuid NULL,
           created_at timestamptz NOT NULL DEFAULT now(),
           offline boolean NOT NULL DEFAULT false,
           payment_method text NOT NULL,
-          idempotency_key text NULL
+          idempotency_key text NULL,
+          rounding_adjustment numeric NOT NULL DEFAULT 0,
+          tip_amount numeric NOT NULL DEFAULT 0
         );
         CREATE TABLE IF NOT EXISTS order_items (
           order_id uuid NOT NULL,
@@ -51,6 +58,7 @@ async fn run_migrations(pool: &sqlx::PgPool) {
           amount numeric NOT NULL,
           status text NOT NULL,
           change_cents int NULL,
+          tip_amount numeric NOT NULL DEFAULT 0,
           created_at timestamptz NOT NULL DEFAULT now()
         );
         CREATE TABLE IF NOT EXISTS tax_rate_overrides (
@@ -82,7 +90,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
     std::env::set_var("ORDER_BYPASS_INVENTORY", "1");
     std::env::set_var("JWT_ISSUER", "https://auth.novapos.local");
     std::env::set_var("JWT_AUDIENCE", "novapos-admin");
-    let verifier = build_jwt_verifier_from_env().await.expect("jwt verifier");
+    let verifier = build_jwt_verifier(&common_config::JwtSettings::from_env().await.expect("jwt settings")).await.expect("jwt verifier");
     let state = AppState {
         db: pool,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -92,6 +100,7 @@ async fn build_test_app(pool: sqlx::PgPool) -> Router {
         inventory_base_url: "http://localhost:8087".to_string(),
         payment_base_url: "http://localhost:8086".to_string(),
         enable_payment_intents: false,
+        pii_key: None,
         #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
         audit_producer: None,
     };
@@ -179,8 +188,9 @@ async fn settlement_report_groups_by_method_and_sums_amounts() {
     sqlx::query("INSERT INTO payments (id, tenant_id, order_id, method, amount, status, created_at) VALUES ($1,$2,$3,$4,$5,$6, $7::timestamptz)")
         .bind(Uuid::new_v4()).bind(tenant).bind(order2).bind("cash").bind(dec(200)).bind("captured").bind(ts)
         .execute(&pool).await.expect("insert p2");
-    sqlx::query("INSERT INTO payments (id, tenant_id, order_id, method, amount, status, created_at) VALUES ($1,$2,$3,$4,$5,$6, $7::timestamptz)")
-        .bind(Uuid::new_v4()).bind(tenant).bind(order3).bind("card").bind(dec(325)).bind("captured").bind(ts)
+    // Card payment includes a 0.50 tip on top of the 2.75 sale
+    sqlx::query("INSERT INTO payments (id, tenant_id, order_id, method, amount, status, created_at, tip_amount) VALUES ($1,$2,$3,$4,$5,$6, $7::timestamptz, $8)")
+        .bind(Uuid::new_v4()).bind(tenant).bind(order3).bind("card").bind(dec(325)).bind("captured").bind(ts).bind(dec(50))
         .execute(&pool).await.expect("insert p3");
     // This one should be excluded by date filter
     sqlx::query("INSERT INTO payments (id, tenant_id, order_id, method, amount, status, created_at) VALUES ($1,$2,$3,$4,$5,$6, $7::timestamptz)")
@@ -212,6 +222,7 @@ async fn settlement_report_groups_by_method_and_sums_amounts() {
     assert_eq!(cash["amount"].as_str().unwrap_or(""), "3.50");
     assert_eq!(card["count"].as_i64().unwrap(), 1);
     assert_eq!(card["amount"].as_str().unwrap_or(""), "3.25");
+    assert_eq!(card["tips"].as_str().unwrap_or(""), "0.50");
 }
 
 #[tokio::test]
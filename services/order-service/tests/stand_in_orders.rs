@@ -0,0 +1,54 @@
+//! Orders paid in payment-service stand-in, against Postgres. Needs Postgres: set ENABLE_ITESTS=1
+//! (and TEST_DATABASE_URL to skip the container).
+
+use common_events::{DomainEvent, PaymentStandInEvent};
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres};
+use order_service::order_stand_in::{apply_stand_in, CONDITIONALLY_APPROVED};
+use order_service::tenant_diagnostics::load_diagnostics;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate order-service");
+    Some(postgres)
+}
+
+fn stand_in(tenant_id: Uuid, order_id: Uuid) -> PaymentStandInEvent {
+    PaymentStandInEvent {
+        schema_version: PaymentStandInEvent::SCHEMA_VERSION,
+        stand_in_id: Uuid::new_v4(),
+        order_id,
+        tenant_id,
+        method: "card".into(),
+        amount_minor: 100,
+        currency: "USD".into(),
+    }
+}
+
+async fn status(db: &PgPool, order_id: Uuid) -> String {
+    sqlx::query_scalar("SELECT status FROM orders WHERE id = $1").bind(order_id).fetch_one(db).await.unwrap()
+}
+
+#[tokio::test]
+async fn stand_in_payments_hold_pending_orders_open_as_conditionally_approved() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, other_tenant) = (Uuid::new_v4(), Uuid::new_v4());
+    let product = Uuid::new_v4();
+    let pending = OrderFixture::new(tenant).line(product, 1, 100).status("PENDING").insert(db).await.unwrap().id;
+    let completed = OrderFixture::new(tenant).line(product, 1, 100).insert(db).await.unwrap().id;
+
+    assert!(apply_stand_in(db, &stand_in(tenant, pending)).await.unwrap());
+    assert_eq!(status(db, pending).await, CONDITIONALLY_APPROVED);
+    assert!(!apply_stand_in(db, &stand_in(tenant, pending)).await.unwrap(), "redelivery is a no-op");
+    assert!(!apply_stand_in(db, &stand_in(other_tenant, pending)).await.unwrap(), "another tenant's event");
+    assert!(!apply_stand_in(db, &stand_in(tenant, completed)).await.unwrap(), "the provider's answer arrived first");
+    assert_eq!(status(db, completed).await, "COMPLETED");
+
+    // Still open until the provider answers.
+    assert_eq!(load_diagnostics(db, tenant).await.unwrap().open_orders, 1);
+}
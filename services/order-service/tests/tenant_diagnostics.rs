@@ -0,0 +1,49 @@
+//! Tenant diagnostics counts against Postgres: only the tenant's open orders and outbox rows are
+//! counted. Needs Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres};
+use order_service::tenant_diagnostics::load_diagnostics;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate order-service");
+    Some(postgres)
+}
+
+async fn outbox_row(db: &PgPool, tenant: Uuid, published: bool) {
+    sqlx::query("INSERT INTO outbox (tenant_id, topic, payload, published_at) VALUES ($1, 'order.completed', '{}', CASE WHEN $2 THEN NOW() END)")
+        .bind(tenant.to_string())
+        .bind(published)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn counts_cover_only_the_requested_tenant() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let (tenant, other_tenant) = (Uuid::new_v4(), Uuid::new_v4());
+    let product = Uuid::new_v4();
+    OrderFixture::new(tenant).line(product, 1, 100).status("PENDING").insert(db).await.unwrap();
+    OrderFixture::new(tenant).line(product, 1, 100).insert(db).await.unwrap();
+    OrderFixture::new(other_tenant).line(product, 1, 100).status("PENDING").insert(db).await.unwrap();
+    outbox_row(db, tenant, false).await;
+    outbox_row(db, tenant, true).await;
+    outbox_row(db, other_tenant, false).await;
+
+    let diagnostics = load_diagnostics(db, tenant).await.unwrap();
+    assert_eq!(diagnostics.open_orders, 1);
+    assert!(diagnostics.last_order_at.is_some());
+    assert_eq!((diagnostics.outbox.pending, diagnostics.outbox.dead), (1, 0));
+    assert!(diagnostics.outbox.oldest_pending_at.is_some() && diagnostics.outbox.last_published_at.is_some());
+
+    let empty = load_diagnostics(db, Uuid::new_v4()).await.unwrap();
+    assert_eq!((empty.open_orders, empty.open_carts, empty.outbox.pending), (0, 0, 0));
+    assert!(empty.last_order_at.is_none());
+}
@@ -0,0 +1,109 @@
+//! Card tip adjustments through the router against Postgres: the tenant cap, manager approval for
+//! raising a tip, and the payment amount moving with it. Needs Postgres: set ENABLE_ITESTS=1 (and
+//! TEST_DATABASE_URL to skip the container).
+
+use axum::body::{to_bytes, Body};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres, TestSigner};
+use http::{Request, StatusCode};
+use order_service::{build_router, AppState};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn app(db: &PgPool, signer: &TestSigner) -> Router {
+    build_router(AppState {
+        db: db.clone(),
+        jwt_verifier: signer.verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: "http://localhost:8087".into(),
+        payment_base_url: "http://localhost:8086".into(),
+        enable_payment_intents: false,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    })
+}
+
+/// A signed-in user: id, role and bearer token.
+type User = (Uuid, &'static str, String);
+
+async fn send(app: &Router, (user_id, role, token): &User, tenant: Uuid, method: &str, uri: String, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-User-ID", user_id.to_string())
+        .header("X-Roles", *role)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn raising_a_tip_needs_approval_and_stays_under_the_cap() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let tenant = Uuid::new_v4();
+    let order = OrderFixture::new(tenant).line(Uuid::new_v4(), 1, 2_000).status("COMPLETED").payment_method("card").insert(db).await.unwrap();
+    sqlx::query("INSERT INTO payments (id, tenant_id, order_id, method, amount, status) VALUES ($1, $2, $3, 'card', 20.00, 'captured')")
+        .bind(Uuid::new_v4())
+        .bind(tenant)
+        .bind(order.id)
+        .execute(db)
+        .await
+        .unwrap();
+
+    let app = app(db, &signer);
+    let user = |role: &'static str| {
+        let id = Uuid::new_v4();
+        (id, role, signer.token_for(id, tenant, &[role]))
+    };
+    let (admin, manager, cashier) = (user("admin"), user("manager"), user("cashier"));
+    let manager_id = manager.0;
+    let settings = json!({"mode": "percentage", "suggestions": [1500, 2000], "max_tip_bps": 2500});
+    let (status, body) = send(&app, &admin, tenant, "POST", "/admin/tip_settings".into(), settings).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = send(&app, &manager, tenant, "PUT", "/admin/approval_pin".into(), json!({"pin": "4821"})).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let uri = format!("/orders/{}/tip", order.id);
+    let (status, _) = send(&app, &cashier, tenant, "POST", uri.clone(), json!({"tip_cents": 300})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "a cashier cannot raise a tip alone");
+    let (status, _) = send(&app, &cashier, tenant, "POST", uri.clone(), json!({"tip_cents": 300, "manager_id": manager_id, "pin": "0000"})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "a wrong PIN does not approve");
+    let (status, body) = send(&app, &cashier, tenant, "POST", uri.clone(), json!({"tip_cents": 300, "manager_id": manager_id, "pin": "4821"})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["payment_amount"], json!("23.00"));
+
+    // 25% of 20.00 is the most anyone can set, approved or not.
+    let (status, body) = send(&app, &manager, tenant, "POST", uri.clone(), json!({"tip_cents": 501})).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("tip_exceeds_limit")));
+    let (status, _) = send(&app, &manager, tenant, "POST", uri.clone(), json!({"tip_cents": 500})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Lowering a tip needs no approval.
+    let (status, body) = send(&app, &cashier, tenant, "POST", uri, json!({"tip_cents": 100})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (order_tip, payment_amount): (f64, f64) = sqlx::query_as(
+        "SELECT o.tip_amount::float8, p.amount::float8 FROM orders o JOIN payments p ON p.order_id = o.id WHERE o.id = $1",
+    )
+    .bind(order.id)
+    .fetch_one(db)
+    .await
+    .unwrap();
+    assert_eq!((order_tip, payment_amount), (1.0, 21.0));
+}
@@ -0,0 +1,116 @@
+//! Manager approval of void requests through the router against Postgres: PIN lockout under
+//! concurrent guesses, the role check on PIN approvals, and the approved void itself. Needs
+//! Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+
+use axum::body::{to_bytes, Body};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, OrderFixture, TestPostgres, TestSigner};
+use http::{Request, StatusCode};
+use order_service::{build_router, AppState};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn app(db: &PgPool, signer: &TestSigner) -> Router {
+    build_router(AppState {
+        db: db.clone(),
+        jwt_verifier: signer.verifier(),
+        http_client: reqwest::Client::new(),
+        inventory_base_url: "http://localhost:8087".into(),
+        payment_base_url: "http://localhost:8086".into(),
+        enable_payment_intents: false,
+        pii_key: None,
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        kafka_producer: panic!("kafka disabled in tests"),
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+        audit_producer: None,
+    })
+}
+
+/// A signed-in user: id, role and bearer token.
+type User = (Uuid, &'static str, String);
+
+async fn send(app: &Router, (user_id, role, token): &User, tenant: Uuid, method: &str, uri: String, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-User-ID", user_id.to_string())
+        .header("X-Roles", *role)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn pin_approvals_lock_out_guessers_and_recheck_the_role() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["order-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let signer = TestSigner::generate();
+    let tenant = Uuid::new_v4();
+    let order = OrderFixture::new(tenant).line(Uuid::new_v4(), 1, 1_000).status("PENDING").payment_method("cash").insert(db).await.unwrap();
+
+    let app = app(db, &signer);
+    let user = |role: &'static str| {
+        let id = Uuid::new_v4();
+        (id, role, signer.token_for(id, tenant, &[role]))
+    };
+    let (locked_manager, manager, cashier) = (user("manager"), user("manager"), user("cashier"));
+    for holder in [&locked_manager, &manager] {
+        let (status, _) = send(&app, holder, tenant, "PUT", "/admin/approval_pin".into(), json!({"pin": "4821"})).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    let (status, body) = send(&app, &cashier, tenant, "POST", format!("/orders/{}/void_requests", order.id), json!({"reason_code": "cashier_error"})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let approve = format!("/orders/void_requests/{}/approve", body["id"].as_str().unwrap());
+
+    // Concurrent wrong guesses: only five are checked before the lockout takes over.
+    let mut guesses = tokio::task::JoinSet::new();
+    for _ in 0..8 {
+        let (app, cashier, uri) = (app.clone(), cashier.clone(), approve.clone());
+        let body = json!({"manager_id": locked_manager.0, "pin": "0000"});
+        guesses.spawn(async move { send(&app, &cashier, tenant, "POST", uri, body).await });
+    }
+    let mut codes = Vec::new();
+    while let Some(result) = guesses.join_next().await {
+        let (status, body) = result.unwrap();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        codes.push(body["code"].as_str().unwrap().to_string());
+    }
+    assert_eq!(codes.iter().filter(|code| *code == "invalid_pin").count(), 5, "{codes:?}");
+    let (status, body) = send(&app, &cashier, tenant, "POST", approve.clone(), json!({"manager_id": locked_manager.0, "pin": "4821"})).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::FORBIDDEN, Some("approver_locked")), "the right PIN is refused while locked");
+
+    // The PIN holder lost the role after setting it: the PIN no longer approves.
+    sqlx::query("UPDATE approval_pins SET roles = '{cashier}' WHERE tenant_id = $1 AND user_id = $2")
+        .bind(tenant)
+        .bind(manager.0)
+        .execute(db)
+        .await
+        .unwrap();
+    let (status, body) = send(&app, &cashier, tenant, "POST", approve.clone(), json!({"manager_id": manager.0, "pin": "4821"})).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::FORBIDDEN, Some("missing_role")));
+
+    let (status, _) = send(&app, &manager, tenant, "PUT", "/admin/approval_pin".into(), json!({"pin": "4821"})).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = send(&app, &cashier, tenant, "POST", approve, json!({"manager_id": manager.0, "pin": "4821"})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], json!("VOIDED"));
+    let (decided_by, method): (Option<Uuid>, Option<String>) =
+        sqlx::query_as("SELECT decided_by, approval_method FROM order_void_requests WHERE order_id = $1")
+            .bind(order.id)
+            .fetch_one(db)
+            .await
+            .unwrap();
+    assert_eq!((decided_by, method.as_deref()), (Some(manager.0), Some("pin")));
+}
@@ -0,0 +1,7 @@
+-- 8004: encrypted cardholder details captured at confirm time.
+-- Values are common-crypto envelopes sealed with a key derived from PAYMENT_PII_KEY for the
+-- caller's tenant; plaintext card data is never stored.
+
+ALTER TABLE payment_intents
+    ADD COLUMN IF NOT EXISTS cardholder_name_encrypted BYTEA,
+    ADD COLUMN IF NOT EXISTS card_last4_encrypted BYTEA;
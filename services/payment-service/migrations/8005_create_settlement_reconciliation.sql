@@ -0,0 +1,32 @@
+-- 8005: provider settlement reports and the reconciliation result for each upload.
+-- A batch is one settlement report (CSV file or API payload) for one provider and period;
+-- reconciliation_items holds one row per settlement line plus one per recorded payment the
+-- report did not mention.
+
+CREATE TABLE IF NOT EXISTS settlement_batches (
+    id              UUID PRIMARY KEY,
+    tenant_id       UUID NOT NULL,
+    provider        TEXT NOT NULL,
+    source          TEXT NOT NULL CHECK (source IN ('csv','api')),
+    period_start    TIMESTAMPTZ NOT NULL,
+    period_end      TIMESTAMPTZ NOT NULL,
+    line_count      INT NOT NULL,
+    created_by      UUID,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_batches_tenant ON settlement_batches(tenant_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS reconciliation_items (
+    id                      BIGSERIAL PRIMARY KEY,
+    batch_id                UUID NOT NULL REFERENCES settlement_batches(id) ON DELETE CASCADE,
+    provider_ref            TEXT NOT NULL,
+    payment_intent_id       TEXT,
+    status                  TEXT NOT NULL CHECK (status IN ('matched','missing_payment','missing_settlement','amount_delta','duplicate')),
+    settled_amount_minor    BIGINT,
+    recorded_amount_minor   BIGINT,
+    currency                TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_items_batch ON reconciliation_items(batch_id, status);
+CREATE INDEX IF NOT EXISTS idx_payment_intents_provider_ref ON payment_intents(provider_ref) WHERE provider_ref IS NOT NULL;
@@ -0,0 +1,29 @@
+-- 8006: chargebacks and disputes reported by providers.
+-- payment_intents gains the owning tenant so a provider webhook (which carries no tenant) can be
+-- tied back to the tenant and order; intents created before this migration stay NULL.
+
+ALTER TABLE payment_intents ADD COLUMN IF NOT EXISTS tenant_id UUID;
+
+CREATE TABLE IF NOT EXISTS disputes (
+    id                   UUID PRIMARY KEY,
+    provider             TEXT NOT NULL,
+    provider_dispute_id  TEXT NOT NULL,
+    provider_ref         TEXT NOT NULL,
+    payment_intent_id    TEXT REFERENCES payment_intents(id),
+    order_id             TEXT,
+    tenant_id            UUID,
+    status               TEXT NOT NULL CHECK (status IN ('needs_response','under_review','won','lost')),
+    amount_minor         BIGINT NOT NULL,
+    currency             TEXT NOT NULL,
+    reason               TEXT,
+    evidence_due_by      TIMESTAMPTZ,
+    evidence             JSONB,
+    evidence_submitted_at TIMESTAMPTZ,
+    evidence_submitted_by UUID,
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at           TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (provider, provider_dispute_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_disputes_tenant_status ON disputes(tenant_id, status, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_disputes_order ON disputes(order_id);
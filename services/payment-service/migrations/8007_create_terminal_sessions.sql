@@ -0,0 +1,28 @@
+-- 8007: card-present payments taken on a physical terminal (semi-integrated).
+-- A session is one attempt to charge an order on one terminal. Approved sessions also get a
+-- captured payment_intents row so reconciliation and disputes see terminal payments.
+
+CREATE TABLE IF NOT EXISTS terminal_sessions (
+    id                 UUID PRIMARY KEY,
+    tenant_id          UUID NOT NULL,
+    order_id           UUID NOT NULL,
+    terminal_id        TEXT NOT NULL,
+    driver             TEXT NOT NULL,
+    driver_ref         TEXT,
+    amount_minor       BIGINT NOT NULL CHECK (amount_minor > 0),
+    currency           TEXT NOT NULL,
+    status             TEXT NOT NULL CHECK (status IN ('pending','approved','declined','cancelled','failed')),
+    approval_code      TEXT,
+    card_brand         TEXT,
+    failure_reason     TEXT,
+    payment_intent_id  TEXT REFERENCES payment_intents(id),
+    created_by         UUID,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at       TIMESTAMPTZ
+);
+
+-- One transaction at a time per terminal.
+CREATE UNIQUE INDEX IF NOT EXISTS uq_terminal_sessions_active ON terminal_sessions(tenant_id, terminal_id) WHERE status = 'pending';
+CREATE UNIQUE INDEX IF NOT EXISTS uq_terminal_sessions_driver_ref ON terminal_sessions(driver, driver_ref) WHERE driver_ref IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_terminal_sessions_order ON terminal_sessions(tenant_id, order_id);
@@ -0,0 +1,4 @@
+-- 8008: tip carried on a card payment intent. amount_minor stays the full charge (sale plus tip);
+-- tip_minor is the part of it that is tip, adjustable until the payment settles.
+
+ALTER TABLE payment_intents ADD COLUMN IF NOT EXISTS tip_minor BIGINT NOT NULL DEFAULT 0 CHECK (tip_minor >= 0);
@@ -0,0 +1,33 @@
+-- 8009: stand-in processing for card payment provider outages.
+-- Tenants opt in with a floor limit; while the provider is unreachable, authorizations at or
+-- below it are queued here and answered as conditionally approved. The processor replays them
+-- once the provider recovers and force-declines what still fails.
+
+CREATE TABLE IF NOT EXISTS stand_in_settings (
+    tenant_id          UUID PRIMARY KEY,
+    enabled            BOOLEAN NOT NULL DEFAULT FALSE,
+    floor_limit_minor  BIGINT NOT NULL DEFAULT 0 CHECK (floor_limit_minor >= 0),
+    updated_by         UUID,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS stand_in_authorizations (
+    id                 UUID PRIMARY KEY,
+    tenant_id          UUID NOT NULL,
+    order_id           UUID NOT NULL,
+    method             TEXT NOT NULL,
+    amount_minor       BIGINT NOT NULL CHECK (amount_minor > 0),
+    currency           TEXT NOT NULL,
+    status             TEXT NOT NULL DEFAULT 'queued' CHECK (status IN ('queued','approved','declined')),
+    attempts           INT NOT NULL DEFAULT 0,
+    approval_code      TEXT,
+    last_error         TEXT,
+    next_attempt_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    resolved_at        TIMESTAMPTZ
+);
+
+-- A retried payment for the same order joins the existing entry.
+CREATE UNIQUE INDEX IF NOT EXISTS uq_stand_in_authorizations_order ON stand_in_authorizations(tenant_id, order_id);
+CREATE INDEX IF NOT EXISTS idx_stand_in_authorizations_due ON stand_in_authorizations(next_attempt_at) WHERE status = 'queued';
@@ -0,0 +1,7 @@
+-- 8010: retention. Payment intents past PAYMENT_RETENTION_MONTHS lose their encrypted cardholder
+-- details (see payment_service::retention); anonymized_at records when that happened.
+
+ALTER TABLE payment_intents ADD COLUMN IF NOT EXISTS anonymized_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_payment_intents_retention_candidates ON payment_intents(created_at)
+    WHERE cardholder_name_encrypted IS NOT NULL OR card_last4_encrypted IS NOT NULL;
@@ -0,0 +1,13 @@
+-- 8011: stand-in replays lease an entry ('processing' until locked_until) and call the provider
+-- outside any transaction. A lease that runs out, e.g. because the processor died mid-call, makes
+-- the entry due again.
+
+ALTER TABLE stand_in_authorizations ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+
+ALTER TABLE stand_in_authorizations DROP CONSTRAINT IF EXISTS stand_in_authorizations_status_check;
+ALTER TABLE stand_in_authorizations ADD CONSTRAINT stand_in_authorizations_status_check
+    CHECK (status IN ('queued','processing','approved','declined'));
+
+DROP INDEX IF EXISTS idx_stand_in_authorizations_due;
+CREATE INDEX IF NOT EXISTS idx_stand_in_authorizations_due ON stand_in_authorizations(next_attempt_at)
+    WHERE status IN ('queued','processing');
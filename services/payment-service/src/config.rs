@@ -0,0 +1,73 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentConfig {
+    pub http: HttpSettings,
+    /// Without a database the service runs without persistence.
+    pub database_url: Option<Redacted<String>>,
+    pub pool: PoolSettings,
+    /// `KAFKA_BROKERS`; audit events are only emitted when set.
+    pub kafka_brokers: Option<String>,
+    pub audit_topic: String,
+    pub jwt: JwtSettings,
+    /// `PAYMENT_PROVIDER_RETRIES`: retries of an authorization the provider could not take, at most 10.
+    pub provider_retries: u32,
+    /// `PAYMENT_PROVIDER_RETRY_BACKOFF_MS`: wait before the first retry; doubled for each further one.
+    pub provider_retry_backoff_ms: u64,
+    /// `STAND_IN_PROCESS_SECS`: how often queued stand-in payments are replayed; 0 disables.
+    pub stand_in_process_secs: u64,
+    /// `STAND_IN_MAX_ATTEMPTS`: replays before a queued payment is force-declined.
+    pub stand_in_max_attempts: i32,
+    /// `PAYMENT_RETENTION_MONTHS`: settled payments older than this lose their card details; 0 keeps them.
+    pub retention_months: u32,
+    /// `PAYMENT_RETENTION_BATCH_SIZE`: rows anonymized per statement.
+    pub retention_batch_size: i64,
+    /// `PAYMENT_RETENTION_DRY_RUN`: only count and audit what the retention job would strip.
+    pub retention_dry_run: bool,
+    /// `PAYMENT_RETENTION_INTERVAL_SECS`: how often the retention job runs.
+    pub retention_interval_secs: u64,
+}
+
+impl PaymentConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8086);
+        let database_url = env.secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_brokers = env.optional("KAFKA_BROKERS");
+        let audit_topic = env.or("AUDIT_TOPIC", "audit.events".to_string());
+        let jwt = JwtSettings::read(&mut env).await;
+        let provider_retries: u32 = env.or("PAYMENT_PROVIDER_RETRIES", 2);
+        env.check("PAYMENT_PROVIDER_RETRIES", provider_retries <= 10, "must be at most 10");
+        let provider_retry_backoff_ms: u64 = env.or("PAYMENT_PROVIDER_RETRY_BACKOFF_MS", 200);
+        let stand_in_process_secs = env.or("STAND_IN_PROCESS_SECS", 30);
+        let stand_in_max_attempts: i32 = env.or("STAND_IN_MAX_ATTEMPTS", 20);
+        let retention_months: u32 = env.or("PAYMENT_RETENTION_MONTHS", 0);
+        let retention_batch_size: i64 = env.or("PAYMENT_RETENTION_BATCH_SIZE", 500);
+        let retention_dry_run = env.flag("PAYMENT_RETENTION_DRY_RUN", false);
+        let retention_interval_secs: u64 = env.or("PAYMENT_RETENTION_INTERVAL_SECS", 86_400);
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url,
+                pool,
+                kafka_brokers,
+                audit_topic,
+                jwt: jwt?,
+                provider_retries,
+                provider_retry_backoff_ms,
+                stand_in_process_secs,
+                stand_in_max_attempts: stand_in_max_attempts.max(1),
+                retention_months,
+                retention_batch_size: retention_batch_size.max(1),
+                retention_dry_run,
+                retention_interval_secs: retention_interval_secs.max(60),
+            })
+        })
+    }
+}
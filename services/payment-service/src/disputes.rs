@@ -0,0 +1,398 @@
+//! Chargebacks and disputes raised against captured payments.
+//!
+//! Providers report disputes through `POST /webhooks/disputes` (`dispute.created` /
+//! `dispute.updated`, signature-checked by [`crate::webhook::verify_webhook`]). Each dispute is
+//! tied to its payment intent by `provider_ref`, and through the intent to the order and tenant.
+//! Finance submits evidence through `POST /disputes/:id/evidence`. Every status change is written
+//! to the outbox as `payment.dispute.updated` so order-service and analytics can show disputed
+//! revenue.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_events::{DisputeStatus, DomainEvent, PaymentDisputeUpdatedEvent};
+use common_http_errors::ApiError;
+use common_security::{Capability, SecurityContext, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, Opts};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{authorize, db_error, AppState};
+
+/// Map a provider's dispute status onto ours. Providers differ in naming; the early-warning
+/// (inquiry) states count as disputes that need a response.
+pub fn parse_provider_status(value: &str) -> Option<DisputeStatus> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "needs_response" | "warning_needs_response" | "open" => Some(DisputeStatus::NeedsResponse),
+        "under_review" | "warning_under_review" => Some(DisputeStatus::UnderReview),
+        "won" => Some(DisputeStatus::Won),
+        // A merchant accepting the dispute or refunding the charge forfeits it.
+        "lost" | "accepted" | "charge_refunded" => Some(DisputeStatus::Lost),
+        _ => None,
+    }
+}
+
+/// Allowed status changes. Issuers may ask for more evidence while reviewing; decisions are final.
+pub fn is_valid_transition(from: DisputeStatus, to: DisputeStatus) -> bool {
+    use DisputeStatus::*;
+    match from {
+        NeedsResponse => matches!(to, UnderReview | Won | Lost),
+        UnderReview => matches!(to, NeedsResponse | Won | Lost),
+        Won | Lost => false,
+    }
+}
+
+static DISPUTE_TRANSITIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_dispute_transitions_total", "Dispute status changes by provider and new status"),
+        &["provider", "status"],
+    ).expect("payment_dispute_transitions_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Dispute {
+    pub id: Uuid,
+    pub provider: String,
+    #[serde(rename = "providerDisputeId")] pub provider_dispute_id: String,
+    #[serde(rename = "providerRef")] pub provider_ref: String,
+    #[serde(rename = "paymentIntentId")] pub payment_intent_id: Option<String>,
+    #[serde(rename = "orderId")] pub order_id: Option<String>,
+    #[serde(skip)]
+    pub tenant_id: Option<Uuid>,
+    pub status: String,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+    pub reason: Option<String>,
+    #[serde(rename = "evidenceDueBy")] pub evidence_due_by: Option<DateTime<Utc>>,
+    pub evidence: Option<serde_json::Value>,
+    #[serde(rename = "evidenceSubmittedAt")] pub evidence_submitted_at: Option<DateTime<Utc>>,
+    #[serde(rename = "evidenceSubmittedBy")] pub evidence_submitted_by: Option<Uuid>,
+    #[serde(rename = "createdAt")] pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")] pub updated_at: DateTime<Utc>,
+}
+
+const DISPUTE_COLUMNS: &str = "id, provider, provider_dispute_id, provider_ref, payment_intent_id, order_id, tenant_id, status, amount_minor, currency, reason, evidence_due_by, evidence, evidence_submitted_at, evidence_submitted_by, created_at, updated_at";
+
+impl Dispute {
+    fn status(&self) -> DisputeStatus {
+        DisputeStatus::parse(&self.status).unwrap_or(DisputeStatus::NeedsResponse)
+    }
+}
+
+/// Stage `payment.dispute.updated` in the outbox. Disputes whose payment we can't place (unknown
+/// intent, intent without tenant, or a non-UUID order id) are kept but not published.
+async fn stage_event(tx: &mut Transaction<'_, Postgres>, dispute: &Dispute, previous: Option<DisputeStatus>) -> anyhow::Result<()> {
+    let order_id = dispute.order_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+    let (Some(tenant_id), Some(order_id)) = (dispute.tenant_id, order_id) else {
+        warn!(dispute_id = %dispute.id, provider_ref = %dispute.provider_ref, "dispute not linked to a tenant order; event not published");
+        return Ok(());
+    };
+    let event = PaymentDisputeUpdatedEvent {
+        schema_version: PaymentDisputeUpdatedEvent::SCHEMA_VERSION,
+        dispute_id: dispute.id,
+        order_id,
+        tenant_id,
+        payment_intent_id: dispute.payment_intent_id.clone(),
+        status: dispute.status(),
+        previous_status: previous,
+        amount_minor: dispute.amount_minor,
+        currency: dispute.currency.clone(),
+        reason: dispute.reason.clone(),
+    };
+    crate::outbox::stage(tx, tenant_id, &event).await?;
+    DISPUTE_TRANSITIONS_TOTAL.with_label_values(&[&dispute.provider, dispute.status.as_str()]).inc();
+    Ok(())
+}
+
+// ---- Webhook ----
+#[derive(Debug, Deserialize)]
+pub struct DisputeWebhook {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Falls back to the `X-Provider` header.
+    pub provider: Option<String>,
+    pub dispute: DisputePayload,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisputePayload {
+    pub id: String,
+    #[serde(rename = "providerRef")] pub provider_ref: String,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+    pub status: String,
+    pub reason: Option<String>,
+    #[serde(rename = "evidenceDueBy")] pub evidence_due_by: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisputeWebhookResponse {
+    pub id: Uuid,
+    pub status: String,
+    /// False when the update was stale (e.g. arrived after the dispute closed) and was ignored.
+    pub applied: bool,
+}
+
+/// What a webhook delivery does to the stored dispute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookOutcome {
+    Opened,
+    Transitioned(DisputeStatus),
+    /// Same status; amounts, reason and due date are refreshed.
+    Refreshed,
+    Ignored,
+}
+
+pub fn webhook_outcome(current: Option<DisputeStatus>, incoming: DisputeStatus) -> WebhookOutcome {
+    match current {
+        None => WebhookOutcome::Opened,
+        Some(from) if from == incoming => WebhookOutcome::Refreshed,
+        Some(from) if is_valid_transition(from, incoming) => WebhookOutcome::Transitioned(from),
+        Some(_) => WebhookOutcome::Ignored,
+    }
+}
+
+pub async fn dispute_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(hook): Json<DisputeWebhook>,
+) -> Result<Json<DisputeWebhookResponse>, ApiError> {
+    let bad_request = |code: &'static str, message: String| ApiError::BadRequest { code, trace_id: None, message: Some(message) };
+    if !matches!(hook.event_type.as_str(), "dispute.created" | "dispute.updated") {
+        return Err(bad_request("unsupported_event_type", format!("unsupported event type {}", hook.event_type)));
+    }
+    let provider = hook
+        .provider
+        .clone()
+        .or_else(|| headers.get("X-Provider").and_then(|v| v.to_str().ok()).map(str::to_string))
+        .filter(|p| !p.trim().is_empty())
+        .ok_or_else(|| bad_request("missing_provider", "provider is required".into()))?;
+    let status = parse_provider_status(&hook.dispute.status)
+        .ok_or_else(|| bad_request("invalid_dispute_status", format!("unknown dispute status {}", hook.dispute.status)))?;
+    let db = state.db.as_ref().ok_or(ApiError::Internal { trace_id: None, message: Some("database_not_configured".into()) })?;
+
+    let (dispute, applied) = apply_webhook(db, &provider, &hook.dispute, status)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("db_error: {e}")) })?;
+    Ok(Json(DisputeWebhookResponse { id: dispute.id, status: dispute.status, applied }))
+}
+
+async fn apply_webhook(db: &PgPool, provider: &str, payload: &DisputePayload, status: DisputeStatus) -> anyhow::Result<(Dispute, bool)> {
+    let mut tx = db.begin().await?;
+    let existing = sqlx::query_as::<_, Dispute>(&format!(
+        "SELECT {DISPUTE_COLUMNS} FROM disputes WHERE provider = $1 AND provider_dispute_id = $2 FOR UPDATE"
+    ))
+    .bind(provider)
+    .bind(&payload.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let outcome = webhook_outcome(existing.as_ref().map(Dispute::status), status);
+    let dispute = match existing {
+        None => {
+            // Intents only carry a tenant since migration 8006; older ones stay unlinked.
+            let intent: Option<(String, String, Option<Uuid>)> = sqlx::query_as(
+                "SELECT id, order_id, tenant_id FROM payment_intents WHERE provider_ref = $1 ORDER BY created_at LIMIT 1",
+            )
+            .bind(&payload.provider_ref)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let (intent_id, order_id, tenant_id) = match intent {
+                Some((id, order_id, tenant_id)) => (Some(id), Some(order_id), tenant_id),
+                None => (None, None, None),
+            };
+            sqlx::query_as::<_, Dispute>(&format!(
+                "INSERT INTO disputes (id, provider, provider_dispute_id, provider_ref, payment_intent_id, order_id, tenant_id, status, amount_minor, currency, reason, evidence_due_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING {DISPUTE_COLUMNS}"
+            ))
+            .bind(Uuid::new_v4())
+            .bind(provider)
+            .bind(&payload.id)
+            .bind(&payload.provider_ref)
+            .bind(intent_id)
+            .bind(order_id)
+            .bind(tenant_id)
+            .bind(status.as_str())
+            .bind(payload.amount_minor)
+            .bind(payload.currency.to_ascii_uppercase())
+            .bind(&payload.reason)
+            .bind(payload.evidence_due_by)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        Some(current) if outcome == WebhookOutcome::Ignored => {
+            warn!(dispute_id = %current.id, current = %current.status, incoming = status.as_str(), "ignoring stale dispute update");
+            tx.rollback().await?;
+            return Ok((current, false));
+        }
+        Some(current) => {
+            sqlx::query_as::<_, Dispute>(&format!(
+                "UPDATE disputes
+                 SET status = $2, amount_minor = $3, reason = COALESCE($4, reason),
+                     evidence_due_by = COALESCE($5, evidence_due_by), updated_at = now()
+                 WHERE id = $1 RETURNING {DISPUTE_COLUMNS}"
+            ))
+            .bind(current.id)
+            .bind(status.as_str())
+            .bind(payload.amount_minor)
+            .bind(&payload.reason)
+            .bind(payload.evidence_due_by)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+    match outcome {
+        WebhookOutcome::Opened => stage_event(&mut tx, &dispute, None).await?,
+        WebhookOutcome::Transitioned(from) => stage_event(&mut tx, &dispute, Some(from)).await?,
+        WebhookOutcome::Refreshed | WebhookOutcome::Ignored => {}
+    }
+    tx.commit().await?;
+    info!(dispute_id = %dispute.id, provider, status = %dispute.status, ?outcome, "dispute webhook applied");
+    Ok((dispute, true))
+}
+
+// ---- Finance endpoints ----
+#[derive(Debug, Default, Deserialize)]
+pub struct ListDisputesParams {
+    pub status: Option<String>,
+}
+
+/// `GET /disputes?status=..`: the tenant's most recent disputes.
+pub async fn list_disputes(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<ListDisputesParams>,
+) -> Result<Json<Vec<Dispute>>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    let status = match params.status.as_deref() {
+        None => None,
+        Some(raw) => Some(DisputeStatus::parse(raw).ok_or(ApiError::BadRequest {
+            code: "invalid_status",
+            trace_id: sec.trace_id,
+            message: Some(format!("unknown status {raw}")),
+        })?),
+    };
+    let rows = sqlx::query_as::<_, Dispute>(&format!(
+        "SELECT {DISPUTE_COLUMNS} FROM disputes WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2) ORDER BY created_at DESC LIMIT 200"
+    ))
+    .bind(sec.tenant_id)
+    .bind(status.map(|s| s.as_str()))
+    .fetch_all(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    Ok(Json(rows))
+}
+
+async fn load_for_tenant(db: &PgPool, sec: &SecurityContext, id: Uuid) -> Result<Dispute, ApiError> {
+    sqlx::query_as::<_, Dispute>(&format!("SELECT {DISPUTE_COLUMNS} FROM disputes WHERE id = $1 AND tenant_id = $2"))
+        .bind(id)
+        .bind(sec.tenant_id)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "dispute_not_found", trace_id: sec.trace_id })
+}
+
+/// `GET /disputes/:id`
+pub async fn get_dispute(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Dispute>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    load_for_tenant(&db, &sec, id).await.map(Json)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EvidenceDocument {
+    /// e.g. `receipt`, `shipping_proof`, `customer_communication`, `refund_policy`.
+    pub kind: String,
+    /// Where the document is stored (file id or URL); the document itself is not uploaded here.
+    pub reference: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvidenceRequest {
+    pub note: Option<String>,
+    #[serde(default)]
+    pub documents: Vec<EvidenceDocument>,
+}
+
+/// `POST /disputes/:id/evidence`: record what was submitted to the provider and move the dispute to
+/// `under_review`. Only disputes that still need a response, before their due date, accept evidence.
+pub async fn submit_evidence(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(id): Path<Uuid>,
+    Json(req): Json<EvidenceRequest>,
+) -> Result<Json<Dispute>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    let note = req.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if note.is_none() && req.documents.is_empty() {
+        return Err(ApiError::BadRequest { code: "evidence_required", trace_id: sec.trace_id, message: Some("provide a note or at least one document".into()) });
+    }
+    if req.documents.iter().any(|d| d.kind.trim().is_empty() || d.reference.trim().is_empty()) {
+        return Err(ApiError::BadRequest { code: "invalid_evidence_document", trace_id: sec.trace_id, message: Some("documents need a kind and a reference".into()) });
+    }
+    let dispute = load_for_tenant(&db, &sec, id).await?;
+    if dispute.status() != DisputeStatus::NeedsResponse {
+        return Err(ApiError::Conflict { code: "invalid_dispute_state", trace_id: sec.trace_id, message: Some(format!("dispute is {}", dispute.status)) });
+    }
+    if dispute.evidence_due_by.is_some_and(|due| due < Utc::now()) {
+        return Err(ApiError::Conflict { code: "evidence_deadline_passed", trace_id: sec.trace_id, message: None });
+    }
+
+    let evidence = serde_json::json!({ "note": note, "documents": req.documents });
+    let mut tx = db.begin().await.map_err(db_error(sec.trace_id))?;
+    let updated = sqlx::query_as::<_, Dispute>(&format!(
+        "UPDATE disputes
+         SET evidence = $2, evidence_submitted_at = now(), evidence_submitted_by = $3, status = $4, updated_at = now()
+         WHERE id = $1 AND status = $5 RETURNING {DISPUTE_COLUMNS}"
+    ))
+    .bind(dispute.id)
+    .bind(&evidence)
+    .bind(sec.actor.id)
+    .bind(DisputeStatus::UnderReview.as_str())
+    .bind(DisputeStatus::NeedsResponse.as_str())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error(sec.trace_id))?
+    .ok_or(ApiError::Conflict { code: "invalid_dispute_state", trace_id: sec.trace_id, message: Some("dispute changed concurrently".into()) })?;
+    stage_event(&mut tx, &updated, Some(DisputeStatus::NeedsResponse))
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("outbox_error: {e}")) })?;
+    tx.commit().await.map_err(db_error(sec.trace_id))?;
+    Ok(Json(updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_statuses_map_onto_the_lifecycle() {
+        assert_eq!(parse_provider_status("warning_needs_response"), Some(DisputeStatus::NeedsResponse));
+        assert_eq!(parse_provider_status("Under_Review"), Some(DisputeStatus::UnderReview));
+        assert_eq!(parse_provider_status("charge_refunded"), Some(DisputeStatus::Lost));
+        assert_eq!(parse_provider_status("escalated"), None);
+    }
+
+    #[test]
+    fn closed_disputes_ignore_late_updates() {
+        use DisputeStatus::*;
+        assert_eq!(webhook_outcome(None, UnderReview), WebhookOutcome::Opened);
+        assert_eq!(webhook_outcome(Some(NeedsResponse), NeedsResponse), WebhookOutcome::Refreshed);
+        assert_eq!(webhook_outcome(Some(NeedsResponse), UnderReview), WebhookOutcome::Transitioned(NeedsResponse));
+        assert_eq!(webhook_outcome(Some(UnderReview), NeedsResponse), WebhookOutcome::Transitioned(UnderReview));
+        assert_eq!(webhook_outcome(Some(Won), UnderReview), WebhookOutcome::Ignored);
+        assert_eq!(webhook_outcome(Some(Lost), Won), WebhookOutcome::Ignored);
+    }
+}
@@ -1,17 +1,36 @@
 use anyhow::Result;
 
+/// The provider's answer to an authorization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Authorization {
+    Approved { approval_code: String },
+    Declined { reason: String },
+}
+
 #[async_trait::async_trait]
 pub trait PaymentGateway: Send + Sync {
+    /// `Err` means the provider could not be reached; declines are `Ok`.
+    async fn authorize(&self, order_id: &str, amount_minor: i64, currency: &str) -> Result<Authorization>;
     async fn void(&self, provider: &str, provider_ref: &str) -> Result<Option<String>>;
     async fn refund(&self, provider: &str, provider_ref: &str) -> Result<Option<String>>;
 }
 
+/// Approves everything except, like provider test cards, amounts ending in `.05` (declined) and
+/// `.13` (provider unreachable).
+#[derive(Default)]
 pub struct StubGateway;
 
 impl StubGateway { pub fn new() -> Self { Self } }
 
 #[async_trait::async_trait]
 impl PaymentGateway for StubGateway {
+    async fn authorize(&self, order_id: &str, amount_minor: i64, _currency: &str) -> Result<Authorization> {
+        match amount_minor % 100 {
+            5 => Ok(Authorization::Declined { reason: "insufficient funds".into() }),
+            13 => anyhow::bail!("provider unreachable"),
+            _ => Ok(Authorization::Approved { approval_code: format!("VAL-APPROVED-{}", &order_id[..8.min(order_id.len())]) }),
+        }
+    }
     async fn void(&self, _provider: &str, provider_ref: &str) -> Result<Option<String>> {
         Ok(Some(format!("{}-void", provider_ref)))
     }
@@ -3,12 +3,19 @@ use common_auth::JwtVerifier;
 use axum::extract::FromRef;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_audit::{BufferedAuditProducer, KafkaAuditSink};
 use sqlx::PgPool;
+use common_crypto::ColumnKey;
+use common_http_errors::ApiError;
+use common_security::{ensure_capability, Capability, SecurityContext};
 
 
 #[derive(Clone)]
 pub struct AppState {
     pub jwt_verifier: Arc<JwtVerifier>,
     pub db: Option<PgPool>,
+    /// Key for encrypted card detail columns (`PAYMENT_PII_KEY`). Card details are dropped when unset.
+    pub pii_key: Option<Arc<ColumnKey>>,
+    /// Retries of a card authorization the provider could not take.
+    pub retry_policy: stand_in::RetryPolicy,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] pub audit_producer: Option<Arc<BufferedAuditProducer<KafkaAuditSink>>>,
 }
 
@@ -16,4 +23,42 @@ pub mod payment_handlers;
 pub mod repo;
 pub mod webhook;
 pub mod gateway;
+pub mod reconciliation;
+pub mod stand_in;
+pub mod disputes;
+pub mod outbox;
+pub mod retention;
+pub mod terminal;
+pub mod terminal_driver;
+pub const CARDHOLDER_NAME_FIELD: &str = "payment_intents.cardholder_name";
+pub const CARD_LAST4_FIELD: &str = "payment_intents.card_last4";
+
+/// Load the card detail column key from `PAYMENT_PII_KEY` (base64, 32 bytes).
+pub fn pii_key_from_env() -> anyhow::Result<Option<Arc<ColumnKey>>> {
+    match std::env::var("PAYMENT_PII_KEY") {
+        Ok(value) if !value.trim().is_empty() => ColumnKey::from_base64(value.trim())
+            .map(|key| Some(Arc::new(key)))
+            .map_err(|err| anyhow::anyhow!("Invalid PAYMENT_PII_KEY: {err}")),
+        _ => Ok(None),
+    }
+}
+
+/// Check `capability` for an endpoint that needs the database and hand back the pool. Denials
+/// are audited when Kafka is enabled and answered with `role` as the missing role.
+pub(crate) async fn authorize(state: &AppState, sec: &SecurityContext, capability: Capability, role: &'static str) -> Result<PgPool, ApiError> {
+    if ensure_capability(sec, capability).is_err() {
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))] common_security::emit_capability_denial_audit(state.audit_producer.as_deref(), sec, capability, "payment-service").await;
+        return Err(ApiError::ForbiddenMissingRole { role, trace_id: sec.trace_id });
+    }
+    state.db.clone().ok_or(ApiError::Internal { trace_id: sec.trace_id, message: Some("database_not_configured".into()) })
+}
+
+/// Map a query error to a response: pool exhaustion is a 503, anything else a 500.
+pub(crate) fn db_error(trace_id: Option<uuid::Uuid>) -> impl Fn(sqlx::Error) -> ApiError {
+    move |e| match e {
+        sqlx::Error::PoolTimedOut => common_db::db_error(e, trace_id),
+        e => ApiError::Internal { trace_id, message: Some(format!("db_error: {e}")) },
+    }
+}
+
 impl FromRef<AppState> for Arc<JwtVerifier> { fn from_ref(state:&AppState)->Self { state.jwt_verifier.clone() } }
@@ -1,4 +1,3 @@
-use anyhow::Context;
 use axum::{
     http::{
         header::{ACCEPT, CONTENT_TYPE},
@@ -8,53 +7,99 @@ use axum::{
     Router,
 };
 use common_auth::{JwtConfig, JwtVerifier};
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
 use once_cell::sync::Lazy;
-use prometheus::{Registry, IntCounterVec, Opts};
+use prometheus::{Registry, IntCounterVec, Opts, Encoder, TextEncoder};
 use axum::middleware;
 use common_money::log_rounding_mode_once;
-use std::{env, net::SocketAddr, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 use tokio::time::{interval, MissedTickBehavior};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 
-use payment_service::{payment_handlers::{process_card_payment, void_card_payment, create_intent, confirm_intent, capture_intent, void_intent, refund_intent, get_intent}, AppState};
+use payment_service::{payment_handlers::{process_card_payment, void_card_payment, create_intent, confirm_intent, capture_intent, void_intent, refund_intent, get_intent, adjust_intent_tip}, AppState};
+use payment_service::disputes::{dispute_webhook, get_dispute, list_disputes, submit_evidence};
+use payment_service::gateway::StubGateway;
+use payment_service::reconciliation::{get_report, upload_settlement, upload_settlement_csv};
+use payment_service::stand_in::{get_settings, list_authorizations, put_settings, spawn_stand_in_processor, RetryPolicy};
+use payment_service::retention::{spawn_retention_job, RetentionPolicy};
+use payment_service::terminal::{cancel_session, create_session, get_session, terminal_webhook};
 use payment_service::webhook::verify_webhook;
-use sqlx::PgPool;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_audit::{KafkaAuditSink, AuditProducer, AuditProducerConfig, BufferedAuditProducer};
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
+
+mod config;
+use config::PaymentConfig;
 
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("payment-service");
     log_rounding_mode_once();
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let config = PaymentConfig::from_env().await?;
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
+    common_security::spawn_policy_refresh(jwt_verifier.clone());
 
-    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let audit_producer = {
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let kafka_producer: Option<FutureProducer> = {
         // Simplified: if KAFKA_BROKERS unset we fallback to None
-        if let Ok(brokers) = env::var("KAFKA_BROKERS") {
-            let producer: FutureProducer = rdkafka::ClientConfig::new()
-                .set("bootstrap.servers", &brokers)
-                .create()
+        if let Some(brokers) = &config.kafka_brokers {
+            let producer = common_kafka::ProducerSettings::from_env()?
+                .create(brokers, &KafkaSecurity::from_env().await?)
                 .expect("failed kafka producer");
-            let sink = KafkaAuditSink::new(producer, AuditProducerConfig { topic: env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events".into()) });
-            Some(Arc::new(BufferedAuditProducer::new(AuditProducer::new(sink), 256)))
+            common_kafka::register_metrics(prometheus::default_registry());
+            Some(producer)
         } else { None }
     };
-    let db = match env::var("DATABASE_URL") {
-        Ok(url) if !url.is_empty() => {
-            match PgPool::connect(&url).await {
-                Ok(pool) => Some(pool),
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))] let audit_producer = kafka_producer.clone().map(|producer| {
+        let sink = KafkaAuditSink::new(producer, AuditProducerConfig { topic: config.audit_topic.clone() });
+        Arc::new(BufferedAuditProducer::new(AuditProducer::new(sink), 256))
+    });
+    let db = match &config.database_url {
+        Some(url) => {
+            match config.pool.connect(url.expose()).await {
+                Ok(pool) => {
+                    common_db::pool::register_metrics(prometheus::default_registry());
+                    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, pool.clone());
+                    Some(pool)
+                }
                 Err(err) => { warn!(error = %err, "Failed to connect to DATABASE_URL; running without DB"); None }
             }
         }
-        _ => None,
+        None => None,
     };
 
-    let state = AppState { jwt_verifier, db, #[cfg(any(feature = "kafka", feature = "kafka-producer"))] audit_producer };
+    let pii_key = payment_service::pii_key_from_env()?;
+    if pii_key.is_none() {
+        warn!("PAYMENT_PII_KEY not set; cardholder details will not be stored");
+    }
+
+    if let (Some(pool), true) = (&db, config.stand_in_process_secs > 0) {
+        spawn_stand_in_processor(
+            pool.clone(),
+            Arc::new(StubGateway::new()),
+            Duration::from_secs(config.stand_in_process_secs),
+            config.stand_in_max_attempts,
+        );
+    }
+
+    let retry_policy = RetryPolicy { retries: config.provider_retries, backoff: Duration::from_millis(config.provider_retry_backoff_ms) };
+    let state = AppState { jwt_verifier, db, pii_key, retry_policy, #[cfg(any(feature = "kafka", feature = "kafka-producer"))] audit_producer };
+
+    if config.retention_months > 0 && state.db.is_some() {
+        let policy = RetentionPolicy {
+            months: config.retention_months,
+            batch_size: config.retention_batch_size,
+            dry_run: config.retention_dry_run,
+        };
+        info!(months = policy.months, dry_run = policy.dry_run, "Payment retention job enabled");
+        spawn_retention_job(state.clone(), policy, Duration::from_secs(config.retention_interval_secs));
+    }
 
     let allowed_origins = [
         "http://localhost:3000",
@@ -69,7 +114,7 @@ async fn main() -> anyhow::Result<()> {
                 .filter_map(|origin| origin.parse::<HeaderValue>().ok())
                 .collect::<Vec<_>>(),
         ))
-        .allow_methods([Method::GET, Method::POST])
+        .allow_methods([Method::GET, Method::POST, Method::PUT])
         .allow_headers([
             ACCEPT,
             CONTENT_TYPE,
@@ -100,17 +145,25 @@ async fn main() -> anyhow::Result<()> {
     }
 
     async fn metrics() -> (axum::http::StatusCode, String) {
-        // Minimal Prometheus text exposition to standardize endpoint; expand later
-        let body = "# HELP service_up 1 if the service is running\n# TYPE service_up gauge\nservice_up{service=\"payment-service\"} 1\n";
-        (axum::http::StatusCode::OK, body.to_string())
+        let mut body = "# HELP service_up 1 if the service is running\n# TYPE service_up gauge\nservice_up{service=\"payment-service\"} 1\n".to_string();
+        // Default registry: capability checks, reconciliation gauges, dispute and terminal counters.
+        let mut buffer = Vec::new();
+        if TextEncoder::new().encode(&prometheus::gather(), &mut buffer).is_ok() {
+            body.push_str(&String::from_utf8_lossy(&buffer));
+        }
+        (axum::http::StatusCode::OK, body)
     }
 
     // Webhook signature verification middleware with HMAC, timestamp skew and nonce replay protection
     // verify_webhook now lives in payment_service::webhook
 
+    let slo = SloTracker::new("payment-service", SloConfig::from_env(), prometheus::default_registry());
     let app = Router::new()
         .route("/healthz", get(|| async { "ok" }))
         .route("/metrics", get(metrics))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .route("/payments", post(process_card_payment))
         .route("/payments/void", post(void_card_payment))
         // Payment intents MVP (HTTP JSON stubs)
@@ -120,44 +173,55 @@ async fn main() -> anyhow::Result<()> {
         .route("/payment_intents/capture", post(capture_intent))
         .route("/payment_intents/void", post(void_intent))
         .route("/payment_intents/refund", post(refund_intent))
+        .route("/payment_intents/tip", post(adjust_intent_tip))
+        .route("/stand-in/settings", get(get_settings).put(put_settings))
+        .route("/stand-in/authorizations", get(list_authorizations))
+        .route("/reconciliation/settlements", post(upload_settlement))
+        .route("/reconciliation/settlements/csv", post(upload_settlement_csv))
+        .route("/reconciliation/reports/:id", get(get_report))
+        .route("/disputes", get(list_disputes))
+        .route("/disputes/:id", get(get_dispute))
+        .route("/disputes/:id/evidence", post(submit_evidence))
+        .route("/webhooks/disputes", post(dispute_webhook))
+        .route("/terminal/sessions", post(create_session))
+        .route("/terminal/sessions/:id", get(get_session))
+        .route("/terminal/sessions/:id/cancel", post(cancel_session))
+        .route("/webhooks/terminal", post(terminal_webhook))
     .with_state(state)
     .layer(middleware::from_fn(http_error_metrics))
     .layer(middleware::from_fn(verify_webhook))
-        .layer(cors);
+        .layer(cors)
+        .layer(middleware::from_fn(slo.layer()))
+        .layer(middleware::from_fn(common_observability::request_span));
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .unwrap_or_else(|_| "8086".to_string())
-        .parse()?;
-    let addr = SocketAddr::new(host.parse()?, port);
+    let addr = config.http.addr();
     println!("starting payment-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    if let Some(producer) = &kafka_producer {
+        common_kafka::flush(producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
+    }
     Ok(())
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -166,16 +230,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
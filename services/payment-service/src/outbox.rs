@@ -0,0 +1,17 @@
+//! Events leave payment-service through the shared `outbox` table, written in the same
+//! transaction as the state change. order-service's relay publishes them to Kafka.
+
+use common_events::DomainEvent;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+pub async fn stage<E: DomainEvent>(tx: &mut Transaction<'_, Postgres>, tenant_id: Uuid, event: &E) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO outbox (tenant_id, topic, payload, message_key) VALUES ($1, $2, $3, $4)")
+        .bind(tenant_id.to_string())
+        .bind(E::TOPIC)
+        .bind(common_events::to_value(event)?)
+        .bind(event.partition_key())
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
@@ -1,10 +1,11 @@
-use crate::{AppState, repo};
+use crate::{AppState, repo, stand_in, CARDHOLDER_NAME_FIELD, CARD_LAST4_FIELD};
 use axum::{
     extract::State,
     http::HeaderMap,
     Json,
 };
-use common_security::{SecurityCtxExtractor, Capability, ensure_capability};
+use common_security::{SecurityCtxExtractor, SecurityContext, Capability, ensure_capability};
+use common_crypto::EncryptedColumn;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_security::emit_capability_denial_audit;
 use common_http_errors::ApiError;
 use serde::{Deserialize, Serialize};
@@ -12,21 +13,30 @@ use bigdecimal::BigDecimal;
 use common_money::Money; // normalize_scale not needed here
 use std::time::Duration;
 use tokio::time::sleep;
-use crate::gateway::{PaymentGateway, StubGateway};
+use crate::gateway::{Authorization, PaymentGateway, StubGateway};
+use tracing::warn;
 
 #[derive(Deserialize)]
 pub struct PaymentRequest {
     #[serde(rename = "orderId")]
     pub order_id: String,
-    #[allow(dead_code)]
     pub method: String,
     pub amount: BigDecimal,
+    /// ISO 4217 code; callers that predate the field are charged in [`DEFAULT_CURRENCY`].
+    #[serde(default)]
+    pub currency: Option<String>,
 }
+
+pub const DEFAULT_CURRENCY: &str = "USD";
+
 // Legacy PAYMENT_ROLES removed: rely solely on PaymentProcess capability (Cashier + Admin-like roles allowed by mapping).
 #[derive(Serialize)]
 pub struct PaymentResponse {
+    /// `approved`, or `conditionally_approved` when taken in stand-in during a provider outage.
     pub status: String,
     pub approval_code: String,
+    #[serde(rename = "standInId", skip_serializing_if = "Option::is_none")]
+    pub stand_in_id: Option<uuid::Uuid>,
 }
 
 #[derive(Deserialize)]
@@ -53,12 +63,71 @@ pub struct CreateIntentRequest {
     #[serde(rename = "amountMinor")] pub amount_minor: i64,
     pub currency: String,
     #[serde(rename = "idempotencyKey")] pub idempotency_key: Option<String>,
+    /// Part of `amountMinor` that is tip.
+    #[serde(rename = "tipMinor", default)] pub tip_minor: i64,
 }
 
 #[derive(Serialize)]
 pub struct IntentResponse {
     pub id: String,
     pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<CardDetails>,
+}
+
+#[derive(Serialize)]
+pub struct CardDetails {
+    #[serde(rename = "cardholderName", skip_serializing_if = "Option::is_none")] pub cardholder_name: Option<String>,
+    #[serde(rename = "last4", skip_serializing_if = "Option::is_none")] pub last4: Option<String>,
+}
+
+const MAX_CARDHOLDER_NAME_LEN: usize = 128;
+
+type SealedCardDetails = (Option<EncryptedColumn<String>>, Option<EncryptedColumn<String>>);
+
+/// Validate and encrypt card details from a confirm request. Without a configured key the details
+/// are dropped rather than stored in plaintext.
+fn seal_card_details(state: &AppState, sec: &SecurityContext, req: &ConfirmIntentRequest) -> Result<SealedCardDetails, ApiError> {
+    let name = req.cardholder_name.as_deref().map(str::trim).filter(|v| !v.is_empty());
+    let last4 = req.card_last4.as_deref().map(str::trim).filter(|v| !v.is_empty());
+    if name.is_some_and(|v| v.chars().count() > MAX_CARDHOLDER_NAME_LEN) {
+        return Err(ApiError::BadRequest { code: "invalid_cardholder_name", trace_id: sec.trace_id, message: Some(format!("cardholderName must be at most {MAX_CARDHOLDER_NAME_LEN} characters")) });
+    }
+    if last4.is_some_and(|v| v.len() != 4 || !v.bytes().all(|b| b.is_ascii_digit())) {
+        return Err(ApiError::BadRequest { code: "invalid_card_last4", trace_id: sec.trace_id, message: Some("cardLast4 must be exactly 4 digits".into()) });
+    }
+    if name.is_none() && last4.is_none() {
+        return Ok((None, None));
+    }
+    let Some(key) = state.pii_key.as_ref() else {
+        warn!(intent_id = %req.id, "PAYMENT_PII_KEY not configured; discarding card details");
+        return Ok((None, None));
+    };
+    let tenant = sec.tenant_id.as_bytes();
+    let seal = |field: &str, value: &str| key.seal(tenant, field, &value.to_string())
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("encryption_error: {e}")) });
+    Ok((
+        name.map(|v| seal(CARDHOLDER_NAME_FIELD, v)).transpose()?,
+        last4.map(|v| seal(CARD_LAST4_FIELD, v)).transpose()?,
+    ))
+}
+
+/// Decrypt stored card details for the caller's tenant. Values that fail to open (e.g. sealed for
+/// another tenant) are omitted.
+fn reveal_card_details(state: &AppState, sec: &SecurityContext, intent: &repo::PaymentIntent) -> Option<CardDetails> {
+    let key = state.pii_key.as_ref()?;
+    let tenant = sec.tenant_id.as_bytes();
+    let open = |field: &str, sealed: &Option<EncryptedColumn<String>>| {
+        sealed.as_ref().and_then(|value| match key.open(tenant, field, value) {
+            Ok(plain) => Some(plain),
+            Err(err) => { warn!(intent_id = %intent.id, field, error = %err, "Failed to decrypt card details"); None }
+        })
+    };
+    let details = CardDetails {
+        cardholder_name: open(CARDHOLDER_NAME_FIELD, &intent.cardholder_name_encrypted),
+        last4: open(CARD_LAST4_FIELD, &intent.card_last4_encrypted),
+    };
+    (details.cardholder_name.is_some() || details.last4.is_some()).then_some(details)
 }
 
 #[allow(unused_variables)]
@@ -73,12 +142,15 @@ pub async fn create_intent(
         return Err(ApiError::ForbiddenMissingRole { role: "payment_access", trace_id: sec.trace_id });
     }
     if let Some(db) = &state.db {
-        let rec = repo::create_intent(db, &req.id, &req.order_id, req.amount_minor, &req.currency, req.idempotency_key.as_deref()).await
+        if req.tip_minor < 0 || req.tip_minor > req.amount_minor {
+            return Err(ApiError::BadRequest { code: "invalid_tip", trace_id: sec.trace_id, message: Some("tipMinor must be between 0 and amountMinor".into()) });
+        }
+        let rec = repo::create_intent(db, sec.tenant_id, &req.id, &req.order_id, req.amount_minor, req.tip_minor, &req.currency, req.idempotency_key.as_deref()).await
             .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
-        return Ok(Json(IntentResponse { id: rec.id, state: rec.state }));
+        return Ok(Json(IntentResponse { id: rec.id, state: rec.state, card: None }));
     }
     // Fallback: no DB configured
-    Ok(Json(IntentResponse { id: req.id, state: "created".into() }))
+    Ok(Json(IntentResponse { id: req.id, state: "created".into(), card: None }))
 }
 
 #[derive(Deserialize)]
@@ -87,6 +159,8 @@ pub struct ConfirmIntentRequest {
     pub provider: Option<String>,
     #[serde(rename = "providerRef")] pub provider_ref: Option<String>,
     #[serde(rename = "metadata")] pub metadata_json: Option<serde_json::Value>,
+    #[serde(rename = "cardholderName")] pub cardholder_name: Option<String>,
+    #[serde(rename = "cardLast4")] pub card_last4: Option<String>,
 }
 
 #[allow(unused_variables)]
@@ -100,6 +174,7 @@ pub async fn confirm_intent(
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] emit_capability_denial_audit(state.audit_producer.as_deref(), &sec, Capability::PaymentProcess, "payment-service").await;
         return Err(ApiError::ForbiddenMissingRole { role: "payment_access", trace_id: sec.trace_id });
     }
+    let (cardholder_name, card_last4) = seal_card_details(&state, &sec, &req)?;
     if let Some(db) = &state.db {
         // Fetch current state and validate transition
         let existing = repo::get_intent(db, &req.id).await
@@ -108,12 +183,16 @@ pub async fn confirm_intent(
         if !repo::is_valid_transition(&cur.state, repo::IntentState::Authorized) {
             return Err(ApiError::Conflict { code: "invalid_state_transition", trace_id: sec.trace_id, message: Some(format!("from={} to=authorized", cur.state)) });
         }
+        if cardholder_name.is_some() || card_last4.is_some() {
+            repo::set_card_details(db, &req.id, cardholder_name.as_ref(), card_last4.as_ref()).await
+                .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
+        }
         let rec = repo::transition_with_provider(db, &req.id, repo::IntentState::Authorized, req.provider.as_deref(), req.provider_ref.as_deref(), req.metadata_json.as_ref()).await
             .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
-        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state })); }
+        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state, card: None })); }
         return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
     }
-    Ok(Json(IntentResponse { id: req.id, state: "authorized".into() }))
+    Ok(Json(IntentResponse { id: req.id, state: "authorized".into(), card: None }))
 }
 
 #[derive(Deserialize)]
@@ -144,10 +223,10 @@ pub async fn capture_intent(
         }
         let rec = repo::transition_with_provider(db, &req.id, repo::IntentState::Captured, req.provider.as_deref(), req.provider_ref.as_deref(), req.metadata_json.as_ref()).await
             .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
-        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state })); }
+        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state, card: None })); }
         return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
     }
-    Ok(Json(IntentResponse { id: req.id, state: "captured".into() }))
+    Ok(Json(IntentResponse { id: req.id, state: "captured".into(), card: None }))
 }
 
 #[derive(Deserialize)]
@@ -187,10 +266,10 @@ pub async fn void_intent(
             repo::transition_state(db, &req.id, repo::IntentState::Voided).await
                 .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?
         };
-        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state })); }
+        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state, card: None })); }
         return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
     }
-    Ok(Json(IntentResponse { id: req.id, state: "voided".into() }))
+    Ok(Json(IntentResponse { id: req.id, state: "voided".into(), card: None }))
 }
 
 #[derive(Deserialize)]
@@ -230,10 +309,58 @@ pub async fn refund_intent(
             repo::transition_state(db, &req.id, repo::IntentState::Refunded).await
                 .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?
         };
-        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state })); }
+        if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state, card: None })); }
         return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
     }
-    Ok(Json(IntentResponse { id: req.id, state: "refunded".into() }))
+    Ok(Json(IntentResponse { id: req.id, state: "refunded".into(), card: None }))
+}
+
+#[derive(Deserialize)]
+pub struct AdjustTipRequest {
+    pub id: String,
+    #[serde(rename = "tipMinor")] pub tip_minor: i64,
+}
+
+/// Replace the tip on an open, authorized or captured intent (e.g. from a signed receipt). Refused once
+/// a settlement report has included the payment, since the provider has already paid it out. The
+/// tip can't exceed the sale itself; order-service applies the tenant's tighter tip policy.
+pub async fn adjust_intent_tip(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<AdjustTipRequest>,
+) -> Result<Json<IntentResponse>, ApiError> {
+    if ensure_capability(&sec, Capability::PaymentProcess).is_err() {
+        #[cfg(any(feature = "kafka", feature = "kafka-producer"))] emit_capability_denial_audit(state.audit_producer.as_deref(), &sec, Capability::PaymentProcess, "payment-service").await;
+        return Err(ApiError::ForbiddenMissingRole { role: "payment_access", trace_id: sec.trace_id });
+    }
+    if req.tip_minor < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_tip", trace_id: sec.trace_id, message: Some("tipMinor cannot be negative".into()) });
+    }
+    let Some(db) = &state.db else {
+        return Ok(Json(IntentResponse { id: req.id, state: "captured".into(), card: None }));
+    };
+    let db_err = |e: anyhow::Error| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) };
+    let Some(cur) = repo::get_intent(db, &req.id).await.map_err(db_err)? else {
+        return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
+    };
+    if !matches!(cur.state.parse::<repo::IntentState>(), Ok(repo::IntentState::Created | repo::IntentState::Authorized | repo::IntentState::Captured)) {
+        return Err(ApiError::Conflict { code: "tip_not_adjustable", trace_id: sec.trace_id, message: Some(format!("state={}", cur.state)) });
+    }
+    let sale_minor = cur.amount_minor - cur.tip_minor;
+    if req.tip_minor > sale_minor {
+        return Err(ApiError::BadRequest { code: "tip_exceeds_sale", trace_id: sec.trace_id, message: Some(format!("tipMinor cannot exceed the sale amount ({sale_minor})")) });
+    }
+    if repo::is_settled(db, sec.tenant_id, &req.id).await.map_err(db_err)? {
+        return Err(ApiError::Conflict { code: "payment_settled", trace_id: sec.trace_id, message: Some("Payment already appears in a settlement report".into()) });
+    }
+    match repo::set_tip(db, sec.tenant_id, &req.id, req.tip_minor).await.map_err(db_err)? {
+        Some(pi) => {
+            tracing::info!(intent_id = %pi.id, previous_tip_minor = cur.tip_minor, tip_minor = pi.tip_minor, amount_minor = pi.amount_minor, "Payment intent tip adjusted");
+            Ok(Json(IntentResponse { id: pi.id, state: pi.state, card: None }))
+        }
+        // Another tenant's intent.
+        None => Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id }),
+    }
 }
 
 #[derive(Deserialize)]
@@ -252,11 +379,14 @@ pub async fn get_intent(
     if let Some(db) = &state.db {
         let rec = repo::get_intent(db, &id).await
             .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
-    if let Some(pi) = rec { return Ok(Json(IntentResponse { id: pi.id, state: pi.state })); }
+    if let Some(pi) = rec {
+        let card = reveal_card_details(&state, &sec, &pi);
+        return Ok(Json(IntentResponse { id: pi.id, state: pi.state, card }));
+    }
     return Err(ApiError::NotFound { code: "payment_intent_not_found", trace_id: sec.trace_id });
     }
     // Fallback behavior without DB: pretend created
-    Ok(Json(IntentResponse { id, state: "created".into() }))
+    Ok(Json(IntentResponse { id, state: "created".into(), card: None }))
 }
 
 #[allow(unused_variables)]
@@ -270,21 +400,41 @@ pub async fn process_card_payment(
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] emit_capability_denial_audit(state.audit_producer.as_deref(), &sec, Capability::PaymentProcess, "payment-service").await;
         return Err(ApiError::ForbiddenMissingRole { role: "payment_access", trace_id: sec.trace_id });
     }
-    let _tenant_id = sec.tenant_id;
+    let currency = match req.currency.as_deref().map(str::trim) {
+        None | Some("") => DEFAULT_CURRENCY.to_string(),
+        Some(code) if code.len() == 3 && code.bytes().all(|b| b.is_ascii_alphabetic()) => code.to_ascii_uppercase(),
+        Some(_) => return Err(ApiError::BadRequest { code: "invalid_currency", trace_id: sec.trace_id, message: Some("currency must be a three-letter ISO code".into()) }),
+    };
+    let amount_minor = Money::new(req.amount.clone()).as_cents();
+    let gateway = StubGateway::new();
+    let err = match stand_in::authorize_with_retry(&gateway, state.retry_policy, &req.order_id, amount_minor, &currency).await {
+        Ok(Authorization::Approved { approval_code }) => {
+            tracing::info!(order_id = %req.order_id, amount_minor, approval_code = %approval_code, "Card payment approved");
+            return Ok(Json(PaymentResponse { status: "approved".into(), approval_code, stand_in_id: None }));
+        }
+        Ok(Authorization::Declined { reason }) => {
+            return Err(ApiError::Conflict { code: "card_declined", trace_id: sec.trace_id, message: Some(reason) });
+        }
+        Err(err) => err,
+    };
 
-    let amount_money = Money::new(req.amount.clone());
-    println!("Valor stub: processing card payment for Order {} amount={} (normalized={})", req.order_id, req.amount, amount_money);
-    sleep(Duration::from_secs(2)).await;
-    let approval_code = format!(
-        "VAL-APPROVED-{}",
-        &req.order_id[..8.min(req.order_id.len())]
-    );
-    println!("Valor stub: payment approved, code={}", approval_code);
-    let response = PaymentResponse {
-        status: "approved".into(),
-        approval_code,
+    // The provider is down: take the payment in stand-in if the tenant allows it for this amount.
+    let unavailable = ApiError::ServiceUnavailable { code: "payment_provider_unavailable", trace_id: sec.trace_id, retry_after_secs: 30 };
+    warn!(order_id = %req.order_id, error = %err, "Payment provider unavailable");
+    let (Some(db), Ok(order_id)) = (&state.db, uuid::Uuid::parse_str(&req.order_id)) else {
+        return Err(unavailable);
     };
-    Ok(Json(response))
+    let queued = stand_in::enqueue(db, sec.tenant_id, order_id, &req.method, amount_minor, &currency).await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) })?;
+    match queued {
+        stand_in::Enqueued::Pending(entry) => Ok(Json(PaymentResponse { status: "conditionally_approved".into(), approval_code: entry.stand_in_code(), stand_in_id: Some(entry.id) })),
+        // A retry after the queue was replayed: answer with the provider's result, not the stand-in's.
+        stand_in::Enqueued::Resolved(entry) if entry.status == "approved" => {
+            Ok(Json(PaymentResponse { status: "approved".into(), approval_code: entry.approval_code.unwrap_or_default(), stand_in_id: Some(entry.id) }))
+        }
+        stand_in::Enqueued::Resolved(entry) => Err(ApiError::Conflict { code: "card_declined", trace_id: sec.trace_id, message: entry.last_error }),
+        stand_in::Enqueued::Ineligible => Err(unavailable),
+    }
 }
 
 #[allow(unused_variables)]
@@ -0,0 +1,621 @@
+//! Reconciliation of recorded payments against provider settlement reports.
+//!
+//! Finance uploads a provider's settlement report, either as the provider's CSV export or as JSON
+//! lines pulled from the provider's API. Each line is matched to a payment intent by
+//! `provider_ref`; captured intents of that provider inside the report period that the report
+//! does not mention are flagged as well. The result is stored per upload and served as a report.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use common_http_errors::ApiError;
+use common_money::Money;
+use common_security::{Capability, SecurityContext, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{authorize, db_error, AppState};
+
+const MAX_SETTLEMENT_LINES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementSource {
+    Csv,
+    Api,
+}
+
+impl SettlementSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettlementSource::Csv => "csv",
+            SettlementSource::Api => "api",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Matched,
+    /// The provider settled a reference we have no payment for.
+    MissingPayment,
+    /// A captured payment in the period that the provider did not settle.
+    MissingSettlement,
+    /// Both sides know the reference but disagree on amount or currency.
+    AmountDelta,
+    /// The reference appears more than once in the report, or on more than one payment.
+    Duplicate,
+}
+
+impl ItemStatus {
+    pub const ALL: [ItemStatus; 5] = [
+        ItemStatus::Matched,
+        ItemStatus::MissingPayment,
+        ItemStatus::MissingSettlement,
+        ItemStatus::AmountDelta,
+        ItemStatus::Duplicate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemStatus::Matched => "matched",
+            ItemStatus::MissingPayment => "missing_payment",
+            ItemStatus::MissingSettlement => "missing_settlement",
+            ItemStatus::AmountDelta => "amount_delta",
+            ItemStatus::Duplicate => "duplicate",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|status| status.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SettlementLine {
+    #[serde(rename = "providerRef")] pub provider_ref: String,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+}
+
+/// A payment intent as seen by reconciliation.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct RecordedPayment {
+    pub id: String,
+    pub provider_ref: String,
+    pub amount_minor: i64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct ReconciliationItem {
+    #[serde(rename = "providerRef")] pub provider_ref: String,
+    #[serde(rename = "paymentIntentId")] pub payment_intent_id: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub status: ItemStatus,
+    #[serde(rename = "settledAmountMinor")] pub settled_amount_minor: Option<i64>,
+    #[serde(rename = "recordedAmountMinor")] pub recorded_amount_minor: Option<i64>,
+    pub currency: Option<String>,
+}
+
+impl TryFrom<String> for ItemStatus {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ItemStatus::parse(&value).ok_or_else(|| format!("unknown reconciliation status {value}"))
+    }
+}
+
+impl ReconciliationItem {
+    /// The amount this item leaves unexplained: what was settled without a matching payment, what
+    /// we recorded without a settlement, or the absolute difference between the two.
+    pub fn unmatched_amount_minor(&self) -> i64 {
+        match self.status {
+            ItemStatus::Matched => 0,
+            ItemStatus::MissingPayment | ItemStatus::Duplicate => self.settled_amount_minor.unwrap_or(0).saturating_abs(),
+            ItemStatus::MissingSettlement => self.recorded_amount_minor.unwrap_or(0).saturating_abs(),
+            ItemStatus::AmountDelta => self
+                .settled_amount_minor
+                .unwrap_or(0)
+                .saturating_sub(self.recorded_amount_minor.unwrap_or(0))
+                .saturating_abs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettlementFileError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SettlementFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parse a settlement CSV export. The header row names the columns (any order, case-insensitive):
+/// `provider_ref`, `currency`, and either `amount_minor` or `amount` in major units.
+pub fn parse_settlement_csv(text: &str) -> Result<Vec<SettlementLine>, SettlementFileError> {
+    let mut rows = text
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (header_line, header) = rows.next().ok_or(SettlementFileError { line: 1, message: "file is empty".into() })?;
+    let columns: Vec<String> = split_csv_row(header)
+        .map_err(|message| SettlementFileError { line: header_line, message })?
+        .into_iter()
+        .map(|c| c.trim().to_ascii_lowercase())
+        .collect();
+    let find = |name: &str| columns.iter().position(|c| c == name);
+    let missing = |name: &str| SettlementFileError { line: header_line, message: format!("missing column {name}") };
+    let ref_col = find("provider_ref").ok_or_else(|| missing("provider_ref"))?;
+    let currency_col = find("currency").ok_or_else(|| missing("currency"))?;
+    let amount_col = match (find("amount_minor"), find("amount")) {
+        (Some(col), _) => AmountColumn::Minor(col),
+        (None, Some(col)) => AmountColumn::Major(col),
+        (None, None) => return Err(missing("amount_minor or amount")),
+    };
+
+    let mut lines = Vec::new();
+    for (line_no, row) in rows {
+        let err = |message: String| SettlementFileError { line: line_no, message };
+        let fields = split_csv_row(row).map_err(err)?;
+        let field = |col: usize| fields.get(col).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let provider_ref = field(ref_col).ok_or_else(|| err("provider_ref is empty".into()))?;
+        let currency = field(currency_col).ok_or_else(|| err("currency is empty".into()))?;
+        let amount_minor = match amount_col {
+            AmountColumn::Minor(col) => {
+                let raw = field(col).ok_or_else(|| err("amount_minor is empty".into()))?;
+                raw.parse::<i64>().map_err(|_| err(format!("invalid amount_minor {raw}")))?
+            }
+            AmountColumn::Major(col) => {
+                let raw = field(col).ok_or_else(|| err("amount is empty".into()))?;
+                let amount: BigDecimal = raw.parse().map_err(|_| err(format!("invalid amount {raw}")))?;
+                if amount.with_scale(2) != amount {
+                    return Err(err(format!("amount {raw} has more than two decimal places")));
+                }
+                Money::new(amount).as_cents()
+            }
+        };
+        lines.push(SettlementLine { provider_ref: provider_ref.to_string(), amount_minor, currency: currency.to_ascii_uppercase() });
+        if lines.len() > MAX_SETTLEMENT_LINES {
+            return Err(err(format!("more than {MAX_SETTLEMENT_LINES} lines")));
+        }
+    }
+    Ok(lines)
+}
+
+#[derive(Clone, Copy)]
+enum AmountColumn {
+    Minor(usize),
+    Major(usize),
+}
+
+/// Split one CSV row, honouring double-quoted fields (with `""` as an escaped quote).
+fn split_csv_row(row: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if current.trim().is_empty() => {
+                current.clear();
+                in_quotes = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut current)),
+            (c, _) => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".into());
+    }
+    fields.push(current);
+    Ok(fields)
+}
+
+/// Match settlement lines to recorded payments.
+///
+/// `payments` should hold every intent whose `provider_ref` appears in `lines`, plus the captured
+/// intents of the settlement period so unsettled ones surface as `missing_settlement`. The first
+/// line for a reference is compared against the payment; later lines for the same reference are
+/// `duplicate`, as is a reference carried by more than one payment.
+pub fn reconcile(lines: &[SettlementLine], payments: &[RecordedPayment]) -> Vec<ReconciliationItem> {
+    let mut by_ref: HashMap<&str, Vec<&RecordedPayment>> = HashMap::new();
+    for payment in payments {
+        by_ref.entry(payment.provider_ref.as_str()).or_default().push(payment);
+    }
+
+    let mut items = Vec::with_capacity(lines.len());
+    let mut seen: HashSet<&str> = HashSet::new();
+    for line in lines {
+        let recorded = by_ref.get(line.provider_ref.as_str()).map(Vec::as_slice).unwrap_or_default();
+        let first_sighting = seen.insert(line.provider_ref.as_str());
+        let payment = recorded.first();
+        let status = match payment {
+            _ if !first_sighting || recorded.len() > 1 => ItemStatus::Duplicate,
+            None => ItemStatus::MissingPayment,
+            Some(p) if p.amount_minor != line.amount_minor || !p.currency.eq_ignore_ascii_case(&line.currency) => ItemStatus::AmountDelta,
+            Some(_) => ItemStatus::Matched,
+        };
+        items.push(ReconciliationItem {
+            provider_ref: line.provider_ref.clone(),
+            payment_intent_id: payment.map(|p| p.id.clone()),
+            status,
+            settled_amount_minor: Some(line.amount_minor),
+            recorded_amount_minor: payment.map(|p| p.amount_minor),
+            currency: Some(line.currency.clone()),
+        });
+    }
+
+    let mut reported: HashSet<&str> = HashSet::new();
+    for payment in payments {
+        if !seen.contains(payment.provider_ref.as_str()) && reported.insert(payment.id.as_str()) {
+            items.push(ReconciliationItem {
+                provider_ref: payment.provider_ref.clone(),
+                payment_intent_id: Some(payment.id.clone()),
+                status: ItemStatus::MissingSettlement,
+                settled_amount_minor: None,
+                recorded_amount_minor: Some(payment.amount_minor),
+                currency: Some(payment.currency.clone()),
+            });
+        }
+    }
+    items
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReportSummary {
+    pub matched: u64,
+    #[serde(rename = "missingPayment")] pub missing_payment: u64,
+    #[serde(rename = "missingSettlement")] pub missing_settlement: u64,
+    #[serde(rename = "amountDelta")] pub amount_delta: u64,
+    pub duplicate: u64,
+    #[serde(rename = "unmatchedAmountMinor")] pub unmatched_amount_minor: i64,
+}
+
+impl ReportSummary {
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a ReconciliationItem>) -> Self {
+        let mut summary = ReportSummary::default();
+        for item in items {
+            match item.status {
+                ItemStatus::Matched => summary.matched += 1,
+                ItemStatus::MissingPayment => summary.missing_payment += 1,
+                ItemStatus::MissingSettlement => summary.missing_settlement += 1,
+                ItemStatus::AmountDelta => summary.amount_delta += 1,
+                ItemStatus::Duplicate => summary.duplicate += 1,
+            }
+            summary.unmatched_amount_minor = summary.unmatched_amount_minor.saturating_add(item.unmatched_amount_minor());
+        }
+        summary
+    }
+}
+
+// ---- Metrics ----
+static RECONCILIATION_RUNS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_reconciliation_runs_total", "Settlement reports reconciled, by provider and source (csv|api)"),
+        &["provider", "source"],
+    ).expect("payment_reconciliation_runs_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static RECONCILIATION_UNMATCHED_ITEMS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new("payment_reconciliation_unmatched_items", "Unmatched items in the latest reconciliation per provider, by status"),
+        &["provider", "status"],
+    ).expect("payment_reconciliation_unmatched_items");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+static RECONCILIATION_UNMATCHED_AMOUNT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new("payment_reconciliation_unmatched_amount_minor", "Unexplained amount (minor units) in the latest reconciliation per provider, by status"),
+        &["provider", "status"],
+    ).expect("payment_reconciliation_unmatched_amount_minor");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+fn record_metrics(provider: &str, source: SettlementSource, items: &[ReconciliationItem]) {
+    RECONCILIATION_RUNS_TOTAL.with_label_values(&[provider, source.as_str()]).inc();
+    for status in ItemStatus::ALL.into_iter().filter(|s| *s != ItemStatus::Matched) {
+        let (count, amount) = items
+            .iter()
+            .filter(|item| item.status == status)
+            .fold((0i64, 0i64), |(count, amount), item| (count + 1, amount.saturating_add(item.unmatched_amount_minor())));
+        RECONCILIATION_UNMATCHED_ITEMS.with_label_values(&[provider, status.as_str()]).set(count);
+        RECONCILIATION_UNMATCHED_AMOUNT.with_label_values(&[provider, status.as_str()]).set(amount);
+    }
+}
+
+// ---- Persistence ----
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SettlementBatch {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub tenant_id: Uuid,
+    pub provider: String,
+    pub source: String,
+    #[serde(rename = "periodStart")] pub period_start: DateTime<Utc>,
+    #[serde(rename = "periodEnd")] pub period_end: DateTime<Utc>,
+    #[serde(rename = "lineCount")] pub line_count: i32,
+    #[serde(rename = "createdAt")] pub created_at: DateTime<Utc>,
+}
+
+const BATCH_COLUMNS: &str = "id, tenant_id, provider, source, period_start, period_end, line_count, created_at";
+
+/// The tenant's intents referenced by the report, plus its captured intents with the provider
+/// updated in the period.
+async fn load_recorded_payments(
+    db: &PgPool,
+    tenant_id: Uuid,
+    provider: &str,
+    refs: &[String],
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> anyhow::Result<Vec<RecordedPayment>> {
+    let rows = sqlx::query_as::<_, RecordedPayment>(
+        r#"SELECT id, provider_ref, amount_minor, currency
+           FROM payment_intents
+           WHERE tenant_id = $5
+             AND (provider_ref = ANY($1)
+                  OR (provider = $2 AND provider_ref IS NOT NULL AND state = 'captured'
+                      AND updated_at >= $3 AND updated_at < $4))
+           ORDER BY created_at, id"#,
+    )
+    .bind(refs)
+    .bind(provider)
+    .bind(period_start)
+    .bind(period_end)
+    .bind(tenant_id)
+    .fetch_all(db)
+    .await?;
+    Ok(rows)
+}
+
+async fn store_batch(
+    db: &PgPool,
+    sec: &SecurityContext,
+    upload: &SettlementUpload,
+    source: SettlementSource,
+    items: &[ReconciliationItem],
+) -> anyhow::Result<SettlementBatch> {
+    let mut tx = db.begin().await?;
+    let batch = sqlx::query_as::<_, SettlementBatch>(&format!(
+        "INSERT INTO settlement_batches (id, tenant_id, provider, source, period_start, period_end, line_count, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {BATCH_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(sec.tenant_id)
+    .bind(&upload.provider)
+    .bind(source.as_str())
+    .bind(upload.period_start)
+    .bind(upload.period_end)
+    .bind(upload.lines.len() as i32)
+    .bind(sec.actor.id)
+    .fetch_one(&mut *tx)
+    .await?;
+    for item in items {
+        sqlx::query(
+            "INSERT INTO reconciliation_items (batch_id, provider_ref, payment_intent_id, status, settled_amount_minor, recorded_amount_minor, currency)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(batch.id)
+        .bind(&item.provider_ref)
+        .bind(&item.payment_intent_id)
+        .bind(item.status.as_str())
+        .bind(item.settled_amount_minor)
+        .bind(item.recorded_amount_minor)
+        .bind(&item.currency)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(batch)
+}
+
+// ---- HTTP ----
+#[derive(Debug, Deserialize)]
+pub struct SettlementUpload {
+    pub provider: String,
+    #[serde(rename = "periodStart")] pub period_start: DateTime<Utc>,
+    #[serde(rename = "periodEnd")] pub period_end: DateTime<Utc>,
+    #[serde(default)]
+    pub lines: Vec<SettlementLine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CsvUploadParams {
+    pub provider: String,
+    #[serde(rename = "periodStart")] pub period_start: DateTime<Utc>,
+    #[serde(rename = "periodEnd")] pub period_end: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReportParams {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationReport {
+    #[serde(flatten)]
+    pub batch: SettlementBatch,
+    pub summary: ReportSummary,
+    pub items: Vec<ReconciliationItem>,
+}
+
+async fn ingest(db: &PgPool, sec: &SecurityContext, upload: SettlementUpload, source: SettlementSource) -> Result<ReconciliationReport, ApiError> {
+    let bad_request = |code: &'static str, message: String| ApiError::BadRequest { code, trace_id: sec.trace_id, message: Some(message) };
+    if upload.provider.trim().is_empty() {
+        return Err(bad_request("invalid_provider", "provider is required".into()));
+    }
+    if upload.period_end <= upload.period_start {
+        return Err(bad_request("invalid_period", "periodEnd must be after periodStart".into()));
+    }
+    if upload.lines.len() > MAX_SETTLEMENT_LINES {
+        return Err(bad_request("invalid_settlement_file", format!("more than {MAX_SETTLEMENT_LINES} lines")));
+    }
+    if let Some(line) = upload.lines.iter().find(|l| l.provider_ref.trim().is_empty() || l.currency.trim().is_empty()) {
+        return Err(bad_request("invalid_settlement_file", format!("line for {:?} needs providerRef and currency", line.provider_ref)));
+    }
+
+    let db_error = |e: anyhow::Error| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("db_error: {e}")) };
+    let refs: Vec<String> = upload.lines.iter().map(|l| l.provider_ref.clone()).collect();
+    let payments = load_recorded_payments(db, sec.tenant_id, &upload.provider, &refs, upload.period_start, upload.period_end).await.map_err(db_error)?;
+    let items = reconcile(&upload.lines, &payments);
+    let batch = store_batch(db, sec, &upload, source, &items).await.map_err(db_error)?;
+    record_metrics(&batch.provider, source, &items);
+    tracing::info!(batch_id = %batch.id, provider = %batch.provider, lines = upload.lines.len(), "settlement report reconciled");
+    Ok(ReconciliationReport { summary: ReportSummary::from_items(&items), batch, items })
+}
+
+/// `POST /reconciliation/settlements`: settlement lines fetched from a provider API, as JSON.
+pub async fn upload_settlement(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(upload): Json<SettlementUpload>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    ingest(&db, &sec, upload, SettlementSource::Api).await.map(Json)
+}
+
+/// `POST /reconciliation/settlements/csv?provider=..&periodStart=..&periodEnd=..`: the provider's
+/// CSV export as the request body.
+pub async fn upload_settlement_csv(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<CsvUploadParams>,
+    body: String,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    let lines = parse_settlement_csv(&body).map_err(|e| ApiError::BadRequest {
+        code: "invalid_settlement_file",
+        trace_id: sec.trace_id,
+        message: Some(e.to_string()),
+    })?;
+    let upload = SettlementUpload { provider: params.provider, period_start: params.period_start, period_end: params.period_end, lines };
+    ingest(&db, &sec, upload, SettlementSource::Csv).await.map(Json)
+}
+
+/// `GET /reconciliation/reports/:id?status=..`: a stored reconciliation, optionally narrowed to one
+/// item status.
+pub async fn get_report(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(batch_id): Path<Uuid>,
+    Query(params): Query<ReportParams>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentReconcile, "payment_reconcile").await?;
+    let status = match params.status.as_deref() {
+        None => None,
+        Some(raw) => Some(ItemStatus::parse(raw).ok_or(ApiError::BadRequest {
+            code: "invalid_status",
+            trace_id: sec.trace_id,
+            message: Some(format!("unknown status {raw}")),
+        })?),
+    };
+    let batch = sqlx::query_as::<_, SettlementBatch>(&format!(
+        "SELECT {BATCH_COLUMNS} FROM settlement_batches WHERE id = $1 AND tenant_id = $2"
+    ))
+    .bind(batch_id)
+    .bind(sec.tenant_id)
+    .fetch_optional(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "settlement_batch_not_found", trace_id: sec.trace_id })?;
+    let items = sqlx::query_as::<_, ReconciliationItem>(
+        "SELECT provider_ref, payment_intent_id, status, settled_amount_minor, recorded_amount_minor, currency
+         FROM reconciliation_items WHERE batch_id = $1 ORDER BY id",
+    )
+    .bind(batch.id)
+    .fetch_all(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    let summary = ReportSummary::from_items(&items);
+    let items = items.into_iter().filter(|item| status.is_none_or(|s| item.status == s)).collect();
+    Ok(Json(ReconciliationReport { batch, summary, items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(provider_ref: &str, amount_minor: i64) -> SettlementLine {
+        SettlementLine { provider_ref: provider_ref.into(), amount_minor, currency: "USD".into() }
+    }
+
+    fn payment(id: &str, provider_ref: &str, amount_minor: i64) -> RecordedPayment {
+        RecordedPayment { id: id.into(), provider_ref: provider_ref.into(), amount_minor, currency: "USD".into() }
+    }
+
+    fn statuses(items: &[ReconciliationItem]) -> Vec<(&str, ItemStatus)> {
+        items.iter().map(|i| (i.provider_ref.as_str(), i.status)).collect()
+    }
+
+    #[test]
+    fn csv_accepts_minor_or_major_amounts_in_any_column_order() {
+        let minor = parse_settlement_csv("Currency,Provider_Ref,Amount_Minor\r\nusd,ch_1,1250\n\n").unwrap();
+        assert_eq!(minor, vec![line("ch_1", 1250)]);
+        let major = parse_settlement_csv("provider_ref,amount,currency,fee\n\"ch_2\",\"1,234.5\",USD,0.30\nch_3,-7.25,USD,0\n");
+        assert_eq!(major.unwrap_err().line, 2, "thousands separators are rejected");
+        let major = parse_settlement_csv("provider_ref,amount,currency,fee\n\"ch_2\",1234.5,USD,0.30\nch_3,-7.25,USD,0\n").unwrap();
+        assert_eq!(major, vec![line("ch_2", 123450), line("ch_3", -725)]);
+        assert!(parse_settlement_csv("provider_ref,amount,currency\nch_4,1.005,USD").is_err(), "sub-cent amounts are not rounded away");
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        assert_eq!(parse_settlement_csv("provider_ref,currency\nch_1,USD").unwrap_err().message, "missing column amount_minor or amount");
+        let err = parse_settlement_csv("provider_ref,amount_minor,currency\nch_1,10,USD\n,5,USD").unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (3, "provider_ref is empty"));
+        assert!(parse_settlement_csv("provider_ref,amount_minor,currency\n\"ch_1,10,USD").is_err());
+        assert!(parse_settlement_csv("").is_err());
+    }
+
+    #[test]
+    fn reconcile_flags_each_kind_of_mismatch() {
+        let lines = [line("ch_ok", 500), line("ch_delta", 700), line("ch_unknown", 300), line("ch_ok", 500)];
+        let payments = [payment("pi_ok", "ch_ok", 500), payment("pi_delta", "ch_delta", 750), payment("pi_unsettled", "ch_unsettled", 900)];
+        let items = reconcile(&lines, &payments);
+        assert_eq!(
+            statuses(&items),
+            vec![
+                ("ch_ok", ItemStatus::Matched),
+                ("ch_delta", ItemStatus::AmountDelta),
+                ("ch_unknown", ItemStatus::MissingPayment),
+                ("ch_ok", ItemStatus::Duplicate),
+                ("ch_unsettled", ItemStatus::MissingSettlement),
+            ]
+        );
+        let summary = ReportSummary::from_items(&items);
+        assert_eq!((summary.matched, summary.missing_payment, summary.missing_settlement, summary.amount_delta, summary.duplicate), (1, 1, 1, 1, 1));
+        assert_eq!(summary.unmatched_amount_minor, 50 + 300 + 500 + 900);
+    }
+
+    #[test]
+    fn currency_mismatch_and_shared_references_are_flagged() {
+        let eur = SettlementLine { currency: "EUR".into(), ..line("ch_1", 500) };
+        assert_eq!(statuses(&reconcile(&[eur], &[payment("pi_1", "ch_1", 500)])), vec![("ch_1", ItemStatus::AmountDelta)]);
+        let shared = reconcile(&[line("ch_2", 100)], &[payment("pi_a", "ch_2", 100), payment("pi_b", "ch_2", 100)]);
+        assert_eq!(statuses(&shared), vec![("ch_2", ItemStatus::Duplicate)]);
+    }
+}
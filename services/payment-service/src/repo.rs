@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use chrono::{DateTime, Utc};
 use anyhow::Result;
+use common_crypto::EncryptedColumn;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -25,16 +27,20 @@ impl IntentState {
             IntentState::Failed => "failed",
         }
     }
+}
+
+impl std::str::FromStr for IntentState {
+    type Err = anyhow::Error;
 
-    pub fn from_str(s: &str) -> Option<IntentState> {
+    fn from_str(s: &str) -> Result<Self> {
         match s {
-            "created" => Some(IntentState::Created),
-            "authorized" => Some(IntentState::Authorized),
-            "captured" => Some(IntentState::Captured),
-            "refunded" => Some(IntentState::Refunded),
-            "voided" => Some(IntentState::Voided),
-            "failed" => Some(IntentState::Failed),
-            _ => None,
+            "created" => Ok(IntentState::Created),
+            "authorized" => Ok(IntentState::Authorized),
+            "captured" => Ok(IntentState::Captured),
+            "refunded" => Ok(IntentState::Refunded),
+            "voided" => Ok(IntentState::Voided),
+            "failed" => Ok(IntentState::Failed),
+            other => Err(anyhow::anyhow!("unknown intent state '{other}'")),
         }
     }
 }
@@ -45,13 +51,13 @@ impl IntentState {
 /// captured -> refunded
 /// Any other transition is invalid and should return HTTP 409
 pub fn is_valid_transition(from_state: &str, to: IntentState) -> bool {
-    match IntentState::from_str(from_state) {
-        Some(IntentState::Created) => matches!(to, IntentState::Authorized),
-        Some(IntentState::Authorized) => matches!(to, IntentState::Captured | IntentState::Voided),
-        Some(IntentState::Captured) => matches!(to, IntentState::Refunded),
+    match from_state.parse::<IntentState>() {
+        Ok(IntentState::Created) => matches!(to, IntentState::Authorized),
+        Ok(IntentState::Authorized) => matches!(to, IntentState::Captured | IntentState::Voided),
+        Ok(IntentState::Captured) => matches!(to, IntentState::Refunded),
         // Terminal states: refunded/voided/failed cannot transition further in MVP
-        Some(IntentState::Refunded | IntentState::Voided | IntentState::Failed) => false,
-        None => false,
+        Ok(IntentState::Refunded | IntentState::Voided | IntentState::Failed) => false,
+        Err(_) => false,
     }
 }
 
@@ -68,11 +74,18 @@ pub struct PaymentIntent {
     pub metadata_json: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub cardholder_name_encrypted: Option<EncryptedColumn<String>>,
+    #[serde(skip)]
+    pub card_last4_encrypted: Option<EncryptedColumn<String>>,
+    /// Part of `amount_minor` that is tip.
+    #[serde(default)]
+    pub tip_minor: i64,
 }
 
 pub async fn get_intent(db: &PgPool, id: &str) -> Result<Option<PaymentIntent>> {
     let rec = sqlx::query_as::<_, PaymentIntent>(
-        r#"SELECT id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at
+        r#"SELECT id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at, cardholder_name_encrypted, card_last4_encrypted, tip_minor
            FROM payment_intents WHERE id = $1"#,
     )
     .bind(id)
@@ -81,25 +94,30 @@ pub async fn get_intent(db: &PgPool, id: &str) -> Result<Option<PaymentIntent>>
     Ok(rec)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_intent(
     db: &PgPool,
+    tenant_id: Uuid,
     id: &str,
     order_id: &str,
     amount_minor: i64,
+    tip_minor: i64,
     currency: &str,
     idempotency_key: Option<&str>,
 ) -> Result<PaymentIntent> {
     let rec = sqlx::query_as::<_, PaymentIntent>(
-        r#"INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, idempotency_key)
-           VALUES ($1, $2, $3, $4, 'created', $5)
+        r#"INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, idempotency_key, tenant_id, tip_minor)
+           VALUES ($1, $2, $3, $4, 'created', $5, $6, $7)
            ON CONFLICT (id) DO UPDATE SET updated_at = now()
-           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at"#,
+           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at, cardholder_name_encrypted, card_last4_encrypted, tip_minor"#,
     )
     .bind(id)
     .bind(order_id)
     .bind(amount_minor)
     .bind(currency)
     .bind(idempotency_key)
+    .bind(tenant_id)
+    .bind(tip_minor)
     .fetch_one(db)
     .await?;
     Ok(rec)
@@ -109,7 +127,7 @@ pub async fn transition_state(db: &PgPool, id: &str, new_state: IntentState) ->
     let rec = sqlx::query_as::<_, PaymentIntent>(
         r#"UPDATE payment_intents SET state = $2, updated_at = now()
            WHERE id = $1
-           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at"#,
+           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at, cardholder_name_encrypted, card_last4_encrypted, tip_minor"#,
     )
     .bind(id)
     .bind(new_state.as_str())
@@ -134,7 +152,7 @@ pub async fn transition_with_provider(
                metadata_json = COALESCE($5, metadata_json),
                updated_at = now()
            WHERE id = $1
-           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at"#,
+           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at, cardholder_name_encrypted, card_last4_encrypted, tip_minor"#,
     )
     .bind(id)
     .bind(new_state.as_str())
@@ -145,3 +163,53 @@ pub async fn transition_with_provider(
     .await?;
     Ok(rec)
 }
+
+/// Replace the tip on a tenant's intent, moving `amount_minor` by the difference.
+pub async fn set_tip(db: &PgPool, tenant_id: Uuid, id: &str, tip_minor: i64) -> Result<Option<PaymentIntent>> {
+    let rec = sqlx::query_as::<_, PaymentIntent>(
+        r#"UPDATE payment_intents SET amount_minor = amount_minor - tip_minor + $3, tip_minor = $3, updated_at = now()
+           WHERE id = $1 AND tenant_id = $2
+           RETURNING id, order_id, amount_minor, currency, state, provider, provider_ref, idempotency_key, metadata_json, created_at, updated_at, cardholder_name_encrypted, card_last4_encrypted, tip_minor"#,
+    )
+    .bind(id)
+    .bind(tenant_id)
+    .bind(tip_minor)
+    .fetch_optional(db)
+    .await?;
+    Ok(rec)
+}
+
+/// Whether one of the tenant's provider settlement reports has included the intent.
+pub async fn is_settled(db: &PgPool, tenant_id: Uuid, id: &str) -> Result<bool> {
+    let settled = sqlx::query_scalar::<_, bool>(
+        r#"SELECT EXISTS (
+               SELECT 1 FROM reconciliation_items i JOIN settlement_batches b ON b.id = i.batch_id
+               WHERE i.payment_intent_id = $1 AND b.tenant_id = $2 AND i.status <> 'missing_settlement')"#,
+    )
+    .bind(id)
+    .bind(tenant_id)
+    .fetch_one(db)
+    .await?;
+    Ok(settled)
+}
+
+pub async fn set_card_details(
+    db: &PgPool,
+    id: &str,
+    cardholder_name: Option<&EncryptedColumn<String>>,
+    card_last4: Option<&EncryptedColumn<String>>,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE payment_intents
+           SET cardholder_name_encrypted = COALESCE($2, cardholder_name_encrypted),
+               card_last4_encrypted = COALESCE($3, card_last4_encrypted),
+               updated_at = now()
+           WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(cardholder_name)
+    .bind(card_last4)
+    .execute(db)
+    .await?;
+    Ok(())
+}
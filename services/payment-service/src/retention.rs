@@ -0,0 +1,235 @@
+//! Retention for settled payments: once a payment intent is older than `PAYMENT_RETENTION_MONTHS`
+//! its encrypted cardholder name and card last four are dropped and `anonymized_at` is set, and
+//! evidence on disputes closed before the cutoff is removed. Amounts, states, provider references
+//! and settlement batches stay, so reconciliation and revenue reports are unchanged.
+//!
+//! Intents still `created` or `authorized`, and intents with an open dispute, are skipped.
+//! [`spawn_retention_job`] runs [`run_retention`] in batches of `PAYMENT_RETENTION_BATCH_SIZE`
+//! rows taken with `SKIP LOCKED` (replicas can all run it) and emits one audit event per tenant
+//! summarizing the run. With `PAYMENT_RETENTION_DRY_RUN` the job only counts what it would strip.
+//! Intents created before tenants were recorded on them (migration 8006) are anonymized too but
+//! can only be reported in the logs.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use common_security::{CapabilitySet, SystemActor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Identity the retention job audits under.
+pub const RETENTION_JOB: SystemActor = SystemActor::new("payment-service", "retention", CapabilitySet::EMPTY);
+
+/// Payment intents whose card details the job strips; `$1` is the cutoff.
+const INTENT_CANDIDATES: &str = "FROM payment_intents p
+     WHERE p.created_at < $1
+       AND p.state IN ('captured', 'refunded', 'voided', 'failed')
+       AND (p.cardholder_name_encrypted IS NOT NULL OR p.card_last4_encrypted IS NOT NULL)
+       AND NOT EXISTS (
+           SELECT 1 FROM disputes d WHERE d.payment_intent_id = p.id AND d.status IN ('needs_response', 'under_review')
+       )";
+
+/// Closed disputes whose evidence the job strips; `$1` is the cutoff.
+const DISPUTE_CANDIDATES: &str = "FROM disputes d
+     WHERE d.updated_at < $1 AND d.status IN ('won', 'lost') AND d.evidence IS NOT NULL";
+
+static RETENTION_ROWS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_retention_rows_total", "Rows the retention job anonymized (or would have, in dry-run mode), by table and mode"),
+        &["table", "mode"],
+    ).expect("payment_retention_rows_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static RETENTION_PENDING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new("payment_retention_pending", "Rows past the retention period still holding customer data, by table"),
+        &["table"],
+    ).expect("payment_retention_pending");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+static RETENTION_LAST_RUN: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new("payment_retention_last_run_timestamp_seconds", "Unix time the last retention run finished")
+        .expect("payment_retention_last_run_timestamp_seconds");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RetentionPolicy {
+    /// Age in months after which payments are anonymized; 0 disables the job.
+    pub months: u32,
+    pub batch_size: i64,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TenantRetention {
+    pub payment_intents: u64,
+    pub disputes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetentionRun {
+    pub cutoff: DateTime<Utc>,
+    pub dry_run: bool,
+    pub batches: u32,
+    pub tenants: BTreeMap<Uuid, TenantRetention>,
+    /// Rows without a tenant (created before migration 8006).
+    pub unattributed: TenantRetention,
+}
+
+impl RetentionRun {
+    fn count(&mut self, tenant_id: Option<Uuid>) -> &mut TenantRetention {
+        match tenant_id {
+            Some(tenant_id) => self.tenants.entry(tenant_id).or_default(),
+            None => &mut self.unattributed,
+        }
+    }
+
+    pub fn total(&self) -> TenantRetention {
+        self.tenants.values().chain([&self.unattributed]).fold(TenantRetention::default(), |sum, t| TenantRetention {
+            payment_intents: sum.payment_intents + t.payment_intents,
+            disputes: sum.disputes + t.disputes,
+        })
+    }
+}
+
+/// One pass over every tenant. In dry-run mode nothing is written and the counts are what a
+/// real run would strip.
+pub async fn run_retention(db: &PgPool, policy: &RetentionPolicy) -> sqlx::Result<RetentionRun> {
+    let cutoff: DateTime<Utc> = sqlx::query_scalar("SELECT now() - make_interval(months => $1)")
+        .bind(policy.months as i32)
+        .fetch_one(db)
+        .await?;
+    let mut run = RetentionRun { cutoff, dry_run: policy.dry_run, batches: 0, tenants: BTreeMap::new(), unattributed: TenantRetention::default() };
+
+    if policy.dry_run {
+        for (table, alias, candidates) in [("payment_intents", "p", INTENT_CANDIDATES), ("disputes", "d", DISPUTE_CANDIDATES)] {
+            let counts: Vec<(Option<Uuid>, i64)> =
+                sqlx::query_as(&format!("SELECT {alias}.tenant_id, COUNT(*) {candidates} GROUP BY {alias}.tenant_id"))
+                    .bind(cutoff)
+                    .fetch_all(db)
+                    .await?;
+            for (tenant_id, count) in counts {
+                let entry = run.count(tenant_id);
+                if table == "payment_intents" { entry.payment_intents = count as u64 } else { entry.disputes = count as u64 }
+                RETENTION_ROWS.with_label_values(&[table, "dry_run"]).inc_by(count as u64);
+            }
+        }
+    } else {
+        let intents = format!(
+            "WITH batch AS (SELECT p.id {INTENT_CANDIDATES} ORDER BY p.created_at LIMIT $2 FOR UPDATE SKIP LOCKED)
+             UPDATE payment_intents SET cardholder_name_encrypted = NULL, card_last4_encrypted = NULL, anonymized_at = now()
+             FROM batch WHERE payment_intents.id = batch.id
+             RETURNING payment_intents.tenant_id"
+        );
+        for tenant_id in in_batches(db, &intents, cutoff, policy.batch_size, &mut run.batches, "payment_intents").await? {
+            run.count(tenant_id).payment_intents += 1;
+        }
+        let disputes = format!(
+            "WITH batch AS (SELECT d.id {DISPUTE_CANDIDATES} ORDER BY d.updated_at LIMIT $2 FOR UPDATE SKIP LOCKED)
+             UPDATE disputes SET evidence = NULL
+             FROM batch WHERE disputes.id = batch.id
+             RETURNING disputes.tenant_id"
+        );
+        for tenant_id in in_batches(db, &disputes, cutoff, policy.batch_size, &mut run.batches, "disputes").await? {
+            run.count(tenant_id).disputes += 1;
+        }
+    }
+
+    for (table, candidates) in [("payment_intents", INTENT_CANDIDATES), ("disputes", DISPUTE_CANDIDATES)] {
+        let pending: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {candidates}")).bind(cutoff).fetch_one(db).await?;
+        RETENTION_PENDING.with_label_values(&[table]).set(pending);
+    }
+    RETENTION_LAST_RUN.set(Utc::now().timestamp());
+    Ok(run)
+}
+
+/// Run a batched `UPDATE ... RETURNING tenant_id` until a batch comes back short. Returns the
+/// tenant of every row changed.
+async fn in_batches(
+    db: &PgPool,
+    statement: &str,
+    cutoff: DateTime<Utc>,
+    batch_size: i64,
+    batches: &mut u32,
+    table: &str,
+) -> sqlx::Result<Vec<Option<Uuid>>> {
+    let mut changed = Vec::new();
+    loop {
+        let tenants: Vec<Option<Uuid>> = sqlx::query_scalar(statement).bind(cutoff).bind(batch_size).fetch_all(db).await?;
+        *batches += 1;
+        RETENTION_ROWS.with_label_values(&[table, "anonymized"]).inc_by(tenants.len() as u64);
+        let done = (tenants.len() as i64) < batch_size;
+        changed.extend(tenants);
+        if done {
+            return Ok(changed);
+        }
+    }
+}
+
+/// Run [`run_retention`] every `interval` and audit what each tenant lost. Needs a database.
+pub fn spawn_retention_job(state: AppState, policy: RetentionPolicy, interval: Duration) {
+    let Some(db) = state.db.clone() else { return };
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let run = match run_retention(&db, &policy).await {
+                Ok(run) => run,
+                Err(err) => {
+                    warn!(error = %err, "Payment retention run failed");
+                    continue;
+                }
+            };
+            let total = run.total();
+            if total == TenantRetention::default() {
+                continue;
+            }
+            info!(
+                dry_run = run.dry_run,
+                cutoff = %run.cutoff,
+                payment_intents = total.payment_intents,
+                disputes = total.disputes,
+                unattributed = run.unattributed.payment_intents + run.unattributed.disputes,
+                "Payment retention run finished"
+            );
+            #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+            if let Some(audit) = &state.audit_producer {
+                for (tenant_id, counts) in &run.tenants {
+                    let _ = audit
+                        .emit(
+                            *tenant_id,
+                            RETENTION_JOB.audit_actor(),
+                            "payment_retention",
+                            None,
+                            if run.dry_run { "dry_run" } else { "anonymized" },
+                            "payment-service",
+                            common_audit::AuditSeverity::Info,
+                            None,
+                            serde_json::json!({
+                                "cutoff": run.cutoff,
+                                "retention_months": policy.months,
+                                "payment_intents": counts.payment_intents,
+                                "disputes": counts.disputes,
+                            }),
+                            serde_json::json!({"source": "payment-service"}),
+                        )
+                        .await;
+                }
+            }
+        }
+    });
+}
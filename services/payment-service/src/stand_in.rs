@@ -0,0 +1,513 @@
+//! Retries and stand-in processing for card authorizations while the provider is down.
+//!
+//! Every authorization is retried with backoff when the provider cannot be reached
+//! ([`RetryPolicy`]). If it still fails and the tenant enabled stand-in, payments at or below the
+//! tenant's floor limit are queued in `stand_in_authorizations` and answered as conditionally
+//! approved; larger ones get 503. [`process_due`] replays the queue: approvals write
+//! `payment.completed` to the outbox, declines and entries that run out of attempts are
+//! force-declined with `payment.failed`, on which order-service voids the pending order.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_events::{DomainEvent, PaymentCompletedEvent, PaymentFailedEvent, PaymentStandInEvent};
+use common_http_errors::ApiError;
+use common_security::{Capability, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGauge, Opts};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::gateway::{Authorization, PaymentGateway};
+use crate::{authorize, db_error, outbox, AppState};
+
+static STAND_IN_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_stand_in_total", "Stand-in authorizations by result (queued, approved, declined, expired)"),
+        &["result"],
+    ).expect("payment_stand_in_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+static STAND_IN_QUEUED: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new("payment_stand_in_queued", "Stand-in authorizations waiting for the provider").expect("payment_stand_in_queued");
+    let _ = prometheus::default_registry().register(Box::new(g.clone()));
+    g
+});
+
+static PROVIDER_RETRIES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_provider_retries_total", "Authorization retries after the provider could not be reached, by final result"),
+        &["result"],
+    ).expect("payment_provider_retries_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+/// Entries replayed per processing run.
+const BATCH_SIZE: i64 = 50;
+/// Longest wait between replays of one entry.
+const MAX_REPLAY_BACKOFF_SECS: i64 = 3600;
+/// How long a replay holds its entry. Longer than any provider call; an entry whose processor
+/// died is picked up again once it runs out.
+const CLAIM_LEASE_SECS: f64 = 300.0;
+
+/// How often a live authorization is retried before falling back to stand-in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.
+    pub retries: u32,
+    /// Wait before the first retry; doubled for each further one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// The defaults of `PAYMENT_PROVIDER_RETRIES` and `PAYMENT_PROVIDER_RETRY_BACKOFF_MS`.
+    fn default() -> Self {
+        Self { retries: 2, backoff: Duration::from_millis(200) }
+    }
+}
+
+/// Authorize, retrying while the provider cannot be reached. `Err` means every attempt failed.
+pub async fn authorize_with_retry(
+    gateway: &dyn PaymentGateway,
+    policy: RetryPolicy,
+    order_id: &str,
+    amount_minor: i64,
+    currency: &str,
+) -> anyhow::Result<Authorization> {
+    let mut backoff = policy.backoff;
+    let mut attempt = 0;
+    loop {
+        match gateway.authorize(order_id, amount_minor, currency).await {
+            Ok(outcome) => {
+                if attempt > 0 {
+                    PROVIDER_RETRIES_TOTAL.with_label_values(&["recovered"]).inc();
+                }
+                return Ok(outcome);
+            }
+            Err(err) if attempt >= policy.retries => {
+                if attempt > 0 {
+                    PROVIDER_RETRIES_TOTAL.with_label_values(&["exhausted"]).inc();
+                }
+                return Err(err);
+            }
+            Err(err) => {
+                warn!(order_id, attempt, error = %err, "Payment provider unreachable; retrying");
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StandInSettings {
+    #[serde(skip)]
+    pub tenant_id: Uuid,
+    pub enabled: bool,
+    #[serde(rename = "floorLimitMinor")] pub floor_limit_minor: i64,
+    #[serde(rename = "updatedBy")] pub updated_by: Option<Uuid>,
+    #[serde(rename = "updatedAt")] pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StandInAuthorization {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub tenant_id: Uuid,
+    #[serde(rename = "orderId")] pub order_id: Uuid,
+    pub method: String,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+    /// `queued`, `processing` while a replay holds it, then `approved` or `declined`.
+    pub status: String,
+    pub attempts: i32,
+    #[serde(rename = "approvalCode")] pub approval_code: Option<String>,
+    #[serde(rename = "lastError")] pub last_error: Option<String>,
+    #[serde(rename = "nextAttemptAt")] pub next_attempt_at: DateTime<Utc>,
+    #[serde(rename = "createdAt")] pub created_at: DateTime<Utc>,
+    #[serde(rename = "resolvedAt")] pub resolved_at: Option<DateTime<Utc>>,
+    /// End of the lease of the replay processing the entry.
+    #[serde(skip)]
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl StandInAuthorization {
+    /// Code handed to the POS for a conditionally approved payment.
+    pub fn stand_in_code(&self) -> String {
+        format!("STANDIN-{}", &self.id.simple().to_string()[..8].to_ascii_uppercase())
+    }
+
+    /// Still waiting for the provider.
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status.as_str(), "queued" | "processing")
+    }
+}
+
+const AUTHORIZATION_COLUMNS: &str = "id, tenant_id, order_id, method, amount_minor, currency, status, attempts, approval_code, last_error, next_attempt_at, created_at, resolved_at, locked_until";
+
+/// What [`enqueue`] made of a payment the provider could not take.
+#[derive(Debug, Clone)]
+pub enum Enqueued {
+    /// Waiting for the provider, queued now or by an earlier attempt for the same order.
+    Pending(StandInAuthorization),
+    /// An earlier attempt for the order was already approved or declined by the provider.
+    Resolved(StandInAuthorization),
+    /// The tenant has stand-in off or the amount is above its floor limit.
+    Ineligible,
+}
+
+/// Queue an authorization the provider could not take and stage `payment.stand_in` so the order
+/// shows as conditionally approved. A payment retried for the same order joins its entry while
+/// that is pending, and gets the provider's answer once it is resolved.
+pub async fn enqueue(
+    db: &PgPool,
+    tenant_id: Uuid,
+    order_id: Uuid,
+    method: &str,
+    amount_minor: i64,
+    currency: &str,
+) -> anyhow::Result<Enqueued> {
+    let mut tx = db.begin().await?;
+    let queued = sqlx::query_as::<_, StandInAuthorization>(&format!(
+        "INSERT INTO stand_in_authorizations (id, tenant_id, order_id, method, amount_minor, currency)
+         SELECT $1, $2, $3, $4, $5, $6 FROM stand_in_settings
+          WHERE tenant_id = $2 AND enabled AND $5 > 0 AND $5 <= floor_limit_minor
+         ON CONFLICT (tenant_id, order_id) DO NOTHING
+         RETURNING {AUTHORIZATION_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(order_id)
+    .bind(method)
+    .bind(amount_minor)
+    .bind(currency)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(entry) = queued {
+        let event = PaymentStandInEvent {
+            schema_version: PaymentStandInEvent::SCHEMA_VERSION,
+            stand_in_id: entry.id,
+            order_id,
+            tenant_id,
+            method: entry.method.clone(),
+            amount_minor,
+            currency: entry.currency.clone(),
+        };
+        outbox::stage(&mut tx, tenant_id, &event).await?;
+        tx.commit().await?;
+        STAND_IN_TOTAL.with_label_values(&["queued"]).inc();
+        warn!(stand_in_id = %entry.id, order_id = %order_id, tenant_id = %tenant_id, amount_minor, "Payment queued for stand-in processing");
+        return Ok(Enqueued::Pending(entry));
+    }
+    let existing = sqlx::query_as::<_, StandInAuthorization>(&format!(
+        "SELECT {AUTHORIZATION_COLUMNS} FROM stand_in_authorizations WHERE tenant_id = $1 AND order_id = $2"
+    ))
+    .bind(tenant_id)
+    .bind(order_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    Ok(match existing {
+        Some(entry) if entry.is_pending() => Enqueued::Pending(entry),
+        Some(entry) => Enqueued::Resolved(entry),
+        None => Enqueued::Ineligible,
+    })
+}
+
+/// What one processing run did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessSummary {
+    pub approved: u32,
+    pub declined: u32,
+    /// Still unreachable; the run stops at the first of these.
+    pub deferred: u32,
+}
+
+fn amount_major(amount_minor: i64) -> f64 {
+    amount_minor as f64 / 100.0
+}
+
+/// Replay due queue entries, oldest first. Stops at the first entry the provider still cannot
+/// take, so an ongoing outage costs one call per run. Entries that were tried `max_attempts`
+/// times are force-declined.
+///
+/// Each entry is leased in its own statement and the provider is called with no transaction
+/// open; the result is written in a second short transaction, if the lease is still ours.
+pub async fn process_due(db: &PgPool, gateway: &dyn PaymentGateway, max_attempts: i32) -> anyhow::Result<ProcessSummary> {
+    let mut summary = ProcessSummary::default();
+    for _ in 0..BATCH_SIZE {
+        let Some(entry) = claim_next(db).await? else { break };
+
+        let outcome = gateway.authorize(&entry.order_id.to_string(), entry.amount_minor, &entry.currency).await;
+        let attempts = entry.attempts + 1;
+        let mut tx = db.begin().await?;
+        let decline_reason = match outcome {
+            Ok(Authorization::Approved { approval_code }) => {
+                if !resolve(&mut tx, &entry, "approved", attempts, Some(&approval_code), None).await? {
+                    continue;
+                }
+                let event = PaymentCompletedEvent {
+                    schema_version: PaymentCompletedEvent::SCHEMA_VERSION,
+                    order_id: entry.order_id,
+                    tenant_id: entry.tenant_id,
+                    method: entry.method.clone(),
+                    amount: amount_major(entry.amount_minor),
+                };
+                outbox::stage(&mut tx, entry.tenant_id, &event).await?;
+                tx.commit().await?;
+                STAND_IN_TOTAL.with_label_values(&["approved"]).inc();
+                info!(stand_in_id = %entry.id, order_id = %entry.order_id, attempts, "Stand-in payment approved by provider");
+                summary.approved += 1;
+                continue;
+            }
+            Ok(Authorization::Declined { reason }) => format!("stand_in_declined: {reason}"),
+            Err(err) if attempts >= max_attempts => format!("stand_in_expired: provider unavailable after {attempts} attempts ({err})"),
+            Err(err) => {
+                let backoff_secs = (60 * i64::from(attempts)).min(MAX_REPLAY_BACKOFF_SECS);
+                let released = sqlx::query(
+                    "UPDATE stand_in_authorizations
+                     SET status = 'queued', locked_until = NULL, attempts = $3, last_error = $4,
+                         next_attempt_at = now() + make_interval(secs => $5), updated_at = now()
+                     WHERE id = $1 AND status = 'processing' AND locked_until = $2",
+                )
+                .bind(entry.id)
+                .bind(entry.locked_until)
+                .bind(attempts)
+                .bind(err.to_string())
+                .bind(backoff_secs as f64)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                if released.rows_affected() == 0 {
+                    warn!(stand_in_id = %entry.id, "Stand-in lease ran out before the replay finished");
+                }
+                summary.deferred += 1;
+                break;
+            }
+        };
+
+        if !resolve(&mut tx, &entry, "declined", attempts, None, Some(&decline_reason)).await? {
+            continue;
+        }
+        let event = PaymentFailedEvent {
+            schema_version: PaymentFailedEvent::SCHEMA_VERSION,
+            order_id: entry.order_id,
+            tenant_id: entry.tenant_id,
+            method: entry.method.clone(),
+            reason: decline_reason.clone(),
+        };
+        outbox::stage(&mut tx, entry.tenant_id, &event).await?;
+        tx.commit().await?;
+        let result = if decline_reason.starts_with("stand_in_expired") { "expired" } else { "declined" };
+        STAND_IN_TOTAL.with_label_values(&[result]).inc();
+        warn!(stand_in_id = %entry.id, order_id = %entry.order_id, reason = %decline_reason, "Stand-in payment force-declined");
+        summary.declined += 1;
+    }
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stand_in_authorizations WHERE status IN ('queued', 'processing')")
+        .fetch_one(db)
+        .await?;
+    STAND_IN_QUEUED.set(queued);
+    Ok(summary)
+}
+
+/// Lease the oldest due entry: a queued one whose retry time has come, or one whose previous
+/// lease ran out.
+async fn claim_next(db: &PgPool) -> Result<Option<StandInAuthorization>, sqlx::Error> {
+    sqlx::query_as::<_, StandInAuthorization>(&format!(
+        "UPDATE stand_in_authorizations
+         SET status = 'processing', locked_until = now() + make_interval(secs => $1), updated_at = now()
+         WHERE id = (
+             SELECT id FROM stand_in_authorizations
+             WHERE (status = 'queued' AND next_attempt_at <= now()) OR (status = 'processing' AND locked_until <= now())
+             ORDER BY created_at
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING {AUTHORIZATION_COLUMNS}"
+    ))
+    .bind(CLAIM_LEASE_SECS)
+    .fetch_optional(db)
+    .await
+}
+
+/// Record the provider's answer. `false` when the lease ran out and another replay took the
+/// entry over, in which case nothing is written.
+async fn resolve(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    entry: &StandInAuthorization,
+    status: &str,
+    attempts: i32,
+    approval_code: Option<&str>,
+    last_error: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE stand_in_authorizations
+         SET status = $3, locked_until = NULL, attempts = $4, approval_code = $5, last_error = COALESCE($6, last_error), resolved_at = now(), updated_at = now()
+         WHERE id = $1 AND status = 'processing' AND locked_until = $2",
+    )
+    .bind(entry.id)
+    .bind(entry.locked_until)
+    .bind(status)
+    .bind(attempts)
+    .bind(approval_code)
+    .bind(last_error)
+    .execute(&mut **tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        warn!(stand_in_id = %entry.id, status, "Stand-in lease ran out before the replay finished; result dropped");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Replay the queue every `every` until shutdown.
+pub fn spawn_stand_in_processor(db: PgPool, gateway: std::sync::Arc<dyn PaymentGateway>, every: Duration, max_attempts: i32) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match process_due(&db, gateway.as_ref(), max_attempts).await {
+                Ok(summary) if summary.approved + summary.declined > 0 => {
+                    info!(approved = summary.approved, declined = summary.declined, "Processed stand-in queue");
+                }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "Stand-in processing failed"),
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StandInSettingsRequest {
+    pub enabled: bool,
+    #[serde(rename = "floorLimitMinor")] pub floor_limit_minor: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct QueueParams {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// `GET /stand-in/settings`: the tenant's settings; stand-in is off until saved.
+pub async fn get_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<StandInSettings>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentStandIn, "payment_stand_in").await?;
+    let settings = sqlx::query_as::<_, StandInSettings>(
+        "SELECT tenant_id, enabled, floor_limit_minor, updated_by, updated_at FROM stand_in_settings WHERE tenant_id = $1",
+    )
+    .bind(sec.tenant_id)
+    .fetch_optional(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    Ok(Json(settings.unwrap_or(StandInSettings {
+        tenant_id: sec.tenant_id,
+        enabled: false,
+        floor_limit_minor: 0,
+        updated_by: None,
+        updated_at: DateTime::UNIX_EPOCH,
+    })))
+}
+
+/// `PUT /stand-in/settings`
+pub async fn put_settings(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<StandInSettingsRequest>,
+) -> Result<Json<StandInSettings>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentStandIn, "payment_stand_in").await?;
+    if req.floor_limit_minor < 0 {
+        return Err(ApiError::BadRequest { code: "invalid_floor_limit", trace_id: sec.trace_id, message: Some("floorLimitMinor cannot be negative".into()) });
+    }
+    let settings = sqlx::query_as::<_, StandInSettings>(
+        "INSERT INTO stand_in_settings (tenant_id, enabled, floor_limit_minor, updated_by)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (tenant_id) DO UPDATE
+           SET enabled = EXCLUDED.enabled, floor_limit_minor = EXCLUDED.floor_limit_minor, updated_by = EXCLUDED.updated_by, updated_at = now()
+         RETURNING tenant_id, enabled, floor_limit_minor, updated_by, updated_at",
+    )
+    .bind(sec.tenant_id)
+    .bind(req.enabled)
+    .bind(req.floor_limit_minor)
+    .bind(sec.actor.id)
+    .fetch_one(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    info!(tenant_id = %sec.tenant_id, enabled = req.enabled, floor_limit_minor = req.floor_limit_minor, "Stand-in settings saved");
+    Ok(Json(settings))
+}
+
+/// `GET /stand-in/authorizations?status=queued|processing|approved|declined&limit=`: newest first.
+pub async fn list_authorizations(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(params): Query<QueueParams>,
+) -> Result<Json<Vec<StandInAuthorization>>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentStandIn, "payment_stand_in").await?;
+    if let Some(status) = params.status.as_deref() {
+        if !matches!(status, "queued" | "processing" | "approved" | "declined") {
+            return Err(ApiError::BadRequest { code: "invalid_status", trace_id: sec.trace_id, message: Some("status must be queued, processing, approved or declined".into()) });
+        }
+    }
+    let entries = sqlx::query_as::<_, StandInAuthorization>(&format!(
+        "SELECT {AUTHORIZATION_COLUMNS} FROM stand_in_authorizations
+         WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2)
+         ORDER BY created_at DESC LIMIT $3"
+    ))
+    .bind(sec.tenant_id)
+    .bind(params.status.as_deref())
+    .bind(params.limit.unwrap_or(100).clamp(1, 500))
+    .fetch_all(&db)
+    .await
+    .map_err(db_error(sec.trace_id))?;
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Unreachable for the first `failures` calls, then approves.
+    struct FlakyGateway {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl PaymentGateway for FlakyGateway {
+        async fn authorize(&self, _order_id: &str, _amount_minor: i64, _currency: &str) -> anyhow::Result<Authorization> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                anyhow::bail!("connection refused");
+            }
+            Ok(Authorization::Approved { approval_code: "OK".into() })
+        }
+        async fn void(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+        async fn refund(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_provider_answers() {
+        let policy = RetryPolicy { retries: 2, backoff: Duration::ZERO };
+        let gateway = FlakyGateway { failures: 2, calls: AtomicU32::new(0) };
+        let outcome = authorize_with_retry(&gateway, policy, "order", 1000, "USD").await.unwrap();
+        assert_eq!(outcome, Authorization::Approved { approval_code: "OK".into() });
+        assert_eq!(gateway.calls.load(Ordering::SeqCst), 3);
+
+        let gateway = FlakyGateway { failures: 3, calls: AtomicU32::new(0) };
+        assert!(authorize_with_retry(&gateway, policy, "order", 1000, "USD").await.is_err());
+        assert_eq!(gateway.calls.load(Ordering::SeqCst), 3, "no more than retries + 1 calls");
+    }
+}
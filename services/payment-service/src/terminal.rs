@@ -0,0 +1,361 @@
+//! Card-present payments on a physical terminal (semi-integrated).
+//!
+//! The POS opens a session with `POST /terminal/sessions`; the amount is pushed to the terminal
+//! through its [`TerminalDriver`]. The result arrives either by polling (`GET
+//! /terminal/sessions/:id`) or by the driver calling `POST /webhooks/terminal`. An approved
+//! session records a captured payment intent and writes `payment.completed` to the outbox, the
+//! same event online payments produce, so order-service completes the order as usual. Declines
+//! leave the order open for another attempt.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use common_crypto::EncryptedColumn;
+use common_events::{DomainEvent, PaymentCompletedEvent};
+use common_http_errors::ApiError;
+use common_security::{Capability, SecurityContext, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, Opts};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::terminal_driver::{self, TerminalDriver, TerminalOutcome, TerminalRequest, TerminalResult};
+use crate::{authorize, db_error, AppState, CARD_LAST4_FIELD};
+
+static TERMINAL_SESSIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("payment_terminal_sessions_total", "Finished terminal sessions by driver and outcome"),
+        &["driver", "status"],
+    ).expect("payment_terminal_sessions_total");
+    let _ = prometheus::default_registry().register(Box::new(c.clone()));
+    c
+});
+
+const PENDING: &str = "pending";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TerminalSession {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub tenant_id: Uuid,
+    #[serde(rename = "orderId")] pub order_id: Uuid,
+    #[serde(rename = "terminalId")] pub terminal_id: String,
+    pub driver: String,
+    #[serde(rename = "driverRef")] pub driver_ref: Option<String>,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+    /// `pending`, then one of `approved`, `declined`, `cancelled`, `failed`.
+    pub status: String,
+    #[serde(rename = "approvalCode")] pub approval_code: Option<String>,
+    #[serde(rename = "cardBrand")] pub card_brand: Option<String>,
+    #[serde(rename = "failureReason")] pub failure_reason: Option<String>,
+    #[serde(rename = "paymentIntentId")] pub payment_intent_id: Option<String>,
+    #[serde(rename = "createdAt")] pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")] pub updated_at: DateTime<Utc>,
+    #[serde(rename = "completedAt")] pub completed_at: Option<DateTime<Utc>>,
+}
+
+const SESSION_COLUMNS: &str = "id, tenant_id, order_id, terminal_id, driver, driver_ref, amount_minor, currency, status, approval_code, card_brand, failure_reason, payment_intent_id, created_at, updated_at, completed_at";
+
+impl TerminalSession {
+    fn is_pending(&self) -> bool {
+        self.status == PENDING
+    }
+}
+
+fn driver_for(session: &TerminalSession) -> anyhow::Result<std::sync::Arc<dyn TerminalDriver>> {
+    terminal_driver::driver(&session.driver).ok_or_else(|| anyhow::anyhow!("terminal driver {} is not available", session.driver))
+}
+
+/// Record the terminal's result on a pending session. Returns the session and whether this call
+/// finished it; results for sessions that already finished are ignored.
+async fn finish(state: &AppState, db: &PgPool, id: Uuid, result: &TerminalResult) -> anyhow::Result<(TerminalSession, bool)> {
+    let mut tx = db.begin().await?;
+    let session = sqlx::query_as::<_, TerminalSession>(&format!("SELECT {SESSION_COLUMNS} FROM terminal_sessions WHERE id = $1 FOR UPDATE"))
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !session.is_pending() {
+        tx.rollback().await?;
+        return Ok((session, false));
+    }
+
+    let mut payment_intent_id = None;
+    if result.outcome == TerminalOutcome::Approved {
+        let intent_id = session.id.to_string();
+        // Without PAYMENT_PII_KEY the last four digits are dropped, as for online intents.
+        let card_last4: Option<EncryptedColumn<String>> = match (state.pii_key.as_ref(), result.card_last4.as_ref()) {
+            (Some(key), Some(last4)) => Some(key.seal(session.tenant_id.as_bytes(), CARD_LAST4_FIELD, last4)?),
+            _ => None,
+        };
+        let metadata = serde_json::json!({
+            "channel": "card_present",
+            "terminalId": session.terminal_id,
+            "approvalCode": result.approval_code,
+            "cardBrand": result.card_brand,
+        });
+        sqlx::query(
+            "INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, provider, provider_ref, metadata_json, tenant_id, card_last4_encrypted)
+             VALUES ($1, $2, $3, $4, 'captured', $5, $6, $7, $8, $9)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(&intent_id)
+        .bind(session.order_id.to_string())
+        .bind(session.amount_minor)
+        .bind(&session.currency)
+        .bind(&session.driver)
+        .bind(&session.driver_ref)
+        .bind(&metadata)
+        .bind(session.tenant_id)
+        .bind(card_last4)
+        .execute(&mut *tx)
+        .await?;
+        payment_intent_id = Some(intent_id);
+    }
+
+    let session = sqlx::query_as::<_, TerminalSession>(&format!(
+        "UPDATE terminal_sessions
+         SET status = $2, approval_code = $3, card_brand = $4, failure_reason = $5, payment_intent_id = $6,
+             completed_at = now(), updated_at = now()
+         WHERE id = $1 RETURNING {SESSION_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(result.outcome.as_str())
+    .bind(&result.approval_code)
+    .bind(&result.card_brand)
+    .bind(&result.message)
+    .bind(&payment_intent_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if result.outcome == TerminalOutcome::Approved {
+        let event = PaymentCompletedEvent {
+            schema_version: PaymentCompletedEvent::SCHEMA_VERSION,
+            order_id: session.order_id,
+            tenant_id: session.tenant_id,
+            method: "card".into(),
+            amount: session.amount_minor as f64 / 100.0,
+        };
+        crate::outbox::stage(&mut tx, session.tenant_id, &event).await?;
+    }
+    tx.commit().await?;
+    TERMINAL_SESSIONS_TOTAL.with_label_values(&[&session.driver, &session.status]).inc();
+    info!(session_id = %session.id, order_id = %session.order_id, terminal_id = %session.terminal_id, status = %session.status, "terminal session finished");
+    Ok((session, true))
+}
+
+/// Ask the driver for the result of a pending session and record it if the terminal is done.
+async fn refresh(state: &AppState, db: &PgPool, session: TerminalSession) -> anyhow::Result<TerminalSession> {
+    let Some(driver_ref) = session.driver_ref.as_deref().filter(|_| session.is_pending()) else {
+        return Ok(session);
+    };
+    match driver_for(&session)?.poll(driver_ref).await? {
+        Some(result) => Ok(finish(state, db, session.id, &result).await?.0),
+        None => Ok(session),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSessionRequest {
+    #[serde(rename = "orderId")] pub order_id: Uuid,
+    #[serde(rename = "terminalId")] pub terminal_id: String,
+    #[serde(rename = "amountMinor")] pub amount_minor: i64,
+    pub currency: String,
+    /// Defaults to `TERMINAL_DRIVER`.
+    pub driver: Option<String>,
+}
+
+/// `POST /terminal/sessions`: push an order's amount to a terminal.
+pub async fn create_session(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<CreateSessionRequest>,
+) -> Result<Json<TerminalSession>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentProcess, "payment_access").await?;
+    let bad_request = |code: &'static str, message: &str| ApiError::BadRequest { code, trace_id: sec.trace_id, message: Some(message.into()) };
+    let terminal_id = req.terminal_id.trim();
+    if terminal_id.is_empty() {
+        return Err(bad_request("invalid_terminal_id", "terminalId is required"));
+    }
+    if req.amount_minor <= 0 {
+        return Err(bad_request("invalid_amount", "amountMinor must be positive"));
+    }
+    if req.currency.len() != 3 || !req.currency.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(bad_request("invalid_currency", "currency must be a three-letter ISO code"));
+    }
+    let driver_name = req.driver.as_deref().map(|d| d.trim().to_ascii_lowercase()).unwrap_or_else(terminal_driver::default_driver_name);
+    let driver = terminal_driver::driver(&driver_name).ok_or_else(|| bad_request("unknown_terminal_driver", &format!("no terminal driver named {driver_name}")))?;
+
+    let already_paid: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM terminal_sessions WHERE tenant_id = $1 AND order_id = $2 AND status = 'approved' LIMIT 1")
+        .bind(sec.tenant_id)
+        .bind(req.order_id)
+        .fetch_optional(&db)
+        .await
+        .map_err(db_error(sec.trace_id))?;
+    if let Some((session_id,)) = already_paid {
+        return Err(ApiError::Conflict { code: "order_already_paid", trace_id: sec.trace_id, message: Some(format!("terminal session {session_id} was approved")) });
+    }
+
+    let session = sqlx::query_as::<_, TerminalSession>(&format!(
+        "INSERT INTO terminal_sessions (id, tenant_id, order_id, terminal_id, driver, amount_minor, currency, status, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING {SESSION_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(sec.tenant_id)
+    .bind(req.order_id)
+    .bind(terminal_id)
+    .bind(driver.name())
+    .bind(req.amount_minor)
+    .bind(req.currency.to_ascii_uppercase())
+    .bind(PENDING)
+    .bind(sec.actor.id)
+    .fetch_one(&db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => ApiError::Conflict {
+            code: "terminal_busy",
+            trace_id: sec.trace_id,
+            message: Some(format!("terminal {terminal_id} has a transaction in progress")),
+        },
+        other => db_error(sec.trace_id)(other),
+    })?;
+
+    let push = TerminalRequest {
+        session_id: session.id,
+        terminal_id: session.terminal_id.clone(),
+        amount_minor: session.amount_minor,
+        currency: session.currency.clone(),
+    };
+    let internal = |e: anyhow::Error| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("terminal_error: {e}")) };
+    match driver.push(&push).await {
+        Ok(driver_ref) => {
+            let session = sqlx::query_as::<_, TerminalSession>(&format!(
+                "UPDATE terminal_sessions SET driver_ref = $2, updated_at = now() WHERE id = $1 RETURNING {SESSION_COLUMNS}"
+            ))
+            .bind(session.id)
+            .bind(driver_ref)
+            .fetch_one(&db)
+            .await
+            .map_err(db_error(sec.trace_id))?;
+            Ok(Json(session))
+        }
+        Err(err) => {
+            // Free the terminal; the POS shows the failure and can retry.
+            warn!(session_id = %session.id, terminal_id = %session.terminal_id, error = %err, "failed to push amount to terminal");
+            let result = TerminalResult {
+                outcome: TerminalOutcome::Failed,
+                approval_code: None,
+                card_brand: None,
+                card_last4: None,
+                message: Some(format!("terminal unreachable: {err}")),
+            };
+            finish(&state, &db, session.id, &result).await.map(|(s, _)| Json(s)).map_err(internal)
+        }
+    }
+}
+
+async fn load_for_tenant(db: &PgPool, sec: &SecurityContext, id: Uuid) -> Result<TerminalSession, ApiError> {
+    sqlx::query_as::<_, TerminalSession>(&format!("SELECT {SESSION_COLUMNS} FROM terminal_sessions WHERE id = $1 AND tenant_id = $2"))
+        .bind(id)
+        .bind(sec.tenant_id)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error(sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "terminal_session_not_found", trace_id: sec.trace_id })
+}
+
+/// `GET /terminal/sessions/:id`: the POS polls this until the session leaves `pending`.
+pub async fn get_session(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TerminalSession>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentProcess, "payment_access").await?;
+    let session = load_for_tenant(&db, &sec, id).await?;
+    let session = refresh(&state, &db, session)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("terminal_error: {e}")) })?;
+    Ok(Json(session))
+}
+
+/// `POST /terminal/sessions/:id/cancel`: abort before the customer pays. If the terminal already
+/// finished, its result is recorded instead and returned.
+pub async fn cancel_session(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TerminalSession>, ApiError> {
+    let db = authorize(&state, &sec, Capability::PaymentProcess, "payment_access").await?;
+    let session = load_for_tenant(&db, &sec, id).await?;
+    if !session.is_pending() {
+        return Err(ApiError::Conflict { code: "invalid_session_state", trace_id: sec.trace_id, message: Some(format!("session is {}", session.status)) });
+    }
+    let internal = |e: anyhow::Error| ApiError::Internal { trace_id: sec.trace_id, message: Some(format!("terminal_error: {e}")) };
+    let cancelled = match session.driver_ref.as_deref() {
+        Some(driver_ref) => driver_for(&session).map_err(internal)?.cancel(driver_ref).await.map_err(internal)?,
+        None => true,
+    };
+    if !cancelled {
+        return refresh(&state, &db, session).await.map(Json).map_err(internal);
+    }
+    let result = TerminalResult {
+        outcome: TerminalOutcome::Cancelled,
+        approval_code: None,
+        card_brand: None,
+        card_last4: None,
+        message: Some("cancelled by operator".into()),
+    };
+    finish(&state, &db, session.id, &result).await.map(|(s, _)| Json(s)).map_err(internal)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TerminalCallback {
+    pub driver: String,
+    #[serde(rename = "driverRef")] pub driver_ref: String,
+    pub outcome: String,
+    #[serde(rename = "approvalCode")] pub approval_code: Option<String>,
+    #[serde(rename = "cardBrand")] pub card_brand: Option<String>,
+    #[serde(rename = "cardLast4")] pub card_last4: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TerminalCallbackResponse {
+    pub id: Uuid,
+    pub status: String,
+    /// False when the session had already finished (duplicate or late callback).
+    pub applied: bool,
+}
+
+/// `POST /webhooks/terminal`: result pushed by the terminal or its cloud service. Signature checked
+/// by [`crate::webhook::verify_webhook`].
+pub async fn terminal_webhook(
+    State(state): State<AppState>,
+    Json(callback): Json<TerminalCallback>,
+) -> Result<Json<TerminalCallbackResponse>, ApiError> {
+    let outcome = TerminalOutcome::parse(&callback.outcome).ok_or_else(|| ApiError::BadRequest {
+        code: "invalid_terminal_outcome",
+        trace_id: None,
+        message: Some(format!("unknown outcome {}", callback.outcome)),
+    })?;
+    let card_last4 = callback.card_last4.filter(|v| v.len() == 4 && v.bytes().all(|b| b.is_ascii_digit()));
+    let db = state.db.clone().ok_or(ApiError::Internal { trace_id: None, message: Some("database_not_configured".into()) })?;
+    let session: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM terminal_sessions WHERE driver = $1 AND driver_ref = $2")
+        .bind(callback.driver.trim().to_ascii_lowercase())
+        .bind(&callback.driver_ref)
+        .fetch_optional(&db)
+        .await
+        .map_err(db_error(None))?;
+    let Some((id,)) = session else {
+        return Err(ApiError::NotFound { code: "terminal_session_not_found", trace_id: None });
+    };
+    let result = TerminalResult { outcome, approval_code: callback.approval_code, card_brand: callback.card_brand, card_last4, message: callback.message };
+    let (session, applied) = finish(&state, &db, id, &result)
+        .await
+        .map_err(|e| ApiError::Internal { trace_id: None, message: Some(format!("db_error: {e}")) })?;
+    Ok(Json(TerminalCallbackResponse { id: session.id, status: session.status, applied }))
+}
@@ -0,0 +1,209 @@
+//! Drivers for card-present terminals in a semi-integrated setup: the POS sends the amount to the
+//! terminal, the terminal handles the card, and only the result comes back to us.
+//!
+//! Drivers are looked up by name ([`driver`]); `simulator` is the only one built in. Real
+//! terminals either answer [`TerminalDriver::poll`] or call back on `POST /webhooks/terminal`.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// What the terminal is asked to charge.
+#[derive(Debug, Clone)]
+pub struct TerminalRequest {
+    pub session_id: Uuid,
+    pub terminal_id: String,
+    pub amount_minor: i64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalOutcome {
+    Approved,
+    Declined,
+    /// Cancelled on the terminal or from the POS before a card was presented.
+    Cancelled,
+    /// The terminal could not complete the transaction (timeout, communication error).
+    Failed,
+}
+
+impl TerminalOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TerminalOutcome::Approved => "approved",
+            TerminalOutcome::Declined => "declined",
+            TerminalOutcome::Cancelled => "cancelled",
+            TerminalOutcome::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "approved" => Some(TerminalOutcome::Approved),
+            "declined" => Some(TerminalOutcome::Declined),
+            "cancelled" | "canceled" => Some(TerminalOutcome::Cancelled),
+            "failed" | "error" | "timeout" => Some(TerminalOutcome::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Final result of a terminal transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalResult {
+    pub outcome: TerminalOutcome,
+    pub approval_code: Option<String>,
+    pub card_brand: Option<String>,
+    pub card_last4: Option<String>,
+    /// Decline or error text from the terminal.
+    pub message: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait TerminalDriver: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Show the amount on the terminal. Returns the driver's reference for the transaction.
+    async fn push(&self, req: &TerminalRequest) -> Result<String>;
+    /// `None` while the customer is still at the terminal.
+    async fn poll(&self, driver_ref: &str) -> Result<Option<TerminalResult>>;
+    /// Abort a pending transaction. Returns false when the terminal already finished it.
+    async fn cancel(&self, driver_ref: &str) -> Result<bool>;
+}
+
+/// Driver used when a session does not name one (`TERMINAL_DRIVER`, default `simulator`).
+pub fn default_driver_name() -> String {
+    std::env::var("TERMINAL_DRIVER")
+        .ok()
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "simulator".into())
+}
+
+static DRIVERS: Lazy<HashMap<&'static str, Arc<dyn TerminalDriver>>> = Lazy::new(|| {
+    let delay = std::env::var("TERMINAL_SIMULATOR_DELAY_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or_default();
+    let simulator: Arc<dyn TerminalDriver> = Arc::new(SimulatorDriver::new(delay));
+    HashMap::from([(simulator.name(), simulator)])
+});
+
+pub fn driver(name: &str) -> Option<Arc<dyn TerminalDriver>> {
+    DRIVERS.get(name).cloned()
+}
+
+struct SimulatedTransaction {
+    amount_minor: i64,
+    ready_at: Instant,
+    cancelled: bool,
+}
+
+/// In-memory terminal for development and tests. The customer "taps" once `delay` has passed, and
+/// the cents of the amount pick the result, like provider test cards:
+/// `.05` declines, `.13` fails, anything else is approved.
+pub struct SimulatorDriver {
+    delay: Duration,
+    transactions: Mutex<HashMap<String, SimulatedTransaction>>,
+}
+
+impl SimulatorDriver {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay, transactions: Mutex::new(HashMap::new()) }
+    }
+
+    fn result_for(driver_ref: &str, txn: &SimulatedTransaction) -> TerminalResult {
+        let result = |outcome, message: Option<&str>| TerminalResult {
+            outcome,
+            approval_code: None,
+            card_brand: None,
+            card_last4: None,
+            message: message.map(str::to_string),
+        };
+        if txn.cancelled {
+            return result(TerminalOutcome::Cancelled, Some("cancelled by operator"));
+        }
+        match txn.amount_minor % 100 {
+            5 => TerminalResult { card_brand: Some("visa".into()), card_last4: Some("0002".into()), ..result(TerminalOutcome::Declined, Some("insufficient funds")) },
+            13 => result(TerminalOutcome::Failed, Some("terminal timeout")),
+            _ => TerminalResult {
+                approval_code: Some(format!("SIM-{}", &driver_ref[driver_ref.len().saturating_sub(6)..])),
+                card_brand: Some("visa".into()),
+                card_last4: Some("4242".into()),
+                ..result(TerminalOutcome::Approved, None)
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TerminalDriver for SimulatorDriver {
+    fn name(&self) -> &'static str {
+        "simulator"
+    }
+
+    async fn push(&self, req: &TerminalRequest) -> Result<String> {
+        let driver_ref = format!("sim_{}", req.session_id.simple());
+        let txn = SimulatedTransaction { amount_minor: req.amount_minor, ready_at: Instant::now() + self.delay, cancelled: false };
+        self.transactions.lock().unwrap().insert(driver_ref.clone(), txn);
+        Ok(driver_ref)
+    }
+
+    async fn poll(&self, driver_ref: &str) -> Result<Option<TerminalResult>> {
+        let transactions = self.transactions.lock().unwrap();
+        let txn = transactions.get(driver_ref).ok_or_else(|| anyhow::anyhow!("unknown terminal transaction {driver_ref}"))?;
+        if !txn.cancelled && Instant::now() < txn.ready_at {
+            return Ok(None);
+        }
+        Ok(Some(Self::result_for(driver_ref, txn)))
+    }
+
+    async fn cancel(&self, driver_ref: &str) -> Result<bool> {
+        let mut transactions = self.transactions.lock().unwrap();
+        let txn = transactions.get_mut(driver_ref).ok_or_else(|| anyhow::anyhow!("unknown terminal transaction {driver_ref}"))?;
+        if txn.cancelled || Instant::now() >= txn.ready_at {
+            return Ok(txn.cancelled);
+        }
+        txn.cancelled = true;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(amount_minor: i64) -> TerminalRequest {
+        TerminalRequest { session_id: Uuid::new_v4(), terminal_id: "lane-1".into(), amount_minor, currency: "USD".into() }
+    }
+
+    #[tokio::test]
+    async fn simulator_picks_the_result_from_the_cents() {
+        let sim = SimulatorDriver::new(Duration::ZERO);
+        let approved = sim.push(&request(1250)).await.unwrap();
+        let declined = sim.push(&request(1205)).await.unwrap();
+        let failed = sim.push(&request(1213)).await.unwrap();
+
+        let result = sim.poll(&approved).await.unwrap().unwrap();
+        assert_eq!(result.outcome, TerminalOutcome::Approved);
+        assert!(result.approval_code.is_some());
+        assert_eq!(sim.poll(&declined).await.unwrap().unwrap().outcome, TerminalOutcome::Declined);
+        assert_eq!(sim.poll(&failed).await.unwrap().unwrap().outcome, TerminalOutcome::Failed);
+        assert!(!sim.cancel(&approved).await.unwrap(), "finished transactions can't be cancelled");
+    }
+
+    #[tokio::test]
+    async fn simulator_waits_for_the_tap_and_can_be_cancelled() {
+        let sim = SimulatorDriver::new(Duration::from_secs(60));
+        let pending = sim.push(&request(1000)).await.unwrap();
+        assert_eq!(sim.poll(&pending).await.unwrap(), None);
+        assert!(sim.cancel(&pending).await.unwrap());
+        assert_eq!(sim.poll(&pending).await.unwrap().unwrap().outcome, TerminalOutcome::Cancelled);
+        assert!(sim.poll("sim_unknown").await.is_err());
+    }
+}
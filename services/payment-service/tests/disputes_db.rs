@@ -0,0 +1,98 @@
+use axum::{Router, routing::{get, post}, http::Request, body::{Body, to_bytes}};
+use payment_service::{AppState, disputes::{dispute_webhook, get_dispute, list_disputes, submit_evidence}};
+use common_auth::{JwtVerifier, JwtConfig};
+use std::sync::Arc;
+use tower::ServiceExt;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Executor};
+
+const TENANT: &str = "00000000-0000-0000-0000-0000000000d1";
+const ORDER: &str = "00000000-0000-0000-0000-0000000000d2";
+
+fn app(db: PgPool) -> Router {
+    let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
+    let state = AppState { jwt_verifier: verifier, db: Some(db), pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
+    // Signature checks are covered by webhook_middleware.rs; this router exercises the handler only.
+    Router::new()
+        .route("/webhooks/disputes", post(dispute_webhook))
+        .route("/disputes", get(list_disputes))
+        .route("/disputes/:id", get(get_dispute))
+        .route("/disputes/:id/evidence", post(submit_evidence))
+        .with_state(state)
+}
+
+async fn call(app: &Router, method: &str, uri: &str, body: Value) -> (u16, Value) {
+    let req = Request::builder().uri(uri).method(method)
+        .header("content-type", "application/json")
+        .header("X-Tenant-ID", TENANT)
+        .header("X-Roles", "admin")
+        .body(Body::from(body.to_string())).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status().as_u16();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn hook(event_type: &str, status: &str) -> Value {
+    json!({
+        "type": event_type, "provider": "stub",
+        "dispute": {"id": "dp_db_1", "providerRef": "ch_dispute_1", "amountMinor": 2500, "currency": "usd", "status": status, "reason": "fraudulent"}
+    })
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_dispute_lifecycle() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes payment-service migrations (through 8006) have been applied.
+    pool.execute(format!(r#"
+        ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+        DELETE FROM disputes WHERE provider = 'stub' AND provider_dispute_id = 'dp_db_1';
+        DELETE FROM outbox WHERE topic = 'payment.dispute.updated' AND message_key = '{ORDER}';
+        DELETE FROM payment_intents WHERE id = 'pi_dispute_1';
+        INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, provider, provider_ref, tenant_id)
+        VALUES ('pi_dispute_1', '{ORDER}', 2500, 'USD', 'captured', 'stub', 'ch_dispute_1', '{TENANT}');
+    "#).as_str()).await.unwrap();
+    let app = app(pool.clone());
+
+    let (status, opened) = call(&app, "POST", "/webhooks/disputes", hook("dispute.created", "warning_needs_response")).await;
+    assert_eq!(status, 200, "{opened}");
+    assert_eq!(opened["status"], "needs_response");
+    let id = opened["id"].as_str().unwrap().to_string();
+
+    // Redelivery of the same status is not a transition.
+    let (_, again) = call(&app, "POST", "/webhooks/disputes", hook("dispute.updated", "needs_response")).await;
+    assert_eq!(again["applied"], true);
+
+    let (status, _) = call(&app, "POST", &format!("/disputes/{id}/evidence"), json!({})).await;
+    assert_eq!(status, 400);
+    let evidence = json!({"note": "Signed receipt attached", "documents": [{"kind": "receipt", "reference": "file_123"}]});
+    let (status, updated) = call(&app, "POST", &format!("/disputes/{id}/evidence"), evidence.clone()).await;
+    assert_eq!(status, 200, "{updated}");
+    assert_eq!(updated["status"], "under_review");
+    assert_eq!(updated["evidence"]["documents"][0]["kind"], "receipt");
+    let (status, _) = call(&app, "POST", &format!("/disputes/{id}/evidence"), evidence).await;
+    assert_eq!(status, 409);
+
+    let (_, lost) = call(&app, "POST", "/webhooks/disputes", hook("dispute.updated", "lost")).await;
+    assert_eq!((lost["status"].as_str(), lost["applied"].as_bool()), (Some("lost"), Some(true)));
+    let (_, late) = call(&app, "POST", "/webhooks/disputes", hook("dispute.updated", "under_review")).await;
+    assert_eq!((late["status"].as_str(), late["applied"].as_bool()), (Some("lost"), Some(false)));
+
+    let (_, listed) = call(&app, "GET", "/disputes?status=lost", Value::Null).await;
+    assert!(listed.as_array().unwrap().iter().any(|d| d["id"] == id.as_str() && d["orderId"] == ORDER));
+
+    let published: Vec<(Value,)> = sqlx::query_as("SELECT payload FROM outbox WHERE topic = 'payment.dispute.updated' AND message_key = $1 ORDER BY id")
+        .bind(ORDER)
+        .fetch_all(&pool).await.unwrap();
+    let transitions: Vec<(Option<&str>, Option<&str>)> = published.iter()
+        .map(|(p,)| (p["previous_status"].as_str(), p["status"].as_str()))
+        .collect();
+    assert_eq!(transitions, vec![
+        (None, Some("needs_response")),
+        (Some("needs_response"), Some("under_review")),
+        (Some("under_review"), Some("lost")),
+    ]);
+    assert!(published.iter().all(|(p,)| p["tenant_id"] == TENANT && p["amount_minor"] == 2500));
+}
@@ -7,7 +7,7 @@ use serde_json::json;
 
 fn app() -> Router {
     let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
-    let state = AppState { jwt_verifier: verifier, db: None, #[cfg(feature="kafka")] audit_producer: None };
+    let state = AppState { jwt_verifier: verifier, db: None, pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
     Router::new()
         .route("/payment_intents", post(create_intent))
         .route("/payment_intents/:id", get(get_intent))
@@ -63,3 +63,18 @@ async fn create_and_confirm_intent_without_db() {
     let resp = app.clone().oneshot(req).await.unwrap();
     assert!(resp.status().is_success());
 }
+
+#[tokio::test]
+async fn confirm_rejects_malformed_card_last4() {
+    let app = app();
+    let body = json!({"id": "pi_test_card", "cardholderName": "Ada Lovelace", "cardLast4": "12a4"}).to_string();
+    let mut req = Request::builder().uri("/payment_intents/confirm").method("POST")
+        .header("content-type","application/json")
+        .body(axum::body::Body::from(body)).unwrap();
+    let headers = req.headers_mut();
+    headers.insert("X-Tenant-ID", "00000000-0000-0000-0000-000000000000".parse().unwrap());
+    headers.insert("X-Roles", "cashier".parse().unwrap());
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "invalid_card_last4");
+}
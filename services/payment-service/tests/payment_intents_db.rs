@@ -1,14 +1,15 @@
 use axum::{Router, routing::{post, get}, http::Request};
-use payment_service::{AppState, payment_handlers::{create_intent, confirm_intent, capture_intent, refund_intent, void_intent, get_intent}};
+use payment_service::{AppState, CARD_LAST4_FIELD, payment_handlers::{create_intent, confirm_intent, capture_intent, refund_intent, void_intent, get_intent}};
 use common_auth::{JwtVerifier, JwtConfig};
 use std::sync::Arc;
+use common_crypto::{ColumnKey, MasterKey};
 use tower::ServiceExt;
 use serde_json::json;
 use sqlx::{PgPool, Executor};
 
 async fn app_with_db(db: PgPool) -> Router {
     let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
-    let state = AppState { jwt_verifier: verifier, db: Some(db), #[cfg(feature="kafka")] audit_producer: None };
+    let state = AppState { jwt_verifier: verifier, db: Some(db), pii_key: Some(Arc::new(ColumnKey::new(MasterKey::from_bytes([7u8; 32]).unwrap()))), retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
     Router::new()
         .route("/payment_intents", post(create_intent))
         .route("/payment_intents/:id", get(get_intent))
@@ -41,6 +42,11 @@ async fn db_backed_transitions_and_conflicts() {
         created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
         updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
     );
+    ALTER TABLE payment_intents
+        ADD COLUMN IF NOT EXISTS cardholder_name_encrypted BYTEA,
+        ADD COLUMN IF NOT EXISTS card_last4_encrypted BYTEA,
+        ADD COLUMN IF NOT EXISTS tenant_id UUID,
+        ADD COLUMN IF NOT EXISTS tip_minor BIGINT NOT NULL DEFAULT 0;
     "#).await.unwrap();
 
     // Clone pool so we can run direct assertions after moving one clone into the app state
@@ -76,7 +82,7 @@ async fn db_backed_transitions_and_conflicts() {
     assert_eq!(resp.status().as_u16(), 409);
 
     // Confirm (created -> authorized) with provider refs
-    let body = json!({"id":"pi_db_1","provider":"valor","providerRef":"auth_123","metadata": {"term":"T1"}}).to_string();
+    let body = json!({"id":"pi_db_1","provider":"valor","providerRef":"auth_123","metadata": {"term":"T1"},"cardholderName":"Ada Lovelace","cardLast4":"4242"}).to_string();
     let mut req = Request::builder().uri("/payment_intents/confirm").method("POST")
         .header("content-type","application/json")
         .body(axum::body::Body::from(body)).unwrap();
@@ -105,7 +111,23 @@ async fn db_backed_transitions_and_conflicts() {
     headers.insert("X-Roles", "cashier".parse().unwrap());
     let resp = app.clone().oneshot(req).await.unwrap();
     assert!(resp.status().is_success());
-    // We can't see provider_ref via current GET response shape; for now only ensure success.
+    // We can't see provider_ref via current GET response shape, but card details are decrypted.
+    let bytes = axum::body::to_bytes(resp.into_body(), 1024*16).await.unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(v["card"]["cardholderName"], "Ada Lovelace");
+    assert_eq!(v["card"]["last4"], "4242");
+
+    // Card details are only stored as tenant-bound ciphertext
+    let stored: Option<Vec<u8>> = sqlx::query_scalar("SELECT card_last4_encrypted FROM payment_intents WHERE id = $1")
+        .bind("pi_db_1")
+        .fetch_one(&verify_pool)
+        .await
+        .unwrap();
+    let stored = stored.expect("card_last4_encrypted populated");
+    assert!(!stored.windows(4).any(|w| w == b"4242"));
+    let other_tenant = ColumnKey::new(MasterKey::from_bytes([7u8; 32]).unwrap());
+    let sealed = common_crypto::EncryptedColumn::<String>::from_ciphertext(stored);
+    assert!(other_tenant.open(uuid::Uuid::from_u128(1).as_bytes(), CARD_LAST4_FIELD, &sealed).is_err());
 
     // Invalid: capture again after captured -> 409
     let body = json!({"id":"pi_db_1"}).to_string();
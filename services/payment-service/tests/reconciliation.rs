@@ -0,0 +1,124 @@
+use axum::{Router, routing::{get, post}, http::Request, body::{Body, to_bytes}};
+use payment_service::{AppState, payment_handlers::adjust_intent_tip, reconciliation::{get_report, upload_settlement, upload_settlement_csv}};
+use common_auth::{JwtVerifier, JwtConfig};
+use std::sync::Arc;
+use tower::ServiceExt;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Executor};
+
+fn app(db: Option<PgPool>) -> Router {
+    let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
+    let state = AppState { jwt_verifier: verifier, db, pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
+    Router::new()
+        .route("/reconciliation/settlements", post(upload_settlement))
+        .route("/reconciliation/settlements/csv", post(upload_settlement_csv))
+        .route("/reconciliation/reports/:id", get(get_report))
+        .route("/payment_intents/tip", post(adjust_intent_tip))
+        .with_state(state)
+}
+
+const TENANT: &str = "00000000-0000-0000-0000-000000000000";
+
+fn request(method: &str, uri: &str, role: &str, content_type: &str, body: String) -> Request<Body> {
+    request_as(TENANT, method, uri, role, content_type, body)
+}
+
+fn request_as(tenant: &str, method: &str, uri: &str, role: &str, content_type: &str, body: String) -> Request<Body> {
+    Request::builder().uri(uri).method(method)
+        .header("content-type", content_type)
+        .header("X-Tenant-ID", tenant)
+        .header("X-Roles", role)
+        .body(Body::from(body)).unwrap()
+}
+
+#[tokio::test]
+async fn cashiers_and_managers_cannot_reconcile() {
+    let body = json!({"provider":"stub","periodStart":"2026-01-01T00:00:00Z","periodEnd":"2026-01-02T00:00:00Z","lines":[]}).to_string();
+    for role in ["cashier", "manager"] {
+        let resp = app(None).oneshot(request("POST", "/reconciliation/settlements", role, "application/json", body.clone())).await.unwrap();
+        assert_eq!(resp.status().as_u16(), 403);
+        assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "missing_role");
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_csv_reconciliation_report() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes migrations 8002, 8005 and 8008 have been applied.
+    pool.execute(r#"
+        DELETE FROM payment_intents WHERE id LIKE 'pi_recon_%';
+        INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, provider, provider_ref, updated_at, tenant_id) VALUES
+            ('pi_recon_ok', 'o1', 1000, 'USD', 'captured', 'recon-test', 'ch_recon_ok', '2026-03-01T10:00:00Z', '00000000-0000-0000-0000-000000000000'),
+            ('pi_recon_delta', 'o2', 2000, 'USD', 'captured', 'recon-test', 'ch_recon_delta', '2026-03-01T11:00:00Z', '00000000-0000-0000-0000-000000000000'),
+            ('pi_recon_unsettled', 'o3', 3000, 'USD', 'captured', 'recon-test', 'ch_recon_unsettled', '2026-03-01T12:00:00Z', '00000000-0000-0000-0000-000000000000');
+    "#).await.unwrap();
+
+    let csv = "provider_ref,amount,currency\nch_recon_ok,10.00,USD\nch_recon_delta,19.50,USD\nch_recon_unknown,4.00,USD\nch_recon_ok,10.00,USD\n";
+    let uri = "/reconciliation/settlements/csv?provider=recon-test&periodStart=2026-03-01T00:00:00Z&periodEnd=2026-03-02T00:00:00Z";
+    let resp = app(Some(pool.clone())).oneshot(request("POST", uri, "admin", "text/csv", csv.into())).await.unwrap();
+    assert!(resp.status().is_success(), "status {}", resp.status());
+    let report: Value = serde_json::from_slice(&to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(report["source"], "csv");
+    assert_eq!(report["summary"], json!({
+        "matched": 1, "missingPayment": 1, "missingSettlement": 1, "amountDelta": 1, "duplicate": 1,
+        "unmatchedAmountMinor": 50 + 400 + 3000 + 1000
+    }));
+
+    let uri = format!("/reconciliation/reports/{}?status=missing_settlement", report["id"].as_str().unwrap());
+    let resp = app(Some(pool.clone())).oneshot(request("GET", &uri, "admin", "application/json", String::new())).await.unwrap();
+    let report: Value = serde_json::from_slice(&to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+    let items = report["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["paymentIntentId"], "pi_recon_unsettled");
+    // Tips can still move on the payment the provider hasn't settled, but not on settled ones.
+    let tip = |id: &str, tip_minor: i64| request("POST", "/payment_intents/tip", "cashier", "application/json", json!({"id": id, "tipMinor": tip_minor}).to_string());
+    let resp = app(Some(pool.clone())).oneshot(tip("pi_recon_ok", 200)).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 409);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "payment_settled");
+    for tip_minor in [500, 300] {
+        let resp = app(Some(pool.clone())).oneshot(tip("pi_recon_unsettled", tip_minor)).await.unwrap();
+        assert!(resp.status().is_success(), "status {}", resp.status());
+    }
+    // The tip is capped at the sale, whatever tip is already on the intent.
+    let resp = app(Some(pool.clone())).oneshot(tip("pi_recon_unsettled", 3001)).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+    assert_eq!(resp.headers().get("X-Error-Code").unwrap(), "tip_exceeds_sale");
+    let (amount, tip_minor): (i64, i64) = sqlx::query_as("SELECT amount_minor, tip_minor FROM payment_intents WHERE id = 'pi_recon_unsettled'")
+        .fetch_one(&pool).await.unwrap();
+    assert_eq!((amount, tip_minor), (3300, 300));
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_reconciliation_sees_only_the_tenants_payments() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes migrations 8002, 8005, 8006 and 8008 have been applied.
+    const OTHER: &str = "11111111-1111-1111-1111-111111111111";
+    pool.execute(r#"
+        DELETE FROM payment_intents WHERE id LIKE 'pi_tenancy_%';
+        INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, provider, provider_ref, updated_at, tenant_id) VALUES
+            ('pi_tenancy_mine', 'o1', 1000, 'USD', 'captured', 'tenancy-test', 'ch_tenancy_mine', '2026-04-01T10:00:00Z', '00000000-0000-0000-0000-000000000000'),
+            ('pi_tenancy_other', 'o2', 2000, 'USD', 'captured', 'tenancy-test', 'ch_tenancy_other', '2026-04-01T11:00:00Z', '11111111-1111-1111-1111-111111111111');
+    "#).await.unwrap();
+
+    // The other tenant's charge is neither matched by this tenant's report nor reported as unsettled.
+    let csv = "provider_ref,amount,currency\nch_tenancy_mine,10.00,USD\nch_tenancy_other,20.00,USD\n";
+    let uri = "/reconciliation/settlements/csv?provider=tenancy-test&periodStart=2026-04-01T00:00:00Z&periodEnd=2026-04-02T00:00:00Z";
+    let resp = app(Some(pool.clone())).oneshot(request("POST", uri, "admin", "text/csv", csv.into())).await.unwrap();
+    assert!(resp.status().is_success(), "status {}", resp.status());
+    let report: Value = serde_json::from_slice(&to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(report["summary"], json!({
+        "matched": 1, "missingPayment": 1, "missingSettlement": 0, "amountDelta": 0, "duplicate": 0,
+        "unmatchedAmountMinor": 2000
+    }));
+
+    // This tenant's settled payment is locked; the other tenant's is not, whatever this report said.
+    let tip = |tenant: &str, id: &str| request_as(tenant, "POST", "/payment_intents/tip", "cashier", "application/json", json!({"id": id, "tipMinor": 100}).to_string());
+    let resp = app(Some(pool.clone())).oneshot(tip(TENANT, "pi_tenancy_mine")).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 409);
+    let resp = app(Some(pool.clone())).oneshot(tip(OTHER, "pi_tenancy_other")).await.unwrap();
+    assert!(resp.status().is_success(), "status {}", resp.status());
+}
@@ -0,0 +1,47 @@
+use payment_service::retention::{run_retention, RetentionPolicy, TenantRetention};
+use sqlx::{PgPool, Executor};
+use uuid::Uuid;
+
+const TENANT: &str = "00000000-0000-0000-0000-0000000000e1";
+
+async fn card_details(pool: &PgPool, id: &str) -> (bool, bool) {
+    sqlx::query_as("SELECT card_last4_encrypted IS NOT NULL, anonymized_at IS NOT NULL FROM payment_intents WHERE id = $1")
+        .bind(id).fetch_one(pool).await.unwrap()
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_retention_strips_only_settled_intents() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes payment-service migrations (through 8010) have been applied.
+    pool.execute(format!(r#"
+        DELETE FROM disputes WHERE tenant_id = '{TENANT}';
+        DELETE FROM payment_intents WHERE tenant_id = '{TENANT}';
+        INSERT INTO payment_intents (id, order_id, amount_minor, currency, state, tenant_id, card_last4_encrypted, created_at) VALUES
+            ('pi_ret_old', 'o1', 1000, 'USD', 'captured', '{TENANT}', '\x01', now() - interval '3 years'),
+            ('pi_ret_open', 'o2', 1000, 'USD', 'authorized', '{TENANT}', '\x01', now() - interval '3 years'),
+            ('pi_ret_disputed', 'o3', 1000, 'USD', 'captured', '{TENANT}', '\x01', now() - interval '3 years'),
+            ('pi_ret_new', 'o4', 1000, 'USD', 'captured', '{TENANT}', '\x01', now());
+        INSERT INTO disputes (id, provider, provider_dispute_id, provider_ref, payment_intent_id, tenant_id, status, amount_minor, currency, evidence, updated_at) VALUES
+            ('{}', 'stub', 'dp_ret_open', 'ch_ret', 'pi_ret_disputed', '{TENANT}', 'under_review', 1000, 'USD', '{{"note": "open"}}', now() - interval '3 years'),
+            ('{}', 'stub', 'dp_ret_closed', 'ch_ret', 'pi_ret_old', '{TENANT}', 'won', 1000, 'USD', '{{"note": "closed"}}', now() - interval '3 years');
+    "#, Uuid::new_v4(), Uuid::new_v4()).as_str()).await.unwrap();
+    let tenant: Uuid = TENANT.parse().unwrap();
+
+    let mut policy = RetentionPolicy { months: 24, batch_size: 1, dry_run: true };
+    let dry = run_retention(&pool, &policy).await.unwrap();
+    assert_eq!(dry.tenants.get(&tenant), Some(&TenantRetention { payment_intents: 1, disputes: 1 }));
+    assert_eq!(card_details(&pool, "pi_ret_old").await, (true, false), "a dry run changes nothing");
+
+    policy.dry_run = false;
+    let run = run_retention(&pool, &policy).await.unwrap();
+    assert_eq!(run.tenants.get(&tenant), Some(&TenantRetention { payment_intents: 1, disputes: 1 }));
+    assert_eq!(card_details(&pool, "pi_ret_old").await, (false, true));
+    for kept in ["pi_ret_open", "pi_ret_disputed", "pi_ret_new"] {
+        assert_eq!(card_details(&pool, kept).await, (true, false), "{kept}");
+    }
+    let evidence: Vec<(String, bool)> = sqlx::query_as("SELECT provider_dispute_id, evidence IS NOT NULL FROM disputes WHERE tenant_id = $1 ORDER BY provider_dispute_id")
+        .bind(tenant).fetch_all(&pool).await.unwrap();
+    assert_eq!(evidence, [("dp_ret_closed".to_string(), false), ("dp_ret_open".to_string(), true)]);
+}
@@ -0,0 +1,209 @@
+use axum::{Router, routing::{get, post}, http::Request, body::{Body, to_bytes}};
+use payment_service::{AppState, payment_handlers::process_card_payment};
+use payment_service::gateway::{Authorization, PaymentGateway};
+use payment_service::stand_in::{self, get_settings, list_authorizations, put_settings};
+use common_auth::{JwtVerifier, JwtConfig};
+use std::sync::Arc;
+use tower::ServiceExt;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Executor};
+
+const TENANT: &str = "00000000-0000-0000-0000-0000000000f1";
+const ORDER: &str = "00000000-0000-0000-0000-0000000000f2";
+const BIG_ORDER: &str = "00000000-0000-0000-0000-0000000000f3";
+const DECLINED_ORDER: &str = "00000000-0000-0000-0000-0000000000f4";
+
+fn app(db: Option<PgPool>) -> Router {
+    let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
+    let state = AppState { jwt_verifier: verifier, db, pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
+    Router::new()
+        .route("/payments", post(process_card_payment))
+        .route("/stand-in/settings", get(get_settings).put(put_settings))
+        .route("/stand-in/authorizations", get(list_authorizations))
+        .with_state(state)
+}
+
+async fn call(app: &Router, method: &str, uri: &str, role: &str, body: Value) -> (u16, Value) {
+    let req = Request::builder().uri(uri).method(method)
+        .header("content-type", "application/json")
+        .header("X-Tenant-ID", TENANT)
+        .header("X-Roles", role)
+        .body(Body::from(body.to_string())).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status().as_u16();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn payment(order_id: &str, amount: &str) -> Value {
+    json!({"orderId": order_id, "method": "card", "amount": amount})
+}
+
+/// The provider answers every replay the same way.
+struct Provider(Option<Authorization>);
+
+#[async_trait::async_trait]
+impl PaymentGateway for Provider {
+    async fn authorize(&self, _order_id: &str, _amount_minor: i64, _currency: &str) -> anyhow::Result<Authorization> {
+        self.0.clone().ok_or_else(|| anyhow::anyhow!("provider unreachable"))
+    }
+    async fn void(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+    async fn refund(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+}
+
+#[tokio::test]
+async fn outage_without_stand_in_is_503_and_declines_are_409() {
+    // The stub provider is unreachable for amounts ending in .13 and declines .05.
+    let (status, body) = call(&app(None), "POST", "/payments", "cashier", payment(ORDER, "12.13")).await;
+    assert_eq!(status, 503, "{body}");
+    assert_eq!(body["code"], "payment_provider_unavailable");
+    let (status, body) = call(&app(None), "POST", "/payments", "cashier", payment(ORDER, "12.05")).await;
+    assert_eq!(status, 409, "{body}");
+    assert_eq!(body["code"], "card_declined");
+    let (status, body) = call(&app(None), "POST", "/payments", "cashier", payment(ORDER, "12.50")).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["status"], "approved");
+    let (status, body) = call(&app(None), "POST", "/payments", "cashier", json!({"orderId": ORDER, "method": "card", "amount": "12.50", "currency": "US$"})).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "invalid_currency");
+}
+
+#[tokio::test]
+async fn stand_in_endpoints_need_the_stand_in_capability() {
+    for (method, uri) in [("GET", "/stand-in/settings"), ("PUT", "/stand-in/settings"), ("GET", "/stand-in/authorizations")] {
+        let (status, body) = call(&app(None), method, uri, "cashier", json!({"enabled": true, "floorLimitMinor": 2000})).await;
+        assert_eq!(status, 403, "{method} {uri}: {body}");
+        assert_eq!(body["missing_role"], "payment_stand_in", "{method} {uri}: {body}");
+    }
+    // Managers get past the capability check; without a database the request then fails.
+    let (status, _) = call(&app(None), "GET", "/stand-in/settings", "manager", Value::Null).await;
+    assert_eq!(status, 500);
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_stand_in_queue_is_replayed_and_force_declined() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes payment-service migrations (through 8009) have been applied.
+    pool.execute(format!(r#"
+        ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+        DELETE FROM stand_in_authorizations WHERE tenant_id = '{TENANT}';
+        DELETE FROM stand_in_settings WHERE tenant_id = '{TENANT}';
+        DELETE FROM outbox WHERE tenant_id = '{TENANT}';
+    "#).as_str()).await.unwrap();
+    let app = app(Some(pool.clone()));
+
+    let (status, _) = call(&app, "POST", "/payments", "cashier", payment(ORDER, "12.13")).await;
+    assert_eq!(status, 503, "stand-in is off until the tenant enables it");
+    let (status, settings) = call(&app, "PUT", "/stand-in/settings", "admin", json!({"enabled": true, "floorLimitMinor": 2000})).await;
+    assert_eq!(status, 200, "{settings}");
+    assert_eq!(settings["floorLimitMinor"], 2000);
+
+    let (status, queued) = call(&app, "POST", "/payments", "cashier", payment(ORDER, "12.13")).await;
+    assert_eq!(status, 200, "{queued}");
+    assert_eq!(queued["status"], "conditionally_approved");
+    let (_, again) = call(&app, "POST", "/payments", "cashier", payment(ORDER, "12.13")).await;
+    assert_eq!(again["standInId"], queued["standInId"], "a retried payment joins the queued one");
+    let (status, _) = call(&app, "POST", "/payments", "cashier", payment(BIG_ORDER, "25.13")).await;
+    assert_eq!(status, 503, "above the floor limit");
+    let (_, declined) = call(&app, "POST", "/payments", "cashier", json!({"orderId": DECLINED_ORDER, "method": "card", "amount": "8.13", "currency": "cad"})).await;
+    assert_eq!(declined["status"], "conditionally_approved");
+    sqlx::query("UPDATE stand_in_authorizations SET next_attempt_at = now() + interval '1 hour' WHERE order_id = $1::uuid")
+        .bind(DECLINED_ORDER).execute(&pool).await.unwrap();
+
+    // Still down: the entry is pushed back, nothing more is published.
+    let summary = stand_in::process_due(&pool, &Provider(None), 5).await.unwrap();
+    assert_eq!((summary.approved, summary.declined, summary.deferred), (0, 0, 1));
+    let (_, entries) = call(&app, "GET", "/stand-in/authorizations?status=queued", "admin", Value::Null).await;
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+    let currency_of = |order: &str| entries.as_array().unwrap().iter().find(|e| e["orderId"] == order).map(|e| e["currency"].clone());
+    assert_eq!((currency_of(ORDER), currency_of(DECLINED_ORDER)), (Some(json!("USD")), Some(json!("CAD"))), "the request's currency is queued");
+
+    sqlx::query("UPDATE stand_in_authorizations SET next_attempt_at = now() WHERE tenant_id = $1::uuid").bind(TENANT).execute(&pool).await.unwrap();
+    let recovered = Provider(Some(Authorization::Approved { approval_code: "LATE-1".into() }));
+    sqlx::query("UPDATE stand_in_authorizations SET next_attempt_at = now() + interval '1 hour' WHERE order_id = $1::uuid")
+        .bind(DECLINED_ORDER).execute(&pool).await.unwrap();
+    let summary = stand_in::process_due(&pool, &recovered, 5).await.unwrap();
+    assert_eq!(summary.approved, 1);
+
+    sqlx::query("UPDATE stand_in_authorizations SET next_attempt_at = now() WHERE order_id = $1::uuid").bind(DECLINED_ORDER).execute(&pool).await.unwrap();
+    let refused = Provider(Some(Authorization::Declined { reason: "do not honor".into() }));
+    let summary = stand_in::process_due(&pool, &refused, 5).await.unwrap();
+    assert_eq!(summary.declined, 1);
+
+    let (_, entries) = call(&app, "GET", "/stand-in/authorizations", "admin", Value::Null).await;
+    let status_of = |order: &str| entries.as_array().unwrap().iter().find(|e| e["orderId"] == order).map(|e| (e["status"].clone(), e["approvalCode"].clone()));
+    assert_eq!(status_of(ORDER), Some((json!("approved"), json!("LATE-1"))));
+    assert_eq!(status_of(DECLINED_ORDER), Some((json!("declined"), Value::Null)));
+
+    let published: Vec<(String, Value)> = sqlx::query_as("SELECT topic, payload FROM outbox WHERE tenant_id = $1 ORDER BY id")
+        .bind(TENANT).fetch_all(&pool).await.unwrap();
+    let topics: Vec<&str> = published.iter().map(|(topic, _)| topic.as_str()).collect();
+    assert_eq!(topics, ["payment.stand_in", "payment.stand_in", "payment.completed", "payment.failed"], "one stand-in event per queued order");
+    assert_eq!(published[0].1, json!({
+        "schema_version": 1, "stand_in_id": queued["standInId"], "order_id": ORDER, "tenant_id": TENANT,
+        "method": "card", "amount_minor": 1213, "currency": "USD",
+    }));
+    assert_eq!(published[1].1["currency"], "CAD");
+    assert_eq!(published[2].1, json!({"schema_version": 1, "order_id": ORDER, "tenant_id": TENANT, "method": "card", "amount": 12.13}));
+    assert_eq!(published[3].1["reason"], "stand_in_declined: do not honor");
+
+    // The provider is still down when the till retries: it gets the replayed result, not a new stand-in.
+    let (status, body) = call(&app, "POST", "/payments", "cashier", json!({"orderId": DECLINED_ORDER, "method": "card", "amount": "8.13", "currency": "cad"})).await;
+    assert_eq!((status, body["code"].as_str()), (409, Some("card_declined")), "{body}");
+    assert_eq!(body["message"], "stand_in_declined: do not honor");
+    let (status, body) = call(&app, "POST", "/payments", "cashier", payment(ORDER, "12.13")).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!((body["status"].as_str(), body["approval_code"].as_str()), (Some("approved"), Some("LATE-1")));
+    assert_eq!(body["standInId"], queued["standInId"]);
+}
+
+/// Checks, while authorizing, that no transaction holds the entry's row.
+struct LockProbe(PgPool);
+
+#[async_trait::async_trait]
+impl PaymentGateway for LockProbe {
+    async fn authorize(&self, order_id: &str, _amount_minor: i64, _currency: &str) -> anyhow::Result<Authorization> {
+        let mut tx = self.0.begin().await?;
+        let status: String = sqlx::query_scalar("SELECT status FROM stand_in_authorizations WHERE order_id = $1::uuid FOR UPDATE NOWAIT")
+            .bind(order_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        assert_eq!(status, "processing", "the entry is leased while the provider is called");
+        Ok(Authorization::Approved { approval_code: "PROBE".into() })
+    }
+    async fn void(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+    async fn refund(&self, _provider: &str, _provider_ref: &str) -> anyhow::Result<Option<String>> { Ok(None) }
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_replays_lease_entries_instead_of_holding_row_locks() {
+    const LEASE_TENANT: &str = "00000000-0000-0000-0000-0000000000e1";
+    const STALE_ORDER: &str = "00000000-0000-0000-0000-0000000000e2";
+    const LEASED_ORDER: &str = "00000000-0000-0000-0000-0000000000e3";
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes payment-service migrations (through 8011) have been applied.
+    pool.execute(format!(r#"
+        ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+        DELETE FROM stand_in_authorizations WHERE tenant_id = '{LEASE_TENANT}';
+        DELETE FROM outbox WHERE tenant_id = '{LEASE_TENANT}';
+        INSERT INTO stand_in_authorizations (id, tenant_id, order_id, method, amount_minor, currency, status, locked_until)
+        VALUES (gen_random_uuid(), '{LEASE_TENANT}', '{STALE_ORDER}', 'card', 500, 'USD', 'processing', now() - interval '1 minute'),
+               (gen_random_uuid(), '{LEASE_TENANT}', '{LEASED_ORDER}', 'card', 700, 'USD', 'processing', now() + interval '5 minutes');
+    "#).as_str()).await.unwrap();
+
+    // The entry whose replay died is taken over; the one another replay holds is left alone.
+    let summary = stand_in::process_due(&pool, &LockProbe(pool.clone()), 5).await.unwrap();
+    assert_eq!((summary.approved, summary.declined, summary.deferred), (1, 0, 0));
+    let rows: Vec<(String, String, Option<String>, bool)> = sqlx::query_as(
+        "SELECT order_id::text, status, approval_code, locked_until IS NULL FROM stand_in_authorizations WHERE tenant_id = $1::uuid ORDER BY order_id",
+    )
+    .bind(LEASE_TENANT).fetch_all(&pool).await.unwrap();
+    assert_eq!(rows, vec![
+        (STALE_ORDER.to_string(), "approved".to_string(), Some("PROBE".to_string()), true),
+        (LEASED_ORDER.to_string(), "processing".to_string(), None, false),
+    ]);
+}
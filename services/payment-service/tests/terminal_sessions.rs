@@ -0,0 +1,104 @@
+use axum::{Router, routing::{get, post}, http::Request, body::{Body, to_bytes}};
+use payment_service::{AppState, terminal::{cancel_session, create_session, get_session, terminal_webhook}};
+use common_auth::{JwtVerifier, JwtConfig};
+use std::sync::Arc;
+use tower::ServiceExt;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Executor};
+
+const TENANT: &str = "00000000-0000-0000-0000-0000000000e1";
+const ORDER: &str = "00000000-0000-0000-0000-0000000000e2";
+const OTHER_ORDER: &str = "00000000-0000-0000-0000-0000000000e3";
+
+fn app(db: Option<PgPool>) -> Router {
+    let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
+    let state = AppState { jwt_verifier: verifier, db, pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
+    // Signature checks are covered by webhook_middleware.rs; this router exercises the handlers only.
+    Router::new()
+        .route("/terminal/sessions", post(create_session))
+        .route("/terminal/sessions/:id", get(get_session))
+        .route("/terminal/sessions/:id/cancel", post(cancel_session))
+        .route("/webhooks/terminal", post(terminal_webhook))
+        .with_state(state)
+}
+
+async fn call(app: &Router, method: &str, uri: &str, role: &str, body: Value) -> (u16, Value) {
+    let req = Request::builder().uri(uri).method(method)
+        .header("content-type", "application/json")
+        .header("X-Tenant-ID", TENANT)
+        .header("X-Roles", role)
+        .body(Body::from(body.to_string())).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status().as_u16();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn only_payment_roles_can_start_terminal_sessions() {
+    let body = json!({"orderId": ORDER, "terminalId": "lane-1", "amountMinor": 1000, "currency": "USD"});
+    for role in ["inventory", "support"] {
+        let (status, _) = call(&app(None), "POST", "/terminal/sessions", role, body.clone()).await;
+        assert_eq!(status, 403, "{role}");
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn db_backed_terminal_payment_completes_order() {
+    let dsn = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for this ignored test");
+    let pool = PgPool::connect(&dsn).await.unwrap();
+    // Assumes payment-service migrations (through 8007) have been applied. The simulator answers
+    // immediately unless TERMINAL_SIMULATOR_DELAY_MS is set.
+    pool.execute(format!(r#"
+        ALTER TABLE outbox ADD COLUMN IF NOT EXISTS message_key TEXT;
+        DELETE FROM terminal_sessions WHERE tenant_id = '{TENANT}';
+        DELETE FROM payment_intents WHERE tenant_id = '{TENANT}';
+        DELETE FROM outbox WHERE tenant_id = '{TENANT}';
+    "#).as_str()).await.unwrap();
+    let app = app(Some(pool.clone()));
+
+    // Cents of .05 make the simulator decline; the order stays open and nothing is published.
+    let (status, declined) = call(&app, "POST", "/terminal/sessions", "cashier", json!({"orderId": ORDER, "terminalId": "lane-1", "amountMinor": 1205, "currency": "usd"})).await;
+    assert_eq!(status, 200, "{declined}");
+    assert_eq!(declined["status"], "pending");
+    let (_, declined) = call(&app, "GET", &format!("/terminal/sessions/{}", declined["id"].as_str().unwrap()), "cashier", Value::Null).await;
+    assert_eq!((declined["status"].as_str(), declined["failureReason"].as_str()), (Some("declined"), Some("insufficient funds")));
+
+    let (_, session) = call(&app, "POST", "/terminal/sessions", "cashier", json!({"orderId": ORDER, "terminalId": "lane-1", "amountMinor": 1250, "currency": "USD"})).await;
+    let id = session["id"].as_str().unwrap().to_string();
+    let (status, busy) = call(&app, "POST", "/terminal/sessions", "cashier", json!({"orderId": OTHER_ORDER, "terminalId": "lane-1", "amountMinor": 500, "currency": "USD"})).await;
+    assert_eq!(status, 409, "{busy}");
+
+    // The terminal's callback and the POS poll race; only the first one counts.
+    let callback = json!({"driver": "simulator", "driverRef": session["driverRef"], "outcome": "approved", "approvalCode": "CB-1", "cardBrand": "mastercard", "cardLast4": "4444"});
+    let (status, hook) = call(&app, "POST", "/webhooks/terminal", "", callback.clone()).await;
+    assert_eq!(status, 200, "{hook}");
+    assert_eq!((hook["status"].as_str(), hook["applied"].as_bool()), (Some("approved"), Some(true)));
+    let (_, again) = call(&app, "POST", "/webhooks/terminal", "", callback).await;
+    assert_eq!(again["applied"], false);
+
+    let (_, approved) = call(&app, "GET", &format!("/terminal/sessions/{id}"), "cashier", Value::Null).await;
+    assert_eq!(approved["status"], "approved");
+    assert_eq!(approved["approvalCode"], "CB-1");
+    assert_eq!(approved["paymentIntentId"], id.as_str());
+    let (status, _) = call(&app, "POST", &format!("/terminal/sessions/{id}/cancel"), "cashier", Value::Null).await;
+    assert_eq!(status, 409);
+    let (status, _) = call(&app, "POST", "/terminal/sessions", "cashier", json!({"orderId": ORDER, "terminalId": "lane-2", "amountMinor": 1250, "currency": "USD"})).await;
+    assert_eq!(status, 409, "an approved order can't be charged again");
+
+    let intent: (String, String, String) = sqlx::query_as("SELECT state, provider, order_id FROM payment_intents WHERE id = $1")
+        .bind(&id).fetch_one(&pool).await.unwrap();
+    assert_eq!(intent, ("captured".to_string(), "simulator".to_string(), ORDER.to_string()));
+    let published: Vec<(String, Value)> = sqlx::query_as("SELECT topic, payload FROM outbox WHERE tenant_id = $1 ORDER BY id")
+        .bind(TENANT).fetch_all(&pool).await.unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].0, "payment.completed");
+    assert_eq!(published[0].1, json!({"schema_version": 1, "order_id": ORDER, "tenant_id": TENANT, "method": "card", "amount": 12.5}));
+
+    // Without a simulator delay the terminal has already answered, so cancel records that result.
+    let (_, other) = call(&app, "POST", "/terminal/sessions", "cashier", json!({"orderId": OTHER_ORDER, "terminalId": "lane-1", "amountMinor": 500, "currency": "USD"})).await;
+    let (status, cancelled) = call(&app, "POST", &format!("/terminal/sessions/{}/cancel", other["id"].as_str().unwrap()), "cashier", Value::Null).await;
+    assert_eq!(status, 200, "{cancelled}");
+    assert!(matches!(cancelled["status"].as_str(), Some("cancelled" | "approved")));
+}
@@ -9,7 +9,7 @@ use sha2::{Sha256, Digest};
 
 fn test_router() -> Router {
     let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
-    let state = AppState { jwt_verifier: verifier, db: None, #[cfg(feature="kafka")] audit_producer: None };
+    let state = AppState { jwt_verifier: verifier, db: None, pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
     async fn ok_handler(body: String) -> impl IntoResponse { (axum::http::StatusCode::OK, body) }
     Router::new()
         .route("/webhooks/test", post(ok_handler))
@@ -99,7 +99,7 @@ async fn webhook_timestamp_skew_401() {
 
 async fn app_with_db(db: sqlx::PgPool) -> Router {
     let verifier = Arc::new(JwtVerifier::new(JwtConfig::new("issuer","aud")));
-    let state = AppState { jwt_verifier: verifier, db: Some(db), #[cfg(feature="kafka")] audit_producer: None };
+    let state = AppState { jwt_verifier: verifier, db: Some(db), pii_key: None, retry_policy: Default::default(), #[cfg(feature="kafka")] audit_producer: None };
     async fn ok_handler(body: String) -> impl IntoResponse { (axum::http::StatusCode::OK, body) }
     Router::new()
         .route("/webhooks/test", post(ok_handler))
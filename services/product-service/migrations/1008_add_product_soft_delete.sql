@@ -0,0 +1,9 @@
+-- Soft delete: products stay referenced by historical orders and analytics, so DELETE only hides them
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ NULL;
+
+-- SKU uniqueness only applies to live products so a deleted SKU can be reused
+DROP INDEX IF EXISTS idx_products_tenant_sku_unique;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_products_tenant_sku_unique
+  ON products(tenant_id, sku)
+  WHERE sku IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,25 @@
+-- One row per field a product audit entry changed, so "who changed the price and when" is a
+-- filter instead of a scan over the JSON snapshots in product_audit_log.changes.
+CREATE TABLE IF NOT EXISTS product_field_changes (
+  audit_id UUID NOT NULL REFERENCES product_audit_log(id) ON DELETE CASCADE,
+  tenant_id UUID NOT NULL,
+  product_id UUID NOT NULL,
+  field TEXT NOT NULL,
+  old_value JSONB NULL,
+  new_value JSONB NULL,
+  PRIMARY KEY (audit_id, field)
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_field_changes_product_field
+  ON product_field_changes (tenant_id, product_id, field);
+
+-- Backfill from existing entries that carry full before/after snapshots.
+INSERT INTO product_field_changes (audit_id, tenant_id, product_id, field, old_value, new_value)
+SELECT l.id, l.tenant_id, l.product_id, f.key, l.changes->'before'->f.key, f.value
+FROM product_audit_log l
+CROSS JOIN LATERAL jsonb_each(l.changes->'after') AS f(key, value)
+WHERE l.action IN ('updated', 'restored')
+  AND jsonb_typeof(l.changes->'before') = 'object'
+  AND f.key NOT IN ('id', 'tenant_id', 'version', 'image_url')
+  AND (l.changes->'before'->f.key) IS DISTINCT FROM f.value
+ON CONFLICT DO NOTHING;
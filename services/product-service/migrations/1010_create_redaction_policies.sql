@@ -0,0 +1,11 @@
+-- Per-tenant view redaction rules: a field (dotted path) is hidden from viewers below min_role
+-- unless they hold capability. Tenants without rows use AUDIT_VIEW_REDACTION_PATHS (Admin only).
+CREATE TABLE IF NOT EXISTS redaction_policies (
+  tenant_id UUID NOT NULL,
+  field TEXT NOT NULL,
+  min_role TEXT NULL,
+  capability TEXT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (tenant_id, field),
+  CHECK (min_role IS NOT NULL OR capability IS NOT NULL)
+);
@@ -0,0 +1,74 @@
+-- Per-tenant change log behind GET /products/sync. Sequence numbers come from a per-tenant counter
+-- row whose lock is held until the writing transaction commits, so they become visible in order
+-- and a terminal that has synced up to N never later misses a change numbered N or below.
+CREATE TABLE IF NOT EXISTS product_change_seq (
+  tenant_id UUID PRIMARY KEY,
+  last_seq BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS product_changes (
+  tenant_id UUID NOT NULL,
+  seq BIGINT NOT NULL,
+  product_id UUID NOT NULL,
+  change TEXT NOT NULL CHECK (change IN ('created', 'updated', 'price_changed', 'deleted', 'restored')),
+  changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (tenant_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_changes_product
+  ON product_changes (tenant_id, product_id, seq DESC);
+
+-- Existing products start the log, so a full sync after the upgrade still sees the whole catalog.
+INSERT INTO product_changes (tenant_id, seq, product_id, change)
+SELECT tenant_id,
+       ROW_NUMBER() OVER (PARTITION BY tenant_id ORDER BY id),
+       id,
+       CASE WHEN deleted_at IS NULL THEN 'created' ELSE 'deleted' END
+FROM products
+ON CONFLICT DO NOTHING;
+
+INSERT INTO product_change_seq (tenant_id, last_seq)
+SELECT tenant_id, MAX(seq) FROM product_changes GROUP BY tenant_id
+ON CONFLICT (tenant_id) DO UPDATE SET last_seq = GREATEST(product_change_seq.last_seq, EXCLUDED.last_seq);
+
+CREATE OR REPLACE FUNCTION record_product_change() RETURNS trigger AS $$
+DECLARE
+  row_tenant UUID;
+  row_product UUID;
+  kind TEXT;
+  next_seq BIGINT;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    row_tenant := OLD.tenant_id;
+    row_product := OLD.id;
+    kind := 'deleted';
+  ELSE
+    row_tenant := NEW.tenant_id;
+    row_product := NEW.id;
+    IF TG_OP = 'INSERT' THEN
+      kind := 'created';
+    ELSIF OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN
+      kind := 'deleted';
+    ELSIF OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+      kind := 'restored';
+    ELSIF OLD.price IS DISTINCT FROM NEW.price THEN
+      kind := 'price_changed';
+    ELSE
+      kind := 'updated';
+    END IF;
+  END IF;
+
+  INSERT INTO product_change_seq AS s (tenant_id, last_seq) VALUES (row_tenant, 1)
+  ON CONFLICT (tenant_id) DO UPDATE SET last_seq = s.last_seq + 1
+  RETURNING last_seq INTO next_seq;
+
+  INSERT INTO product_changes (tenant_id, seq, product_id, change)
+  VALUES (row_tenant, next_seq, row_product, kind);
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS products_change_log ON products;
+CREATE TRIGGER products_change_log
+  AFTER INSERT OR UPDATE OR DELETE ON products
+  FOR EACH ROW EXECUTE FUNCTION record_product_change();
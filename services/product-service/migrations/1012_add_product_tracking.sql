@@ -0,0 +1,5 @@
+-- Serial/lot capture that inventory-service enforces for a product: 'serial' products need one
+-- serial per unit received or sold, 'lot' products need a lot code on receipt.
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS tracking TEXT NOT NULL DEFAULT 'none'
+  CHECK (tracking IN ('none', 'serial', 'lot'));
@@ -0,0 +1,8 @@
+-- Weighed products: price is per uom ('kg' or 'lb'); 'each' products are counted as before.
+-- tare_weight is the default container weight (same unit) subtracted from scale readings.
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS uom TEXT NOT NULL DEFAULT 'each'
+  CHECK (uom IN ('each', 'kg', 'lb'));
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS tare_weight NUMERIC(12,3) NULL
+  CHECK (tare_weight IS NULL OR tare_weight >= 0);
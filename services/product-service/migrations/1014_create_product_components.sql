@@ -0,0 +1,19 @@
+-- Bundles and recipes: a product whose sale consumes other products' stock. `quantity` is per
+-- bundle sold, in the component's unit (three decimals for kg/lb components, whole units for
+-- `each`). Components cannot be bundles themselves; product-service enforces one level.
+CREATE TABLE IF NOT EXISTS product_components (
+  tenant_id UUID NOT NULL,
+  bundle_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  component_id UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+  quantity NUMERIC(12,3) NOT NULL CHECK (quantity > 0),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (tenant_id, bundle_id, component_id),
+  CHECK (bundle_id <> component_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_components_component
+  ON product_components (tenant_id, component_id);
+
+-- Unit cost, rolled up across a bundle's components to show its margin.
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS cost NUMERIC(12,2) NULL CHECK (cost IS NULL OR cost >= 0);
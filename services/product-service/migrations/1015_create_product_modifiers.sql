@@ -0,0 +1,28 @@
+-- Menu modifiers: groups of options chosen on an order line ("Choose a size", "Add toppings").
+-- A group with min_select > 0 is forced; order-service refuses lines that leave it unanswered.
+-- price_delta is added to the line's unit price and may be negative.
+CREATE TABLE IF NOT EXISTS product_modifier_groups (
+  id UUID PRIMARY KEY,
+  tenant_id UUID NOT NULL,
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  min_select INT NOT NULL DEFAULT 0 CHECK (min_select >= 0),
+  max_select INT NOT NULL CHECK (max_select >= 1 AND max_select >= min_select),
+  position INT NOT NULL DEFAULT 0,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_modifier_groups_product
+  ON product_modifier_groups (tenant_id, product_id, position);
+
+CREATE TABLE IF NOT EXISTS product_modifiers (
+  id UUID PRIMARY KEY,
+  tenant_id UUID NOT NULL,
+  group_id UUID NOT NULL REFERENCES product_modifier_groups(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  price_delta NUMERIC(12,2) NOT NULL DEFAULT 0,
+  position INT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_modifiers_group
+  ON product_modifiers (tenant_id, group_id, position);
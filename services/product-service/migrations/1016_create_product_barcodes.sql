@@ -0,0 +1,19 @@
+-- Barcodes: any number per product, unique per tenant among live rows. A soft-deleted
+-- product's barcodes are released with it (deleted_at) and claimed back on restore, as its SKU is.
+CREATE TABLE IF NOT EXISTS product_barcodes (
+  tenant_id UUID NOT NULL,
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  barcode TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  deleted_at TIMESTAMPTZ NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_product_barcodes_tenant_barcode_unique
+  ON product_barcodes (tenant_id, barcode)
+  WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_product_barcodes_product
+  ON product_barcodes (tenant_id, product_id);
+
+-- Merging duplicates soft-deletes the duplicate and points it at the product it was merged into.
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS merged_into UUID NULL REFERENCES products(id);
@@ -1,26 +1,37 @@
 use std::sync::Arc;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
 use sqlx::PgPool;
+use common_db::{ReadPool, WritePool};
 use common_auth::JwtVerifier;
 use axum::extract::FromRef;
+use crate::redaction_policy::RedactionPolicyCache;
 
 /// Shared application state used by handlers (moved from main.rs so tests & library code can reference it).
 #[derive(Clone)]
 pub struct AppState {
-    pub(crate) db: PgPool,
+    pub(crate) db: WritePool,
+    /// Replica-backed pool for read-only list/search handlers; falls back to the primary.
+    pub(crate) read_db: ReadPool,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] pub(crate) kafka_producer: FutureProducer,
     pub(crate) jwt_verifier: Arc<JwtVerifier>,
+    /// Per-tenant view redaction rules, loaded lazily and cached with a TTL.
+    pub(crate) redaction_policies: RedactionPolicyCache,
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))] pub(crate) audit_producer: Option<Arc<common_audit::BufferedAuditProducer<common_audit::KafkaAuditSink>>>,
 }
 
 impl AppState {
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     pub fn new(db: PgPool, kafka_producer: FutureProducer, jwt_verifier: Arc<JwtVerifier>, audit_producer: Option<Arc<common_audit::BufferedAuditProducer<common_audit::KafkaAuditSink>>>) -> Self {
-        Self { db, kafka_producer, jwt_verifier, audit_producer }
+        Self { read_db: ReadPool::primary_only(db.clone()), db: WritePool::new(db), kafka_producer, jwt_verifier, redaction_policies: RedactionPolicyCache::default(), audit_producer }
     }
     #[cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
     pub fn new(db: PgPool, _kafka_producer: (), jwt_verifier: Arc<JwtVerifier>, _audit_producer: Option<Arc<()>>) -> Self {
-        Self { db, jwt_verifier }
+        Self { read_db: ReadPool::primary_only(db.clone()), db: WritePool::new(db), jwt_verifier, redaction_policies: RedactionPolicyCache::default() }
+    }
+    /// Route read-only handlers through `read_db` (e.g. one built with [`ReadPool::from_env`]).
+    pub fn with_read_pool(mut self, read_db: ReadPool) -> Self {
+        self.read_db = read_db;
+        self
     }
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     pub fn audit_buffer(&self) -> Option<&Arc<common_audit::BufferedAuditProducer<common_audit::KafkaAuditSink>>> { self.audit_producer.as_ref() }
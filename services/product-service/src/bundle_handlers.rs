@@ -0,0 +1,297 @@
+//! Bundles and recipes (bill of materials).
+//!
+//! A bundle is an ordinary product with rows in `product_components` (migration `1014`): a meal
+//! combo lists the items it includes, a cocktail the measures it pours. The bundle keeps its own
+//! price; selling it consumes the components' stock, which inventory-service expands when it
+//! reserves and completes the order. Bundles are one level deep, so a component is never itself a
+//! bundle.
+
+use crate::app_state::AppState;
+use crate::product_handlers::{record_product_audit, AuditActor};
+use crate::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use common_money::measure::{extend_price, normalize_measure, UnitOfMeasure};
+use common_money::{Money, RoundingContext};
+use common_security::{Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Components one bundle may list.
+const MAX_COMPONENTS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct ComponentInput {
+    pub component_id: Uuid,
+    /// Per bundle sold, in the component's unit: whole units for `each`, up to three decimals for
+    /// `kg` / `lb`.
+    pub quantity: BigDecimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutComponentsRequest {
+    /// Replaces the bundle's components; an empty list turns it back into a plain product.
+    pub components: Vec<ComponentInput>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BundleComponent {
+    pub component_id: Uuid,
+    pub name: String,
+    pub sku: Option<String>,
+    pub uom: String,
+    pub quantity: BigDecimal,
+    pub unit_cost: Option<BigDecimal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleComponentLine {
+    #[serde(flatten)]
+    pub component: BundleComponent,
+    /// `unit_cost` times `quantity`; absent when the component has no cost.
+    pub extended_cost: Option<Money>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleDetail {
+    pub bundle_id: Uuid,
+    pub price: Money,
+    pub components: Vec<BundleComponentLine>,
+    /// Cost of one bundle from its components; absent while any component has no cost.
+    pub component_cost: Option<Money>,
+    /// `price` minus `component_cost`.
+    pub margin: Option<Money>,
+    /// Components without a unit cost, which keep the rollup from being complete.
+    pub missing_cost: Vec<Uuid>,
+}
+
+/// Extended cost of each line and, when every line has a cost, their total.
+fn rollup_cost(components: &[BundleComponent], rounding: &RoundingContext) -> (Vec<Option<Money>>, Option<Money>) {
+    let extended: Vec<Option<Money>> = components
+        .iter()
+        .map(|c| c.unit_cost.as_ref().map(|cost| extend_price(&Money::new(cost.clone()), &c.quantity, rounding)))
+        .collect();
+    let total = extended.iter().cloned().sum::<Option<Money>>();
+    (extended, total)
+}
+
+/// Component ids and quantities, as written to the product audit log.
+fn component_summary<'a>(lines: impl Iterator<Item = &'a BundleComponent>) -> serde_json::Value {
+    lines.map(|c| json!({ "component_id": c.component_id, "quantity": c.quantity })).collect()
+}
+
+fn bad_request(code: &'static str, trace_id: Option<Uuid>, message: String) -> ApiError {
+    ApiError::BadRequest { code, trace_id, message: Some(message) }
+}
+
+async fn load_components(db: &PgPool, tenant_id: Uuid, bundle_id: Uuid) -> Result<Vec<BundleComponent>, sqlx::Error> {
+    sqlx::query_as::<_, BundleComponent>(
+        "SELECT pc.component_id, p.name, p.sku, p.uom, pc.quantity, p.cost AS unit_cost
+         FROM product_components pc JOIN products p ON p.id = pc.component_id AND p.tenant_id = pc.tenant_id
+         WHERE pc.tenant_id = $1 AND pc.bundle_id = $2
+         ORDER BY p.name, pc.component_id",
+    )
+    .bind(tenant_id)
+    .bind(bundle_id)
+    .fetch_all(db)
+    .await
+}
+
+async fn bundle_detail(db: &PgPool, tenant_id: Uuid, bundle_id: Uuid, price: BigDecimal) -> Result<BundleDetail, sqlx::Error> {
+    let components = load_components(db, tenant_id, bundle_id).await?;
+    let (extended, component_cost) = rollup_cost(&components, &RoundingContext::global());
+    let price = Money::new(price);
+    let margin = component_cost.as_ref().map(|cost| &price - cost.clone());
+    let missing_cost = components.iter().filter(|c| c.unit_cost.is_none()).map(|c| c.component_id).collect();
+    Ok(BundleDetail {
+        bundle_id,
+        price,
+        components: components
+            .into_iter()
+            .zip(extended)
+            .map(|(component, extended_cost)| BundleComponentLine { component, extended_cost })
+            .collect(),
+        component_cost,
+        margin,
+        missing_cost,
+    })
+}
+
+/// Price and unit of a live product.
+async fn product_basics(db: &PgPool, tenant_id: Uuid, product_id: Uuid) -> Result<Option<(BigDecimal, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (BigDecimal, String)>(
+        "SELECT price, uom FROM products WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
+    )
+    .bind(product_id)
+    .bind(tenant_id)
+    .fetch_optional(db)
+    .await
+}
+
+/// `GET /products/:id/components`: the bundle's components with their cost rollup.
+pub async fn get_components(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(bundle_id): Path<Uuid>,
+) -> Result<Json<BundleDetail>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let db = state.db.pool();
+    let (price, _) = product_basics(db, sec.tenant_id, bundle_id)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .ok_or(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id })?;
+    let detail = bundle_detail(db, sec.tenant_id, bundle_id, price).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(detail))
+}
+
+/// `PUT /products/:id/components`: replace the bundle's components.
+pub async fn put_components(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(bundle_id): Path<Uuid>,
+    Json(req): Json<PutComponentsRequest>,
+) -> Result<Json<BundleDetail>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let (tenant_id, trace_id) = (sec.tenant_id, sec.trace_id);
+    if req.components.len() > MAX_COMPONENTS {
+        return Err(bad_request("too_many_components", trace_id, format!("A bundle is limited to {MAX_COMPONENTS} components")));
+    }
+    let db = state.db.pool();
+    let (price, bundle_uom) = product_basics(db, tenant_id, bundle_id)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?
+        .ok_or(ApiError::NotFound { code: "product_not_found", trace_id })?;
+
+    let mut seen = HashSet::new();
+    for line in &req.components {
+        if line.component_id == bundle_id {
+            return Err(bad_request("self_component", trace_id, "A bundle cannot contain itself".into()));
+        }
+        if !seen.insert(line.component_id) {
+            return Err(bad_request("duplicate_component", trace_id, format!("Component {} is listed more than once", line.component_id)));
+        }
+    }
+
+    let before = load_components(db, tenant_id, bundle_id).await.map_err(|e| ApiError::internal(e, trace_id))?;
+    let mut tx = db.begin().await.map_err(|e| ApiError::internal(e, trace_id))?;
+    if !req.components.is_empty() {
+        if UnitOfMeasure::parse(&bundle_uom).is_some_and(UnitOfMeasure::is_measured) {
+            return Err(bad_request("invalid_bundle", trace_id, "Weighed products cannot be bundles".into()));
+        }
+        let is_component: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM product_components WHERE tenant_id = $1 AND component_id = $2)",
+        )
+        .bind(tenant_id)
+        .bind(bundle_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+        if is_component {
+            return Err(bad_request("nested_bundle", trace_id, "This product is a component of another bundle".into()));
+        }
+    }
+
+    let ids: Vec<Uuid> = req.components.iter().map(|c| c.component_id).collect();
+    let rows = sqlx::query_as::<_, (Uuid, String, bool)>(
+        "SELECT p.id, p.uom, EXISTS (SELECT 1 FROM product_components pc WHERE pc.tenant_id = p.tenant_id AND pc.bundle_id = p.id)
+         FROM products p WHERE p.tenant_id = $1 AND p.id = ANY($2) AND p.deleted_at IS NULL",
+    )
+    .bind(tenant_id)
+    .bind(&ids)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, trace_id))?;
+
+    let mut normalized: Vec<(Uuid, BigDecimal)> = Vec::with_capacity(req.components.len());
+    for line in &req.components {
+        let (_, uom, is_bundle) = rows
+            .iter()
+            .find(|(id, _, _)| *id == line.component_id)
+            .ok_or_else(|| bad_request("component_not_found", trace_id, format!("Component {} does not exist", line.component_id)))?;
+        if *is_bundle {
+            return Err(bad_request("nested_bundle", trace_id, format!("Component {} is itself a bundle", line.component_id)));
+        }
+        let quantity = normalize_measure(&line.quantity);
+        let measured = UnitOfMeasure::parse(uom).is_some_and(UnitOfMeasure::is_measured);
+        if quantity <= BigDecimal::from(0) || (!measured && !quantity.is_integer()) {
+            return Err(bad_request(
+                "invalid_component_quantity",
+                trace_id,
+                format!("Quantity for component {} must be positive{}", line.component_id, if measured { "" } else { " and whole" }),
+            ));
+        }
+        normalized.push((line.component_id, quantity));
+    }
+
+    sqlx::query("DELETE FROM product_components WHERE tenant_id = $1 AND bundle_id = $2")
+        .bind(tenant_id)
+        .bind(bundle_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+    for (component_id, quantity) in &normalized {
+        sqlx::query("INSERT INTO product_components (tenant_id, bundle_id, component_id, quantity) VALUES ($1, $2, $3, $4)")
+            .bind(tenant_id)
+            .bind(bundle_id)
+            .bind(component_id)
+            .bind(quantity)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?;
+    }
+    tx.commit().await.map_err(|e| ApiError::internal(e, trace_id))?;
+
+    let detail = bundle_detail(db, tenant_id, bundle_id, price).await.map_err(|e| ApiError::internal(e, trace_id))?;
+    let changes = json!({
+        "before": { "components": component_summary(before.iter()) },
+        "after": { "components": component_summary(detail.components.iter().map(|line| &line.component)) },
+    });
+    let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
+    record_product_audit(db, &actor, bundle_id, tenant_id, "components_updated", changes).await;
+    Ok(Json(detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_money::RoundingMode;
+    use std::str::FromStr;
+
+    fn component(quantity: &str, unit_cost: Option<&str>) -> BundleComponent {
+        BundleComponent {
+            component_id: Uuid::new_v4(),
+            name: "c".into(),
+            sku: None,
+            uom: "each".into(),
+            quantity: BigDecimal::from_str(quantity).unwrap(),
+            unit_cost: unit_cost.map(|c| BigDecimal::from_str(c).unwrap()),
+        }
+    }
+
+    #[test]
+    fn rollup_sums_extended_costs_rounded_per_line() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        // 2 buns at 0.35, 0.125 kg of beef at 11.99/kg (1.49875)
+        let (lines, total) = rollup_cost(&[component("2", Some("0.35")), component("0.125", Some("11.99"))], &rounding);
+        assert_eq!(lines[1].as_ref().unwrap().as_cents(), 150);
+        assert_eq!(total.unwrap().as_cents(), 220);
+    }
+
+    #[test]
+    fn rollup_is_incomplete_while_a_component_has_no_cost() {
+        let rounding = RoundingContext::new(RoundingMode::HalfUp);
+        let (lines, total) = rollup_cost(&[component("1", Some("1.00")), component("1", None)], &rounding);
+        assert!(lines[1].is_none());
+        assert!(total.is_none());
+    }
+}
@@ -0,0 +1,229 @@
+//! Offline catalog sync for POS terminals.
+//!
+//! Every insert, update and delete on `products` appends a row to the per-tenant `product_changes`
+//! log (migration `1011`). Sequence numbers are handed out under a per-tenant counter lock, so they
+//! become visible in commit order and a terminal holding version N never later misses a change
+//! numbered N or below. `GET /products/sync?since_version=N` returns the latest state of every
+//! product changed after N, one delta per product, with tombstones for deleted ones.
+
+use crate::app_state::AppState;
+use crate::product_handlers::Product;
+use crate::ApiError;
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use common_security::SecurityCtxExtractor;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const DEFAULT_SYNC_LIMIT: i64 = 500;
+const MAX_SYNC_LIMIT: i64 = 1000;
+
+/// Fields a terminal does not need in its offline copy.
+const OMITTED_FIELDS: &[&str] = &["tenant_id", "image_url", "cost", "deleted_at"];
+
+#[derive(Deserialize, Default)]
+pub struct SyncQuery {
+    /// Version returned by the previous sync; 0 (the default) requests the full catalog.
+    #[serde(default)]
+    pub since_version: i64,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CatalogSync {
+    /// Pass back as `since_version` on the next call.
+    pub version: i64,
+    /// More changes are pending; call again with `version` straight away.
+    pub has_more: bool,
+    pub changes: Vec<CatalogDelta>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CatalogDelta {
+    Upsert { seq: i64, change: String, product: Value },
+    Delete { seq: i64, id: Uuid },
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ChangeRow {
+    seq: i64,
+    product_id: Uuid,
+    change: String,
+}
+
+/// Latest change per product in `(since, head]`, oldest first.
+const CHANGES_SQL: &str = "SELECT seq, product_id, change FROM (
+        SELECT DISTINCT ON (product_id) seq, product_id, change
+        FROM product_changes
+        WHERE tenant_id = $1 AND seq > $2 AND seq <= $3
+        ORDER BY product_id, seq DESC
+    ) latest
+    ORDER BY seq
+    LIMIT $4";
+
+pub async fn sync_products(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(q): Query<SyncQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    if q.since_version < 0 {
+        return Err(ApiError::BadRequest {
+            code: "invalid_since_version",
+            trace_id: sec.trace_id,
+            message: Some("since_version must not be negative".into()),
+        });
+    }
+    let tenant_id = sec.tenant_id;
+    let limit = q.limit.unwrap_or(DEFAULT_SYNC_LIMIT).clamp(1, MAX_SYNC_LIMIT);
+    let read_db = state.read_db.get().await;
+
+    // Bounding the page by the committed head keeps a concurrent writer from slipping a lower
+    // sequence number in behind the version handed back.
+    let head: Option<i64> = sqlx::query_scalar("SELECT last_seq FROM product_change_seq WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .fetch_optional(read_db)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let head = head.unwrap_or(0).max(q.since_version);
+
+    let mut changes = sqlx::query_as::<_, ChangeRow>(CHANGES_SQL)
+        .bind(tenant_id)
+        .bind(q.since_version)
+        .bind(head)
+        .bind(limit + 1)
+        .fetch_all(read_db)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let has_more = changes.len() as i64 > limit;
+    changes.truncate(limit as usize);
+    let version = match changes.last() {
+        Some(last) if has_more => last.seq,
+        _ => head,
+    };
+
+    let ids: Vec<Uuid> = changes.iter().map(|c| c.product_id).collect();
+    let products = sqlx::query_as::<_, Product>(
+        "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE tenant_id = $1 AND id = ANY($2)",
+    )
+    .bind(tenant_id)
+    .bind(&ids)
+    .fetch_all(read_db)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let policy = state.redaction_policies.get(state.db.pool(), tenant_id).await;
+    let mut view = policy.for_viewer(&sec, false);
+    let deltas = build_deltas(changes, products, q.since_version == 0, |product| {
+        let mut value = serde_json::to_value(product).unwrap_or(Value::Null);
+        view.redact(&mut value);
+        if let Value::Object(fields) = &mut value {
+            for field in OMITTED_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        value
+    });
+    view.finish();
+
+    Ok((
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(CatalogSync { version, has_more, changes: deltas }),
+    ))
+}
+
+/// Pair each change with the product's current row. Products that are gone or soft-deleted become
+/// tombstones, which a full sync leaves out since the terminal has nothing to remove.
+fn build_deltas(
+    changes: Vec<ChangeRow>,
+    products: Vec<Product>,
+    full_sync: bool,
+    mut render: impl FnMut(&Product) -> Value,
+) -> Vec<CatalogDelta> {
+    let products: HashMap<Uuid, Product> = products.into_iter().map(|p| (p.id, p)).collect();
+    changes
+        .into_iter()
+        .filter_map(|change| match products.get(&change.product_id) {
+            Some(product) if product.deleted_at.is_none() => Some(CatalogDelta::Upsert {
+                seq: change.seq,
+                change: change.change,
+                product: render(product),
+            }),
+            _ if full_sync => None,
+            _ => Some(CatalogDelta::Delete { seq: change.seq, id: change.product_id }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use common_money::Money;
+    use serde_json::json;
+
+    fn product(id: Uuid, deleted: bool) -> Product {
+        Product {
+            id,
+            tenant_id: Uuid::nil(),
+            name: "Latte".into(),
+            price: Money::new(BigDecimal::from(4)),
+            description: String::new(),
+            image: String::new(),
+            active: true,
+            sku: None,
+            tax_code: None,
+            tracking: "none".into(),
+            uom: "each".into(),
+            tare_weight: None,
+            cost: None,
+            version: 2,
+            deleted_at: deleted.then(Utc::now),
+        }
+    }
+
+    fn change(seq: i64, product_id: Uuid, change: &str) -> ChangeRow {
+        ChangeRow { seq, product_id, change: change.into() }
+    }
+
+    #[test]
+    fn deleted_and_missing_products_become_tombstones() {
+        let (live, deleted, purged) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let changes = || {
+            vec![
+                change(3, live, "price_changed"),
+                change(4, deleted, "deleted"),
+                change(5, purged, "deleted"),
+            ]
+        };
+        let products = || vec![product(live, false), product(deleted, true)];
+        let render = |p: &Product| json!({ "id": p.id });
+
+        let deltas = build_deltas(changes(), products(), false, render);
+        assert_eq!(
+            deltas,
+            vec![
+                CatalogDelta::Upsert { seq: 3, change: "price_changed".into(), product: json!({ "id": live }) },
+                CatalogDelta::Delete { seq: 4, id: deleted },
+                CatalogDelta::Delete { seq: 5, id: purged },
+            ]
+        );
+
+        let full = build_deltas(changes(), products(), true, render);
+        assert_eq!(full.len(), 1);
+    }
+
+    #[test]
+    fn deltas_serialize_with_an_op_tag() {
+        let id = Uuid::nil();
+        let value = serde_json::to_value(CatalogDelta::Delete { seq: 7, id }).unwrap();
+        assert_eq!(value, json!({ "op": "delete", "seq": 7, "id": id }));
+    }
+}
@@ -0,0 +1,38 @@
+//! Startup configuration, validated before the service connects to anything.
+
+use common_config::{ConfigError, EnvReader, HttpSettings, JwtSettings, Redacted};
+use common_db::PoolSettings;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductConfig {
+    pub http: HttpSettings,
+    pub database_url: Redacted<String>,
+    pub pool: PoolSettings,
+    pub kafka_bootstrap: String,
+    pub audit_topic: String,
+    pub jwt: JwtSettings,
+}
+
+impl ProductConfig {
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::new();
+        let http = HttpSettings::read(&mut env, 8081);
+        let database_url = env.required_secret("DATABASE_URL").await;
+        let pool = PoolSettings::read(&mut env);
+        let kafka_bootstrap = env.or("KAFKA_BOOTSTRAP", "localhost:9092".to_string());
+        let audit_topic = env.or("AUDIT_TOPIC", "audit.events".to_string());
+        let jwt = JwtSettings::read(&mut env).await;
+
+        env.finish(|| {
+            Some(Self {
+                http,
+                database_url: database_url?,
+                pool,
+                kafka_bootstrap,
+                audit_topic,
+                jwt: jwt?,
+            })
+        })
+    }
+}
@@ -0,0 +1,43 @@
+//! Catalog counts for the gateway's tenant overview (`GET /tenants/:tenant_id/diagnostics`).
+
+use crate::app_state::AppState;
+use crate::ApiError;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use common_security::{ensure_role, Role, SecurityCtxExtractor};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CatalogDiagnostics {
+    pub products: i64,
+    pub deleted_products: i64,
+    pub merged_products: i64,
+    /// Latest `product_changes` sequence number, what a fully synced terminal holds.
+    pub catalog_version: i64,
+    pub last_change_at: Option<DateTime<Utc>>,
+}
+
+/// Support engineers look at any tenant, so the path tenant is not matched against the caller's.
+pub async fn tenant_diagnostics(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<CatalogDiagnostics>, ApiError> {
+    ensure_role(&sec, Role::SuperAdmin)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "super_admin", trace_id: sec.trace_id })?;
+    let diagnostics = sqlx::query_as::<_, CatalogDiagnostics>(
+        "SELECT COUNT(*) FILTER (WHERE deleted_at IS NULL) AS products,
+                COUNT(*) FILTER (WHERE deleted_at IS NOT NULL) AS deleted_products,
+                COUNT(*) FILTER (WHERE merged_into IS NOT NULL) AS merged_products,
+                COALESCE((SELECT last_seq FROM product_change_seq WHERE tenant_id = $1), 0) AS catalog_version,
+                (SELECT MAX(changed_at) FROM product_changes WHERE tenant_id = $1) AS last_change_at
+         FROM products WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_one(state.read_db.get().await)
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(diagnostics))
+}
@@ -0,0 +1,305 @@
+//! SKUs and barcodes: the identifiers a scanner or an import resolves to a product.
+//!
+//! Both are unique per tenant among live products. SKUs are a column on `products`; barcodes live
+//! in `product_barcodes` (migration `1016`) since a product can carry several (case and unit
+//! GTINs, a legacy code). A soft-deleted product releases its SKU and barcodes so they can be
+//! reused, and claims them back on restore unless another product took them in the meantime.
+//! Every write that would reuse a live identifier fails with a 409 `sku_conflict` or
+//! `barcode_conflict` naming the product that holds it; `GET /products/identifiers` lets import
+//! tools and product forms check first.
+
+use crate::app_state::AppState;
+use crate::product_handlers::{record_product_audit, AuditActor};
+use crate::ApiError;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use common_security::{Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Executor, Postgres};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Barcodes one product may carry.
+const MAX_BARCODES: usize = 20;
+const MAX_BARCODE_LEN: usize = 64;
+
+/// Unique indexes guarding live identifiers (migrations `1008` and `1016`).
+const SKU_INDEX: &str = "idx_products_tenant_sku_unique";
+const BARCODE_INDEX: &str = "idx_product_barcodes_tenant_barcode_unique";
+
+pub(crate) fn sku_conflict(sku: &str, owner: Option<Uuid>, trace_id: Option<Uuid>) -> ApiError {
+    let message = match owner {
+        Some(owner) => format!("SKU {sku} is already used by product {owner}"),
+        None => format!("SKU {sku} is already used by another active product"),
+    };
+    ApiError::Conflict { code: "sku_conflict", trace_id, message: Some(message) }
+}
+
+pub(crate) fn barcode_conflict(barcode: &str, owner: Option<Uuid>, trace_id: Option<Uuid>) -> ApiError {
+    let message = match owner {
+        Some(owner) => format!("Barcode {barcode} is already used by product {owner}"),
+        None => format!("Barcode {barcode} is already used by another active product"),
+    };
+    ApiError::Conflict { code: "barcode_conflict", trace_id, message: Some(message) }
+}
+
+/// Maps a write that lost a race for a live SKU or barcode to its 409; any other error is a 500.
+/// Handlers check owners first so the usual conflict names the product; this covers the window
+/// between that check and the write.
+pub(crate) fn identifier_conflict(err: sqlx::Error, trace_id: Option<Uuid>) -> ApiError {
+    if let sqlx::Error::Database(db) = &err {
+        match db.constraint() {
+            Some(SKU_INDEX) => {
+                return ApiError::Conflict { code: "sku_conflict", trace_id, message: Some("Another active product already uses this SKU".into()) }
+            }
+            Some(BARCODE_INDEX) => {
+                return ApiError::Conflict { code: "barcode_conflict", trace_id, message: Some("Another active product already uses this barcode".into()) }
+            }
+            _ => {}
+        }
+    }
+    ApiError::internal(err, trace_id)
+}
+
+/// The live product using `sku`, other than `exclude`.
+pub(crate) async fn sku_owner<'e, E: Executor<'e, Database = Postgres>>(
+    db: E,
+    tenant_id: Uuid,
+    sku: &str,
+    exclude: Option<Uuid>,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT id FROM products
+         WHERE tenant_id = $1 AND sku = $2 AND deleted_at IS NULL AND ($3::uuid IS NULL OR id <> $3)
+         LIMIT 1",
+    )
+    .bind(tenant_id)
+    .bind(sku)
+    .bind(exclude)
+    .fetch_optional(db)
+    .await
+}
+
+/// Live products (other than `exclude`) using any of `barcodes`, as `(barcode, product_id)`.
+pub(crate) async fn barcode_owners<'e, E: Executor<'e, Database = Postgres>>(
+    db: E,
+    tenant_id: Uuid,
+    barcodes: &[String],
+    exclude: Option<Uuid>,
+) -> Result<Vec<(String, Uuid)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT barcode, product_id FROM product_barcodes
+         WHERE tenant_id = $1 AND barcode = ANY($2) AND deleted_at IS NULL AND ($3::uuid IS NULL OR product_id <> $3)
+         ORDER BY barcode",
+    )
+    .bind(tenant_id)
+    .bind(barcodes)
+    .bind(exclude)
+    .fetch_all(db)
+    .await
+}
+
+/// Trimmed barcode, or `None` when it is empty, too long, or has whitespace or control characters.
+pub fn normalize_barcode(input: &str) -> Option<String> {
+    let barcode = input.trim();
+    let valid = !barcode.is_empty()
+        && barcode.len() <= MAX_BARCODE_LEN
+        && barcode.chars().all(|c| c.is_ascii_graphic());
+    valid.then(|| barcode.to_string())
+}
+
+async fn live_barcodes<'e, E: Executor<'e, Database = Postgres>>(db: E, tenant_id: Uuid, product_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT barcode FROM product_barcodes WHERE tenant_id = $1 AND product_id = $2 AND deleted_at IS NULL ORDER BY created_at, barcode",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .fetch_all(db)
+    .await
+}
+
+async fn ensure_live_product(state: &AppState, tenant_id: Uuid, product_id: Uuid, trace_id: Option<Uuid>) -> Result<(), ApiError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM products WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL)")
+        .bind(product_id)
+        .bind(tenant_id)
+        .fetch_one(state.db.pool())
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+    if exists {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound { code: "product_not_found", trace_id })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductBarcodes {
+    pub product_id: Uuid,
+    pub barcodes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutBarcodesRequest {
+    pub barcodes: Vec<String>,
+}
+
+/// `GET /products/:id/barcodes`
+pub async fn get_barcodes(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<ProductBarcodes>, ApiError> {
+    ensure_live_product(&state, sec.tenant_id, product_id, sec.trace_id).await?;
+    let barcodes = live_barcodes(state.db.pool(), sec.tenant_id, product_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(ProductBarcodes { product_id, barcodes }))
+}
+
+/// `PUT /products/:id/barcodes`: replace the product's barcodes.
+pub async fn put_barcodes(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): Path<Uuid>,
+    Json(req): Json<PutBarcodesRequest>,
+) -> Result<Json<ProductBarcodes>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let (tenant_id, trace_id) = (sec.tenant_id, sec.trace_id);
+    if req.barcodes.len() > MAX_BARCODES {
+        return Err(ApiError::BadRequest { code: "too_many_barcodes", trace_id, message: Some(format!("A product is limited to {MAX_BARCODES} barcodes")) });
+    }
+    let mut barcodes = Vec::with_capacity(req.barcodes.len());
+    let mut seen = HashSet::new();
+    for raw in &req.barcodes {
+        let barcode = normalize_barcode(raw).ok_or_else(|| ApiError::BadRequest {
+            code: "invalid_barcode",
+            trace_id,
+            message: Some(format!("Barcode {raw:?} must be 1-{MAX_BARCODE_LEN} printable characters without spaces")),
+        })?;
+        if !seen.insert(barcode.clone()) {
+            return Err(ApiError::BadRequest { code: "duplicate_barcode", trace_id, message: Some(format!("Barcode {barcode} is listed more than once")) });
+        }
+        barcodes.push(barcode);
+    }
+    ensure_live_product(&state, tenant_id, product_id, trace_id).await?;
+
+    let db = state.db.pool();
+    let mut tx = db.begin().await.map_err(|e| ApiError::internal(e, trace_id))?;
+    if let Some((barcode, owner)) = barcode_owners(&mut *tx, tenant_id, &barcodes, Some(product_id))
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?
+        .into_iter()
+        .next()
+    {
+        return Err(barcode_conflict(&barcode, Some(owner), trace_id));
+    }
+    let before = live_barcodes(&mut *tx, tenant_id, product_id).await.map_err(|e| ApiError::internal(e, trace_id))?;
+    // Released barcodes from an earlier delete are dropped too: the new list is the product's now.
+    sqlx::query("DELETE FROM product_barcodes WHERE tenant_id = $1 AND product_id = $2")
+        .bind(tenant_id)
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+    for barcode in &barcodes {
+        sqlx::query("INSERT INTO product_barcodes (tenant_id, product_id, barcode) VALUES ($1, $2, $3)")
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(barcode)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| identifier_conflict(e, trace_id))?;
+    }
+    tx.commit().await.map_err(|e| identifier_conflict(e, trace_id))?;
+
+    let changes = json!({ "before": { "barcodes": before }, "after": { "barcodes": barcodes } });
+    let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
+    record_product_audit(db, &actor, product_id, tenant_id, "barcodes_updated", changes).await;
+    Ok(Json(ProductBarcodes { product_id, barcodes }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdentifierQuery {
+    pub sku: Option<String>,
+    pub barcode: Option<String>,
+    /// The product being edited, whose own identifiers don't count as taken.
+    pub exclude_product_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentifierAvailability {
+    pub value: String,
+    pub available: bool,
+    /// The live product holding the identifier when it is taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentifierCheck {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<IdentifierAvailability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub barcode: Option<IdentifierAvailability>,
+}
+
+/// `GET /products/identifiers?sku=&barcode=`: whether a SKU and/or barcode is free to use.
+pub async fn check_identifiers(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Query(q): Query<IdentifierQuery>,
+) -> Result<Json<IdentifierCheck>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let (tenant_id, trace_id) = (sec.tenant_id, sec.trace_id);
+    let sku = q.sku.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    let barcode = match q.barcode.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(raw) => Some(normalize_barcode(raw).ok_or_else(|| ApiError::BadRequest {
+            code: "invalid_barcode",
+            trace_id,
+            message: Some(format!("Barcode must be 1-{MAX_BARCODE_LEN} printable characters without spaces")),
+        })?),
+        None => None,
+    };
+    if sku.is_none() && barcode.is_none() {
+        return Err(ApiError::BadRequest { code: "missing_identifier", trace_id, message: Some("Query param 'sku' or 'barcode' is required".into()) });
+    }
+    let db = state.read_db.get().await;
+    let sku = match sku {
+        Some(sku) => {
+            let owner = sku_owner(db, tenant_id, sku, q.exclude_product_id).await.map_err(|e| ApiError::internal(e, trace_id))?;
+            Some(IdentifierAvailability { value: sku.to_string(), available: owner.is_none(), product_id: owner })
+        }
+        None => None,
+    };
+    let barcode = match barcode {
+        Some(barcode) => {
+            let owners = barcode_owners(db, tenant_id, std::slice::from_ref(&barcode), q.exclude_product_id)
+                .await
+                .map_err(|e| ApiError::internal(e, trace_id))?;
+            let owner = owners.first().map(|(_, product_id)| *product_id);
+            Some(IdentifierAvailability { value: barcode, available: owner.is_none(), product_id: owner })
+        }
+        None => None,
+    };
+    Ok(Json(IdentifierCheck { sku, barcode }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barcodes_are_trimmed_and_limited_to_printable_ascii() {
+        assert_eq!(normalize_barcode(" 0012345678905 ").as_deref(), Some("0012345678905"));
+        assert_eq!(normalize_barcode("ABC-123/X").as_deref(), Some("ABC-123/X"));
+        assert!(normalize_barcode("   ").is_none());
+        assert!(normalize_barcode("12 34").is_none());
+        assert!(normalize_barcode("caf\u{e9}").is_none());
+        assert!(normalize_barcode(&"9".repeat(MAX_BARCODE_LEN + 1)).is_none());
+    }
+}
@@ -1,7 +1,14 @@
 pub mod app_state;
 pub mod view_redaction;
+pub mod redaction_policy;
 pub mod audit_handlers;
 pub mod product_handlers;
+pub mod catalog_sync;
+pub mod bundle_handlers;
+pub mod modifier_handlers;
+pub mod identifier_handlers;
+pub mod merge_handlers;
+pub mod diagnostics_handlers;
 pub mod metrics;
 
 pub use common_http_errors::ApiError;
\ No newline at end of file
@@ -1,18 +1,19 @@
-﻿use anyhow::Context;
-use axum::{
+﻿use axum::{
     extract::State,
     http::{
-        header::{ACCEPT, CONTENT_TYPE},
+        header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MATCH},
         HeaderName, HeaderValue, Method, StatusCode,
     },
-    routing::{get, post, put},
+    routing::{get, post},
     Router,
 };
 use common_auth::{JwtConfig, JwtVerifier};
+use common_config::{config_route, JwtSettings};
+use common_observability::{SloConfig, SloTracker};
 use common_money::log_rounding_mode_once;
 #[cfg(any(feature = "kafka", feature = "kafka-producer"))] use rdkafka::producer::FutureProducer;
-use sqlx::PgPool;
-use std::{env, net::SocketAddr, sync::Arc};
+#[cfg(any(feature = "kafka", feature = "kafka-producer"))] use common_config::KafkaSecurity;
+use std::sync::Arc;
 use tokio::{
     net::TcpListener,
     time::{interval, Duration, MissedTickBehavior},
@@ -21,9 +22,19 @@ use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::{debug, info, warn};
 
 use product_service::product_handlers::{
-    create_product, delete_product, list_product_audit, list_products, update_product, lookup_product_by_sku,
+    create_product, delete_product, get_product, list_product_audit, list_products, restore_product, update_product, lookup_product_by_sku,
+    export_tenant_data,
 };
-use product_service::audit_handlers::{audit_search, view_redactions_count, VIEW_REDACTIONS_LABELS};
+use product_service::catalog_sync::sync_products;
+use product_service::bundle_handlers::{get_components, put_components};
+use product_service::modifier_handlers::{get_modifiers, put_modifiers};
+use product_service::identifier_handlers::{check_identifiers, get_barcodes, put_barcodes};
+use product_service::merge_handlers::merge_products;
+use product_service::diagnostics_handlers::tenant_diagnostics;
+use product_service::redaction_policy::{get_redaction_policy, policy_fields, put_redaction_policy};
+use product_service::audit_handlers::{audit_search, list_product_field_changes, view_redactions_count, VIEW_REDACTIONS_LABELS};
+mod config;
+use config::ProductConfig;
 mod metrics;
 use metrics::{
     update_redaction_counters,
@@ -82,7 +93,7 @@ async fn metrics(State(state): State<AppState>) -> (StatusCode, String) {
         for ((tenant, role, field), count) in map.iter() {
             converted.insert((tenant.to_string(), role.clone(), field.clone()), *count);
         }
-    update_redaction_counters(view_redactions_count(), &converted);
+    update_redaction_counters(view_redactions_count(), &converted, &policy_fields());
     }
     let out = gather_metrics(true);
     (StatusCode::OK, out)
@@ -96,31 +107,32 @@ async fn health() -> &'static str {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().with_env_filter("info").init();
+    common_observability::init_logging("product-service");
     log_rounding_mode_once();
     // Initialize database connection pool
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db = PgPool::connect(&database_url).await?;
+    let config = ProductConfig::from_env().await?;
+    let db = config.pool.connect(config.database_url.expose()).await?;
+    common_db::pool::register_metrics(&metrics::REGISTRY);
+    common_db::pool::spawn_pool_gauges(common_db::pool::PRIMARY_POOL, db.clone());
     // Ensure database schema is up to date before serving traffic
     let mut migrator = sqlx::migrate!("./migrations");
     migrator.set_ignore_missing(true);
     migrator.run(&db).await?;
     // Initialize Kafka producer for downstream events
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let kafka_producer: FutureProducer = rdkafka::ClientConfig::new()
-        .set(
-            "bootstrap.servers",
-            env::var("KAFKA_BOOTSTRAP").unwrap_or("localhost:9092".into()),
-        )
-        .create()
+    let kafka_producer: FutureProducer = common_kafka::ProducerSettings::from_env()?
+        .create(&config.kafka_bootstrap, &KafkaSecurity::from_env().await?)
         .expect("failed to create kafka producer");
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::register_metrics(&metrics::REGISTRY);
 
-    let jwt_verifier = build_jwt_verifier_from_env().await?;
-    spawn_jwks_refresh(jwt_verifier.clone());
+    let jwt_verifier = build_jwt_verifier(&config.jwt).await?;
+    spawn_jwks_refresh(jwt_verifier.clone(), config.jwt.jwks_refresh_seconds);
+    common_auth::spawn_tenant_status_refresh(jwt_verifier.clone());
 
     // Build application state
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
-    let audit_topic = env::var("AUDIT_TOPIC").unwrap_or_else(|_| "audit.events".to_string());
+    let audit_topic = config.audit_topic.clone();
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
     let base = common_audit::AuditProducer::new(common_audit::KafkaAuditSink::new(kafka_producer.clone(), common_audit::AuditProducerConfig { topic: audit_topic.clone() }));
     #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
@@ -130,7 +142,13 @@ async fn main() -> anyhow::Result<()> {
     let audit_producer: Option<Arc<()>> = None;
     #[cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
     let kafka_producer = (); // placeholder when kafka disabled
-    let state = AppState::new(db, kafka_producer, jwt_verifier, audit_producer);
+    let read_db = common_db::ReadPool::from_env(db.clone())?;
+    if read_db.has_replica() {
+        tracing::info!("Read replica configured for list and audit search endpoints");
+    }
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    let shutdown_producer = kafka_producer.clone();
+    let state = AppState::new(db, kafka_producer, jwt_verifier, audit_producer).with_read_pool(read_db);
 
     let allowed_origins = [
         "http://localhost:3000",
@@ -160,6 +178,7 @@ async fn main() -> anyhow::Result<()> {
             [
                 ACCEPT,
                 CONTENT_TYPE,
+                IF_MATCH,
                 HeaderName::from_static("authorization"),
                 HeaderName::from_static("x-tenant-id"),
                 HeaderName::from_static("x-roles"),
@@ -169,58 +188,67 @@ async fn main() -> anyhow::Result<()> {
             ]
             .into_iter()
             .collect::<Vec<_>>(),
-        );
+        )
+        .expose_headers([ETAG]);
 
     // Build application routes
+    let slo = SloTracker::new("product-service", SloConfig::from_env(), &product_service::metrics::REGISTRY);
     let app = Router::new()
         .route("/healthz", get(health))
     .route("/products", post(create_product).get(list_products))
     .route("/products/lookup", get(lookup_product_by_sku))
-        .route("/products/:id", put(update_product).delete(delete_product))
+    .route("/products/identifiers", get(check_identifiers))
+    .route("/products/sync", get(sync_products))
+        .route("/products/:id", get(get_product).put(update_product).delete(delete_product))
+        .route("/products/:id/restore", post(restore_product))
+        .route("/products/:id/components", get(get_components).put(put_components))
+        .route("/products/:id/modifiers", get(get_modifiers).put(put_modifiers))
+        .route("/products/:id/barcodes", get(get_barcodes).put(put_barcodes))
         .route("/products/:id/audit", get(list_product_audit))
+        .route("/products/:id/audit/changes", get(list_product_field_changes))
+        .route("/tenants/:tenant_id/export", get(export_tenant_data))
+        .route("/tenants/:tenant_id/diagnostics", get(tenant_diagnostics))
         .route("/audit/events", get(audit_search))
+        .route("/admin/products/merge", post(merge_products))
+        .route("/admin/redaction_policy", get(get_redaction_policy).put(put_redaction_policy))
         .route("/internal/audit_metrics", get(audit_metrics))
         .route("/internal/metrics", get(metrics))
+        .route("/internal/config", config_route(&config))
+        .route("/internal/slo", slo.route())
+        .route("/internal/slo/rules", slo.rules_route())
         .route("/metrics", get(metrics))
         .with_state(state)
         .layer(middleware::from_fn(error_metrics_mw))
-        .layer(cors);
+        .layer(cors)
+        .layer(middleware::from_fn(slo.layer()))
+        .layer(middleware::from_fn(common_observability::request_span));
     // Start server
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8081);
-    let ip: std::net::IpAddr = host.parse()?;
-    let addr = SocketAddr::from((ip, port));
+    let addr = config.http.addr();
     println!("starting product-service on {addr}");
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).with_graceful_shutdown(common_observability::shutdown_signal()).await?;
+    #[cfg(any(feature = "kafka", feature = "kafka-producer"))]
+    common_kafka::flush(&shutdown_producer, common_kafka::SHUTDOWN_FLUSH_TIMEOUT);
     Ok(())
 }
 
-async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
-    let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER must be set")?;
-    let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE must be set")?;
-
-    let mut config = JwtConfig::new(issuer, audience);
-    if let Ok(value) = env::var("JWT_LEEWAY_SECONDS") {
-        if let Ok(leeway) = value.parse::<u32>() {
-            config = config.with_leeway(leeway);
-        }
+async fn build_jwt_verifier(settings: &JwtSettings) -> anyhow::Result<Arc<JwtVerifier>> {
+    let mut config = JwtConfig::new(settings.issuer.clone(), settings.audience.clone());
+    if let Some(leeway) = settings.leeway_seconds {
+        config = config.with_leeway(leeway);
     }
 
     let mut builder = JwtVerifier::builder(config);
 
-    if let Ok(url) = env::var("JWT_JWKS_URL") {
+    if let Some(url) = &settings.jwks_url {
         info!(jwks_url = %url, "Configuring JWKS fetcher");
-        builder = builder.with_jwks_url(url);
+        builder = builder.with_jwks_url(url.clone());
     }
 
-    if let Ok(pem) = env::var("JWT_DEV_PUBLIC_KEY_PEM") {
+    if let Some(pem) = &settings.dev_public_key_pem {
         warn!("Using JWT_DEV_PUBLIC_KEY_PEM for verification; do not enable in production");
         builder = builder
-            .with_rsa_pem("local-dev", pem.as_bytes())
+            .with_rsa_pem("local-dev", pem.expose().as_bytes())
             .map_err(anyhow::Error::from)?;
     }
 
@@ -229,16 +257,11 @@ async fn build_jwt_verifier_from_env() -> anyhow::Result<Arc<JwtVerifier>> {
     Ok(Arc::new(verifier))
 }
 
-fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>) {
+fn spawn_jwks_refresh(verifier: Arc<JwtVerifier>, refresh_secs: u64) {
     let Some(fetcher) = verifier.jwks_fetcher() else {
         return;
     };
 
-    let refresh_secs = env::var("JWKS_REFRESH_SECONDS")
-        .ok()
-        .and_then(|value| value.parse::<u64>().ok())
-        .unwrap_or(300);
-    let refresh_secs = refresh_secs.max(60);
     let interval_duration = Duration::from_secs(refresh_secs);
     let url = fetcher.url().to_owned();
     let handle = verifier.clone();
@@ -0,0 +1,223 @@
+//! Folding duplicate products into one.
+//!
+//! Imports and hurried data entry leave the same item in the catalog twice. `POST
+//! /admin/products/merge` keeps the canonical product and retires the duplicate: its barcodes
+//! and bundle memberships move to the canonical product here, and the duplicate is soft-deleted
+//! with `merged_into` set (and announced as `product.deleted` for caches). `product.merged` then
+//! tells inventory-service (stock, lots, serials, cost layers) and order-service (order lines,
+//! returns, open carts) to repoint their rows. Both consumers are idempotent, so repeating a
+//! merge that already happened only republishes the event, which is how a merge whose event
+//! failed to publish is recovered.
+
+use crate::app_state::AppState;
+use crate::product_handlers::{record_product_audit, AuditActor, Product};
+#[cfg(feature = "kafka")]
+use crate::product_handlers::publish_product_lifecycle;
+use crate::ApiError;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use common_events::{DomainEvent, ProductMergedEvent};
+use common_money::measure::UnitOfMeasure;
+use common_security::{Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct MergeProductsRequest {
+    pub duplicate_id: Uuid,
+    pub canonical_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeProductsResponse {
+    pub duplicate_id: Uuid,
+    pub canonical_id: Uuid,
+    pub merged_at: DateTime<Utc>,
+    /// Barcodes moved from the duplicate to the canonical product.
+    pub barcodes_moved: u64,
+    /// Bundle lines that listed the duplicate and now list the canonical product.
+    pub components_rewritten: u64,
+    /// False when the duplicate had already been merged and only the event was republished.
+    pub merged: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct MergeCandidate {
+    id: Uuid,
+    sku: Option<String>,
+    uom: String,
+    deleted_at: Option<DateTime<Utc>>,
+    merged_into: Option<Uuid>,
+    is_bundle: bool,
+    is_component: bool,
+}
+
+fn bad_request(code: &'static str, trace_id: Option<Uuid>, message: &str) -> ApiError {
+    ApiError::BadRequest { code, trace_id, message: Some(message.into()) }
+}
+
+/// Stock of weighed products is kept in thousandths, so only products counted the same way merge.
+fn measured(uom: &str) -> bool {
+    UnitOfMeasure::parse(uom).is_some_and(UnitOfMeasure::is_measured)
+}
+
+/// `POST /admin/products/merge`: retire `duplicate_id` in favour of `canonical_id`.
+pub async fn merge_products(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(req): Json<MergeProductsRequest>,
+) -> Result<Json<MergeProductsResponse>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Admin", trace_id: sec.trace_id });
+    }
+    let (tenant_id, trace_id) = (sec.tenant_id, sec.trace_id);
+    if req.duplicate_id == req.canonical_id {
+        return Err(bad_request("merge_same_product", trace_id, "A product cannot be merged into itself"));
+    }
+    let db = state.db.pool();
+    let mut tx = db.begin().await.map_err(|e| ApiError::internal(e, trace_id))?;
+    let rows = sqlx::query_as::<_, MergeCandidate>(
+        "SELECT p.id, p.sku, p.uom, p.deleted_at, p.merged_into,
+                EXISTS (SELECT 1 FROM product_components pc WHERE pc.tenant_id = p.tenant_id AND pc.bundle_id = p.id) AS is_bundle,
+                EXISTS (SELECT 1 FROM product_components pc WHERE pc.tenant_id = p.tenant_id AND pc.component_id = p.id) AS is_component
+         FROM products p WHERE p.tenant_id = $1 AND p.id = ANY($2)
+         FOR UPDATE OF p",
+    )
+    .bind(tenant_id)
+    .bind([req.duplicate_id, req.canonical_id])
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal(e, trace_id))?;
+    let find = |id: Uuid| rows.iter().find(|row| row.id == id);
+    let canonical = find(req.canonical_id)
+        .filter(|row| row.deleted_at.is_none())
+        .ok_or(ApiError::NotFound { code: "product_not_found", trace_id })?;
+    let duplicate = find(req.duplicate_id).ok_or(ApiError::NotFound { code: "product_not_found", trace_id })?;
+
+    let mut response = MergeProductsResponse {
+        duplicate_id: duplicate.id,
+        canonical_id: canonical.id,
+        merged_at: Utc::now(),
+        barcodes_moved: 0,
+        components_rewritten: 0,
+        merged: false,
+    };
+    let mut retired = None;
+    match (duplicate.deleted_at, duplicate.merged_into) {
+        (Some(merged_at), Some(into)) if into == canonical.id => response.merged_at = merged_at,
+        (Some(_), Some(_)) => {
+            return Err(ApiError::Conflict { code: "already_merged", trace_id, message: Some("The duplicate was merged into another product".into()) })
+        }
+        (Some(_), None) => return Err(ApiError::NotFound { code: "product_not_found", trace_id }),
+        (None, _) => {
+            if measured(&duplicate.uom) != measured(&canonical.uom) {
+                return Err(bad_request("merge_uom_mismatch", trace_id, "Weighed and counted products cannot be merged"));
+            }
+            if canonical.is_bundle && duplicate.is_component {
+                return Err(bad_request("nested_bundle", trace_id, "The duplicate is a bundle component and the canonical product is a bundle"));
+            }
+            response.barcodes_moved = sqlx::query(
+                "UPDATE product_barcodes SET product_id = $3 WHERE tenant_id = $1 AND product_id = $2 AND deleted_at IS NULL",
+            )
+            .bind(tenant_id)
+            .bind(duplicate.id)
+            .bind(canonical.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?
+            .rows_affected();
+            // A bundle listing both keeps one line with the quantities added up.
+            sqlx::query(
+                "UPDATE product_components c SET quantity = c.quantity + d.quantity
+                 FROM product_components d
+                 WHERE c.tenant_id = $1 AND d.tenant_id = $1 AND c.bundle_id = d.bundle_id
+                   AND c.component_id = $3 AND d.component_id = $2",
+            )
+            .bind(tenant_id)
+            .bind(duplicate.id)
+            .bind(canonical.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?;
+            let summed = sqlx::query(
+                "DELETE FROM product_components d
+                 WHERE d.tenant_id = $1 AND d.component_id = $2
+                   AND EXISTS (SELECT 1 FROM product_components c WHERE c.tenant_id = $1 AND c.bundle_id = d.bundle_id AND c.component_id = $3)",
+            )
+            .bind(tenant_id)
+            .bind(duplicate.id)
+            .bind(canonical.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?
+            .rows_affected();
+            let moved = sqlx::query("UPDATE product_components SET component_id = $3 WHERE tenant_id = $1 AND component_id = $2")
+                .bind(tenant_id)
+                .bind(duplicate.id)
+                .bind(canonical.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| ApiError::internal(e, trace_id))?
+                .rows_affected();
+            response.components_rewritten = summed + moved;
+            let product = sqlx::query_as::<_, Product>(
+                "UPDATE products SET deleted_at = now(), merged_into = $3, version = version + 1
+                 WHERE tenant_id = $1 AND id = $2
+                 RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at",
+            )
+            .bind(tenant_id)
+            .bind(duplicate.id)
+            .bind(canonical.id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?;
+            response.merged_at = product.deleted_at.unwrap_or(response.merged_at);
+            response.merged = true;
+            retired = Some(product);
+        }
+    }
+    tx.commit().await.map_err(|e| ApiError::internal(e, trace_id))?;
+
+    let event = ProductMergedEvent {
+        schema_version: ProductMergedEvent::SCHEMA_VERSION,
+        tenant_id,
+        duplicate_id: duplicate.id,
+        canonical_id: canonical.id,
+        duplicate_sku: duplicate.sku.clone(),
+        canonical_sku: canonical.sku.clone(),
+        merged_at: response.merged_at,
+    };
+    if let Some(product) = &retired {
+        let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
+        let changes = json!({
+            "before": { "deleted_at": null, "merged_into": null },
+            "after": { "deleted_at": product.deleted_at, "merged_into": canonical.id, "barcodes_moved": response.barcodes_moved },
+        });
+        record_product_audit(db, &actor, duplicate.id, tenant_id, "merged", changes).await;
+        let changes = json!({ "after": { "merged_from": duplicate.id, "barcodes_moved": response.barcodes_moved, "components_rewritten": response.components_rewritten } });
+        record_product_audit(db, &actor, canonical.id, tenant_id, "merged_from", changes).await;
+    }
+    #[cfg(feature = "kafka")]
+    {
+        if let Some(product) = &retired {
+            publish_product_lifecycle(&state, "product.deleted", product).await;
+        }
+        publish_merged(&state, &event).await;
+    }
+    #[cfg(not(feature = "kafka"))]
+    let _ = event;
+
+    Ok(Json(response))
+}
+
+#[cfg(feature = "kafka")]
+async fn publish_merged(state: &AppState, event: &ProductMergedEvent) {
+    let payload = match common_events::encode(event) {
+        Ok(payload) => payload,
+        Err(err) => return tracing::error!(?err, "Failed to encode product.merged event"),
+    };
+    if let Err(err) = common_kafka::publish(&state.kafka_producer, ProductMergedEvent::TOPIC, &event.partition_key(), &payload).await {
+        tracing::error!(?err, duplicate_id = %event.duplicate_id, "Failed to publish product.merged event; repeat the merge to republish it");
+    }
+}
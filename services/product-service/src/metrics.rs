@@ -88,7 +88,13 @@ pub fn update_buffer_metrics(queued: u64, emitted_total: u64, dropped_total: u64
     }
 }
 
-pub fn update_redaction_counters(total_redactions: u64, labelled: &std::collections::HashMap<(String,String,String), u64>) {
+/// Field labels outside `AUDIT_VIEW_REDACTION_PATHS` are dropped unless a tenant redaction policy
+/// names them in `policy_fields`.
+pub fn update_redaction_counters(
+    total_redactions: u64,
+    labelled: &std::collections::HashMap<(String,String,String), u64>,
+    policy_fields: &HashSet<String>,
+) {
     // Overall
     let prev_total = LAST_VIEW_REDACTIONS.load(Ordering::Relaxed);
     if total_redactions > prev_total {
@@ -99,7 +105,7 @@ pub fn update_redaction_counters(total_redactions: u64, labelled: &std::collecti
     if let Ok(mut last_map) = LAST_VIEW_LABELLED.lock() {
         for ((tenant, role, field), count) in labelled.iter() {
             if let Some(whitelist) = &*REDACTION_FIELD_WHITELIST {
-                if !whitelist.contains(field) { continue; }
+                if !whitelist.contains(field) && !policy_fields.contains(field) { continue; }
             }
             let key = (tenant.clone(), role.clone(), field.clone());
             let prev = *last_map.get(&key).unwrap_or(&0);
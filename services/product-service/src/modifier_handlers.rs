@@ -0,0 +1,365 @@
+//! Menu modifiers for food-service products.
+//!
+//! A product can carry modifier groups (migration `1015`) such as "Choose a size" or "Add
+//! toppings". Each group lists its options with a price delta and bounds how many the cashier
+//! picks: `min_select` of 1 or more makes the group forced, so order-service refuses a line that
+//! leaves it unanswered. Deltas are added to the unit price of the line they are chosen on.
+
+use crate::app_state::AppState;
+use crate::product_handlers::{record_product_audit, AuditActor};
+use crate::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use common_money::Money;
+use common_security::{Role, SecurityCtxExtractor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Modifier groups one product may carry.
+const MAX_GROUPS: usize = 20;
+/// Options one group may list.
+const MAX_OPTIONS: usize = 50;
+const MAX_NAME_LEN: usize = 80;
+
+#[derive(Debug, Deserialize)]
+pub struct ModifierInput {
+    /// Keep an existing option's id so parked carts that chose it stay valid; omit for new ones.
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub name: String,
+    /// Added to the line's unit price when chosen; negative for "no cheese" style options.
+    #[serde(default)]
+    pub price_delta: BigDecimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModifierGroupInput {
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub name: String,
+    /// Options that must be chosen; 1 or more makes the group forced.
+    #[serde(default)]
+    pub min_select: i32,
+    pub max_select: i32,
+    pub options: Vec<ModifierInput>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutModifiersRequest {
+    /// Replaces the product's groups in order; an empty list removes them all.
+    pub groups: Vec<ModifierGroupInput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Modifier {
+    pub id: Uuid,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModifierGroup {
+    pub id: Uuid,
+    pub name: String,
+    pub min_select: i32,
+    pub max_select: i32,
+    /// `min_select > 0`: the order is refused until the group is answered.
+    pub required: bool,
+    pub options: Vec<Modifier>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductModifiers {
+    pub product_id: Uuid,
+    pub groups: Vec<ModifierGroup>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ModifierRow {
+    group_id: Uuid,
+    group_name: String,
+    min_select: i32,
+    max_select: i32,
+    modifier_id: Option<Uuid>,
+    modifier_name: Option<String>,
+    price_delta: Option<BigDecimal>,
+}
+
+fn bad_request(code: &'static str, trace_id: Option<Uuid>, message: String) -> ApiError {
+    ApiError::BadRequest { code, trace_id, message: Some(message) }
+}
+
+fn fold_groups(rows: Vec<ModifierRow>) -> Vec<ModifierGroup> {
+    let mut groups: Vec<ModifierGroup> = Vec::new();
+    for row in rows {
+        if groups.last().is_none_or(|g| g.id != row.group_id) {
+            groups.push(ModifierGroup {
+                id: row.group_id,
+                name: row.group_name,
+                min_select: row.min_select,
+                max_select: row.max_select,
+                required: row.min_select > 0,
+                options: Vec::new(),
+            });
+        }
+        if let (Some(id), Some(name), Some(price_delta), Some(group)) = (row.modifier_id, row.modifier_name, row.price_delta, groups.last_mut()) {
+            group.options.push(Modifier { id, name, price_delta: Money::new(price_delta) });
+        }
+    }
+    groups
+}
+
+async fn load_groups(db: &PgPool, tenant_id: Uuid, product_id: Uuid) -> Result<Vec<ModifierGroup>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ModifierRow>(
+        "SELECT g.id AS group_id, g.name AS group_name, g.min_select, g.max_select,
+                m.id AS modifier_id, m.name AS modifier_name, m.price_delta
+         FROM product_modifier_groups g
+         LEFT JOIN product_modifiers m ON m.group_id = g.id AND m.tenant_id = g.tenant_id
+         WHERE g.tenant_id = $1 AND g.product_id = $2
+         ORDER BY g.position, g.id, m.position, m.id",
+    )
+    .bind(tenant_id)
+    .bind(product_id)
+    .fetch_all(db)
+    .await?;
+    Ok(fold_groups(rows))
+}
+
+async fn product_exists(db: &PgPool, tenant_id: Uuid, product_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM products WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL)")
+        .bind(product_id)
+        .bind(tenant_id)
+        .fetch_one(db)
+        .await
+}
+
+/// Trimmed name, or a 400 with `code` when blank or too long.
+fn clean_name(raw: &str, code: &'static str, trace_id: Option<Uuid>) -> Result<String, ApiError> {
+    let name = raw.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(bad_request(code, trace_id, format!("Names must be 1 to {MAX_NAME_LEN} characters")));
+    }
+    Ok(name.to_string())
+}
+
+/// Check a replacement set against the product's current groups and assign ids. Supplied ids
+/// must already belong to this product.
+fn validate_groups(
+    input: &[ModifierGroupInput],
+    current: &[ModifierGroup],
+    trace_id: Option<Uuid>,
+) -> Result<Vec<ModifierGroup>, ApiError> {
+    if input.len() > MAX_GROUPS {
+        return Err(bad_request("too_many_modifier_groups", trace_id, format!("A product is limited to {MAX_GROUPS} modifier groups")));
+    }
+    let known_groups: HashSet<Uuid> = current.iter().map(|g| g.id).collect();
+    let known_options: HashSet<Uuid> = current.iter().flat_map(|g| g.options.iter().map(|o| o.id)).collect();
+    let mut seen_ids: HashSet<Uuid> = HashSet::new();
+    let mut group_names: HashSet<String> = HashSet::new();
+    let mut groups = Vec::with_capacity(input.len());
+    for group in input {
+        let name = clean_name(&group.name, "invalid_modifier_group", trace_id)?;
+        if !group_names.insert(name.to_lowercase()) {
+            return Err(bad_request("duplicate_modifier_group", trace_id, format!("Group '{name}' is listed more than once")));
+        }
+        let id = match group.id {
+            Some(id) if !known_groups.contains(&id) || !seen_ids.insert(id) => {
+                return Err(bad_request("unknown_modifier_group", trace_id, format!("Group {id} does not belong to this product")));
+            }
+            Some(id) => id,
+            None => Uuid::new_v4(),
+        };
+        let option_count = group.options.len() as i32;
+        if group.options.is_empty() || group.options.len() > MAX_OPTIONS {
+            return Err(bad_request("invalid_modifier_group", trace_id, format!("Group '{name}' needs 1 to {MAX_OPTIONS} options")));
+        }
+        if group.min_select < 0 || group.max_select < 1 || group.min_select > group.max_select || group.max_select > option_count {
+            return Err(bad_request(
+                "invalid_modifier_group",
+                trace_id,
+                format!("Group '{name}' needs 0 <= min_select <= max_select, with max_select between 1 and its {option_count} options"),
+            ));
+        }
+        let mut option_names: HashSet<String> = HashSet::new();
+        let mut options = Vec::with_capacity(group.options.len());
+        for option in &group.options {
+            let option_name = clean_name(&option.name, "invalid_modifier", trace_id)?;
+            if !option_names.insert(option_name.to_lowercase()) {
+                return Err(bad_request("duplicate_modifier", trace_id, format!("Option '{option_name}' is listed twice in '{name}'")));
+            }
+            let option_id = match option.id {
+                Some(id) if !known_options.contains(&id) || !seen_ids.insert(id) => {
+                    return Err(bad_request("unknown_modifier", trace_id, format!("Option {id} does not belong to this product")));
+                }
+                Some(id) => id,
+                None => Uuid::new_v4(),
+            };
+            let price_delta = Money::new(option.price_delta.clone());
+            if price_delta.inner() != &option.price_delta {
+                return Err(bad_request("invalid_modifier", trace_id, format!("Price delta for '{option_name}' has more than two decimals")));
+            }
+            options.push(Modifier { id: option_id, name: option_name, price_delta });
+        }
+        groups.push(ModifierGroup { id, name, min_select: group.min_select, max_select: group.max_select, required: group.min_select > 0, options });
+    }
+    Ok(groups)
+}
+
+/// Group and option names with their bounds and deltas, as written to the product audit log.
+fn modifier_summary(groups: &[ModifierGroup]) -> serde_json::Value {
+    groups
+        .iter()
+        .map(|g| {
+            json!({
+                "name": g.name,
+                "min_select": g.min_select,
+                "max_select": g.max_select,
+                "options": g.options.iter().map(|o| json!({ "name": o.name, "price_delta": o.price_delta })).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+/// `GET /products/:id/modifiers`: the product's modifier groups, in display order.
+pub async fn get_modifiers(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<ProductModifiers>, ApiError> {
+    let db = state.db.pool();
+    if !product_exists(db, sec.tenant_id, product_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))? {
+        return Err(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id });
+    }
+    let groups = load_groups(db, sec.tenant_id, product_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(ProductModifiers { product_id, groups }))
+}
+
+/// `PUT /products/:id/modifiers`: replace the product's modifier groups.
+pub async fn put_modifiers(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): Path<Uuid>,
+    Json(req): Json<PutModifiersRequest>,
+) -> Result<Json<ProductModifiers>, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let (tenant_id, trace_id) = (sec.tenant_id, sec.trace_id);
+    let db = state.db.pool();
+    if !product_exists(db, tenant_id, product_id).await.map_err(|e| ApiError::internal(e, trace_id))? {
+        return Err(ApiError::NotFound { code: "product_not_found", trace_id });
+    }
+    let before = load_groups(db, tenant_id, product_id).await.map_err(|e| ApiError::internal(e, trace_id))?;
+    let groups = validate_groups(&req.groups, &before, trace_id)?;
+
+    let mut tx = db.begin().await.map_err(|e| ApiError::internal(e, trace_id))?;
+    sqlx::query("DELETE FROM product_modifier_groups WHERE tenant_id = $1 AND product_id = $2")
+        .bind(tenant_id)
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+    for (position, group) in groups.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO product_modifier_groups (id, tenant_id, product_id, name, min_select, max_select, position)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(group.id)
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(&group.name)
+        .bind(group.min_select)
+        .bind(group.max_select)
+        .bind(position as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, trace_id))?;
+        for (option_position, option) in group.options.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO product_modifiers (id, tenant_id, group_id, name, price_delta, position) VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(option.id)
+            .bind(tenant_id)
+            .bind(group.id)
+            .bind(&option.name)
+            .bind(option.price_delta.inner())
+            .bind(option_position as i32)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal(e, trace_id))?;
+        }
+    }
+    tx.commit().await.map_err(|e| ApiError::internal(e, trace_id))?;
+
+    let changes = json!({
+        "before": { "groups": modifier_summary(&before) },
+        "after": { "groups": modifier_summary(&groups) },
+    });
+    let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
+    record_product_audit(db, &actor, product_id, tenant_id, "modifiers_updated", changes).await;
+    Ok(Json(ProductModifiers { product_id, groups }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn option(name: &str, delta: &str) -> ModifierInput {
+        ModifierInput { id: None, name: name.into(), price_delta: BigDecimal::from_str(delta).unwrap() }
+    }
+
+    fn size_group(min_select: i32, max_select: i32) -> ModifierGroupInput {
+        ModifierGroupInput {
+            id: None,
+            name: "Size".into(),
+            min_select,
+            max_select,
+            options: vec![option("Small", "0"), option("Large", "1.50")],
+        }
+    }
+
+    fn code(err: ApiError) -> &'static str {
+        match err {
+            ApiError::BadRequest { code, .. } => code,
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn forced_group_is_marked_required() {
+        let groups = validate_groups(&[size_group(1, 1)], &[], None).unwrap();
+        assert!(groups[0].required);
+        assert_eq!(groups[0].options[1].price_delta.as_cents(), 150);
+    }
+
+    #[test]
+    fn bounds_must_fit_the_options() {
+        assert_eq!(code(validate_groups(&[size_group(2, 1)], &[], None).unwrap_err()), "invalid_modifier_group");
+        assert_eq!(code(validate_groups(&[size_group(0, 3)], &[], None).unwrap_err()), "invalid_modifier_group");
+        let mut dup = size_group(0, 1);
+        dup.options.push(option("large", "2"));
+        assert_eq!(code(validate_groups(&[dup], &[], None).unwrap_err()), "duplicate_modifier");
+    }
+
+    #[test]
+    fn ids_must_belong_to_the_product() {
+        let mut group = size_group(1, 1);
+        group.options[0].id = Some(Uuid::new_v4());
+        assert_eq!(code(validate_groups(&[group], &[], None).unwrap_err()), "unknown_modifier");
+
+        let current = validate_groups(&[size_group(1, 1)], &[], None).unwrap();
+        let mut kept = size_group(1, 1);
+        kept.id = Some(current[0].id);
+        kept.options[0].id = Some(current[0].options[0].id);
+        let groups = validate_groups(&[kept], &current, None).unwrap();
+        assert_eq!(groups[0].id, current[0].id);
+        assert_eq!(groups[0].options[0].id, current[0].options[0].id);
+    }
+}
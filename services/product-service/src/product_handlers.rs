@@ -1,23 +1,29 @@
 use crate::app_state::AppState;
+use crate::audit_handlers::{diff_fields, store_field_changes};
+use crate::identifier_handlers::{barcode_conflict, barcode_owners, identifier_conflict, normalize_barcode, sku_conflict, sku_owner};
+use crate::redaction_policy::ViewRedaction;
 use crate::ApiError;
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
     Json,
 };
 use chrono::{DateTime, Utc};
 // AuthContext no longer required in handlers; SecurityCtxExtractor provides actor & tenant.
-use common_security::{SecurityCtxExtractor, Role};
+use common_security::{context::SecurityContext, SecurityCtxExtractor, Role};
 #[cfg(feature = "kafka")] use common_audit::AuditActor as SharedAuditActor;
-#[cfg(feature = "kafka")] use rdkafka::producer::FutureRecord;
 use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
 use bigdecimal::BigDecimal;
+use common_money::measure::{normalize_measure, UnitOfMeasure};
 use common_money::{normalize_scale, Money};
 use serde_json::{json, Value};
-use sqlx::{query, query_as, PgPool};
+use common_http_errors::etag;
+use common_http_errors::validation::{Validate, Validator};
+use common_db::pagination::{self, Listing, PageRequest, SortDirection, SortField, SortSpec};
+use sqlx::{query_as, PgPool, Postgres, QueryBuilder};
 use std::env;
-#[cfg(feature = "kafka")] use std::time::Duration;
 use uuid::Uuid;
 
 #[allow(dead_code)]
@@ -34,6 +40,105 @@ pub struct UpdateProduct {
     pub sku: Option<String>,
     #[serde(default)]
     pub tax_code: Option<String>,
+    /// `none`, `serial` or `lot`; omitted keeps the current mode.
+    #[serde(default)]
+    pub tracking: Option<String>,
+    /// `each`, `kg` or `lb`; omitted keeps the current unit.
+    #[serde(default)]
+    pub uom: Option<String>,
+    /// Default container weight in `uom`; omitted keeps the current tare.
+    #[serde(default)]
+    pub tare_weight: Option<BigDecimal>,
+    /// Unit cost; omitted keeps the current cost.
+    #[serde(default)]
+    pub cost: Option<BigDecimal>,
+}
+
+/// Serial/lot capture modes inventory-service understands.
+const TRACKING_MODES: &[&str] = &["none", "serial", "lot"];
+
+fn normalize_tracking(input: Option<&str>, trace_id: Option<Uuid>) -> Result<Option<String>, ApiError> {
+    let Some(mode) = input.map(|m| m.trim().to_ascii_lowercase()) else { return Ok(None) };
+    if !TRACKING_MODES.contains(&mode.as_str()) {
+        return Err(ApiError::BadRequest {
+            code: "invalid_tracking",
+            trace_id,
+            message: Some(format!("tracking must be one of: {}", TRACKING_MODES.join(", "))),
+        });
+    }
+    Ok(Some(mode))
+}
+
+fn normalize_uom(input: Option<&str>, trace_id: Option<Uuid>) -> Result<Option<String>, ApiError> {
+    let Some(raw) = input else { return Ok(None) };
+    match UnitOfMeasure::parse(raw) {
+        Some(uom) => Ok(Some(uom.as_str().to_string())),
+        None => Err(ApiError::BadRequest {
+            code: "invalid_uom",
+            trace_id,
+            message: Some(format!(
+                "uom must be one of: {}",
+                UnitOfMeasure::ALL.iter().map(|u| u.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }),
+    }
+}
+
+fn normalize_tare(input: Option<&BigDecimal>, trace_id: Option<Uuid>) -> Result<Option<BigDecimal>, ApiError> {
+    let Some(tare) = input else { return Ok(None) };
+    if tare < &BigDecimal::from(0) {
+        return Err(ApiError::BadRequest { code: "invalid_tare_weight", trace_id, message: Some("tare_weight cannot be negative".into()) });
+    }
+    Ok(Some(normalize_measure(tare)))
+}
+
+fn normalize_cost(input: Option<&BigDecimal>, trace_id: Option<Uuid>) -> Result<Option<BigDecimal>, ApiError> {
+    let Some(cost) = input else { return Ok(None) };
+    if cost < &BigDecimal::from(0) {
+        return Err(ApiError::BadRequest { code: "invalid_cost", trace_id, message: Some("cost cannot be negative".into()) });
+    }
+    Ok(Some(normalize_scale(cost)))
+}
+
+const MAX_PRODUCT_NAME_LEN: usize = 200;
+
+/// Checks shared by create and update. `tracking`/`uom` are matched the way the normalizers
+/// above read them, so a value that passes here always normalizes.
+fn check_product_fields(
+    v: &mut Validator,
+    name: &str,
+    price: &BigDecimal,
+    tracking: Option<&str>,
+    uom: Option<&str>,
+    tare_weight: Option<&BigDecimal>,
+    cost: Option<&BigDecimal>,
+) {
+    if v.required("name", name) {
+        v.max_len("name", name.trim(), MAX_PRODUCT_NAME_LEN);
+    }
+    let zero = BigDecimal::from(0);
+    v.check(price >= &zero, "price", "must_not_be_negative");
+    if let Some(mode) = tracking {
+        v.one_of("tracking", &mode.trim().to_ascii_lowercase(), TRACKING_MODES);
+    }
+    if let Some(raw) = uom {
+        let names: Vec<&str> = UnitOfMeasure::ALL.iter().map(|u| u.as_str()).collect();
+        v.check_with(UnitOfMeasure::parse(raw).is_some(), "uom", "not_allowed", || format!("must be one of: {}", names.join(", ")));
+    }
+    v.check(tare_weight.is_none_or(|t| t >= &zero), "tare_weight", "must_not_be_negative");
+    v.check(cost.is_none_or(|c| c >= &zero), "cost", "must_not_be_negative");
+}
+
+impl Validate for NewProduct {
+    fn validate(&self, v: &mut Validator) {
+        check_product_fields(v, &self.name, &self.price, self.tracking.as_deref(), self.uom.as_deref(), self.tare_weight.as_ref(), self.cost.as_ref());
+    }
+}
+
+impl Validate for UpdateProduct {
+    fn validate(&self, v: &mut Validator) {
+        check_product_fields(v, &self.name, &self.price, self.tracking.as_deref(), self.uom.as_deref(), self.tare_weight.as_ref(), self.cost.as_ref());
+    }
 }
 
 fn default_product_image() -> String {
@@ -69,7 +174,7 @@ fn shared(actor: &AuditActor) -> SharedAuditActor {
     SharedAuditActor { id: actor.id, name: actor.name.clone(), email: actor.email.clone() }
 }
 
-async fn record_product_audit(
+pub(crate) async fn record_product_audit(
     db: &PgPool,
     actor: &AuditActor,
     product_id: Uuid,
@@ -77,20 +182,28 @@ async fn record_product_audit(
     action: &str,
     changes: Value,
 ) {
-    if let Err(err) = sqlx::query(
-        "INSERT INTO product_audit_log (id, product_id, tenant_id, actor_id, actor_name, actor_email, action, changes) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-    )
-    .bind(Uuid::new_v4())
-    .bind(product_id)
-    .bind(tenant_id)
-    .bind(actor.id)
-    .bind(actor.name.as_deref())
-    .bind(actor.email.as_deref())
-    .bind(action)
-    .bind(changes)
-    .execute(db)
-    .await
-    {
+    let field_changes = diff_fields(&changes);
+    let audit_id = Uuid::new_v4();
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = db.begin().await?;
+        sqlx::query(
+            "INSERT INTO product_audit_log (id, product_id, tenant_id, actor_id, actor_name, actor_email, action, changes) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(audit_id)
+        .bind(product_id)
+        .bind(tenant_id)
+        .bind(actor.id)
+        .bind(actor.name.as_deref())
+        .bind(actor.email.as_deref())
+        .bind(action)
+        .bind(changes)
+        .execute(&mut *tx)
+        .await?;
+        store_field_changes(&mut tx, audit_id, tenant_id, product_id, &field_changes).await?;
+        tx.commit().await
+    }
+    .await;
+    if let Err(err) = result {
         tracing::warn!(?err, product_id = %product_id, action, "Failed to write product audit log");
     }
 }
@@ -99,12 +212,19 @@ fn product_to_value(product: &Product) -> Value {
     serde_json::to_value(product).unwrap_or(Value::Null)
 }
 
+/// Serialize a product for a reader, without the fields the tenant's redaction policy hides from them.
+fn redacted_product_value(view: &mut ViewRedaction<'_>, product: &Product) -> Value {
+    let mut value = product_to_value(product);
+    view.redact(&mut value);
+    value
+}
+
 impl Serialize for Product {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-    let mut state = serializer.serialize_struct("Product", 10)?;
+    let mut state = serializer.serialize_struct("Product", 16)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("tenant_id", &self.tenant_id)?;
         state.serialize_field("name", &self.name)?;
@@ -115,36 +235,98 @@ impl Serialize for Product {
         state.serialize_field("active", &self.active)?;
         state.serialize_field("sku", &self.sku)?;
         state.serialize_field("tax_code", &self.tax_code)?;
+        state.serialize_field("tracking", &self.tracking)?;
+        state.serialize_field("uom", &self.uom)?;
+        state.serialize_field("tare_weight", &self.tare_weight)?;
+        state.serialize_field("cost", &self.cost)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("deleted_at", &self.deleted_at)?;
         state.end()
     }
 }
+#[derive(Deserialize, Default)]
+pub struct IncludeDeletedQuery {
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+/// Soft-deleted products are only visible to admins, and only when asked for explicitly.
+fn ensure_include_deleted_allowed(sec: &SecurityContext, include_deleted: bool) -> Result<(), ApiError> {
+    if include_deleted && !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Admin", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+pub async fn get_product(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): axum::extract::Path<Uuid>,
+    Query(q): Query<IncludeDeletedQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_include_deleted_allowed(&sec, q.include_deleted)?;
+    let product = query_as::<_, Product>(
+        "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE id = $1 AND tenant_id = $2 AND ($3 OR deleted_at IS NULL)",
+    )
+    .bind(product_id)
+    .bind(sec.tenant_id)
+    .bind(q.include_deleted)
+    .fetch_optional(state.db.pool())
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id })?;
+    let policy = state.redaction_policies.get(state.db.pool(), sec.tenant_id).await;
+    let mut view = policy.for_viewer(&sec, false);
+    let body = redacted_product_value(&mut view, &product);
+    view.finish();
+    Ok((etag::etag_header(product.version), Json(body)))
+}
+
 pub async fn update_product(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Path(product_id): axum::extract::Path<Uuid>,
+    headers: HeaderMap,
     Json(upd): Json<UpdateProduct>,
-) -> Result<Json<Product>, ApiError> {
+) -> Result<impl IntoResponse, ApiError> {
     // Temporary dual enforcement: old roles + new context roles
     if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
         return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
     }
+    let expected_version = etag::if_match_version(&headers, sec.trace_id)?;
+    upd.ensure_valid(sec.trace_id)?;
+    let tracking = normalize_tracking(upd.tracking.as_deref(), sec.trace_id)?;
+    let uom = normalize_uom(upd.uom.as_deref(), sec.trace_id)?;
+    let tare_weight = normalize_tare(upd.tare_weight.as_ref(), sec.trace_id)?;
+    let cost = normalize_cost(upd.cost.as_ref(), sec.trace_id)?;
     let tenant_id = sec.tenant_id;
     let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
     let existing = query_as::<_, Product>(
-    "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code FROM products WHERE id = $1 AND tenant_id = $2",
+    "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL",
     )
     .bind(product_id)
     .bind(tenant_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(state.db.pool())
     .await
     .map_err(|e| ApiError::internal(e, sec.trace_id))?;
     let existing = match existing {
         Some(product) => product,
         None => return Err(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id }),
     };
+    let expected_version = expected_version.unwrap_or(existing.version);
+    if expected_version != existing.version {
+        return Err(etag::version_conflict(sec.trace_id));
+    }
     let image = normalize_image_input(upd.image);
+    let sku = upd.sku.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(sku) = sku.filter(|sku| existing.sku.as_deref() != Some(*sku)) {
+        if let Some(owner) = sku_owner(state.db.pool(), tenant_id, sku, Some(product_id)).await.map_err(|e| ApiError::internal(e, sec.trace_id))? {
+            return Err(sku_conflict(sku, Some(owner), sec.trace_id));
+        }
+    }
+    // The version predicate closes the window between the read above and this write.
     let product = query_as::<_, Product>(
-        "UPDATE products SET name = $1, price = $2, description = $3, active = $4, image = COALESCE($5, image), sku = COALESCE($8, sku), tax_code = COALESCE($9, tax_code)\n         WHERE id = $6 AND tenant_id = $7\n         RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code"
+        "UPDATE products SET name = $1, price = $2, description = $3, active = $4, image = COALESCE($5, image), sku = COALESCE($8, sku), tax_code = COALESCE($9, tax_code), tracking = COALESCE($11, tracking), uom = COALESCE($12, uom), tare_weight = COALESCE($13, tare_weight), cost = COALESCE($14, cost), version = version + 1\n         WHERE id = $6 AND tenant_id = $7 AND version = $10 AND deleted_at IS NULL\n         RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at"
     )
         .bind(upd.name)
         .bind(normalize_scale(&upd.price))
@@ -153,19 +335,46 @@ pub async fn update_product(
         .bind(image)
     .bind(product_id)
     .bind(tenant_id)
-    .bind(upd.sku.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()))
+    .bind(sku)
     .bind(upd.tax_code.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()))
-        .fetch_one(&state.db)
+    .bind(expected_version)
+    .bind(tracking)
+    .bind(uom)
+    .bind(tare_weight)
+    .bind(cost)
+        .fetch_optional(state.db.pool())
     .await
-    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    .map_err(|e| identifier_conflict(e, sec.trace_id))?
+    .ok_or_else(|| etag::version_conflict(sec.trace_id))?;
     let changes = json!({
         "before": product_to_value(&existing),
         "after": product_to_value(&product),
     });
-    record_product_audit(&state.db, &actor, product.id, tenant_id, "updated", changes.clone()).await;
+    record_product_audit(state.db.pool(), &actor, product.id, tenant_id, "updated", changes.clone()).await;
     #[cfg(feature = "kafka")]
     if let Some(audit) = &state.audit_producer { let _ = audit.emit(tenant_id, shared(&actor), "product", Some(product.id), "updated", "product-service", common_audit::AuditSeverity::Info, None, changes, json!({"source":"product-service"})).await; }
-    Ok(Json(product))
+
+    #[cfg(feature = "kafka")]
+    let event = serde_json::json!({
+        "product_id": product.id,
+        "tenant_id": tenant_id,
+        "version": product.version,
+        "previous_version": existing.version,
+        "product": product_to_value(&product),
+    });
+    #[cfg(feature = "kafka")]
+    if let Err(err) = common_kafka::publish(
+        &state.kafka_producer,
+        "product.updated",
+        &product.id.to_string(),
+        &event.to_string(),
+    )
+    .await
+    {
+        tracing::error!("Failed to publish product.updated event: {:?}", err);
+    }
+
+    Ok((etag::etag_header(product.version), Json(product)))
 }
 
 #[derive(Deserialize)]
@@ -179,6 +388,16 @@ pub struct NewProduct {
     pub sku: Option<String>,
     #[serde(default)]
     pub tax_code: Option<String>,
+    /// `none` (default), `serial` or `lot`.
+    #[serde(default)]
+    pub tracking: Option<String>,
+    /// `each` (default), `kg` or `lb`; `price` is per unit.
+    #[serde(default)]
+    pub uom: Option<String>,
+    #[serde(default)]
+    pub tare_weight: Option<BigDecimal>,
+    #[serde(default)]
+    pub cost: Option<BigDecimal>,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -192,6 +411,18 @@ pub struct Product {
     pub active: bool,
     pub sku: Option<String>,
     pub tax_code: Option<String>,
+    /// Serial/lot capture inventory-service requires for this product: `none`, `serial` or `lot`.
+    pub tracking: String,
+    /// Unit `price` is quoted in: `each`, or `kg` / `lb` for weighed products.
+    pub uom: String,
+    /// Default container weight (in `uom`) subtracted from scale readings.
+    pub tare_weight: Option<BigDecimal>,
+    /// Unit cost, rolled up into bundle margins; `None` when not recorded.
+    pub cost: Option<BigDecimal>,
+    /// Optimistic-concurrency version; bumped on every update and exposed as the ETag.
+    pub version: i64,
+    /// Set when the product is soft-deleted; such rows are hidden unless `include_deleted` is requested.
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 pub async fn create_product(
@@ -202,16 +433,27 @@ pub async fn create_product(
     if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
         return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
     }
+    new_product.ensure_valid(sec.trace_id)?;
     let tenant_id = sec.tenant_id;
     let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
 
     let product_id = Uuid::new_v4();
-    let NewProduct { name, price, description, image, sku, tax_code } = new_product;
+    let NewProduct { name, price, description, image, sku, tax_code, tracking, uom, tare_weight, cost } = new_product;
+    let tracking = normalize_tracking(tracking.as_deref(), sec.trace_id)?.unwrap_or_else(|| "none".to_string());
+    let uom = normalize_uom(uom.as_deref(), sec.trace_id)?.unwrap_or_else(|| UnitOfMeasure::Each.as_str().to_string());
+    let tare_weight = normalize_tare(tare_weight.as_ref(), sec.trace_id)?;
+    let cost = normalize_cost(cost.as_ref(), sec.trace_id)?;
     let desc = description.unwrap_or_default();
     let image = normalize_image_input(image).unwrap_or_else(default_product_image);
+    let sku = sku.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(sku) = sku {
+        if let Some(owner) = sku_owner(state.db.pool(), tenant_id, sku, None).await.map_err(|e| ApiError::internal(e, sec.trace_id))? {
+            return Err(sku_conflict(sku, Some(owner), sec.trace_id));
+        }
+    }
 
     let product = query_as::<_, Product>(
-        "INSERT INTO products (id, tenant_id, name, price, description, active, image, sku, tax_code) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code"
+        "INSERT INTO products (id, tenant_id, name, price, description, active, image, sku, tax_code, tracking, uom, tare_weight, cost) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at"
     )
         .bind(product_id)
         .bind(tenant_id)
@@ -220,16 +462,20 @@ pub async fn create_product(
         .bind(desc)
         .bind(true)
     .bind(image)
-    .bind(sku.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()))
+    .bind(sku)
     .bind(tax_code.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()))
-        .fetch_one(&state.db)
+    .bind(tracking)
+    .bind(uom)
+    .bind(tare_weight)
+    .bind(cost)
+        .fetch_one(state.db.pool())
     .await
-    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    .map_err(|e| identifier_conflict(e, sec.trace_id))?;
 
     let changes = json!({
         "after": product_to_value(&product),
     });
-    record_product_audit(&state.db, &actor, product.id, tenant_id, "created", changes.clone()).await;
+    record_product_audit(state.db.pool(), &actor, product.id, tenant_id, "created", changes.clone()).await;
     #[cfg(feature = "kafka")]
     if let Some(audit) = &state.audit_producer { let _ = audit.emit(tenant_id, shared(&actor), "product", Some(product.id), "created", "product-service", common_audit::AuditSeverity::Info, None, json!({"after": product_to_value(&product)}), json!({"source":"product-service"})).await; }
 
@@ -241,15 +487,13 @@ pub async fn create_product(
         "threshold": INVENTORY_DEFAULT_THRESHOLD,
     });
     #[cfg(feature = "kafka")]
-    if let Err(err) = state
-        .kafka_producer
-        .send(
-            FutureRecord::to("product.created")
-                .payload(&event.to_string())
-                .key(&tenant_id.to_string()),
-            Duration::from_secs(0),
-        )
-        .await
+    if let Err(err) = common_kafka::publish(
+        &state.kafka_producer,
+        "product.created",
+        &product.id.to_string(),
+        &event.to_string(),
+    )
+    .await
     {
         tracing::error!("Failed to publish product.created event: {:?}", err);
     }
@@ -258,37 +502,81 @@ pub async fn create_product(
 }
 
 #[derive(Deserialize, Default)]
-pub struct ListProductsQuery { pub sku: Option<String> }
+pub struct ListProductsQuery {
+    pub sku: Option<String>,
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+const PRODUCT_LIST_SELECT: &str =
+    "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE tenant_id = ";
+
+static PRODUCT_SORT: SortSpec = SortSpec {
+    fields: &[
+        SortField::new("name", "name", "text"),
+        SortField::new("price", "price", "numeric"),
+        SortField::new("sku", "COALESCE(sku, '')", "text"),
+    ],
+    default_field: "name",
+    default_direction: SortDirection::Asc,
+    tiebreak: "id",
+};
+
+fn push_product_filters(builder: &mut QueryBuilder<'_, Postgres>, tenant_id: Uuid, sku: Option<&str>, include_deleted: bool) {
+    builder.push_bind(tenant_id);
+    if !include_deleted {
+        builder.push(" AND deleted_at IS NULL");
+    }
+    if let Some(sku) = sku {
+        builder.push(" AND sku = ");
+        builder.push_bind(sku.to_string());
+    }
+}
 
 pub async fn list_products(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Query(q): Query<ListProductsQuery>,
-) -> Result<Json<Vec<Product>>, ApiError> {
+    page: PageRequest,
+) -> Result<Json<Listing<Value>>, ApiError> {
+    ensure_include_deleted_allowed(&sec, q.include_deleted)?;
     let tenant_id = sec.tenant_id;
-
-    let products = if let Some(s) = q.sku.as_ref().and_then(|v| {
-        let t = v.trim(); if t.is_empty() { None } else { Some(t) }
-    }) {
-        query_as::<_, Product>(
-            "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code FROM products WHERE tenant_id = $1 AND sku = $2",
-        )
-        .bind(tenant_id)
-        .bind(s)
-        .fetch_all(&state.db)
+    let plan = page.resolve(&PRODUCT_SORT, sec.trace_id)?;
+    let read_db = state.read_db.get().await;
+    let sku = q.sku.as_deref().map(str::trim).filter(|v| !v.is_empty());
+
+    let mut builder = QueryBuilder::new(PRODUCT_LIST_SELECT);
+    push_product_filters(&mut builder, tenant_id, sku, q.include_deleted);
+    plan.push_cursor_filter(&mut builder);
+    plan.push_order_by(&mut builder);
+    plan.push_limit(&mut builder);
+    let products = builder
+        .build_query_as::<Product>()
+        .fetch_all(read_db)
         .await
-        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let total_estimate = if plan.is_paginated() {
+        let mut estimate = pagination::estimate_query(PRODUCT_LIST_SELECT);
+        push_product_filters(&mut estimate, tenant_id, sku, q.include_deleted);
+        Some(pagination::estimate_rows(read_db, estimate).await.map_err(|e| ApiError::internal(e, sec.trace_id))?)
     } else {
-        query_as::<_, Product>(
-            "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code FROM products WHERE tenant_id = $1",
-        )
-        .bind(tenant_id)
-        .fetch_all(&state.db)
-        .await
-        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        None
     };
 
-    Ok(Json(products))
+    let listing = plan.finish(products, total_estimate, |product, field| {
+        let value = match field {
+            "price" => product.price.to_string(),
+            "sku" => product.sku.clone().unwrap_or_default(),
+            _ => product.name.clone(),
+        };
+        (value, product.id)
+    });
+    let policy = state.redaction_policies.get(state.db.pool(), tenant_id).await;
+    let mut view = policy.for_viewer(&sec, false);
+    let listing = listing.map(|product| redacted_product_value(&mut view, &product));
+    view.finish();
+    Ok(Json(listing))
 }
 
 pub async fn delete_product(
@@ -302,57 +590,148 @@ pub async fn delete_product(
     let tenant_id = sec.tenant_id;
     let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
 
-    let existing = query_as::<_, Product>(
-        "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code FROM products WHERE id = $1 AND tenant_id = $2",
+    // Soft delete: orders and analytics keep referencing the row, so it is only hidden.
+    let mut tx = state.db.pool().begin().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let product = query_as::<_, Product>(
+        "UPDATE products SET deleted_at = now(), version = version + 1 WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NULL RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at",
     )
     .bind(product_id)
     .bind(tenant_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
-    .map_err(|e| ApiError::internal(e, sec.trace_id))?;
-    let existing = match existing {
-        Some(product) => product,
-        None => return Err(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id }),
-    };
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id })?;
+    // Its barcodes are released with it, like its SKU, and claimed back on restore.
+    sqlx::query("UPDATE product_barcodes SET deleted_at = $3 WHERE tenant_id = $1 AND product_id = $2 AND deleted_at IS NULL")
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(product.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let changes = json!({
+        "before": { "deleted_at": null },
+        "after": product_to_value(&product),
+    });
+    record_product_audit(state.db.pool(), &actor, product.id, tenant_id, "deleted", changes.clone()).await;
+    #[cfg(feature = "kafka")]
+    if let Some(audit) = &state.audit_producer { let _ = audit.emit(tenant_id, shared(&actor), "product", Some(product.id), "deleted", "product-service", common_audit::AuditSeverity::Info, None, changes, json!({"source":"product-service"})).await; }
+
+    #[cfg(feature = "kafka")]
+    publish_product_lifecycle(&state, "product.deleted", &product).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    let result = query("DELETE FROM products WHERE id = $1 AND tenant_id = $2")
+pub async fn restore_product(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(product_id): axum::extract::Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::Manager | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Manager", trace_id: sec.trace_id });
+    }
+    let tenant_id = sec.tenant_id;
+    let actor = AuditActor { id: sec.actor.id, name: sec.actor.name.clone(), email: sec.actor.email.clone() };
+
+    let existing = query_as::<_, Product>(
+        "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE id = $1 AND tenant_id = $2",
+    )
+    .bind(product_id)
+    .bind(tenant_id)
+    .fetch_optional(state.db.pool())
+    .await
+    .map_err(|e| ApiError::internal(e, sec.trace_id))?
+    .ok_or(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id })?;
+    if existing.deleted_at.is_none() {
+        return Err(ApiError::Conflict { code: "product_not_deleted", trace_id: sec.trace_id, message: None });
+    }
+    // A merged duplicate's stock and sales now belong to the product it was merged into.
+    let merged_into: Option<Uuid> = sqlx::query_scalar("SELECT merged_into FROM products WHERE id = $1 AND tenant_id = $2")
         .bind(product_id)
         .bind(tenant_id)
-        .execute(&state.db)
+        .fetch_one(state.db.pool())
         .await
         .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    if let Some(canonical) = merged_into {
+        return Err(ApiError::Conflict { code: "product_merged", trace_id: sec.trace_id, message: Some(format!("This product was merged into {canonical}")) });
+    }
 
-    if result.rows_affected() == 0 {
-        return Err(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id });
+    // Another live product may have taken over the SKU or a barcode while this one was deleted.
+    if let Some(sku) = existing.sku.as_deref() {
+        if let Some(owner) = sku_owner(state.db.pool(), tenant_id, sku, Some(product_id)).await.map_err(|e| ApiError::internal(e, sec.trace_id))? {
+            return Err(sku_conflict(sku, Some(owner), sec.trace_id));
+        }
+    }
+    let released: Vec<String> = sqlx::query_scalar("SELECT barcode FROM product_barcodes WHERE tenant_id = $1 AND product_id = $2 AND deleted_at IS NOT NULL")
+        .bind(tenant_id)
+        .bind(product_id)
+        .fetch_all(state.db.pool())
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    if let Some((barcode, owner)) = barcode_owners(state.db.pool(), tenant_id, &released, Some(product_id))
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?
+        .into_iter()
+        .next()
+    {
+        return Err(barcode_conflict(&barcode, Some(owner), sec.trace_id));
     }
 
+    let mut tx = state.db.pool().begin().await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let product = query_as::<_, Product>(
+        "UPDATE products SET deleted_at = NULL, version = version + 1 WHERE id = $1 AND tenant_id = $2 AND deleted_at IS NOT NULL RETURNING id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at",
+    )
+    .bind(product_id)
+    .bind(tenant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| identifier_conflict(e, sec.trace_id))?
+    .ok_or(ApiError::Conflict { code: "product_not_deleted", trace_id: sec.trace_id, message: None })?;
+    sqlx::query("UPDATE product_barcodes SET deleted_at = NULL WHERE tenant_id = $1 AND product_id = $2 AND deleted_at IS NOT NULL")
+        .bind(tenant_id)
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| identifier_conflict(e, sec.trace_id))?;
+    tx.commit().await.map_err(|e| identifier_conflict(e, sec.trace_id))?;
+
     let changes = json!({
         "before": product_to_value(&existing),
+        "after": product_to_value(&product),
     });
-    record_product_audit(&state.db, &actor, existing.id, tenant_id, "deleted", changes.clone()).await;
+    record_product_audit(state.db.pool(), &actor, product.id, tenant_id, "restored", changes.clone()).await;
     #[cfg(feature = "kafka")]
-    if let Some(audit) = &state.audit_producer { let _ = audit.emit(tenant_id, shared(&actor), "product", Some(existing.id), "deleted", "product-service", common_audit::AuditSeverity::Info, None, json!({"before": product_to_value(&existing)}), json!({"source":"product-service"})).await; }
+    if let Some(audit) = &state.audit_producer { let _ = audit.emit(tenant_id, shared(&actor), "product", Some(product.id), "restored", "product-service", common_audit::AuditSeverity::Info, None, changes, json!({"source":"product-service"})).await; }
 
     #[cfg(feature = "kafka")]
+    publish_product_lifecycle(&state, "product.restored", &product).await;
+
+    Ok((etag::etag_header(product.version), Json(product)))
+}
+
+/// `product.deleted` / `product.restored`: lets inventory and POS caches drop or re-add the product.
+#[cfg(feature = "kafka")]
+pub(crate) async fn publish_product_lifecycle(state: &AppState, topic: &str, product: &Product) {
     let event = serde_json::json!({
-        "product_id": product_id,
-        "tenant_id": tenant_id,
+        "product_id": product.id,
+        "tenant_id": product.tenant_id,
+        "version": product.version,
+        "deleted_at": product.deleted_at,
+        "sku": product.sku,
     });
-    #[cfg(feature = "kafka")]
-    if let Err(err) = state
-        .kafka_producer
-        .send(
-            FutureRecord::to("product.deleted")
-                .payload(&event.to_string())
-                .key(&tenant_id.to_string()),
-            Duration::from_secs(0),
-        )
-        .await
+    if let Err(err) = common_kafka::publish(
+        &state.kafka_producer,
+        topic,
+        &product.id.to_string(),
+        &event.to_string(),
+    )
+    .await
     {
-        tracing::error!("Failed to publish product.deleted event: {:?}", err);
+        tracing::error!("Failed to publish {} event: {:?}", topic, err);
     }
-
-    Ok(StatusCode::NO_CONTENT)
 }
 
 #[derive(Deserialize)]
@@ -385,7 +764,7 @@ pub async fn list_product_audit(
     let mut limit = params.limit.unwrap_or(10);
     limit = limit.clamp(1, 50);
 
-    let entries = sqlx::query_as::<_, ProductAuditEntry>(
+    let mut entries = sqlx::query_as::<_, ProductAuditEntry>(
         "SELECT id, action, changes, actor_id, actor_name, actor_email, created_at
          FROM product_audit_log
          WHERE product_id = $1 AND tenant_id = $2
@@ -395,34 +774,98 @@ pub async fn list_product_audit(
     .bind(product_id)
     .bind(tenant_id)
     .bind(limit)
-    .fetch_all(&state.db)
+    .fetch_all(state.db.pool())
     .await
     .map_err(|e| ApiError::internal(e, sec.trace_id))?;
 
+    let policy = state.redaction_policies.get(state.db.pool(), tenant_id).await;
+    let mut view = policy.for_viewer(&sec, false);
+    for entry in &mut entries {
+        view.redact_changes(&mut entry.changes);
+    }
+    view.finish();
     Ok(Json(entries))
 }
 
 #[derive(Deserialize)]
-pub struct LookupQuery { pub sku: String }
+pub struct LookupQuery {
+    pub sku: Option<String>,
+    /// Resolves a scanned barcode instead of a SKU.
+    pub barcode: Option<String>,
+}
 
 pub async fn lookup_product_by_sku(
     State(state): State<AppState>,
     SecurityCtxExtractor(sec): SecurityCtxExtractor,
     Query(params): Query<LookupQuery>,
-) -> Result<Json<Product>, ApiError> {
+) -> Result<Json<Value>, ApiError> {
     let tenant_id = sec.tenant_id;
-    let sku = params.sku.trim();
-    if sku.is_empty() {
-        return Err(ApiError::BadRequest { code: "missing_sku", trace_id: sec.trace_id, message: Some("Query param 'sku' is required".into()) });
-    }
-    let product = sqlx::query_as::<_, Product>(
-        "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code FROM products WHERE tenant_id = $1 AND sku = $2 AND active = TRUE"
-    )
-    .bind(tenant_id)
-    .bind(sku)
-    .fetch_optional(&state.db)
+    let sku = params.sku.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    let barcode = params.barcode.as_deref().and_then(normalize_barcode);
+    let query = match (sku, barcode) {
+        (Some(sku), _) => sqlx::query_as::<_, Product>(
+            "SELECT id, tenant_id, name, price, description, image, active, sku, tax_code, tracking, uom, tare_weight, cost, version, deleted_at FROM products WHERE tenant_id = $1 AND sku = $2 AND active = TRUE AND deleted_at IS NULL"
+        )
+        .bind(tenant_id)
+        .bind(sku.to_string()),
+        (None, Some(barcode)) => sqlx::query_as::<_, Product>(
+            "SELECT p.id, p.tenant_id, p.name, p.price, p.description, p.image, p.active, p.sku, p.tax_code, p.tracking, p.uom, p.tare_weight, p.cost, p.version, p.deleted_at
+             FROM product_barcodes b JOIN products p ON p.id = b.product_id AND p.tenant_id = b.tenant_id
+             WHERE b.tenant_id = $1 AND b.barcode = $2 AND b.deleted_at IS NULL AND p.active = TRUE AND p.deleted_at IS NULL"
+        )
+        .bind(tenant_id)
+        .bind(barcode),
+        (None, None) => {
+            return Err(ApiError::BadRequest { code: "missing_sku", trace_id: sec.trace_id, message: Some("Query param 'sku' or 'barcode' is required".into()) })
+        }
+    };
+    let product = query
+    .fetch_optional(state.db.pool())
     .await
     .map_err(|e| ApiError::internal(e, sec.trace_id))?
     .ok_or(ApiError::NotFound { code: "product_not_found", trace_id: sec.trace_id })?;
-    Ok(Json(product))
+    let policy = state.redaction_policies.get(state.db.pool(), tenant_id).await;
+    let mut view = policy.for_viewer(&sec, false);
+    let body = redacted_product_value(&mut view, &product);
+    view.finish();
+    Ok(Json(body))
+}
+
+/// Tables bundled into a tenant offboarding export; each query binds the tenant id as `$1`.
+const TENANT_EXPORT_QUERIES: &[(&str, &str)] = &[
+    ("products", "SELECT * FROM products WHERE tenant_id = $1"),
+    ("product_audit_log", "SELECT * FROM product_audit_log WHERE tenant_id = $1 ORDER BY created_at"),
+    ("product_field_changes", "SELECT * FROM product_field_changes WHERE tenant_id = $1"),
+    ("product_components", "SELECT * FROM product_components WHERE tenant_id = $1"),
+    ("product_modifier_groups", "SELECT * FROM product_modifier_groups WHERE tenant_id = $1"),
+    ("product_modifiers", "SELECT * FROM product_modifiers WHERE tenant_id = $1"),
+    ("product_barcodes", "SELECT * FROM product_barcodes WHERE tenant_id = $1"),
+    ("audit_events", "SELECT * FROM audit_events WHERE tenant_id = $1 ORDER BY occurred_at"),
+];
+
+/// Offboarding export pulled by auth-service's tenant export job.
+pub async fn export_tenant_data(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Value>, ApiError> {
+    common_security::ensure_capability(&sec, common_security::Capability::GdprManage)
+        .map_err(|_| ApiError::ForbiddenMissingRole { role: "gdpr_manage", trace_id: sec.trace_id })?;
+    if sec.tenant_id != tenant_id {
+        return Err(ApiError::Forbidden { trace_id: sec.trace_id });
+    }
+
+    let mut tables = serde_json::Map::new();
+    for (name, query) in TENANT_EXPORT_QUERIES {
+        let rows: String = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM ({query}) t"
+        ))
+        .bind(tenant_id)
+        .fetch_one(state.db.pool())
+        .await
+        .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        let rows = serde_json::from_str(&rows).map_err(|e| ApiError::internal(e, sec.trace_id))?;
+        tables.insert((*name).to_string(), rows);
+    }
+    Ok(Json(Value::Object(tables)))
 }
@@ -0,0 +1,450 @@
+//! Per-tenant view redaction policies (`redaction_policies`): which fields a viewer needs a
+//! minimum role or a capability to see. Applied by the product list/get/audit endpoints.
+
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use common_security::{has_capability, Capability, Role, SecurityContext, SecurityCtxExtractor};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::audit_handlers::record_view_redactions;
+use crate::view_redaction::apply_redaction;
+use crate::ApiError;
+
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+const MAX_RULES: usize = 100;
+
+/// Fields named by any loaded tenant policy; extends the metrics label whitelist so configured
+/// fields are still broken out when `AUDIT_VIEW_REDACTION_PATHS` is set.
+static POLICY_FIELDS: Lazy<RwLock<HashSet<String>>> = Lazy::new(|| RwLock::new(HashSet::new()));
+
+pub fn policy_fields() -> HashSet<String> {
+    POLICY_FIELDS.read().map(|fields| fields.clone()).unwrap_or_default()
+}
+
+/// Wire and storage shape of one rule. At least one of `min_role` / `capability` is set; a viewer
+/// meeting either sees the field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RedactionRuleConfig {
+    pub field: String,
+    #[serde(default)]
+    pub min_role: Option<String>,
+    #[serde(default)]
+    pub capability: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct RedactionRule {
+    field: String,
+    path: Vec<String>,
+    min_role: Option<Role>,
+    capability: Option<Capability>,
+}
+
+/// Privilege order used for `min_role`; SuperAdmin is never redacted.
+fn role_rank(role: &Role) -> u8 {
+    match role {
+        Role::SuperAdmin => 5,
+        Role::Admin => 4,
+        Role::Manager => 3,
+        Role::Support => 2,
+        Role::Inventory | Role::Cashier => 1,
+        Role::Unknown(_) => 0,
+    }
+}
+
+impl RedactionRule {
+    fn parse(config: &RedactionRuleConfig) -> Result<Self, String> {
+        let field = config.field.trim();
+        let path: Vec<String> = field.split('.').map(|seg| seg.trim().to_string()).collect();
+        if field.is_empty() || path.iter().any(String::is_empty) {
+            return Err(format!("invalid field path '{}'", config.field));
+        }
+        let min_role = match config.min_role.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+            Some(raw) => match Role::parse_role(raw) {
+                Role::Unknown(_) => return Err(format!("unknown role '{raw}' for field '{field}'")),
+                role => Some(role),
+            },
+            None => None,
+        };
+        let capability = match config.capability.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            Some(raw) => Some(Capability::parse(raw).ok_or_else(|| format!("unknown capability '{raw}' for field '{field}'"))?),
+            None => None,
+        };
+        if min_role.is_none() && capability.is_none() {
+            return Err(format!("field '{field}' needs a min_role or capability"));
+        }
+        Ok(Self { field: field.to_string(), path, min_role, capability })
+    }
+
+    fn visible_to(&self, sec: &SecurityContext) -> bool {
+        if sec.roles.contains(&Role::SuperAdmin) {
+            return true;
+        }
+        let by_role = self
+            .min_role
+            .as_ref()
+            .is_some_and(|min| sec.roles.iter().any(|r| role_rank(r) >= role_rank(min)));
+        by_role || self.capability.is_some_and(|cap| has_capability(sec, cap))
+    }
+
+    fn config(&self) -> RedactionRuleConfig {
+        RedactionRuleConfig {
+            field: self.field.clone(),
+            min_role: self.min_role.as_ref().map(|r| r.as_str().to_string()),
+            capability: self.capability.map(|c| c.as_str().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    rules: Vec<RedactionRule>,
+    /// True when the tenant has no rows and the `AUDIT_VIEW_REDACTION_PATHS` default applies.
+    pub is_default: bool,
+}
+
+impl RedactionPolicy {
+    /// `AUDIT_VIEW_REDACTION_PATHS`, each path visible to Admin and above.
+    pub fn env_default() -> Self {
+        let raw = std::env::var("AUDIT_VIEW_REDACTION_PATHS").unwrap_or_default();
+        let rules = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter_map(|field| {
+                RedactionRule::parse(&RedactionRuleConfig { field: field.to_string(), min_role: Some("admin".into()), capability: None }).ok()
+            })
+            .collect();
+        Self { rules, is_default: true }
+    }
+
+    /// Validate tenant rules; duplicate fields are rejected rather than silently merged.
+    pub fn from_rules(configs: &[RedactionRuleConfig]) -> Result<Self, String> {
+        if configs.len() > MAX_RULES {
+            return Err(format!("at most {MAX_RULES} rules per tenant"));
+        }
+        let mut seen = HashSet::new();
+        let mut rules = Vec::with_capacity(configs.len());
+        for config in configs {
+            let rule = RedactionRule::parse(config)?;
+            if !seen.insert(rule.field.clone()) {
+                return Err(format!("duplicate field '{}'", rule.field));
+            }
+            rules.push(rule);
+        }
+        Ok(Self { rules, is_default: false })
+    }
+
+    pub fn rules(&self) -> Vec<RedactionRuleConfig> {
+        self.rules.iter().map(RedactionRule::config).collect()
+    }
+
+    /// The rules hiding fields from this viewer.
+    pub fn for_viewer<'a>(&'a self, sec: &SecurityContext, include_redacted: bool) -> ViewRedaction<'a> {
+        ViewRedaction {
+            tenant_id: sec.tenant_id,
+            role: format!("{:?}", sec.roles.first().cloned().unwrap_or(Role::Unknown("none".into()))),
+            hidden: self.rules.iter().filter(|rule| !rule.visible_to(sec)).collect(),
+            include_redacted,
+            applied: Vec::new(),
+        }
+    }
+}
+
+/// Redactions for one request. Call [`ViewRedaction::finish`] once to publish the counts to the
+/// `VIEW_REDACTIONS` metrics.
+pub struct ViewRedaction<'a> {
+    tenant_id: Uuid,
+    role: String,
+    hidden: Vec<&'a RedactionRule>,
+    include_redacted: bool,
+    applied: Vec<String>,
+}
+
+impl ViewRedaction<'_> {
+    /// Nothing is hidden from this viewer.
+    pub fn is_privileged(&self) -> bool {
+        self.hidden.is_empty()
+    }
+
+    /// Top-level fields hidden from this viewer, e.g. for filtering per-field change rows.
+    pub fn hidden_top_level_fields(&self) -> Vec<String> {
+        self.hidden.iter().filter(|rule| rule.path.len() == 1).map(|rule| rule.field.clone()).collect()
+    }
+
+    /// Redact a product document (or snapshot); returns the fields redacted.
+    pub fn redact(&mut self, doc: &mut Value) -> Vec<String> {
+        let mut fields = Vec::new();
+        for rule in &self.hidden {
+            if apply_redaction(doc, &rule.path, self.include_redacted) {
+                fields.push(rule.field.clone());
+            }
+        }
+        self.applied.extend(fields.iter().cloned());
+        fields
+    }
+
+    /// Redact the `before`/`after` snapshots of a product audit entry's changes.
+    pub fn redact_changes(&mut self, changes: &mut Value) -> Vec<String> {
+        let mut fields = Vec::new();
+        for key in ["before", "after"] {
+            if let Some(snapshot) = changes.get_mut(key) {
+                for field in self.redact(snapshot) {
+                    if !fields.contains(&field) {
+                        fields.push(field);
+                    }
+                }
+            }
+        }
+        fields
+    }
+
+    /// Redact an audit event: each path is tried against the payload (and its `before`/`after`
+    /// product snapshots), then the meta, as `AUDIT_VIEW_REDACTION_PATHS` always was.
+    pub fn redact_event(&mut self, payload: &mut Value, meta: &mut Value) -> Vec<String> {
+        let mut fields = Vec::new();
+        for rule in &self.hidden {
+            let mut applied = apply_redaction(payload, &rule.path, self.include_redacted);
+            for key in ["before", "after"] {
+                if let Some(snapshot) = payload.get_mut(key) {
+                    applied |= apply_redaction(snapshot, &rule.path, self.include_redacted);
+                }
+            }
+            if !applied {
+                applied = apply_redaction(meta, &rule.path, self.include_redacted);
+            }
+            if applied {
+                fields.push(rule.field.clone());
+            }
+        }
+        self.applied.extend(fields.iter().cloned());
+        fields
+    }
+
+    /// Publish this request's redactions; returns how many were applied.
+    pub fn finish(self) -> u64 {
+        record_view_redactions(self.tenant_id, &self.role, &self.applied);
+        self.applied.len() as u64
+    }
+}
+
+type CachedPolicies = HashMap<Uuid, (Instant, Arc<RedactionPolicy>)>;
+
+/// Tenant policies cached for `REDACTION_POLICY_CACHE_TTL_SECS` (default 60). Updates through
+/// this instance invalidate immediately; other replicas pick them up when their entry expires.
+#[derive(Clone)]
+pub struct RedactionPolicyCache {
+    entries: Arc<RwLock<CachedPolicies>>,
+    ttl: Duration,
+}
+
+impl Default for RedactionPolicyCache {
+    fn default() -> Self {
+        let ttl = std::env::var("REDACTION_POLICY_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_TTL_SECS);
+        Self { entries: Arc::new(RwLock::new(HashMap::new())), ttl: Duration::from_secs(ttl) }
+    }
+}
+
+impl RedactionPolicyCache {
+    /// The tenant's policy. If it cannot be loaded a stale entry is reused, else the env default.
+    pub async fn get(&self, db: &PgPool, tenant_id: Uuid) -> Arc<RedactionPolicy> {
+        let cached = self.entries.read().ok().and_then(|entries| entries.get(&tenant_id).cloned());
+        if let Some((loaded_at, policy)) = &cached {
+            if loaded_at.elapsed() < self.ttl {
+                return policy.clone();
+            }
+        }
+        match load_policy(db, tenant_id).await {
+            Ok(policy) => self.insert(tenant_id, policy),
+            Err(err) => {
+                tracing::warn!(?err, %tenant_id, "Failed to load redaction policy");
+                cached.map(|(_, policy)| policy).unwrap_or_else(|| Arc::new(RedactionPolicy::env_default()))
+            }
+        }
+    }
+
+    pub fn insert(&self, tenant_id: Uuid, policy: RedactionPolicy) -> Arc<RedactionPolicy> {
+        if let Ok(mut fields) = POLICY_FIELDS.write() {
+            fields.extend(policy.rules.iter().map(|rule| rule.field.clone()));
+        }
+        let policy = Arc::new(policy);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(tenant_id, (Instant::now(), policy.clone()));
+        }
+        policy
+    }
+}
+
+async fn load_policy(db: &PgPool, tenant_id: Uuid) -> Result<RedactionPolicy, sqlx::Error> {
+    let rows = sqlx::query_as::<_, RedactionRuleConfig>(
+        "SELECT field, min_role, capability FROM redaction_policies WHERE tenant_id = $1 ORDER BY field",
+    )
+    .bind(tenant_id)
+    .fetch_all(db)
+    .await?;
+    if rows.is_empty() {
+        return Ok(RedactionPolicy::env_default());
+    }
+    // Rows were validated on write; skip any that no longer parse (e.g. a retired capability).
+    let rules = rows
+        .iter()
+        .filter_map(|row| match RedactionRule::parse(row) {
+            Ok(rule) => Some(rule),
+            Err(reason) => {
+                tracing::warn!(%tenant_id, field = %row.field, %reason, "Ignoring invalid redaction rule");
+                None
+            }
+        })
+        .collect();
+    Ok(RedactionPolicy { rules, is_default: false })
+}
+
+#[derive(Serialize)]
+pub struct RedactionPolicyResponse {
+    pub tenant_id: Uuid,
+    /// `true` while the tenant has no rules of its own and the service default applies.
+    pub is_default: bool,
+    pub rules: Vec<RedactionRuleConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateRedactionPolicy {
+    pub rules: Vec<RedactionRuleConfig>,
+}
+
+fn ensure_policy_admin(sec: &SecurityContext) -> Result<(), ApiError> {
+    if !sec.roles.iter().any(|r| matches!(r, Role::Admin | Role::SuperAdmin)) {
+        return Err(ApiError::ForbiddenMissingRole { role: "Admin", trace_id: sec.trace_id });
+    }
+    Ok(())
+}
+
+/// `GET /admin/redaction_policy`: the caller's tenant policy as currently enforced.
+pub async fn get_redaction_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+) -> Result<Json<RedactionPolicyResponse>, ApiError> {
+    ensure_policy_admin(&sec)?;
+    let policy = load_policy(state.db.pool(), sec.tenant_id).await.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    let updated_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT max(updated_at) FROM redaction_policies WHERE tenant_id = $1")
+            .bind(sec.tenant_id)
+            .fetch_one(state.db.pool())
+            .await
+            .map_err(|e| ApiError::internal(e, sec.trace_id))?;
+    Ok(Json(RedactionPolicyResponse { tenant_id: sec.tenant_id, is_default: policy.is_default, rules: policy.rules(), updated_at }))
+}
+
+/// `PUT /admin/redaction_policy`: replace the tenant's rules. An empty list reverts to the default.
+pub async fn put_redaction_policy(
+    State(state): State<AppState>,
+    SecurityCtxExtractor(sec): SecurityCtxExtractor,
+    Json(body): Json<UpdateRedactionPolicy>,
+) -> Result<Json<RedactionPolicyResponse>, ApiError> {
+    ensure_policy_admin(&sec)?;
+    let policy = RedactionPolicy::from_rules(&body.rules).map_err(|message| ApiError::BadRequest {
+        code: "invalid_redaction_policy",
+        trace_id: sec.trace_id,
+        message: Some(message),
+    })?;
+    let rules = policy.rules();
+
+    let result: Result<DateTime<Utc>, sqlx::Error> = async {
+        let mut tx = state.db.pool().begin().await?;
+        sqlx::query("DELETE FROM redaction_policies WHERE tenant_id = $1").bind(sec.tenant_id).execute(&mut *tx).await?;
+        let now: DateTime<Utc> = sqlx::query_scalar("SELECT now()").fetch_one(&mut *tx).await?;
+        for rule in &rules {
+            sqlx::query(
+                "INSERT INTO redaction_policies (tenant_id, field, min_role, capability, updated_at) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(sec.tenant_id)
+            .bind(&rule.field)
+            .bind(rule.min_role.as_deref())
+            .bind(rule.capability.as_deref())
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(now)
+    }
+    .await;
+    let updated_at = result.map_err(|e| ApiError::internal(e, sec.trace_id))?;
+
+    let policy = if rules.is_empty() { RedactionPolicy::env_default() } else { policy };
+    let policy = state.redaction_policies.insert(sec.tenant_id, policy);
+    tracing::info!(tenant_id = %sec.tenant_id, rules = rules.len(), "Redaction policy updated");
+    Ok(Json(RedactionPolicyResponse {
+        tenant_id: sec.tenant_id,
+        is_default: policy.is_default,
+        rules: policy.rules(),
+        updated_at: (!rules.is_empty()).then_some(updated_at),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ctx(roles: Vec<Role>) -> SecurityContext {
+        SecurityContext { tenant_id: Uuid::new_v4(), actor: Default::default(), roles, trace_id: None, residency: None, capability_cache: Default::default(), system: None }
+    }
+
+    fn rule(field: &str, min_role: Option<&str>, capability: Option<&str>) -> RedactionRuleConfig {
+        RedactionRuleConfig { field: field.into(), min_role: min_role.map(Into::into), capability: capability.map(Into::into) }
+    }
+
+    #[test]
+    fn min_role_or_capability_reveals_the_field() {
+        let policy = RedactionPolicy::from_rules(&[
+            rule("price", Some("manager"), None),
+            rule("customer.email", Some("admin"), Some("customer_view")),
+        ])
+        .unwrap();
+
+        let mut doc = json!({"price": "4.50", "customer": {"email": "a@b.c"}});
+        let mut cashier = policy.for_viewer(&ctx(vec![Role::Cashier]), false);
+        assert_eq!(cashier.redact(&mut doc), vec!["price".to_string()]);
+        assert!(doc.get("price").is_none());
+        assert_eq!(doc["customer"]["email"], json!("a@b.c"), "cashiers hold customer_view by default");
+
+        assert!(policy.for_viewer(&ctx(vec![Role::Manager]), false).is_privileged());
+        assert!(policy.for_viewer(&ctx(vec![Role::SuperAdmin]), false).is_privileged());
+        let support = policy.for_viewer(&ctx(vec![Role::Support]), true);
+        assert_eq!(support.hidden_top_level_fields(), vec!["price".to_string()]);
+    }
+
+    #[test]
+    fn audit_events_redact_payload_snapshots_then_meta() {
+        let policy = RedactionPolicy::from_rules(&[rule("price", Some("admin"), None), rule("ip", Some("admin"), None)]).unwrap();
+        let mut view = policy.for_viewer(&ctx(vec![Role::Support]), true);
+        let mut payload = json!({"before": {"price": "1.00"}, "after": {"price": "2.00"}});
+        let mut meta = json!({"ip": "10.0.0.1"});
+        assert_eq!(view.redact_event(&mut payload, &mut meta), vec!["price".to_string(), "ip".to_string()]);
+        assert_eq!(payload["after"]["price"], json!("****"));
+        assert_eq!(meta["ip"], json!("****"));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(RedactionPolicy::from_rules(&[rule("price", None, None)]).is_err());
+        assert!(RedactionPolicy::from_rules(&[rule("price", Some("owner"), None)]).is_err());
+        assert!(RedactionPolicy::from_rules(&[rule("price", None, Some("see_prices"))]).is_err());
+        assert!(RedactionPolicy::from_rules(&[rule("a..b", Some("admin"), None)]).is_err());
+        assert!(RedactionPolicy::from_rules(&[rule("price", Some("admin"), None), rule(" price", Some("manager"), None)]).is_err());
+    }
+}
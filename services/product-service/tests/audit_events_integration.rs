@@ -132,3 +132,95 @@ async fn audit_events_redaction_flow() {
     let first_admin = &json_admin["data"][0];
     assert_eq!(first_admin["payload"]["customer"]["email"], serde_json::json!("cust@example.com"));
 }
+
+#[cfg(feature = "kafka")]
+#[tokio::test]
+async fn audit_events_keyset_pages_and_ndjson_export() {
+    let db_url = match env::var("TEST_AUDIT_DB_URL") { Ok(v) => v, Err(_) => { eprintln!("skipping: TEST_AUDIT_DB_URL not set"); return; } };
+    let pool = PgPool::connect(&db_url).await.expect("connect db");
+    sqlx::query(r#"CREATE TABLE IF NOT EXISTS audit_events (
+        event_id UUID PRIMARY KEY,
+        event_version INT NOT NULL,
+        tenant_id UUID NOT NULL,
+        actor_id UUID NULL,
+        actor_name TEXT NULL,
+        actor_email TEXT NULL,
+        entity_type TEXT NOT NULL,
+        entity_id UUID NULL,
+        action TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        source_service TEXT NOT NULL,
+        occurred_at TIMESTAMPTZ NOT NULL,
+        trace_id UUID NULL,
+        payload JSONB NOT NULL,
+        meta JSONB NOT NULL
+    )"#).execute(&pool).await.expect("create table");
+
+    // Three events sharing one timestamp: only the event_id tiebreak separates them.
+    let tenant_id = Uuid::new_v4();
+    let occurred_at = chrono::Utc::now();
+    for _ in 0..3 {
+        sqlx::query(r#"INSERT INTO audit_events (event_id,event_version,tenant_id,entity_type,action,severity,source_service,occurred_at,payload,meta)
+            VALUES ($1,1,$2,'product','update','INFO','product-service',$3,'{}','{}')"#)
+            .bind(Uuid::new_v4())
+            .bind(tenant_id)
+            .bind(occurred_at)
+            .execute(&pool).await.unwrap();
+    }
+
+    #[cfg(feature = "kafka")] use rdkafka::producer::FutureProducer;
+    let kafka: FutureProducer = rdkafka::ClientConfig::new()
+        .set("bootstrap.servers","localhost:9092")
+        .set("message.timeout.ms","5000")
+        .create()
+        .expect("future producer");
+    let state = AppState::new(pool.clone(), kafka, dummy_verifier().await, None);
+    let app = Router::new().route("/audit/events", get(audit_search)).with_state(state);
+    let get_page = |uri: String| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .uri(uri)
+                .header("X-Tenant-ID", tenant_id.to_string())
+                .header("X-User-ID", Uuid::new_v4().to_string())
+                .header("X-Roles", "Admin")
+                .body(Body::empty()).unwrap();
+            let resp = app.oneshot(req).await.unwrap();
+            let status = resp.status();
+            let content_type = resp.headers().get("content-type").map(|v| v.to_str().unwrap().to_string());
+            let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+            (status, content_type, bytes)
+        }
+    };
+
+    let (status, _, body) = get_page("/audit/events?limit=2".into()).await;
+    assert!(status.is_success());
+    let first: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(first["count"], 2);
+    assert_eq!(first["has_more"], true);
+    let cursor = first["next_cursor"].as_str().unwrap().to_string();
+    let cursor_event = first["next_cursor_event_id"].as_str().unwrap().to_string();
+
+    let (_, _, body) = get_page(format!(
+        "/audit/events?limit=2&before={}&before_event_id={cursor_event}",
+        cursor.replace('+', "%2B")
+    )).await;
+    let second: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(second["count"], 1);
+    assert_eq!(second["has_more"], false);
+    assert!(second["next_cursor"].is_null());
+    let seen: std::collections::HashSet<String> = first["data"].as_array().unwrap().iter().chain(second["data"].as_array().unwrap()).map(|e| e["event_id"].to_string()).collect();
+    assert_eq!(seen.len(), 3);
+
+    let (status, _, body) = get_page("/audit/events?limit=500".into()).await;
+    assert_eq!(status.as_u16(), 400);
+    let err: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(err["code"], "page_size_too_large");
+
+    let (status, content_type, body) = get_page("/audit/events?format=ndjson".into()).await;
+    assert!(status.is_success());
+    assert_eq!(content_type.as_deref(), Some("application/x-ndjson"));
+    let lines: Vec<serde_json::Value> = std::str::from_utf8(&body).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|e| e["tenant_id"] == serde_json::json!(tenant_id)));
+}
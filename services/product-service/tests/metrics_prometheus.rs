@@ -87,7 +87,7 @@ async fn prometheus_metrics_exposed() {
             use std::collections::HashMap;
             let mut converted: HashMap<(String,String,String), u64> = HashMap::new();
             for ((tenant, role, field), count) in map.iter() { converted.insert((tenant.to_string(), role.clone(), field.clone()), *count); }
-            product_service::metrics::update_redaction_counters(product_service::audit_handlers::view_redactions_count(), &converted);
+            product_service::metrics::update_redaction_counters(product_service::audit_handlers::view_redactions_count(), &converted, &Default::default());
         }
         let out = product_service::metrics::gather(true);
         (axum::http::StatusCode::OK, out)
@@ -0,0 +1,81 @@
+//! Optimistic concurrency on product updates against Postgres: reads carry the version as an ETag,
+//! updates need it back in If-Match, and a stale tag loses to the write that bumped it. Needs
+//! Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+#![cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use common_test_fixtures::{itests_enabled, ProductFixture, TenantFixture, TestPostgres, TestSigner};
+use http_body_util::BodyExt;
+use product_service::app_state::AppState;
+use product_service::product_handlers::{get_product, update_product};
+use serde_json::{json, Value};
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+struct Reply {
+    status: StatusCode,
+    etag: Option<String>,
+    body: Value,
+}
+
+async fn call(app: &Router, tenant: Uuid, method: Method, uri: &str, if_match: Option<&str>, body: Option<Value>) -> Reply {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", "admin")
+        .header("content-type", "application/json");
+    if let Some(tag) = if_match {
+        request = request.header(header::IF_MATCH, tag);
+    }
+    let request = request.body(body.map_or_else(Body::empty, |b| Body::from(b.to_string()))).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let etag = response.headers().get(header::ETAG).map(|v| v.to_str().unwrap().to_string());
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    Reply { status, etag, body: serde_json::from_slice(&bytes).unwrap_or(Value::Null) }
+}
+
+#[tokio::test]
+async fn updates_need_the_current_etag() {
+    if !itests_enabled() {
+        return;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service"]).await.expect("migrate");
+    let db = postgres.pool();
+    let tenant = TenantFixture::new().insert(db).await.unwrap().id;
+    let product = ProductFixture::new(tenant).insert(db).await.unwrap().id;
+    let state = AppState::new(db.clone(), (), TestSigner::generate().verifier(), None);
+    let app = Router::new().route("/products/:id", get(get_product).put(update_product)).with_state(state);
+    let uri = format!("/products/{product}");
+    let edit = |name: &str| Some(json!({"name": name, "price": "3.25", "description": "", "active": true}));
+
+    let read = call(&app, tenant, Method::GET, &uri, None, None).await;
+    assert_eq!(read.status, StatusCode::OK);
+    let tag = read.etag.expect("reads carry an ETag");
+    assert_eq!(tag, format!("\"{}\"", read.body["version"]));
+
+    let missing = call(&app, tenant, Method::PUT, &uri, None, edit("No tag")).await;
+    assert_eq!((missing.status, missing.body["code"].as_str()), (StatusCode::PRECONDITION_REQUIRED, Some("if_match_required")));
+    let weak = call(&app, tenant, Method::PUT, &uri, Some(&format!("W/{tag}")), edit("Weak tag")).await;
+    assert_eq!((weak.status, weak.body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("invalid_if_match")));
+
+    let first = call(&app, tenant, Method::PUT, &uri, Some(&tag), edit("First")).await;
+    assert_eq!(first.status, StatusCode::OK);
+    let bumped = first.etag.expect("updates return the new ETag");
+    assert_ne!(bumped, tag);
+
+    // A second writer still holding the original tag loses.
+    let stale = call(&app, tenant, Method::PUT, &uri, Some(&tag), edit("Second")).await;
+    assert_eq!((stale.status, stale.body["code"].as_str()), (StatusCode::CONFLICT, Some("version_conflict")));
+    let current = call(&app, tenant, Method::GET, &uri, None, None).await;
+    assert_eq!((current.body["name"].as_str(), current.etag), (Some("First"), Some(bumped.clone())));
+
+    let forced = call(&app, tenant, Method::PUT, &uri, Some("*"), edit("Forced")).await;
+    assert_eq!(forced.status, StatusCode::OK);
+    assert_ne!(forced.etag, Some(bumped));
+}
@@ -0,0 +1,158 @@
+//! SKU and barcode uniqueness, the pre-flight check and duplicate merges against Postgres. Needs
+//! Postgres: set ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+#![cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, ProductFixture, TenantFixture, TestPostgres, TestSigner};
+use http_body_util::BodyExt;
+use product_service::app_state::AppState;
+use product_service::identifier_handlers::{check_identifiers, get_barcodes, put_barcodes};
+use product_service::merge_handlers::merge_products;
+use product_service::product_handlers::{create_product, delete_product, lookup_product_by_sku, restore_product};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn app(pool: &PgPool) -> Router {
+    let state = AppState::new(pool.clone(), (), TestSigner::generate().verifier(), None);
+    Router::new()
+        .route("/products", post(create_product))
+        .route("/products/lookup", get(lookup_product_by_sku))
+        .route("/products/identifiers", get(check_identifiers))
+        .route("/products/:id", axum::routing::delete(delete_product))
+        .route("/products/:id/restore", post(restore_product))
+        .route("/products/:id/barcodes", get(get_barcodes).put(put_barcodes))
+        .route("/admin/products/merge", post(merge_products))
+        .with_state(state)
+}
+
+async fn call(app: &Router, tenant: Uuid, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", "admin")
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn reused_skus_and_barcodes_are_typed_conflicts_naming_the_owner() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = TenantFixture::new().insert(db).await.unwrap().id;
+    let owner = ProductFixture::new(tenant).sku("LATTE-12").insert(db).await.unwrap().id;
+    let other = ProductFixture::new(tenant).insert(db).await.unwrap().id;
+    let app = app(db);
+
+    let (status, body) = call(&app, tenant, Method::POST, "/products", Some(json!({"name": "Latte", "price": "4.50", "sku": " LATTE-12 "}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "sku_conflict");
+    assert!(body["message"].as_str().unwrap().contains(&owner.to_string()), "{body}");
+
+    let (status, _) = call(&app, tenant, Method::PUT, &format!("/products/{owner}/barcodes"), Some(json!({"barcodes": ["0012345678905", " 4006381333931"]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(&app, tenant, Method::PUT, &format!("/products/{other}/barcodes"), Some(json!({"barcodes": ["4006381333931"]}))).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::CONFLICT, Some("barcode_conflict")));
+    let (status, body) = call(&app, tenant, Method::PUT, &format!("/products/{other}/barcodes"), Some(json!({"barcodes": ["1 2"]}))).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("invalid_barcode")));
+
+    let (_, body) = call(&app, tenant, Method::GET, "/products/identifiers?sku=LATTE-12&barcode=4006381333931", None).await;
+    assert_eq!(body["sku"]["available"], false);
+    assert_eq!(body["sku"]["product_id"], owner.to_string());
+    assert_eq!(body["barcode"]["product_id"], owner.to_string());
+    let uri = format!("/products/identifiers?sku=LATTE-12&exclude_product_id={owner}");
+    let (_, body) = call(&app, tenant, Method::GET, &uri, None).await;
+    assert_eq!(body["sku"]["available"], true, "a product's own SKU is not taken for it");
+    let (status, body) = call(&app, tenant, Method::GET, "/products/identifiers", None).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("missing_identifier")));
+
+    let (_, body) = call(&app, tenant, Method::GET, "/products/lookup?barcode=0012345678905", None).await;
+    assert_eq!(body["id"], owner.to_string());
+
+    // Deleting releases the barcodes; restoring fails once another product has claimed one.
+    let (status, _) = call(&app, tenant, Method::DELETE, &format!("/products/{owner}"), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(&app, tenant, Method::PUT, &format!("/products/{other}/barcodes"), Some(json!({"barcodes": ["4006381333931"]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(&app, tenant, Method::POST, &format!("/products/{owner}/restore"), None).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::CONFLICT, Some("barcode_conflict")));
+
+    let (status, _) = call(&app, tenant, Method::PUT, &format!("/products/{other}/barcodes"), Some(json!({"barcodes": []}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, tenant, Method::POST, &format!("/products/{owner}/restore"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = call(&app, tenant, Method::GET, &format!("/products/{owner}/barcodes"), None).await;
+    assert_eq!(body["barcodes"], json!(["0012345678905", "4006381333931"]));
+}
+
+#[tokio::test]
+async fn merging_moves_barcodes_and_bundle_lines_to_the_canonical_product() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = TenantFixture::new().insert(db).await.unwrap().id;
+    let canonical = ProductFixture::new(tenant).sku("LATTE-12").insert(db).await.unwrap().id;
+    let duplicate = ProductFixture::new(tenant).sku("LATTE-12OZ").insert(db).await.unwrap().id;
+    let bundle = ProductFixture::new(tenant).insert(db).await.unwrap().id;
+    for (component, quantity) in [(canonical, 1), (duplicate, 2)] {
+        sqlx::query("INSERT INTO product_components (tenant_id, bundle_id, component_id, quantity) VALUES ($1, $2, $3, $4)")
+            .bind(tenant)
+            .bind(bundle)
+            .bind(component)
+            .bind(quantity)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+    let app = app(db);
+    call(&app, tenant, Method::PUT, &format!("/products/{duplicate}/barcodes"), Some(json!({"barcodes": ["0012345678905"]}))).await;
+
+    let (status, body) = call(&app, tenant, Method::POST, "/admin/products/merge", Some(json!({"duplicate_id": duplicate, "canonical_id": duplicate}))).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("merge_same_product")));
+
+    let merge = json!({"duplicate_id": duplicate, "canonical_id": canonical});
+    let (status, body) = call(&app, tenant, Method::POST, "/admin/products/merge", Some(merge.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!((body["merged"].as_bool(), body["barcodes_moved"].as_u64(), body["components_rewritten"].as_u64()), (Some(true), Some(1), Some(1)));
+
+    let (_, body) = call(&app, tenant, Method::GET, "/products/lookup?barcode=0012345678905", None).await;
+    assert_eq!(body["id"], canonical.to_string());
+    let lines: Vec<(Uuid, String)> = sqlx::query_as("SELECT component_id, quantity::text FROM product_components WHERE tenant_id = $1 AND bundle_id = $2")
+        .bind(tenant)
+        .bind(bundle)
+        .fetch_all(db)
+        .await
+        .unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].0, canonical);
+    assert_eq!(lines[0].1.parse::<f64>().unwrap(), 3.0);
+    let merged_into: Option<Uuid> = sqlx::query_scalar("SELECT merged_into FROM products WHERE id = $1").bind(duplicate).fetch_one(db).await.unwrap();
+    assert_eq!(merged_into, Some(canonical));
+
+    // Repeating the merge only republishes the event; the duplicate stays retired.
+    let (status, body) = call(&app, tenant, Method::POST, "/admin/products/merge", Some(merge)).await;
+    assert_eq!((status, body["merged"].as_bool()), (StatusCode::OK, Some(false)));
+    let (status, body) = call(&app, tenant, Method::POST, &format!("/products/{duplicate}/restore"), None).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::CONFLICT, Some("product_merged")));
+    // The duplicate's SKU is free again.
+    let (_, body) = call(&app, tenant, Method::GET, "/products/identifiers?sku=LATTE-12OZ", None).await;
+    assert_eq!(body["sku"]["available"], true);
+}
@@ -0,0 +1,103 @@
+//! Soft delete, `include_deleted` and restore against Postgres. Needs Postgres: set
+//! ENABLE_ITESTS=1 (and TEST_DATABASE_URL to skip the container).
+#![cfg(not(any(feature = "kafka", feature = "kafka-producer")))]
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use common_test_fixtures::{itests_enabled, ProductFixture, TenantFixture, TestPostgres, TestSigner};
+use http_body_util::BodyExt;
+use product_service::app_state::AppState;
+use product_service::product_handlers::{delete_product, get_product, list_products, lookup_product_by_sku, restore_product};
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+async fn database() -> Option<TestPostgres> {
+    if !itests_enabled() {
+        return None;
+    }
+    let postgres = TestPostgres::start().await.expect("start postgres");
+    postgres.migrate(&["product-service"]).await.expect("migrate");
+    Some(postgres)
+}
+
+fn app(pool: &PgPool) -> Router {
+    let state = AppState::new(pool.clone(), (), TestSigner::generate().verifier(), None);
+    Router::new()
+        .route("/products", get(list_products))
+        .route("/products/lookup", get(lookup_product_by_sku))
+        .route("/products/:id", get(get_product).delete(delete_product))
+        .route("/products/:id/restore", post(restore_product))
+        .with_state(state)
+}
+
+async fn call(app: &Router, tenant: Uuid, role: &str, method: Method, uri: &str) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Tenant-ID", tenant.to_string())
+        .header("X-Roles", role)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn listed_ids(body: &Value) -> Vec<String> {
+    body.as_array().unwrap().iter().map(|product| product["id"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn deleted_products_are_hidden_until_restored() {
+    let Some(postgres) = database().await else { return };
+    let db = postgres.pool();
+    let tenant = TenantFixture::new().insert(db).await.unwrap().id;
+    let product = ProductFixture::new(tenant).sku("MOCHA-8").insert(db).await.unwrap().id;
+    let app = app(db);
+
+    let (status, _) = call(&app, tenant, "cashier", Method::DELETE, &format!("/products/{product}")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, tenant, "manager", Method::DELETE, &format!("/products/{product}")).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = call(&app, tenant, "manager", Method::DELETE, &format!("/products/{product}")).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::NOT_FOUND, Some("product_not_found")));
+
+    // The row stays for orders and analytics but drops out of reads and lookups.
+    let (status, _) = call(&app, tenant, "manager", Method::GET, &format!("/products/{product}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = call(&app, tenant, "manager", Method::GET, "/products").await;
+    assert!(!listed_ids(&body).contains(&product.to_string()), "{body}");
+    let (status, _) = call(&app, tenant, "cashier", Method::GET, "/products/lookup?sku=MOCHA-8").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Only admins may ask for deleted rows back.
+    let (status, body) = call(&app, tenant, "manager", Method::GET, &format!("/products/{product}?include_deleted=true")).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::FORBIDDEN, Some("missing_role")));
+    let (status, body) = call(&app, tenant, "admin", Method::GET, &format!("/products/{product}?include_deleted=true")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["deleted_at"].is_string(), "{body}");
+    let (_, body) = call(&app, tenant, "admin", Method::GET, "/products?include_deleted=true").await;
+    assert!(listed_ids(&body).contains(&product.to_string()), "{body}");
+
+    let (status, _) = call(&app, tenant, "cashier", Method::POST, &format!("/products/{product}/restore")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = call(&app, tenant, "manager", Method::POST, &format!("/products/{product}/restore")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["deleted_at"].is_null(), "{body}");
+    let (status, body) = call(&app, tenant, "manager", Method::POST, &format!("/products/{product}/restore")).await;
+    assert_eq!((status, body["code"].as_str()), (StatusCode::CONFLICT, Some("product_not_deleted")));
+    let (status, _) = call(&app, tenant, "cashier", Method::GET, "/products/lookup?sku=MOCHA-8").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let actions: Vec<String> = sqlx::query_scalar("SELECT action FROM product_audit_log WHERE product_id = $1 ORDER BY created_at")
+        .bind(product)
+        .fetch_all(db)
+        .await
+        .unwrap();
+    assert_eq!(actions, ["deleted", "restored"]);
+}
@@ -0,0 +1,24 @@
+use common_http_errors::test_helpers::assert_field_errors;
+use common_http_errors::validation::Validate;
+use product_service::product_handlers::{NewProduct, UpdateProduct};
+use serde_json::json;
+
+#[tokio::test]
+async fn new_products_list_every_invalid_field() {
+    let product: NewProduct = serde_json::from_value(json!({
+        "name": " ", "price": "-1.00", "tracking": "batch", "uom": "oz", "tare_weight": "-0.1", "cost": "2.50"
+    })).unwrap();
+    let err = product.ensure_valid(None).expect_err("invalid product");
+    assert_field_errors(err, &["name", "price", "tracking", "uom", "tare_weight"]).await;
+
+    let product: NewProduct = serde_json::from_value(json!({"name": "Bananas", "price": "0.69", "uom": "KG", "tracking": "Lot"})).unwrap();
+    assert!(product.ensure_valid(None).is_ok());
+}
+
+#[tokio::test]
+async fn updates_use_the_same_rules() {
+    let product: UpdateProduct = serde_json::from_value(json!({
+        "name": "Coffee", "price": "3.50", "description": "", "active": true, "cost": "-1"
+    })).unwrap();
+    assert_field_errors(product.ensure_valid(None).expect_err("negative cost"), &["cost"]).await;
+}